@@ -0,0 +1,67 @@
+//! Compare history blob sizes and encode/decode cost, uncompressed vs zstd, on realistic payloads.
+//!
+//! Usage: `cargo bench --bench blob_size`
+
+use homeserver::history_repo::blob::{BLOB_VERSION, decode_blob, encode_blob};
+use homeserver::models::{InterfaceStat, NetworkStats};
+use std::time::Instant;
+
+const ITERATIONS: u32 = 2_000;
+
+fn network(interfaces: usize) -> NetworkStats {
+    NetworkStats::new(
+        (0..interfaces)
+            .map(|i| InterfaceStat {
+                name: format!("veth{:07x}", i * 7919),
+                display_name: format!("veth{:07x}", i * 7919),
+                mac_address: format!("02:42:ac:11:{:02x}:{:02x}", i / 256, i % 256),
+                ipv4: vec![format!("172.{}.0.1", 17 + i)],
+                ipv6: vec![format!("fe80::42:acff:fe11:{:x}", i)],
+                bytes_sent: 18_446_744 * (i as u64 + 1),
+                bytes_recv: 9_223_372 * (i as u64 + 3),
+                packets_sent: 120_000 + i as u64,
+                packets_recv: 240_000 + i as u64,
+                speed: 10_000_000_000,
+                received_bytes_per_sec: 1_536.5 * i as f64,
+                transmitted_bytes_per_sec: 768.25 * i as f64,
+                is_up: i % 5 != 0,
+                packets_recv_per_sec: 0.0,
+                packets_sent_per_sec: 0.0,
+            })
+            .collect(),
+    )
+}
+
+fn report<T>(label: &str, value: &T)
+where
+    T: wincode::SchemaWrite<wincode::config::DefaultConfig, Src = T>
+        + for<'de> wincode::SchemaRead<'de, wincode::config::DefaultConfig, Dst = T>,
+{
+    for compress in [false, true] {
+        let encoded = encode_blob(value, BLOB_VERSION, compress).unwrap();
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            std::hint::black_box(encode_blob(value, BLOB_VERSION, compress).unwrap());
+        }
+        let encode_us = start.elapsed().as_secs_f64() * 1e6 / ITERATIONS as f64;
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            std::hint::black_box(decode_blob::<T>(&encoded, BLOB_VERSION, "bench").unwrap());
+        }
+        let decode_us = start.elapsed().as_secs_f64() * 1e6 / ITERATIONS as f64;
+        println!(
+            "{:<24} {:<5} {:>8} bytes  encode {:>8.2} us  decode {:>8.2} us",
+            label,
+            if compress { "zstd" } else { "plain" },
+            encoded.len(),
+            encode_us,
+            decode_us
+        );
+    }
+}
+
+fn main() {
+    report("network_data (5 ifaces)", &network(5));
+    report("network_data (30 ifaces)", &network(30));
+    report("network_data (60 ifaces)", &network(60));
+}
@@ -0,0 +1,65 @@
+// Build metadata for version.rs: git commit, build time, rustc version and target triple.
+// Every value falls back to "unknown" (e.g. a tarball or Docker build without `.git`).
+
+use std::process::Command;
+
+const UNKNOWN: &str = "unknown";
+
+/// Trimmed stdout of `program args`, or `None` when it cannot run or fails.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8(out.stdout).ok()?;
+    let text = text.trim();
+    (out.status.success() && !text.is_empty()).then(|| text.to_string())
+}
+
+/// Short commit hash, with `-dirty` when tracked files have uncommitted changes.
+fn git_commit() -> Option<String> {
+    let hash = output("git", &["rev-parse", "--short=12", "HEAD"])?;
+    let dirty = Command::new("git")
+        .args(["diff-index", "--quiet", "HEAD", "--"])
+        .status()
+        .is_ok_and(|s| !s.success());
+    Some(if dirty { format!("{hash}-dirty") } else { hash })
+}
+
+/// RFC 3339 UTC; `SOURCE_DATE_EPOCH` pins it for reproducible builds.
+fn build_time() -> String {
+    let pinned = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0));
+    pinned
+        .unwrap_or_else(chrono::Utc::now)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let values = [
+        ("HOMESERVER_GIT_COMMIT", git_commit()),
+        ("HOMESERVER_BUILD_TIME", Some(build_time())),
+        ("HOMESERVER_RUSTC_VERSION", output(&rustc, &["--version"])),
+        ("HOMESERVER_TARGET", std::env::var("TARGET").ok()),
+    ];
+    for (name, value) in values {
+        let value = value.unwrap_or_else(|| UNKNOWN.into());
+        println!("cargo:rustc-env={name}={value}");
+    }
+
+    // Re-run on a new commit, a staged change or an edited source file (for `-dirty`).
+    if let Some(git_dir) = output("git", &["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+        println!("cargo:rerun-if-changed={git_dir}/index");
+        // A packed ref has no file; a missing path would re-run the script on every build.
+        let head_ref = output("git", &["symbolic-ref", "-q", "HEAD"])
+            .map(|r| format!("{git_dir}/{r}"))
+            .filter(|path| std::path::Path::new(path).exists());
+        if let Some(path) = head_ref {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
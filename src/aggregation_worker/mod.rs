@@ -0,0 +1,266 @@
+// Background worker: roll raw 1s → first tier, then each tier into the next (by default
+// 1-min → 5-min → 1-hour → 1-day, see `database.aggregation_tiers`), then prune.
+// Runs every aggregation_interval_secs when enable_aggregation is true.
+// VACUUM runs on a configurable schedule (cron expression or fixed interval) when the file is
+// fragmented enough to be worth it; the WAL is checkpointed every wal_checkpoint_interval_secs.
+// Both are SQLite-only: on Postgres the worker only rolls up and prunes.
+
+mod report;
+mod rollup;
+mod settings;
+mod vacuum;
+
+pub use report::{AggregationMetrics, AggregationMetricsSnapshot, AggregationReport};
+pub use rollup::MAX_CHUNKS_PER_PASS;
+use rollup::{ChunkPlan, roll_up_raw, roll_up_tier};
+pub use settings::AggregationWorkerConfig;
+use vacuum::vacuum_scheduler;
+pub use vacuum::{run_vacuum, run_wal_checkpoint};
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::{Duration, Instant};
+
+use crate::history_repo::{HistoryStore, tier_rollup_after_ms};
+use crate::supervisor::{Backoff, supervise};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument, warn};
+
+const MS_PER_HOUR: i64 = 3_600_000;
+
+/// Log name of a tier: "1-min", "5-min", "1-hour", "1-day", else "<n>s".
+fn tier_label(resolution_seconds: i32) -> String {
+    match resolution_seconds {
+        60 => "1-min".into(),
+        300 => "5-min".into(),
+        3600 => "1-hour".into(),
+        86400 => "1-day".into(),
+        n => format!("{n}s"),
+    }
+}
+
+/// Spawns the aggregation worker. Returns a join handle.
+/// Callers cancel `shutdown`, then await this handle: an in-flight pass stops after its current
+/// chunk (each chunk is one transaction), a running VACUUM or checkpoint completes, and the
+/// vacuum scheduler exits before the task does. Every pass's [`AggregationReport`] is added to
+/// `metrics`. A panicking pass restarts the worker after a backoff, counted in `restarts`.
+pub fn spawn(
+    repo: Arc<dyn HistoryStore>,
+    config: AggregationWorkerConfig,
+    shutdown: CancellationToken,
+    metrics: Arc<AggregationMetrics>,
+    restarts: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    let token = shutdown.clone();
+    tokio::spawn(supervise(
+        "aggregation_worker",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || run(repo.clone(), config.clone(), token.clone(), metrics.clone()),
+    ))
+}
+
+#[instrument(
+    skip(repo, shutdown, metrics),
+    fields(interval_secs = config.aggregation_interval_secs)
+)]
+async fn run(
+    repo: Arc<dyn HistoryStore>,
+    config: AggregationWorkerConfig,
+    shutdown: CancellationToken,
+    metrics: Arc<AggregationMetrics>,
+) {
+    let mut agg_interval =
+        tokio::time::interval(Duration::from_secs(config.aggregation_interval_secs));
+    agg_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let mut wal_interval = tokio::time::interval(Duration::from_secs(
+        config.wal_checkpoint_interval_secs.max(1),
+    ));
+    wal_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let (vacuum_tx, mut vacuum_rx) = tokio::sync::mpsc::channel::<()>(1);
+    let scheduler = tokio::spawn(vacuum_scheduler(
+        config.clone(),
+        vacuum_tx,
+        shutdown.clone(),
+    ));
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = shutdown.cancelled() => {
+                tracing::debug!("aggregation worker shutting down");
+                break;
+            }
+            _ = agg_interval.tick() => {
+                match run_one_tick_until(repo.as_ref(), &config, &shutdown).await {
+                    Ok(report) => {
+                        log_report(&report, "aggregation pass");
+                        metrics.record(&report);
+                        // Backlog left: run the next pass now instead of after a full interval.
+                        if report.more_work_remaining {
+                            agg_interval.reset_immediately();
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "aggregation tick failed"),
+                }
+            }
+            // WAL and VACUUM are SQLite's; a Postgres server runs its own autovacuum.
+            _ = wal_interval.tick() => {
+                if let Some(sqlite) = repo.as_sqlite()
+                    && let Err(e) = run_wal_checkpoint(sqlite, &config).await
+                {
+                    warn!(error = %e, "wal checkpoint failed");
+                }
+            }
+            _ = vacuum_rx.recv() => {
+                if let Some(sqlite) = repo.as_sqlite()
+                    && let Err(e) = run_vacuum(sqlite, &config).await
+                {
+                    warn!(error = %e, "vacuum failed");
+                }
+            }
+        }
+    }
+    let _ = scheduler.await;
+}
+
+/// Log a pass (or backfill total): info when it did something, debug when idle.
+pub(crate) fn log_report(report: &AggregationReport, message: &str) {
+    let duration_ms = report.duration.as_millis() as u64;
+    if report.is_idle() {
+        tracing::debug!(duration_ms, more = report.more_work_remaining, "{message}");
+        return;
+    }
+    info!(
+        raw_buckets = report.raw_buckets,
+        rolled_up_buckets = report.rolled_up_buckets,
+        raw_rows_deleted = report.raw_rows_deleted,
+        minute_rows_deleted = report.minute_rows_deleted,
+        pruned_raw = report.pruned_raw,
+        pruned_aggregated = report.pruned_aggregated,
+        duration_ms,
+        more = report.more_work_remaining,
+        "{message}"
+    );
+}
+
+/// Runs one aggregation pass (raw → first tier, each tier → the next, prune). Used by worker loop
+/// and by backfill. Each tier handles at most [`MAX_CHUNKS_PER_PASS`] chunks; the report's
+/// `more_work_remaining` is set when backlog remains, so callers can run another pass right away.
+pub async fn run_one_tick(
+    repo: &dyn HistoryStore,
+    config: &AggregationWorkerConfig,
+) -> anyhow::Result<AggregationReport> {
+    run_one_tick_until(repo, config, &CancellationToken::new()).await
+}
+
+/// [`run_one_tick`] that stops after the current chunk once `shutdown` is cancelled; later tiers
+/// and pruning are skipped and `more_work_remaining` is set (the rest is left for the next pass).
+pub async fn run_one_tick_until(
+    repo: &dyn HistoryStore,
+    config: &AggregationWorkerConfig,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<AggregationReport> {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as i64;
+    run_one_tick_at(repo, config, now_ms, shutdown).await
+}
+
+/// [`run_one_tick_until`] with the wall clock read as `now_ms`. The clock is clamped to the last
+/// pass's ([`HistoryStore::clamp_aggregation_clock`]), so a clock that steps back (NTP, RTC-less
+/// boot) never moves roll-up or prune cutoffs backwards. Each pass is its own trace.
+#[instrument(name = "aggregation_pass", parent = None, skip_all, fields(now_ms))]
+pub async fn run_one_tick_at(
+    repo: &dyn HistoryStore,
+    config: &AggregationWorkerConfig,
+    now_ms: i64,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<AggregationReport> {
+    let started = Instant::now();
+    let mut report = AggregationReport::default();
+    run_pass(repo, config, now_ms, shutdown, &mut report).await?;
+    report.duration = started.elapsed();
+    Ok(report)
+}
+
+/// Body of [`run_one_tick_at`], filling `report` as it goes.
+async fn run_pass(
+    repo: &dyn HistoryStore,
+    config: &AggregationWorkerConfig,
+    now_ms: i64,
+    shutdown: &CancellationToken,
+    report: &mut AggregationReport,
+) -> anyhow::Result<()> {
+    let now_ms = repo.clamp_aggregation_clock(now_ms).await?;
+    let Some(&first_tier) = config.aggregation_tiers.first() else {
+        return Ok(());
+    };
+
+    let chunks = ChunkPlan {
+        buckets: config.chunk_buckets.max(1) as i64,
+        container_limit: config.container_limit,
+        timezone: config.bucket_timezone,
+    };
+    let raw = roll_up_raw(
+        repo,
+        first_tier,
+        now_ms - (config.raw_retention_hours as i64) * MS_PER_HOUR,
+        &chunks,
+        shutdown,
+    )
+    .await?;
+    report.raw_buckets = raw.buckets;
+    report.raw_rows_deleted = raw.source_rows_deleted;
+    report.more_work_remaining = raw.more;
+
+    // Each coarser tier takes over rows of the finer tier once they age past its retention.
+    for (i, pair) in config.aggregation_tiers.windows(2).enumerate() {
+        if shutdown.is_cancelled() {
+            report.more_work_remaining = true;
+            return Ok(());
+        }
+        let (from_resolution, to_resolution) = (pair[0], pair[1]);
+        let keep_ms = tier_rollup_after_ms(
+            i,
+            config.minute_retention_hours,
+            config.five_minute_retention_days,
+            config.hourly_retention_days,
+        );
+        let tier = roll_up_tier(
+            repo,
+            from_resolution,
+            to_resolution,
+            now_ms - keep_ms,
+            &chunks,
+            shutdown,
+        )
+        .await?;
+        report.rolled_up_buckets += tier.buckets;
+        if i == 0 {
+            report.minute_rows_deleted = tier.source_rows_deleted;
+        }
+        report.more_work_remaining |= tier.more;
+        if tier.buckets > 0 {
+            info!(
+                rolled_up_buckets = tier.buckets,
+                "{} -> {} aggregation",
+                tier_label(from_resolution),
+                tier_label(to_resolution)
+            );
+        }
+    }
+
+    if shutdown.is_cancelled() {
+        report.more_work_remaining = true;
+        return Ok(());
+    }
+    // Raw pruning is owned by the main worker's prune_tick (so it still runs when
+    // aggregation is disabled); here we only prune the aggregated tiers, each at its own cutoff.
+    let cutoffs = repo.aggregated_prune_cutoffs(now_ms).await?;
+    report.pruned_aggregated = repo.prune_aggregated_old_data(&cutoffs).await?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+// What an aggregation pass did, and running totals of those reports for /api/stats and /metrics.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Outcome of one [`run_one_tick`](super::run_one_tick) pass.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AggregationReport {
+    /// First-tier buckets written from raw rows.
+    pub raw_buckets: u64,
+    /// Buckets written into the coarser tiers (every tier → next tier step).
+    pub rolled_up_buckets: u64,
+    /// Raw rows removed because they were rolled into the first tier.
+    pub raw_rows_deleted: u64,
+    /// First-tier rows removed because they were rolled into the second tier.
+    pub minute_rows_deleted: u64,
+    /// Raw rows removed by retention. Raw pruning belongs to the stats worker's prune tick, so a
+    /// pass leaves this 0; see [`AggregationMetrics::record_raw_prune`].
+    pub pruned_raw: u64,
+    /// Aggregated rows removed by per-tier retention.
+    pub pruned_aggregated: u64,
+    pub duration: Duration,
+    /// A tier stopped with buckets still pending (or the pass was cancelled); run again soon.
+    pub more_work_remaining: bool,
+}
+
+impl AggregationReport {
+    /// True when the pass wrote or removed nothing.
+    pub fn is_idle(&self) -> bool {
+        self.raw_buckets == 0
+            && self.rolled_up_buckets == 0
+            && self.raw_rows_deleted == 0
+            && self.minute_rows_deleted == 0
+            && self.pruned_raw == 0
+            && self.pruned_aggregated == 0
+    }
+
+    /// Add another pass's counts and duration (backfill totals); `more_work_remaining` is taken
+    /// from `other`, the later pass.
+    pub fn add(&mut self, other: &AggregationReport) {
+        self.raw_buckets += other.raw_buckets;
+        self.rolled_up_buckets += other.rolled_up_buckets;
+        self.raw_rows_deleted += other.raw_rows_deleted;
+        self.minute_rows_deleted += other.minute_rows_deleted;
+        self.pruned_raw += other.pruned_raw;
+        self.pruned_aggregated += other.pruned_aggregated;
+        self.duration += other.duration;
+        self.more_work_remaining = other.more_work_remaining;
+    }
+}
+
+/// Running totals of [`AggregationReport`]s, shared between the aggregation worker, the stats
+/// worker (raw pruning) and the HTTP handlers.
+#[derive(Debug, Default)]
+pub struct AggregationMetrics {
+    passes: AtomicU64,
+    raw_buckets: AtomicU64,
+    rolled_up_buckets: AtomicU64,
+    raw_rows_deleted: AtomicU64,
+    minute_rows_deleted: AtomicU64,
+    pruned_raw: AtomicU64,
+    pruned_aggregated: AtomicU64,
+    last_pass_ms: AtomicU64,
+}
+
+/// Point-in-time copy of [`AggregationMetrics`] (the `aggregation` object of `/api/stats`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationMetricsSnapshot {
+    pub passes_total: u64,
+    pub raw_buckets_total: u64,
+    pub rolled_up_buckets_total: u64,
+    pub raw_rows_deleted_total: u64,
+    pub minute_rows_deleted_total: u64,
+    pub pruned_raw_total: u64,
+    pub pruned_aggregated_total: u64,
+    pub last_pass_ms: u64,
+}
+
+impl AggregationMetrics {
+    /// Count a finished pass.
+    pub fn record(&self, report: &AggregationReport) {
+        self.passes.fetch_add(1, Ordering::Relaxed);
+        self.raw_buckets
+            .fetch_add(report.raw_buckets, Ordering::Relaxed);
+        self.rolled_up_buckets
+            .fetch_add(report.rolled_up_buckets, Ordering::Relaxed);
+        self.raw_rows_deleted
+            .fetch_add(report.raw_rows_deleted, Ordering::Relaxed);
+        self.minute_rows_deleted
+            .fetch_add(report.minute_rows_deleted, Ordering::Relaxed);
+        self.pruned_raw
+            .fetch_add(report.pruned_raw, Ordering::Relaxed);
+        self.pruned_aggregated
+            .fetch_add(report.pruned_aggregated, Ordering::Relaxed);
+        self.last_pass_ms
+            .store(report.duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Count raw rows removed by the stats worker's retention prune.
+    pub fn record_raw_prune(&self, rows: u64) {
+        self.pruned_raw.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> AggregationMetricsSnapshot {
+        AggregationMetricsSnapshot {
+            passes_total: self.passes.load(Ordering::Relaxed),
+            raw_buckets_total: self.raw_buckets.load(Ordering::Relaxed),
+            rolled_up_buckets_total: self.rolled_up_buckets.load(Ordering::Relaxed),
+            raw_rows_deleted_total: self.raw_rows_deleted.load(Ordering::Relaxed),
+            minute_rows_deleted_total: self.minute_rows_deleted.load(Ordering::Relaxed),
+            pruned_raw_total: self.pruned_raw.load(Ordering::Relaxed),
+            pruned_aggregated_total: self.pruned_aggregated.load(Ordering::Relaxed),
+            last_pass_ms: self.last_pass_ms.load(Ordering::Relaxed),
+        }
+    }
+}
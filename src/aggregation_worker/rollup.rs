@@ -0,0 +1,192 @@
+// Chunked roll-up of one tier: raw → the first tier, or a finer aggregated tier into a coarser one.
+
+use std::collections::BTreeMap;
+
+use super::tier_label;
+use crate::history_repo::HistoryStore;
+use crate::history_repo::aggregation::{self, BucketGrid, BucketTimezone};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Chunks (of `chunk_buckets` buckets, one transaction each) a tier processes per pass before
+/// yielding the rest of its backlog to the next pass. A cancelled pass stops at the next chunk
+/// boundary instead.
+pub const MAX_CHUNKS_PER_PASS: u32 = 20;
+
+/// How a tier's roll-up cuts and writes its chunks.
+pub(super) struct ChunkPlan {
+    /// Buckets per chunk (one transaction).
+    pub buckets: i64,
+    /// Containers kept per bucket; 0 = no limit.
+    pub container_limit: usize,
+    /// Where buckets of hour-and-coarser tiers start.
+    pub timezone: BucketTimezone,
+}
+
+/// What one tier's roll-up did in a pass.
+#[derive(Debug, Default)]
+pub(super) struct TierRollUp {
+    /// Buckets written into the target tier.
+    pub buckets: u64,
+    /// Source rows deleted (rolled into those buckets).
+    pub source_rows_deleted: u64,
+    /// The pass stopped with buckets still pending.
+    pub more: bool,
+}
+
+/// First bucket to roll up: the oldest pending row's bucket, but never behind the tier's
+/// watermark (late rows behind it would overwrite a finished bucket; retention prunes them).
+fn first_bucket(min_ts: i64, watermark: Option<i64>, grid: &BucketGrid) -> i64 {
+    let floor = grid.floor(min_ts);
+    watermark.map_or(floor, |w| floor.max(w))
+}
+
+/// Group rows by bucket start on `grid`, preserving order within a bucket.
+fn split_into_buckets<T>(
+    rows: Vec<T>,
+    ts: impl Fn(&T) -> i64,
+    grid: &BucketGrid,
+) -> BTreeMap<i64, Vec<T>> {
+    let mut buckets: BTreeMap<i64, Vec<T>> = BTreeMap::new();
+    for row in rows {
+        let start = grid.floor(ts(&row));
+        buckets.entry(start).or_default().push(row);
+    }
+    buckets
+}
+
+/// raw → first tier: aggregate raw rows older than `cutoff_raw` into `resolution` buckets.
+pub(super) async fn roll_up_raw(
+    repo: &dyn HistoryStore,
+    resolution: i32,
+    cutoff_raw: i64,
+    plan: &ChunkPlan,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<TierRollUp> {
+    let Some(min_ts) = repo.get_min_raw_created_at_before(cutoff_raw).await? else {
+        return Ok(TierRollUp::default());
+    };
+    let watermark = repo.get_aggregation_watermark(resolution).await?;
+
+    let grid = BucketGrid::new(resolution, plan.timezone);
+    let end = grid.floor(cutoff_raw);
+    let mut chunk_start = first_bucket(min_ts, watermark, &grid);
+    let mut aggregated_count: u64 = 0;
+    let mut rows_deleted: u64 = 0;
+    let mut chunks: u32 = 0;
+
+    while chunk_start < end && chunks < MAX_CHUNKS_PER_PASS && !shutdown.is_cancelled() {
+        // Jump over stretches with no rows (downtime) instead of spending chunks on them.
+        let Some(next) = repo.get_min_raw_created_at_in(chunk_start, end).await? else {
+            chunk_start = end;
+            break;
+        };
+        chunk_start = chunk_start.max(grid.floor(next));
+        let chunk_end = grid.advance(chunk_start, plan.buckets).min(end);
+        let snapshots = repo
+            .get_raw_snapshots_by_time_range(chunk_start, chunk_end)
+            .await?;
+        let aggs: Vec<_> = split_into_buckets(snapshots, |s| s.timestamp as i64, &grid)
+            .into_iter()
+            .filter_map(|(start, bucket)| {
+                aggregation::aggregate_snapshots_with_container_limit(
+                    &bucket,
+                    start,
+                    resolution,
+                    plan.container_limit,
+                )
+            })
+            .collect();
+        aggregated_count += aggs.len() as u64;
+        rows_deleted += repo
+            .roll_up_raw_range(&aggs, chunk_start, chunk_end, resolution)
+            .await?;
+        chunk_start = chunk_end;
+        chunks += 1;
+        // Short transactions + a yield between them let the history writer's inserts through.
+        tokio::task::yield_now().await;
+    }
+
+    if aggregated_count > 0 {
+        info!(
+            aggregated_buckets = aggregated_count,
+            "raw -> {} aggregation",
+            tier_label(resolution)
+        );
+    }
+    Ok(TierRollUp {
+        buckets: aggregated_count,
+        source_rows_deleted: rows_deleted,
+        more: chunk_start < end,
+    })
+}
+
+/// Roll `from_resolution` rows older than `cutoff` into `to_resolution` buckets (aligned to the
+/// epoch, or to `plan.timezone`'s wall clock for tiers of an hour or more).
+pub(super) async fn roll_up_tier(
+    repo: &dyn HistoryStore,
+    from_resolution: i32,
+    to_resolution: i32,
+    cutoff: i64,
+    plan: &ChunkPlan,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<TierRollUp> {
+    let Some(min_ts) = repo
+        .get_min_aggregated_created_at_before(cutoff, from_resolution)
+        .await?
+    else {
+        return Ok(TierRollUp::default());
+    };
+    let watermark = repo.get_aggregation_watermark(to_resolution).await?;
+
+    let grid = BucketGrid::new(to_resolution, plan.timezone);
+    let end = grid.floor(cutoff);
+    let mut chunk_start = first_bucket(min_ts, watermark, &grid);
+    let mut rolled_up_count: u64 = 0;
+    let mut rows_deleted: u64 = 0;
+    let mut chunks: u32 = 0;
+
+    while chunk_start < end && chunks < MAX_CHUNKS_PER_PASS && !shutdown.is_cancelled() {
+        let Some(next) = repo
+            .get_min_aggregated_created_at_in(chunk_start, end, from_resolution)
+            .await?
+        else {
+            chunk_start = end;
+            break;
+        };
+        chunk_start = chunk_start.max(grid.floor(next));
+        let chunk_end = grid.advance(chunk_start, plan.buckets).min(end);
+        let rows = repo
+            .get_aggregated_snapshots_by_time_range(chunk_start, chunk_end, from_resolution)
+            .await?;
+        let aggs: Vec<_> = split_into_buckets(rows, |r| r.created_at, &grid)
+            .into_iter()
+            .filter_map(|(start, bucket)| {
+                aggregation::aggregate_aggregated_snapshots_with_container_limit(
+                    &bucket,
+                    start,
+                    to_resolution,
+                    plan.container_limit,
+                )
+            })
+            .collect();
+        rolled_up_count += aggs.len() as u64;
+        rows_deleted += repo
+            .roll_up_aggregated_range(
+                &aggs,
+                chunk_start,
+                chunk_end,
+                from_resolution,
+                to_resolution,
+            )
+            .await?;
+        chunk_start = chunk_end;
+        chunks += 1;
+        tokio::task::yield_now().await;
+    }
+    Ok(TierRollUp {
+        buckets: rolled_up_count,
+        source_rows_deleted: rows_deleted,
+        more: chunk_start < end,
+    })
+}
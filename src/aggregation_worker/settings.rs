@@ -0,0 +1,66 @@
+// Aggregation worker settings, taken from `[database]`.
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::aggregation::BucketTimezone;
+
+/// Config for the aggregation worker.
+#[derive(Debug, Clone)]
+pub struct AggregationWorkerConfig {
+    pub aggregation_interval_secs: u64,
+    /// Buckets rolled up per transaction (`database.aggregation_chunk_buckets`).
+    pub chunk_buckets: u32,
+    /// Tier resolutions in seconds, finest first (`database.aggregation_tiers`).
+    pub aggregation_tiers: Vec<i32>,
+    /// Where buckets of hour-and-coarser tiers start (`database.bucket_timezone`).
+    pub bucket_timezone: BucketTimezone,
+    /// Containers kept per bucket (`database.aggregation_container_limit`); 0 = no limit.
+    pub container_limit: usize,
+    pub raw_retention_hours: u32,
+    /// Roll first-tier rows older than this into the second tier.
+    pub minute_retention_hours: u32,
+    /// Roll second-tier rows older than this into the third tier.
+    pub five_minute_retention_days: u32,
+    /// Roll rows of the third and later tiers older than this into the next tier.
+    pub hourly_retention_days: u32,
+    pub retention_days: u32,
+    /// Optional cron expression for VACUUM (e.g. "0 3 * * *" = 03:00 daily). Uses local time.
+    pub vacuum_schedule: Option<String>,
+    /// Run VACUUM every N seconds when vacuum_schedule is not set.
+    pub vacuum_interval_secs: u64,
+    /// `PRAGMA incremental_vacuum` instead of a full VACUUM (`database.vacuum_mode = "incremental"`).
+    pub vacuum_incremental: bool,
+    /// Skip the scheduled vacuum unless free pages exceed this percentage of the file.
+    pub vacuum_min_free_percent: u32,
+    /// Pages released per incremental vacuum; 0 clears the whole freelist.
+    pub vacuum_incremental_pages: u32,
+    /// `PRAGMA wal_checkpoint(TRUNCATE)` every N seconds.
+    pub wal_checkpoint_interval_secs: u64,
+    /// Warn when the WAL is still larger than this after a checkpoint (bytes).
+    pub wal_warn_bytes: u64,
+}
+
+impl AggregationWorkerConfig {
+    /// The worker's `[database]` settings (also used by `homeserver-cli aggregate` / `vacuum`).
+    pub fn from_database(config: &DatabaseConfig) -> Self {
+        Self {
+            aggregation_interval_secs: config.aggregation_interval_secs,
+            chunk_buckets: config.aggregation_chunk_buckets,
+            aggregation_tiers: config.aggregation_tiers.clone(),
+            // Validated with the config; an unparsable value cannot reach here.
+            bucket_timezone: config.bucket_timezone.parse().unwrap_or_default(),
+            container_limit: config.aggregation_container_limit,
+            raw_retention_hours: config.raw_retention_hours,
+            minute_retention_hours: config.minute_retention_hours,
+            five_minute_retention_days: config.five_minute_retention_days,
+            hourly_retention_days: config.hourly_retention_days,
+            retention_days: config.retention_days,
+            vacuum_schedule: config.vacuum_schedule.clone(),
+            vacuum_interval_secs: config.vacuum_interval_secs,
+            vacuum_incremental: config.vacuum_mode == "incremental",
+            vacuum_min_free_percent: config.vacuum_min_free_percent,
+            vacuum_incremental_pages: config.vacuum_incremental_pages,
+            wal_checkpoint_interval_secs: config.wal_checkpoint_interval_secs,
+            wal_warn_bytes: config.wal_warn_bytes,
+        }
+    }
+}
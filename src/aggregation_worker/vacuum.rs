@@ -0,0 +1,114 @@
+// VACUUM scheduling and the free-page guard that decides whether a scheduled run is worth it;
+// periodic WAL checkpoints.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use super::AggregationWorkerConfig;
+use crate::history_repo::HistoryRepo;
+
+/// Vacuum if free pages exceed `vacuum_min_free_percent`; full or incremental per config.
+/// Returns whether a vacuum ran. Logs file size before and after.
+pub async fn run_vacuum(
+    repo: &HistoryRepo,
+    config: &AggregationWorkerConfig,
+) -> anyhow::Result<bool> {
+    let before = repo.fragmentation().await?;
+    if !before.needs_vacuum(config.vacuum_min_free_percent) {
+        info!(
+            size_bytes = before.size_bytes(),
+            free_percent = before.free_percent(),
+            min_free_percent = config.vacuum_min_free_percent,
+            "vacuum skipped: fragmentation below threshold"
+        );
+        return Ok(false);
+    }
+    if config.vacuum_incremental {
+        repo.incremental_vacuum(config.vacuum_incremental_pages)
+            .await?;
+    } else {
+        repo.vacuum().await?;
+    }
+    let after = repo.fragmentation().await?;
+    info!(
+        incremental = config.vacuum_incremental,
+        before_bytes = before.size_bytes(),
+        after_bytes = after.size_bytes(),
+        free_percent_before = before.free_percent(),
+        free_percent_after = after.free_percent(),
+        "vacuum complete"
+    );
+    Ok(true)
+}
+
+/// Checkpoint and truncate the WAL; warn when it is still above `wal_warn_bytes` afterwards
+/// (typically a long-running reader holding it open). Returns the WAL size after the checkpoint.
+pub async fn run_wal_checkpoint(
+    repo: &HistoryRepo,
+    config: &AggregationWorkerConfig,
+) -> anyhow::Result<u64> {
+    let checkpoint = repo.wal_checkpoint().await?;
+    let wal_bytes = repo.wal_size()?;
+    if wal_bytes > config.wal_warn_bytes {
+        warn!(
+            wal_bytes,
+            threshold_bytes = config.wal_warn_bytes,
+            busy = checkpoint.busy,
+            log_frames = checkpoint.log_frames,
+            "WAL file above threshold after checkpoint"
+        );
+    } else {
+        tracing::debug!(
+            wal_bytes,
+            checkpointed_frames = checkpoint.checkpointed_frames,
+            "wal checkpoint"
+        );
+    }
+    Ok(wal_bytes)
+}
+
+/// Sends a message on `tx` at each VACUUM time (cron or fixed interval). Uses local time for cron.
+/// Returns when `shutdown` is cancelled or the worker drops the receiver.
+pub(super) async fn vacuum_scheduler(
+    config: AggregationWorkerConfig,
+    tx: tokio::sync::mpsc::Sender<()>,
+    shutdown: CancellationToken,
+) {
+    let schedule = match config.vacuum_schedule {
+        Some(ref cron_str) => {
+            let normalized = crate::config::normalize_cron_expression(cron_str);
+            let Ok(schedule) = cron::Schedule::from_str(&normalized) else {
+                warn!(cron = %cron_str, "invalid vacuum_schedule; VACUUM will not run");
+                return;
+            };
+            Some(schedule)
+        }
+        None => None,
+    };
+    let interval = Duration::from_secs(config.vacuum_interval_secs);
+    loop {
+        let (delay, fire) = match &schedule {
+            Some(schedule) => {
+                let now = chrono::Local::now();
+                match schedule.after(&now).next() {
+                    Some(next) => (
+                        (next - now).to_std().unwrap_or(Duration::from_secs(1)),
+                        true,
+                    ),
+                    None => (Duration::from_secs(3600), false),
+                }
+            }
+            None => (interval, true),
+        };
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        if fire && tx.send(()).await.is_err() {
+            break;
+        }
+    }
+}
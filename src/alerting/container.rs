@@ -0,0 +1,175 @@
+// Container event rules: match Docker container events against `[[alerts.container_rules]]`,
+// count restarts in a sliding window, and suppress repeats per rule and container for the
+// cooldown. Pure — the event time is the clock.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{AlertContainer, AlertEvent, AlertState};
+use crate::config::{ContainerEventKind, ContainerRule};
+use crate::models::{ContainerAction, ContainerEvent};
+
+/// Evaluates container rules against Docker events.
+pub struct ContainerAlertEngine {
+    rules: Vec<ContainerRule>,
+    /// (rule index, container name) → event time (ms) of the last alert.
+    last_fired: HashMap<(usize, String), u64>,
+    /// Container name → restart times (ms), oldest first.
+    restarts: HashMap<String, VecDeque<u64>>,
+    /// Containers whose last event was a `die`; their next `start` is a restart.
+    died: HashSet<String>,
+}
+
+impl ContainerAlertEngine {
+    pub fn new(rules: Vec<ContainerRule>) -> Self {
+        Self {
+            rules,
+            last_fired: HashMap::new(),
+            restarts: HashMap::new(),
+            died: HashSet::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Feed one event; returns the alerts it fires. A restart is a `restart` event or a `start`
+    /// after a `die`. Containers are tracked by name so a recreated container keeps its history.
+    pub fn observe(&mut self, ev: &ContainerEvent) -> Vec<AlertEvent> {
+        let restarted = match ev.action {
+            ContainerAction::Die => {
+                self.died.insert(ev.name.clone());
+                false
+            }
+            ContainerAction::Start => self.died.remove(&ev.name),
+            ContainerAction::Restart => {
+                self.died.remove(&ev.name);
+                true
+            }
+            _ => false,
+        };
+        if restarted {
+            let times = self.restarts.entry(ev.name.clone()).or_default();
+            times.push_back(ev.time_ms);
+        }
+
+        let mut events = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !matches_container(rule, ev) {
+                continue;
+            }
+            let fired = match rule.event {
+                ContainerEventKind::Die => (ev.action == ContainerAction::Die)
+                    .then_some(ev.exit_code)
+                    .flatten()
+                    .filter(|&code| code != 0)
+                    .map(|code| ("!=", code as f64, 0.0)),
+                ContainerEventKind::Oom => {
+                    (ev.action == ContainerAction::Oom).then_some(("=", 1.0, 1.0))
+                }
+                ContainerEventKind::Unhealthy => {
+                    (ev.action == ContainerAction::Unhealthy).then_some(("=", 1.0, 1.0))
+                }
+                ContainerEventKind::Restarts => restarted
+                    .then(|| self.restarts_within(&ev.name, ev.time_ms, rule.restart_window_secs))
+                    .filter(|&n| n > rule.restart_count as usize)
+                    .map(|n| (">", n as f64, rule.restart_count as f64)),
+            };
+            let Some((op, value, threshold)) = fired else {
+                continue;
+            };
+            let key = (index, ev.name.clone());
+            let cooling = self.last_fired.get(&key).is_some_and(|&last| {
+                ev.time_ms.saturating_sub(last) < rule.cooldown_secs.saturating_mul(1000)
+            });
+            if cooling {
+                continue;
+            }
+            self.last_fired.insert(key, ev.time_ms);
+            events.push(AlertEvent {
+                rule_name: rule.name.clone(),
+                metric: format!("container_{}", kind_name(rule.event)),
+                op: op.into(),
+                value,
+                threshold,
+                severity: rule.severity,
+                state: AlertState::Firing,
+                timestamp: ev.time_ms,
+                container: Some(AlertContainer {
+                    id: ev.id.clone(),
+                    name: ev.name.clone(),
+                    image: ev.image.clone(),
+                    exit_code: ev.exit_code,
+                }),
+                check: None,
+            });
+        }
+        if restarted {
+            self.prune_restarts(&ev.name, ev.time_ms);
+        }
+        events
+    }
+
+    fn restarts_within(&self, name: &str, now_ms: u64, window_secs: u64) -> usize {
+        let since = now_ms.saturating_sub(window_secs.saturating_mul(1000));
+        self.restarts
+            .get(name)
+            .map_or(0, |times| times.iter().filter(|&&t| t > since).count())
+    }
+
+    /// Drop restarts older than the longest restart window of any rule.
+    fn prune_restarts(&mut self, name: &str, now_ms: u64) {
+        let window = self
+            .rules
+            .iter()
+            .filter(|r| r.event == ContainerEventKind::Restarts)
+            .map(|r| r.restart_window_secs)
+            .max()
+            .unwrap_or(0);
+        let since = now_ms.saturating_sub(window.saturating_mul(1000));
+        if let Some(times) = self.restarts.get_mut(name) {
+            while times.front().is_some_and(|&t| t <= since) {
+                times.pop_front();
+            }
+        }
+    }
+}
+
+fn matches_container(rule: &ContainerRule, ev: &ContainerEvent) -> bool {
+    rule.container
+        .as_deref()
+        .is_none_or(|pattern| name_matches(pattern, &ev.name))
+        && rule
+            .labels
+            .iter()
+            .all(|(k, v)| ev.attributes.get(k) == Some(v))
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+fn kind_name(kind: ContainerEventKind) -> &'static str {
+    match kind {
+        ContainerEventKind::Die => "die",
+        ContainerEventKind::Oom => "oom",
+        ContainerEventKind::Unhealthy => "unhealthy",
+        ContainerEventKind::Restarts => "restarts",
+    }
+}
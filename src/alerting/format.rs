@@ -0,0 +1,68 @@
+// Webhook payloads per `WebhookFormat`. Pure — unit-testable without a receiver.
+
+use super::{AlertEvent, AlertState};
+use crate::config::{Severity, WebhookFormat};
+
+/// The JSON body POSTed to a webhook of `format`.
+pub fn payload(format: WebhookFormat, ev: &AlertEvent) -> serde_json::Value {
+    match format {
+        WebhookFormat::Generic => generic_payload(ev),
+        WebhookFormat::Discord => serde_json::json!({ "content": chat_text(ev) }),
+        WebhookFormat::Slack => serde_json::json!({ "text": chat_text(ev) }),
+    }
+}
+
+/// Machine-readable event: rule, metric, comparison, severity, state, timestamp and, for
+/// container rules, the container.
+pub fn generic_payload(ev: &AlertEvent) -> serde_json::Value {
+    serde_json::to_value(ev).unwrap_or_default()
+}
+
+/// One chat line, e.g. `[FIRING] cpu hot (critical): cpu_temperature is 91.2 (> 85)`,
+/// `[FIRING] crash (critical): container db died with exit code 137` or
+/// `[FIRING] jellyfin (warning): http://jellyfin.lan/health is down: timed out`.
+pub fn chat_text(ev: &AlertEvent) -> String {
+    let severity = match ev.severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Critical => "critical",
+    };
+    let what = match (&ev.container, &ev.check) {
+        (_, Some(check)) => match (ev.state, &check.error) {
+            (AlertState::Firing, Some(error)) => format!("{} is down: {error}", check.url),
+            (AlertState::Firing, None) => format!("{} is down", check.url),
+            (AlertState::Resolved, _) => format!("{} is up", check.url),
+        },
+        (Some(c), None) => match ev.metric.as_str() {
+            "container_die" => format!(
+                "container {} died with exit code {}",
+                c.name,
+                c.exit_code.unwrap_or_default()
+            ),
+            "container_oom" => format!("container {} was OOM-killed", c.name),
+            "container_unhealthy" => format!("container {} is unhealthy", c.name),
+            _ => format!(
+                "container {} restarted {} times (> {})",
+                c.name, ev.value, ev.threshold
+            ),
+        },
+        (None, None) => format!(
+            "{} is {:.1} ({} {})",
+            ev.metric, ev.value, ev.op, ev.threshold
+        ),
+    };
+    format!(
+        "[{}] {} ({}): {}",
+        state_name(ev.state).to_uppercase(),
+        ev.rule_name,
+        severity,
+        what
+    )
+}
+
+fn state_name(state: AlertState) -> &'static str {
+    match state {
+        AlertState::Firing => "firing",
+        AlertState::Resolved => "resolved",
+    }
+}
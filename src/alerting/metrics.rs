@@ -3,7 +3,7 @@
 use crate::models::FullSystemSnapshot;
 
 /// Extract a named scalar metric from a snapshot. Returns None if unavailable
-/// (e.g. no swap, no GPUs, no partitions).
+/// (e.g. no swap, no GPUs, no partitions, no containers).
 pub fn extract_metric(metric: &str, s: &FullSystemSnapshot) -> Option<f64> {
     match metric {
         "cpu_usage" => Some(s.cpu.usage_percent),
@@ -29,6 +29,11 @@ pub fn extract_metric(metric: &str, s: &FullSystemSnapshot) -> Option<f64> {
             .iter()
             .map(|g| g.utilization_percent)
             .fold(None, fold_max),
+        "container_pids_usage_percent" => s
+            .containers
+            .iter()
+            .map(|c| c.pids_usage_percent)
+            .fold(None, fold_max),
         _ => None,
     }
 }
@@ -44,6 +49,16 @@ pub fn compare(value: f64, op: &str, threshold: f64) -> bool {
     }
 }
 
+/// Whether a firing rule is still breached: `value op threshold` with the threshold moved
+/// `hysteresis` towards the healthy side, so a value hovering at the threshold does not flap.
+pub fn still_breached(value: f64, op: &str, threshold: f64, hysteresis: f64) -> bool {
+    let release = match op {
+        ">" | ">=" => threshold - hysteresis,
+        _ => threshold + hysteresis,
+    };
+    compare(value, op, release)
+}
+
 fn fold_max(acc: Option<f64>, v: f64) -> Option<f64> {
     Some(match acc {
         Some(m) => m.max(v),
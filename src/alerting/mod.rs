@@ -1,38 +1,141 @@
-// Threshold alerting: evaluate configured rules against each snapshot and emit fire/resolve
-// events. Pure state machine (clock injected) + a notifier that logs and optionally POSTs a webhook.
+// Alerting: evaluate threshold rules against each snapshot and container rules against Docker
+// events, emitting fire/resolve events. Pure state machines (clock injected) + a notifier that
+// logs and POSTs to webhooks, run by tasks on the snapshot broadcast and the Docker event stream
+// (see task.rs).
 
+mod container;
+mod format;
 mod metrics;
 mod notify;
+mod task;
 
-pub use metrics::{compare, extract_metric};
-pub use notify::Notifier;
+pub use container::{ContainerAlertEngine, name_matches};
+pub use format::{chat_text, generic_payload, payload};
+pub use metrics::{compare, extract_metric, still_breached};
+pub use notify::{Notifier, WebhookTarget};
+pub use task::{ContainerEventSource, spawn, spawn_configured, spawn_container_alerts};
 
-use crate::config::AlertRule;
+use crate::config::{AlertRule, Severity};
 use crate::models::FullSystemSnapshot;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AlertState {
     Firing,
     Resolved,
 }
 
-/// A state transition for one rule, produced by [`AlertEngine::evaluate`].
-#[derive(Debug, Clone)]
+/// A state transition for one rule, produced by [`AlertEngine::evaluate`],
+/// [`ContainerAlertEngine::observe`] or an HTTP check going down or up (`crate::http_checks`).
+/// Serializes to the generic webhook payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AlertEvent {
+    #[serde(rename = "rule")]
     pub rule_name: String,
     pub metric: String,
     pub op: String,
     pub value: f64,
     pub threshold: f64,
+    pub severity: Severity,
     pub state: AlertState,
+    /// Timestamp (Unix ms) of the snapshot or Docker event that caused the transition.
+    pub timestamp: u64,
+    /// The container, for container rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<AlertContainer>,
+    /// The check, for `http_check` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check: Option<AlertCheck>,
+}
+
+/// The `[[http_checks]]` entry an `http_check` event is about; its name is the event's `rule`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertCheck {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Why the request failed, while firing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The container a container-rule event is about.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertContainer {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i64>,
+}
+
+/// A rule that is currently firing, as served by `GET /api/alerts`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveAlert {
+    pub rule: String,
+    pub metric: String,
+    pub op: String,
+    pub threshold: f64,
+    pub severity: Severity,
+    /// Latest value of the metric.
+    pub value: f64,
+    /// Snapshot timestamp (Unix ms) at which the rule fired.
+    pub since: u64,
+}
+
+/// Events kept for `GET /api/alerts` `recent`.
+pub const ALERT_HISTORY_LEN: usize = 100;
+
+/// Written by the alert tasks, read by `GET /api/alerts`: the threshold rules firing after the
+/// latest evaluation and the last [`ALERT_HISTORY_LEN`] events of all rules.
+#[derive(Debug, Default)]
+pub struct AlertStatus {
+    active: Mutex<Vec<ActiveAlert>>,
+    recent: Mutex<VecDeque<AlertEvent>>,
+}
+
+impl AlertStatus {
+    pub fn set_active(&self, alerts: Vec<ActiveAlert>) {
+        *self.active.lock().unwrap_or_else(|e| e.into_inner()) = alerts;
+    }
+
+    pub fn active(&self) -> Vec<ActiveAlert> {
+        self.active
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn record(&self, event: &AlertEvent) {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == ALERT_HISTORY_LEN {
+            recent.pop_front();
+        }
+        recent.push_back(event.clone());
+    }
+
+    /// Recorded events, newest first.
+    pub fn recent(&self) -> Vec<AlertEvent> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().rev().cloned().collect()
+    }
 }
 
 #[derive(Default)]
 struct RuleState {
     breached_since: Option<Instant>,
-    firing: bool,
+    /// Snapshot timestamp of the firing transition while firing.
+    firing_since: Option<u64>,
     last_fired: Option<Instant>,
+    last_value: f64,
 }
 
 /// Evaluates alert rules against snapshots, tracking sustain/cooldown timing per rule.
@@ -53,44 +156,72 @@ impl AlertEngine {
 
     /// Evaluate all rules at instant `now`. Emits a `Firing` event when a rule has been breached
     /// for at least `duration_secs` and the `cooldown_secs` debounce has elapsed, and a `Resolved`
-    /// event when a firing rule recovers.
+    /// event when a firing rule recovers past its `hysteresis` band.
     pub fn evaluate(&mut self, snapshot: &FullSystemSnapshot, now: Instant) -> Vec<AlertEvent> {
         let mut events = Vec::new();
         for (rule, st) in self.rules.iter().zip(self.states.iter_mut()) {
             let Some(value) = extract_metric(&rule.metric, snapshot) else {
                 continue;
             };
-            if compare(value, &rule.op, rule.threshold) {
+            st.last_value = value;
+            let breached = if st.firing_since.is_some() {
+                still_breached(value, &rule.op, rule.threshold, rule.hysteresis)
+            } else {
+                compare(value, &rule.op, rule.threshold)
+            };
+            if breached {
                 let since = *st.breached_since.get_or_insert(now);
                 let sustained =
                     now.duration_since(since) >= Duration::from_secs(rule.duration_secs);
                 let cooled = st.last_fired.is_none_or(|t| {
                     now.duration_since(t) >= Duration::from_secs(rule.cooldown_secs)
                 });
-                if sustained && !st.firing && cooled {
-                    st.firing = true;
+                if sustained && st.firing_since.is_none() && cooled {
+                    st.firing_since = Some(snapshot.timestamp);
                     st.last_fired = Some(now);
-                    events.push(event(rule, value, AlertState::Firing));
+                    events.push(event(rule, value, AlertState::Firing, snapshot.timestamp));
                 }
             } else {
-                if st.firing {
-                    events.push(event(rule, value, AlertState::Resolved));
+                if st.firing_since.take().is_some() {
+                    events.push(event(rule, value, AlertState::Resolved, snapshot.timestamp));
                 }
-                st.firing = false;
                 st.breached_since = None;
             }
         }
         events
     }
+
+    /// The rules firing after the last [`Self::evaluate`], with their latest values.
+    pub fn active(&self) -> Vec<ActiveAlert> {
+        self.rules
+            .iter()
+            .zip(&self.states)
+            .filter_map(|(rule, st)| {
+                Some(ActiveAlert {
+                    rule: rule.name.clone(),
+                    metric: rule.metric.clone(),
+                    op: rule.op.clone(),
+                    threshold: rule.threshold,
+                    severity: rule.severity,
+                    value: st.last_value,
+                    since: st.firing_since?,
+                })
+            })
+            .collect()
+    }
 }
 
-fn event(rule: &AlertRule, value: f64, state: AlertState) -> AlertEvent {
+fn event(rule: &AlertRule, value: f64, state: AlertState, timestamp: u64) -> AlertEvent {
     AlertEvent {
         rule_name: rule.name.clone(),
         metric: rule.metric.clone(),
         op: rule.op.clone(),
         value,
         threshold: rule.threshold,
+        severity: rule.severity,
         state,
+        timestamp,
+        container: None,
+        check: None,
     }
 }
@@ -1,50 +1,115 @@
-// Alert delivery: always log via tracing; optionally POST a JSON payload to a webhook.
+// Alert delivery: always log via tracing; POST each event to every configured webhook, retrying
+// failures with exponential backoff.
 
-use super::{AlertEvent, AlertState};
+use std::time::Duration;
 
-/// Logs every alert event and, when a webhook URL is configured, POSTs it as JSON.
+use super::{AlertEvent, AlertState, payload};
+use crate::config::{AlertsConfig, WebhookFormat};
+
+/// A webhook URL and the payload shape it expects.
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+/// Logs every alert event and POSTs it to each [`WebhookTarget`].
 /// Cloneable (reqwest::Client is internally reference-counted) so it can be moved into tasks.
 #[derive(Clone)]
 pub struct Notifier {
     client: Option<reqwest::Client>,
-    webhook_url: Option<String>,
+    targets: Vec<WebhookTarget>,
+    retries: u32,
+    backoff: Duration,
 }
 
 impl Notifier {
-    pub fn new(webhook_url: Option<String>) -> Self {
-        let client = webhook_url.as_ref().map(|_| reqwest::Client::new());
+    /// `retries` extra attempts per target after a failure, waiting `backoff`, then twice that,
+    /// and so on.
+    pub fn new(targets: Vec<WebhookTarget>, retries: u32, backoff: Duration) -> Self {
+        let client = (!targets.is_empty()).then(reqwest::Client::new);
         Self {
             client,
-            webhook_url,
+            targets,
+            retries,
+            backoff,
         }
     }
 
+    /// `webhook_url` (generic format) followed by `[[alerts.webhooks]]`.
+    pub fn from_config(config: &AlertsConfig) -> Self {
+        let generic = config.webhook_url.iter().map(|url| WebhookTarget {
+            url: url.expose().to_string(),
+            format: WebhookFormat::Generic,
+        });
+        let targets = generic
+            .chain(config.webhooks.iter().map(|w| WebhookTarget {
+                url: w.url.expose().to_string(),
+                format: w.format,
+            }))
+            .collect();
+        Self::new(
+            targets,
+            config.webhook_retries,
+            Duration::from_millis(config.webhook_retry_backoff_ms),
+        )
+    }
+
     pub async fn notify(&self, ev: &AlertEvent) {
         match ev.state {
             AlertState::Firing => tracing::warn!(
                 rule = %ev.rule_name, metric = %ev.metric, op = %ev.op,
-                value = ev.value, threshold = ev.threshold, "alert firing"
+                value = ev.value, threshold = ev.threshold, severity = ?ev.severity,
+                "alert firing"
             ),
             AlertState::Resolved => tracing::info!(
                 rule = %ev.rule_name, metric = %ev.metric, "alert resolved"
             ),
         }
 
-        if let (Some(client), Some(url)) = (&self.client, &self.webhook_url) {
-            let state = match ev.state {
-                AlertState::Firing => "firing",
-                AlertState::Resolved => "resolved",
-            };
-            let payload = serde_json::json!({
-                "rule": ev.rule_name,
-                "metric": ev.metric,
-                "op": ev.op,
-                "value": ev.value,
-                "threshold": ev.threshold,
-                "state": state,
-            });
-            if let Err(e) = client.post(url).json(&payload).send().await {
-                tracing::warn!(error = %e, rule = %ev.rule_name, "alert webhook POST failed");
+        self.post(&ev.rule_name, |format| payload(format, ev)).await;
+    }
+
+    /// POST `body(format)` to every target; `what` names the message in failure logs. Used for
+    /// alert events and scheduled usage reports.
+    pub async fn post(&self, what: &str, body: impl Fn(WebhookFormat) -> serde_json::Value) {
+        if let Some(client) = &self.client {
+            let sends = self
+                .targets
+                .iter()
+                .map(|target| self.send(client, target, what, body(target.format)));
+            futures_util::future::join_all(sends).await;
+        }
+    }
+
+    /// POST to one target until it answers 2xx or the retries run out.
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        target: &WebhookTarget,
+        what: &str,
+        body: serde_json::Value,
+    ) {
+        let mut delay = self.backoff;
+        for attempt in 0..=self.retries {
+            let result = client
+                .post(&target.url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                // The URL may carry a token; keep it out of the logs.
+                .map_err(reqwest::Error::without_url);
+            match result {
+                Ok(_) => return,
+                Err(e) if attempt < self.retries => {
+                    tracing::debug!(error = %e, what, attempt, "webhook POST failed; retrying");
+                    tokio::time::sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, what, "webhook POST failed")
+                }
             }
         }
     }
@@ -0,0 +1,207 @@
+// Alert tasks: the evaluator subscribes to the snapshot broadcast and runs the threshold engine on
+// every snapshot; the container task follows the Docker event stream. Both publish to
+// `AlertStatus` and hand events to the notifier.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+
+use super::{AlertEngine, AlertEvent, AlertStatus, ContainerAlertEngine, Notifier};
+use crate::config::AlertsConfig;
+use crate::docker_repo::DockerRepo;
+use crate::models::{ContainerEvent, FullSystemSnapshot};
+use crate::supervisor::{Backoff, supervise};
+
+/// Wait before re-subscribing after the event stream ends or fails (daemon restart, socket gone).
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Where container events come from; Docker in production, a channel in tests.
+pub trait ContainerEventSource: Send + Sync {
+    fn events(&self) -> BoxStream<'static, anyhow::Result<ContainerEvent>>;
+}
+
+impl ContainerEventSource for DockerRepo {
+    fn events(&self) -> BoxStream<'static, anyhow::Result<ContainerEvent>> {
+        self.container_events()
+    }
+}
+
+/// The tasks `config` needs: the evaluator with `[[alerts.rules]]` and the built-in ones
+/// ([`AlertsConfig::effective_rules`]), the container task on
+/// `containers` with `[[alerts.container_rules]]`; both share one notifier and `status`.
+pub fn spawn_configured(
+    config: &AlertsConfig,
+    snapshots: broadcast::Sender<FullSystemSnapshot>,
+    containers: Arc<dyn ContainerEventSource>,
+    status: Arc<AlertStatus>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> Vec<tokio::task::JoinHandle<()>> {
+    let notifier = Notifier::from_config(config);
+    let mut handles = Vec::new();
+    let rules = config.effective_rules();
+    if !rules.is_empty() {
+        handles.push(spawn(
+            AlertEngine::new(rules),
+            notifier.clone(),
+            snapshots,
+            status.clone(),
+            shutdown.clone(),
+            restarts.clone(),
+        ));
+    }
+    if !config.container_rules.is_empty() {
+        handles.push(spawn_container_alerts(
+            ContainerAlertEngine::new(config.container_rules.clone()),
+            notifier,
+            containers,
+            status,
+            shutdown,
+            restarts,
+        ));
+    }
+    handles
+}
+
+/// Spawn the evaluator on `snapshots` until `shutdown`. A panic restarts it (counted in
+/// `restarts`) with a fresh subscription; firing state lives in the engine and carries over.
+pub fn spawn(
+    engine: AlertEngine,
+    notifier: Notifier,
+    snapshots: broadcast::Sender<FullSystemSnapshot>,
+    status: Arc<AlertStatus>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    let engine = Arc::new(Mutex::new(engine));
+    let token = shutdown.clone();
+    tokio::spawn(supervise(
+        "alert_evaluator",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            run(
+                engine.clone(),
+                notifier.clone(),
+                snapshots.subscribe(),
+                status.clone(),
+                token.clone(),
+            )
+        },
+    ))
+}
+
+async fn run(
+    engine: Arc<Mutex<AlertEngine>>,
+    notifier: Notifier,
+    mut snapshots: broadcast::Receiver<FullSystemSnapshot>,
+    status: Arc<AlertStatus>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let snapshot = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            received = snapshots.recv() => match received {
+                // Replayed from history at startup: already evaluated before the restart.
+                Ok(snapshot) if snapshot.historical => continue,
+                Ok(snapshot) => snapshot,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!(skipped, "alert evaluator lagged; skipping snapshots");
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            },
+        };
+        let events = {
+            let mut engine = engine.lock().unwrap_or_else(|e| e.into_inner());
+            let events = engine.evaluate(&snapshot, Instant::now());
+            status.set_active(engine.active());
+            events
+        };
+        deliver(&notifier, &status, events);
+    }
+}
+
+/// Spawn the container rules on `source` until `shutdown`, re-subscribing when the stream ends.
+/// A panic restarts it (counted in `restarts`); restart windows and cooldowns carry over.
+pub fn spawn_container_alerts(
+    engine: ContainerAlertEngine,
+    notifier: Notifier,
+    source: Arc<dyn ContainerEventSource>,
+    status: Arc<AlertStatus>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    let engine = Arc::new(Mutex::new(engine));
+    let token = shutdown.clone();
+    tokio::spawn(supervise(
+        "container_alerts",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            run_container(
+                engine.clone(),
+                notifier.clone(),
+                source.clone(),
+                status.clone(),
+                token.clone(),
+            )
+        },
+    ))
+}
+
+async fn run_container(
+    engine: Arc<Mutex<ContainerAlertEngine>>,
+    notifier: Notifier,
+    source: Arc<dyn ContainerEventSource>,
+    status: Arc<AlertStatus>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let mut events = source.events();
+        loop {
+            let received = tokio::select! {
+                _ = shutdown.cancelled() => return,
+                received = events.next() => received,
+            };
+            match received {
+                Some(Ok(ev)) => {
+                    let fired = engine
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .observe(&ev);
+                    deliver(&notifier, &status, fired);
+                }
+                Some(Err(e)) => {
+                    tracing::warn!(error = %e, "docker event stream failed; reconnecting");
+                    break;
+                }
+                None => {
+                    tracing::debug!("docker event stream ended; reconnecting");
+                    break;
+                }
+            }
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+        }
+    }
+}
+
+/// Record events in the history and notify. Webhook POSTs (with retries) are detached so a slow
+/// receiver never delays evaluation.
+fn deliver(notifier: &Notifier, status: &AlertStatus, events: Vec<AlertEvent>) {
+    for ev in events {
+        status.record(&ev);
+        let notifier = notifier.clone();
+        tokio::spawn(async move { notifier.notify(&ev).await });
+    }
+}
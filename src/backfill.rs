@@ -1,16 +1,30 @@
-// One-time backfill: run one aggregation pass at startup to roll existing raw/1-min data.
+// One-time backfill: run aggregation passes at startup until existing raw/1-min data is rolled up.
 
-use crate::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
-use crate::history_repo::HistoryRepo;
+use crate::aggregation_worker::{
+    AggregationReport, AggregationWorkerConfig, log_report, run_one_tick,
+};
+use crate::history_repo::HistoryStore;
 use std::sync::Arc;
 use tracing::info;
 
-/// Runs one aggregation pass to backfill existing raw data into 1-min and 5-min buckets.
+/// Runs aggregation passes until no backlog remains, rolling existing raw data into the tiers.
+/// Each pass is bounded and its transactions short, so the history writer keeps saving meanwhile.
+/// Returns the passes' combined report, which is also logged.
 pub async fn run_backfill(
-    repo: Arc<HistoryRepo>,
+    repo: Arc<dyn HistoryStore>,
     config: &AggregationWorkerConfig,
-) -> anyhow::Result<()> {
-    run_one_tick(repo.as_ref(), config).await?;
-    info!("backfill complete");
-    Ok(())
+) -> anyhow::Result<AggregationReport> {
+    let mut total = AggregationReport::default();
+    let mut passes: u32 = 0;
+    loop {
+        let report = run_one_tick(repo.as_ref(), config).await?;
+        total.add(&report);
+        passes += 1;
+        if !report.more_work_remaining {
+            break;
+        }
+    }
+    log_report(&total, "backfill report");
+    info!(passes, "backfill complete");
+    Ok(total)
 }
@@ -0,0 +1,255 @@
+// Maintenance CLI for the history database. Settings come from the server's config (file,
+// HOMESERVER_* environment, `--config` / `--db-path`), so it operates on the same file.
+//
+// Usage: homeserver-cli [--config PATH] [--db-path PATH] <COMMAND>
+//   dump [--limit N] [--from MS] [--to MS] [--resolution DUR]   snapshots as JSON
+//   stats                                                        row counts and sizes per tier
+//   prune --older-than DUR [--force]                             delete rows older than DUR
+//   aggregate [--force]                                          run one aggregation pass
+//   vacuum [--force]                                             full VACUUM
+//   verify [--from MS] [--to MS] [--delete [--force]]            decode every blob
+//   export OUT_FILE [--from MS] [--to MS]                        raw history to a portable file
+//   import IN_FILE [--force]                                     load an export (creating the file)
+//
+// Read commands (dump, stats, verify, export) open the file read-only. Write commands (and
+// verify --delete) refuse to run while the `-wal` file exists (the server is running) unless
+// --force.
+
+use clap::{Parser, Subcommand};
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::{AppConfig, CliOverrides};
+use homeserver::history_repo::VerifyReport;
+use homeserver::maintenance::{self, DumpRange};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "homeserver-cli",
+    version,
+    about = "Inspect and maintain the history database"
+)]
+struct Cli {
+    /// Config file to read; takes precedence over CONFIG_FILE.
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<String>,
+    /// SQLite database file (`database.path`).
+    #[arg(long, value_name = "PATH", global = true)]
+    db_path: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print snapshots as JSON: the newest raw rows, or a time range like /api/history.
+    Dump {
+        /// Newest raw rows to print (ignored with --from/--to/--resolution).
+        #[arg(long, default_value_t = 5)]
+        limit: u32,
+        /// Range start, epoch ms (default: one hour before --to).
+        #[arg(long)]
+        from: Option<i64>,
+        /// Range end, epoch ms (default: now).
+        #[arg(long)]
+        to: Option<i64>,
+        /// Bucket size, e.g. 1s, 5m, 1h (default: 1m).
+        #[arg(long, value_parser = maintenance::parse_duration)]
+        resolution: Option<std::time::Duration>,
+    },
+    /// Row counts, sizes per tier and page accounting as JSON.
+    Stats,
+    /// Delete raw and aggregated rows older than a duration.
+    Prune {
+        /// Age, e.g. 30d or 12h.
+        #[arg(long, value_parser = maintenance::parse_duration)]
+        older_than: std::time::Duration,
+        /// Run even though the WAL file exists (a server may be writing).
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run one aggregation pass (roll-ups and tier retention) offline.
+    Aggregate {
+        /// Run even though the WAL file exists (a server may be writing).
+        #[arg(long)]
+        force: bool,
+    },
+    /// Rewrite the file with VACUUM to reclaim free pages.
+    Vacuum {
+        /// Run even though the WAL file exists (a server may be writing).
+        #[arg(long)]
+        force: bool,
+    },
+    /// Decode every stored blob and report corrupt rows; nonzero exit code when any are found.
+    Verify {
+        /// Range start, epoch ms (default: the first row).
+        #[arg(long)]
+        from: Option<i64>,
+        /// Range end, epoch ms (default: after the last row).
+        #[arg(long)]
+        to: Option<i64>,
+        /// Delete the rows with corrupt blobs (a write command).
+        #[arg(long)]
+        delete: bool,
+        /// With --delete: run even though the WAL file exists (a server may be writing).
+        #[arg(long, requires = "delete")]
+        force: bool,
+    },
+    /// Write raw snapshots and the stored system info to a portable file, e.g. to move
+    /// history to new hardware.
+    Export {
+        /// File to write.
+        out: PathBuf,
+        /// Range start, epoch ms (default: the first row).
+        #[arg(long)]
+        from: Option<i64>,
+        /// Range end, epoch ms (default: after the last row).
+        #[arg(long)]
+        to: Option<i64>,
+    },
+    /// Load an export file; timestamps already stored are skipped, so re-running is safe.
+    Import {
+        /// File written by `export`.
+        input: PathBuf,
+        /// Run even though the WAL file exists (a server may be writing).
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn print_verify(report: &VerifyReport) {
+    for c in &report.corrupt {
+        println!(
+            "{} id={} created_at={} {} (blob version {}): {}",
+            c.table.name(),
+            c.id,
+            c.created_at,
+            c.column,
+            c.version,
+            c.reason
+        );
+    }
+    println!(
+        "checked {} raw and {} aggregated rows: {} corrupt blobs",
+        report.raw_rows,
+        report.aggregated_rows,
+        report.corrupt.len()
+    );
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = AppConfig::load_with_overrides(&CliOverrides {
+        config: cli.config,
+        db_path: cli.db_path,
+        ..Default::default()
+    })?;
+    let database = &config.database;
+    match cli.command {
+        Command::Dump {
+            limit,
+            from,
+            to,
+            resolution,
+        } => {
+            let range = (from.is_some() || to.is_some() || resolution.is_some()).then(|| {
+                let to_ms = to.unwrap_or_else(now_ms);
+                DumpRange {
+                    from_ms: from.unwrap_or(to_ms.saturating_sub(3_600_000)),
+                    to_ms,
+                    resolution_secs: resolution.map_or(60, |d| d.as_secs() as u32),
+                }
+            });
+            let repo = maintenance::open_read_only(&database.path).await?;
+            let snapshots =
+                maintenance::dump(&repo, limit, range, database.raw_retention_hours).await?;
+            repo.close().await;
+            println!("{}", serde_json::to_string_pretty(&snapshots)?);
+        }
+        Command::Stats => {
+            let repo = maintenance::open_read_only(&database.path).await?;
+            let report = maintenance::stats(&repo).await?;
+            repo.close().await;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        Command::Verify {
+            from,
+            to,
+            delete,
+            force,
+        } => {
+            let (from, to) = (from.unwrap_or(i64::MIN), to.unwrap_or(i64::MAX));
+            if delete {
+                let repo = maintenance::open_for_write(database, force).await?;
+                let report = repo.verify_blobs(from, to, u64::MAX).await?;
+                let (raw, aggregated) = maintenance::delete_corrupt(&repo, &report).await?;
+                repo.close().await;
+                print_verify(&report);
+                println!("deleted {raw} raw and {aggregated} aggregated rows");
+            } else {
+                let repo = maintenance::open_read_only(&database.path).await?;
+                let report = repo.verify_blobs(from, to, u64::MAX).await?;
+                repo.close().await;
+                print_verify(&report);
+                if !report.corrupt.is_empty() {
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Export { out, from, to } => {
+            let repo = maintenance::open_read_only(&database.path).await?;
+            let written = repo
+                .export_range(
+                    from.unwrap_or(0),
+                    to.unwrap_or(i64::MAX),
+                    BufWriter::new(File::create(&out)?),
+                )
+                .await?;
+            repo.close().await;
+            println!("exported {written} snapshots to {}", out.display());
+        }
+        Command::Import { input, force } => {
+            let repo = maintenance::open_for_import(database, force).await?;
+            let report = repo.import(BufReader::new(File::open(&input)?)).await?;
+            repo.close().await;
+            println!(
+                "imported {} snapshots into {} ({} duplicates skipped)",
+                report.imported, database.path, report.skipped_duplicates
+            );
+        }
+        Command::Prune { older_than, force } => {
+            let repo = maintenance::open_for_write(database, force).await?;
+            let report = maintenance::prune(&repo, older_than, now_ms()).await?;
+            repo.close().await;
+            println!(
+                "deleted {} raw and {} aggregated rows older than {}",
+                report.raw_rows, report.aggregated_rows, report.cutoff_ms
+            );
+        }
+        Command::Aggregate { force } => {
+            let repo = maintenance::open_for_write(database, force).await?;
+            let report =
+                run_one_tick(&repo, &AggregationWorkerConfig::from_database(database)).await?;
+            repo.close().await;
+            println!("{report:#?}");
+        }
+        Command::Vacuum { force } => {
+            let repo = maintenance::open_for_write(database, force).await?;
+            let (before, after) = maintenance::vacuum(&repo).await?;
+            repo.close().await;
+            println!(
+                "vacuumed: {} -> {} bytes ({} free pages before)",
+                before.size_bytes(),
+                after.size_bytes(),
+                before.freelist_count
+            );
+        }
+    }
+    Ok(())
+}
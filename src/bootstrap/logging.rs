@@ -0,0 +1,76 @@
+// The tracing subscriber: a reloadable OpenTelemetry slot (empty until `[telemetry]` is
+// configured), the reloadable filter and the `fmt` layer with local-time timestamps.
+
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::fmt::time::FormatTime;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::config::TelemetryConfig;
+use crate::models::SystemInfo;
+use crate::reload::LogFilterSetter;
+use crate::telemetry::{self, OtelLayer};
+
+struct LocalTimer;
+
+impl FormatTime for LocalTimer {
+    fn format_time(&self, w: &mut tracing_subscriber::fmt::format::Writer<'_>) -> std::fmt::Result {
+        write!(
+            w,
+            "{}",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+        )
+    }
+}
+
+/// Handles into the installed subscriber.
+pub struct Logging {
+    /// Replaces the filter (`logging.filter`, also on a config reload).
+    pub set_filter: LogFilterSetter,
+    otel: reload::Handle<Option<OtelLayer>, Registry>,
+}
+
+/// Install the global subscriber with `level` (`--log-level`) as the filter, else `RUST_LOG`,
+/// else `info`.
+pub fn init_logging(level: Option<&str>) -> anyhow::Result<Logging> {
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    // The filter in front of the OpenTelemetry slot applies to exported spans as well.
+    let (otel_layer, otel) = reload::Layer::new(None::<OtelLayer>);
+    let (filter, filter_handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_timer(LocalTimer))
+        .init();
+    Ok(Logging {
+        set_filter: Box::new(move |filter: &str| {
+            filter_handle.reload(EnvFilter::try_new(filter)?)?;
+            Ok(())
+        }),
+        otel,
+    })
+}
+
+impl Logging {
+    /// With `telemetry.otlp_endpoint`, build the OTLP tracer provider and fill the OpenTelemetry
+    /// slot; `main` shuts the provider down (flushing spans) on exit.
+    pub fn export_traces(
+        &self,
+        config: &TelemetryConfig,
+        system_info: &SystemInfo,
+    ) -> anyhow::Result<Option<SdkTracerProvider>> {
+        let provider = telemetry::otlp_tracer_provider(config, system_info)?;
+        if let Some(provider) = &provider {
+            self.otel.reload(Some(telemetry::layer(provider)))?;
+            tracing::info!(
+                endpoint = config.otlp_endpoint.as_deref(),
+                sampling_ratio = config.sampling_ratio,
+                "exporting traces over OTLP"
+            );
+        }
+        Ok(provider)
+    }
+}
@@ -0,0 +1,9 @@
+// Startup wiring for the server binary that does not need `main`'s locals: the tracing
+// subscriber (log filter and the OpenTelemetry slot) and the optional background tasks. The
+// history store is opened by `crate::startup`, the listeners bound by `crate::serve`.
+
+mod logging;
+mod tasks;
+
+pub use logging::{Logging, init_logging};
+pub use tasks::{TaskDeps, spawn_tasks};
@@ -0,0 +1,112 @@
+// The optional background tasks, each started only when its section is configured: alert
+// rules, usage reports, MQTT, latency probes, HTTP checks, remote write and the system info
+// refresh. The worker, history writer and mDNS responder are started by `main`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::AppConfig;
+use crate::docker_repo::DockerRepo;
+use crate::history_repo::HistoryHandle;
+use crate::metrics::ServiceMetrics;
+use crate::models::FullSystemSnapshot;
+use crate::sysinfo_repo::SysinfoRepo;
+use crate::system_info_refresh::SharedSystemInfo;
+use crate::{alerting, http_checks, mqtt, probes, remote_write, reports, system_info_refresh};
+
+/// What the background tasks read from and report to.
+pub struct TaskDeps<'a> {
+    pub config: &'a AppConfig,
+    pub snapshots: &'a broadcast::Sender<FullSystemSnapshot>,
+    pub docker_repo: Arc<DockerRepo>,
+    pub sysinfo_repo: Arc<SysinfoRepo>,
+    pub system_info: SharedSystemInfo,
+    pub history_repo: HistoryHandle,
+    pub metrics: &'a ServiceMetrics,
+    /// Each task stops on a child token of this one.
+    pub shutdown: &'a CancellationToken,
+}
+
+/// Spawn the configured tasks. Threshold rules follow the snapshot broadcast, container rules
+/// the Docker event stream; no task without rules. Usage reports only with a
+/// `report_schedule`, MQTT publishing only with a broker, probes only with targets, HTTP checks
+/// only when configured, remote write only with a URL.
+pub fn spawn_tasks(deps: TaskDeps<'_>) -> anyhow::Result<Vec<JoinHandle<()>>> {
+    let TaskDeps {
+        config,
+        snapshots,
+        docker_repo,
+        sysinfo_repo,
+        system_info,
+        history_repo,
+        metrics,
+        shutdown,
+    } = deps;
+    let mut handles = alerting::spawn_configured(
+        &config.alerts,
+        snapshots.clone(),
+        docker_repo,
+        metrics.alerts.clone(),
+        shutdown.child_token(),
+        metrics.worker_restarts_total.clone(),
+    );
+    handles.extend(reports::spawn_scheduled(
+        &config.alerts,
+        history_repo.clone(),
+        config.database.raw_retention_hours,
+        shutdown.child_token(),
+        metrics.worker_restarts_total.clone(),
+    ));
+    if config.mqtt.broker_url.is_some() {
+        handles.push(mqtt::spawn(
+            config.mqtt.clone(),
+            config.docker.cpu_percent_mode,
+            snapshots.clone(),
+            shutdown.child_token(),
+            metrics.worker_restarts_total.clone(),
+        )?);
+    }
+    if !config.probes.targets.is_empty() {
+        handles.push(probes::spawn(
+            metrics.probes.clone(),
+            Arc::new(probes::SystemProber::new(&config.probes)),
+            shutdown.child_token(),
+            metrics.worker_restarts_total.clone(),
+        ));
+    }
+    if !config.http_checks.is_empty() {
+        handles.push(http_checks::spawn(
+            metrics.checks.clone(),
+            http_checks::CheckReporter::new(
+                history_repo.clone(),
+                alerting::Notifier::from_config(&config.alerts),
+                metrics.alerts.clone(),
+            ),
+            shutdown.child_token(),
+            metrics.worker_restarts_total.clone(),
+        )?);
+    }
+    if config.remote_write.url.is_some() {
+        handles.push(remote_write::spawn(
+            config.remote_write.clone(),
+            config.server.node_name.clone(),
+            snapshots.clone(),
+            shutdown.child_token(),
+            metrics.worker_restarts_total.clone(),
+        )?);
+    }
+    if let Some(secs) = config.monitoring.system_info_refresh_secs {
+        handles.push(system_info_refresh::spawn_periodic(
+            system_info,
+            sysinfo_repo,
+            history_repo,
+            Duration::from_secs(secs),
+            shutdown.child_token(),
+        ));
+    }
+    Ok(handles)
+}
@@ -0,0 +1,76 @@
+// Pausing collection at runtime (POST /api/worker/pause, /api/worker/resume): the stats worker
+// keeps ticking but skips collection, broadcasting and persistence while paused. /ws/system
+// clients hear about it through the `heartbeat` message.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+
+/// Shared pause switch, set by the HTTP handlers and read by the worker every tick.
+#[derive(Debug, Default)]
+pub struct CollectionPause {
+    /// `None`: running. `Some(None)`: paused until resumed. `Some(Some(t))`: paused until `t`.
+    state: Mutex<Option<Option<Instant>>>,
+    /// Notified on every pause and resume call (not when a timed pause runs out).
+    changed: watch::Sender<()>,
+}
+
+/// Body of the pause / resume endpoints and of the `/ws/system` heartbeat.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseStatus {
+    pub paused: bool,
+    /// Seconds until the automatic resume (`None` when running or paused indefinitely).
+    pub resumes_in_secs: Option<u64>,
+}
+
+impl CollectionPause {
+    /// Pause collection, for `duration` or until [`Self::resume`]. Replaces an earlier pause.
+    pub fn pause(&self, duration: Option<Duration>) {
+        *self.lock() = Some(duration.map(|d| Instant::now() + d));
+        self.changed.send_replace(());
+    }
+
+    pub fn resume(&self) {
+        *self.lock() = None;
+        self.changed.send_replace(());
+    }
+
+    /// Changes with every [`Self::pause`] and [`Self::resume`].
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    /// Whether collection is paused now; a pause past its deadline counts as resumed.
+    pub fn is_paused(&self) -> bool {
+        self.status().paused
+    }
+
+    pub fn status(&self) -> PauseStatus {
+        let mut state = self.lock();
+        let now = Instant::now();
+        match *state {
+            Some(Some(until)) if until <= now => {
+                *state = None;
+                PauseStatus {
+                    paused: false,
+                    resumes_in_secs: None,
+                }
+            }
+            Some(until) => PauseStatus {
+                paused: true,
+                resumes_in_secs: until.map(|t| (t - now).as_secs_f64().ceil() as u64),
+            },
+            None => PauseStatus {
+                paused: false,
+                resumes_in_secs: None,
+            },
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Option<Instant>>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,246 @@
+// `[alerts]` section: webhook targets, threshold rules and container event rules.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Secret, normalize_cron_expression};
+use crate::reports::ReportPeriod;
+
+/// Threshold-based alerting. Every event is logged via `tracing` and POSTed to `webhook_url` (the
+/// generic JSON payload) and each `[[alerts.webhooks]]` entry. URLs often embed a token, so they
+/// are [`Secret`]s. A failed POST is retried `webhook_retries` times, doubling the delay from
+/// `webhook_retry_backoff_ms`. `container_rules` fire on Docker container events instead of
+/// snapshot values. With `report_schedule` (cron, local time) a usage report for the last
+/// `report_period` is posted to the same webhooks. Unless `pids_saturation_percent` is 0, a
+/// built-in rule fires when a container nears its pids limit (see [`Self::effective_rules`]).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub webhook_url: Option<Secret>,
+    pub webhooks: Vec<WebhookConfig>,
+    pub webhook_retries: u32,
+    pub webhook_retry_backoff_ms: u64,
+    pub rules: Vec<AlertRule>,
+    pub container_rules: Vec<ContainerRule>,
+    pub report_schedule: Option<String>,
+    pub report_period: ReportPeriod,
+    pub pids_saturation_percent: f64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            webhooks: Vec::new(),
+            webhook_retries: 3,
+            webhook_retry_backoff_ms: 1000,
+            rules: Vec::new(),
+            container_rules: Vec::new(),
+            report_schedule: None,
+            report_period: ReportPeriod::Day,
+            pids_saturation_percent: DEFAULT_PIDS_SATURATION_PERCENT,
+        }
+    }
+}
+
+/// One webhook target (`[[alerts.webhooks]]`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    pub url: Secret,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// Payload shape of a webhook: the generic JSON object, or a chat message for Discord
+/// (`{"content": ...}`) or Slack (`{"text": ...}`) incoming webhooks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Generic,
+    Discord,
+    Slack,
+}
+
+/// How urgent an alert is; passed through to notifications and `GET /api/alerts`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// One alert rule: fire when `metric op threshold` holds for `duration_secs`, then debounce
+/// re-notification for `cooldown_secs`. A firing rule resolves only once the value is
+/// `hysteresis` past the threshold on the other side (e.g. `> 90` with 5 resolves below 85).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertRule {
+    pub name: String,
+    /// One of: cpu_usage, mem_usage_percent, swap_usage_percent, load_avg_1, cpu_temperature,
+    /// disk_usage_percent, gpu_temperature, gpu_utilization, container_pids_usage_percent.
+    pub metric: String,
+    /// Comparison operator: ">", ">=", "<", "<=".
+    pub op: String,
+    pub threshold: f64,
+    #[serde(default)]
+    pub duration_secs: u64,
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub hysteresis: f64,
+}
+
+fn default_cooldown_secs() -> u64 {
+    300
+}
+
+/// One container event rule (`[[alerts.container_rules]]`): fire on `event` for containers whose
+/// name matches `container` (`*` wildcards; unset = any) and that carry all `labels`. Repeat
+/// alerts for the same rule and container are suppressed for `cooldown_secs`, so a crash loop
+/// does not flood the webhooks.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContainerRule {
+    pub name: String,
+    pub event: ContainerEventKind,
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// `restarts`: fire when a container restarts more than this many times ...
+    #[serde(default = "default_restart_count")]
+    pub restart_count: u32,
+    /// ... within this many seconds.
+    #[serde(default = "default_restart_window_secs")]
+    pub restart_window_secs: u64,
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// What a container rule fires on: a nonzero exit (`die`), an OOM kill, a failing health check,
+/// or too many restarts in a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerEventKind {
+    Die,
+    Oom,
+    Unhealthy,
+    Restarts,
+}
+
+fn default_restart_count() -> u32 {
+    3
+}
+
+fn default_restart_window_secs() -> u64 {
+    600
+}
+
+/// Default `alerts.pids_saturation_percent`.
+pub const DEFAULT_PIDS_SATURATION_PERCENT: f64 = 90.0;
+
+/// Name of the built-in rule added by `alerts.pids_saturation_percent`.
+pub const PIDS_SATURATION_RULE: &str = "container_pids_saturation";
+
+impl AlertsConfig {
+    /// `rules` plus the built-in [`PIDS_SATURATION_RULE`] (`container_pids_usage_percent >=
+    /// pids_saturation_percent`, warning, 5 points of hysteresis) unless the threshold is 0 or a
+    /// configured rule already watches the metric.
+    pub fn effective_rules(&self) -> Vec<AlertRule> {
+        let mut rules = self.rules.clone();
+        let watched = rules
+            .iter()
+            .any(|r| r.metric == "container_pids_usage_percent");
+        if self.pids_saturation_percent > 0.0 && !watched {
+            rules.push(AlertRule {
+                name: PIDS_SATURATION_RULE.into(),
+                metric: "container_pids_usage_percent".into(),
+                op: ">=".into(),
+                threshold: self.pids_saturation_percent,
+                duration_secs: 0,
+                cooldown_secs: default_cooldown_secs(),
+                severity: Severity::Warning,
+                hysteresis: 5.0,
+            });
+        }
+        rules
+    }
+
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        let percent = self.pids_saturation_percent;
+        anyhow::ensure!(
+            (0.0..=100.0).contains(&percent),
+            "alerts.pids_saturation_percent must be 0 (off) to 100, got {percent}"
+        );
+        for rule in &self.rules {
+            anyhow::ensure!(!rule.name.is_empty(), "alert rule name must be non-empty");
+            anyhow::ensure!(
+                ALERT_METRICS.contains(&rule.metric.as_str()),
+                "alert rule '{}' has unknown metric '{}' (expected one of {:?})",
+                rule.name,
+                rule.metric,
+                ALERT_METRICS
+            );
+            anyhow::ensure!(
+                matches!(rule.op.as_str(), ">" | ">=" | "<" | "<="),
+                "alert rule '{}' has invalid op '{}' (expected >, >=, <, <=)",
+                rule.name,
+                rule.op
+            );
+            anyhow::ensure!(
+                rule.hysteresis >= 0.0 && rule.hysteresis.is_finite(),
+                "alert rule '{}' has invalid hysteresis {} (expected >= 0)",
+                rule.name,
+                rule.hysteresis
+            );
+        }
+        for rule in &self.container_rules {
+            anyhow::ensure!(
+                !rule.name.is_empty(),
+                "container alert rule name must be non-empty"
+            );
+            anyhow::ensure!(
+                rule.restart_window_secs > 0,
+                "container alert rule '{}' needs restart_window_secs > 0",
+                rule.name
+            );
+        }
+        let urls = self
+            .webhook_url
+            .iter()
+            .chain(self.webhooks.iter().map(|w| &w.url));
+        for url in urls {
+            // The URL is secret: name the problem, not the value.
+            anyhow::ensure!(
+                url.expose().starts_with("http://") || url.expose().starts_with("https://"),
+                "alert webhook URLs must start with http:// or https://"
+            );
+        }
+        if let Some(ref cron_str) = self.report_schedule {
+            cron::Schedule::from_str(&normalize_cron_expression(cron_str)).map_err(|e| {
+                anyhow::anyhow!("alerts.report_schedule invalid cron expression: {e}")
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Metric names accepted in alert rules.
+const ALERT_METRICS: &[&str] = &[
+    "cpu_usage",
+    "mem_usage_percent",
+    "swap_usage_percent",
+    "load_avg_1",
+    "cpu_temperature",
+    "disk_usage_percent",
+    "gpu_temperature",
+    "gpu_utilization",
+    "container_pids_usage_percent",
+];
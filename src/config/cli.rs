@@ -0,0 +1,101 @@
+// Command-line flags: overrides layered over file and environment (highest precedence), plus
+// the `--print-config` / `--check-config` modes that exit instead of starting the server.
+
+use clap::{Args, Parser};
+
+use super::AppConfig;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "homeserver",
+    version,
+    about = "System and Docker stats over WebSockets"
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub overrides: CliOverrides,
+    /// Print the effective configuration (secrets redacted) and exit.
+    #[arg(long, conflicts_with = "check_config")]
+    pub print_config: bool,
+    /// Validate the configuration and exit; nonzero exit code when it is invalid.
+    #[arg(long)]
+    pub check_config: bool,
+}
+
+/// Config values settable from the command line; `None` leaves the file/environment value.
+#[derive(Debug, Clone, Default, Args)]
+pub struct CliOverrides {
+    /// Config file to read; takes precedence over CONFIG_FILE.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
+    /// Listen port (`server.port`).
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// SQLite database file (`database.path`).
+    #[arg(long, value_name = "PATH")]
+    pub db_path: Option<String>,
+    /// Log filter, e.g. `debug` or `homeserver=trace,sqlx=warn` (`logging.filter`, which takes
+    /// precedence over RUST_LOG).
+    #[arg(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+}
+
+impl CliOverrides {
+    /// Write the set flags into the parsed config table; returns the dotted keys they set.
+    pub(super) fn apply(&self, table: &mut toml::Table) -> Vec<&'static str> {
+        let mut values = Vec::new();
+        if let Some(port) = self.port {
+            values.push(("server.port", toml::Value::Integer(port.into())));
+        }
+        if let Some(path) = &self.db_path {
+            values.push(("database.path", toml::Value::String(path.clone())));
+        }
+        if let Some(filter) = &self.log_level {
+            values.push(("logging.filter", toml::Value::String(filter.clone())));
+        }
+        let mut keys = Vec::with_capacity(values.len());
+        for (dotted, value) in values {
+            let (section, key) = dotted.split_once('.').expect("section.key");
+            // A non-table `section` fails deserialization right after, with a clearer message.
+            if let Some(section) = table
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+            {
+                section.insert(key.into(), value);
+            }
+            keys.push(dotted);
+        }
+        keys
+    }
+}
+
+/// What `--print-config` / `--check-config` writes and the process exit code.
+#[derive(Debug)]
+pub struct CliReport {
+    pub output: String,
+    pub exit_code: i32,
+}
+
+impl Cli {
+    /// Run the print/check mode, if one was requested; `None` means start the server.
+    pub fn report(&self) -> Option<CliReport> {
+        if !self.print_config && !self.check_config {
+            return None;
+        }
+        Some(match AppConfig::load_with_overrides(&self.overrides) {
+            Ok(config) if self.print_config => CliReport {
+                output: format!("{config:#?}"),
+                exit_code: 0,
+            },
+            Ok(_) => CliReport {
+                output: "configuration OK".into(),
+                exit_code: 0,
+            },
+            Err(e) => CliReport {
+                output: format!("invalid configuration: {e:#}"),
+                exit_code: 1,
+            },
+        })
+    }
+}
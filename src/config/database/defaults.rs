@@ -0,0 +1,201 @@
+// Defaults for `[database]`: serde field defaults and `DatabaseConfig::default()`.
+
+use super::DatabaseConfig;
+use crate::history_repo::aggregation::AGGREGATED_RESOLUTIONS;
+
+impl Default for DatabaseConfig {
+    /// Same values as the shipped `config.toml`; used for anything the file leaves out.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            engine: default_engine(),
+            url: None,
+            path: "data/server.db".into(),
+            max_pool_size: 10,
+            flush_rate: 10,
+            flush_interval_secs: default_flush_interval_secs(),
+            flush_warn_ms: default_flush_warn_ms(),
+            overflow_policy: default_overflow_policy(),
+            retention_days: default_retention_days(),
+            prune_interval_secs: default_prune_interval_secs(),
+            error_retention_days: default_error_retention_days(),
+            enable_aggregation: default_enable_aggregation(),
+            aggregation_interval_secs: default_aggregation_interval_secs(),
+            aggregation_chunk_buckets: default_aggregation_chunk_buckets(),
+            aggregation_tiers: default_aggregation_tiers(),
+            bucket_timezone: default_bucket_timezone(),
+            aggregation_container_limit: default_aggregation_container_limit(),
+            container_history_top_n: default_container_history_top_n(),
+            container_history_retention_days: default_container_history_retention_days(),
+            container_inventory_retention_days: default_container_inventory_retention_days(),
+            raw_retention_hours: default_raw_retention_hours(),
+            minute_retention_hours: default_minute_retention_hours(),
+            five_minute_retention_days: default_five_minute_retention_days(),
+            hourly_retention_days: default_hourly_retention_days(),
+            aggregated_retention_days: default_aggregated_retention_days(),
+            vacuum_schedule: None,
+            vacuum_interval_secs: default_vacuum_interval_secs(),
+            wal_checkpoint_interval_secs: default_wal_checkpoint_interval_secs(),
+            wal_warn_bytes: default_wal_warn_bytes(),
+            vacuum_mode: default_vacuum_mode(),
+            vacuum_min_free_percent: default_vacuum_min_free_percent(),
+            vacuum_incremental_pages: 0,
+            integrity_check_on_start: true,
+            recover_on_corruption: false,
+            backup_dir: default_backup_dir(),
+            backup_retention_count: default_backup_retention_count(),
+            disk_budget_bytes: 0,
+            min_free_bytes: default_min_free_bytes(),
+            max_history_points: default_max_history_points(),
+            min_snapshot_timestamp_ms: default_min_snapshot_timestamp_ms(),
+            max_future_skew_minutes: default_max_future_skew_minutes(),
+            max_prune_fraction: default_max_prune_fraction(),
+            persist_gpu: true,
+            persist_smart: true,
+            cache_size_kib: default_cache_size_kib(),
+            mmap_size_bytes: 0,
+            temp_store: default_temp_store(),
+            compress_blobs: true,
+        }
+    }
+}
+
+pub(super) fn default_engine() -> String {
+    "sqlite".into()
+}
+
+pub(super) fn default_retention_days() -> u32 {
+    3
+}
+
+pub(super) fn default_flush_interval_secs() -> u64 {
+    30
+}
+
+pub(super) fn default_flush_warn_ms() -> u64 {
+    1000
+}
+
+pub(super) fn default_overflow_policy() -> String {
+    "drop_new".into()
+}
+
+pub(super) fn default_prune_interval_secs() -> u64 {
+    3600
+}
+
+pub(super) fn default_error_retention_days() -> u32 {
+    7
+}
+
+pub(super) fn default_container_history_top_n() -> usize {
+    10
+}
+
+pub(super) fn default_container_history_retention_days() -> u32 {
+    30
+}
+
+pub(super) fn default_container_inventory_retention_days() -> u32 {
+    365
+}
+
+pub(super) fn default_max_history_points() -> u32 {
+    50_000
+}
+
+pub(super) fn default_min_snapshot_timestamp_ms() -> u64 {
+    1_735_689_600_000
+}
+
+pub(super) fn default_max_future_skew_minutes() -> u64 {
+    5
+}
+
+pub(super) fn default_max_prune_fraction() -> f64 {
+    0.5
+}
+
+pub(super) fn default_vacuum_interval_secs() -> u64 {
+    86400
+}
+
+pub(super) fn default_wal_checkpoint_interval_secs() -> u64 {
+    300
+}
+
+pub(super) fn default_wal_warn_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+pub(super) fn default_vacuum_mode() -> String {
+    "full".into()
+}
+
+pub(super) fn default_vacuum_min_free_percent() -> u32 {
+    20
+}
+
+pub(super) fn default_backup_dir() -> String {
+    "data/backups".into()
+}
+
+pub(super) fn default_backup_retention_count() -> u32 {
+    7
+}
+
+pub(super) fn default_enable_aggregation() -> bool {
+    true
+}
+
+pub(super) fn default_aggregation_interval_secs() -> u64 {
+    3600
+}
+
+pub(super) fn default_aggregation_chunk_buckets() -> u32 {
+    50
+}
+
+pub(super) fn default_aggregation_container_limit() -> usize {
+    100
+}
+
+pub(super) fn default_aggregation_tiers() -> Vec<i32> {
+    AGGREGATED_RESOLUTIONS.to_vec()
+}
+
+pub(super) fn default_bucket_timezone() -> String {
+    "UTC".into()
+}
+
+pub(super) fn default_raw_retention_hours() -> u32 {
+    1
+}
+
+pub(super) fn default_minute_retention_hours() -> u32 {
+    24
+}
+
+pub(super) fn default_five_minute_retention_days() -> u32 {
+    7
+}
+
+pub(super) fn default_hourly_retention_days() -> u32 {
+    90
+}
+
+pub(super) fn default_aggregated_retention_days() -> u32 {
+    365
+}
+
+pub(super) fn default_cache_size_kib() -> u32 {
+    8192
+}
+
+pub(super) fn default_temp_store() -> String {
+    "memory".into()
+}
+
+pub(super) fn default_min_free_bytes() -> u64 {
+    200 * 1024 * 1024
+}
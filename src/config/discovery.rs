@@ -0,0 +1,12 @@
+// `[discovery]` section: optional mDNS / DNS-SD advertisement of the server (see
+// `crate::discovery`).
+
+use serde::{Deserialize, Serialize};
+
+/// Off by default: nothing binds UDP 5353 and no task is spawned.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    /// Advertise `_homeserver._tcp.local.` on the TCP listener's port, named after the hostname.
+    pub mdns: bool,
+}
@@ -0,0 +1,21 @@
+// `[docker]` section: how container stats are reported (see `crate::docker_repo`).
+
+use serde::{Deserialize, Serialize};
+
+/// What 100% means for `ContainerStats::cpu_percent` (and its kernel / user split).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuPercentMode {
+    /// One fully used core; a container can reach `online_cpus` × 100% (`docker stats`).
+    #[default]
+    PerCore,
+    /// The whole host, like the host CPU graph: the per-core value divided by `online_cpus`.
+    HostTotal,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DockerConfig {
+    /// Scale of the container CPU percentages; `cpu_percent_of_host` is reported either way.
+    pub cpu_percent_mode: CpuPercentMode,
+}
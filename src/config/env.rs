@@ -0,0 +1,155 @@
+// Environment overrides: `HOMESERVER_<SECTION>__<KEY>=value` layered over the TOML file, e.g.
+// `HOMESERVER_SERVER__PORT=9090` or `HOMESERVER_DATABASE__AGGREGATION_TIERS=[60, 3600]`.
+
+use anyhow::Context;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Prefix of the variables read by [`apply_env_overrides`].
+pub const ENV_PREFIX: &str = "HOMESERVER_";
+/// Separator between nested keys (`SECTION__KEY`); single underscores stay part of a key.
+const KEY_SEPARATOR: &str = "__";
+
+/// A value set from the environment: the dotted key (`server.port`) and the variable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvOverride {
+    pub key: String,
+    pub var: String,
+}
+
+/// Overrides written into the config table, plus the raw text of those whose type was guessed.
+pub(super) struct AppliedEnv {
+    pub overrides: Vec<EnvOverride>,
+    /// Key path and raw value of overrides for keys neither the file nor the defaults hold
+    /// (unset `Option`s such as `server.admin_token`), parsed as TOML on a guess.
+    guessed: Vec<(Vec<String>, String)>,
+}
+
+/// Write every `HOMESERVER_*` variable of `vars` into `table`. A key the file (or, when the file
+/// leaves it out, `defaults`) holds as a string stays a string; other values are read as TOML
+/// (`9090`, `true`, `[60, 300]`, `"quoted"`), falling back to a plain string.
+pub(super) fn apply_env_overrides(
+    table: &mut toml::Table,
+    defaults: &toml::Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<AppliedEnv> {
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    // Deterministic when two variables differ only in case.
+    vars.sort();
+    let mut applied = Vec::with_capacity(vars.len());
+    let mut guessed = Vec::new();
+    for (var, raw) in vars {
+        let path: Vec<String> = var[ENV_PREFIX.len()..]
+            .split(KEY_SEPARATOR)
+            .map(str::to_ascii_lowercase)
+            .collect();
+        anyhow::ensure!(
+            path.iter().all(|segment| !segment.is_empty()),
+            "{var}: empty key (expected {ENV_PREFIX}<SECTION>__<KEY>)"
+        );
+        let (leaf, sections) = path
+            .split_last()
+            .expect("split yields at least one segment");
+        let mut current = &mut *table;
+        for section in sections {
+            current = current
+                .entry(section.as_str())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .with_context(|| format!("{var}: `{section}` is not a section"))?;
+        }
+        let value = match current
+            .get(leaf.as_str())
+            .or_else(|| lookup(defaults, &path))
+        {
+            Some(toml::Value::String(_)) => toml::Value::String(raw),
+            Some(_) => parse_value(raw),
+            None => {
+                guessed.push((path.clone(), raw.clone()));
+                parse_value(raw)
+            }
+        };
+        current.insert(leaf.clone(), value);
+        applied.push(EnvOverride {
+            key: path.join("."),
+            var,
+        });
+    }
+    Ok(AppliedEnv {
+        overrides: applied,
+        guessed,
+    })
+}
+
+impl AppliedEnv {
+    /// Forget overrides for `keys` (set again by a later layer, e.g. the command line).
+    pub(super) fn retain_unset(&mut self, keys: &[&str]) {
+        self.overrides.retain(|o| !keys.contains(&o.key.as_str()));
+        self.guessed
+            .retain(|(path, _)| !keys.contains(&path.join(".").as_str()));
+    }
+
+    /// Deserialize the merged `table`. When a guessed override has the wrong type for its key
+    /// (`HOMESERVER_SERVER__ADMIN_TOKEN=1234` read as an integer), it is retried as the raw
+    /// string; remaining errors name the variable.
+    pub(super) fn deserialize<T: DeserializeOwned>(
+        &self,
+        mut table: toml::Table,
+    ) -> anyhow::Result<T> {
+        loop {
+            let error = match table.clone().try_into::<T>() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            let message = error.to_string();
+            let retry = self.guessed.iter().find(|(path, _)| {
+                message.contains("invalid type")
+                    && message.contains(&path.join("."))
+                    && !matches!(lookup(&table, path), Some(toml::Value::String(_)))
+            });
+            let Some((path, raw)) = retry else {
+                return Err(name_env_variable(error.into(), &self.overrides));
+            };
+            let (leaf, sections) = path.split_last().expect("override paths are non-empty");
+            let mut current = &mut table;
+            for section in sections {
+                current = current
+                    .get_mut(section.as_str())
+                    .and_then(toml::Value::as_table_mut)
+                    .expect("override sections are tables");
+            }
+            current.insert(leaf.clone(), toml::Value::String(raw.clone()));
+        }
+    }
+}
+
+/// The value at `path` in `table`, if every section on the way is a table.
+fn lookup<'a>(table: &'a toml::Table, path: &[String]) -> Option<&'a toml::Value> {
+    let (leaf, sections) = path.split_last()?;
+    let mut current = table;
+    for section in sections {
+        current = current.get(section.as_str())?.as_table()?;
+    }
+    current.get(leaf.as_str())
+}
+
+/// `raw` as a TOML value, or as a string when it is not valid TOML (`0.0.0.0`, `data/x.db`).
+fn parse_value(raw: String) -> toml::Value {
+    match format!("value = {raw}").parse::<toml::Table>() {
+        Ok(mut parsed) => parsed.remove("value").unwrap_or(toml::Value::String(raw)),
+        Err(_) => toml::Value::String(raw),
+    }
+}
+
+/// Prefix a type or validation error about a key set from the environment with the variable's
+/// name, e.g. `HOMESERVER_SERVER__PORT: invalid type: string "abc", expected u16 in `server.port``.
+pub(super) fn name_env_variable(error: anyhow::Error, overrides: &[EnvOverride]) -> anyhow::Error {
+    let message = error.to_string();
+    match overrides.iter().find(|o| message.contains(&o.key)) {
+        Some(o) => anyhow::anyhow!("{}: {}", o.var, message.trim().replace('\n', " ")),
+        None => error,
+    }
+}
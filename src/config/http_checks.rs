@@ -0,0 +1,81 @@
+// [[http_checks]]: HTTP endpoints polled for uptime (see `crate::http_checks`), served at
+// GET /api/checks.
+
+use serde::{Deserialize, Serialize};
+
+use super::ProbesConfig;
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HttpCheck {
+    /// Key in snapshots, `/api/checks`, alerts and events; unique, non-empty.
+    pub name: String,
+    /// `http://` or `https://` URL requested with GET.
+    pub url: String,
+    /// At least 1.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// A request without a response by then fails.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Status the endpoint must answer; unset = any 2xx.
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+    /// Name of the container serving the endpoint, passed through for the dashboard.
+    #[serde(default)]
+    pub container: Option<String>,
+}
+
+/// `[probes]` and `[[http_checks]]`, the checks of other hosts and endpoints.
+pub(super) fn validate_reachability(
+    probes: &ProbesConfig,
+    checks: &[HttpCheck],
+) -> anyhow::Result<()> {
+    probes.validate()?;
+    validate_http_checks(checks)
+}
+
+/// Names must be non-empty and unique, URLs absolute http(s), statuses valid HTTP codes.
+fn validate_http_checks(checks: &[HttpCheck]) -> anyhow::Result<()> {
+    for (i, check) in checks.iter().enumerate() {
+        anyhow::ensure!(
+            !check.name.trim().is_empty(),
+            "http_checks[{i}].name must be non-empty"
+        );
+        anyhow::ensure!(
+            !checks[..i].iter().any(|c| c.name == check.name),
+            "http_checks: duplicate name '{}'",
+            check.name
+        );
+        let url = reqwest::Url::parse(&check.url)
+            .map_err(|e| anyhow::anyhow!("http_checks[{i}].url '{}': {e}", check.url))?;
+        anyhow::ensure!(
+            matches!(url.scheme(), "http" | "https") && url.has_host(),
+            "http_checks[{i}].url must be an http:// or https:// URL, got '{}'",
+            check.url
+        );
+        anyhow::ensure!(
+            check.interval_secs >= 1,
+            "http_checks[{i}].interval_secs must be at least 1 second, got {}",
+            check.interval_secs
+        );
+        anyhow::ensure!(
+            check.timeout_ms > 0,
+            "http_checks[{i}].timeout_ms must be > 0"
+        );
+        if let Some(status) = check.expected_status {
+            anyhow::ensure!(
+                (100..=599).contains(&status),
+                "http_checks[{i}].expected_status must be 100-599, got {status}"
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,270 @@
+// AppConfig: TOML sections, loading (defaults, file, environment overrides in env.rs, then
+// command-line flags in cli.rs), validation (see validate.rs).
+
+mod alerts;
+mod cli;
+mod database;
+mod discovery;
+mod docker;
+mod env;
+mod http_checks;
+mod monitoring;
+mod mqtt;
+mod probes;
+mod remote_write;
+mod sanitized;
+mod secret;
+mod sources;
+mod telemetry;
+mod tls;
+mod validate;
+mod wol;
+
+pub use alerts::{
+    AlertRule, AlertsConfig, ContainerEventKind, ContainerRule, PIDS_SATURATION_RULE, Severity,
+    WebhookConfig, WebhookFormat,
+};
+pub use cli::{Cli, CliOverrides, CliReport};
+pub use database::DatabaseConfig;
+pub use discovery::DiscoveryConfig;
+pub use docker::{CpuPercentMode, DockerConfig};
+pub use env::{ENV_PREFIX, EnvOverride};
+pub use http_checks::HttpCheck;
+pub use monitoring::MonitoringConfig;
+pub use mqtt::MqttConfig;
+pub use probes::{ProbeTarget, ProbesConfig};
+pub use remote_write::{MAX_INGEST_BATCH, RemoteWriteConfig, RemoteWriteFormat, valid_node};
+pub use sanitized::SanitizedConfig;
+pub use secret::Secret;
+pub use sources::ConfigSources;
+pub use telemetry::TelemetryConfig;
+pub use tls::TlsConfig;
+pub use wol::WolTarget;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Converts 5-field Unix cron (min hour dom month dow) to 6-field (sec min hour dom month dow)
+/// by prepending "0" for seconds. The cron crate expects at least 6 fields.
+pub(crate) fn normalize_cron_expression(s: &str) -> String {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.len() == 5 {
+        format!("0 {}", parts.join(" "))
+    } else {
+        s.trim().to_string()
+    }
+}
+
+/// Every section and field has a built-in default, so an empty file (or none at all) is a
+/// valid config; values that are present are still validated.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub publishing: PublishingConfig,
+    pub monitoring: MonitoringConfig,
+    pub alerts: AlertsConfig,
+    pub mqtt: MqttConfig,
+    pub remote_write: RemoteWriteConfig,
+    pub discovery: DiscoveryConfig,
+    pub docker: DockerConfig,
+    pub probes: ProbesConfig,
+    /// `[[http_checks]]`: endpoints polled for uptime; none = no checker task.
+    pub http_checks: Vec<HttpCheck>,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// 0 lets the OS pick a free port (see `serve::ServerHandle::bound_address`).
+    pub port: u16,
+    pub host: String,
+    /// Serve on `host:port`; set false to listen only on `unix_socket_path`.
+    pub tcp_enabled: bool,
+    /// Also (or, with `tcp_enabled = false`, only) serve on this Unix domain socket.
+    pub unix_socket_path: Option<String>,
+    /// Permission bits applied to the socket file, e.g. `0o660`.
+    pub unix_socket_mode: u32,
+    /// Bearer token required by admin endpoints (`POST /api/config/reload`); unset disables them.
+    pub admin_token: Option<Secret>,
+    /// When set, WebSocket upgrades need `Authorization: Bearer <ws_token>` or `?token=`.
+    pub ws_token: Option<Secret>,
+    /// Serve HTTPS / WSS on the TCP listener; the certificate is re-read on SIGHUP.
+    pub tls: Option<TlsConfig>,
+    /// Serve the built-in HTML status page at `/`; false answers a plain text line instead.
+    pub status_page: bool,
+    /// This instance's name on `SystemInfo`, `/api/stats`, `/api/bootstrap` and as the `node`
+    /// label of every metric, so clients behind one proxy can tell instances apart. Also tags
+    /// remote-written batches and names the local rows for `/api/history?node=`.
+    pub node_name: String,
+    /// Serve `POST /api/wol` (admin token required) and `GET /api/wol/targets`.
+    pub enable_wol: bool,
+    /// Named machines the dashboard can offer to wake.
+    pub wol_targets: Vec<WolTarget>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8081,
+            host: "0.0.0.0".into(),
+            tcp_enabled: true,
+            unix_socket_path: None,
+            unix_socket_mode: 0o660,
+            admin_token: None,
+            ws_token: None,
+            tls: None,
+            status_page: true,
+            node_name: default_node_name(),
+            enable_wol: false,
+            wol_targets: Vec::new(),
+        }
+    }
+}
+
+/// The host name when it is a [`valid_node`], else "homeserver".
+fn default_node_name() -> String {
+    sysinfo::System::host_name()
+        .filter(|h| valid_node(h))
+        .unwrap_or_else(|| "homeserver".into())
+}
+
+pub(super) fn default_true() -> bool {
+    true
+}
+
+/// Smallest accepted `publishing.max_snapshot_bytes`; even an idle host's snapshot is close to it.
+pub(super) const MIN_SNAPSHOT_BYTES: u64 = 1024;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PublishingConfig {
+    pub cpu_stats_frequency_ms: u64,
+    pub ram_stats_frequency_ms: u64,
+    /// Max number of full-system snapshots kept in the broadcast channel for /ws/system (slow clients may lag).
+    pub broadcast_capacity: usize,
+    /// Besides the per-lag warning, warn that clients keep lagging when /ws/system clients fall
+    /// behind the broadcast more often than this within a minute (0: on the first lag of each
+    /// minute).
+    pub lag_warn_per_minute: u64,
+    /// Warn when one snapshot serializes to more than this many bytes (each queued copy holds it).
+    pub max_snapshot_bytes: u64,
+    /// Open WebSocket connections (all channels) beyond which upgrades are refused with 503.
+    /// Unset: no limit.
+    pub max_ws_connections: Option<usize>,
+}
+
+impl Default for PublishingConfig {
+    fn default() -> Self {
+        Self {
+            cpu_stats_frequency_ms: 1000,
+            ram_stats_frequency_ms: 1000,
+            broadcast_capacity: 60,
+            lag_warn_per_minute: 10,
+            max_snapshot_bytes: 1024 * 1024,
+            max_ws_connections: None,
+        }
+    }
+}
+
+/// `[logging]`: the `tracing` filter (`EnvFilter` syntax, e.g. "info,sqlx=warn"). Unset falls back
+/// to `RUST_LOG`, then "info". Applied again on reload.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub filter: Option<String>,
+}
+
+impl AppConfig {
+    /// Load `CONFIG_FILE` (default `config.toml`), then apply `HOMESERVER_*` environment
+    /// overrides. Without `CONFIG_FILE`, a missing `config.toml` means built-in defaults; an
+    /// explicit `CONFIG_FILE` must exist.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_with_overrides(&CliOverrides::default())
+    }
+
+    /// [`AppConfig::load`] with command-line flags on top: `--config` replaces `CONFIG_FILE`,
+    /// the value flags win over both file and environment.
+    pub fn load_with_overrides(cli: &CliOverrides) -> anyhow::Result<Self> {
+        Self::load_with_sources(cli).map(|(config, _)| config)
+    }
+
+    /// [`AppConfig::load_with_overrides`], also returning the file read and the overridden keys.
+    pub fn load_with_sources(cli: &CliOverrides) -> anyhow::Result<(Self, ConfigSources)> {
+        let explicit = cli
+            .config
+            .clone()
+            .or_else(|| std::env::var("CONFIG_FILE").ok());
+        let (s, path) = match explicit {
+            Some(path) => (
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("reading config file {path}"))?,
+                Some(path),
+            ),
+            None => match std::fs::read_to_string("config.toml") {
+                Ok(s) => (s, Some("config.toml".to_string())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    tracing::info!("no config file found, using defaults");
+                    (String::new(), None)
+                }
+                Err(e) => return Err(e).context("reading config file config.toml"),
+            },
+        };
+        let (config, sources) = Self::load_layered_with_sources(&s, std::env::vars(), cli)?;
+        Ok((config, ConfigSources { path, ..sources }))
+    }
+
+    /// Parse `s`, apply the `HOMESERVER_*` entries of `vars` on top, then validate the result.
+    pub fn load_with_env(
+        s: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<Self> {
+        Self::load_layered(s, vars, &CliOverrides::default())
+    }
+
+    /// Parse `s`, then apply environment overrides from `vars` and the value flags of `cli`
+    /// (highest precedence), then validate the result.
+    pub fn load_layered(
+        s: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+        cli: &CliOverrides,
+    ) -> anyhow::Result<Self> {
+        Self::load_layered_with_sources(s, vars, cli).map(|(config, _)| config)
+    }
+
+    /// [`AppConfig::load_layered`], also returning the keys the environment and `cli` set
+    /// (`path` is left `None`).
+    pub fn load_layered_with_sources(
+        s: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+        cli: &CliOverrides,
+    ) -> anyhow::Result<(Self, ConfigSources)> {
+        let mut table: toml::Table = toml::from_str(s)?;
+        // Types for keys the file leaves out come from the defaults.
+        let defaults = toml::Table::try_from(AppConfig::default())?;
+        let mut env = env::apply_env_overrides(&mut table, &defaults, vars)?;
+        let cli_keys = cli.apply(&mut table);
+        // Errors about a key the command line set must not blame an environment variable.
+        env.retain_unset(&cli_keys);
+        let config: AppConfig = env.deserialize(table)?;
+        config
+            .validate()
+            .map_err(|e| env::name_env_variable(e, &env.overrides))?;
+        let sources = ConfigSources {
+            path: None,
+            env: env.overrides,
+            cli: cli_keys.into_iter().map(String::from).collect(),
+        };
+        Ok((config, sources))
+    }
+
+    /// Parse and validate config from a string (e.g. for tests).
+    pub fn load_from_str(s: &str) -> anyhow::Result<Self> {
+        let config: AppConfig = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,166 @@
+// `[monitoring]` section: sampling, per-subsystem and idle intervals, system info refresh,
+// priming the live view from history.
+
+use serde::{Deserialize, Serialize};
+
+use super::default_true;
+
+fn default_smart_poll_interval_secs() -> u64 {
+    900
+}
+
+fn default_sensor_rescan_secs() -> u64 {
+    300
+}
+
+fn default_error_record_interval_secs() -> u64 {
+    60
+}
+
+fn default_idle_grace_secs() -> u64 {
+    30
+}
+
+fn default_prime_from_history_minutes() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MonitoringConfig {
+    pub sample_interval_ms: u64,
+    /// How often to log app stats (ws_system clients, snapshots saved/pruned) at INFO level.
+    pub stats_log_interval_secs: u64,
+    /// Collect GPU metrics each tick (NVIDIA needs the `gpu-nvidia` build feature; AMD/Intel via /sys).
+    #[serde(default = "default_true")]
+    pub collect_gpu: bool,
+    /// Collect SMART disk health (requires smartctl + device privileges). Off by default.
+    #[serde(default)]
+    pub collect_smart: bool,
+    /// How often to refresh SMART data (seconds). SMART reads are slow/privileged.
+    #[serde(default = "default_smart_poll_interval_secs")]
+    pub smart_poll_interval_secs: u64,
+    /// Read hwmon temperatures, fan speeds and voltages (NVMe, chipset, drives) each tick.
+    #[serde(default = "default_true")]
+    pub collect_sensors: bool,
+    /// Look for added or removed hwmon sensors every N seconds.
+    #[serde(default = "default_sensor_rescan_secs")]
+    pub sensor_rescan_secs: u64,
+    /// Record at most one collector failure per source every N seconds in `collection_errors`;
+    /// failures in between are counted on the next entry.
+    #[serde(default = "default_error_record_interval_secs")]
+    pub error_record_interval_secs: u64,
+    /// Re-collect storage every N ms (a multiple of `sample_interval_ms`); ticks in between reuse
+    /// the previous reading. Unset means every tick.
+    #[serde(default)]
+    pub storage_interval_ms: Option<u64>,
+    /// Re-list Docker containers every N ms; same rules as `storage_interval_ms`.
+    #[serde(default)]
+    pub docker_interval_ms: Option<u64>,
+    /// Re-read uptime, process count and load every N ms; same rules as `storage_interval_ms`.
+    #[serde(default)]
+    pub system_interval_ms: Option<u64>,
+    /// Tick interval while no WebSocket client is connected (after `idle_grace_secs`); the
+    /// worker returns to `sample_interval_ms` as soon as one connects. Unset disables it.
+    #[serde(default)]
+    pub idle_sample_interval_ms: Option<u64>,
+    /// How long without any WebSocket client before switching to `idle_sample_interval_ms`.
+    #[serde(default = "default_idle_grace_secs")]
+    pub idle_grace_secs: u64,
+    /// Re-detect the system identity (`/api/info`) every N seconds, e.g. after a host rename or
+    /// DMI data that was not readable at boot. Unset: only at startup and on
+    /// `POST /api/info/refresh`.
+    #[serde(default)]
+    pub system_info_refresh_secs: Option<u64>,
+    /// Drop a container's cached Docker stats once they are older than N ms and restart its
+    /// stats stream. Unset keeps serving the last entry however old.
+    #[serde(default)]
+    pub container_stale_ms: Option<u64>,
+    /// At startup, serve the newest stored snapshot as the latest one (tagged `historical`) when
+    /// it is at most N minutes old, until the first tick. 0 disables it.
+    #[serde(default = "default_prime_from_history_minutes")]
+    pub prime_from_history_minutes: u64,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_ms: 1000,
+            stats_log_interval_secs: 60,
+            collect_gpu: true,
+            collect_smart: false,
+            smart_poll_interval_secs: default_smart_poll_interval_secs(),
+            collect_sensors: true,
+            sensor_rescan_secs: default_sensor_rescan_secs(),
+            error_record_interval_secs: default_error_record_interval_secs(),
+            storage_interval_ms: None,
+            docker_interval_ms: None,
+            system_interval_ms: None,
+            idle_sample_interval_ms: None,
+            idle_grace_secs: default_idle_grace_secs(),
+            system_info_refresh_secs: None,
+            container_stale_ms: None,
+            prime_from_history_minutes: default_prime_from_history_minutes(),
+        }
+    }
+}
+
+impl MonitoringConfig {
+    /// The configured subsystem intervals, each falling back to `sample_interval_ms`.
+    pub fn subsystem_intervals_ms(&self) -> [(&'static str, u64); 3] {
+        let or_sample = |ms: Option<u64>| ms.unwrap_or(self.sample_interval_ms);
+        [
+            ("storage_interval_ms", or_sample(self.storage_interval_ms)),
+            ("docker_interval_ms", or_sample(self.docker_interval_ms)),
+            ("system_interval_ms", or_sample(self.system_interval_ms)),
+        ]
+    }
+
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.sample_interval_ms > 0,
+            "monitoring.sample_interval_ms must be > 0, got {}",
+            self.sample_interval_ms
+        );
+        anyhow::ensure!(
+            self.stats_log_interval_secs > 0,
+            "monitoring.stats_log_interval_secs must be > 0, got {}",
+            self.stats_log_interval_secs
+        );
+        anyhow::ensure!(
+            self.sensor_rescan_secs > 0,
+            "monitoring.sensor_rescan_secs must be > 0, got {}",
+            self.sensor_rescan_secs
+        );
+        anyhow::ensure!(
+            self.error_record_interval_secs > 0,
+            "monitoring.error_record_interval_secs must be > 0, got {}",
+            self.error_record_interval_secs
+        );
+        for (name, interval_ms) in self.subsystem_intervals_ms() {
+            anyhow::ensure!(
+                interval_ms > 0 && interval_ms % self.sample_interval_ms == 0,
+                "monitoring.{name} must be a positive multiple of sample_interval_ms ({}), got {}",
+                self.sample_interval_ms,
+                interval_ms
+            );
+        }
+        if let Some(idle_ms) = self.idle_sample_interval_ms {
+            anyhow::ensure!(
+                idle_ms >= self.sample_interval_ms,
+                "monitoring.idle_sample_interval_ms must be >= sample_interval_ms ({}), got {}",
+                self.sample_interval_ms,
+                idle_ms
+            );
+        }
+        anyhow::ensure!(
+            self.system_info_refresh_secs != Some(0),
+            "monitoring.system_info_refresh_secs must be > 0 (omit it to disable the refresh)"
+        );
+        anyhow::ensure!(
+            self.container_stale_ms != Some(0),
+            "monitoring.container_stale_ms must be > 0 (omit it to keep serving cached stats)"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+// `[mqtt]` section: optional snapshot publishing to an MQTT broker (see `crate::mqtt`).
+
+use serde::{Deserialize, Serialize};
+
+use super::Secret;
+
+/// Per-metric state topics under `base_topic` plus Home Assistant discovery. Without
+/// `broker_url` nothing connects and no task is spawned.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    /// `mqtt://host[:port]` (port 1883 by default). Plain TCP only.
+    pub broker_url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub client_id: String,
+    /// Prefix of the state topics, e.g. `homeserver/cpu/usage`; also names the HA device.
+    pub base_topic: String,
+    /// Home Assistant discovery prefix (HA's default is `homeassistant`).
+    pub discovery_prefix: String,
+    /// 0, 1 or 2.
+    pub qos: u8,
+    /// Publish the latest snapshot at most this often, whatever the sample rate.
+    pub publish_interval_secs: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker_url: None,
+            username: None,
+            password: None,
+            client_id: "homeserver".into(),
+            base_topic: "homeserver".into(),
+            discovery_prefix: "homeassistant".into(),
+            qos: 0,
+            publish_interval_secs: 10,
+        }
+    }
+}
+
+impl MqttConfig {
+    /// Host and port of `broker_url`, or `None` when MQTT is off.
+    pub fn broker(&self) -> anyhow::Result<Option<(String, u16)>> {
+        let Some(url) = &self.broker_url else {
+            return Ok(None);
+        };
+        let rest = url
+            .strip_prefix("mqtt://")
+            .or_else(|| url.strip_prefix("tcp://"))
+            .ok_or_else(|| {
+                anyhow::anyhow!("mqtt.broker_url must be an mqtt:// URL, got '{url}'")
+            })?;
+        let rest = rest.trim_end_matches('/');
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| anyhow::anyhow!("mqtt.broker_url has an invalid port: '{url}'"))?,
+            ),
+            None => (rest, 1883),
+        };
+        anyhow::ensure!(!host.is_empty(), "mqtt.broker_url has no host: '{url}'");
+        Ok(Some((host.to_string(), port)))
+    }
+
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        self.broker()?;
+        anyhow::ensure!(
+            self.qos <= 2,
+            "mqtt.qos must be 0, 1 or 2, got {}",
+            self.qos
+        );
+        anyhow::ensure!(
+            self.publish_interval_secs > 0,
+            "mqtt.publish_interval_secs must be > 0, got {}",
+            self.publish_interval_secs
+        );
+        for (key, value) in [
+            ("client_id", &self.client_id),
+            ("base_topic", &self.base_topic),
+            ("discovery_prefix", &self.discovery_prefix),
+        ] {
+            anyhow::ensure!(!value.is_empty(), "mqtt.{key} must be non-empty");
+            anyhow::ensure!(
+                !value.contains(['+', '#']),
+                "mqtt.{key} must not contain MQTT wildcards, got '{value}'"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+// `[probes]` section: reachability / latency probes to configured hosts (see `crate::probes`).
+
+use serde::{Deserialize, Serialize};
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+/// One `[[probes.targets]]` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProbeTarget {
+    /// Key of the result in snapshots and `/api/probes`; unique, non-empty.
+    pub name: String,
+    /// Host name or IP address; names are resolved again before every probe.
+    pub target: String,
+    /// At least 1.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// TCP port of the connect fallback for this target; unset = `probes.tcp_port`.
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+/// Without targets no task is spawned.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProbesConfig {
+    pub targets: Vec<ProbeTarget>,
+    /// A probe without an answer by then counts as lost.
+    pub timeout_ms: u64,
+    /// ICMP echo needs CAP_NET_RAW; without it every target is probed with a TCP connect to
+    /// this port instead (an answered connect, even a refused one, is a round trip).
+    pub tcp_port: u16,
+    /// Probes per target that RTT statistics and loss are computed over.
+    pub window: usize,
+}
+
+impl Default for ProbesConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            timeout_ms: 1000,
+            tcp_port: 443,
+            window: 10,
+        }
+    }
+}
+
+impl ProbesConfig {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.timeout_ms > 0,
+            "probes.timeout_ms must be > 0, got {}",
+            self.timeout_ms
+        );
+        anyhow::ensure!(self.tcp_port > 0, "probes.tcp_port must be > 0");
+        anyhow::ensure!(
+            self.window > 0,
+            "probes.window must be > 0, got {}",
+            self.window
+        );
+        for (i, probe) in self.targets.iter().enumerate() {
+            anyhow::ensure!(
+                !probe.name.trim().is_empty(),
+                "probes.targets[{i}].name must be non-empty"
+            );
+            anyhow::ensure!(
+                !self.targets[..i].iter().any(|p| p.name == probe.name),
+                "probes.targets: duplicate name '{}'",
+                probe.name
+            );
+            anyhow::ensure!(
+                !probe.target.trim().is_empty(),
+                "probes.targets[{i}].target must be non-empty"
+            );
+            anyhow::ensure!(
+                probe.interval_secs >= 1,
+                "probes.targets[{i}].interval_secs must be at least 1 second, got {}",
+                probe.interval_secs
+            );
+            anyhow::ensure!(
+                probe.port != Some(0),
+                "probes.targets[{i}].port must be > 0"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+// `[remote_write]` section: pushing snapshots to another instance and accepting pushes from
+// others (see `crate::remote_write` and `POST /api/ingest`).
+
+use serde::{Deserialize, Serialize};
+
+use super::Secret;
+
+/// Largest batch `POST /api/ingest` accepts (and so the largest `batch_size`).
+pub const MAX_INGEST_BATCH: usize = 1000;
+
+/// Body encoding of the pushed batches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteWriteFormat {
+    #[default]
+    Json,
+    /// Compact binary (`application/x-wincode`); both ends must run the same version.
+    Wincode,
+}
+
+/// Sending side: without `url` no task is spawned; batches are tagged with `server.node_name`. Receiving side: without `ingest_api_key`
+/// `POST /api/ingest` answers 403.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RemoteWriteConfig {
+    /// Base URL of the central instance, e.g. `http://central.lan:8081`; batches go to
+    /// `<url>/api/ingest`.
+    pub url: Option<String>,
+    /// Sent as `Authorization: Bearer`; must match the central instance's `ingest_api_key`.
+    pub api_key: Option<Secret>,
+    /// Key this instance requires on `POST /api/ingest`.
+    pub ingest_api_key: Option<Secret>,
+    pub format: RemoteWriteFormat,
+    /// Snapshots per POST; a partial batch is sent every `flush_interval_secs`.
+    pub batch_size: usize,
+    pub flush_interval_secs: u64,
+    /// Batches that cannot be delivered are written here (one file each) once a few are queued
+    /// in memory, and sent oldest first when the central instance is back.
+    pub spill_dir: String,
+    /// Cap on the spill directory; the oldest batches are dropped beyond it.
+    pub max_spill_bytes: u64,
+}
+
+impl Default for RemoteWriteConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            api_key: None,
+            ingest_api_key: None,
+            format: RemoteWriteFormat::Json,
+            batch_size: 60,
+            flush_interval_secs: 10,
+            spill_dir: "data/remote_write".into(),
+            max_spill_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Node names are 1–64 ASCII alphanumerics, `-`, `_` or `.`.
+pub fn valid_node(node: &str) -> bool {
+    (1..=64).contains(&node.len())
+        && node
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl RemoteWriteConfig {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        if let Some(url) = &self.url {
+            anyhow::ensure!(
+                url.starts_with("http://") || url.starts_with("https://"),
+                "remote_write.url must be an http:// or https:// URL, got '{url}'"
+            );
+        }
+        for (key, secret) in [
+            ("api_key", &self.api_key),
+            ("ingest_api_key", &self.ingest_api_key),
+        ] {
+            if let Some(secret) = secret {
+                anyhow::ensure!(
+                    !secret.expose().is_empty(),
+                    "remote_write.{key} must be non-empty when set"
+                );
+            }
+        }
+        anyhow::ensure!(
+            (1..=MAX_INGEST_BATCH).contains(&self.batch_size),
+            "remote_write.batch_size must be between 1 and {MAX_INGEST_BATCH}, got {}",
+            self.batch_size
+        );
+        anyhow::ensure!(
+            self.flush_interval_secs > 0,
+            "remote_write.flush_interval_secs must be > 0, got {}",
+            self.flush_interval_secs
+        );
+        anyhow::ensure!(
+            !self.spill_dir.is_empty(),
+            "remote_write.spill_dir must be non-empty"
+        );
+        Ok(())
+    }
+}
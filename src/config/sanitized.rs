@@ -0,0 +1,105 @@
+// Read-only view of the effective config for `GET /api/config`, with every secret replaced by a
+// marker and credentials stripped from URLs. Every section (and list entry) has its own struct
+// built by destructuring, so a new field does not compile until it is classified here as safe to
+// show, redacted, or shown without userinfo.
+
+mod alerts;
+mod database;
+mod monitoring;
+mod outputs;
+mod server;
+
+use serde::Serialize;
+
+use super::{AppConfig, Secret};
+use alerts::SanitizedAlerts;
+use database::SanitizedDatabase;
+use monitoring::{
+    SanitizedDiscovery, SanitizedDocker, SanitizedHttpCheck, SanitizedLogging, SanitizedMonitoring,
+    SanitizedProbes, SanitizedPublishing,
+};
+use outputs::{SanitizedMqtt, SanitizedRemoteWrite, SanitizedTelemetry};
+use server::SanitizedServer;
+
+/// What a redacted value serializes as; unset values stay `null`.
+pub const REDACTED: &str = "<redacted>";
+
+fn redact(_: &Secret) -> &'static str {
+    REDACTED
+}
+
+fn redact_opt(secret: &Option<Secret>) -> Option<&'static str> {
+    secret.as_ref().map(redact)
+}
+
+/// `url` without its `user:password@` part; unchanged when it has none. A value that does not
+/// parse as a URL is redacted whole, since where its credentials are cannot be told.
+fn without_userinfo(url: String) -> String {
+    match reqwest::Url::parse(&url) {
+        Ok(parsed) if parsed.username().is_empty() && parsed.password().is_none() => url,
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.into()
+        }
+        Err(_) if url.contains('@') => REDACTED.to_string(),
+        Err(_) => url,
+    }
+}
+
+fn without_userinfo_opt(url: Option<String>) -> Option<String> {
+    url.map(without_userinfo)
+}
+
+/// [`AppConfig`] with the same keys as `config.toml`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedConfig {
+    pub server: SanitizedServer,
+    pub database: SanitizedDatabase,
+    pub publishing: SanitizedPublishing,
+    pub monitoring: SanitizedMonitoring,
+    pub alerts: SanitizedAlerts,
+    pub mqtt: SanitizedMqtt,
+    pub remote_write: SanitizedRemoteWrite,
+    pub discovery: SanitizedDiscovery,
+    pub docker: SanitizedDocker,
+    pub probes: SanitizedProbes,
+    pub http_checks: Vec<SanitizedHttpCheck>,
+    pub logging: SanitizedLogging,
+    pub telemetry: SanitizedTelemetry,
+}
+
+impl SanitizedConfig {
+    pub fn new(config: AppConfig) -> Self {
+        let AppConfig {
+            server,
+            database,
+            publishing,
+            monitoring,
+            alerts,
+            mqtt,
+            remote_write,
+            discovery,
+            docker,
+            probes,
+            http_checks,
+            logging,
+            telemetry,
+        } = config;
+        Self {
+            server: server.into(),
+            database: database.into(),
+            publishing: publishing.into(),
+            monitoring: monitoring.into(),
+            alerts: alerts.into(),
+            mqtt: mqtt.into(),
+            remote_write: remote_write.into(),
+            discovery: discovery.into(),
+            docker: docker.into(),
+            probes: probes.into(),
+            http_checks: http_checks.into_iter().map(Into::into).collect(),
+            logging: logging.into(),
+            telemetry: telemetry.into(),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// `[alerts]` for `GET /api/config`: webhook URLs carry their tokens and are redacted.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::{redact, redact_opt};
+use crate::config::{
+    AlertRule, AlertsConfig, ContainerEventKind, ContainerRule, Severity, WebhookConfig,
+    WebhookFormat,
+};
+use crate::reports::ReportPeriod;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedAlerts {
+    pub webhook_url: Option<&'static str>,
+    pub webhooks: Vec<SanitizedWebhook>,
+    pub webhook_retries: u32,
+    pub webhook_retry_backoff_ms: u64,
+    pub rules: Vec<SanitizedAlertRule>,
+    pub container_rules: Vec<SanitizedContainerRule>,
+    pub report_schedule: Option<String>,
+    pub report_period: ReportPeriod,
+    pub pids_saturation_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedWebhook {
+    pub url: &'static str,
+    pub format: WebhookFormat,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedAlertRule {
+    pub name: String,
+    pub metric: String,
+    pub op: String,
+    pub threshold: f64,
+    pub duration_secs: u64,
+    pub cooldown_secs: u64,
+    pub severity: Severity,
+    pub hysteresis: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedContainerRule {
+    pub name: String,
+    pub event: ContainerEventKind,
+    pub container: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    pub restart_count: u32,
+    pub restart_window_secs: u64,
+    pub cooldown_secs: u64,
+    pub severity: Severity,
+}
+
+impl From<AlertsConfig> for SanitizedAlerts {
+    fn from(alerts: AlertsConfig) -> Self {
+        let AlertsConfig {
+            webhook_url,
+            webhooks,
+            webhook_retries,
+            webhook_retry_backoff_ms,
+            rules,
+            container_rules,
+            report_schedule,
+            report_period,
+            pids_saturation_percent,
+        } = alerts;
+        Self {
+            webhook_url: redact_opt(&webhook_url),
+            webhooks: webhooks
+                .into_iter()
+                .map(|WebhookConfig { url, format }| SanitizedWebhook {
+                    url: redact(&url),
+                    format,
+                })
+                .collect(),
+            webhook_retries,
+            webhook_retry_backoff_ms,
+            rules: rules.into_iter().map(Into::into).collect(),
+            container_rules: container_rules.into_iter().map(Into::into).collect(),
+            report_schedule,
+            report_period,
+            pids_saturation_percent,
+        }
+    }
+}
+
+impl From<AlertRule> for SanitizedAlertRule {
+    fn from(rule: AlertRule) -> Self {
+        let AlertRule {
+            name,
+            metric,
+            op,
+            threshold,
+            duration_secs,
+            cooldown_secs,
+            severity,
+            hysteresis,
+        } = rule;
+        Self {
+            name,
+            metric,
+            op,
+            threshold,
+            duration_secs,
+            cooldown_secs,
+            severity,
+            hysteresis,
+        }
+    }
+}
+
+impl From<ContainerRule> for SanitizedContainerRule {
+    fn from(rule: ContainerRule) -> Self {
+        let ContainerRule {
+            name,
+            event,
+            container,
+            labels,
+            restart_count,
+            restart_window_secs,
+            cooldown_secs,
+            severity,
+        } = rule;
+        Self {
+            name,
+            event,
+            container,
+            labels,
+            restart_count,
+            restart_window_secs,
+            cooldown_secs,
+            severity,
+        }
+    }
+}
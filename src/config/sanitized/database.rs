@@ -0,0 +1,167 @@
+// `[database]` for `GET /api/config`: the Postgres `url` carries a password and is redacted.
+
+use serde::Serialize;
+
+use super::redact_opt;
+use crate::config::DatabaseConfig;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedDatabase {
+    pub enabled: bool,
+    pub engine: String,
+    /// Carries the Postgres password.
+    pub url: Option<&'static str>,
+    pub path: String,
+    pub max_pool_size: u32,
+    pub flush_rate: u64,
+    pub flush_interval_secs: u64,
+    pub flush_warn_ms: u64,
+    pub overflow_policy: String,
+    pub retention_days: u32,
+    pub prune_interval_secs: u64,
+    pub error_retention_days: u32,
+    pub enable_aggregation: bool,
+    pub aggregation_interval_secs: u64,
+    pub aggregation_chunk_buckets: u32,
+    pub aggregation_tiers: Vec<i32>,
+    pub bucket_timezone: String,
+    pub aggregation_container_limit: usize,
+    pub container_history_top_n: usize,
+    pub container_history_retention_days: u32,
+    pub container_inventory_retention_days: u32,
+    pub raw_retention_hours: u32,
+    pub minute_retention_hours: u32,
+    pub five_minute_retention_days: u32,
+    pub hourly_retention_days: u32,
+    pub aggregated_retention_days: u32,
+    pub vacuum_schedule: Option<String>,
+    pub vacuum_interval_secs: u64,
+    pub wal_checkpoint_interval_secs: u64,
+    pub wal_warn_bytes: u64,
+    pub vacuum_mode: String,
+    pub vacuum_min_free_percent: u32,
+    pub vacuum_incremental_pages: u32,
+    pub integrity_check_on_start: bool,
+    pub recover_on_corruption: bool,
+    pub backup_dir: String,
+    pub backup_retention_count: u32,
+    pub disk_budget_bytes: u64,
+    pub min_free_bytes: u64,
+    pub max_history_points: u32,
+    pub min_snapshot_timestamp_ms: u64,
+    pub max_future_skew_minutes: u64,
+    pub max_prune_fraction: f64,
+    pub persist_gpu: bool,
+    pub persist_smart: bool,
+    pub cache_size_kib: u32,
+    pub mmap_size_bytes: u64,
+    pub temp_store: String,
+    pub compress_blobs: bool,
+}
+
+impl From<DatabaseConfig> for SanitizedDatabase {
+    fn from(database: DatabaseConfig) -> Self {
+        let DatabaseConfig {
+            enabled,
+            engine,
+            url,
+            path,
+            max_pool_size,
+            flush_rate,
+            flush_interval_secs,
+            flush_warn_ms,
+            overflow_policy,
+            retention_days,
+            prune_interval_secs,
+            error_retention_days,
+            enable_aggregation,
+            aggregation_interval_secs,
+            aggregation_chunk_buckets,
+            aggregation_tiers,
+            bucket_timezone,
+            aggregation_container_limit,
+            container_history_top_n,
+            container_history_retention_days,
+            container_inventory_retention_days,
+            raw_retention_hours,
+            minute_retention_hours,
+            five_minute_retention_days,
+            hourly_retention_days,
+            aggregated_retention_days,
+            vacuum_schedule,
+            vacuum_interval_secs,
+            wal_checkpoint_interval_secs,
+            wal_warn_bytes,
+            vacuum_mode,
+            vacuum_min_free_percent,
+            vacuum_incremental_pages,
+            integrity_check_on_start,
+            recover_on_corruption,
+            backup_dir,
+            backup_retention_count,
+            disk_budget_bytes,
+            min_free_bytes,
+            max_history_points,
+            min_snapshot_timestamp_ms,
+            max_future_skew_minutes,
+            max_prune_fraction,
+            persist_gpu,
+            persist_smart,
+            cache_size_kib,
+            mmap_size_bytes,
+            temp_store,
+            compress_blobs,
+        } = database;
+        Self {
+            enabled,
+            engine,
+            url: redact_opt(&url),
+            path,
+            max_pool_size,
+            flush_rate,
+            flush_interval_secs,
+            flush_warn_ms,
+            overflow_policy,
+            retention_days,
+            prune_interval_secs,
+            error_retention_days,
+            enable_aggregation,
+            aggregation_interval_secs,
+            aggregation_chunk_buckets,
+            aggregation_tiers,
+            bucket_timezone,
+            aggregation_container_limit,
+            container_history_top_n,
+            container_history_retention_days,
+            container_inventory_retention_days,
+            raw_retention_hours,
+            minute_retention_hours,
+            five_minute_retention_days,
+            hourly_retention_days,
+            aggregated_retention_days,
+            vacuum_schedule,
+            vacuum_interval_secs,
+            wal_checkpoint_interval_secs,
+            wal_warn_bytes,
+            vacuum_mode,
+            vacuum_min_free_percent,
+            vacuum_incremental_pages,
+            integrity_check_on_start,
+            recover_on_corruption,
+            backup_dir,
+            backup_retention_count,
+            disk_budget_bytes,
+            min_free_bytes,
+            max_history_points,
+            min_snapshot_timestamp_ms,
+            max_future_skew_minutes,
+            max_prune_fraction,
+            persist_gpu,
+            persist_smart,
+            cache_size_kib,
+            mmap_size_bytes,
+            temp_store,
+            compress_blobs,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Sections without secrets for `GET /api/config`: `[publishing]`, `[monitoring]`, `[docker]`,
+// `[probes]`, `[discovery]`, `[logging]`, and `[[http_checks]]` (URLs shown without userinfo).
+
+use serde::Serialize;
+
+use super::without_userinfo;
+use crate::config::{
+    CpuPercentMode, DiscoveryConfig, DockerConfig, HttpCheck, LoggingConfig, MonitoringConfig,
+    ProbeTarget, ProbesConfig, PublishingConfig,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedPublishing {
+    pub cpu_stats_frequency_ms: u64,
+    pub ram_stats_frequency_ms: u64,
+    pub broadcast_capacity: usize,
+    pub lag_warn_per_minute: u64,
+    pub max_snapshot_bytes: u64,
+    pub max_ws_connections: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedMonitoring {
+    pub sample_interval_ms: u64,
+    pub stats_log_interval_secs: u64,
+    pub collect_gpu: bool,
+    pub collect_smart: bool,
+    pub smart_poll_interval_secs: u64,
+    pub collect_sensors: bool,
+    pub sensor_rescan_secs: u64,
+    pub error_record_interval_secs: u64,
+    pub storage_interval_ms: Option<u64>,
+    pub docker_interval_ms: Option<u64>,
+    pub system_interval_ms: Option<u64>,
+    pub idle_sample_interval_ms: Option<u64>,
+    pub idle_grace_secs: u64,
+    pub system_info_refresh_secs: Option<u64>,
+    pub container_stale_ms: Option<u64>,
+    pub prime_from_history_minutes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedDocker {
+    pub cpu_percent_mode: CpuPercentMode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedProbes {
+    pub targets: Vec<SanitizedProbeTarget>,
+    pub timeout_ms: u64,
+    pub tcp_port: u16,
+    pub window: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedProbeTarget {
+    pub name: String,
+    pub target: String,
+    pub interval_secs: u64,
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedHttpCheck {
+    pub name: String,
+    pub url: String,
+    pub interval_secs: u64,
+    pub timeout_ms: u64,
+    pub expected_status: Option<u16>,
+    pub container: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedDiscovery {
+    pub mdns: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedLogging {
+    pub filter: Option<String>,
+}
+
+impl From<PublishingConfig> for SanitizedPublishing {
+    fn from(publishing: PublishingConfig) -> Self {
+        let PublishingConfig {
+            cpu_stats_frequency_ms,
+            ram_stats_frequency_ms,
+            broadcast_capacity,
+            lag_warn_per_minute,
+            max_snapshot_bytes,
+            max_ws_connections,
+        } = publishing;
+        Self {
+            cpu_stats_frequency_ms,
+            ram_stats_frequency_ms,
+            broadcast_capacity,
+            lag_warn_per_minute,
+            max_snapshot_bytes,
+            max_ws_connections,
+        }
+    }
+}
+
+impl From<MonitoringConfig> for SanitizedMonitoring {
+    fn from(monitoring: MonitoringConfig) -> Self {
+        let MonitoringConfig {
+            sample_interval_ms,
+            stats_log_interval_secs,
+            collect_gpu,
+            collect_smart,
+            smart_poll_interval_secs,
+            collect_sensors,
+            sensor_rescan_secs,
+            error_record_interval_secs,
+            storage_interval_ms,
+            docker_interval_ms,
+            system_interval_ms,
+            idle_sample_interval_ms,
+            idle_grace_secs,
+            system_info_refresh_secs,
+            container_stale_ms,
+            prime_from_history_minutes,
+        } = monitoring;
+        Self {
+            sample_interval_ms,
+            stats_log_interval_secs,
+            collect_gpu,
+            collect_smart,
+            smart_poll_interval_secs,
+            collect_sensors,
+            sensor_rescan_secs,
+            error_record_interval_secs,
+            storage_interval_ms,
+            docker_interval_ms,
+            system_interval_ms,
+            idle_sample_interval_ms,
+            idle_grace_secs,
+            system_info_refresh_secs,
+            container_stale_ms,
+            prime_from_history_minutes,
+        }
+    }
+}
+
+impl From<DockerConfig> for SanitizedDocker {
+    fn from(DockerConfig { cpu_percent_mode }: DockerConfig) -> Self {
+        Self { cpu_percent_mode }
+    }
+}
+
+impl From<ProbesConfig> for SanitizedProbes {
+    fn from(probes: ProbesConfig) -> Self {
+        let ProbesConfig {
+            targets,
+            timeout_ms,
+            tcp_port,
+            window,
+        } = probes;
+        Self {
+            targets: targets.into_iter().map(Into::into).collect(),
+            timeout_ms,
+            tcp_port,
+            window,
+        }
+    }
+}
+
+impl From<ProbeTarget> for SanitizedProbeTarget {
+    fn from(target: ProbeTarget) -> Self {
+        let ProbeTarget {
+            name,
+            target,
+            interval_secs,
+            port,
+        } = target;
+        Self {
+            name,
+            target,
+            interval_secs,
+            port,
+        }
+    }
+}
+
+impl From<HttpCheck> for SanitizedHttpCheck {
+    fn from(check: HttpCheck) -> Self {
+        let HttpCheck {
+            name,
+            url,
+            interval_secs,
+            timeout_ms,
+            expected_status,
+            container,
+        } = check;
+        Self {
+            name,
+            url: without_userinfo(url),
+            interval_secs,
+            timeout_ms,
+            expected_status,
+            container,
+        }
+    }
+}
+
+impl From<DiscoveryConfig> for SanitizedDiscovery {
+    fn from(DiscoveryConfig { mdns }: DiscoveryConfig) -> Self {
+        Self { mdns }
+    }
+}
+
+impl From<LoggingConfig> for SanitizedLogging {
+    fn from(LoggingConfig { filter }: LoggingConfig) -> Self {
+        Self { filter }
+    }
+}
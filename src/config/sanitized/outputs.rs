@@ -0,0 +1,100 @@
+// `[mqtt]`, `[remote_write]` and `[telemetry]` for `GET /api/config`: passwords and keys
+// redacted, broker / central / collector URLs shown without userinfo.
+
+use serde::Serialize;
+
+use super::{redact_opt, without_userinfo_opt};
+use crate::config::{MqttConfig, RemoteWriteConfig, RemoteWriteFormat, TelemetryConfig};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedMqtt {
+    pub broker_url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<&'static str>,
+    pub client_id: String,
+    pub base_topic: String,
+    pub discovery_prefix: String,
+    pub qos: u8,
+    pub publish_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedRemoteWrite {
+    pub url: Option<String>,
+    pub api_key: Option<&'static str>,
+    pub ingest_api_key: Option<&'static str>,
+    pub format: RemoteWriteFormat,
+    pub batch_size: usize,
+    pub flush_interval_secs: u64,
+    pub spill_dir: String,
+    pub max_spill_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedTelemetry {
+    pub otlp_endpoint: Option<String>,
+    pub sampling_ratio: f64,
+}
+
+impl From<MqttConfig> for SanitizedMqtt {
+    fn from(mqtt: MqttConfig) -> Self {
+        let MqttConfig {
+            broker_url,
+            username,
+            password,
+            client_id,
+            base_topic,
+            discovery_prefix,
+            qos,
+            publish_interval_secs,
+        } = mqtt;
+        Self {
+            broker_url: without_userinfo_opt(broker_url),
+            username,
+            password: redact_opt(&password),
+            client_id,
+            base_topic,
+            discovery_prefix,
+            qos,
+            publish_interval_secs,
+        }
+    }
+}
+
+impl From<RemoteWriteConfig> for SanitizedRemoteWrite {
+    fn from(remote_write: RemoteWriteConfig) -> Self {
+        let RemoteWriteConfig {
+            url,
+            api_key,
+            ingest_api_key,
+            format,
+            batch_size,
+            flush_interval_secs,
+            spill_dir,
+            max_spill_bytes,
+        } = remote_write;
+        Self {
+            url: without_userinfo_opt(url),
+            api_key: redact_opt(&api_key),
+            ingest_api_key: redact_opt(&ingest_api_key),
+            format,
+            batch_size,
+            flush_interval_secs,
+            spill_dir,
+            max_spill_bytes,
+        }
+    }
+}
+
+impl From<TelemetryConfig> for SanitizedTelemetry {
+    fn from(telemetry: TelemetryConfig) -> Self {
+        let TelemetryConfig {
+            otlp_endpoint,
+            sampling_ratio,
+        } = telemetry;
+        Self {
+            otlp_endpoint: without_userinfo_opt(otlp_endpoint),
+            sampling_ratio,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// `[server]` for `GET /api/config`: tokens and the TLS key location redacted.
+
+use std::net::Ipv4Addr;
+
+use serde::Serialize;
+
+use super::{REDACTED, redact_opt};
+use crate::config::{ServerConfig, TlsConfig, WolTarget};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedServer {
+    pub port: u16,
+    pub host: String,
+    pub tcp_enabled: bool,
+    pub unix_socket_path: Option<String>,
+    pub unix_socket_mode: u32,
+    pub admin_token: Option<&'static str>,
+    pub ws_token: Option<&'static str>,
+    pub tls: Option<SanitizedTls>,
+    pub status_page: bool,
+    pub node_name: String,
+    pub enable_wol: bool,
+    pub wol_targets: Vec<SanitizedWolTarget>,
+}
+
+/// The certificate is public; the key's location is not shown.
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedTls {
+    pub cert_path: String,
+    pub key_path: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SanitizedWolTarget {
+    pub name: String,
+    pub mac: String,
+    pub broadcast: Option<Ipv4Addr>,
+}
+
+impl From<ServerConfig> for SanitizedServer {
+    fn from(server: ServerConfig) -> Self {
+        let ServerConfig {
+            port,
+            host,
+            tcp_enabled,
+            unix_socket_path,
+            unix_socket_mode,
+            admin_token,
+            ws_token,
+            tls,
+            status_page,
+            node_name,
+            enable_wol,
+            wol_targets,
+        } = server;
+        Self {
+            port,
+            host,
+            tcp_enabled,
+            unix_socket_path,
+            unix_socket_mode,
+            admin_token: redact_opt(&admin_token),
+            ws_token: redact_opt(&ws_token),
+            tls: tls.map(
+                |TlsConfig {
+                     cert_path,
+                     key_path: _,
+                 }| SanitizedTls {
+                    cert_path,
+                    key_path: REDACTED,
+                },
+            ),
+            status_page,
+            node_name,
+            enable_wol,
+            wol_targets: wol_targets
+                .into_iter()
+                .map(
+                    |WolTarget {
+                         name,
+                         mac,
+                         broadcast,
+                     }| SanitizedWolTarget {
+                        name,
+                        mac,
+                        broadcast,
+                    },
+                )
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// Secret config values (webhook URLs carrying tokens, future API keys): deserialized like a
+// plain string, but `Debug` never prints them, so logging the effective config is safe.
+
+use serde::{Deserialize, Serialize};
+
+/// A string that is redacted in `Debug` output. Use [`Secret::expose`] where the value is needed.
+/// `Serialize` writes the real value (config diffs on reload); never log serialized config.
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The actual value; never log it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("\"<redacted>\"")
+    }
+}
@@ -0,0 +1,17 @@
+// Where the effective config came from: the file read and the keys set by the environment or
+// the command line (shown on `GET /api/config`).
+
+use serde::Serialize;
+
+use super::env::EnvOverride;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSources {
+    /// The config file read; `None` when no file was found and only defaults apply.
+    pub path: Option<String>,
+    /// Keys set by `HOMESERVER_*` variables, except those a flag set again.
+    pub env: Vec<EnvOverride>,
+    /// Keys set by command-line flags.
+    pub cli: Vec<String>,
+}
@@ -0,0 +1,39 @@
+// `[telemetry]` section: optional OTLP trace export (see `crate::telemetry`).
+
+use serde::{Deserialize, Serialize};
+
+/// OTLP/HTTP trace export. Without `otlp_endpoint` nothing is exported and no exporter is built.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Full traces URL of the collector, e.g. `http://localhost:4318/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of new traces to sample, 0.0 to 1.0 (child spans follow their parent).
+    pub sampling_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            sampling_ratio: 1.0,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&self.sampling_ratio),
+            "telemetry.sampling_ratio must be between 0.0 and 1.0, got {}",
+            self.sampling_ratio
+        );
+        if let Some(endpoint) = &self.otlp_endpoint {
+            anyhow::ensure!(
+                endpoint.starts_with("http://") || endpoint.starts_with("https://"),
+                "telemetry.otlp_endpoint must be an http:// or https:// URL, got '{endpoint}'"
+            );
+        }
+        Ok(())
+    }
+}
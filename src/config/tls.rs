@@ -0,0 +1,49 @@
+// [server.tls]: PEM certificate chain and private key for HTTPS / WSS on the TCP listener.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first (e.g. Let's Encrypt `fullchain.pem`).
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1) matching the leaf certificate.
+    pub key_path: String,
+}
+
+impl TlsConfig {
+    /// Read and check the certificate and key. Used by validation (so startup, `--check-config`
+    /// and reload fail fast) and by the listener, which also calls it again on SIGHUP.
+    pub fn load(&self) -> anyhow::Result<rustls::ServerConfig> {
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("server.tls.cert_path {} is not readable", self.cert_path))?;
+        anyhow::ensure!(
+            !certs.is_empty(),
+            "server.tls.cert_path {} contains no PEM certificate",
+            self.cert_path
+        );
+        let key = PrivateKeyDer::from_pem_file(&self.key_path).with_context(|| {
+            format!(
+                "server.tls.key_path {} is not a readable PEM private key",
+                self.key_path
+            )
+        })?;
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .with_context(|| {
+                format!(
+                    "server.tls: key {} does not match certificate {}",
+                    self.key_path, self.cert_path
+                )
+            })?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
@@ -0,0 +1,300 @@
+// AppConfig validation: fail fast on nonsensical values before anything is started.
+
+use std::str::FromStr;
+
+use super::database::{
+    MAX_MMAP_SIZE_BYTES, OVERFLOW_POLICY_VALUES, TEMP_STORE_VALUES, VACUUM_MODE_VALUES,
+};
+use super::{
+    AppConfig, MIN_SNAPSHOT_BYTES, http_checks, normalize_cron_expression, valid_node, wol,
+};
+use crate::history_repo::aggregation::BucketTimezone;
+
+impl AppConfig {
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        self.database.validate_engine()?;
+        anyhow::ensure!(
+            !self.database.path.is_empty(),
+            "database.path must be non-empty"
+        );
+        anyhow::ensure!(
+            self.database.max_pool_size > 0,
+            "database.max_pool_size must be > 0, got {}",
+            self.database.max_pool_size
+        );
+        anyhow::ensure!(
+            self.database.cache_size_kib > 0,
+            "database.cache_size_kib must be > 0, got {}",
+            self.database.cache_size_kib
+        );
+        anyhow::ensure!(
+            self.database.mmap_size_bytes <= MAX_MMAP_SIZE_BYTES,
+            "database.mmap_size_bytes must be <= {}, got {}",
+            MAX_MMAP_SIZE_BYTES,
+            self.database.mmap_size_bytes
+        );
+        anyhow::ensure!(
+            TEMP_STORE_VALUES.contains(&self.database.temp_store.as_str()),
+            "database.temp_store must be one of {:?}, got '{}'",
+            TEMP_STORE_VALUES,
+            self.database.temp_store
+        );
+        anyhow::ensure!(
+            self.database.flush_rate > 0,
+            "database.flush_rate must be > 0, got {}",
+            self.database.flush_rate
+        );
+        anyhow::ensure!(
+            self.database.flush_interval_secs > 0,
+            "database.flush_interval_secs must be > 0, got {}",
+            self.database.flush_interval_secs
+        );
+        anyhow::ensure!(
+            self.database.retention_days > 0,
+            "database.retention_days must be > 0, got {}",
+            self.database.retention_days
+        );
+        anyhow::ensure!(
+            self.database.prune_interval_secs > 0,
+            "database.prune_interval_secs must be > 0, got {}",
+            self.database.prune_interval_secs
+        );
+        let db = &self.database;
+        for (key, days) in [
+            ("error_retention_days", db.error_retention_days),
+            (
+                "container_history_retention_days",
+                db.container_history_retention_days,
+            ),
+            (
+                "container_inventory_retention_days",
+                db.container_inventory_retention_days,
+            ),
+            ("aggregated_retention_days", db.aggregated_retention_days),
+        ] {
+            anyhow::ensure!(days > 0, "database.{key} must be > 0, got {days}");
+        }
+        anyhow::ensure!(
+            self.database.max_history_points > 0,
+            "database.max_history_points must be > 0, got {}",
+            self.database.max_history_points
+        );
+        anyhow::ensure!(
+            self.database.max_prune_fraction > 0.0 && self.database.max_prune_fraction <= 1.0,
+            "database.max_prune_fraction must be in (0, 1], got {}",
+            self.database.max_prune_fraction
+        );
+        self.validate_aggregation_tiers()?;
+        self.database
+            .bucket_timezone
+            .parse::<BucketTimezone>()
+            .map_err(|e| anyhow::anyhow!("database.bucket_timezone: {e}"))?;
+        if let Some(ref cron_str) = self.database.vacuum_schedule {
+            let normalized = normalize_cron_expression(cron_str);
+            cron::Schedule::from_str(&normalized).map_err(|e| {
+                anyhow::anyhow!("database.vacuum_schedule invalid cron expression: {}", e)
+            })?;
+        } else {
+            anyhow::ensure!(
+                self.database.vacuum_interval_secs > 0,
+                "database.vacuum_interval_secs must be > 0 when vacuum_schedule is not set, got {}",
+                self.database.vacuum_interval_secs
+            );
+        }
+        anyhow::ensure!(
+            self.database.wal_checkpoint_interval_secs > 0,
+            "database.wal_checkpoint_interval_secs must be > 0, got {}",
+            self.database.wal_checkpoint_interval_secs
+        );
+        anyhow::ensure!(
+            OVERFLOW_POLICY_VALUES.contains(&self.database.overflow_policy.as_str()),
+            "database.overflow_policy must be one of {:?}, got '{}'",
+            OVERFLOW_POLICY_VALUES,
+            self.database.overflow_policy
+        );
+        anyhow::ensure!(
+            VACUUM_MODE_VALUES.contains(&self.database.vacuum_mode.as_str()),
+            "database.vacuum_mode must be one of {:?}, got '{}'",
+            VACUUM_MODE_VALUES,
+            self.database.vacuum_mode
+        );
+        anyhow::ensure!(
+            self.database.vacuum_min_free_percent <= 100,
+            "database.vacuum_min_free_percent must be <= 100, got {}",
+            self.database.vacuum_min_free_percent
+        );
+        anyhow::ensure!(
+            !self.database.backup_dir.is_empty(),
+            "database.backup_dir must not be empty"
+        );
+        anyhow::ensure!(
+            self.database.backup_retention_count > 0,
+            "database.backup_retention_count must be > 0, got {}",
+            self.database.backup_retention_count
+        );
+        if self.database.enable_aggregation {
+            anyhow::ensure!(
+                self.database.aggregation_interval_secs > 0,
+                "database.aggregation_interval_secs must be > 0 when enable_aggregation is true, got {}",
+                self.database.aggregation_interval_secs
+            );
+            anyhow::ensure!(
+                self.database.aggregation_chunk_buckets > 0,
+                "database.aggregation_chunk_buckets must be > 0 when enable_aggregation is true, got {}",
+                self.database.aggregation_chunk_buckets
+            );
+            anyhow::ensure!(
+                self.database.raw_retention_hours > 0,
+                "database.raw_retention_hours must be > 0 when enable_aggregation is true, got {}",
+                self.database.raw_retention_hours
+            );
+            anyhow::ensure!(
+                self.database.minute_retention_hours > 0,
+                "database.minute_retention_hours must be > 0 when enable_aggregation is true, got {}",
+                self.database.minute_retention_hours
+            );
+            anyhow::ensure!(
+                self.database.five_minute_retention_days > 0,
+                "database.five_minute_retention_days must be > 0 when enable_aggregation is true, got {}",
+                self.database.five_minute_retention_days
+            );
+            anyhow::ensure!(
+                self.database.hourly_retention_days > 0,
+                "database.hourly_retention_days must be > 0 when enable_aggregation is true, got {}",
+                self.database.hourly_retention_days
+            );
+            self.validate_retention_order()?;
+        }
+        anyhow::ensure!(
+            self.publishing.cpu_stats_frequency_ms > 0,
+            "publishing.cpu_stats_frequency_ms must be > 0, got {}",
+            self.publishing.cpu_stats_frequency_ms
+        );
+        anyhow::ensure!(
+            self.publishing.ram_stats_frequency_ms > 0,
+            "publishing.ram_stats_frequency_ms must be > 0, got {}",
+            self.publishing.ram_stats_frequency_ms
+        );
+        anyhow::ensure!(
+            self.publishing.broadcast_capacity > 0,
+            "publishing.broadcast_capacity must be > 0, got {}",
+            self.publishing.broadcast_capacity
+        );
+        anyhow::ensure!(
+            self.publishing.max_snapshot_bytes >= MIN_SNAPSHOT_BYTES,
+            "publishing.max_snapshot_bytes must be >= {MIN_SNAPSHOT_BYTES}, got {}",
+            self.publishing.max_snapshot_bytes
+        );
+        anyhow::ensure!(
+            self.publishing.max_ws_connections != Some(0),
+            "publishing.max_ws_connections must be > 0 (omit it for no limit)"
+        );
+        self.monitoring.validate()?;
+        if let Some(filter) = &self.logging.filter {
+            tracing_subscriber::EnvFilter::try_new(filter)
+                .map_err(|e| anyhow::anyhow!("logging.filter '{filter}' is invalid: {e}"))?;
+        }
+        anyhow::ensure!(
+            self.server.tcp_enabled || self.server.unix_socket_path.is_some(),
+            "server.tcp_enabled = false needs server.unix_socket_path"
+        );
+        anyhow::ensure!(
+            self.server.unix_socket_mode <= 0o777,
+            "server.unix_socket_mode must be <= 0o777, got {:#o}",
+            self.server.unix_socket_mode
+        );
+        anyhow::ensure!(
+            valid_node(&self.server.node_name),
+            "server.node_name must be 1-64 characters of [A-Za-z0-9._-], got '{}'",
+            self.server.node_name
+        );
+        wol::validate_wol_targets(&self.server.wol_targets)?;
+        if let Some(tls) = &self.server.tls {
+            tls.load()?;
+        }
+        if let Some(token) = &self.server.admin_token {
+            anyhow::ensure!(
+                !token.expose().is_empty(),
+                "server.admin_token must be non-empty when set"
+            );
+        }
+        if let Some(token) = &self.server.ws_token {
+            anyhow::ensure!(
+                !token.expose().is_empty(),
+                "server.ws_token must be non-empty when set"
+            );
+        }
+        self.telemetry.validate()?;
+        self.mqtt.validate()?;
+        http_checks::validate_reachability(&self.probes, &self.http_checks)?;
+        self.remote_write.validate()?;
+        self.alerts.validate()
+    }
+
+    /// Tiers must be positive, ascending, and each a multiple of the finer one it is rolled up
+    /// from, so every coarser bucket is made of whole finer buckets.
+    fn validate_aggregation_tiers(&self) -> anyhow::Result<()> {
+        let tiers = &self.database.aggregation_tiers;
+        anyhow::ensure!(
+            !tiers.is_empty(),
+            "database.aggregation_tiers must not be empty"
+        );
+        anyhow::ensure!(
+            tiers[0] > 0,
+            "database.aggregation_tiers entries must be > 0, got {:?}",
+            tiers
+        );
+        for pair in tiers.windows(2) {
+            let (finer, coarser) = (pair[0], pair[1]);
+            anyhow::ensure!(
+                coarser > finer,
+                "database.aggregation_tiers must be strictly ascending, got {:?}",
+                tiers
+            );
+            anyhow::ensure!(
+                coarser % finer == 0,
+                "database.aggregation_tiers: {coarser} is not a multiple of {finer}"
+            );
+        }
+        Ok(())
+    }
+
+    /// Each tier must keep rows at least as long as the finer tier it is rolled up from, and raw
+    /// rows must survive pruning until they are rolled up.
+    fn validate_retention_order(&self) -> anyhow::Result<()> {
+        let db = &self.database;
+        let tiers = [
+            ("raw_retention_hours", u64::from(db.raw_retention_hours)),
+            (
+                "minute_retention_hours",
+                u64::from(db.minute_retention_hours),
+            ),
+            (
+                "five_minute_retention_days",
+                u64::from(db.five_minute_retention_days) * 24,
+            ),
+            (
+                "hourly_retention_days",
+                u64::from(db.hourly_retention_days) * 24,
+            ),
+            (
+                "aggregated_retention_days",
+                u64::from(db.aggregated_retention_days) * 24,
+            ),
+        ];
+        for pair in tiers.windows(2) {
+            let ((finer, finer_h), (coarser, coarser_h)) = (pair[0], pair[1]);
+            anyhow::ensure!(
+                coarser_h >= finer_h,
+                "database.{coarser} ({coarser_h} h) must be >= database.{finer} ({finer_h} h)"
+            );
+        }
+        let retention_h = u64::from(db.retention_days) * 24;
+        anyhow::ensure!(
+            retention_h >= u64::from(db.raw_retention_hours),
+            "database.retention_days ({retention_h} h) must be >= database.raw_retention_hours ({} h), or raw rows are pruned before they are rolled up",
+            db.raw_retention_hours
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+// [[server.wol_targets]]: named Wake-on-LAN targets, listed by GET /api/wol/targets and woken
+// with POST /api/wol {"target": name} when `server.enable_wol` is set.
+
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::net_tools::MacAddr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WolTarget {
+    /// Shown on the dashboard button; unique, non-empty.
+    pub name: String,
+    /// `AA:BB:CC:DD:EE:FF` (or `-`-separated).
+    pub mac: String,
+    /// Where to send the packet; unset = the primary interface's broadcast address.
+    #[serde(default)]
+    pub broadcast: Option<Ipv4Addr>,
+}
+
+/// Names must be non-empty and unique, MACs well-formed.
+pub(super) fn validate_wol_targets(targets: &[WolTarget]) -> anyhow::Result<()> {
+    for (i, target) in targets.iter().enumerate() {
+        anyhow::ensure!(
+            !target.name.trim().is_empty(),
+            "server.wol_targets[{i}].name must be non-empty"
+        );
+        anyhow::ensure!(
+            !targets[..i].iter().any(|t| t.name == target.name),
+            "server.wol_targets: duplicate name '{}'",
+            target.name
+        );
+        target
+            .mac
+            .parse::<MacAddr>()
+            .map_err(|e| anyhow::anyhow!("server.wol_targets[{i}].mac: {e}"))?;
+    }
+    Ok(())
+}
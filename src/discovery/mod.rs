@@ -0,0 +1,101 @@
+// mDNS / DNS-SD advertisement (`[discovery] mdns = true`): registers `_homeserver._tcp.local.`
+// with an `mdns-sd` daemon once the listener is bound (probing, conflict renames, announcements
+// and answers are the daemon's) and unregisters it on shutdown so browsers drop it at once.
+
+mod task;
+
+pub use task::spawn;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::AppConfig;
+
+/// DNS-SD service type the server registers under.
+pub const SERVICE_TYPE: &str = "_homeserver._tcp.local.";
+
+/// Longest DNS label (RFC 1035); longer hostnames are cut.
+const MAX_LABEL: usize = 63;
+
+/// What is advertised: `<instance>._homeserver._tcp.local.` pointing at `<host>.local.:<port>`,
+/// with the host's interface addresses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdnsService {
+    /// Instance label, e.g. `nas` (may contain spaces; shown by browsers as is, never dots).
+    pub instance: String,
+    /// Host label of the SRV target, e.g. `nas` for `nas.local.`.
+    pub host: String,
+    pub port: u16,
+    /// TXT entries as key / value.
+    pub txt: Vec<(&'static str, String)>,
+}
+
+impl MdnsService {
+    /// The service for `config` on `port`, named after the first label of `hostname` (`nas` for
+    /// `nas.lan`), with the TXT entries of [`txt_records`].
+    pub fn new(config: &AppConfig, hostname: &str, port: u16) -> Self {
+        let first = hostname.trim().split('.').next().unwrap_or_default();
+        let instance = truncate_label(first);
+        let instance = if instance.is_empty() {
+            crate::version::NAME.to_string()
+        } else {
+            instance
+        };
+        Self {
+            host: host_label(&instance),
+            instance,
+            port,
+            txt: txt_records(
+                crate::version::VERSION,
+                config.server.tls.is_some(),
+                config.server.ws_token.is_some(),
+            ),
+        }
+    }
+}
+
+/// Start the responder when `[discovery] mdns` is on, advertising `tcp_port` (the bound port of
+/// the TCP listener). Without TCP there is nothing to advertise: a warning, no task.
+pub fn spawn_configured(
+    config: &AppConfig,
+    tcp_port: Option<u16>,
+    shutdown: CancellationToken,
+) -> Option<JoinHandle<()>> {
+    if !config.discovery.mdns {
+        return None;
+    }
+    let Some(port) = tcp_port else {
+        tracing::warn!(
+            "discovery.mdns needs the TCP listener (server.tcp_enabled); not advertising"
+        );
+        return None;
+    };
+    let hostname = sysinfo::System::host_name().unwrap_or_default();
+    Some(spawn(MdnsService::new(config, &hostname, port), shutdown))
+}
+
+/// TXT entries: the server version and whether the listener is TLS and the live streams need a
+/// token (`ws_token`), so clients can pick `https://` and prompt for it before connecting.
+pub fn txt_records(version: &str, tls: bool, auth: bool) -> Vec<(&'static str, String)> {
+    vec![
+        ("version", version.to_string()),
+        ("tls", tls.to_string()),
+        ("auth", auth.to_string()),
+    ]
+}
+
+/// `name` cut to one DNS label on a character boundary.
+fn truncate_label(name: &str) -> String {
+    let mut end = name.len().min(MAX_LABEL);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].to_string()
+}
+
+/// A hostname label: anything but letters, digits and `-` as `-`.
+fn host_label(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
@@ -0,0 +1,79 @@
+// The advertisement task: register the service with an `mdns-sd` daemon, wait for shutdown, then
+// unregister it (a goodbye with TTL 0) and stop the daemon.
+
+use std::time::Duration;
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::MdnsService;
+
+/// How long shutdown waits for the goodbye to go out.
+const UNREGISTER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Advertise `service` until `shutdown` is cancelled. Without a usable network (no multicast
+/// interface, the daemon cannot start) it logs a warning and the task ends; the server keeps
+/// running.
+pub fn spawn(service: MdnsService, shutdown: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let daemon = match ServiceDaemon::new() {
+            Ok(daemon) => daemon,
+            Err(e) => {
+                tracing::warn!(error = %e, "mDNS advertisement unavailable");
+                return;
+            }
+        };
+        match register(&daemon, &service) {
+            Ok(fullname) => {
+                tracing::info!(
+                    instance = %service.instance,
+                    port = service.port,
+                    "Advertising {} over mDNS",
+                    super::SERVICE_TYPE
+                );
+                shutdown.cancelled().await;
+                unregister(&daemon, &fullname).await;
+            }
+            Err(e) => tracing::warn!(error = %e, "mDNS advertisement unavailable"),
+        }
+        if let Err(e) = daemon.shutdown() {
+            tracing::debug!(error = %e, "mDNS daemon shutdown failed");
+        }
+    })
+}
+
+/// Register `service` on every interface's addresses; returns its full name.
+fn register(daemon: &ServiceDaemon, service: &MdnsService) -> mdns_sd::Result<String> {
+    let txt: Vec<(&str, &str)> = service
+        .txt
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect();
+    let info = ServiceInfo::new(
+        super::SERVICE_TYPE,
+        &service.instance,
+        &format!("{}.local.", service.host),
+        "",
+        service.port,
+        txt.as_slice(),
+    )?
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info)?;
+    Ok(fullname)
+}
+
+async fn unregister(daemon: &ServiceDaemon, fullname: &str) {
+    match daemon.unregister(fullname) {
+        Ok(status) => {
+            if tokio::time::timeout(UNREGISTER_TIMEOUT, status.recv_async())
+                .await
+                .is_err()
+            {
+                tracing::debug!("mDNS unregister did not complete in time");
+            }
+        }
+        Err(e) => tracing::debug!(error = %e, "mDNS unregister failed"),
+    }
+}
@@ -0,0 +1,70 @@
+// Docker event stream: container lifecycle events for event-based alert rules.
+
+use bollard::models::{EventMessage, EventMessageTypeEnum};
+use bollard::query_parameters::EventsOptions;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use std::collections::HashMap;
+
+use super::DockerRepo;
+use crate::models::{ContainerAction, ContainerEvent};
+
+impl DockerRepo {
+    /// Container events from now on. The stream ends (or yields an error) when the daemon
+    /// connection drops; callers reconnect.
+    pub fn container_events(&self) -> BoxStream<'static, anyhow::Result<ContainerEvent>> {
+        let options = EventsOptions {
+            filters: Some(HashMap::from([(
+                "type".to_string(),
+                vec!["container".to_string()],
+            )])),
+            ..Default::default()
+        };
+        self.docker
+            .events(Some(options))
+            .filter_map(|msg| async move {
+                match msg {
+                    Ok(msg) => parse_event(&msg).map(Ok),
+                    Err(e) => Some(Err(e.into())),
+                }
+            })
+            .boxed()
+    }
+}
+
+/// A [`ContainerEvent`] from a Docker event message; `None` for other object types and for
+/// actions alerting ignores (create, kill, exec_*, ...).
+pub fn parse_event(msg: &EventMessage) -> Option<ContainerEvent> {
+    if msg.typ != Some(EventMessageTypeEnum::CONTAINER) {
+        return None;
+    }
+    let action = match msg.action.as_deref()? {
+        "start" => ContainerAction::Start,
+        "die" => ContainerAction::Die,
+        "oom" => ContainerAction::Oom,
+        "restart" => ContainerAction::Restart,
+        "health_status: unhealthy" => ContainerAction::Unhealthy,
+        "health_status: healthy" => ContainerAction::Healthy,
+        _ => return None,
+    };
+    let actor = msg.actor.as_ref()?;
+    let attributes = actor.attributes.clone().unwrap_or_default();
+    let id = actor.id.clone().unwrap_or_default();
+    let time_ms = match (msg.time_nano, msg.time) {
+        (Some(ns), _) => ns / 1_000_000,
+        (None, Some(secs)) => secs * 1000,
+        (None, None) => 0,
+    };
+    Some(ContainerEvent {
+        time_ms: time_ms.max(0) as u64,
+        action,
+        name: attributes
+            .get("name")
+            .cloned()
+            .unwrap_or_else(|| id.clone()),
+        image: attributes.get("image").cloned().unwrap_or_default(),
+        exit_code: attributes.get("exitCode").and_then(|c| c.parse().ok()),
+        id,
+        attributes,
+    })
+}
@@ -1,22 +1,27 @@
 // Docker container stats via bollard
 
+mod events;
 mod stats;
+mod stream;
 
-pub use stats::process_statistics;
+pub use events::parse_event;
+pub use stats::{apply_rates, process_statistics};
+pub use stream::partition_stale;
 
 use crate::models::ContainerStats;
 use bollard::Docker;
-use bollard::query_parameters::{ListContainersOptions, StatsOptions};
-use futures_util::StreamExt;
+use bollard::query_parameters::ListContainersOptions;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::instrument;
 
 pub struct DockerRepo {
     docker: Docker,
     live_stats: Arc<RwLock<HashMap<String, ContainerStats>>>,
     active_streams: Arc<RwLock<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    /// Cached entries older than this are not served, and their stream is restarted
+    /// (`monitoring.container_stale_ms`). `None` serves the last entry however old.
+    stale_after_ms: Option<u64>,
 }
 
 impl DockerRepo {
@@ -26,10 +31,35 @@ impl DockerRepo {
             docker,
             live_stats: Arc::new(RwLock::new(HashMap::new())),
             active_streams: Arc::new(RwLock::new(HashMap::new())),
+            stale_after_ms: None,
         })
     }
 
+    /// Stop serving cached stats older than `stale_after_ms` (see [`partition_stale`]).
+    pub fn with_stale_after_ms(mut self, stale_after_ms: Option<u64>) -> Self {
+        self.stale_after_ms = stale_after_ms;
+        self
+    }
+
+    /// Make `stats` the cached entry for its container, stamped with the current time.
+    pub async fn cache_stats(&self, stats: ContainerStats) {
+        stream::store_stats(&self.live_stats, stats).await;
+    }
+
+    /// Like [`try_list_running_and_refresh_stats`](Self::try_list_running_and_refresh_stats), but
+    /// falls back to the cached stats when listing fails.
     pub async fn list_running_and_refresh_stats(&self) -> Vec<ContainerStats> {
+        match self.try_list_running_and_refresh_stats().await {
+            Ok(stats) => stats,
+            Err(_) => self.get_cached_stats().await,
+        }
+    }
+
+    /// Sync stats streams with the running containers and return their latest stats.
+    /// Fails (after logging a warning) when the container list cannot be fetched.
+    pub async fn try_list_running_and_refresh_stats(
+        &self,
+    ) -> Result<Vec<ContainerStats>, bollard::errors::Error> {
         let mut filters = HashMap::new();
         filters.insert("status".to_string(), vec!["running".to_string()]);
 
@@ -54,12 +84,13 @@ impl DockerRepo {
                     operation = "list_containers",
                     "Docker list_containers failed"
                 );
-                return self.get_cached_stats().await;
+                return Err(e);
             }
         };
 
         let mut running_ids = Vec::with_capacity(containers.len());
         let mut id_to_name = HashMap::with_capacity(containers.len());
+        let mut id_to_image = HashMap::with_capacity(containers.len());
         for c in &containers {
             let id = c.id.as_ref().cloned().unwrap_or_default();
             let name = c
@@ -71,9 +102,29 @@ impl DockerRepo {
             let name = name.trim_start_matches('/').to_string();
             running_ids.push(id.clone());
             id_to_name.insert(id.clone(), name);
+            id_to_image.insert(id.clone(), c.image.clone().unwrap_or_default());
         }
         let running_set: HashSet<String> = running_ids.iter().cloned().collect();
 
+        // A stream that stopped delivering without ending (daemon hiccup, stuck container) is
+        // dropped here, so the diff below starts a new one.
+        let stale = self.evict_stale().await;
+        if !stale.is_empty() {
+            tracing::warn!(
+                operation = "stale_stream",
+                containers_count = stale.len(),
+                container_ids = ?stale,
+                stale_after_ms = self.stale_after_ms,
+                "Restarting stats streams that stopped delivering"
+            );
+            let mut streams = self.active_streams.write().await;
+            for id in &stale {
+                if let Some(handle) = streams.remove(id) {
+                    handle.abort();
+                }
+            }
+        }
+
         let current_keys: Vec<String> = {
             let r = self.active_streams.read().await;
             r.keys().cloned().collect()
@@ -135,106 +186,13 @@ impl DockerRepo {
             }
         }
 
-        self.get_cached_stats().await
-    }
-
-    #[instrument(skip(self), fields(container_id = %id, container_name = %name))]
-    fn start_monitoring(&self, id: String, name: String) -> tokio::task::JoinHandle<()> {
-        let docker = self.docker.clone();
-        let live_stats = self.live_stats.clone();
-        let active_streams = self.active_streams.clone();
-
-        tracing::info!(
-            operation = "start_monitoring",
-            "Starting Docker stats stream for container"
-        );
-
-        tokio::spawn(async move {
-            let span = tracing::span!(
-                tracing::Level::DEBUG,
-                "docker_stats_stream",
-                container_id = %id,
-                container_name = %name
-            );
-            let _guard = span.enter();
-
-            let options = StatsOptions {
-                stream: true,
-                ..Default::default()
-            };
-            let mut stream = docker.stats(&id, Some(options));
-
-            let mut stats_count = 0u64;
-            while let Some(result) = stream.next().await {
-                match result {
-                    Ok(s) => {
-                        if let Some(stats) = stats::process_statistics(&s, &id, &name) {
-                            stats_count += 1;
-                            // Log key metrics periodically (every 10th stat update) at debug level
-                            if stats_count.is_multiple_of(10) {
-                                let memory_percent = if stats.memory_limit_bytes > 0 {
-                                    (stats.memory_usage_bytes as f64
-                                        / stats.memory_limit_bytes as f64)
-                                        * 100.0
-                                } else {
-                                    0.0
-                                };
-                                tracing::debug!(
-                                    container_id = %id,
-                                    container_name = %name,
-                                    cpu_percent = stats.cpu_percent,
-                                    memory_usage_mb = stats.memory_usage_bytes / 1024 / 1024,
-                                    memory_limit_mb = stats.memory_limit_bytes / 1024 / 1024,
-                                    memory_percent = memory_percent,
-                                    network_rx_mb = stats.network_rx_bytes / 1024 / 1024,
-                                    network_tx_mb = stats.network_tx_bytes / 1024 / 1024,
-                                    block_read_mb = stats.block_read_bytes / 1024 / 1024,
-                                    block_write_mb = stats.block_write_bytes / 1024 / 1024,
-                                    pids = stats.pids,
-                                    cpu_throttled = stats.cpu_throttled,
-                                    "Container stats update"
-                                );
-                            }
-                            live_stats.write().await.insert(id.clone(), stats);
-                        } else {
-                            tracing::debug!(
-                                container_id = %id,
-                                container_name = %name,
-                                "Failed to process container stats (missing data)"
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!(
-                            error = %e,
-                            container_id = %id,
-                            container_name = %name,
-                            operation = "docker_stats_stream",
-                            "Stats stream error for container"
-                        );
-                        break;
-                    }
-                }
+        // The stats stream does not carry the image; the listing does.
+        let mut stats = self.get_cached_stats().await;
+        for c in &mut stats {
+            if let Some(image) = id_to_image.get(&c.id) {
+                c.image.clone_from(image);
             }
-            tracing::info!(
-                container_id = %id,
-                container_name = %name,
-                operation = "docker_stats_stream",
-                stats_updates_count = stats_count,
-                "Stats stream ended for container"
-            );
-            active_streams.write().await.remove(&id);
-        })
-    }
-
-    async fn get_cached_stats(&self) -> Vec<ContainerStats> {
-        let live = self.live_stats.read().await;
-        let stats: Vec<ContainerStats> = live.values().cloned().collect();
-        tracing::debug!(
-            operation = "get_cached_stats",
-            containers_count = stats.len(),
-            "Retrieved cached container stats"
-        );
-        stats
+        }
+        Ok(stats)
     }
 }
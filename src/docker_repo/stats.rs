@@ -1,9 +1,13 @@
-// Process raw Docker stats API response into ContainerStats.
+// Process raw Docker stats API response into ContainerStats, and derive byte rates from two
+// consecutive samples.
 
-use crate::models::{ContainerState, ContainerStats};
+use crate::models::{
+    ContainerRates, ContainerState, ContainerStats, cpu_percent_of_host, pids_usage_percent,
+};
 use bollard::models::ContainerStatsResponse;
 
-/// Process a raw Docker stats response into [`ContainerStats`].
+/// Process a raw Docker stats response into [`ContainerStats`]. `cpu_percent` and its kernel /
+/// user split are per core, like `docker stats`; `cpu_percent_of_host` is the host share.
 pub fn process_statistics(
     s: &ContainerStatsResponse,
     id: &str,
@@ -23,23 +27,19 @@ pub fn process_statistics(
         - precpu_usage.usage_in_usermode.unwrap_or(0) as i64;
     let system_delta_check = cpu_stats.system_cpu_usage.unwrap_or(0) as i64
         - precpu_stats.system_cpu_usage.unwrap_or(0) as i64;
-    let online = cpu_stats.online_cpus.unwrap_or(1) as f64;
     let online_cpus = cpu_stats.online_cpus.unwrap_or(1);
-    let cpu_percent = if system_delta_check > 0 && online > 0.0 {
-        (cpu_delta as f64 / system_delta_check as f64) * online * 100.0
-    } else {
-        0.0
-    };
-    let cpu_kernel_percent = if system_delta_check > 0 && online > 0.0 {
-        (kernel_delta as f64 / system_delta_check as f64) * online * 100.0
-    } else {
-        0.0
-    };
-    let cpu_user_percent = if system_delta_check > 0 && online > 0.0 {
-        (user_delta as f64 / system_delta_check as f64) * online * 100.0
-    } else {
-        0.0
+    // `system_cpu_usage` counts every core, so delta / system_delta is already a share of the host.
+    let host_percent = |delta: i64| {
+        if system_delta_check > 0 && online_cpus > 0 {
+            delta as f64 / system_delta_check as f64 * 100.0
+        } else {
+            0.0
+        }
     };
+    let cpu_percent = host_percent(cpu_delta) * online_cpus as f64;
+    let cpu_kernel_percent = host_percent(kernel_delta) * online_cpus as f64;
+    let cpu_user_percent = host_percent(user_delta) * online_cpus as f64;
+    let cpu_percent_of_host = cpu_percent_of_host(cpu_percent, online_cpus);
 
     let mem_usage = s.memory_stats.as_ref().and_then(|m| m.usage).unwrap_or(0);
     let mem_limit = s.memory_stats.as_ref().and_then(|m| m.limit).unwrap_or(0);
@@ -145,10 +145,12 @@ pub fn process_statistics(
     Some(ContainerStats {
         id: id.to_string(),
         name: name.to_string(),
+        image: String::new(),
         cpu_percent,
         cpu_kernel_percent,
         cpu_user_percent,
         online_cpus,
+        cpu_percent_of_host,
         memory_usage_bytes: mem_usage,
         memory_limit_bytes: mem_limit,
         state: ContainerState::Running,
@@ -166,9 +168,41 @@ pub fn process_statistics(
         block_write_ops,
         pids,
         pids_limit,
+        pids_usage_percent: pids_usage_percent(pids, pids_limit),
         cpu_throttled: throttled,
         cpu_throttled_periods: throttled_periods,
         cpu_throttled_time_ns: throttled_time_ns,
         memory_max_usage_bytes: mem_max,
+        // Stamped when the entry is cached, which also fills in the rates.
+        observed_at: 0,
+        network_rx_bytes_per_sec: 0.0,
+        network_tx_bytes_per_sec: 0.0,
+        block_read_bytes_per_sec: 0.0,
+        block_write_bytes_per_sec: 0.0,
     })
 }
+
+/// Set `current`'s byte rates from the counters moved since `previous` (the container's last
+/// sample) over their `observed_at` gap. A counter that went backwards (container restarted)
+/// counts as 0; without a previous sample the rates are 0, and with no time between the two
+/// samples the previous rates are kept.
+pub fn apply_rates(current: &mut ContainerStats, previous: Option<&ContainerStats>) {
+    let Some(previous) = previous else {
+        ContainerRates::default().apply(current);
+        return;
+    };
+    let elapsed_ms = current.observed_at.saturating_sub(previous.observed_at);
+    if elapsed_ms == 0 {
+        ContainerRates::of(previous).apply(current);
+        return;
+    }
+    let rate =
+        |now: u64, before: u64| now.saturating_sub(before) as f64 * 1000.0 / elapsed_ms as f64;
+    ContainerRates {
+        network_rx_bytes_per_sec: rate(current.network_rx_bytes, previous.network_rx_bytes),
+        network_tx_bytes_per_sec: rate(current.network_tx_bytes, previous.network_tx_bytes),
+        block_read_bytes_per_sec: rate(current.block_read_bytes, previous.block_read_bytes),
+        block_write_bytes_per_sec: rate(current.block_write_bytes, previous.block_write_bytes),
+    }
+    .apply(current);
+}
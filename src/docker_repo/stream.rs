@@ -0,0 +1,172 @@
+// Per-container Docker stats streams: one task per running container keeps its latest entry
+// (with rates) in `live_stats`; stale entries are evicted and their stream restarted.
+
+use bollard::query_parameters::StatsOptions;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::instrument;
+
+use super::{DockerRepo, apply_rates, stats};
+use crate::models::ContainerStats;
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Split cached `stats` into the entries observed within `stale_after_ms` of `now_ms` and the
+/// ids of the older ones. Without a limit everything is fresh.
+pub fn partition_stale(
+    stats: impl IntoIterator<Item = ContainerStats>,
+    now_ms: u64,
+    stale_after_ms: Option<u64>,
+) -> (Vec<ContainerStats>, Vec<String>) {
+    let mut fresh = Vec::new();
+    let mut stale = Vec::new();
+    for s in stats {
+        match stale_after_ms {
+            Some(limit) if now_ms.saturating_sub(s.observed_at) > limit => stale.push(s.id),
+            _ => fresh.push(s),
+        }
+    }
+    (fresh, stale)
+}
+
+impl DockerRepo {
+    #[instrument(skip(self), fields(container_id = %id, container_name = %name))]
+    pub(super) fn start_monitoring(&self, id: String, name: String) -> tokio::task::JoinHandle<()> {
+        let docker = self.docker.clone();
+        let live_stats = self.live_stats.clone();
+        let active_streams = self.active_streams.clone();
+
+        tracing::info!(
+            operation = "start_monitoring",
+            "Starting Docker stats stream for container"
+        );
+
+        tokio::spawn(async move {
+            let span = tracing::span!(
+                tracing::Level::DEBUG,
+                "docker_stats_stream",
+                container_id = %id,
+                container_name = %name
+            );
+            let _guard = span.enter();
+
+            let options = StatsOptions {
+                stream: true,
+                ..Default::default()
+            };
+            let mut stream = docker.stats(&id, Some(options));
+
+            let mut stats_count = 0u64;
+            while let Some(result) = stream.next().await {
+                match result {
+                    Ok(s) => {
+                        if let Some(stats) = stats::process_statistics(&s, &id, &name) {
+                            stats_count += 1;
+                            // Log key metrics periodically (every 10th stat update) at debug level
+                            if stats_count.is_multiple_of(10) {
+                                let memory_percent = if stats.memory_limit_bytes > 0 {
+                                    (stats.memory_usage_bytes as f64
+                                        / stats.memory_limit_bytes as f64)
+                                        * 100.0
+                                } else {
+                                    0.0
+                                };
+                                tracing::debug!(
+                                    container_id = %id,
+                                    container_name = %name,
+                                    cpu_percent = stats.cpu_percent,
+                                    memory_usage_mb = stats.memory_usage_bytes / 1024 / 1024,
+                                    memory_limit_mb = stats.memory_limit_bytes / 1024 / 1024,
+                                    memory_percent = memory_percent,
+                                    network_rx_mb = stats.network_rx_bytes / 1024 / 1024,
+                                    network_tx_mb = stats.network_tx_bytes / 1024 / 1024,
+                                    block_read_mb = stats.block_read_bytes / 1024 / 1024,
+                                    block_write_mb = stats.block_write_bytes / 1024 / 1024,
+                                    pids = stats.pids,
+                                    cpu_throttled = stats.cpu_throttled,
+                                    "Container stats update"
+                                );
+                            }
+                            store_stats(&live_stats, stats).await;
+                        } else {
+                            tracing::debug!(
+                                container_id = %id,
+                                container_name = %name,
+                                "Failed to process container stats (missing data)"
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            error = %e,
+                            container_id = %id,
+                            container_name = %name,
+                            operation = "docker_stats_stream",
+                            "Stats stream error for container"
+                        );
+                        break;
+                    }
+                }
+            }
+            tracing::info!(
+                container_id = %id,
+                container_name = %name,
+                operation = "docker_stats_stream",
+                stats_updates_count = stats_count,
+                "Stats stream ended for container"
+            );
+            active_streams.write().await.remove(&id);
+        })
+    }
+
+    /// Remove cached entries older than `stale_after_ms`; returns their container ids.
+    pub(super) async fn evict_stale(&self) -> Vec<String> {
+        let Some(limit) = self.stale_after_ms else {
+            return Vec::new();
+        };
+        let now = now_ms();
+        let mut live = self.live_stats.write().await;
+        let stale: Vec<String> = live
+            .values()
+            .filter(|s| now.saturating_sub(s.observed_at) > limit)
+            .map(|s| s.id.clone())
+            .collect();
+        for id in &stale {
+            live.remove(id);
+        }
+        stale
+    }
+
+    /// Latest stats of the monitored containers, without contacting the daemon. Entries older
+    /// than `stale_after_ms` are left out.
+    pub async fn get_cached_stats(&self) -> Vec<ContainerStats> {
+        let live = self.live_stats.read().await;
+        let (stats, stale) = partition_stale(live.values().cloned(), now_ms(), self.stale_after_ms);
+        tracing::debug!(
+            operation = "get_cached_stats",
+            containers_count = stats.len(),
+            stale_count = stale.len(),
+            "Retrieved cached container stats"
+        );
+        stats
+    }
+}
+
+/// Stamp `stats` with `observed_at` = now, derive its rates from the entry it replaces and make
+/// it the cached entry for its container.
+pub(super) async fn store_stats(
+    live_stats: &RwLock<HashMap<String, ContainerStats>>,
+    mut stats: ContainerStats,
+) {
+    stats.observed_at = now_ms();
+    let mut live = live_stats.write().await;
+    let previous = live.get(&stats.id);
+    apply_rates(&mut stats, previous);
+    live.insert(stats.id.clone(), stats);
+}
@@ -1,64 +1,103 @@
 // `system_history_aggregated` reads, writes, and retention prune.
 
-use crate::history_repo::HistoryRepo;
 use crate::history_repo::blob;
 use crate::history_repo::history_merge::{
-    deserialize_container_data, deserialize_cpu_data, deserialize_gpu_data,
-    deserialize_network_data, deserialize_ram_data, deserialize_smart_data,
-    deserialize_storage_data,
+    decode_or, deserialize_container_data, deserialize_cpu_data, deserialize_gpu_data,
+    deserialize_network_data, deserialize_probe_data, deserialize_ram_data,
+    deserialize_sensor_data, deserialize_smart_data, deserialize_storage_data,
 };
+use crate::history_repo::raw_read::parse_rows;
+use crate::history_repo::row::HistoryRow;
+use crate::history_repo::{HistoryRepo, HistoryResult};
 use crate::models::{AggregatedSnapshot, SystemStatsDynamic};
-use sqlx::Row;
+use sqlx::query::Query;
+use sqlx::{Database, Encode, Type};
 use tracing::instrument;
 
-impl HistoryRepo {
-    #[instrument(
-        skip(self, agg),
-        fields(repo = "history", operation = "save_aggregated_snapshot")
-    )]
-    pub async fn save_aggregated_snapshot(&self, agg: &AggregatedSnapshot) -> anyhow::Result<()> {
-        let container_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.containers).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let storage_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.storage).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let network_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.network).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let system_data = blob::with_version_prefix(
-            blob::BLOB_VERSION_SYSTEM_DYNAMIC,
-            wincode::serialize(&agg.system).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let cpu_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.cpu).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let ram_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.ram).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let gpu_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.gpus).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-        let smart_data = blob::with_version_prefix(
-            blob::BLOB_VERSION,
-            wincode::serialize(&agg.smart).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-        );
-
-        sqlx::query(
-            r#"
-            INSERT INTO system_history_aggregated
+/// One tier's rows in [$1, $2) with resolution $3, ascending by created_at.
+pub(in crate::history_repo) const AGG_SELECT_RANGE: &str =
+    "SELECT created_at, resolution_seconds, cpu_load_avg, cpu_load_min, cpu_load_max,
+            memory_used_avg, memory_used_min, memory_used_max,
+            container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data,
+            probe_data, sensor_data, memory_total_avg, memory_total_min, memory_total_max,
+            cpu_temperature_avg, cpu_temperature_min, cpu_temperature_max,
+            cpu_load_p95, memory_used_p95, sample_count
+     FROM system_history_aggregated
+     WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3
+     ORDER BY created_at ASC";
+
+/// One aggregated row write, `$1..$27` as bound by [`bind_aggregated`].
+macro_rules! agg_insert {
+    ($verb:literal) => {
+        concat!(
+            $verb,
+            " INTO system_history_aggregated
             (created_at, resolution_seconds, cpu_load_avg, cpu_load_min, cpu_load_max,
              memory_used_avg, memory_used_min, memory_used_max,
-             container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
-            "#,
+             container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data,
+             probe_data, sensor_data, memory_total_avg, memory_total_min, memory_total_max,
+             cpu_temperature_avg, cpu_temperature_min, cpu_temperature_max,
+             cpu_load_p95, memory_used_p95, sample_count)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                    $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27)"
         )
+    };
+}
+
+const AGG_INSERT_OR_REPLACE: &str = agg_insert!("INSERT OR REPLACE");
+/// Postgres has no `OR REPLACE`: the bucket's old row is deleted first, in the same transaction.
+pub(in crate::history_repo) const AGG_INSERT: &str = agg_insert!("INSERT");
+
+/// An aggregated snapshot's blobs, encoded (and compressed) for its row.
+pub(in crate::history_repo) struct AggregatedBlobs {
+    container: Vec<u8>,
+    storage: Vec<u8>,
+    network: Vec<u8>,
+    system: Vec<u8>,
+    cpu: Vec<u8>,
+    ram: Vec<u8>,
+    gpu: Vec<u8>,
+    smart: Vec<u8>,
+    probes: Vec<u8>,
+    sensors: Vec<u8>,
+}
+
+impl AggregatedBlobs {
+    pub(in crate::history_repo) fn encode(
+        agg: &AggregatedSnapshot,
+        compress: bool,
+    ) -> HistoryResult<Self> {
+        Ok(Self {
+            container: blob::encode_containers(&agg.containers, compress)?,
+            storage: blob::encode_blob(&agg.storage, blob::BLOB_VERSION, compress)?,
+            network: blob::encode_blob(&agg.network, blob::BLOB_VERSION, compress)?,
+            system: blob::encode_blob(&agg.system, blob::BLOB_VERSION_SYSTEM_DYNAMIC, compress)?,
+            cpu: blob::encode_blob(&agg.cpu, blob::BLOB_VERSION, compress)?,
+            ram: blob::encode_blob(&agg.ram, blob::BLOB_VERSION, compress)?,
+            gpu: blob::encode_blob(&agg.gpus, blob::BLOB_VERSION, compress)?,
+            smart: blob::encode_blob(&agg.smart, blob::BLOB_VERSION, compress)?,
+            probes: blob::encode_blob(&agg.probes, blob::BLOB_VERSION, compress)?,
+            sensors: blob::encode_blob(&agg.sensors, blob::BLOB_VERSION, compress)?,
+        })
+    }
+}
+
+/// Bind `agg` and its `blobs` to an [`agg_insert!`] statement on either engine.
+pub(in crate::history_repo) fn bind_aggregated<'q, DB>(
+    query: Query<'q, DB, DB::Arguments>,
+    agg: &AggregatedSnapshot,
+    blobs: AggregatedBlobs,
+) -> Query<'q, DB, DB::Arguments>
+where
+    DB: Database,
+    for<'t> i32: Encode<'t, DB> + Type<DB>,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> f64: Encode<'t, DB> + Type<DB>,
+    for<'t> Option<i64>: Encode<'t, DB> + Type<DB>,
+    for<'t> Option<f64>: Encode<'t, DB> + Type<DB>,
+    for<'t> Vec<u8>: Encode<'t, DB> + Type<DB>,
+{
+    query
         .bind(agg.created_at)
         .bind(agg.resolution_seconds)
         .bind(agg.cpu_load_avg)
@@ -67,17 +106,48 @@ impl HistoryRepo {
         .bind(agg.memory_used_avg)
         .bind(agg.memory_used_min)
         .bind(agg.memory_used_max)
-        .bind(&container_data)
-        .bind(&storage_data)
-        .bind(&network_data)
-        .bind(&system_data)
-        .bind(&cpu_data)
-        .bind(&ram_data)
-        .bind(&gpu_data)
-        .bind(&smart_data)
-        .execute(&self.pool)
-        .await?;
+        .bind(blobs.container)
+        .bind(blobs.storage)
+        .bind(blobs.network)
+        .bind(blobs.system)
+        .bind(blobs.cpu)
+        .bind(blobs.ram)
+        .bind(blobs.gpu)
+        .bind(blobs.smart)
+        .bind(blobs.probes)
+        .bind(blobs.sensors)
+        .bind(agg.memory_total_avg)
+        .bind(agg.memory_total_min)
+        .bind(agg.memory_total_max)
+        .bind(agg.cpu_temperature_avg)
+        .bind(agg.cpu_temperature_min)
+        .bind(agg.cpu_temperature_max)
+        .bind(agg.cpu_load_p95)
+        .bind(agg.memory_used_p95)
+        .bind(agg.sample_count)
+}
 
+impl HistoryRepo {
+    #[instrument(
+        skip(self, agg),
+        fields(repo = "history", operation = "save_aggregated_snapshot")
+    )]
+    pub async fn save_aggregated_snapshot(&self, agg: &AggregatedSnapshot) -> HistoryResult<()> {
+        let mut conn = self.writer.acquire().await?;
+        self.insert_aggregated(&mut conn, agg).await
+    }
+
+    /// Write `agg`, replacing any row already stored for its `(created_at, resolution_seconds)`
+    /// bucket (a roll-up retried after a crash overwrites instead of duplicating).
+    pub(in crate::history_repo) async fn insert_aggregated(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+        agg: &AggregatedSnapshot,
+    ) -> HistoryResult<()> {
+        let blobs = AggregatedBlobs::encode(agg, self.compress_blobs)?;
+        bind_aggregated(sqlx::query(AGG_INSERT_OR_REPLACE), agg, blobs)
+            .execute(&mut *conn)
+            .await?;
         Ok(())
     }
 
@@ -91,26 +161,15 @@ impl HistoryRepo {
         from_ts: i64,
         to_ts: i64,
         resolution_seconds: i32,
-    ) -> anyhow::Result<Vec<AggregatedSnapshot>> {
-        let rows = sqlx::query(
-            "SELECT created_at, resolution_seconds, cpu_load_avg, cpu_load_min, cpu_load_max,
-                    memory_used_avg, memory_used_min, memory_used_max,
-                    container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data
-             FROM system_history_aggregated
-             WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3
-             ORDER BY created_at ASC",
-        )
-        .bind(from_ts)
-        .bind(to_ts)
-        .bind(resolution_seconds)
-        .fetch_all(&self.pool)
-        .await?;
+    ) -> HistoryResult<Vec<AggregatedSnapshot>> {
+        let rows = sqlx::query(AGG_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(resolution_seconds)
+            .fetch_all(&self.pool)
+            .await?;
 
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows {
-            out.push(Self::parse_aggregated_row(&row)?);
-        }
-        Ok(out)
+        parse_rows(&rows, Self::parse_aggregated_row)
     }
 
     /// Minimum created_at in system_history_aggregated with created_at < cutoff_ts and given resolution.
@@ -118,7 +177,7 @@ impl HistoryRepo {
         &self,
         cutoff_ts: i64,
         resolution_seconds: i32,
-    ) -> anyhow::Result<Option<i64>> {
+    ) -> HistoryResult<Option<i64>> {
         let row = sqlx::query_scalar::<_, Option<i64>>(
             "SELECT MIN(created_at) FROM system_history_aggregated WHERE created_at < $1 AND resolution_seconds = $2",
         )
@@ -139,79 +198,92 @@ impl HistoryRepo {
         from_ts: i64,
         to_ts: i64,
         resolution_seconds: i32,
-    ) -> anyhow::Result<u64> {
+    ) -> HistoryResult<u64> {
         let r = sqlx::query(
             "DELETE FROM system_history_aggregated WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3",
         )
         .bind(from_ts)
         .bind(to_ts)
         .bind(resolution_seconds)
-        .execute(&self.pool)
+        .execute(&self.writer)
         .await?;
         Ok(r.rows_affected())
     }
 
-    /// Prune aggregated rows older than retention_days.
-    #[instrument(
-        skip(self),
-        fields(repo = "history", operation = "prune_aggregated_old_data")
-    )]
-    pub async fn prune_aggregated_old_data(&self) -> anyhow::Result<u64> {
-        let cutoff = (std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_millis() as i64)
-            - self.retention_ms;
-        let r = sqlx::query("DELETE FROM system_history_aggregated WHERE created_at < $1")
-            .bind(cutoff)
-            .execute(&self.pool)
-            .await?;
-        Ok(r.rows_affected())
-    }
+    pub(in crate::history_repo) fn parse_aggregated_row<R: HistoryRow>(
+        row: &R,
+    ) -> HistoryResult<AggregatedSnapshot> {
+        let created_at: i64 = row.get_i64("created_at")?;
+        let resolution_seconds: i32 = row.get_i32("resolution_seconds")?;
+        let cpu_load_avg: f64 = row.get_f64("cpu_load_avg")?;
+        let cpu_load_min: f64 = row.get_f64("cpu_load_min")?;
+        let cpu_load_max: f64 = row.get_f64("cpu_load_max")?;
+        let memory_used_avg: i64 = row.get_i64("memory_used_avg")?;
+        let memory_used_min: i64 = row.get_i64("memory_used_min")?;
+        let memory_used_max: i64 = row.get_i64("memory_used_max")?;
+        // Schema v7+; NULL on older rows.
+        let cpu_load_p95: Option<f64> = row.get_opt_f64("cpu_load_p95")?;
+        let memory_used_p95: Option<i64> = row.get_opt_i64("memory_used_p95")?;
+        // Schema v10+; legacy rows read 0 (unknown).
+        let sample_count: i64 = row.get_i64("sample_count")?;
+        // Schema v6+; legacy rows read 0.
+        let memory_total_avg: i64 = row.get_i64("memory_total_avg")?;
+        let memory_total_min: i64 = row.get_i64("memory_total_min")?;
+        let memory_total_max: i64 = row.get_i64("memory_total_max")?;
+        let cpu_temperature_avg: f64 = row.get_f64("cpu_temperature_avg")?;
+        let cpu_temperature_min: f64 = row.get_f64("cpu_temperature_min")?;
+        let cpu_temperature_max: f64 = row.get_f64("cpu_temperature_max")?;
+        let container_data: Vec<u8> = row.get_bytes("container_data")?;
+        let storage_data: Vec<u8> = row.get_bytes("storage_data")?;
+        let network_data: Vec<u8> = row.get_bytes("network_data")?;
+        let system_data: Vec<u8> = row.get_bytes("system_data")?;
+        // Nullable on rows written before schema v3 (CPU/RAM) / v4 (GPU) / v14 (probes) /
+        // v16 (sensors).
+        let cpu_data: Option<Vec<u8>> = row.get_opt_bytes("cpu_data")?;
+        let ram_data: Option<Vec<u8>> = row.get_opt_bytes("ram_data")?;
+        let gpu_data: Option<Vec<u8>> = row.get_opt_bytes("gpu_data")?;
+        let smart_data: Option<Vec<u8>> = row.get_opt_bytes("smart_data")?;
+        let probe_data: Option<Vec<u8>> = row.get_opt_bytes("probe_data")?;
+        let sensor_data: Option<Vec<u8>> = row.get_opt_bytes("sensor_data")?;
 
-    fn parse_aggregated_row(row: &sqlx::sqlite::SqliteRow) -> anyhow::Result<AggregatedSnapshot> {
-        let created_at: i64 = row.try_get("created_at")?;
-        let resolution_seconds: i32 = row.try_get("resolution_seconds")?;
-        let cpu_load_avg: f64 = row.try_get("cpu_load_avg")?;
-        let cpu_load_min: f64 = row.try_get("cpu_load_min")?;
-        let cpu_load_max: f64 = row.try_get("cpu_load_max")?;
-        let memory_used_avg: i64 = row.try_get("memory_used_avg")?;
-        let memory_used_min: i64 = row.try_get("memory_used_min")?;
-        let memory_used_max: i64 = row.try_get("memory_used_max")?;
-        let container_data: Vec<u8> = row.try_get("container_data")?;
-        let storage_data: Vec<u8> = row.try_get("storage_data")?;
-        let network_data: Vec<u8> = row.try_get("network_data")?;
-        let system_data: Vec<u8> = row.try_get("system_data")?;
-        // Nullable on rows written before schema v3 (CPU/RAM) / v4 (GPU).
-        let cpu_data: Option<Vec<u8>> = row.try_get("cpu_data")?;
-        let ram_data: Option<Vec<u8>> = row.try_get("ram_data")?;
-        let gpu_data: Option<Vec<u8>> = row.try_get("gpu_data")?;
-        let smart_data: Option<Vec<u8>> = row.try_get("smart_data")?;
-
-        let containers = deserialize_container_data(&container_data);
-        let storage = deserialize_storage_data(&storage_data);
-        let network = deserialize_network_data(&network_data);
-        let cpu = deserialize_cpu_data(cpu_data.as_deref(), cpu_load_avg);
-        let ram = deserialize_ram_data(ram_data.as_deref(), memory_used_avg as u64);
-        let gpus = deserialize_gpu_data(gpu_data.as_deref());
-        let smart = deserialize_smart_data(smart_data.as_deref());
-        let system = wincode::deserialize(blob::blob_payload(
+        let containers = deserialize_container_data(&container_data)?;
+        let storage = deserialize_storage_data(&storage_data)?;
+        let network = deserialize_network_data(&network_data)?;
+        let cpu = deserialize_cpu_data(cpu_data.as_deref(), cpu_load_avg, cpu_temperature_avg)?;
+        let ram = deserialize_ram_data(
+            ram_data.as_deref(),
+            memory_used_avg as u64,
+            memory_total_avg as u64,
+        )?;
+        let gpus = deserialize_gpu_data(gpu_data.as_deref())?;
+        let smart = deserialize_smart_data(smart_data.as_deref())?;
+        let probes = deserialize_probe_data(probe_data.as_deref())?;
+        let sensors = deserialize_sensor_data(sensor_data.as_deref())?;
+        let system = decode_or(
             &system_data,
             blob::BLOB_VERSION_SYSTEM_DYNAMIC,
-        ))
-        .unwrap_or_else(|e| {
-            tracing::debug!(error = %e, "wincode deserialize aggregated system, using default");
-            SystemStatsDynamic::default()
-        });
+            "system_data",
+            SystemStatsDynamic::default,
+        )?;
 
         Ok(AggregatedSnapshot {
             created_at,
             resolution_seconds,
+            sample_count,
             cpu_load_avg,
             cpu_load_min,
             cpu_load_max,
             memory_used_avg,
             memory_used_min,
             memory_used_max,
+            cpu_load_p95,
+            memory_used_p95,
+            memory_total_avg,
+            memory_total_min,
+            memory_total_max,
+            cpu_temperature_avg,
+            cpu_temperature_min,
+            cpu_temperature_max,
             cpu,
             ram,
             containers,
@@ -220,6 +292,8 @@ impl HistoryRepo {
             system,
             gpus,
             smart,
+            probes,
+            sensors,
         })
     }
 }
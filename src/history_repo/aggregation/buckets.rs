@@ -0,0 +1,121 @@
+// Bucket boundaries for the aggregated tiers: epoch multiples of the resolution, or wall-clock
+// multiples in `database.bucket_timezone` for tiers of an hour or more (so 1-day rows are local
+// days). DST makes those buckets 23 or 25 hours long.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Tiers at least this coarse (seconds) follow `bucket_timezone`; finer ones stay epoch-aligned.
+pub const WALL_CLOCK_MIN_RESOLUTION_SECS: i32 = 3600;
+
+/// Where hour-and-coarser buckets start (`database.bucket_timezone`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BucketTimezone {
+    /// Epoch multiples (UTC days).
+    #[default]
+    Utc,
+    /// The host's local zone, read at each bucket computation.
+    Local,
+    /// An IANA zone, e.g. `Europe/Berlin`.
+    Zone(Tz),
+}
+
+impl FromStr for BucketTimezone {
+    type Err = anyhow::Error;
+
+    /// `"UTC"` and `"local"` (any case), else an IANA zone name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            v if v.eq_ignore_ascii_case("utc") => Ok(Self::Utc),
+            v if v.eq_ignore_ascii_case("local") => Ok(Self::Local),
+            v => v
+                .parse::<Tz>()
+                .map(Self::Zone)
+                .map_err(|_| anyhow::anyhow!("unknown time zone '{v}'")),
+        }
+    }
+}
+
+/// Bucket boundaries of one tier.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketGrid {
+    resolution_ms: i64,
+    /// `None` = epoch-aligned (UTC, or a sub-hour tier).
+    timezone: Option<BucketTimezone>,
+}
+
+impl BucketGrid {
+    pub fn new(resolution_seconds: i32, timezone: BucketTimezone) -> Self {
+        let wall_clock =
+            resolution_seconds >= WALL_CLOCK_MIN_RESOLUTION_SECS && timezone != BucketTimezone::Utc;
+        Self {
+            resolution_ms: (resolution_seconds as i64) * 1000,
+            timezone: wall_clock.then_some(timezone),
+        }
+    }
+
+    /// Start of the bucket holding `ts_ms`.
+    pub fn floor(&self, ts_ms: i64) -> i64 {
+        match self.timezone {
+            None | Some(BucketTimezone::Utc) => (ts_ms / self.resolution_ms) * self.resolution_ms,
+            Some(BucketTimezone::Local) => {
+                wall_clock_boundary(&chrono::Local, ts_ms, self.resolution_ms, 0)
+            }
+            Some(BucketTimezone::Zone(tz)) => {
+                wall_clock_boundary(&tz, ts_ms, self.resolution_ms, 0)
+            }
+        }
+    }
+
+    /// Start of the bucket after the one starting at `start`.
+    pub fn next(&self, start: i64) -> i64 {
+        match self.timezone {
+            None | Some(BucketTimezone::Utc) => start + self.resolution_ms,
+            Some(BucketTimezone::Local) => {
+                wall_clock_boundary(&chrono::Local, start, self.resolution_ms, 1)
+            }
+            Some(BucketTimezone::Zone(tz)) => {
+                wall_clock_boundary(&tz, start, self.resolution_ms, 1)
+            }
+        }
+    }
+
+    /// Start of the bucket `n` buckets after the one starting at `start`.
+    pub fn advance(&self, start: i64, n: i64) -> i64 {
+        match self.timezone {
+            None | Some(BucketTimezone::Utc) => start + self.resolution_ms * n,
+            Some(_) => (0..n).fold(start, |t, _| self.next(t)),
+        }
+    }
+}
+
+/// Instant at which the wall clock of `tz` reaches the `resolution_ms` multiple `steps` after the
+/// one at or before `ts_ms` (local time counted from the naive epoch).
+fn wall_clock_boundary<Z: TimeZone>(tz: &Z, ts_ms: i64, resolution_ms: i64, steps: i64) -> i64 {
+    let Some(utc) = DateTime::from_timestamp_millis(ts_ms) else {
+        return (ts_ms / resolution_ms) * resolution_ms;
+    };
+    let local_ms = utc
+        .with_timezone(tz)
+        .naive_local()
+        .and_utc()
+        .timestamp_millis();
+    let start = local_ms.div_euclid(resolution_ms) * resolution_ms + steps * resolution_ms;
+    local_to_instant(tz, start).unwrap_or(ts_ms)
+}
+
+/// First instant showing wall-clock time `local_ms`: the earlier one in a repeated (fall-back)
+/// hour, the end of the gap when the time is skipped (spring-forward; gaps are quarter-hours).
+fn local_to_instant<Z: TimeZone>(tz: &Z, local_ms: i64) -> Option<i64> {
+    const QUARTER_HOUR_MS: i64 = 15 * 60 * 1000;
+    (0..=8).find_map(|q| {
+        let naive: NaiveDateTime =
+            DateTime::from_timestamp_millis(local_ms + q * QUARTER_HOUR_MS)?.naive_utc();
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => Some(t.timestamp_millis()),
+            LocalResult::None => None,
+        }
+    })
+}
@@ -0,0 +1,266 @@
+// Per-container roll-up: gauges and byte rates averaged (weighted by sample count), cumulative
+// counters and state from the last sample, pids from the one nearest its limit. Buckets are capped
+// to the busiest containers, the rest collapsed into one `__other__` entry.
+
+use std::collections::{HashMap, HashSet};
+
+use super::math::{weighted_mean_f64, weighted_mean_u64};
+use crate::models::{
+    AggregatedSnapshot, ContainerState, ContainerStats, FullSystemSnapshot, pids_usage_percent,
+};
+
+/// Id and name of the synthetic entry holding every container past the limit.
+pub const OTHER_CONTAINERS_ID: &str = "__other__";
+
+/// Group by container id across aggregated snapshots; for each container call aggregate_one_container.
+/// `weights[i]` is the sample count of `aggs[i]` and weights that child's gauges.
+pub(super) fn aggregate_containers_from_aggregated(
+    aggs: &[AggregatedSnapshot],
+    weights: &[i64],
+) -> Vec<ContainerStats> {
+    let mut by_id: HashMap<String, Vec<(&ContainerStats, i64)>> = HashMap::new();
+    for (a, &w) in aggs.iter().zip(weights) {
+        for c in &a.containers {
+            by_id.entry(c.id.clone()).or_default().push((c, w));
+        }
+    }
+    let mut out: Vec<ContainerStats> = Vec::with_capacity(by_id.len());
+    for (_id, refs) in by_id {
+        if refs.is_empty() {
+            continue;
+        }
+        out.push(aggregate_one_container(&refs));
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+/// Group by container id; for each container compute avg (gauges), last (counters, state/pids).
+pub(super) fn aggregate_containers(snapshots: &[FullSystemSnapshot]) -> Vec<ContainerStats> {
+    type Key = String;
+    let mut by_id: HashMap<Key, Vec<(&ContainerStats, i64)>> = HashMap::new();
+    for s in snapshots {
+        for c in &s.containers {
+            by_id.entry(c.id.clone()).or_default().push((c, 1));
+        }
+    }
+
+    let mut out: Vec<ContainerStats> = Vec::with_capacity(by_id.len());
+    for (_id, refs) in by_id {
+        if refs.is_empty() {
+            continue;
+        }
+        let c = aggregate_one_container(&refs);
+        out.push(c);
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+fn aggregate_one_container(weighted: &[(&ContainerStats, i64)]) -> ContainerStats {
+    let refs: Vec<&ContainerStats> = weighted.iter().map(|(c, _)| *c).collect();
+    let first = refs[0];
+    let gauge_f64 = |f: fn(&ContainerStats) -> f64| {
+        weighted_mean_f64(&weighted.iter().map(|(c, w)| (f(c), *w)).collect::<Vec<_>>())
+    };
+    let gauge_u64 = |f: fn(&ContainerStats) -> u64| {
+        weighted_mean_u64(&weighted.iter().map(|(c, w)| (f(c), *w)).collect::<Vec<_>>())
+    };
+
+    let cpu_percent_avg = gauge_f64(|c| c.cpu_percent);
+    let cpu_percent_of_host_avg = gauge_f64(|c| c.cpu_percent_of_host);
+    let memory_usage_avg = gauge_u64(|c| c.memory_usage_bytes);
+    let memory_limit_avg = gauge_u64(|c| c.memory_limit_bytes);
+
+    let cpu_kernel_avg = gauge_f64(|c| c.cpu_kernel_percent);
+    let cpu_user_avg = gauge_f64(|c| c.cpu_user_percent);
+    let network_rx_rate_avg = gauge_f64(|c| c.network_rx_bytes_per_sec);
+    let network_tx_rate_avg = gauge_f64(|c| c.network_tx_bytes_per_sec);
+    let block_read_rate_avg = gauge_f64(|c| c.block_read_bytes_per_sec);
+    let block_write_rate_avg = gauge_f64(|c| c.block_write_bytes_per_sec);
+
+    // Docker reports network/block/throttling counters as running totals since container start,
+    // so the bucket keeps the last reading; summing them would multiply by the sample count.
+    let last = refs[refs.len() - 1];
+    // pids come from the sample closest to the pids limit (the last one when unlimited), so the
+    // stored row keeps the bucket's peak `pids_usage_percent`.
+    let pids_peak = refs
+        .iter()
+        .max_by(|a, b| a.pids_usage_percent.total_cmp(&b.pids_usage_percent))
+        .unwrap_or(&last);
+    ContainerStats {
+        id: first.id.clone(),
+        name: first.name.clone(),
+        image: last.image.clone(),
+        cpu_percent: cpu_percent_avg,
+        memory_usage_bytes: memory_usage_avg,
+        memory_limit_bytes: memory_limit_avg,
+        state: last.state,
+        network_rx_bytes: last.network_rx_bytes,
+        network_tx_bytes: last.network_tx_bytes,
+        network_rx_packets: last.network_rx_packets,
+        network_tx_packets: last.network_tx_packets,
+        network_rx_errors: last.network_rx_errors,
+        network_tx_errors: last.network_tx_errors,
+        network_rx_dropped: last.network_rx_dropped,
+        network_tx_dropped: last.network_tx_dropped,
+        block_read_bytes: last.block_read_bytes,
+        block_write_bytes: last.block_write_bytes,
+        block_read_ops: last.block_read_ops,
+        block_write_ops: last.block_write_ops,
+        pids: pids_peak.pids,
+        pids_limit: pids_peak.pids_limit,
+        pids_usage_percent: pids_peak.pids_usage_percent,
+        cpu_throttled: last.cpu_throttled,
+        cpu_throttled_periods: last.cpu_throttled_periods,
+        cpu_throttled_time_ns: last.cpu_throttled_time_ns,
+        cpu_kernel_percent: cpu_kernel_avg,
+        cpu_user_percent: cpu_user_avg,
+        online_cpus: last.online_cpus,
+        cpu_percent_of_host: cpu_percent_of_host_avg,
+        memory_max_usage_bytes: last.memory_max_usage_bytes,
+        observed_at: 0,
+        network_rx_bytes_per_sec: network_rx_rate_avg,
+        network_tx_bytes_per_sec: network_tx_rate_avg,
+        block_read_bytes_per_sec: block_read_rate_avg,
+        block_write_bytes_per_sec: block_write_rate_avg,
+    }
+}
+
+/// [`aggregate_snapshots`](super::aggregate_snapshots) keeping at most `container_limit`
+/// containers plus those running in the last snapshot (see [`limit_containers`]); 0 = no limit.
+pub fn aggregate_snapshots_with_container_limit(
+    snapshots: &[FullSystemSnapshot],
+    bucket_start_ts: i64,
+    resolution_seconds: i32,
+    container_limit: usize,
+) -> Option<AggregatedSnapshot> {
+    let mut agg = super::aggregate_snapshots(snapshots, bucket_start_ts, resolution_seconds)?;
+    let running = running_ids(&snapshots.last()?.containers);
+    agg.containers = limit_containers(
+        std::mem::take(&mut agg.containers),
+        &running,
+        container_limit,
+    );
+    Some(agg)
+}
+
+/// [`aggregate_aggregated_snapshots`](super::aggregate_aggregated_snapshots) with the same cap,
+/// keeping containers running in the last child bucket.
+pub fn aggregate_aggregated_snapshots_with_container_limit(
+    aggs: &[AggregatedSnapshot],
+    bucket_start_ts: i64,
+    resolution_seconds: i32,
+    container_limit: usize,
+) -> Option<AggregatedSnapshot> {
+    let mut agg = super::aggregate_aggregated_snapshots(aggs, bucket_start_ts, resolution_seconds)?;
+    let running = running_ids(&aggs.last()?.containers);
+    agg.containers = limit_containers(
+        std::mem::take(&mut agg.containers),
+        &running,
+        container_limit,
+    );
+    Some(agg)
+}
+
+/// Keep the `limit` containers with the highest average CPU plus every id in `running_at_end`,
+/// and sum the rest (and any earlier `__other__` entry) into one [`OTHER_CONTAINERS_ID`] entry
+/// appended after the kept ones, which stay in name order. `limit == 0` keeps everything.
+fn limit_containers(
+    containers: Vec<ContainerStats>,
+    running_at_end: &HashSet<&str>,
+    limit: usize,
+) -> Vec<ContainerStats> {
+    if limit == 0 || containers.len() <= limit {
+        return containers;
+    }
+    let mut by_cpu: Vec<&ContainerStats> = containers
+        .iter()
+        .filter(|c| c.id != OTHER_CONTAINERS_ID)
+        .collect();
+    // Name breaks CPU ties so the cut does not depend on input order.
+    by_cpu.sort_by(|a, b| {
+        b.cpu_percent
+            .total_cmp(&a.cpu_percent)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let top: HashSet<String> = by_cpu.iter().take(limit).map(|c| c.id.clone()).collect();
+
+    let (mut kept, rest): (Vec<_>, Vec<_>) = containers.into_iter().partition(|c| {
+        c.id != OTHER_CONTAINERS_ID
+            && (top.contains(&c.id) || running_at_end.contains(c.id.as_str()))
+    });
+    kept.sort_by(|a, b| a.name.cmp(&b.name));
+    if let Some(other) = sum_containers(&rest) {
+        kept.push(other);
+    }
+    kept
+}
+
+/// Ids running in the bucket's last sample.
+fn running_ids(containers: &[ContainerStats]) -> HashSet<&str> {
+    containers
+        .iter()
+        .filter(|c| c.state == ContainerState::Running)
+        .map(|c| c.id.as_str())
+        .collect()
+}
+
+/// One [`OTHER_CONTAINERS_ID`] entry with every gauge and counter summed, so bucket totals stay
+/// accurate; `None` when there is nothing to collapse. The group is unlimited (`pids_limit` 0)
+/// once any member is, so unlimited members' pids never count against the others' limits.
+fn sum_containers(rest: &[ContainerStats]) -> Option<ContainerStats> {
+    if rest.is_empty() {
+        return None;
+    }
+    let sum_u64 = |f: fn(&ContainerStats) -> u64| rest.iter().map(f).sum::<u64>();
+    let sum_f64 = |f: fn(&ContainerStats) -> f64| rest.iter().map(f).sum::<f64>();
+    let any_running = rest.iter().any(|c| c.state == ContainerState::Running);
+    let pids = sum_u64(|c| c.pids);
+    let pids_limit = if rest.iter().all(|c| c.pids_limit > 0) {
+        sum_u64(|c| c.pids_limit)
+    } else {
+        0
+    };
+    Some(ContainerStats {
+        id: OTHER_CONTAINERS_ID.into(),
+        name: OTHER_CONTAINERS_ID.into(),
+        image: String::new(),
+        cpu_percent: sum_f64(|c| c.cpu_percent),
+        memory_usage_bytes: sum_u64(|c| c.memory_usage_bytes),
+        memory_limit_bytes: sum_u64(|c| c.memory_limit_bytes),
+        state: if any_running {
+            ContainerState::Running
+        } else {
+            ContainerState::Exited
+        },
+        network_rx_bytes: sum_u64(|c| c.network_rx_bytes),
+        network_tx_bytes: sum_u64(|c| c.network_tx_bytes),
+        network_rx_packets: sum_u64(|c| c.network_rx_packets),
+        network_tx_packets: sum_u64(|c| c.network_tx_packets),
+        network_rx_errors: sum_u64(|c| c.network_rx_errors),
+        network_tx_errors: sum_u64(|c| c.network_tx_errors),
+        network_rx_dropped: sum_u64(|c| c.network_rx_dropped),
+        network_tx_dropped: sum_u64(|c| c.network_tx_dropped),
+        block_read_bytes: sum_u64(|c| c.block_read_bytes),
+        block_write_bytes: sum_u64(|c| c.block_write_bytes),
+        block_read_ops: sum_u64(|c| c.block_read_ops),
+        block_write_ops: sum_u64(|c| c.block_write_ops),
+        pids,
+        pids_limit,
+        pids_usage_percent: pids_usage_percent(pids, pids_limit),
+        cpu_throttled: rest.iter().any(|c| c.cpu_throttled),
+        cpu_throttled_periods: sum_u64(|c| c.cpu_throttled_periods),
+        cpu_throttled_time_ns: sum_u64(|c| c.cpu_throttled_time_ns),
+        cpu_kernel_percent: sum_f64(|c| c.cpu_kernel_percent),
+        cpu_user_percent: sum_f64(|c| c.cpu_user_percent),
+        online_cpus: rest.iter().map(|c| c.online_cpus).max().unwrap_or(0),
+        cpu_percent_of_host: sum_f64(|c| c.cpu_percent_of_host),
+        memory_max_usage_bytes: sum_u64(|c| c.memory_max_usage_bytes),
+        observed_at: 0,
+        network_rx_bytes_per_sec: sum_f64(|c| c.network_rx_bytes_per_sec),
+        network_tx_bytes_per_sec: sum_f64(|c| c.network_tx_bytes_per_sec),
+        block_read_bytes_per_sec: sum_f64(|c| c.block_read_bytes_per_sec),
+        block_write_bytes_per_sec: sum_f64(|c| c.block_write_bytes_per_sec),
+    })
+}
@@ -0,0 +1,85 @@
+// Aggregation math: plain and weighted means, min/max, nearest-rank percentile. Float inputs that
+// are NaN or infinite are skipped, so one bad reading cannot poison (or infinity-fill) a bucket.
+
+/// Nearest-rank percentile (`p` in 0..=100) of `values`; `None` when empty.
+pub fn percentile<T: Copy + PartialOrd>(values: &[T], p: f64) -> Option<T> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let rank = ((p.clamp(0.0, 100.0) / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.saturating_sub(1)])
+}
+
+pub(super) fn mean_f64(v: &[f64]) -> f64 {
+    let finite: Vec<f64> = v.iter().copied().filter(|x| x.is_finite()).collect();
+    if finite.is_empty() {
+        return 0.0;
+    }
+    finite.iter().sum::<f64>() / (finite.len() as f64)
+}
+
+/// Smallest finite value of `v`; 0 when there is none (empty, or all NaN / infinite).
+pub(crate) fn finite_min(v: impl IntoIterator<Item = f64>) -> f64 {
+    v.into_iter()
+        .filter(|x| x.is_finite())
+        .reduce(f64::min)
+        .unwrap_or(0.0)
+}
+
+/// Largest finite value of `v`; 0 when there is none.
+pub(crate) fn finite_max(v: impl IntoIterator<Item = f64>) -> f64 {
+    v.into_iter()
+        .filter(|x| x.is_finite())
+        .reduce(f64::max)
+        .unwrap_or(0.0)
+}
+
+pub(super) fn mean_i64(v: &[i64]) -> i64 {
+    if v.is_empty() {
+        return 0;
+    }
+    v.iter().sum::<i64>() / (v.len() as i64)
+}
+
+pub(super) fn mean_u64(v: &[u64]) -> u64 {
+    if v.is_empty() {
+        return 0;
+    }
+    v.iter().sum::<u64>() / (v.len() as u64)
+}
+
+/// Mean of the finite `(value, weight)` pairs; plain mean when the weights sum to zero.
+pub(super) fn weighted_mean_f64(v: &[(f64, i64)]) -> f64 {
+    let v: Vec<(f64, i64)> = v.iter().copied().filter(|(x, _)| x.is_finite()).collect();
+    let total: i64 = v.iter().map(|(_, w)| *w).sum();
+    if total <= 0 {
+        return mean_f64(&v.iter().map(|(x, _)| *x).collect::<Vec<_>>());
+    }
+    v.iter().map(|(x, w)| x * (*w as f64)).sum::<f64>() / (total as f64)
+}
+
+/// Integer mean of `(value, weight)` pairs; i128 accumulator since byte counts × weights can overflow.
+pub(super) fn weighted_mean_i64(v: &[(i64, i64)]) -> i64 {
+    let total: i64 = v.iter().map(|(_, w)| *w).sum();
+    if total <= 0 {
+        return mean_i64(&v.iter().map(|(x, _)| *x).collect::<Vec<_>>());
+    }
+    (v.iter()
+        .map(|(x, w)| (*x as i128) * (*w as i128))
+        .sum::<i128>()
+        / (total as i128)) as i64
+}
+
+/// Unsigned variant of `weighted_mean_i64` (container memory gauges).
+pub(super) fn weighted_mean_u64(v: &[(u64, i64)]) -> u64 {
+    let total: i64 = v.iter().map(|(_, w)| *w).sum();
+    if total <= 0 {
+        return mean_u64(&v.iter().map(|(x, _)| *x).collect::<Vec<_>>());
+    }
+    (v.iter()
+        .map(|(x, w)| (*x as u128) * (*w as u128))
+        .sum::<u128>()
+        / (total as u128)) as u64
+}
@@ -0,0 +1,259 @@
+// Downsampling: pure aggregation logic (gauges averaged, cumulative counters keep the last
+// reading; see `containers`, `network`, `probes`, `sensors`, `storage`) and the aggregated
+// table's DDL (`table`). DB access (get by range, save, delete) stays in history_repo::mod.
+
+mod buckets;
+mod containers;
+mod math;
+mod network;
+mod probes;
+mod sensors;
+mod storage;
+mod table;
+
+pub use buckets::{BucketGrid, BucketTimezone, WALL_CLOCK_MIN_RESOLUTION_SECS};
+pub use containers::{
+    OTHER_CONTAINERS_ID, aggregate_aggregated_snapshots_with_container_limit,
+    aggregate_snapshots_with_container_limit,
+};
+pub use math::percentile;
+pub(crate) use math::{finite_max, finite_min};
+pub use table::init_aggregated_table;
+
+use crate::models::{AggregatedSnapshot, FullSystemSnapshot};
+use containers::{aggregate_containers, aggregate_containers_from_aggregated};
+use math::{mean_f64, mean_i64, weighted_mean_f64, weighted_mean_i64};
+use network::aggregate_network;
+use probes::aggregate_probes;
+use sensors::aggregate_sensors;
+use storage::aggregate_storage;
+
+/// Default aggregated tiers (`database.aggregation_tiers`), finest first: 1-min, 5-min, 1-hour,
+/// 1-day (`resolution_seconds`).
+pub const RESOLUTION_1MIN: i32 = 60;
+pub const RESOLUTION_5MIN: i32 = 300;
+pub const RESOLUTION_1H: i32 = 3600;
+pub const RESOLUTION_1D: i32 = 86400;
+pub const AGGREGATED_RESOLUTIONS: [i32; 4] = [
+    RESOLUTION_1MIN,
+    RESOLUTION_5MIN,
+    RESOLUTION_1H,
+    RESOLUTION_1D,
+];
+
+/// Aggregates a bucket of raw snapshots into one AggregatedSnapshot.
+/// Uses bucket_start_ts as created_at; resolution_seconds is normally 60.
+pub fn aggregate_snapshots(
+    snapshots: &[FullSystemSnapshot],
+    bucket_start_ts: i64,
+    resolution_seconds: i32,
+) -> Option<AggregatedSnapshot> {
+    if snapshots.is_empty() {
+        return None;
+    }
+
+    // NaN / infinite readings are left out of every float figure (all of them → 0, no p95).
+    let cpu_loads: Vec<f64> = snapshots
+        .iter()
+        .map(|s| s.cpu.usage_percent)
+        .filter(|v| v.is_finite())
+        .collect();
+    let memory_used: Vec<i64> = snapshots.iter().map(|s| s.ram.used as i64).collect();
+
+    let cpu_load_avg = mean_f64(&cpu_loads);
+    let cpu_load_min = finite_min(cpu_loads.iter().copied());
+    let cpu_load_max = finite_max(cpu_loads.iter().copied());
+
+    let memory_used_avg = mean_i64(&memory_used);
+    let memory_used_min = *memory_used.iter().min().unwrap_or(&0);
+    let memory_used_max = *memory_used.iter().max().unwrap_or(&0);
+
+    let cpu_load_p95 = percentile(&cpu_loads, 95.0);
+    let memory_used_p95 = percentile(&memory_used, 95.0);
+
+    let memory_totals: Vec<i64> = snapshots.iter().map(|s| s.ram.total as i64).collect();
+    let cpu_temperatures: Vec<f64> = snapshots.iter().map(|s| s.cpu.temperature).collect();
+
+    let memory_total_avg = mean_i64(&memory_totals);
+    let memory_total_min = *memory_totals.iter().min().unwrap_or(&0);
+    let memory_total_max = *memory_totals.iter().max().unwrap_or(&0);
+
+    let cpu_temperature_avg = mean_f64(&cpu_temperatures);
+    let cpu_temperature_min = finite_min(cpu_temperatures.iter().copied());
+    let cpu_temperature_max = finite_max(cpu_temperatures.iter().copied());
+
+    let containers = aggregate_containers(snapshots);
+    let networks: Vec<_> = snapshots.iter().map(|s| (&s.network, 1)).collect();
+    let network = aggregate_network(&networks);
+    let storages: Vec<_> = snapshots.iter().map(|s| (&s.storage, 1)).collect();
+    let storage = aggregate_storage(&storages);
+    let probe_lists: Vec<_> = snapshots.iter().map(|s| (s.probes.as_slice(), 1)).collect();
+    let probes = aggregate_probes(&probe_lists);
+    let sensor_lists: Vec<_> = snapshots.iter().map(|s| (&s.sensors[..], 1)).collect();
+    let sensors = aggregate_sensors(&sensor_lists);
+    let last = snapshots.last().unwrap();
+    let cpu = last.cpu.clone();
+    let ram = last.ram.clone();
+    let system = last.system.clone();
+    let gpus = last.gpus.clone();
+    let smart = last.smart.clone();
+
+    Some(AggregatedSnapshot {
+        created_at: bucket_start_ts,
+        resolution_seconds,
+        sample_count: snapshots.len() as i64,
+        cpu_load_avg,
+        cpu_load_min,
+        cpu_load_max,
+        memory_used_avg,
+        memory_used_min,
+        memory_used_max,
+        cpu_load_p95,
+        memory_used_p95,
+        memory_total_avg,
+        memory_total_min,
+        memory_total_max,
+        cpu_temperature_avg,
+        cpu_temperature_min,
+        cpu_temperature_max,
+        cpu,
+        ram,
+        containers,
+        storage,
+        network,
+        system,
+        gpus,
+        smart,
+        probes,
+        sensors,
+    })
+}
+
+/// Aggregates a bucket of finer aggregated snapshots into one coarser AggregatedSnapshot
+/// (1-min → 5-min, 5-min → 1-hour, 1-hour → 1-day).
+pub fn aggregate_aggregated_snapshots(
+    aggs: &[AggregatedSnapshot],
+    bucket_start_ts: i64,
+    resolution_seconds: i32,
+) -> Option<AggregatedSnapshot> {
+    if aggs.is_empty() {
+        return None;
+    }
+
+    // Means are weighted by each child's raw sample count so a short bucket (restart gap)
+    // counts for less. Any legacy child without a count (0) falls back to equal weights.
+    let counts_known = aggs.iter().all(|a| a.sample_count > 0);
+    let weights: Vec<i64> = aggs
+        .iter()
+        .map(|a| if counts_known { a.sample_count } else { 1 })
+        .collect();
+    let sample_count = if counts_known {
+        weights.iter().sum()
+    } else {
+        0
+    };
+    let weighted_f64 = |f: fn(&AggregatedSnapshot) -> f64| {
+        weighted_mean_f64(
+            &aggs
+                .iter()
+                .map(f)
+                .zip(weights.iter().copied())
+                .collect::<Vec<_>>(),
+        )
+    };
+    let weighted_i64 = |f: fn(&AggregatedSnapshot) -> i64| {
+        weighted_mean_i64(
+            &aggs
+                .iter()
+                .map(f)
+                .zip(weights.iter().copied())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let cpu_load_avg = weighted_f64(|a| a.cpu_load_avg);
+    let cpu_load_min = finite_min(aggs.iter().map(|a| a.cpu_load_min));
+    let cpu_load_max = finite_max(aggs.iter().map(|a| a.cpu_load_max));
+
+    let memory_used_avg = weighted_i64(|a| a.memory_used_avg);
+    let memory_used_min = aggs.iter().map(|a| a.memory_used_min).min().unwrap_or(0);
+    let memory_used_max = aggs.iter().map(|a| a.memory_used_max).max().unwrap_or(0);
+
+    // Child p95s can't be merged exactly; the max of them is a conservative upper estimate.
+    let cpu_load_p95 = aggs
+        .iter()
+        .filter_map(|a| a.cpu_load_p95)
+        .filter(|p| p.is_finite())
+        .reduce(f64::max);
+    let memory_used_p95 = aggs.iter().filter_map(|a| a.memory_used_p95).max();
+
+    let memory_total_avg = weighted_i64(|a| a.memory_total_avg);
+    let memory_total_min = aggs.iter().map(|a| a.memory_total_min).min().unwrap_or(0);
+    let memory_total_max = aggs.iter().map(|a| a.memory_total_max).max().unwrap_or(0);
+
+    let cpu_temperature_avg = weighted_f64(|a| a.cpu_temperature_avg);
+    let cpu_temperature_min = finite_min(aggs.iter().map(|a| a.cpu_temperature_min));
+    let cpu_temperature_max = finite_max(aggs.iter().map(|a| a.cpu_temperature_max));
+
+    let containers = aggregate_containers_from_aggregated(aggs, &weights);
+    let networks: Vec<_> = aggs
+        .iter()
+        .map(|a| &a.network)
+        .zip(weights.iter().copied())
+        .collect();
+    let network = aggregate_network(&networks);
+    let storages: Vec<_> = aggs
+        .iter()
+        .map(|a| &a.storage)
+        .zip(weights.iter().copied())
+        .collect();
+    let storage = aggregate_storage(&storages);
+    let probe_lists: Vec<_> = aggs
+        .iter()
+        .map(|a| a.probes.as_slice())
+        .zip(weights.iter().copied())
+        .collect();
+    let probes = aggregate_probes(&probe_lists);
+    let sensor_lists: Vec<_> = aggs
+        .iter()
+        .map(|a| a.sensors.as_slice())
+        .zip(weights.iter().copied())
+        .collect();
+    let sensors = aggregate_sensors(&sensor_lists);
+    let last = aggs.last().unwrap();
+    let cpu = last.cpu.clone();
+    let ram = last.ram.clone();
+    let system = last.system.clone();
+    let gpus = last.gpus.clone();
+    let smart = last.smart.clone();
+
+    Some(AggregatedSnapshot {
+        created_at: bucket_start_ts,
+        resolution_seconds,
+        sample_count,
+        cpu_load_avg,
+        cpu_load_min,
+        cpu_load_max,
+        memory_used_avg,
+        memory_used_min,
+        memory_used_max,
+        cpu_load_p95,
+        memory_used_p95,
+        memory_total_avg,
+        memory_total_min,
+        memory_total_max,
+        cpu_temperature_avg,
+        cpu_temperature_min,
+        cpu_temperature_max,
+        cpu,
+        ram,
+        containers,
+        storage,
+        network,
+        system,
+        gpus,
+        smart,
+        probes,
+        sensors,
+    })
+}
@@ -0,0 +1,73 @@
+// Per-interface network roll-up: rx/tx byte and packet rates averaged per interface name (weighted
+// by sample count); counters, addresses and state from the interface's last sample. The host
+// totals are averaged over the samples.
+
+use std::collections::HashMap;
+
+use super::math::weighted_mean_f64;
+use crate::models::{InterfaceStat, NetworkStats, NetworkTotals};
+
+/// Entries of a bucket of `(entries, weight)` samples grouped by `key`, each group oldest first.
+/// Order: the last sample's keys, then any that disappeared during the bucket in first-seen order.
+pub(super) fn group_by_key<'a, T>(
+    samples: &[(&'a [T], i64)],
+    key: fn(&T) -> &str,
+) -> Vec<Vec<(&'a T, i64)>> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut by_key: HashMap<&str, Vec<(&T, i64)>> = HashMap::new();
+    for (entries, weight) in samples {
+        for entry in *entries {
+            let group = by_key.entry(key(entry)).or_default();
+            if group.is_empty() {
+                order.push(key(entry));
+            }
+            group.push((entry, *weight));
+        }
+    }
+    if let Some((last, _)) = samples.last() {
+        let last_keys: Vec<&str> = last.iter().map(key).collect();
+        order.retain(|k| !last_keys.contains(k));
+        order.splice(0..0, last_keys);
+    }
+    order.into_iter().filter_map(|k| by_key.remove(k)).collect()
+}
+
+/// Interfaces of a bucket of `(network, weight)` samples, oldest first, ordered as in
+/// [`group_by_key`].
+pub(super) fn aggregate_network(samples: &[(&NetworkStats, i64)]) -> NetworkStats {
+    let lists: Vec<_> = samples
+        .iter()
+        .map(|(n, w)| (n.interfaces.as_slice(), *w))
+        .collect();
+    let interfaces = group_by_key(&lists, |i: &InterfaceStat| i.name.as_str())
+        .into_iter()
+        .map(|refs| {
+            let rate = |f: fn(&InterfaceStat) -> f64| {
+                weighted_mean_f64(&refs.iter().map(|(i, w)| (f(i), *w)).collect::<Vec<_>>())
+            };
+            let mut out = refs[refs.len() - 1].0.clone();
+            out.received_bytes_per_sec = rate(|i| i.received_bytes_per_sec);
+            out.transmitted_bytes_per_sec = rate(|i| i.transmitted_bytes_per_sec);
+            out.packets_recv_per_sec = rate(|i| i.packets_recv_per_sec);
+            out.packets_sent_per_sec = rate(|i| i.packets_sent_per_sec);
+            out
+        })
+        .collect();
+    let total = |f: fn(&NetworkTotals) -> f64| {
+        weighted_mean_f64(
+            &samples
+                .iter()
+                .map(|(n, w)| (f(&n.totals), *w))
+                .collect::<Vec<_>>(),
+        )
+    };
+    NetworkStats {
+        interfaces,
+        totals: NetworkTotals {
+            rx_bytes_per_sec: total(|t| t.rx_bytes_per_sec),
+            tx_bytes_per_sec: total(|t| t.tx_bytes_per_sec),
+            rx_packets_per_sec: total(|t| t.rx_packets_per_sec),
+            tx_packets_per_sec: total(|t| t.tx_packets_per_sec),
+        },
+    }
+}
@@ -0,0 +1,38 @@
+// Probe roll-up per probe name: average round trip and loss weighted by sample count, the
+// bucket's fastest / slowest round trip, target, method and latest round trip from the last sample.
+
+use super::math::weighted_mean_f64;
+use super::network::group_by_key;
+use crate::models::ProbeStat;
+
+/// Probes of a bucket of `(probes, weight)` samples, oldest first, ordered as in
+/// [`group_by_key`]. Round trips stay `None` when no sample in the bucket had one.
+pub(super) fn aggregate_probes(samples: &[(&[ProbeStat], i64)]) -> Vec<ProbeStat> {
+    group_by_key(samples, |p: &ProbeStat| p.name.as_str())
+        .into_iter()
+        .map(|refs| {
+            let rtts: Vec<(f64, i64)> = refs
+                .iter()
+                .filter_map(|(p, w)| p.rtt_avg_ms.map(|rtt| (rtt, *w)))
+                .filter(|(rtt, _)| rtt.is_finite())
+                .collect();
+            let extreme = |f: fn(&ProbeStat) -> Option<f64>, pick: fn(f64, f64) -> f64| {
+                refs.iter()
+                    .filter_map(|(p, _)| f(p))
+                    .filter(|v| v.is_finite())
+                    .reduce(pick)
+            };
+            let mut out = refs[refs.len() - 1].0.clone();
+            out.rtt_avg_ms = (!rtts.is_empty()).then(|| weighted_mean_f64(&rtts));
+            out.rtt_min_ms = extreme(|p| p.rtt_min_ms, f64::min);
+            out.rtt_max_ms = extreme(|p| p.rtt_max_ms, f64::max);
+            out.loss_percent = weighted_mean_f64(
+                &refs
+                    .iter()
+                    .map(|(p, w)| (p.loss_percent, *w))
+                    .collect::<Vec<_>>(),
+            );
+            out
+        })
+        .collect()
+}
@@ -0,0 +1,29 @@
+// Sensor roll-up per sensor id: the reading averaged by sample count and the bucket's highest
+// reading; name, label, kind and unit from the last sample.
+
+use super::math::weighted_mean_f64;
+use super::network::group_by_key;
+use crate::models::SensorStat;
+
+/// Sensors of a bucket of `(sensors, weight)` samples, oldest first, ordered as in
+/// [`group_by_key`]. An aggregated input contributes its own `max`, a raw one its `value`.
+pub(super) fn aggregate_sensors(samples: &[(&[SensorStat], i64)]) -> Vec<SensorStat> {
+    group_by_key(samples, |s: &SensorStat| s.id.as_str())
+        .into_iter()
+        .map(|refs| {
+            let values: Vec<(f64, i64)> = refs
+                .iter()
+                .map(|(s, w)| (s.value, *w))
+                .filter(|(v, _)| v.is_finite())
+                .collect();
+            let mut out = refs[refs.len() - 1].0.clone();
+            out.value = weighted_mean_f64(&values);
+            out.max = refs
+                .iter()
+                .map(|(s, _)| s.max.unwrap_or(s.value))
+                .filter(|v| v.is_finite())
+                .reduce(f64::max);
+            out
+        })
+        .collect()
+}
@@ -0,0 +1,38 @@
+// Storage roll-up: partition usage averaged per mount (weighted by sample count); partition
+// sizes and the disk I/O counters (cumulative since boot) from the last sample.
+
+use super::math::{weighted_mean_f64, weighted_mean_u64};
+use super::network::group_by_key;
+use crate::models::{PartitionStat, StorageStats};
+
+/// Storage of a bucket of `(storage, weight)` samples, oldest first. Partitions are ordered as
+/// in [`group_by_key`]; disks are the last sample's.
+pub(super) fn aggregate_storage(samples: &[(&StorageStats, i64)]) -> StorageStats {
+    let partition_samples: Vec<_> = samples
+        .iter()
+        .map(|(s, w)| (s.partitions.as_slice(), *w))
+        .collect();
+    let partitions = group_by_key(&partition_samples, |p: &PartitionStat| p.mount.as_str())
+        .into_iter()
+        .map(|refs| {
+            let gauge = |f: fn(&PartitionStat) -> u64| {
+                weighted_mean_u64(&refs.iter().map(|(p, w)| (f(p), *w)).collect::<Vec<_>>())
+            };
+            let mut out = refs[refs.len() - 1].0.clone();
+            out.used_space = gauge(|p| p.used_space);
+            out.available_space = gauge(|p| p.available_space);
+            out.usage_percent = weighted_mean_f64(
+                &refs
+                    .iter()
+                    .map(|(p, w)| (p.usage_percent, *w))
+                    .collect::<Vec<_>>(),
+            );
+            out
+        })
+        .collect();
+    let disks = samples
+        .last()
+        .map(|(s, _)| s.disks.clone())
+        .unwrap_or_default();
+    StorageStats { partitions, disks }
+}
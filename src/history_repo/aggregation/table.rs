@@ -0,0 +1,53 @@
+// DDL of the aggregated tier table (`system_history_aggregated`); columns added later come in
+// through `migrations.rs` on existing databases.
+
+use crate::history_repo::HistoryResult;
+use sqlx::SqlitePool;
+
+/// Creates the system_history_aggregated table and its unique bucket index if not present.
+pub async fn init_aggregated_table(pool: &SqlitePool) -> HistoryResult<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS system_history_aggregated (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at INTEGER NOT NULL,
+            resolution_seconds INTEGER NOT NULL,
+            cpu_load_avg REAL NOT NULL,
+            cpu_load_min REAL,
+            cpu_load_max REAL,
+            memory_used_avg INTEGER NOT NULL,
+            memory_used_min INTEGER,
+            memory_used_max INTEGER,
+            container_data BLOB NOT NULL,
+            storage_data BLOB NOT NULL,
+            network_data BLOB NOT NULL,
+            system_data BLOB NOT NULL,
+            cpu_data BLOB,
+            ram_data BLOB,
+            gpu_data BLOB,
+            smart_data BLOB,
+            probe_data BLOB,
+            sensor_data BLOB,
+            memory_total_avg INTEGER NOT NULL DEFAULT 0,
+            memory_total_min INTEGER NOT NULL DEFAULT 0,
+            memory_total_max INTEGER NOT NULL DEFAULT 0,
+            cpu_temperature_avg REAL NOT NULL DEFAULT 0,
+            cpu_temperature_min REAL NOT NULL DEFAULT 0,
+            cpu_temperature_max REAL NOT NULL DEFAULT 0,
+            cpu_load_p95 REAL,
+            memory_used_p95 INTEGER,
+            sample_count INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_aggregated_created_at_resolution ON system_history_aggregated(created_at, resolution_seconds)",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+// Anomaly flagging for /api/history?annotate=anomalies: a rolling z-score over the CPU and RAM
+// series of the returned points. Pure — no database access.
+
+use crate::models::{Anomaly, AnomalyMetric, AnomalySeverity, HistoryPoint};
+
+/// How points are scored: each point against the mean and standard deviation of the `window`
+/// points before it; `|z| >= threshold` flags it. `min_stddev` (in percentage points) keeps a
+/// nearly flat baseline from turning every small wobble into an anomaly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyParams {
+    pub window: usize,
+    pub threshold: f64,
+    pub min_stddev: f64,
+}
+
+impl Default for AnomalyParams {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            threshold: 3.0,
+            min_stddev: 0.5,
+        }
+    }
+}
+
+/// CPU usage and RAM usage (percent) anomalies of `points`, ordered by `from`, CPU first on ties.
+pub fn detect_history_anomalies(points: &[HistoryPoint], params: AnomalyParams) -> Vec<Anomaly> {
+    let timestamps: Vec<u64> = points.iter().map(|p| p.snapshot.timestamp).collect();
+    let cpu: Vec<f64> = points
+        .iter()
+        .map(|p| p.snapshot.cpu.usage_percent)
+        .collect();
+    let ram: Vec<f64> = points
+        .iter()
+        .map(|p| p.snapshot.ram.usage_percent)
+        .collect();
+    let mut anomalies = detect_anomalies(&timestamps, &cpu, AnomalyMetric::Cpu, params);
+    anomalies.extend(detect_anomalies(
+        &timestamps,
+        &ram,
+        AnomalyMetric::Ram,
+        params,
+    ));
+    anomalies.sort_by_key(|a| (a.from, a.metric == AnomalyMetric::Ram));
+    anomalies
+}
+
+/// Anomalies of one series (`values[i]` observed at `timestamps[i]`). The first `window` points
+/// only form the baseline. Consecutive flagged points merge into one anomaly spanning them, at
+/// the severity of the worst. A spike shows as a short run; a level shift as a run that lasts
+/// until the new level dominates the window.
+pub fn detect_anomalies(
+    timestamps: &[u64],
+    values: &[f64],
+    metric: AnomalyMetric,
+    params: AnomalyParams,
+) -> Vec<Anomaly> {
+    let window = params.window.max(2);
+    let n = timestamps.len().min(values.len());
+    let mut anomalies: Vec<Anomaly> = Vec::new();
+    let mut open = false;
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for i in 0..n {
+        if i >= window {
+            let mean = sum / window as f64;
+            let variance = (sum_sq / window as f64 - mean * mean).max(0.0);
+            let stddev = variance.sqrt().max(params.min_stddev).max(f64::EPSILON);
+            let z = (values[i] - mean).abs() / stddev;
+            let severity = if z >= 2.0 * params.threshold {
+                Some(AnomalySeverity::Critical)
+            } else if z >= params.threshold {
+                Some(AnomalySeverity::Warning)
+            } else {
+                None
+            };
+            match (severity, anomalies.last_mut()) {
+                (Some(severity), Some(last)) if open => {
+                    last.to = timestamps[i];
+                    last.severity = last.severity.max(severity);
+                }
+                (Some(severity), _) => anomalies.push(Anomaly {
+                    from: timestamps[i],
+                    to: timestamps[i],
+                    metric,
+                    severity,
+                }),
+                (None, _) => {}
+            }
+            open = severity.is_some();
+            let leaving = values[i - window];
+            sum -= leaving;
+            sum_sq -= leaving * leaving;
+        }
+        sum += values[i];
+        sum_sq += values[i] * values[i];
+    }
+    anomalies
+}
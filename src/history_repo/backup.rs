@@ -0,0 +1,92 @@
+// Online backups: `VACUUM INTO` snapshots written to timestamped files, with count-based retention.
+
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult};
+use crate::models::BackupInfo;
+
+const BACKUP_PREFIX: &str = "history-";
+const BACKUP_EXTENSION: &str = ".db";
+
+impl HistoryRepo {
+    /// Write a consistent copy of the database to `path` (`VACUUM INTO`); returns its size in bytes.
+    /// Safe while the server is writing: the copy is one read transaction, WAL content included.
+    /// Fails if `path` already exists.
+    #[instrument(skip(self), fields(repo = "history", operation = "backup_to"))]
+    pub async fn backup_to(&self, path: &Path) -> HistoryResult<u64> {
+        let target = path.to_str().ok_or_else(|| {
+            HistoryError::InvalidArgument(format!("backup path {} is not UTF-8", path.display()))
+        })?;
+        sqlx::query("VACUUM INTO $1")
+            .bind(target)
+            .execute(&self.pool)
+            .await?;
+        Ok(tokio::fs::metadata(path).await?.len())
+    }
+
+    /// Back up into `dir` as `history-<UTC timestamp>.db`, then delete all but the newest
+    /// `retention_count` backups there. File system calls stay off the runtime threads.
+    pub async fn create_backup(
+        &self,
+        dir: &Path,
+        retention_count: u32,
+    ) -> HistoryResult<BackupInfo> {
+        tokio::fs::create_dir_all(dir).await?;
+        let name = format!(
+            "{BACKUP_PREFIX}{}{BACKUP_EXTENSION}",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")
+        );
+        let path = dir.join(name);
+        let size_bytes = self.backup_to(&path).await?;
+        let prune_dir = dir.to_path_buf();
+        let pruned =
+            tokio::task::spawn_blocking(move || prune_backups(&prune_dir, retention_count))
+                .await??;
+        tracing::info!(path = %path.display(), size_bytes, pruned, "database backup written");
+        Ok(BackupInfo {
+            path: path.display().to_string(),
+            size_bytes,
+        })
+    }
+}
+
+/// Backup files in `dir`, oldest first (the fixed-width timestamp sorts chronologically).
+/// A missing directory has no backups. Blocking: async callers go through `spawn_blocking`.
+pub fn list_backups(dir: &Path) -> HistoryResult<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(BACKUP_PREFIX)
+            && name.ends_with(BACKUP_EXTENSION)
+            && entry.file_type()?.is_file()
+        {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Most recent backup in `dir`, if any.
+pub fn latest_backup(dir: &Path) -> HistoryResult<Option<PathBuf>> {
+    Ok(list_backups(dir)?.pop())
+}
+
+/// Delete the oldest backups in `dir` so at most `keep` remain; returns how many were removed.
+pub fn prune_backups(dir: &Path, keep: u32) -> HistoryResult<usize> {
+    let backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep as usize);
+    for path in &backups[..excess] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(excess)
+}
@@ -1,9 +1,73 @@
 // BLOB version prefix helpers. [version: u8][payload].
 // system_data: version 1 = full SystemStats (legacy), version 2 = SystemStatsDynamic only.
+// Versions 3 and 4 are the zstd-compressed counterparts of 1 and 2 (payload = zstd(wincode)).
+// container_data: version 5 = `ContainerBlob` (containers plus their per-second rates), 6 its
+// compressed form; 1 / 3 hold the containers alone and read back with zero rates.
+// Anything above 6 is a future format: decoding fails with `UnknownBlobVersion` instead of
+// falling back to empty values, unless the whole blob is an exact pre-prefix payload.
 
-pub(super) const BLOB_VERSION: u8 = 1;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use wincode::config::DefaultConfig;
+
+use crate::history_repo::{HistoryError, HistoryResult};
+use crate::models::{
+    ContainerBlob, ContainerStats, SystemInfo, cpu_percent_of_host, pids_usage_percent,
+};
+
+pub const BLOB_VERSION: u8 = 1;
 /// system_data: dynamic-only (Phase 2). Legacy v1 = full SystemStats.
-pub(super) const BLOB_VERSION_SYSTEM_DYNAMIC: u8 = 2;
+pub const BLOB_VERSION_SYSTEM_DYNAMIC: u8 = 2;
+/// zstd-compressed `BLOB_VERSION` payload.
+pub const BLOB_VERSION_COMPRESSED: u8 = 3;
+/// zstd-compressed `BLOB_VERSION_SYSTEM_DYNAMIC` payload.
+pub const BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED: u8 = 4;
+/// container_data: [`ContainerBlob`] (containers plus rates).
+pub const BLOB_VERSION_CONTAINERS: u8 = 5;
+/// zstd-compressed `BLOB_VERSION_CONTAINERS` payload.
+pub const BLOB_VERSION_CONTAINERS_COMPRESSED: u8 = 6;
+
+/// Highest version byte this build writes or reads.
+pub const MAX_KNOWN_BLOB_VERSION: u8 = BLOB_VERSION_CONTAINERS_COMPRESSED;
+
+/// Blobs read with a version above [`MAX_KNOWN_BLOB_VERSION`] since the process started.
+static UNKNOWN_VERSION_TOTAL: AtomicU64 = AtomicU64::new(0);
+/// One bit per version byte already logged at WARN.
+static UNKNOWN_VERSION_WARNED: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+/// Blobs with an unknown version met by any reader in this process
+/// (`homeserver_history_blob_unknown_version_total`).
+pub fn unknown_version_total() -> u64 {
+    UNKNOWN_VERSION_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Count an unknown-version blob, warn once per distinct version, and build the error.
+fn unknown_version(column: &'static str, version: u8) -> HistoryError {
+    UNKNOWN_VERSION_TOTAL.fetch_add(1, Ordering::Relaxed);
+    let bit = 1u64 << (version % 64);
+    let warned = UNKNOWN_VERSION_WARNED[usize::from(version / 64)].fetch_or(bit, Ordering::Relaxed);
+    if warned & bit == 0 {
+        tracing::warn!(
+            column,
+            version,
+            "history blob has an unknown version (written by a newer build?); skipping such rows"
+        );
+    }
+    HistoryError::UnknownBlobVersion { column, version }
+}
+
+/// zstd level for history blobs: small snapshots gain little from higher levels.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compressed counterpart of an uncompressed blob version (1 → 3, 2 → 4, 5 → 6).
+pub const fn compressed_version(version: u8) -> u8 {
+    match version {
+        BLOB_VERSION => BLOB_VERSION_COMPRESSED,
+        BLOB_VERSION_SYSTEM_DYNAMIC => BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED,
+        BLOB_VERSION_CONTAINERS => BLOB_VERSION_CONTAINERS_COMPRESSED,
+        other => other,
+    }
+}
 
 pub(super) fn with_version_prefix(version: u8, payload: Vec<u8>) -> Vec<u8> {
     let mut out = Vec::with_capacity(1 + payload.len());
@@ -26,3 +90,139 @@ pub(super) fn blob_payload(bytes: &[u8], expected_version: u8) -> &[u8] {
 pub(super) fn blob_version(bytes: &[u8]) -> u8 {
     if bytes.is_empty() { 0 } else { bytes[0] }
 }
+
+/// Serialize `value` with wincode and prefix it with `version`. With `compress`, the payload is
+/// zstd-compressed and the prefix becomes the compressed variant of `version`.
+pub fn encode_blob<T>(value: &T, version: u8, compress: bool) -> HistoryResult<Vec<u8>>
+where
+    T: wincode::SchemaWrite<DefaultConfig, Src = T> + ?Sized,
+{
+    let payload =
+        wincode::serialize(value).map_err(|e| HistoryError::BlobEncode(format!("wincode: {e}")))?;
+    if compress {
+        let packed = zstd::bulk::compress(&payload, ZSTD_LEVEL)
+            .map_err(|e| HistoryError::BlobEncode(format!("zstd compress: {e}")))?;
+        Ok(with_version_prefix(compressed_version(version), packed))
+    } else {
+        Ok(with_version_prefix(version, payload))
+    }
+}
+
+/// Inverse of [`encode_blob`]: accepts `version`, its compressed variant, and legacy
+/// unprefixed blobs (whole blob is the wincode payload). A version byte above
+/// [`MAX_KNOWN_BLOB_VERSION`] is only accepted when the whole blob decodes exactly as a legacy
+/// payload; otherwise it is [`HistoryError::UnknownBlobVersion`]. `column` names the source in
+/// errors.
+pub fn decode_blob<T>(bytes: &[u8], version: u8, column: &'static str) -> HistoryResult<T>
+where
+    T: for<'de> wincode::SchemaRead<'de, DefaultConfig, Dst = T>,
+{
+    let decode_error = |e: wincode::ReadError| HistoryError::BlobDecode {
+        column,
+        version: blob_version(bytes),
+        reason: e.to_string(),
+    };
+    if blob_version(bytes) == compressed_version(version) && compressed_version(version) != version
+    {
+        // A legacy unprefixed payload may start with the same byte; fall through if it isn't zstd.
+        if let Ok(payload) = zstd::decode_all(&bytes[1..]) {
+            return wincode::deserialize(&payload).map_err(decode_error);
+        }
+    }
+    let found = blob_version(bytes);
+    if found > MAX_KNOWN_BLOB_VERSION {
+        // Pre-prefix payloads may start with any byte; a future format almost never decodes
+        // exactly as the legacy layout.
+        return wincode::deserialize_exact(bytes).map_err(|_| unknown_version(column, found));
+    }
+    wincode::deserialize(blob_payload(bytes, version)).map_err(decode_error)
+}
+
+/// Encode `container_data` as a [`ContainerBlob`], keeping each container's rates.
+pub fn encode_containers(containers: &[ContainerStats], compress: bool) -> HistoryResult<Vec<u8>> {
+    encode_blob(
+        &ContainerBlob::new(containers),
+        BLOB_VERSION_CONTAINERS,
+        compress,
+    )
+}
+
+/// Inverse of [`encode_containers`]; version 1 / 3 and legacy unprefixed blobs decode as plain
+/// containers with zero rates. The derived `pids_usage_percent` and `cpu_percent_of_host` are
+/// recomputed from the stored fields.
+pub fn decode_containers(bytes: &[u8], column: &'static str) -> HistoryResult<Vec<ContainerStats>> {
+    let mut containers = decode_container_list(bytes, column)?;
+    for c in &mut containers {
+        c.pids_usage_percent = pids_usage_percent(c.pids, c.pids_limit);
+        c.cpu_percent_of_host = cpu_percent_of_host(c.cpu_percent, c.online_cpus);
+    }
+    Ok(containers)
+}
+
+fn decode_container_list(bytes: &[u8], column: &'static str) -> HistoryResult<Vec<ContainerStats>> {
+    match blob_version(bytes) {
+        BLOB_VERSION_CONTAINERS | BLOB_VERSION_CONTAINERS_COMPRESSED => {
+            match decode_blob::<ContainerBlob>(bytes, BLOB_VERSION_CONTAINERS, column) {
+                Ok(blob) => Ok(blob.into_containers()),
+                // A legacy unprefixed list of five or six containers starts with the same byte.
+                Err(e) => decode_blob(bytes, BLOB_VERSION, column).map_err(|_| e),
+            }
+        }
+        _ => decode_blob(bytes, BLOB_VERSION, column),
+    }
+}
+
+/// `SystemInfo` as stored before `node_name` was added.
+#[derive(wincode::SchemaRead)]
+struct SystemInfoV1 {
+    os_family: String,
+    os_manufacturer: String,
+    os_version: String,
+    system_manufacturer: String,
+    system_model: String,
+    processor_name: String,
+}
+
+/// `SystemInfo` as stored before the primary addresses were added.
+#[derive(wincode::SchemaRead)]
+struct SystemInfoV2 {
+    v1: SystemInfoV1,
+    node_name: String,
+}
+
+impl From<SystemInfoV2> for SystemInfo {
+    fn from(v2: SystemInfoV2) -> Self {
+        let v1 = v2.v1;
+        SystemInfo {
+            os_family: v1.os_family,
+            os_manufacturer: v1.os_manufacturer,
+            os_version: v1.os_version,
+            system_manufacturer: v1.system_manufacturer,
+            system_model: v1.system_model,
+            processor_name: v1.processor_name,
+            node_name: v2.node_name,
+            primary_ipv4: None,
+            primary_ipv6: None,
+        }
+    }
+}
+
+/// Decode an unprefixed wincode `SystemInfo` (`system_info.data`, export headers); rows written
+/// before `node_name` or the primary addresses existed read back with them empty.
+pub fn decode_system_info(bytes: &[u8], column: &'static str) -> HistoryResult<SystemInfo> {
+    wincode::deserialize(bytes).or_else(|e| {
+        let legacy = wincode::deserialize_exact::<SystemInfoV2>(bytes).or_else(|_| {
+            wincode::deserialize_exact::<SystemInfoV1>(bytes).map(|v1| SystemInfoV2 {
+                v1,
+                node_name: String::new(),
+            })
+        });
+        legacy
+            .map(SystemInfo::from)
+            .map_err(|_| HistoryError::BlobDecode {
+                column,
+                version: 0,
+                reason: e.to_string(),
+            })
+    })
+}
@@ -0,0 +1,56 @@
+// Content-addressed `blob_store`: storage/network sections shared between raw rows by hash.
+
+use std::collections::BTreeMap;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use sqlx::{QueryBuilder, Sqlite};
+use tracing::instrument;
+
+/// `blob_store` rows per INSERT (two binds each), under SQLite's historical 999-variable limit.
+const SHARED_BLOB_CHUNK_ROWS: usize = 999 / 2;
+
+/// blake3 digest of an encoded blob (version prefix included), used as the `blob_store` key.
+pub fn blob_hash(bytes: &[u8]) -> Vec<u8> {
+    blake3::hash(bytes).as_bytes().to_vec()
+}
+
+impl HistoryRepo {
+    /// Store each `(hash, bytes)` entry once (existing hashes are left alone), in multi-row
+    /// INSERTs of up to [`SHARED_BLOB_CHUNK_ROWS`] entries.
+    pub(in crate::history_repo) async fn put_shared_blobs(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        blobs: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> HistoryResult<()> {
+        let entries: Vec<_> = blobs.iter().collect();
+        for chunk in entries.chunks(SHARED_BLOB_CHUNK_ROWS) {
+            let mut qb =
+                QueryBuilder::<Sqlite>::new("INSERT OR IGNORE INTO blob_store (hash, data) ");
+            qb.push_values(chunk, |mut b, (hash, data)| {
+                b.push_bind(*hash).push_bind(*data);
+            });
+            qb.build().execute(&mut **tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete `blob_store` entries no raw row references any more. Returns rows removed.
+    #[instrument(skip(self), fields(repo = "history", operation = "gc_blob_store"))]
+    pub async fn gc_blob_store(&self) -> HistoryResult<u64> {
+        let r = sqlx::query(
+            "DELETE FROM blob_store
+             WHERE NOT EXISTS (SELECT 1 FROM system_history WHERE storage_hash = blob_store.hash)
+               AND NOT EXISTS (SELECT 1 FROM system_history WHERE network_hash = blob_store.hash)",
+        )
+        .execute(&self.writer)
+        .await?;
+        Ok(r.rows_affected())
+    }
+
+    /// Number of distinct blobs in `blob_store`.
+    pub async fn blob_store_count(&self) -> HistoryResult<i64> {
+        let n = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM blob_store")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(n)
+    }
+}
@@ -0,0 +1,50 @@
+// Retry for read queries that hit SQLITE_BUSY / SQLITE_LOCKED. Writes never contend with each
+// other (they share the single `writer` connection); readers can still be locked out briefly by a
+// checkpoint or VACUUM past `busy_timeout`.
+
+use std::time::Duration;
+
+use super::{HistoryRepo, HistoryResult};
+
+/// Extra attempts after the first busy failure.
+const READ_BUSY_RETRIES: u32 = 4;
+/// Wait before the first retry; doubled after each further failure, plus up to as much jitter.
+const READ_BUSY_INITIAL_DELAY: Duration = Duration::from_millis(25);
+
+/// `base` plus a pseudo-random share of it, so readers locked out together do not retry in step.
+fn with_jitter(base: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    base + base.mul_f64(f64::from(nanos % 1000) / 1000.0)
+}
+
+impl HistoryRepo {
+    /// Run the read `query`, again after a short, growing delay while it fails with
+    /// [`HistoryError::is_busy`](super::HistoryError::is_busy), at most `READ_BUSY_RETRIES`
+    /// times. Other errors, and the last busy one, are returned as is.
+    pub(in crate::history_repo) async fn retry_busy<T, F, Fut>(
+        &self,
+        operation: &'static str,
+        mut query: F,
+    ) -> HistoryResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = HistoryResult<T>>,
+    {
+        let mut delay = READ_BUSY_INITIAL_DELAY;
+        let mut attempt = 0;
+        loop {
+            match query().await {
+                Err(e) if e.is_busy() && attempt < READ_BUSY_RETRIES => {
+                    attempt += 1;
+                    tracing::debug!(operation, attempt, error = %e, "database busy, retrying read");
+                    tokio::time::sleep(with_jitter(delay)).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// `container_inventory`: one row per container id seen in local snapshots, with its first and
+// latest sighting, so a container that is gone can still be named and dated after its raw rows
+// have aged out.
+
+use std::collections::HashMap;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{ContainerInventoryEntry, ContainerState, FullSystemSnapshot};
+use sqlx::{Database, Encode, QueryBuilder, Sqlite, Type};
+
+pub(super) const CREATE_CONTAINER_INVENTORY: &str = "CREATE TABLE IF NOT EXISTS container_inventory (container_id TEXT PRIMARY KEY, name TEXT NOT NULL, image TEXT NOT NULL, first_seen INTEGER NOT NULL, last_seen INTEGER NOT NULL, last_state TEXT NOT NULL, previous_names TEXT NOT NULL DEFAULT '[]')";
+/// Backs the retention prune.
+pub(super) const CREATE_CONTAINER_INVENTORY_INDEX: &str = "CREATE INDEX IF NOT EXISTS idx_container_inventory_last_seen ON container_inventory(last_seen)";
+
+/// Binds per `container_inventory` row in the multi-row UPSERT below.
+const UPSERT_BINDS: usize = 6;
+/// Rows per UPSERT statement, under SQLite's historical 999-variable limit.
+pub(in crate::history_repo) const UPSERT_CHUNK_ROWS: usize = 999 / UPSERT_BINDS;
+
+/// Folds a sighting (`excluded`) into the stored row. A newer sighting under another name moves the stored name to
+/// `previous_names` (once); an older one (a late batch) only widens `first_seen` / `last_seen`.
+const UPSERT_CONFLICT: &str = " ON CONFLICT(container_id) DO UPDATE SET
+    previous_names = CASE
+        WHEN excluded.name = container_inventory.name
+            OR excluded.last_seen < container_inventory.last_seen
+            OR EXISTS (SELECT 1 FROM json_each(container_inventory.previous_names)
+                       WHERE value = container_inventory.name)
+        THEN container_inventory.previous_names
+        ELSE json_insert(container_inventory.previous_names, '$[#]', container_inventory.name)
+    END,
+    name = CASE WHEN excluded.last_seen >= container_inventory.last_seen
+        THEN excluded.name ELSE container_inventory.name END,
+    image = CASE WHEN excluded.image <> '' AND excluded.last_seen >= container_inventory.last_seen
+        THEN excluded.image ELSE container_inventory.image END,
+    last_state = CASE WHEN excluded.last_seen >= container_inventory.last_seen
+        THEN excluded.last_state ELSE container_inventory.last_state END,
+    first_seen = MIN(container_inventory.first_seen, excluded.first_seen),
+    last_seen = MAX(container_inventory.last_seen, excluded.last_seen)";
+
+/// `container_id, name, image, first_seen, last_seen, last_state, previous_names, gone`.
+pub(in crate::history_repo) type InventoryRecord =
+    (String, String, String, i64, i64, String, String, bool);
+
+/// Consecutive sightings of one container under one name within a flush.
+pub(in crate::history_repo) struct InventoryRow<'a> {
+    pub(in crate::history_repo) id: &'a str,
+    name: &'a str,
+    image: &'a str,
+    first_seen: i64,
+    last_seen: i64,
+    last_state: ContainerState,
+}
+
+/// `container_inventory` INSERT of `rows`, on either engine; the caller appends its engine's
+/// conflict clause.
+pub(in crate::history_repo) fn inventory_insert<'a, DB>(
+    rows: &[&'a InventoryRow<'a>],
+) -> QueryBuilder<DB>
+where
+    DB: Database,
+    DB::Arguments: Default,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> &'t str: Encode<'t, DB> + Type<DB>,
+{
+    let mut qb = QueryBuilder::<DB>::new(
+        "INSERT INTO container_inventory (container_id, name, image, first_seen, last_seen, last_state) ",
+    );
+    qb.push_values(rows, |mut b, r| {
+        b.push_bind(r.id)
+            .push_bind(r.name)
+            .push_bind(r.image)
+            .push_bind(r.first_seen)
+            .push_bind(r.last_seen)
+            .push_bind(r.last_state.as_str());
+    });
+    qb
+}
+
+pub(in crate::history_repo) fn inventory_entry(
+    (container_id, name, image, first, last, state, previous, gone): InventoryRecord,
+) -> ContainerInventoryEntry {
+    ContainerInventoryEntry {
+        container_id,
+        name,
+        image,
+        first_seen: first.max(0) as u64,
+        last_seen: last.max(0) as u64,
+        last_state: ContainerState::from_docker(&state),
+        previous_names: serde_json::from_str(&previous).unwrap_or_default(),
+        gone,
+    }
+}
+
+/// One row per container and name run across `snapshots`, in the order the runs started, so a
+/// rename inside the batch is upserted as the old name followed by the new one.
+pub(in crate::history_repo) fn inventory_rows(
+    snapshots: &[FullSystemSnapshot],
+) -> Vec<InventoryRow<'_>> {
+    let mut rows: Vec<InventoryRow<'_>> = Vec::new();
+    let mut current: HashMap<&str, usize> = HashMap::new();
+    for s in snapshots {
+        let ts = s.timestamp as i64;
+        for c in &s.containers {
+            match current.get(c.id.as_str()).map(|&i| &mut rows[i]) {
+                Some(row) if row.name == c.name => {
+                    row.first_seen = row.first_seen.min(ts);
+                    if ts >= row.last_seen {
+                        row.last_seen = ts;
+                        row.last_state = c.state;
+                        if !c.image.is_empty() {
+                            row.image = &c.image;
+                        }
+                    }
+                }
+                _ => {
+                    current.insert(&c.id, rows.len());
+                    rows.push(InventoryRow {
+                        id: &c.id,
+                        name: &c.name,
+                        image: &c.image,
+                        first_seen: ts,
+                        last_seen: ts,
+                        last_state: c.state,
+                    });
+                }
+            }
+        }
+    }
+    rows
+}
+
+impl HistoryRepo {
+    /// Record every container of `snapshots` in `container_inventory`, in the flush's transaction.
+    pub(in crate::history_repo) async fn put_container_inventory(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        snapshots: &[FullSystemSnapshot],
+    ) -> HistoryResult<()> {
+        let rows = inventory_rows(snapshots);
+        let rows: Vec<_> = rows.iter().collect();
+        for chunk in rows.chunks(UPSERT_CHUNK_ROWS) {
+            let mut qb = inventory_insert::<Sqlite>(chunk);
+            qb.push(UPSERT_CONFLICT);
+            qb.build().execute(&mut **tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Known containers, those still in the latest local snapshot first (by name), then the gone
+    /// ones (most recently seen first) when `include_gone`.
+    pub async fn get_container_inventory(
+        &self,
+        include_gone: bool,
+    ) -> HistoryResult<Vec<ContainerInventoryEntry>> {
+        self.retry_busy("get_container_inventory", || async {
+            let rows: Vec<InventoryRecord> = sqlx::query_as(
+                "SELECT * FROM (
+                         SELECT container_id, name, image, first_seen, last_seen, last_state,
+                                previous_names,
+                                last_seen < (SELECT COALESCE(MAX(created_at), 0) FROM system_history
+                                             WHERE node IS NULL) AS gone
+                         FROM container_inventory)
+                     WHERE $1 OR NOT gone
+                     ORDER BY gone, last_seen DESC, name",
+            )
+            .bind(include_gone)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows.into_iter().map(inventory_entry).collect())
+        })
+        .await
+    }
+
+    /// Delete `container_inventory` rows not seen for `database.container_inventory_retention_days`
+    /// (relative to `now_ms`). Returns rows removed. Called from
+    /// [`prune_old_data`](Self::prune_old_data).
+    pub async fn prune_container_inventory(&self, now_ms: i64) -> HistoryResult<u64> {
+        let r = sqlx::query("DELETE FROM container_inventory WHERE last_seen < $1")
+            .bind(now_ms - self.container_inventory_retention_ms)
+            .execute(&self.writer)
+            .await?;
+        Ok(r.rows_affected())
+    }
+}
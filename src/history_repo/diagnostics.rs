@@ -0,0 +1,81 @@
+// `collection_errors`: collector failures recorded by the stats worker, served at /api/errors.
+
+use std::sync::atomic::Ordering;
+
+use sqlx::Row;
+use tracing::instrument;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{CollectionError, ErrorSourceCount};
+
+impl HistoryRepo {
+    /// Append one failure entry.
+    #[instrument(skip(self, error), fields(repo = "history", operation = "record_error", source = %error.source))]
+    pub async fn record_error(&self, error: &CollectionError) -> HistoryResult<()> {
+        sqlx::query(
+            "INSERT INTO collection_errors (ts, source, message, suppressed) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(error.ts)
+        .bind(&error.source)
+        .bind(&error.message)
+        .bind(i64::try_from(error.suppressed).unwrap_or(i64::MAX))
+        .execute(&self.writer)
+        .await?;
+        Ok(())
+    }
+
+    /// The newest `limit` entries, newest first.
+    pub async fn get_recent_errors(&self, limit: u32) -> HistoryResult<Vec<CollectionError>> {
+        self.retry_busy("get_recent_errors", || async move {
+            let rows = sqlx::query(
+                "SELECT ts, source, message, suppressed FROM collection_errors
+                 ORDER BY ts DESC, id DESC LIMIT $1",
+            )
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+            rows.into_iter()
+                .map(|row| {
+                    Ok(CollectionError {
+                        ts: row.try_get("ts")?,
+                        source: row.try_get("source")?,
+                        message: row.try_get("message")?,
+                        suppressed: row.try_get::<i64, _>("suppressed")?.max(0) as u64,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Failures per source at or after `since_ts`, counting suppressed ones. Sorted by source.
+    pub async fn error_counts_since(&self, since_ts: i64) -> HistoryResult<Vec<ErrorSourceCount>> {
+        self.retry_busy("error_counts_since", || async move {
+            let rows: Vec<(String, i64)> = sqlx::query_as(
+                "SELECT source, SUM(1 + suppressed) FROM collection_errors
+                 WHERE ts >= $1 GROUP BY source ORDER BY source",
+            )
+            .bind(since_ts)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows
+                .into_iter()
+                .map(|(source, count)| ErrorSourceCount {
+                    source,
+                    count: count.max(0) as u64,
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Delete entries older than `database.error_retention_days` (relative to `now_ms`).
+    /// Returns rows removed. Called from [`prune_old_data`](Self::prune_old_data).
+    pub async fn prune_collection_errors(&self, now_ms: i64) -> HistoryResult<u64> {
+        let r = sqlx::query("DELETE FROM collection_errors WHERE ts < $1")
+            .bind(now_ms - self.error_retention_ms.load(Ordering::Relaxed))
+            .execute(&self.writer)
+            .await?;
+        Ok(r.rows_affected())
+    }
+}
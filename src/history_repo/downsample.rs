@@ -0,0 +1,129 @@
+// Raw-snapshot downsampling for /api/history: bucket average (default) or last sample per bucket;
+// plus Largest-Triangle-Three-Buckets selection over the merged points (`downsample=lttb`).
+
+use crate::history_repo::aggregation::aggregate_snapshots;
+use crate::history_repo::envelope::{aggregated_point, merge_bucket, raw_point};
+use crate::models::{FullSystemSnapshot, HistoryPoint};
+
+/// How raw snapshots are reduced to one point per resolution bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownsampleMode {
+    /// Mean CPU/RAM and container gauges over the bucket; cumulative counters keep the last value.
+    #[default]
+    Average,
+    /// The bucket's last sample (pre-averaging behavior).
+    Last,
+}
+
+/// One point for the raw snapshots of the bucket starting at `bucket_start`. Envelopes cover
+/// every sample.
+pub(in crate::history_repo) fn reduce_raw_bucket(
+    snapshots: Vec<FullSystemSnapshot>,
+    bucket_start: i64,
+    resolution_ms: i64,
+    mode: DownsampleMode,
+) -> Option<HistoryPoint> {
+    match mode {
+        DownsampleMode::Last => merge_bucket(snapshots.into_iter().map(raw_point).collect()),
+        DownsampleMode::Average => average_bucket(&snapshots, bucket_start, resolution_ms),
+    }
+}
+
+/// One averaged point for a bucket, stamped with the bucket start like aggregated rows.
+fn average_bucket(
+    snaps: &[FullSystemSnapshot],
+    bucket_start: i64,
+    resolution_ms: i64,
+) -> Option<HistoryPoint> {
+    let agg = aggregate_snapshots(snaps, bucket_start, (resolution_ms / 1000) as i32)?;
+    let (cpu_avg, temp_avg) = (agg.cpu_load_avg, agg.cpu_temperature_avg);
+    let (used_avg, total_avg) = (agg.memory_used_avg as u64, agg.memory_total_avg as u64);
+
+    let mut point = aggregated_point(agg);
+    let s = &mut point.snapshot;
+    s.cpu.usage_percent = cpu_avg;
+    s.cpu.temperature = temp_avg;
+    s.cpu.core_usages = mean_core_usages(snaps);
+    s.ram.used = used_avg;
+    s.ram.total = total_avg;
+    s.ram.available = total_avg.saturating_sub(used_avg);
+    s.ram.usage_percent = if total_avg > 0 {
+        used_avg as f64 / total_avg as f64 * 100.0
+    } else {
+        0.0
+    };
+    Some(point)
+}
+
+/// Element-wise mean of per-core usage; the last sample's cores if the core count changed.
+fn mean_core_usages(snaps: &[FullSystemSnapshot]) -> Vec<f64> {
+    let Some(last) = snaps.last().map(|s| &s.cpu.core_usages) else {
+        return vec![];
+    };
+    if snaps.iter().any(|s| s.cpu.core_usages.len() != last.len()) {
+        return last.clone();
+    }
+    (0..last.len())
+        .map(|i| snaps.iter().map(|s| s.cpu.core_usages[i]).sum::<f64>() / snaps.len() as f64)
+        .collect()
+}
+
+/// Largest-Triangle-Three-Buckets: indices of at most `threshold` points of the series
+/// `(xs[i], ys[i])` (sorted by x) that keep its visual shape. The first and last points are always
+/// kept; each bucket in between contributes the point forming the largest triangle with the
+/// previously kept point and the next bucket's average. Every index is returned when
+/// `threshold >= xs.len()` or `threshold < 3`.
+pub fn lttb_indices(xs: &[f64], ys: &[f64], threshold: usize) -> Vec<usize> {
+    let n = xs.len().min(ys.len());
+    if threshold >= n || threshold < 3 {
+        return (0..n).collect();
+    }
+    let every = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut kept = Vec::with_capacity(threshold);
+    let mut a = 0;
+    kept.push(a);
+    for i in 0..threshold - 2 {
+        let next_start = ((i + 1) as f64 * every) as usize + 1;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(n);
+        let next_len = (next_end - next_start) as f64;
+        let avg_x = xs[next_start..next_end].iter().sum::<f64>() / next_len;
+        let avg_y = ys[next_start..next_end].iter().sum::<f64>() / next_len;
+
+        let start = (i as f64 * every) as usize + 1;
+        let end = ((i + 1) as f64 * every) as usize + 1;
+        let (ax, ay) = (xs[a], ys[a]);
+        // A bucket whose areas are all NaN keeps its first point rather than repeating `a`.
+        a = start;
+        let mut max_area = -1.0;
+        for j in start..end {
+            let area = ((ax - avg_x) * (ys[j] - ay) - (ax - xs[j]) * (avg_y - ay)).abs();
+            if area > max_area {
+                max_area = area;
+                a = j;
+            }
+        }
+        kept.push(a);
+    }
+    kept.push(n - 1);
+    kept
+}
+
+/// `points` reduced to at most `threshold` by [`lttb_indices`] over the CPU load series. RAM and
+/// the other fields follow the same selected points, so every series stays time-aligned.
+pub fn lttb_points(points: Vec<HistoryPoint>, threshold: usize) -> Vec<HistoryPoint> {
+    if threshold >= points.len() {
+        return points;
+    }
+    let xs: Vec<f64> = points.iter().map(|p| p.snapshot.timestamp as f64).collect();
+    let ys: Vec<f64> = points
+        .iter()
+        .map(|p| p.snapshot.cpu.usage_percent)
+        .collect();
+    let mut keep = lttb_indices(&xs, &ys, threshold).into_iter().peekable();
+    points
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep.next_if_eq(i).is_some())
+        .map(|(_, p)| p)
+        .collect()
+}
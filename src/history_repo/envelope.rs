@@ -0,0 +1,70 @@
+// History points with min/max/p95 envelopes: built from raw or aggregated rows, merged per bucket.
+
+use crate::history_repo::aggregation::{finite_max, finite_min, percentile};
+use crate::history_repo::history_merge::aggregated_to_snapshot;
+use crate::models::{AggregatedSnapshot, FullSystemSnapshot, HistoryEnvelope, HistoryPoint};
+
+/// A raw sample: its envelope collapses to the sample itself.
+pub(in crate::history_repo) fn raw_point(s: FullSystemSnapshot) -> HistoryPoint {
+    let cpu = s.cpu.usage_percent;
+    let mem = s.ram.used as i64;
+    HistoryPoint {
+        envelope: Some(HistoryEnvelope {
+            cpu_load_min: cpu,
+            cpu_load_max: cpu,
+            memory_used_min: mem,
+            memory_used_max: mem,
+            cpu_load_p95: Some(cpu),
+            memory_used_p95: Some(mem),
+        }),
+        snapshot: s,
+    }
+}
+
+/// An aggregated row: envelope from its min/max/p95 columns.
+pub(in crate::history_repo) fn aggregated_point(agg: AggregatedSnapshot) -> HistoryPoint {
+    let envelope = HistoryEnvelope {
+        cpu_load_min: agg.cpu_load_min,
+        cpu_load_max: agg.cpu_load_max,
+        memory_used_min: agg.memory_used_min,
+        memory_used_max: agg.memory_used_max,
+        cpu_load_p95: agg.cpu_load_p95,
+        memory_used_p95: agg.memory_used_p95,
+    };
+    HistoryPoint {
+        snapshot: aggregated_to_snapshot(agg),
+        envelope: Some(envelope),
+    }
+}
+
+/// Merge one bucket's points: keep the last point; the envelope widens to cover every point, and
+/// p95 is re-estimated from the points' p95s (exact when they are raw samples).
+pub(in crate::history_repo) fn merge_bucket(points: Vec<HistoryPoint>) -> Option<HistoryPoint> {
+    let envelopes: Vec<&HistoryEnvelope> =
+        points.iter().filter_map(|p| p.envelope.as_ref()).collect();
+    let envelope = (!envelopes.is_empty()).then(|| {
+        let cpu_p95s: Vec<f64> = envelopes.iter().filter_map(|e| e.cpu_load_p95).collect();
+        let mem_p95s: Vec<i64> = envelopes.iter().filter_map(|e| e.memory_used_p95).collect();
+        HistoryEnvelope {
+            cpu_load_min: finite_min(envelopes.iter().map(|e| e.cpu_load_min)),
+            cpu_load_max: finite_max(envelopes.iter().map(|e| e.cpu_load_max)),
+            memory_used_min: envelopes
+                .iter()
+                .map(|e| e.memory_used_min)
+                .min()
+                .unwrap_or(0),
+            memory_used_max: envelopes
+                .iter()
+                .map(|e| e.memory_used_max)
+                .max()
+                .unwrap_or(0),
+            cpu_load_p95: percentile(&cpu_p95s, 95.0),
+            memory_used_p95: percentile(&mem_p95s, 95.0),
+        }
+    });
+    let last = points.into_iter().last()?;
+    Some(HistoryPoint {
+        snapshot: last.snapshot,
+        envelope,
+    })
+}
@@ -0,0 +1,97 @@
+// Typed errors for HistoryRepo, so embedders and routes can tell failure classes apart.
+
+/// Everything a [`HistoryRepo`](super::HistoryRepo) method can fail with.
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    /// Query, connection or pool failure (see [`is_unavailable`](Self::is_unavailable)).
+    #[error("database error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    /// A stored blob that must be readable could not be decoded. `version` is the blob's prefix
+    /// byte (0 for unprefixed blobs such as `system_info.data`).
+    #[error("failed to decode {column} (blob version {version}): {reason}")]
+    BlobDecode {
+        column: &'static str,
+        version: u8,
+        reason: String,
+    },
+    /// A blob's version byte is newer than this build understands (written by a newer release,
+    /// or damaged). Readers skip the row rather than substitute empty values.
+    #[error(
+        "{column} has unknown blob version {version} (this build reads up to {})",
+        crate::history_repo::blob::MAX_KNOWN_BLOB_VERSION
+    )]
+    UnknownBlobVersion { column: &'static str, version: u8 },
+    /// Serializing or compressing a value for storage failed.
+    #[error("failed to encode blob: {0}")]
+    BlobEncode(String),
+    /// Filesystem access (database directory, `-wal` file, backups, export files).
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The system clock is before the Unix epoch.
+    #[error("system clock error: {0}")]
+    Time(#[from] std::time::SystemTimeError),
+    /// The database was written by a newer build; opening it would lose data.
+    #[error("database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: i64, supported: u32 },
+    /// `PRAGMA quick_check` failed or SQLite reported a malformed / non-database file.
+    #[error(
+        "database integrity check failed: {0}; restore a backup or set database.recover_on_corruption = true"
+    )]
+    Corrupt(String),
+    /// An export file failed validation on import.
+    #[error("invalid export file: {0}")]
+    InvalidExport(String),
+    /// The caller passed an argument the repo cannot use (unknown pragma, non-UTF-8 path, …).
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    /// A `spawn_blocking` task (row decoding, backup pruning) panicked or was cancelled.
+    #[error("background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+pub type HistoryResult<T> = Result<T, HistoryError>;
+
+impl HistoryError {
+    /// Transient: the pool is exhausted or closed, or SQLite stayed locked past `busy_timeout`.
+    /// Retrying later may succeed (HTTP 503).
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            HistoryError::Sqlx(sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed) => true,
+            _ => self.is_busy(),
+        }
+    }
+
+    /// SQLite returned SQLITE_BUSY (5) or SQLITE_LOCKED (6): another connection held the lock
+    /// past `busy_timeout`. Reads are retried a few times before this surfaces.
+    pub fn is_busy(&self) -> bool {
+        matches!(self.sqlite_primary_code(), Some(5 | 6))
+    }
+
+    /// The file is damaged: [`Corrupt`](Self::Corrupt), or SQLite returned SQLITE_CORRUPT (11)
+    /// or SQLITE_NOTADB (26).
+    pub fn is_corruption(&self) -> bool {
+        matches!(self, HistoryError::Corrupt(_))
+            || matches!(self.sqlite_primary_code(), Some(11 | 26))
+    }
+
+    /// A blob with a version this build cannot read ([`UnknownBlobVersion`](Self::UnknownBlobVersion)):
+    /// history readers skip such rows.
+    pub fn is_unknown_blob_version(&self) -> bool {
+        matches!(self, HistoryError::UnknownBlobVersion { .. })
+    }
+
+    /// Primary SQLite result code of a database error (extended codes masked to the low byte).
+    /// Postgres SQLSTATEs are numeric-looking too, so only SQLite errors are read.
+    fn sqlite_primary_code(&self) -> Option<i32> {
+        match self {
+            HistoryError::Sqlx(sqlx::Error::Database(e))
+                if e.try_downcast_ref::<sqlx::sqlite::SqliteError>().is_some() =>
+            {
+                e.code()
+                    .and_then(|c| c.parse::<i32>().ok())
+                    .map(|c| c & 0xff)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// Portable history export/import for moving raw history between servers.
+//
+// File layout (all integers little-endian):
+//   magic "HSHX" | u32 format version | u8 has_system_info [| u32 len | wincode SystemInfo]
+//   then records until EOF: u32 len | wincode SnapshotRecord
+// Version 1 records were a bare wincode FullSystemSnapshot, without probes and sensors; they are
+// still imported.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use tracing::instrument;
+use wincode::config::DefaultConfig;
+
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult, blob};
+use crate::models::{FullSystemSnapshot, SnapshotRecord, SystemInfo};
+
+const EXPORT_MAGIC: &[u8; 4] = b"HSHX";
+/// Bumped whenever the record encoding changes; import reads this and every earlier version.
+pub const EXPORT_FORMAT_VERSION: u32 = 2;
+/// Raw rows are read this many ms at a time so exports never load the whole table.
+const EXPORT_WINDOW_MS: i64 = 3_600_000;
+/// Snapshots inserted per import transaction.
+const IMPORT_BATCH: usize = 1000;
+/// Upper bound for one record; anything larger is a corrupt length prefix.
+const MAX_RECORD_BYTES: u32 = 16 * 1024 * 1024;
+
+/// Outcome of [`HistoryRepo::import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: u64,
+    /// Snapshots whose timestamp already existed (in the database or earlier in the file).
+    pub skipped_duplicates: u64,
+}
+
+impl HistoryRepo {
+    /// Write raw snapshots in [from_ts, to_ts) plus the stored `SystemInfo` to `writer` in the
+    /// export format. Returns the number of snapshots written.
+    #[instrument(
+        skip(self, writer),
+        fields(repo = "history", operation = "export_range")
+    )]
+    pub async fn export_range<W: Write>(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        mut writer: W,
+    ) -> HistoryResult<u64> {
+        writer.write_all(EXPORT_MAGIC)?;
+        writer.write_all(&EXPORT_FORMAT_VERSION.to_le_bytes())?;
+        match self.get_stored_system_info().await? {
+            Some(info) => {
+                writer.write_all(&[1])?;
+                write_record(&mut writer, &info)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+
+        // Walk only the span that holds rows, so a wide range over a short history stays cheap.
+        let (first, last): (Option<i64>, Option<i64>) = sqlx::query_as(
+            "SELECT MIN(created_at), MAX(created_at) FROM system_history
+             WHERE node IS NULL AND created_at >= $1 AND created_at < $2",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .fetch_one(&self.pool)
+        .await?;
+        let (Some(mut window_start), Some(last)) = (first, last) else {
+            writer.flush()?;
+            return Ok(0);
+        };
+        let mut written = 0u64;
+        while window_start <= last {
+            let window_end = window_start.saturating_add(EXPORT_WINDOW_MS).min(to_ts);
+            for snapshot in self
+                .get_raw_snapshots_by_time_range(window_start, window_end)
+                .await?
+            {
+                write_record(&mut writer, &SnapshotRecord::from(snapshot))?;
+                written += 1;
+            }
+            window_start = window_end;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Read an export produced by [`export_range`](Self::export_range) and insert its snapshots,
+    /// skipping timestamps that are already present. Each batch is one transaction.
+    /// The database's own `SystemInfo` wins; the file's is only used when none is stored yet.
+    #[instrument(skip(self, reader), fields(repo = "history", operation = "import"))]
+    pub async fn import<R: Read>(&self, mut reader: R) -> HistoryResult<ImportReport> {
+        let mut header = [0u8; 9];
+        reader
+            .read_exact(&mut header)
+            .map_err(|e| truncated_or_io(e, "header"))?;
+        if &header[..4] != EXPORT_MAGIC {
+            return Err(invalid("not a history export file"));
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if !(1..=EXPORT_FORMAT_VERSION).contains(&version) {
+            return Err(invalid(format!(
+                "unsupported export format version {version} (expected 1 to {EXPORT_FORMAT_VERSION})"
+            )));
+        }
+        let file_info: Option<SystemInfo> = match header[8] {
+            0 => None,
+            1 => {
+                let bytes = read_record_bytes(&mut reader)?
+                    .ok_or_else(|| invalid("truncated system info"))?;
+                Some(blob::decode_system_info(&bytes, "export record")?)
+            }
+            other => return Err(invalid(format!("invalid system info flag {other}"))),
+        };
+        let system_info = match self.get_stored_system_info().await? {
+            Some(info) => info,
+            None => file_info.unwrap_or_default(),
+        };
+
+        let mut report = ImportReport::default();
+        let mut batch = Vec::with_capacity(IMPORT_BATCH);
+        while let Some(snapshot) = match version {
+            1 => read_record::<FullSystemSnapshot>(&mut reader)?,
+            _ => read_record::<SnapshotRecord>(&mut reader)?.map(FullSystemSnapshot::from),
+        } {
+            if i64::try_from(snapshot.timestamp).is_err() {
+                return Err(invalid(format!(
+                    "snapshot timestamp {} out of range",
+                    snapshot.timestamp
+                )));
+            }
+            batch.push(snapshot);
+            if batch.len() == IMPORT_BATCH {
+                self.import_batch(&mut batch, &system_info, &mut report)
+                    .await?;
+            }
+        }
+        self.import_batch(&mut batch, &system_info, &mut report)
+            .await?;
+        Ok(report)
+    }
+
+    /// Drop snapshots whose timestamp exists in the database or earlier in `batch`, then save
+    /// the rest in one transaction.
+    async fn import_batch(
+        &self,
+        batch: &mut Vec<FullSystemSnapshot>,
+        system_info: &SystemInfo,
+        report: &mut ImportReport,
+    ) -> HistoryResult<()> {
+        let (Some(min), Some(max)) = (
+            batch.iter().map(|s| s.timestamp).min(),
+            batch.iter().map(|s| s.timestamp).max(),
+        ) else {
+            return Ok(());
+        };
+        let existing: Vec<i64> = sqlx::query_scalar(
+            "SELECT created_at FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at <= $2",
+        )
+        .bind(min as i64)
+        .bind(max as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut seen: HashSet<u64> = existing.into_iter().map(|ts| ts as u64).collect();
+        let before = batch.len();
+        batch.retain(|s| seen.insert(s.timestamp));
+        report.skipped_duplicates += (before - batch.len()) as u64;
+        report.imported += batch.len() as u64;
+        self.save_snapshots(batch, system_info).await?;
+        batch.clear();
+        Ok(())
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> HistoryError {
+    HistoryError::InvalidExport(reason.into())
+}
+
+/// A short read means a cut-off file (invalid export); anything else is a real I/O error.
+fn truncated_or_io(e: std::io::Error, what: &str) -> HistoryError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        invalid(format!("export file truncated in {what}"))
+    } else {
+        HistoryError::Io(e)
+    }
+}
+
+fn write_record<T: wincode::SchemaWrite<DefaultConfig, Src = T>>(
+    writer: &mut impl Write,
+    value: &T,
+) -> HistoryResult<()> {
+    let bytes = wincode::serialize(value)
+        .map_err(|e| HistoryError::BlobEncode(format!("wincode export: {e}")))?;
+    let len = u32::try_from(bytes.len())
+        .ok()
+        .filter(|&len| len <= MAX_RECORD_BYTES)
+        .ok_or_else(|| HistoryError::BlobEncode("export record too large".into()))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Next length-prefixed record, or `None` at a clean end of file.
+fn read_record<T>(reader: &mut impl Read) -> HistoryResult<Option<T>>
+where
+    T: for<'de> wincode::SchemaRead<'de, DefaultConfig, Dst = T>,
+{
+    let Some(bytes) = read_record_bytes(reader)? else {
+        return Ok(None);
+    };
+    let value = wincode::deserialize(&bytes).map_err(|e| HistoryError::BlobDecode {
+        column: "export record",
+        version: 0,
+        reason: e.to_string(),
+    })?;
+    Ok(Some(value))
+}
+
+/// The payload of the next length-prefixed record, or `None` at a clean end of file.
+fn read_record_bytes(reader: &mut impl Read) -> HistoryResult<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(invalid("export file truncated in record length")),
+            n => filled += n,
+        }
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_RECORD_BYTES {
+        return Err(invalid(format!(
+            "record length {len} exceeds {MAX_RECORD_BYTES}"
+        )));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| truncated_or_io(e, "record"))?;
+    Ok(Some(bytes))
+}
@@ -0,0 +1,126 @@
+// Free space on the database's filesystem, and the emergency prune the history writer runs
+// while it is below `database.min_free_bytes`.
+
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use tracing::instrument;
+
+use super::{HistoryRepo, HistoryResult};
+
+/// Shortest raw retention an emergency prune goes down to.
+const EMERGENCY_MIN_KEEP_MS: i64 = 3_600_000;
+/// Shortest aggregated tier retention an emergency prune goes down to.
+const EMERGENCY_MIN_KEEP_AGGREGATED_MS: i64 = 86_400_000;
+
+/// Rows an emergency prune deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmergencyPrune {
+    pub raw_removed: u64,
+    pub aggregated_removed: u64,
+}
+
+/// Bytes available to this process on the filesystem holding `path`. [`Statvfs`] in production;
+/// tests swap in a fake with [`HistoryRepo::with_free_space`].
+pub trait FreeSpace: Send + Sync {
+    fn available_bytes(&self, path: &str) -> std::io::Result<u64>;
+}
+
+/// `statvfs(2)`: blocks available to unprivileged users times the fragment size.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Statvfs;
+
+impl FreeSpace for Statvfs {
+    #[cfg(unix)]
+    fn available_bytes(&self, path: &str) -> std::io::Result<u64> {
+        let stat = rustix::fs::statvfs(path)?;
+        Ok(stat.f_bavail.saturating_mul(stat.f_frsize))
+    }
+
+    #[cfg(not(unix))]
+    fn available_bytes(&self, _path: &str) -> std::io::Result<u64> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+impl HistoryRepo {
+    /// Measure free space with `free_space` instead of `statvfs` (tests).
+    pub fn with_free_space(mut self, free_space: Arc<dyn FreeSpace>) -> Self {
+        self.free_space = free_space;
+        self
+    }
+
+    /// Bytes available on the filesystem holding the database file (`database.path`).
+    pub fn available_bytes(&self) -> std::io::Result<u64> {
+        self.free_space.available_bytes(&self.path)
+    }
+
+    /// Bytes of free pages inside the database file; writes reuse them before the file grows.
+    pub async fn reusable_bytes(&self) -> HistoryResult<u64> {
+        let pages = self.fragmentation().await?;
+        Ok(u64::try_from(pages.freelist_count * pages.page_size).unwrap_or(0))
+    }
+
+    /// Delete raw rows and aggregated rows past their retention halved `halvings` times (raw
+    /// down to an hour, aggregated tiers down to a day), then the `blob_store` entries nothing
+    /// references. Raw rows not yet rolled into the first tier and tier rows not yet rolled into
+    /// the next one are kept, as in the regular prune; rows pushed by other nodes never roll up.
+    /// The disk is full, so `max_prune_fraction` does not apply. The freed pages go back to the
+    /// filesystem with `incremental_vacuum` when the file is INCREMENTAL (a full VACUUM would
+    /// need room the disk lacks; otherwise SQLite reuses them, see [`Self::reusable_bytes`]) and
+    /// a TRUNCATE checkpoint empties the WAL.
+    #[instrument(skip(self), fields(repo = "history", operation = "emergency_prune"))]
+    pub async fn emergency_prune(&self, halvings: u32) -> HistoryResult<EmergencyPrune> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis() as i64;
+        let raw_keep_ms = self
+            .raw_retention_ms
+            .load(Ordering::Relaxed)
+            .checked_shr(halvings)
+            .unwrap_or(0)
+            .max(EMERGENCY_MIN_KEEP_MS);
+        let rolled_up = match self.retention.resolutions().next() {
+            Some(finest) => self
+                .get_aggregation_watermark(finest)
+                .await?
+                .unwrap_or(i64::MIN),
+            None => i64::MAX,
+        };
+        let raw_removed = sqlx::query(
+            "DELETE FROM system_history WHERE created_at < $1 AND (node IS NOT NULL OR created_at < $2)",
+        )
+        .bind(now_ms - raw_keep_ms)
+        .bind(rolled_up)
+        .execute(&self.writer)
+        .await?
+        .rows_affected();
+        let cutoffs = self
+            .retention
+            .halved(halvings, EMERGENCY_MIN_KEEP_AGGREGATED_MS)
+            .prune_cutoffs(now_ms, |resolution| {
+                self.get_aggregation_watermark(resolution)
+            })
+            .await?;
+        let mut aggregated_removed = 0;
+        for (resolution, cutoff) in cutoffs {
+            aggregated_removed += sqlx::query(
+                "DELETE FROM system_history_aggregated WHERE resolution_seconds = $1 AND created_at < $2",
+            )
+            .bind(resolution)
+            .bind(cutoff)
+            .execute(&self.writer)
+            .await?
+            .rows_affected();
+        }
+        self.gc_blob_store().await?;
+        sqlx::query("PRAGMA incremental_vacuum")
+            .execute(&self.writer)
+            .await?;
+        self.wal_checkpoint().await?;
+        Ok(EmergencyPrune {
+            raw_removed,
+            aggregated_removed,
+        })
+    }
+}
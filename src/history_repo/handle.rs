@@ -0,0 +1,92 @@
+// Shared access to a repo that may not be open yet: the HTTP server and the worker start while
+// `connect`/`init` is still retrying (e.g. a NAS mount that is not up), and pick the repo up once
+// it is ready.
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::history_repo::{HistoryRepo, HistoryStore};
+
+/// The local history store: disabled (agent mode), pending (still opening) or ready. Clones
+/// share the state.
+#[derive(Clone, Default)]
+pub struct HistoryHandle(Option<Arc<Slot>>);
+
+#[derive(Default)]
+struct Slot {
+    repo: OnceLock<Arc<dyn HistoryStore>>,
+    /// Why the last open attempt failed, while pending.
+    last_error: Mutex<Option<String>>,
+}
+
+impl HistoryHandle {
+    /// Agent mode (`database.enabled = false`): there never is a repo.
+    pub fn disabled() -> Self {
+        Self(None)
+    }
+
+    /// A repo that is still being opened; see [`Self::set_ready`].
+    pub fn pending() -> Self {
+        Self(Some(Arc::default()))
+    }
+
+    pub fn ready(repo: Arc<dyn HistoryStore>) -> Self {
+        let handle = Self::pending();
+        handle.set_ready(repo);
+        handle
+    }
+
+    /// The repo once it is open.
+    pub fn get(&self) -> Option<&Arc<dyn HistoryStore>> {
+        self.0.as_ref()?.repo.get()
+    }
+
+    /// False in agent mode; true while pending.
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Why the last open attempt failed; `None` when ready, disabled or not tried yet.
+    pub fn last_error(&self) -> Option<String> {
+        let slot = self.0.as_ref()?;
+        slot.last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Publish the opened repo to every clone. Ignored when disabled or already set.
+    pub fn set_ready(&self, repo: Arc<dyn HistoryStore>) {
+        if let Some(slot) = &self.0 {
+            *slot.last_error.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            let _ = slot.repo.set(repo);
+        }
+    }
+
+    /// Record a failed open attempt (shown in the 503 answers meanwhile).
+    pub fn set_error(&self, error: impl Into<String>) {
+        if let Some(slot) = &self.0
+            && slot.repo.get().is_none()
+        {
+            *slot.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.into());
+        }
+    }
+}
+
+impl From<Arc<dyn HistoryStore>> for HistoryHandle {
+    fn from(repo: Arc<dyn HistoryStore>) -> Self {
+        Self::ready(repo)
+    }
+}
+
+impl From<Arc<HistoryRepo>> for HistoryHandle {
+    fn from(repo: Arc<HistoryRepo>) -> Self {
+        Self::ready(repo)
+    }
+}
+
+impl From<Option<Arc<HistoryRepo>>> for HistoryHandle {
+    /// `None` is agent mode.
+    fn from(repo: Option<Arc<HistoryRepo>>) -> Self {
+        repo.map_or_else(Self::disabled, |repo| Self::ready(repo))
+    }
+}
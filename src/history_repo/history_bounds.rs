@@ -0,0 +1,34 @@
+// Oldest and newest timestamps /api/history can answer, across raw rows and the aggregated tiers.
+
+use tracing::instrument;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+
+impl HistoryRepo {
+    /// `(earliest, latest)` `created_at` (epoch ms) over local raw rows and the configured
+    /// aggregated tiers; `None` when they hold no rows. Rows pushed by other instances and tiers
+    /// no longer listed in `aggregation_tiers` are not counted.
+    #[instrument(skip(self), fields(repo = "history", operation = "get_history_bounds"))]
+    pub async fn get_history_bounds(&self) -> HistoryResult<(Option<i64>, Option<i64>)> {
+        self.retry_busy("get_history_bounds", || async move {
+            let (mut earliest, mut latest): (Option<i64>, Option<i64>) = sqlx::query_as(
+                "SELECT MIN(created_at), MAX(created_at) FROM system_history WHERE node IS NULL",
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            for resolution in self.retention.resolutions() {
+                let (oldest, newest): (Option<i64>, Option<i64>) = sqlx::query_as(
+                    "SELECT MIN(created_at), MAX(created_at) FROM system_history_aggregated
+                     WHERE resolution_seconds = $1",
+                )
+                .bind(resolution)
+                .fetch_one(&self.pool)
+                .await?;
+                earliest = earliest.into_iter().chain(oldest).min();
+                latest = latest.into_iter().chain(newest).max();
+            }
+            Ok((earliest, latest))
+        })
+        .await
+    }
+}
@@ -1,103 +1,155 @@
-// API history merge path, deserialization helpers for stored blobs, VACUUM.
+// API history merge path, deserialization helpers for stored blobs, ping.
 
-use crate::history_repo::{HistoryRepo, blob};
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult, blob};
 use crate::models::{
-    AggregatedSnapshot, ContainerStats, CpuStats, FullSystemSnapshot, GpuStats, NetworkStats,
-    RamStats, SmartHealth, StorageStats,
+    AggregatedSnapshot, ContainerStats, CpuStats, FullSystemSnapshot, GpuStats, HistoryPoint,
+    NetworkStats, ProbeStat, RamStats, SensorStat, SmartHealth, StorageStats,
 };
-use std::collections::BTreeMap;
 use tracing::instrument;
 
-/// Deserialize container_data; on legacy/corrupt blob return empty vec and log.
-pub(in crate::history_repo) fn deserialize_container_data(bytes: &[u8]) -> Vec<ContainerStats> {
-    wincode::deserialize(blob::blob_payload(bytes, blob::BLOB_VERSION)).unwrap_or_else(|e| {
-        tracing::debug!(error = %e, "wincode deserialize containers (legacy/corrupt), using empty");
-        vec![]
-    })
+/// Decode a blob that has a fallback: a blob of unknown version is an error (the caller skips
+/// the row); legacy or corrupt data is logged and replaced by `fallback()`.
+pub(in crate::history_repo) fn decode_or<T>(
+    bytes: &[u8],
+    version: u8,
+    column: &'static str,
+    fallback: impl FnOnce() -> T,
+) -> HistoryResult<T>
+where
+    T: for<'de> wincode::SchemaRead<'de, wincode::config::DefaultConfig, Dst = T>,
+{
+    match blob::decode_blob(bytes, version, column) {
+        Ok(value) => Ok(value),
+        Err(e) if e.is_unknown_blob_version() => Err(e),
+        Err(e) => {
+            tracing::debug!(error = %e, column, "wincode deserialize (legacy/corrupt), using fallback");
+            Ok(fallback())
+        }
+    }
 }
 
-pub(in crate::history_repo) fn deserialize_storage_data(bytes: &[u8]) -> StorageStats {
-    wincode::deserialize(blob::blob_payload(bytes, blob::BLOB_VERSION)).unwrap_or_else(|e| {
-        tracing::debug!(error = %e, "wincode deserialize storage (legacy/corrupt), using empty");
-        StorageStats {
-            partitions: vec![],
-            disks: vec![],
+/// Deserialize container_data (with rates from v5 on); on a corrupt blob return empty vec and
+/// log.
+pub(in crate::history_repo) fn deserialize_container_data(
+    bytes: &[u8],
+) -> HistoryResult<Vec<ContainerStats>> {
+    match blob::decode_containers(bytes, "container_data") {
+        Ok(containers) => Ok(containers),
+        Err(e) if e.is_unknown_blob_version() => Err(e),
+        Err(e) => {
+            tracing::debug!(error = %e, column = "container_data", "wincode deserialize (legacy/corrupt), using fallback");
+            Ok(Vec::new())
         }
-    })
+    }
 }
 
-pub(in crate::history_repo) fn deserialize_network_data(bytes: &[u8]) -> NetworkStats {
-    wincode::deserialize(blob::blob_payload(bytes, blob::BLOB_VERSION)).unwrap_or_else(|e| {
-        tracing::debug!(error = %e, "wincode deserialize network (legacy/corrupt), using empty");
-        NetworkStats { interfaces: vec![] }
+pub(in crate::history_repo) fn deserialize_storage_data(
+    bytes: &[u8],
+) -> HistoryResult<StorageStats> {
+    decode_or(bytes, blob::BLOB_VERSION, "storage_data", || StorageStats {
+        partitions: vec![],
+        disks: vec![],
     })
 }
 
+pub(in crate::history_repo) fn deserialize_network_data(
+    bytes: &[u8],
+) -> HistoryResult<NetworkStats> {
+    let network = decode_or(
+        bytes,
+        blob::BLOB_VERSION,
+        "network_data",
+        NetworkStats::default,
+    )?;
+    // `totals` is not stored.
+    Ok(NetworkStats::new(network.interfaces))
+}
+
 /// Deserialize the optional `gpu_data` blob (schema v4+). NULL/empty/corrupt → empty vec.
-pub(in crate::history_repo) fn deserialize_gpu_data(bytes: Option<&[u8]>) -> Vec<GpuStats> {
+pub(in crate::history_repo) fn deserialize_gpu_data(
+    bytes: Option<&[u8]>,
+) -> HistoryResult<Vec<GpuStats>> {
     match bytes {
-        Some(b) if !b.is_empty() => wincode::deserialize(blob::blob_payload(b, blob::BLOB_VERSION))
-            .unwrap_or_else(|e| {
-                tracing::debug!(error = %e, "wincode deserialize gpus (legacy/corrupt), using empty");
-                vec![]
-            }),
-        _ => vec![],
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "gpu_data", Vec::new),
+        _ => Ok(vec![]),
     }
 }
 
 /// Deserialize the optional `smart_data` blob (schema v5+). NULL/empty/corrupt → empty vec.
-pub(in crate::history_repo) fn deserialize_smart_data(bytes: Option<&[u8]>) -> Vec<SmartHealth> {
+pub(in crate::history_repo) fn deserialize_smart_data(
+    bytes: Option<&[u8]>,
+) -> HistoryResult<Vec<SmartHealth>> {
+    match bytes {
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "smart_data", Vec::new),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Deserialize the optional `probe_data` blob (schema v14+). NULL/empty/corrupt → empty vec.
+pub(in crate::history_repo) fn deserialize_probe_data(
+    bytes: Option<&[u8]>,
+) -> HistoryResult<Vec<ProbeStat>> {
     match bytes {
-        Some(b) if !b.is_empty() => wincode::deserialize(blob::blob_payload(b, blob::BLOB_VERSION))
-            .unwrap_or_else(|e| {
-                tracing::debug!(error = %e, "wincode deserialize smart (legacy/corrupt), using empty");
-                vec![]
-            }),
-        _ => vec![],
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "probe_data", Vec::new),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Deserialize the optional `sensor_data` blob (schema v16+). NULL/empty/corrupt → empty vec.
+pub(in crate::history_repo) fn deserialize_sensor_data(
+    bytes: Option<&[u8]>,
+) -> HistoryResult<Vec<SensorStat>> {
+    match bytes {
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "sensor_data", Vec::new),
+        _ => Ok(vec![]),
     }
 }
 
 /// Deserialize the optional `cpu_data` blob. For legacy rows (NULL/empty) or corrupt data,
-/// reconstruct a minimal `CpuStats` from the scalar `cpu_load` column.
+/// reconstruct a minimal `CpuStats` from the scalar `cpu_load` / `cpu_temperature` columns.
 pub(in crate::history_repo) fn deserialize_cpu_data(
     bytes: Option<&[u8]>,
     fallback_usage_percent: f64,
-) -> CpuStats {
+    fallback_temperature: f64,
+) -> HistoryResult<CpuStats> {
+    let fallback = || CpuStats {
+        usage_percent: fallback_usage_percent,
+        temperature: fallback_temperature,
+        ..Default::default()
+    };
     match bytes {
-        Some(b) if !b.is_empty() => wincode::deserialize(blob::blob_payload(b, blob::BLOB_VERSION))
-            .unwrap_or_else(|e| {
-                tracing::debug!(error = %e, "wincode deserialize cpu (legacy/corrupt), using scalar fallback");
-                CpuStats {
-                    usage_percent: fallback_usage_percent,
-                    ..Default::default()
-                }
-            }),
-        _ => CpuStats {
-            usage_percent: fallback_usage_percent,
-            ..Default::default()
-        },
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "cpu_data", fallback),
+        _ => Ok(fallback()),
     }
 }
 
 /// Deserialize the optional `ram_data` blob. For legacy rows (NULL/empty) or corrupt data,
-/// reconstruct a minimal `RamStats` from the scalar `memory_used` column.
+/// reconstruct a minimal `RamStats` from the scalar `memory_used` / `memory_total` columns.
 pub(in crate::history_repo) fn deserialize_ram_data(
     bytes: Option<&[u8]>,
     fallback_used: u64,
-) -> RamStats {
+    fallback_total: u64,
+) -> HistoryResult<RamStats> {
+    let fallback = || ram_from_scalars(fallback_used, fallback_total);
     match bytes {
-        Some(b) if !b.is_empty() => wincode::deserialize(blob::blob_payload(b, blob::BLOB_VERSION))
-            .unwrap_or_else(|e| {
-                tracing::debug!(error = %e, "wincode deserialize ram (legacy/corrupt), using scalar fallback");
-                RamStats {
-                    used: fallback_used,
-                    ..Default::default()
-                }
-            }),
-        _ => RamStats {
-            used: fallback_used,
-            ..Default::default()
-        },
+        Some(b) if !b.is_empty() => decode_or(b, blob::BLOB_VERSION, "ram_data", fallback),
+        _ => Ok(fallback()),
+    }
+}
+
+/// Minimal `RamStats` from the scalar columns; `usage_percent` is 0 when total is unknown (0).
+fn ram_from_scalars(used: u64, total: u64) -> RamStats {
+    let usage_percent = if total > 0 {
+        used as f64 / total as f64 * 100.0
+    } else {
+        0.0
+    };
+    RamStats {
+        total,
+        used,
+        available: total.saturating_sub(used),
+        usage_percent,
+        ..Default::default()
     }
 }
 
@@ -114,28 +166,41 @@ pub(in crate::history_repo) fn aggregated_to_snapshot(
         system: agg.system,
         gpus: agg.gpus,
         smart: agg.smart,
+        probes: agg.probes,
+        sensors: agg.sensors,
+        checks: vec![],
+        degraded: vec![],
+        self_stats: None,
+        historical: false,
     }
 }
 
-pub(in crate::history_repo) fn downsample_snapshots(
-    snapshots: &[FullSystemSnapshot],
-    resolution_ms: i64,
-) -> Vec<FullSystemSnapshot> {
-    if snapshots.is_empty() || resolution_ms <= 0 {
-        return snapshots.to_vec();
-    }
-    let mut by_bucket: BTreeMap<i64, &FullSystemSnapshot> = BTreeMap::new();
-    for s in snapshots {
-        let bucket = (s.timestamp as i64 / resolution_ms) * resolution_ms;
-        by_bucket.insert(bucket, s);
+impl HistoryRepo {
+    /// Boundary between the aggregated tiers and raw rows for history reads:
+    /// `raw_retention_hours` before the newest local raw row, or before `now_ms` when there is
+    /// none (or it lies in the future). Anchored on the data rather than on the requested `to`,
+    /// so a window in the past is read from the aggregated tiers, and raw rows written before
+    /// downtime (when no aggregation pass ran) still count as raw.
+    pub async fn history_raw_cutoff(
+        &self,
+        now_ms: i64,
+        raw_retention_hours: u32,
+    ) -> HistoryResult<i64> {
+        self.retry_busy("history_raw_cutoff", || async move {
+            let newest = self
+                .get_max_raw_created_at()
+                .await?
+                .map_or(now_ms, |ts| ts.min(now_ms));
+            Ok(newest.saturating_sub(i64::from(raw_retention_hours) * 3_600_000))
+        })
+        .await
     }
-    by_bucket.into_values().cloned().collect()
-}
 
-impl HistoryRepo {
     /// History for API: merge raw (recent) + aggregated (older) by time range and resolution.
-    /// raw_cutoff_ts: timestamps >= this are read from raw table; older from aggregated (60 or 300s).
-    /// resolution_secs: 1, 30, 60, 300. Raw is downsampled to this if > 1.
+    /// raw_cutoff_ts: timestamps >= this are read from raw table; older from the aggregated tiers
+    /// (`database.aggregation_tiers`, picked by resolution and by how far back the range reaches).
+    /// resolution_secs: 1, 30, 60, 300, 3600, 86400. Raw is downsampled to this if > 1
+    /// (bucket averages; see [`DownsampleMode`]).
     #[instrument(skip(self), fields(repo = "history", operation = "get_history"))]
     pub async fn get_history(
         &self,
@@ -143,51 +208,56 @@ impl HistoryRepo {
         to_ts: i64,
         resolution_secs: u32,
         raw_cutoff_ts: i64,
-    ) -> anyhow::Result<Vec<FullSystemSnapshot>> {
-        let resolution_ms = (resolution_secs as i64) * 1000;
-
-        let mut raw = if to_ts > raw_cutoff_ts {
-            let raw_from = from_ts.max(raw_cutoff_ts);
-            self.get_raw_snapshots_by_time_range(raw_from, to_ts)
-                .await?
-        } else {
-            Vec::new()
-        };
-
-        if resolution_secs > 1 && !raw.is_empty() {
-            raw = downsample_snapshots(&raw, resolution_ms);
-        }
-
-        let agg_snapshots: Vec<FullSystemSnapshot> = if from_ts < raw_cutoff_ts {
-            let agg_to = to_ts.min(raw_cutoff_ts);
-            let agg_resolution = if resolution_secs >= 300 { 300 } else { 60 };
-            let aggs = self
-                .get_aggregated_snapshots_by_time_range(from_ts, agg_to, agg_resolution)
-                .await?;
-            aggs.into_iter().map(aggregated_to_snapshot).collect()
-        } else {
-            Vec::new()
-        };
-
-        let mut out = Vec::with_capacity(agg_snapshots.len() + raw.len());
-        out.extend(agg_snapshots);
-        out.extend(raw);
-        out.sort_by_key(|s| s.timestamp);
-        Ok(out)
+    ) -> HistoryResult<Vec<FullSystemSnapshot>> {
+        let points = self
+            .get_history_points(
+                from_ts,
+                to_ts,
+                resolution_secs,
+                raw_cutoff_ts,
+                DownsampleMode::default(),
+            )
+            .await?;
+        Ok(points.into_iter().map(|p| p.snapshot).collect())
     }
 
-    /// Reclaim space after deletes (run periodically after pruning).
-    #[instrument(skip(self), fields(repo = "history", operation = "vacuum"))]
-    pub async fn vacuum(&self) -> anyhow::Result<()> {
-        sqlx::query("VACUUM").execute(&self.pool).await?;
-        Ok(())
+    /// Same as [`Self::get_history`], with each point's min/max/p95 envelope and a choice of
+    /// how raw snapshots are downsampled. Uncapped; see [`Self::get_history_points_bounded`].
+    #[instrument(skip(self), fields(repo = "history", operation = "get_history_points"))]
+    pub async fn get_history_points(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+    ) -> HistoryResult<Vec<HistoryPoint>> {
+        let (points, _) = self
+            .get_history_points_bounded(
+                from_ts,
+                to_ts,
+                resolution_secs,
+                raw_cutoff_ts,
+                downsample,
+                usize::MAX,
+            )
+            .await?;
+        Ok(points)
     }
 
     /// Cheap liveness check: verifies a connection can be acquired and queried.
-    pub async fn ping(&self) -> anyhow::Result<()> {
+    pub async fn ping(&self) -> HistoryResult<()> {
         sqlx::query_scalar::<_, i64>("SELECT 1")
             .fetch_one(&self.pool)
             .await?;
         Ok(())
     }
+
+    /// Close every pooled connection, the writer's included. Later calls fail with a
+    /// `PoolClosed` error
+    /// ([`HistoryError::is_unavailable`](crate::history_repo::HistoryError::is_unavailable)).
+    pub async fn close(&self) {
+        self.pool.close().await;
+        self.writer.close().await;
+    }
 }
@@ -0,0 +1,195 @@
+// Bounded /api/history reads: the raw stretch plus the aggregated tiers that cover the range,
+// streamed per table / tier (history_stream.rs) and merged. Shared by both engines through
+// `RowSource`.
+
+use futures_util::stream::BoxStream;
+use sqlx::sqlite::SqliteRow;
+
+use crate::history_repo::agg_store::AGG_SELECT_RANGE;
+use crate::history_repo::envelope::merge_bucket;
+use crate::history_repo::history_stream::{
+    Bucketer, Reducer, Run, merge_runs, parse_aggregated, raw_run, stream_run,
+};
+use crate::history_repo::raw_read::RAW_SELECT_RANGE;
+use crate::history_repo::row::HistoryRow;
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult};
+use crate::models::HistoryPoint;
+
+/// Where the bounded history read streams its rows from: each engine's raw range and tier
+/// range queries ([`RAW_SELECT_RANGE`], [`AGG_SELECT_RANGE`]).
+pub(in crate::history_repo) trait RowSource: Sync {
+    type Row: HistoryRow;
+    /// Configured aggregated tier resolutions, finest first.
+    fn tiers(&self) -> Vec<i32>;
+    fn raw_rows(&self, from_ts: i64, to_ts: i64) -> BoxStream<'_, Result<Self::Row, sqlx::Error>>;
+    fn tier_rows(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        tier: i32,
+    ) -> BoxStream<'_, Result<Self::Row, sqlx::Error>>;
+}
+
+impl RowSource for HistoryRepo {
+    type Row = SqliteRow;
+
+    fn tiers(&self) -> Vec<i32> {
+        self.aggregation_tiers()
+    }
+
+    fn raw_rows(&self, from_ts: i64, to_ts: i64) -> BoxStream<'_, Result<SqliteRow, sqlx::Error>> {
+        sqlx::query(RAW_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .fetch(&self.pool)
+    }
+
+    fn tier_rows(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        tier: i32,
+    ) -> BoxStream<'_, Result<SqliteRow, sqlx::Error>> {
+        sqlx::query(AGG_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(tier)
+            .fetch(&self.pool)
+    }
+}
+
+/// Body of [`HistoryRepo::get_history_points_bounded`], over either engine's rows.
+pub(in crate::history_repo) async fn history_points_bounded<S: RowSource>(
+    source: &S,
+    from_ts: i64,
+    to_ts: i64,
+    resolution_secs: u32,
+    raw_cutoff_ts: i64,
+    downsample: DownsampleMode,
+    max_points: usize,
+) -> HistoryResult<(Vec<HistoryPoint>, bool)> {
+    let mut runs = if from_ts < raw_cutoff_ts {
+        let agg_to = to_ts.min(raw_cutoff_ts);
+        let tiers = source.tiers();
+        stream_aggregated_runs(source, &tiers, from_ts, agg_to, resolution_secs, max_points).await?
+    } else {
+        Vec::new()
+    };
+    if to_ts > raw_cutoff_ts {
+        let rows = source.raw_rows(from_ts.max(raw_cutoff_ts), to_ts);
+        runs.push(raw_run(rows, resolution_secs, downsample, max_points).await?);
+    }
+    Ok(merge_runs(runs, max_points))
+}
+
+/// Aggregated runs for the history range. Reads the coarsest configured tier not coarser than
+/// `resolution_secs`; older stretches already rolled into coarser tiers are filled from those,
+/// and the recent stretch not yet rolled up is filled from finer tiers (downsampled).
+async fn stream_aggregated_runs<S: RowSource>(
+    source: &S,
+    tiers: &[i32],
+    from_ts: i64,
+    to_ts: i64,
+    resolution_secs: u32,
+    max_points: usize,
+) -> HistoryResult<Vec<Vec<HistoryPoint>>> {
+    let chosen = tiers
+        .iter()
+        .rposition(|&r| r as u32 <= resolution_secs)
+        .unwrap_or(0);
+
+    let mut runs = Vec::new();
+    // Chosen tier, then coarser ones, walking back from `to_ts`.
+    let mut covered_from = to_ts;
+    let mut covered_to: Option<i64> = None;
+    for &tier in &tiers[chosen..] {
+        if from_ts >= covered_from {
+            break;
+        }
+        let run = stream_tier(
+            source,
+            from_ts,
+            covered_from,
+            tier,
+            resolution_secs,
+            max_points,
+        )
+        .await?;
+        if let (Some(first), Some(last)) = (run.first_ts, run.last_ts) {
+            covered_to.get_or_insert(last + (tier as i64) * 1000);
+            covered_from = first;
+        }
+        runs.push(run.points);
+    }
+    // Finer tiers, walking forward to `to_ts`.
+    let mut newer_from = covered_to.unwrap_or(from_ts);
+    for &tier in tiers[..chosen].iter().rev() {
+        if newer_from >= to_ts {
+            break;
+        }
+        let run = stream_tier(source, newer_from, to_ts, tier, resolution_secs, max_points).await?;
+        if let Some(last) = run.last_ts {
+            newer_from = last + (tier as i64) * 1000;
+        }
+        runs.push(run.points);
+    }
+    Ok(runs)
+}
+
+/// One tier's rows in `[from_ts, to_ts)` as points, merged per bucket when finer than
+/// `resolution_secs`.
+async fn stream_tier<S: RowSource>(
+    source: &S,
+    from_ts: i64,
+    to_ts: i64,
+    tier: i32,
+    resolution_secs: u32,
+    max_points: usize,
+) -> HistoryResult<Run> {
+    let resolution_ms = if resolution_secs > tier as u32 {
+        (resolution_secs as i64) * 1000
+    } else {
+        0
+    };
+    let reduce: Reducer<HistoryPoint> = if resolution_ms > 0 {
+        Box::new(|points, _| merge_bucket(points))
+    } else {
+        Box::new(|points, _| points.into_iter().next())
+    };
+    stream_run(
+        source.tier_rows(from_ts, to_ts, tier),
+        parse_aggregated,
+        Bucketer::new(resolution_ms, reduce),
+        max_points,
+    )
+    .await
+}
+
+impl HistoryRepo {
+    /// [`Self::get_history_points`] capped at `max_points`: rows are streamed and decoded in
+    /// fixed-size batches, so memory stays proportional to the returned points (plus one decode
+    /// batch and one open bucket) rather than to the rows scanned. The flag is true when points past the cap were dropped (the earliest
+    /// `max_points` are kept).
+    pub async fn get_history_points_bounded(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> HistoryResult<(Vec<HistoryPoint>, bool)> {
+        self.retry_busy("get_history_points_bounded", || {
+            history_points_bounded(
+                self,
+                from_ts,
+                to_ts,
+                resolution_secs,
+                raw_cutoff_ts,
+                downsample,
+                max_points,
+            )
+        })
+        .await
+    }
+}
@@ -0,0 +1,181 @@
+// Streamed /api/history reads: rows are fetched incrementally, decoded in fixed-size
+// `spawn_blocking` batches, bucketed on the fly, and the per-table runs k-way merged by timestamp.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use futures_util::TryStreamExt;
+use futures_util::stream::BoxStream;
+
+use crate::history_repo::downsample::reduce_raw_bucket;
+use crate::history_repo::envelope::{aggregated_point, raw_point};
+use crate::history_repo::row::HistoryRow;
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult};
+use crate::models::HistoryPoint;
+
+/// Rows decoded per `spawn_blocking` call; bounds how many undecoded rows are held at once.
+const HISTORY_DECODE_BATCH: usize = 512;
+
+pub(in crate::history_repo) type Reducer<T> =
+    Box<dyn Fn(Vec<T>, i64) -> Option<HistoryPoint> + Send>;
+
+/// Groups ascending items into `resolution_ms` buckets and reduces each closed bucket to a point.
+/// With `resolution_ms <= 0` every item is its own bucket.
+pub(in crate::history_repo) struct Bucketer<T> {
+    resolution_ms: i64,
+    open: Option<(i64, Vec<T>)>,
+    reduce: Reducer<T>,
+}
+
+impl<T> Bucketer<T> {
+    pub(in crate::history_repo) fn new(resolution_ms: i64, reduce: Reducer<T>) -> Self {
+        Self {
+            resolution_ms,
+            open: None,
+            reduce,
+        }
+    }
+
+    fn push(&mut self, ts: i64, item: T, out: &mut Vec<HistoryPoint>) {
+        if self.resolution_ms <= 0 {
+            out.extend((self.reduce)(vec![item], ts));
+            return;
+        }
+        let bucket = (ts / self.resolution_ms) * self.resolution_ms;
+        match &mut self.open {
+            Some((start, items)) if *start == bucket => items.push(item),
+            _ => {
+                self.finish(out);
+                self.open = Some((bucket, vec![item]));
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<HistoryPoint>) {
+        if let Some((start, items)) = self.open.take() {
+            out.extend((self.reduce)(items, start));
+        }
+    }
+}
+
+/// Points of one table/tier in ascending order, plus the first and last source row timestamps.
+pub(in crate::history_repo) struct Run {
+    pub(in crate::history_repo) points: Vec<HistoryPoint>,
+    pub(in crate::history_repo) first_ts: Option<i64>,
+    pub(in crate::history_repo) last_ts: Option<i64>,
+}
+
+/// Drain `rows` in [`HISTORY_DECODE_BATCH`]-sized batches, decoding and bucketing each batch on
+/// the blocking pool. Stops early once the run holds more than `max_points` points: later points
+/// cannot make it into the capped result.
+pub(in crate::history_repo) async fn stream_run<R: HistoryRow, T: Send + 'static>(
+    mut rows: BoxStream<'_, Result<R, sqlx::Error>>,
+    parse: fn(&R) -> HistoryResult<(i64, T)>,
+    mut bucketer: Bucketer<T>,
+    max_points: usize,
+) -> HistoryResult<Run> {
+    let mut run = Run {
+        points: Vec::new(),
+        first_ts: None,
+        last_ts: None,
+    };
+    let mut batch = Vec::with_capacity(HISTORY_DECODE_BATCH);
+    loop {
+        let next = rows.try_next().await?;
+        let done = next.is_none();
+        batch.extend(next);
+        if !done && batch.len() < HISTORY_DECODE_BATCH {
+            continue;
+        }
+        let rows_batch = std::mem::replace(&mut batch, Vec::with_capacity(HISTORY_DECODE_BATCH));
+        let (first, last) = (run.first_ts, run.last_ts);
+        let (b, points, first, last) = tokio::task::spawn_blocking(move || {
+            let (mut first, mut last) = (first, last);
+            let mut out = Vec::new();
+            for row in &rows_batch {
+                // Rows with a blob of unknown version are left out, not emitted empty.
+                let (ts, item) = match parse(row) {
+                    Ok(parsed) => parsed,
+                    Err(e) if e.is_unknown_blob_version() => continue,
+                    Err(e) => return Err(e),
+                };
+                first.get_or_insert(ts);
+                last = Some(ts);
+                bucketer.push(ts, item, &mut out);
+            }
+            if done {
+                bucketer.finish(&mut out);
+            }
+            HistoryResult::Ok((bucketer, out, first, last))
+        })
+        .await??;
+        bucketer = b;
+        run.points.extend(points);
+        (run.first_ts, run.last_ts) = (first, last);
+        if done || run.points.len() > max_points {
+            return Ok(run);
+        }
+    }
+}
+
+/// Raw `rows` as points: one per row, or reduced per `resolution_secs` bucket when coarser than
+/// a second.
+pub(in crate::history_repo) async fn raw_run<R: HistoryRow>(
+    rows: BoxStream<'_, Result<R, sqlx::Error>>,
+    resolution_secs: u32,
+    downsample: DownsampleMode,
+    max_points: usize,
+) -> HistoryResult<Vec<HistoryPoint>> {
+    let (bucket_ms, reduce): (i64, Reducer<_>) = if resolution_secs > 1 {
+        let resolution_ms = (resolution_secs as i64) * 1000;
+        let reduce = move |snaps, start| reduce_raw_bucket(snaps, start, resolution_ms, downsample);
+        (resolution_ms, Box::new(reduce))
+    } else {
+        (
+            0,
+            Box::new(|snaps: Vec<_>, _| snaps.into_iter().next().map(raw_point)),
+        )
+    };
+    let bucketer = Bucketer::new(bucket_ms, reduce);
+    Ok(stream_run(rows, parse_raw, bucketer, max_points)
+        .await?
+        .points)
+}
+
+/// Merge ascending runs by timestamp; ties keep run order. Returns at most `max_points` points and
+/// whether more were available.
+pub(in crate::history_repo) fn merge_runs(
+    runs: Vec<Vec<HistoryPoint>>,
+    max_points: usize,
+) -> (Vec<HistoryPoint>, bool) {
+    let total: usize = runs.iter().map(Vec::len).sum();
+    let mut iters: Vec<_> = runs.into_iter().map(|r| r.into_iter().peekable()).collect();
+    let mut heap = BinaryHeap::new();
+    for (i, it) in iters.iter_mut().enumerate() {
+        if let Some(p) = it.peek() {
+            heap.push(Reverse((p.snapshot.timestamp, i)));
+        }
+    }
+    let mut out = Vec::with_capacity(total.min(max_points));
+    while let Some(Reverse((_, i))) = heap.pop() {
+        if out.len() == max_points {
+            break;
+        }
+        let it = &mut iters[i];
+        out.extend(it.next());
+        if let Some(p) = it.peek() {
+            heap.push(Reverse((p.snapshot.timestamp, i)));
+        }
+    }
+    (out, total > max_points)
+}
+
+fn parse_raw<R: HistoryRow>(row: &R) -> HistoryResult<(i64, crate::models::FullSystemSnapshot)> {
+    HistoryRepo::parse_snapshot_row(row).map(|s| (s.timestamp as i64, s))
+}
+
+pub(in crate::history_repo) fn parse_aggregated<R: HistoryRow>(
+    row: &R,
+) -> HistoryResult<(i64, HistoryPoint)> {
+    HistoryRepo::parse_aggregated_row(row).map(|a| (a.created_at, aggregated_point(a)))
+}
@@ -0,0 +1,79 @@
+// Corruption detection: `PRAGMA quick_check` and the startup recovery path.
+
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult};
+
+impl HistoryRepo {
+    /// Run `PRAGMA quick_check`. Returns the problems it reports; empty means the file is sound.
+    /// A file too damaged to scan at all comes back as a single problem rather than an error.
+    #[instrument(skip(self), fields(repo = "history", operation = "integrity_check"))]
+    pub async fn integrity_check(&self) -> HistoryResult<Vec<String>> {
+        match sqlx::query_scalar::<_, String>("PRAGMA quick_check")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(HistoryError::from)
+        {
+            Ok(rows) => Ok(rows.into_iter().filter(|r| r != "ok").collect()),
+            Err(e) if e.is_corruption() => Ok(vec![e.to_string()]),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Open and `quick_check` the database. On corruption, fail with [`HistoryError::Corrupt`],
+    /// or with `recover_on_corruption` move the damaged file (and its `-wal` / `-shm`) aside as
+    /// `<path>.corrupt-<timestamp>` and start over with an empty database.
+    pub(in crate::history_repo) async fn open_checked(
+        config: &DatabaseConfig,
+    ) -> HistoryResult<Self> {
+        let problems = match Self::open(config).await {
+            Ok(repo) => {
+                let problems = repo.integrity_check().await?;
+                if problems.is_empty() {
+                    return Ok(repo);
+                }
+                repo.close().await;
+                problems
+            }
+            // Damage in the header or schema pages already fails the connection's setup pragmas.
+            Err(e) if e.is_corruption() => vec![e.to_string()],
+            Err(e) => return Err(e),
+        };
+        let details = problems.join("; ");
+        if !config.recover_on_corruption {
+            return Err(HistoryError::Corrupt(details));
+        }
+        let moved_to = quarantine(Path::new(&config.path))?;
+        tracing::error!(
+            path = %config.path,
+            moved_to = %moved_to.display(),
+            problems = %details,
+            "history database is corrupt; moved it aside and starting with an EMPTY database"
+        );
+        Self::open(config).await
+    }
+}
+
+/// Rename `path` and its `-wal` / `-shm` companions to `<name>.corrupt-<UTC timestamp>[-wal|-shm]`.
+/// Returns the new database path.
+fn quarantine(path: &Path) -> HistoryResult<PathBuf> {
+    let suffix = chrono::Utc::now().format(".corrupt-%Y%m%d-%H%M%S");
+    let mut target = path.as_os_str().to_owned();
+    target.push(suffix.to_string());
+    let target = PathBuf::from(target);
+    for companion in ["", "-wal", "-shm"] {
+        let mut from = path.as_os_str().to_owned();
+        from.push(companion);
+        let mut to = target.as_os_str().to_owned();
+        to.push(companion);
+        match std::fs::rename(&from, &to) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !companion.is_empty() => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(target)
+}
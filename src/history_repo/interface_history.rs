@@ -0,0 +1,25 @@
+// Per-interface network series for /api/history/network, cut from the merged history points.
+
+use crate::models::{HistoryPoint, InterfaceHistoryPoint};
+
+/// Rates and byte counters of interface `iface` at each of `points` (from
+/// [`HistoryStore::get_history_points_bounded`](super::HistoryStore::get_history_points_bounded),
+/// raw buckets averaged); points where the interface is missing are skipped.
+pub fn interface_series(points: &[HistoryPoint], iface: &str) -> Vec<InterfaceHistoryPoint> {
+    points
+        .iter()
+        .filter_map(|p| interface_point(p, iface))
+        .collect()
+}
+
+fn interface_point(point: &HistoryPoint, iface: &str) -> Option<InterfaceHistoryPoint> {
+    let s = &point.snapshot;
+    let i = s.network.interfaces.iter().find(|i| i.name == iface)?;
+    Some(InterfaceHistoryPoint {
+        timestamp: s.timestamp,
+        rx_bytes_per_sec: i.received_bytes_per_sec,
+        tx_bytes_per_sec: i.transmitted_bytes_per_sec,
+        rx_bytes: i.bytes_recv,
+        tx_bytes: i.bytes_sent,
+    })
+}
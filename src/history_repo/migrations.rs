@@ -0,0 +1,180 @@
+// Ordered, additive schema migrations and the runner that applies them.
+
+use super::container_inventory::{CREATE_CONTAINER_INVENTORY, CREATE_CONTAINER_INVENTORY_INDEX};
+use super::service_events::CREATE_SERVICE_EVENTS_V14;
+use super::top_containers::{
+    CREATE_CONTAINER_HISTORY, CREATE_CONTAINER_HISTORY_INDEX, CREATE_CONTAINER_HISTORY_TS_INDEX,
+};
+use super::{CURRENT_SCHEMA_VERSION, HistoryRepo, HistoryResult};
+
+/// Ordered, additive forward migrations. Entry `(v, statements)` migrates schema `v` → `v + 1`.
+/// Only data-preserving DDL (e.g. `ALTER TABLE ... ADD COLUMN`) belongs here.
+/// v2 → v3: add nullable `cpu_data` / `ram_data` blobs so full CPU/RAM detail is persisted;
+/// old rows keep NULL and are read via the scalar fallback.
+const MIGRATIONS: &[(u32, &[&str])] = &[
+    (
+        2,
+        &[
+            "ALTER TABLE system_history ADD COLUMN cpu_data BLOB",
+            "ALTER TABLE system_history ADD COLUMN ram_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN ram_data BLOB",
+        ],
+    ),
+    // v3 → v4: persist GPU metrics. Nullable; rows without it read as an empty GPU list.
+    (
+        3,
+        &[
+            "ALTER TABLE system_history ADD COLUMN gpu_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN gpu_data BLOB",
+        ],
+    ),
+    // v4 → v5: persist SMART disk health. Nullable; absent → empty list.
+    (
+        4,
+        &[
+            "ALTER TABLE system_history ADD COLUMN smart_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN smart_data BLOB",
+        ],
+    ),
+    // v5 → v6: RAM total and CPU temperature as queryable columns. Legacy rows read as 0.
+    (
+        5,
+        &[
+            "ALTER TABLE system_history ADD COLUMN memory_total INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history ADD COLUMN cpu_temperature REAL NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN memory_total_avg INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN memory_total_min INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN memory_total_max INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_temperature_avg REAL NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_temperature_min REAL NOT NULL DEFAULT 0",
+            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_temperature_max REAL NOT NULL DEFAULT 0",
+        ],
+    ),
+    // v6 → v7: p95 of CPU load / used memory per bucket. Nullable; legacy rows have no percentile.
+    (
+        6,
+        &[
+            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_load_p95 REAL",
+            "ALTER TABLE system_history_aggregated ADD COLUMN memory_used_p95 INTEGER",
+        ],
+    ),
+    // v7 → v8: storage/network sections deduplicated into `blob_store` (created in `init`) and
+    // referenced by hash. Existing rows keep NULL hashes and their inline blobs.
+    (
+        7,
+        &[
+            "ALTER TABLE system_history ADD COLUMN storage_hash BLOB",
+            "ALTER TABLE system_history ADD COLUMN network_hash BLOB",
+        ],
+    ),
+    // v8 → v9: one aggregated row per (created_at, resolution_seconds). Duplicates left by a
+    // roll-up interrupted between save and delete are dropped, keeping the latest write.
+    (
+        8,
+        &[
+            "DELETE FROM system_history_aggregated WHERE id NOT IN (SELECT MAX(id) FROM system_history_aggregated GROUP BY created_at, resolution_seconds)",
+            "DROP INDEX IF EXISTS idx_aggregated_created_at_resolution",
+            "CREATE UNIQUE INDEX idx_aggregated_created_at_resolution ON system_history_aggregated(created_at, resolution_seconds)",
+        ],
+    ),
+    // v9 → v10: raw sample count per bucket, used to weight roll-up averages. Legacy rows read 0.
+    (
+        9,
+        &[
+            "ALTER TABLE system_history_aggregated ADD COLUMN sample_count INTEGER NOT NULL DEFAULT 0",
+        ],
+    ),
+    // v10 → v11: source node of rows pushed by other instances (`POST /api/ingest`). Local rows,
+    // including every existing one, keep NULL.
+    (
+        10,
+        &[
+            "ALTER TABLE system_history ADD COLUMN node TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_history_node_created_at ON system_history(node, created_at)",
+        ],
+    ),
+    // v11 → v12: narrow per-container rows for top-consumer queries, filled from new flushes only.
+    (
+        11,
+        &[
+            CREATE_CONTAINER_HISTORY,
+            CREATE_CONTAINER_HISTORY_INDEX,
+            CREATE_CONTAINER_HISTORY_TS_INDEX,
+        ],
+    ),
+    // v12 → v13: first / last sighting per container id, filled from new flushes only.
+    (
+        12,
+        &[CREATE_CONTAINER_INVENTORY, CREATE_CONTAINER_INVENTORY_INDEX],
+    ),
+    // v13 → v14: persist probe results. Nullable; absent → empty list.
+    (
+        13,
+        &[
+            "ALTER TABLE system_history ADD COLUMN probe_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN probe_data BLOB",
+        ],
+    ),
+    // v14 → v15: HTTP check transitions in `service_events`, keyed by check name. `init` creates
+    // the table only after migrating, so an older database may not have it yet: create it in its
+    // previous shape first.
+    (
+        14,
+        &[
+            CREATE_SERVICE_EVENTS_V14,
+            "ALTER TABLE service_events ADD COLUMN name TEXT",
+            "ALTER TABLE service_events ADD COLUMN detail TEXT",
+        ],
+    ),
+    // v15 → v16: persist hwmon sensor readings. Nullable; absent → empty list.
+    (
+        15,
+        &[
+            "ALTER TABLE system_history ADD COLUMN sensor_data BLOB",
+            "ALTER TABLE system_history_aggregated ADD COLUMN sensor_data BLOB",
+        ],
+    ),
+];
+
+impl HistoryRepo {
+    /// Apply ordered, additive, data-preserving migrations from `from_version` up to
+    /// `CURRENT_SCHEMA_VERSION`. Each step runs in its own transaction (SQLite DDL is
+    /// transactional, so a crash mid-step rolls back cleanly and is retried next start).
+    /// If a step has no registered migration, falls back to a destructive purge.
+    pub(in crate::history_repo) async fn run_migrations(
+        &self,
+        from_version: u32,
+    ) -> HistoryResult<()> {
+        let mut version = from_version;
+        while version < CURRENT_SCHEMA_VERSION {
+            let Some((_, statements)) = MIGRATIONS.iter().find(|(v, _)| *v == version) else {
+                tracing::warn!(
+                    "no migration registered from schema v{}; purging history",
+                    version
+                );
+                let mut tx = self.writer.begin().await?;
+                Self::drop_history_user_tables(&mut tx).await?;
+                sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
+                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                return Ok(());
+            };
+            let mut tx = self.writer.begin().await?;
+            for stmt in *statements {
+                // `*stmt` is a `&'static str` from the MIGRATIONS table (not user input).
+                sqlx::query(*stmt).execute(&mut *tx).await?;
+            }
+            sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
+                .bind(i64::from(version + 1))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            tracing::info!("migrated history schema v{} -> v{}", version, version + 1);
+            version += 1;
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,107 @@
-// SQLite history. system_info table stores static SystemInfo once; merge when loading.
+// History storage: SQLite ([`HistoryRepo`]) or Postgres ([`PgHistoryRepo`]) behind [`HistoryStore`].
+// system_info table stores static SystemInfo once; merge when loading.
 
 mod agg_store;
 pub mod aggregation;
-mod blob;
+mod anomaly;
+mod backup;
+pub mod blob;
+pub mod blob_store;
+mod busy;
+mod container_inventory;
+mod diagnostics;
+mod downsample;
+mod envelope;
+mod error;
+mod export;
+mod free_space;
+mod handle;
+mod history_bounds;
 mod history_merge;
+mod history_points;
+mod history_stream;
+mod integrity;
+mod interface_history;
+mod migrations;
+mod nodes;
+mod partition_history;
+mod postgres;
 mod raw;
+mod raw_read;
+mod read_only;
+mod retention;
+mod rollup;
+mod row;
 mod schema;
+mod schema_version;
+mod service_events;
+mod service_history;
+mod sqlite_store;
+mod stats;
+mod store;
+mod sync;
+mod tier_stats;
+mod top_containers;
+mod vacuum;
+mod verify;
+mod wal;
+mod watermark;
 
-pub const CURRENT_SCHEMA_VERSION: u32 = 5;
+pub use anomaly::{AnomalyParams, detect_anomalies, detect_history_anomalies};
+pub use backup::{latest_backup, list_backups, prune_backups};
+pub use downsample::{DownsampleMode, lttb_indices, lttb_points};
+pub use error::{HistoryError, HistoryResult};
+pub use export::{EXPORT_FORMAT_VERSION, ImportReport};
+pub use free_space::{EmergencyPrune, FreeSpace, Statvfs};
+pub use handle::HistoryHandle;
+pub use interface_history::interface_series;
+pub use partition_history::{
+    FLAT_BYTES_PER_DAY, PartitionSample, linear_slope, partition_series, project_partition,
+};
+pub use postgres::{PG_SCHEMA_VERSION, PgHistoryRepo};
+pub use raw_read::MAX_SNAPSHOTS_SINCE;
+pub use retention::{RetentionPolicy, tier_rollup_after_ms};
+pub use service_history::ServiceHistory;
+pub use store::{HistoryStore, NodeHistory};
+pub use sync::DEFAULT_SYNC_LIMIT;
+pub use tier_stats::project_storage;
+pub use top_containers::TopContainerMetric;
+pub use vacuum::Fragmentation;
+pub use verify::{CorruptBlob, HistoryTable, VerifyReport};
+pub use wal::WalCheckpoint;
+
+pub const CURRENT_SCHEMA_VERSION: u32 = 16;
+
+use std::sync::atomic::AtomicI64;
 
 use sqlx::sqlite::SqlitePool;
 
 pub struct HistoryRepo {
+    /// Read connections (`database.max_pool_size`).
     pub(in crate::history_repo) pool: SqlitePool,
-    pub(in crate::history_repo) retention_ms: i64,
+    /// The one connection every write goes through, so the history writer, aggregation and
+    /// maintenance queue here instead of contending for SQLite's write lock.
+    pub(in crate::history_repo) writer: SqlitePool,
+    /// Per-tier row retention (tier settings, `aggregated_retention_days`). Raw retention is
+    /// `raw_retention_ms`, which a config reload can change.
+    pub(in crate::history_repo) retention: RetentionPolicy,
+    /// Raw row retention (`database.retention_days`); see [`Self::set_retention_days`].
+    pub(in crate::history_repo) raw_retention_ms: AtomicI64,
+    /// `collection_errors` retention (`database.error_retention_days`).
+    pub(in crate::history_repo) error_retention_ms: AtomicI64,
+    /// Containers per raw snapshot written to `container_history`
+    /// (`database.container_history_top_n`).
+    pub(in crate::history_repo) container_history_top_n: usize,
+    /// `container_history` retention (`database.container_history_retention_days`).
+    pub(in crate::history_repo) container_history_retention_ms: i64,
+    /// `container_inventory` retention (`database.container_inventory_retention_days`).
+    pub(in crate::history_repo) container_inventory_retention_ms: i64,
+    /// Write new blobs zstd-compressed (`database.compress_blobs`).
+    pub(in crate::history_repo) compress_blobs: bool,
+    /// Largest share of a table one prune pass may delete (`database.max_prune_fraction`).
+    pub(in crate::history_repo) max_prune_fraction: f64,
+    /// `database.path`, for [`Self::available_bytes`].
+    pub(in crate::history_repo) path: String,
+    /// [`Statvfs`] unless replaced by [`Self::with_free_space`].
+    pub(in crate::history_repo) free_space: std::sync::Arc<dyn FreeSpace>,
 }
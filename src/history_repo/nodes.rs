@@ -0,0 +1,110 @@
+// Rows pushed by other instances (`POST /api/ingest`): stored raw with their `node`, never rolled
+// up, pruned with the local raw rows, and read back per node for `/api/history?node=`.
+
+use std::collections::HashSet;
+
+use futures_util::future::BoxFuture;
+
+use crate::history_repo::history_stream::{merge_runs, raw_run};
+use crate::history_repo::raw_read::RAW_SELECT_NODE_RANGE;
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult, NodeHistory};
+use crate::models::{FullSystemSnapshot, HistoryPoint};
+use tracing::instrument;
+
+/// Timestamps a node already has stored in [$2, $3].
+pub(in crate::history_repo) const NODE_STORED_TIMESTAMPS: &str = "SELECT created_at FROM system_history WHERE node = $1 AND created_at >= $2 AND created_at <= $3";
+
+/// The snapshots whose timestamp is neither `existing` (already stored for the node) nor taken
+/// by an earlier one of the batch.
+pub(in crate::history_repo) fn unseen(
+    snapshots: &[FullSystemSnapshot],
+    existing: Vec<i64>,
+) -> Vec<FullSystemSnapshot> {
+    let mut seen: HashSet<u64> = existing.into_iter().map(|ts| ts as u64).collect();
+    snapshots
+        .iter()
+        .filter(|s| seen.insert(s.timestamp))
+        .cloned()
+        .collect()
+}
+
+impl HistoryRepo {
+    /// Store `snapshots` from `node`, skipping timestamps already stored for it, so a batch
+    /// re-sent after a lost response is not duplicated. Returns rows written.
+    #[instrument(skip(self, snapshots), fields(repo = "history", operation = "save_node_snapshots", snapshots_count = snapshots.len()))]
+    pub async fn save_node_snapshots(
+        &self,
+        node: &str,
+        snapshots: &[FullSystemSnapshot],
+    ) -> HistoryResult<u64> {
+        let (Some(min), Some(max)) = (
+            snapshots.iter().map(|s| s.timestamp).min(),
+            snapshots.iter().map(|s| s.timestamp).max(),
+        ) else {
+            return Ok(0);
+        };
+        let existing: Vec<i64> = sqlx::query_scalar(NODE_STORED_TIMESTAMPS)
+            .bind(node)
+            .bind(min as i64)
+            .bind(max as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        let fresh = unseen(snapshots, existing);
+        self.insert_snapshots(&fresh, None, Some(node)).await?;
+        Ok(fresh.len() as u64)
+    }
+
+    /// `node`'s raw rows in [from_ts, to_ts) as points, bucketed like the raw stretch of
+    /// [`Self::get_history_points_bounded`]; the flag is true when points past `max_points` were
+    /// dropped.
+    pub async fn get_node_history_points_bounded(
+        &self,
+        node: &str,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> HistoryResult<(Vec<HistoryPoint>, bool)> {
+        self.retry_busy("get_node_history_points_bounded", || async move {
+            let rows = sqlx::query(RAW_SELECT_NODE_RANGE)
+                .bind(from_ts)
+                .bind(to_ts)
+                .bind(node)
+                .fetch(&self.pool);
+            let run = raw_run(rows, resolution_secs, downsample, max_points).await?;
+            Ok(merge_runs(vec![run], max_points))
+        })
+        .await
+    }
+}
+
+impl NodeHistory for HistoryRepo {
+    fn save_node_snapshots<'a>(
+        &'a self,
+        node: &'a str,
+        snapshots: &'a [FullSystemSnapshot],
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::save_node_snapshots(self, node, snapshots))
+    }
+
+    fn get_node_history_points_bounded<'a>(
+        &'a self,
+        node: &'a str,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'a, HistoryResult<(Vec<HistoryPoint>, bool)>> {
+        Box::pin(HistoryRepo::get_node_history_points_bounded(
+            self,
+            node,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            downsample,
+            max_points,
+        ))
+    }
+}
@@ -0,0 +1,85 @@
+// Per-mount used-space series and the days-until-full projection for /api/storage/projection.
+// The series is cut from the merged history points; the fit and extrapolation are pure.
+
+use std::collections::BTreeMap;
+
+use crate::models::{HistoryPoint, PartitionProjection};
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// Growth below this many bytes per day counts as flat: no `days_until_full`.
+pub const FLAT_BYTES_PER_DAY: f64 = 1024.0 * 1024.0;
+
+/// One partition reading of a history point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartitionSample {
+    pub timestamp: u64,
+    pub used_space: u64,
+    pub total_space: u64,
+}
+
+/// Used and total space per mount at each of `points` (from
+/// [`HistoryStore::get_history_points`](super::HistoryStore::get_history_points), raw buckets
+/// averaged), oldest first, keyed by mount point.
+pub fn partition_series(points: &[HistoryPoint]) -> BTreeMap<String, Vec<PartitionSample>> {
+    let mut series: BTreeMap<String, Vec<PartitionSample>> = BTreeMap::new();
+    for point in points {
+        for p in &point.snapshot.storage.partitions {
+            series
+                .entry(p.mount.clone())
+                .or_default()
+                .push(PartitionSample {
+                    timestamp: point.snapshot.timestamp,
+                    used_space: p.used_space,
+                    total_space: p.total_space,
+                });
+        }
+    }
+    series
+}
+
+/// Least-squares slope of `y` over `x`; `None` with fewer than two points or when every `x` is
+/// the same.
+pub fn linear_slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (x, y) in points {
+        cov += (x - mean_x) * (y - mean_y);
+        var += (x - mean_x) * (x - mean_x);
+    }
+    (var > 0.0).then(|| cov / var)
+}
+
+/// The projection for `mount` from its samples (oldest first): the latest used / total space,
+/// the fitted growth in bytes per day, and the days left at that rate when it is above
+/// [`FLAT_BYTES_PER_DAY`]. `None` without samples.
+pub fn project_partition(
+    mount: String,
+    samples: &[PartitionSample],
+) -> Option<PartitionProjection> {
+    let last = samples.last()?;
+    let start = samples[0].timestamp;
+    let fit: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|s| {
+            let days = s.timestamp.saturating_sub(start) as f64 / MS_PER_DAY;
+            (days, s.used_space as f64)
+        })
+        .collect();
+    let bytes_per_day = linear_slope(&fit);
+    let days_until_full = bytes_per_day
+        .filter(|&rate| rate >= FLAT_BYTES_PER_DAY)
+        .map(|rate| last.total_space.saturating_sub(last.used_space) as f64 / rate);
+    Some(PartitionProjection {
+        mount,
+        used_space: last.used_space,
+        total_space: last.total_space,
+        bytes_per_day,
+        days_until_full,
+    })
+}
@@ -0,0 +1,163 @@
+// Postgres range bounds, aggregation watermarks and the pass clock, as in the SQLite
+// `raw.rs` / `rollup.rs` / `watermark.rs` / `history_bounds.rs` reads.
+
+use tracing::warn;
+
+use super::PgHistoryRepo;
+use crate::history_repo::HistoryResult;
+use crate::history_repo::watermark::LAST_PASS_CLOCK_KEY;
+
+impl PgHistoryRepo {
+    pub(super) async fn get_min_raw_created_at_before(
+        &self,
+        cutoff_ts: i64,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history WHERE node IS NULL AND created_at < $1",
+        )
+        .bind(cutoff_ts)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    pub(super) async fn get_min_raw_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at < $2",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    async fn get_max_raw_created_at(&self) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(created_at) FROM system_history WHERE node IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    pub(super) async fn get_min_aggregated_created_at_before(
+        &self,
+        cutoff_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history_aggregated WHERE created_at < $1 AND resolution_seconds = $2",
+        )
+        .bind(cutoff_ts)
+        .bind(resolution_seconds)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    pub(super) async fn get_min_aggregated_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history_aggregated WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .bind(resolution_seconds)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    /// See [`HistoryRepo::history_raw_cutoff`](crate::history_repo::HistoryRepo::history_raw_cutoff).
+    pub(super) async fn history_raw_cutoff(
+        &self,
+        now_ms: i64,
+        raw_retention_hours: u32,
+    ) -> HistoryResult<i64> {
+        let newest = self
+            .get_max_raw_created_at()
+            .await?
+            .map_or(now_ms, |ts| ts.min(now_ms));
+        Ok(newest.saturating_sub(i64::from(raw_retention_hours) * 3_600_000))
+    }
+
+    /// `(earliest, latest)` over local raw rows and the configured tiers.
+    pub(super) async fn get_history_bounds(&self) -> HistoryResult<(Option<i64>, Option<i64>)> {
+        let (mut earliest, mut latest): (Option<i64>, Option<i64>) = sqlx::query_as(
+            "SELECT MIN(created_at), MAX(created_at) FROM system_history WHERE node IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        for resolution in self.retention.resolutions() {
+            let (oldest, newest): (Option<i64>, Option<i64>) = sqlx::query_as(
+                "SELECT MIN(created_at), MAX(created_at) FROM system_history_aggregated
+                 WHERE resolution_seconds = $1",
+            )
+            .bind(resolution)
+            .fetch_one(&self.pool)
+            .await?;
+            earliest = earliest.into_iter().chain(oldest).min();
+            latest = latest.into_iter().chain(newest).max();
+        }
+        Ok((earliest, latest))
+    }
+
+    pub(super) async fn get_aggregation_watermark(
+        &self,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, i64>(
+            "SELECT watermark FROM aggregation_state WHERE resolution_seconds = $1",
+        )
+        .bind(resolution_seconds)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    /// See
+    /// [`HistoryRepo::clamp_aggregation_clock`](crate::history_repo::HistoryRepo::clamp_aggregation_clock).
+    pub(super) async fn clamp_aggregation_clock(&self, now_ms: i64) -> HistoryResult<i64> {
+        let mut conn = self.pool.acquire().await?;
+        let last = sqlx::query_scalar::<_, i64>(
+            "SELECT watermark FROM aggregation_state WHERE resolution_seconds = $1",
+        )
+        .bind(LAST_PASS_CLOCK_KEY)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match last {
+            Some(last) if last > now_ms => {
+                warn!(
+                    now_ms,
+                    last_pass_ms = last,
+                    "clock is behind the last aggregation pass; keeping the earlier cutoffs"
+                );
+                Ok(last)
+            }
+            _ => {
+                Self::set_aggregation_watermark(&mut conn, LAST_PASS_CLOCK_KEY, now_ms).await?;
+                Ok(now_ms)
+            }
+        }
+    }
+
+    pub(super) async fn aggregated_prune_cutoffs(
+        &self,
+        now_ms: i64,
+    ) -> HistoryResult<Vec<(i32, i64)>> {
+        self.retention
+            .prune_cutoffs(now_ms, |resolution| {
+                self.get_aggregation_watermark(resolution)
+            })
+            .await
+    }
+}
@@ -0,0 +1,157 @@
+// Postgres `container_history` and `container_inventory`: written with each local batch, read by
+// /api/history/top-containers, /api/containers/inventory and the usage report. Row building is
+// shared with the SQLite repo.
+
+use std::collections::HashMap;
+
+use sqlx::{AssertSqlSafe, Postgres, Transaction};
+
+use super::PgHistoryRepo;
+use crate::history_repo::HistoryResult;
+use crate::history_repo::container_inventory::{
+    InventoryRecord, InventoryRow, UPSERT_CHUNK_ROWS, inventory_entry, inventory_insert,
+    inventory_rows,
+};
+use crate::history_repo::top_containers::{
+    INSERT_CHUNK_ROWS, TopContainerMetric, TopContainerRecord, check_range,
+    container_history_insert, container_rows, top_container,
+};
+use crate::models::{ContainerInventoryEntry, FullSystemSnapshot, TopContainer};
+
+/// The SQLite upsert of `container_inventory` in Postgres terms: `previous_names` is edited as
+/// `jsonb`, MIN / MAX become LEAST / GREATEST.
+const UPSERT_CONFLICT: &str = " ON CONFLICT (container_id) DO UPDATE SET
+    previous_names = CASE
+        WHEN EXCLUDED.name = container_inventory.name
+            OR EXCLUDED.last_seen < container_inventory.last_seen
+            OR container_inventory.previous_names::jsonb ? container_inventory.name
+        THEN container_inventory.previous_names
+        ELSE (container_inventory.previous_names::jsonb
+              || to_jsonb(container_inventory.name))::text
+    END,
+    name = CASE WHEN EXCLUDED.last_seen >= container_inventory.last_seen
+        THEN EXCLUDED.name ELSE container_inventory.name END,
+    image = CASE WHEN EXCLUDED.image <> '' AND EXCLUDED.last_seen >= container_inventory.last_seen
+        THEN EXCLUDED.image ELSE container_inventory.image END,
+    last_state = CASE WHEN EXCLUDED.last_seen >= container_inventory.last_seen
+        THEN EXCLUDED.last_state ELSE container_inventory.last_state END,
+    first_seen = LEAST(container_inventory.first_seen, EXCLUDED.first_seen),
+    last_seen = GREATEST(container_inventory.last_seen, EXCLUDED.last_seen)";
+
+/// `rows` split so no statement upserts one container twice (Postgres refuses that, SQLite
+/// applies the rows in order): the n-th run of each container goes into the n-th group.
+fn upsert_rounds<'a, 'r>(rows: &'r [InventoryRow<'a>]) -> Vec<Vec<&'r InventoryRow<'a>>> {
+    let mut rounds: Vec<Vec<&InventoryRow<'_>>> = Vec::new();
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for row in rows {
+        let round = seen.entry(row.id).or_default();
+        if *round == rounds.len() {
+            rounds.push(Vec::new());
+        }
+        rounds[*round].push(row);
+        *round += 1;
+    }
+    rounds
+}
+
+impl PgHistoryRepo {
+    /// Write the `container_history_top_n` busiest containers of each snapshot and update
+    /// `container_inventory`, in the flush's transaction.
+    pub(super) async fn put_containers(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        snapshots: &[FullSystemSnapshot],
+    ) -> HistoryResult<()> {
+        let rows = container_rows(snapshots, self.container_history_top_n);
+        for chunk in rows.chunks(INSERT_CHUNK_ROWS) {
+            container_history_insert::<Postgres>(chunk)
+                .build()
+                .execute(&mut **tx)
+                .await?;
+        }
+        let rows = inventory_rows(snapshots);
+        for round in upsert_rounds(&rows) {
+            for chunk in round.chunks(UPSERT_CHUNK_ROWS) {
+                let mut qb = inventory_insert::<Postgres>(chunk);
+                qb.push(UPSERT_CONFLICT);
+                qb.build().execute(&mut **tx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Known containers, present ones first (by name), then the gone ones (most recently seen
+    /// first) when `include_gone`.
+    pub(super) async fn get_container_inventory(
+        &self,
+        include_gone: bool,
+    ) -> HistoryResult<Vec<ContainerInventoryEntry>> {
+        let rows: Vec<InventoryRecord> = sqlx::query_as(
+            "SELECT * FROM (
+                     SELECT container_id, name, image, first_seen, last_seen, last_state,
+                            previous_names,
+                            last_seen < (SELECT COALESCE(MAX(created_at), 0) FROM system_history
+                                         WHERE node IS NULL) AS gone
+                     FROM container_inventory) AS inventory
+                 WHERE $1 OR NOT gone
+                 ORDER BY gone, last_seen DESC, name",
+        )
+        .bind(include_gone)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(inventory_entry).collect())
+    }
+
+    /// Containers with the highest average `metric` over `container_history` rows in
+    /// [from_ts, to_ts), as on SQLite. Without an inventory entry a container is named as in its
+    /// latest row of the range.
+    pub(super) async fn get_top_containers(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        metric: TopContainerMetric,
+        limit: u32,
+    ) -> HistoryResult<Vec<TopContainer>> {
+        check_range(from_ts, to_ts)?;
+        let column = metric.column();
+        // `column` comes from a fixed list, not from user input.
+        let sql = format!(
+            "SELECT h.container_id,
+                    COALESCE((SELECT i.name FROM container_inventory i
+                              WHERE i.container_id = h.container_id),
+                             (SELECT l.name FROM container_history l
+                              WHERE l.container_id = h.container_id
+                                AND l.created_at >= $1 AND l.created_at < $2
+                              ORDER BY l.created_at DESC LIMIT 1)) AS name,
+                    MAX(h.created_at) AS latest,
+                    AVG({column})::DOUBLE PRECISION AS average,
+                    MAX({column})::DOUBLE PRECISION AS peak, COUNT(*) AS samples
+             FROM container_history h
+             WHERE h.created_at >= $1 AND h.created_at < $2
+             GROUP BY h.container_id
+             ORDER BY average DESC, name
+             LIMIT $3"
+        );
+        let rows: Vec<TopContainerRecord> = sqlx::query_as(AssertSqlSafe(sql))
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(top_container).collect())
+    }
+
+    /// Delete expired `container_history` and `container_inventory` rows (relative to `now_ms`).
+    /// Called from `prune_old_data`.
+    pub(super) async fn prune_containers(&self, now_ms: i64) -> HistoryResult<()> {
+        sqlx::query("DELETE FROM container_history WHERE created_at < $1")
+            .bind(now_ms - self.container_history_retention_ms)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM container_inventory WHERE last_seen < $1")
+            .bind(now_ms - self.container_inventory_retention_ms)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
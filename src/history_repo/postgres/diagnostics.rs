@@ -0,0 +1,127 @@
+// Postgres `collection_errors` (/api/errors) and `service_events` (/api/events), with the
+// semantics of the SQLite tables: a start after anything but a clean shutdown is a recovery.
+
+use std::sync::atomic::Ordering;
+
+use super::PgHistoryRepo;
+use crate::history_repo::HistoryResult;
+use crate::models::{CollectionError, ErrorSourceCount, ServiceEvent, ServiceEventKind};
+
+impl PgHistoryRepo {
+    pub(super) async fn record_error(&self, error: &CollectionError) -> HistoryResult<()> {
+        sqlx::query(
+            "INSERT INTO collection_errors (ts, source, message, suppressed) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(error.ts)
+        .bind(&error.source)
+        .bind(&error.message)
+        .bind(i64::try_from(error.suppressed).unwrap_or(i64::MAX))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub(super) async fn get_recent_errors(
+        &self,
+        limit: u32,
+    ) -> HistoryResult<Vec<CollectionError>> {
+        let rows: Vec<(i64, String, String, i64)> = sqlx::query_as(
+            "SELECT ts, source, message, suppressed FROM collection_errors
+             ORDER BY ts DESC, id DESC LIMIT $1",
+        )
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(ts, source, message, suppressed)| CollectionError {
+                ts,
+                source,
+                message,
+                suppressed: suppressed.max(0) as u64,
+            })
+            .collect())
+    }
+
+    pub(super) async fn error_counts_since(
+        &self,
+        since_ts: i64,
+    ) -> HistoryResult<Vec<ErrorSourceCount>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT source, SUM(1 + suppressed)::BIGINT FROM collection_errors
+             WHERE ts >= $1 GROUP BY source ORDER BY source",
+        )
+        .bind(since_ts)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(source, count)| ErrorSourceCount {
+                source,
+                count: count.max(0) as u64,
+            })
+            .collect())
+    }
+
+    /// Delete entries older than `database.error_retention_days` (relative to `now_ms`).
+    pub(super) async fn prune_collection_errors(&self, now_ms: i64) -> HistoryResult<u64> {
+        let r = sqlx::query("DELETE FROM collection_errors WHERE ts < $1")
+            .bind(now_ms - self.error_retention_ms.load(Ordering::Relaxed))
+            .execute(&self.pool)
+            .await?;
+        Ok(r.rows_affected())
+    }
+
+    pub(super) async fn record_service_start(&self, ts: i64) -> HistoryResult<ServiceEventKind> {
+        // Check rows carry a name; only starts and shutdowns tell how the last run ended.
+        let previous: Option<String> = sqlx::query_scalar(
+            "SELECT event FROM service_events WHERE name IS NULL ORDER BY id DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let event = match previous.as_deref() {
+            None | Some("clean_shutdown") => ServiceEventKind::Started,
+            Some(_) => ServiceEventKind::RecoveredAfterCrash,
+        };
+        self.insert_service_event(ts, event, None, None).await?;
+        Ok(event)
+    }
+
+    pub(super) async fn insert_service_event(
+        &self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: Option<&str>,
+        detail: Option<&str>,
+    ) -> HistoryResult<()> {
+        sqlx::query("INSERT INTO service_events (ts, event, name, detail) VALUES ($1, $2, $3, $4)")
+            .bind(ts)
+            .bind(event.as_str())
+            .bind(name)
+            .bind(detail)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The newest `limit` events, newest first; events this build does not know are skipped.
+    pub(super) async fn get_service_events(&self, limit: u32) -> HistoryResult<Vec<ServiceEvent>> {
+        let rows: Vec<(i64, String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT ts, event, name, detail FROM service_events ORDER BY id DESC LIMIT $1",
+        )
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(ts, event, name, detail)| {
+                Some(ServiceEvent {
+                    ts,
+                    event: ServiceEventKind::parse(&event)?,
+                    name,
+                    detail,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,86 @@
+// Postgres history store (`database.engine = "postgres"`): the raw and aggregated tables of the
+// SQLite schema with BIGINT timestamps and BYTEA blobs, behind the same `HistoryStore` trait.
+// Row decoding, blob encoding, bucketing and retention cutoffs are shared with the SQLite repo.
+// Maintenance of the SQLite file (VACUUM, WAL, backups, `/api/db`, the free-space guard) has no
+// Postgres counterpart; the server runs its own.
+
+mod bounds;
+mod containers;
+mod diagnostics;
+mod nodes;
+mod read;
+mod schema;
+mod service_history;
+mod store;
+mod write;
+
+use std::sync::atomic::AtomicI64;
+
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+use super::retention::MS_PER_DAY;
+use super::{HistoryError, HistoryResult, RetentionPolicy};
+use crate::config::DatabaseConfig;
+
+/// Version of the Postgres schema (its own lineage; SQLite's is `CURRENT_SCHEMA_VERSION`).
+pub const PG_SCHEMA_VERSION: u32 = 1;
+
+/// How long a caller waits for a free pooled connection before failing.
+const POOL_ACQUIRE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+pub struct PgHistoryRepo {
+    /// Every read and write (`database.max_pool_size`).
+    pool: PgPool,
+    /// Held by the writes that add or collect `blob_store` entries, as SQLite's single writer
+    /// connection is: a GC must not run between a batch finding a blob already stored and
+    /// committing the rows that reference it.
+    blob_lock: tokio::sync::Mutex<()>,
+    /// Per-tier row retention, as for SQLite.
+    retention: RetentionPolicy,
+    /// Raw row retention (`database.retention_days`); a config reload can change it.
+    raw_retention_ms: AtomicI64,
+    /// `collection_errors` retention (`database.error_retention_days`); reloadable too.
+    error_retention_ms: AtomicI64,
+    /// Containers per raw snapshot written to `container_history`
+    /// (`database.container_history_top_n`).
+    container_history_top_n: usize,
+    /// `container_history` retention (`database.container_history_retention_days`).
+    container_history_retention_ms: i64,
+    /// `container_inventory` retention (`database.container_inventory_retention_days`).
+    container_inventory_retention_ms: i64,
+    /// Write new blobs zstd-compressed (`database.compress_blobs`).
+    compress_blobs: bool,
+    /// Largest share of a table one prune pass may delete (`database.max_prune_fraction`).
+    max_prune_fraction: f64,
+}
+
+impl PgHistoryRepo {
+    /// Connect to `database.url` and create the tables that are missing. A schema written by a
+    /// newer build is refused with [`HistoryError::SchemaTooNew`].
+    pub async fn connect(config: &DatabaseConfig) -> HistoryResult<Self> {
+        let url = config.url.as_ref().ok_or_else(|| {
+            HistoryError::InvalidArgument("database.url is required for postgres".into())
+        })?;
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_pool_size)
+            .acquire_timeout(POOL_ACQUIRE_TIMEOUT)
+            .connect(url.expose())
+            .await?;
+        let repo = Self {
+            pool,
+            blob_lock: tokio::sync::Mutex::new(()),
+            retention: RetentionPolicy::from_config(config),
+            raw_retention_ms: AtomicI64::new(i64::from(config.retention_days) * MS_PER_DAY),
+            error_retention_ms: AtomicI64::new(i64::from(config.error_retention_days) * MS_PER_DAY),
+            container_history_top_n: config.container_history_top_n,
+            container_history_retention_ms: i64::from(config.container_history_retention_days)
+                * MS_PER_DAY,
+            container_inventory_retention_ms: i64::from(config.container_inventory_retention_days)
+                * MS_PER_DAY,
+            compress_blobs: config.compress_blobs,
+            max_prune_fraction: config.max_prune_fraction,
+        };
+        repo.init().await?;
+        Ok(repo)
+    }
+}
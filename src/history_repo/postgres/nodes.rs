@@ -0,0 +1,97 @@
+// Postgres rows pushed by other instances (`POST /api/ingest`): the SQLite repo's node-tagged
+// raw rows, deduplicated per node by timestamp and read back per node.
+
+use futures_util::future::BoxFuture;
+use sqlx::Postgres;
+
+use super::PgHistoryRepo;
+use crate::history_repo::history_stream::{merge_runs, raw_run};
+use crate::history_repo::nodes::{NODE_STORED_TIMESTAMPS, unseen};
+use crate::history_repo::raw::{RAW_INSERT_CHUNK_ROWS, encode_rows, raw_insert};
+use crate::history_repo::raw_read::RAW_SELECT_NODE_RANGE;
+use crate::history_repo::{DownsampleMode, HistoryResult, NodeHistory};
+use crate::models::{FullSystemSnapshot, HistoryPoint};
+
+impl PgHistoryRepo {
+    async fn save_node_snapshots(
+        &self,
+        node: &str,
+        snapshots: &[FullSystemSnapshot],
+    ) -> HistoryResult<u64> {
+        let (Some(min), Some(max)) = (
+            snapshots.iter().map(|s| s.timestamp).min(),
+            snapshots.iter().map(|s| s.timestamp).max(),
+        ) else {
+            return Ok(0);
+        };
+        let existing: Vec<i64> = sqlx::query_scalar(NODE_STORED_TIMESTAMPS)
+            .bind(node)
+            .bind(min as i64)
+            .bind(max as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        let fresh = unseen(snapshots, existing);
+        let written = fresh.len() as u64;
+        let compress = self.compress_blobs;
+        let (rows, shared) =
+            tokio::task::spawn_blocking(move || encode_rows(&fresh, compress)).await??;
+
+        let _blobs = self.blob_lock.lock().await;
+        let mut tx = self.pool.begin().await?;
+        Self::put_shared_blobs(&mut tx, &shared).await?;
+        for chunk in rows.chunks(RAW_INSERT_CHUNK_ROWS) {
+            let mut qb = raw_insert::<Postgres>(chunk, Some(node), "''::bytea");
+            qb.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    async fn get_node_history_points_bounded(
+        &self,
+        node: &str,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> HistoryResult<(Vec<HistoryPoint>, bool)> {
+        let rows = sqlx::query(RAW_SELECT_NODE_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(node)
+            .fetch(&self.pool);
+        let run = raw_run(rows, resolution_secs, downsample, max_points).await?;
+        Ok(merge_runs(vec![run], max_points))
+    }
+}
+
+impl NodeHistory for PgHistoryRepo {
+    fn save_node_snapshots<'a>(
+        &'a self,
+        node: &'a str,
+        snapshots: &'a [FullSystemSnapshot],
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::save_node_snapshots(self, node, snapshots))
+    }
+
+    fn get_node_history_points_bounded<'a>(
+        &'a self,
+        node: &'a str,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'a, HistoryResult<(Vec<HistoryPoint>, bool)>> {
+        Box::pin(PgHistoryRepo::get_node_history_points_bounded(
+            self,
+            node,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            downsample,
+            max_points,
+        ))
+    }
+}
@@ -0,0 +1,152 @@
+// Postgres reads: raw and aggregated rows (the SQLite SELECTs, run unchanged), sync pages and
+// the bounded history read.
+
+use futures_util::stream::BoxStream;
+use sqlx::postgres::PgRow;
+
+use super::PgHistoryRepo;
+use crate::history_repo::agg_store::AGG_SELECT_RANGE;
+use crate::history_repo::history_points::{RowSource, history_points_bounded};
+use crate::history_repo::raw_read::{
+    MAX_SNAPSHOTS_SINCE, RAW_SELECT_RANGE, RAW_SELECT_RECENT, RAW_SELECT_SINCE, parse_rows,
+};
+use crate::history_repo::sync::{SYNC_CURSOR_PROBE, SyncPlan, SyncProbe};
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult, blob};
+use crate::models::{
+    AggregatedSnapshot, FullSystemSnapshot, HistoryPoint, SyncBatch, SyncCursor, SystemInfo,
+};
+
+impl RowSource for PgHistoryRepo {
+    type Row = PgRow;
+
+    fn tiers(&self) -> Vec<i32> {
+        self.retention.resolutions().collect()
+    }
+
+    fn raw_rows(&self, from_ts: i64, to_ts: i64) -> BoxStream<'_, Result<PgRow, sqlx::Error>> {
+        sqlx::query(RAW_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .fetch(&self.pool)
+    }
+
+    fn tier_rows(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        tier: i32,
+    ) -> BoxStream<'_, Result<PgRow, sqlx::Error>> {
+        sqlx::query(AGG_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(tier)
+            .fetch(&self.pool)
+    }
+}
+
+impl PgHistoryRepo {
+    pub(super) async fn get_stored_system_info(&self) -> HistoryResult<Option<SystemInfo>> {
+        let data: Option<Vec<u8>> = sqlx::query_scalar("SELECT data FROM system_info WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(data) = data else {
+            return Ok(None);
+        };
+        Ok(Some(blob::decode_system_info(&data, "system_info.data")?))
+    }
+
+    pub(super) async fn get_recent_snapshots(
+        &self,
+        limit: u32,
+    ) -> HistoryResult<(Option<SystemInfo>, Vec<FullSystemSnapshot>)> {
+        let stored_info = self.get_stored_system_info().await?;
+        let rows = sqlx::query(RAW_SELECT_RECENT)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+        let mut out = parse_rows(&rows, HistoryRepo::parse_snapshot_row)?;
+        out.reverse();
+        Ok((stored_info, out))
+    }
+
+    pub(super) async fn get_snapshots_since(
+        &self,
+        since_ts: i64,
+        limit: u32,
+    ) -> HistoryResult<Vec<FullSystemSnapshot>> {
+        let rows = sqlx::query(RAW_SELECT_SINCE)
+            .bind(since_ts)
+            .bind(i64::from(limit.min(MAX_SNAPSHOTS_SINCE)))
+            .fetch_all(&self.pool)
+            .await?;
+        parse_rows(&rows, HistoryRepo::parse_snapshot_row)
+    }
+
+    pub(super) async fn get_sync_batch(
+        &self,
+        cursor: SyncCursor,
+        limit: u32,
+    ) -> HistoryResult<SyncBatch> {
+        let probe: SyncProbe = sqlx::query_as(SYNC_CURSOR_PROBE)
+            .bind(SyncPlan::probe_seq(cursor))
+            .fetch_one(&self.pool)
+            .await?;
+        let plan = SyncPlan::new(cursor, probe);
+        let (sql, from) = plan.query();
+        let rows = sqlx::query(sql)
+            .bind(from)
+            .bind(SyncPlan::fetch_limit(limit))
+            .fetch_all(&self.pool)
+            .await?;
+        plan.batch(cursor, rows, limit)
+    }
+
+    pub(super) async fn get_raw_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> HistoryResult<Vec<FullSystemSnapshot>> {
+        let rows = sqlx::query(RAW_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .fetch_all(&self.pool)
+            .await?;
+        parse_rows(&rows, HistoryRepo::parse_snapshot_row)
+    }
+
+    pub(super) async fn get_aggregated_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Vec<AggregatedSnapshot>> {
+        let rows = sqlx::query(AGG_SELECT_RANGE)
+            .bind(from_ts)
+            .bind(to_ts)
+            .bind(resolution_seconds)
+            .fetch_all(&self.pool)
+            .await?;
+        parse_rows(&rows, HistoryRepo::parse_aggregated_row)
+    }
+
+    pub(super) async fn get_history_points_bounded(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> HistoryResult<(Vec<HistoryPoint>, bool)> {
+        history_points_bounded(
+            self,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+            downsample,
+            max_points,
+        )
+        .await
+    }
+}
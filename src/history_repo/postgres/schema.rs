@@ -0,0 +1,148 @@
+// Postgres DDL: the SQLite tables the history store uses, with BIGINT timestamps, DOUBLE
+// PRECISION readings and BYTEA blobs. Column names match, so the shared SELECTs and INSERTs run
+// unchanged. Tables added after the first release are created on the next start, like the
+// columns below.
+
+use super::{PG_SCHEMA_VERSION, PgHistoryRepo};
+use crate::history_repo::{HistoryError, HistoryResult};
+
+const CREATE_TABLES: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS system_history (
+        id BIGSERIAL PRIMARY KEY,
+        created_at BIGINT NOT NULL,
+        cpu_load DOUBLE PRECISION NOT NULL,
+        memory_used BIGINT NOT NULL,
+        container_data BYTEA NOT NULL,
+        storage_data BYTEA NOT NULL,
+        network_data BYTEA NOT NULL,
+        system_data BYTEA NOT NULL,
+        cpu_data BYTEA,
+        ram_data BYTEA,
+        gpu_data BYTEA,
+        smart_data BYTEA,
+        probe_data BYTEA,
+        sensor_data BYTEA,
+        memory_total BIGINT NOT NULL DEFAULT 0,
+        cpu_temperature DOUBLE PRECISION NOT NULL DEFAULT 0,
+        storage_hash BYTEA,
+        network_hash BYTEA,
+        node TEXT
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_history_created_at ON system_history(created_at)",
+    // Node pushes and the `node IS NULL` reads of local history, as on SQLite.
+    "CREATE INDEX IF NOT EXISTS idx_history_node_created_at ON system_history(node, created_at)",
+    "CREATE INDEX IF NOT EXISTS idx_history_storage_hash ON system_history(storage_hash)",
+    "CREATE INDEX IF NOT EXISTS idx_history_network_hash ON system_history(network_hash)",
+    "CREATE TABLE IF NOT EXISTS blob_store (hash BYTEA PRIMARY KEY, data BYTEA NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS system_info (id INTEGER PRIMARY KEY CHECK (id = 1), data BYTEA NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS system_history_aggregated (
+        id BIGSERIAL PRIMARY KEY,
+        created_at BIGINT NOT NULL,
+        resolution_seconds INTEGER NOT NULL,
+        cpu_load_avg DOUBLE PRECISION NOT NULL,
+        cpu_load_min DOUBLE PRECISION,
+        cpu_load_max DOUBLE PRECISION,
+        memory_used_avg BIGINT NOT NULL,
+        memory_used_min BIGINT,
+        memory_used_max BIGINT,
+        container_data BYTEA NOT NULL,
+        storage_data BYTEA NOT NULL,
+        network_data BYTEA NOT NULL,
+        system_data BYTEA NOT NULL,
+        cpu_data BYTEA,
+        ram_data BYTEA,
+        gpu_data BYTEA,
+        smart_data BYTEA,
+        probe_data BYTEA,
+        sensor_data BYTEA,
+        memory_total_avg BIGINT NOT NULL DEFAULT 0,
+        memory_total_min BIGINT NOT NULL DEFAULT 0,
+        memory_total_max BIGINT NOT NULL DEFAULT 0,
+        cpu_temperature_avg DOUBLE PRECISION NOT NULL DEFAULT 0,
+        cpu_temperature_min DOUBLE PRECISION NOT NULL DEFAULT 0,
+        cpu_temperature_max DOUBLE PRECISION NOT NULL DEFAULT 0,
+        cpu_load_p95 DOUBLE PRECISION,
+        memory_used_p95 BIGINT,
+        sample_count BIGINT NOT NULL DEFAULT 0
+    )",
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_aggregated_created_at_resolution ON system_history_aggregated(created_at, resolution_seconds)",
+    "CREATE TABLE IF NOT EXISTS aggregation_state (resolution_seconds INTEGER PRIMARY KEY, watermark BIGINT NOT NULL)",
+    // Added after the first release: tables created before it gain the nullable column (older
+    // servers keep working, they just leave it NULL). The service tables below came later still;
+    // older servers ignore them.
+    "ALTER TABLE system_history ADD COLUMN IF NOT EXISTS probe_data BYTEA",
+    "ALTER TABLE system_history_aggregated ADD COLUMN IF NOT EXISTS probe_data BYTEA",
+    "ALTER TABLE system_history ADD COLUMN IF NOT EXISTS sensor_data BYTEA",
+    "ALTER TABLE system_history_aggregated ADD COLUMN IF NOT EXISTS sensor_data BYTEA",
+    "CREATE TABLE IF NOT EXISTS collection_errors (
+        id BIGSERIAL PRIMARY KEY,
+        ts BIGINT NOT NULL,
+        source TEXT NOT NULL,
+        message TEXT NOT NULL,
+        suppressed BIGINT NOT NULL DEFAULT 0
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_collection_errors_ts ON collection_errors(ts)",
+    "CREATE TABLE IF NOT EXISTS service_events (
+        id BIGSERIAL PRIMARY KEY,
+        ts BIGINT NOT NULL,
+        event TEXT NOT NULL,
+        name TEXT,
+        detail TEXT
+    )",
+    "CREATE TABLE IF NOT EXISTS container_history (
+        created_at BIGINT NOT NULL,
+        container_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        cpu_percent DOUBLE PRECISION NOT NULL,
+        mem_bytes BIGINT NOT NULL,
+        rx_rate DOUBLE PRECISION NOT NULL,
+        tx_rate DOUBLE PRECISION NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_container_history_id_created_at ON container_history(container_id, created_at)",
+    "CREATE INDEX IF NOT EXISTS idx_container_history_created_at ON container_history(created_at)",
+    // `previous_names` is a JSON array as text, as on SQLite.
+    "CREATE TABLE IF NOT EXISTS container_inventory (
+        container_id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        image TEXT NOT NULL,
+        first_seen BIGINT NOT NULL,
+        last_seen BIGINT NOT NULL,
+        last_state TEXT NOT NULL,
+        previous_names TEXT NOT NULL DEFAULT '[]'
+    )",
+    "CREATE INDEX IF NOT EXISTS idx_container_inventory_last_seen ON container_inventory(last_seen)",
+];
+
+impl PgHistoryRepo {
+    /// Create the schema (idempotent) and record [`PG_SCHEMA_VERSION`], in one transaction.
+    pub async fn init(&self) -> HistoryResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS schema_version (key TEXT PRIMARY KEY, value BIGINT NOT NULL)",
+        )
+        .execute(&mut *tx)
+        .await?;
+        let found: Option<i64> =
+            sqlx::query_scalar("SELECT value FROM schema_version WHERE key = 'schema'")
+                .fetch_optional(&mut *tx)
+                .await?;
+        if let Some(found) = found.filter(|&v| v > i64::from(PG_SCHEMA_VERSION)) {
+            return Err(HistoryError::SchemaTooNew {
+                found,
+                supported: PG_SCHEMA_VERSION,
+            });
+        }
+        for ddl in CREATE_TABLES {
+            sqlx::query(*ddl).execute(&mut *tx).await?;
+        }
+        sqlx::query(
+            "INSERT INTO schema_version (key, value) VALUES ('schema', $1)
+             ON CONFLICT (key) DO NOTHING",
+        )
+        .bind(i64::from(PG_SCHEMA_VERSION))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+// `ServiceHistory` for the Postgres repo: every method is the inherent one of the same name.
+
+use futures_util::future::BoxFuture;
+
+use super::PgHistoryRepo;
+use crate::history_repo::{HistoryResult, ServiceHistory, TopContainerMetric};
+use crate::models::{
+    CollectionError, ContainerInventoryEntry, ErrorSourceCount, ServiceEvent, ServiceEventKind,
+    TopContainer,
+};
+
+impl ServiceHistory for PgHistoryRepo {
+    fn record_error<'a>(&'a self, error: &'a CollectionError) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(PgHistoryRepo::record_error(self, error))
+    }
+
+    fn get_recent_errors(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<CollectionError>>> {
+        Box::pin(PgHistoryRepo::get_recent_errors(self, limit))
+    }
+
+    fn error_counts_since(
+        &self,
+        since_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ErrorSourceCount>>> {
+        Box::pin(PgHistoryRepo::error_counts_since(self, since_ts))
+    }
+
+    fn record_service_start(&self, ts: i64) -> BoxFuture<'_, HistoryResult<ServiceEventKind>> {
+        Box::pin(PgHistoryRepo::record_service_start(self, ts))
+    }
+
+    fn record_clean_shutdown(&self, ts: i64) -> BoxFuture<'_, HistoryResult<()>> {
+        Box::pin(self.insert_service_event(ts, ServiceEventKind::CleanShutdown, None, None))
+    }
+
+    fn record_check_event<'a>(
+        &'a self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: &'a str,
+        detail: Option<&'a str>,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(self.insert_service_event(ts, event, Some(name), detail))
+    }
+
+    fn get_service_events(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<ServiceEvent>>> {
+        Box::pin(PgHistoryRepo::get_service_events(self, limit))
+    }
+
+    fn get_container_inventory(
+        &self,
+        include_gone: bool,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ContainerInventoryEntry>>> {
+        Box::pin(PgHistoryRepo::get_container_inventory(self, include_gone))
+    }
+
+    fn get_top_containers(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        metric: TopContainerMetric,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<TopContainer>>> {
+        Box::pin(PgHistoryRepo::get_top_containers(
+            self, from_ts, to_ts, metric, limit,
+        ))
+    }
+}
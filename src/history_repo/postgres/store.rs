@@ -0,0 +1,264 @@
+// `HistoryStore` for the Postgres repo: every method is the inherent one of the same name.
+
+use std::sync::atomic::Ordering;
+
+use futures_util::future::BoxFuture;
+
+use super::PgHistoryRepo;
+use crate::history_repo::retention::MS_PER_DAY;
+use crate::history_repo::{DownsampleMode, HistoryResult, HistoryStore};
+use crate::models::{
+    AggregatedSnapshot, FullSystemSnapshot, HistoryPoint, SyncBatch, SyncCursor, SystemInfo,
+};
+
+impl HistoryStore for PgHistoryRepo {
+    fn engine(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn ping(&self) -> BoxFuture<'_, HistoryResult<()>> {
+        Box::pin(async move {
+            sqlx::query_scalar::<_, i32>("SELECT 1")
+                .fetch_one(&self.pool)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn close(&self) -> BoxFuture<'_, ()> {
+        Box::pin(self.pool.close())
+    }
+
+    fn save_snapshots<'a>(
+        &'a self,
+        snapshots: &'a [FullSystemSnapshot],
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::save_snapshots(self, snapshots, system_info))
+    }
+
+    fn save_system_info<'a>(
+        &'a self,
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(PgHistoryRepo::save_system_info(self, system_info))
+    }
+
+    fn get_stored_system_info(&self) -> BoxFuture<'_, HistoryResult<Option<SystemInfo>>> {
+        Box::pin(PgHistoryRepo::get_stored_system_info(self))
+    }
+
+    fn get_recent_snapshots(
+        &self,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<(Option<SystemInfo>, Vec<FullSystemSnapshot>)>> {
+        Box::pin(PgHistoryRepo::get_recent_snapshots(self, limit))
+    }
+
+    fn get_snapshots_since(
+        &self,
+        since_ts: i64,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(PgHistoryRepo::get_snapshots_since(self, since_ts, limit))
+    }
+
+    fn get_sync_batch(
+        &self,
+        cursor: SyncCursor,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<SyncBatch>> {
+        Box::pin(PgHistoryRepo::get_sync_batch(self, cursor, limit))
+    }
+
+    fn get_raw_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(PgHistoryRepo::get_raw_snapshots_by_time_range(
+            self, from_ts, to_ts,
+        ))
+    }
+
+    fn get_min_raw_created_at_before(
+        &self,
+        cutoff_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(PgHistoryRepo::get_min_raw_created_at_before(
+            self, cutoff_ts,
+        ))
+    }
+
+    fn get_min_raw_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(PgHistoryRepo::get_min_raw_created_at_in(
+            self, from_ts, to_ts,
+        ))
+    }
+
+    fn history_raw_cutoff(
+        &self,
+        now_ms: i64,
+        raw_retention_hours: u32,
+    ) -> BoxFuture<'_, HistoryResult<i64>> {
+        Box::pin(PgHistoryRepo::history_raw_cutoff(
+            self,
+            now_ms,
+            raw_retention_hours,
+        ))
+    }
+
+    fn get_history_points_bounded(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'_, HistoryResult<(Vec<HistoryPoint>, bool)>> {
+        Box::pin(PgHistoryRepo::get_history_points_bounded(
+            self,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+            downsample,
+            max_points,
+        ))
+    }
+
+    fn get_history_bounds(&self) -> BoxFuture<'_, HistoryResult<(Option<i64>, Option<i64>)>> {
+        Box::pin(PgHistoryRepo::get_history_bounds(self))
+    }
+
+    fn save_aggregated_snapshot<'a>(
+        &'a self,
+        agg: &'a AggregatedSnapshot,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(PgHistoryRepo::save_aggregated_snapshot(self, agg))
+    }
+
+    fn get_aggregated_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<AggregatedSnapshot>>> {
+        Box::pin(PgHistoryRepo::get_aggregated_snapshots_by_time_range(
+            self,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_min_aggregated_created_at_before(
+        &self,
+        cutoff_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(PgHistoryRepo::get_min_aggregated_created_at_before(
+            self,
+            cutoff_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_min_aggregated_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(PgHistoryRepo::get_min_aggregated_created_at_in(
+            self,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_aggregation_watermark(
+        &self,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(PgHistoryRepo::get_aggregation_watermark(
+            self,
+            resolution_seconds,
+        ))
+    }
+
+    fn clamp_aggregation_clock(&self, now_ms: i64) -> BoxFuture<'_, HistoryResult<i64>> {
+        Box::pin(PgHistoryRepo::clamp_aggregation_clock(self, now_ms))
+    }
+
+    fn roll_up_raw_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::roll_up_raw_range(
+            self,
+            aggs,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn roll_up_aggregated_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        from_resolution: i32,
+        to_resolution: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::roll_up_aggregated_range(
+            self,
+            aggs,
+            from_ts,
+            to_ts,
+            from_resolution,
+            to_resolution,
+        ))
+    }
+
+    fn aggregated_prune_cutoffs(
+        &self,
+        now_ms: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<(i32, i64)>>> {
+        Box::pin(PgHistoryRepo::aggregated_prune_cutoffs(self, now_ms))
+    }
+
+    fn prune_aggregated_old_data<'a>(
+        &'a self,
+        cutoffs: &'a [(i32, i64)],
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::prune_aggregated_old_data(self, cutoffs))
+    }
+
+    fn prune_old_data(&self) -> BoxFuture<'_, HistoryResult<u64>> {
+        Box::pin(PgHistoryRepo::prune_old_data(self))
+    }
+
+    fn set_retention_days(&self, retention_days: u32, error_retention_days: u32) {
+        self.raw_retention_ms
+            .store(i64::from(retention_days) * MS_PER_DAY, Ordering::Relaxed);
+        self.error_retention_ms.store(
+            i64::from(error_retention_days) * MS_PER_DAY,
+            Ordering::Relaxed,
+        );
+    }
+
+    fn aggregation_tiers(&self) -> Vec<i32> {
+        self.retention.resolutions().collect()
+    }
+}
@@ -0,0 +1,264 @@
+// Postgres writes: raw batches (with their container history and inventory), the stored
+// SystemInfo, aggregated rows, atomic roll-ups and pruning.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+use sqlx::{Postgres, QueryBuilder, Transaction};
+
+use super::PgHistoryRepo;
+use crate::history_repo::agg_store::{AGG_INSERT, AggregatedBlobs, bind_aggregated};
+use crate::history_repo::raw::{EncodedRow, RAW_INSERT_CHUNK_ROWS, encode_rows, raw_insert};
+use crate::history_repo::retention::prune_allowed;
+use crate::history_repo::{HistoryError, HistoryResult};
+use crate::models::{AggregatedSnapshot, FullSystemSnapshot, SystemInfo};
+
+/// `blob_store` rows per INSERT (two binds each).
+const SHARED_BLOB_CHUNK_ROWS: usize = 999 / 2;
+
+const UPSERT_SYSTEM_INFO: &str = "INSERT INTO system_info (id, data) VALUES (1, $1)
+     ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data";
+
+fn encode_system_info(system_info: &SystemInfo) -> HistoryResult<Vec<u8>> {
+    wincode::serialize(system_info)
+        .map_err(|e| HistoryError::BlobEncode(format!("wincode system_info: {e}")))
+}
+
+impl PgHistoryRepo {
+    /// Append local snapshots, their `container_history` / `container_inventory` rows and replace
+    /// the stored `SystemInfo` in one transaction; blobs are encoded on the blocking pool.
+    /// Returns the encoded blob bytes of the batch.
+    pub(super) async fn save_snapshots(
+        &self,
+        snapshots: &[FullSystemSnapshot],
+        system_info: &SystemInfo,
+    ) -> HistoryResult<u64> {
+        let info_blob = encode_system_info(system_info)?;
+        let owned = snapshots.to_vec();
+        let compress = self.compress_blobs;
+        let (rows, shared) =
+            tokio::task::spawn_blocking(move || encode_rows(&owned, compress)).await??;
+        let bytes = rows.iter().map(EncodedRow::blob_bytes).sum::<usize>()
+            + shared.values().map(Vec::len).sum::<usize>();
+
+        let _blobs = self.blob_lock.lock().await;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(UPSERT_SYSTEM_INFO)
+            .bind(info_blob)
+            .execute(&mut *tx)
+            .await?;
+        Self::put_shared_blobs(&mut tx, &shared).await?;
+        self.put_containers(&mut tx, snapshots).await?;
+        for chunk in rows.chunks(RAW_INSERT_CHUNK_ROWS) {
+            let mut qb = raw_insert::<Postgres>(chunk, None, "''::bytea");
+            qb.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(bytes as u64)
+    }
+
+    pub(super) async fn save_system_info(&self, system_info: &SystemInfo) -> HistoryResult<()> {
+        sqlx::query(UPSERT_SYSTEM_INFO)
+            .bind(encode_system_info(system_info)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Store each `(hash, bytes)` entry once; existing hashes are left alone.
+    pub(super) async fn put_shared_blobs(
+        tx: &mut Transaction<'_, Postgres>,
+        blobs: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> HistoryResult<()> {
+        let entries: Vec<_> = blobs.iter().collect();
+        for chunk in entries.chunks(SHARED_BLOB_CHUNK_ROWS) {
+            let mut qb = QueryBuilder::<Postgres>::new("INSERT INTO blob_store (hash, data) ");
+            qb.push_values(chunk, |mut b, (hash, data)| {
+                b.push_bind(hash.as_slice()).push_bind(data.as_slice());
+            });
+            qb.push(" ON CONFLICT (hash) DO NOTHING");
+            qb.build().execute(&mut **tx).await?;
+        }
+        Ok(())
+    }
+
+    /// Write `agg`, replacing the row of its `(created_at, resolution_seconds)` bucket.
+    async fn insert_aggregated(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        agg: &AggregatedSnapshot,
+    ) -> HistoryResult<()> {
+        let blobs = AggregatedBlobs::encode(agg, self.compress_blobs)?;
+        sqlx::query(
+            "DELETE FROM system_history_aggregated WHERE created_at = $1 AND resolution_seconds = $2",
+        )
+        .bind(agg.created_at)
+        .bind(agg.resolution_seconds)
+        .execute(&mut **tx)
+        .await?;
+        bind_aggregated(sqlx::query(AGG_INSERT), agg, blobs)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    pub(super) async fn save_aggregated_snapshot(
+        &self,
+        agg: &AggregatedSnapshot,
+    ) -> HistoryResult<()> {
+        let mut tx = self.pool.begin().await?;
+        self.insert_aggregated(&mut tx, agg).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub(super) async fn set_aggregation_watermark(
+        conn: &mut sqlx::PgConnection,
+        resolution_seconds: i32,
+        watermark: i64,
+    ) -> HistoryResult<()> {
+        sqlx::query(
+            "INSERT INTO aggregation_state (resolution_seconds, watermark) VALUES ($1, $2)
+             ON CONFLICT (resolution_seconds) DO UPDATE SET watermark = EXCLUDED.watermark",
+        )
+        .bind(resolution_seconds)
+        .bind(watermark)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    pub(super) async fn roll_up_raw_range(
+        &self,
+        aggs: &[AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<u64> {
+        let _blobs = self.blob_lock.lock().await;
+        let mut tx = self.pool.begin().await?;
+        for agg in aggs {
+            self.insert_aggregated(&mut tx, agg).await?;
+        }
+        let r = sqlx::query(
+            "DELETE FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at < $2",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .execute(&mut *tx)
+        .await?;
+        Self::set_aggregation_watermark(&mut tx, resolution_seconds, to_ts).await?;
+        tx.commit().await?;
+        if r.rows_affected() > 0 {
+            self.gc_blob_store().await?;
+        }
+        Ok(r.rows_affected())
+    }
+
+    pub(super) async fn roll_up_aggregated_range(
+        &self,
+        aggs: &[AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        from_resolution: i32,
+        to_resolution: i32,
+    ) -> HistoryResult<u64> {
+        let mut tx = self.pool.begin().await?;
+        for agg in aggs {
+            self.insert_aggregated(&mut tx, agg).await?;
+        }
+        let r = sqlx::query(
+            "DELETE FROM system_history_aggregated WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .bind(from_resolution)
+        .execute(&mut *tx)
+        .await?;
+        Self::set_aggregation_watermark(&mut tx, to_resolution, to_ts).await?;
+        tx.commit().await?;
+        Ok(r.rows_affected())
+    }
+
+    /// Delete raw rows older than `retention_days` (within `max_prune_fraction`), unreferenced
+    /// `blob_store` entries and expired `collection_errors` / `container_history` /
+    /// `container_inventory` rows. Returns raw rows removed.
+    pub(super) async fn prune_old_data(&self) -> HistoryResult<u64> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis() as i64;
+        let cutoff = now_ms - self.raw_retention_ms.load(Ordering::Relaxed);
+        let (total, doomed): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COUNT(*) FILTER (WHERE created_at < $1) FROM system_history",
+        )
+        .bind(cutoff)
+        .fetch_one(&self.pool)
+        .await?;
+        let _blobs = self.blob_lock.lock().await;
+        let mut removed = 0;
+        if prune_allowed("system_history", doomed, total, self.max_prune_fraction) {
+            removed = sqlx::query("DELETE FROM system_history WHERE created_at < $1")
+                .bind(cutoff)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        }
+        self.gc_blob_store().await?;
+        self.prune_collection_errors(now_ms).await?;
+        self.prune_containers(now_ms).await?;
+        Ok(removed)
+    }
+
+    /// Delete aggregated rows older than their tier's cutoff. Returns rows removed.
+    pub(super) async fn prune_aggregated_old_data(
+        &self,
+        cutoffs: &[(i32, i64)],
+    ) -> HistoryResult<u64> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_history_aggregated")
+            .fetch_one(&self.pool)
+            .await?;
+        let mut doomed = 0;
+        for &(resolution, cutoff) in cutoffs {
+            doomed += sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM system_history_aggregated WHERE resolution_seconds = $1 AND created_at < $2",
+            )
+            .bind(resolution)
+            .bind(cutoff)
+            .fetch_one(&self.pool)
+            .await?;
+        }
+        if !prune_allowed(
+            "system_history_aggregated",
+            doomed,
+            total,
+            self.max_prune_fraction,
+        ) {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for &(resolution, cutoff) in cutoffs {
+            removed += sqlx::query(
+                "DELETE FROM system_history_aggregated WHERE resolution_seconds = $1 AND created_at < $2",
+            )
+            .bind(resolution)
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+        Ok(removed)
+    }
+
+    /// Delete `blob_store` entries no raw row references any more (callers hold `blob_lock`).
+    /// Returns rows removed.
+    async fn gc_blob_store(&self) -> HistoryResult<u64> {
+        let r = sqlx::query(
+            "DELETE FROM blob_store
+             WHERE NOT EXISTS (SELECT 1 FROM system_history WHERE storage_hash = blob_store.hash)
+               AND NOT EXISTS (SELECT 1 FROM system_history WHERE network_hash = blob_store.hash)",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(r.rows_affected())
+    }
+}
@@ -1,103 +1,238 @@
-// Raw `system_history` + `system_info` reads and writes.
+// Raw `system_history` + `system_info` writes, pruning and range bounds (row reads: raw_read.rs).
+
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
 
-use crate::history_repo::HistoryRepo;
 use crate::history_repo::blob;
-use crate::history_repo::history_merge::{
-    deserialize_container_data, deserialize_cpu_data, deserialize_gpu_data,
-    deserialize_network_data, deserialize_ram_data, deserialize_smart_data,
-    deserialize_storage_data,
-};
-use crate::models::{FullSystemSnapshot, SystemInfo, SystemStats, SystemStatsDynamic};
-use sqlx::Row;
+use crate::history_repo::blob_store::blob_hash;
+use crate::history_repo::retention::prune_allowed;
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult};
+use crate::models::json_float::finite;
+use crate::models::{FullSystemSnapshot, SystemInfo};
+use sqlx::{Database, Encode, QueryBuilder, Row, Sqlite, Type};
 use tracing::instrument;
 
+/// Binds per `system_history` row in the multi-row INSERT below.
+const RAW_INSERT_BINDS: usize = 16;
+/// Rows per INSERT statement, keeping binds under SQLite's historical 999-variable limit.
+pub(in crate::history_repo) const RAW_INSERT_CHUNK_ROWS: usize = 999 / RAW_INSERT_BINDS;
+
+/// One snapshot's column values with every blob already encoded.
+pub(in crate::history_repo) struct EncodedRow {
+    created_at: i64,
+    cpu_load: f64,
+    memory_used: i64,
+    memory_total: i64,
+    cpu_temperature: f64,
+    container_data: Vec<u8>,
+    system_data: Vec<u8>,
+    cpu_data: Vec<u8>,
+    ram_data: Vec<u8>,
+    gpu_data: Vec<u8>,
+    smart_data: Vec<u8>,
+    probe_data: Vec<u8>,
+    sensor_data: Vec<u8>,
+    storage_hash: Vec<u8>,
+    network_hash: Vec<u8>,
+}
+
+impl EncodedRow {
+    /// Bytes of the blobs stored inline in the row.
+    pub(in crate::history_repo) fn blob_bytes(&self) -> usize {
+        self.container_data.len()
+            + self.system_data.len()
+            + self.cpu_data.len()
+            + self.ram_data.len()
+            + self.gpu_data.len()
+            + self.smart_data.len()
+            + self.probe_data.len()
+            + self.sensor_data.len()
+    }
+}
+
+/// Encoded rows plus the distinct storage/network blobs they reference, keyed by hash.
+pub(in crate::history_repo) type EncodedBatch = (Vec<EncodedRow>, BTreeMap<Vec<u8>, Vec<u8>>);
+
+/// Encode (and compress) every blob of `snapshots`. CPU-bound: run on the blocking pool.
+pub(in crate::history_repo) fn encode_rows(
+    snapshots: &[FullSystemSnapshot],
+    compress: bool,
+) -> HistoryResult<EncodedBatch> {
+    let mut shared = BTreeMap::new();
+    let mut share = |bytes: Vec<u8>| {
+        let hash = blob_hash(&bytes);
+        shared.entry(hash.clone()).or_insert(bytes);
+        hash
+    };
+    let mut rows = Vec::with_capacity(snapshots.len());
+    for s in snapshots {
+        // Storage/network rarely change between samples: store them once in blob_store and
+        // reference by hash; the inline columns stay empty.
+        let storage_hash = share(blob::encode_blob(&s.storage, blob::BLOB_VERSION, compress)?);
+        let network_hash = share(blob::encode_blob(&s.network, blob::BLOB_VERSION, compress)?);
+        rows.push(EncodedRow {
+            created_at: s.timestamp as i64,
+            // SQLite binds NaN as NULL, which the NOT NULL query columns refuse; the blobs
+            // keep the reading as measured.
+            cpu_load: finite(s.cpu.usage_percent),
+            memory_used: s.ram.used as i64,
+            memory_total: s.ram.total as i64,
+            cpu_temperature: finite(s.cpu.temperature),
+            container_data: blob::encode_containers(&s.containers, compress)?,
+            system_data: blob::encode_blob(&s.system, blob::BLOB_VERSION_SYSTEM_DYNAMIC, compress)?,
+            cpu_data: blob::encode_blob(&s.cpu, blob::BLOB_VERSION, compress)?,
+            ram_data: blob::encode_blob(&s.ram, blob::BLOB_VERSION, compress)?,
+            gpu_data: blob::encode_blob(&s.gpus, blob::BLOB_VERSION, compress)?,
+            smart_data: blob::encode_blob(&s.smart, blob::BLOB_VERSION, compress)?,
+            probe_data: blob::encode_blob(&s.probes, blob::BLOB_VERSION, compress)?,
+            sensor_data: blob::encode_blob(&s.sensors, blob::BLOB_VERSION, compress)?,
+            storage_hash,
+            network_hash,
+        });
+    }
+    Ok((rows, shared))
+}
+
+/// Multi-row `system_history` INSERT of `rows`, tagged with `node`. The inline storage / network
+/// columns are left empty (`empty_blob`, the engine's empty-blob literal): they resolve through
+/// `blob_store` by hash.
+pub(in crate::history_repo) fn raw_insert<'a, DB>(
+    rows: &'a [EncodedRow],
+    node: Option<&'a str>,
+    empty_blob: &'static str,
+) -> QueryBuilder<DB>
+where
+    DB: Database,
+    DB::Arguments: Default,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> f64: Encode<'t, DB> + Type<DB>,
+    for<'t> &'t [u8]: Encode<'t, DB> + Type<DB>,
+    for<'t> Option<&'t str>: Encode<'t, DB> + Type<DB>,
+{
+    let mut qb = QueryBuilder::<DB>::new(
+        "INSERT INTO system_history (created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data, probe_data, sensor_data, memory_total, cpu_temperature, storage_hash, network_hash, node) ",
+    );
+    qb.push_values(rows, |mut b, r| {
+        b.push_bind(r.created_at)
+            .push_bind(r.cpu_load)
+            .push_bind(r.memory_used)
+            .push_bind(r.container_data.as_slice())
+            .push(empty_blob)
+            .push(empty_blob)
+            .push_bind(r.system_data.as_slice())
+            .push_bind(r.cpu_data.as_slice())
+            .push_bind(r.ram_data.as_slice())
+            .push_bind(r.gpu_data.as_slice())
+            .push_bind(r.smart_data.as_slice())
+            .push_bind(r.probe_data.as_slice())
+            .push_bind(r.sensor_data.as_slice())
+            .push_bind(r.memory_total)
+            .push_bind(r.cpu_temperature)
+            .push_bind(r.storage_hash.as_slice())
+            .push_bind(r.network_hash.as_slice())
+            .push_bind(node);
+    });
+    qb
+}
+
 impl HistoryRepo {
+    /// Append snapshots in one transaction. Blobs are encoded on the blocking pool, then rows go
+    /// in as multi-row INSERTs of up to [`RAW_INSERT_CHUNK_ROWS`] rows. Returns the encoded blob
+    /// bytes of the batch (row blobs plus the distinct shared storage / network blobs, whether or
+    /// not `blob_store` already held them).
     #[instrument(skip(self, snapshots, system_info), fields(repo = "history", operation = "save_snapshots", snapshots_count = snapshots.len()))]
     pub async fn save_snapshots(
         &self,
         snapshots: &[FullSystemSnapshot],
         system_info: &SystemInfo,
-    ) -> anyhow::Result<()> {
-        if snapshots.is_empty() {
-            return Ok(());
-        }
-        let mut tx = self.pool.begin().await?;
+    ) -> HistoryResult<u64> {
+        self.insert_snapshots(snapshots, Some(system_info), None)
+            .await
+    }
 
-        let info_blob = wincode::serialize(system_info)
-            .map_err(|e| anyhow::anyhow!("wincode system_info: {}", e))?;
+    /// Replace the stored local `SystemInfo` (also written with every [`Self::save_snapshots`]).
+    pub async fn save_system_info(&self, system_info: &SystemInfo) -> HistoryResult<()> {
+        let blob = wincode::serialize(system_info)
+            .map_err(|e| HistoryError::BlobEncode(format!("wincode system_info: {e}")))?;
         sqlx::query("INSERT OR REPLACE INTO system_info (id, data) VALUES (1, $1)")
-            .bind(&info_blob)
-            .execute(&mut *tx)
+            .bind(blob)
+            .execute(&self.writer)
             .await?;
+        Ok(())
+    }
 
-        for s in snapshots {
-            let container_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.containers).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let storage_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.storage).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let network_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.network).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let system_data = blob::with_version_prefix(
-                blob::BLOB_VERSION_SYSTEM_DYNAMIC,
-                wincode::serialize(&s.system).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let cpu_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.cpu).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let ram_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.ram).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let gpu_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.gpus).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            let smart_data = blob::with_version_prefix(
-                blob::BLOB_VERSION,
-                wincode::serialize(&s.smart).map_err(|e| anyhow::anyhow!("wincode: {}", e))?,
-            );
-            sqlx::query(
-                "INSERT INTO system_history (created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
-            )
-            .bind(s.timestamp as i64)
-            .bind(s.cpu.usage_percent)
-            .bind(s.ram.used as i64)
-            .bind(&container_data)
-            .bind(&storage_data)
-            .bind(&network_data)
-            .bind(&system_data)
-            .bind(&cpu_data)
-            .bind(&ram_data)
-            .bind(&gpu_data)
-            .bind(&smart_data)
-            .execute(&mut *tx)
-            .await?;
+    /// Rows for `snapshots`, tagged with `node` (`None` for local rows). `system_info`, when
+    /// given, replaces the stored local one in the same transaction. Returns the blob bytes
+    /// written, as for [`Self::save_snapshots`].
+    pub(in crate::history_repo) async fn insert_snapshots(
+        &self,
+        snapshots: &[FullSystemSnapshot],
+        system_info: Option<&SystemInfo>,
+        node: Option<&str>,
+    ) -> HistoryResult<u64> {
+        let info_blob = system_info
+            .map(wincode::serialize)
+            .transpose()
+            .map_err(|e| HistoryError::BlobEncode(format!("wincode system_info: {e}")))?;
+        let owned = snapshots.to_vec();
+        let compress = self.compress_blobs;
+        let (rows, shared) =
+            tokio::task::spawn_blocking(move || encode_rows(&owned, compress)).await??;
+        let bytes = rows.iter().map(EncodedRow::blob_bytes).sum::<usize>()
+            + shared.values().map(Vec::len).sum::<usize>();
+
+        let mut tx = self.writer.begin().await?;
+        if let Some(info_blob) = &info_blob {
+            sqlx::query("INSERT OR REPLACE INTO system_info (id, data) VALUES (1, $1)")
+                .bind(info_blob)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Self::put_shared_blobs(&mut tx, &shared).await?;
+        if node.is_none() {
+            Self::put_container_history(&mut tx, snapshots, self.container_history_top_n).await?;
+            Self::put_container_inventory(&mut tx, snapshots).await?;
+        }
+        for chunk in rows.chunks(RAW_INSERT_CHUNK_ROWS) {
+            let mut qb = raw_insert::<Sqlite>(chunk, node, "X''");
+            qb.build().execute(&mut *tx).await?;
         }
         tx.commit().await?;
-        Ok(())
+        Ok(bytes as u64)
     }
 
+    /// Delete raw rows older than `retention_days`, unreferenced `blob_store` entries and expired
+    /// `collection_errors` / `container_history` / `container_inventory` rows. Aggregated tiers are pruned separately
+    /// ([`prune_aggregated_old_data`](Self::prune_aggregated_old_data)). Raw rows are left alone
+    /// when they would exceed `max_prune_fraction` of the table. Returns raw rows removed.
     #[instrument(skip(self), fields(repo = "history", operation = "prune_old_data"))]
-    pub async fn prune_old_data(&self) -> anyhow::Result<()> {
-        let cutoff = (std::time::SystemTime::now()
+    pub async fn prune_old_data(&self) -> HistoryResult<u64> {
+        let now_ms = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
-            .as_millis() as i64)
-            - self.retention_ms;
-        sqlx::query("DELETE FROM system_history WHERE created_at < $1")
-            .bind(cutoff)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+            .as_millis() as i64;
+        let cutoff = now_ms - self.raw_retention_ms.load(Ordering::Relaxed);
+        let (total, doomed): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(created_at < $1), 0) FROM system_history",
+        )
+        .bind(cutoff)
+        .fetch_one(&self.pool)
+        .await?;
+        let mut removed = 0;
+        if prune_allowed("system_history", doomed, total, self.max_prune_fraction) {
+            removed = sqlx::query("DELETE FROM system_history WHERE created_at < $1")
+                .bind(cutoff)
+                .execute(&self.writer)
+                .await?
+                .rows_affected();
+        }
+        self.gc_blob_store().await?;
+        self.prune_collection_errors(now_ms).await?;
+        self.prune_container_history(now_ms).await?;
+        self.prune_container_inventory(now_ms).await?;
+        Ok(removed)
     }
 
-    pub async fn get_stored_system_info(&self) -> anyhow::Result<Option<SystemInfo>> {
+    pub async fn get_stored_system_info(&self) -> HistoryResult<Option<SystemInfo>> {
         let row = sqlx::query("SELECT data FROM system_info WHERE id = 1")
             .fetch_optional(&self.pool)
             .await?;
@@ -105,66 +240,17 @@ impl HistoryRepo {
             return Ok(None);
         };
         let data: Vec<u8> = row.try_get("data")?;
-        let info = wincode::deserialize(&data)
-            .map_err(|e| anyhow::anyhow!("wincode deserialize system_info: {}", e))?;
-        Ok(Some(info))
-    }
-
-    pub async fn get_recent_snapshots(
-        &self,
-        limit: u32,
-    ) -> anyhow::Result<(Option<SystemInfo>, Vec<FullSystemSnapshot>)> {
-        let stored_info = self.get_stored_system_info().await?;
-
-        let rows = sqlx::query(
-            "SELECT created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data
-             FROM system_history ORDER BY id DESC LIMIT $1",
-        )
-        .bind(limit as i64)
-        .fetch_all(&self.pool)
-        .await?;
-
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows {
-            out.push(Self::parse_snapshot_row(&row)?);
-        }
-        out.reverse();
-        Ok((stored_info, out))
-    }
-
-    /// Raw snapshots in [from_ts, to_ts) for aggregation. Order: ascending by created_at.
-    #[instrument(
-        skip(self),
-        fields(repo = "history", operation = "get_raw_snapshots_by_time_range")
-    )]
-    pub async fn get_raw_snapshots_by_time_range(
-        &self,
-        from_ts: i64,
-        to_ts: i64,
-    ) -> anyhow::Result<Vec<FullSystemSnapshot>> {
-        let rows = sqlx::query(
-            "SELECT created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data, cpu_data, ram_data, gpu_data, smart_data
-             FROM system_history WHERE created_at >= $1 AND created_at < $2 ORDER BY created_at ASC",
-        )
-        .bind(from_ts)
-        .bind(to_ts)
-        .fetch_all(&self.pool)
-        .await?;
-
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows {
-            out.push(Self::parse_snapshot_row(&row)?);
-        }
-        Ok(out)
+        // Unprefixed wincode (no blob version byte).
+        Ok(Some(blob::decode_system_info(&data, "system_info.data")?))
     }
 
     /// Minimum created_at in system_history with created_at < cutoff_ts (for aggregation bounds).
     pub async fn get_min_raw_created_at_before(
         &self,
         cutoff_ts: i64,
-    ) -> anyhow::Result<Option<i64>> {
+    ) -> HistoryResult<Option<i64>> {
         let row = sqlx::query_scalar::<_, Option<i64>>(
-            "SELECT MIN(created_at) FROM system_history WHERE created_at < $1",
+            "SELECT MIN(created_at) FROM system_history WHERE node IS NULL AND created_at < $1",
         )
         .bind(cutoff_ts)
         .fetch_one(&self.pool)
@@ -172,96 +258,27 @@ impl HistoryRepo {
         Ok(row)
     }
 
+    /// Newest local raw `created_at` (rows pushed by other instances excluded); `None` when the
+    /// table has none.
+    pub async fn get_max_raw_created_at(&self) -> HistoryResult<Option<i64>> {
+        let row = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(created_at) FROM system_history WHERE node IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row)
+    }
+
     /// Delete raw rows in [from_ts, to_ts).
     #[instrument(skip(self), fields(repo = "history", operation = "delete_raw_range"))]
-    pub async fn delete_raw_range(&self, from_ts: i64, to_ts: i64) -> anyhow::Result<u64> {
+    pub async fn delete_raw_range(&self, from_ts: i64, to_ts: i64) -> HistoryResult<u64> {
         let r =
-            sqlx::query("DELETE FROM system_history WHERE created_at >= $1 AND created_at < $2")
+            sqlx::query("DELETE FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at < $2")
                 .bind(from_ts)
                 .bind(to_ts)
-                .execute(&self.pool)
+                .execute(&self.writer)
                 .await?;
+        self.gc_blob_store().await?;
         Ok(r.rows_affected())
     }
-
-    fn parse_snapshot_row(row: &sqlx::sqlite::SqliteRow) -> anyhow::Result<FullSystemSnapshot> {
-        let created_at: i64 = row.try_get("created_at")?;
-        let cpu_load: f64 = row.try_get("cpu_load")?;
-        let memory_used: i64 = row.try_get("memory_used")?;
-        let container_data: Vec<u8> = row.try_get("container_data")?;
-        let storage_data: Vec<u8> = row.try_get("storage_data")?;
-        let network_data: Vec<u8> = row.try_get("network_data")?;
-        let system_data: Vec<u8> = row.try_get("system_data")?;
-        // Nullable on rows written before schema v3 (CPU/RAM) / v4 (GPU).
-        let cpu_data: Option<Vec<u8>> = row.try_get("cpu_data")?;
-        let ram_data: Option<Vec<u8>> = row.try_get("ram_data")?;
-        let gpu_data: Option<Vec<u8>> = row.try_get("gpu_data")?;
-        let smart_data: Option<Vec<u8>> = row.try_get("smart_data")?;
-
-        let containers = deserialize_container_data(&container_data);
-        let storage = deserialize_storage_data(&storage_data);
-        let network = deserialize_network_data(&network_data);
-        let cpu = deserialize_cpu_data(cpu_data.as_deref(), cpu_load);
-        let ram = deserialize_ram_data(ram_data.as_deref(), memory_used as u64);
-        let gpus = deserialize_gpu_data(gpu_data.as_deref());
-        let smart = deserialize_smart_data(smart_data.as_deref());
-
-        let system = match blob::blob_version(&system_data) {
-            blob::BLOB_VERSION_SYSTEM_DYNAMIC => {
-                match wincode::deserialize::<SystemStatsDynamic>(blob::blob_payload(
-                    &system_data,
-                    blob::BLOB_VERSION_SYSTEM_DYNAMIC,
-                )) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        tracing::debug!(error = %e, "wincode deserialize system (dynamic), using default");
-                        SystemStatsDynamic {
-                            uptime_secs: 0,
-                            process_count: 0,
-                            thread_count: 0,
-                            load_avg_1: 0.0,
-                            load_avg_5: 0.0,
-                            load_avg_15: 0.0,
-                        }
-                    }
-                }
-            }
-            _ => match wincode::deserialize::<SystemStats>(blob::blob_payload(
-                &system_data,
-                blob::BLOB_VERSION,
-            )) {
-                Ok(full) => SystemStatsDynamic {
-                    uptime_secs: full.uptime_secs,
-                    process_count: full.process_count,
-                    thread_count: full.thread_count,
-                    load_avg_1: 0.0,
-                    load_avg_5: 0.0,
-                    load_avg_15: 0.0,
-                },
-                Err(e) => {
-                    tracing::debug!(error = %e, "wincode deserialize system (legacy), using default");
-                    SystemStatsDynamic {
-                        uptime_secs: 0,
-                        process_count: 0,
-                        thread_count: 0,
-                        load_avg_1: 0.0,
-                        load_avg_5: 0.0,
-                        load_avg_15: 0.0,
-                    }
-                }
-            },
-        };
-
-        Ok(FullSystemSnapshot {
-            timestamp: created_at as u64,
-            cpu,
-            ram,
-            containers,
-            storage,
-            network,
-            system,
-            gpus,
-            smart,
-        })
-    }
 }
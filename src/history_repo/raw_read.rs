@@ -0,0 +1,217 @@
+// Raw `system_history` row reads: recent, since a timestamp, and by time range.
+
+use crate::history_repo::blob;
+use crate::history_repo::history_merge::{
+    decode_or, deserialize_container_data, deserialize_cpu_data, deserialize_gpu_data,
+    deserialize_network_data, deserialize_probe_data, deserialize_ram_data,
+    deserialize_sensor_data, deserialize_smart_data, deserialize_storage_data,
+};
+use crate::history_repo::row::HistoryRow;
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{FullSystemSnapshot, SystemInfo, SystemStats, SystemStatsDynamic};
+use tracing::instrument;
+
+/// Raw row columns; storage/network resolve through `blob_store` (schema v8+) and fall back to
+/// the inline blobs on older rows.
+macro_rules! raw_select {
+    ($tail:literal) => {
+        concat!(
+            "SELECT h.id, h.created_at, h.cpu_load, h.memory_used, h.container_data,
+                    COALESCE(bs.data, h.storage_data) AS storage_data,
+                    COALESCE(bn.data, h.network_data) AS network_data,
+                    h.system_data, h.cpu_data, h.ram_data, h.gpu_data, h.smart_data,
+                    h.probe_data, h.sensor_data, h.memory_total, h.cpu_temperature
+             FROM system_history h
+             LEFT JOIN blob_store bs ON bs.hash = h.storage_hash
+             LEFT JOIN blob_store bn ON bn.hash = h.network_hash ",
+            $tail
+        )
+    };
+}
+
+// Ordered by created_at (idx_history_created_at), not id: imported rows may be out of id order.
+// Local rows only (`node IS NULL`); rows pushed by other instances are read by node below.
+pub(in crate::history_repo) const RAW_SELECT_RECENT: &str =
+    raw_select!("WHERE h.node IS NULL ORDER BY h.created_at DESC, h.id DESC LIMIT $1");
+pub(in crate::history_repo) const RAW_SELECT_SINCE: &str = raw_select!(
+    "WHERE h.node IS NULL AND h.created_at > $1 ORDER BY h.created_at ASC, h.id ASC LIMIT $2"
+);
+pub(in crate::history_repo) const RAW_SELECT_RANGE: &str = raw_select!(
+    "WHERE h.node IS NULL AND h.created_at >= $1 AND h.created_at < $2 ORDER BY h.created_at ASC"
+);
+/// Local rows after a sync `seq` (the row id), in id order (the primary key).
+pub(in crate::history_repo) const RAW_SELECT_AFTER_SEQ: &str =
+    raw_select!("WHERE h.node IS NULL AND h.id > $1 ORDER BY h.id ASC LIMIT $2");
+/// One remote node's rows in [$1, $2) (idx_history_node_created_at).
+pub(in crate::history_repo) const RAW_SELECT_NODE_RANGE: &str = raw_select!(
+    "WHERE h.node = $3 AND h.created_at >= $1 AND h.created_at < $2 ORDER BY h.created_at ASC"
+);
+
+/// Decode `rows` with `parse`, skipping rows that hold a blob of unknown version
+/// ([`UnknownBlobVersion`](crate::history_repo::HistoryError::UnknownBlobVersion)) instead of
+/// failing or returning empty values.
+pub(in crate::history_repo) fn parse_rows<R, T>(
+    rows: &[R],
+    parse: fn(&R) -> HistoryResult<T>,
+) -> HistoryResult<Vec<T>> {
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        match parse(row) {
+            Ok(item) => out.push(item),
+            Err(e) if e.is_unknown_blob_version() => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(out)
+}
+
+/// Upper bound on rows one [`HistoryRepo::get_snapshots_since`] call returns.
+pub const MAX_SNAPSHOTS_SINCE: u32 = 3600;
+
+impl HistoryRepo {
+    /// The newest `limit` raw snapshots (by `created_at`), returned oldest first.
+    pub async fn get_recent_snapshots(
+        &self,
+        limit: u32,
+    ) -> HistoryResult<(Option<SystemInfo>, Vec<FullSystemSnapshot>)> {
+        self.retry_busy("get_recent_snapshots", || async move {
+            let stored_info = self.get_stored_system_info().await?;
+
+            let rows = sqlx::query(RAW_SELECT_RECENT)
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+            let mut out = parse_rows(&rows, Self::parse_snapshot_row)?;
+            out.reverse();
+            Ok((stored_info, out))
+        })
+        .await
+    }
+
+    /// Raw snapshots with `created_at > since_ts`, oldest first, at most
+    /// `min(limit, MAX_SNAPSHOTS_SINCE)`. Pass the last returned timestamp to continue.
+    #[instrument(
+        skip(self),
+        fields(repo = "history", operation = "get_snapshots_since")
+    )]
+    pub async fn get_snapshots_since(
+        &self,
+        since_ts: i64,
+        limit: u32,
+    ) -> HistoryResult<Vec<FullSystemSnapshot>> {
+        self.retry_busy("get_snapshots_since", || async move {
+            let rows = sqlx::query(RAW_SELECT_SINCE)
+                .bind(since_ts)
+                .bind(i64::from(limit.min(MAX_SNAPSHOTS_SINCE)))
+                .fetch_all(&self.pool)
+                .await?;
+            parse_rows(&rows, Self::parse_snapshot_row)
+        })
+        .await
+    }
+
+    /// Raw snapshots in [from_ts, to_ts) for aggregation. Order: ascending by created_at.
+    #[instrument(
+        skip(self),
+        fields(repo = "history", operation = "get_raw_snapshots_by_time_range")
+    )]
+    pub async fn get_raw_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> HistoryResult<Vec<FullSystemSnapshot>> {
+        self.retry_busy("get_raw_snapshots_by_time_range", || async move {
+            let rows = sqlx::query(RAW_SELECT_RANGE)
+                .bind(from_ts)
+                .bind(to_ts)
+                .fetch_all(&self.pool)
+                .await?;
+
+            parse_rows(&rows, Self::parse_snapshot_row)
+        })
+        .await
+    }
+
+    pub(in crate::history_repo) fn parse_snapshot_row<R: HistoryRow>(
+        row: &R,
+    ) -> HistoryResult<FullSystemSnapshot> {
+        let created_at: i64 = row.get_i64("created_at")?;
+        let cpu_load: f64 = row.get_f64("cpu_load")?;
+        let memory_used: i64 = row.get_i64("memory_used")?;
+        // Schema v6+; legacy rows read 0.
+        let memory_total: i64 = row.get_i64("memory_total")?;
+        let cpu_temperature: f64 = row.get_f64("cpu_temperature")?;
+        let container_data: Vec<u8> = row.get_bytes("container_data")?;
+        let storage_data: Vec<u8> = row.get_bytes("storage_data")?;
+        let network_data: Vec<u8> = row.get_bytes("network_data")?;
+        let system_data: Vec<u8> = row.get_bytes("system_data")?;
+        // Nullable on rows written before schema v3 (CPU/RAM) / v4 (GPU) / v14 (probes) /
+        // v16 (sensors).
+        let cpu_data: Option<Vec<u8>> = row.get_opt_bytes("cpu_data")?;
+        let ram_data: Option<Vec<u8>> = row.get_opt_bytes("ram_data")?;
+        let gpu_data: Option<Vec<u8>> = row.get_opt_bytes("gpu_data")?;
+        let smart_data: Option<Vec<u8>> = row.get_opt_bytes("smart_data")?;
+        let probe_data: Option<Vec<u8>> = row.get_opt_bytes("probe_data")?;
+        let sensor_data: Option<Vec<u8>> = row.get_opt_bytes("sensor_data")?;
+
+        let containers = deserialize_container_data(&container_data)?;
+        let storage = deserialize_storage_data(&storage_data)?;
+        let network = deserialize_network_data(&network_data)?;
+        let cpu = deserialize_cpu_data(cpu_data.as_deref(), cpu_load, cpu_temperature)?;
+        let ram =
+            deserialize_ram_data(ram_data.as_deref(), memory_used as u64, memory_total as u64)?;
+        let gpus = deserialize_gpu_data(gpu_data.as_deref())?;
+        let smart = deserialize_smart_data(smart_data.as_deref())?;
+        let probes = deserialize_probe_data(probe_data.as_deref())?;
+        let sensors = deserialize_sensor_data(sensor_data.as_deref())?;
+
+        let system = match blob::blob_version(&system_data) {
+            blob::BLOB_VERSION_SYSTEM_DYNAMIC | blob::BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED => {
+                decode_or(
+                    &system_data,
+                    blob::BLOB_VERSION_SYSTEM_DYNAMIC,
+                    "system_data",
+                    SystemStatsDynamic::default,
+                )?
+            }
+            _ => match blob::decode_blob::<SystemStats>(
+                &system_data,
+                blob::BLOB_VERSION,
+                "system_data",
+            ) {
+                Ok(full) => SystemStatsDynamic {
+                    uptime_secs: full.uptime_secs,
+                    process_count: full.process_count,
+                    thread_count: full.thread_count,
+                    load_avg_1: 0.0,
+                    load_avg_5: 0.0,
+                    load_avg_15: 0.0,
+                },
+                Err(e) if e.is_unknown_blob_version() => return Err(e),
+                Err(e) => {
+                    tracing::debug!(error = %e, "wincode deserialize system (legacy), using default");
+                    SystemStatsDynamic::default()
+                }
+            },
+        };
+
+        Ok(FullSystemSnapshot {
+            timestamp: created_at as u64,
+            cpu,
+            ram,
+            containers,
+            storage,
+            network,
+            system,
+            gpus,
+            smart,
+            probes,
+            sensors,
+            checks: vec![],
+            degraded: vec![],
+            self_stats: None,
+            historical: false,
+        })
+    }
+}
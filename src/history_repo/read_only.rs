@@ -0,0 +1,70 @@
+// Read-only open for inspection tools (examples, CLIs) that must never write or create the file.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::AtomicI64;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::retention::MS_PER_DAY;
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult, RetentionPolicy};
+
+/// Inspection tools run a handful of sequential queries; two connections are plenty.
+const READ_ONLY_POOL_SIZE: u32 = 2;
+
+/// Reject paths `connect` could never use: a directory instead of a database file.
+pub(in crate::history_repo) fn ensure_not_directory(path: &str) -> HistoryResult<()> {
+    if Path::new(path).is_dir() {
+        return Err(HistoryError::InvalidArgument(format!(
+            "database path '{path}' is a directory, expected a database file"
+        )));
+    }
+    Ok(())
+}
+
+impl HistoryRepo {
+    /// Open an existing database read-only: no file is created, no migration or pragma that
+    /// writes is run, and every write fails with SQLite's `SQLITE_READONLY`. A missing file is a
+    /// [`HistoryError::Io`] (`NotFound`) naming the path, so a typo does not leave an empty
+    /// database behind. Call [`get_recent_snapshots`](Self::get_recent_snapshots) and the other
+    /// readers directly; `init` is not needed, and fails if the schema would have to migrate.
+    pub async fn connect_read_only(path: &str) -> HistoryResult<Self> {
+        ensure_not_directory(path)?;
+        if !Path::new(path).is_file() {
+            return Err(HistoryError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("database file '{path}' does not exist"),
+            )));
+        }
+        let opts = SqliteConnectOptions::from_str(&format!("sqlite:{}", path))?
+            .read_only(true)
+            .create_if_missing(false)
+            .busy_timeout(std::time::Duration::from_secs(5));
+        let pool = SqlitePoolOptions::new()
+            .max_connections(READ_ONLY_POOL_SIZE)
+            .connect_with(opts)
+            .await?;
+        let defaults = DatabaseConfig::default();
+        Ok(Self {
+            // Writes fail anyway; they may as well go through the same connections.
+            writer: pool.clone(),
+            pool,
+            retention: RetentionPolicy::from_config(&defaults),
+            raw_retention_ms: AtomicI64::new(i64::from(defaults.retention_days) * MS_PER_DAY),
+            error_retention_ms: AtomicI64::new(
+                i64::from(defaults.error_retention_days) * MS_PER_DAY,
+            ),
+            container_history_top_n: defaults.container_history_top_n,
+            container_history_retention_ms: i64::from(defaults.container_history_retention_days)
+                * MS_PER_DAY,
+            container_inventory_retention_ms: i64::from(
+                defaults.container_inventory_retention_days,
+            ) * MS_PER_DAY,
+            compress_blobs: defaults.compress_blobs,
+            max_prune_fraction: defaults.max_prune_fraction,
+            path: path.to_string(),
+            free_space: std::sync::Arc::new(super::Statvfs),
+        })
+    }
+}
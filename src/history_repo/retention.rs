@@ -0,0 +1,239 @@
+// Per-tier retention: how long raw rows and each aggregated tier are kept, and the prune cutoffs.
+
+use std::sync::atomic::Ordering;
+
+use tracing::{instrument, warn};
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::{HistoryRepo, HistoryResult};
+
+const MS_PER_HOUR: i64 = 3_600_000;
+pub(super) const MS_PER_DAY: i64 = 86_400_000;
+
+/// How long rows of the `index`-th aggregated tier (finest first) stay before rolling into the
+/// next tier: `minute_retention_hours` for the first, `five_minute_retention_days` for the
+/// second, `hourly_retention_days` for any after that. The coarsest tier does not roll up; it is
+/// kept for `aggregated_retention_days`.
+pub fn tier_rollup_after_ms(
+    index: usize,
+    minute_retention_hours: u32,
+    five_minute_retention_days: u32,
+    hourly_retention_days: u32,
+) -> i64 {
+    match index {
+        0 => i64::from(minute_retention_hours) * MS_PER_HOUR,
+        1 => i64::from(five_minute_retention_days) * MS_PER_DAY,
+        _ => i64::from(hourly_retention_days) * MS_PER_DAY,
+    }
+}
+
+/// Maximum row age (ms) per table/tier, from the `[database]` retention settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Raw rows (`retention_days`). With aggregation on they normally roll up long before this.
+    pub raw_ms: i64,
+    /// `(resolution_seconds, max age)` per `aggregation_tiers` entry, finest first; see
+    /// [`tier_rollup_after_ms`].
+    pub tiers: Vec<(i32, i64)>,
+    /// The cap for every aggregated tier (`aggregated_retention_days`).
+    pub aggregated_ms: i64,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &DatabaseConfig) -> Self {
+        let aggregated_ms = i64::from(config.aggregated_retention_days) * MS_PER_DAY;
+        let last = config.aggregation_tiers.len().saturating_sub(1);
+        let tiers = config
+            .aggregation_tiers
+            .iter()
+            .enumerate()
+            .map(|(i, &resolution)| {
+                let keep_ms = if i == last {
+                    aggregated_ms
+                } else {
+                    tier_rollup_after_ms(
+                        i,
+                        config.minute_retention_hours,
+                        config.five_minute_retention_days,
+                        config.hourly_retention_days,
+                    )
+                };
+                (resolution, keep_ms)
+            })
+            .collect();
+        Self {
+            raw_ms: i64::from(config.retention_days) * MS_PER_DAY,
+            tiers,
+            aggregated_ms,
+        }
+    }
+
+    /// Configured tier resolutions (`resolution_seconds`), finest first.
+    pub fn resolutions(&self) -> impl Iterator<Item = i32> + '_ {
+        self.tiers.iter().map(|&(resolution, _)| resolution)
+    }
+
+    /// Every age limit halved `halvings` times, but none below `min_keep_ms` (emergency prune).
+    pub fn halved(&self, halvings: u32, min_keep_ms: i64) -> Self {
+        let halve = |ms: i64| ms.checked_shr(halvings).unwrap_or(0).max(min_keep_ms);
+        Self {
+            raw_ms: halve(self.raw_ms),
+            tiers: self
+                .tiers
+                .iter()
+                .map(|&(resolution, keep_ms)| (resolution, halve(keep_ms)))
+                .collect(),
+            aggregated_ms: halve(self.aggregated_ms),
+        }
+    }
+
+    /// Per-resolution prune cutoffs at `now_ms`, given each tier's roll-up `watermark`; see
+    /// [`HistoryRepo::aggregated_prune_cutoffs`].
+    pub(in crate::history_repo) async fn prune_cutoffs<F, Fut>(
+        &self,
+        now_ms: i64,
+        watermark: F,
+    ) -> HistoryResult<Vec<(i32, i64)>>
+    where
+        F: Fn(i32) -> Fut,
+        Fut: Future<Output = HistoryResult<Option<i64>>>,
+    {
+        let total_cutoff = now_ms - self.aggregated_ms;
+        let mut cutoffs = Vec::with_capacity(self.tiers.len());
+        for (i, &(resolution, keep_ms)) in self.tiers.iter().enumerate() {
+            let age_cutoff = now_ms - keep_ms;
+            let cutoff = match self.tiers.get(i + 1) {
+                Some(&(coarser, _)) => {
+                    let rolled_up = watermark(coarser).await?;
+                    age_cutoff.min(rolled_up.unwrap_or(i64::MIN))
+                }
+                None => age_cutoff,
+            };
+            cutoffs.push((resolution, cutoff.max(total_cutoff)));
+        }
+        Ok(cutoffs)
+    }
+
+    /// How long rows of an aggregated tier are kept (anything not configured gets the
+    /// aggregated cap).
+    pub fn tier_ms(&self, resolution_seconds: i32) -> i64 {
+        self.tiers
+            .iter()
+            .find(|&&(resolution, _)| resolution == resolution_seconds)
+            .map_or(self.aggregated_ms, |&(_, keep_ms)| keep_ms)
+    }
+}
+
+impl HistoryRepo {
+    /// Change raw (`database.retention_days`) and `collection_errors`
+    /// (`database.error_retention_days`) retention at runtime; the next prune uses them.
+    pub fn set_retention_days(&self, retention_days: u32, error_retention_days: u32) {
+        self.raw_retention_ms
+            .store(i64::from(retention_days) * MS_PER_DAY, Ordering::Relaxed);
+        self.error_retention_ms.store(
+            i64::from(error_retention_days) * MS_PER_DAY,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Configured aggregated tier resolutions (`database.aggregation_tiers`), finest first.
+    pub fn aggregation_tiers(&self) -> Vec<i32> {
+        self.retention.resolutions().collect()
+    }
+
+    /// Per-resolution prune cutoffs at `now_ms` for [`Self::prune_aggregated_old_data`]. A tier
+    /// that rolls into a coarser one is only pruned behind that tier's watermark, so rows past
+    /// their retention but still waiting for a backlogged roll-up survive; nothing outlives
+    /// `aggregated_retention_days`.
+    pub async fn aggregated_prune_cutoffs(&self, now_ms: i64) -> HistoryResult<Vec<(i32, i64)>> {
+        self.retention
+            .prune_cutoffs(now_ms, |resolution| {
+                self.get_aggregation_watermark(resolution)
+            })
+            .await
+    }
+
+    /// Delete aggregated rows older than their tier's cutoff (`(resolution_seconds, cutoff_ts)`
+    /// pairs, see [`Self::aggregated_prune_cutoffs`]). Returns rows removed.
+    #[instrument(
+        skip(self),
+        fields(repo = "history", operation = "prune_aggregated_old_data")
+    )]
+    pub async fn prune_aggregated_old_data(&self, cutoffs: &[(i32, i64)]) -> HistoryResult<u64> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_history_aggregated")
+            .fetch_one(&self.pool)
+            .await?;
+        let mut doomed = 0;
+        for &(resolution, cutoff) in cutoffs {
+            doomed += sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM system_history_aggregated WHERE resolution_seconds = $1 AND created_at < $2",
+            )
+            .bind(resolution)
+            .bind(cutoff)
+            .fetch_one(&self.pool)
+            .await?;
+        }
+        if !prune_allowed(
+            "system_history_aggregated",
+            doomed,
+            total,
+            self.max_prune_fraction,
+        ) {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for &(resolution, cutoff) in cutoffs {
+            let r = sqlx::query(
+                "DELETE FROM system_history_aggregated WHERE resolution_seconds = $1 AND created_at < $2",
+            )
+            .bind(resolution)
+            .bind(cutoff)
+            .execute(&self.writer)
+            .await?;
+            removed += r.rows_affected();
+        }
+        Ok(removed)
+    }
+
+    /// Delete every raw and aggregated row with `created_at < cutoff_ms`, then the `blob_store`
+    /// entries no raw row references any more. An explicit request (`homeserver-cli prune`), so
+    /// `max_prune_fraction` does not apply. Returns (raw, aggregated) rows removed.
+    #[instrument(skip(self), fields(repo = "history", operation = "prune_before"))]
+    pub async fn prune_before(&self, cutoff_ms: i64) -> HistoryResult<(u64, u64)> {
+        let mut tx = self.writer.begin().await?;
+        let raw = sqlx::query("DELETE FROM system_history WHERE created_at < $1")
+            .bind(cutoff_ms)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let aggregated = sqlx::query("DELETE FROM system_history_aggregated WHERE created_at < $1")
+            .bind(cutoff_ms)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        self.gc_blob_store().await?;
+        Ok((raw, aggregated))
+    }
+}
+
+/// Whether deleting `doomed` of `total` rows of `table` stays within `max_prune_fraction`. Warns
+/// and returns false otherwise: deleting most of a table at once usually means the clock jumped.
+pub(in crate::history_repo) fn prune_allowed(
+    table: &str,
+    doomed: i64,
+    total: i64,
+    max_prune_fraction: f64,
+) -> bool {
+    if total <= 0 || doomed as f64 <= total as f64 * max_prune_fraction {
+        return true;
+    }
+    warn!(
+        table,
+        doomed,
+        total,
+        max_prune_fraction,
+        "prune skipped: it would delete too large a share of the table (clock jump?)"
+    );
+    false
+}
@@ -0,0 +1,106 @@
+// Atomic roll-up steps: write a chunk's aggregates, delete their source rows and advance the
+// tier watermark in one transaction.
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::AggregatedSnapshot;
+use tracing::instrument;
+
+impl HistoryRepo {
+    /// Oldest raw `created_at` in [from_ts, to_ts): lets a roll-up pass skip empty stretches.
+    pub async fn get_min_raw_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at < $2",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    /// Oldest `resolution_seconds` row's `created_at` in [from_ts, to_ts).
+    pub async fn get_min_aggregated_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MIN(created_at) FROM system_history_aggregated WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .bind(resolution_seconds)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    /// Save the first-tier (`resolution_seconds`) aggregates of raw rows in [from_ts, to_ts),
+    /// delete those rows and set that tier's watermark to `to_ts`, atomically. Returns raw rows
+    /// deleted.
+    #[instrument(
+        skip(self, aggs),
+        fields(repo = "history", operation = "roll_up_raw_range", buckets = aggs.len())
+    )]
+    pub async fn roll_up_raw_range(
+        &self,
+        aggs: &[AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> HistoryResult<u64> {
+        let mut tx = self.writer.begin().await?;
+        for agg in aggs {
+            self.insert_aggregated(&mut tx, agg).await?;
+        }
+        let r =
+            sqlx::query("DELETE FROM system_history WHERE node IS NULL AND created_at >= $1 AND created_at < $2")
+                .bind(from_ts)
+                .bind(to_ts)
+                .execute(&mut *tx)
+                .await?;
+        Self::set_aggregation_watermark(&mut tx, resolution_seconds, to_ts).await?;
+        tx.commit().await?;
+        if r.rows_affected() > 0 {
+            self.gc_blob_store().await?;
+        }
+        Ok(r.rows_affected())
+    }
+
+    /// Save `to_resolution` aggregates of `from_resolution` rows in [from_ts, to_ts), delete
+    /// those rows and set the `to_resolution` watermark to `to_ts`, atomically. Returns source
+    /// rows deleted.
+    #[instrument(
+        skip(self, aggs),
+        fields(repo = "history", operation = "roll_up_aggregated_range", buckets = aggs.len())
+    )]
+    pub async fn roll_up_aggregated_range(
+        &self,
+        aggs: &[AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        from_resolution: i32,
+        to_resolution: i32,
+    ) -> HistoryResult<u64> {
+        let mut tx = self.writer.begin().await?;
+        for agg in aggs {
+            self.insert_aggregated(&mut tx, agg).await?;
+        }
+        let r = sqlx::query(
+            "DELETE FROM system_history_aggregated WHERE created_at >= $1 AND created_at < $2 AND resolution_seconds = $3",
+        )
+        .bind(from_ts)
+        .bind(to_ts)
+        .bind(from_resolution)
+        .execute(&mut *tx)
+        .await?;
+        Self::set_aggregation_watermark(&mut tx, to_resolution, to_ts).await?;
+        tx.commit().await?;
+        Ok(r.rows_affected())
+    }
+}
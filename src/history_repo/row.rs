@@ -0,0 +1,48 @@
+// Column reads shared by the SQLite and Postgres row decoders, so `parse_snapshot_row`,
+// `parse_aggregated_row` and the streamed history reads work on either engine's rows.
+
+use sqlx::Row;
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+
+/// A fetched `system_history` / `system_history_aggregated` row, read by column name.
+pub(in crate::history_repo) trait HistoryRow: Send + 'static {
+    fn get_i32(&self, column: &str) -> Result<i32, sqlx::Error>;
+    fn get_i64(&self, column: &str) -> Result<i64, sqlx::Error>;
+    fn get_f64(&self, column: &str) -> Result<f64, sqlx::Error>;
+    fn get_opt_i64(&self, column: &str) -> Result<Option<i64>, sqlx::Error>;
+    fn get_opt_f64(&self, column: &str) -> Result<Option<f64>, sqlx::Error>;
+    fn get_bytes(&self, column: &str) -> Result<Vec<u8>, sqlx::Error>;
+    fn get_opt_bytes(&self, column: &str) -> Result<Option<Vec<u8>>, sqlx::Error>;
+}
+
+macro_rules! impl_history_row {
+    ($row:ty) => {
+        impl HistoryRow for $row {
+            fn get_i32(&self, column: &str) -> Result<i32, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_i64(&self, column: &str) -> Result<i64, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_f64(&self, column: &str) -> Result<f64, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_opt_i64(&self, column: &str) -> Result<Option<i64>, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_opt_f64(&self, column: &str) -> Result<Option<f64>, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_bytes(&self, column: &str) -> Result<Vec<u8>, sqlx::Error> {
+                self.try_get(column)
+            }
+            fn get_opt_bytes(&self, column: &str) -> Result<Option<Vec<u8>>, sqlx::Error> {
+                self.try_get(column)
+            }
+        }
+    };
+}
+
+impl_history_row!(SqliteRow);
+impl_history_row!(PgRow);
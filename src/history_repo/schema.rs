@@ -1,183 +1,120 @@
-// Pool connection, schema version, DDL for raw tables.
-
-use super::{CURRENT_SCHEMA_VERSION, HistoryRepo};
-use crate::history_repo::aggregation;
+// Pool connection and DDL for raw tables (version check in schema_version.rs, migrations in
+// migrations.rs).
+
+use super::container_inventory::{CREATE_CONTAINER_INVENTORY, CREATE_CONTAINER_INVENTORY_INDEX};
+use super::retention::MS_PER_DAY;
+use super::service_events::CREATE_SERVICE_EVENTS;
+use super::top_containers::{
+    CREATE_CONTAINER_HISTORY, CREATE_CONTAINER_HISTORY_INDEX, CREATE_CONTAINER_HISTORY_TS_INDEX,
+};
+use super::{HistoryError, HistoryRepo, HistoryResult, RetentionPolicy};
+use crate::config::DatabaseConfig;
+use crate::history_repo::{aggregation, read_only};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::AtomicI64;
+
+use sqlx::sqlite::{SqliteAutoVacuum, SqliteConnectOptions, SqlitePoolOptions};
 
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-
-/// Ordered, additive forward migrations. Entry `(v, statements)` migrates schema `v` → `v + 1`.
-/// Only data-preserving DDL (e.g. `ALTER TABLE ... ADD COLUMN`) belongs here.
-/// v2 → v3: add nullable `cpu_data` / `ram_data` blobs so full CPU/RAM detail is persisted;
-/// old rows keep NULL and are read via the scalar fallback.
-const MIGRATIONS: &[(u32, &[&str])] = &[
-    (
-        2,
-        &[
-            "ALTER TABLE system_history ADD COLUMN cpu_data BLOB",
-            "ALTER TABLE system_history ADD COLUMN ram_data BLOB",
-            "ALTER TABLE system_history_aggregated ADD COLUMN cpu_data BLOB",
-            "ALTER TABLE system_history_aggregated ADD COLUMN ram_data BLOB",
-        ],
-    ),
-    // v3 → v4: persist GPU metrics. Nullable; rows without it read as an empty GPU list.
-    (
-        3,
-        &[
-            "ALTER TABLE system_history ADD COLUMN gpu_data BLOB",
-            "ALTER TABLE system_history_aggregated ADD COLUMN gpu_data BLOB",
-        ],
-    ),
-    // v4 → v5: persist SMART disk health. Nullable; absent → empty list.
-    (
-        4,
-        &[
-            "ALTER TABLE system_history ADD COLUMN smart_data BLOB",
-            "ALTER TABLE system_history_aggregated ADD COLUMN smart_data BLOB",
-        ],
-    ),
-];
+/// Connections kept open even when idle, so the first query after a quiet period is cheap.
+const POOL_MIN_CONNECTIONS: u32 = 1;
+/// How long a caller waits for a free pooled connection before failing.
+const POOL_ACQUIRE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// How long a write waits for the writer connection. Longer than for reads: writes queue behind
+/// each other there (an aggregation chunk, a VACUUM) instead of retrying on SQLITE_BUSY.
+const WRITER_ACQUIRE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
 
 impl HistoryRepo {
-    pub async fn connect(path: &str, retention_days: u32) -> anyhow::Result<Self> {
+    /// Open (creating if missing) the SQLite database at `config.path`; a directory there is
+    /// rejected with [`HistoryError::InvalidArgument`]. Reads use a pool capped at
+    /// `max_pool_size`, writes one separate connection; `cache_size_kib`, `mmap_size_bytes` and `temp_store` are applied as
+    /// per-connection pragmas. With `vacuum_mode = "incremental"`, a newly created database gets
+    /// `auto_vacuum = INCREMENTAL` (sqlx applies it before the WAL switch, while the file is empty;
+    /// existing files are converted on their first incremental vacuum).
+    /// With `integrity_check_on_start`, a damaged file is reported (or moved aside) before use;
+    /// see [`open_checked`](Self::open_checked).
+    pub async fn connect(config: &DatabaseConfig) -> HistoryResult<Self> {
+        if config.integrity_check_on_start {
+            Self::open_checked(config).await
+        } else {
+            Self::open(config).await
+        }
+    }
+
+    /// Build the pool without any integrity check.
+    pub(in crate::history_repo) async fn open(config: &DatabaseConfig) -> HistoryResult<Self> {
+        let path = config.path.as_str();
+        read_only::ensure_not_directory(path)?;
         if let Some(parent) = Path::new(path).parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let opts = SqliteConnectOptions::from_str(&format!("sqlite:{}", path))?
+        let mut opts = SqliteConnectOptions::from_str(&format!("sqlite:{}", path))?
             .create_if_missing(true)
             .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
             .busy_timeout(std::time::Duration::from_secs(5))
-            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal);
-        let pool = SqlitePoolOptions::new().connect_with(opts).await?;
-        let retention_ms = (retention_days as i64) * 24 * 60 * 60 * 1000;
-        Ok(Self { pool, retention_ms })
-    }
-
-    async fn drop_history_user_tables(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-    ) -> anyhow::Result<()> {
-        sqlx::query("DROP TABLE IF EXISTS system_history")
-            .execute(&mut **tx)
-            .await?;
-        sqlx::query("DROP TABLE IF EXISTS system_history_aggregated")
-            .execute(&mut **tx)
+            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
+            .pragma("cache_size", format!("-{}", config.cache_size_kib))
+            .pragma("mmap_size", config.mmap_size_bytes.to_string())
+            .pragma("temp_store", config.temp_store.to_uppercase());
+        if config.vacuum_mode == "incremental" {
+            opts = opts.auto_vacuum(SqliteAutoVacuum::Incremental);
+        }
+        // The writer first: it creates the file and switches it to WAL before readers connect.
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .acquire_timeout(WRITER_ACQUIRE_TIMEOUT)
+            .connect_with(opts.clone())
             .await?;
-        sqlx::query("DROP TABLE IF EXISTS system_info")
-            .execute(&mut **tx)
+        let pool = SqlitePoolOptions::new()
+            .max_connections(config.max_pool_size)
+            .min_connections(POOL_MIN_CONNECTIONS.min(config.max_pool_size))
+            .acquire_timeout(POOL_ACQUIRE_TIMEOUT)
+            .connect_with(opts)
             .await?;
-        Ok(())
+        Ok(Self {
+            pool,
+            writer,
+            retention: RetentionPolicy::from_config(config),
+            raw_retention_ms: AtomicI64::new(i64::from(config.retention_days) * MS_PER_DAY),
+            error_retention_ms: AtomicI64::new(i64::from(config.error_retention_days) * MS_PER_DAY),
+            container_history_top_n: config.container_history_top_n,
+            container_history_retention_ms: i64::from(config.container_history_retention_days)
+                * MS_PER_DAY,
+            container_inventory_retention_ms: i64::from(config.container_inventory_retention_days)
+                * MS_PER_DAY,
+            compress_blobs: config.compress_blobs,
+            max_prune_fraction: config.max_prune_fraction,
+            path: config.path.clone(),
+            free_space: std::sync::Arc::new(super::Statvfs),
+        })
     }
 
-    async fn ensure_schema_version(&self) -> anyhow::Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS schema_version (key TEXT PRIMARY KEY, value INTEGER NOT NULL)",
-        )
-        .execute(&self.pool)
-        .await?;
-
-        let row: Option<i64> =
-            sqlx::query_scalar("SELECT value FROM schema_version WHERE key = 'schema'")
-                .fetch_optional(&self.pool)
-                .await?;
-
-        match row {
-            None => {
-                let legacy_tables: i64 = sqlx::query_scalar(
-                    r#"SELECT COUNT(*) FROM sqlite_master
-                       WHERE type = 'table'
-                         AND name IN ('system_history', 'system_info', 'system_history_aggregated')"#,
-                )
-                .fetch_one(&self.pool)
-                .await?;
-
-                if legacy_tables > 0 {
-                    tracing::warn!(
-                        "schema version row missing but history tables present; purging history"
-                    );
-                    let mut tx = self.pool.begin().await?;
-                    Self::drop_history_user_tables(&mut tx).await?;
-                    sqlx::query(
-                        r#"INSERT INTO schema_version (key, value) VALUES ('schema', $1)
-                           ON CONFLICT(key) DO UPDATE SET value = excluded.value"#,
-                    )
-                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
-                    .execute(&mut *tx)
-                    .await?;
-                    tx.commit().await?;
-                } else {
-                    sqlx::query(
-                        r#"INSERT INTO schema_version (key, value) VALUES ('schema', $1)
-                           ON CONFLICT(key) DO NOTHING"#,
-                    )
-                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
-                    .execute(&self.pool)
-                    .await?;
-                }
-            }
-            Some(v) if v == i64::from(CURRENT_SCHEMA_VERSION) => {}
-            Some(found) if found > 0 && found < i64::from(CURRENT_SCHEMA_VERSION) => {
-                // Forward, data-preserving migration.
-                self.run_migrations(found as u32).await?;
-            }
-            Some(found) => {
-                // Downgrade or unknown future version: no safe path, purge.
-                tracing::warn!(
-                    "schema version {} newer than supported {} (downgrade?); purging history",
-                    found,
-                    CURRENT_SCHEMA_VERSION
-                );
-                let mut tx = self.pool.begin().await?;
-                Self::drop_history_user_tables(&mut tx).await?;
-                sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
-                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
-                    .execute(&mut *tx)
-                    .await?;
-                tx.commit().await?;
-            }
-        }
-
-        Ok(())
+    /// Upper bound on pooled connections (`database.max_pool_size`).
+    pub fn pool_max_connections(&self) -> u32 {
+        self.pool.options().get_max_connections()
     }
 
-    /// Apply ordered, additive, data-preserving migrations from `from_version` up to
-    /// `CURRENT_SCHEMA_VERSION`. Each step runs in its own transaction (SQLite DDL is
-    /// transactional, so a crash mid-step rolls back cleanly and is retried next start).
-    /// If a step has no registered migration, falls back to a destructive purge.
-    async fn run_migrations(&self, from_version: u32) -> anyhow::Result<()> {
-        let mut version = from_version;
-        while version < CURRENT_SCHEMA_VERSION {
-            let Some((_, statements)) = MIGRATIONS.iter().find(|(v, _)| *v == version) else {
-                tracing::warn!(
-                    "no migration registered from schema v{}; purging history",
-                    version
-                );
-                let mut tx = self.pool.begin().await?;
-                Self::drop_history_user_tables(&mut tx).await?;
-                sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
-                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
-                    .execute(&mut *tx)
-                    .await?;
-                tx.commit().await?;
-                return Ok(());
-            };
-            let mut tx = self.pool.begin().await?;
-            for stmt in *statements {
-                // `*stmt` is a `&'static str` from the MIGRATIONS table (not user input).
-                sqlx::query(*stmt).execute(&mut *tx).await?;
+    /// Read one of the tuned integer PRAGMAs (`cache_size`, `mmap_size`, `temp_store`) from a
+    /// pooled connection.
+    pub async fn pragma_i64(&self, name: &str) -> HistoryResult<i64> {
+        let sql = match name {
+            "cache_size" => "PRAGMA cache_size",
+            "mmap_size" => "PRAGMA mmap_size",
+            "temp_store" => "PRAGMA temp_store",
+            other => {
+                return Err(HistoryError::InvalidArgument(format!(
+                    "unsupported pragma '{other}'"
+                )));
             }
-            sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
-                .bind(i64::from(version + 1))
-                .execute(&mut *tx)
-                .await?;
-            tx.commit().await?;
-            tracing::info!("migrated history schema v{} -> v{}", version, version + 1);
-            version += 1;
-        }
-        Ok(())
+        };
+        let v = sqlx::query_scalar::<_, i64>(sql)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(v)
     }
 
-    pub async fn init(&self) -> anyhow::Result<()> {
+    pub async fn init(&self) -> HistoryResult<()> {
         self.ensure_schema_version().await?;
 
         sqlx::query(
@@ -194,26 +131,92 @@ impl HistoryRepo {
                 cpu_data BLOB,
                 ram_data BLOB,
                 gpu_data BLOB,
-                smart_data BLOB
+                smart_data BLOB,
+                probe_data BLOB,
+                sensor_data BLOB,
+                memory_total INTEGER NOT NULL DEFAULT 0,
+                cpu_temperature REAL NOT NULL DEFAULT 0,
+                storage_hash BLOB,
+                network_hash BLOB,
+                node TEXT
             )
             "#,
         )
-        .execute(&self.pool)
+        .execute(&self.writer)
         .await?;
 
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_history_created_at ON system_history(created_at)",
         )
-        .execute(&self.pool)
+        .execute(&self.writer)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_history_node_created_at ON system_history(node, created_at)",
+        )
+        .execute(&self.writer)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS blob_store (hash BLOB PRIMARY KEY, data BLOB NOT NULL)",
+        )
+        .execute(&self.writer)
+        .await?;
+        // Back the blob_store GC's reference lookups.
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_history_storage_hash ON system_history(storage_hash)",
+        )
+        .execute(&self.writer)
+        .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_history_network_hash ON system_history(network_hash)",
+        )
+        .execute(&self.writer)
         .await?;
 
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS system_info (id INTEGER PRIMARY KEY CHECK (id = 1), data BLOB NOT NULL)",
         )
-        .execute(&self.pool)
+        .execute(&self.writer)
+        .await?;
+
+        aggregation::init_aggregated_table(&self.writer).await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS aggregation_state (resolution_seconds INTEGER PRIMARY KEY, watermark INTEGER NOT NULL)",
+        )
+        .execute(&self.writer)
         .await?;
 
-        aggregation::init_aggregated_table(&self.pool).await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS collection_errors (id INTEGER PRIMARY KEY AUTOINCREMENT, ts INTEGER NOT NULL, source TEXT NOT NULL, message TEXT NOT NULL, suppressed INTEGER NOT NULL DEFAULT 0)",
+        )
+        .execute(&self.writer)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_collection_errors_ts ON collection_errors(ts)")
+            .execute(&self.writer)
+            .await?;
+
+        sqlx::query(CREATE_CONTAINER_HISTORY)
+            .execute(&self.writer)
+            .await?;
+        sqlx::query(CREATE_CONTAINER_HISTORY_INDEX)
+            .execute(&self.writer)
+            .await?;
+        sqlx::query(CREATE_CONTAINER_HISTORY_TS_INDEX)
+            .execute(&self.writer)
+            .await?;
+
+        sqlx::query(CREATE_CONTAINER_INVENTORY)
+            .execute(&self.writer)
+            .await?;
+        sqlx::query(CREATE_CONTAINER_INVENTORY_INDEX)
+            .execute(&self.writer)
+            .await?;
+
+        sqlx::query(CREATE_SERVICE_EVENTS)
+            .execute(&self.writer)
+            .await?;
 
         Ok(())
     }
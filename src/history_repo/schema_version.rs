@@ -0,0 +1,117 @@
+// Schema version check when the database is opened: migrate an older schema forward, refuse a
+// newer one and purge a legacy or invalid one. The migrations themselves are in migrations.rs.
+
+use super::{CURRENT_SCHEMA_VERSION, HistoryError, HistoryRepo, HistoryResult};
+
+impl HistoryRepo {
+    pub(in crate::history_repo) async fn drop_history_user_tables(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ) -> HistoryResult<()> {
+        sqlx::query("DROP TABLE IF EXISTS system_history")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS system_history_aggregated")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS system_info")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS blob_store")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS aggregation_state")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS collection_errors")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS container_history")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS container_inventory")
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DROP TABLE IF EXISTS service_events")
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    pub(in crate::history_repo) async fn ensure_schema_version(&self) -> HistoryResult<()> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS schema_version (key TEXT PRIMARY KEY, value INTEGER NOT NULL)",
+        )
+        .execute(&self.writer)
+        .await?;
+
+        let row: Option<i64> =
+            sqlx::query_scalar("SELECT value FROM schema_version WHERE key = 'schema'")
+                .fetch_optional(&self.writer)
+                .await?;
+
+        match row {
+            None => {
+                let legacy_tables: i64 = sqlx::query_scalar(
+                    r#"SELECT COUNT(*) FROM sqlite_master
+                       WHERE type = 'table'
+                         AND name IN ('system_history', 'system_info', 'system_history_aggregated')"#,
+                )
+                .fetch_one(&self.writer)
+                .await?;
+
+                if legacy_tables > 0 {
+                    tracing::warn!(
+                        "schema version row missing but history tables present; purging history"
+                    );
+                    let mut tx = self.writer.begin().await?;
+                    Self::drop_history_user_tables(&mut tx).await?;
+                    sqlx::query(
+                        r#"INSERT INTO schema_version (key, value) VALUES ('schema', $1)
+                           ON CONFLICT(key) DO UPDATE SET value = excluded.value"#,
+                    )
+                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
+                    .execute(&mut *tx)
+                    .await?;
+                    tx.commit().await?;
+                } else {
+                    sqlx::query(
+                        r#"INSERT INTO schema_version (key, value) VALUES ('schema', $1)
+                           ON CONFLICT(key) DO NOTHING"#,
+                    )
+                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
+                    .execute(&self.writer)
+                    .await?;
+                }
+            }
+            Some(v) if v == i64::from(CURRENT_SCHEMA_VERSION) => {}
+            Some(found) if found > 0 && found < i64::from(CURRENT_SCHEMA_VERSION) => {
+                // Forward, data-preserving migration.
+                self.run_migrations(found as u32).await?;
+            }
+            Some(found) if found > i64::from(CURRENT_SCHEMA_VERSION) => {
+                // Written by a newer build (downgrade): refuse rather than purge its data.
+                return Err(HistoryError::SchemaTooNew {
+                    found,
+                    supported: CURRENT_SCHEMA_VERSION,
+                });
+            }
+            Some(found) => {
+                // Invalid version (<= 0): no safe path, purge.
+                tracing::warn!(
+                    "schema version {} invalid (supported {}); purging history",
+                    found,
+                    CURRENT_SCHEMA_VERSION
+                );
+                let mut tx = self.writer.begin().await?;
+                Self::drop_history_user_tables(&mut tx).await?;
+                sqlx::query("UPDATE schema_version SET value = $1 WHERE key = 'schema'")
+                    .bind(i64::from(CURRENT_SCHEMA_VERSION))
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+// `service_events`: server starts and clean shutdowns, served at /api/events. A start whose
+// previous lifecycle row is not a clean shutdown is recorded as a recovery after a crash. HTTP
+// check transitions (`check_down` / `check_up`) are kept here too, with the check's `name`.
+
+use sqlx::Row;
+use tracing::instrument;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{ServiceEvent, ServiceEventKind};
+
+pub(in crate::history_repo) const CREATE_SERVICE_EVENTS: &str = "CREATE TABLE IF NOT EXISTS service_events (id INTEGER PRIMARY KEY AUTOINCREMENT, ts INTEGER NOT NULL, event TEXT NOT NULL, name TEXT, detail TEXT)";
+/// The table as created before schema v15, for databases older than the table itself.
+pub(in crate::history_repo) const CREATE_SERVICE_EVENTS_V14: &str = "CREATE TABLE IF NOT EXISTS service_events (id INTEGER PRIMARY KEY AUTOINCREMENT, ts INTEGER NOT NULL, event TEXT NOT NULL)";
+
+impl HistoryRepo {
+    /// Record a start at `ts` (epoch ms): `started` after a clean shutdown or on an empty table,
+    /// `recovered_after_crash` otherwise. Returns the event written.
+    #[instrument(
+        skip(self),
+        fields(repo = "history", operation = "record_service_start")
+    )]
+    pub async fn record_service_start(&self, ts: i64) -> HistoryResult<ServiceEventKind> {
+        // Check rows carry a name; only starts and shutdowns tell how the last run ended.
+        let previous: Option<String> = sqlx::query_scalar(
+            "SELECT event FROM service_events WHERE name IS NULL ORDER BY id DESC LIMIT 1",
+        )
+        .fetch_optional(&self.writer)
+        .await?;
+        let event = match previous.as_deref() {
+            None | Some("clean_shutdown") => ServiceEventKind::Started,
+            Some(_) => ServiceEventKind::RecoveredAfterCrash,
+        };
+        self.insert_service_event(ts, event, None, None).await?;
+        Ok(event)
+    }
+
+    /// Record a clean shutdown at `ts` (epoch ms); the next start is then a plain `started`.
+    #[instrument(
+        skip(self),
+        fields(repo = "history", operation = "record_clean_shutdown")
+    )]
+    pub async fn record_clean_shutdown(&self, ts: i64) -> HistoryResult<()> {
+        self.insert_service_event(ts, ServiceEventKind::CleanShutdown, None, None)
+            .await
+    }
+
+    /// Record HTTP check `name` going down or coming back up at `ts` (epoch ms).
+    #[instrument(
+        skip(self, detail),
+        fields(repo = "history", operation = "record_check_event")
+    )]
+    pub async fn record_check_event(
+        &self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: &str,
+        detail: Option<&str>,
+    ) -> HistoryResult<()> {
+        self.insert_service_event(ts, event, Some(name), detail)
+            .await
+    }
+
+    async fn insert_service_event(
+        &self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: Option<&str>,
+        detail: Option<&str>,
+    ) -> HistoryResult<()> {
+        sqlx::query("INSERT INTO service_events (ts, event, name, detail) VALUES ($1, $2, $3, $4)")
+            .bind(ts)
+            .bind(event.as_str())
+            .bind(name)
+            .bind(detail)
+            .execute(&self.writer)
+            .await?;
+        Ok(())
+    }
+
+    /// The newest `limit` events, newest first. Rows with an event this build does not know
+    /// (written by a newer one) are skipped.
+    pub async fn get_service_events(&self, limit: u32) -> HistoryResult<Vec<ServiceEvent>> {
+        self.retry_busy("get_service_events", || async move {
+            let rows = sqlx::query(
+                "SELECT ts, event, name, detail FROM service_events ORDER BY id DESC LIMIT $1",
+            )
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+            let mut events = Vec::with_capacity(rows.len());
+            for row in rows {
+                let event: String = row.try_get("event")?;
+                if let Some(event) = ServiceEventKind::parse(&event) {
+                    events.push(ServiceEvent {
+                        ts: row.try_get("ts")?,
+                        event,
+                        name: row.try_get("name")?,
+                        detail: row.try_get("detail")?,
+                    });
+                }
+            }
+            Ok(events)
+        })
+        .await
+    }
+}
@@ -0,0 +1,120 @@
+// What the server records beside the snapshots on either engine: collector failures
+// (`collection_errors`), service events and the per-container tables (`container_history`,
+// `container_inventory`, written with each local batch). The SQLite impl is here.
+
+use futures_util::future::BoxFuture;
+
+use crate::history_repo::{HistoryRepo, HistoryResult, TopContainerMetric};
+use crate::models::{
+    CollectionError, ContainerInventoryEntry, ErrorSourceCount, ServiceEvent, ServiceEventKind,
+    TopContainer,
+};
+
+/// Collector failures, service events and container history. Methods mirror the
+/// [`HistoryRepo`] ones of the same name.
+pub trait ServiceHistory: Send + Sync {
+    /// Append one collector failure.
+    fn record_error<'a>(&'a self, error: &'a CollectionError) -> BoxFuture<'a, HistoryResult<()>>;
+
+    /// The newest `limit` failures, newest first.
+    fn get_recent_errors(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<CollectionError>>>;
+
+    /// Failures per source at or after `since_ts`, counting suppressed ones; sorted by source.
+    fn error_counts_since(
+        &self,
+        since_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ErrorSourceCount>>>;
+
+    /// Record a start at `ts`: `started`, or `recovered_after_crash` when the previous run did
+    /// not shut down cleanly. Returns the event written.
+    fn record_service_start(&self, ts: i64) -> BoxFuture<'_, HistoryResult<ServiceEventKind>>;
+
+    fn record_clean_shutdown(&self, ts: i64) -> BoxFuture<'_, HistoryResult<()>>;
+
+    /// Record HTTP check `name` going down or coming back up at `ts`.
+    fn record_check_event<'a>(
+        &'a self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: &'a str,
+        detail: Option<&'a str>,
+    ) -> BoxFuture<'a, HistoryResult<()>>;
+
+    /// The newest `limit` service events, newest first.
+    fn get_service_events(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<ServiceEvent>>>;
+
+    /// Known containers, present ones first; the gone ones too when `include_gone`.
+    fn get_container_inventory(
+        &self,
+        include_gone: bool,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ContainerInventoryEntry>>>;
+
+    /// Containers with the highest average `metric` in [from_ts, to_ts), at most `limit`.
+    fn get_top_containers(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        metric: TopContainerMetric,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<TopContainer>>>;
+}
+
+impl ServiceHistory for HistoryRepo {
+    fn record_error<'a>(&'a self, error: &'a CollectionError) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(HistoryRepo::record_error(self, error))
+    }
+
+    fn get_recent_errors(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<CollectionError>>> {
+        Box::pin(HistoryRepo::get_recent_errors(self, limit))
+    }
+
+    fn error_counts_since(
+        &self,
+        since_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ErrorSourceCount>>> {
+        Box::pin(HistoryRepo::error_counts_since(self, since_ts))
+    }
+
+    fn record_service_start(&self, ts: i64) -> BoxFuture<'_, HistoryResult<ServiceEventKind>> {
+        Box::pin(HistoryRepo::record_service_start(self, ts))
+    }
+
+    fn record_clean_shutdown(&self, ts: i64) -> BoxFuture<'_, HistoryResult<()>> {
+        Box::pin(HistoryRepo::record_clean_shutdown(self, ts))
+    }
+
+    fn record_check_event<'a>(
+        &'a self,
+        ts: i64,
+        event: ServiceEventKind,
+        name: &'a str,
+        detail: Option<&'a str>,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(HistoryRepo::record_check_event(
+            self, ts, event, name, detail,
+        ))
+    }
+
+    fn get_service_events(&self, limit: u32) -> BoxFuture<'_, HistoryResult<Vec<ServiceEvent>>> {
+        Box::pin(HistoryRepo::get_service_events(self, limit))
+    }
+
+    fn get_container_inventory(
+        &self,
+        include_gone: bool,
+    ) -> BoxFuture<'_, HistoryResult<Vec<ContainerInventoryEntry>>> {
+        Box::pin(HistoryRepo::get_container_inventory(self, include_gone))
+    }
+
+    fn get_top_containers(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        metric: TopContainerMetric,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<TopContainer>>> {
+        Box::pin(HistoryRepo::get_top_containers(
+            self, from_ts, to_ts, metric, limit,
+        ))
+    }
+}
@@ -0,0 +1,284 @@
+// `HistoryStore` for the SQLite repo: every method is the inherent one of the same name.
+
+use futures_util::future::BoxFuture;
+
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult, HistoryStore};
+use crate::models::{
+    AggregatedSnapshot, FullSystemSnapshot, HistoryPoint, SyncBatch, SyncCursor, SystemInfo,
+};
+
+impl HistoryStore for HistoryRepo {
+    fn engine(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn as_sqlite(&self) -> Option<&HistoryRepo> {
+        Some(self)
+    }
+
+    fn ping(&self) -> BoxFuture<'_, HistoryResult<()>> {
+        Box::pin(HistoryRepo::ping(self))
+    }
+
+    fn close(&self) -> BoxFuture<'_, ()> {
+        Box::pin(HistoryRepo::close(self))
+    }
+
+    fn save_snapshots<'a>(
+        &'a self,
+        snapshots: &'a [FullSystemSnapshot],
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::save_snapshots(self, snapshots, system_info))
+    }
+
+    fn save_system_info<'a>(
+        &'a self,
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(HistoryRepo::save_system_info(self, system_info))
+    }
+
+    fn get_stored_system_info(&self) -> BoxFuture<'_, HistoryResult<Option<SystemInfo>>> {
+        Box::pin(HistoryRepo::get_stored_system_info(self))
+    }
+
+    fn get_recent_snapshots(
+        &self,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<(Option<SystemInfo>, Vec<FullSystemSnapshot>)>> {
+        Box::pin(HistoryRepo::get_recent_snapshots(self, limit))
+    }
+
+    fn get_snapshots_since(
+        &self,
+        since_ts: i64,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(HistoryRepo::get_snapshots_since(self, since_ts, limit))
+    }
+
+    fn get_sync_batch(
+        &self,
+        cursor: SyncCursor,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<SyncBatch>> {
+        Box::pin(HistoryRepo::get_sync_batch(self, cursor, limit))
+    }
+
+    fn get_raw_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(HistoryRepo::get_raw_snapshots_by_time_range(
+            self, from_ts, to_ts,
+        ))
+    }
+
+    fn get_min_raw_created_at_before(
+        &self,
+        cutoff_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(HistoryRepo::get_min_raw_created_at_before(self, cutoff_ts))
+    }
+
+    fn get_min_raw_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(HistoryRepo::get_min_raw_created_at_in(self, from_ts, to_ts))
+    }
+
+    fn history_raw_cutoff(
+        &self,
+        now_ms: i64,
+        raw_retention_hours: u32,
+    ) -> BoxFuture<'_, HistoryResult<i64>> {
+        Box::pin(HistoryRepo::history_raw_cutoff(
+            self,
+            now_ms,
+            raw_retention_hours,
+        ))
+    }
+
+    fn get_history_points_bounded(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'_, HistoryResult<(Vec<HistoryPoint>, bool)>> {
+        Box::pin(HistoryRepo::get_history_points_bounded(
+            self,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+            downsample,
+            max_points,
+        ))
+    }
+
+    fn get_history_points(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+    ) -> BoxFuture<'_, HistoryResult<Vec<HistoryPoint>>> {
+        Box::pin(HistoryRepo::get_history_points(
+            self,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+            downsample,
+        ))
+    }
+
+    fn get_history(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(HistoryRepo::get_history(
+            self,
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+        ))
+    }
+
+    fn get_history_bounds(&self) -> BoxFuture<'_, HistoryResult<(Option<i64>, Option<i64>)>> {
+        Box::pin(HistoryRepo::get_history_bounds(self))
+    }
+
+    fn save_aggregated_snapshot<'a>(
+        &'a self,
+        agg: &'a AggregatedSnapshot,
+    ) -> BoxFuture<'a, HistoryResult<()>> {
+        Box::pin(HistoryRepo::save_aggregated_snapshot(self, agg))
+    }
+
+    fn get_aggregated_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<AggregatedSnapshot>>> {
+        Box::pin(HistoryRepo::get_aggregated_snapshots_by_time_range(
+            self,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_min_aggregated_created_at_before(
+        &self,
+        cutoff_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(HistoryRepo::get_min_aggregated_created_at_before(
+            self,
+            cutoff_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_min_aggregated_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(HistoryRepo::get_min_aggregated_created_at_in(
+            self,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn get_aggregation_watermark(
+        &self,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>> {
+        Box::pin(HistoryRepo::get_aggregation_watermark(
+            self,
+            resolution_seconds,
+        ))
+    }
+
+    fn clamp_aggregation_clock(&self, now_ms: i64) -> BoxFuture<'_, HistoryResult<i64>> {
+        Box::pin(HistoryRepo::clamp_aggregation_clock(self, now_ms))
+    }
+
+    fn roll_up_raw_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::roll_up_raw_range(
+            self,
+            aggs,
+            from_ts,
+            to_ts,
+            resolution_seconds,
+        ))
+    }
+
+    fn roll_up_aggregated_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        from_resolution: i32,
+        to_resolution: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::roll_up_aggregated_range(
+            self,
+            aggs,
+            from_ts,
+            to_ts,
+            from_resolution,
+            to_resolution,
+        ))
+    }
+
+    fn aggregated_prune_cutoffs(
+        &self,
+        now_ms: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<(i32, i64)>>> {
+        Box::pin(HistoryRepo::aggregated_prune_cutoffs(self, now_ms))
+    }
+
+    fn prune_aggregated_old_data<'a>(
+        &'a self,
+        cutoffs: &'a [(i32, i64)],
+    ) -> BoxFuture<'a, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::prune_aggregated_old_data(self, cutoffs))
+    }
+
+    fn prune_old_data(&self) -> BoxFuture<'_, HistoryResult<u64>> {
+        Box::pin(HistoryRepo::prune_old_data(self))
+    }
+
+    fn set_retention_days(&self, retention_days: u32, error_retention_days: u32) {
+        HistoryRepo::set_retention_days(self, retention_days, error_retention_days);
+    }
+
+    fn aggregation_tiers(&self) -> Vec<i32> {
+        HistoryRepo::aggregation_tiers(self)
+    }
+}
@@ -0,0 +1,34 @@
+// Database statistics for `/api/db`.
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::DbStats;
+
+impl HistoryRepo {
+    /// Schema version, row counts, aggregation watermarks and WAL size.
+    pub async fn db_stats(&self) -> HistoryResult<DbStats> {
+        self.retry_busy("db_stats", || async move {
+            let schema_version: i64 =
+                sqlx::query_scalar("SELECT value FROM schema_version WHERE key = 'schema'")
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .unwrap_or(0);
+            let raw_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_history")
+                .fetch_one(&self.pool)
+                .await?;
+            let aggregated_rows: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM system_history_aggregated")
+                    .fetch_one(&self.pool)
+                    .await?;
+            Ok(DbStats {
+                schema_version,
+                raw_rows,
+                aggregated_rows,
+                blob_store_entries: self.blob_store_count().await?,
+                aggregation_watermarks: self.get_aggregation_watermarks().await?,
+                wal_size_bytes: self.wal_size()?,
+                integrity_problems: None,
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,256 @@
+// The history store behind the writer, the aggregation worker and the history routes:
+// [`HistoryRepo`] (SQLite) or [`PgHistoryRepo`](super::PgHistoryRepo) (Postgres), picked by
+// `database.engine`.
+
+use futures_util::future::BoxFuture;
+
+use crate::history_repo::{DownsampleMode, HistoryRepo, HistoryResult, ServiceHistory};
+use crate::models::{
+    AggregatedSnapshot, FullSystemSnapshot, HistoryPoint, SyncBatch, SyncCursor, SystemInfo,
+};
+
+/// Rows pushed by other instances (`POST /api/ingest`): kept raw under their `node`, never rolled
+/// up, and read back per node for `/api/history?node=`. Methods mirror the [`HistoryRepo`] ones of
+/// the same name.
+pub trait NodeHistory: Send + Sync {
+    /// Store `snapshots` from `node`, skipping timestamps already stored for it. Returns rows
+    /// written.
+    fn save_node_snapshots<'a>(
+        &'a self,
+        node: &'a str,
+        snapshots: &'a [FullSystemSnapshot],
+    ) -> BoxFuture<'a, HistoryResult<u64>>;
+
+    /// `node`'s raw rows in [from_ts, to_ts) as points capped at `max_points`; the flag is true
+    /// when points past the cap were dropped.
+    fn get_node_history_points_bounded<'a>(
+        &'a self,
+        node: &'a str,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'a, HistoryResult<(Vec<HistoryPoint>, bool)>>;
+}
+
+/// Raw snapshots, the aggregated tiers and their watermarks on either engine, plus the rows of
+/// other nodes ([`NodeHistory`]) and the service tables ([`ServiceHistory`]). Methods mirror the
+/// [`HistoryRepo`] ones of the same name. Maintenance of the SQLite file (VACUUM, WAL, backups,
+/// `/api/db`, the free-space guard) is reached through [`Self::as_sqlite`].
+pub trait HistoryStore: NodeHistory + ServiceHistory {
+    /// `database.engine`: "sqlite" or "postgres".
+    fn engine(&self) -> &'static str;
+
+    /// The SQLite repo, for maintenance of its file; `None` on Postgres.
+    fn as_sqlite(&self) -> Option<&HistoryRepo> {
+        None
+    }
+
+    /// Cheap liveness check: a connection can be acquired and queried.
+    fn ping(&self) -> BoxFuture<'_, HistoryResult<()>>;
+
+    /// Close every pooled connection; later calls fail as unavailable.
+    fn close(&self) -> BoxFuture<'_, ()>;
+
+    /// Append local snapshots and replace the stored `SystemInfo`, in one transaction. Returns
+    /// the encoded blob bytes of the batch.
+    fn save_snapshots<'a>(
+        &'a self,
+        snapshots: &'a [FullSystemSnapshot],
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<u64>>;
+
+    fn save_system_info<'a>(
+        &'a self,
+        system_info: &'a SystemInfo,
+    ) -> BoxFuture<'a, HistoryResult<()>>;
+
+    fn get_stored_system_info(&self) -> BoxFuture<'_, HistoryResult<Option<SystemInfo>>>;
+
+    /// The newest `limit` raw snapshots, oldest first, with the stored `SystemInfo`.
+    fn get_recent_snapshots(
+        &self,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<(Option<SystemInfo>, Vec<FullSystemSnapshot>)>>;
+
+    /// Raw snapshots with `created_at > since_ts`, oldest first, at most
+    /// `min(limit, MAX_SNAPSHOTS_SINCE)`.
+    fn get_snapshots_since(
+        &self,
+        since_ts: i64,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>>;
+
+    /// The `/api/history/sync` page after `cursor`; see [`HistoryRepo::get_sync_batch`].
+    fn get_sync_batch(
+        &self,
+        cursor: SyncCursor,
+        limit: u32,
+    ) -> BoxFuture<'_, HistoryResult<SyncBatch>>;
+
+    /// Raw snapshots in [from_ts, to_ts), ascending.
+    fn get_raw_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>>;
+
+    fn get_min_raw_created_at_before(
+        &self,
+        cutoff_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>>;
+
+    fn get_min_raw_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>>;
+
+    /// Boundary between the aggregated tiers and raw rows for history reads; see
+    /// [`HistoryRepo::history_raw_cutoff`].
+    fn history_raw_cutoff(
+        &self,
+        now_ms: i64,
+        raw_retention_hours: u32,
+    ) -> BoxFuture<'_, HistoryResult<i64>>;
+
+    /// Merged raw + aggregated history capped at `max_points`; the flag is true when points past
+    /// the cap were dropped. See [`HistoryRepo::get_history_points_bounded`].
+    fn get_history_points_bounded(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+        max_points: usize,
+    ) -> BoxFuture<'_, HistoryResult<(Vec<HistoryPoint>, bool)>>;
+
+    /// [`Self::get_history_points_bounded`], uncapped.
+    fn get_history_points(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+        downsample: DownsampleMode,
+    ) -> BoxFuture<'_, HistoryResult<Vec<HistoryPoint>>> {
+        Box::pin(async move {
+            let (points, _) = self
+                .get_history_points_bounded(
+                    from_ts,
+                    to_ts,
+                    resolution_secs,
+                    raw_cutoff_ts,
+                    downsample,
+                    usize::MAX,
+                )
+                .await?;
+            Ok(points)
+        })
+    }
+
+    /// [`Self::get_history_points`] without envelopes, raw buckets averaged.
+    fn get_history(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_secs: u32,
+        raw_cutoff_ts: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<FullSystemSnapshot>>> {
+        Box::pin(async move {
+            let points = self
+                .get_history_points(
+                    from_ts,
+                    to_ts,
+                    resolution_secs,
+                    raw_cutoff_ts,
+                    DownsampleMode::default(),
+                )
+                .await?;
+            Ok(points.into_iter().map(|p| p.snapshot).collect())
+        })
+    }
+
+    /// `(earliest, latest)` `created_at` over local raw rows and the configured tiers.
+    fn get_history_bounds(&self) -> BoxFuture<'_, HistoryResult<(Option<i64>, Option<i64>)>>;
+
+    fn save_aggregated_snapshot<'a>(
+        &'a self,
+        agg: &'a AggregatedSnapshot,
+    ) -> BoxFuture<'a, HistoryResult<()>>;
+
+    /// One tier's rows in [from_ts, to_ts), ascending.
+    fn get_aggregated_snapshots_by_time_range(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Vec<AggregatedSnapshot>>>;
+
+    fn get_min_aggregated_created_at_before(
+        &self,
+        cutoff_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>>;
+
+    fn get_min_aggregated_created_at_in(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>>;
+
+    /// Watermark for roll-ups into `resolution_seconds` buckets; `None` before the first pass.
+    fn get_aggregation_watermark(
+        &self,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'_, HistoryResult<Option<i64>>>;
+
+    /// `now_ms`, but never earlier than the last aggregation pass's clock (persisted).
+    fn clamp_aggregation_clock(&self, now_ms: i64) -> BoxFuture<'_, HistoryResult<i64>>;
+
+    /// Write `aggs`, delete the raw rows in [from_ts, to_ts) and advance the
+    /// `resolution_seconds` watermark, atomically. Returns raw rows deleted.
+    fn roll_up_raw_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        resolution_seconds: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>>;
+
+    /// Write `aggs`, delete the `from_resolution` rows in [from_ts, to_ts) and advance the
+    /// `to_resolution` watermark, atomically. Returns source rows deleted.
+    fn roll_up_aggregated_range<'a>(
+        &'a self,
+        aggs: &'a [AggregatedSnapshot],
+        from_ts: i64,
+        to_ts: i64,
+        from_resolution: i32,
+        to_resolution: i32,
+    ) -> BoxFuture<'a, HistoryResult<u64>>;
+
+    /// Per-resolution prune cutoffs at `now_ms`; see [`HistoryRepo::aggregated_prune_cutoffs`].
+    fn aggregated_prune_cutoffs(
+        &self,
+        now_ms: i64,
+    ) -> BoxFuture<'_, HistoryResult<Vec<(i32, i64)>>>;
+
+    /// Delete aggregated rows older than their tier's cutoff. Returns rows removed.
+    fn prune_aggregated_old_data<'a>(
+        &'a self,
+        cutoffs: &'a [(i32, i64)],
+    ) -> BoxFuture<'a, HistoryResult<u64>>;
+
+    /// Delete raw rows past `retention_days` (within `max_prune_fraction`) and whatever else
+    /// the engine expires with them. Returns raw rows removed.
+    fn prune_old_data(&self) -> BoxFuture<'_, HistoryResult<u64>>;
+
+    /// Change raw and error retention at runtime; the next prune uses them.
+    fn set_retention_days(&self, retention_days: u32, error_retention_days: u32);
+
+    /// Configured aggregated tier resolutions, finest first.
+    fn aggregation_tiers(&self) -> Vec<i32>;
+}
@@ -0,0 +1,158 @@
+// Cursor-based raw history sync for clients that keep their own cache (`/api/history/sync`):
+// the page after a `seq` (the `system_history` row id) or, for older clients, a timestamp, and
+// whether the cursor has fallen out of retention.
+
+use crate::history_repo::raw_read::{
+    MAX_SNAPSHOTS_SINCE, RAW_SELECT_AFTER_SEQ, RAW_SELECT_SINCE, parse_rows,
+};
+use crate::history_repo::row::HistoryRow;
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{SyncBatch, SyncCursor};
+
+/// Page size of `/api/history/sync` when the client does not pass `limit`.
+pub const DEFAULT_SYNC_LIMIT: u32 = 500;
+
+/// Whether the cursor's row is still stored ($1 = seq), the oldest local timestamp and the
+/// newest local seq.
+pub(in crate::history_repo) const SYNC_CURSOR_PROBE: &str =
+    "SELECT EXISTS (SELECT 1 FROM system_history WHERE id = $1 AND node IS NULL),
+            (SELECT MIN(created_at) FROM system_history WHERE node IS NULL),
+            (SELECT MAX(id) FROM system_history WHERE node IS NULL)";
+
+/// Row of [`SYNC_CURSOR_PROBE`].
+pub(in crate::history_repo) type SyncProbe = (bool, Option<i64>, Option<i64>);
+
+/// Which rows a sync page reads, after resolving the client's cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(in crate::history_repo) struct SyncPlan {
+    /// `Some(seq)`: rows with `id > seq`, in id order; `None`: rows with `created_at > ts`.
+    seq: Option<i64>,
+    ts: i64,
+    reset: bool,
+    /// Newest local seq when the page was planned.
+    max_seq: Option<i64>,
+}
+
+impl SyncPlan {
+    /// Resolve `cursor` against the probe of [`SYNC_CURSOR_PROBE`]. `since_seq` wins over
+    /// `since_ts`. A seq cursor is stale once its row is gone (pruned, or the database was
+    /// replaced); a timestamp cursor once it is older than the oldest local row. A stale cursor
+    /// restarts from the beginning with `reset`; no cursor (or 0) is an initial sync, never a reset.
+    pub(in crate::history_repo) fn new(cursor: SyncCursor, probe: SyncProbe) -> Self {
+        let (seq_row_exists, oldest_ts, max_seq) = probe;
+        let from_start = Self {
+            seq: Some(0),
+            ts: 0,
+            reset: true,
+            max_seq,
+        };
+        match (cursor.since_seq, cursor.since_ts) {
+            (Some(seq), _) if seq > 0 && seq_row_exists => Self {
+                reset: false,
+                seq: Some(seq as i64),
+                ..from_start
+            },
+            (Some(seq), _) if seq > 0 => from_start,
+            (None, Some(ts)) if ts > 0 && oldest_ts.is_some_and(|oldest| ts as i64 >= oldest) => {
+                Self {
+                    seq: None,
+                    ts: ts as i64,
+                    reset: false,
+                    max_seq,
+                }
+            }
+            (None, Some(ts)) if ts > 0 => from_start,
+            _ => Self {
+                reset: false,
+                ..from_start
+            },
+        }
+    }
+
+    /// The seq to probe for: the requested one, or 0 (never a row) without a seq cursor.
+    pub(in crate::history_repo) fn probe_seq(cursor: SyncCursor) -> i64 {
+        cursor.since_seq.unwrap_or(0) as i64
+    }
+
+    /// The page query and its lower bound.
+    pub(in crate::history_repo) fn query(&self) -> (&'static str, i64) {
+        match self.seq {
+            Some(seq) => (RAW_SELECT_AFTER_SEQ, seq),
+            None => (RAW_SELECT_SINCE, self.ts),
+        }
+    }
+
+    /// `limit` clamped to 1..=[`MAX_SNAPSHOTS_SINCE`], plus the one extra row fetched to tell
+    /// whether more follow.
+    pub(in crate::history_repo) fn fetch_limit(limit: u32) -> i64 {
+        i64::from(limit.clamp(1, MAX_SNAPSHOTS_SINCE)) + 1
+    }
+
+    /// Build the page from `rows` (fetched with [`Self::fetch_limit`]). `next_cursor` follows the
+    /// last row returned, even one skipped for an unknown blob version, so it is never re-sent.
+    /// A timestamp cursor only gains a seq once it has caught up: before that, rows with a
+    /// lower seq but a later timestamp are still to come.
+    pub(in crate::history_repo) fn batch<R: HistoryRow>(
+        &self,
+        cursor: SyncCursor,
+        mut rows: Vec<R>,
+        limit: u32,
+    ) -> HistoryResult<SyncBatch> {
+        let page = limit.clamp(1, MAX_SNAPSHOTS_SINCE) as usize;
+        let has_more = rows.len() > page;
+        rows.truncate(page);
+        let next_cursor = match (rows.last(), self.seq) {
+            (Some(row), Some(_)) => SyncCursor {
+                since_seq: Some(row.get_i64("id")?.max(0) as u64),
+                since_ts: Some(row.get_i64("created_at")?.max(0) as u64),
+            },
+            (Some(row), None) => SyncCursor {
+                since_seq: self.caught_up_seq(has_more),
+                since_ts: Some(row.get_i64("created_at")?.max(0) as u64),
+            },
+            (None, _) if self.reset => SyncCursor::default(),
+            (None, Some(_)) => cursor,
+            (None, None) => SyncCursor {
+                since_seq: self.caught_up_seq(false),
+                ..cursor
+            },
+        };
+        Ok(SyncBatch {
+            snapshots: parse_rows(&rows, HistoryRepo::parse_snapshot_row)?,
+            next_cursor,
+            reset_required: self.reset,
+            has_more,
+        })
+    }
+
+    /// The seq a timestamp-cursor client holds everything up to, once no more rows follow.
+    fn caught_up_seq(&self, has_more: bool) -> Option<u64> {
+        self.max_seq
+            .filter(|_| !has_more)
+            .map(|seq| seq.max(0) as u64)
+    }
+}
+
+impl HistoryRepo {
+    /// Local raw snapshots after `cursor`, at most `min(limit, MAX_SNAPSHOTS_SINCE)`: by seq
+    /// (row id) order when resuming from `since_seq`, by timestamp otherwise. Rows imported
+    /// after the fact get new seqs, so a seq-following client also receives them. See
+    /// [`SyncBatch`] for `resetRequired` and the cursor to send next.
+    pub async fn get_sync_batch(&self, cursor: SyncCursor, limit: u32) -> HistoryResult<SyncBatch> {
+        self.retry_busy("get_sync_batch", || async move {
+            let probe: SyncProbe = sqlx::query_as(SYNC_CURSOR_PROBE)
+                .bind(SyncPlan::probe_seq(cursor))
+                .fetch_one(&self.pool)
+                .await?;
+            let plan = SyncPlan::new(cursor, probe);
+            let (sql, from) = plan.query();
+            let rows = sqlx::query(sql)
+                .bind(from)
+                .bind(SyncPlan::fetch_limit(limit))
+                .fetch_all(&self.pool)
+                .await?;
+            plan.batch(cursor, rows, limit)
+        })
+        .await
+    }
+}
@@ -0,0 +1,159 @@
+// Per-tier row statistics and the steady-state size projection behind /api/db/projection.
+
+use tracing::instrument;
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::{HistoryRepo, HistoryResult, tier_rollup_after_ms};
+use crate::models::{StorageProjection, TierProjection, TierStats};
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// Blob payload of one raw row (storage/network live in `blob_store`, referenced by hash).
+const RAW_STATS_SQL: &str = "SELECT COUNT(*), MIN(created_at), MAX(created_at),
+        COALESCE(SUM(IFNULL(length(container_data), 0) + IFNULL(length(storage_data), 0)
+            + IFNULL(length(network_data), 0) + IFNULL(length(system_data), 0)
+            + IFNULL(length(cpu_data), 0) + IFNULL(length(ram_data), 0)
+            + IFNULL(length(gpu_data), 0) + IFNULL(length(smart_data), 0)
+            + IFNULL(length(probe_data), 0) + IFNULL(length(sensor_data), 0)
+            + IFNULL(length(storage_hash), 0) + IFNULL(length(network_hash), 0)), 0)
+     FROM system_history";
+
+const AGGREGATED_STATS_SQL: &str =
+    "SELECT resolution_seconds, COUNT(*), MIN(created_at), MAX(created_at),
+        COALESCE(SUM(IFNULL(length(container_data), 0) + IFNULL(length(storage_data), 0)
+            + IFNULL(length(network_data), 0) + IFNULL(length(system_data), 0)
+            + IFNULL(length(cpu_data), 0) + IFNULL(length(ram_data), 0)
+            + IFNULL(length(gpu_data), 0) + IFNULL(length(smart_data), 0)
+            + IFNULL(length(probe_data), 0) + IFNULL(length(sensor_data), 0)), 0)
+     FROM system_history_aggregated GROUP BY resolution_seconds";
+
+/// (rows, oldest, newest, bytes).
+type StatsRow = (i64, Option<i64>, Option<i64>, i64);
+type AggregatedStatsRow = (i32, i64, Option<i64>, Option<i64>, i64);
+
+impl HistoryRepo {
+    /// Row count, time span and blob bytes for raw and each aggregated tier, finest first.
+    /// Empty tiers are included with zero rows.
+    #[instrument(skip(self), fields(repo = "history", operation = "get_tier_stats"))]
+    pub async fn get_tier_stats(&self) -> HistoryResult<Vec<TierStats>> {
+        self.retry_busy("get_tier_stats", || async move {
+            let (rows, oldest, newest, bytes): StatsRow =
+                sqlx::query_as(RAW_STATS_SQL).fetch_one(&self.pool).await?;
+            let shared_bytes: i64 =
+                sqlx::query_scalar("SELECT COALESCE(SUM(length(data)), 0) FROM blob_store")
+                    .fetch_one(&self.pool)
+                    .await?;
+            let mut tiers = vec![tier_stats(0, (rows, oldest, newest, bytes + shared_bytes))];
+
+            let aggregated: Vec<AggregatedStatsRow> = sqlx::query_as(AGGREGATED_STATS_SQL)
+                .fetch_all(&self.pool)
+                .await?;
+            for resolution in self.retention.resolutions() {
+                let row = aggregated
+                    .iter()
+                    .find(|r| r.0 == resolution)
+                    .map(|&(_, rows, oldest, newest, bytes)| (rows, oldest, newest, bytes))
+                    .unwrap_or((0, None, None, 0));
+                tiers.push(tier_stats(resolution, row));
+            }
+            Ok(tiers)
+        })
+        .await
+    }
+}
+
+fn tier_stats(resolution_seconds: i32, (rows, oldest, newest, total_bytes): StatsRow) -> TierStats {
+    let rows = rows.max(0) as u64;
+    let total_bytes = total_bytes.max(0) as u64;
+    let avg_row_bytes = total_bytes.checked_div(rows).unwrap_or(0);
+    // n evenly spaced rows spanning `span` ms cover (n - 1) intervals of history.
+    let bytes_per_day = match (oldest, newest) {
+        (Some(oldest), Some(newest)) if rows >= 2 && newest > oldest => {
+            let rows_per_day = (rows - 1) as f64 * MS_PER_DAY / (newest - oldest) as f64;
+            Some((rows_per_day * avg_row_bytes as f64).round() as u64)
+        }
+        _ => None,
+    };
+    TierStats {
+        resolution_seconds,
+        rows,
+        oldest,
+        newest,
+        total_bytes,
+        avg_row_bytes,
+        bytes_per_day,
+    }
+}
+
+/// Age range (hours) a tier holds in steady state, before the retention caps: raw until
+/// `raw_retention_hours`, then each configured tier until it rolls into the next; the coarsest
+/// tier has no upper bound.
+fn tier_age_hours(resolution_seconds: i32, config: &DatabaseConfig) -> (f64, f64) {
+    let raw = f64::from(config.raw_retention_hours);
+    let rollup_hours = |index: usize| {
+        tier_rollup_after_ms(
+            index,
+            config.minute_retention_hours,
+            config.five_minute_retention_days,
+            config.hourly_retention_days,
+        ) as f64
+            / MS_PER_HOUR
+    };
+    if resolution_seconds == 0 {
+        return (0.0, raw);
+    }
+    let tiers = &config.aggregation_tiers;
+    match tiers.iter().position(|&r| r == resolution_seconds) {
+        Some(i) => {
+            let from = if i == 0 { raw } else { rollup_hours(i - 1) };
+            let to = if i + 1 == tiers.len() {
+                f64::INFINITY
+            } else {
+                rollup_hours(i)
+            };
+            (from, to)
+        }
+        None => (0.0, 0.0),
+    }
+}
+
+/// Project each tier's size once retention is in steady state and compare the total to
+/// `database.disk_budget_bytes`. Raw rows are capped at `retention_days`, aggregated tiers at
+/// `aggregated_retention_days`. Without aggregation all history stays raw.
+pub fn project_storage(tiers: &[TierStats], config: &DatabaseConfig) -> StorageProjection {
+    let raw_cap_hours = f64::from(config.retention_days) * 24.0;
+    let aggregated_cap_hours = f64::from(config.aggregated_retention_days) * 24.0;
+    let tiers: Vec<TierProjection> = tiers
+        .iter()
+        .map(|stats| {
+            let (from, to) = if config.enable_aggregation {
+                tier_age_hours(stats.resolution_seconds, config)
+            } else if stats.resolution_seconds == 0 {
+                (0.0, raw_cap_hours)
+            } else {
+                (0.0, 0.0)
+            };
+            let cap = if stats.resolution_seconds == 0 {
+                raw_cap_hours
+            } else {
+                aggregated_cap_hours
+            };
+            let window_days = (to.min(cap) - from.min(cap)).max(0.0) * MS_PER_HOUR / MS_PER_DAY;
+            TierProjection {
+                stats: stats.clone(),
+                window_days,
+                projected_bytes: stats
+                    .bytes_per_day
+                    .map(|rate| (rate as f64 * window_days).round() as u64),
+            }
+        })
+        .collect();
+    let projected_bytes = tiers.iter().filter_map(|t| t.projected_bytes).sum();
+    StorageProjection {
+        tiers,
+        projected_bytes,
+        disk_budget_bytes: config.disk_budget_bytes,
+        exceeds_budget: config.disk_budget_bytes > 0 && projected_bytes > config.disk_budget_bytes,
+    }
+}
@@ -0,0 +1,210 @@
+// `container_history`: the busiest containers of each raw snapshot as narrow rows, so "top
+// consumers over a range" is one SQL aggregate instead of decoding every container blob.
+
+use crate::history_repo::{HistoryError, HistoryRepo, HistoryResult};
+use crate::models::{FullSystemSnapshot, TopContainer};
+use sqlx::{AssertSqlSafe, Database, Encode, QueryBuilder, Sqlite, Type};
+
+pub(super) const CREATE_CONTAINER_HISTORY: &str = "CREATE TABLE IF NOT EXISTS container_history (created_at INTEGER NOT NULL, container_id TEXT NOT NULL, name TEXT NOT NULL, cpu_percent REAL NOT NULL, mem_bytes INTEGER NOT NULL, rx_rate REAL NOT NULL, tx_rate REAL NOT NULL)";
+pub(super) const CREATE_CONTAINER_HISTORY_INDEX: &str = "CREATE INDEX IF NOT EXISTS idx_container_history_id_created_at ON container_history(container_id, created_at)";
+/// Backs range scans over every container and the retention prune.
+pub(super) const CREATE_CONTAINER_HISTORY_TS_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_container_history_created_at ON container_history(created_at)";
+
+/// Binds per `container_history` row in the multi-row INSERT below.
+const INSERT_BINDS: usize = 7;
+/// Rows per INSERT statement, under SQLite's historical 999-variable limit.
+pub(in crate::history_repo) const INSERT_CHUNK_ROWS: usize = 999 / INSERT_BINDS;
+
+const CONTAINER_HISTORY_INSERT: &str = "INSERT INTO container_history (created_at, container_id, name, cpu_percent, mem_bytes, rx_rate, tx_rate) ";
+
+/// What `GET /api/history/top-containers` ranks by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopContainerMetric {
+    Cpu,
+    Memory,
+    RxRate,
+    TxRate,
+}
+
+impl TopContainerMetric {
+    /// Accepted `metric` values, as listed in errors.
+    pub const NAMES: &[&str] = &["cpu", "memory", "rx", "tx"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "cpu" => Some(Self::Cpu),
+            "memory" | "mem" => Some(Self::Memory),
+            "rx" => Some(Self::RxRate),
+            "tx" => Some(Self::TxRate),
+            _ => None,
+        }
+    }
+
+    pub(in crate::history_repo) fn column(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu_percent",
+            Self::Memory => "mem_bytes",
+            Self::RxRate => "rx_rate",
+            Self::TxRate => "tx_rate",
+        }
+    }
+}
+
+/// One `container_history` row.
+pub(in crate::history_repo) struct ContainerRow<'a> {
+    created_at: i64,
+    id: &'a str,
+    name: &'a str,
+    cpu_percent: f64,
+    mem_bytes: i64,
+    rx_rate: f64,
+    tx_rate: f64,
+}
+
+/// `container_history` INSERT of `rows`, on either engine.
+pub(in crate::history_repo) fn container_history_insert<'a, DB>(
+    rows: &'a [ContainerRow<'a>],
+) -> QueryBuilder<DB>
+where
+    DB: Database,
+    DB::Arguments: Default,
+    for<'t> i64: Encode<'t, DB> + Type<DB>,
+    for<'t> f64: Encode<'t, DB> + Type<DB>,
+    for<'t> &'t str: Encode<'t, DB> + Type<DB>,
+{
+    let mut qb = QueryBuilder::<DB>::new(CONTAINER_HISTORY_INSERT);
+    qb.push_values(rows, |mut b, r| {
+        b.push_bind(r.created_at)
+            .push_bind(r.id)
+            .push_bind(r.name)
+            .push_bind(r.cpu_percent)
+            .push_bind(r.mem_bytes)
+            .push_bind(r.rx_rate)
+            .push_bind(r.tx_rate);
+    });
+    qb
+}
+
+/// The `top_n` containers of each snapshot by CPU (ties broken by name).
+pub(in crate::history_repo) fn container_rows(
+    snapshots: &[FullSystemSnapshot],
+    top_n: usize,
+) -> Vec<ContainerRow<'_>> {
+    let mut rows = Vec::new();
+    for s in snapshots {
+        let mut busiest: Vec<_> = s.containers.iter().collect();
+        busiest.sort_by(|a, b| {
+            b.cpu_percent
+                .total_cmp(&a.cpu_percent)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        rows.extend(busiest.into_iter().take(top_n).map(|c| ContainerRow {
+            created_at: s.timestamp as i64,
+            id: &c.id,
+            name: &c.name,
+            cpu_percent: c.cpu_percent,
+            mem_bytes: c.memory_usage_bytes as i64,
+            rx_rate: c.network_rx_bytes_per_sec,
+            tx_rate: c.network_tx_bytes_per_sec,
+        }));
+    }
+    rows
+}
+
+/// `container_id, name, latest, average, peak, samples` of the top-containers query.
+pub(in crate::history_repo) type TopContainerRecord = (String, String, i64, f64, f64, i64);
+
+pub(in crate::history_repo) fn top_container(
+    (container_id, name, _, average, max, samples): TopContainerRecord,
+) -> TopContainer {
+    TopContainer {
+        container_id,
+        name,
+        average,
+        max,
+        samples: samples.max(0) as u64,
+    }
+}
+
+/// Rejects an empty or inverted range.
+pub(in crate::history_repo) fn check_range(from_ts: i64, to_ts: i64) -> HistoryResult<()> {
+    if from_ts >= to_ts {
+        return Err(HistoryError::InvalidArgument(
+            "from must be less than to".into(),
+        ));
+    }
+    Ok(())
+}
+
+impl HistoryRepo {
+    /// Write the `top_n` busiest containers of each snapshot in the flush's transaction.
+    pub(in crate::history_repo) async fn put_container_history(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        snapshots: &[FullSystemSnapshot],
+        top_n: usize,
+    ) -> HistoryResult<()> {
+        let rows = container_rows(snapshots, top_n);
+        for chunk in rows.chunks(INSERT_CHUNK_ROWS) {
+            container_history_insert::<Sqlite>(chunk)
+                .build()
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Containers with the highest average `metric` over `container_history` rows in
+    /// [from_ts, to_ts), at most `limit`, highest first (ties by name). A container only counts
+    /// the snapshots in which it was among the `container_history_top_n` busiest; it is named as
+    /// in `container_inventory`, so a renamed or long-gone container shows its last name.
+    pub async fn get_top_containers(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        metric: TopContainerMetric,
+        limit: u32,
+    ) -> HistoryResult<Vec<TopContainer>> {
+        check_range(from_ts, to_ts)?;
+        let column = metric.column();
+        // `column` comes from the fixed list above, not from user input. The name is the one in
+        // `container_inventory`, else the bare `h.name` next to MAX(created_at), which is taken
+        // from the container's latest row (SQLite min/max semantics).
+        let sql = format!(
+            "SELECT h.container_id,
+                    COALESCE((SELECT i.name FROM container_inventory i
+                              WHERE i.container_id = h.container_id), h.name) AS name,
+                    MAX(h.created_at) AS latest, AVG({column}) AS average,
+                    CAST(MAX({column}) AS REAL) AS peak, COUNT(*) AS samples
+             FROM container_history h
+             WHERE h.created_at >= $1 AND h.created_at < $2
+             GROUP BY h.container_id
+             ORDER BY average DESC, name
+             LIMIT $3"
+        );
+        self.retry_busy("get_top_containers", || {
+            let sql = sql.clone();
+            async move {
+                let rows: Vec<TopContainerRecord> = sqlx::query_as(AssertSqlSafe(sql))
+                    .bind(from_ts)
+                    .bind(to_ts)
+                    .bind(i64::from(limit))
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.into_iter().map(top_container).collect())
+            }
+        })
+        .await
+    }
+
+    /// Delete `container_history` rows older than `database.container_history_retention_days`
+    /// (relative to `now_ms`). Returns rows removed. Called from
+    /// [`prune_old_data`](Self::prune_old_data).
+    pub async fn prune_container_history(&self, now_ms: i64) -> HistoryResult<u64> {
+        let r = sqlx::query("DELETE FROM container_history WHERE created_at < $1")
+            .bind(now_ms - self.container_history_retention_ms)
+            .execute(&self.writer)
+            .await?;
+        Ok(r.rows_affected())
+    }
+}
@@ -0,0 +1,102 @@
+// Space reclamation: free-page accounting, full VACUUM and incremental vacuum.
+
+use sqlx::AssertSqlSafe;
+use tracing::instrument;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+
+/// Page accounting from `PRAGMA page_count` / `freelist_count` / `page_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fragmentation {
+    pub page_count: i64,
+    pub freelist_count: i64,
+    pub page_size: i64,
+}
+
+impl Fragmentation {
+    /// Database file size implied by the page count, in bytes.
+    pub fn size_bytes(&self) -> i64 {
+        self.page_count * self.page_size
+    }
+
+    /// Share of pages on the freelist, 0.0–100.0 (0 for an empty database).
+    pub fn free_percent(&self) -> f64 {
+        if self.page_count <= 0 {
+            return 0.0;
+        }
+        self.freelist_count as f64 / self.page_count as f64 * 100.0
+    }
+
+    /// Whether free pages exceed `min_free_percent` of the file, i.e. a VACUUM is worth its cost.
+    pub fn needs_vacuum(&self, min_free_percent: u32) -> bool {
+        self.freelist_count > 0 && self.free_percent() > f64::from(min_free_percent)
+    }
+}
+
+impl HistoryRepo {
+    /// Current page and freelist counts (cheap: reads the database header).
+    pub async fn fragmentation(&self) -> HistoryResult<Fragmentation> {
+        let mut conn = self.pool.acquire().await?;
+        let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+            .fetch_one(&mut *conn)
+            .await?;
+        let freelist_count: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+            .fetch_one(&mut *conn)
+            .await?;
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+            .fetch_one(&mut *conn)
+            .await?;
+        Ok(Fragmentation {
+            page_count,
+            freelist_count,
+            page_size,
+        })
+    }
+
+    /// `PRAGMA auto_vacuum`: 0 = NONE, 1 = FULL, 2 = INCREMENTAL. On a file whose mode was changed
+    /// but not yet vacuumed, this is the pending value.
+    pub async fn auto_vacuum(&self) -> HistoryResult<i64> {
+        let v = sqlx::query_scalar::<_, i64>("PRAGMA auto_vacuum")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(v)
+    }
+
+    /// Reclaim space after deletes by rewriting the whole file (blocks writers while it runs).
+    #[instrument(skip(self), fields(repo = "history", operation = "vacuum"))]
+    pub async fn vacuum(&self) -> HistoryResult<()> {
+        sqlx::query("VACUUM").execute(&self.writer).await?;
+        Ok(())
+    }
+
+    /// Release up to `pages` free pages back to the filesystem (`PRAGMA incremental_vacuum(N)`).
+    /// A file created before incremental mode was enabled (auto_vacuum = NONE on disk) frees
+    /// nothing; it is switched over with one full VACUUM instead.
+    #[instrument(skip(self), fields(repo = "history", operation = "incremental_vacuum"))]
+    pub async fn incremental_vacuum(&self, pages: u32) -> HistoryResult<()> {
+        let mut conn = self.writer.acquire().await?;
+        let free_before: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+            .fetch_one(&mut *conn)
+            .await?;
+        // `pages` is an integer, so the formatted statement cannot carry injected SQL.
+        sqlx::query(AssertSqlSafe(format!("PRAGMA incremental_vacuum({pages})")))
+            .execute(&mut *conn)
+            .await?;
+        let free_after: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+            .fetch_one(&mut *conn)
+            .await?;
+        // `PRAGMA auto_vacuum` reports the pending per-connection setting, not the on-disk mode,
+        // so a no-op is the reliable sign the file is not INCREMENTAL yet.
+        if free_before > 0 && free_after >= free_before {
+            tracing::info!(
+                free_pages = free_before,
+                "switching database to auto_vacuum = INCREMENTAL (one full VACUUM)"
+            );
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("VACUUM").execute(&mut *conn).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+// Blob verification and repair (`homeserver-cli verify`, `GET /api/db/verify`): strictly decode
+// every stored blob and report the rows the readers would silently replace with empty/default
+// values, then delete them by id.
+
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row, Sqlite};
+use tracing::instrument;
+
+use crate::history_repo::blob::{self, BLOB_VERSION, BLOB_VERSION_SYSTEM_DYNAMIC};
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::{
+    CpuStats, GpuStats, NetworkStats, ProbeStat, RamStats, SensorStat, SmartHealth, StorageStats,
+    SystemStats, SystemStatsDynamic,
+};
+
+const RAW_VERIFY_SQL: &str = "SELECT h.id, h.created_at, h.container_data, h.storage_data,
+        h.network_data, h.system_data, h.cpu_data, h.ram_data, h.gpu_data, h.smart_data,
+        h.probe_data, h.sensor_data, h.storage_hash, h.network_hash, bs.data AS storage_shared, bn.data AS network_shared
+     FROM system_history h
+     LEFT JOIN blob_store bs ON bs.hash = h.storage_hash
+     LEFT JOIN blob_store bn ON bn.hash = h.network_hash
+     WHERE h.created_at >= $1 AND h.created_at < $2
+     ORDER BY h.id";
+
+const AGGREGATED_VERIFY_SQL: &str = "SELECT id, created_at, container_data, storage_data,
+        network_data, system_data, cpu_data, ram_data, gpu_data, smart_data, probe_data,
+        sensor_data
+     FROM system_history_aggregated
+     WHERE created_at >= $1 AND created_at < $2
+     ORDER BY id";
+
+/// Ids per DELETE statement, under SQLite's historical 999-variable limit.
+const DELETE_CHUNK_IDS: usize = 999;
+
+/// A history table whose rows carry blobs; serialized as the table name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HistoryTable {
+    #[serde(rename = "system_history")]
+    Raw,
+    #[serde(rename = "system_history_aggregated")]
+    Aggregated,
+}
+
+impl HistoryTable {
+    pub fn name(self) -> &'static str {
+        match self {
+            HistoryTable::Raw => "system_history",
+            HistoryTable::Aggregated => "system_history_aggregated",
+        }
+    }
+}
+
+/// One blob that failed to decode.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorruptBlob {
+    pub table: HistoryTable,
+    pub id: i64,
+    pub created_at: i64,
+    pub column: &'static str,
+    /// The blob's prefix byte (0 for a dangling `blob_store` reference).
+    pub version: u8,
+    pub reason: String,
+}
+
+/// Result of [`HistoryRepo::verify_blobs`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub raw_rows: u64,
+    pub aggregated_rows: u64,
+    /// The row cap was reached before the end of the range.
+    pub truncated: bool,
+    pub corrupt: Vec<CorruptBlob>,
+}
+
+impl VerifyReport {
+    /// Distinct ids of `table`'s rows with at least one corrupt blob, ascending.
+    pub fn corrupt_ids(&self, table: HistoryTable) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .corrupt
+            .iter()
+            .filter(|c| c.table == table)
+            .map(|c| c.id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// A blob that failed: (column, version byte, reason).
+type Problem = (&'static str, u8, String);
+
+/// Strictly decode `bytes` as `T`; empty blobs (legacy rows, hashed inline columns) are absent,
+/// not corrupt.
+fn check<T>(problems: &mut Vec<Problem>, bytes: &[u8], version: u8, column: &'static str)
+where
+    T: for<'de> wincode::SchemaRead<'de, wincode::config::DefaultConfig, Dst = T>,
+{
+    if bytes.is_empty() {
+        return;
+    }
+    if let Err(e) = blob::decode_blob::<T>(bytes, version, column) {
+        problems.push((column, blob::blob_version(bytes), e.to_string()));
+    }
+}
+
+/// Columns shared by both tables. Raw `system_data` may still be a legacy full `SystemStats`.
+fn check_common(row: &SqliteRow, table: HistoryTable) -> HistoryResult<Vec<Problem>> {
+    let blob = |column| -> HistoryResult<Vec<u8>> {
+        Ok(row
+            .try_get::<Option<Vec<u8>>, _>(column)?
+            .unwrap_or_default())
+    };
+    let mut p = Vec::new();
+    let containers = blob("container_data")?;
+    if !containers.is_empty()
+        && let Err(e) = blob::decode_containers(&containers, "container_data")
+    {
+        p.push((
+            "container_data",
+            blob::blob_version(&containers),
+            e.to_string(),
+        ));
+    }
+    let system = blob("system_data")?;
+    let dynamic = matches!(
+        blob::blob_version(&system),
+        blob::BLOB_VERSION_SYSTEM_DYNAMIC | blob::BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED
+    );
+    if table == HistoryTable::Raw && !dynamic {
+        check::<SystemStats>(&mut p, &system, BLOB_VERSION, "system_data");
+    } else {
+        check::<SystemStatsDynamic>(&mut p, &system, BLOB_VERSION_SYSTEM_DYNAMIC, "system_data");
+    }
+    check::<CpuStats>(&mut p, &blob("cpu_data")?, BLOB_VERSION, "cpu_data");
+    check::<RamStats>(&mut p, &blob("ram_data")?, BLOB_VERSION, "ram_data");
+    check::<Vec<GpuStats>>(&mut p, &blob("gpu_data")?, BLOB_VERSION, "gpu_data");
+    check::<Vec<SmartHealth>>(&mut p, &blob("smart_data")?, BLOB_VERSION, "smart_data");
+    check::<Vec<ProbeStat>>(&mut p, &blob("probe_data")?, BLOB_VERSION, "probe_data");
+    check::<Vec<SensorStat>>(&mut p, &blob("sensor_data")?, BLOB_VERSION, "sensor_data");
+    Ok(p)
+}
+
+/// A raw storage/network section: the `blob_store` entry when the row has a hash (it must
+/// exist), else the inline column.
+fn check_shared<T>(
+    problems: &mut Vec<Problem>,
+    row: &SqliteRow,
+    column: &'static str,
+    hash_column: &str,
+    shared_column: &str,
+) -> HistoryResult<()>
+where
+    T: for<'de> wincode::SchemaRead<'de, wincode::config::DefaultConfig, Dst = T>,
+{
+    let hash: Option<Vec<u8>> = row.try_get(hash_column)?;
+    let shared: Option<Vec<u8>> = row.try_get(shared_column)?;
+    match (hash, shared) {
+        (Some(_), None) => {
+            problems.push((column, 0, format!("{hash_column} has no blob_store entry")))
+        }
+        (Some(_), Some(bytes)) => check::<T>(problems, &bytes, BLOB_VERSION, column),
+        (None, _) => check::<T>(
+            problems,
+            &row.try_get::<Vec<u8>, _>(column)?,
+            BLOB_VERSION,
+            column,
+        ),
+    }
+    Ok(())
+}
+
+fn check_row(row: &SqliteRow, table: HistoryTable) -> HistoryResult<Vec<Problem>> {
+    let mut p = check_common(row, table)?;
+    match table {
+        HistoryTable::Raw => {
+            check_shared::<StorageStats>(
+                &mut p,
+                row,
+                "storage_data",
+                "storage_hash",
+                "storage_shared",
+            )?;
+            check_shared::<NetworkStats>(
+                &mut p,
+                row,
+                "network_data",
+                "network_hash",
+                "network_shared",
+            )?;
+        }
+        HistoryTable::Aggregated => {
+            let storage: Vec<u8> = row.try_get("storage_data")?;
+            let network: Vec<u8> = row.try_get("network_data")?;
+            check::<StorageStats>(&mut p, &storage, BLOB_VERSION, "storage_data");
+            check::<NetworkStats>(&mut p, &network, BLOB_VERSION, "network_data");
+        }
+    }
+    Ok(p)
+}
+
+impl HistoryRepo {
+    /// Decode every blob of the raw, then aggregated, rows with `created_at` in
+    /// `[from_ts, to_ts)` (storage/network through `blob_store`) and report the ones that fail.
+    /// Stops with `truncated` after `max_rows` rows. Rows are streamed, so memory stays flat.
+    #[instrument(skip(self), fields(repo = "history", operation = "verify_blobs"))]
+    pub async fn verify_blobs(
+        &self,
+        from_ts: i64,
+        to_ts: i64,
+        max_rows: u64,
+    ) -> HistoryResult<VerifyReport> {
+        let mut report = VerifyReport::default();
+        for (table, sql) in [
+            (HistoryTable::Raw, RAW_VERIFY_SQL),
+            (HistoryTable::Aggregated, AGGREGATED_VERIFY_SQL),
+        ] {
+            let mut rows = sqlx::query(sql).bind(from_ts).bind(to_ts).fetch(&self.pool);
+            while let Some(row) = rows.try_next().await? {
+                if report.raw_rows + report.aggregated_rows >= max_rows {
+                    report.truncated = true;
+                    return Ok(report);
+                }
+                match table {
+                    HistoryTable::Raw => report.raw_rows += 1,
+                    HistoryTable::Aggregated => report.aggregated_rows += 1,
+                }
+                let problems = check_row(&row, table)?;
+                if problems.is_empty() {
+                    continue;
+                }
+                let id: i64 = row.try_get("id")?;
+                let created_at: i64 = row.try_get("created_at")?;
+                report
+                    .corrupt
+                    .extend(
+                        problems
+                            .into_iter()
+                            .map(|(column, version, reason)| CorruptBlob {
+                                table,
+                                id,
+                                created_at,
+                                column,
+                                version,
+                                reason,
+                            }),
+                    );
+            }
+        }
+        Ok(report)
+    }
+
+    /// Delete `table`'s rows with the given ids (e.g. [`VerifyReport::corrupt_ids`]) in one
+    /// transaction; raw deletes also drop `blob_store` entries left unreferenced. Returns rows
+    /// removed.
+    #[instrument(skip(self, ids), fields(repo = "history", operation = "delete_rows", ids = ids.len()))]
+    pub async fn delete_rows(&self, table: HistoryTable, ids: &[i64]) -> HistoryResult<u64> {
+        let mut tx = self.writer.begin().await?;
+        let mut removed = 0;
+        for chunk in ids.chunks(DELETE_CHUNK_IDS) {
+            let mut qb = QueryBuilder::<Sqlite>::new(match table {
+                HistoryTable::Raw => "DELETE FROM system_history WHERE id IN (",
+                HistoryTable::Aggregated => "DELETE FROM system_history_aggregated WHERE id IN (",
+            });
+            let mut list = qb.separated(", ");
+            for id in chunk {
+                list.push_bind(id);
+            }
+            qb.push(")");
+            removed += qb.build().execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        if table == HistoryTable::Raw && removed > 0 {
+            self.gc_blob_store().await?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,57 @@
+// WAL maintenance: database and `-wal` file sizes, explicit TRUNCATE checkpoints.
+
+use sqlx::Row;
+use tracing::instrument;
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+
+/// Result row of `PRAGMA wal_checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalCheckpoint {
+    /// A reader or writer blocked the checkpoint from completing.
+    pub busy: bool,
+    /// Frames in the WAL before the checkpoint.
+    pub log_frames: i64,
+    /// Frames copied back into the database file.
+    pub checkpointed_frames: i64,
+}
+
+impl HistoryRepo {
+    /// Size of the `-wal` file next to the database, in bytes; 0 when it does not exist.
+    pub fn wal_size(&self) -> HistoryResult<u64> {
+        let mut wal = self.file_path();
+        wal.push("-wal");
+        match std::fs::metadata(&wal) {
+            Ok(m) => Ok(m.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// On-disk size of the history: the database file plus its `-wal`, in bytes.
+    pub fn file_size(&self) -> HistoryResult<u64> {
+        Ok(std::fs::metadata(self.file_path())?.len() + self.wal_size()?)
+    }
+
+    fn file_path(&self) -> std::ffi::OsString {
+        self.pool
+            .connect_options()
+            .get_filename()
+            .as_os_str()
+            .to_owned()
+    }
+
+    /// Copy the WAL back into the database and truncate it (`PRAGMA wal_checkpoint(TRUNCATE)`).
+    /// A long-running reader makes this report `busy` and leave the WAL in place.
+    #[instrument(skip(self), fields(repo = "history", operation = "wal_checkpoint"))]
+    pub async fn wal_checkpoint(&self) -> HistoryResult<WalCheckpoint> {
+        let row = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(&self.writer)
+            .await?;
+        Ok(WalCheckpoint {
+            busy: row.try_get::<i64, _>(0)? != 0,
+            log_frames: row.try_get(1)?,
+            checkpointed_frames: row.try_get(2)?,
+        })
+    }
+}
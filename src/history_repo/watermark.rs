@@ -0,0 +1,83 @@
+// `aggregation_state`: per-tier watermark (end of the last fully aggregated bucket), plus the
+// clock of the last aggregation pass.
+
+use crate::history_repo::{HistoryRepo, HistoryResult};
+use crate::models::AggregationWatermark;
+use tracing::warn;
+
+/// `aggregation_state` key holding the last pass's clock rather than a tier watermark.
+pub(in crate::history_repo) const LAST_PASS_CLOCK_KEY: i32 = 0;
+
+impl HistoryRepo {
+    /// Watermark for roll-ups into `resolution_seconds` buckets; `None` before the first pass.
+    pub async fn get_aggregation_watermark(
+        &self,
+        resolution_seconds: i32,
+    ) -> HistoryResult<Option<i64>> {
+        let v = sqlx::query_scalar::<_, i64>(
+            "SELECT watermark FROM aggregation_state WHERE resolution_seconds = $1",
+        )
+        .bind(resolution_seconds)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(v)
+    }
+
+    /// All stored watermarks, finest tier first.
+    pub async fn get_aggregation_watermarks(&self) -> HistoryResult<Vec<AggregationWatermark>> {
+        let rows = sqlx::query_as::<_, (i32, i64)>(
+            "SELECT resolution_seconds, watermark FROM aggregation_state WHERE resolution_seconds > 0 ORDER BY resolution_seconds",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(resolution_seconds, watermark)| AggregationWatermark {
+                resolution_seconds,
+                watermark,
+            })
+            .collect())
+    }
+
+    /// The clock an aggregation pass at wall-clock `now_ms` should use: never earlier than the
+    /// last pass's (persisted), so cutoffs do not move backwards when the clock does.
+    pub async fn clamp_aggregation_clock(&self, now_ms: i64) -> HistoryResult<i64> {
+        let mut conn = self.writer.acquire().await?;
+        let last = sqlx::query_scalar::<_, i64>(
+            "SELECT watermark FROM aggregation_state WHERE resolution_seconds = $1",
+        )
+        .bind(LAST_PASS_CLOCK_KEY)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match last {
+            Some(last) if last > now_ms => {
+                warn!(
+                    now_ms,
+                    last_pass_ms = last,
+                    "clock is behind the last aggregation pass; keeping the earlier cutoffs"
+                );
+                Ok(last)
+            }
+            _ => {
+                Self::set_aggregation_watermark(&mut conn, LAST_PASS_CLOCK_KEY, now_ms).await?;
+                Ok(now_ms)
+            }
+        }
+    }
+
+    pub(in crate::history_repo) async fn set_aggregation_watermark(
+        conn: &mut sqlx::SqliteConnection,
+        resolution_seconds: i32,
+        watermark: i64,
+    ) -> HistoryResult<()> {
+        sqlx::query(
+            "INSERT INTO aggregation_state (resolution_seconds, watermark) VALUES ($1, $2)
+             ON CONFLICT(resolution_seconds) DO UPDATE SET watermark = excluded.watermark",
+        )
+        .bind(resolution_seconds)
+        .bind(watermark)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+}
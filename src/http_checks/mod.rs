@@ -0,0 +1,221 @@
+// HTTP uptime checks (`[[http_checks]]`): each endpoint is requested every `interval_secs` and
+// keeps its state (up / down, latency, failures in a row, last error). The worker copies
+// [`CheckSet::summary`] into every snapshot and `GET /api/checks` serves [`CheckSet::current`];
+// a check going down or coming back up is reported by [`CheckReporter`].
+
+mod report;
+mod request;
+
+pub use report::{CheckReporter, alert_event};
+pub use request::{CheckResult, check_once};
+
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::future::join_all;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::HttpCheck;
+use crate::models::{CheckState, CheckSummary, HttpCheckStatus};
+use crate::supervisor::{Backoff, supervise};
+
+/// How often the loop looks for due checks when none is scheduled.
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// A check changing state: down after being up or pending, up after being down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckTransition {
+    pub name: String,
+    pub url: String,
+    pub container: Option<String>,
+    /// The new state, `Up` or `Down`.
+    pub state: CheckState,
+    pub http_status: Option<u16>,
+    /// The error when down.
+    pub error: Option<String>,
+    pub consecutive_failures: u32,
+    /// Epoch ms of the request.
+    pub at_ms: u64,
+}
+
+#[derive(Debug)]
+struct CheckEntry {
+    check: HttpCheck,
+    status: HttpCheckStatus,
+    /// `None` until the first request: due right away.
+    next_due: Option<Instant>,
+}
+
+impl CheckEntry {
+    /// Apply `result` of a request made at `at_ms`; the transition it caused, if any.
+    fn record(&mut self, result: CheckResult, at_ms: u64) -> Option<CheckTransition> {
+        let status = &mut self.status;
+        let state = if result.is_up() {
+            status.consecutive_failures = 0;
+            CheckState::Up
+        } else {
+            status.consecutive_failures += 1;
+            CheckState::Down
+        };
+        status.http_status = result.http_status;
+        if let Some(latency) = result.latency {
+            status.latency_ms = Some(latency.as_secs_f64() * 1000.0);
+        }
+        status.last_error = result.error;
+        status.last_checked_ms = Some(at_ms);
+        let previous = std::mem::replace(&mut status.state, state);
+        if previous == state {
+            return None;
+        }
+        status.since_ms = Some(at_ms);
+        // The first success is where a check starts, not a recovery.
+        (previous != CheckState::Pending || state == CheckState::Down).then(|| CheckTransition {
+            name: status.name.clone(),
+            url: status.url.clone(),
+            container: status.container.clone(),
+            state,
+            http_status: status.http_status,
+            error: status.last_error.clone(),
+            consecutive_failures: status.consecutive_failures,
+            at_ms,
+        })
+    }
+}
+
+/// The configured checks with their latest state.
+#[derive(Debug, Default)]
+pub struct CheckSet {
+    checks: Mutex<Vec<CheckEntry>>,
+}
+
+impl CheckSet {
+    pub fn new(checks: &[HttpCheck]) -> Self {
+        let checks = checks
+            .iter()
+            .map(|check| CheckEntry {
+                status: HttpCheckStatus {
+                    name: check.name.clone(),
+                    url: check.url.clone(),
+                    container: check.container.clone(),
+                    ..Default::default()
+                },
+                check: check.clone(),
+                next_due: None,
+            })
+            .collect();
+        Self {
+            checks: Mutex::new(checks),
+        }
+    }
+
+    /// Every check in config order, pending ones included.
+    pub fn current(&self) -> Vec<HttpCheckStatus> {
+        let checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        checks.iter().map(|entry| entry.status.clone()).collect()
+    }
+
+    /// The snapshot part: name, state and latency per check.
+    pub fn summary(&self) -> Vec<CheckSummary> {
+        let checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        checks
+            .iter()
+            .map(|entry| CheckSummary {
+                name: entry.status.name.clone(),
+                state: entry.status.state,
+                latency_ms: entry.status.latency_ms,
+            })
+            .collect()
+    }
+
+    /// When the next check is due; `None` without checks.
+    pub fn next_due(&self) -> Option<Instant> {
+        let checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        checks
+            .iter()
+            .map(|entry| entry.next_due)
+            .min()
+            .map(|due| due.unwrap_or_else(Instant::now))
+    }
+
+    /// Request every check due at `now` (concurrently), record the results and schedule each
+    /// again `interval_secs` after `now`. Returns the transitions, in config order.
+    pub async fn run_due(&self, client: &reqwest::Client, now: Instant) -> Vec<CheckTransition> {
+        let due: Vec<HttpCheck> = {
+            let mut checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+            checks
+                .iter_mut()
+                .filter(|entry| entry.next_due.is_none_or(|due| due <= now))
+                .map(|entry| {
+                    entry.next_due = Some(now + Duration::from_secs(entry.check.interval_secs));
+                    entry.check.clone()
+                })
+                .collect()
+        };
+        let results = join_all(due.iter().map(|check| check_once(client, check))).await;
+        let at_ms = now_ms();
+        let mut checks = self.checks.lock().unwrap_or_else(|e| e.into_inner());
+        due.iter()
+            .zip(results)
+            .filter_map(|(check, result)| {
+                let entry = checks.iter_mut().find(|e| e.check.name == check.name)?;
+                entry.record(result, at_ms)
+            })
+            .collect()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Run `checks` until `shutdown`, handing transitions to `reporter`. A panic restarts the loop
+/// (counted in `restarts`); the check states live in `checks` and carry over.
+pub fn spawn(
+    checks: Arc<CheckSet>,
+    reporter: CheckReporter,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("homeserver/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let token = shutdown.clone();
+    Ok(tokio::spawn(supervise(
+        "http_checks",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            run(
+                checks.clone(),
+                client.clone(),
+                reporter.clone(),
+                token.clone(),
+            )
+        },
+    )))
+}
+
+async fn run(
+    checks: Arc<CheckSet>,
+    client: reqwest::Client,
+    reporter: CheckReporter,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let transitions = checks.run_due(&client, Instant::now()).await;
+        reporter.report(transitions).await;
+        let wake = checks
+            .next_due()
+            .unwrap_or_else(|| Instant::now() + IDLE_POLL);
+        tokio::select! {
+            _ = tokio::time::sleep_until(wake) => {}
+            _ = shutdown.cancelled() => return,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// Where check transitions go: the alert history and notifier (logged, POSTed to the
+// `[alerts]` webhooks when configured) and, once the history store is open, `service_events`.
+
+use std::sync::Arc;
+
+use super::CheckTransition;
+use crate::alerting::{AlertCheck, AlertEvent, AlertState, AlertStatus, Notifier};
+use crate::config::Severity;
+use crate::history_repo::HistoryHandle;
+use crate::models::{CheckState, ServiceEventKind};
+
+/// Alerts and persists check transitions. Cloneable, so it can be moved into the check task.
+#[derive(Clone)]
+pub struct CheckReporter {
+    history: HistoryHandle,
+    notifier: Notifier,
+    alerts: Arc<AlertStatus>,
+}
+
+impl CheckReporter {
+    pub fn new(history: HistoryHandle, notifier: Notifier, alerts: Arc<AlertStatus>) -> Self {
+        Self {
+            history,
+            notifier,
+            alerts,
+        }
+    }
+
+    /// Record each transition in the alert history, notify (detached, like rule alerts) and
+    /// store it as a `check_down` / `check_up` service event.
+    pub async fn report(&self, transitions: Vec<CheckTransition>) {
+        for transition in transitions {
+            let event = alert_event(&transition);
+            self.alerts.record(&event);
+            let notifier = self.notifier.clone();
+            tokio::spawn(async move { notifier.notify(&event).await });
+
+            // Before the store is open the service events are dropped.
+            let Some(repo) = self.history.get() else {
+                continue;
+            };
+            let (kind, detail) = match transition.state {
+                CheckState::Down => (ServiceEventKind::CheckDown, transition.error.clone()),
+                _ => (
+                    ServiceEventKind::CheckUp,
+                    transition
+                        .http_status
+                        .map(|status| format!("HTTP {status}")),
+                ),
+            };
+            if let Err(e) = repo
+                .record_check_event(
+                    transition.at_ms as i64,
+                    kind,
+                    &transition.name,
+                    detail.as_deref(),
+                )
+                .await
+            {
+                tracing::warn!(check = %transition.name, error = %e, "recording a check event failed");
+            }
+        }
+    }
+}
+
+/// Fires with the failures in a row as its value (`> 0`), resolves when the check is up.
+pub fn alert_event(transition: &CheckTransition) -> AlertEvent {
+    let firing = transition.state == CheckState::Down;
+    AlertEvent {
+        rule_name: transition.name.clone(),
+        metric: "http_check".into(),
+        op: ">".into(),
+        value: f64::from(transition.consecutive_failures),
+        threshold: 0.0,
+        severity: Severity::Warning,
+        state: if firing {
+            AlertState::Firing
+        } else {
+            AlertState::Resolved
+        },
+        timestamp: transition.at_ms,
+        container: None,
+        check: Some(AlertCheck {
+            url: transition.url.clone(),
+            container: transition.container.clone(),
+            error: transition.error.clone(),
+        }),
+    }
+}
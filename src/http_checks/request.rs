@@ -0,0 +1,75 @@
+// One check request: GET the URL within the check's timeout and judge the answer.
+
+use std::error::Error as _;
+
+use tokio::time::{Duration, Instant};
+
+use crate::config::HttpCheck;
+
+/// Outcome of one request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// `None` when no response arrived.
+    pub http_status: Option<u16>,
+    /// Time to the response headers; `None` without a response.
+    pub latency: Option<Duration>,
+    /// Why the check failed; `None` when the endpoint answered as expected.
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn is_up(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Request `check.url` once. Up on `expected_status`, or on any 2xx when that is unset; the body
+/// is not read.
+pub async fn check_once(client: &reqwest::Client, check: &HttpCheck) -> CheckResult {
+    let started = Instant::now();
+    let sent = client
+        .get(&check.url)
+        .timeout(Duration::from_millis(check.timeout_ms))
+        .send()
+        .await;
+    match sent {
+        Ok(response) => {
+            let latency = started.elapsed();
+            let status = response.status();
+            let error = match check.expected_status {
+                Some(expected) if status.as_u16() != expected => {
+                    Some(format!("HTTP {}, expected {expected}", status.as_u16()))
+                }
+                None if !status.is_success() => Some(format!("HTTP {}", status.as_u16())),
+                _ => None,
+            };
+            CheckResult {
+                http_status: Some(status.as_u16()),
+                latency: Some(latency),
+                error,
+            }
+        }
+        Err(e) => CheckResult {
+            http_status: None,
+            latency: None,
+            error: Some(describe(e)),
+        },
+    }
+}
+
+/// "timed out", or the error with its causes ("error sending request: ... Connection refused").
+/// The URL is left out: it is reported next to the error anyway.
+fn describe(e: reqwest::Error) -> String {
+    if e.is_timeout() {
+        return "timed out".into();
+    }
+    let e = e.without_url();
+    let mut text = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        text.push_str(": ");
+        text.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    text
+}
@@ -3,13 +3,34 @@
 pub mod aggregation_worker;
 pub mod alerting;
 pub mod backfill;
+pub mod bootstrap;
+pub mod collection_pause;
 pub mod config;
+pub mod discovery;
 pub mod docker_repo;
 pub mod gpu_repo;
 pub mod history_repo;
+pub mod http_checks;
+pub mod maintenance;
+pub mod metrics;
 pub mod models;
+pub mod mqtt;
+pub mod net_tools;
+pub mod probes;
+pub mod reload;
+pub mod remote_write;
+pub mod reports;
 pub mod routes;
+pub mod sensors_repo;
+pub mod serve;
+pub mod shutdown;
 pub mod smart_repo;
+pub mod startup;
+pub mod supervisor;
 pub mod sysinfo_repo;
+pub mod system_info_refresh;
+pub mod systemd;
+pub mod telemetry;
 pub mod version;
 pub mod worker;
+pub mod ws_connections;
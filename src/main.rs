@@ -1,10 +1,9 @@
 use anyhow::Result;
+use clap::Parser;
+use homeserver::history_repo::HistoryHandle;
 use homeserver::*;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
 use tokio::sync::broadcast;
-use tracing_subscriber::EnvFilter;
-use tracing_subscriber::fmt::time::FormatTime;
 
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
@@ -13,143 +12,241 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
-struct LocalTimer;
-
-impl FormatTime for LocalTimer {
-    fn format_time(&self, w: &mut tracing_subscriber::fmt::format::Writer<'_>) -> std::fmt::Result {
-        write!(
-            w,
-            "{}",
-            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z")
-        )
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt()
-        .with_timer(LocalTimer)
-        .with_env_filter(filter)
-        .init();
+    // Service uptime (/api/stats, /api/events) counts from here.
+    let service_start_ms = version::service_start_epoch_ms();
+    let cli = config::Cli::parse();
+    if let Some(report) = cli.report() {
+        if report.exit_code == 0 {
+            println!("{}", report.output);
+        } else {
+            eprintln!("{}", report.output);
+        }
+        std::process::exit(report.exit_code);
+    }
+
+    // The OpenTelemetry slot stays empty unless [telemetry] is configured (filled below, once
+    // the host name is known).
+    let logging = bootstrap::init_logging(cli.overrides.log_level.as_deref())?;
 
-    let app_config = config::AppConfig::load()?;
+    let (app_config, config_sources) = config::AppConfig::load_with_sources(&cli.overrides)?;
+    if let Some(filter) = &app_config.logging.filter {
+        (logging.set_filter)(filter)?;
+    }
+    // Secret values (webhook URL) print as "<redacted>".
+    tracing::info!(config = ?app_config, "effective configuration");
     let (tx, _) =
         broadcast::channel::<models::FullSystemSnapshot>(app_config.publishing.broadcast_capacity);
 
-    let sysinfo_repo = Arc::new(sysinfo_repo::SysinfoRepo::new());
-    let system_info = Arc::new(
+    let sysinfo_repo = Arc::new(
+        sysinfo_repo::SysinfoRepo::new().with_node_name(app_config.server.node_name.clone()),
+    );
+    // One shared value for the routes and the history writer, swapped by a refresh.
+    let system_info = system_info_refresh::SharedSystemInfo::new(
         sysinfo_repo
             .get_system_info()
             .await
             .map_err(|e| anyhow::anyhow!("system info: {}", e))?,
     );
-    let docker_repo = Arc::new(docker_repo::DockerRepo::connect()?);
+    let tracer_provider = logging.export_traces(&app_config.telemetry, &system_info.get())?;
+    let docker_repo = Arc::new(
+        docker_repo::DockerRepo::connect()?
+            .with_stale_after_ms(app_config.monitoring.container_stale_ms),
+    );
     let gpu_repo = Arc::new(gpu_repo::GpuRepo::new());
     let smart_repo = Arc::new(smart_repo::SmartRepo::new());
-    let history_repo = Arc::new(
-        history_repo::HistoryRepo::connect(
-            &app_config.database.path,
-            app_config.database.retention_days,
-        )
-        .await?,
-    );
-    history_repo.init().await?;
-
-    let mut agg_shutdown_tx: Option<tokio::sync::oneshot::Sender<()>> = None;
-    let mut agg_handle: Option<tokio::task::JoinHandle<()>> = None;
-
-    if app_config.database.enable_aggregation {
-        let agg_config = aggregation_worker::AggregationWorkerConfig {
-            aggregation_interval_secs: app_config.database.aggregation_interval_secs,
-            raw_retention_hours: app_config.database.raw_retention_hours,
-            minute_retention_hours: app_config.database.minute_retention_hours,
-            retention_days: app_config.database.retention_days,
-            vacuum_schedule: app_config.database.vacuum_schedule.clone(),
-            vacuum_interval_secs: app_config.database.vacuum_interval_secs,
-        };
-        if let Err(e) = backfill::run_backfill(history_repo.clone(), &agg_config).await {
-            tracing::error!(error = %e, "backfill failed (continuing)");
-        }
-        let (shutdown_agg_tx, shutdown_agg_rx) = tokio::sync::oneshot::channel();
-        agg_shutdown_tx = Some(shutdown_agg_tx);
-        agg_handle = Some(aggregation_worker::spawn(
-            history_repo.clone(),
-            agg_config,
-            shutdown_agg_rx,
-        ));
-    }
+    let service_metrics = metrics::ServiceMetrics {
+        probes: Arc::new(probes::ProbeSet::new(&app_config.probes)),
+        checks: Arc::new(http_checks::CheckSet::new(&app_config.http_checks)),
+        sensors: Arc::new(sensors_repo::SensorsRepo::new(
+            std::time::Duration::from_secs(app_config.monitoring.sensor_rescan_secs),
+        )),
+        ..Default::default()
+    };
+    let tasks_shutdown = tokio_util::sync::CancellationToken::new();
+    let history_shutdown = tokio_util::sync::CancellationToken::new();
+    // Agent mode (database.enabled = false): no database, writer or aggregation at all.
+    // Otherwise the database is opened in the background (below), so the server comes up even
+    // while it is unreachable; the history endpoints answer 503 until it is ready.
+    let history_repo = if app_config.database.enabled {
+        HistoryHandle::pending()
+    } else {
+        tracing::info!("database disabled; running as an agent without local history");
+        HistoryHandle::disabled()
+    };
 
-    let ws_system_connections = Arc::new(AtomicUsize::new(0));
-    let snapshots_saved_total = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let ws_connections = Arc::new(ws_connections::WsConnections::default());
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
 
-    let writer_capacity = worker::writer_channel_capacity(app_config.database.flush_rate);
-    let (write_tx, write_rx) = tokio::sync::mpsc::channel(writer_capacity);
-    let writer_handle = worker::spawn_history_writer(
-        write_rx,
-        history_repo.clone(),
-        system_info.clone(),
-        worker::HistoryWriterConfig {
-            flush_rate: app_config.database.flush_rate,
-            flush_interval_secs: app_config.database.flush_interval_secs,
-            persist_gpu: app_config.database.persist_gpu,
-            persist_smart: app_config.database.persist_smart,
-        },
-        snapshots_saved_total.clone(),
+    let (reloader, worker_config, writer_config) =
+        reload::ConfigReloader::new(app_config.clone(), cli.overrides.clone(), None);
+    let reloader = Arc::new(
+        reloader
+            .with_log_filter(logging.set_filter)
+            .with_sources(config_sources),
     );
-    let worker_handle = worker::spawn(
-        worker::WorkerDeps {
+    #[cfg(unix)]
+    reload::spawn_sighup_listener(reloader.clone())?;
+    // Snapshots queue up (overflow policy applies) until the writer starts with the open repo.
+    // Right after the open the live view is primed from history; the worker waits for that.
+    let (primed_tx, primed_rx) = tokio::sync::oneshot::channel::<()>();
+    let (write_tx, history_startup) = if history_repo.is_enabled() {
+        let (write_tx, write_rx) = worker::write_queue(
+            worker::writer_channel_capacity(app_config.database.flush_rate),
+            worker::OverflowPolicy::from_config(&app_config.database.overflow_policy),
+            service_metrics.write_queue.clone(),
+        );
+        let database = app_config.database.clone();
+        let metrics = service_metrics.clone();
+        let shutdown = history_shutdown.clone();
+        let handle = history_repo.clone();
+        let reloader = reloader.clone();
+        let system_info = system_info.clone();
+        let prime_age =
+            std::time::Duration::from_secs(app_config.monitoring.prime_from_history_minutes * 60);
+        let tx = tx.clone();
+        let startup = tokio::spawn(async move {
+            let store =
+                startup::open_history_store_with_retry(&database, &metrics, shutdown, &handle)
+                    .await?;
+            match store
+                .repo
+                .record_service_start(service_start_ms as i64)
+                .await
+            {
+                Ok(event) => tracing::info!(event = event.as_str(), "service start recorded"),
+                Err(e) => tracing::warn!(error = %e, "recording the service start failed"),
+            }
+            if !prime_age.is_zero()
+                && let Err(e) = worker::prime_from_history(
+                    store.repo.as_ref(),
+                    prime_age,
+                    &metrics.broadcast,
+                    Some(&tx),
+                )
+                .await
+            {
+                tracing::warn!(error = %e, "priming the live view from history failed");
+            }
+            let _ = primed_tx.send(());
+            reloader.attach_history_repo(store.repo.clone());
+            let writer = worker::spawn_history_writer_reloadable(
+                write_rx,
+                store.repo,
+                system_info,
+                writer_config,
+                metrics.snapshots_saved_total.clone(),
+                metrics.history_flush.clone(),
+                metrics.worker_restarts_total.clone(),
+            );
+            Some((writer, store.aggregation))
+        });
+        (Some(write_tx), Some(startup))
+    } else {
+        drop(primed_tx);
+        (None, None)
+    };
+    let worker_deps = worker::WorkerDeps {
+        collector: Arc::new(worker::HostCollector {
             sysinfo_repo: sysinfo_repo.clone(),
-            system_info: system_info.clone(),
-            docker_repo: docker_repo.clone(),
-            gpu_repo: gpu_repo.clone(),
-            smart_repo: smart_repo.clone(),
-            history_repo: history_repo.clone(),
-            tx: tx.clone(),
-            write_tx,
-            ws_system_connections: ws_system_connections.clone(),
-            snapshots_saved_total,
-            alert_engine: alerting::AlertEngine::new(app_config.alerts.rules.clone()),
-            notifier: alerting::Notifier::new(app_config.alerts.webhook_url.clone()),
-            shutdown_rx,
-        },
-        worker::WorkerConfig {
-            sample_interval_ms: app_config.monitoring.sample_interval_ms,
-            stats_log_interval_secs: app_config.monitoring.stats_log_interval_secs,
-            prune_interval_secs: app_config.database.prune_interval_secs,
-            collect_gpu: app_config.monitoring.collect_gpu,
-            collect_smart: app_config.monitoring.collect_smart,
-            smart_poll_interval_secs: app_config.monitoring.smart_poll_interval_secs,
-        },
-    );
+            docker: docker_repo.clone(),
+        }),
+        system_info: system_info.get(),
+        gpu_repo: gpu_repo.clone(),
+        smart_repo: smart_repo.clone(),
+        sensors_repo: service_metrics.sensors.clone(),
+        history_repo: history_repo.clone(),
+        tx: tx.clone(),
+        write_tx,
+        ws_connections: ws_connections.clone(),
+        snapshots_saved_total: service_metrics.snapshots_saved_total.clone(),
+        aggregation_metrics: service_metrics.aggregation.clone(),
+        collection_metrics: service_metrics.collection.clone(),
+        broadcast_metrics: service_metrics.broadcast.clone(),
+        worker_restarts_total: service_metrics.worker_restarts_total.clone(),
+        pause: service_metrics.pause.clone(),
+        probes: service_metrics.probes.clone(),
+        checks: service_metrics.checks.clone(),
+        shutdown_rx,
+    };
+    let config = worker_config.clone();
+    let worker_handle = tokio::spawn(async move {
+        // A database still being retried only delays the first tick by PRIME_WAIT.
+        let _ = tokio::time::timeout(worker::PRIME_WAIT, primed_rx).await;
+        let _ = worker::spawn_reloadable(worker_deps, config).await;
+    });
+    let collection_metrics = service_metrics.collection.clone();
+    let mut task_handles = bootstrap::spawn_tasks(bootstrap::TaskDeps {
+        config: &app_config,
+        snapshots: &tx,
+        docker_repo,
+        sysinfo_repo: sysinfo_repo.clone(),
+        system_info: system_info.clone(),
+        history_repo: history_repo.clone(),
+        metrics: &service_metrics,
+        shutdown: &tasks_shutdown,
+    })?;
 
     let app = routes::app(
         tx,
         sysinfo_repo,
         system_info,
-        ws_system_connections,
+        ws_connections.clone(),
         app_config.clone(),
-        history_repo,
-    );
-    let addr = format!("{}:{}", app_config.server.host, app_config.server.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    tracing::info!("Listening on http://{}", addr);
+        history_repo.clone(),
+        service_metrics,
+    )
+    .layer(axum::Extension(reloader));
 
     // Unified graceful shutdown — works in both Docker and native.
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    let notifier: Arc<dyn systemd::Notifier> = Arc::new(systemd::SdNotifier);
+    let stopping = notifier.clone();
+    let shutdown = async move {
+        shutdown_signal().await;
+        stopping.notify(systemd::ServiceState::Stopping);
+    };
+    // Bound (and the worker running): advertise the port actually bound, tell systemd we are
+    // ready, then keep the watchdog fed while the worker ticks.
+    let server = serve::run(app, &app_config.server, shutdown).await?;
+    task_handles.extend(discovery::spawn_configured(
+        &app_config,
+        server.bound_address().map(|addr| addr.port()),
+        tasks_shutdown.child_token(),
+    ));
+    notifier.notify(systemd::ServiceState::Ready);
+    let watchdog_stop = tasks_shutdown.child_token();
+    if let Some(interval) = systemd::watchdog_interval() {
+        tracing::info!(?interval, "systemd watchdog enabled");
+        tokio::spawn(systemd::run_watchdog(
+            notifier.clone(),
+            interval,
+            collection_metrics,
+            worker_config,
+            watchdog_stop.clone(),
+        ));
+    }
+    server.wait().await?;
 
-    tracing::info!("Server stopped; sending shutdown to workers");
-    let _ = shutdown_tx.send(());
-    let _ = worker_handle.await;
-    let _ = writer_handle.await;
-    if let Some(tx) = agg_shutdown_tx.take() {
-        let _ = tx.send(());
+    tracing::info!("Server stopped; draining workers");
+    // The worker stops first, so its ticks no longer prove liveness.
+    watchdog_stop.cancel();
+    shutdown::Drain {
+        worker_stop: shutdown_tx,
+        worker: worker_handle,
+        history_startup,
+        history_shutdown,
+        tasks_shutdown,
+        tasks: task_handles,
+        ws_connections,
+        history_repo,
     }
-    if let Some(h) = agg_handle {
-        let _ = h.await;
+    .run()
+    .await;
+    if let Some(provider) = tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        tracing::warn!(error = %e, "flushing OTLP traces failed");
     }
 
     Ok(())
@@ -0,0 +1,245 @@
+// Offline database maintenance behind `homeserver-cli`: each subcommand is a thin wrapper over
+// one function here (or a `HistoryRepo` method). Read commands open the file read-only; write
+// commands refuse to run while a server holds the WAL unless forced.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::config::DatabaseConfig;
+use crate::history_repo::{Fragmentation, HistoryRepo, HistoryResult, HistoryTable, VerifyReport};
+use crate::models::{
+    DbStats, FullSystemSnapshot, FullSystemSnapshotDisplay, SystemInfo, TierStats,
+    merge_system_info,
+};
+
+/// The `-wal` file next to the database at `db_path`.
+pub fn wal_path(db_path: &str) -> PathBuf {
+    let mut wal = Path::new(db_path).as_os_str().to_owned();
+    wal.push("-wal");
+    PathBuf::from(wal)
+}
+
+/// Whether a `-wal` file exists next to `db_path`. SQLite removes it when the last connection
+/// closes cleanly, so one on disk means a server (or another tool) has the database open, or
+/// one exited without closing it.
+pub fn server_holds_wal(db_path: &str) -> bool {
+    wal_path(db_path).exists()
+}
+
+/// Open `config.path` for a write command (migrating it like the server does). Refuses while
+/// [`server_holds_wal`], unless `force`. Close the repo when done so the WAL is removed.
+pub async fn open_for_write(config: &DatabaseConfig, force: bool) -> anyhow::Result<HistoryRepo> {
+    if !Path::new(&config.path).is_file() {
+        anyhow::bail!("database file '{}' does not exist", config.path);
+    }
+    open_for_import(config, force).await
+}
+
+/// Like [`open_for_write`], but a missing file is created: `import` may fill a new database,
+/// e.g. on new hardware.
+pub async fn open_for_import(config: &DatabaseConfig, force: bool) -> anyhow::Result<HistoryRepo> {
+    if server_holds_wal(&config.path) && !force {
+        anyhow::bail!(
+            "{} exists: a server appears to be using the database (or exited uncleanly); \
+             stop it first, or pass --force",
+            wal_path(&config.path).display()
+        );
+    }
+    let repo = HistoryRepo::connect(config)
+        .await
+        .with_context(|| format!("opening {}", config.path))?;
+    repo.init().await?;
+    Ok(repo)
+}
+
+/// A read-only repo for the inspection commands (`dump`, `stats`, `verify`). SQLite cannot
+/// remove the `-wal` and `-shm` files when the last connection to close is read-only, which
+/// would trip [`open_for_write`]'s guard afterwards; [`ReadOnlyDb::close`] removes them again
+/// when they were not there before and the WAL is still empty.
+pub struct ReadOnlyDb {
+    repo: HistoryRepo,
+    db_path: String,
+    had_wal: bool,
+}
+
+impl Deref for ReadOnlyDb {
+    type Target = HistoryRepo;
+
+    fn deref(&self) -> &HistoryRepo {
+        &self.repo
+    }
+}
+
+impl ReadOnlyDb {
+    pub async fn close(self) {
+        self.repo.close().await;
+        let wal = wal_path(&self.db_path);
+        let empty = std::fs::metadata(&wal).is_ok_and(|m| m.len() == 0);
+        if !self.had_wal && empty {
+            let mut shm = Path::new(&self.db_path).as_os_str().to_owned();
+            shm.push("-shm");
+            let _ = std::fs::remove_file(&wal);
+            let _ = std::fs::remove_file(shm);
+        }
+    }
+}
+
+/// Open `db_path` with [`HistoryRepo::connect_read_only`]; close it with [`ReadOnlyDb::close`].
+pub async fn open_read_only(db_path: &str) -> HistoryResult<ReadOnlyDb> {
+    let had_wal = server_holds_wal(db_path);
+    Ok(ReadOnlyDb {
+        repo: HistoryRepo::connect_read_only(db_path).await?,
+        db_path: db_path.to_string(),
+        had_wal,
+    })
+}
+
+/// Parse a duration such as `90s`, `15m`, `12h`, `30d` or plain seconds (`3600`).
+pub fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let (digits, unit_secs) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        Some((i, 'd')) => (&s[..i], 86_400),
+        _ => (s, 1),
+    };
+    let n: u64 = digits
+        .parse()
+        .with_context(|| format!("invalid duration '{s}' (expected e.g. 90s, 15m, 12h, 30d)"))?;
+    n.checked_mul(unit_secs)
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .with_context(|| format!("duration '{s}' must be positive and fit in 64 bits"))
+}
+
+/// A `[from_ms, to_ms)` history query for `dump`, as `/api/history` would answer it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpRange {
+    pub from_ms: i64,
+    pub to_ms: i64,
+    pub resolution_secs: u32,
+}
+
+/// Snapshots with the stored static `SystemInfo` merged in: the newest `limit` raw rows, or
+/// `range` read across raw and aggregated tiers like `/api/history`
+/// ([`HistoryRepo::history_raw_cutoff`]).
+pub async fn dump(
+    repo: &HistoryRepo,
+    limit: u32,
+    range: Option<DumpRange>,
+    raw_retention_hours: u32,
+) -> HistoryResult<Vec<FullSystemSnapshotDisplay>> {
+    let (stored_info, snapshots) = match range {
+        None => repo.get_recent_snapshots(limit).await?,
+        Some(r) => {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            let raw_cutoff = repo.history_raw_cutoff(now_ms, raw_retention_hours).await?;
+            let snapshots = repo
+                .get_history(r.from_ms, r.to_ms, r.resolution_secs, raw_cutoff)
+                .await?;
+            (repo.get_stored_system_info().await?, snapshots)
+        }
+    };
+    Ok(snapshots
+        .into_iter()
+        .map(|s| display(stored_info.as_ref(), s))
+        .collect())
+}
+
+fn display(info: Option<&SystemInfo>, s: FullSystemSnapshot) -> FullSystemSnapshotDisplay {
+    FullSystemSnapshotDisplay {
+        timestamp: s.timestamp,
+        system: merge_system_info(info, &s.system),
+        cpu: s.cpu,
+        ram: s.ram,
+        containers: s.containers,
+        storage: s.storage,
+        network: s.network,
+        gpus: s.gpus,
+        smart: s.smart,
+        probes: s.probes,
+        sensors: s.sensors,
+    }
+}
+
+/// Output of `stats`: row counts, per-tier sizes and the file's page accounting.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsReport {
+    pub db: DbStats,
+    /// Raw first (`resolutionSeconds` 0), then each aggregated tier.
+    pub tiers: Vec<TierStats>,
+    /// Database file plus `-wal`.
+    pub file_bytes: u64,
+    pub page_count: i64,
+    pub free_pages: i64,
+    pub free_percent: f64,
+}
+
+/// Collect a [`StatsReport`]; read-only.
+pub async fn stats(repo: &HistoryRepo) -> HistoryResult<StatsReport> {
+    let fragmentation = repo.fragmentation().await?;
+    Ok(StatsReport {
+        db: repo.db_stats().await?,
+        tiers: repo.get_tier_stats().await?,
+        file_bytes: repo.file_size()?,
+        page_count: fragmentation.page_count,
+        free_pages: fragmentation.freelist_count,
+        free_percent: fragmentation.free_percent(),
+    })
+}
+
+/// Output of `prune`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneReport {
+    pub cutoff_ms: i64,
+    pub raw_rows: u64,
+    pub aggregated_rows: u64,
+}
+
+/// Delete raw and aggregated rows older than `older_than` before `now_ms`
+/// ([`HistoryRepo::prune_before`]).
+pub async fn prune(
+    repo: &HistoryRepo,
+    older_than: Duration,
+    now_ms: i64,
+) -> HistoryResult<PruneReport> {
+    let cutoff_ms = now_ms.saturating_sub(older_than.as_millis() as i64);
+    let (raw_rows, aggregated_rows) = repo.prune_before(cutoff_ms).await?;
+    Ok(PruneReport {
+        cutoff_ms,
+        raw_rows,
+        aggregated_rows,
+    })
+}
+
+/// Full VACUUM regardless of `vacuum_min_free_percent`; returns the page accounting before and
+/// after.
+pub async fn vacuum(repo: &HistoryRepo) -> HistoryResult<(Fragmentation, Fragmentation)> {
+    let before = repo.fragmentation().await?;
+    repo.vacuum().await?;
+    Ok((before, repo.fragmentation().await?))
+}
+
+/// Delete every row `report` found a corrupt blob in ([`HistoryRepo::delete_rows`]). Returns
+/// (raw, aggregated) rows removed.
+pub async fn delete_corrupt(
+    repo: &HistoryRepo,
+    report: &VerifyReport,
+) -> HistoryResult<(u64, u64)> {
+    let raw = repo
+        .delete_rows(HistoryTable::Raw, &report.corrupt_ids(HistoryTable::Raw))
+        .await?;
+    let aggregated = repo
+        .delete_rows(
+            HistoryTable::Aggregated,
+            &report.corrupt_ids(HistoryTable::Aggregated),
+        )
+        .await?;
+    Ok((raw, aggregated))
+}
@@ -0,0 +1,111 @@
+// Process-wide counters shared by the workers and served on /api/stats and /metrics (Prometheus
+// text exposition format). The /metrics rendering is in `prometheus.rs`.
+
+mod prometheus;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::aggregation_worker::{AggregationMetrics, AggregationMetricsSnapshot};
+use crate::alerting::AlertStatus;
+use crate::collection_pause::CollectionPause;
+use crate::history_repo::blob;
+use crate::http_checks::CheckSet;
+use crate::models::SelfStats;
+use crate::probes::ProbeSet;
+use crate::routes::{HttpMetrics, RouteStats};
+use crate::sensors_repo::SensorsRepo;
+use crate::version;
+use crate::worker::{
+    BroadcastMetrics, BroadcastMetricsSnapshot, CollectionMetrics, CollectionMetricsSnapshot,
+    FlushMetrics, FlushMetricsSnapshot, WriteQueueMetrics,
+};
+use crate::ws_connections::{WsChannel, WsConnections};
+
+/// Shared counters; cheap to clone (every field is an `Arc`).
+#[derive(Debug, Clone, Default)]
+pub struct ServiceMetrics {
+    /// Snapshots persisted by the history writer.
+    pub snapshots_saved_total: Arc<AtomicU64>,
+    /// History writer queue depth and overflow drops.
+    pub write_queue: Arc<WriteQueueMetrics>,
+    /// History writer flush time, batch size and bytes.
+    pub history_flush: Arc<FlushMetrics>,
+    /// Aggregation passes and pruning.
+    pub aggregation: Arc<AggregationMetrics>,
+    /// Worker tick collection time.
+    pub collection: Arc<CollectionMetrics>,
+    /// Snapshot broadcast queue use, lagging clients and snapshot size.
+    pub broadcast: Arc<BroadcastMetrics>,
+    /// Panic restarts of the stats worker, history writer and aggregation worker.
+    pub worker_restarts_total: Arc<AtomicU64>,
+    /// Collection paused via `/api/worker/pause`.
+    pub pause: Arc<CollectionPause>,
+    /// Firing alert rules and recent alert events, served on `/api/alerts`.
+    pub alerts: Arc<AlertStatus>,
+    /// Latest results per `[[probes.targets]]` entry, served on `/api/probes`.
+    pub probes: Arc<ProbeSet>,
+    /// State per `[[http_checks]]` entry, served on `/api/checks`.
+    pub checks: Arc<CheckSet>,
+    /// hwmon readings of the last worker tick, served on `/api/sensors`.
+    pub sensors: Arc<SensorsRepo>,
+    /// HTTP requests and latency per route.
+    pub http: Arc<HttpMetrics>,
+}
+
+/// Body of `GET /api/stats`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStats {
+    pub snapshots_saved_total: u64,
+    /// Snapshots discarded because the history writer queue was full.
+    pub snapshots_dropped_total: u64,
+    /// Snapshots waiting for the history writer.
+    pub writer_queue_depth: u64,
+    /// History writer flushes: time, batch size, bytes and time since the last success.
+    pub history_flush: FlushMetricsSnapshot,
+    pub worker_restarts_total: u64,
+    /// History blobs with a version newer than this build reads; their rows are skipped.
+    pub history_blob_unknown_version_total: u64,
+    pub paused: bool,
+    pub ws_system_connections: usize,
+    pub ws_cpu_connections: usize,
+    pub ws_ram_connections: usize,
+    pub aggregation: AggregationMetricsSnapshot,
+    pub collection: CollectionMetricsSnapshot,
+    /// Snapshot broadcast: queue use, receivers, lagging clients and serialized snapshot size.
+    pub broadcast: BroadcastMetricsSnapshot,
+    /// The server's own CPU, memory, descriptors, tasks and database size at the last tick.
+    pub self_stats: Option<SelfStats>,
+    /// Requests, status codes and latency per route.
+    pub http: Vec<RouteStats>,
+    /// When this process started (epoch ms) and seconds since; host uptime is in the snapshots.
+    pub service_start_epoch_ms: u64,
+    pub service_uptime_secs: u64,
+}
+
+impl ServiceMetrics {
+    pub fn stats(&self, ws: &WsConnections) -> ServiceStats {
+        ServiceStats {
+            snapshots_saved_total: self.snapshots_saved_total.load(Ordering::Relaxed),
+            snapshots_dropped_total: self.write_queue.dropped_total(),
+            writer_queue_depth: self.write_queue.depth(),
+            history_flush: self.history_flush.snapshot(),
+            worker_restarts_total: self.worker_restarts_total.load(Ordering::Relaxed),
+            history_blob_unknown_version_total: blob::unknown_version_total(),
+            paused: self.pause.is_paused(),
+            ws_system_connections: ws.get(WsChannel::System),
+            ws_cpu_connections: ws.get(WsChannel::Cpu),
+            ws_ram_connections: ws.get(WsChannel::Ram),
+            aggregation: self.aggregation.snapshot(),
+            collection: self.collection.snapshot(),
+            broadcast: self.broadcast.snapshot(),
+            self_stats: self.collection.latest_self(),
+            http: self.http.snapshot(),
+            service_start_epoch_ms: version::service_start_epoch_ms(),
+            service_uptime_secs: version::service_uptime_secs(),
+        }
+    }
+}
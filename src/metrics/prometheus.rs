@@ -0,0 +1,268 @@
+// Prometheus text exposition of `ServiceStats` for GET /metrics.
+
+use std::fmt::Write;
+
+use super::ServiceMetrics;
+use crate::ws_connections::WsConnections;
+
+impl ServiceMetrics {
+    /// Render [`Self::stats`] in the Prometheus text format.
+    pub fn prometheus_text(&self, ws: &WsConnections) -> String {
+        let stats = self.stats(ws);
+        let agg = &stats.aggregation;
+        let collection = &stats.collection;
+        let flush = &stats.history_flush;
+        let broadcast = &stats.broadcast;
+        let counters: [(&str, &str, u64); 25] = [
+            (
+                "homeserver_snapshots_saved_total",
+                "Snapshots persisted by the history writer.",
+                stats.snapshots_saved_total,
+            ),
+            (
+                "homeserver_snapshots_dropped_total",
+                "Snapshots dropped because the history writer queue was full.",
+                stats.snapshots_dropped_total,
+            ),
+            (
+                "homeserver_history_flushes_total",
+                "History writer flushes committed.",
+                flush.flushes_total,
+            ),
+            (
+                "homeserver_history_flush_failures_total",
+                "History writer flushes that failed.",
+                flush.failures_total,
+            ),
+            (
+                "homeserver_history_flush_slow_total",
+                "History writer flushes slower than database.flush_warn_ms.",
+                flush.slow_flushes_total,
+            ),
+            (
+                "homeserver_history_flush_bytes_total",
+                "Encoded blob bytes written by the history writer.",
+                flush.bytes_total,
+            ),
+            (
+                "homeserver_snapshots_dropped_disk_full_total",
+                "Snapshots dropped because free disk space was below database.min_free_bytes.",
+                flush.snapshots_dropped_disk_full,
+            ),
+            (
+                "homeserver_history_emergency_prunes_total",
+                "Emergency prunes of raw history while the disk was full.",
+                flush.emergency_prunes_total,
+            ),
+            (
+                "homeserver_worker_restarts_total",
+                "Background task restarts after a panic.",
+                stats.worker_restarts_total,
+            ),
+            (
+                "homeserver_history_blob_unknown_version_total",
+                "History blobs with an unknown version; their rows are skipped.",
+                stats.history_blob_unknown_version_total,
+            ),
+            (
+                "homeserver_aggregation_passes_total",
+                "Aggregation passes run.",
+                agg.passes_total,
+            ),
+            (
+                "homeserver_aggregation_raw_buckets_total",
+                "First-tier buckets written from raw rows.",
+                agg.raw_buckets_total,
+            ),
+            (
+                "homeserver_aggregation_rolled_up_buckets_total",
+                "Buckets written into coarser tiers.",
+                agg.rolled_up_buckets_total,
+            ),
+            (
+                "homeserver_aggregation_raw_rows_deleted_total",
+                "Raw rows removed after rolling into the first tier.",
+                agg.raw_rows_deleted_total,
+            ),
+            (
+                "homeserver_aggregation_minute_rows_deleted_total",
+                "First-tier rows removed after rolling into the second tier.",
+                agg.minute_rows_deleted_total,
+            ),
+            (
+                "homeserver_pruned_raw_rows_total",
+                "Raw rows removed by retention.",
+                agg.pruned_raw_total,
+            ),
+            (
+                "homeserver_pruned_aggregated_rows_total",
+                "Aggregated rows removed by retention.",
+                agg.pruned_aggregated_total,
+            ),
+            (
+                "homeserver_collection_ticks_total",
+                "Worker collection ticks.",
+                collection.ticks_total,
+            ),
+            (
+                "homeserver_collection_slow_ticks_total",
+                "Collection ticks slower than the sample interval.",
+                collection.slow_ticks_total,
+            ),
+            (
+                "homeserver_collection_degraded_ticks_total",
+                "Collection ticks with at least one failed collector.",
+                collection.degraded_ticks_total,
+            ),
+            (
+                "homeserver_broadcast_sent_total",
+                "Snapshots sent on the broadcast channel.",
+                broadcast.sent_total,
+            ),
+            (
+                "homeserver_broadcast_lag_events_total",
+                "Times a /ws/system client fell behind the broadcast.",
+                broadcast.lag_events_total,
+            ),
+            (
+                "homeserver_broadcast_lagged_messages_total",
+                "Snapshots skipped by lagging /ws/system clients.",
+                broadcast.lagged_messages_total,
+            ),
+            (
+                "homeserver_broadcast_lag_warnings_total",
+                "Minutes with more lag events than publishing.lag_warn_per_minute.",
+                broadcast.lag_warnings_total,
+            ),
+            (
+                "homeserver_snapshot_oversized_total",
+                "Snapshots larger than publishing.max_snapshot_bytes.",
+                broadcast.oversized_snapshots_total,
+            ),
+        ];
+        let mut out = String::new();
+        for (name, help, value) in counters {
+            let _ = writeln!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}"
+            );
+        }
+        let name = "homeserver_collection_failures_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Collector failures per source.\n# TYPE {name} counter"
+        );
+        for (source, value) in &collection.failures_total {
+            let _ = writeln!(out, "{name}{{source=\"{source}\"}} {value}");
+        }
+        self.http.write_prometheus(&mut out);
+        let gauges: [(&str, &str, f64); 18] = [
+            (
+                "homeserver_service_uptime_seconds",
+                "Time since this process started.",
+                stats.service_uptime_secs as f64,
+            ),
+            (
+                "homeserver_aggregation_last_pass_seconds",
+                "Duration of the latest aggregation pass.",
+                agg.last_pass_ms as f64 / 1000.0,
+            ),
+            (
+                "homeserver_ws_system_connections",
+                "Open /ws/system connections.",
+                stats.ws_system_connections as f64,
+            ),
+            (
+                "homeserver_ws_cpu_connections",
+                "Open /ws/cpu connections.",
+                stats.ws_cpu_connections as f64,
+            ),
+            (
+                "homeserver_ws_ram_connections",
+                "Open /ws/ram connections.",
+                stats.ws_ram_connections as f64,
+            ),
+            (
+                "homeserver_writer_queue_depth",
+                "Snapshots waiting for the history writer.",
+                stats.writer_queue_depth as f64,
+            ),
+            (
+                "homeserver_history_flush_last_seconds",
+                "Duration of the latest history flush.",
+                flush.last_ms as f64 / 1000.0,
+            ),
+            (
+                "homeserver_history_flush_max_seconds",
+                "Slowest history flush since start.",
+                flush.max_ms as f64 / 1000.0,
+            ),
+            (
+                "homeserver_history_flush_last_batch_snapshots",
+                "Snapshots in the latest history flush.",
+                flush.last_batch as f64,
+            ),
+            (
+                "homeserver_history_flush_last_bytes",
+                "Encoded blob bytes of the latest history flush.",
+                flush.last_bytes as f64,
+            ),
+            (
+                "homeserver_history_disk_full",
+                "1 while free disk space is below database.min_free_bytes and history is not written.",
+                f64::from(u8::from(flush.disk_full)),
+            ),
+            (
+                "homeserver_collection_paused",
+                "1 while collection is paused via /api/worker/pause.",
+                f64::from(u8::from(stats.paused)),
+            ),
+            (
+                "homeserver_collection_last_seconds",
+                "Duration of the latest collection tick.",
+                collection.last_ms as f64 / 1000.0,
+            ),
+            (
+                "homeserver_collection_max_seconds",
+                "Slowest collection tick since start.",
+                collection.max_ms as f64 / 1000.0,
+            ),
+            (
+                "homeserver_broadcast_queued_snapshots",
+                "Snapshots held in the broadcast channel after the latest send.",
+                broadcast.queued as f64,
+            ),
+            (
+                "homeserver_broadcast_receivers",
+                "Broadcast subscribers at the latest send.",
+                broadcast.receivers as f64,
+            ),
+            (
+                "homeserver_snapshot_last_bytes",
+                "Serialized size of the latest snapshot.",
+                broadcast.last_snapshot_bytes as f64,
+            ),
+            (
+                "homeserver_snapshot_max_bytes",
+                "Largest serialized snapshot since start.",
+                broadcast.max_snapshot_bytes as f64,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+            );
+        }
+        // Absent until the first flush commits.
+        if let Some(ms) = flush.since_last_success_ms {
+            let name = "homeserver_history_flush_since_success_seconds";
+            let _ = writeln!(
+                out,
+                "# HELP {name} Time since the last committed history flush.\n# TYPE {name} gauge\n{name} {}",
+                ms as f64 / 1000.0
+            );
+        }
+        out
+    }
+}
@@ -4,33 +4,65 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
-    ContainerStats, CpuStats, GpuStats, NetworkStats, RamStats, SmartHealth, StorageStats,
-    SystemStatsDynamic,
+    ContainerStats, CpuStats, GpuStats, NetworkStats, ProbeStat, RamStats, SensorStat, SmartHealth,
+    StorageStats, SystemStatsDynamic,
 };
 
 /// One aggregated row: bucket start time, resolution, scalar aggregates, and blob data.
 ///
-/// `cpu` / `ram` carry the full structs from the last sample in the bucket (mirroring
-/// `storage` / `network` / `system`) so the rich fields — CPU temperature, per-core usage,
-/// RAM total/available/swap — survive aggregation. The scalar `cpu_load_*` / `memory_used_*`
-/// aggregates are retained for graphing.
+/// `cpu` / `ram` carry the full structs from the last sample in the bucket (like `system`) so
+/// the rich fields — CPU temperature, per-core usage, RAM total/available/swap — survive
+/// aggregation. The scalar `cpu_load_*` / `memory_used_*` aggregates (plus `memory_total_*` /
+/// `cpu_temperature_*`) are retained for graphing.
+///
+/// `containers`, `storage` and `network` average their gauges over the bucket (weighted by
+/// `sample_count` when rolling up) and keep the last reading of cumulative counters: container
+/// network/block/throttling totals, interface byte/packet counters, disk I/O counters.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AggregatedSnapshot {
     pub created_at: i64,
     pub resolution_seconds: i32,
+    /// Raw samples behind this bucket; weights the `*_avg` fields when rolling up to a coarser
+    /// tier. 0 on rows written before schema v10 (unknown → equal weights).
+    pub sample_count: i64,
+    #[serde(with = "super::json_float::percent")]
     pub cpu_load_avg: f64,
+    #[serde(with = "super::json_float::percent")]
     pub cpu_load_min: f64,
+    #[serde(with = "super::json_float::percent")]
     pub cpu_load_max: f64,
     pub memory_used_avg: i64,
     pub memory_used_min: i64,
     pub memory_used_max: i64,
+    /// 95th percentile of the bucket's CPU load. `None` on rows written before schema v7.
+    #[serde(serialize_with = "super::json_float::percent_opt")]
+    pub cpu_load_p95: Option<f64>,
+    /// 95th percentile of the bucket's used memory. `None` on rows written before schema v7.
+    pub memory_used_p95: Option<i64>,
+    pub memory_total_avg: i64,
+    pub memory_total_min: i64,
+    pub memory_total_max: i64,
+    #[serde(with = "super::json_float::any")]
+    pub cpu_temperature_avg: f64,
+    #[serde(with = "super::json_float::any")]
+    pub cpu_temperature_min: f64,
+    #[serde(with = "super::json_float::any")]
+    pub cpu_temperature_max: f64,
     pub cpu: CpuStats,
     pub ram: RamStats,
+    /// Per container: CPU/memory averaged, counters from the last sample.
     pub containers: Vec<ContainerStats>,
+    /// Partition usage averaged per mount; sizes and disk counters from the last sample.
     pub storage: StorageStats,
+    /// Interface rx/tx rates averaged per name; byte/packet counters from the last sample.
     pub network: NetworkStats,
     pub system: SystemStatsDynamic,
     pub gpus: Vec<GpuStats>,
     pub smart: Vec<SmartHealth>,
+    /// Per probe name: `rtt_avg_ms` and `loss_percent` averaged, `rtt_min_ms` / `rtt_max_ms` the
+    /// bucket's extremes, `rtt_ms` from the last sample.
+    pub probes: Vec<ProbeStat>,
+    /// Per sensor id: `value` averaged, `max` the bucket's highest reading.
+    pub sensors: Vec<SensorStat>,
 }
@@ -0,0 +1,52 @@
+// /api/capabilities: what this instance can answer (history range, retention, optional features).
+
+use serde::{Deserialize, Serialize};
+
+/// Response of `GET /api/capabilities`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub name: String,
+    pub version: String,
+    /// Oldest / newest stored history point (epoch ms); `None` without history.
+    pub history_earliest_ts: Option<i64>,
+    pub history_latest_ts: Option<i64>,
+    pub sample_interval_ms: u64,
+    /// Raw first, then each aggregated tier, finest first; empty in agent mode.
+    pub retention: Vec<TierRetention>,
+    pub features: Features,
+}
+
+/// How long rows of one history tier are kept (`resolution_seconds` 0 = raw).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierRetention {
+    pub resolution_seconds: i32,
+    /// Age at which rows roll into the next tier, or are pruned from the last one.
+    pub keep_ms: i64,
+}
+
+/// Optional features enabled in the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Features {
+    /// `database.enabled`: the history endpoints answer (false in agent mode).
+    pub history: bool,
+    pub aggregation: bool,
+    /// `monitoring.collect_gpu`.
+    pub gpu: bool,
+    /// `monitoring.collect_smart`.
+    pub smart: bool,
+    /// `monitoring.collect_sensors`: GET /api/sensors and snapshot `sensors` are filled.
+    pub sensors: bool,
+    /// Any `[[alerts.rules]]` or `[[alerts.container_rules]]` configured.
+    pub alerts: bool,
+    pub mqtt: bool,
+    pub remote_write: bool,
+    /// `server.enable_wol`: POST /api/wol and GET /api/wol/targets answer.
+    pub wol: bool,
+    /// Any `[[probes.targets]]` configured.
+    pub probes: bool,
+    /// Any `[[http_checks]]` configured.
+    pub http_checks: bool,
+}
@@ -0,0 +1,48 @@
+// HTTP uptime check models. Populated by `crate::http_checks` from `[[http_checks]]`.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a check stands after its latest request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckState {
+    /// Not requested yet.
+    #[default]
+    Pending,
+    Up,
+    Down,
+}
+
+/// One `[[http_checks]]` entry as served by `GET /api/checks`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCheckStatus {
+    pub name: String,
+    pub url: String,
+    /// The linked container, as configured.
+    pub container: Option<String>,
+    pub state: CheckState,
+    /// Status of the latest response; `None` when there was none (timeout, refused, TLS).
+    pub http_status: Option<u16>,
+    /// Time to the response headers of the latest request that got one.
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub latency_ms: Option<f64>,
+    /// Failed requests in a row; 0 while up.
+    pub consecutive_failures: u32,
+    /// Why the latest request failed; cleared by a success.
+    pub last_error: Option<String>,
+    /// Epoch ms of the latest request.
+    pub last_checked_ms: Option<u64>,
+    /// Epoch ms since which `state` holds.
+    pub since_ms: Option<u64>,
+}
+
+/// The compact per-check part of every snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckSummary {
+    pub name: String,
+    pub state: CheckState,
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub latency_ms: Option<f64>,
+}
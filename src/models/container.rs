@@ -1,8 +1,12 @@
 // Docker container models
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use wincode::{SchemaRead, SchemaWrite};
 
+use crate::config::CpuPercentMode;
+
 /// Docker container state; serializes to lowercase JSON (e.g. "running").
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, SchemaRead, SchemaWrite)]
 #[serde(rename_all = "lowercase")]
@@ -26,6 +30,17 @@ impl ContainerState {
             _ => ContainerState::Unknown,
         }
     }
+
+    /// The lowercase name it serializes to, which [`Self::from_docker`] reads back.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContainerState::Running => "running",
+            ContainerState::Exited => "exited",
+            ContainerState::Paused => "paused",
+            ContainerState::Restarting => "restarting",
+            ContainerState::Unknown => "unknown",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, SchemaRead, SchemaWrite)]
@@ -33,10 +48,21 @@ impl ContainerState {
 pub struct ContainerStats {
     pub id: String,
     pub name: String,
+    /// Image the container runs, from the Docker listing. Live only: history keeps it in
+    /// `container_inventory`, so entries read back from snapshots carry "".
+    #[serde(default)]
+    #[wincode(skip)]
+    pub image: String,
+    /// Percent of one core, like `docker stats` (so up to `online_cpus` × 100), as collected and
+    /// stored; API responses put it on the `docker.cpu_percent_mode` scale
+    /// ([`Self::apply_cpu_percent_mode`]), as are `cpu_kernel_percent` and `cpu_user_percent`.
+    #[serde(with = "super::json_float::percent")]
     pub cpu_percent: f64,
     pub memory_usage_bytes: u64,
     pub memory_limit_bytes: u64,
     pub state: ContainerState,
+    /// Network, block I/O and throttling counters below are cumulative since container start;
+    /// aggregated rows keep the bucket's last reading.
     #[serde(default)]
     pub network_rx_bytes: u64,
     #[serde(default)]
@@ -65,18 +91,192 @@ pub struct ContainerStats {
     pub pids: u64,
     #[serde(default)]
     pub pids_limit: u64,
+    /// `pids` as a percent of `pids_limit` (0 without a limit): fork failures start at 100.
+    /// Derived, so not part of this layout; history decoding recomputes it from the two fields.
+    #[serde(default, with = "super::json_float::percent")]
+    #[wincode(skip)]
+    pub pids_usage_percent: f64,
     #[serde(default)]
     pub cpu_throttled: bool,
     #[serde(default)]
     pub cpu_throttled_periods: u64,
     #[serde(default)]
     pub cpu_throttled_time_ns: u64,
-    #[serde(default)]
+    #[serde(default, with = "super::json_float::percent")]
     pub cpu_kernel_percent: f64,
-    #[serde(default)]
+    #[serde(default, with = "super::json_float::percent")]
     pub cpu_user_percent: f64,
     #[serde(default)]
     pub online_cpus: u32,
+    /// Share of the whole host's CPU (`cpu_percent` per core / `online_cpus`) whatever
+    /// `docker.cpu_percent_mode` says, for showing next to the host CPU graph. Derived, so not
+    /// part of this layout; history decoding recomputes it ([`cpu_percent_of_host`]).
+    #[serde(default, with = "super::json_float::percent")]
+    #[wincode(skip)]
+    pub cpu_percent_of_host: f64,
     #[serde(default)]
     pub memory_max_usage_bytes: u64,
+    /// When the Docker stats stream delivered this entry (epoch ms). Live only: history rows
+    /// do not store it, so entries read back carry 0.
+    #[serde(default)]
+    #[wincode(skip)]
+    pub observed_at: u64,
+    /// Byte rates since the previous stats sample of this container (0 on the first sample and
+    /// when a counter went backwards, e.g. after a restart); aggregated rows average them. Not
+    /// part of this layout: history stores them next to the containers ([`ContainerRates`]).
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub network_rx_bytes_per_sec: f64,
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub network_tx_bytes_per_sec: f64,
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub block_read_bytes_per_sec: f64,
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub block_write_bytes_per_sec: f64,
+}
+
+impl ContainerStats {
+    /// Put `cpu_percent` and its kernel / user split on `mode`'s scale. Everything inside the
+    /// process (collection, history, sync, remote write) is per core; call this once, on an
+    /// owned copy, where stats leave for a client.
+    pub fn apply_cpu_percent_mode(&mut self, mode: CpuPercentMode) {
+        if mode == CpuPercentMode::HostTotal {
+            self.cpu_percent = cpu_percent_of_host(self.cpu_percent, self.online_cpus);
+            self.cpu_kernel_percent =
+                cpu_percent_of_host(self.cpu_kernel_percent, self.online_cpus);
+            self.cpu_user_percent = cpu_percent_of_host(self.cpu_user_percent, self.online_cpus);
+        }
+    }
+}
+
+/// A per-core percent as a share of the whole host; `online_cpus` 0 (rows stored before it
+/// was) counts as one core.
+pub fn cpu_percent_of_host(per_core_percent: f64, online_cpus: u32) -> f64 {
+    per_core_percent / f64::from(online_cpus.max(1))
+}
+
+/// `pids` as a percent of `pids_limit`; 0 when the limit is 0 (unlimited).
+pub fn pids_usage_percent(pids: u64, pids_limit: u64) -> f64 {
+    if pids_limit == 0 {
+        0.0
+    } else {
+        pids as f64 / pids_limit as f64 * 100.0
+    }
+}
+
+/// The per-second rates of one [`ContainerStats`], stored in `container_data` beside the
+/// containers so blobs written before the rates existed still decode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, SchemaRead, SchemaWrite)]
+pub struct ContainerRates {
+    pub network_rx_bytes_per_sec: f64,
+    pub network_tx_bytes_per_sec: f64,
+    pub block_read_bytes_per_sec: f64,
+    pub block_write_bytes_per_sec: f64,
+}
+
+impl ContainerRates {
+    pub fn of(c: &ContainerStats) -> Self {
+        Self {
+            network_rx_bytes_per_sec: c.network_rx_bytes_per_sec,
+            network_tx_bytes_per_sec: c.network_tx_bytes_per_sec,
+            block_read_bytes_per_sec: c.block_read_bytes_per_sec,
+            block_write_bytes_per_sec: c.block_write_bytes_per_sec,
+        }
+    }
+
+    pub fn apply(self, c: &mut ContainerStats) {
+        c.network_rx_bytes_per_sec = self.network_rx_bytes_per_sec;
+        c.network_tx_bytes_per_sec = self.network_tx_bytes_per_sec;
+        c.block_read_bytes_per_sec = self.block_read_bytes_per_sec;
+        c.block_write_bytes_per_sec = self.block_write_bytes_per_sec;
+    }
+}
+
+/// `container_data` from blob version 5 on: the containers and their rates, index for index.
+#[derive(Debug, Clone, Default, SchemaRead, SchemaWrite)]
+pub struct ContainerBlob {
+    pub containers: Vec<ContainerStats>,
+    pub rates: Vec<ContainerRates>,
+}
+
+impl ContainerBlob {
+    pub fn new(containers: &[ContainerStats]) -> Self {
+        Self {
+            containers: containers.to_vec(),
+            rates: containers.iter().map(ContainerRates::of).collect(),
+        }
+    }
+
+    /// The containers with their rates restored (missing rates stay 0).
+    pub fn into_containers(self) -> Vec<ContainerStats> {
+        let mut containers = self.containers;
+        for (c, rates) in containers.iter_mut().zip(self.rates) {
+            rates.apply(c);
+        }
+        containers
+    }
+}
+
+/// Container lifecycle actions from the Docker event stream that alerting cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerAction {
+    Start,
+    Die,
+    Oom,
+    Restart,
+    /// `health_status: unhealthy`.
+    Unhealthy,
+    /// `health_status: healthy`.
+    Healthy,
+}
+
+/// One Docker container event (`docker events --filter type=container`).
+#[derive(Debug, Clone)]
+pub struct ContainerEvent {
+    /// Unix ms.
+    pub time_ms: u64,
+    pub action: ContainerAction,
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// `exitCode` of a `die` event.
+    pub exit_code: Option<i64>,
+    /// Event attributes: the container's labels plus `name`, `image` and the like.
+    pub attributes: HashMap<String, String>,
+}
+
+/// One entry of `GET /api/history/top-containers`: a container's average and peak of the ranked
+/// metric over the range, from the `container_history` rows stored for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopContainer {
+    pub container_id: String,
+    /// Latest name from `container_inventory` (the container's latest row without an entry).
+    pub name: String,
+    pub average: f64,
+    pub max: f64,
+    /// Rows (raw snapshots in which it was among the busiest) behind the figures.
+    pub samples: u64,
+}
+
+/// One entry of `GET /api/containers/inventory`: a container's lifetime as recorded by the
+/// history writer in `container_inventory`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerInventoryEntry {
+    pub container_id: String,
+    /// Name at the latest sighting.
+    pub name: String,
+    pub image: String,
+    /// Unix ms of the first and latest flushed snapshot listing it.
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub last_state: ContainerState,
+    /// Earlier names of the same id, oldest first.
+    pub previous_names: Vec<String>,
+    /// Missing from the latest local snapshot in history.
+    pub gone: bool,
 }
@@ -0,0 +1,76 @@
+// /api/db: database statistics, backups and storage projection.
+
+use serde::{Deserialize, Serialize};
+
+/// End (exclusive, epoch ms) of the last bucket fully rolled up into `resolution_seconds`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationWatermark {
+    pub resolution_seconds: i32,
+    pub watermark: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbStats {
+    pub schema_version: i64,
+    pub raw_rows: i64,
+    pub aggregated_rows: i64,
+    /// Distinct shared storage/network blobs (see `history_repo::blob_store`).
+    pub blob_store_entries: i64,
+    pub aggregation_watermarks: Vec<AggregationWatermark>,
+    /// Current size of the `-wal` file in bytes.
+    pub wal_size_bytes: u64,
+    /// `PRAGMA quick_check` problems (empty = sound); only present for `/api/db?integrity=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity_problems: Option<Vec<String>>,
+}
+
+/// Result of `POST /api/db/backup`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// Row statistics for one history tier (`resolution_seconds` 0 = raw).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierStats {
+    pub resolution_seconds: i32,
+    pub rows: u64,
+    /// Oldest / newest `created_at` (epoch ms); `None` when the tier is empty.
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    /// Sum of the rows' blob lengths (`length()`); raw also counts the shared `blob_store`.
+    /// Excludes indexes and page overhead.
+    pub total_bytes: u64,
+    pub avg_row_bytes: u64,
+    /// Bytes per day of history covered, from the rows' spacing; `None` with fewer than 2 rows.
+    pub bytes_per_day: Option<u64>,
+}
+
+/// One tier of a [`StorageProjection`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierProjection {
+    #[serde(flatten)]
+    pub stats: TierStats,
+    /// Days of history this tier holds once retention is in steady state.
+    pub window_days: f64,
+    /// `bytes_per_day * window_days`; `None` while the rate is unknown.
+    pub projected_bytes: Option<u64>,
+}
+
+/// Response of `GET /api/db/projection`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProjection {
+    pub tiers: Vec<TierProjection>,
+    /// Sum of the known per-tier projections.
+    pub projected_bytes: u64,
+    /// `database.disk_budget_bytes`; 0 = no budget.
+    pub disk_budget_bytes: u64,
+    pub exceeds_budget: bool,
+}
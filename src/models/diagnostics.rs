@@ -0,0 +1,104 @@
+// /api/errors: collector failures recorded by the stats worker; /api/events: server starts and stops.
+
+use serde::{Deserialize, Serialize};
+
+/// One row of `collection_errors`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionError {
+    /// Epoch ms.
+    pub ts: i64,
+    /// Failing collector, e.g. `"docker"` or `"network"`.
+    pub source: String,
+    pub message: String,
+    /// Failures of the same source dropped by the rate limit since the previous entry.
+    pub suppressed: u64,
+}
+
+/// Failures per source in a time window (recorded entries plus their suppressed counts).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorSourceCount {
+    pub source: String,
+    pub count: u64,
+}
+
+/// Response of `GET /api/errors`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorsSummary {
+    /// Newest first.
+    pub errors: Vec<CollectionError>,
+    /// Start of the `counts` window (epoch ms).
+    pub since: i64,
+    pub counts: Vec<ErrorSourceCount>,
+}
+
+/// What a `service_events` row records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceEventKind {
+    /// The server started after a clean shutdown (or for the first time).
+    Started,
+    /// The server drained and closed the database.
+    CleanShutdown,
+    /// The server started, but the previous run ended without a clean shutdown.
+    RecoveredAfterCrash,
+    /// An `[[http_checks]]` entry started failing (`name`, `detail` = the error).
+    CheckDown,
+    /// A failing `[[http_checks]]` entry answered as expected again.
+    CheckUp,
+}
+
+impl ServiceEventKind {
+    /// The `event` column value (same as the JSON).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Started => "started",
+            Self::CleanShutdown => "clean_shutdown",
+            Self::RecoveredAfterCrash => "recovered_after_crash",
+            Self::CheckDown => "check_down",
+            Self::CheckUp => "check_up",
+        }
+    }
+
+    /// Parse an `event` column value; `None` for anything else.
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            Self::Started,
+            Self::CleanShutdown,
+            Self::RecoveredAfterCrash,
+            Self::CheckDown,
+            Self::CheckUp,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == value)
+    }
+}
+
+/// One row of `service_events`: the server starting or stopping, or an HTTP check changing
+/// state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEvent {
+    /// Epoch ms.
+    pub ts: i64,
+    pub event: ServiceEventKind,
+    /// The check, for `check_*` events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Why a check failed, or how it answered on recovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Response of `GET /api/events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEventsSummary {
+    /// When this process started (epoch ms) and how long ago, in seconds.
+    pub service_start_epoch_ms: u64,
+    pub service_uptime_secs: u64,
+    /// Newest first.
+    pub events: Vec<ServiceEvent>,
+}
@@ -11,12 +11,16 @@ pub struct GpuStats {
     /// "nvidia" | "amd" | "intel".
     pub vendor: String,
     pub name: String,
+    #[serde(with = "super::json_float::percent")]
     pub utilization_percent: f64,
     pub memory_used_bytes: u64,
     pub memory_total_bytes: u64,
+    #[serde(with = "super::json_float::any")]
     pub temperature_c: f64,
     /// Board power draw in watts, when exposed.
+    #[serde(serialize_with = "super::json_float::any_opt")]
     pub power_watts: Option<f64>,
     /// Fan speed as a percentage, when exposed.
+    #[serde(serialize_with = "super::json_float::percent_opt")]
     pub fan_percent: Option<f64>,
 }
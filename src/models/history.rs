@@ -0,0 +1,88 @@
+// /api/history points: a snapshot plus the optional min/max (and p95) envelope of its bucket, and
+// the anomalies flagged by `annotate=anomalies`.
+
+use serde::{Deserialize, Serialize};
+
+use super::FullSystemSnapshot;
+
+/// Spread of the samples behind one history point. Raw points have min = max = p95 = value.
+/// p95 is `None` for aggregated rows written before percentiles were tracked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEnvelope {
+    #[serde(with = "super::json_float::percent")]
+    pub cpu_load_min: f64,
+    #[serde(with = "super::json_float::percent")]
+    pub cpu_load_max: f64,
+    pub memory_used_min: i64,
+    pub memory_used_max: i64,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "super::json_float::percent_opt"
+    )]
+    pub cpu_load_p95: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_used_p95: Option<i64>,
+}
+
+/// One /api/history point. Serializes as the snapshot's fields plus `envelope` when requested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPoint {
+    #[serde(flatten)]
+    pub snapshot: FullSystemSnapshot,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<HistoryEnvelope>,
+}
+
+/// Which series an [`Anomaly`] was found in: CPU usage or RAM usage (percent).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyMetric {
+    Cpu,
+    Ram,
+}
+
+/// `Warning` past the z-score threshold, `Critical` past twice the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalySeverity {
+    Warning,
+    Critical,
+}
+
+/// A run of consecutive history points (`from` / `to` are their timestamps, Unix ms) whose
+/// `metric` lies beyond the z-score threshold; `severity` is that of the worst point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Anomaly {
+    pub from: u64,
+    pub to: u64,
+    pub metric: AnomalyMetric,
+    pub severity: AnomalySeverity,
+}
+
+/// Where a `/api/history/sync` client resumes from: the `seq` (`system_history` row id) of the
+/// last snapshot it holds, or its timestamp (Unix ms) when it predates sequence numbers. Both
+/// `None` asks for an initial sync from the oldest retained snapshot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCursor {
+    pub since_seq: Option<u64>,
+    pub since_ts: Option<u64>,
+}
+
+/// One `/api/history/sync` page: local snapshots after the cursor, oldest first. With
+/// `reset_required` the cursor was older than retention (or unknown to this database): the
+/// client drops its cache and applies `snapshots`, which then start from the oldest retained one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncBatch {
+    pub snapshots: Vec<FullSystemSnapshot>,
+    /// The cursor to send next; the request's own cursor when no rows were returned.
+    pub next_cursor: SyncCursor,
+    pub reset_required: bool,
+    /// More rows follow `next_cursor`: request again without waiting.
+    pub has_more: bool,
+}
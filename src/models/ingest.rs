@@ -0,0 +1,68 @@
+// POST /api/ingest body: snapshots pushed by another instance ([remote_write]).
+
+use serde::{Deserialize, Serialize};
+use wincode::{SchemaRead, SchemaWrite};
+
+use super::{FullSystemSnapshot, SnapshotRecord, json_float};
+
+/// Content type of wincode-encoded batches; anything else is read as JSON.
+pub const INGEST_WINCODE_CONTENT_TYPE: &str = "application/x-wincode";
+
+/// One pushed batch, sent as JSON ([`IngestBatch::to_json`]) or wincode
+/// ([`IngestBatch::to_wincode`]). `node` names the sending instance; its rows are stored under
+/// that name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestBatch {
+    pub node: String,
+    pub snapshots: Vec<FullSystemSnapshot>,
+}
+
+/// Wincode form of an [`IngestBatch`]: each snapshot as a [`SnapshotRecord`], so `probes` and
+/// `sensors` travel with it.
+#[derive(SchemaRead, SchemaWrite)]
+struct WincodeBatch {
+    node: String,
+    snapshots: Vec<SnapshotRecord>,
+}
+
+/// The wincode form sent before probes and sensors were carried: bare snapshots. Still read,
+/// from older senders and from batches they spilled.
+#[derive(SchemaRead, SchemaWrite)]
+struct LegacyWincodeBatch {
+    node: String,
+    snapshots: Vec<FullSystemSnapshot>,
+}
+
+impl IngestBatch {
+    /// Body of a JSON batch (`remote_write.format = "json"`): floats as measured (see
+    /// [`json_float::exact`]), so the receiver stores what the sender does.
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        json_float::exact(|| serde_json::to_vec(self))
+    }
+
+    /// Body of a wincode batch (`remote_write.format = "wincode"`) and of a spill file.
+    pub fn to_wincode(&self) -> Result<Vec<u8>, wincode::WriteError> {
+        wincode::serialize(&WincodeBatch {
+            node: self.node.clone(),
+            snapshots: self.snapshots.iter().cloned().map(Into::into).collect(),
+        })
+    }
+
+    /// Decode [`Self::to_wincode`] output, or a batch in the older form without probes and
+    /// sensors.
+    pub fn from_wincode(bytes: &[u8]) -> Result<Self, wincode::ReadError> {
+        match wincode::deserialize_exact::<WincodeBatch>(bytes) {
+            Ok(batch) => Ok(Self {
+                node: batch.node,
+                snapshots: batch.snapshots.into_iter().map(Into::into).collect(),
+            }),
+            Err(e) => wincode::deserialize_exact::<LegacyWincodeBatch>(bytes)
+                .map(|batch| Self {
+                    node: batch.node,
+                    snapshots: batch.snapshots,
+                })
+                .map_err(|_| e),
+        }
+    }
+}
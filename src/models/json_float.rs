@@ -0,0 +1,148 @@
+// `with` helpers for float fields in JSON output (WS, HTTP, MQTT, webhooks): NaN and ±Infinity
+// become 0 (serde_json would write `null`), percentages keep two decimals and byte rates are
+// whole numbers. Binary history rows (wincode) keep the values as measured, and so does JSON
+// written under [`exact`] (remote write batches).
+
+use std::cell::Cell;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+thread_local! {
+    static EXACT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` (a serialization) with the helpers writing floats as measured: no rounding, and NaN /
+/// ±Infinity as `null`, which the helpers read back as NaN. For copies another instance stores
+/// (`POST /api/ingest`), not for display.
+pub fn exact<T>(f: impl FnOnce() -> T) -> T {
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            EXACT.set(self.0);
+        }
+    }
+    let _restore = Restore(EXACT.replace(true));
+    f()
+}
+
+fn is_exact() -> bool {
+    EXACT.get()
+}
+
+/// `v`, or 0 when it is NaN or infinite.
+pub fn finite(v: f64) -> f64 {
+    if v.is_finite() { v } else { 0.0 }
+}
+
+/// `v` scrubbed by [`finite`] and rounded to two decimals.
+pub fn round_percent(v: f64) -> f64 {
+    let rounded = (finite(v) * 100.0).round() / 100.0;
+    // `* 100.0` overflows near f64::MAX; `+ 0.0` turns -0.0 into 0.
+    if rounded.is_finite() {
+        rounded + 0.0
+    } else {
+        0.0
+    }
+}
+
+/// `v` scrubbed by [`finite`] and rounded to a whole number (saturating at the `i64` range).
+pub fn round_rate(v: f64) -> i64 {
+    finite(v).round() as i64
+}
+
+/// Under [`exact`]: `v` as measured, `null` when it is not finite.
+fn serialize_exact<S: Serializer>(v: f64, s: S) -> Result<S::Ok, S::Error> {
+    if v.is_finite() {
+        s.serialize_f64(v)
+    } else {
+        s.serialize_none()
+    }
+}
+
+/// A number, or `null` (written under [`exact`] for NaN / ±Infinity) as NaN.
+fn deserialize_nan<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(d)?.unwrap_or(f64::NAN))
+}
+
+/// Any float: scrubbed, otherwise as measured.
+pub mod any {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &f64, s: S) -> Result<S::Ok, S::Error> {
+        if is_exact() {
+            return serialize_exact(*v, s);
+        }
+        s.serialize_f64(finite(*v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        deserialize_nan(d)
+    }
+}
+
+/// `Option` fields keep serde's own handling of `null` and missing keys; NaN under [`exact`] reads
+/// back as `None`.
+pub fn any_opt<S: Serializer>(v: &Option<f64>, s: S) -> Result<S::Ok, S::Error> {
+    match v {
+        Some(v) => any::serialize(v, s),
+        None => s.serialize_none(),
+    }
+}
+
+/// Percentages: two decimals.
+pub mod percent {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &f64, s: S) -> Result<S::Ok, S::Error> {
+        if is_exact() {
+            return serialize_exact(*v, s);
+        }
+        s.serialize_f64(round_percent(*v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        deserialize_nan(d)
+    }
+}
+
+pub fn percent_opt<S: Serializer>(v: &Option<f64>, s: S) -> Result<S::Ok, S::Error> {
+    match v {
+        Some(v) => percent::serialize(v, s),
+        None => s.serialize_none(),
+    }
+}
+
+/// A list of percentages (per-core usage).
+pub mod percents {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &[f64], s: S) -> Result<S::Ok, S::Error> {
+        if is_exact() {
+            return s.collect_seq(v.iter().map(|p| p.is_finite().then_some(*p)));
+        }
+        s.collect_seq(v.iter().map(|&p| round_percent(p)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<f64>, D::Error> {
+        Ok(Vec::<Option<f64>>::deserialize(d)?
+            .into_iter()
+            .map(|p| p.unwrap_or(f64::NAN))
+            .collect())
+    }
+}
+
+/// Byte (or packet) rates per second: whole numbers.
+pub mod rate {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &f64, s: S) -> Result<S::Ok, S::Error> {
+        if is_exact() {
+            return serialize_exact(*v, s);
+        }
+        s.serialize_i64(round_rate(*v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        deserialize_nan(d)
+    }
+}
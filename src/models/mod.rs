@@ -1,20 +1,53 @@
 // Domain models (ported from shared Kotlin)
 
 mod aggregation;
+mod capabilities;
+mod check;
 mod container;
+mod db;
+mod diagnostics;
 mod gpu;
+mod history;
+mod ingest;
+pub mod json_float;
 mod network;
+mod probe;
+mod report;
+mod self_stats;
+mod sensor;
 mod smart;
 mod storage;
 mod system;
 
 pub use aggregation::AggregatedSnapshot;
-pub use container::{ContainerState, ContainerStats};
+pub use capabilities::{Capabilities, Features, TierRetention};
+pub use check::{CheckState, CheckSummary, HttpCheckStatus};
+pub use container::{
+    ContainerAction, ContainerBlob, ContainerEvent, ContainerInventoryEntry, ContainerRates,
+    ContainerState, ContainerStats, TopContainer, cpu_percent_of_host, pids_usage_percent,
+};
+pub use db::{
+    AggregationWatermark, BackupInfo, DbStats, StorageProjection, TierProjection, TierStats,
+};
+pub use diagnostics::{
+    CollectionError, ErrorSourceCount, ErrorsSummary, ServiceEvent, ServiceEventKind,
+    ServiceEventsSummary,
+};
 pub use gpu::GpuStats;
-pub use network::{InterfaceStat, NetworkStats};
+pub use history::{
+    Anomaly, AnomalyMetric, AnomalySeverity, HistoryEnvelope, HistoryPoint, SyncBatch, SyncCursor,
+};
+pub use ingest::{INGEST_WINCODE_CONTENT_TYPE, IngestBatch};
+pub use network::{
+    InterfaceHistoryPoint, InterfaceStat, NetworkStats, NetworkTotals, is_physical_interface,
+};
+pub use probe::ProbeStat;
+pub use report::{PartitionGrowth, UsageReport};
+pub use self_stats::SelfStats;
+pub use sensor::{SensorKind, SensorStat};
 pub use smart::SmartHealth;
-pub use storage::{DiskDeviceStat, PartitionStat, StorageStats};
+pub use storage::{DiskDeviceStat, PartitionProjection, PartitionStat, StorageStats};
 pub use system::{
-    CpuStats, FullSystemSnapshot, FullSystemSnapshotDisplay, RamStats, SystemInfo, SystemStats,
-    SystemStatsDynamic, merge_system_info,
+    CpuStats, FullSystemSnapshot, FullSystemSnapshotDisplay, RamStats, SnapshotRecord, SystemInfo,
+    SystemStats, SystemStatsDynamic, merge_system_info,
 };
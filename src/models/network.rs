@@ -16,15 +16,107 @@ pub struct InterfaceStat {
     pub packets_sent: u64,
     pub packets_recv: u64,
     pub speed: u64,
-    #[serde(default)]
+    #[serde(default, with = "super::json_float::rate")]
     pub received_bytes_per_sec: f64,
-    #[serde(default)]
+    #[serde(default, with = "super::json_float::rate")]
     pub transmitted_bytes_per_sec: f64,
     pub is_up: bool,
+    /// Live only: not stored in history, so history rows read 0.
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub packets_recv_per_sec: f64,
+    /// Live only, like `packets_recv_per_sec`.
+    #[serde(default, with = "super::json_float::rate")]
+    #[wincode(skip)]
+    pub packets_sent_per_sec: f64,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, SchemaRead, SchemaWrite)]
 #[serde(rename_all = "camelCase")]
 pub struct NetworkStats {
     pub interfaces: Vec<InterfaceStat>,
+    /// Host-wide rates over the physical interfaces. Not stored: history rows derive it from
+    /// their interfaces on read ([`NetworkTotals::of`]).
+    #[serde(default)]
+    #[wincode(skip)]
+    pub totals: NetworkTotals,
+}
+
+impl NetworkStats {
+    /// `interfaces` with their [`NetworkTotals`].
+    pub fn new(interfaces: Vec<InterfaceStat>) -> Self {
+        let totals = NetworkTotals::of(&interfaces);
+        Self { interfaces, totals }
+    }
+}
+
+/// Rates summed over the interfaces [`is_physical_interface`] keeps, so traffic that crosses a
+/// bridge or veth pair on its way out is counted once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkTotals {
+    #[serde(with = "super::json_float::rate")]
+    pub rx_bytes_per_sec: f64,
+    #[serde(with = "super::json_float::rate")]
+    pub tx_bytes_per_sec: f64,
+    #[serde(with = "super::json_float::rate")]
+    pub rx_packets_per_sec: f64,
+    #[serde(with = "super::json_float::rate")]
+    pub tx_packets_per_sec: f64,
+}
+
+impl NetworkTotals {
+    pub fn of(interfaces: &[InterfaceStat]) -> Self {
+        interfaces
+            .iter()
+            .filter(|i| is_physical_interface(&i.name))
+            .fold(Self::default(), |t, i| Self {
+                rx_bytes_per_sec: t.rx_bytes_per_sec + i.received_bytes_per_sec,
+                tx_bytes_per_sec: t.tx_bytes_per_sec + i.transmitted_bytes_per_sec,
+                rx_packets_per_sec: t.rx_packets_per_sec + i.packets_recv_per_sec,
+                tx_packets_per_sec: t.tx_packets_per_sec + i.packets_sent_per_sec,
+            })
+    }
+}
+
+/// Name prefixes of virtual interfaces whose traffic also crosses a physical one: loopback,
+/// container veth pairs, bridges (Docker, libvirt, CNI) and overlay / VPN tunnels.
+const VIRTUAL_INTERFACE_PREFIXES: &[&str] = &[
+    "lo",
+    "veth",
+    "br",
+    "vmbr",
+    "docker",
+    "virbr",
+    "cni",
+    "flannel",
+    "cali",
+    "vxlan",
+    "tun",
+    "tap",
+    "wg",
+    "tailscale",
+    "zt",
+];
+
+/// Whether `name` looks like a physical NIC (a heuristic on the name: not loopback, a veth
+/// pair, a bridge or a tunnel).
+pub fn is_physical_interface(name: &str) -> bool {
+    !VIRTUAL_INTERFACE_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// One point of `GET /api/history/network`: an interface's rates (averaged over the bucket) and
+/// its cumulative byte counters (last value in the bucket).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceHistoryPoint {
+    pub timestamp: u64,
+    #[serde(with = "super::json_float::rate")]
+    pub rx_bytes_per_sec: f64,
+    #[serde(with = "super::json_float::rate")]
+    pub tx_bytes_per_sec: f64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
 }
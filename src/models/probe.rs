@@ -0,0 +1,31 @@
+// Reachability probe model. Populated by `crate::probes` from `[[probes.targets]]`.
+
+use serde::{Deserialize, Serialize};
+use wincode::{SchemaRead, SchemaWrite};
+
+/// One probe target over its recent window of probes (`probes.window`). Round-trip times are
+/// `None` while no probe in the window got an answer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SchemaRead, SchemaWrite)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeStat {
+    /// `name` of the `[[probes.targets]]` entry; unique.
+    pub name: String,
+    /// Host name or address as configured.
+    pub target: String,
+    /// "icmp" | "tcp" (connect to `probes.tcp_port` when ICMP needs a privilege we lack).
+    pub method: String,
+    /// Round trip of the latest probe; `None` when it was lost.
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub rtt_ms: Option<f64>,
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub rtt_avg_ms: Option<f64>,
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub rtt_min_ms: Option<f64>,
+    #[serde(serialize_with = "super::json_float::any_opt")]
+    pub rtt_max_ms: Option<f64>,
+    /// Share of the window's probes that got no answer.
+    #[serde(with = "super::json_float::percent")]
+    pub loss_percent: f64,
+    /// Probes in the window (fewer than `probes.window` right after startup).
+    pub samples: u32,
+}
@@ -0,0 +1,43 @@
+// Usage report: CPU/RAM averages and peaks, disk growth, network transfer and the busiest
+// containers over a window, as served by GET /api/report and pushed to the alert webhooks.
+
+use serde::{Deserialize, Serialize};
+
+use super::TopContainer;
+
+/// Summary of a window (`from` inclusive, `to` exclusive, Unix ms) of history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub from: i64,
+    pub to: i64,
+    /// History points the figures come from; 0 when the window holds no data.
+    pub samples: usize,
+    #[serde(with = "super::json_float::percent")]
+    pub cpu_average_percent: f64,
+    #[serde(with = "super::json_float::percent")]
+    pub cpu_peak_percent: f64,
+    pub ram_average_bytes: u64,
+    pub ram_peak_bytes: u64,
+    /// Installed RAM at the end of the window.
+    pub ram_total_bytes: u64,
+    /// One entry per mount seen in the window, by mount point.
+    pub partitions: Vec<PartitionGrowth>,
+    /// Bytes received / sent over all interfaces, from the cumulative counters.
+    pub network_rx_bytes: u64,
+    pub network_tx_bytes: u64,
+    /// Highest average CPU first.
+    pub top_containers: Vec<TopContainer>,
+}
+
+/// Used space of one partition at the first and last point it appears in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionGrowth {
+    pub mount: String,
+    pub total_space: u64,
+    pub start_used_bytes: u64,
+    pub end_used_bytes: u64,
+    /// `end_used_bytes - start_used_bytes`; negative when space was freed.
+    pub growth_bytes: i64,
+}
@@ -0,0 +1,19 @@
+// Self-monitoring model: the server's own process, sampled by the stats worker each tick.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfStats {
+    /// CPU used since the previous tick, in percent of one core (can exceed 100; 0 on the first).
+    #[serde(with = "super::json_float::percent")]
+    pub cpu_percent: f64,
+    /// Resident set size.
+    pub rss_bytes: u64,
+    /// Open file descriptors (`None` where the platform does not report them).
+    pub open_fds: Option<u32>,
+    /// Tasks alive on the tokio runtime.
+    pub tokio_tasks: Option<u64>,
+    /// History database file plus its `-wal`; `None` without a local database (agent mode).
+    pub db_file_bytes: Option<u64>,
+}
@@ -0,0 +1,56 @@
+// Hardware sensor model. Populated by `crate::sensors_repo` from the hwmon tree.
+
+use serde::{Deserialize, Serialize};
+use wincode::{SchemaRead, SchemaWrite};
+
+/// What a sensor measures, from its hwmon file prefix.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, SchemaRead, SchemaWrite,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SensorKind {
+    /// `temp*_input`, in °C.
+    #[default]
+    Temp,
+    /// `fan*_input`, in RPM.
+    Fan,
+    /// `in*_input`, in V.
+    Voltage,
+}
+
+impl SensorKind {
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Temp => "°C",
+            Self::Fan => "RPM",
+            Self::Voltage => "V",
+        }
+    }
+}
+
+/// One hwmon reading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SchemaRead, SchemaWrite)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorStat {
+    /// `<chip>/<label>`, where the chip is the hwmon `name` plus the device it sits on
+    /// (`nvme-0000:01:00.0`): stable across reboots that renumber `hwmon*`. Unique.
+    pub id: String,
+    /// The chip's hwmon `name` ("nvme", "k10temp", "nct6798").
+    pub name: String,
+    /// `*_label` when the driver provides one ("Composite", "Tctl"), else the file prefix
+    /// ("temp2").
+    pub label: String,
+    pub kind: SensorKind,
+    #[serde(with = "super::json_float::any")]
+    pub value: f64,
+    /// `°C`, `RPM` or `V`.
+    pub unit: String,
+    /// Highest reading of an aggregated bucket (`value` is then the average); `None` on live
+    /// readings.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "super::json_float::any_opt"
+    )]
+    pub max: Option<f64>,
+}
@@ -13,6 +13,7 @@ pub struct PartitionStat {
     pub total_space: u64,
     pub used_space: u64,
     pub available_space: u64,
+    #[serde(with = "super::json_float::percent")]
     pub usage_percent: f64,
 }
 
@@ -35,3 +36,18 @@ pub struct StorageStats {
     pub partitions: Vec<PartitionStat>,
     pub disks: Vec<DiskDeviceStat>,
 }
+
+/// One mount of `GET /api/storage/projection`: its latest usage and the linear trend of
+/// `used_space` over the requested days.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionProjection {
+    pub mount: String,
+    pub used_space: u64,
+    pub total_space: u64,
+    /// Slope of the fit; `None` with fewer than two samples at different times.
+    pub bytes_per_day: Option<f64>,
+    /// Days until `used_space` reaches `total_space` at that slope (0 when already full);
+    /// `None` when the trend is flat or shrinking.
+    pub days_until_full: Option<f64>,
+}
@@ -3,7 +3,10 @@
 use serde::{Deserialize, Serialize};
 use wincode::{SchemaRead, SchemaWrite};
 
-use super::{ContainerStats, GpuStats, NetworkStats, SmartHealth, StorageStats};
+use super::{
+    CheckSummary, ContainerStats, GpuStats, NetworkStats, ProbeStat, SelfStats, SensorStat,
+    SmartHealth, StorageStats,
+};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, SchemaRead, SchemaWrite)]
 #[serde(rename_all = "camelCase")]
@@ -11,9 +14,12 @@ pub struct CpuStats {
     pub model: String,
     pub physical_cores: u32,
     pub logical_cores: u32,
+    #[serde(with = "super::json_float::percent")]
     pub usage_percent: f64,
+    #[serde(with = "super::json_float::any")]
     pub temperature: f64,
     /// Per-logical-core usage percentages (empty if unavailable).
+    #[serde(with = "super::json_float::percents")]
     pub core_usages: Vec<f64>,
 }
 
@@ -23,14 +29,15 @@ pub struct RamStats {
     pub total: u64,
     pub used: u64,
     pub available: u64,
+    #[serde(with = "super::json_float::percent")]
     pub usage_percent: f64,
     pub swap_total: u64,
     pub swap_used: u64,
     pub swap_free: u64,
 }
 
-/// Static system identity; fetched once at startup and exposed via GET /api/info.
-#[derive(Debug, Clone, Default, Serialize, Deserialize, SchemaRead, SchemaWrite)]
+/// Static system identity; detected at startup (and on refresh) and exposed via GET /api/info.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, SchemaRead, SchemaWrite)]
 #[serde(rename_all = "camelCase")]
 pub struct SystemInfo {
     pub os_family: String,
@@ -39,6 +46,16 @@ pub struct SystemInfo {
     pub system_manufacturer: String,
     pub system_model: String,
     pub processor_name: String,
+    /// `server.node_name` of the instance that detected it; empty in rows stored before it existed.
+    #[serde(default)]
+    pub node_name: String,
+    /// Address of the interface holding the default route (else the first other interface with
+    /// one); `None` when the host has none. Refreshed with the rest of `SystemInfo`.
+    #[serde(default)]
+    pub primary_ipv4: Option<String>,
+    /// Like `primary_ipv4`, skipping link-local addresses.
+    #[serde(default)]
+    pub primary_ipv6: Option<String>,
 }
 
 /// Dynamic-only system metrics (wire + history). Static identity is GET /api/info or WS welcome.
@@ -48,8 +65,11 @@ pub struct SystemStatsDynamic {
     pub uptime_secs: u64,
     pub process_count: u32,
     pub thread_count: u32,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_1: f64,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_5: f64,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_15: f64,
 }
 
@@ -65,12 +85,15 @@ pub struct SystemStats {
     pub uptime_secs: u64,
     pub process_count: u32,
     pub thread_count: u32,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_1: f64,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_5: f64,
+    #[serde(with = "super::json_float::any")]
     pub load_avg_15: f64,
 }
 
-/// Merge static identity + dynamic metrics for display (e.g. dump_history, legacy readers).
+/// Merge static identity + dynamic metrics for display (e.g. `homeserver-cli dump`, legacy readers).
 pub fn merge_system_info(info: Option<&SystemInfo>, dynamic: &SystemStatsDynamic) -> SystemStats {
     let (os_family, os_manufacturer, os_version, system_manufacturer, system_model, processor_name) =
         match info {
@@ -121,9 +144,82 @@ pub struct FullSystemSnapshot {
     pub gpus: Vec<GpuStats>,
     #[serde(default)]
     pub smart: Vec<SmartHealth>,
+    /// Latest reachability per `[[probes.targets]]` entry. Stored in its own history column
+    /// (`probe_data`); left out of this wincode record and carried beside it by
+    /// [`SnapshotRecord`].
+    #[serde(default)]
+    #[wincode(skip)]
+    pub probes: Vec<ProbeStat>,
+    /// hwmon temperatures, fan speeds and voltages (`monitoring.collect_sensors`). Stored in
+    /// `sensor_data`; like `probes`, carried by [`SnapshotRecord`].
+    #[serde(default)]
+    #[wincode(skip)]
+    pub sensors: Vec<SensorStat>,
+    /// State of each `[[http_checks]]` entry. Live only: transitions are kept in
+    /// `service_events` instead, so rows read from history carry it empty.
+    #[serde(default)]
+    #[wincode(skip)]
+    pub checks: Vec<CheckSummary>,
+    /// Sections (`cpu`, `ram`, `containers`, `storage`, `network`, `system`) whose collector
+    /// failed this tick and carry the last known-good value (or a default) instead. Live only:
+    /// history rows and exports do not store it, so reads always return it empty.
+    #[serde(default)]
+    #[wincode(skip)]
+    pub degraded: Vec<String>,
+    /// The server's own resource usage this tick. Live only, like `degraded`: `None` on rows
+    /// read from history, in exports and in wincode remote write batches.
+    #[serde(default)]
+    #[wincode(skip)]
+    pub self_stats: Option<SelfStats>,
+    /// Replayed from history at startup (`worker::prime_from_history`), not collected live.
+    /// Live only; left out of the JSON unless set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[wincode(skip)]
+    pub historical: bool,
+}
+
+/// A [`FullSystemSnapshot`] with the stored fields its own wincode record skips (`probes`,
+/// `sensors`): one record of a history export, and one snapshot of a wincode remote write batch
+/// or spill file.
+#[derive(Debug, Clone, SchemaRead, SchemaWrite)]
+pub struct SnapshotRecord {
+    pub snapshot: FullSystemSnapshot,
+    pub probes: Vec<ProbeStat>,
+    pub sensors: Vec<SensorStat>,
 }
 
-/// Snapshot with merged system (static + dynamic) for display, e.g. dump_history.
+impl From<FullSystemSnapshot> for SnapshotRecord {
+    fn from(mut snapshot: FullSystemSnapshot) -> Self {
+        let probes = std::mem::take(&mut snapshot.probes);
+        let sensors = std::mem::take(&mut snapshot.sensors);
+        Self {
+            snapshot,
+            probes,
+            sensors,
+        }
+    }
+}
+
+impl From<SnapshotRecord> for FullSystemSnapshot {
+    fn from(record: SnapshotRecord) -> Self {
+        Self {
+            probes: record.probes,
+            sensors: record.sensors,
+            ..record.snapshot
+        }
+    }
+}
+
+impl FullSystemSnapshot {
+    /// [`ContainerStats::apply_cpu_percent_mode`] for every container.
+    pub fn apply_cpu_percent_mode(&mut self, mode: crate::config::CpuPercentMode) {
+        for c in &mut self.containers {
+            c.apply_cpu_percent_mode(mode);
+        }
+    }
+}
+
+/// Snapshot with merged system (static + dynamic) for display, e.g. `homeserver-cli dump`.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FullSystemSnapshotDisplay {
@@ -138,4 +234,8 @@ pub struct FullSystemSnapshotDisplay {
     pub gpus: Vec<GpuStats>,
     #[serde(default)]
     pub smart: Vec<SmartHealth>,
+    #[serde(default)]
+    pub probes: Vec<ProbeStat>,
+    #[serde(default)]
+    pub sensors: Vec<SensorStat>,
 }
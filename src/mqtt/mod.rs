@@ -0,0 +1,84 @@
+// MQTT publishing ([mqtt]): the latest snapshot is published at most every
+// `publish_interval_secs` as per-metric topics, with Home Assistant discovery configs sent once
+// per connection (and for containers as they appear).
+
+mod task;
+mod topics;
+
+pub use task::{MqttSink, publish_loop, spawn};
+pub use topics::{
+    MqttMessage, Sensor, availability_message, availability_topic, discovery_message, sensors,
+    state_message,
+};
+
+use std::collections::HashSet;
+
+use crate::config::{CpuPercentMode, MqttConfig};
+use crate::models::FullSystemSnapshot;
+
+/// Turns snapshots into messages, remembering which sensors were announced on the current
+/// broker connection.
+pub struct Publisher {
+    base_topic: String,
+    discovery_prefix: String,
+    /// Connection the announcements below were sent on.
+    connection: Option<u64>,
+    announced: HashSet<String>,
+    /// Scale of the container CPU states (`docker.cpu_percent_mode`).
+    cpu_percent_mode: CpuPercentMode,
+}
+
+impl Publisher {
+    pub fn new(config: &MqttConfig) -> Self {
+        Self {
+            base_topic: config.base_topic.clone(),
+            discovery_prefix: config.discovery_prefix.clone(),
+            connection: None,
+            announced: HashSet::new(),
+            cpu_percent_mode: CpuPercentMode::default(),
+        }
+    }
+
+    /// Publish container CPU per core (the default) or as a share of the whole host.
+    pub fn with_cpu_percent_mode(mut self, mode: CpuPercentMode) -> Self {
+        self.cpu_percent_mode = mode;
+        self
+    }
+
+    /// Messages for one publish on broker connection `connection` (a counter bumped on every
+    /// connect): on a new connection `online` and discovery for every sensor, otherwise discovery
+    /// only for sensors not seen yet (new containers); then every state.
+    pub fn messages(&mut self, snapshot: &FullSystemSnapshot, connection: u64) -> Vec<MqttMessage> {
+        let mut messages = Vec::new();
+        if self.connection != Some(connection) {
+            self.connection = Some(connection);
+            self.announced.clear();
+            messages.push(availability_message(&self.base_topic, true));
+        }
+        let sensors = match self.cpu_percent_mode {
+            CpuPercentMode::PerCore => sensors(snapshot),
+            mode => {
+                let mut snapshot = snapshot.clone();
+                snapshot.apply_cpu_percent_mode(mode);
+                sensors(&snapshot)
+            }
+        };
+        for sensor in &sensors {
+            if self.announced.insert(sensor.id.clone()) {
+                messages.push(discovery_message(
+                    &self.discovery_prefix,
+                    &self.base_topic,
+                    sensor,
+                ));
+            }
+        }
+        messages.extend(sensors.iter().map(|s| state_message(&self.base_topic, s)));
+        messages
+    }
+
+    /// Forget the announcements, e.g. after a publish failed part-way.
+    pub fn reset(&mut self) {
+        self.connection = None;
+        self.announced.clear();
+    }
+}
@@ -0,0 +1,239 @@
+// MQTT task: drives the rumqttc event loop (reconnecting with backoff) and, next to it, publishes
+// the latest snapshot from the broadcast every `publish_interval_secs`.
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use super::{MqttMessage, Publisher, availability_message};
+use crate::config::{CpuPercentMode, MqttConfig};
+use crate::models::FullSystemSnapshot;
+use crate::supervisor::{Backoff, supervise};
+
+/// Queued requests between the publisher and the event loop; one publish is about
+/// 2 × (7 + 2 × containers) messages right after a connect.
+const REQUEST_CAPACITY: usize = 1024;
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// How long shutdown waits for `offline` and the DISCONNECT to go out.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where messages go; the rumqttc client in production, a recorder in tests. Must not block:
+/// a full queue is an error.
+pub trait MqttSink: Send + Sync {
+    fn publish(&self, message: MqttMessage) -> anyhow::Result<()>;
+}
+
+struct ClientSink {
+    client: AsyncClient,
+    qos: QoS,
+}
+
+impl MqttSink for ClientSink {
+    fn publish(&self, m: MqttMessage) -> anyhow::Result<()> {
+        Ok(self
+            .client
+            .try_publish(m.topic, self.qos, m.retain, m.payload)?)
+    }
+}
+
+/// Spawn the MQTT client for `config` (which must have a `broker_url`) until `shutdown`,
+/// publishing container CPU on the `cpu_percent_mode` scale. A panic restarts it (counted in
+/// `restarts`) with a fresh connection.
+pub fn spawn(
+    config: MqttConfig,
+    cpu_percent_mode: CpuPercentMode,
+    snapshots: broadcast::Sender<FullSystemSnapshot>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    let options = options(&config)?;
+    let token = shutdown.clone();
+    Ok(tokio::spawn(supervise(
+        "mqtt_publisher",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            run(
+                config.clone(),
+                cpu_percent_mode,
+                options.clone(),
+                snapshots.subscribe(),
+                token.clone(),
+            )
+        },
+    )))
+}
+
+fn qos(config: &MqttConfig) -> QoS {
+    match config.qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        _ => QoS::ExactlyOnce,
+    }
+}
+
+fn options(config: &MqttConfig) -> anyhow::Result<MqttOptions> {
+    let (host, port) = config
+        .broker()?
+        .ok_or_else(|| anyhow::anyhow!("mqtt.broker_url is not set"))?;
+    let mut options = MqttOptions::new(config.client_id.clone(), host, port);
+    options
+        .set_keep_alive(Duration::from_secs(30))
+        .set_request_channel_capacity(REQUEST_CAPACITY);
+    if let Some(username) = &config.username {
+        let password = config.password.as_ref().map(|p| p.expose()).unwrap_or("");
+        options.set_credentials(username.clone(), password);
+    }
+    let offline = availability_message(&config.base_topic, false);
+    options.set_last_will(LastWill::new(
+        offline.topic,
+        offline.payload,
+        qos(config),
+        true,
+    ));
+    Ok(options)
+}
+
+async fn run(
+    config: MqttConfig,
+    cpu_percent_mode: CpuPercentMode,
+    options: MqttOptions,
+    snapshots: broadcast::Receiver<FullSystemSnapshot>,
+    shutdown: CancellationToken,
+) {
+    let (client, eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+    let sink = Arc::new(ClientSink {
+        client,
+        qos: qos(&config),
+    });
+    let (connection_tx, connection_rx) = watch::channel(None);
+    let interval = Duration::from_secs(config.publish_interval_secs);
+    tokio::join!(
+        publish_loop(
+            Publisher::new(&config).with_cpu_percent_mode(cpu_percent_mode),
+            sink.clone(),
+            snapshots,
+            connection_rx,
+            interval,
+            shutdown.clone(),
+        ),
+        drive(
+            eventloop,
+            connection_tx,
+            shutdown.clone(),
+            Goodbye {
+                sink,
+                offline: availability_message(&config.base_topic, false),
+            },
+        ),
+    );
+}
+
+/// Sent on shutdown while connected, rather than leaving it to the last will.
+struct Goodbye {
+    sink: Arc<ClientSink>,
+    offline: MqttMessage,
+}
+
+/// Poll the event loop until `shutdown`. `connection` holds `Some(n)` while connected (n counts
+/// connects) and `None` while not; a failed poll waits with exponential backoff, then the next
+/// poll reconnects.
+async fn drive(
+    mut eventloop: EventLoop,
+    connection: watch::Sender<Option<u64>>,
+    shutdown: CancellationToken,
+    goodbye: Goodbye,
+) {
+    let mut connects = 0;
+    let mut backoff = RECONNECT_MIN;
+    loop {
+        let event = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            event = eventloop.poll() => event,
+        };
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                connects += 1;
+                backoff = RECONNECT_MIN;
+                tracing::info!("connected to MQTT broker");
+                connection.send_replace(Some(connects));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                connection.send_replace(None);
+                tracing::warn!(error = %e, retry_in = ?backoff, "MQTT connection failed");
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+            }
+        }
+    }
+    if connection.borrow().is_none() {
+        return;
+    }
+    if goodbye.sink.publish(goodbye.offline).is_err()
+        || goodbye.sink.client.try_disconnect().is_err()
+    {
+        return;
+    }
+    let flush = async {
+        while let Ok(event) = eventloop.poll().await {
+            if matches!(event, Event::Outgoing(Outgoing::Disconnect)) {
+                break;
+            }
+        }
+    };
+    let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, flush).await;
+}
+
+/// Keep the latest snapshot from `snapshots` and every `interval`, while `connection` is
+/// `Some`, publish it through `publisher` to `sink`. Faster sampling only replaces the pending
+/// snapshot, so the broker sees at most one update per interval.
+pub async fn publish_loop(
+    mut publisher: Publisher,
+    sink: Arc<dyn MqttSink>,
+    mut snapshots: broadcast::Receiver<FullSystemSnapshot>,
+    connection: watch::Receiver<Option<u64>>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut latest = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            received = snapshots.recv() => match received {
+                // Replayed from history at startup: published before the restart.
+                Ok(snapshot) if snapshot.historical => {}
+                Ok(snapshot) => latest = Some(snapshot),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            },
+            _ = ticker.tick() => {
+                let Some(connected) = *connection.borrow() else {
+                    continue;
+                };
+                let Some(snapshot) = latest.take() else {
+                    continue;
+                };
+                for message in publisher.messages(&snapshot, connected) {
+                    if let Err(e) = sink.publish(message) {
+                        tracing::debug!(error = %e, "MQTT publish failed; re-announcing next time");
+                        publisher.reset();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Snapshot → MQTT messages: per-metric state topics under the base topic and Home Assistant
+// discovery configs for them. Pure — unit-testable.
+
+use crate::models::FullSystemSnapshot;
+use crate::version;
+
+/// One message to publish.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    /// Retained messages (discovery, availability) are kept by the broker for new subscribers.
+    pub retain: bool,
+}
+
+/// A scalar read from a snapshot, published on `<base>/<path>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    /// Unique within the device, e.g. `cpu_usage`, `container_db_cpu`.
+    pub id: String,
+    /// State topic below the base topic, e.g. `cpu/usage`, `container/db/cpu`.
+    pub path: String,
+    /// Home Assistant display name.
+    pub name: String,
+    pub unit: Option<&'static str>,
+    pub device_class: Option<&'static str>,
+    pub value: String,
+}
+
+fn sensor(
+    id: &str,
+    path: &str,
+    name: &str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+    value: String,
+) -> Sensor {
+    Sensor {
+        id: id.into(),
+        path: path.into(),
+        name: name.into(),
+        unit,
+        device_class,
+        value,
+    }
+}
+
+/// Every sensor in `snapshot`: host CPU, RAM, swap, load, fullest partition and uptime, then CPU
+/// and memory of each container.
+pub fn sensors(s: &FullSystemSnapshot) -> Vec<Sensor> {
+    let mut sensors = vec![
+        sensor(
+            "cpu_usage",
+            "cpu/usage",
+            "CPU usage",
+            Some("%"),
+            None,
+            percent(s.cpu.usage_percent),
+        ),
+        sensor(
+            "cpu_temperature",
+            "cpu/temperature",
+            "CPU temperature",
+            Some("°C"),
+            Some("temperature"),
+            percent(s.cpu.temperature),
+        ),
+        sensor(
+            "ram_used",
+            "ram/used",
+            "RAM used",
+            Some("B"),
+            Some("data_size"),
+            s.ram.used.to_string(),
+        ),
+        sensor(
+            "ram_usage",
+            "ram/usage",
+            "RAM usage",
+            Some("%"),
+            None,
+            percent(s.ram.usage_percent),
+        ),
+        sensor(
+            "swap_used",
+            "swap/used",
+            "Swap used",
+            Some("B"),
+            Some("data_size"),
+            s.ram.swap_used.to_string(),
+        ),
+        sensor(
+            "load_1",
+            "load/1",
+            "Load (1 min)",
+            None,
+            None,
+            format!("{:.2}", s.system.load_avg_1),
+        ),
+        sensor(
+            "uptime",
+            "system/uptime",
+            "Uptime",
+            Some("s"),
+            Some("duration"),
+            s.system.uptime_secs.to_string(),
+        ),
+    ];
+    let fullest = s.storage.partitions.iter().map(|p| p.usage_percent);
+    if let Some(disk) = fullest.reduce(f64::max) {
+        sensors.push(sensor(
+            "disk_usage",
+            "disk/usage",
+            "Disk usage (fullest)",
+            Some("%"),
+            None,
+            percent(disk),
+        ));
+    }
+    for c in &s.containers {
+        let topic = topic_segment(&c.name);
+        let id = object_id(&c.name);
+        sensors.push(sensor(
+            &format!("container_{id}_cpu"),
+            &format!("container/{topic}/cpu"),
+            &format!("{} CPU", c.name),
+            Some("%"),
+            None,
+            percent(c.cpu_percent),
+        ));
+        sensors.push(sensor(
+            &format!("container_{id}_memory"),
+            &format!("container/{topic}/memory"),
+            &format!("{} memory", c.name),
+            Some("B"),
+            Some("data_size"),
+            c.memory_usage_bytes.to_string(),
+        ));
+    }
+    sensors
+}
+
+/// The state message of `sensor`, not retained.
+pub fn state_message(base_topic: &str, sensor: &Sensor) -> MqttMessage {
+    MqttMessage {
+        topic: format!("{base_topic}/{}", sensor.path),
+        payload: sensor.value.clone(),
+        retain: false,
+    }
+}
+
+/// The retained availability message (`online` / `offline`), also used as the last will.
+pub fn availability_message(base_topic: &str, online: bool) -> MqttMessage {
+    MqttMessage {
+        topic: availability_topic(base_topic),
+        payload: if online { "online" } else { "offline" }.into(),
+        retain: true,
+    }
+}
+
+pub fn availability_topic(base_topic: &str) -> String {
+    format!("{base_topic}/status")
+}
+
+/// Retained Home Assistant discovery config for `sensor` on
+/// `<discovery_prefix>/sensor/<node>/<id>/config`; all sensors share one device named after
+/// the base topic.
+pub fn discovery_message(discovery_prefix: &str, base_topic: &str, sensor: &Sensor) -> MqttMessage {
+    let node = object_id(base_topic);
+    let mut config = serde_json::json!({
+        "name": sensor.name,
+        "unique_id": format!("{node}_{}", sensor.id),
+        "state_topic": format!("{base_topic}/{}", sensor.path),
+        "availability_topic": availability_topic(base_topic),
+        "state_class": "measurement",
+        "device": {
+            "identifiers": [node],
+            "name": base_topic,
+            "sw_version": version::VERSION,
+        },
+    });
+    if let Some(unit) = sensor.unit {
+        config["unit_of_measurement"] = unit.into();
+    }
+    if let Some(class) = sensor.device_class {
+        config["device_class"] = class.into();
+    }
+    MqttMessage {
+        topic: format!("{discovery_prefix}/sensor/{node}/{}/config", sensor.id),
+        payload: config.to_string(),
+        retain: true,
+    }
+}
+
+fn percent(value: f64) -> String {
+    format!("{value:.1}")
+}
+
+/// `name` as one topic level: MQTT wildcards, separators and whitespace become `_`.
+fn topic_segment(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '+' | '#' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// `name` as a Home Assistant object id: ASCII alphanumerics and `_` only.
+fn object_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,84 @@
+// Small LAN helpers behind the API: Wake-on-LAN magic packets for POST /api/wol.
+
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::str::FromStr;
+
+/// UDP port magic packets are sent to (discard; what most wake-on-LAN tools use).
+pub const WOL_PORT: u16 = 9;
+
+/// Six `0xFF` bytes, then the target MAC 16 times.
+pub const MAGIC_PACKET_LEN: usize = 6 + 16 * 6;
+
+/// A 48-bit hardware address, parsed from `AA:BB:CC:DD:EE:FF` or `aa-bb-cc-dd-ee-ff` and shown
+/// in the colon form, upper case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacAddr(pub [u8; 6]);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid MAC address '{0}': expected six hex pairs separated by ':' or '-'")]
+pub struct InvalidMac(pub String);
+
+impl FromStr for MacAddr {
+    type Err = InvalidMac;
+
+    fn from_str(s: &str) -> Result<Self, InvalidMac> {
+        let invalid = || InvalidMac(s.to_string());
+        let separator = if s.contains('-') { '-' } else { ':' };
+        let mut bytes = [0u8; 6];
+        let mut parts = s.split(separator);
+        for byte in &mut bytes {
+            let part = parts.next().ok_or_else(invalid)?;
+            if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(Self(bytes)),
+        }
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02X}:{b:02X}:{c:02X}:{d:02X}:{e:02X}:{g:02X}")
+    }
+}
+
+/// The magic packet that wakes `mac`.
+pub fn magic_packet(mac: MacAddr) -> [u8; MAGIC_PACKET_LEN] {
+    let mut packet = [0xFF; MAGIC_PACKET_LEN];
+    for chunk in packet[6..].chunks_exact_mut(6) {
+        chunk.copy_from_slice(&mac.0);
+    }
+    packet
+}
+
+/// The directed broadcast address of `addr`'s subnet, e.g. 192.168.1.255 for 192.168.1.20/24.
+/// A prefix of 31 or 32 has no broadcast address; the limited broadcast is used instead.
+pub fn directed_broadcast(addr: Ipv4Addr, prefix_len: u8) -> Ipv4Addr {
+    if prefix_len >= 31 {
+        return Ipv4Addr::BROADCAST;
+    }
+    let host_mask = u32::MAX >> prefix_len;
+    Ipv4Addr::from(u32::from(addr) | host_mask)
+}
+
+/// Send the magic packet for `mac` to `broadcast`:[`WOL_PORT`] from a UDP socket bound to
+/// `source` (the local address of the interface to leave by), or to any address when `None`.
+pub async fn send_magic_packet(
+    mac: MacAddr,
+    broadcast: Ipv4Addr,
+    source: Option<Ipv4Addr>,
+) -> std::io::Result<()> {
+    let bind = SocketAddrV4::new(source.unwrap_or(Ipv4Addr::UNSPECIFIED), 0);
+    let socket = tokio::net::UdpSocket::bind(bind).await?;
+    socket.set_broadcast(true)?;
+    socket
+        .send_to(&magic_packet(mac), SocketAddrV4::new(broadcast, WOL_PORT))
+        .await?;
+    Ok(())
+}
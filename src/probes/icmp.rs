@@ -0,0 +1,101 @@
+// ICMP echo over a raw socket (needs CAP_NET_RAW): request encoding, reply parsing and one
+// blocking ping. Callers run `ping` on the blocking pool.
+
+use std::io::{self, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+pub const ECHO_REQUEST_V4: u8 = 8;
+pub const ECHO_REPLY_V4: u8 = 0;
+pub const ECHO_REQUEST_V6: u8 = 128;
+pub const ECHO_REPLY_V6: u8 = 129;
+
+/// Payload bytes after the 8-byte ICMP header.
+const PAYLOAD: &[u8] = b"homeserver-probe";
+
+/// RFC 1071 Internet checksum of `data`.
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// An echo request with `ident` and `seq`. The ICMPv4 checksum is filled in; for ICMPv6 the
+/// kernel computes it (it covers the IPv6 pseudo-header).
+pub fn echo_request(v6: bool, ident: u16, seq: u16) -> Vec<u8> {
+    let kind = if v6 { ECHO_REQUEST_V6 } else { ECHO_REQUEST_V4 };
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    if !v6 {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// `(ident, seq)` of an echo reply as read from the raw socket: an IPv4 socket delivers the IP
+/// header first, an IPv6 one only the ICMP message. `None` for anything else.
+pub fn parse_echo_reply(packet: &[u8], v6: bool) -> Option<(u16, u16)> {
+    let (icmp, reply) = if v6 {
+        (packet, ECHO_REPLY_V6)
+    } else {
+        let header_len = usize::from(packet.first()? & 0x0F) * 4;
+        (packet.get(header_len..)?, ECHO_REPLY_V4)
+    };
+    if icmp.len() < 8 || icmp[0] != reply || icmp[1] != 0 {
+        return None;
+    }
+    Some((
+        u16::from_be_bytes([icmp[4], icmp[5]]),
+        u16::from_be_bytes([icmp[6], icmp[7]]),
+    ))
+}
+
+/// Send one echo request to `addr` and wait up to `timeout` for its reply. `Ok(None)` when none
+/// came; `Err` with [`io::ErrorKind::PermissionDenied`] without CAP_NET_RAW.
+pub fn ping(addr: IpAddr, ident: u16, seq: u16, timeout: Duration) -> io::Result<Option<Duration>> {
+    let v6 = addr.is_ipv6();
+    let (domain, protocol) = if v6 {
+        (Domain::IPV6, Protocol::ICMPV6)
+    } else {
+        (Domain::IPV4, Protocol::ICMPV4)
+    };
+    let socket = Socket::new(domain, Type::RAW, Some(protocol))?;
+    // Connected: the kernel only hands this socket packets from `addr`.
+    socket.connect(&SockAddr::from(SocketAddr::new(addr, 0)))?;
+    let started = Instant::now();
+    socket.send(&echo_request(v6, ident, seq))?;
+    let deadline = started + timeout;
+    let mut buf = [0u8; 1500];
+    loop {
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            return Ok(None);
+        };
+        socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))))?;
+        let len = match (&socket).read(&mut buf) {
+            Ok(len) => len,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        // Raw sockets see every ICMP message from `addr`: skip other pings' replies.
+        if parse_echo_reply(&buf[..len], v6) == Some((ident, seq)) {
+            return Ok(Some(started.elapsed()));
+        }
+    }
+}
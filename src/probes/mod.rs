@@ -0,0 +1,165 @@
+// Reachability / latency probes (`[[probes.targets]]`): each target is probed every
+// `interval_secs` by a [`Prober`] ([`SystemProber`]: ICMP echo, or a TCP connect without
+// CAP_NET_RAW) and keeps a window of recent results. The worker copies [`ProbeSet::current`]
+// into every snapshot; `GET /api/probes` serves it directly.
+
+pub mod icmp;
+mod system;
+mod window;
+
+pub use system::SystemProber;
+pub use window::ProbeWindow;
+
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::{BoxFuture, join_all};
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{ProbeTarget, ProbesConfig};
+use crate::models::ProbeStat;
+use crate::supervisor::{Backoff, supervise};
+
+/// How often the loop looks for due targets when none is scheduled (no targets yet probed).
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// How a probe reached (or tried to reach) its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeMethod {
+    Icmp,
+    Tcp,
+}
+
+impl ProbeMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Icmp => "icmp",
+            Self::Tcp => "tcp",
+        }
+    }
+}
+
+/// Result of one probe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeOutcome {
+    pub method: ProbeMethod,
+    /// `None` when the probe got no answer in time (or the name did not resolve).
+    pub rtt: Option<Duration>,
+}
+
+/// Sends one probe; [`SystemProber`] in production, scripted in tests.
+pub trait Prober: Send + Sync {
+    fn probe<'a>(&'a self, target: &'a ProbeTarget) -> BoxFuture<'a, ProbeOutcome>;
+}
+
+#[derive(Debug)]
+struct TargetState {
+    target: ProbeTarget,
+    window: ProbeWindow,
+    /// `None` until the first probe: due right away.
+    next_due: Option<Instant>,
+}
+
+/// The configured targets with their windows of results.
+#[derive(Debug, Default)]
+pub struct ProbeSet {
+    targets: Mutex<Vec<TargetState>>,
+}
+
+impl ProbeSet {
+    pub fn new(config: &ProbesConfig) -> Self {
+        let targets = config
+            .targets
+            .iter()
+            .map(|target| TargetState {
+                target: target.clone(),
+                window: ProbeWindow::new(config.window),
+                next_due: None,
+            })
+            .collect();
+        Self {
+            targets: Mutex::new(targets),
+        }
+    }
+
+    /// Results of every target probed at least once, in config order.
+    pub fn current(&self) -> Vec<ProbeStat> {
+        let Ok(targets) = self.targets.lock() else {
+            return Vec::new();
+        };
+        targets
+            .iter()
+            .filter_map(|state| state.window.stat(&state.target))
+            .collect()
+    }
+
+    /// When the next target is due; `None` without targets.
+    pub fn next_due(&self) -> Option<Instant> {
+        let targets = self.targets.lock().ok()?;
+        targets
+            .iter()
+            .map(|state| state.next_due)
+            .min()
+            .map(|due| due.unwrap_or_else(Instant::now))
+    }
+
+    /// Probe every target due at `now` (concurrently), record the outcomes and schedule each
+    /// again `interval_secs` after `now`. Returns how many were probed.
+    pub async fn run_due(&self, prober: &dyn Prober, now: Instant) -> usize {
+        let due: Vec<ProbeTarget> = match self.targets.lock() {
+            Ok(mut targets) => targets
+                .iter_mut()
+                .filter(|state| state.next_due.is_none_or(|due| due <= now))
+                .map(|state| {
+                    state.next_due = Some(now + Duration::from_secs(state.target.interval_secs));
+                    state.target.clone()
+                })
+                .collect(),
+            Err(_) => return 0,
+        };
+        let outcomes = join_all(due.iter().map(|target| prober.probe(target))).await;
+        if let Ok(mut targets) = self.targets.lock() {
+            for (target, outcome) in due.iter().zip(outcomes) {
+                if let Some(state) = targets.iter_mut().find(|s| s.target.name == target.name) {
+                    state.window.record(outcome);
+                }
+            }
+        }
+        due.len()
+    }
+}
+
+/// Probe `probes` with `prober` until `shutdown`. A panic restarts the loop (counted in
+/// `restarts`); the windows live in `probes` and carry over.
+pub fn spawn(
+    probes: Arc<ProbeSet>,
+    prober: Arc<dyn Prober>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let token = shutdown.clone();
+        supervise(
+            "probes",
+            restarts,
+            Backoff::default(),
+            shutdown,
+            move || run(probes.clone(), prober.clone(), token.clone()),
+        )
+        .await
+    })
+}
+
+async fn run(probes: Arc<ProbeSet>, prober: Arc<dyn Prober>, shutdown: CancellationToken) {
+    loop {
+        probes.run_due(prober.as_ref(), Instant::now()).await;
+        let wake = probes
+            .next_due()
+            .unwrap_or_else(|| Instant::now() + IDLE_POLL);
+        tokio::select! {
+            _ = tokio::time::sleep_until(wake) => {}
+            _ = shutdown.cancelled() => return,
+        }
+    }
+}
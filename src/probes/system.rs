@@ -0,0 +1,114 @@
+// The production prober: ICMP echo while the process may open raw sockets, a TCP connect to the
+// target's port once it may not.
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+use futures_util::future::BoxFuture;
+use tokio::net::TcpStream;
+use tokio::time::{Duration, Instant};
+
+use super::{ProbeMethod, ProbeOutcome, Prober, icmp};
+use crate::config::{ProbeTarget, ProbesConfig};
+
+pub struct SystemProber {
+    timeout: Duration,
+    tcp_port: u16,
+    /// Cleared (for good) by the first raw socket refused for lack of CAP_NET_RAW.
+    icmp: AtomicBool,
+    seq: AtomicU16,
+}
+
+impl SystemProber {
+    pub fn new(config: &ProbesConfig) -> Self {
+        Self {
+            timeout: Duration::from_millis(config.timeout_ms),
+            tcp_port: config.tcp_port,
+            icmp: AtomicBool::new(true),
+            seq: AtomicU16::new(0),
+        }
+    }
+
+    /// Probe with TCP connects only, as if ICMP were not permitted.
+    pub fn tcp_only(self) -> Self {
+        self.icmp.store(false, Ordering::Relaxed);
+        self
+    }
+
+    async fn resolve(&self, target: &ProbeTarget, port: u16) -> Option<SocketAddr> {
+        match tokio::net::lookup_host((target.target.as_str(), port)).await {
+            Ok(mut addrs) => addrs.next(),
+            Err(e) => {
+                tracing::debug!(probe = %target.name, error = %e, "probe target did not resolve");
+                None
+            }
+        }
+    }
+
+    /// Echo round trip; `Err(())` when raw sockets turned out not to be permitted.
+    async fn icmp(&self, addr: SocketAddr) -> Result<Option<Duration>, ()> {
+        let ident = std::process::id() as u16;
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let timeout = self.timeout;
+        let ping = tokio::task::spawn_blocking(move || icmp::ping(addr.ip(), ident, seq, timeout));
+        match ping.await {
+            Ok(Ok(rtt)) => Ok(rtt),
+            Ok(Err(e)) if e.kind() == io::ErrorKind::PermissionDenied => {
+                if self.icmp.swap(false, Ordering::Relaxed) {
+                    tracing::warn!(
+                        tcp_port = self.tcp_port,
+                        "ICMP probes need CAP_NET_RAW; probing with TCP connects instead"
+                    );
+                }
+                Err(())
+            }
+            Ok(Err(e)) => {
+                tracing::debug!(%addr, error = %e, "ICMP probe failed");
+                Ok(None)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Connect round trip. A refused connection still proves the host answered.
+    async fn tcp(&self, addr: SocketAddr) -> Option<Duration> {
+        let started = Instant::now();
+        match tokio::time::timeout(self.timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => Some(started.elapsed()),
+            Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Some(started.elapsed()),
+            Ok(Err(_)) | Err(_) => None,
+        }
+    }
+}
+
+impl Prober for SystemProber {
+    fn probe<'a>(&'a self, target: &'a ProbeTarget) -> BoxFuture<'a, ProbeOutcome> {
+        Box::pin(async move {
+            let port = target.port.unwrap_or(self.tcp_port);
+            let addr = self.resolve(target, port).await;
+            if self.icmp.load(Ordering::Relaxed) {
+                let Some(addr) = addr else {
+                    return ProbeOutcome {
+                        method: ProbeMethod::Icmp,
+                        rtt: None,
+                    };
+                };
+                if let Ok(rtt) = self.icmp(addr).await {
+                    return ProbeOutcome {
+                        method: ProbeMethod::Icmp,
+                        rtt,
+                    };
+                }
+            }
+            let rtt = match addr {
+                Some(addr) => self.tcp(addr).await,
+                None => None,
+            };
+            ProbeOutcome {
+                method: ProbeMethod::Tcp,
+                rtt,
+            }
+        })
+    }
+}
@@ -0,0 +1,56 @@
+// A target's last `probes.window` results and the statistics reported over them.
+
+use std::collections::VecDeque;
+
+use super::{ProbeMethod, ProbeOutcome};
+use crate::config::ProbeTarget;
+use crate::models::ProbeStat;
+
+/// Round trips in milliseconds, oldest first; `None` for a lost probe.
+#[derive(Debug, Clone)]
+pub struct ProbeWindow {
+    results: VecDeque<Option<f64>>,
+    capacity: usize,
+    method: Option<ProbeMethod>,
+}
+
+impl ProbeWindow {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            results: VecDeque::with_capacity(capacity),
+            capacity,
+            method: None,
+        }
+    }
+
+    /// Add `outcome`, dropping the oldest result once the window is full.
+    pub fn record(&mut self, outcome: ProbeOutcome) {
+        if self.results.len() == self.capacity {
+            self.results.pop_front();
+        }
+        self.results
+            .push_back(outcome.rtt.map(|rtt| rtt.as_secs_f64() * 1000.0));
+        self.method = Some(outcome.method);
+    }
+
+    /// Statistics for `target`; `None` before its first probe.
+    pub fn stat(&self, target: &ProbeTarget) -> Option<ProbeStat> {
+        let method = self.method?;
+        let answered: Vec<f64> = self.results.iter().flatten().copied().collect();
+        let samples = self.results.len();
+        let lost = samples - answered.len();
+        Some(ProbeStat {
+            name: target.name.clone(),
+            target: target.target.clone(),
+            method: method.as_str().to_string(),
+            rtt_ms: self.results.back().copied().flatten(),
+            rtt_avg_ms: (!answered.is_empty())
+                .then(|| answered.iter().sum::<f64>() / answered.len() as f64),
+            rtt_min_ms: answered.iter().copied().reduce(f64::min),
+            rtt_max_ms: answered.iter().copied().reduce(f64::max),
+            loss_percent: lost as f64 / samples as f64 * 100.0,
+            samples: samples as u32,
+        })
+    }
+}
@@ -0,0 +1,275 @@
+// Config hot reload (SIGHUP or POST /api/config/reload): re-read and validate the config, push
+// runtime-tunable values to the worker and history writer over `watch` channels, set retention
+// and the log filter, and report anything else that changed as needing a restart.
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::config::{AppConfig, CliOverrides, ConfigSources};
+use crate::history_repo::{HistoryRepo, HistoryStore};
+use crate::worker::{HistoryWriterConfig, WorkerConfig};
+
+/// Keys (dotted, as in `config.toml`) a reload applies to the running server. Any other change
+/// takes effect after a restart.
+pub const RELOADABLE_KEYS: &[&str] = &[
+    "monitoring.sample_interval_ms",
+    "monitoring.stats_log_interval_secs",
+    "monitoring.collect_gpu",
+    "monitoring.collect_smart",
+    "monitoring.smart_poll_interval_secs",
+    "monitoring.collect_sensors",
+    "monitoring.error_record_interval_secs",
+    "monitoring.storage_interval_ms",
+    "monitoring.docker_interval_ms",
+    "monitoring.system_interval_ms",
+    "monitoring.idle_sample_interval_ms",
+    "monitoring.idle_grace_secs",
+    "publishing.max_snapshot_bytes",
+    "database.prune_interval_secs",
+    "database.flush_rate",
+    "database.flush_interval_secs",
+    "database.flush_warn_ms",
+    "database.persist_gpu",
+    "database.persist_smart",
+    "database.min_snapshot_timestamp_ms",
+    "database.max_future_skew_minutes",
+    "database.min_free_bytes",
+    "database.retention_days",
+    "database.error_retention_days",
+    "logging.filter",
+];
+
+/// Sets the process log filter (`EnvFilter` syntax); wraps the `tracing_subscriber` reload handle.
+pub type LogFilterSetter = Box<dyn Fn(&str) -> anyhow::Result<()> + Send + Sync>;
+
+/// Outcome of a successful reload (body of `POST /api/config/reload`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadReport {
+    /// Changed keys now in effect.
+    pub applied: Vec<String>,
+    /// Keys that differ from the config the server started with; they apply after a restart.
+    pub requires_restart: Vec<String>,
+}
+
+/// Holds the running config and the channels that push reloadable values to the tasks.
+pub struct ConfigReloader {
+    /// Where the config came from: `--config` and the other flags are applied again on reload.
+    source: CliOverrides,
+    /// The config the server started with (restart-only values are compared against it).
+    started: AppConfig,
+    /// The config in effect: `started` with the reloadable keys of the last applied config.
+    current: Mutex<AppConfig>,
+    /// Where the last config read came from (`GET /api/config`).
+    sources: Mutex<ConfigSources>,
+    worker_tx: watch::Sender<WorkerConfig>,
+    writer_tx: watch::Sender<HistoryWriterConfig>,
+    /// Unset in agent mode (`database.enabled = false`) and until the database is open; see
+    /// [`Self::attach_history_repo`].
+    history_repo: OnceLock<Arc<dyn HistoryStore>>,
+    log_filter: Option<LogFilterSetter>,
+    /// Filter used when `logging.filter` is unset (`RUST_LOG`, else "info").
+    default_log_filter: String,
+}
+
+impl ConfigReloader {
+    /// A reloader for `config` (loaded from `source`); returns the receivers to hand to
+    /// [`crate::worker::spawn_reloadable`] and [`crate::worker::spawn_history_writer_reloadable`].
+    pub fn new(
+        config: AppConfig,
+        source: CliOverrides,
+        history_repo: impl Into<Option<Arc<HistoryRepo>>>,
+    ) -> (
+        Self,
+        watch::Receiver<WorkerConfig>,
+        watch::Receiver<HistoryWriterConfig>,
+    ) {
+        let (worker_tx, worker_rx) = watch::channel(WorkerConfig::from_app(&config));
+        let (writer_tx, writer_rx) = watch::channel(HistoryWriterConfig::from_app(&config));
+        let reloader = Self {
+            source,
+            started: config.clone(),
+            current: Mutex::new(config),
+            sources: Mutex::default(),
+            worker_tx,
+            writer_tx,
+            history_repo: history_repo
+                .into()
+                .map(|repo| OnceLock::from(repo as Arc<dyn HistoryStore>))
+                .unwrap_or_default(),
+            log_filter: None,
+            default_log_filter: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+        };
+        (reloader, worker_rx, writer_rx)
+    }
+
+    /// Apply `logging.filter` changes through `setter`.
+    pub fn with_log_filter(mut self, setter: LogFilterSetter) -> Self {
+        self.log_filter = Some(setter);
+        self
+    }
+
+    /// Hand over a repo opened after the reloader was built, applying the running retention
+    /// (a reload may have changed it meanwhile). Ignored when one is already set.
+    pub fn attach_history_repo(&self, repo: Arc<dyn HistoryStore>) {
+        let current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        repo.set_retention_days(
+            current.database.retention_days,
+            current.database.error_retention_days,
+        );
+        let _ = self.history_repo.set(repo);
+    }
+
+    /// Record where the initial config came from ([`AppConfig::load_with_sources`]).
+    pub fn with_sources(self, sources: ConfigSources) -> Self {
+        *self.sources.lock().unwrap_or_else(|e| e.into_inner()) = sources;
+        self
+    }
+
+    /// The running config and where it came from.
+    pub fn running(&self) -> (AppConfig, ConfigSources) {
+        let current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        (current.clone(), sources.clone())
+    }
+
+    /// Re-read the config file, environment and flags, then [`Self::apply`] it. On any error the
+    /// running config is left untouched.
+    pub fn reload(&self) -> anyhow::Result<ReloadReport> {
+        let (config, sources) = AppConfig::load_with_sources(&self.source)?;
+        let report = self.apply(config)?;
+        *self.sources.lock().unwrap_or_else(|e| e.into_inner()) = sources;
+        Ok(report)
+    }
+
+    /// Apply an already validated config: reloadable values take effect, the rest is reported
+    /// and keeps its started value in [`Self::running`] until a restart.
+    pub fn apply(&self, config: AppConfig) -> anyhow::Result<ReloadReport> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let requires_restart = changed_keys(&self.started, &config)?
+            .into_iter()
+            .filter(|key| !RELOADABLE_KEYS.contains(&key.as_str()))
+            .collect();
+        let config = with_reloadable_keys(&self.started, &config)?;
+        let applied: Vec<String> = changed_keys(&current, &config)?
+            .into_iter()
+            .filter(|key| RELOADABLE_KEYS.contains(&key.as_str()))
+            .collect();
+        // The only step that can fail goes first, so an error leaves everything as it was.
+        if applied.iter().any(|key| key == "logging.filter")
+            && let Some(set) = &self.log_filter
+        {
+            set(config
+                .logging
+                .filter
+                .as_deref()
+                .unwrap_or(&self.default_log_filter))?;
+        }
+        self.worker_tx
+            .send_if_modified(|worker| replace_if_changed(worker, WorkerConfig::from_app(&config)));
+        self.writer_tx.send_if_modified(|writer| {
+            replace_if_changed(writer, HistoryWriterConfig::from_app(&config))
+        });
+        if let Some(history_repo) = self.history_repo.get() {
+            history_repo.set_retention_days(
+                config.database.retention_days,
+                config.database.error_retention_days,
+            );
+        }
+        *current = config;
+        Ok(ReloadReport {
+            applied,
+            requires_restart,
+        })
+    }
+
+    /// Like [`Self::reload`], logging the outcome instead of returning it.
+    pub fn reload_and_log(&self) -> anyhow::Result<ReloadReport> {
+        match self.reload() {
+            Ok(report) => {
+                tracing::info!(applied = ?report.applied, "config reloaded");
+                if !report.requires_restart.is_empty() {
+                    tracing::warn!(
+                        keys = ?report.requires_restart,
+                        "config changes take effect after a restart"
+                    );
+                }
+                Ok(report)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "config reload rejected; keeping the running config");
+                Err(e)
+            }
+        }
+    }
+}
+
+/// `started` with the [`RELOADABLE_KEYS`] of `new`: the config the process actually runs with.
+fn with_reloadable_keys(started: &AppConfig, new: &AppConfig) -> anyhow::Result<AppConfig> {
+    let mut running = toml::Table::try_from(started)?;
+    let new = toml::Table::try_from(new)?;
+    for key in RELOADABLE_KEYS {
+        let Some((section, field)) = key.split_once('.') else {
+            continue;
+        };
+        let Some(toml::Value::Table(table)) = running.get_mut(section) else {
+            continue;
+        };
+        // Unset options are left out of the table, so a key missing from `new` is removed.
+        match new.get(section).and_then(|s| s.get(field)) {
+            Some(value) => table.insert(field.to_string(), value.clone()),
+            None => table.remove(field),
+        };
+    }
+    Ok(running.try_into()?)
+}
+
+fn replace_if_changed<T: PartialEq>(slot: &mut T, new: T) -> bool {
+    if *slot == new {
+        return false;
+    }
+    *slot = new;
+    true
+}
+
+/// Dotted keys whose value differs between `old` and `new` (arrays compare as a whole).
+pub fn changed_keys(old: &AppConfig, new: &AppConfig) -> anyhow::Result<Vec<String>> {
+    let old = toml::Table::try_from(old)?;
+    let new = toml::Table::try_from(new)?;
+    let mut changed = Vec::new();
+    diff_tables("", &old, &new, &mut changed);
+    changed.sort();
+    Ok(changed)
+}
+
+fn diff_tables(prefix: &str, old: &toml::Table, new: &toml::Table, changed: &mut Vec<String>) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let path = format!("{prefix}{key}");
+        match (old.get(key), new.get(key)) {
+            (Some(toml::Value::Table(a)), Some(toml::Value::Table(b))) => {
+                diff_tables(&format!("{path}."), a, b, changed);
+            }
+            (a, b) if a != b => changed.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Reload on every SIGHUP until the process exits.
+#[cfg(unix)]
+pub fn spawn_sighup_listener(reloader: Arc<ConfigReloader>) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP; reloading config");
+            let _ = reloader.reload_and_log();
+        }
+    });
+    Ok(())
+}
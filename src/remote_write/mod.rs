@@ -0,0 +1,8 @@
+// Remote write ([remote_write]): snapshots from the broadcast are batched and POSTed to another
+// instance's `/api/ingest`, retrying with backoff; undelivered batches spill to disk.
+
+mod spill;
+mod task;
+
+pub use spill::Spill;
+pub use task::spawn;
@@ -0,0 +1,128 @@
+// On-disk buffer of undelivered batches: one wincode file per batch, named by a sequence number
+// so name order is send order.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::models::IngestBatch;
+
+const EXTENSION: &str = "batch";
+
+/// Batches spilled to `dir`, oldest first, capped at `max_bytes` by dropping the oldest.
+/// Files left by a previous run are picked up by [`Spill::open`].
+pub struct Spill {
+    dir: PathBuf,
+    max_bytes: u64,
+    next_seq: u64,
+    files: VecDeque<(u64, u64)>,
+    bytes: u64,
+}
+
+impl Spill {
+    /// Open (creating if missing) `dir` and index the batches already in it.
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let seq = path.file_stem().and_then(|s| s.to_str()?.parse().ok());
+            if let Some(seq) = seq {
+                files.push((seq, std::fs::metadata(&path)?.len()));
+            }
+        }
+        files.sort_unstable();
+        let mut spill = Self {
+            dir,
+            max_bytes,
+            next_seq: files.last().map_or(0, |(seq, _)| seq + 1),
+            bytes: files.iter().map(|(_, len)| len).sum(),
+            files: files.into(),
+        };
+        spill.evict(0);
+        Ok(spill)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Bytes currently on disk.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    fn path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{seq:020}.{EXTENSION}"))
+    }
+
+    /// Append `batch` as the newest file, dropping the oldest ones past `max_bytes`.
+    pub fn push(&mut self, batch: &IngestBatch) -> anyhow::Result<()> {
+        let bytes = batch.to_wincode()?;
+        let len = bytes.len() as u64;
+        let (seq, path) = (self.next_seq, self.path(self.next_seq));
+        // Write then rename, so a crash never leaves a truncated batch behind.
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(&tmp, &path)?;
+        self.next_seq += 1;
+        self.evict(len);
+        self.files.push_back((seq, len));
+        self.bytes += len;
+        Ok(())
+    }
+
+    /// Drop the oldest files until `incoming` more bytes fit under `max_bytes`.
+    fn evict(&mut self, incoming: u64) {
+        while self.bytes + incoming > self.max_bytes {
+            let Some((seq, _)) = self.files.front().copied() else {
+                return;
+            };
+            tracing::warn!(
+                dir = %self.dir.display(),
+                "remote write spill full; dropping the oldest batch"
+            );
+            self.remove_front(seq);
+        }
+    }
+
+    /// The oldest batch, or `None` when empty. A file that cannot be read back is dropped.
+    pub fn front(&mut self) -> Option<IngestBatch> {
+        while let Some((seq, _)) = self.files.front().copied() {
+            let decoded = std::fs::read(self.path(seq))
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(IngestBatch::from_wincode(&bytes)?));
+            match decoded {
+                Ok(batch) => return Some(batch),
+                Err(e) => {
+                    tracing::warn!(error = %e, seq, "dropping unreadable spilled batch");
+                    self.remove_front(seq);
+                }
+            }
+        }
+        None
+    }
+
+    /// Remove the oldest batch (after it was delivered).
+    pub fn pop_front(&mut self) {
+        if let Some((seq, _)) = self.files.front().copied() {
+            self.remove_front(seq);
+        }
+    }
+
+    fn remove_front(&mut self, seq: u64) {
+        if let Some((_, len)) = self.files.pop_front() {
+            self.bytes -= len;
+        }
+        if let Err(e) = std::fs::remove_file(self.path(seq)) {
+            tracing::warn!(error = %e, seq, "failed to remove spilled batch");
+        }
+    }
+}
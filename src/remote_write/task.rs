@@ -0,0 +1,236 @@
+// remote_write task: batch snapshots from the broadcast, POST them oldest first, back off while
+// the central instance is unreachable and keep the backlog in the spill directory.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use super::Spill;
+use crate::config::{RemoteWriteConfig, RemoteWriteFormat};
+use crate::models::{FullSystemSnapshot, INGEST_WINCODE_CONTENT_TYPE, IngestBatch};
+use crate::supervisor::{Backoff, supervise};
+
+/// Batches kept in memory before the oldest is moved to the spill directory.
+const MEMORY_BATCHES: usize = 4;
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Spawn the remote_write task for `config` (which must have a `url`) until `shutdown`, tagging
+/// batches with `node` (`server.node_name`). A panic restarts it (counted in `restarts`); only
+/// the in-memory batches are lost.
+pub fn spawn(
+    config: RemoteWriteConfig,
+    node: String,
+    snapshots: broadcast::Sender<FullSystemSnapshot>,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    let url = config
+        .url
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("remote_write.url is not set"))?;
+    let endpoint = format!("{}/api/ingest", url.trim_end_matches('/'));
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let token = shutdown.clone();
+    Ok(tokio::spawn(supervise(
+        "remote_write",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            let sender = Sender {
+                client: client.clone(),
+                endpoint: endpoint.clone(),
+                config: config.clone(),
+                node: node.clone(),
+            };
+            run(sender, snapshots.subscribe(), token.clone())
+        },
+    )))
+}
+
+struct Sender {
+    client: reqwest::Client,
+    endpoint: String,
+    config: RemoteWriteConfig,
+    node: String,
+}
+
+enum Outcome {
+    Delivered,
+    /// The receiver will never accept this batch (malformed, too large): drop it.
+    Rejected(StatusCode),
+    /// Worth retrying: connection errors, timeouts, 5xx, 401/403 (a key fixed on the receiver),
+    /// 408 and 429.
+    Failed(String),
+}
+
+impl Sender {
+    async fn post(&self, batch: &IngestBatch) -> Outcome {
+        let mut request = self.client.post(&self.endpoint);
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key.expose());
+        }
+        request = match self.config.format {
+            RemoteWriteFormat::Json => match batch.to_json() {
+                Ok(body) => request
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body),
+                Err(e) => return Outcome::Failed(format!("json: {e}")),
+            },
+            RemoteWriteFormat::Wincode => match batch.to_wincode() {
+                Ok(body) => request
+                    .header(reqwest::header::CONTENT_TYPE, INGEST_WINCODE_CONTENT_TYPE)
+                    .body(body),
+                Err(e) => return Outcome::Failed(format!("wincode: {e}")),
+            },
+        };
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Outcome::Delivered,
+            Ok(response) => match response.status() {
+                s @ (StatusCode::UNAUTHORIZED
+                | StatusCode::FORBIDDEN
+                | StatusCode::REQUEST_TIMEOUT
+                | StatusCode::TOO_MANY_REQUESTS) => Outcome::Failed(format!("HTTP {s}")),
+                s if s.is_client_error() => Outcome::Rejected(s),
+                s => Outcome::Failed(format!("HTTP {s}")),
+            },
+            Err(e) => Outcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// Pending batches: the spill holds the oldest, memory the newest [`MEMORY_BATCHES`].
+struct Backlog {
+    memory: VecDeque<IngestBatch>,
+    spill: Option<Spill>,
+}
+
+impl Backlog {
+    fn is_empty(&self) -> bool {
+        self.memory.is_empty() && self.spill.as_ref().is_none_or(Spill::is_empty)
+    }
+
+    fn push(&mut self, batch: IngestBatch) {
+        self.memory.push_back(batch);
+        while self.memory.len() > MEMORY_BATCHES {
+            let oldest = self.memory.pop_front().expect("non-empty");
+            self.spill_batch(&oldest);
+        }
+    }
+
+    fn spill_batch(&mut self, batch: &IngestBatch) {
+        let Some(spill) = &mut self.spill else {
+            tracing::warn!("remote write backlog full and no spill directory; dropping a batch");
+            return;
+        };
+        if let Err(e) = spill.push(batch) {
+            tracing::warn!(error = %e, "failed to spill remote write batch; dropping it");
+        }
+    }
+
+    /// The oldest pending batch.
+    fn front(&mut self) -> Option<IngestBatch> {
+        if let Some(batch) = self.spill.as_mut().and_then(Spill::front) {
+            return Some(batch);
+        }
+        self.memory.front().cloned()
+    }
+
+    fn pop_front(&mut self) {
+        match &mut self.spill {
+            Some(spill) if !spill.is_empty() => spill.pop_front(),
+            _ => {
+                self.memory.pop_front();
+            }
+        }
+    }
+
+    /// Move everything still in memory to disk (on shutdown).
+    fn spill_all(&mut self) {
+        while let Some(batch) = self.memory.pop_front() {
+            self.spill_batch(&batch);
+        }
+    }
+}
+
+async fn run(
+    sender: Sender,
+    mut snapshots: broadcast::Receiver<FullSystemSnapshot>,
+    shutdown: CancellationToken,
+) {
+    let config = &sender.config;
+    let spill = Spill::open(&config.spill_dir, config.max_spill_bytes)
+        .inspect_err(|e| tracing::warn!(error = %e, dir = %config.spill_dir, "remote write spill unavailable; undeliverable batches will be dropped"))
+        .ok();
+    let mut backlog = Backlog {
+        memory: VecDeque::new(),
+        spill,
+    };
+    let seal = |open: &mut Vec<FullSystemSnapshot>, backlog: &mut Backlog| {
+        if !open.is_empty() {
+            backlog.push(IngestBatch {
+                node: sender.node.clone(),
+                snapshots: std::mem::take(open),
+            });
+        }
+    };
+    let mut open = Vec::with_capacity(config.batch_size);
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.flush_interval_secs));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut next_attempt = Instant::now();
+    let mut backoff = RETRY_MIN;
+    loop {
+        let pending = !backlog.is_empty();
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            received = snapshots.recv() => match received {
+                // Replayed from history at startup: sent before the restart.
+                Ok(snapshot) if snapshot.historical => {}
+                Ok(snapshot) => {
+                    open.push(snapshot);
+                    if open.len() >= config.batch_size {
+                        seal(&mut open, &mut backlog);
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    tracing::debug!(skipped = n, "remote write lagged behind the broadcast");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => seal(&mut open, &mut backlog),
+            _ = tokio::time::sleep_until(next_attempt), if pending => {
+                let Some(batch) = backlog.front() else {
+                    continue;
+                };
+                match sender.post(&batch).await {
+                    Outcome::Delivered => {
+                        backlog.pop_front();
+                        backoff = RETRY_MIN;
+                    }
+                    Outcome::Rejected(status) => {
+                        tracing::warn!(%status, snapshots = batch.snapshots.len(), "remote write batch rejected; dropping it");
+                        backlog.pop_front();
+                    }
+                    Outcome::Failed(e) => {
+                        tracing::warn!(error = %e, retry_in = ?backoff, "remote write failed");
+                        next_attempt = Instant::now() + backoff;
+                        backoff = (backoff * 2).min(RETRY_MAX);
+                    }
+                }
+            }
+        }
+    }
+    // Keep what was not delivered for the next start.
+    seal(&mut open, &mut backlog);
+    backlog.spill_all();
+}
@@ -0,0 +1,110 @@
+// The report figures, computed from already-fetched history points. Pure — unit-testable on
+// synthetic data without a database.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::{HistoryPoint, PartitionGrowth, TopContainer, UsageReport};
+
+/// Summarize `points` (in time order, as `HistoryRepo::get_history_points` returns them) for the
+/// window [`from`, `to`). Averages weigh every point equally; peaks use each point's envelope
+/// when it has one, so an aggregated row contributes the highest sample of its bucket. Network
+/// transfer adds up the growth of each interface's cumulative counters between consecutive
+/// points; a counter that went down (reboot, interface reset) counts from zero again.
+pub fn build_report(
+    from: i64,
+    to: i64,
+    points: &[HistoryPoint],
+    top_containers: Vec<TopContainer>,
+) -> UsageReport {
+    let samples = points.len();
+    let mean = |sum: f64| {
+        if samples == 0 {
+            0.0
+        } else {
+            sum / samples as f64
+        }
+    };
+
+    let cpu_average_percent = mean(points.iter().map(|p| p.snapshot.cpu.usage_percent).sum());
+    let cpu_peak_percent = points
+        .iter()
+        .map(|p| {
+            let value = p.snapshot.cpu.usage_percent;
+            p.envelope
+                .as_ref()
+                .map_or(value, |e| e.cpu_load_max.max(value))
+        })
+        .fold(0.0, f64::max);
+    let ram_average_bytes = mean(points.iter().map(|p| p.snapshot.ram.used as f64).sum()) as u64;
+    let ram_peak_bytes = points
+        .iter()
+        .map(|p| {
+            let value = p.snapshot.ram.used;
+            p.envelope
+                .as_ref()
+                .map_or(value, |e| value.max(e.memory_used_max.max(0) as u64))
+        })
+        .max()
+        .unwrap_or_default();
+    let ram_total_bytes = points.last().map_or(0, |p| p.snapshot.ram.total);
+    let (network_rx_bytes, network_tx_bytes) = network_transfer(points);
+
+    UsageReport {
+        from,
+        to,
+        samples,
+        cpu_average_percent,
+        cpu_peak_percent,
+        ram_average_bytes,
+        ram_peak_bytes,
+        ram_total_bytes,
+        partitions: partition_growth(points),
+        network_rx_bytes,
+        network_tx_bytes,
+        top_containers,
+    }
+}
+
+fn partition_growth(points: &[HistoryPoint]) -> Vec<PartitionGrowth> {
+    let mut by_mount: BTreeMap<&str, PartitionGrowth> = BTreeMap::new();
+    for partition in points.iter().flat_map(|p| &p.snapshot.storage.partitions) {
+        let entry = by_mount
+            .entry(&partition.mount)
+            .or_insert_with(|| PartitionGrowth {
+                mount: partition.mount.clone(),
+                total_space: partition.total_space,
+                start_used_bytes: partition.used_space,
+                end_used_bytes: partition.used_space,
+                growth_bytes: 0,
+            });
+        entry.total_space = partition.total_space;
+        entry.end_used_bytes = partition.used_space;
+    }
+    by_mount
+        .into_values()
+        .map(|mut growth| {
+            growth.growth_bytes = growth.end_used_bytes as i64 - growth.start_used_bytes as i64;
+            growth
+        })
+        .collect()
+}
+
+fn network_transfer(points: &[HistoryPoint]) -> (u64, u64) {
+    let grown = |previous: u64, current: u64| {
+        if current >= previous {
+            current - previous
+        } else {
+            current
+        }
+    };
+    let mut last: HashMap<&str, (u64, u64)> = HashMap::new();
+    let (mut rx, mut tx) = (0u64, 0u64);
+    for interface in points.iter().flat_map(|p| &p.snapshot.network.interfaces) {
+        let current = (interface.bytes_recv, interface.bytes_sent);
+        if let Some(previous) = last.insert(&interface.name, current) {
+            rx = rx.saturating_add(grown(previous.0, current.0));
+            tx = tx.saturating_add(grown(previous.1, current.1));
+        }
+    }
+    (rx, tx)
+}
@@ -0,0 +1,90 @@
+// Plain-text rendering of a UsageReport: GET /api/report?format=markdown and the message posted to
+// Discord / Slack webhooks. Pure.
+
+use std::fmt::Write;
+
+use crate::models::UsageReport;
+
+/// The report as a short markdown message: a title with the window, CPU/RAM/network lines, disk
+/// growth per mount and the top containers by CPU.
+pub fn render_markdown(report: &UsageReport) -> String {
+    let mut out = format!(
+        "**Usage report** {} to {} ({} samples)\n",
+        format_time(report.from),
+        format_time(report.to),
+        report.samples
+    );
+    if report.samples == 0 {
+        out.push_str("\nNo history in this window.\n");
+        return out;
+    }
+    let _ = writeln!(
+        out,
+        "\n- CPU: {:.1}% average, {:.1}% peak",
+        report.cpu_average_percent, report.cpu_peak_percent
+    );
+    let _ = writeln!(
+        out,
+        "- RAM: {} average, {} peak of {}",
+        format_bytes(report.ram_average_bytes),
+        format_bytes(report.ram_peak_bytes),
+        format_bytes(report.ram_total_bytes)
+    );
+    let _ = writeln!(
+        out,
+        "- Network: {} received, {} sent",
+        format_bytes(report.network_rx_bytes),
+        format_bytes(report.network_tx_bytes)
+    );
+    if !report.partitions.is_empty() {
+        out.push_str("\n**Disk growth**\n");
+        for p in &report.partitions {
+            let sign = if p.growth_bytes < 0 { "-" } else { "+" };
+            let _ = writeln!(
+                out,
+                "- {}: {}{} ({} of {} used)",
+                p.mount,
+                sign,
+                format_bytes(p.growth_bytes.unsigned_abs()),
+                format_bytes(p.end_used_bytes),
+                format_bytes(p.total_space)
+            );
+        }
+    }
+    if !report.top_containers.is_empty() {
+        out.push_str("\n**Top containers by CPU**\n");
+        for (rank, c) in report.top_containers.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}. {}: {:.1}% average, {:.1}% peak",
+                rank + 1,
+                c.name,
+                c.average,
+                c.max
+            );
+        }
+    }
+    out
+}
+
+/// `1536` -> `1.5 KiB`; plain bytes below 1 KiB.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn format_time(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms).map_or_else(
+        || ms.to_string(),
+        |t| t.format("%Y-%m-%d %H:%M UTC").to_string(),
+    )
+}
@@ -0,0 +1,90 @@
+// Usage reports: a day/week/month summary of history (CPU/RAM averages and peaks, disk growth,
+// network transfer, busiest containers). `generate_report` fetches the points and container
+// rankings, `build_report` computes the figures (pure), `render_markdown` formats them for chat
+// webhooks, and the task posts one on `alerts.report_schedule`.
+
+mod build;
+mod markdown;
+mod task;
+
+pub use build::build_report;
+pub use markdown::{format_bytes, render_markdown};
+pub use task::{report_payload, spawn_scheduled};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history_repo::{DownsampleMode, HistoryResult, HistoryStore, TopContainerMetric};
+use crate::models::UsageReport;
+
+/// Containers listed in a report.
+pub const REPORT_TOP_CONTAINERS: u32 = 5;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// The window a report covers, ending now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    #[default]
+    Day,
+    Week,
+    /// 30 days.
+    Month,
+}
+
+impl ReportPeriod {
+    pub const NAMES: &[&str] = &["day", "week", "month"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    pub fn span_ms(self) -> i64 {
+        match self {
+            Self::Day => MS_PER_DAY,
+            Self::Week => 7 * MS_PER_DAY,
+            Self::Month => 30 * MS_PER_DAY,
+        }
+    }
+}
+
+/// History resolution for a report over `span_ms`: 5-minute points up to two days, hourly
+/// beyond, so even a month stays a few hundred points read from the aggregated tiers.
+pub fn report_resolution_secs(span_ms: i64) -> u32 {
+    if span_ms <= 2 * MS_PER_DAY { 300 } else { 3600 }
+}
+
+/// The report for [`from_ts`, `to_ts`): history points at [`report_resolution_secs`] (raw rows
+/// newer than `raw_retention_hours`, the aggregated tiers before that) and the
+/// [`REPORT_TOP_CONTAINERS`] containers with the highest average CPU in `container_history`.
+pub async fn generate_report(
+    repo: &dyn HistoryStore,
+    from_ts: i64,
+    to_ts: i64,
+    raw_retention_hours: u32,
+) -> HistoryResult<UsageReport> {
+    let top_containers = repo
+        .get_top_containers(
+            from_ts,
+            to_ts,
+            TopContainerMetric::Cpu,
+            REPORT_TOP_CONTAINERS,
+        )
+        .await?;
+    let raw_cutoff_ts = repo.history_raw_cutoff(to_ts, raw_retention_hours).await?;
+    let points = repo
+        .get_history_points(
+            from_ts,
+            to_ts,
+            report_resolution_secs(to_ts - from_ts),
+            raw_cutoff_ts,
+            DownsampleMode::Average,
+        )
+        .await?;
+    Ok(build_report(from_ts, to_ts, &points, top_containers))
+}
@@ -0,0 +1,116 @@
+// Scheduled reports: at each `alerts.report_schedule` time (cron, local time) generate the report
+// for the `alerts.report_period` ending now and POST it to the alert webhooks.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use super::{ReportPeriod, generate_report, render_markdown};
+use crate::alerting::Notifier;
+use crate::config::{AlertsConfig, WebhookFormat, normalize_cron_expression};
+use crate::history_repo::HistoryHandle;
+use crate::models::UsageReport;
+use crate::supervisor::{Backoff, supervise};
+
+/// The body POSTed for a report: for generic webhooks the report plus its text, tagged
+/// `"type": "report"` so receivers can tell it from alert events; for Discord / Slack the text
+/// as a chat message.
+pub fn report_payload(format: WebhookFormat, report: &UsageReport) -> serde_json::Value {
+    let text = render_markdown(report);
+    match format {
+        WebhookFormat::Generic => {
+            serde_json::json!({ "type": "report", "report": report, "text": text })
+        }
+        WebhookFormat::Discord => serde_json::json!({ "content": text }),
+        WebhookFormat::Slack => serde_json::json!({ "text": text }),
+    }
+}
+
+/// Spawn the report task when `report_schedule` is set, until `shutdown`. Reports are read from
+/// `history` (skipped while it is not open); a panic restarts the task (counted in `restarts`).
+pub fn spawn_scheduled(
+    config: &AlertsConfig,
+    history: HistoryHandle,
+    raw_retention_hours: u32,
+    shutdown: CancellationToken,
+    restarts: Arc<AtomicU64>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let cron_str = config.report_schedule.as_ref()?;
+    let Ok(schedule) = cron::Schedule::from_str(&normalize_cron_expression(cron_str)) else {
+        tracing::warn!(cron = %cron_str, "invalid report_schedule; no reports will be sent");
+        return None;
+    };
+    let notifier = Notifier::from_config(config);
+    let period = config.report_period;
+    let token = shutdown.clone();
+    Some(tokio::spawn(supervise(
+        "usage_reports",
+        restarts,
+        Backoff::default(),
+        shutdown,
+        move || {
+            run(
+                schedule.clone(),
+                period,
+                notifier.clone(),
+                history.clone(),
+                raw_retention_hours,
+                token.clone(),
+            )
+        },
+    )))
+}
+
+async fn run(
+    schedule: cron::Schedule,
+    period: ReportPeriod,
+    notifier: Notifier,
+    history: HistoryHandle,
+    raw_retention_hours: u32,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let now = chrono::Local::now();
+        let Some(next) = schedule.after(&now).next() else {
+            return;
+        };
+        let delay = (next - now).to_std().unwrap_or(Duration::from_secs(1));
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        send_report(period, &notifier, &history, raw_retention_hours).await;
+    }
+}
+
+async fn send_report(
+    period: ReportPeriod,
+    notifier: &Notifier,
+    history: &HistoryHandle,
+    raw_retention_hours: u32,
+) {
+    let Some(repo) = history.get() else {
+        tracing::debug!("history database not open; skipping usage report");
+        return;
+    };
+    let to = chrono::Utc::now().timestamp_millis();
+    match generate_report(
+        repo.as_ref(),
+        to - period.span_ms(),
+        to,
+        raw_retention_hours,
+    )
+    .await
+    {
+        Ok(report) => {
+            tracing::info!(?period, samples = report.samples, "sending usage report");
+            notifier
+                .post("usage report", |format| report_payload(format, &report))
+                .await;
+        }
+        Err(e) => tracing::warn!(error = %e, "usage report failed"),
+    }
+}
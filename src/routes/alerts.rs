@@ -0,0 +1,28 @@
+// GET /api/alerts: the alert rules currently firing and the latest alert events.
+
+use axum::{Json, extract::State};
+use serde::Serialize;
+
+use super::AppState;
+use crate::alerting::{ActiveAlert, AlertEvent};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct AlertsSummary {
+    /// Firing rules, in config order.
+    alerts: Vec<ActiveAlert>,
+    /// Latest firing/resolved events (threshold and container rules), newest first.
+    recent: Vec<AlertEvent>,
+    /// Number of configured threshold and container rules (0: alerting is off).
+    rules: usize,
+}
+
+/// GET /api/alerts — firing rules with their severity, latest value and when they fired, plus
+/// the recent event history.
+pub(super) async fn api_alerts_handler(State(state): State<AppState>) -> Json<AlertsSummary> {
+    Json(AlertsSummary {
+        alerts: state.metrics.alerts.active(),
+        recent: state.metrics.alerts.recent(),
+        rules: state.config.alerts.rules.len() + state.config.alerts.container_rules.len(),
+    })
+}
@@ -0,0 +1,166 @@
+// The error envelope every JSON route answers with, `{"error", "code", "details"?}`; the query
+// extractor whose rejection uses it; and the fallbacks for unknown paths (404) and methods (405).
+
+use axum::{
+    extract::{
+        FromRequestParts, Query,
+        rejection::{BytesRejection, JsonRejection, QueryRejection},
+    },
+    http::{Method, StatusCode, Uri, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+
+use crate::history_repo::HistoryError;
+
+/// A failed request: `status` with `{"error": message, "code": ..., "details": ...}`. `code` is
+/// the stable, machine-readable class derived from the status (see [`error_code`]);
+/// `details` is omitted when there is nothing more to say.
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    status: StatusCode,
+    message: String,
+    details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub(crate) fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// 400: a malformed or out-of-range parameter or body.
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// 404: no such route, resource or feature on this instance.
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    /// 503: temporarily unable to answer; retrying later may succeed.
+    pub(crate) fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, message)
+    }
+
+    /// 500: a failure on this side (I/O, corrupt data, the clock).
+    pub(crate) fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// A failed history query: 503 while the database is temporarily unavailable (pool exhausted
+    /// or closed, file locked), 400 for arguments the repo rejects, 500 otherwise (corrupt
+    /// blobs, I/O, schema too new). `message` says what failed; the cause is only logged.
+    pub(crate) fn history(e: &HistoryError, message: impl Into<String>) -> Self {
+        let status = match e {
+            e if e.is_unavailable() => StatusCode::SERVICE_UNAVAILABLE,
+            HistoryError::InvalidArgument(_) | HistoryError::InvalidExport(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, message)
+    }
+
+    pub(crate) fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// The error class clients switch on; see [`error_code`].
+    pub(crate) fn code(&self) -> &'static str {
+        error_code(self.status)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({
+            "error": self.message,
+            "code": self.code(),
+        });
+        if let Some(details) = self.details {
+            body["details"] = details;
+        }
+        (self.status, axum::Json(body)).into_response()
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<BytesRejection> for ApiError {
+    fn from(rejection: BytesRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+/// `Query<T>` whose rejection (an unparsable query string) is an [`ApiError`] rather than
+/// axum's plain text 400.
+pub(crate) struct ApiQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, ApiError> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+/// The `code` of an error answered with `status`: one class per status, so clients can tell rate
+/// limiting or a conflict from a validation error. Other 4xx are `client_error`, other statuses
+/// `internal`.
+pub fn error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::NOT_ACCEPTABLE => "not_acceptable",
+        StatusCode::REQUEST_TIMEOUT => "request_timeout",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::GONE => "gone",
+        StatusCode::LENGTH_REQUIRED => "length_required",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::URI_TOO_LONG => "uri_too_long",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable",
+        StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+        StatusCode::NOT_IMPLEMENTED => "not_implemented",
+        StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+        s if s.is_client_error() => "client_error",
+        _ => "internal",
+    }
+}
+
+/// Router fallback: a path no route matches.
+pub(super) async fn not_found_fallback(uri: Uri) -> ApiError {
+    ApiError::not_found(format!("no route for {}", uri.path()))
+}
+
+/// Method fallback: a known path with a method it does not serve. axum still sets `Allow`.
+pub(super) async fn method_not_allowed_fallback(method: Method, uri: Uri) -> ApiError {
+    ApiError::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        format!("{method} is not allowed on {}", uri.path()),
+    )
+}
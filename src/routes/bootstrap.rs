@@ -0,0 +1,145 @@
+// GET /api/bootstrap: what a dashboard loads on launch (info, version, latest snapshot, recent
+// history, capabilities) in one round trip.
+
+use axum::{
+    Extension,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use super::capabilities::capabilities;
+use super::history_window::HistoryWindow;
+use super::info::{bound_address, info_json, version_json};
+use crate::serve::BoundAddress;
+
+const DEFAULT_HISTORY_SECS: i64 = 3600;
+const DEFAULT_RESOLUTION: &str = "30";
+
+#[derive(Debug, Deserialize)]
+pub(super) struct BootstrapQuery {
+    /// History window ending now, in seconds (default 3600).
+    history_secs: Option<i64>,
+    /// Same values as /api/history (default 30 s).
+    resolution: Option<String>,
+    /// `false` leaves the section out of the envelope; every section is included by default.
+    info: Option<bool>,
+    version: Option<bool>,
+    latest: Option<bool>,
+    history: Option<bool>,
+    capabilities: Option<bool>,
+}
+
+/// GET /api/bootstrap?history_secs=&resolution=&info=&version=&latest=&history=&capabilities= —
+/// `{nodeName, info, version, latest, history, capabilities}`, each section as its own endpoint
+/// would answer it (`/api/info`, `/version`, the worker's last snapshot, `/api/history`,
+/// `/api/capabilities`); `nodeName` (`server.node_name`) is always present. A section that cannot
+/// be produced (no snapshot yet, no local database, a failed read) is `null` rather than failing
+/// the whole response; only a bad `history_secs` / `resolution` is a 400.
+pub(super) async fn api_bootstrap_handler(
+    State(state): State<AppState>,
+    bound: Option<Extension<BoundAddress>>,
+    ApiQuery(q): ApiQuery<BootstrapQuery>,
+) -> Response {
+    let bound = bound_address(bound);
+    let wanted = |flag: Option<bool>| flag.unwrap_or(true);
+    let window = if wanted(q.history) {
+        match history_window(&state, &q) {
+            Ok(window) => Some(window),
+            Err(e) => return e.into_response(),
+        }
+    } else {
+        None
+    };
+
+    let mut body = serde_json::Map::new();
+    body.insert(
+        "nodeName".into(),
+        serde_json::json!(state.config.server.node_name),
+    );
+    if wanted(q.info) {
+        body.insert("info".into(), info_json(&state.system_info.get(), bound));
+    }
+    if wanted(q.version) {
+        body.insert("version".into(), version_json(bound));
+    }
+    if wanted(q.latest) {
+        let latest = state.metrics.broadcast.latest_snapshot().map(|latest| {
+            let mut latest = (*latest).clone();
+            latest.apply_cpu_percent_mode(state.config.docker.cpu_percent_mode);
+            latest
+        });
+        body.insert("latest".into(), serde_json::json!(latest));
+    }
+    if let Some(window) = window {
+        body.insert("history".into(), history(&state, window).await);
+    }
+    if wanted(q.capabilities) {
+        let capabilities = match capabilities(&state).await {
+            Ok(capabilities) => serde_json::json!(capabilities),
+            Err(e) => {
+                tracing::warn!(error = %e, "bootstrap capabilities failed");
+                serde_json::Value::Null
+            }
+        };
+        body.insert("capabilities".into(), capabilities);
+    }
+    (StatusCode::OK, axum::Json(body)).into_response()
+}
+
+/// The last `history_secs` at `resolution`, validated like /api/history.
+fn history_window(state: &AppState, q: &BootstrapQuery) -> Result<HistoryWindow, ApiError> {
+    let secs = q.history_secs.unwrap_or(DEFAULT_HISTORY_SECS);
+    let Some(span_ms) = secs.checked_mul(1000).filter(|&ms| ms > 0) else {
+        return Err(ApiError::bad_request(
+            "history_secs must be a positive number of seconds",
+        ));
+    };
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    HistoryWindow::parse(
+        Some(now_ms.saturating_sub(span_ms)),
+        Some(now_ms),
+        Some(q.resolution.as_deref().unwrap_or(DEFAULT_RESOLUTION)),
+        state.config.database.max_history_points,
+        false,
+    )
+}
+
+/// `HistoryRepo::get_history` over `window`, or `null` without a local database or on failure.
+async fn history(state: &AppState, window: HistoryWindow) -> serde_json::Value {
+    let Ok(repo) = state.history() else {
+        return serde_json::Value::Null;
+    };
+    let raw_retention_hours = state.config.database.raw_retention_hours;
+    let result = async {
+        let raw_cutoff_ts = repo
+            .history_raw_cutoff(window.now_ms, raw_retention_hours)
+            .await?;
+        repo.get_history(
+            window.from_ts,
+            window.to_ts,
+            window.resolution_secs,
+            raw_cutoff_ts,
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(mut snapshots) => {
+            for s in &mut snapshots {
+                s.apply_cpu_percent_mode(state.config.docker.cpu_percent_mode);
+            }
+            serde_json::json!(snapshots)
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "bootstrap history failed");
+            serde_json::Value::Null
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// /api/capabilities: history range, retention, sample interval and optional features, so clients
+// can limit date pickers to what this instance can answer.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+
+use super::AppState;
+use super::api_error::ApiError;
+use crate::config::AppConfig;
+use crate::history_repo::{HistoryResult, HistoryStore, RetentionPolicy};
+use crate::models::{Capabilities, Features, TierRetention};
+use crate::version::{NAME, VERSION};
+
+/// How long the database-derived history range is reused before querying again.
+const HISTORY_BOUNDS_TTL: Duration = Duration::from_secs(10);
+
+const MS_PER_HOUR: i64 = 3_600_000;
+
+/// `(earliest, latest)` from [`HistoryStore::get_history_bounds`].
+type HistoryBounds = (Option<i64>, Option<i64>);
+
+/// Last history range read and when it was read.
+#[derive(Default)]
+pub(crate) struct HistoryBoundsCache(Mutex<Option<(Instant, HistoryBounds)>>);
+
+impl HistoryBoundsCache {
+    async fn get(&self, repo: &dyn HistoryStore) -> HistoryResult<HistoryBounds> {
+        if let Some((read_at, bounds)) = *self.0.lock().unwrap()
+            && read_at.elapsed() < HISTORY_BOUNDS_TTL
+        {
+            return Ok(bounds);
+        }
+        let bounds = repo.get_history_bounds().await?;
+        *self.0.lock().unwrap() = Some((Instant::now(), bounds));
+        Ok(bounds)
+    }
+}
+
+/// GET /api/capabilities — 200 in agent mode too, with no history range or retention.
+pub(super) async fn api_capabilities_handler(State(state): State<AppState>) -> Response {
+    match capabilities(&state).await {
+        Ok(capabilities) => axum::Json(capabilities).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "get_history_bounds failed");
+            ApiError::history(&e, "failed to read the history range").into_response()
+        }
+    }
+}
+
+/// `/api/capabilities` (also the `capabilities` section of `/api/bootstrap`); fails only when the
+/// history range cannot be read.
+pub(super) async fn capabilities(state: &AppState) -> HistoryResult<Capabilities> {
+    let (history_earliest_ts, history_latest_ts) = match state.history_repo.get() {
+        Some(repo) => state.history_bounds.get(repo.as_ref()).await?,
+        // Agent mode, or still opening the database.
+        None => (None, None),
+    };
+    let config = &state.config;
+    Ok(Capabilities {
+        name: NAME.to_string(),
+        version: VERSION.to_string(),
+        history_earliest_ts,
+        history_latest_ts,
+        sample_interval_ms: config.monitoring.sample_interval_ms,
+        retention: retention(config),
+        features: features(config),
+    })
+}
+
+/// Raw rows roll into the first tier after `raw_retention_hours`; without aggregation they are
+/// kept for `retention_days`.
+fn retention(config: &AppConfig) -> Vec<TierRetention> {
+    let db = &config.database;
+    if !db.enabled {
+        return Vec::new();
+    }
+    let policy = RetentionPolicy::from_config(db);
+    let aggregated = db.enable_aggregation && !policy.tiers.is_empty();
+    let raw_ms = if aggregated {
+        i64::from(db.raw_retention_hours) * MS_PER_HOUR
+    } else {
+        policy.raw_ms
+    };
+    let tiers = if aggregated { policy.tiers } else { Vec::new() };
+    std::iter::once((0, raw_ms))
+        .chain(tiers)
+        .map(|(resolution_seconds, keep_ms)| TierRetention {
+            resolution_seconds,
+            keep_ms,
+        })
+        .collect()
+}
+
+fn features(config: &AppConfig) -> Features {
+    Features {
+        history: config.database.enabled,
+        aggregation: config.database.enabled && config.database.enable_aggregation,
+        gpu: config.monitoring.collect_gpu,
+        smart: config.monitoring.collect_smart,
+        sensors: config.monitoring.collect_sensors,
+        alerts: !config.alerts.rules.is_empty() || !config.alerts.container_rules.is_empty(),
+        mqtt: config.mqtt.broker_url.is_some(),
+        remote_write: config.remote_write.url.is_some(),
+        wol: config.server.enable_wol,
+        probes: !config.probes.targets.is_empty(),
+        http_checks: !config.http_checks.is_empty(),
+    }
+}
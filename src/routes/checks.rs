@@ -0,0 +1,14 @@
+// GET /api/checks: the state of every `[[http_checks]]` entry.
+
+use axum::{Json, extract::State};
+
+use super::AppState;
+use crate::models::HttpCheckStatus;
+
+/// GET /api/checks — each check in config order: up / down / pending, status and latency of the
+/// latest response, failures in a row and the last error. Empty without checks.
+pub(super) async fn api_checks_handler(
+    State(state): State<AppState>,
+) -> Json<Vec<HttpCheckStatus>> {
+    Json(state.metrics.checks.current())
+}
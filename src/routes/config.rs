@@ -0,0 +1,107 @@
+// /api/config: the effective config with secrets redacted, reloading the config file without
+// restarting (admin token required), and the bearer-token check shared with /api/ingest and
+// the WebSocket routes.
+
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use super::AppState;
+use super::api_error::ApiError;
+use crate::config::{ConfigSources, SanitizedConfig, Secret};
+use crate::reload::ConfigReloader;
+
+/// `Authorization: Bearer <server.admin_token>`; admin endpoints are off without a token.
+/// Returns the rejection, or `None` when the caller is authorized.
+pub(super) fn admin_rejection(state: &AppState, headers: &HeaderMap) -> Option<Response> {
+    bearer_rejection(
+        state.config.server.admin_token.as_ref(),
+        headers,
+        "admin endpoints are disabled; set server.admin_token",
+        "invalid or missing admin token",
+    )
+}
+
+/// 403 `disabled` without a configured `token`, 401 `invalid` unless the request carries
+/// `Authorization: Bearer <token>`; `None` when authorized.
+pub(super) fn bearer_rejection(
+    token: Option<&Secret>,
+    headers: &HeaderMap,
+    disabled: &str,
+    invalid: &str,
+) -> Option<Response> {
+    let Some(token) = token else {
+        return Some(ApiError::new(StatusCode::FORBIDDEN, disabled).into_response());
+    };
+    let presented = bearer_token(headers).unwrap_or_default();
+    if constant_time_eq(presented.as_bytes(), token.expose().as_bytes()) {
+        return None;
+    }
+    let mut response = ApiError::new(StatusCode::UNAUTHORIZED, invalid).into_response();
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Bearer"),
+    );
+    Some(response)
+}
+
+/// The token of an `Authorization: Bearer <token>` header.
+pub(super) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// POST /api/config/reload — re-read and validate the config; reloadable values apply at once,
+/// restart-only changes are listed in `requiresRestart`. An invalid config is rejected (422)
+/// and the running one kept.
+pub(super) async fn api_config_reload_handler(
+    State(state): State<AppState>,
+    reloader: Option<Extension<Arc<ConfigReloader>>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = admin_rejection(&state, &headers) {
+        return response;
+    }
+    let Some(Extension(reloader)) = reloader else {
+        return ApiError::unavailable("config reload is not available").into_response();
+    };
+    match reloader.reload_and_log() {
+        Ok(report) => (StatusCode::OK, axum::Json(report)).into_response(),
+        Err(e) => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, format!("{e:#}")).into_response(),
+    }
+}
+
+/// GET /api/config — the running config as `{sources, config}`: the file read and the keys set
+/// by `HOMESERVER_*` variables or flags, and [`SanitizedConfig`]. Needs the admin token when
+/// `server.admin_token` is set; open otherwise, since nothing secret is shown.
+pub(super) async fn api_config_handler(
+    State(state): State<AppState>,
+    reloader: Option<Extension<Arc<ConfigReloader>>>,
+    headers: HeaderMap,
+) -> Response {
+    if state.config.server.admin_token.is_some()
+        && let Some(response) = admin_rejection(&state, &headers)
+    {
+        return response;
+    }
+    let (config, sources) = match reloader {
+        Some(Extension(reloader)) => reloader.running(),
+        None => (state.config.clone(), ConfigSources::default()),
+    };
+    axum::Json(serde_json::json!({
+        "sources": sources,
+        "config": SanitizedConfig::new(config),
+    }))
+    .into_response()
+}
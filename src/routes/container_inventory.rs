@@ -0,0 +1,39 @@
+// GET /api/containers/inventory: every container seen locally, with its first / last sighting.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct InventoryQuery {
+    /// Also list containers missing from the latest snapshot (default false).
+    #[serde(default)]
+    include_gone: bool,
+}
+
+/// GET /api/containers/inventory?include_gone= — `{containerId, name, image, firstSeen, lastSeen,
+/// lastState, previousNames, gone}` per container from `container_inventory`: the ones in the
+/// latest local snapshot by name, then (with `include_gone=true`) the gone ones, most recently
+/// seen first.
+pub(super) async fn api_container_inventory_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<InventoryQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    match repo.get_container_inventory(q.include_gone).await {
+        Ok(inventory) => (StatusCode::OK, axum::Json(inventory)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "get_container_inventory failed");
+            ApiError::history(&e, "failed to load the container inventory").into_response()
+        }
+    }
+}
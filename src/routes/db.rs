@@ -0,0 +1,202 @@
+// /api/db: history database statistics, storage projection, blob verification and online backups.
+
+use std::path::{Path, PathBuf};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use serde::Deserialize;
+
+use super::api_error::{ApiError, ApiQuery};
+use super::config::admin_rejection;
+use super::{AppState, HistoryUnavailable};
+use crate::history_repo::{latest_backup, project_storage};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DbQuery {
+    /// Also run `PRAGMA quick_check` (reads the whole file, so opt-in).
+    #[serde(default)]
+    integrity: bool,
+}
+
+/// GET /api/db — schema version, row counts, blob_store size, aggregation watermarks and WAL size;
+/// `?integrity=true` adds `integrityProblems`.
+pub(super) async fn api_db_handler(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<DbQuery>,
+) -> Response {
+    let repo = match state.history_sqlite() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let mut stats = match repo.db_stats().await {
+        Ok(stats) => stats,
+        Err(e) => {
+            tracing::warn!(error = %e, "db_stats failed");
+            return ApiError::history(&e, "failed to load database stats").into_response();
+        }
+    };
+    if query.integrity {
+        match repo.integrity_check().await {
+            Ok(problems) => stats.integrity_problems = Some(problems),
+            Err(e) => {
+                tracing::warn!(error = %e, "integrity_check failed");
+                return ApiError::history(&e, "failed to run integrity check").into_response();
+            }
+        }
+    }
+    (StatusCode::OK, axum::Json(stats)).into_response()
+}
+
+/// GET /api/db/projection — per-tier row statistics and the steady-state size projected from the
+/// configured retentions, checked against `database.disk_budget_bytes`.
+pub(super) async fn api_db_projection_handler(State(state): State<AppState>) -> Response {
+    let repo = match state.history_sqlite() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    match repo.get_tier_stats().await {
+        Ok(tiers) => (
+            StatusCode::OK,
+            axum::Json(project_storage(&tiers, &state.config.database)),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "get_tier_stats failed");
+            ApiError::history(&e, "failed to load tier stats").into_response()
+        }
+    }
+}
+
+/// Rows one `GET /api/db/verify` decodes at most (each row decodes up to ten blobs).
+const MAX_VERIFY_ROWS: u64 = 50_000;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct VerifyQuery {
+    /// Range start, epoch ms (default: the first row).
+    from: Option<i64>,
+    /// Range end, epoch ms, exclusive (default: after the last row).
+    to: Option<i64>,
+    /// Row cap, at most [`MAX_VERIFY_ROWS`] (the default).
+    limit: Option<u64>,
+}
+
+/// GET /api/db/verify?from=&to=&limit= — decode every raw and aggregated blob in the range and
+/// list the ones that fail (`VerifyReport`); `truncated` when the row cap stopped the scan.
+/// Admin token: one call can decode [`MAX_VERIFY_ROWS`] rows.
+pub(super) async fn api_db_verify_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<VerifyQuery>,
+) -> Response {
+    if !state.history_repo.is_enabled() {
+        return HistoryUnavailable::Disabled.into_response();
+    }
+    if let Some(rejection) = admin_rejection(&state, &headers) {
+        return rejection;
+    }
+    let repo = match state.history_sqlite() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let (from, to) = (query.from.unwrap_or(i64::MIN), query.to.unwrap_or(i64::MAX));
+    if from >= to {
+        return ApiError::bad_request("from must be less than to").into_response();
+    }
+    let max_rows = query.limit.unwrap_or(MAX_VERIFY_ROWS).min(MAX_VERIFY_ROWS);
+    match repo.verify_blobs(from, to, max_rows).await {
+        Ok(report) => (StatusCode::OK, axum::Json(report)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "verify_blobs failed");
+            ApiError::history(&e, "failed to verify blobs").into_response()
+        }
+    }
+}
+
+/// POST /api/db/backup — `VACUUM INTO` a timestamped file under `database.backup_dir`,
+/// prune to `database.backup_retention_count`, and return `{ path, sizeBytes }`. Admin token.
+pub(super) async fn api_db_backup_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if !state.history_repo.is_enabled() {
+        return HistoryUnavailable::Disabled.into_response();
+    }
+    if let Some(rejection) = admin_rejection(&state, &headers) {
+        return rejection;
+    }
+    let repo = match state.history_sqlite() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let db = &state.config.database;
+    match repo
+        .create_backup(Path::new(&db.backup_dir), db.backup_retention_count)
+        .await
+    {
+        Ok(info) => (StatusCode::OK, axum::Json(info)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "database backup failed");
+            ApiError::history(&e, "database backup failed").into_response()
+        }
+    }
+}
+
+/// GET /api/db/backup/download — stream the most recent backup file (404 when there is none).
+/// Admin token: the file is the whole history.
+pub(super) async fn api_db_backup_download_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    // Backup files are readable even while the database itself is not.
+    if !state.history_repo.is_enabled() {
+        return HistoryUnavailable::Disabled.into_response();
+    }
+    if let Some(rejection) = admin_rejection(&state, &headers) {
+        return rejection;
+    }
+    let dir = PathBuf::from(&state.config.database.backup_dir);
+    let listed = tokio::task::spawn_blocking(move || latest_backup(&dir))
+        .await
+        .unwrap_or_else(|e| Err(e.into()));
+    let path = match listed {
+        Ok(Some(path)) => path,
+        Ok(None) => return ApiError::not_found("no backup available").into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "listing backups failed");
+            return ApiError::internal("failed to list backups").into_response();
+        }
+    };
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path.display(), "opening backup failed");
+            return ApiError::internal("failed to open backup").into_response();
+        }
+    };
+    let mut headers = vec![
+        (header::CONTENT_TYPE, "application/vnd.sqlite3".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}\"",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        ),
+    ];
+    if let Ok(meta) = file.metadata().await {
+        headers.push((header::CONTENT_LENGTH, meta.len().to_string()));
+    }
+    let body = Body::from_stream(tokio_util::io::ReaderStream::new(file));
+    let mut response = body.into_response();
+    for (name, value) in headers {
+        if let Ok(value) = value.parse() {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,63 @@
+// /api/errors: recent collector failures and per-source counts.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use crate::models::ErrorsSummary;
+
+const DEFAULT_ERRORS_LIMIT: u32 = 100;
+const MAX_ERRORS_LIMIT: u32 = 1000;
+const DEFAULT_COUNT_WINDOW_HOURS: u32 = 24;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ErrorsQuery {
+    /// Entries returned (default 100, capped at 1000).
+    limit: Option<u32>,
+    /// Window for the per-source counts (default 24).
+    hours: Option<u32>,
+}
+
+/// GET /api/errors?limit=&hours= — newest `collection_errors` entries plus failures per source
+/// over the last `hours`.
+pub(super) async fn api_errors_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<ErrorsQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let now_ms = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(_) => {
+            return ApiError::internal("system clock is before the Unix epoch").into_response();
+        }
+    };
+    let limit = q
+        .limit
+        .unwrap_or(DEFAULT_ERRORS_LIMIT)
+        .min(MAX_ERRORS_LIMIT);
+    let since = now_ms - i64::from(q.hours.unwrap_or(DEFAULT_COUNT_WINDOW_HOURS)) * MS_PER_HOUR;
+    let result = async {
+        Ok::<_, crate::history_repo::HistoryError>(ErrorsSummary {
+            errors: repo.get_recent_errors(limit).await?,
+            since,
+            counts: repo.error_counts_since(since).await?,
+        })
+    }
+    .await;
+    match result {
+        Ok(summary) => (StatusCode::OK, axum::Json(summary)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "collection errors query failed");
+            ApiError::history(&e, "failed to load collection errors").into_response()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// /api/events: server starts and clean shutdowns, and how long this process has been running.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use crate::models::ServiceEventsSummary;
+use crate::version;
+
+const DEFAULT_EVENTS_LIMIT: u32 = 100;
+const MAX_EVENTS_LIMIT: u32 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct EventsQuery {
+    /// Entries returned (default 100, capped at 1000).
+    limit: Option<u32>,
+}
+
+/// GET /api/events?limit= — newest `service_events` entries (`started`, `clean_shutdown`,
+/// `recovered_after_crash`) plus this process's start time and uptime.
+pub(super) async fn api_events_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<EventsQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let limit = q
+        .limit
+        .unwrap_or(DEFAULT_EVENTS_LIMIT)
+        .min(MAX_EVENTS_LIMIT);
+    match repo.get_service_events(limit).await {
+        Ok(events) => (
+            StatusCode::OK,
+            axum::Json(ServiceEventsSummary {
+                service_start_epoch_ms: version::service_start_epoch_ms(),
+                service_uptime_secs: version::service_uptime_secs(),
+                events,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "service events query failed");
+            ApiError::history(&e, "failed to load service events").into_response()
+        }
+    }
+}
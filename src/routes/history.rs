@@ -0,0 +1,209 @@
+// GET /api/history: query parsing (envelope, downsample, LTTB points) and the handler, which
+// merges raw and aggregated rows (api/history/since and /sync: since.rs; window: history_window.rs).
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use super::history_annotate::{history_json, parse_annotate};
+use super::history_window::{HistoryWindow, parse_auto};
+use crate::history_repo::{DownsampleMode, detect_history_anomalies, lttb_points};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct HistoryQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Resolution: "1s", "30s", "1m", "5m", "1h", "1d" or seconds 1, 30, 60, 300, 3600, 86400.
+    pub resolution: Option<String>,
+    /// Per-point band: "minmax" (min/max of CPU load and used memory) or "p95" (min/max + p95).
+    /// Omitted → plain snapshots.
+    pub envelope: Option<String>,
+    /// Raw downsampling: "avg" (default, bucket mean) or "last" (last sample per bucket); "lttb"
+    /// thins the averaged points to `points` with Largest-Triangle-Three-Buckets.
+    pub downsample: Option<String>,
+    /// Point budget for `downsample=lttb` (`LTTB_MIN_POINTS..=LTTB_MAX_POINTS`); required there.
+    pub points: Option<u32>,
+    /// Rows pushed by that instance (`POST /api/ingest`); omitted or `server.node_name` → local.
+    pub node: Option<String>,
+    /// "anomalies" wraps the response as `{points, anomalies}` with z-score flags on CPU / RAM.
+    pub annotate: Option<String>,
+    /// Points in the rolling baseline for `annotate=anomalies` (default 30).
+    pub anomaly_window: Option<usize>,
+    /// z-score that flags a point for `annotate=anomalies` (default 3; twice it is critical).
+    pub anomaly_threshold: Option<f64>,
+    /// "1" (or "true"): coarsen a request over `database.max_history_points` instead of a 422.
+    pub auto: Option<String>,
+}
+
+/// Which envelope /api/history attaches to each point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvelopeMode {
+    None,
+    MinMax,
+    P95,
+}
+
+fn parse_envelope(s: Option<&str>) -> Option<EnvelopeMode> {
+    match s.map(|v| v.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("none") => Some(EnvelopeMode::None),
+        Some("minmax") => Some(EnvelopeMode::MinMax),
+        Some("p95") => Some(EnvelopeMode::P95),
+        Some(_) => None,
+    }
+}
+
+/// Bounds of `points` for `downsample=lttb`.
+const LTTB_MIN_POINTS: u32 = 10;
+const LTTB_MAX_POINTS: u32 = 5000;
+
+/// What /api/history does with raw samples: one bucket reduction, or bucket means thinned by LTTB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Downsample {
+    Bucket(DownsampleMode),
+    Lttb,
+}
+
+fn parse_downsample(s: Option<&str>) -> Option<Downsample> {
+    match s.map(|v| v.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("avg") => Some(Downsample::Bucket(DownsampleMode::Average)),
+        Some("last") => Some(Downsample::Bucket(DownsampleMode::Last)),
+        Some("lttb") => Some(Downsample::Lttb),
+        Some(_) => None,
+    }
+}
+
+/// GET /api/history?from=&to=&resolution=&envelope=&downsample=&points=&node=&annotate= —
+/// history for mobile (merge raw + aggregated).
+/// Capped at `database.max_history_points`: a larger estimate is a 422 naming a coarser
+/// resolution, or with `auto=1` is answered at it; `x-effective-resolution` carries the seconds
+/// per point used. A clamped response carries `x-history-truncated: true`.
+/// `downsample=lttb&points=N` then keeps at most N of the merged points (LTTB over CPU load).
+/// `annotate=anomalies` answers `{points, anomalies}`, flagging CPU / RAM runs of the returned
+/// points beyond `anomaly_threshold` (see [`detect_history_anomalies`]).
+pub(super) async fn api_history_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<HistoryQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let Some(envelope) = parse_envelope(q.envelope.as_deref()) else {
+        return ApiError::bad_request("envelope must be one of: minmax, p95").into_response();
+    };
+    let Some(downsample) = parse_downsample(q.downsample.as_deref()) else {
+        return ApiError::bad_request("downsample must be one of: avg, last, lttb").into_response();
+    };
+    let lttb_threshold = match (downsample, q.points) {
+        (Downsample::Lttb, Some(n)) if (LTTB_MIN_POINTS..=LTTB_MAX_POINTS).contains(&n) => Some(n),
+        (Downsample::Lttb, _) => {
+            return ApiError::bad_request(format!(
+                "downsample=lttb needs points between {LTTB_MIN_POINTS} and {LTTB_MAX_POINTS}"
+            ))
+            .into_response();
+        }
+        (Downsample::Bucket(_), Some(_)) => {
+            return ApiError::bad_request("points requires downsample=lttb").into_response();
+        }
+        (Downsample::Bucket(_), None) => None,
+    };
+    let anomaly_params =
+        match parse_annotate(q.annotate.as_deref(), q.anomaly_window, q.anomaly_threshold) {
+            Ok(params) => params,
+            Err(error) => return ApiError::bad_request(error).into_response(),
+        };
+    // LTTB picks among bucket means, so its input is the same as `avg`.
+    let downsample = match downsample {
+        Downsample::Bucket(mode) => mode,
+        Downsample::Lttb => DownsampleMode::Average,
+    };
+    let Some(coarsen) = parse_auto(q.auto.as_deref()) else {
+        return ApiError::bad_request("auto must be one of: 1, 0, true, false").into_response();
+    };
+    let max_points = state.config.database.max_history_points;
+    let HistoryWindow {
+        now_ms,
+        from_ts,
+        to_ts,
+        resolution_secs,
+    } = match HistoryWindow::parse(q.from, q.to, q.resolution.as_deref(), max_points, coarsen) {
+        Ok(window) => window,
+        Err(e) => return e.into_response(),
+    };
+
+    let local = &state.config.server.node_name;
+    let result = match q.node.as_deref().filter(|node| node != local) {
+        // Pushed rows are kept raw (never rolled up), so there is no aggregated stretch.
+        Some(node) => {
+            repo.get_node_history_points_bounded(
+                node,
+                from_ts,
+                to_ts,
+                resolution_secs,
+                downsample,
+                max_points as usize,
+            )
+            .await
+        }
+        None => {
+            let raw_retention_hours = state.config.database.raw_retention_hours;
+            async {
+                let raw_cutoff_ts = repo.history_raw_cutoff(now_ms, raw_retention_hours).await?;
+                repo.get_history_points_bounded(
+                    from_ts,
+                    to_ts,
+                    resolution_secs,
+                    raw_cutoff_ts,
+                    downsample,
+                    max_points as usize,
+                )
+                .await
+            }
+            .await
+        }
+    };
+    let (mut points, truncated) = match result {
+        Ok((points, truncated)) => match lttb_threshold {
+            Some(n) => (lttb_points(points, n as usize), truncated),
+            None => (points, truncated),
+        },
+        Err(e) => {
+            tracing::warn!(error = %e, "get_history failed");
+            return ApiError::history(&e, "failed to load history").into_response();
+        }
+    };
+
+    for p in &mut points {
+        p.snapshot
+            .apply_cpu_percent_mode(state.config.docker.cpu_percent_mode);
+    }
+    let anomalies = anomaly_params.map(|params| detect_history_anomalies(&points, params));
+    let mut response = match envelope {
+        EnvelopeMode::None => {
+            let snapshots: Vec<_> = points.into_iter().map(|p| p.snapshot).collect();
+            history_json(snapshots, anomalies)
+        }
+        EnvelopeMode::MinMax | EnvelopeMode::P95 => {
+            if envelope == EnvelopeMode::MinMax {
+                for e in points.iter_mut().filter_map(|p| p.envelope.as_mut()) {
+                    e.cpu_load_p95 = None;
+                    e.memory_used_p95 = None;
+                }
+            }
+            history_json(points, anomalies)
+        }
+    };
+    let headers = response.headers_mut();
+    headers.insert("x-effective-resolution", resolution_secs.into());
+    if truncated {
+        headers.insert(
+            "x-history-truncated",
+            axum::http::HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
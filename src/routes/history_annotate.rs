@@ -0,0 +1,69 @@
+// /api/history?annotate=anomalies: parameters for the z-score detector and the
+// `{points, anomalies}` envelope around the usual response.
+
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::history_repo::AnomalyParams;
+use crate::models::Anomaly;
+
+/// Accepted `anomaly_window` (points before each scored point).
+const ANOMALY_WINDOW_RANGE: std::ops::RangeInclusive<usize> = 2..=1000;
+/// Upper bound for `anomaly_threshold`.
+const MAX_ANOMALY_THRESHOLD: f64 = 100.0;
+
+/// `annotate` (`anomalies`, or omitted for none) with the optional `anomaly_window` and
+/// `anomaly_threshold`; the error is the 400 message.
+pub(super) fn parse_annotate(
+    annotate: Option<&str>,
+    window: Option<usize>,
+    threshold: Option<f64>,
+) -> Result<Option<AnomalyParams>, String> {
+    match annotate.map(|v| v.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("none") => {
+            if window.is_some() || threshold.is_some() {
+                return Err("anomaly_window and anomaly_threshold need annotate=anomalies".into());
+            }
+            Ok(None)
+        }
+        Some("anomalies") => {
+            let mut params = AnomalyParams::default();
+            if let Some(window) = window {
+                if !ANOMALY_WINDOW_RANGE.contains(&window) {
+                    return Err(format!(
+                        "anomaly_window must be between {} and {}",
+                        ANOMALY_WINDOW_RANGE.start(),
+                        ANOMALY_WINDOW_RANGE.end()
+                    ));
+                }
+                params.window = window;
+            }
+            if let Some(threshold) = threshold {
+                if !(threshold > 0.0 && threshold <= MAX_ANOMALY_THRESHOLD) {
+                    return Err(format!(
+                        "anomaly_threshold must be > 0 and <= {MAX_ANOMALY_THRESHOLD}"
+                    ));
+                }
+                params.threshold = threshold;
+            }
+            Ok(Some(params))
+        }
+        Some(_) => Err("annotate must be one of: anomalies".into()),
+    }
+}
+
+#[derive(Serialize)]
+struct Annotated<T> {
+    points: T,
+    anomalies: Vec<Anomaly>,
+}
+
+/// `points` as the JSON array /api/history normally answers, or wrapped as
+/// `{points, anomalies}` when anomalies were requested.
+pub(super) fn history_json<T: Serialize>(points: T, anomalies: Option<Vec<Anomaly>>) -> Response {
+    let ok = axum::http::StatusCode::OK;
+    match anomalies {
+        Some(anomalies) => (ok, axum::Json(Annotated { points, anomalies })).into_response(),
+        None => (ok, axum::Json(points)).into_response(),
+    }
+}
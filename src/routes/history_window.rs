@@ -0,0 +1,135 @@
+// `from`/`to`/`resolution` validation shared by /api/history, /api/history/network and
+// /api/history/top-containers.
+
+use axum::http::StatusCode;
+
+use super::api_error::ApiError;
+
+/// Maximum span accepted by the history endpoints (guards against unbounded scans / OOM).
+const MAX_HISTORY_SPAN_MS: i64 = 31 * 24 * 3600 * 1000; // 31 days
+
+fn parse_resolution(s: &str) -> Option<u32> {
+    let s = s.trim().to_lowercase();
+    if s == "1s" || s == "1" {
+        return Some(1);
+    }
+    if s == "30s" || s == "30" {
+        return Some(30);
+    }
+    if s == "1m" || s == "60" {
+        return Some(60);
+    }
+    if s == "5m" || s == "300" {
+        return Some(300);
+    }
+    if s == "1h" || s == "3600" {
+        return Some(3600);
+    }
+    if s == "1d" || s == "86400" {
+        return Some(86400);
+    }
+    s.parse::<u32>().ok().filter(|&n| n > 0 && n <= 86400)
+}
+
+/// `auto` on /api/history: whether a too-dense request is coarsened; `None` for an unknown value.
+pub(super) fn parse_auto(s: Option<&str>) -> Option<bool> {
+    match s.map(|v| v.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("0") | Some("false") => Some(false),
+        Some("1") | Some("true") => Some(true),
+        Some(_) => None,
+    }
+}
+
+/// The resolutions a too-dense request is pointed at (or coarsened to with `auto=1`), finest first.
+pub const RESOLUTION_STEPS: [u32; 6] = [1, 30, 60, 300, 3600, 86400];
+
+/// Points a `span_ms` window yields at `resolution_secs`: one per full bucket.
+pub fn estimate_points(span_ms: i64, resolution_secs: u32) -> i64 {
+    span_ms / (i64::from(resolution_secs) * 1000).max(1)
+}
+
+/// The finest of [`RESOLUTION_STEPS`] at which `span_ms` stays within `max_points`, or `None`
+/// when even a day per point is too many (only a narrower range helps).
+pub fn suggest_resolution(span_ms: i64, max_points: u32) -> Option<u32> {
+    RESOLUTION_STEPS
+        .into_iter()
+        .find(|&step| estimate_points(span_ms, step) <= i64::from(max_points))
+}
+
+/// A validated history range: `to` defaults to now, `from` to one hour before, resolution to 1m.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HistoryWindow {
+    pub now_ms: i64,
+    pub from_ts: i64,
+    pub to_ts: i64,
+    pub resolution_secs: u32,
+}
+
+impl HistoryWindow {
+    /// The window for the query, or the error to answer: 400 for a bad range, 422 when the
+    /// estimated point count exceeds `max_points` (with the suggested resolution in `details`),
+    /// 500 if the clock is before the epoch. With `coarsen`, a too-dense request takes the
+    /// suggested resolution instead of the 422.
+    pub(super) fn parse(
+        from: Option<i64>,
+        to: Option<i64>,
+        resolution: Option<&str>,
+        max_points: u32,
+        coarsen: bool,
+    ) -> Result<Self, ApiError> {
+        let now_ms = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as i64,
+            Err(_) => return Err(ApiError::internal("system clock is before the Unix epoch")),
+        };
+
+        let to_ts = to.unwrap_or(now_ms);
+        let from_ts = from.unwrap_or(now_ms.saturating_sub(3600 * 1000)); // default last 1h
+        let mut resolution_secs = resolution.and_then(parse_resolution).unwrap_or(60);
+
+        if from_ts >= to_ts {
+            return Err(ApiError::bad_request("from must be less than to"));
+        }
+        // checked_sub: `from`/`to` are unbounded user input, so the difference can overflow i64
+        // (e.g. to=i64::MAX, from=i64::MIN) — which would panic in debug or wrap past the cap in release.
+        let Some(span_ms) = to_ts.checked_sub(from_ts) else {
+            return Err(ApiError::bad_request("invalid time range (overflow)"));
+        };
+        if span_ms > MAX_HISTORY_SPAN_MS {
+            return Err(ApiError::bad_request("time range too large (max 31 days)"));
+        }
+        // `database.max_history_points` caps the response: reject (or coarsen) requests that
+        // would clearly exceed it, and clamp (x-history-truncated) the rest when the actual rows
+        // still do.
+        let estimated_points = estimate_points(span_ms, resolution_secs);
+        if estimated_points > i64::from(max_points) {
+            let suggested = suggest_resolution(span_ms, max_points);
+            match suggested {
+                Some(step) if coarsen => resolution_secs = step,
+                _ => {
+                    let hint = match suggested {
+                        Some(step) => format!("use resolution={step} or narrow the range"),
+                        None => "narrow the range".to_string(),
+                    };
+                    return Err(ApiError::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!(
+                            "too many points for the requested resolution \
+                             ({estimated_points} > {max_points}); {hint}"
+                        ),
+                    )
+                    .with_details(serde_json::json!({
+                        "estimatedPoints": estimated_points,
+                        "maxPoints": max_points,
+                        "suggestedResolution": suggested,
+                    })));
+                }
+            }
+        }
+        Ok(Self {
+            now_ms,
+            from_ts,
+            to_ts,
+            resolution_secs,
+        })
+    }
+}
@@ -1,17 +1,45 @@
-// GET handlers: version, api/info, api/history
+// GET handlers: health, api/cpu, api/ram (api/history: history.rs; version, api/info: info.rs)
+
+use std::time::{Duration, Instant};
 
 use axum::{
-    extract::{Query, State},
+    extract::State,
     response::{IntoResponse, Response},
 };
-use serde::Deserialize;
+use serde::Serialize;
 
-use super::AppState;
-use crate::version::{NAME, VERSION};
+use super::api_error::ApiError;
+use super::{AppState, HISTORY_RETRY_AFTER_SECS, HistoryUnavailable};
 
-/// GET /health — liveness/readiness probe. 200 when the SQLite pool is reachable, else 503.
+/// GET /health — liveness/readiness probe. 200 when the SQLite pool is reachable (always in agent
+/// mode, which has none), else 503; with `Retry-After` while the database is still being opened,
+/// and `disk full` while the history writer drops batches for lack of space. Plain text, unlike
+/// the JSON routes, so probes can match the body.
 pub(super) async fn health_handler(State(state): State<AppState>) -> Response {
-    match state.history_repo.ping().await {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        // Agent mode has no database to check; `history()` itself never reports `NotSqlite`,
+        // which only the `/api/db*` endpoints answer.
+        Err(HistoryUnavailable::Disabled | HistoryUnavailable::NotSqlite) => {
+            return (axum::http::StatusCode::OK, "ok").into_response();
+        }
+        Err(HistoryUnavailable::Starting(_)) => {
+            return (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                [(
+                    axum::http::header::RETRY_AFTER,
+                    HISTORY_RETRY_AFTER_SECS.to_string(),
+                )],
+                "database starting",
+            )
+                .into_response();
+        }
+    };
+    match repo.ping().await {
+        // History is being dropped: the database answers, but nothing new reaches it.
+        Ok(()) if state.metrics.history_flush.disk_full() => {
+            (axum::http::StatusCode::SERVICE_UNAVAILABLE, "disk full").into_response()
+        }
         Ok(()) => (axum::http::StatusCode::OK, "ok").into_response(),
         Err(e) => {
             tracing::warn!(error = %e, "health check failed");
@@ -24,123 +52,55 @@ pub(super) async fn health_handler(State(state): State<AppState>) -> Response {
     }
 }
 
-/// GET /version — returns service name and version (from Cargo.toml at build time).
-pub(super) async fn version_handler() -> impl IntoResponse {
-    axum::Json(serde_json::json!({
-        "name": NAME,
-        "version": VERSION,
-    }))
-}
-
-/// GET /api/info — returns static system identity (fetch once; not sent every tick on WS).
-pub(super) async fn api_info_handler(State(state): State<AppState>) -> impl IntoResponse {
-    axum::Json(state.system_info.as_ref().clone())
-}
-
-#[derive(Debug, Deserialize)]
-pub(super) struct HistoryQuery {
-    pub from: Option<i64>,
-    pub to: Option<i64>,
-    /// Resolution: "1s", "30s", "1m", "5m" or seconds 1, 30, 60, 300.
-    pub resolution: Option<String>,
-}
+/// How long a `/api/cpu` or `/api/ram` reading is reused, so a burst of calls takes the sysinfo
+/// lock once.
+pub const READING_CACHE_TTL: Duration = Duration::from_millis(500);
 
-/// Maximum span accepted by /api/history (guards against unbounded scans / OOM).
-const MAX_HISTORY_SPAN_MS: i64 = 31 * 24 * 3600 * 1000; // 31 days
-/// Maximum number of points a single /api/history response may materialize.
-const MAX_HISTORY_POINTS: i64 = 50_000;
+/// Last one-shot reading and when it was taken. The lock is held while reading, so concurrent
+/// requests on an expired entry wait for a single refresh.
+#[derive(Default)]
+pub(crate) struct ReadingCache<T>(tokio::sync::Mutex<Option<(Instant, T)>>);
 
-fn parse_resolution(s: &str) -> Option<u32> {
-    let s = s.trim().to_lowercase();
-    if s == "1s" || s == "1" {
-        return Some(1);
-    }
-    if s == "30s" || s == "30" {
-        return Some(30);
-    }
-    if s == "1m" || s == "60" {
-        return Some(60);
-    }
-    if s == "5m" || s == "300" {
-        return Some(300);
+impl<T: Clone> ReadingCache<T> {
+    async fn get<F: Future<Output = anyhow::Result<T>>>(&self, read: F) -> anyhow::Result<T> {
+        let mut cached = self.0.lock().await;
+        if let Some((read_at, value)) = &*cached
+            && read_at.elapsed() < READING_CACHE_TTL
+        {
+            return Ok(value.clone());
+        }
+        let value = read.await?;
+        *cached = Some((Instant::now(), value.clone()));
+        Ok(value)
     }
-    s.parse::<u32>().ok().filter(|&n| n > 0 && n <= 3600)
 }
 
-/// GET /api/history?from=&to=&resolution= — history for mobile (merge raw + aggregated).
-pub(super) async fn api_history_handler(
-    State(state): State<AppState>,
-    Query(q): Query<HistoryQuery>,
+/// Answer a cached reading as JSON, or 500 when `what` could not be read.
+async fn reading_response<T: Clone + Serialize>(
+    cache: &ReadingCache<T>,
+    read: impl Future<Output = anyhow::Result<T>>,
+    what: &str,
 ) -> Response {
-    let now_ms = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
-        Ok(d) => d.as_millis() as i64,
-        Err(_) => {
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "system time").into_response();
+    match cache.get(read).await {
+        Ok(value) => axum::Json(value).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "{what} read failed");
+            ApiError::internal(format!("failed to read {what} stats")).into_response()
         }
-    };
-
-    let to_ts = q.to.unwrap_or(now_ms);
-    let from_ts = q.from.unwrap_or(now_ms.saturating_sub(3600 * 1000)); // default last 1h
-    let resolution_secs = q
-        .resolution
-        .as_deref()
-        .and_then(parse_resolution)
-        .unwrap_or(60);
-
-    if from_ts >= to_ts {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({"error": "from must be less than to"})),
-        )
-            .into_response();
     }
+}
 
-    // checked_sub: `from`/`to` are unbounded user input, so the difference can overflow i64
-    // (e.g. to=i64::MAX, from=i64::MIN) — which would panic in debug or wrap past the cap in release.
-    let Some(span_ms) = to_ts.checked_sub(from_ts) else {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({"error": "invalid time range (overflow)"})),
-        )
-            .into_response();
-    };
-    if span_ms > MAX_HISTORY_SPAN_MS {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({"error": "time range too large (max 31 days)"})),
-        )
-            .into_response();
-    }
-    let estimated_points = span_ms / ((resolution_secs as i64) * 1000).max(1);
-    if estimated_points > MAX_HISTORY_POINTS {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            axum::Json(serde_json::json!({
-                "error": "too many points for the requested resolution; increase resolution or narrow the range"
-            })),
-        )
-            .into_response();
-    }
-
-    // saturating_sub: avoid underflow when `to` is near i64::MIN (clamps to the start of time).
-    let raw_cutoff_ts =
-        to_ts.saturating_sub((state.config.database.raw_retention_hours as i64) * 3600 * 1000);
-
-    let snapshots = match state
-        .history_repo
-        .get_history(from_ts, to_ts, resolution_secs, raw_cutoff_ts)
-        .await
-    {
-        Ok(s) => s,
-        Err(e) => {
-            tracing::warn!(error = %e, "get_history failed");
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(serde_json::json!({"error": "failed to load history"})),
-            )
-                .into_response();
-        }
-    };
+/// GET /api/cpu — one `CpuStats` reading, reused for [`READING_CACHE_TTL`]. `usagePercent` is the
+/// change since the previous CPU refresh on the shared `SysinfoRepo`: with the worker running
+/// that is its last tick; on a repo nobody sampled yet the first call only takes the baseline
+/// (0) and a call `sysinfo::MINIMUM_CPU_UPDATE_INTERVAL` later reports real usage.
+pub(super) async fn api_cpu_handler(State(state): State<AppState>) -> Response {
+    let repo = &state.sysinfo_repo;
+    reading_response(&state.cpu_reading, repo.get_cpu_stats(), "cpu").await
+}
 
-    (axum::http::StatusCode::OK, axum::Json(snapshots)).into_response()
+/// GET /api/ram — one `RamStats` reading, reused for [`READING_CACHE_TTL`].
+pub(super) async fn api_ram_handler(State(state): State<AppState>) -> Response {
+    let repo = &state.sysinfo_repo;
+    reading_response(&state.ram_reading, repo.get_ram_stats(), "ram").await
 }
@@ -0,0 +1,83 @@
+// /version and /api/info (both with the listener's `boundAddress`), and /api/info/refresh:
+// re-detect the system identity without restarting (admin token required).
+
+use std::net::SocketAddr;
+
+use axum::{
+    Extension,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use super::AppState;
+use super::api_error::ApiError;
+use super::config::admin_rejection;
+use crate::models::SystemInfo;
+use crate::serve::BoundAddress;
+use crate::system_info_refresh;
+use crate::version::{self, NAME, VERSION};
+
+/// GET /version — service name and version (Cargo.toml) plus build metadata (build.rs).
+pub(super) async fn version_handler(bound: Option<Extension<BoundAddress>>) -> impl IntoResponse {
+    axum::Json(version_json(bound_address(bound)))
+}
+
+/// Body of `/version` (also the `version` section of `/api/bootstrap`). `boundAddress` is the
+/// TCP listener's actual address (the picked port with `server.port = 0`); `null` when the
+/// router is not served over TCP by `serve::run`.
+pub(super) fn version_json(bound_address: Option<SocketAddr>) -> serde_json::Value {
+    serde_json::json!({
+        "name": NAME,
+        "version": VERSION,
+        "gitCommit": version::GIT_COMMIT,
+        "buildTime": version::BUILD_TIME,
+        "rustcVersion": version::RUSTC_VERSION,
+        "target": version::TARGET,
+        "boundAddress": bound_address.map(|a| a.to_string()),
+    })
+}
+
+/// GET /api/info — returns static system identity (fetch once; not sent every tick on WS).
+pub(super) async fn api_info_handler(
+    State(state): State<AppState>,
+    bound: Option<Extension<BoundAddress>>,
+) -> impl IntoResponse {
+    axum::Json(info_json(&state.system_info.get(), bound_address(bound)))
+}
+
+/// Body of `/api/info` (also the `info` section of `/api/bootstrap`): the [`SystemInfo`] fields
+/// plus `boundAddress` as in [`version_json`].
+pub(super) fn info_json(info: &SystemInfo, bound_address: Option<SocketAddr>) -> serde_json::Value {
+    let mut body = serde_json::json!(info);
+    body["boundAddress"] = serde_json::json!(bound_address.map(|a| a.to_string()));
+    body
+}
+
+pub(super) fn bound_address(bound: Option<Extension<BoundAddress>>) -> Option<SocketAddr> {
+    bound.map(|Extension(BoundAddress(addr))| addr)
+}
+
+/// POST /api/info/refresh — re-read the host identity, serve it on `/api/info`, store it and
+/// send it to the `/ws/system` clients when it changed.
+pub(super) async fn api_info_refresh_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = admin_rejection(&state, &headers) {
+        return response;
+    }
+    match system_info_refresh::refresh(
+        &state.system_info,
+        &state.sysinfo_repo,
+        state.history_repo.get().map(|repo| repo.as_ref()),
+    )
+    .await
+    {
+        Ok(report) => (StatusCode::OK, axum::Json(report)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "system info refresh failed");
+            ApiError::internal(format!("{e:#}")).into_response()
+        }
+    }
+}
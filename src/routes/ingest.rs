@@ -0,0 +1,90 @@
+// POST /api/ingest: snapshot batches pushed by other instances ([remote_write]).
+
+use axum::{
+    body::Bytes,
+    extract::{State, rejection::BytesRejection},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+
+use super::AppState;
+use super::api_error::ApiError;
+use super::config::bearer_rejection;
+use crate::config::{MAX_INGEST_BATCH, valid_node};
+use crate::models::{INGEST_WINCODE_CONTENT_TYPE, IngestBatch};
+
+/// Request body cap: a full batch of snapshots with many containers is well over axum's 2 MiB
+/// default.
+pub(super) const INGEST_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+fn decode(headers: &HeaderMap, body: &[u8]) -> Result<IngestBatch, String> {
+    let wincode = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with(INGEST_WINCODE_CONTENT_TYPE));
+    if wincode {
+        IngestBatch::from_wincode(body).map_err(|e| format!("invalid wincode batch: {e}"))
+    } else {
+        serde_json::from_slice(body).map_err(|e| format!("invalid JSON batch: {e}"))
+    }
+}
+
+/// POST /api/ingest (`Authorization: Bearer <remote_write.ingest_api_key>`) — store an
+/// [`IngestBatch`] under its `node`; 200 `{"stored": n}`, n excluding timestamps already stored
+/// for that node. 400 for a malformed batch, an invalid node name or this instance's own name.
+pub(super) async fn api_ingest_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Response {
+    if let Some(response) = bearer_rejection(
+        state.config.remote_write.ingest_api_key.as_ref(),
+        &headers,
+        "ingest is disabled; set remote_write.ingest_api_key",
+        "invalid or missing ingest key",
+    ) {
+        return response;
+    }
+    // After the key check, so an unauthenticated caller learns nothing about the database (agent
+    // mode, still opening and why) or the body limit.
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return ApiError::from(rejection).into_response(),
+    };
+    let batch = match decode(&headers, &body) {
+        Ok(batch) => batch,
+        Err(message) => return ApiError::bad_request(message).into_response(),
+    };
+    if !valid_node(&batch.node) {
+        return ApiError::bad_request("node must be 1-64 characters of [A-Za-z0-9._-]")
+            .into_response();
+    }
+    if batch.node == state.config.server.node_name {
+        return ApiError::bad_request("node is this instance's own name (server.node_name)")
+            .into_response();
+    }
+    if batch.snapshots.len() > MAX_INGEST_BATCH {
+        return ApiError::bad_request(format!("at most {MAX_INGEST_BATCH} snapshots per batch"))
+            .into_response();
+    }
+    if batch.snapshots.iter().any(|s| s.timestamp == 0) {
+        return ApiError::bad_request("snapshot timestamps must be > 0").into_response();
+    }
+    match repo
+        .save_node_snapshots(&batch.node, &batch.snapshots)
+        .await
+    {
+        Ok(stored) => {
+            tracing::debug!(node = %batch.node, stored, "ingested remote snapshots");
+            axum::Json(serde_json::json!({"stored": stored})).into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, node = %batch.node, "ingest failed");
+            ApiError::history(&e, "failed to store batch").into_response()
+        }
+    }
+}
@@ -1,54 +1,279 @@
 // HTTP + WebSocket routes
 
+mod alerts;
+mod api_error;
+mod bootstrap;
+mod capabilities;
+mod checks;
+mod config;
+mod container_inventory;
+mod db;
+mod errors;
+mod events;
+mod history;
+mod history_annotate;
+mod history_window;
 mod http;
+mod info;
+mod ingest;
+mod network_history;
+mod probes;
+mod report;
+mod request_metrics;
+mod sensors;
+mod since;
+mod stats;
+mod status_page;
+mod storage_projection;
+mod top_containers;
+mod wol;
+mod worker;
 mod ws;
 
-use axum::{Router, routing::get};
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, MatchedPath, Request},
+    http::{HeaderName, StatusCode, header},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
 use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
 
 use crate::config::AppConfig;
-use crate::history_repo::HistoryRepo;
-use crate::models::{FullSystemSnapshot, SystemInfo};
+use crate::history_repo::{HistoryHandle, HistoryRepo, HistoryStore};
+use crate::metrics::ServiceMetrics;
+use crate::models::{CpuStats, FullSystemSnapshot, RamStats};
 use crate::sysinfo_repo::SysinfoRepo;
+use crate::system_info_refresh::SharedSystemInfo;
+use crate::ws_connections::WsConnections;
+use api_error::ApiError;
+
+pub use api_error::error_code;
+pub use history_window::{RESOLUTION_STEPS, estimate_points, suggest_resolution};
+pub use http::READING_CACHE_TTL;
+pub use request_metrics::{HttpMetrics, RouteStats, UNMATCHED_ROUTE};
 
 #[derive(Clone)]
 pub(crate) struct AppState {
     pub(crate) stats_tx: broadcast::Sender<FullSystemSnapshot>,
     pub(crate) sysinfo_repo: Arc<SysinfoRepo>,
-    pub(crate) system_info: Arc<SystemInfo>,
-    pub(crate) ws_system_connections: Arc<AtomicUsize>,
+    /// Swapped by `POST /api/info/refresh` and the periodic refresh.
+    pub(crate) system_info: SharedSystemInfo,
+    pub(crate) ws_connections: Arc<WsConnections>,
     pub(crate) config: AppConfig,
-    pub(crate) history_repo: Arc<HistoryRepo>,
+    /// Disabled with `database.enabled = false` (agent mode): the history endpoints answer 404;
+    /// 503 while the database is still being opened. See [`AppState::history`].
+    pub(crate) history_repo: HistoryHandle,
+    pub(crate) metrics: ServiceMetrics,
+    /// Cached `/api/capabilities` history range.
+    pub(crate) history_bounds: Arc<capabilities::HistoryBoundsCache>,
+    /// Cached `/api/cpu` and `/api/ram` readings.
+    pub(crate) cpu_reading: Arc<http::ReadingCache<CpuStats>>,
+    pub(crate) ram_reading: Arc<http::ReadingCache<RamStats>>,
+}
+
+/// Response headers a cross-origin dashboard may read; browsers hide those that are not
+/// CORS-safelisted unless the server exposes them.
+const EXPOSED_HEADERS: [HeaderName; 4] = [
+    HeaderName::from_static("x-next-since"),
+    HeaderName::from_static("x-effective-resolution"),
+    HeaderName::from_static("x-history-truncated"),
+    header::RETRY_AFTER,
+];
+
+/// `Retry-After` (seconds) of the 503 answered while the database is being opened.
+pub(crate) const HISTORY_RETRY_AFTER_SECS: u64 = 5;
+
+/// Why a history endpoint has no repo to read.
+pub(crate) enum HistoryUnavailable {
+    /// Agent mode: 404.
+    Disabled,
+    /// `connect`/`init` still retrying: 503 with `Retry-After`, and the last failure if any.
+    Starting(Option<String>),
+    /// The endpoint maintains the SQLite file (`/api/db*`) and `database.engine` is "postgres":
+    /// 501.
+    NotSqlite,
 }
 
+impl IntoResponse for HistoryUnavailable {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Disabled => ApiError::not_found(
+                "history is disabled on this instance (database.enabled = false)",
+            )
+            .into_response(),
+            Self::Starting(reason) => (
+                [(header::RETRY_AFTER, HISTORY_RETRY_AFTER_SECS.to_string())],
+                ApiError::unavailable("the history database is not ready yet")
+                    .with_details(serde_json::json!({ "reason": reason })),
+            )
+                .into_response(),
+            Self::NotSqlite => ApiError::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "not available with the postgres history engine (database.engine)",
+            )
+            .into_response(),
+        }
+    }
+}
+
+impl AppState {
+    /// The local repo, or why there is none (answer it with `into_response`).
+    pub(crate) fn history(&self) -> Result<&Arc<dyn HistoryStore>, HistoryUnavailable> {
+        match self.history_repo.get() {
+            Some(repo) => Ok(repo),
+            None if self.history_repo.is_enabled() => {
+                Err(HistoryUnavailable::Starting(self.history_repo.last_error()))
+            }
+            None => Err(HistoryUnavailable::Disabled),
+        }
+    }
+
+    /// [`Self::history`] for the endpoints that maintain the SQLite file (`/api/db` statistics,
+    /// projection, verification and backups).
+    pub(crate) fn history_sqlite(&self) -> Result<&HistoryRepo, HistoryUnavailable> {
+        self.history()?
+            .as_sqlite()
+            .ok_or(HistoryUnavailable::NotSqlite)
+    }
+}
+
+/// `history_repo`: an `Arc<HistoryRepo>` or `Arc<dyn HistoryStore>`, `None` for agent mode (no local database), or a
+/// [`HistoryHandle`] that is still pending.
 pub fn app(
     stats_tx: broadcast::Sender<FullSystemSnapshot>,
     sysinfo_repo: Arc<SysinfoRepo>,
-    system_info: Arc<SystemInfo>,
-    ws_system_connections: Arc<AtomicUsize>,
+    system_info: impl Into<SharedSystemInfo>,
+    ws_connections: Arc<WsConnections>,
     config: AppConfig,
-    history_repo: Arc<HistoryRepo>,
+    history_repo: impl Into<HistoryHandle>,
+    metrics: ServiceMetrics,
 ) -> Router {
+    let traced = config.telemetry.otlp_endpoint.is_some();
+    let http_metrics = metrics.http.clone();
     let state = AppState {
         stats_tx,
         sysinfo_repo,
-        system_info,
-        ws_system_connections,
+        system_info: system_info.into(),
+        ws_connections,
         config,
-        history_repo,
+        history_repo: history_repo.into(),
+        metrics,
+        history_bounds: Default::default(),
+        cpu_reading: Default::default(),
+        ram_reading: Default::default(),
     };
-    Router::new()
-        .route("/", get(|| async { "Ktor: Hello from Rust homeserver!" })) // GET /
+    let router = Router::new()
+        .route("/", get(status_page::status_page_handler)) // GET / (HTML status page)
         .route("/health", get(http::health_handler)) // GET /health
-        .route("/version", get(http::version_handler)) // GET /version
-        .route("/api/info", get(http::api_info_handler)) // GET /api/info
-        .route("/api/history", get(http::api_history_handler)) // GET /api/history?from=&to=&resolution=
+        .route("/version", get(info::version_handler)) // GET /version
+        .route("/api/info", get(info::api_info_handler)) // GET /api/info
+        .route("/api/cpu", get(http::api_cpu_handler)) // GET /api/cpu
+        .route("/api/ram", get(http::api_ram_handler)) // GET /api/ram
+        .route(
+            "/api/capabilities",
+            get(capabilities::api_capabilities_handler),
+        ) // GET /api/capabilities
+        .route("/api/bootstrap", get(bootstrap::api_bootstrap_handler)) // GET /api/bootstrap?history_secs=&resolution=&info=&version=&latest=&history=&capabilities=
+        .route("/api/info/refresh", post(info::api_info_refresh_handler)) // POST /api/info/refresh (Authorization: Bearer <server.admin_token>)
+        .route("/api/history", get(history::api_history_handler)) // GET /api/history?from=&to=&resolution=&node=&annotate=
+        .route("/api/history/since", get(since::api_history_since_handler)) // GET /api/history/since?ts=&limit=
+        .route("/api/history/sync", get(since::api_history_sync_handler)) // GET /api/history/sync?since_seq=&since_ts=&limit=
+        .route(
+            "/api/history/network",
+            get(network_history::api_history_network_handler),
+        ) // GET /api/history/network?iface=&from=&to=&resolution=
+        .route(
+            "/api/history/top-containers",
+            get(top_containers::api_history_top_containers_handler),
+        ) // GET /api/history/top-containers?from=&to=&metric=&limit=
+        .route(
+            "/api/containers/inventory",
+            get(container_inventory::api_container_inventory_handler),
+        ) // GET /api/containers/inventory?include_gone=
+        .route("/api/report", get(report::api_report_handler)) // GET /api/report?period=&format=
+        .route(
+            "/api/storage/projection",
+            get(storage_projection::api_storage_projection_handler),
+        ) // GET /api/storage/projection?days=
+        .route(
+            "/api/ingest",
+            post(ingest::api_ingest_handler)
+                .layer(DefaultBodyLimit::max(ingest::INGEST_BODY_LIMIT)),
+        ) // POST /api/ingest (Authorization: Bearer <remote_write.ingest_api_key>)
+        .route("/api/errors", get(errors::api_errors_handler)) // GET /api/errors?limit=&hours=
+        .route("/api/events", get(events::api_events_handler)) // GET /api/events?limit=
+        .route("/api/alerts", get(alerts::api_alerts_handler)) // GET /api/alerts
+        .route("/api/probes", get(probes::api_probes_handler)) // GET /api/probes
+        .route("/api/checks", get(checks::api_checks_handler)) // GET /api/checks
+        .route("/api/sensors", get(sensors::api_sensors_handler)) // GET /api/sensors
+        .route("/api/stats", get(stats::api_stats_handler)) // GET /api/stats
+        .route("/metrics", get(stats::metrics_handler)) // GET /metrics (Prometheus)
+        .route("/api/worker/pause", post(worker::api_worker_pause_handler)) // POST /api/worker/pause?duration_secs=
+        .route(
+            "/api/worker/resume",
+            post(worker::api_worker_resume_handler),
+        ) // POST /api/worker/resume
+        .route("/api/wol", post(wol::api_wol_handler)) // POST /api/wol (Authorization: Bearer <server.admin_token>; server.enable_wol)
+        .route("/api/wol/targets", get(wol::api_wol_targets_handler)) // GET /api/wol/targets (server.enable_wol)
+        .route("/api/config", get(config::api_config_handler)) // GET /api/config (Authorization: Bearer <server.admin_token> when set)
+        .route(
+            "/api/config/reload",
+            post(config::api_config_reload_handler),
+        ) // POST /api/config/reload (Authorization: Bearer <server.admin_token>)
+        .route("/api/db", get(db::api_db_handler)) // GET /api/db
+        .route("/api/db/projection", get(db::api_db_projection_handler)) // GET /api/db/projection
+        .route("/api/db/verify", get(db::api_db_verify_handler)) // GET /api/db/verify?from=&to=&limit=
+        .route("/api/db/backup", post(db::api_db_backup_handler)) // POST /api/db/backup
+        .route(
+            "/api/db/backup/download",
+            get(db::api_db_backup_download_handler),
+        ) // GET /api/db/backup/download
         .route("/ws/cpu", get(ws::ws_cpu)) // WS /ws/cpu
         .route("/ws/ram", get(ws::ws_ram)) // WS /ws/ram
         .route("/ws/system", get(ws::ws_system)) // WS /ws/system
-        .layer(CorsLayer::new().allow_origin(Any))
-        .with_state(state)
+        .fallback(api_error::not_found_fallback)
+        .method_not_allowed_fallback(api_error::method_not_allowed_fallback)
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .expose_headers(EXPOSED_HEADERS),
+        )
+        .layer(middleware::from_fn_with_state(
+            http_metrics,
+            request_metrics::record_request,
+        ));
+    // A span per request for OTLP export; without [telemetry] the layer is not added at all.
+    let router = if traced {
+        router.layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|req: &Request| {
+                    let path = req
+                        .extensions()
+                        .get::<MatchedPath>()
+                        .map_or(req.uri().path(), MatchedPath::as_str);
+                    tracing::info_span!(
+                        "request",
+                        method = %req.method(),
+                        path,
+                        status = tracing::field::Empty,
+                        latency_ms = tracing::field::Empty,
+                    )
+                })
+                .on_response(
+                    |res: &Response, latency: std::time::Duration, span: &Span| {
+                        span.record("status", res.status().as_u16());
+                        span.record("latency_ms", latency.as_millis() as u64);
+                    },
+                ),
+        )
+    } else {
+        router
+    };
+    router.with_state(state)
 }
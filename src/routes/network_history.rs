@@ -0,0 +1,78 @@
+// GET /api/history/network: one interface's rx/tx series.
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use super::history_window::HistoryWindow;
+use crate::history_repo::{DownsampleMode, interface_series};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct NetworkHistoryQuery {
+    /// Interface name (e.g. `eth0`); required.
+    pub iface: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Same values as /api/history.
+    pub resolution: Option<String>,
+}
+
+/// GET /api/history/network?iface=&from=&to=&resolution= — `{timestamp, rxBytesPerSec,
+/// txBytesPerSec, rxBytes, txBytes}` per point for one interface of the local node. Range rules
+/// and the `x-history-truncated` cap match /api/history.
+pub(super) async fn api_history_network_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<NetworkHistoryQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let Some(iface) = q.iface.as_deref().map(str::trim).filter(|i| !i.is_empty()) else {
+        return ApiError::bad_request("iface is required").into_response();
+    };
+    let max_points = state.config.database.max_history_points;
+    let HistoryWindow {
+        now_ms,
+        from_ts,
+        to_ts,
+        resolution_secs,
+    } = match HistoryWindow::parse(q.from, q.to, q.resolution.as_deref(), max_points, false) {
+        Ok(window) => window,
+        Err(e) => return e.into_response(),
+    };
+
+    let raw_retention_hours = state.config.database.raw_retention_hours;
+    let result = async {
+        let raw_cutoff_ts = repo.history_raw_cutoff(now_ms, raw_retention_hours).await?;
+        repo.get_history_points_bounded(
+            from_ts,
+            to_ts,
+            resolution_secs,
+            raw_cutoff_ts,
+            DownsampleMode::Average,
+            max_points as usize,
+        )
+        .await
+    }
+    .await;
+    let (series, truncated) = match result {
+        Ok((points, truncated)) => (interface_series(&points, iface), truncated),
+        Err(e) => {
+            tracing::warn!(error = %e, "get_history_points_bounded failed");
+            return ApiError::history(&e, "failed to load history").into_response();
+        }
+    };
+    let mut response = (axum::http::StatusCode::OK, axum::Json(series)).into_response();
+    if truncated {
+        response.headers_mut().insert(
+            "x-history-truncated",
+            axum::http::HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
@@ -0,0 +1,12 @@
+// GET /api/probes: the latest reachability and latency per `[[probes.targets]]` entry.
+
+use axum::{Json, extract::State};
+
+use super::AppState;
+use crate::models::ProbeStat;
+
+/// GET /api/probes — each target probed so far, in config order: latest, average, fastest and
+/// slowest round trip and loss over its window (`probes.window`). Empty without targets.
+pub(super) async fn api_probes_handler(State(state): State<AppState>) -> Json<Vec<ProbeStat>> {
+    Json(state.metrics.probes.current())
+}
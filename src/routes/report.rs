@@ -0,0 +1,78 @@
+// GET /api/report: the usage report for the last day, week or month, as JSON or markdown.
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use crate::reports::{ReportPeriod, generate_report, render_markdown};
+
+const MARKDOWN_CONTENT_TYPE: &str = "text/markdown; charset=utf-8";
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ReportQuery {
+    /// `day` (default), `week` or `month` (30 days), ending now.
+    pub period: Option<String>,
+    /// `json` (default) or `markdown`.
+    pub format: Option<String>,
+}
+
+/// GET /api/report?period=&format= — [`UsageReport`](crate::models::UsageReport) for the period
+/// ending now, or the same report rendered as the markdown posted to chat webhooks.
+pub(super) async fn api_report_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<ReportQuery>,
+) -> Response {
+    let bad_request = |error: String| ApiError::bad_request(error).into_response();
+    let period = match q.period.as_deref() {
+        None => ReportPeriod::Day,
+        Some(p) => match ReportPeriod::parse(p) {
+            Some(period) => period,
+            None => {
+                return bad_request(format!(
+                    "period must be one of {}",
+                    ReportPeriod::NAMES.join(", ")
+                ));
+            }
+        },
+    };
+    let markdown = match q.format.as_deref() {
+        None | Some("json") => false,
+        Some("markdown") => true,
+        Some(_) => return bad_request("format must be one of json, markdown".into()),
+    };
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+
+    let to = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let raw_retention_hours = state.config.database.raw_retention_hours;
+    match generate_report(
+        repo.as_ref(),
+        to - period.span_ms(),
+        to,
+        raw_retention_hours,
+    )
+    .await
+    {
+        Ok(report) if markdown => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, MARKDOWN_CONTENT_TYPE)],
+            render_markdown(&report),
+        )
+            .into_response(),
+        Ok(report) => (StatusCode::OK, axum::Json(report)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "generate_report failed");
+            ApiError::history(&e, "failed to generate report").into_response()
+        }
+    }
+}
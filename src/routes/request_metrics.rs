@@ -0,0 +1,176 @@
+// Per-route HTTP request counters and latency histograms (`/api/stats` `http`, `/metrics`
+// `homeserver_http_*`), filled by the `record_request` middleware.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+
+/// Upper bounds (ms) of the latency histogram buckets; slower requests land in one more, open
+/// bucket.
+const LATENCY_BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 10_000];
+
+/// Route label of requests no route matched (the 404 fallback), so unknown paths share a series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Quantiles reported per route.
+const QUANTILES: [f64; 3] = [0.5, 0.95, 0.99];
+
+#[derive(Debug, Default)]
+struct RouteEntry {
+    statuses: BTreeMap<u16, u64>,
+    /// Requests per [`LATENCY_BUCKETS_MS`] bucket, plus the open bucket past the last bound.
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    timed: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl RouteEntry {
+    /// Upper bound of the bucket holding the `q` quantile, capped at the slowest request.
+    fn quantile_ms(&self, q: f64) -> f64 {
+        let max_ms = self.max_us as f64 / 1000.0;
+        let rank = ((self.timed as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bound, count) in LATENCY_BUCKETS_MS.iter().zip(self.buckets) {
+            seen += count;
+            if seen >= rank {
+                return (*bound as f64).min(max_ms);
+            }
+        }
+        max_ms
+    }
+}
+
+/// Request counts and latencies per matched route; cheap to share behind an `Arc`.
+#[derive(Debug, Default)]
+pub struct HttpMetrics {
+    routes: Mutex<BTreeMap<String, RouteEntry>>,
+}
+
+/// One route in `/api/stats` `http`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteStats {
+    /// Route template, e.g. `/api/history`, or [`UNMATCHED_ROUTE`].
+    pub route: String,
+    pub requests_total: u64,
+    /// Responses per status code.
+    pub status_total: BTreeMap<u16, u64>,
+    /// Requests behind the latency figures (WebSocket upgrades are not timed).
+    pub timed_total: u64,
+    pub mean_ms: f64,
+    /// Estimated from the histogram: the bucket bound, at most `max_ms`.
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl HttpMetrics {
+    /// Count a response with `status` for `route`; `latency` is `None` for WebSocket upgrades,
+    /// whose connection outlives the request.
+    pub fn record(&self, route: &str, status: u16, latency: Option<Duration>) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = match routes.get_mut(route) {
+            Some(entry) => entry,
+            None => routes.entry(route.to_string()).or_default(),
+        };
+        *entry.statuses.entry(status).or_default() += 1;
+        let Some(latency) = latency else {
+            return;
+        };
+        let us = latency.as_micros().min(u128::from(u64::MAX)) as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| us <= bound * 1000)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        entry.buckets[bucket] += 1;
+        entry.timed += 1;
+        entry.sum_us = entry.sum_us.saturating_add(us);
+        entry.max_us = entry.max_us.max(us);
+    }
+
+    /// Every route seen so far, by route.
+    pub fn snapshot(&self) -> Vec<RouteStats> {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes
+            .iter()
+            .map(|(route, e)| RouteStats {
+                route: route.clone(),
+                requests_total: e.statuses.values().sum(),
+                status_total: e.statuses.clone(),
+                timed_total: e.timed,
+                mean_ms: if e.timed > 0 {
+                    e.sum_us as f64 / e.timed as f64 / 1000.0
+                } else {
+                    0.0
+                },
+                p50_ms: e.quantile_ms(QUANTILES[0]),
+                p95_ms: e.quantile_ms(QUANTILES[1]),
+                p99_ms: e.quantile_ms(QUANTILES[2]),
+                max_ms: e.max_us as f64 / 1000.0,
+            })
+            .collect()
+    }
+
+    /// `homeserver_http_requests_total{route,status}` and the
+    /// `homeserver_http_request_duration_seconds{route}` summary, in the Prometheus text format.
+    pub(crate) fn write_prometheus(&self, out: &mut String) {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let name = "homeserver_http_requests_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} HTTP responses per route and status.\n# TYPE {name} counter"
+        );
+        for (route, e) in routes.iter() {
+            for (status, count) in &e.statuses {
+                let _ = writeln!(
+                    out,
+                    "{name}{{route=\"{route}\",status=\"{status}\"}} {count}"
+                );
+            }
+        }
+        let name = "homeserver_http_request_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} HTTP request latency per route (WebSocket upgrades excluded).\n# TYPE {name} summary"
+        );
+        for (route, e) in routes.iter().filter(|(_, e)| e.timed > 0) {
+            for q in QUANTILES {
+                let seconds = e.quantile_ms(q) / 1000.0;
+                let _ = writeln!(
+                    out,
+                    "{name}{{route=\"{route}\",quantile=\"{q}\"}} {seconds}"
+                );
+            }
+            let sum = e.sum_us as f64 / 1_000_000.0;
+            let _ = writeln!(out, "{name}_sum{{route=\"{route}\"}} {sum}");
+            let _ = writeln!(out, "{name}_count{{route=\"{route}\"}} {}", e.timed);
+        }
+    }
+}
+
+/// Middleware: time the request and record it under its matched route.
+pub(super) async fn record_request(
+    State(metrics): State<Arc<HttpMetrics>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE.to_string(), |p| p.as_str().to_string());
+    let started = Instant::now();
+    let response = next.run(req).await;
+    let status = response.status();
+    let latency = (status != StatusCode::SWITCHING_PROTOCOLS).then(|| started.elapsed());
+    metrics.record(&route, status.as_u16(), latency);
+    response
+}
@@ -0,0 +1,12 @@
+// GET /api/sensors: the hwmon temperatures, fan speeds and voltages of the last worker tick.
+
+use axum::{Json, extract::State};
+
+use super::AppState;
+use crate::models::SensorStat;
+
+/// GET /api/sensors — every sensor read at the last tick, sorted by id. Empty before the first
+/// tick, with `monitoring.collect_sensors = false` and on hosts without hwmon.
+pub(super) async fn api_sensors_handler(State(state): State<AppState>) -> Json<Vec<SensorStat>> {
+    Json(state.metrics.sensors.current())
+}
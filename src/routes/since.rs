@@ -0,0 +1,97 @@
+// GET /api/history/since (incremental raw polling) and GET /api/history/sync (cursor-based
+// sync for clients that keep a local cache).
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use crate::history_repo::{DEFAULT_SYNC_LIMIT, MAX_SNAPSHOTS_SINCE};
+use crate::models::SyncCursor;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SinceQuery {
+    /// Exclusive lower bound (epoch ms): the `X-Next-Since` of the previous response.
+    pub ts: Option<i64>,
+    /// Max snapshots (default and cap: `MAX_SNAPSHOTS_SINCE`).
+    pub limit: Option<u32>,
+}
+
+/// GET /api/history/since?ts=&limit= — raw snapshots newer than `ts`, oldest first, for
+/// incremental polling. `X-Next-Since` carries the `ts` to send next (unchanged when empty).
+pub(super) async fn api_history_since_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<SinceQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let Some(since_ts) = q.ts else {
+        return ApiError::bad_request("ts is required").into_response();
+    };
+    let limit = q.limit.unwrap_or(MAX_SNAPSHOTS_SINCE);
+    match repo.get_snapshots_since(since_ts, limit).await {
+        Ok(mut snapshots) => {
+            for s in &mut snapshots {
+                s.apply_cpu_percent_mode(state.config.docker.cpu_percent_mode);
+            }
+            let next = snapshots.last().map_or(since_ts, |s| s.timestamp as i64);
+            (
+                axum::http::StatusCode::OK,
+                [("x-next-since", next.to_string())],
+                axum::Json(snapshots),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "get_snapshots_since failed");
+            ApiError::history(&e, "failed to load history").into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SyncQuery {
+    /// `nextCursor.sinceSeq` of the previous page; preferred over `since_ts`.
+    pub since_seq: Option<u64>,
+    /// `nextCursor.sinceTs` of the previous page, for clients without a seq.
+    pub since_ts: Option<u64>,
+    /// Page size (default `DEFAULT_SYNC_LIMIT`, clamped to 1..=`MAX_SNAPSHOTS_SINCE`).
+    pub limit: Option<u32>,
+}
+
+/// GET /api/history/sync?since_seq=&since_ts=&limit= — the local snapshots after the client's
+/// cursor with the `nextCursor` to send next. No cursor starts an initial sync. `resetRequired`
+/// tells the client its cursor predates retention: drop the cache, then apply this page.
+pub(super) async fn api_history_sync_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<SyncQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let cursor = SyncCursor {
+        since_seq: q.since_seq,
+        since_ts: q.since_ts,
+    };
+    match repo
+        .get_sync_batch(cursor, q.limit.unwrap_or(DEFAULT_SYNC_LIMIT))
+        .await
+    {
+        Ok(mut batch) => {
+            for s in &mut batch.snapshots {
+                s.apply_cpu_percent_mode(state.config.docker.cpu_percent_mode);
+            }
+            axum::Json(batch).into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "get_sync_batch failed");
+            ApiError::history(&e, "failed to load history").into_response()
+        }
+    }
+}
@@ -0,0 +1,114 @@
+// /api/stats and /metrics: service counters (history writer, aggregation, pruning, WebSockets),
+// tagged with `server.node_name`; /metrics also carries per-container pids gauges.
+
+use std::fmt::Write;
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use serde::Serialize;
+
+use super::AppState;
+use crate::metrics::ServiceStats;
+use crate::models::{ContainerStats, FullSystemSnapshot};
+
+/// Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Body of `GET /api/stats`: [`ServiceStats`] plus the instance's `nodeName`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsBody<'a> {
+    node_name: &'a str,
+    #[serde(flatten)]
+    stats: ServiceStats,
+}
+
+/// GET /api/stats — snapshots saved, open WebSocket connections and aggregation totals.
+pub(super) async fn api_stats_handler(State(state): State<AppState>) -> Response {
+    let body = StatsBody {
+        node_name: &state.config.server.node_name,
+        stats: state.metrics.stats(&state.ws_connections),
+    };
+    (StatusCode::OK, axum::Json(body)).into_response()
+}
+
+/// GET /metrics — the same counters for Prometheus scraping, plus the pids gauges of the
+/// containers in the latest snapshot.
+pub(super) async fn metrics_handler(State(state): State<AppState>) -> Response {
+    let mut text = state.metrics.prometheus_text(&state.ws_connections);
+    write_container_pids(
+        &mut text,
+        state.metrics.broadcast.latest_snapshot().as_deref(),
+    );
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        with_node_label(&text, &state.config.server.node_name),
+    )
+        .into_response()
+}
+
+/// Metric name, help text and value of one per-container gauge.
+type ContainerGauge = (&'static str, &'static str, fn(&ContainerStats) -> f64);
+
+/// `homeserver_container_pids`, `_pids_limit` and `_pids_usage_percent`, one sample per
+/// container of `snapshot` labelled `container="<name>"`.
+fn write_container_pids(out: &mut String, snapshot: Option<&FullSystemSnapshot>) {
+    let containers = snapshot.map_or(&[][..], |s| &s.containers);
+    let gauges: [ContainerGauge; 3] = [
+        (
+            "homeserver_container_pids",
+            "Processes and threads in the container.",
+            |c| c.pids as f64,
+        ),
+        (
+            "homeserver_container_pids_limit",
+            "The container's pids limit (0 = unlimited).",
+            |c| c.pids_limit as f64,
+        ),
+        (
+            "homeserver_container_pids_usage_percent",
+            "pids as a percent of the pids limit (0 when unlimited).",
+            |c| c.pids_usage_percent,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+        for c in containers {
+            let label = escape_label_value(&c.name);
+            let _ = writeln!(out, "{name}{{container=\"{label}\"}} {}", value(c));
+        }
+    }
+}
+
+/// `value` with `\`, `"` and newlines escaped for a Prometheus label.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `text` with `node="<node>"` added to every sample's labels (`node` is a valid node name, so
+/// it needs no escaping).
+fn with_node_label(text: &str, node: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.lines().count() * (node.len() + 9));
+    for line in text.lines() {
+        match line.find(['{', ' ']) {
+            _ if line.starts_with('#') => out.push_str(line),
+            Some(i) if line[i..].starts_with('{') => {
+                let _ = write!(out, "{}node=\"{node}\",{}", &line[..=i], &line[i + 1..]);
+            }
+            Some(i) => {
+                let _ = write!(out, "{}{{node=\"{node}\"}}{}", &line[..i], &line[i..]);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,51 @@
+// GET / — a small self-contained HTML status page (compiled in, no external assets), or a plain
+// text line with `server.status_page = false`.
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Response},
+};
+
+use super::AppState;
+use crate::version::{NAME, VERSION};
+
+const TEMPLATE: &str = include_str!("status_page.html");
+
+/// GET / — host identity, version and container count filled in server-side; CPU and RAM are
+/// polled from `/api/cpu` and `/api/ram` by the page's inline script.
+pub(super) async fn status_page_handler(State(state): State<AppState>) -> Response {
+    if !state.config.server.status_page {
+        return format!("{NAME} {VERSION}").into_response();
+    }
+    let info = state.system_info.get();
+    let containers = state
+        .metrics
+        .broadcast
+        .latest_snapshot()
+        .map_or_else(|| "–".to_string(), |s| s.containers.len().to_string());
+    let os = format!("{} {}", info.os_family, info.os_version);
+    let page = TEMPLATE
+        .replace("{{hostname}}", &escape(&info.system_model))
+        .replace("{{version}}", &escape(VERSION))
+        .replace("{{os}}", &escape(os.trim()))
+        .replace("{{hardware}}", &escape(&info.system_manufacturer))
+        .replace("{{processor}}", &escape(&info.processor_name))
+        .replace("{{containers}}", &containers);
+    Html(page).into_response()
+}
+
+/// Minimal HTML escaping for values placed in element text.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
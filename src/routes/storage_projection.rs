@@ -0,0 +1,74 @@
+// GET /api/storage/projection: per-mount growth trend and days until full.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use crate::history_repo::{DownsampleMode, partition_series, project_partition};
+
+const DEFAULT_PROJECTION_DAYS: u32 = 7;
+const MAX_PROJECTION_DAYS: u32 = 31;
+/// Hourly points: enough for a trend over days, and read from the aggregated tiers.
+const PROJECTION_RESOLUTION_SECS: u32 = 3600;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct StorageProjectionQuery {
+    /// Days of history the trend is fitted over, ending now (default 7, 1–31).
+    pub days: Option<u32>,
+}
+
+/// GET /api/storage/projection?days= — `[{mount, usedSpace, totalSpace, bytesPerDay,
+/// daysUntilFull}]` per mount seen in the window, by mount point. `bytesPerDay` is the
+/// least-squares slope of used space over hourly history; `daysUntilFull` is `null` when the
+/// trend is flat or shrinking.
+pub(super) async fn api_storage_projection_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<StorageProjectionQuery>,
+) -> Response {
+    let days = q.days.unwrap_or(DEFAULT_PROJECTION_DAYS);
+    if !(1..=MAX_PROJECTION_DAYS).contains(&days) {
+        let error = format!("days must be between 1 and {MAX_PROJECTION_DAYS}");
+        return ApiError::bad_request(error).into_response();
+    }
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let from_ts = now_ms - i64::from(days) * 86_400_000;
+    let raw_retention_hours = state.config.database.raw_retention_hours;
+    let result = async {
+        let raw_cutoff_ts = repo.history_raw_cutoff(now_ms, raw_retention_hours).await?;
+        repo.get_history_points(
+            from_ts,
+            now_ms,
+            PROJECTION_RESOLUTION_SECS,
+            raw_cutoff_ts,
+            DownsampleMode::Average,
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(points) => {
+            let projections: Vec<_> = partition_series(&points)
+                .into_iter()
+                .filter_map(|(mount, samples)| project_partition(mount, &samples))
+                .collect();
+            (StatusCode::OK, axum::Json(projections)).into_response()
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "get_history_points failed");
+            ApiError::history(&e, "failed to load history").into_response()
+        }
+    }
+}
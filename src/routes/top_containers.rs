@@ -0,0 +1,72 @@
+// GET /api/history/top-containers: containers ranked by average CPU, memory or network rate.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use super::history_window::HistoryWindow;
+use crate::history_repo::TopContainerMetric;
+
+const DEFAULT_TOP_LIMIT: u32 = 10;
+const MAX_TOP_LIMIT: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct TopContainersQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// `cpu` (default), `memory`, `rx` or `tx`.
+    pub metric: Option<String>,
+    /// Entries returned (default 10, capped at 100).
+    pub limit: Option<u32>,
+}
+
+/// GET /api/history/top-containers?from=&to=&metric=&limit= — `{containerId, name, average, max,
+/// samples}` per container, highest average first, from `container_history`. `from`/`to` default
+/// and span rules match /api/history.
+pub(super) async fn api_history_top_containers_handler(
+    State(state): State<AppState>,
+    ApiQuery(q): ApiQuery<TopContainersQuery>,
+) -> Response {
+    let repo = match state.history() {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let metric = match q.metric.as_deref() {
+        None => TopContainerMetric::Cpu,
+        Some(m) => match TopContainerMetric::parse(m) {
+            Some(metric) => metric,
+            None => {
+                let error = format!(
+                    "metric must be one of {}",
+                    TopContainerMetric::NAMES.join(", ")
+                );
+                return ApiError::bad_request(error).into_response();
+            }
+        },
+    };
+    // Not a series: the coarsest resolution keeps the point estimate from limiting the span.
+    let HistoryWindow { from_ts, to_ts, .. } = match HistoryWindow::parse(
+        q.from,
+        q.to,
+        Some("1d"),
+        state.config.database.max_history_points,
+        false,
+    ) {
+        Ok(window) => window,
+        Err(e) => return e.into_response(),
+    };
+    let limit = q.limit.unwrap_or(DEFAULT_TOP_LIMIT).clamp(1, MAX_TOP_LIMIT);
+
+    match repo.get_top_containers(from_ts, to_ts, metric, limit).await {
+        Ok(top) => (StatusCode::OK, axum::Json(top)).into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "get_top_containers failed");
+            ApiError::history(&e, "failed to load top containers").into_response()
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Wake-on-LAN relay: POST /api/wol sends a magic packet to a machine on the LAN, GET
+// /api/wol/targets lists the named ones from `[[server.wol_targets]]`. Both are off unless
+// `server.enable_wol` is set.
+
+use std::net::Ipv4Addr;
+
+use axum::{
+    Json,
+    extract::{State, rejection::JsonRejection},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::AppState;
+use super::api_error::ApiError;
+use super::config::admin_rejection;
+use crate::net_tools::{self, MacAddr, WOL_PORT};
+
+/// Body of POST /api/wol: `mac` or the name of a configured `target`.
+#[derive(Debug, Deserialize)]
+pub(super) struct WolRequest {
+    mac: Option<String>,
+    target: Option<String>,
+    /// Overrides the target's `broadcast` and the primary interface's broadcast address.
+    broadcast: Option<Ipv4Addr>,
+}
+
+/// 404 while `server.enable_wol` is off.
+fn disabled_rejection(state: &AppState) -> Option<Response> {
+    (!state.config.server.enable_wol).then(|| {
+        ApiError::not_found("wake-on-lan is disabled; set server.enable_wol").into_response()
+    })
+}
+
+/// The broadcast address of the primary interface and its local address to send from; the
+/// limited broadcast from any address when the host has no primary IPv4.
+fn primary_broadcast(state: &AppState) -> (Ipv4Addr, Option<Ipv4Addr>) {
+    let info = state.system_info.get();
+    match info.primary_ipv4.as_deref().and_then(|ip| ip.parse().ok()) {
+        Some(addr) => {
+            let prefix_len = state.sysinfo_repo.ipv4_prefix_len(addr).unwrap_or(32);
+            (net_tools::directed_broadcast(addr, prefix_len), Some(addr))
+        }
+        None => (Ipv4Addr::BROADCAST, None),
+    }
+}
+
+/// POST /api/wol (`Authorization: Bearer <server.admin_token>`) — `{"mac": "AA:BB:CC:DD:EE:FF",
+/// "broadcast": "192.168.1.255"}` or `{"target": "desktop"}`; 200 `{mac, broadcast, port}` once
+/// the packet is sent. 400 for a malformed body or MAC, 404 for an unknown target.
+pub(super) async fn api_wol_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<WolRequest>, JsonRejection>,
+) -> Response {
+    if let Some(response) = disabled_rejection(&state) {
+        return response;
+    }
+    if let Some(response) = admin_rejection(&state, &headers) {
+        return response;
+    }
+    let Json(request) = match body {
+        Ok(body) => body,
+        Err(rejection) => return ApiError::from(rejection).into_response(),
+    };
+    let (mac, target_broadcast) = match (&request.mac, &request.target) {
+        (Some(mac), None) => (mac.as_str(), None),
+        (None, Some(name)) => {
+            let targets = &state.config.server.wol_targets;
+            match targets.iter().find(|t| &t.name == name) {
+                Some(target) => (target.mac.as_str(), target.broadcast),
+                None => {
+                    return ApiError::not_found(format!("no wake-on-lan target named '{name}'"))
+                        .into_response();
+                }
+            }
+        }
+        _ => return ApiError::bad_request("send exactly one of mac or target").into_response(),
+    };
+    let mac: MacAddr = match mac.parse() {
+        Ok(mac) => mac,
+        Err(e) => return ApiError::bad_request(e.to_string()).into_response(),
+    };
+    let (broadcast, source) = match request.broadcast.or(target_broadcast) {
+        Some(broadcast) => (broadcast, None),
+        None => primary_broadcast(&state),
+    };
+    if let Err(e) = net_tools::send_magic_packet(mac, broadcast, source).await {
+        tracing::warn!(error = %e, %mac, %broadcast, "wake-on-lan send failed");
+        return ApiError::internal("failed to send the magic packet").into_response();
+    }
+    tracing::info!(%mac, %broadcast, "wake-on-lan packet sent");
+    Json(serde_json::json!({
+        "mac": mac.to_string(),
+        "broadcast": broadcast,
+        "port": WOL_PORT,
+    }))
+    .into_response()
+}
+
+/// GET /api/wol/targets — the configured `[[server.wol_targets]]` (`name`, `mac`, `broadcast`);
+/// needs the admin token when one is set, like GET /api/config.
+pub(super) async fn api_wol_targets_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = disabled_rejection(&state) {
+        return response;
+    }
+    if state.config.server.admin_token.is_some()
+        && let Some(response) = admin_rejection(&state, &headers)
+    {
+        return response;
+    }
+    Json(&state.config.server.wol_targets).into_response()
+}
@@ -0,0 +1,52 @@
+// /api/worker: pause and resume collection without restarting the service.
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tokio::time::Duration;
+
+use super::AppState;
+use super::api_error::{ApiError, ApiQuery};
+use super::config::admin_rejection;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct PauseQuery {
+    /// Resume automatically after this many seconds (default: stay paused until resumed).
+    duration_secs: Option<u64>,
+}
+
+/// POST /api/worker/pause?duration_secs= — stop collecting (the tick keeps firing but nothing is
+/// sampled, broadcast or stored) until resumed or `duration_secs` have passed. Admin token.
+pub(super) async fn api_worker_pause_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ApiQuery(q): ApiQuery<PauseQuery>,
+) -> Response {
+    if let Some(rejection) = admin_rejection(&state, &headers) {
+        return rejection;
+    }
+    if q.duration_secs == Some(0) {
+        return ApiError::bad_request("duration_secs must be positive").into_response();
+    }
+    let pause = &state.metrics.pause;
+    pause.pause(q.duration_secs.map(Duration::from_secs));
+    tracing::info!(duration_secs = q.duration_secs, "collection paused");
+    (StatusCode::OK, axum::Json(pause.status())).into_response()
+}
+
+/// POST /api/worker/resume — collect again from the next tick. Admin token.
+pub(super) async fn api_worker_resume_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(rejection) = admin_rejection(&state, &headers) {
+        return rejection;
+    }
+    let pause = &state.metrics.pause;
+    pause.resume();
+    tracing::info!("collection resumed");
+    (StatusCode::OK, axum::Json(pause.status())).into_response()
+}
@@ -0,0 +1,106 @@
+// WebSocket handlers and stream logic.
+//
+// Uses `yawc` for the WebSocket transport so connections negotiate permessage-deflate
+// (RFC 7692) compression. The socket is split into a sink + stream: the sink sends
+// stats/pings, while the stream is polled so client Close frames terminate the loop
+// promptly (and pongs are drained). At shutdown every handler sends a Close frame (1001,
+// going away) and waits briefly for the client's reply, so clients reconnect instead of waiting
+// for a dead socket.
+//
+// Every route runs `validate_upgrade` before upgrading, so a refused client gets a status and a
+// JSON `{"error": ...}` body instead of a bare connection failure.
+//
+// Upgrade checks are in `validate`, the `/ws/cpu` and `/ws/ram` pumps in `periodic` and the
+// snapshot stream in `system`; the transport helpers they share are here.
+
+mod periodic;
+mod system;
+mod validate;
+
+pub(super) use periodic::{ws_cpu, ws_ram};
+pub(super) use system::ws_system;
+
+use axum::response::{IntoResponse, Response};
+use futures_util::{SinkExt, StreamExt};
+use tokio::time::{Duration, timeout};
+use yawc::frame::{Frame, OpCode};
+use yawc::{IncomingUpgrade, Options};
+
+use super::api_error::ApiError;
+
+const WS_PING_INTERVAL: Duration = Duration::from_secs(30);
+const WS_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a shutdown Close waits for the client's Close reply before the socket is dropped.
+const WS_CLOSE_REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+/// `Retry-After` (seconds) of the 503 answered at `publishing.max_ws_connections`.
+const WS_RETRY_AFTER_SECS: u64 = 5;
+
+/// WebSocket options: balanced permessage-deflate compression, always on (clients negotiate).
+fn ws_options() -> Options {
+    Options::default().with_balanced_compression()
+}
+
+/// Send a frame under the standard timeout. Returns false if it timed out or errored.
+async fn send_frame<S>(sink: &mut S, frame: Frame) -> bool
+where
+    S: futures_util::Sink<Frame> + Unpin,
+{
+    matches!(timeout(WS_SEND_TIMEOUT, sink.send(frame)).await, Ok(Ok(())))
+}
+
+/// Close frame sent to every client when the server shuts down.
+fn going_away() -> Frame {
+    Frame::close(yawc::close::CloseCode::Away, "server shutting down")
+}
+
+/// Send [`going_away`] and wait briefly for the client's Close reply. Dropping the socket with
+/// the reply (or a pong) still unread makes the kernel reset the connection, and the client may
+/// then lose the Close frame.
+async fn close_going_away<Si, St>(sink: &mut Si, stream: &mut St)
+where
+    Si: futures_util::Sink<Frame> + Unpin,
+    St: futures_util::Stream<Item = Frame> + Unpin,
+{
+    if !send_frame(sink, going_away()).await {
+        return;
+    }
+    let _ = timeout(WS_CLOSE_REPLY_TIMEOUT, async {
+        while !is_close(&stream.next().await) {}
+    })
+    .await;
+}
+
+/// True if an inbound stream item means the connection should close (peer closed / stream ended).
+fn is_close(incoming: &Option<Frame>) -> bool {
+    match incoming {
+        None => true,
+        Some(frame) => frame.opcode() == OpCode::Close,
+    }
+}
+
+/// Completes the upgrade and spawns `run` with the established WebSocket. Returns the HTTP
+/// upgrade response (or a JSON 400 if the handshake request is malformed). `_repo` etc. are
+/// captured by the `run` closure.
+fn upgrade<F, Fut>(ws: IncomingUpgrade, stream: &'static str, run: F) -> Response
+where
+    F: FnOnce(yawc::HttpWebSocket) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let (response, fut) = match ws.upgrade(ws_options()) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::info!(error = %e, stream, "WebSocket upgrade rejected");
+            return ApiError::bad_request("invalid WebSocket upgrade request").into_response();
+        }
+    };
+    tokio::spawn(async move {
+        match fut.await {
+            Ok(socket) => {
+                tracing::info!(stream, "WebSocket client connected");
+                run(socket).await;
+            }
+            Err(e) => tracing::info!(error = %e, stream, "WebSocket handshake failed"),
+        }
+    });
+    response.into_response()
+}
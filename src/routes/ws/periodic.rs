@@ -0,0 +1,131 @@
+// `/ws/cpu` and `/ws/ram`: the current CPU / RAM stats every `interval_ms`.
+
+use axum::{
+    extract::{Query, State, rejection::QueryRejection},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use yawc::IncomingUpgrade;
+use yawc::frame::Frame;
+
+use super::validate::{WsQuery, validate_upgrade};
+use super::{WS_PING_INTERVAL, close_going_away, is_close, send_frame, upgrade};
+use crate::routes::AppState;
+use crate::ws_connections::WsChannel;
+
+pub(in crate::routes) async fn ws_cpu(
+    ws: IncomingUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WsQuery>, QueryRejection>,
+) -> Response {
+    let params = match validate_upgrade(&state, WsChannel::Cpu, query, &headers) {
+        Ok(params) => params,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let repo = state.sysinfo_repo.clone();
+    let interval_ms = params
+        .interval_ms
+        .unwrap_or(state.config.publishing.cpu_stats_frequency_ms);
+    let connections = state.ws_connections.clone();
+    upgrade(ws, "cpu", move |socket| async move {
+        let (_guard, _) = connections.connect(WsChannel::Cpu);
+        let (sink, stream) = socket.split();
+        pump_periodic(
+            sink,
+            stream,
+            interval_ms,
+            connections.closing(),
+            move || {
+                let repo = repo.clone();
+                async move { repo.get_cpu_stats().await }
+            },
+        )
+        .await;
+    })
+}
+
+pub(in crate::routes) async fn ws_ram(
+    ws: IncomingUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WsQuery>, QueryRejection>,
+) -> Response {
+    let params = match validate_upgrade(&state, WsChannel::Ram, query, &headers) {
+        Ok(params) => params,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let repo = state.sysinfo_repo.clone();
+    let interval_ms = params
+        .interval_ms
+        .unwrap_or(state.config.publishing.ram_stats_frequency_ms);
+    let connections = state.ws_connections.clone();
+    upgrade(ws, "ram", move |socket| async move {
+        let (_guard, _) = connections.connect(WsChannel::Ram);
+        let (sink, stream) = socket.split();
+        pump_periodic(
+            sink,
+            stream,
+            interval_ms,
+            connections.closing(),
+            move || {
+                let repo = repo.clone();
+                async move { repo.get_ram_stats().await }
+            },
+        )
+        .await;
+    })
+}
+
+/// Periodically fetch a serializable stat and push it as a text frame; ping on `WS_PING_INTERVAL`;
+/// stop when the peer closes, a send times out, fetching fails or `closing` is cancelled.
+async fn pump_periodic<Si, St, F, Fut, T>(
+    mut sink: Si,
+    mut stream: St,
+    interval_ms: u64,
+    closing: CancellationToken,
+    fetch: F,
+) where
+    Si: futures_util::Sink<Frame> + Unpin,
+    St: futures_util::Stream<Item = Frame> + Unpin,
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+    T: serde::Serialize,
+{
+    let mut tick = tokio::time::interval(Duration::from_millis(interval_ms));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut ping = tokio::time::interval(WS_PING_INTERVAL);
+    ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                let stats = match fetch().await {
+                    Ok(s) => s,
+                    Err(e) => { tracing::info!(error = %e, "WebSocket stat fetch failed"); break; }
+                };
+                let Ok(json) = serde_json::to_string(&stats) else { break };
+                if !send_frame(&mut sink, Frame::text(json)).await {
+                    break;
+                }
+            }
+            _ = ping.tick() => {
+                if !send_frame(&mut sink, Frame::ping(Bytes::new())).await {
+                    break;
+                }
+            }
+            _ = closing.cancelled() => {
+                close_going_away(&mut sink, &mut stream).await;
+                break;
+            }
+            incoming = stream.next() => {
+                if is_close(&incoming) {
+                    break;
+                }
+            }
+        }
+    }
+}
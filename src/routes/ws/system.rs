@@ -0,0 +1,170 @@
+// `/ws/system`: system info, then every broadcast snapshot, with pause heartbeats.
+
+use axum::{
+    extract::{Query, State, rejection::QueryRejection},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use yawc::IncomingUpgrade;
+use yawc::frame::Frame;
+
+use super::validate::{WsQuery, validate_upgrade};
+use super::{WS_PING_INTERVAL, close_going_away, is_close, send_frame, upgrade};
+use crate::collection_pause::CollectionPause;
+use crate::config::CpuPercentMode;
+use crate::models::{FullSystemSnapshot, SystemInfo};
+use crate::routes::AppState;
+use crate::system_info_refresh::SharedSystemInfo;
+use crate::version;
+use crate::worker::BroadcastMetrics;
+use crate::ws_connections::{WsChannel, WsConnections};
+
+pub(in crate::routes) async fn ws_system(
+    ws: IncomingUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WsQuery>, QueryRejection>,
+) -> Response {
+    if let Err(rejection) = validate_upgrade(&state, WsChannel::System, query, &headers) {
+        return rejection.into_response();
+    }
+    let tx = state.stats_tx.clone();
+    let connections = state.ws_connections.clone();
+    let system_info = state.system_info.clone();
+    let pause = state.metrics.pause.clone();
+    let cpu_percent_mode = state.config.docker.cpu_percent_mode;
+    let lag = LagAccounting {
+        metrics: state.metrics.broadcast.clone(),
+        warn_per_minute: state.config.publishing.lag_warn_per_minute,
+    };
+    upgrade(ws, "system", move |socket| async move {
+        let mut rx = tx.subscribe();
+        stream_system(
+            socket,
+            &mut rx,
+            connections,
+            system_info,
+            pause,
+            cpu_percent_mode,
+            lag,
+        )
+        .await;
+    })
+}
+
+/// Where `/ws/system` counts clients falling behind the broadcast.
+struct LagAccounting {
+    metrics: Arc<BroadcastMetrics>,
+    /// `publishing.lag_warn_per_minute`.
+    warn_per_minute: u64,
+}
+
+/// `/ws/system`: send a welcome with static system info, then re-broadcast every snapshot; a
+/// refreshed system info is sent again as another `info` message. A `heartbeat` goes out with
+/// every ping and whenever collection is paused or resumed (no snapshots flow while paused).
+/// Container CPU goes out on the `cpu_percent_mode` scale.
+async fn stream_system<Ws>(
+    socket: Ws,
+    rx: &mut broadcast::Receiver<FullSystemSnapshot>,
+    connections: Arc<WsConnections>,
+    system_info: SharedSystemInfo,
+    pause: Arc<CollectionPause>,
+    cpu_percent_mode: CpuPercentMode,
+    lag: LagAccounting,
+) where
+    Ws: futures_util::Sink<Frame> + futures_util::Stream<Item = Frame> + Unpin,
+{
+    let (_guard, current) = connections.connect(WsChannel::System);
+    let closing = connections.closing();
+    tracing::info!(
+        connections = current,
+        stream = "system",
+        "System stream subscribed"
+    );
+
+    let (mut sink, mut stream) = socket.split();
+
+    let mut info_rx = system_info.subscribe();
+    let welcome = info_rx.borrow_and_update().clone();
+    if !send_frame(&mut sink, info_frame(&welcome)).await {
+        return;
+    }
+
+    let mut pause_rx = pause.subscribe();
+    let mut ping = tokio::time::interval(WS_PING_INTERVAL);
+    ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            result = rx.recv() => {
+                match result {
+                    Ok(mut snapshot) => {
+                        snapshot.apply_cpu_percent_mode(cpu_percent_mode);
+                        let Ok(json) = serde_json::to_string(&snapshot) else { break };
+                        if !send_frame(&mut sink, Frame::text(json)).await {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(messages_skipped = n, stream = "system", "WebSocket client lagged");
+                        if let Some(events) = lag.metrics.record_lag(n, lag.warn_per_minute) {
+                            tracing::warn!(
+                                lag_events = events,
+                                lag_warn_per_minute = lag.warn_per_minute,
+                                stream = "system",
+                                "WebSocket clients keep lagging behind the snapshot broadcast; \
+                                 raise publishing.broadcast_capacity or check client bandwidth"
+                            );
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            Ok(()) = info_rx.changed() => {
+                let info = info_rx.borrow_and_update().clone();
+                if !send_frame(&mut sink, info_frame(&info)).await {
+                    break;
+                }
+            }
+            Ok(()) = pause_rx.changed() => {
+                if !send_frame(&mut sink, heartbeat_frame(&pause)).await {
+                    break;
+                }
+            }
+            _ = ping.tick() => {
+                if !send_frame(&mut sink, Frame::ping(Bytes::new())).await
+                    || !send_frame(&mut sink, heartbeat_frame(&pause)).await
+                {
+                    break;
+                }
+            }
+            _ = closing.cancelled() => {
+                close_going_away(&mut sink, &mut stream).await;
+                break;
+            }
+            incoming = stream.next() => {
+                if is_close(&incoming) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// `{"type": "info", "systemInfo": ...}`: the welcome, and the message after a refresh.
+fn info_frame(info: &SystemInfo) -> Frame {
+    let message = serde_json::json!({ "type": "info", "systemInfo": info });
+    Frame::text(message.to_string())
+}
+
+/// `{"type": "heartbeat", "paused": ..., "resumesInSecs": ..., "serviceUptimeSecs": ...}`; the
+/// uptime lets a client notice a restart between two heartbeats.
+fn heartbeat_frame(pause: &CollectionPause) -> Frame {
+    let mut message = serde_json::json!(pause.status());
+    message["type"] = "heartbeat".into();
+    message["serviceUptimeSecs"] = version::service_uptime_secs().into();
+    Frame::text(message.to_string())
+}
@@ -0,0 +1,132 @@
+// Pre-upgrade checks of the WebSocket routes: `server.ws_token`, the query parameters and
+// `publishing.max_ws_connections`.
+
+use axum::{
+    extract::{Query, rejection::QueryRejection},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use super::WS_RETRY_AFTER_SECS;
+use crate::routes::AppState;
+use crate::routes::api_error::ApiError;
+use crate::routes::config::{bearer_token, constant_time_eq};
+use crate::ws_connections::WsChannel;
+
+/// Bounds of the `interval_ms` query parameter of `/ws/cpu` and `/ws/ram`.
+const MIN_INTERVAL_MS: u64 = 100;
+const MAX_INTERVAL_MS: u64 = 60_000;
+
+/// Query parameters of the WebSocket routes; kept as strings so a bad value gets our own 400.
+#[derive(Debug, Default, Deserialize)]
+pub(in crate::routes) struct WsQuery {
+    /// `/ws/cpu`, `/ws/ram`: push interval instead of `publishing.{cpu,ram}_stats_frequency_ms`.
+    interval_ms: Option<String>,
+    /// `server.ws_token`, for clients (browsers) that cannot set `Authorization` on an upgrade.
+    token: Option<String>,
+}
+
+/// What a WebSocket request asked for, once [`validate_upgrade`] accepted it.
+#[derive(Debug)]
+pub(super) struct ValidatedParams {
+    /// The `interval_ms` override; always `None` on `/ws/system`.
+    pub(super) interval_ms: Option<u64>,
+}
+
+/// Why a WebSocket upgrade was refused before the handshake.
+#[derive(Debug)]
+pub(super) enum WsRejection {
+    /// `server.ws_token` set and not presented: 401.
+    Unauthorized,
+    /// Malformed or out-of-range query parameters: 400.
+    BadRequest(String),
+    /// `publishing.max_ws_connections` reached: 503 with `Retry-After`.
+    TooManyConnections,
+}
+
+impl IntoResponse for WsRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Unauthorized => {
+                let mut response =
+                    ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+                response.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    header::HeaderValue::from_static("Bearer"),
+                );
+                response
+            }
+            Self::BadRequest(message) => ApiError::bad_request(message).into_response(),
+            Self::TooManyConnections => (
+                [(header::RETRY_AFTER, WS_RETRY_AFTER_SECS.to_string())],
+                ApiError::unavailable("too many connections")
+                    .with_details(serde_json::json!({ "retryAfterSecs": WS_RETRY_AFTER_SECS })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Pre-upgrade checks shared by the WebSocket routes, in order: the `server.ws_token`, the
+/// query parameters, then `publishing.max_ws_connections`. Answer a rejection with
+/// `into_response`.
+pub(super) fn validate_upgrade(
+    state: &AppState,
+    channel: WsChannel,
+    query: Result<Query<WsQuery>, QueryRejection>,
+    headers: &HeaderMap,
+) -> Result<ValidatedParams, WsRejection> {
+    let stream = channel_name(channel);
+    let result = check_upgrade(state, channel, query, headers);
+    if let Err(rejection) = &result {
+        tracing::info!(?rejection, stream, "WebSocket upgrade rejected");
+    }
+    result
+}
+
+fn check_upgrade(
+    state: &AppState,
+    channel: WsChannel,
+    query: Result<Query<WsQuery>, QueryRejection>,
+    headers: &HeaderMap,
+) -> Result<ValidatedParams, WsRejection> {
+    let Query(query) = query.map_err(|e| WsRejection::BadRequest(e.body_text()))?;
+    if let Some(token) = &state.config.server.ws_token {
+        let presented = query.token.as_deref().or_else(|| bearer_token(headers));
+        if !presented.is_some_and(|p| constant_time_eq(p.as_bytes(), token.expose().as_bytes())) {
+            return Err(WsRejection::Unauthorized);
+        }
+    }
+    let interval_ms = match (query.interval_ms.as_deref(), channel) {
+        (None, _) => None,
+        (Some(_), WsChannel::System) => {
+            return Err(WsRejection::BadRequest(
+                "interval_ms is not supported on /ws/system (it follows the sample interval)"
+                    .into(),
+            ));
+        }
+        (Some(raw), _) => match raw.parse::<u64>() {
+            Ok(ms) if (MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&ms) => Some(ms),
+            _ => {
+                return Err(WsRejection::BadRequest(format!(
+                    "interval_ms must be an integer between {MIN_INTERVAL_MS} and {MAX_INTERVAL_MS}"
+                )));
+            }
+        },
+    };
+    if let Some(max) = state.config.publishing.max_ws_connections
+        && state.ws_connections.total() >= max
+    {
+        return Err(WsRejection::TooManyConnections);
+    }
+    Ok(ValidatedParams { interval_ms })
+}
+
+pub(super) fn channel_name(channel: WsChannel) -> &'static str {
+    match channel {
+        WsChannel::System => "system",
+        WsChannel::Cpu => "cpu",
+        WsChannel::Ram => "ram",
+    }
+}
@@ -0,0 +1,161 @@
+// hwmon discovery and reads under a sysfs root (`/sys` in production, a fixture tree in tests).
+//
+// Pure parse_* / chip_id helpers are split from file I/O so they are testable without hardware.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{SensorKind, SensorStat};
+
+/// One sensor found by [`discover`]: where to read it and what to call it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorSource {
+    pub id: String,
+    pub name: String,
+    pub label: String,
+    pub kind: SensorKind,
+    /// The `*_input` file.
+    pub input: PathBuf,
+}
+
+impl SensorSource {
+    /// The current reading; `None` when the file is unreadable (some drivers answer EIO or
+    /// ENODATA while a device sleeps) or not a number.
+    pub fn read(&self) -> Option<SensorStat> {
+        let content = fs::read_to_string(&self.input).ok()?;
+        Some(SensorStat {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            label: self.label.clone(),
+            kind: self.kind,
+            value: parse_sensor_value(self.kind, &content)?,
+            unit: self.kind.unit().into(),
+            max: None,
+        })
+    }
+}
+
+/// Split an hwmon file name such as `temp3_input` into its kind and prefix (`temp3`).
+pub fn parse_input_file_name(file_name: &str) -> Option<(SensorKind, &str)> {
+    let prefix = file_name.strip_suffix("_input")?;
+    let (kind, index) = if let Some(index) = prefix.strip_prefix("temp") {
+        (SensorKind::Temp, index)
+    } else if let Some(index) = prefix.strip_prefix("fan") {
+        (SensorKind::Fan, index)
+    } else if let Some(index) = prefix.strip_prefix("in") {
+        (SensorKind::Voltage, index)
+    } else {
+        return None;
+    };
+    (!index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())).then_some((kind, prefix))
+}
+
+/// Millidegrees → °C, RPM as is, millivolts → V.
+pub fn parse_sensor_value(kind: SensorKind, content: &str) -> Option<f64> {
+    let raw: i64 = content.trim().parse().ok()?;
+    Some(match kind {
+        SensorKind::Temp | SensorKind::Voltage => raw as f64 / 1000.0,
+        SensorKind::Fan => raw as f64,
+    })
+}
+
+/// `<name>-<device>`, or just `<name>` for a chip without a device (`acpitz`). The device is the
+/// bus address (`0000:01:00.0`, `coretemp.0`), which survives reboots, unlike `hwmon<N>`.
+pub fn chip_id(name: &str, device: Option<&str>) -> String {
+    match device {
+        Some(device) => format!("{name}-{device}"),
+        None => name.to_string(),
+    }
+}
+
+/// Every temperature, fan and voltage input under `root/class/hwmon` and
+/// `root/class/nvme/*/hwmon*`, sorted by id. A chip listed under both is read once.
+pub fn discover(root: &Path) -> Vec<SensorSource> {
+    let mut dirs: Vec<PathBuf> = list_dir(&root.join("class/hwmon"));
+    for nvme in list_dir(&root.join("class/nvme")) {
+        dirs.extend(list_dir(&nvme).into_iter().filter(|dir| {
+            dir.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("hwmon"))
+        }));
+    }
+    let mut seen = HashSet::new();
+    let mut ids = HashSet::new();
+    let mut out = Vec::new();
+    for dir in dirs {
+        let Ok(dir) = fs::canonicalize(&dir) else {
+            continue;
+        };
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        let Some(name) = read_trimmed(&dir.join("name")) else {
+            continue;
+        };
+        let chip = chip_id(&name, device_key(&dir).as_deref());
+        let mut inputs: Vec<(SensorKind, String)> = list_dir(&dir)
+            .iter()
+            .filter_map(|path| {
+                let file_name = path.file_name()?.to_str()?;
+                let (kind, prefix) = parse_input_file_name(file_name)?;
+                Some((kind, prefix.to_string()))
+            })
+            .collect();
+        inputs.sort_by(|a, b| natural_key(&a.1).cmp(&natural_key(&b.1)));
+        for (kind, prefix) in inputs {
+            let label =
+                read_trimmed(&dir.join(format!("{prefix}_label"))).unwrap_or(prefix.clone());
+            // Two inputs with one label on a chip: the later one is told apart by its prefix.
+            let mut id = format!("{chip}/{label}");
+            if !ids.insert(id.clone()) {
+                id = format!("{chip}/{prefix}");
+                ids.insert(id.clone());
+            }
+            out.push(SensorSource {
+                id,
+                name: name.clone(),
+                label,
+                kind,
+                input: dir.join(format!("{prefix}_input")),
+            });
+        }
+    }
+    out.sort_by(|a, b| a.id.cmp(&b.id));
+    out
+}
+
+/// The bus device an hwmon directory belongs to. A class device in between (the NVMe
+/// controller `nvme0`, numbered at boot like `hwmon<N>`) is followed to its own device.
+fn device_key(hwmon_dir: &Path) -> Option<String> {
+    let device = fs::canonicalize(hwmon_dir.join("device")).ok()?;
+    let device = fs::canonicalize(device.join("device"))
+        .ok()
+        .filter(|parent| parent.is_dir())
+        .unwrap_or(device);
+    Some(device.file_name()?.to_str()?.to_string())
+}
+
+/// `temp2` before `temp10`.
+fn natural_key(prefix: &str) -> (&str, u32) {
+    let split = prefix
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(prefix.len());
+    let (kind, index) = prefix.split_at(split);
+    (kind, index.parse().unwrap_or(0))
+}
+
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    paths
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let trimmed = content.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
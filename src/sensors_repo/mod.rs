@@ -0,0 +1,80 @@
+// Hardware sensors (temperatures, fans, voltages) from hwmon: `/sys/class/hwmon` plus the NVMe
+// controllers' `hwmon*`. The sensor list is discovered on the first read and again every
+// `monitoring.sensor_rescan_secs` (drives and modules come and go); each worker tick reads the
+// values. collect() does blocking sysfs reads: call it from the blocking pool.
+
+mod hwmon;
+
+pub use hwmon::{SensorSource, chip_id, discover, parse_input_file_name, parse_sensor_value};
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::SensorStat;
+
+const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Discovered sensors plus the latest readings, served on `/api/sensors`.
+#[derive(Debug)]
+pub struct SensorsRepo {
+    root: PathBuf,
+    rescan_interval: Duration,
+    /// When the list was last discovered, and the list.
+    sources: Mutex<Option<(Instant, Vec<SensorSource>)>>,
+    latest: Mutex<Vec<SensorStat>>,
+}
+
+impl SensorsRepo {
+    /// Sensors under `/sys`, rediscovered every `rescan_interval`.
+    pub fn new(rescan_interval: Duration) -> Self {
+        Self::with_root("/sys", rescan_interval)
+    }
+
+    /// Sensors under another sysfs root (a fixture tree in tests).
+    pub fn with_root(root: impl Into<PathBuf>, rescan_interval: Duration) -> Self {
+        Self {
+            root: root.into(),
+            rescan_interval,
+            sources: Mutex::new(None),
+            latest: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Read every sensor, rediscovering them first when the list is older than the rescan
+    /// interval. Never errors; an unreadable sensor is left out of this reading.
+    pub fn collect(&self) -> Vec<SensorStat> {
+        let readings: Vec<SensorStat> = {
+            let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+            if sources
+                .as_ref()
+                .is_none_or(|(at, _)| at.elapsed() >= self.rescan_interval)
+            {
+                let found = discover(&self.root);
+                let before = sources.as_ref().map(|(_, list)| list.len());
+                if before != Some(found.len()) {
+                    tracing::info!(sensors = found.len(), "hwmon sensors discovered");
+                }
+                *sources = Some((Instant::now(), found));
+            }
+            let (_, list) = sources.as_ref().expect("discovered above");
+            list.iter().filter_map(SensorSource::read).collect()
+        };
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = readings.clone();
+        readings
+    }
+
+    /// Readings of the last [`collect`](Self::collect); empty before the first.
+    pub fn current(&self) -> Vec<SensorStat> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Default for SensorsRepo {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESCAN_INTERVAL)
+    }
+}
@@ -0,0 +1,249 @@
+// Listeners: the router is served on TCP (`host:port`, HTTPS with `[server.tls]`), a Unix domain
+// socket (`unix_socket_path`), or both, with one shared graceful shutdown. `run` serves in the
+// background and hands back the bound address.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::ServerConfig;
+
+/// Serve `app` on every listener `server` configures until `shutdown` resolves: [`run`], then
+/// [`ServerHandle::wait`].
+pub async fn serve(
+    app: Router,
+    server: &ServerConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    run(app, server, shutdown).await?.wait().await
+}
+
+/// The TCP listener's actual address, as a request extension for `/version` and `/api/info`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundAddress(pub SocketAddr);
+
+/// [`bind`] every listener, then serve `app` in a background task until `shutdown` resolves or
+/// [`ServerHandle::shutdown`] is called. Bind errors are returned here; serve errors from
+/// [`ServerHandle::wait`].
+pub async fn run(
+    app: Router,
+    server: &ServerConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<ServerHandle> {
+    let listeners = bind(server).await?;
+    let bound_address = listeners.local_addr();
+    let app = match bound_address {
+        Some(addr) => app.layer(axum::Extension(BoundAddress(addr))),
+        None => app,
+    };
+    let stop = CancellationToken::new();
+    let stopped = stop.clone();
+    let task = tokio::spawn(listeners.serve(app, async move {
+        tokio::select! {
+            _ = shutdown => {}
+            _ = stopped.cancelled() => {}
+        }
+    }));
+    Ok(ServerHandle {
+        bound_address,
+        stop,
+        task,
+    })
+}
+
+/// A server started by [`run`].
+pub struct ServerHandle {
+    bound_address: Option<SocketAddr>,
+    stop: CancellationToken,
+    task: tokio::task::JoinHandle<anyhow::Result<()>>,
+}
+
+impl ServerHandle {
+    /// Address of the TCP listener (with `server.port = 0`, the port the OS picked); `None`
+    /// when only the Unix socket is served.
+    pub fn bound_address(&self) -> Option<SocketAddr> {
+        self.bound_address
+    }
+
+    /// Stop accepting and let in-flight requests finish, as the `shutdown` future would.
+    pub fn shutdown(&self) {
+        self.stop.cancel();
+    }
+
+    /// Wait until every listener has stopped; the first listener error, if any.
+    pub async fn wait(self) -> anyhow::Result<()> {
+        self.task.await.context("server task")?
+    }
+}
+
+#[cfg(unix)]
+type SocketListener = tokio::net::UnixListener;
+#[cfg(not(unix))]
+type SocketListener = tokio::net::TcpListener;
+
+/// Bound listeners; connections queue in the kernel until [`Listeners::serve`] runs.
+pub struct Listeners {
+    tcp: Option<tokio::net::TcpListener>,
+    /// Loaded certificate and the config it came from (re-read on SIGHUP).
+    tls: Option<(RustlsConfig, crate::config::TlsConfig)>,
+    unix: Option<(SocketListener, String)>,
+}
+
+/// Bind every listener `server` configures and load the TLS certificate, so errors surface
+/// before the service reports itself ready.
+pub async fn bind(server: &ServerConfig) -> anyhow::Result<Listeners> {
+    let mut listeners = Listeners {
+        tcp: None,
+        tls: None,
+        unix: None,
+    };
+    if server.tcp_enabled {
+        let addr = format!("{}:{}", server.host, server.port);
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("bind {addr}"))?;
+        // With port 0 the OS picks one: log the address actually bound.
+        let addr = listener.local_addr().map_or(addr, |a| a.to_string());
+        if let Some(tls) = &server.tls {
+            let rustls = RustlsConfig::from_config(Arc::new(tls.load()?));
+            listeners.tls = Some((rustls, tls.clone()));
+            tracing::info!("Listening on https://{}", addr);
+        } else {
+            tracing::info!("Listening on http://{}", addr);
+        }
+        listeners.tcp = Some(listener);
+    }
+    if let Some(path) = &server.unix_socket_path {
+        listeners.unix = Some((bind_unix(path, server.unix_socket_mode)?, path.clone()));
+        tracing::info!("Listening on unix:{}", path);
+    }
+    Ok(listeners)
+}
+
+impl Listeners {
+    /// Address the TCP listener is bound to (the OS-assigned port for `port = 0`); `None`
+    /// without TCP.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.tcp.as_ref()?.local_addr().ok()
+    }
+
+    /// Serve `app` on every listener until `shutdown` resolves, then let each finish its
+    /// in-flight requests. The socket file is removed afterwards.
+    pub async fn serve(
+        self,
+        app: Router,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        let token = CancellationToken::new();
+        let mut servers = tokio::task::JoinSet::new();
+
+        match (self.tcp, self.tls) {
+            (Some(listener), Some((rustls, tls))) => {
+                let handle = axum_server::Handle::new();
+                let serve = axum_server::from_tcp_rustls(listener.into_std()?, rustls.clone())?
+                    .handle(handle.clone())
+                    .serve(app.clone().into_make_service());
+                servers.spawn(async move { serve.await.context("TLS server") });
+                let stop = token.clone();
+                tokio::spawn(async move {
+                    stop.cancelled().await;
+                    handle.graceful_shutdown(None);
+                });
+                #[cfg(unix)]
+                spawn_certificate_reload(rustls, tls, token.clone())?;
+                #[cfg(not(unix))]
+                let _ = tls;
+            }
+            (Some(listener), None) => {
+                let serve = axum::serve(listener, app.clone())
+                    .with_graceful_shutdown(token.clone().cancelled_owned());
+                servers.spawn(async move { serve.await.context("TCP server") });
+            }
+            (None, _) => {}
+        }
+        if let Some((listener, path)) = self.unix {
+            let serve = axum::serve(listener, app.clone())
+                .with_graceful_shutdown(token.clone().cancelled_owned());
+            servers.spawn(async move {
+                let result = serve.await.context("Unix socket server");
+                let _ = std::fs::remove_file(&path);
+                result
+            });
+        }
+
+        // Either the shutdown signal or a listener failing stops all of them.
+        let mut first_error = None;
+        tokio::select! {
+            _ = shutdown => {}
+            Some(joined) = servers.join_next() => first_error = joined?.err(),
+        }
+        token.cancel();
+        while let Some(joined) = servers.join_next().await {
+            if let Err(e) = joined? {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// Re-read the certificate and key on every SIGHUP (e.g. after a Let's Encrypt renewal); new
+/// connections use the new certificate. A bad pair is logged and the current one kept.
+#[cfg(unix)]
+fn spawn_certificate_reload(
+    rustls: RustlsConfig,
+    tls: crate::config::TlsConfig,
+    stop: CancellationToken,
+) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                Some(()) = hangup.recv() => match tls.load() {
+                    Ok(config) => {
+                        rustls.reload_from_config(Arc::new(config));
+                        tracing::info!("TLS certificate reloaded");
+                    }
+                    Err(e) => tracing::warn!(
+                        error = %format!("{e:#}"),
+                        "TLS certificate reload failed; keeping the current certificate"
+                    ),
+                },
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Bind `path`, replacing a stale socket left by an unclean exit, and set its permissions.
+/// Any other existing file is an error rather than being deleted.
+#[cfg(unix)]
+pub fn bind_unix(path: &str, mode: u32) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {
+            std::fs::remove_file(path).with_context(|| format!("remove stale socket {path}"))?;
+        }
+        Ok(_) => anyhow::bail!("server.unix_socket_path {path} exists and is not a socket"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("stat {path}")),
+    }
+    let listener =
+        tokio::net::UnixListener::bind(path).with_context(|| format!("bind unix:{path}"))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("chmod {mode:#o} {path}"))?;
+    Ok(listener)
+}
+
+#[cfg(not(unix))]
+pub fn bind_unix(_path: &str, _mode: u32) -> anyhow::Result<tokio::net::TcpListener> {
+    anyhow::bail!("server.unix_socket_path is only supported on Unix")
+}
@@ -0,0 +1,82 @@
+// Ordered shutdown once the listeners have stopped: every snapshot the worker collected reaches
+// SQLite before aggregation runs its last pass, and the pool is closed last.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::history_repo::HistoryHandle;
+use crate::ws_connections::WsConnections;
+
+/// How long WebSocket clients get to go after their Close frame.
+pub const WS_CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+/// Resolves to the history writer and aggregation worker once the database is open; `None`
+/// when the history shutdown token was cancelled first.
+pub type HistoryStartup = JoinHandle<Option<(JoinHandle<()>, Option<JoinHandle<()>>)>>;
+
+/// Everything running in the background, stopped by [`Drain::run`].
+pub struct Drain {
+    /// The stats worker's shutdown signal (`WorkerDeps::shutdown_rx`).
+    pub worker_stop: oneshot::Sender<()>,
+    pub worker: JoinHandle<()>,
+    /// `None` in agent mode (`database.enabled = false`).
+    pub history_startup: Option<HistoryStartup>,
+    /// Given to the history startup (retries, aggregation worker).
+    pub history_shutdown: CancellationToken,
+    /// Parent of every other task's token (alerts, reports, MQTT, remote write, ...).
+    pub tasks_shutdown: CancellationToken,
+    pub tasks: Vec<JoinHandle<()>>,
+    pub ws_connections: Arc<WsConnections>,
+    pub history_repo: HistoryHandle,
+}
+
+impl Drain {
+    /// Stop the worker (dropping its queue sender), wait for the writer's final flush, cancel
+    /// and await aggregation, close the WebSocket connections, stop the other tasks, record the
+    /// clean shutdown in `service_events` and close the history pool, in that order.
+    pub async fn run(self) {
+        let _ = self.worker_stop.send(());
+        let _ = self.worker.await;
+        tracing::info!("Worker stopped; flushing history");
+        if let Some(startup) = self.history_startup {
+            // Still retrying the open: give up, nothing was written anyway.
+            if !startup.is_finished() {
+                self.history_shutdown.cancel();
+            }
+            if let Ok(Some((writer, aggregation))) = startup.await {
+                let _ = writer.await;
+                self.history_shutdown.cancel();
+                if let Some(aggregation) = aggregation {
+                    let _ = aggregation.await;
+                }
+            }
+        }
+        self.history_shutdown.cancel();
+        let open = self.ws_connections.close_all(WS_CLOSE_GRACE).await;
+        if open > 0 {
+            tracing::warn!(
+                connections = open,
+                "WebSocket clients did not close in time"
+            );
+        }
+        self.tasks_shutdown.cancel();
+        for task in self.tasks {
+            let _ = task.await;
+        }
+        if let Some(repo) = self.history_repo.get() {
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            if let Err(e) = repo.record_clean_shutdown(now_ms).await {
+                tracing::warn!(error = %e, "recording the clean shutdown failed");
+            }
+            repo.close().await;
+            tracing::info!("History database closed");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Startup of the local history store: connect and migrate (retried in the background until the
+// file or server is reachable), check the disk budget, backfill and start the aggregation worker.
+// Skipped entirely with `database.enabled = false` (agent mode).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::aggregation_worker::{self, AggregationWorkerConfig};
+use crate::backfill;
+use crate::config::DatabaseConfig;
+use crate::history_repo::{self, HistoryHandle, HistoryRepo, HistoryStore, PgHistoryRepo};
+use crate::metrics::ServiceMetrics;
+
+/// Wait before the first retry of a failed open; doubled per failure up to
+/// [`OPEN_RETRY_MAX_DELAY`].
+pub const OPEN_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(1);
+pub const OPEN_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// What only exists for the SQLite file, logged when `database.engine = "postgres"`: the server
+/// does its own vacuuming, checkpoints, backups and disk monitoring.
+const POSTGRES_FEATURES_OFF: &str = "/api/db, /api/db/projection, /api/db/verify, /api/db/backup (501), database.disk_budget_bytes, database.min_free_bytes, WAL checkpoints, VACUUM, dbFileBytes in self stats";
+
+/// The opened repo and, with `database.enable_aggregation`, the aggregation worker.
+pub struct OpenedHistory {
+    pub repo: Arc<dyn HistoryStore>,
+    /// Resolves after `shutdown` (passed to [`open_history_store`]) is cancelled.
+    pub aggregation: Option<JoinHandle<()>>,
+}
+
+/// Open `config.path` (or, with `database.engine = "postgres"`, `config.url`) and start its
+/// background work. Backfill failures are logged, not fatal.
+pub async fn open_history_store(
+    config: &DatabaseConfig,
+    metrics: &ServiceMetrics,
+    shutdown: CancellationToken,
+) -> anyhow::Result<OpenedHistory> {
+    let repo: Arc<dyn HistoryStore> = if config.is_postgres() {
+        let repo = PgHistoryRepo::connect(config).await?;
+        tracing::warn!(
+            off = POSTGRES_FEATURES_OFF,
+            "postgres history engine: SQLite file maintenance is off"
+        );
+        Arc::new(repo)
+    } else {
+        let repo = HistoryRepo::connect(config).await?;
+        repo.init().await?;
+        check_disk_budget(&repo, config).await;
+        Arc::new(repo)
+    };
+    if !config.enable_aggregation {
+        return Ok(OpenedHistory {
+            repo,
+            aggregation: None,
+        });
+    }
+    let agg_config = AggregationWorkerConfig::from_database(config);
+    match backfill::run_backfill(repo.clone(), &agg_config).await {
+        Ok(report) => metrics.aggregation.record(&report),
+        Err(e) => tracing::error!(error = %e, "backfill failed (continuing)"),
+    }
+    let aggregation = aggregation_worker::spawn(
+        repo.clone(),
+        agg_config,
+        shutdown,
+        metrics.aggregation.clone(),
+        metrics.worker_restarts_total.clone(),
+    );
+    Ok(OpenedHistory {
+        repo,
+        aggregation: Some(aggregation),
+    })
+}
+
+/// Warn when the SQLite history is projected past `database.disk_budget_bytes` (0: no budget).
+async fn check_disk_budget(repo: &HistoryRepo, config: &DatabaseConfig) {
+    if config.disk_budget_bytes == 0 {
+        return;
+    }
+    match repo.get_tier_stats().await {
+        Ok(tiers) => {
+            let projection = history_repo::project_storage(&tiers, config);
+            if projection.exceeds_budget {
+                tracing::warn!(
+                    projected_bytes = projection.projected_bytes,
+                    disk_budget_bytes = projection.disk_budget_bytes,
+                    "projected history size exceeds database.disk_budget_bytes; lower the retention settings"
+                );
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "tier stats failed; skipping disk budget check"),
+    }
+}
+
+/// [`open_history_store`] until it succeeds, backing off between attempts (e.g. the database
+/// lives on a NAS mount that is not up yet). Each failure is logged and recorded in `handle`;
+/// on success the repo is published there. `None` when `shutdown` is cancelled first.
+pub async fn open_history_store_with_retry(
+    config: &DatabaseConfig,
+    metrics: &ServiceMetrics,
+    shutdown: CancellationToken,
+    handle: &HistoryHandle,
+) -> Option<OpenedHistory> {
+    let mut delay = OPEN_RETRY_INITIAL_DELAY;
+    loop {
+        match open_history_store(config, metrics, shutdown.clone()).await {
+            Ok(store) => {
+                handle.set_ready(store.repo.clone());
+                return Some(store);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = format!("{e:#}"),
+                    engine = %config.engine,
+                    path = %config.path,
+                    retry_in_secs = delay.as_secs(),
+                    "opening the history database failed; history endpoints answer 503 meanwhile"
+                );
+                handle.set_error(format!("{e:#}"));
+            }
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return None,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        delay = (delay * 2).min(OPEN_RETRY_MAX_DELAY);
+    }
+}
@@ -0,0 +1,70 @@
+// Restart-on-panic supervision for the long-running background tasks (stats worker, history
+// writer, aggregation worker). A panicking task is respawned with exponential backoff instead of
+// leaving the server serving stale data.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Delay before respawning a panicked task: `initial`, doubling per consecutive panic up to
+/// `max`. A task that ran for at least `max` before panicking starts again from `initial`.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Run `make()` in its own task until it returns. When it panics, add one to `restarts`, wait
+/// the backoff and run a fresh `make()`. Returns once a run ends normally, or when `shutdown`
+/// is cancelled while waiting to restart.
+pub async fn supervise<F, Fut>(
+    task: &'static str,
+    restarts: Arc<AtomicU64>,
+    backoff: Backoff,
+    shutdown: CancellationToken,
+    mut make: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut delay = backoff.initial;
+    loop {
+        let started = Instant::now();
+        let err = match tokio::spawn(make()).await {
+            Ok(()) => return,
+            Err(e) if e.is_panic() => e,
+            Err(e) => {
+                tracing::warn!(error = %e, task, "supervised task cancelled");
+                return;
+            }
+        };
+        if started.elapsed() >= backoff.max {
+            delay = backoff.initial;
+        }
+        let total = restarts.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::error!(
+            error = %err,
+            task,
+            restart_in_ms = delay.as_millis() as u64,
+            worker_restarts_total = total,
+            "supervised task panicked; restarting"
+        );
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        delay = (delay * 2).min(backoff.max);
+    }
+}
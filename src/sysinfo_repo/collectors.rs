@@ -1,16 +1,14 @@
 // Secondary impl block for SysinfoRepo: storage, network, system-info and system-stats collectors.
 
-use super::linux;
+use super::{linux, primary_ip};
 use crate::models::*;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::net::IpAddr;
 use std::time::Instant;
 use sysinfo::{ProcessesToUpdate, System};
 use tracing::instrument;
 
 use super::SysinfoRepo;
-
-/// Times we skipped a per-interface rate sample because a cumulative counter decreased (reset / driver quirk).
-static NETWORK_RATE_COUNTER_DECREASE_SKIPS: AtomicU64 = AtomicU64::new(0);
+use super::network_rates::apply_network_rates;
 
 impl SysinfoRepo {
     #[instrument(skip(self), fields(repo = "sysinfo", operation = "get_storage_stats"))]
@@ -114,6 +112,8 @@ impl SysinfoRepo {
                     received_bytes_per_sec: 0.0,
                     transmitted_bytes_per_sec: 0.0,
                     is_up: linux::read_interface_operstate(name),
+                    packets_recv_per_sec: 0.0,
+                    packets_sent_per_sec: 0.0,
                 })
                 .collect();
 
@@ -123,55 +123,12 @@ impl SysinfoRepo {
                 .map_err(|e| anyhow::anyhow!("sysinfo last_network lock poisoned: {}", e))?;
             if let Some((ref prev, prev_ts)) = *last_guard {
                 let dt_secs = now.duration_since(prev_ts).as_secs_f64();
-                if dt_secs > 0.0 {
-                    for iface in &mut interfaces {
-                        if let Some(p) = prev.interfaces.iter().find(|i| i.name == iface.name) {
-                            if iface.bytes_recv >= p.bytes_recv {
-                                let drx = iface.bytes_recv - p.bytes_recv;
-                                iface.received_bytes_per_sec = drx as f64 / dt_secs;
-                            } else {
-                                let n = NETWORK_RATE_COUNTER_DECREASE_SKIPS
-                                    .fetch_add(1, Ordering::Relaxed)
-                                    + 1;
-                                tracing::debug!(
-                                    operation = "network_rate_skip",
-                                    iface = %iface.name,
-                                    field = "bytes_recv",
-                                    curr = iface.bytes_recv,
-                                    prev = p.bytes_recv,
-                                    skips_total = n,
-                                    "cumulative recv counter decreased; skipping recv rate this interval"
-                                );
-                            }
-                            if iface.bytes_sent >= p.bytes_sent {
-                                let dtx = iface.bytes_sent - p.bytes_sent;
-                                iface.transmitted_bytes_per_sec = dtx as f64 / dt_secs;
-                            } else {
-                                let n = NETWORK_RATE_COUNTER_DECREASE_SKIPS
-                                    .fetch_add(1, Ordering::Relaxed)
-                                    + 1;
-                                tracing::debug!(
-                                    operation = "network_rate_skip",
-                                    iface = %iface.name,
-                                    field = "bytes_sent",
-                                    curr = iface.bytes_sent,
-                                    prev = p.bytes_sent,
-                                    skips_total = n,
-                                    "cumulative sent counter decreased; skipping sent rate this interval"
-                                );
-                            }
-                        }
-                    }
-                }
+                apply_network_rates(&mut interfaces, &prev.interfaces, dt_secs);
             }
-            *last_guard = Some((
-                NetworkStats {
-                    interfaces: interfaces.clone(),
-                },
-                now,
-            ));
+            let stats = NetworkStats::new(interfaces);
+            *last_guard = Some((stats.clone(), now));
 
-            Ok(NetworkStats { interfaces })
+            Ok(stats)
         })
         .await
         .map_err(|e| anyhow::anyhow!("sysinfo task join: {}", e))?
@@ -180,7 +137,23 @@ impl SysinfoRepo {
     #[instrument(skip(self), fields(repo = "sysinfo", operation = "get_system_info"))]
     pub async fn get_system_info(&self) -> anyhow::Result<SystemInfo> {
         let sys = self.sys.clone();
+        let networks = self.networks.clone();
+        let node_name = self.node_name.clone();
         tokio::task::spawn_blocking(move || {
+            // The interface list is refreshed by get_network_stats every tick.
+            let interfaces: Vec<(String, Vec<IpAddr>)> = networks
+                .lock()
+                .map_err(|e| anyhow::anyhow!("sysinfo networks lock poisoned: {}", e))?
+                .list()
+                .iter()
+                .map(|(name, data)| {
+                    let addrs = data.ip_networks().iter().map(|n| n.addr).collect();
+                    (name.clone(), addrs)
+                })
+                .collect();
+            let default_iface = primary_ip::read_default_route_interface();
+            let (primary_ipv4, primary_ipv6) =
+                primary_ip::primary_addresses(default_iface.as_deref(), &interfaces);
             let sys = sys
                 .lock()
                 .map_err(|e| anyhow::anyhow!("sysinfo lock poisoned: {}", e))?;
@@ -204,6 +177,9 @@ impl SysinfoRepo {
                 system_manufacturer,
                 system_model: host_name,
                 processor_name: cpu_name,
+                node_name,
+                primary_ipv4: primary_ipv4.map(|ip| ip.to_string()),
+                primary_ipv6: primary_ipv6.map(|ip| ip.to_string()),
             })
         })
         .await
@@ -2,8 +2,11 @@
 
 mod collectors;
 pub mod linux;
+pub mod network_rates;
+pub mod primary_ip;
 
 use crate::models::*;
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
 use std::time::Instant;
 use sysinfo::{Disks, Networks, System};
@@ -15,6 +18,7 @@ pub struct SysinfoRepo {
     networks: Arc<std::sync::Mutex<Networks>>,
     last_network: Arc<std::sync::Mutex<Option<(NetworkStats, Instant)>>>,
     last_cpu_refresh: Arc<std::sync::Mutex<Option<(Instant, f64)>>>,
+    node_name: String,
 }
 
 impl Default for SysinfoRepo {
@@ -35,9 +39,28 @@ impl SysinfoRepo {
             networks: Arc::new(std::sync::Mutex::new(networks)),
             last_network: Arc::new(std::sync::Mutex::new(None)),
             last_cpu_refresh: Arc::new(std::sync::Mutex::new(None)),
+            node_name: String::new(),
         }
     }
 
+    /// Stamp `SystemInfo::node_name` with `node_name` (`server.node_name`).
+    pub fn with_node_name(mut self, node_name: impl Into<String>) -> Self {
+        self.node_name = node_name.into();
+        self
+    }
+
+    /// Prefix length of the local address `addr` (e.g. `SystemInfo.primary_ipv4`), from the
+    /// interface list last refreshed by [`Self::get_network_stats`]; `None` when no interface has it.
+    pub fn ipv4_prefix_len(&self, addr: Ipv4Addr) -> Option<u8> {
+        let networks = self.networks.lock().ok()?;
+        networks.list().values().find_map(|data| {
+            data.ip_networks()
+                .iter()
+                .find(|n| n.addr == IpAddr::V4(addr))
+                .map(|n| n.prefix)
+        })
+    }
+
     #[instrument(skip(self), fields(repo = "sysinfo", operation = "get_cpu_stats"))]
     pub async fn get_cpu_stats(&self) -> anyhow::Result<CpuStats> {
         let sys = self.sys.clone();
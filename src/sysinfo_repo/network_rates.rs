@@ -0,0 +1,58 @@
+// Per-interface rates from two consecutive readings of the cumulative byte / packet counters.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::InterfaceStat;
+
+/// Times we skipped a per-interface rate sample because a cumulative counter decreased (reset / driver quirk).
+static NETWORK_RATE_COUNTER_DECREASE_SKIPS: AtomicU64 = AtomicU64::new(0);
+
+/// Fill the byte and packet rates of `interfaces` from `prev`, read `dt_secs` earlier. An
+/// interface missing from `prev`, or a counter that went backwards, keeps its rate at 0.
+pub fn apply_network_rates(interfaces: &mut [InterfaceStat], prev: &[InterfaceStat], dt_secs: f64) {
+    if dt_secs <= 0.0 {
+        return;
+    }
+    for iface in interfaces {
+        let Some(p) = prev.iter().find(|i| i.name == iface.name) else {
+            continue;
+        };
+        let rate = |field: &'static str, curr: u64, prev: u64| {
+            counter_rate(&iface.name, field, curr, prev, dt_secs)
+        };
+        let [rx, tx, rx_packets, tx_packets] = [
+            rate("bytes_recv", iface.bytes_recv, p.bytes_recv),
+            rate("bytes_sent", iface.bytes_sent, p.bytes_sent),
+            rate("packets_recv", iface.packets_recv, p.packets_recv),
+            rate("packets_sent", iface.packets_sent, p.packets_sent),
+        ];
+        iface.received_bytes_per_sec = rx.unwrap_or(iface.received_bytes_per_sec);
+        iface.transmitted_bytes_per_sec = tx.unwrap_or(iface.transmitted_bytes_per_sec);
+        iface.packets_recv_per_sec = rx_packets.unwrap_or(iface.packets_recv_per_sec);
+        iface.packets_sent_per_sec = tx_packets.unwrap_or(iface.packets_sent_per_sec);
+    }
+}
+
+/// `(curr - prev) / dt_secs`, or `None` (counted and logged) when the counter decreased.
+fn counter_rate(
+    iface: &str,
+    field: &'static str,
+    curr: u64,
+    prev: u64,
+    dt_secs: f64,
+) -> Option<f64> {
+    if curr >= prev {
+        return Some((curr - prev) as f64 / dt_secs);
+    }
+    let n = NETWORK_RATE_COUNTER_DECREASE_SKIPS.fetch_add(1, Ordering::Relaxed) + 1;
+    tracing::debug!(
+        operation = "network_rate_skip",
+        iface,
+        field,
+        curr,
+        prev,
+        skips_total = n,
+        "cumulative counter decreased; skipping this rate for the interval"
+    );
+    None
+}
@@ -0,0 +1,75 @@
+// Primary IPv4 / IPv6 of the host for `SystemInfo`: the addresses of the interface holding the
+// default route (`/proc/net/route`), else of the first other interface with one.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::models::is_physical_interface;
+
+/// `RTF_UP` in the `Flags` column of `/proc/net/route`.
+const RTF_UP: u32 = 0x1;
+
+/// Interface of the IPv4 default route in `/proc/net/route` content: destination and mask
+/// `00000000` with the route up; the lowest metric wins. `None` when there is no such route.
+pub fn parse_default_route(content: &str) -> Option<String> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            // Iface Destination Gateway Flags RefCnt Use Metric Mask MTU Window IRTT
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            let [iface, dest, _, flags, _, _, metric, mask, ..] = cols[..] else {
+                return None;
+            };
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            let metric = metric.parse::<u32>().ok()?;
+            (dest == "00000000" && mask == "00000000" && flags & RTF_UP != 0)
+                .then_some((metric, iface))
+        })
+        .min_by_key(|(metric, _)| *metric)
+        .map(|(_, iface)| iface.to_string())
+}
+
+/// Pick the primary addresses among `interfaces` (name, addresses). Interfaces are tried in
+/// order: `default_iface`, then physical ones, then any other, each group by name; loopback
+/// never counts. IPv6 skips link-local (`fe80::/10`) addresses, which are not reachable from
+/// another network.
+pub fn primary_addresses(
+    default_iface: Option<&str>,
+    interfaces: &[(String, Vec<IpAddr>)],
+) -> (Option<Ipv4Addr>, Option<Ipv6Addr>) {
+    let mut candidates: Vec<&(String, Vec<IpAddr>)> =
+        interfaces.iter().filter(|(name, _)| name != "lo").collect();
+    candidates.sort_by_key(|(name, _)| {
+        (
+            Some(name.as_str()) != default_iface,
+            !is_physical_interface(name),
+            name.clone(),
+        )
+    });
+    let addrs = || candidates.iter().flat_map(|(_, addrs)| addrs.iter());
+    let ipv4 = addrs().find_map(|addr| match addr {
+        IpAddr::V4(v4) if !v4.is_loopback() && !v4.is_unspecified() => Some(*v4),
+        _ => None,
+    });
+    let ipv6 = addrs().find_map(|addr| match addr {
+        IpAddr::V6(v6)
+            if !v6.is_loopback() && !v6.is_unspecified() && !v6.is_unicast_link_local() =>
+        {
+            Some(*v6)
+        }
+        _ => None,
+    });
+    (ipv4, ipv6)
+}
+
+/// Read the default-route interface from /proc/net/route (Linux).
+pub(super) fn read_default_route_interface() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/net/route")
+            .ok()
+            .and_then(|content| parse_default_route(&content))
+    }
+    #[cfg(not(target_os = "linux"))]
+    None
+}
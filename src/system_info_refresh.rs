@@ -0,0 +1,118 @@
+// Runtime refresh of the static host identity (POST /api/info/refresh and
+// `[monitoring] system_info_refresh_secs`): re-detect, swap the shared value, persist it to the
+// `system_info` table and wake the /ws/system streams, which send a new `info` message.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::history_repo::{HistoryHandle, HistoryStore};
+use crate::models::SystemInfo;
+use crate::sysinfo_repo::SysinfoRepo;
+
+/// The current [`SystemInfo`], shared by the routes, the history writer and the refresh task.
+/// Cloning shares the value.
+#[derive(Debug, Clone)]
+pub struct SharedSystemInfo {
+    tx: Arc<watch::Sender<Arc<SystemInfo>>>,
+}
+
+impl SharedSystemInfo {
+    pub fn new(info: SystemInfo) -> Self {
+        Arc::new(info).into()
+    }
+
+    pub fn get(&self) -> Arc<SystemInfo> {
+        self.tx.borrow().clone()
+    }
+
+    /// A receiver marked changed on every swap.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<SystemInfo>> {
+        self.tx.subscribe()
+    }
+
+    /// Swap in `info`; returns whether it differed (subscribers are only woken then).
+    pub fn replace(&self, info: SystemInfo) -> bool {
+        self.tx.send_if_modified(|current| {
+            if **current == info {
+                return false;
+            }
+            *current = Arc::new(info);
+            true
+        })
+    }
+}
+
+impl From<Arc<SystemInfo>> for SharedSystemInfo {
+    fn from(info: Arc<SystemInfo>) -> Self {
+        Self {
+            tx: Arc::new(watch::Sender::new(info)),
+        }
+    }
+}
+
+impl From<SystemInfo> for SharedSystemInfo {
+    fn from(info: SystemInfo) -> Self {
+        Self::new(info)
+    }
+}
+
+/// Body of `POST /api/info/refresh`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshReport {
+    /// Whether the re-detected identity differed from the one served before.
+    pub changed: bool,
+    pub system_info: SystemInfo,
+}
+
+/// Re-detect the identity and swap it into `shared`; a changed value is stored in `history_repo`
+/// (`None` in agent mode) right away rather than with the next flush.
+pub async fn refresh(
+    shared: &SharedSystemInfo,
+    sysinfo_repo: &SysinfoRepo,
+    history_repo: Option<&dyn HistoryStore>,
+) -> anyhow::Result<RefreshReport> {
+    let detected = sysinfo_repo.get_system_info().await?;
+    let changed = shared.replace(detected.clone());
+    if changed {
+        tracing::info!(system_info = ?detected, "system info changed");
+        if let Some(repo) = history_repo {
+            repo.save_system_info(&detected).await?;
+        }
+    }
+    Ok(RefreshReport {
+        changed,
+        system_info: detected,
+    })
+}
+
+/// Run [`refresh`] every `interval` until `shutdown`; a failure is logged and retried on the
+/// next tick.
+pub fn spawn_periodic(
+    shared: SharedSystemInfo,
+    sysinfo_repo: Arc<SysinfoRepo>,
+    history_repo: impl Into<HistoryHandle>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    let history_repo = history_repo.into();
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval_at(Instant::now() + interval, interval);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tick.tick() => {
+                    if let Err(e) = refresh(&shared, &sysinfo_repo, history_repo.get().map(|repo| repo.as_ref())).await {
+                        tracing::warn!(error = %e, "system info refresh failed");
+                    }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,100 @@
+// systemd integration (`Type=notify`): READY=1 once the listeners are bound, STOPPING=1 on
+// shutdown, and WATCHDOG=1 pings only while the stats worker keeps ticking, so a stuck
+// collection loop gets the service restarted.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::worker::{CollectionMetrics, WorkerConfig};
+
+/// A state reported to the service manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    Ready,
+    Stopping,
+    Watchdog,
+}
+
+/// Sends [`ServiceState`]s to the service manager; [`SdNotifier`] in production.
+pub trait Notifier: Send + Sync {
+    fn notify(&self, state: ServiceState);
+}
+
+/// `sd_notify` over `NOTIFY_SOCKET`; does nothing when the variable is unset (not under systemd).
+pub struct SdNotifier;
+
+impl Notifier for SdNotifier {
+    #[cfg(unix)]
+    fn notify(&self, state: ServiceState) {
+        let sd_state = match state {
+            ServiceState::Ready => sd_notify::NotifyState::Ready,
+            ServiceState::Stopping => sd_notify::NotifyState::Stopping,
+            ServiceState::Watchdog => sd_notify::NotifyState::Watchdog,
+        };
+        if let Err(e) = sd_notify::notify(&[sd_state]) {
+            tracing::warn!(error = %e, ?state, "sd_notify failed");
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn notify(&self, _state: ServiceState) {}
+}
+
+/// `WATCHDOG_USEC` for this process (`WatchdogSec=` in the unit), if the watchdog is enabled.
+pub fn watchdog_interval() -> Option<Duration> {
+    #[cfg(unix)]
+    return sd_notify::watchdog_enabled();
+    #[cfg(not(unix))]
+    None
+}
+
+/// How long the worker may go without a tick before pings stop: the watchdog interval, or three
+/// tick intervals when sampling (idle sampling included) is slower than that.
+pub fn max_tick_age(watchdog: Duration, config: &WorkerConfig) -> Duration {
+    let longest_ms = config
+        .idle_sample_interval_ms
+        .unwrap_or(0)
+        .max(config.sample_interval_ms);
+    watchdog.max(Duration::from_millis(longest_ms) * 3)
+}
+
+/// Ping `WATCHDOG=1` every half `watchdog` interval while the worker's last tick is younger than
+/// [`max_tick_age`] (measured from the start of this task before the first tick). Once it is
+/// older, pings stop and systemd restarts the service after `WatchdogSec`. Runs until `stop`.
+pub async fn run_watchdog(
+    notifier: Arc<dyn Notifier>,
+    watchdog: Duration,
+    metrics: Arc<CollectionMetrics>,
+    config: watch::Receiver<WorkerConfig>,
+    stop: CancellationToken,
+) {
+    let started = tokio::time::Instant::now();
+    let mut ping = tokio::time::interval(watchdog / 2);
+    let mut stale = false;
+    loop {
+        tokio::select! {
+            _ = stop.cancelled() => return,
+            _ = ping.tick() => {}
+        }
+        let age = metrics
+            .since_last_tick()
+            .unwrap_or_else(|| started.elapsed());
+        let limit = max_tick_age(watchdog, &config.borrow());
+        if age <= limit {
+            if stale {
+                tracing::info!("stats worker is ticking again; resuming watchdog pings");
+                stale = false;
+            }
+            notifier.notify(ServiceState::Watchdog);
+        } else if !stale {
+            tracing::error!(
+                last_tick_secs = age.as_secs(),
+                "stats worker stalled; withholding watchdog pings so systemd restarts the service"
+            );
+            stale = true;
+        }
+    }
+}
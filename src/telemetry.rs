@@ -0,0 +1,66 @@
+// OpenTelemetry trace export: `tracing` spans (worker ticks, history flushes, aggregation passes,
+// HTTP requests) are batched and sent over OTLP/HTTP when `[telemetry] otlp_endpoint` is set.
+// Unconfigured, no provider, exporter thread or layer is ever built.
+
+use anyhow::Context;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider, SpanExporter};
+use tracing_subscriber::{Layer, Registry};
+
+use crate::config::TelemetryConfig;
+use crate::models::SystemInfo;
+use crate::version;
+
+/// The OpenTelemetry layer as slotted into the subscriber by `bootstrap::Logging` (after
+/// startup, once the config and [`SystemInfo`] are known).
+pub type OtelLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// `service.name` / `service.version` from [`version`], `host.name` from [`SystemInfo`].
+pub fn resource(system_info: &SystemInfo) -> Resource {
+    Resource::builder_empty()
+        .with_service_name(version::NAME)
+        .with_attributes([
+            KeyValue::new("service.version", version::VERSION),
+            KeyValue::new("host.name", system_info.system_model.clone()),
+        ])
+        .build()
+}
+
+/// A provider batching spans to `exporter`; new traces are sampled at `config.sampling_ratio`.
+pub fn tracer_provider(
+    exporter: impl SpanExporter + 'static,
+    config: &TelemetryConfig,
+    system_info: &SystemInfo,
+) -> SdkTracerProvider {
+    SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sampling_ratio,
+        ))))
+        .with_resource(resource(system_info))
+        .build()
+}
+
+/// The OTLP/HTTP provider for `config`, or `None` when no endpoint is configured.
+pub fn otlp_tracer_provider(
+    config: &TelemetryConfig,
+    system_info: &SystemInfo,
+) -> anyhow::Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    use opentelemetry_otlp::WithExportConfig;
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .with_context(|| format!("telemetry.otlp_endpoint {endpoint}"))?;
+    Ok(Some(tracer_provider(exporter, config, system_info)))
+}
+
+/// A `tracing` layer turning spans into OpenTelemetry spans on `provider`.
+pub fn layer(provider: &SdkTracerProvider) -> OtelLayer {
+    Box::new(tracing_opentelemetry::layer().with_tracer(provider.tracer(version::NAME)))
+}
@@ -1,7 +1,41 @@
-// Build-time version from Cargo.toml
+// Build-time version from Cargo.toml and build metadata from build.rs, and the process start time
 
 /// Package version (from Cargo.toml).
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Package name (from Cargo.toml).
 pub const NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Short git commit of the build, `-dirty` with uncommitted changes; `"unknown"` without git.
+pub const GIT_COMMIT: &str = env!("HOMESERVER_GIT_COMMIT");
+
+/// Build time (RFC 3339 UTC, `SOURCE_DATE_EPOCH` when set).
+pub const BUILD_TIME: &str = env!("HOMESERVER_BUILD_TIME");
+
+/// `rustc --version` of the compiler that built the binary.
+pub const RUSTC_VERSION: &str = env!("HOMESERVER_RUSTC_VERSION");
+
+/// Target triple, e.g. `x86_64-unknown-linux-gnu`.
+pub const TARGET: &str = env!("HOMESERVER_TARGET");
+
+static SERVICE_START_EPOCH_MS: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+
+/// When this process started (epoch ms): pinned by `main` before anything else, otherwise on the
+/// first call. Host uptime is `SystemStatsDynamic::uptime_secs`.
+pub fn service_start_epoch_ms() -> u64 {
+    *SERVICE_START_EPOCH_MS.get_or_init(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    })
+}
+
+/// Whole seconds since [`service_start_epoch_ms`].
+pub fn service_uptime_secs() -> u64 {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    now_ms.saturating_sub(service_start_epoch_ms()) / 1000
+}
@@ -0,0 +1,175 @@
+// Snapshot broadcast accounting: queue use, receivers, lagging /ws/system clients and the
+// serialized snapshot size, served on /api/stats and /metrics; plus the latest snapshot, served on
+// /api/bootstrap.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::models::FullSystemSnapshot;
+
+/// Window over which lag events are counted against `publishing.lag_warn_per_minute`.
+const LAG_WINDOW: Duration = Duration::from_secs(60);
+
+/// How full the snapshot broadcast runs and how large its snapshots are, shared with `/api/stats`.
+#[derive(Debug, Default)]
+pub struct BroadcastMetrics {
+    sent: AtomicU64,
+    /// Ticks with no receiver, where nothing was sent.
+    skipped: AtomicU64,
+    queued: AtomicU64,
+    max_queued: AtomicU64,
+    receivers: AtomicU64,
+    lag_events: AtomicU64,
+    lagged_messages: AtomicU64,
+    lag_warnings: AtomicU64,
+    /// Start of the current lag window and the events counted in it.
+    lag_window: Mutex<Option<(tokio::time::Instant, u64)>>,
+    last_bytes: AtomicU64,
+    max_bytes: AtomicU64,
+    oversized: AtomicU64,
+    /// The last snapshot the worker built, whether or not anyone received it.
+    latest: Mutex<Option<Arc<FullSystemSnapshot>>>,
+}
+
+/// Point-in-time copy of [`BroadcastMetrics`] (the `broadcast` object of `/api/stats`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastMetricsSnapshot {
+    /// Snapshots sent on the broadcast channel.
+    pub sent_total: u64,
+    /// Ticks not broadcast because nobody was subscribed.
+    pub skipped_total: u64,
+    /// Snapshots still held for the slowest receiver after the last send, and the most seen
+    /// (out of `publishing.broadcast_capacity`).
+    pub queued: u64,
+    pub max_queued: u64,
+    /// Subscribers at the last send.
+    pub receivers: u64,
+    /// Times a `/ws/system` client fell behind, and the snapshots it skipped.
+    pub lag_events_total: u64,
+    pub lagged_messages_total: u64,
+    /// Minutes in which lag events exceeded `publishing.lag_warn_per_minute`.
+    pub lag_warnings_total: u64,
+    /// Serialized (JSON) size of the latest and the largest snapshot.
+    pub last_snapshot_bytes: u64,
+    pub max_snapshot_bytes: u64,
+    /// Snapshots larger than `publishing.max_snapshot_bytes`.
+    pub oversized_snapshots_total: u64,
+}
+
+impl BroadcastMetrics {
+    /// Count a send that left `queued` snapshots in the channel for `receivers` subscribers.
+    pub fn record_send(&self, queued: usize, receivers: usize) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        self.queued.store(queued as u64, Ordering::Relaxed);
+        self.max_queued.fetch_max(queued as u64, Ordering::Relaxed);
+        self.receivers.store(receivers as u64, Ordering::Relaxed);
+    }
+
+    /// Count a tick that was not broadcast for lack of receivers.
+    pub fn record_skip(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        self.queued.store(0, Ordering::Relaxed);
+        self.receivers.store(0, Ordering::Relaxed);
+    }
+
+    /// Count a client that skipped `skipped` snapshots. Returns the events in the current minute
+    /// when this one takes them past `warn_per_minute` (once per window), for the caller to warn.
+    pub fn record_lag(&self, skipped: u64, warn_per_minute: u64) -> Option<u64> {
+        self.lag_events.fetch_add(1, Ordering::Relaxed);
+        self.lagged_messages.fetch_add(skipped, Ordering::Relaxed);
+        let now = tokio::time::Instant::now();
+        let mut window = self.lag_window.lock().unwrap_or_else(|e| e.into_inner());
+        let (_, events) = match &mut *window {
+            Some((start, events)) if now.duration_since(*start) < LAG_WINDOW => {
+                *events += 1;
+                (*start, *events)
+            }
+            _ => *window.insert((now, 1)),
+        };
+        if events == warn_per_minute + 1 {
+            self.lag_warnings.fetch_add(1, Ordering::Relaxed);
+            Some(events)
+        } else {
+            None
+        }
+    }
+
+    /// Record a snapshot of `bytes`; true when it exceeds `limit`.
+    pub fn record_snapshot_bytes(&self, bytes: u64, limit: u64) -> bool {
+        self.last_bytes.store(bytes, Ordering::Relaxed);
+        self.max_bytes.fetch_max(bytes, Ordering::Relaxed);
+        let oversized = bytes > limit;
+        if oversized {
+            self.oversized.fetch_add(1, Ordering::Relaxed);
+        }
+        oversized
+    }
+
+    /// Keep `snapshot` as the latest one.
+    pub fn record_latest(&self, snapshot: &FullSystemSnapshot) {
+        let latest = Arc::new(snapshot.clone());
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(latest);
+    }
+
+    /// Keep `snapshot` as the latest one unless a tick already recorded one (startup priming
+    /// from history, which may finish after the first tick). True when it was kept.
+    pub fn prime_latest(&self, snapshot: &FullSystemSnapshot) -> bool {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        if latest.is_some() {
+            return false;
+        }
+        *latest = Some(Arc::new(snapshot.clone()));
+        true
+    }
+
+    /// The last [`Self::record_latest`] snapshot (`None` before the first tick).
+    pub fn latest_snapshot(&self) -> Option<Arc<FullSystemSnapshot>> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn snapshot(&self) -> BroadcastMetricsSnapshot {
+        BroadcastMetricsSnapshot {
+            sent_total: self.sent.load(Ordering::Relaxed),
+            skipped_total: self.skipped.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            max_queued: self.max_queued.load(Ordering::Relaxed),
+            receivers: self.receivers.load(Ordering::Relaxed),
+            lag_events_total: self.lag_events.load(Ordering::Relaxed),
+            lagged_messages_total: self.lagged_messages.load(Ordering::Relaxed),
+            lag_warnings_total: self.lag_warnings.load(Ordering::Relaxed),
+            last_snapshot_bytes: self.last_bytes.load(Ordering::Relaxed),
+            max_snapshot_bytes: self.max_bytes.load(Ordering::Relaxed),
+            oversized_snapshots_total: self.oversized.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts the bytes written to it.
+struct ByteCount(u64);
+
+impl std::io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Length of `snapshot` as sent on `/ws/system` (JSON), without building the string.
+pub fn snapshot_json_len(snapshot: &FullSystemSnapshot) -> u64 {
+    let mut count = ByteCount(0);
+    match serde_json::to_writer(&mut count, snapshot) {
+        Ok(()) => count.0,
+        Err(_) => 0,
+    }
+}
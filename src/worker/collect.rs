@@ -0,0 +1,286 @@
+// Per-tick collection: the independent collectors run concurrently and a failed section falls
+// back to its last known-good value (or a default) and is marked degraded.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+
+use super::last_good::LastGood;
+use super::schedule::Due;
+use crate::docker_repo::DockerRepo;
+use crate::models::{
+    ContainerStats, CpuStats, NetworkStats, RamStats, StorageStats, SystemStatsDynamic,
+};
+use crate::sysinfo_repo::SysinfoRepo;
+
+/// Sources the worker samples every tick. [`HostCollector`] reads the host; tests inject slow or
+/// failing implementations.
+pub trait StatsCollector: Send + Sync {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>>;
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>>;
+    /// Running containers with fresh stats.
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>>;
+    /// Last known containers, used when [`Self::containers`] fails.
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>>;
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>>;
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>>;
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>>;
+}
+
+/// Container source behind [`HostCollector`]: [`DockerRepo`] in production, so the worker's
+/// host path can be exercised without a Docker daemon.
+pub trait ContainerCollector: Send + Sync {
+    /// Running containers with fresh stats.
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>>;
+    /// Last known containers, used when [`Self::containers`] fails.
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>>;
+}
+
+impl ContainerCollector for DockerRepo {
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async move { Ok(self.try_list_running_and_refresh_stats().await?) })
+    }
+
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(self.get_cached_stats())
+    }
+}
+
+/// The real collectors: sysinfo for the host, a [`ContainerCollector`] (the Docker API) for
+/// containers.
+pub struct HostCollector {
+    pub sysinfo_repo: Arc<SysinfoRepo>,
+    pub docker: Arc<dyn ContainerCollector>,
+}
+
+impl StatsCollector for HostCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(self.sysinfo_repo.get_cpu_stats())
+    }
+
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(self.sysinfo_repo.get_ram_stats())
+    }
+
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        self.docker.containers()
+    }
+
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        self.docker.cached_containers()
+    }
+
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(self.sysinfo_repo.get_storage_stats())
+    }
+
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(self.sysinfo_repo.get_network_stats())
+    }
+
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(self.sysinfo_repo.get_system_stats())
+    }
+}
+
+/// One tick's readings; a failed collector contributes its last known-good value (or a
+/// default), a `failures` entry and a `degraded` section.
+pub(super) struct Collected {
+    pub cpu: CpuStats,
+    pub ram: RamStats,
+    pub containers: Vec<ContainerStats>,
+    pub storage: StorageStats,
+    pub network: NetworkStats,
+    pub system: SystemStatsDynamic,
+    /// `(source, error)` per failed collector, for `collection_errors`.
+    pub failures: Vec<(&'static str, String)>,
+    /// Snapshot sections that are stale this tick (`FullSystemSnapshot::degraded`).
+    pub degraded: Vec<String>,
+    pub elapsed: Duration,
+    /// How long each source that ran this tick took (not due: absent).
+    pub timings: Vec<(&'static str, Duration)>,
+}
+
+/// Failures and stale sections gathered while settling one tick's results.
+#[derive(Default)]
+struct Outcome {
+    failures: Vec<(&'static str, String)>,
+    degraded: Vec<String>,
+}
+
+impl Outcome {
+    /// Keep a successful reading as the new last known-good value; on failure return that value
+    /// (or a default) and mark `section` degraded. `None` (not due this tick) reuses the last
+    /// value without marking anything.
+    fn settle<T: Clone + Default>(
+        &mut self,
+        result: Option<anyhow::Result<T>>,
+        last: &mut Option<T>,
+        source: &'static str,
+        section: &'static str,
+    ) -> T {
+        let Some(result) = result else {
+            return last.clone().unwrap_or_default();
+        };
+        match result {
+            Ok(value) => {
+                *last = Some(value.clone());
+                value
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error = %e,
+                    operation = "collect",
+                    source,
+                    section,
+                    "collector failed; using last known-good value"
+                );
+                self.fail(source, section, e);
+                last.clone().unwrap_or_default()
+            }
+        }
+    }
+
+    fn fail(&mut self, source: &'static str, section: &'static str, e: anyhow::Error) {
+        self.failures.push((source, e.to_string()));
+        self.degraded.push(section.to_string());
+    }
+}
+
+/// Only poll `collect` when the subsystem is `due` this tick.
+async fn when_due<F: Future>(due: bool, collect: impl FnOnce() -> F) -> Option<F::Output> {
+    if due { Some(collect().await) } else { None }
+}
+
+/// Await `collect`, returning its output and how long it took.
+async fn timed<T>(collect: impl Future<Output = T>) -> (T, Duration) {
+    let started = Instant::now();
+    let value = collect.await;
+    (value, started.elapsed())
+}
+
+/// `(source, elapsed)` for an optional, timed reading.
+fn timing<T>(
+    source: &'static str,
+    timed: &Option<(T, Duration)>,
+) -> Option<(&'static str, Duration)> {
+    timed.as_ref().map(|(_, elapsed)| (source, *elapsed))
+}
+
+/// Run every due collector concurrently, so a tick takes as long as the slowest one (typically
+/// the Docker listing) rather than their sum. Subsystems not `due` keep their previous reading.
+pub(super) async fn collect_all(
+    collector: &dyn StatsCollector,
+    due: Due,
+    last: &mut LastGood,
+) -> Collected {
+    let started = Instant::now();
+    let (cpu, ram, containers, storage, network, system) = tokio::join!(
+        timed(collector.cpu_stats()),
+        timed(collector.ram_stats()),
+        when_due(due.containers, || timed(collector.containers())),
+        when_due(due.storage, || timed(collector.storage_stats())),
+        timed(collector.network_stats()),
+        when_due(due.system, || timed(collector.system_stats())),
+    );
+    let timings: Vec<_> = [
+        Some(("cpu", cpu.1)),
+        Some(("ram", ram.1)),
+        timing("docker", &containers),
+        timing("storage", &storage),
+        Some(("network", network.1)),
+        timing("system", &system),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let (cpu, ram, network) = (cpu.0, ram.0, network.0);
+    let (containers, storage, system) = (
+        containers.map(|(v, _)| v),
+        storage.map(|(v, _)| v),
+        system.map(|(v, _)| v),
+    );
+
+    // Degrade gracefully: a failing collector substitutes its last known-good reading rather
+    // than dropping the whole tick (which would punch holes into every chart).
+    let mut outcome = Outcome::default();
+    let cpu = outcome.settle(Some(cpu), &mut last.cpu, "cpu", "cpu");
+    let ram = outcome.settle(Some(ram), &mut last.ram, "ram", "ram");
+    let containers = match containers {
+        Some(Ok(containers)) => containers,
+        Some(Err(e)) => {
+            outcome.fail("docker", "containers", e);
+            collector.cached_containers().await
+        }
+        None => collector.cached_containers().await,
+    };
+    let storage = outcome.settle(storage, &mut last.storage, "storage", "storage");
+    let network = outcome.settle(Some(network), &mut last.network, "network", "network");
+    let system = outcome.settle(system, &mut last.system, "system", "system");
+    Collected {
+        cpu,
+        ram,
+        containers,
+        storage,
+        network,
+        system,
+        failures: outcome.failures,
+        degraded: outcome.degraded,
+        elapsed: started.elapsed(),
+        timings,
+    }
+}
+
+/// Rate-limited "collection took longer than the sample interval" warning.
+#[derive(Default)]
+pub(super) struct SlowTickWarning {
+    last_warn: Option<Instant>,
+    slow_since_warn: u64,
+}
+
+impl SlowTickWarning {
+    /// Whether a tick that took `elapsed` overran `interval`; warns at most once per
+    /// `min_interval`, naming the `slowest` source and counting the slow ticks in between.
+    pub fn observe(
+        &mut self,
+        elapsed: Duration,
+        interval: Duration,
+        min_interval: Duration,
+        slowest: Option<(&'static str, Duration)>,
+    ) -> bool {
+        if elapsed <= interval {
+            return false;
+        }
+        self.slow_since_warn += 1;
+        if self.last_warn.is_none_or(|t| t.elapsed() >= min_interval) {
+            tracing::warn!(
+                elapsed_ms = elapsed.as_millis() as u64,
+                sample_interval_ms = interval.as_millis() as u64,
+                slowest_source = slowest.map(|(source, _)| source),
+                slowest_ms = slowest.map(|(_, d)| d.as_millis() as u64),
+                slow_ticks = self.slow_since_warn,
+                "collection took longer than the sample interval"
+            );
+            self.last_warn = Some(Instant::now());
+            self.slow_since_warn = 0;
+        }
+        true
+    }
+}
+
+/// Wall-clock snapshot timestamp in Unix milliseconds; 0 (with a warning) if the clock is
+/// before the epoch.
+pub(super) fn snapshot_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_else(|e| {
+            tracing::warn!(
+                error = %e,
+                operation = "get_timestamp",
+                "system time error"
+            );
+            0
+        })
+}
@@ -0,0 +1,257 @@
+// Collection timings and failures per source, served on /api/stats and /metrics.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::models::SelfStats;
+
+/// Collector sources, as recorded in `collection_errors` and counted in [`CollectionMetrics`].
+pub const COLLECTION_SOURCES: [&str; 6] = ["cpu", "ram", "docker", "storage", "network", "system"];
+
+/// Samples kept per subsystem for the rolling timing figures (10 minutes at a 1 s tick).
+const TIMING_WINDOW: usize = 600;
+
+/// Key of the whole tick's collection time in [`CollectionTimings`].
+pub const TOTAL_TIMING: &str = "total";
+
+/// The last [`TIMING_WINDOW`] durations of one subsystem.
+#[derive(Debug, Default)]
+struct TimingWindow {
+    /// Microseconds, oldest first.
+    samples: VecDeque<u64>,
+    count: u64,
+}
+
+impl TimingWindow {
+    fn push(&mut self, elapsed: Duration) {
+        if self.samples.len() == TIMING_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back(elapsed.as_micros().min(u128::from(u64::MAX)) as u64);
+        self.count += 1;
+    }
+
+    fn stats(&self) -> TimingStats {
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let ms = |us: u64| us as f64 / 1000.0;
+        let (mean_ms, p95_ms, max_ms) = match sorted.last() {
+            None => (0.0, 0.0, 0.0),
+            Some(&max) => {
+                let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+                let sum: u64 = sorted.iter().sum();
+                (
+                    ms(sum) / sorted.len() as f64,
+                    ms(sorted[rank.max(1) - 1]),
+                    ms(max),
+                )
+            }
+        };
+        TimingStats {
+            count: self.count,
+            mean_ms,
+            p95_ms,
+            max_ms,
+        }
+    }
+}
+
+/// Rolling collection times per subsystem (the [`COLLECTION_SOURCES`] that ran) and for the
+/// whole tick ([`TOTAL_TIMING`]), plus which subsystem was slowest on each slow tick.
+#[derive(Debug, Default)]
+pub struct CollectionTimings {
+    windows: BTreeMap<&'static str, TimingWindow>,
+    slowest_on_slow_ticks: BTreeMap<&'static str, u64>,
+}
+
+impl CollectionTimings {
+    pub fn record(&mut self, subsystem: &'static str, elapsed: Duration) {
+        self.windows.entry(subsystem).or_default().push(elapsed);
+    }
+
+    /// Figures per subsystem: `count` since start; mean, p95 and max over the window.
+    pub fn stats(&self) -> BTreeMap<String, TimingStats> {
+        self.windows
+            .iter()
+            .map(|(name, window)| (name.to_string(), window.stats()))
+            .collect()
+    }
+}
+
+/// One subsystem in `/api/stats` `collection.timings`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingStats {
+    /// Collections timed since start.
+    pub count: u64,
+    /// Over the last `TIMING_WINDOW` (600) collections.
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// The source that took longest in `timings`.
+pub fn slowest_source(timings: &[(&'static str, Duration)]) -> Option<(&'static str, Duration)> {
+    timings.iter().copied().max_by_key(|(_, elapsed)| *elapsed)
+}
+
+/// How long the collectors take per tick, shared with `/api/stats`.
+#[derive(Debug, Default)]
+pub struct CollectionMetrics {
+    ticks: AtomicU64,
+    total_ms: AtomicU64,
+    last_ms: AtomicU64,
+    max_ms: AtomicU64,
+    slow_ticks: AtomicU64,
+    degraded_ticks: AtomicU64,
+    /// Failures per source, indexed like [`COLLECTION_SOURCES`].
+    failures: [AtomicU64; COLLECTION_SOURCES.len()],
+    /// When the worker loop last ticked (paused ticks included); gates the systemd watchdog.
+    last_tick: Mutex<Option<tokio::time::Instant>>,
+    /// The server's own resource usage at the last collected tick.
+    latest_self: Mutex<Option<SelfStats>>,
+    timings: Mutex<CollectionTimings>,
+}
+
+/// Point-in-time copy of [`CollectionMetrics`] (the `collection` object of `/api/stats`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionMetricsSnapshot {
+    pub ticks_total: u64,
+    pub last_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+    /// Ticks whose collection took longer than `sample_interval_ms`.
+    pub slow_ticks_total: u64,
+    /// Ticks with at least one failed collector.
+    pub degraded_ticks_total: u64,
+    /// Failures per source (`cpu`, `ram`, `docker`, `storage`, `network`, `system`).
+    pub failures_total: BTreeMap<String, u64>,
+    /// Rolling collection time per source that ran, and for the whole tick (`total`).
+    pub timings: BTreeMap<String, TimingStats>,
+    /// Slow ticks by the source that took longest on them.
+    pub slow_ticks_by_source: BTreeMap<String, u64>,
+}
+
+impl CollectionMetrics {
+    /// Count a tick that took `elapsed`; `slow` when it overran the sample interval.
+    pub fn record(&self, elapsed: Duration, slow: bool) {
+        let ms = elapsed.as_millis() as u64;
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(ms, Ordering::Relaxed);
+        self.last_ms.store(ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(ms, Ordering::Relaxed);
+        if slow {
+            self.slow_ticks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Add one tick's per-source times (sources not due this tick are absent) and its `total`.
+    pub fn record_timings(&self, sources: &[(&'static str, Duration)], total: Duration) {
+        let mut timings = self.timings.lock().unwrap_or_else(|e| e.into_inner());
+        for (source, elapsed) in sources {
+            timings.record(source, *elapsed);
+        }
+        timings.record(TOTAL_TIMING, total);
+    }
+
+    /// Count a slow tick against the source that took longest on it.
+    pub fn record_slow_source(&self, source: &'static str) {
+        let mut timings = self.timings.lock().unwrap_or_else(|e| e.into_inner());
+        *timings.slowest_on_slow_ticks.entry(source).or_default() += 1;
+    }
+
+    /// Rolling per-source figures (see [`CollectionTimings::stats`]).
+    pub fn timings(&self) -> BTreeMap<String, TimingStats> {
+        self.timings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .stats()
+    }
+
+    /// One line for the periodic "app stats" log: `source=mean/p95/max` in ms per source.
+    pub fn timings_summary(&self) -> String {
+        self.timings()
+            .iter()
+            .map(|(source, t)| format!("{source}={:.1}/{:.1}/{:.1}", t.mean_ms, t.p95_ms, t.max_ms))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The worker loop is alive: called at the start of every tick, paused or not.
+    pub fn beat(&self) {
+        *self.last_tick.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(tokio::time::Instant::now());
+    }
+
+    /// Time since the last [`Self::beat`] (`None` before the first tick).
+    pub fn since_last_tick(&self) -> Option<Duration> {
+        self.last_tick
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .map(|at| at.elapsed())
+    }
+
+    /// Keep `stats` as the latest self-monitoring sample (`/api/stats` `selfStats`).
+    pub fn record_self(&self, stats: SelfStats) {
+        *self.latest_self.lock().unwrap_or_else(|e| e.into_inner()) = Some(stats);
+    }
+
+    /// The last [`Self::record_self`] sample (`None` before the first collected tick).
+    pub fn latest_self(&self) -> Option<SelfStats> {
+        self.latest_self
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Count one tick's failed sources; any failure makes the tick degraded.
+    pub fn record_failures<'a>(&self, sources: impl IntoIterator<Item = &'a str>) {
+        let mut degraded = false;
+        for source in sources {
+            degraded = true;
+            if let Some(i) = COLLECTION_SOURCES.iter().position(|s| *s == source) {
+                self.failures[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if degraded {
+            self.degraded_ticks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> CollectionMetricsSnapshot {
+        let ticks = self.ticks.load(Ordering::Relaxed);
+        let total_ms = self.total_ms.load(Ordering::Relaxed);
+        CollectionMetricsSnapshot {
+            ticks_total: ticks,
+            last_ms: self.last_ms.load(Ordering::Relaxed),
+            max_ms: self.max_ms.load(Ordering::Relaxed),
+            mean_ms: if ticks == 0 {
+                0.0
+            } else {
+                total_ms as f64 / ticks as f64
+            },
+            slow_ticks_total: self.slow_ticks.load(Ordering::Relaxed),
+            degraded_ticks_total: self.degraded_ticks.load(Ordering::Relaxed),
+            failures_total: COLLECTION_SOURCES
+                .iter()
+                .zip(&self.failures)
+                .map(|(source, n)| (source.to_string(), n.load(Ordering::Relaxed)))
+                .collect(),
+            timings: self.timings(),
+            slow_ticks_by_source: self
+                .timings
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .slowest_on_slow_ticks
+                .iter()
+                .map(|(source, n)| (source.to_string(), *n))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// Disk-full guard for the history writer: before each flush the free space on the database's
+// filesystem is compared with `database.min_free_bytes`. Below it the batch is dropped instead of
+// failing inside SQLite, and history is pruned early until space comes back.
+
+use tokio::time::{Duration, Instant};
+
+use super::flush_metrics::FlushMetrics;
+use crate::history_repo::HistoryRepo;
+
+/// At most one ERROR per interval while the disk stays full.
+const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// Gap between emergency prunes; each one keeps half the raw history the previous one kept.
+const EMERGENCY_PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+/// Retention is never halved further than this (it bottoms out at an hour well before).
+const MAX_HALVINGS: u32 = 16;
+
+/// Per-run state of the guard: rate limits and how far retention has been cut.
+#[derive(Debug, Default)]
+pub(super) struct DiskGuard {
+    last_error: Option<Instant>,
+    last_prune: Option<Instant>,
+    /// Emergency prunes so far in this disk-full episode.
+    halvings: u32,
+    /// Snapshots dropped since the last ERROR (or the start of the episode).
+    dropped: u64,
+    /// Snapshots dropped in this episode.
+    dropped_episode: u64,
+}
+
+impl DiskGuard {
+    /// Whether a batch of `batch` snapshots may be written. Without a minimum, or when free
+    /// space cannot be measured, it always may; otherwise free space on the filesystem plus free
+    /// pages inside the database must reach the minimum. The check is recorded in `metrics`;
+    /// a refused batch counts as dropped, logs an ERROR at most once a minute and runs an
+    /// emergency prune at most every ten minutes.
+    pub(super) async fn admit(
+        &mut self,
+        repo: &HistoryRepo,
+        min_free_bytes: u64,
+        batch: usize,
+        metrics: &FlushMetrics,
+    ) -> bool {
+        if min_free_bytes == 0 {
+            self.recover(metrics, None);
+            return true;
+        }
+        let free = match repo.available_bytes() {
+            Ok(free) => free,
+            Err(e) => {
+                tracing::debug!(error = %e, "free space check failed; writing anyway");
+                self.recover(metrics, None);
+                return true;
+            }
+        };
+        // Free pages inside the file (what a prune leaves when the file cannot shrink) take
+        // writes without growing it, so they count as room too.
+        if free >= min_free_bytes
+            || free.saturating_add(repo.reusable_bytes().await.unwrap_or(0)) >= min_free_bytes
+        {
+            self.recover(metrics, Some(free));
+            return true;
+        }
+        metrics.record_free_space(Some(free), true);
+        metrics.record_dropped_disk_full(batch);
+        self.dropped += batch as u64;
+        self.dropped_episode += batch as u64;
+        let now = Instant::now();
+        if self
+            .last_error
+            .is_none_or(|at| now.duration_since(at) >= ERROR_LOG_INTERVAL)
+        {
+            tracing::error!(
+                free_bytes = free,
+                min_free_bytes,
+                dropped = self.dropped,
+                "history writer: disk almost full; dropping snapshots instead of writing them"
+            );
+            self.last_error = Some(now);
+            self.dropped = 0;
+        }
+        if self
+            .last_prune
+            .is_none_or(|at| now.duration_since(at) >= EMERGENCY_PRUNE_INTERVAL)
+        {
+            self.last_prune = Some(now);
+            self.halvings = (self.halvings + 1).min(MAX_HALVINGS);
+            metrics.record_emergency_prune();
+            match repo.emergency_prune(self.halvings).await {
+                Ok(pruned) => tracing::warn!(
+                    raw_removed = pruned.raw_removed,
+                    aggregated_removed = pruned.aggregated_removed,
+                    retention_divisor = 1u64 << self.halvings,
+                    "history writer: emergency prune of history while the disk is full"
+                ),
+                Err(e) => tracing::warn!(error = %e, "history writer: emergency prune failed"),
+            }
+        }
+        false
+    }
+
+    /// Space is fine (or not checked): end a disk-full episode, if any.
+    fn recover(&mut self, metrics: &FlushMetrics, free: Option<u64>) {
+        if metrics.disk_full() {
+            tracing::info!(
+                free_bytes = free,
+                dropped = self.dropped_episode,
+                "history writer: free space recovered; writing history again"
+            );
+        }
+        metrics.record_free_space(free, false);
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,89 @@
+// Per-source rate limit for recording collector failures in `collection_errors`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+
+use crate::history_repo::HistoryStore;
+use crate::models::CollectionError;
+
+/// Admits at most one failure per source every `min_interval`; the failures dropped in between
+/// are reported with the next admitted one.
+pub struct ErrorRateLimiter {
+    min_interval: Duration,
+    sources: HashMap<String, SourceState>,
+}
+
+struct SourceState {
+    last_recorded: Instant,
+    suppressed: u64,
+}
+
+impl ErrorRateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// `Some(suppressed)` when a failure of `source` at `now` should be recorded, carrying the
+    /// number of failures dropped since the previous entry; `None` when it is rate-limited.
+    pub fn admit(&mut self, source: &str, now: Instant) -> Option<u64> {
+        match self.sources.get_mut(source) {
+            Some(state) if now.duration_since(state.last_recorded) < self.min_interval => {
+                state.suppressed += 1;
+                None
+            }
+            Some(state) => {
+                state.last_recorded = now;
+                Some(std::mem::take(&mut state.suppressed))
+            }
+            None => {
+                self.sources.insert(
+                    source.to_string(),
+                    SourceState {
+                        last_recorded: now,
+                        suppressed: 0,
+                    },
+                );
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Record each `(source, message)` failure of the tick at `ts` in `collection_errors`, as far as
+/// `limiter` admits it. The inserts run in a detached task, so a locked or stalled database never
+/// delays the tick's broadcast.
+pub(super) fn record_failures(
+    history_repo: &Arc<dyn HistoryStore>,
+    limiter: &mut ErrorRateLimiter,
+    ts: u64,
+    failures: Vec<(&'static str, String)>,
+) {
+    let now = Instant::now();
+    let entries: Vec<CollectionError> = failures
+        .into_iter()
+        .filter_map(|(source, message)| {
+            let suppressed = limiter.admit(source, now)?;
+            Some(CollectionError {
+                ts: ts as i64,
+                source: source.to_string(),
+                message,
+                suppressed,
+            })
+        })
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    let history_repo = history_repo.clone();
+    tokio::spawn(async move {
+        for entry in entries {
+            if let Err(e) = history_repo.record_error(&entry).await {
+                tracing::warn!(error = %e, operation = "record_error", "Failed to record collector error");
+            }
+        }
+    });
+}
@@ -0,0 +1,152 @@
+// History writer flush timings, batch sizes and bytes, and the disk-full guard's state, served on
+// /api/stats and /metrics.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How long the history writer's flushes take and how much they write, shared with `/api/stats`.
+#[derive(Debug, Default)]
+pub struct FlushMetrics {
+    flushes: AtomicU64,
+    failures: AtomicU64,
+    slow_flushes: AtomicU64,
+    total_ms: AtomicU64,
+    last_ms: AtomicU64,
+    max_ms: AtomicU64,
+    snapshots: AtomicU64,
+    last_batch: AtomicU64,
+    max_batch: AtomicU64,
+    bytes: AtomicU64,
+    last_bytes: AtomicU64,
+    /// When a flush last committed; a writer stuck on the database stops moving it.
+    last_success: Mutex<Option<tokio::time::Instant>>,
+    /// Snapshots dropped instead of written because free space was below `min_free_bytes`.
+    dropped_disk_full: AtomicU64,
+    emergency_prunes: AtomicU64,
+    disk_full: AtomicBool,
+    /// Free bytes at the latest check (`None` before one, or when it could not be measured).
+    free_bytes: Mutex<Option<u64>>,
+}
+
+/// Point-in-time copy of [`FlushMetrics`] (the `historyFlush` object of `/api/stats`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushMetricsSnapshot {
+    /// Flushes that committed.
+    pub flushes_total: u64,
+    /// Flush attempts whose `save_snapshots` failed (the batch stays buffered for the next one).
+    pub failures_total: u64,
+    /// Committed flushes slower than `database.flush_warn_ms`.
+    pub slow_flushes_total: u64,
+    pub last_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+    /// Snapshots in the latest / largest committed batch, and on average.
+    pub last_batch: u64,
+    pub max_batch: u64,
+    pub mean_batch: f64,
+    /// Encoded blob bytes written (row blobs plus shared storage / network blobs).
+    pub bytes_total: u64,
+    pub last_bytes: u64,
+    /// Milliseconds since the last committed flush (`None` before the first).
+    pub since_last_success_ms: Option<u64>,
+    /// Free space on the database's filesystem is below `database.min_free_bytes`: batches are
+    /// dropped instead of written.
+    pub disk_full: bool,
+    /// Free bytes at the latest check before a flush (`None` before one, with
+    /// `min_free_bytes = 0`, or when it could not be measured).
+    pub free_bytes: Option<u64>,
+    pub snapshots_dropped_disk_full: u64,
+    /// Emergency prunes of raw rows run while the disk was full.
+    pub emergency_prunes_total: u64,
+}
+
+impl FlushMetrics {
+    /// Count a committed flush of `batch` snapshots and `bytes` blob bytes that took `elapsed`;
+    /// `slow` when it exceeded the budget.
+    pub fn record(&self, elapsed: Duration, batch: usize, bytes: u64, slow: bool) {
+        let ms = elapsed.as_millis() as u64;
+        let batch = batch as u64;
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(ms, Ordering::Relaxed);
+        self.last_ms.store(ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(ms, Ordering::Relaxed);
+        self.snapshots.fetch_add(batch, Ordering::Relaxed);
+        self.last_batch.store(batch, Ordering::Relaxed);
+        self.max_batch.fetch_max(batch, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.last_bytes.store(bytes, Ordering::Relaxed);
+        if slow {
+            self.slow_flushes.fetch_add(1, Ordering::Relaxed);
+        }
+        *self.last_success.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(tokio::time::Instant::now());
+    }
+
+    /// Count a flush that failed.
+    pub fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `n` snapshots dropped because the disk is full.
+    pub fn record_dropped_disk_full(&self, n: usize) {
+        self.dropped_disk_full
+            .fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_emergency_prune(&self) {
+        self.emergency_prunes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Store the latest free-space check: `free` bytes (if measured) and whether it was below the
+    /// minimum.
+    pub fn record_free_space(&self, free: Option<u64>, full: bool) {
+        *self.free_bytes.lock().unwrap_or_else(|e| e.into_inner()) = free;
+        self.disk_full.store(full, Ordering::Relaxed);
+    }
+
+    /// Whether the latest check found less than `database.min_free_bytes` free.
+    pub fn disk_full(&self) -> bool {
+        self.disk_full.load(Ordering::Relaxed)
+    }
+
+    /// Time since the last committed flush (`None` before the first).
+    pub fn since_last_success(&self) -> Option<Duration> {
+        self.last_success
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .map(|at| at.elapsed())
+    }
+
+    pub fn snapshot(&self) -> FlushMetricsSnapshot {
+        let flushes = self.flushes.load(Ordering::Relaxed);
+        let mean = |total: &AtomicU64| {
+            if flushes == 0 {
+                0.0
+            } else {
+                total.load(Ordering::Relaxed) as f64 / flushes as f64
+            }
+        };
+        FlushMetricsSnapshot {
+            flushes_total: flushes,
+            failures_total: self.failures.load(Ordering::Relaxed),
+            slow_flushes_total: self.slow_flushes.load(Ordering::Relaxed),
+            last_ms: self.last_ms.load(Ordering::Relaxed),
+            max_ms: self.max_ms.load(Ordering::Relaxed),
+            mean_ms: mean(&self.total_ms),
+            last_batch: self.last_batch.load(Ordering::Relaxed),
+            max_batch: self.max_batch.load(Ordering::Relaxed),
+            mean_batch: mean(&self.snapshots),
+            bytes_total: self.bytes.load(Ordering::Relaxed),
+            last_bytes: self.last_bytes.load(Ordering::Relaxed),
+            since_last_success_ms: self.since_last_success().map(|d| d.as_millis() as u64),
+            disk_full: self.disk_full(),
+            free_bytes: *self.free_bytes.lock().unwrap_or_else(|e| e.into_inner()),
+            snapshots_dropped_disk_full: self.dropped_disk_full.load(Ordering::Relaxed),
+            emergency_prunes_total: self.emergency_prunes.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,90 +1,243 @@
 // Dedicated history flush task fed by snapshot channel.
 
-use crate::history_repo::HistoryRepo;
+use crate::history_repo::HistoryStore;
 use crate::models::{FullSystemSnapshot, SystemInfo};
+use crate::supervisor::{Backoff, supervise};
+use crate::system_info_refresh::SharedSystemInfo;
 use std::sync::Arc;
 use std::sync::atomic::AtomicU64;
-use tokio::sync::mpsc;
+use tokio::sync::{Mutex, watch};
 use tokio::time::{Duration, interval};
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
 
 use super::HistoryWriterConfig;
+use super::disk_guard::DiskGuard;
+use super::flush_metrics::FlushMetrics;
+use super::write_queue::WriteReceiver;
+
+/// Whether a snapshot stamped `timestamp_ms` is worth persisting at wall-clock `now_ms`: not
+/// before `min_snapshot_timestamp_ms` (clock not yet synced) and at most
+/// `max_future_skew_minutes` ahead.
+pub fn timestamp_plausible(timestamp_ms: u64, now_ms: u64, config: &HistoryWriterConfig) -> bool {
+    let max_ahead_ms = config.max_future_skew_minutes.saturating_mul(60_000);
+    timestamp_ms >= config.min_snapshot_timestamp_ms
+        && timestamp_ms <= now_ms.saturating_add(max_ahead_ms)
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
 
 /// Spawns the background task that receives snapshots from the worker and flushes to the DB.
 /// Flushes when buffer len >= flush_rate, or every flush_interval_secs, or when channel closes.
-/// When the worker drops its sender, this task flushes remaining and exits.
+/// When the worker drops its sender, this task flushes remaining and exits. Snapshots with an
+/// implausible timestamp ([`timestamp_plausible`]) are dropped; each flush warns with the count.
+/// Every flush is timed into `flush_metrics`, and one slower than `flush_warn_ms` is logged at
+/// WARN. While the database's filesystem has less than `min_free_bytes` free, batches are
+/// dropped (counted in `flush_metrics`) and raw rows pruned early (SQLite only). A panic
+/// restarts the task (counted in `restarts`) on the same queue; only its unflushed buffer is
+/// lost.
 pub fn spawn_history_writer(
-    mut write_rx: mpsc::Receiver<FullSystemSnapshot>,
-    history_repo: Arc<HistoryRepo>,
-    system_info: Arc<SystemInfo>,
+    write_rx: WriteReceiver,
+    history_repo: Arc<dyn HistoryStore>,
+    system_info: impl Into<SharedSystemInfo>,
     config: HistoryWriterConfig,
     snapshots_saved_total: Arc<AtomicU64>,
+    flush_metrics: Arc<FlushMetrics>,
+    restarts: Arc<AtomicU64>,
+) -> tokio::task::JoinHandle<()> {
+    spawn_history_writer_reloadable(
+        write_rx,
+        history_repo,
+        system_info,
+        watch::channel(config).1,
+        snapshots_saved_total,
+        flush_metrics,
+        restarts,
+    )
+}
+
+/// [`spawn_history_writer`] following `config`: a reload applies from the next snapshot.
+pub fn spawn_history_writer_reloadable(
+    write_rx: WriteReceiver,
+    history_repo: Arc<dyn HistoryStore>,
+    system_info: impl Into<SharedSystemInfo>,
+    config: watch::Receiver<HistoryWriterConfig>,
+    snapshots_saved_total: Arc<AtomicU64>,
+    flush_metrics: Arc<FlushMetrics>,
+    restarts: Arc<AtomicU64>,
 ) -> tokio::task::JoinHandle<()> {
+    let write_rx = Arc::new(Mutex::new(write_rx));
+    let system_info = system_info.into();
+    // The writer ends when the channel closes, so nothing ever cancels its restarts.
+    tokio::spawn(supervise(
+        "history_writer",
+        restarts,
+        Backoff::default(),
+        CancellationToken::new(),
+        move || {
+            run(
+                write_rx.clone(),
+                history_repo.clone(),
+                system_info.clone(),
+                config.clone(),
+                Counters {
+                    snapshots_saved_total: snapshots_saved_total.clone(),
+                    flush: flush_metrics.clone(),
+                },
+            )
+        },
+    ))
+}
+
+/// Where the writer counts its work.
+struct Counters {
+    snapshots_saved_total: Arc<AtomicU64>,
+    flush: Arc<FlushMetrics>,
+}
+
+async fn run(
+    write_rx: Arc<Mutex<WriteReceiver>>,
+    history_repo: Arc<dyn HistoryStore>,
+    system_info: SharedSystemInfo,
+    mut config_rx: watch::Receiver<HistoryWriterConfig>,
+    counters: Counters,
+) {
+    let mut write_rx = write_rx.lock().await;
+    let mut config = config_rx.borrow_and_update().clone();
     let flush_interval = Duration::from_secs(config.flush_interval_secs);
-    let persist_gpu = config.persist_gpu;
-    let persist_smart = config.persist_smart;
-    tokio::spawn(async move {
-        let mut buffer: Vec<FullSystemSnapshot> = Vec::new();
-        let mut flush_tick = interval(flush_interval);
-        flush_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut buffer: Vec<FullSystemSnapshot> = Vec::new();
+    let mut dropped: u64 = 0;
+    let mut flush_tick = interval(flush_interval);
+    flush_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut guard = DiskGuard::default();
 
-        loop {
-            tokio::select! {
-                result = write_rx.recv() => {
-                    match result {
-                        Some(mut snapshot) => {
-                            if !persist_gpu {
-                                snapshot.gpus.clear();
-                            }
-                            if !persist_smart {
-                                snapshot.smart.clear();
-                            }
-                            buffer.push(snapshot);
-                            if buffer.len() >= config.flush_rate as usize
-                                && let Err(e) = flush_buffer(&history_repo, &system_info, &mut buffer, &snapshots_saved_total).await
-                            {
-                                tracing::warn!(error = %e, "history writer: save_snapshots failed");
-                            }
+    loop {
+        tokio::select! {
+            result = write_rx.recv() => {
+                match result {
+                    Some(mut snapshot) => {
+                        if !timestamp_plausible(snapshot.timestamp, now_ms(), &config) {
+                            dropped += 1;
+                            continue;
+                        }
+                        if !config.persist_gpu {
+                            snapshot.gpus.clear();
+                        }
+                        if !config.persist_smart {
+                            snapshot.smart.clear();
+                        }
+                        buffer.push(snapshot);
+                        if buffer.len() >= config.flush_rate as usize
+                            && let Err(e) = flush_buffer(history_repo.as_ref(), &system_info.get(), &mut buffer, &counters, &config, &mut guard).await
+                        {
+                            tracing::warn!(error = %e, "history writer: save_snapshots failed");
                         }
-                        None => break,
                     }
+                    None => break,
                 }
-                _ = flush_tick.tick() => {
-                    if let Err(e) = flush_buffer(&history_repo, &system_info, &mut buffer, &snapshots_saved_total).await {
-                        tracing::warn!(error = %e, "history writer: save_snapshots failed");
-                    }
+            }
+            // A dropped sender (no reloader) disables this branch.
+            Ok(()) = config_rx.changed() => {
+                config = config_rx.borrow_and_update().clone();
+                tracing::info!(flush_rate = config.flush_rate, flush_interval_secs = config.flush_interval_secs, "history writer config reloaded");
+                flush_tick = interval(Duration::from_secs(config.flush_interval_secs));
+                flush_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            }
+            _ = flush_tick.tick() => {
+                warn_dropped(&mut dropped);
+                if let Err(e) = flush_buffer(history_repo.as_ref(), &system_info.get(), &mut buffer, &counters, &config, &mut guard).await {
+                    tracing::warn!(error = %e, "history writer: save_snapshots failed");
                 }
             }
         }
-        if let Err(e) = flush_buffer(
-            &history_repo,
-            &system_info,
-            &mut buffer,
-            &snapshots_saved_total,
-        )
-        .await
-        {
-            tracing::warn!(error = %e, "history writer: final flush failed");
-        }
-        tracing::debug!("History writer shutting down");
-    })
+    }
+    warn_dropped(&mut dropped);
+    if let Err(e) = flush_buffer(
+        history_repo.as_ref(),
+        &system_info.get(),
+        &mut buffer,
+        &counters,
+        &config,
+        &mut guard,
+    )
+    .await
+    {
+        tracing::warn!(error = %e, "history writer: final flush failed");
+    }
+    tracing::debug!("History writer shutting down");
 }
 
+fn warn_dropped(dropped: &mut u64) {
+    if *dropped > 0 {
+        tracing::warn!(
+            dropped = *dropped,
+            "history writer: dropped snapshots with an implausible timestamp (clock not synced?)"
+        );
+        *dropped = 0;
+    }
+}
+
+/// Each flush is its own trace. A batch the disk guard refuses is dropped.
+#[instrument(name = "history_flush", parent = None, skip_all, fields(snapshots = buffer.len()))]
 async fn flush_buffer(
-    history_repo: &HistoryRepo,
+    history_repo: &dyn HistoryStore,
     system_info: &SystemInfo,
     buffer: &mut Vec<FullSystemSnapshot>,
-    snapshots_saved_total: &AtomicU64,
+    counters: &Counters,
+    config: &HistoryWriterConfig,
+    guard: &mut DiskGuard,
 ) -> anyhow::Result<()> {
     if buffer.is_empty() {
         return Ok(());
     }
+    // The guard measures the SQLite file's filesystem; a Postgres server watches its own.
+    let admitted = match history_repo.as_sqlite() {
+        Some(sqlite) => {
+            guard
+                .admit(sqlite, config.min_free_bytes, buffer.len(), &counters.flush)
+                .await
+        }
+        None => true,
+    };
+    if !admitted {
+        buffer.clear();
+        return Ok(());
+    }
+    let flush_warn_ms = config.flush_warn_ms;
     let n = buffer.len();
-    history_repo.save_snapshots(buffer, system_info).await?;
-    snapshots_saved_total.fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed);
+    let started = std::time::Instant::now();
+    let bytes = match history_repo.save_snapshots(buffer, system_info).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            counters.flush.record_failure();
+            return Err(e.into());
+        }
+    };
+    let elapsed = started.elapsed();
+    let slow = flush_warn_ms > 0 && elapsed > Duration::from_millis(flush_warn_ms);
+    counters.flush.record(elapsed, n, bytes, slow);
+    counters
+        .snapshots_saved_total
+        .fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed);
     buffer.clear();
+    if slow {
+        tracing::warn!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            flush_warn_ms,
+            snapshots_count = n,
+            bytes,
+            "history writer: slow flush"
+        );
+    }
     tracing::debug!(
         operation = "save_snapshots",
         snapshots_count = n,
+        bytes,
+        elapsed_ms = elapsed.as_millis() as u64,
         "Snapshots saved"
     );
     Ok(())
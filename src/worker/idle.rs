@@ -0,0 +1,70 @@
+// Adaptive sampling: with no WebSocket client for longer than a grace period the worker slows its
+// tick to `idle_sample_interval_ms`, and returns to the fast interval as soon as one connects.
+
+use tokio::time::{Duration, Instant};
+
+/// Tick interval chooser driven by the open-connection count.
+#[derive(Debug)]
+pub struct IdleSampler {
+    fast: Duration,
+    idle: Option<Duration>,
+    grace: Duration,
+    /// When the connection count was first seen at zero.
+    empty_since: Option<Instant>,
+    idle_mode: bool,
+}
+
+impl IdleSampler {
+    /// `idle` of `None` disables adaptive sampling: the interval stays `fast`.
+    pub fn new(fast: Duration, idle: Option<Duration>, grace: Duration) -> Self {
+        Self {
+            fast,
+            idle,
+            grace,
+            empty_since: None,
+            idle_mode: false,
+        }
+    }
+
+    /// The interval to tick at right now.
+    pub fn interval(&self) -> Duration {
+        match self.idle {
+            Some(idle) if self.idle_mode => idle,
+            _ => self.fast,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle_mode
+    }
+
+    /// Feed the current connection count; returns the new interval when it changes. Switches to
+    /// idle once the count has stayed zero for the grace period, back to fast when it is not.
+    pub fn observe(&mut self, connections: usize, now: Instant) -> Option<Duration> {
+        self.idle?;
+        if connections > 0 {
+            self.empty_since = None;
+            return self.set_idle(false);
+        }
+        let since = *self.empty_since.get_or_insert(now);
+        if now.duration_since(since) >= self.grace {
+            self.set_idle(true)
+        } else {
+            None
+        }
+    }
+
+    /// A client connected: back to the fast interval right away.
+    pub fn on_connect(&mut self) -> Option<Duration> {
+        self.empty_since = None;
+        self.set_idle(false)
+    }
+
+    fn set_idle(&mut self, idle: bool) -> Option<Duration> {
+        if self.idle_mode == idle {
+            return None;
+        }
+        self.idle_mode = idle;
+        Some(self.interval())
+    }
+}
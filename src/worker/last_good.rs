@@ -0,0 +1,31 @@
+// The worker's last known-good reading per section, the fallback for a failed collector.
+
+use crate::models::{
+    CpuStats, FullSystemSnapshot, NetworkStats, RamStats, StorageStats, SystemStatsDynamic,
+};
+
+/// Last successful reading per section, kept by the worker across ticks. Containers have no
+/// entry here: [`super::StatsCollector::cached_containers`] already is their last known-good
+/// value.
+#[derive(Default)]
+pub(super) struct LastGood {
+    pub(super) cpu: Option<CpuStats>,
+    pub(super) ram: Option<RamStats>,
+    pub(super) storage: Option<StorageStats>,
+    pub(super) network: Option<NetworkStats>,
+    pub(super) system: Option<SystemStatsDynamic>,
+}
+
+impl LastGood {
+    /// Every section of `snapshot` as the last known-good value, so a collector failing on the
+    /// first ticks after a restart falls back to the reading from before it.
+    pub(super) fn from_snapshot(snapshot: &FullSystemSnapshot) -> Self {
+        Self {
+            cpu: Some(snapshot.cpu.clone()),
+            ram: Some(snapshot.ram.clone()),
+            storage: Some(snapshot.storage.clone()),
+            network: Some(snapshot.network.clone()),
+            system: Some(snapshot.system.clone()),
+        }
+    }
+}
@@ -1,48 +1,102 @@
 // Background stats worker (same logic as Kotlin StatsWorker).
 // Collection runs in the worker; persistence runs in a dedicated history writer task (channel).
+// Collector failures are recorded (rate-limited per source) in the `collection_errors` table.
+// With no WebSocket client the tick slows to `idle_sample_interval_ms` (see `IdleSampler`).
+// Each snapshot also carries the server's own resource usage (`self_stats::SelfMonitor`).
+// At startup the newest stored snapshot can stand in for the first tick (`prime_from_history`).
 
+mod broadcast_metrics;
+mod collect;
+mod collection_metrics;
+mod disk_guard;
+mod error_limiter;
+mod flush_metrics;
 mod history_writer;
+mod idle;
+mod last_good;
+mod prime;
+mod run;
+mod schedule;
+mod self_stats;
+mod tick;
+mod write_queue;
 
-use crate::alerting::{AlertEngine, Notifier};
-use crate::docker_repo::DockerRepo;
+use crate::aggregation_worker::AggregationMetrics;
+use crate::collection_pause::CollectionPause;
+use crate::config::AppConfig;
 use crate::gpu_repo::GpuRepo;
-use crate::history_repo::HistoryRepo;
+use crate::history_repo::HistoryHandle;
+use crate::http_checks::CheckSet;
 use crate::models::{FullSystemSnapshot, SystemInfo};
+use crate::probes::ProbeSet;
+use crate::sensors_repo::SensorsRepo;
 use crate::smart_repo::SmartRepo;
-use crate::sysinfo_repo::SysinfoRepo;
-pub use history_writer::spawn_history_writer;
+use crate::supervisor::{Backoff, supervise};
+use crate::ws_connections::WsConnections;
+pub use broadcast_metrics::{BroadcastMetrics, BroadcastMetricsSnapshot, snapshot_json_len};
+pub use collect::{ContainerCollector, HostCollector, StatsCollector};
+pub use collection_metrics::{
+    COLLECTION_SOURCES, CollectionMetrics, CollectionMetricsSnapshot, CollectionTimings,
+    TOTAL_TIMING, TimingStats, slowest_source,
+};
+pub use error_limiter::ErrorRateLimiter;
+pub use flush_metrics::{FlushMetrics, FlushMetricsSnapshot};
+pub use history_writer::{
+    spawn_history_writer, spawn_history_writer_reloadable, timestamp_plausible,
+};
+pub use idle::IdleSampler;
+pub use prime::{PRIME_WAIT, prime_from_history};
+use run::Shared;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, AtomicUsize};
-use tokio::sync::{broadcast, mpsc};
-use tokio::time::{Duration, Instant, interval};
+use std::sync::atomic::AtomicU64;
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+pub use write_queue::{
+    OverflowPolicy, SendOutcome, WriteQueueMetrics, WriteReceiver, WriteSender, write_queue,
+};
 
-/// Rate limit for "no receivers" warning (avoid logging every second when no one is on /ws/system)
-const NO_RECEIVERS_WARN_INTERVAL: Duration = Duration::from_secs(60);
-
-/// Channel capacity for snapshot writer (backpressure if writer falls behind).
+/// Capacity of the snapshot queue to the history writer (overflow policy applies beyond it).
 pub fn writer_channel_capacity(flush_rate: u64) -> usize {
     (flush_rate as usize * 2).max(32)
 }
 
 /// Repos, channels, and shutdown for the worker.
 pub struct WorkerDeps {
-    pub sysinfo_repo: Arc<SysinfoRepo>,
+    /// CPU, RAM, container, storage, network and system readings ([`HostCollector`] in production).
+    pub collector: Arc<dyn StatsCollector>,
     pub system_info: Arc<SystemInfo>,
-    pub docker_repo: Arc<DockerRepo>,
     pub gpu_repo: Arc<GpuRepo>,
     pub smart_repo: Arc<SmartRepo>,
-    pub history_repo: Arc<HistoryRepo>,
+    pub sensors_repo: Arc<SensorsRepo>,
+    /// Disabled in agent mode (`database.enabled = false`), when `write_tx` is `None`; errors
+    /// are recorded and old rows pruned once it is ready. `Some(repo).into()` for an open repo.
+    pub history_repo: HistoryHandle,
     pub tx: broadcast::Sender<FullSystemSnapshot>,
-    pub write_tx: mpsc::Sender<FullSystemSnapshot>,
-    pub ws_system_connections: Arc<AtomicUsize>,
+    /// Queue to the history writer; never blocks the tick (see [`OverflowPolicy`]).
+    pub write_tx: Option<WriteSender>,
+    /// Open WebSocket connections; connecting wakes an idle worker.
+    pub ws_connections: Arc<WsConnections>,
     pub snapshots_saved_total: Arc<AtomicU64>,
-    pub alert_engine: AlertEngine,
-    pub notifier: Notifier,
+    /// Raw rows removed by the prune tick are counted here.
+    pub aggregation_metrics: Arc<AggregationMetrics>,
+    /// Per-tick collection time.
+    pub collection_metrics: Arc<CollectionMetrics>,
+    /// Broadcast queue use and serialized snapshot size.
+    pub broadcast_metrics: Arc<BroadcastMetrics>,
+    /// Restarts after a panic, across the supervised tasks.
+    pub worker_restarts_total: Arc<AtomicU64>,
+    /// While paused, ticks skip collection, broadcasting and persistence.
+    pub pause: Arc<CollectionPause>,
+    /// Probe results ([`crate::probes`]), copied into every snapshot.
+    pub probes: Arc<ProbeSet>,
+    /// HTTP check states ([`crate::http_checks`]), summarized in every snapshot.
+    pub checks: Arc<CheckSet>,
     pub shutdown_rx: tokio::sync::oneshot::Receiver<()>,
 }
 
 /// Worker timing and logging config.
 /// Stats logging and pruning use real-time intervals, independent of sample_interval_ms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct WorkerConfig {
     pub sample_interval_ms: u64,
     /// How often to log app stats (real seconds).
@@ -55,184 +109,150 @@ pub struct WorkerConfig {
     pub collect_smart: bool,
     /// How often to refresh SMART data (real seconds).
     pub smart_poll_interval_secs: u64,
+    /// Read hwmon sensors each tick.
+    pub collect_sensors: bool,
+    /// Record at most one failure per collector every N seconds.
+    pub error_record_interval_secs: u64,
+    /// Re-collect storage / Docker / system stats every N ms (multiples of `sample_interval_ms`).
+    pub storage_interval_ms: u64,
+    pub docker_interval_ms: u64,
+    pub system_interval_ms: u64,
+    /// Tick interval once no WebSocket client has been connected for `idle_grace_secs`
+    /// (`None`: always `sample_interval_ms`).
+    pub idle_sample_interval_ms: Option<u64>,
+    pub idle_grace_secs: u64,
+    /// Warn (and count) when a snapshot serializes to more than this many bytes.
+    pub max_snapshot_bytes: u64,
 }
 
 /// Writer config: batching for the dedicated history writer task.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HistoryWriterConfig {
     pub flush_rate: u64,
     pub flush_interval_secs: u64,
+    /// Warn when one flush takes longer than this (ms, 0 = never).
+    pub flush_warn_ms: u64,
     /// When false, GPU data is dropped before persisting (live WS still includes it).
     pub persist_gpu: bool,
     /// When false, SMART data is dropped before persisting (live WS still includes it).
     pub persist_smart: bool,
+    /// Drop snapshots stamped before this (ms since the epoch); see [`timestamp_plausible`].
+    pub min_snapshot_timestamp_ms: u64,
+    /// Drop snapshots stamped more than N minutes ahead of the writer's clock.
+    pub max_future_skew_minutes: u64,
+    /// Drop batches instead of flushing them while the database's filesystem has less free
+    /// (bytes, 0 = never check).
+    pub min_free_bytes: u64,
+}
+
+impl WorkerConfig {
+    /// The `[monitoring]` settings plus `database.prune_interval_secs`.
+    pub fn from_app(config: &AppConfig) -> Self {
+        let monitoring = &config.monitoring;
+        let [storage_interval_ms, docker_interval_ms, system_interval_ms] =
+            monitoring.subsystem_intervals_ms().map(|(_, ms)| ms);
+        Self {
+            sample_interval_ms: monitoring.sample_interval_ms,
+            stats_log_interval_secs: monitoring.stats_log_interval_secs,
+            prune_interval_secs: config.database.prune_interval_secs,
+            collect_gpu: monitoring.collect_gpu,
+            collect_smart: monitoring.collect_smart,
+            smart_poll_interval_secs: monitoring.smart_poll_interval_secs,
+            collect_sensors: monitoring.collect_sensors,
+            error_record_interval_secs: monitoring.error_record_interval_secs,
+            storage_interval_ms,
+            docker_interval_ms,
+            system_interval_ms,
+            idle_sample_interval_ms: monitoring.idle_sample_interval_ms,
+            idle_grace_secs: monitoring.idle_grace_secs,
+            max_snapshot_bytes: config.publishing.max_snapshot_bytes,
+        }
+    }
+}
+
+impl HistoryWriterConfig {
+    /// The writer's `[database]` settings.
+    pub fn from_app(config: &AppConfig) -> Self {
+        let database = &config.database;
+        Self {
+            flush_rate: database.flush_rate,
+            flush_interval_secs: database.flush_interval_secs,
+            flush_warn_ms: database.flush_warn_ms,
+            persist_gpu: database.persist_gpu,
+            persist_smart: database.persist_smart,
+            min_snapshot_timestamp_ms: database.min_snapshot_timestamp_ms,
+            max_future_skew_minutes: database.max_future_skew_minutes,
+            min_free_bytes: database.min_free_bytes,
+        }
+    }
 }
 
+/// Start the worker under [`supervise`]: a panicking tick restarts the loop (with backoff)
+/// instead of freezing every chart. The handle resolves after `shutdown_rx` fires.
 pub fn spawn(deps: WorkerDeps, config: WorkerConfig) -> tokio::task::JoinHandle<()> {
+    spawn_reloadable(deps, watch::channel(config).1)
+}
+
+/// [`spawn`] following `config`: each new value (a config reload) restarts the loop's timers.
+pub fn spawn_reloadable(
+    deps: WorkerDeps,
+    config: watch::Receiver<WorkerConfig>,
+) -> tokio::task::JoinHandle<()> {
     let WorkerDeps {
-        sysinfo_repo,
+        collector,
         system_info: _,
-        docker_repo,
         gpu_repo,
         smart_repo,
+        sensors_repo,
         history_repo,
         tx,
         write_tx,
-        ws_system_connections,
+        ws_connections,
         snapshots_saved_total,
-        mut alert_engine,
-        notifier,
-        mut shutdown_rx,
+        aggregation_metrics,
+        collection_metrics,
+        broadcast_metrics,
+        worker_restarts_total,
+        pause,
+        probes,
+        checks,
+        shutdown_rx,
     } = deps;
-    let WorkerConfig {
-        sample_interval_ms,
-        stats_log_interval_secs,
-        prune_interval_secs,
-        collect_gpu,
-        collect_smart,
-        smart_poll_interval_secs,
-    } = config;
-
-    let stats_log_interval = Duration::from_secs(stats_log_interval_secs);
-    let prune_interval = Duration::from_secs(prune_interval_secs);
-
+    let shared = Shared {
+        collector,
+        gpu_repo,
+        smart_repo,
+        sensors_repo,
+        history_repo,
+        tx,
+        write_tx: write_tx.map(Arc::new),
+        ws_connections,
+        snapshots_saved_total,
+        aggregation_metrics,
+        collection_metrics,
+        broadcast_metrics,
+        worker_restarts_total: worker_restarts_total.clone(),
+        pause,
+        probes,
+        checks,
+    };
+    let shutdown = CancellationToken::new();
+    let token = shutdown.clone();
+    // A dropped sender counts as shutdown too, as it did before.
     tokio::spawn(async move {
-        let mut tick = interval(Duration::from_millis(sample_interval_ms));
-        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-        let mut stats_log_tick = interval(stats_log_interval);
-        stats_log_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-        let mut prune_tick = interval(prune_interval);
-        prune_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-        let mut smart_tick = interval(Duration::from_secs(smart_poll_interval_secs.max(1)));
-        smart_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-        let mut snapshots_pruned_total: u64 = 0;
-        let mut last_no_receivers_warn: Option<Instant> = None;
-
-        let worker_span = tracing::span!(tracing::Level::DEBUG, "worker", sample_interval_ms);
-        let _guard = worker_span.enter();
+        let _ = shutdown_rx.await;
+        token.cancel();
+    });
 
-        loop {
-            tokio::select! {
-                _ = tick.tick() => {
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_millis() as u64)
-                .unwrap_or_else(|e| {
-                    tracing::warn!(
-                        error = %e,
-                        operation = "get_timestamp",
-                        "system time error"
-                    );
-                    0
-                });
-
-            // Degrade gracefully: a single failing collector falls back to defaults for that
-            // metric rather than dropping the whole tick (which would lose the healthy metrics too).
-            let cpu = sysinfo_repo.get_cpu_stats().await.unwrap_or_else(|e| {
-                tracing::warn!(error = %e, operation = "get_cpu_stats", "CPU stats failed; using defaults");
-                Default::default()
-            });
-            let ram = sysinfo_repo.get_ram_stats().await.unwrap_or_else(|e| {
-                tracing::warn!(error = %e, operation = "get_ram_stats", "RAM stats failed; using defaults");
-                Default::default()
-            });
-            let containers = docker_repo.list_running_and_refresh_stats().await;
-            let storage = sysinfo_repo.get_storage_stats().await.unwrap_or_else(|e| {
-                tracing::warn!(error = %e, operation = "get_storage_stats", "storage stats failed; using defaults");
-                Default::default()
-            });
-            let network = sysinfo_repo.get_network_stats().await.unwrap_or_else(|e| {
-                tracing::warn!(error = %e, operation = "get_network_stats", "network stats failed; using defaults");
-                Default::default()
-            });
-            let system = sysinfo_repo.get_system_stats().await.unwrap_or_else(|e| {
-                tracing::warn!(error = %e, operation = "get_system_stats", "system stats failed; using defaults");
-                Default::default()
-            });
-            // GPU collection does blocking sysfs reads / NVML ioctls — offload to the blocking
-            // pool so it never stalls the async executor (and other tasks like WS connections).
-            let gpus = if collect_gpu {
-                let gpu_repo = gpu_repo.clone();
-                tokio::task::spawn_blocking(move || gpu_repo.collect())
-                    .await
-                    .unwrap_or_default()
-            } else {
-                Vec::new()
-            };
-            // SMART is refreshed on its own slow cadence (smart_tick); read the cached value here.
-            let smart = smart_repo.current();
-
-            let snapshot = FullSystemSnapshot {
-                timestamp,
-                cpu,
-                ram,
-                containers,
-                storage,
-                network,
-                system,
-                gpus,
-                smart,
-            };
-
-            // Evaluate alert rules and dispatch any fire/resolve events (webhook POST is detached).
-            if !alert_engine.is_empty() {
-                for ev in alert_engine.evaluate(&snapshot, std::time::Instant::now()) {
-                    let notifier = notifier.clone();
-                    tokio::spawn(async move { notifier.notify(&ev).await });
-                }
-            }
-
-            // Only clone for the broadcast when someone is actually listening.
-            if tx.receiver_count() > 0 {
-                let _ = tx.send(snapshot.clone());
-            } else {
-                let should_warn = last_no_receivers_warn
-                    .is_none_or(|t| t.elapsed() >= NO_RECEIVERS_WARN_INTERVAL);
-                if should_warn {
-                    tracing::debug!(
-                        operation = "broadcast_snapshot",
-                        "No active WebSocket clients; skipping broadcast"
-                    );
-                    last_no_receivers_warn = Some(Instant::now());
-                }
-            }
-            if write_tx.send(snapshot).await.is_err() {
-                tracing::debug!("History writer channel closed");
-            }
-                }
-                _ = &mut shutdown_rx => {
-                    tracing::debug!("Worker shutting down");
-                    break;
-                }
-                _ = stats_log_tick.tick() => {
-                    tracing::info!(
-                        ws_system_clients =
-                            ws_system_connections.load(std::sync::atomic::Ordering::Relaxed),
-                        snapshots_saved_total = snapshots_saved_total.load(std::sync::atomic::Ordering::Relaxed),
-                        snapshots_pruned_total = snapshots_pruned_total,
-                        "app stats"
-                    );
-                }
-                _ = smart_tick.tick() => {
-                    // smartctl is slow/blocking; refresh in a detached task so the loop stays responsive.
-                    if collect_smart {
-                        let repo = smart_repo.clone();
-                        tokio::spawn(async move { repo.refresh().await });
-                    }
-                }
-                _ = prune_tick.tick() => {
-                    if let Err(e) = history_repo.prune_old_data().await {
-                        tracing::warn!(
-                            error = %e,
-                            operation = "prune_old_data",
-                            "Failed to prune old data"
-                        );
-                    } else {
-                        tracing::debug!(operation = "prune_old_data", "Old data pruned successfully");
-                        snapshots_pruned_total += 1;
-                    }
-                }
-            }
-        }
+    tokio::spawn(async move {
+        let token = shutdown.clone();
+        supervise(
+            "worker",
+            worker_restarts_total,
+            Backoff::default(),
+            shutdown,
+            move || run::run(shared.clone(), config.clone(), token.clone()),
+        )
+        .await
     })
 }
@@ -0,0 +1,54 @@
+// Startup recovery of the live view: the newest stored snapshot, when recent, stands in for the
+// first tick, so /api/bootstrap and /ws/system clients do not start blank after a restart.
+
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use super::BroadcastMetrics;
+use crate::history_repo::{HistoryResult, HistoryStore};
+use crate::models::FullSystemSnapshot;
+
+/// How long `main` holds back the worker's first tick for the history startup to prime.
+pub const PRIME_WAIT: Duration = Duration::from_secs(2);
+
+/// Prime the live view from `repo`: the newest raw snapshot, when at most `max_age` old, becomes
+/// the latest snapshot in `broadcast_metrics` (and so the worker's last known-good readings when
+/// it starts) unless a tick already recorded one, and with `tx` is sent once with `historical`
+/// set. Returns the snapshot primed from; `None` when nothing recent is stored or a tick won.
+pub async fn prime_from_history(
+    repo: &dyn HistoryStore,
+    max_age: Duration,
+    broadcast_metrics: &BroadcastMetrics,
+    tx: Option<&broadcast::Sender<FullSystemSnapshot>>,
+) -> HistoryResult<Option<FullSystemSnapshot>> {
+    let (_, mut recent) = repo.get_recent_snapshots(1).await?;
+    let Some(mut snapshot) = recent.pop() else {
+        return Ok(None);
+    };
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as u64;
+    let age_ms = now_ms.saturating_sub(snapshot.timestamp);
+    if age_ms > max_age.as_millis() as u64 {
+        tracing::debug!(
+            age_ms,
+            "newest stored snapshot too old to prime the live view"
+        );
+        return Ok(None);
+    }
+    snapshot.historical = true;
+    if !broadcast_metrics.prime_latest(&snapshot) {
+        return Ok(None);
+    }
+    if let Some(tx) = tx {
+        // Nobody subscribed yet is fine: the first tick follows shortly anyway.
+        let _ = tx.send(snapshot.clone());
+    }
+    tracing::info!(
+        age_ms,
+        timestamp = snapshot.timestamp,
+        "live view primed from history"
+    );
+    Ok(Some(snapshot))
+}
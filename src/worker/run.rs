@@ -0,0 +1,194 @@
+// The stats worker loop: one collection tick per (adaptive) interval plus stats logging, SMART
+// refresh and pruning timers. Started, and restarted after a panic, by `super::spawn`. The tick
+// itself (collect, broadcast, queue for the history writer) is in `tick.rs`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::{broadcast, watch};
+use tokio::time::{Duration, Instant, interval, interval_at};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::collection_metrics::TOTAL_TIMING;
+use super::tick::TickState;
+use super::write_queue::WriteSender;
+use super::{BroadcastMetrics, CollectionMetrics, IdleSampler, StatsCollector, WorkerConfig};
+use crate::aggregation_worker::AggregationMetrics;
+use crate::collection_pause::CollectionPause;
+use crate::gpu_repo::GpuRepo;
+use crate::history_repo::HistoryHandle;
+use crate::http_checks::CheckSet;
+use crate::models::FullSystemSnapshot;
+use crate::probes::ProbeSet;
+use crate::sensors_repo::SensorsRepo;
+use crate::smart_repo::SmartRepo;
+use crate::ws_connections::{WsChannel, WsConnections};
+
+/// What survives a restart: everything from `WorkerDeps` except the shutdown receiver.
+#[derive(Clone)]
+pub(super) struct Shared {
+    pub collector: Arc<dyn StatsCollector>,
+    pub gpu_repo: Arc<GpuRepo>,
+    pub smart_repo: Arc<SmartRepo>,
+    pub sensors_repo: Arc<SensorsRepo>,
+    pub history_repo: HistoryHandle,
+    pub tx: broadcast::Sender<FullSystemSnapshot>,
+    pub write_tx: Option<Arc<WriteSender>>,
+    pub ws_connections: Arc<WsConnections>,
+    pub snapshots_saved_total: Arc<AtomicU64>,
+    pub aggregation_metrics: Arc<AggregationMetrics>,
+    pub collection_metrics: Arc<CollectionMetrics>,
+    pub broadcast_metrics: Arc<BroadcastMetrics>,
+    pub worker_restarts_total: Arc<AtomicU64>,
+    pub pause: Arc<CollectionPause>,
+    pub probes: Arc<ProbeSet>,
+    pub checks: Arc<CheckSet>,
+}
+
+/// Run the loop with the latest `config_rx` value, starting over (fresh timers, schedule and
+/// idle state) whenever a config reload changes it.
+pub(super) async fn run(
+    shared: Shared,
+    mut config_rx: watch::Receiver<WorkerConfig>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let config = *config_rx.borrow_and_update();
+        if !run_with(shared.clone(), config, &mut config_rx, &shutdown).await {
+            break;
+        }
+    }
+}
+
+/// One run with a fixed config; returns true when the config changed, false on shutdown.
+async fn run_with(
+    shared: Shared,
+    config: WorkerConfig,
+    config_rx: &mut watch::Receiver<WorkerConfig>,
+    shutdown: &CancellationToken,
+) -> bool {
+    let WorkerConfig {
+        sample_interval_ms,
+        stats_log_interval_secs,
+        prune_interval_secs,
+        collect_smart,
+        smart_poll_interval_secs,
+        idle_sample_interval_ms,
+        idle_grace_secs,
+        ..
+    } = config;
+    let mut state = TickState::new(&shared, config);
+    let mut sampler = IdleSampler::new(
+        Duration::from_millis(sample_interval_ms),
+        idle_sample_interval_ms.map(Duration::from_millis),
+        Duration::from_secs(idle_grace_secs),
+    );
+
+    let stats_log_interval = Duration::from_secs(stats_log_interval_secs);
+    let prune_interval = Duration::from_secs(prune_interval_secs);
+
+    let mut tick = interval(Duration::from_millis(sample_interval_ms));
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut stats_log_tick = interval(stats_log_interval);
+    stats_log_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut prune_tick = interval(prune_interval);
+    prune_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut smart_tick = interval(Duration::from_secs(smart_poll_interval_secs.max(1)));
+    smart_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let mut snapshots_pruned_total: u64 = 0;
+    let collection_metrics = &shared.collection_metrics;
+    let ws_connections = &shared.ws_connections;
+    let history_repo = &shared.history_repo;
+
+    let worker_span = tracing::span!(tracing::Level::DEBUG, "worker", sample_interval_ms);
+    let _guard = worker_span.enter();
+
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {
+                collection_metrics.beat();
+                // Paused: nothing is collected, sent or stored until resumed.
+                if shared.pause.is_paused() {
+                    continue;
+                }
+                let interval_ms = sampler.interval().as_millis() as u64;
+                // Each tick is its own trace (the long-lived "worker" span is not its parent).
+                let tick_span = tracing::info_span!(parent: None, "worker_tick", interval_ms);
+                state
+                    .tick(&shared, sampler.interval())
+                    .instrument(tick_span)
+                    .await;
+                if let Some(next) = sampler.observe(ws_connections.total(), Instant::now()) {
+                    tracing::info!(
+                        interval_ms = next.as_millis() as u64,
+                        idle = sampler.is_idle(),
+                        "sampling interval changed"
+                    );
+                    tick = interval_at(Instant::now() + next, next);
+                    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                }
+            }
+            _ = ws_connections.connected() => {
+                if let Some(fast) = sampler.on_connect() {
+                    tracing::info!(interval_ms = fast.as_millis() as u64, "WebSocket client connected; sampling resumed");
+                    // A fresh interval ticks immediately, so the new client gets data right away.
+                    tick = interval(fast);
+                    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                }
+            }
+            _ = shutdown.cancelled() => {
+                tracing::debug!("Worker shutting down");
+                return false;
+            }
+            // A dropped sender (no reloader) disables this branch.
+            Ok(()) = config_rx.changed() => {
+                tracing::info!(
+                    sample_interval_ms = config_rx.borrow().sample_interval_ms,
+                    "worker config reloaded; restarting timers"
+                );
+                return true;
+            }
+            _ = stats_log_tick.tick() => {
+                let total = collection_metrics.timings().remove(TOTAL_TIMING).unwrap_or_default();
+                tracing::info!(
+                    service_uptime_secs = crate::version::service_uptime_secs(),
+                    ws_system_clients = ws_connections.get(WsChannel::System),
+                    snapshots_saved_total = shared.snapshots_saved_total.load(Ordering::Relaxed),
+                    snapshots_pruned_total = snapshots_pruned_total,
+                    worker_restarts_total = shared.worker_restarts_total.load(Ordering::Relaxed),
+                    rss_bytes = collection_metrics.latest_self().map(|s| s.rss_bytes),
+                    collection_p95_ms = total.p95_ms,
+                    collection_max_ms = total.max_ms,
+                    // mean/p95/max ms per source over the recent ticks
+                    collection_timings = %collection_metrics.timings_summary(),
+                    "app stats"
+                );
+            }
+            _ = smart_tick.tick() => {
+                // smartctl is slow/blocking; refresh in a detached task so the loop stays responsive.
+                if collect_smart {
+                    let repo = shared.smart_repo.clone();
+                    tokio::spawn(async move { repo.refresh().await });
+                }
+            }
+            _ = prune_tick.tick(), if history_repo.is_enabled() => {
+                // Still opening the database: the next tick tries again.
+                let Some(history_repo) = history_repo.get() else { continue };
+                match history_repo.prune_old_data().await {
+                    Ok(rows) => {
+                        tracing::debug!(operation = "prune_old_data", "Old data pruned successfully");
+                        snapshots_pruned_total += 1;
+                        shared.aggregation_metrics.record_raw_prune(rows);
+                    }
+                    Err(e) => tracing::warn!(
+                        error = %e,
+                        operation = "prune_old_data",
+                        "Failed to prune old data"
+                    ),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// Per-subsystem sampling: storage, Docker and system stats can be re-collected less often than
+// CPU and RAM, which are read every tick.
+
+/// Which of the slower subsystems are collected on a given tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Due {
+    pub storage: bool,
+    pub containers: bool,
+    pub system: bool,
+}
+
+/// Due times on a nominal clock: the sum of the intervals the worker ticked at, so timer jitter
+/// never shifts a subsystem by a tick, and one idle tick (see `IdleSampler`) covers several
+/// fast intervals.
+pub(super) struct Schedule {
+    /// Storage, Docker, system.
+    intervals_ms: [u64; 3],
+    next_ms: [u64; 3],
+}
+
+impl Schedule {
+    pub fn new(storage_interval_ms: u64, docker_interval_ms: u64, system_interval_ms: u64) -> Self {
+        Self {
+            intervals_ms: [storage_interval_ms, docker_interval_ms, system_interval_ms],
+            next_ms: [0; 3],
+        }
+    }
+
+    /// Subsystems due at nominal time `now_ms` (the first tick, at 0, collects everything).
+    pub fn due(&mut self, now_ms: u64) -> Due {
+        let mut due = [false; 3];
+        for (i, flag) in due.iter_mut().enumerate() {
+            if now_ms >= self.next_ms[i] {
+                *flag = true;
+                self.next_ms[i] = now_ms + self.intervals_ms[i];
+            }
+        }
+        Due {
+            storage: due[0],
+            containers: due[1],
+            system: due[2],
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Self-monitoring: this process's CPU, RSS and open descriptors (sysinfo), the tokio runtime's
+// live task count and the history database's size, sampled once per tick.
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::history_repo::HistoryRepo;
+use crate::models::SelfStats;
+
+/// Samples the current process; CPU is measured between calls.
+pub(super) struct SelfMonitor {
+    sys: System,
+    pid: Pid,
+}
+
+impl SelfMonitor {
+    pub(super) fn new() -> Self {
+        Self {
+            sys: System::new(),
+            pid: Pid::from_u32(std::process::id()),
+        }
+    }
+
+    /// Refresh only this process (a few `/proc` reads) and read the database file size (SQLite
+    /// only).
+    pub(super) fn sample(&mut self, history_repo: Option<&HistoryRepo>) -> SelfStats {
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[self.pid]),
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+        let process = self.sys.process(self.pid);
+        SelfStats {
+            cpu_percent: process.map_or(0.0, |p| f64::from(p.cpu_usage())),
+            rss_bytes: process.map_or(0, |p| p.memory()),
+            open_fds: process.and_then(|p| p.open_files()).map(|n| n as u32),
+            tokio_tasks: tokio::runtime::Handle::try_current()
+                .ok()
+                .map(|rt| rt.metrics().num_alive_tasks() as u64),
+            db_file_bytes: history_repo.and_then(|repo| {
+                repo.file_size()
+                    .inspect_err(|e| tracing::debug!(error = %e, "database file size unavailable"))
+                    .ok()
+            }),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+// One collection tick of the stats worker: collect, build the snapshot, broadcast it and queue
+// it for the history writer. The loop, its timers and the idle sampler are in `run.rs`.
+
+use tokio::time::{Duration, Instant};
+
+use super::WorkerConfig;
+use super::broadcast_metrics::snapshot_json_len;
+use super::collect::{SlowTickWarning, collect_all, snapshot_timestamp};
+use super::collection_metrics::slowest_source;
+use super::error_limiter::{ErrorRateLimiter, record_failures};
+use super::last_good::LastGood;
+use super::run::Shared;
+use super::schedule::Schedule;
+use super::self_stats::SelfMonitor;
+use super::write_queue::SendOutcome;
+use crate::models::FullSystemSnapshot;
+
+/// Rate limit for "no receivers" warning (avoid logging every second when no one is on /ws/system)
+const NO_RECEIVERS_WARN_INTERVAL: Duration = Duration::from_secs(60);
+/// Rate limit for the "collection slower than the sample interval" warning.
+const SLOW_COLLECTION_WARN_INTERVAL: Duration = Duration::from_secs(60);
+/// Rate limit for the "history writer queue full" warning.
+const QUEUE_FULL_WARN_INTERVAL: Duration = Duration::from_secs(60);
+/// Rate limit for the "snapshot larger than max_snapshot_bytes" warning.
+const OVERSIZED_WARN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What one run of the loop carries from tick to tick; rebuilt when the config changes.
+pub(super) struct TickState {
+    config: WorkerConfig,
+    schedule: Schedule,
+    /// Nominal time for the subsystem schedule: the sum of the intervals ticked at so far.
+    nominal_ms: u64,
+    last_good: LastGood,
+    self_monitor: SelfMonitor,
+    error_limiter: ErrorRateLimiter,
+    slow_warning: SlowTickWarning,
+    last_no_receivers_warn: Option<Instant>,
+    last_queue_full_warn: Option<Instant>,
+    dropped_since_warn: u64,
+    last_oversized_warn: Option<Instant>,
+}
+
+impl TickState {
+    pub(super) fn new(shared: &Shared, config: WorkerConfig) -> Self {
+        Self {
+            config,
+            schedule: Schedule::new(
+                config.storage_interval_ms,
+                config.docker_interval_ms,
+                config.system_interval_ms,
+            ),
+            nominal_ms: 0,
+            // Start from the latest snapshot: the previous run's, or the one primed from history.
+            last_good: shared
+                .broadcast_metrics
+                .latest_snapshot()
+                .map(|snapshot| LastGood::from_snapshot(&snapshot))
+                .unwrap_or_default(),
+            self_monitor: SelfMonitor::new(),
+            error_limiter: ErrorRateLimiter::new(Duration::from_secs(
+                config.error_record_interval_secs,
+            )),
+            slow_warning: SlowTickWarning::default(),
+            last_no_receivers_warn: None,
+            last_queue_full_warn: None,
+            dropped_since_warn: 0,
+            last_oversized_warn: None,
+        }
+    }
+
+    /// Collect and publish one snapshot; `interval` is the current sampling interval.
+    pub(super) async fn tick(&mut self, shared: &Shared, interval: Duration) {
+        let timestamp = snapshot_timestamp();
+        let due = self.schedule.due(self.nominal_ms);
+        self.nominal_ms += interval.as_millis() as u64;
+        let collected = collect_all(shared.collector.as_ref(), due, &mut self.last_good).await;
+        let metrics = &shared.collection_metrics;
+        let slowest = slowest_source(&collected.timings);
+        let slow = self.slow_warning.observe(
+            collected.elapsed,
+            interval,
+            SLOW_COLLECTION_WARN_INTERVAL,
+            slowest,
+        );
+        metrics.record(collected.elapsed, slow);
+        metrics.record_timings(&collected.timings, collected.elapsed);
+        if slow && let Some((source, _)) = slowest {
+            metrics.record_slow_source(source);
+        }
+        metrics.record_failures(collected.failures.iter().map(|(s, _)| *s));
+        if let Some(history_repo) = shared.history_repo.get() {
+            record_failures(
+                history_repo,
+                &mut self.error_limiter,
+                timestamp,
+                collected.failures,
+            );
+        }
+        // GPU collection does blocking sysfs reads / NVML ioctls — offload to the blocking
+        // pool so it never stalls the async executor (and other tasks like WS connections).
+        let gpus = if self.config.collect_gpu {
+            let gpu_repo = shared.gpu_repo.clone();
+            tokio::task::spawn_blocking(move || gpu_repo.collect())
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        // Sensors are plain sysfs reads too, blocking like the GPU ones.
+        let sensors = if self.config.collect_sensors {
+            let sensors_repo = shared.sensors_repo.clone();
+            tokio::task::spawn_blocking(move || sensors_repo.collect())
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        // SMART is refreshed on its own slow cadence (smart_tick); read the cached value here.
+        let smart = shared.smart_repo.current();
+        let self_stats = self
+            .self_monitor
+            .sample(shared.history_repo.get().and_then(|repo| repo.as_sqlite()));
+        metrics.record_self(self_stats.clone());
+
+        let snapshot = FullSystemSnapshot {
+            timestamp,
+            cpu: collected.cpu,
+            ram: collected.ram,
+            containers: collected.containers,
+            storage: collected.storage,
+            network: collected.network,
+            system: collected.system,
+            gpus,
+            smart,
+            // Probed on their own schedule (`crate::probes`); the latest results.
+            probes: shared.probes.current(),
+            sensors,
+            checks: shared.checks.summary(),
+            degraded: collected.degraded,
+            self_stats: Some(self_stats),
+            historical: false,
+        };
+        self.publish(shared, snapshot);
+    }
+
+    /// Record, broadcast and queue the snapshot for the history writer.
+    fn publish(&mut self, shared: &Shared, snapshot: FullSystemSnapshot) {
+        let broadcast_metrics = &shared.broadcast_metrics;
+        let max_snapshot_bytes = self.config.max_snapshot_bytes;
+        broadcast_metrics.record_latest(&snapshot);
+        let bytes = snapshot_json_len(&snapshot);
+        if broadcast_metrics.record_snapshot_bytes(bytes, max_snapshot_bytes)
+            && self
+                .last_oversized_warn
+                .is_none_or(|t| t.elapsed() >= OVERSIZED_WARN_INTERVAL)
+        {
+            tracing::warn!(
+                bytes,
+                max_snapshot_bytes,
+                containers = snapshot.containers.len(),
+                interfaces = snapshot.network.interfaces.len(),
+                "snapshot larger than publishing.max_snapshot_bytes; each queued broadcast copy \
+                 holds this much"
+            );
+            self.last_oversized_warn = Some(Instant::now());
+        }
+        // Only clone for the broadcast when someone is actually listening.
+        let tx = &shared.tx;
+        if tx.receiver_count() > 0 {
+            let receivers = tx.send(snapshot.clone()).unwrap_or(0);
+            broadcast_metrics.record_send(tx.len(), receivers);
+        } else {
+            broadcast_metrics.record_skip();
+            let should_warn = self
+                .last_no_receivers_warn
+                .is_none_or(|t| t.elapsed() >= NO_RECEIVERS_WARN_INTERVAL);
+            if should_warn {
+                tracing::debug!(
+                    operation = "broadcast_snapshot",
+                    "No active WebSocket clients; skipping broadcast"
+                );
+                self.last_no_receivers_warn = Some(Instant::now());
+            }
+        }
+        // Agent mode (no write queue): nothing is persisted locally.
+        if let Some(write_tx) = &shared.write_tx {
+            match write_tx.send(snapshot) {
+                SendOutcome::Queued => {}
+                SendOutcome::Dropped => {
+                    self.dropped_since_warn += 1;
+                    if self
+                        .last_queue_full_warn
+                        .is_none_or(|t| t.elapsed() >= QUEUE_FULL_WARN_INTERVAL)
+                    {
+                        tracing::warn!(
+                            policy = ?write_tx.policy(),
+                            dropped = self.dropped_since_warn,
+                            "history writer queue full; dropping snapshots"
+                        );
+                        self.last_queue_full_warn = Some(Instant::now());
+                        self.dropped_since_warn = 0;
+                    }
+                }
+                SendOutcome::Closed => tracing::debug!("History writer channel closed"),
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Bounded snapshot queue between the worker and the history writer. Pushing never waits: when
+// the writer falls behind (stalled disk) the overflow policy drops a snapshot instead of
+// holding up the tick and the live broadcast.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use crate::models::FullSystemSnapshot;
+
+/// Which snapshot goes when the queue is full (`database.overflow_policy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Keep the queued snapshots, discard the new one.
+    DropNew,
+    /// Discard the oldest queued snapshot to make room.
+    DropOldest,
+}
+
+impl OverflowPolicy {
+    /// Parse a validated `database.overflow_policy` value ("drop_new" or "drop_oldest").
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "drop_oldest" => Self::DropOldest,
+            _ => Self::DropNew,
+        }
+    }
+}
+
+/// Queue depth and drops, shared with `/api/stats`.
+#[derive(Debug, Default)]
+pub struct WriteQueueMetrics {
+    depth: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl WriteQueueMetrics {
+    /// Snapshots waiting for the history writer.
+    pub fn depth(&self) -> u64 {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// Snapshots discarded by the overflow policy since start.
+    pub fn dropped_total(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Result of [`WriteSender::send`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendOutcome {
+    Queued,
+    /// The queue was full; one snapshot was dropped according to the policy.
+    Dropped,
+    /// The writer is gone; the snapshot was discarded.
+    Closed,
+}
+
+struct State {
+    items: VecDeque<FullSystemSnapshot>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    ready: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+    metrics: Arc<WriteQueueMetrics>,
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Worker side. Dropping it lets the writer drain the queue and exit.
+pub struct WriteSender {
+    shared: Arc<Shared>,
+}
+
+/// History writer side.
+pub struct WriteReceiver {
+    shared: Arc<Shared>,
+}
+
+/// A queue holding at most `capacity` snapshots (at least 1).
+pub fn write_queue(
+    capacity: usize,
+    policy: OverflowPolicy,
+    metrics: Arc<WriteQueueMetrics>,
+) -> (WriteSender, WriteReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            items: VecDeque::new(),
+            sender_dropped: false,
+            receiver_dropped: false,
+        }),
+        ready: Notify::new(),
+        capacity: capacity.max(1),
+        policy,
+        metrics,
+    });
+    (
+        WriteSender {
+            shared: shared.clone(),
+        },
+        WriteReceiver { shared },
+    )
+}
+
+impl WriteSender {
+    /// Queue `snapshot` without waiting; a full queue drops per the overflow policy.
+    pub fn send(&self, snapshot: FullSystemSnapshot) -> SendOutcome {
+        let shared = &self.shared;
+        let mut state = shared.lock();
+        if state.receiver_dropped {
+            return SendOutcome::Closed;
+        }
+        let mut outcome = SendOutcome::Queued;
+        if state.items.len() >= shared.capacity {
+            shared.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+            outcome = SendOutcome::Dropped;
+            match shared.policy {
+                OverflowPolicy::DropNew => return outcome,
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                }
+            }
+        }
+        state.items.push_back(snapshot);
+        shared
+            .metrics
+            .depth
+            .store(state.items.len() as u64, Ordering::Relaxed);
+        drop(state);
+        shared.ready.notify_one();
+        outcome
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.shared.policy
+    }
+}
+
+impl Drop for WriteSender {
+    fn drop(&mut self) {
+        self.shared.lock().sender_dropped = true;
+        self.shared.ready.notify_one();
+    }
+}
+
+impl WriteReceiver {
+    /// The oldest queued snapshot; `None` once the sender is dropped and the queue is empty.
+    pub async fn recv(&mut self) -> Option<FullSystemSnapshot> {
+        let shared = &self.shared;
+        loop {
+            {
+                let mut state = shared.lock();
+                if let Some(snapshot) = state.items.pop_front() {
+                    shared
+                        .metrics
+                        .depth
+                        .store(state.items.len() as u64, Ordering::Relaxed);
+                    return Some(snapshot);
+                }
+                if state.sender_dropped {
+                    return None;
+                }
+            }
+            // `notify_one` stores a permit when nobody waits, so a push between the check above
+            // and this await is not missed.
+            shared.ready.notified().await;
+        }
+    }
+}
+
+impl Drop for WriteReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receiver_dropped = true;
+        state.items.clear();
+        self.shared.metrics.depth.store(0, Ordering::Relaxed);
+    }
+}
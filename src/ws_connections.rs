@@ -0,0 +1,110 @@
+// Open WebSocket connections per channel, shared by the WS handlers, the stats worker (adaptive
+// sampling) and /api/stats; `close_all` ends them at shutdown.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+/// A WebSocket channel served by `routes::ws`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsChannel {
+    System,
+    Cpu,
+    Ram,
+}
+
+/// Connection counters per channel plus a wake-up for whoever waits on the next connect.
+#[derive(Debug, Default)]
+pub struct WsConnections {
+    system: AtomicUsize,
+    cpu: AtomicUsize,
+    ram: AtomicUsize,
+    connected: Notify,
+    /// Woken on every disconnect, for [`Self::close_all`].
+    disconnected: Notify,
+    /// Cancelled by [`Self::close_all`]; each handler then sends a Close frame and stops.
+    closing: CancellationToken,
+}
+
+/// Decrements its channel's counter on drop (connect = +1, drop = -1).
+pub struct WsConnectionGuard {
+    connections: Arc<WsConnections>,
+    channel: WsChannel,
+}
+
+impl Drop for WsConnectionGuard {
+    fn drop(&mut self) {
+        self.connections
+            .counter(self.channel)
+            .fetch_sub(1, Ordering::Relaxed);
+        self.connections.disconnected.notify_waiters();
+    }
+}
+
+impl WsConnections {
+    fn counter(&self, channel: WsChannel) -> &AtomicUsize {
+        match channel {
+            WsChannel::System => &self.system,
+            WsChannel::Cpu => &self.cpu,
+            WsChannel::Ram => &self.ram,
+        }
+    }
+
+    /// Count a new connection on `channel` until the guard drops, and wake [`Self::connected`].
+    /// Returns the guard and the channel's count including this connection.
+    pub fn connect(self: &Arc<Self>, channel: WsChannel) -> (WsConnectionGuard, usize) {
+        let current = self.counter(channel).fetch_add(1, Ordering::Relaxed) + 1;
+        self.connected.notify_one();
+        let guard = WsConnectionGuard {
+            connections: self.clone(),
+            channel,
+        };
+        (guard, current)
+    }
+
+    pub fn get(&self, channel: WsChannel) -> usize {
+        self.counter(channel).load(Ordering::Relaxed)
+    }
+
+    /// Open connections across every channel.
+    pub fn total(&self) -> usize {
+        self.get(WsChannel::System) + self.get(WsChannel::Cpu) + self.get(WsChannel::Ram)
+    }
+
+    /// Resolves on the next [`Self::connect`] (or immediately if one happened since the last
+    /// wait). Meant for a single waiter, the stats worker.
+    pub async fn connected(&self) {
+        self.connected.notified().await;
+    }
+
+    /// Cancelled once the server shuts down: handlers select on it to close their connection.
+    pub fn closing(&self) -> CancellationToken {
+        self.closing.clone()
+    }
+
+    /// Ask every handler to close its connection (Close frame, "going away"), then wait up to
+    /// `grace` for them to go. Returns how many were still open.
+    pub async fn close_all(&self, grace: Duration) -> usize {
+        self.closing.cancel();
+        let deadline = tokio::time::Instant::now() + grace;
+        loop {
+            let disconnected = self.disconnected.notified();
+            tokio::pin!(disconnected);
+            // Register before checking, so a disconnect in between is not missed.
+            disconnected.as_mut().enable();
+            let open = self.total();
+            if open == 0 {
+                return 0;
+            }
+            if tokio::time::timeout_at(deadline, disconnected)
+                .await
+                .is_err()
+            {
+                return self.total();
+            }
+        }
+    }
+}
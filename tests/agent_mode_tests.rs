@@ -0,0 +1,201 @@
+// Agent mode (`database.enabled = false`): no history repo or write queue. The router keeps the
+// live endpoints and WebSockets, answers 404 with a JSON error on the history endpoints, and the
+// worker still collects and broadcasts.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use futures_util::future::BoxFuture;
+use homeserver::config::AppConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryHandle;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::worker::{StatsCollector, WorkerConfig, WorkerDeps, spawn};
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const DISABLED: &str = "history is disabled on this instance (database.enabled = false)";
+
+fn agent_config() -> AppConfig {
+    let config = AppConfig::load_from_str("[database]\nenabled = false\n").unwrap();
+    assert!(!config.database.enabled);
+    config
+}
+
+fn agent_server(tx: broadcast::Sender<FullSystemSnapshot>) -> TestServer {
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        agent_config(),
+        None,
+        Default::default(),
+    );
+    TestServer::builder().http_transport().build(app)
+}
+
+#[test]
+fn database_is_enabled_by_default() {
+    assert!(AppConfig::default().database.enabled);
+    assert!(AppConfig::load_from_str("").unwrap().database.enabled);
+}
+
+#[tokio::test]
+async fn live_endpoints_answer_without_a_database() {
+    let (tx, _) = broadcast::channel(4);
+    let server = agent_server(tx);
+    let health = server.get("/health").await;
+    health.assert_status_ok();
+    assert_eq!(health.text(), "ok");
+    for path in [
+        "/version",
+        "/api/info",
+        "/api/stats",
+        "/api/alerts",
+        "/metrics",
+    ] {
+        server.get(path).await.assert_status_ok();
+    }
+}
+
+#[tokio::test]
+async fn history_endpoints_return_the_documented_404() {
+    let (tx, _) = broadcast::channel(4);
+    let server = agent_server(tx);
+    for path in [
+        "/api/history",
+        "/api/history?from=0&to=1000&resolution=1s",
+        "/api/history/since?ts=0",
+        "/api/errors",
+        "/api/events",
+        "/api/db",
+        "/api/db/projection",
+        "/api/db/verify",
+        "/api/db/backup/download",
+    ] {
+        let response = server.get(path).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], DISABLED, "{path}");
+    }
+    let response = server.post("/api/db/backup").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"], DISABLED);
+    // Ingest checks its key first, so agent mode is not revealed to an unauthenticated caller.
+    server
+        .post("/api/ingest")
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn websocket_streams_snapshots_in_agent_mode() {
+    let (tx, _) = broadcast::channel(4);
+    let server = agent_server(tx.clone());
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let _ = tx.send(common::snapshot(42));
+    });
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    loop {
+        let text = ws.receive_text().await;
+        if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text)
+            && v.get("timestamp").is_some()
+        {
+            assert_eq!(v["timestamp"], 42);
+            break;
+        }
+        assert!(tokio::time::Instant::now() < deadline, "no snapshot");
+    }
+}
+
+struct FixedCollector;
+
+impl StatsCollector for FixedCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(async {
+            Ok(CpuStats {
+                usage_percent: 7.0,
+                ..Default::default()
+            })
+        })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        // Failures are not recorded anywhere without a database; the tick goes on.
+        Box::pin(async { Err(anyhow::anyhow!("docker socket unavailable")) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
+    }
+}
+
+#[tokio::test]
+async fn worker_broadcasts_without_a_history_repo() {
+    let (tx, mut rx) = broadcast::channel(64);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let config = WorkerConfig {
+        sample_interval_ms: 20,
+        storage_interval_ms: 20,
+        docker_interval_ms: 20,
+        system_interval_ms: 20,
+        prune_interval_secs: 1,
+        ..WorkerConfig::from_app(&agent_config())
+    };
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(FixedCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: HistoryHandle::disabled(),
+            tx,
+            write_tx: None,
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        config,
+    );
+    let snapshot = tokio::time::timeout(Duration::from_secs(3), rx.recv())
+        .await
+        .expect("a snapshot within 3s")
+        .unwrap();
+    assert_eq!(snapshot.cpu.usage_percent, 7.0);
+    assert!(!snapshot.degraded.is_empty(), "containers marked degraded");
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+}
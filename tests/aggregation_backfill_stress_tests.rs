@@ -0,0 +1,144 @@
+// Backfill under load: bounded passes with short transactions must not starve a concurrent
+// writer (no busy-timeout failures), and run_one_tick reports remaining backlog and what it did.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, MAX_CHUNKS_PER_PASS, run_one_tick};
+use homeserver::backfill::run_backfill;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use tempfile::TempDir;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+fn worker_config(chunk_buckets: u32) -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        chunk_buckets,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        retention_days: 30,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.5,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+/// `minutes` of 10 s raw samples starting at `start`.
+async fn seed_raw(repo: &HistoryRepo, start: i64, minutes: i64) {
+    let snaps: Vec<_> = (0..minutes * 6)
+        .map(|i| snapshot(start + i * 10_000))
+        .collect();
+    for batch in snaps.chunks(2_000) {
+        repo.save_snapshots(batch, &SystemInfo::default())
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn bounded_pass_reports_remaining_backlog() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    let chunk_buckets = 5;
+    let minutes = (MAX_CHUNKS_PER_PASS as i64) * chunk_buckets + 7;
+    let start = ((common::now_ms() - 6 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    seed_raw(&repo, start, minutes).await;
+
+    let config = worker_config(chunk_buckets as u32);
+    let report = run_one_tick(&repo, &config).await.unwrap();
+    assert!(report.more_work_remaining, "backlog left");
+    let first_pass = (MAX_CHUNKS_PER_PASS as i64) * chunk_buckets;
+    assert_eq!(report.raw_buckets as i64, first_pass);
+    assert_eq!(report.raw_rows_deleted as i64, first_pass * 6);
+    assert_eq!(report.rolled_up_buckets, 0);
+    assert_eq!(report.pruned_aggregated, 0);
+    let done = repo
+        .get_aggregated_snapshots_by_time_range(start, start + minutes * MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    assert_eq!(
+        done.len() as i64,
+        (MAX_CHUNKS_PER_PASS as i64) * chunk_buckets
+    );
+
+    let report = run_one_tick(&repo, &config).await.unwrap();
+    assert!(!report.more_work_remaining, "caught up");
+    assert_eq!(report.raw_buckets, 7);
+    assert_eq!(report.raw_rows_deleted, 7 * 6);
+    let done = repo
+        .get_aggregated_snapshots_by_time_range(start, start + minutes * MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    assert_eq!(done.len() as i64, minutes);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn writer_keeps_saving_during_backfill() {
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(connect(&dir).await);
+    // Two days of backlog ending 2 h ago.
+    let start = ((common::now_ms() - 50 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    seed_raw(&repo, start, 48 * 60).await;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let saved = Arc::new(AtomicU32::new(0));
+    let writer = {
+        let (repo, stop, saved) = (repo.clone(), stop.clone(), saved.clone());
+        tokio::spawn(async move {
+            let mut failures = Vec::new();
+            while !stop.load(Ordering::Relaxed) {
+                match repo
+                    .save_snapshots(&[snapshot(common::now_ms())], &SystemInfo::default())
+                    .await
+                {
+                    Ok(_) => {
+                        saved.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => failures.push(e.to_string()),
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+            }
+            failures
+        })
+    };
+
+    let report = run_backfill(repo.clone(), &worker_config(50))
+        .await
+        .unwrap();
+    stop.store(true, Ordering::Relaxed);
+    let failures = writer.await.unwrap();
+
+    assert!(failures.is_empty(), "writer saves failed: {failures:?}");
+    assert!(!report.more_work_remaining);
+    assert_eq!(report.raw_buckets, 48 * 60);
+    assert_eq!(report.raw_rows_deleted, 48 * 60 * 6);
+    // Minutes older than minute_retention_hours (24 h) rolled into 5-min buckets.
+    assert!(report.rolled_up_buckets > 0);
+    assert!(report.minute_rows_deleted > 0);
+    assert!(report.minute_rows_deleted <= 5 * report.rolled_up_buckets);
+    assert!(saved.load(Ordering::Relaxed) > 0);
+    // Everything older than raw retention was rolled up; only the writer's fresh rows remain.
+    assert!(
+        repo.get_raw_snapshots_by_time_range(0, common::now_ms() - MS_PER_HOUR)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,200 @@
+// database.bucket_timezone: BucketGrid boundaries around DST transitions, config validation, and
+// run_one_tick writing hourly / daily rows on local wall-clock boundaries.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::{AppConfig, DatabaseConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::{BucketGrid, BucketTimezone};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const HOUR: i32 = 3600;
+const DAY: i32 = 86_400;
+const HOUR_MS: i64 = 3_600_000;
+
+fn zone(name: &str) -> BucketTimezone {
+    name.parse().unwrap()
+}
+
+#[test]
+fn parses_utc_local_and_iana_names() {
+    assert_eq!(
+        "UTC".parse::<BucketTimezone>().unwrap(),
+        BucketTimezone::Utc
+    );
+    assert_eq!(
+        "utc".parse::<BucketTimezone>().unwrap(),
+        BucketTimezone::Utc
+    );
+    assert_eq!(
+        "local".parse::<BucketTimezone>().unwrap(),
+        BucketTimezone::Local
+    );
+    assert_eq!(
+        zone("Europe/Berlin"),
+        BucketTimezone::Zone(chrono_tz::Europe::Berlin)
+    );
+    assert!("Mars/Olympus_Mons".parse::<BucketTimezone>().is_err());
+}
+
+#[test]
+fn config_rejects_unknown_zone() {
+    let err = AppConfig::load_from_str(
+        "[database]\npath = \"x.db\"\nbucket_timezone = \"Europe/Atlantis\"\n",
+    )
+    .unwrap_err();
+    assert!(
+        format!("{err:#}").contains("database.bucket_timezone"),
+        "{err:#}"
+    );
+    let ok = AppConfig::load_from_str(
+        "[database]\npath = \"x.db\"\nbucket_timezone = \"America/New_York\"\n",
+    )
+    .unwrap();
+    assert_eq!(ok.database.bucket_timezone, "America/New_York");
+    assert_eq!(DatabaseConfig::default().bucket_timezone, "UTC");
+}
+
+#[test]
+fn utc_and_sub_hour_tiers_stay_epoch_aligned() {
+    let ts = 1_735_708_500_000; // 2025-01-01 10:45 in Asia/Kolkata (UTC+5:30)
+    assert_eq!(
+        BucketGrid::new(HOUR, BucketTimezone::Utc).floor(ts),
+        ts / HOUR_MS * HOUR_MS
+    );
+    assert_eq!(
+        BucketGrid::new(300, zone("Asia/Kolkata")).floor(ts),
+        ts / 300_000 * 300_000
+    );
+    // Hourly buckets follow the half-hour offset.
+    assert_eq!(
+        BucketGrid::new(HOUR, zone("Asia/Kolkata")).floor(ts),
+        1_735_705_800_000
+    );
+}
+
+#[test]
+fn daily_buckets_span_23_and_25_hours_across_dst() {
+    let grid = BucketGrid::new(DAY, zone("Europe/Berlin"));
+    // 2025-03-30: clocks go forward at 02:00, the day has 23 hours.
+    let spring = 1_743_289_200_000;
+    assert_eq!(grid.floor(1_743_328_800_000), spring); // 12:00 local
+    assert_eq!(grid.next(spring), 1_743_372_000_000);
+    assert_eq!(grid.next(spring) - spring, 23 * HOUR_MS);
+    // 2025-10-26: clocks go back at 03:00, the day has 25 hours.
+    let autumn = 1_761_429_600_000;
+    assert_eq!(grid.floor(1_761_476_400_000), autumn);
+    assert_eq!(grid.next(autumn) - autumn, 25 * HOUR_MS);
+    assert_eq!(grid.advance(1_761_343_200_000, 3), 1_761_606_000_000);
+    // A bucket start is its own floor.
+    assert_eq!(grid.floor(autumn), autumn);
+}
+
+#[test]
+fn daily_bucket_starts_after_a_skipped_midnight() {
+    // America/Santiago 2025-09-07: 00:00 jumps to 01:00, so the day starts at the transition.
+    let grid = BucketGrid::new(DAY, zone("America/Santiago"));
+    let start = 1_757_217_600_000;
+    assert_eq!(grid.floor(1_757_257_200_000), start);
+    assert_eq!(grid.next(1_757_131_200_000), start);
+    assert_eq!(grid.next(start), 1_757_300_400_000);
+}
+
+#[test]
+fn hourly_buckets_around_new_york_transitions() {
+    let grid = BucketGrid::new(HOUR, zone("America/New_York"));
+    // 2025-11-02: 01:00-02:00 happens twice; it is one two-hour bucket.
+    let repeated = 1_762_059_600_000; // 01:00 EDT
+    assert_eq!(grid.floor(1_762_065_000_000), repeated); // 01:30 EST
+    assert_eq!(grid.next(repeated), 1_762_066_800_000); // 02:00 EST
+    // 2025-03-09: 02:00-03:00 is skipped; 01:00 is followed directly by 03:00 EDT.
+    assert_eq!(grid.floor(1_741_501_800_000), 1_741_500_000_000);
+    assert_eq!(grid.next(1_741_500_000_000), 1_741_503_600_000);
+    assert_eq!(grid.floor(1_741_505_400_000), 1_741_503_600_000);
+}
+
+fn worker_config(tiers: &[i32], timezone: &str) -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        aggregation_tiers: tiers.to_vec(),
+        bucket_timezone: zone(timezone),
+        minute_retention_hours: 1,
+        retention_days: 3650,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(dir: &TempDir, tiers: &[i32]) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        aggregation_tiers: tiers.to_vec(),
+        raw_retention_hours: 1,
+        minute_retention_hours: 1,
+        hourly_retention_days: 3650,
+        aggregated_retention_days: 3650,
+        retention_days: 3650,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+/// One raw sample every 10 minutes in `[from, to)`.
+async fn seed(repo: &HistoryRepo, from: i64, to: i64) {
+    let snaps: Vec<_> = (from..to)
+        .step_by(600_000)
+        .map(|ts| common::snapshot(ts as u64))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+}
+
+async fn rows(repo: &HistoryRepo, resolution: i32) -> Vec<AggregatedSnapshot> {
+    repo.get_aggregated_snapshots_by_time_range(0, i64::MAX, resolution)
+        .await
+        .unwrap()
+}
+
+async fn run_until_idle(repo: &HistoryRepo, config: &AggregationWorkerConfig) {
+    while run_one_tick(repo, config)
+        .await
+        .unwrap()
+        .more_work_remaining
+    {}
+}
+
+#[tokio::test]
+async fn hourly_rows_follow_a_half_hour_offset() {
+    let dir = TempDir::new().unwrap();
+    let tiers = [HOUR];
+    let repo = connect(&dir, &tiers).await;
+    // 2025-01-01 10:30 .. 14:30 in Asia/Kolkata.
+    seed(&repo, 1_735_705_800_000, 1_735_705_800_000 + 4 * HOUR_MS).await;
+    run_until_idle(&repo, &worker_config(&tiers, "Asia/Kolkata")).await;
+
+    let hourly = rows(&repo, HOUR).await;
+    assert_eq!(hourly.len(), 4);
+    for row in &hourly {
+        assert_eq!(row.created_at % HOUR_MS, HOUR_MS / 2, "{}", row.created_at);
+        assert_eq!(row.sample_count, 6);
+    }
+}
+
+#[tokio::test]
+async fn daily_rows_are_local_days_across_fall_back() {
+    let dir = TempDir::new().unwrap();
+    let tiers = [HOUR, DAY];
+    let repo = connect(&dir, &tiers).await;
+    // Europe/Berlin 2025-10-25 00:00 .. 2025-10-28 00:00; the 26th has 25 hours.
+    seed(&repo, 1_761_343_200_000, 1_761_606_000_000).await;
+    run_until_idle(&repo, &worker_config(&tiers, "Europe/Berlin")).await;
+
+    let daily = rows(&repo, DAY).await;
+    let starts: Vec<i64> = daily.iter().map(|r| r.created_at).collect();
+    assert_eq!(
+        starts,
+        [1_761_343_200_000, 1_761_429_600_000, 1_761_519_600_000]
+    );
+    let counts: Vec<i64> = daily.iter().map(|r| r.sample_count).collect();
+    assert_eq!(counts, [144, 150, 144]);
+}
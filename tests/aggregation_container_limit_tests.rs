@@ -0,0 +1,155 @@
+// database.aggregation_container_limit: buckets keep the busiest containers plus those running
+// at the bucket end, the rest collapse into one "__other__" entry with summed counters.
+
+mod common;
+
+use homeserver::history_repo::aggregation::{
+    OTHER_CONTAINERS_ID, aggregate_aggregated_snapshots_with_container_limit,
+    aggregate_snapshots_with_container_limit,
+};
+use homeserver::models::*;
+
+fn container(i: u32, cpu_percent: f64, state: ContainerState) -> ContainerStats {
+    let mut c: ContainerStats = serde_json::from_value(serde_json::json!({
+        "id": format!("id-{i:02}"),
+        "name": format!("c-{i:02}"),
+        "cpuPercent": cpu_percent,
+        "memoryUsageBytes": 1000 + u64::from(i),
+        "memoryLimitBytes": 4000,
+        "state": "running",
+        "networkRxBytes": 10 * u64::from(i),
+        "blockWriteBytes": 7,
+        "pids": 3,
+    }))
+    .unwrap();
+    c.state = state;
+    c
+}
+
+fn snapshot(ts: u64, containers: Vec<ContainerStats>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers,
+        ..common::snapshot(ts)
+    }
+}
+
+/// Six exited containers with CPU 1..=6 % in one sample.
+fn exited_bucket() -> Vec<FullSystemSnapshot> {
+    let containers = (1..=6)
+        .map(|i| container(i, f64::from(i), ContainerState::Exited))
+        .collect();
+    vec![snapshot(60_000, containers)]
+}
+
+fn names(containers: &[ContainerStats]) -> Vec<&str> {
+    containers.iter().map(|c| c.name.as_str()).collect()
+}
+
+#[test]
+fn keeps_the_busiest_containers_and_sums_the_rest() {
+    let agg = aggregate_snapshots_with_container_limit(&exited_bucket(), 60_000, 60, 2).unwrap();
+    assert_eq!(
+        names(&agg.containers),
+        ["c-05", "c-06", OTHER_CONTAINERS_ID]
+    );
+
+    let other = agg.containers.last().unwrap();
+    assert_eq!(other.id, OTHER_CONTAINERS_ID);
+    assert_eq!(other.cpu_percent, 1.0 + 2.0 + 3.0 + 4.0);
+    assert_eq!(other.memory_usage_bytes, 1001 + 1002 + 1003 + 1004);
+    assert_eq!(other.memory_limit_bytes, 4 * 4000);
+    assert_eq!(other.network_rx_bytes, 10 + 20 + 30 + 40);
+    assert_eq!(other.block_write_bytes, 4 * 7);
+    assert_eq!(other.pids, 4 * 3);
+    assert_eq!(other.state, ContainerState::Exited);
+
+    // Totals across the bucket are unchanged by the cut.
+    let total_cpu: f64 = agg.containers.iter().map(|c| c.cpu_percent).sum();
+    assert_eq!(total_cpu, 21.0);
+}
+
+#[test]
+fn containers_running_at_the_bucket_end_are_always_kept() {
+    let mut bucket = exited_bucket();
+    // c-01 (the idlest) is running in the last sample; c-02 was running only earlier.
+    let mut last = bucket[0].clone();
+    last.timestamp = 61_000;
+    last.containers[0].state = ContainerState::Running;
+    bucket[0].containers[1].state = ContainerState::Running;
+    bucket.push(last);
+
+    let agg = aggregate_snapshots_with_container_limit(&bucket, 60_000, 60, 2).unwrap();
+    assert_eq!(
+        names(&agg.containers),
+        ["c-01", "c-05", "c-06", OTHER_CONTAINERS_ID]
+    );
+    let other = agg.containers.last().unwrap();
+    assert_eq!(other.cpu_percent, 2.0 + 3.0 + 4.0);
+}
+
+#[test]
+fn zero_or_a_large_limit_keeps_every_container() {
+    for limit in [0, 6, 100] {
+        let agg =
+            aggregate_snapshots_with_container_limit(&exited_bucket(), 60_000, 60, limit).unwrap();
+        assert_eq!(agg.containers.len(), 6, "limit {limit}");
+        assert!(agg.containers.iter().all(|c| c.id != OTHER_CONTAINERS_ID));
+    }
+}
+
+#[test]
+fn cpu_ties_are_cut_by_name_regardless_of_input_order() {
+    let tied = |order: &[u32]| {
+        let containers = order
+            .iter()
+            .map(|&i| container(i, 5.0, ContainerState::Exited))
+            .collect();
+        let bucket = vec![snapshot(60_000, containers)];
+        let agg = aggregate_snapshots_with_container_limit(&bucket, 60_000, 60, 2).unwrap();
+        names(&agg.containers)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    let expected = ["c-01", "c-02", OTHER_CONTAINERS_ID];
+    assert_eq!(tied(&[1, 2, 3, 4]), expected);
+    assert_eq!(tied(&[4, 3, 2, 1]), expected);
+    assert_eq!(tied(&[3, 1, 4, 2]), expected);
+}
+
+#[test]
+fn coarser_tiers_fold_earlier_other_entries_into_one() {
+    let minutes: Vec<_> = (0..2)
+        .map(|m| {
+            aggregate_snapshots_with_container_limit(&exited_bucket(), 60_000 * (m + 1), 60, 2)
+                .unwrap()
+        })
+        .collect();
+    let five = aggregate_aggregated_snapshots_with_container_limit(&minutes, 0, 300, 1).unwrap();
+    assert_eq!(names(&five.containers), ["c-06", OTHER_CONTAINERS_ID]);
+    let other = five.containers.last().unwrap();
+    // Averaged over the two minutes: c-05 (5 %) plus the earlier "__other__" (10 %).
+    assert_eq!(other.cpu_percent, 15.0);
+    // Cumulative counters: the last minute's reading, summed across the collapsed containers.
+    assert_eq!(other.network_rx_bytes, 50 + 100);
+}
+
+#[test]
+fn other_is_unlimited_once_any_collapsed_container_is() {
+    let limited = |pids_limit: [u64; 4]| {
+        let mut bucket = exited_bucket();
+        for (c, limit) in bucket[0].containers.iter_mut().zip(pids_limit) {
+            c.pids_limit = limit;
+        }
+        let agg = aggregate_snapshots_with_container_limit(&bucket, 60_000, 60, 2).unwrap();
+        agg.containers.last().unwrap().clone()
+    };
+    let all = limited([10, 10, 10, 20]);
+    assert_eq!((all.pids, all.pids_limit), (12, 50));
+    assert_eq!(all.pids_usage_percent, 24.0);
+
+    // c-01 has no limit: its pids must not count against the others' 30.
+    let mixed = limited([0, 10, 10, 10]);
+    assert_eq!((mixed.pids, mixed.pids_limit), (12, 0));
+    assert_eq!(mixed.pids_usage_percent, 0.0);
+}
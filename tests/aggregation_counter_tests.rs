@@ -0,0 +1,156 @@
+// Aggregation of cumulative counters vs gauges: containers, partitions, disks and interfaces keep
+// the last counter reading and average the gauges, in 1-min buckets and tier roll-ups; container
+// pids come from the sample nearest the pids limit.
+
+mod common;
+
+use homeserver::history_repo::aggregation::{aggregate_aggregated_snapshots, aggregate_snapshots};
+use homeserver::history_repo::blob::{decode_containers, encode_containers};
+use homeserver::models::*;
+
+fn container(cpu_percent: f64, counter: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": cpu_percent,
+        "memoryUsageBytes": 100,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+        "networkRxBytes": counter,
+        "networkTxBytes": counter / 2,
+        "networkRxPackets": counter / 10,
+        "blockReadBytes": counter * 3,
+        "blockWriteBytes": counter * 4,
+        "cpuThrottledPeriods": counter / 100,
+        "cpuThrottledTimeNs": counter * 1000,
+    }))
+    .unwrap()
+}
+
+fn partition(mount: &str, used: u64) -> PartitionStat {
+    PartitionStat {
+        mount: mount.into(),
+        name: mount.into(),
+        type_: "ext4".into(),
+        total_space: 1000,
+        used_space: used,
+        available_space: 1000 - used,
+        usage_percent: used as f64 / 10.0,
+    }
+}
+
+fn disk(read_bytes: u64) -> DiskDeviceStat {
+    DiskDeviceStat {
+        name: "sda".into(),
+        model: String::new(),
+        size: 1000,
+        read_bytes,
+        write_bytes: read_bytes * 2,
+        io_time_ms: read_bytes / 10,
+        iops_read: read_bytes / 100,
+        iops_write: 0,
+    }
+}
+
+/// Sample `i` of a bucket: counters grow by 1000 per sample, gauges alternate.
+fn snapshot(ts: u64, i: u64) -> FullSystemSnapshot {
+    let counter = 1_000_000 + 1000 * i;
+    FullSystemSnapshot {
+        containers: vec![container(
+            if i.is_multiple_of(2) { 10.0 } else { 30.0 },
+            counter,
+        )],
+        storage: StorageStats {
+            partitions: vec![partition("/", if i.is_multiple_of(2) { 200 } else { 400 })],
+            disks: vec![disk(counter)],
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+fn bucket(start: u64, samples: u64) -> Vec<FullSystemSnapshot> {
+    (0..samples)
+        .map(|i| snapshot(start + i * 1000, i))
+        .collect()
+}
+
+#[test]
+fn container_counters_keep_last_reading_instead_of_summing() {
+    let out = aggregate_snapshots(&bucket(60_000, 60), 60_000, 60).unwrap();
+    let c = &out.containers[0];
+    let last = 1_000_000 + 1000 * 59;
+    assert_eq!(c.network_rx_bytes, last);
+    assert_eq!(c.network_tx_bytes, last / 2);
+    assert_eq!(c.network_rx_packets, last / 10);
+    assert_eq!(c.block_read_bytes, last * 3);
+    assert_eq!(c.block_write_bytes, last * 4);
+    assert_eq!(c.cpu_throttled_periods, last / 100);
+    assert_eq!(c.cpu_throttled_time_ns, last * 1000);
+    // Gauges still average.
+    assert_eq!(c.cpu_percent, 20.0);
+}
+
+#[test]
+fn partitions_average_usage_and_disks_keep_last_counters() {
+    let out = aggregate_snapshots(&bucket(60_000, 4), 60_000, 60).unwrap();
+    let p = &out.storage.partitions[0];
+    assert_eq!(p.used_space, 300);
+    assert_eq!(p.available_space, 700);
+    assert_eq!(p.usage_percent, 30.0);
+    assert_eq!(p.total_space, 1000);
+
+    let d = &out.storage.disks[0];
+    assert_eq!(d.read_bytes, 1_003_000);
+    assert_eq!(d.write_bytes, 2_006_000);
+    assert_eq!(d.io_time_ms, 100_300);
+}
+
+#[test]
+fn partitions_missing_from_the_last_sample_are_kept() {
+    let mut snaps = bucket(60_000, 2);
+    snaps[0].storage.partitions.push(partition("/mnt/usb", 500));
+    let out = aggregate_snapshots(&snaps, 60_000, 60).unwrap();
+    let mounts: Vec<&str> = out
+        .storage
+        .partitions
+        .iter()
+        .map(|p| p.mount.as_str())
+        .collect();
+    assert_eq!(mounts, ["/", "/mnt/usb"]);
+}
+
+#[test]
+fn roll_ups_keep_the_last_child_counters_and_weight_gauges() {
+    // Minute one: 3 samples (used 200/400/200); minute two: 1 sample (used 200).
+    let minutes = vec![
+        aggregate_snapshots(&bucket(0, 3), 0, 60).unwrap(),
+        aggregate_snapshots(&bucket(60_000, 1), 60_000, 60).unwrap(),
+    ];
+    let five = aggregate_aggregated_snapshots(&minutes, 0, 300).unwrap();
+
+    // The second minute restarted the sequence: its last reading wins, no sum over children.
+    assert_eq!(five.containers[0].network_rx_bytes, 1_000_000);
+    assert_eq!(five.storage.disks[0].read_bytes, 1_000_000);
+    // (266 * 3 + 200 * 1) / 4
+    assert_eq!(five.storage.partitions[0].used_space, 249);
+    assert_eq!(five.containers[0].cpu_percent, 15.0);
+}
+
+#[test]
+fn containers_keep_the_bucket_peak_pids_usage() {
+    let mut snaps = bucket(60_000, 3);
+    for (snap, pids) in snaps.iter_mut().zip([40, 95, 60]) {
+        let c = &mut snap.containers[0];
+        (c.pids, c.pids_limit) = (pids, 100);
+        c.pids_usage_percent = pids_usage_percent(pids, 100);
+    }
+    let agg = aggregate_snapshots(&snaps, 60_000, 60).unwrap();
+    let c = &agg.containers[0];
+    assert_eq!((c.pids, c.pids_limit), (95, 100));
+    assert_eq!(c.pids_usage_percent, 95.0);
+
+    // Not stored in the blob layout, but decoding recomputes it from pids / pids_limit.
+    let bytes = encode_containers(&agg.containers, false).unwrap();
+    let decoded = decode_containers(&bytes, "container_data").unwrap();
+    assert_eq!(decoded[0].pids_usage_percent, 95.0);
+}
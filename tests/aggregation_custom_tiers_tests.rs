@@ -0,0 +1,170 @@
+// database.aggregation_tiers = [30, 120]: run_one_tick rolls raw → 30 s → 120 s on those
+// boundaries, prunes with the custom tiers, and get_history picks among them by resolution.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationReport, AggregationWorkerConfig, run_one_tick};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const MS_PER_HOUR: i64 = 3_600_000;
+const TIERS: [i32; 2] = [30, 120];
+
+/// Raw rolls into 30 s after 1 hour; 30 s rows roll into 120 s after 2 hours.
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        aggregation_tiers: TIERS.to_vec(),
+        minute_retention_hours: 2,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        aggregation_tiers: TIERS.to_vec(),
+        bucket_timezone: Default::default(),
+        raw_retention_hours: 1,
+        minute_retention_hours: 2,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+fn snapshot(ts: i64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+async fn rows(repo: &HistoryRepo, resolution: i32) -> Vec<AggregatedSnapshot> {
+    repo.get_aggregated_snapshots_by_time_range(0, i64::MAX, resolution)
+        .await
+        .unwrap()
+}
+
+/// Raw samples every 10 s over the 4 hours before `now`, CPU cycling 10/20/30.
+async fn seed_raw(repo: &HistoryRepo, now: i64) -> i64 {
+    let start = ((now - 4 * MS_PER_HOUR) / 120_000) * 120_000;
+    let snaps: Vec<_> = (0..)
+        .map(|i| start + i * 10_000)
+        .take_while(|&ts| ts < now)
+        .enumerate()
+        .map(|(i, ts)| snapshot(ts, 10.0 + (i % 3) as f64 * 10.0))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    start
+}
+
+#[tokio::test]
+async fn worker_rolls_up_on_configured_tier_boundaries() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    let now = common::now_ms();
+    let start = seed_raw(&repo, now).await;
+    let mut total = AggregationReport::default();
+    loop {
+        let report = run_one_tick(&repo, &worker_config()).await.unwrap();
+        total.add(&report);
+        if !report.more_work_remaining {
+            break;
+        }
+    }
+
+    let thirty = rows(&repo, 30).await;
+    assert!(!thirty.is_empty(), "raw rolled into 30 s buckets");
+    for r in &thirty {
+        assert_eq!(r.created_at % 30_000, 0, "30 s buckets are 30 s aligned");
+        assert_eq!(r.sample_count, 3);
+        assert!((r.cpu_load_avg - 20.0).abs() < 0.01);
+        assert!(r.created_at >= now - 2 * MS_PER_HOUR - 120_000);
+        assert!(r.created_at < now - MS_PER_HOUR);
+    }
+    let two_min = rows(&repo, 120).await;
+    assert!(!two_min.is_empty(), "30 s rows rolled into 120 s buckets");
+    assert_eq!(two_min[0].created_at, start);
+    for r in &two_min {
+        assert_eq!(r.created_at % 120_000, 0, "120 s buckets are 120 s aligned");
+        assert_eq!(r.sample_count, 12);
+        assert!(r.created_at < now - 2 * MS_PER_HOUR);
+    }
+    // The default tiers are never written.
+    for resolution in [60, 300, 3600, 86400] {
+        assert!(rows(&repo, resolution).await.is_empty(), "{resolution}");
+    }
+    assert_eq!(
+        repo.get_aggregation_watermark(120).await.unwrap(),
+        Some(two_min.last().unwrap().created_at + 120_000)
+    );
+    // Every 120 s bucket came from four 30 s buckets of three raw rows each.
+    let (thirty, two_min) = (thirty.len() as u64, two_min.len() as u64);
+    assert_eq!(total.raw_buckets, thirty + 4 * two_min);
+    assert_eq!(total.raw_rows_deleted, 3 * total.raw_buckets);
+    assert_eq!(total.rolled_up_buckets, two_min);
+    assert_eq!(total.minute_rows_deleted, 4 * two_min);
+    assert_eq!(total.pruned_aggregated, 0);
+}
+
+#[tokio::test]
+async fn get_history_selects_among_configured_tiers() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    let now = common::now_ms();
+    let start = seed_raw(&repo, now).await;
+    while run_one_tick(&repo, &worker_config())
+        .await
+        .unwrap()
+        .more_work_remaining
+    {}
+    let thirty_from = rows(&repo, 30).await[0].created_at;
+    let aggregated_to = ((now - MS_PER_HOUR) / 30_000) * 30_000 - 60_000;
+
+    // 30 s over the 30 s stretch: one point per stored 30 s row.
+    let out = repo
+        .get_history(thirty_from, aggregated_to, 30, now)
+        .await
+        .unwrap();
+    assert_eq!(out.len() as i64, (aggregated_to - thirty_from) / 30_000);
+    assert!(out.iter().all(|s| s.timestamp as i64 % 30_000 == 0));
+
+    // 120 s over the whole aggregated span: the 120 s tier, then 30 s rows merged per 120 s
+    // bucket; one point per bucket either way.
+    let out = repo
+        .get_history(start, aggregated_to, 120, now)
+        .await
+        .unwrap();
+    assert_eq!(out[0].timestamp as i64, start);
+    let buckets: Vec<i64> = out.iter().map(|s| s.timestamp as i64 / 120_000).collect();
+    assert!(buckets.windows(2).all(|w| w[1] == w[0] + 1), "{buckets:?}");
+    assert_eq!(
+        buckets.len() as i64,
+        (aggregated_to - start + 119_999) / 120_000
+    );
+
+    // 60 s sits between the tiers: read from the 30 s tier (not coarser than requested) merged
+    // per minute, with the older stretch filled from the 120 s tier.
+    let out = repo
+        .get_history(start, aggregated_to, 60, now)
+        .await
+        .unwrap();
+    assert_eq!(out[0].timestamp as i64, start);
+    assert_eq!(out[1].timestamp as i64, start + 120_000);
+    let minutes: Vec<i64> = out
+        .iter()
+        .map(|s| s.timestamp as i64)
+        .filter(|&ts| ts >= thirty_from)
+        .map(|ts| ts / 60_000)
+        .collect();
+    assert!(minutes.windows(2).all(|w| w[1] == w[0] + 1), "{minutes:?}");
+    assert_eq!(
+        minutes.len() as i64,
+        (aggregated_to - thirty_from + 59_999) / 60_000
+    );
+}
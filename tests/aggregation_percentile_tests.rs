@@ -0,0 +1,146 @@
+// Percentile tracking: nearest-rank p95 math, p95 in raw → 1m buckets and roll-ups,
+// and the min/max/p95 envelope returned by get_history_points.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::aggregation::{
+    aggregate_aggregated_snapshots, aggregate_snapshots, percentile,
+};
+use homeserver::history_repo::{DownsampleMode, HistoryRepo};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+fn sample(ts: u64, cpu: f64, mem: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            used: mem,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+#[test]
+fn percentile_uniform_1_to_100() {
+    let values: Vec<f64> = (1..=100).map(f64::from).collect();
+    assert_eq!(percentile(&values, 95.0), Some(95.0));
+    assert_eq!(percentile(&values, 99.0), Some(99.0));
+    assert_eq!(percentile(&values, 50.0), Some(50.0));
+    assert_eq!(percentile(&values, 100.0), Some(100.0));
+}
+
+#[test]
+fn percentile_is_order_independent() {
+    let mut values: Vec<i64> = (1..=20).collect();
+    values.reverse();
+    // Nearest rank: ceil(0.95 * 20) = 19th smallest.
+    assert_eq!(percentile(&values, 95.0), Some(19));
+}
+
+#[test]
+fn percentile_single_and_empty() {
+    assert_eq!(percentile(&[42.0], 95.0), Some(42.0));
+    assert_eq!(percentile::<f64>(&[], 95.0), None);
+}
+
+#[test]
+fn percentile_ignores_spike_below_rank() {
+    // 99 quiet samples + 1 spike: p95 stays quiet while max shows the spike.
+    let mut values = vec![10.0; 99];
+    values.push(100.0);
+    assert_eq!(percentile(&values, 95.0), Some(10.0));
+}
+
+#[test]
+fn aggregate_snapshots_computes_p95() {
+    let snaps: Vec<FullSystemSnapshot> = (1..=60)
+        .map(|i| sample(i * 1000, i as f64, i * 100))
+        .collect();
+    let agg = aggregate_snapshots(&snaps, 60_000, 60).unwrap();
+    // ceil(0.95 * 60) = 57th smallest.
+    assert_eq!(agg.cpu_load_p95, Some(57.0));
+    assert_eq!(agg.memory_used_p95, Some(5700));
+    assert_eq!(agg.cpu_load_max, 60.0);
+}
+
+#[test]
+fn roll_up_keeps_worst_child_p95() {
+    let minutes: Vec<_> = (0..5)
+        .map(|m| {
+            let snaps: Vec<FullSystemSnapshot> = (0..60)
+                .map(|i| sample(m * 60_000 + i * 1000, (m * 10) as f64 + 1.0, 100 + m))
+                .collect();
+            aggregate_snapshots(&snaps, (m as i64) * 60_000, 60).unwrap()
+        })
+        .collect();
+    let five = aggregate_aggregated_snapshots(&minutes, 0, 300).unwrap();
+    assert_eq!(five.cpu_load_p95, Some(41.0));
+    assert_eq!(five.memory_used_p95, Some(104));
+
+    // Legacy children without p95 do not invent one.
+    let legacy: Vec<_> = minutes
+        .into_iter()
+        .map(|mut a| {
+            a.cpu_load_p95 = None;
+            a.memory_used_p95 = None;
+            a
+        })
+        .collect();
+    let five = aggregate_aggregated_snapshots(&legacy, 0, 300).unwrap();
+    assert_eq!(five.cpu_load_p95, None);
+    assert_eq!(five.memory_used_p95, None);
+}
+
+#[tokio::test]
+async fn history_points_carry_envelope_for_raw_and_aggregated() {
+    let dir = TempDir::new().unwrap();
+    let config = DatabaseConfig {
+        path: dir.path().join("p95.db").to_str().unwrap().to_string(),
+        ..DatabaseConfig::default()
+    };
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+
+    // One aggregated minute [0, 60s) and raw samples in [60s, 120s).
+    let older: Vec<FullSystemSnapshot> = (0..20).map(|i| sample(i * 3000, i as f64, 1)).collect();
+    let agg = aggregate_snapshots(&older, 0, 60).unwrap();
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+    let raw: Vec<FullSystemSnapshot> = (0..20)
+        .map(|i| sample(60_000 + i * 3000, 50.0 + i as f64, 1000 + i))
+        .collect();
+    repo.save_snapshots(&raw, &Default::default())
+        .await
+        .unwrap();
+
+    let points = repo
+        .get_history_points(0, 120_000, 60, 60_000, DownsampleMode::Average)
+        .await
+        .unwrap();
+    assert_eq!(points.len(), 2);
+
+    let old = points[0].envelope.as_ref().unwrap();
+    assert_eq!((old.cpu_load_min, old.cpu_load_max), (0.0, 19.0));
+    assert_eq!(old.cpu_load_p95, Some(18.0));
+
+    let recent = points[1].envelope.as_ref().unwrap();
+    assert_eq!((recent.cpu_load_min, recent.cpu_load_max), (50.0, 69.0));
+    assert_eq!(recent.cpu_load_p95, Some(68.0));
+    assert_eq!(
+        (recent.memory_used_min, recent.memory_used_max),
+        (1000, 1019)
+    );
+    assert_eq!(recent.memory_used_p95, Some(1018));
+    // The point itself is the bucket average (50..=69); `Last` keeps the final sample.
+    assert_eq!(points[1].snapshot.cpu.usage_percent, 59.5);
+    let last = repo
+        .get_history_points(0, 120_000, 60, 60_000, DownsampleMode::Last)
+        .await
+        .unwrap();
+    assert_eq!(last[1].snapshot.cpu.usage_percent, 69.0);
+    assert_eq!(last[1].envelope, points[1].envelope);
+}
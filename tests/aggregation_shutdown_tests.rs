@@ -0,0 +1,170 @@
+// Aggregation worker shutdown: cancelling mid-backlog stops after the current chunk, never
+// leaving a bucket half rolled up, and the task (with its vacuum scheduler) exits promptly.
+
+mod common;
+
+use homeserver::aggregation_worker::{
+    self, AggregationMetrics, AggregationWorkerConfig, run_one_tick_until,
+};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+/// Two days of 10 s samples: far more than one pass rolls up.
+const BACKLOG_MINUTES: i64 = 48 * 60;
+
+/// One bucket per chunk, so a pass is many short transactions; VACUUM never due.
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        chunk_buckets: 1,
+        minute_retention_hours: 24 * 30,
+        five_minute_retention_days: 30,
+        wal_checkpoint_interval_secs: 3600,
+        ..common::aggregation_config()
+    }
+}
+
+async fn seeded_repo(dir: &TempDir) -> (Arc<HistoryRepo>, i64) {
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        retention_days: 30,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let start = ((common::now_ms() - 50 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..BACKLOG_MINUTES * 6)
+        .map(|i| common::snapshot((start + i * 10_000) as u64))
+        .collect();
+    for batch in snaps.chunks(2_000) {
+        repo.save_snapshots(batch, &SystemInfo::default())
+            .await
+            .unwrap();
+    }
+    (Arc::new(repo), start)
+}
+
+/// Every 1-min bucket is either fully rolled up (aggregate of 6 samples, no raw rows left) or
+/// untouched (6 raw rows, no aggregate), and the watermark sits exactly between the two.
+async fn assert_no_partial_bucket(repo: &HistoryRepo, start: i64) -> (usize, usize) {
+    let end = start + BACKLOG_MINUTES * MS_PER_MINUTE;
+    let aggs = repo
+        .get_aggregated_snapshots_by_time_range(start, end, 60)
+        .await
+        .unwrap();
+    let raw = repo
+        .get_raw_snapshots_by_time_range(start, end)
+        .await
+        .unwrap();
+    for agg in &aggs {
+        assert_eq!(agg.sample_count, 6, "bucket {}", agg.created_at);
+    }
+    let watermark = repo.get_aggregation_watermark(60).await.unwrap().unwrap();
+    assert_eq!(watermark, start + aggs.len() as i64 * MS_PER_MINUTE);
+    assert!(aggs.iter().all(|a| a.created_at < watermark));
+    assert!(raw.iter().all(|s| s.timestamp as i64 >= watermark));
+    assert_eq!(aggs.len() * 6 + raw.len(), (BACKLOG_MINUTES * 6) as usize);
+    (aggs.len(), raw.len())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn cancelled_worker_stops_between_chunks() {
+    let dir = TempDir::new().unwrap();
+    let (repo, start) = seeded_repo(&dir).await;
+    let shutdown = CancellationToken::new();
+    let metrics = Arc::new(AggregationMetrics::default());
+    let handle = aggregation_worker::spawn(
+        repo.clone(),
+        worker_config(),
+        shutdown.clone(),
+        metrics.clone(),
+        Default::default(),
+    );
+
+    // Cancel as soon as the first buckets land, well before the backlog is done.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while repo.get_aggregation_watermark(60).await.unwrap().is_none() {
+        assert!(tokio::time::Instant::now() < deadline, "no roll-up started");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("worker exits promptly after cancel")
+        .unwrap();
+
+    let (rolled_up, pending) = assert_no_partial_bucket(&repo, start).await;
+    assert!(rolled_up > 0);
+    assert!(pending > 0, "backlog left for the next start");
+    // The cancelled pass's report still counts what it finished.
+    let totals = metrics.snapshot();
+    assert_eq!(totals.raw_buckets_total, rolled_up as u64);
+    assert_eq!(totals.raw_rows_deleted_total, rolled_up as u64 * 6);
+}
+
+#[tokio::test]
+async fn cancelled_pass_reports_remaining_work_and_resumes() {
+    let dir = TempDir::new().unwrap();
+    let (repo, start) = seeded_repo(&dir).await;
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+
+    // Already cancelled: nothing is rolled up, the backlog is reported.
+    let report = run_one_tick_until(repo.as_ref(), &worker_config(), &shutdown)
+        .await
+        .unwrap();
+    assert!(report.more_work_remaining);
+    assert!(report.is_idle());
+    assert!(repo.get_aggregation_watermark(60).await.unwrap().is_none());
+
+    // A fresh pass picks up where the cancelled one stopped.
+    let live = CancellationToken::new();
+    let report = run_one_tick_until(repo.as_ref(), &worker_config(), &live)
+        .await
+        .unwrap();
+    assert!(report.more_work_remaining);
+    let (rolled_up, _) = assert_no_partial_bucket(&repo, start).await;
+    assert_eq!(rolled_up as u32, aggregation_worker::MAX_CHUNKS_PER_PASS);
+    assert_eq!(report.raw_buckets, rolled_up as u64);
+}
+
+#[tokio::test]
+async fn idle_worker_and_vacuum_scheduler_exit_on_cancel() {
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    repo.init().await.unwrap();
+    for vacuum_schedule in [None, Some("0 3 * * *".to_string())] {
+        let shutdown = CancellationToken::new();
+        let config = AggregationWorkerConfig {
+            vacuum_schedule,
+            ..worker_config()
+        };
+        let handle = aggregation_worker::spawn(
+            repo.clone(),
+            config,
+            shutdown.clone(),
+            Default::default(),
+            Default::default(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .expect("worker exits while the scheduler sleeps")
+            .unwrap();
+    }
+}
@@ -1,44 +1,21 @@
 // Aggregation logic tests: aggregate_snapshots (avg/min/max, container aggregation)
 
+mod common;
+
 use homeserver::history_repo::aggregation::aggregate_snapshots;
 use homeserver::models::*;
 
 fn snapshot(ts: u64, cpu_percent: f64, memory_used: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp: ts,
         cpu: CpuStats {
-            model: String::new(),
-            physical_cores: 0,
-            logical_cores: 0,
             usage_percent: cpu_percent,
-            temperature: 0.0,
-            core_usages: vec![],
+            ..Default::default()
         },
         ram: RamStats {
-            total: 0,
             used: memory_used,
-            available: 0,
-            usage_percent: 0.0,
-            swap_total: 0,
-            swap_used: 0,
-            swap_free: 0,
-        },
-        containers: vec![],
-        storage: StorageStats {
-            partitions: vec![],
-            disks: vec![],
-        },
-        network: NetworkStats { interfaces: vec![] },
-        system: SystemStatsDynamic {
-            uptime_secs: 0,
-            process_count: 0,
-            thread_count: 0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
+            ..Default::default()
         },
-        gpus: vec![],
-        smart: vec![],
+        ..common::snapshot(ts)
     }
 }
 
@@ -80,6 +57,29 @@ fn aggregate_snapshots_multiple_computes_avg_min_max() {
     assert_eq!(out.memory_used_max, 300);
 }
 
+#[test]
+fn aggregate_snapshots_memory_total_and_cpu_temperature() {
+    let mut snapshots = vec![
+        snapshot(60_000, 10.0, 100),
+        snapshot(60_001, 20.0, 200),
+        snapshot(60_002, 30.0, 300),
+    ];
+    for (s, (total, temp)) in snapshots
+        .iter_mut()
+        .zip([(1000, 40.0), (1000, 50.0), (2000, 60.0)])
+    {
+        s.ram.total = total;
+        s.cpu.temperature = temp;
+    }
+    let out = aggregate_snapshots(&snapshots, 60_000, 60).unwrap();
+    assert_eq!(out.memory_total_avg, 1333);
+    assert_eq!(out.memory_total_min, 1000);
+    assert_eq!(out.memory_total_max, 2000);
+    assert_eq!(out.cpu_temperature_avg, 50.0);
+    assert_eq!(out.cpu_temperature_min, 40.0);
+    assert_eq!(out.cpu_temperature_max, 60.0);
+}
+
 #[test]
 fn aggregate_aggregated_snapshots_empty_returns_none() {
     let aggs: Vec<homeserver::models::AggregatedSnapshot> = vec![];
@@ -94,12 +94,21 @@ fn aggregate_aggregated_snapshots_five_one_min_produces_5min() {
         |created_at: i64, cpu_avg: f64, mem_avg: i64| homeserver::models::AggregatedSnapshot {
             created_at,
             resolution_seconds: 60,
+            sample_count: 1,
             cpu_load_avg: cpu_avg,
             cpu_load_min: cpu_avg - 1.0,
             cpu_load_max: cpu_avg + 1.0,
             memory_used_avg: mem_avg,
             memory_used_min: mem_avg - 10,
             memory_used_max: mem_avg + 10,
+            cpu_load_p95: Some(cpu_avg + 0.5),
+            memory_used_p95: Some(mem_avg + 5),
+            memory_total_avg: 1000,
+            memory_total_min: 1000,
+            memory_total_max: 1000,
+            cpu_temperature_avg: cpu_avg + 40.0,
+            cpu_temperature_min: cpu_avg + 35.0,
+            cpu_temperature_max: cpu_avg + 45.0,
             cpu: Default::default(),
             ram: Default::default(),
             containers: vec![],
@@ -107,7 +116,7 @@ fn aggregate_aggregated_snapshots_five_one_min_produces_5min() {
                 partitions: vec![],
                 disks: vec![],
             },
-            network: NetworkStats { interfaces: vec![] },
+            network: NetworkStats::default(),
             system: SystemStatsDynamic {
                 uptime_secs: 0,
                 process_count: 0,
@@ -118,6 +127,8 @@ fn aggregate_aggregated_snapshots_five_one_min_produces_5min() {
             },
             gpus: vec![],
             smart: vec![],
+            probes: vec![],
+            sensors: vec![],
         };
     let aggs = vec![
         one_min(300_000, 10.0, 100),
@@ -137,12 +148,17 @@ fn aggregate_aggregated_snapshots_five_one_min_produces_5min() {
     assert_eq!(out.memory_used_avg, 300);
     assert_eq!(out.memory_used_min, 90);
     assert_eq!(out.memory_used_max, 510);
+    assert_eq!(out.memory_total_avg, 1000);
+    assert_eq!(out.memory_total_min, 1000);
+    assert_eq!(out.memory_total_max, 1000);
+    assert_eq!(out.cpu_temperature_avg, 70.0);
+    assert_eq!(out.cpu_temperature_min, 45.0);
+    assert_eq!(out.cpu_temperature_max, 95.0);
 }
 
 /// A snapshot whose full CPU/RAM/GPU/SMART detail is distinctive, to verify it survives aggregation.
 fn rich_snapshot(ts: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp: ts,
         cpu: CpuStats {
             model: "Rich CPU".into(),
             physical_cores: 8,
@@ -160,10 +176,6 @@ fn rich_snapshot(ts: u64) -> FullSystemSnapshot {
             swap_used: 256,
             swap_free: 3_744,
         },
-        containers: vec![],
-        storage: StorageStats::default(),
-        network: NetworkStats::default(),
-        system: SystemStatsDynamic::default(),
         gpus: vec![GpuStats {
             index: 0,
             vendor: "amd".into(),
@@ -184,6 +196,7 @@ fn rich_snapshot(ts: u64) -> FullSystemSnapshot {
             reallocated_sectors: Some(0),
             wear_level_percent: Some(3),
         }],
+        ..common::snapshot(ts)
     }
 }
 
@@ -0,0 +1,151 @@
+// Hourly / daily tiers: run_one_tick rolls 5-min → 1-hour → 1-day; get_history picks the tier
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        five_minute_retention_days: 2,
+        hourly_retention_days: 5,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        retention_days: 30,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+/// One aggregated row at `created_at` with the given resolution and CPU load.
+fn agg_row(created_at: i64, resolution_seconds: i32, cpu: f64) -> AggregatedSnapshot {
+    let snap = FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(created_at as u64)
+    };
+    aggregate_snapshots(&[snap], created_at, resolution_seconds).unwrap()
+}
+
+/// 5-min rows every 5 minutes over [start, start + days).
+async fn seed_five_minute_rows(repo: &HistoryRepo, start: i64, days: i64) -> usize {
+    let mut n = 0;
+    let mut ts = start;
+    while ts < start + days * MS_PER_DAY {
+        repo.save_aggregated_snapshot(&agg_row(ts, 300, 10.0 + (n % 3) as f64 * 10.0))
+            .await
+            .unwrap();
+        ts += 300_000;
+        n += 1;
+    }
+    n
+}
+
+async fn count(repo: &HistoryRepo, resolution: i32) -> usize {
+    repo.get_aggregated_snapshots_by_time_range(0, i64::MAX, resolution)
+        .await
+        .unwrap()
+        .len()
+}
+
+#[tokio::test]
+async fn multi_day_five_minute_rows_roll_up_to_hourly_and_daily() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    // Ten whole UTC days ending at least one day before "now".
+    let today = (common::now_ms() / MS_PER_DAY) * MS_PER_DAY;
+    let start = today - 11 * MS_PER_DAY;
+    let seeded = seed_five_minute_rows(&repo, start, 10).await;
+    assert_eq!(seeded, 10 * 288);
+
+    let report = run_one_tick(&repo, &worker_config()).await.unwrap();
+    assert!(!report.more_work_remaining);
+
+    // Rows older than 5 days end up daily; 2..5 days old stay hourly; < 2 days stay 5-min.
+    let daily = repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 86400)
+        .await
+        .unwrap();
+    assert!(!daily.is_empty(), "some days rolled up to 1-day");
+    for d in &daily {
+        assert_eq!(
+            d.created_at % MS_PER_DAY,
+            0,
+            "daily buckets are day-aligned"
+        );
+        assert!(d.created_at + MS_PER_DAY <= common::now_ms() - 5 * MS_PER_DAY);
+        assert!((d.cpu_load_avg - 20.0).abs() < 0.01);
+        assert_eq!(d.cpu_load_min, 10.0);
+        assert_eq!(d.cpu_load_max, 30.0);
+    }
+    let hourly = repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 3600)
+        .await
+        .unwrap();
+    assert!(!hourly.is_empty(), "some hours rolled up to 1-hour");
+    for h in &hourly {
+        assert_eq!(h.created_at % MS_PER_HOUR, 0);
+        assert!(h.created_at >= common::now_ms() - 6 * MS_PER_DAY);
+    }
+    let five_min = count(&repo, 300).await;
+    assert!(five_min > 0 && five_min < seeded);
+    // No raw or 1-min data: every bucket came from whole hours of 5-min rows, then whole days.
+    assert_eq!(report.raw_buckets, 0);
+    assert_eq!(report.raw_rows_deleted, 0);
+    assert_eq!(report.minute_rows_deleted, 0);
+    assert_eq!(
+        report.rolled_up_buckets as usize,
+        (seeded - five_min) / 12 + daily.len()
+    );
+    assert_eq!(report.pruned_aggregated, 0);
+    for r in repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 300)
+        .await
+        .unwrap()
+    {
+        assert!(r.created_at >= common::now_ms() - 3 * MS_PER_DAY);
+    }
+}
+
+#[tokio::test]
+async fn get_history_picks_tier_by_resolution_and_span() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    let base = 1_700_006_400_000; // hour-aligned
+    // Older day of hourly rows, then a day of 5-min rows.
+    for h in 0..24 {
+        repo.save_aggregated_snapshot(&agg_row(base + h * MS_PER_HOUR, 3600, 50.0))
+            .await
+            .unwrap();
+    }
+    let recent = base + MS_PER_DAY;
+    seed_five_minute_rows(&repo, recent, 1).await;
+    let end = recent + MS_PER_DAY;
+
+    // 1h over the recent day: 5-min rows downsampled to 24 hourly points.
+    let out = repo.get_history(recent, end, 3600, end).await.unwrap();
+    assert_eq!(out.len(), 24);
+
+    // 5m over both days: recent day from the 5-min tier, older day filled from the hourly tier.
+    let out = repo.get_history(base, end, 300, end).await.unwrap();
+    assert_eq!(out.len(), 24 + 288);
+    assert!(out.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+    assert_eq!(out[0].cpu.usage_percent, 50.0);
+
+    // 1d: no daily rows yet, so it falls back to the finer tiers, downsampled to days.
+    let out = repo.get_history(base, end, 86400, end).await.unwrap();
+    assert!(!out.is_empty() && out.len() <= 3);
+}
@@ -0,0 +1,154 @@
+// Aggregated bucket uniqueness: re-saving a bucket replaces it, a roll-up retried after a crash
+// leaves one row per bucket, and the v8 → v9 migration collapses existing duplicates.
+
+mod common;
+
+use homeserver::aggregation_worker::run_one_tick;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tempfile::TempDir;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+async fn connect(path: &Path) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        retention_days: 30,
+        ..common::database(path)
+    })
+    .await
+}
+
+async fn raw_pool(path: &Path) -> SqlitePool {
+    SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap()
+}
+
+fn snapshot(ts: i64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+async fn rows_at(path: &Path, created_at: i64, resolution: i32) -> i64 {
+    let pool = raw_pool(path).await;
+    let n = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM system_history_aggregated WHERE created_at = $1 AND resolution_seconds = $2",
+    )
+    .bind(created_at)
+    .bind(resolution)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+    n
+}
+
+#[tokio::test]
+async fn saving_same_bucket_twice_keeps_latest() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+
+    let first = aggregate_snapshots(&[snapshot(0, 10.0)], 0, 60).unwrap();
+    let second = aggregate_snapshots(&[snapshot(0, 90.0)], 0, 60).unwrap();
+    repo.save_aggregated_snapshot(&first).await.unwrap();
+    repo.save_aggregated_snapshot(&second).await.unwrap();
+
+    assert_eq!(rows_at(&path, 0, 60).await, 1);
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, 60_000, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].cpu_load_avg, 90.0);
+}
+
+#[tokio::test]
+async fn roll_up_retried_after_crash_writes_one_row_per_bucket() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+
+    // Two minutes of raw data, old enough to be rolled up.
+    let bucket = ((common::now_ms() - 2 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..120)
+        .map(|i| snapshot(bucket + i * 1000, 20.0))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    // Simulated crash: the first bucket's aggregate was written but its raw rows never deleted.
+    let partial = aggregate_snapshots(&snaps[..60], bucket, 60).unwrap();
+    repo.save_aggregated_snapshot(&partial).await.unwrap();
+
+    run_one_tick(&repo, &common::aggregation_config())
+        .await
+        .unwrap();
+
+    assert_eq!(rows_at(&path, bucket, 60).await, 1);
+    assert_eq!(rows_at(&path, bucket + MS_PER_MINUTE, 60).await, 1);
+    assert!(
+        repo.get_raw_snapshots_by_time_range(bucket, bucket + 2 * MS_PER_MINUTE)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // A second pass over the same window is a no-op.
+    run_one_tick(&repo, &common::aggregation_config())
+        .await
+        .unwrap();
+    assert_eq!(rows_at(&path, bucket, 60).await, 1);
+}
+
+#[tokio::test]
+async fn migration_collapses_duplicate_buckets() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+    for cpu in [10.0, 20.0, 30.0] {
+        repo.save_aggregated_snapshot(&aggregate_snapshots(&[snapshot(0, cpu)], 0, 60).unwrap())
+            .await
+            .unwrap();
+    }
+    drop(repo);
+
+    // Rewind to a v8 database: plain index, duplicated bucket rows.
+    let pool = raw_pool(&path).await;
+    for stmt in [
+        "DROP INDEX idx_aggregated_created_at_resolution",
+        "CREATE INDEX idx_aggregated_created_at_resolution ON system_history_aggregated(created_at, resolution_seconds)",
+        "INSERT INTO system_history_aggregated (created_at, resolution_seconds, cpu_load_avg, memory_used_avg, container_data, storage_data, network_data, system_data)
+         VALUES (0, 60, 1.0, 0, X'', X'', X'', X''), (0, 60, 55.0, 0, X'', X'', X'', X'')",
+        "ALTER TABLE system_history_aggregated DROP COLUMN sample_count",
+        "DROP INDEX idx_history_node_created_at",
+        "ALTER TABLE system_history DROP COLUMN node",
+        "ALTER TABLE system_history DROP COLUMN probe_data",
+        "ALTER TABLE system_history_aggregated DROP COLUMN probe_data",
+        "ALTER TABLE system_history DROP COLUMN sensor_data",
+        "ALTER TABLE system_history_aggregated DROP COLUMN sensor_data",
+        "DROP TABLE service_events",
+        "UPDATE schema_version SET value = 8 WHERE key = 'schema'",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+
+    let repo = connect(&path).await;
+    assert_eq!(rows_at(&path, 0, 60).await, 1);
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, 60_000, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].cpu_load_avg, 55.0, "latest duplicate kept");
+}
@@ -0,0 +1,110 @@
+// Aggregation watermarks: persisted per tier across repo instances, chunked catch-up after
+// downtime, and late rows behind the watermark left alone.
+
+mod common;
+
+use homeserver::aggregation_worker::run_one_tick;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use std::path::Path;
+use tempfile::TempDir;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+async fn connect(path: &Path) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        retention_days: 30,
+        ..common::database(path)
+    })
+    .await
+}
+
+fn snapshot(ts: i64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+#[tokio::test]
+async fn watermark_persists_across_repo_instances() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+    assert_eq!(repo.get_aggregation_watermark(60).await.unwrap(), None);
+
+    let start = ((common::now_ms() - 2 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..30).map(|i| snapshot(start + i * 10_000, 5.0)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    run_one_tick(&repo, &common::aggregation_config())
+        .await
+        .unwrap();
+
+    let wm = repo.get_aggregation_watermark(60).await.unwrap().unwrap();
+    assert_eq!(wm % MS_PER_MINUTE, 0);
+    assert!(wm >= start + 5 * MS_PER_MINUTE && wm <= common::now_ms() - MS_PER_HOUR);
+    drop(repo);
+
+    let repo = connect(&path).await;
+    assert_eq!(repo.get_aggregation_watermark(60).await.unwrap(), Some(wm));
+    let stats = repo.db_stats().await.unwrap();
+    assert_eq!(
+        stats.aggregation_watermarks,
+        vec![AggregationWatermark {
+            resolution_seconds: 60,
+            watermark: wm
+        }]
+    );
+
+    // A late raw row behind the watermark does not overwrite the finished bucket.
+    repo.save_snapshots(&[snapshot(start + 1_000, 99.0)], &SystemInfo::default())
+        .await
+        .unwrap();
+    run_one_tick(&repo, &common::aggregation_config())
+        .await
+        .unwrap();
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(start, start + MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].cpu_load_avg, 5.0);
+}
+
+#[tokio::test]
+async fn catch_up_after_downtime_spans_chunks() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+
+    // 150 minutes of 20 s samples ending 3 h ago: more than two 60-bucket chunks of backlog.
+    let start = ((common::now_ms() - 6 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..150 * 3)
+        .map(|i| snapshot(start + i * 20_000, (i / 3) as f64))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    run_one_tick(&repo, &common::aggregation_config())
+        .await
+        .unwrap();
+
+    let minutes = repo
+        .get_aggregated_snapshots_by_time_range(start, start + 150 * MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    assert_eq!(minutes.len(), 150);
+    for (i, m) in minutes.iter().enumerate() {
+        assert_eq!(m.created_at, start + i as i64 * MS_PER_MINUTE);
+        assert_eq!(m.cpu_load_avg, i as f64);
+    }
+    assert_eq!(repo.db_stats().await.unwrap().raw_rows, 0);
+}
@@ -0,0 +1,110 @@
+// Roll-up averages weighted by per-bucket sample count (uneven buckets, legacy rows, containers).
+
+mod common;
+
+use homeserver::history_repo::aggregation::{aggregate_aggregated_snapshots, aggregate_snapshots};
+use homeserver::models::*;
+
+fn container(cpu_percent: f64, memory_usage_bytes: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": cpu_percent,
+        "memoryUsageBytes": memory_usage_bytes,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+fn child(created_at: i64, sample_count: i64, cpu: f64, mem: i64) -> AggregatedSnapshot {
+    AggregatedSnapshot {
+        created_at,
+        resolution_seconds: 60,
+        sample_count,
+        cpu_load_avg: cpu,
+        cpu_load_min: cpu,
+        cpu_load_max: cpu,
+        memory_used_avg: mem,
+        memory_used_min: mem,
+        memory_used_max: mem,
+        cpu_load_p95: Some(cpu),
+        memory_used_p95: Some(mem),
+        memory_total_avg: mem * 2,
+        memory_total_min: mem * 2,
+        memory_total_max: mem * 2,
+        cpu_temperature_avg: cpu + 40.0,
+        cpu_temperature_min: cpu + 40.0,
+        cpu_temperature_max: cpu + 40.0,
+        cpu: CpuStats::default(),
+        ram: RamStats::default(),
+        containers: vec![container(cpu, mem as u64)],
+        storage: StorageStats::default(),
+        network: NetworkStats::default(),
+        system: SystemStatsDynamic::default(),
+        gpus: vec![],
+        smart: vec![],
+        probes: vec![],
+        sensors: vec![],
+    }
+}
+
+fn raw(ts: u64) -> FullSystemSnapshot {
+    common::snapshot(ts)
+}
+
+#[test]
+fn aggregate_snapshots_records_sample_count() {
+    let snaps: Vec<_> = (0..7).map(|i| raw(i * 1000)).collect();
+    let out = aggregate_snapshots(&snaps, 0, 60).unwrap();
+    assert_eq!(out.sample_count, 7);
+}
+
+#[test]
+fn uneven_children_are_weighted_by_sample_count() {
+    // A 3-sample bucket (restart gap) next to a full 60-sample bucket.
+    let aggs = vec![child(300_000, 3, 10.0, 100), child(360_000, 60, 40.0, 400)];
+    let out = aggregate_aggregated_snapshots(&aggs, 300_000, 300).unwrap();
+
+    assert_eq!(out.sample_count, 63);
+    let expected_cpu = (10.0 * 3.0 + 40.0 * 60.0) / 63.0;
+    assert!((out.cpu_load_avg - expected_cpu).abs() < 1e-9);
+    assert!((out.cpu_temperature_avg - (expected_cpu + 40.0)).abs() < 1e-9);
+    assert_eq!(out.memory_used_avg, (100 * 3 + 400 * 60) / 63);
+    assert_eq!(out.memory_total_avg, (200 * 3 + 800 * 60) / 63);
+    // min/max are unaffected by weighting.
+    assert_eq!(out.cpu_load_min, 10.0);
+    assert_eq!(out.cpu_load_max, 40.0);
+
+    assert_eq!(out.containers.len(), 1);
+    assert!((out.containers[0].cpu_percent - expected_cpu).abs() < 1e-9);
+    assert_eq!(
+        out.containers[0].memory_usage_bytes,
+        (100 * 3 + 400 * 60) / 63
+    );
+}
+
+#[test]
+fn weights_carry_through_a_second_roll_up() {
+    // 5-min buckets built from uneven 1-min children keep their summed count for the 1-hour tier.
+    let a = aggregate_aggregated_snapshots(&[child(0, 10, 10.0, 100)], 0, 300).unwrap();
+    let b = aggregate_aggregated_snapshots(
+        &[child(300_000, 60, 30.0, 300), child(360_000, 30, 30.0, 300)],
+        300_000,
+        300,
+    )
+    .unwrap();
+    let out = aggregate_aggregated_snapshots(&[a, b], 0, 3600).unwrap();
+    assert_eq!(out.sample_count, 100);
+    assert!((out.cpu_load_avg - (10.0 * 10.0 + 30.0 * 90.0) / 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn legacy_children_without_counts_fall_back_to_equal_weights() {
+    let aggs = vec![child(300_000, 0, 10.0, 100), child(360_000, 60, 40.0, 400)];
+    let out = aggregate_aggregated_snapshots(&aggs, 300_000, 300).unwrap();
+    assert_eq!(out.sample_count, 0, "unknown count propagates");
+    assert!((out.cpu_load_avg - 25.0).abs() < 1e-9);
+    assert_eq!(out.memory_used_avg, 250);
+    assert!((out.containers[0].cpu_percent - 25.0).abs() < 1e-9);
+}
@@ -0,0 +1,288 @@
+// Alert delivery: [alerts] webhook config, payload formats, POSTs with retry/backoff against a
+// local axum receiver, the evaluator task on the snapshot broadcast, and GET /api/alerts.
+
+mod common;
+
+use axum::{Json, Router, http::StatusCode, routing::post};
+use axum_test::TestServer;
+use homeserver::alerting::{
+    AlertEngine, AlertEvent, AlertState, AlertStatus, Notifier, WebhookTarget, chat_text, payload,
+};
+use homeserver::config::{AppConfig, DatabaseConfig, Severity, WebhookFormat};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+fn event(state: AlertState) -> AlertEvent {
+    AlertEvent {
+        rule_name: "cpu hot".into(),
+        metric: "cpu_temperature".into(),
+        op: ">".into(),
+        value: 91.24,
+        threshold: 85.0,
+        severity: Severity::Critical,
+        state,
+        timestamp: 1_700_000_000_000,
+        container: None,
+        check: None,
+    }
+}
+
+#[test]
+fn alerts_config_parses_webhooks_and_rule_options() {
+    let config = AppConfig::load_from_str(
+        r#"
+[alerts]
+webhook_url = "https://example.com/generic"
+webhook_retries = 5
+webhook_retry_backoff_ms = 250
+
+[[alerts.webhooks]]
+url = "https://discord.com/api/webhooks/1/abc"
+format = "discord"
+
+[[alerts.webhooks]]
+url = "https://hooks.slack.com/services/T/B/x"
+format = "slack"
+
+[[alerts.rules]]
+name = "disk"
+metric = "disk_usage_percent"
+op = ">"
+threshold = 90.0
+severity = "critical"
+hysteresis = 2.5
+"#,
+    )
+    .unwrap();
+    let alerts = &config.alerts;
+    assert_eq!(
+        (alerts.webhook_retries, alerts.webhook_retry_backoff_ms),
+        (5, 250)
+    );
+    let formats: Vec<_> = alerts.webhooks.iter().map(|w| w.format).collect();
+    assert_eq!(formats, [WebhookFormat::Discord, WebhookFormat::Slack]);
+    assert_eq!(alerts.rules[0].severity, Severity::Critical);
+    assert_eq!(alerts.rules[0].hysteresis, 2.5);
+    assert!(
+        !format!("{alerts:?}").contains("abc"),
+        "webhook URLs are redacted"
+    );
+
+    let defaults = AppConfig::default().alerts;
+    assert_eq!(
+        (defaults.webhook_retries, defaults.webhook_retry_backoff_ms),
+        (3, 1000)
+    );
+}
+
+#[test]
+fn invalid_alert_options_are_rejected() {
+    let rule =
+        "[[alerts.rules]]\nname = \"r\"\nmetric = \"cpu_usage\"\nop = \">\"\nthreshold = 1.0\n";
+    let err = AppConfig::load_from_str(&format!("{rule}hysteresis = -1.0\n")).unwrap_err();
+    assert!(err.to_string().contains("hysteresis"), "{err}");
+    assert!(AppConfig::load_from_str(&format!("{rule}severity = \"page\"\n")).is_err());
+    let err =
+        AppConfig::load_from_str("[[alerts.webhooks]]\nurl = \"ftp://x/secret\"\n").unwrap_err();
+    assert!(err.to_string().contains("http://"), "{err}");
+    assert!(!err.to_string().contains("secret"), "{err}");
+}
+
+#[test]
+fn payload_formats() {
+    let firing = event(AlertState::Firing);
+    let generic = payload(WebhookFormat::Generic, &firing);
+    assert_eq!(generic["rule"], "cpu hot");
+    assert_eq!(generic["severity"], "critical");
+    assert_eq!(generic["state"], "firing");
+    assert_eq!(generic["threshold"], 85.0);
+    assert_eq!(generic["timestamp"], 1_700_000_000_000u64);
+    assert!(generic.get("container").is_none());
+
+    let text = "[FIRING] cpu hot (critical): cpu_temperature is 91.2 (> 85)";
+    assert_eq!(chat_text(&firing), text);
+    assert_eq!(payload(WebhookFormat::Discord, &firing)["content"], text);
+    assert_eq!(payload(WebhookFormat::Slack, &firing)["text"], text);
+    assert!(chat_text(&event(AlertState::Resolved)).starts_with("[RESOLVED] cpu hot"));
+}
+
+/// A local webhook receiver answering 500 to the first `failures` POSTs; returns its base URL
+/// and the bodies it accepted.
+async fn receiver(failures: usize) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let seen = received.clone();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/{format}",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            let calls = calls.clone();
+            async move {
+                if calls.fetch_add(1, Ordering::SeqCst) < failures {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                seen.lock().unwrap().push(body);
+                StatusCode::NO_CONTENT
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}"), received)
+}
+
+fn target(base: &str, format: WebhookFormat) -> WebhookTarget {
+    WebhookTarget {
+        url: format!("{base}/{format:?}"),
+        format,
+    }
+}
+
+#[tokio::test]
+async fn notifier_posts_every_format_and_retries_failures() {
+    let (base, received) = receiver(2).await;
+    let notifier = Notifier::new(
+        vec![target(&base, WebhookFormat::Generic)],
+        2,
+        Duration::from_millis(10),
+    );
+    notifier.notify(&event(AlertState::Firing)).await;
+    let bodies = received.lock().unwrap().clone();
+    assert_eq!(
+        bodies.len(),
+        1,
+        "two 500s, then delivered on the last retry"
+    );
+    assert_eq!(bodies[0]["state"], "firing");
+
+    let (base, received) = receiver(0).await;
+    let notifier = Notifier::new(
+        vec![
+            target(&base, WebhookFormat::Discord),
+            target(&base, WebhookFormat::Slack),
+        ],
+        0,
+        Duration::from_millis(10),
+    );
+    notifier.notify(&event(AlertState::Resolved)).await;
+    let bodies = received.lock().unwrap().clone();
+    assert_eq!(bodies.len(), 2);
+    assert!(bodies.iter().any(|b| b["content"].is_string()));
+    assert!(bodies.iter().any(|b| b["text"].is_string()));
+}
+
+#[tokio::test]
+async fn notifier_gives_up_after_the_retries() {
+    let (base, received) = receiver(usize::MAX).await;
+    let notifier = Notifier::new(
+        vec![target(&base, WebhookFormat::Generic)],
+        1,
+        Duration::from_millis(10),
+    );
+    notifier.notify(&event(AlertState::Firing)).await;
+    assert!(received.lock().unwrap().is_empty());
+}
+
+fn busy(cpu: f64, timestamp: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(timestamp)
+    }
+}
+
+#[tokio::test]
+async fn evaluator_task_follows_the_broadcast_and_serves_active_alerts() {
+    let (base, received) = receiver(0).await;
+    let config = AppConfig::load_from_str(&format!(
+        "[alerts]\nwebhook_url = \"{base}/generic\"\n\n[[alerts.rules]]\nname = \"busy\"\n\
+         metric = \"cpu_usage\"\nop = \">\"\nthreshold = 90.0\nseverity = \"critical\"\n"
+    ))
+    .unwrap();
+    let (tx, _) = broadcast::channel(16);
+    let active = Arc::new(AlertStatus::default());
+    let shutdown = CancellationToken::new();
+    let handle = homeserver::alerting::spawn(
+        AlertEngine::new(config.alerts.rules.clone()),
+        Notifier::from_config(&config.alerts),
+        tx.clone(),
+        active.clone(),
+        shutdown.clone(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    while tx.receiver_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    tx.send(busy(95.0, 1000)).unwrap();
+    let wait_for = |n: usize| {
+        let received = received.clone();
+        async move {
+            for _ in 0..200 {
+                if received.lock().unwrap().len() >= n {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("webhook not called {n} times");
+        }
+    };
+    wait_for(1).await;
+    assert_eq!(received.lock().unwrap()[0]["state"], "firing");
+    assert_eq!(active.active().len(), 1);
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    let metrics = ServiceMetrics {
+        alerts: active.clone(),
+        ..Default::default()
+    };
+    let server = TestServer::new(routes::app(
+        tx.clone(),
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        metrics,
+    ));
+    let body: serde_json::Value = server.get("/api/alerts").await.json();
+    assert_eq!(body["rules"], 1);
+    assert_eq!(body["alerts"][0]["rule"], "busy");
+    assert_eq!(body["alerts"][0]["severity"], "critical");
+    assert_eq!(body["alerts"][0]["value"], 95.0);
+    assert_eq!(body["alerts"][0]["since"], 1000);
+
+    tx.send(busy(10.0, 2000)).unwrap();
+    wait_for(2).await;
+    assert_eq!(received.lock().unwrap()[1]["state"], "resolved");
+    let body: serde_json::Value = server.get("/api/alerts").await.json();
+    assert_eq!(body["alerts"], serde_json::json!([]));
+    let states: Vec<_> = body["recent"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["state"].clone())
+        .collect();
+    assert_eq!(states, ["resolved", "firing"], "newest first");
+
+    shutdown.cancel();
+    handle.await.unwrap();
+}
@@ -0,0 +1,99 @@
+// The container pids-saturation alert: the `container_pids_usage_percent` metric and the
+// built-in rule from `[alerts] pids_saturation_percent` (default, replaced, turned off).
+
+mod common;
+
+use homeserver::alerting::{AlertEngine, AlertState, extract_metric};
+use homeserver::config::{AlertRule, AlertsConfig, AppConfig, PIDS_SATURATION_RULE, Severity};
+use homeserver::models::*;
+use std::time::{Duration, Instant};
+
+fn snapshot(containers: Vec<ContainerStats>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers,
+        ..common::snapshot(0)
+    }
+}
+
+fn pids_container(name: &str, pids_usage_percent: f64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": name,
+        "name": name,
+        "cpuPercent": 0.0,
+        "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0,
+        "state": "running",
+        "networkRxBytes": 0,
+        "networkTxBytes": 0,
+        "blockReadBytes": 0,
+        "blockWriteBytes": 0,
+        "pidsUsagePercent": pids_usage_percent,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn container_pids_metric_is_the_fullest_container() {
+    assert_eq!(
+        extract_metric("container_pids_usage_percent", &snapshot(vec![])),
+        None
+    );
+    let s = snapshot(vec![pids_container("a", 12.5), pids_container("b", 93.0)]);
+    assert_eq!(
+        extract_metric("container_pids_usage_percent", &s),
+        Some(93.0)
+    );
+}
+
+#[test]
+fn built_in_pids_rule_fires_at_90_percent_by_default() {
+    let rules = AlertsConfig::default().effective_rules();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].name, PIDS_SATURATION_RULE);
+    assert_eq!(rules[0].threshold, 90.0);
+
+    let mut engine = AlertEngine::new(rules);
+    let mut s = snapshot(vec![pids_container("web", 89.0)]);
+    let t0 = Instant::now();
+    assert!(engine.evaluate(&s, t0).is_empty());
+    s.containers = vec![pids_container("web", 90.0)];
+    let events = engine.evaluate(&s, t0 + Duration::from_secs(1));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].state, AlertState::Firing);
+}
+
+#[test]
+fn built_in_pids_rule_can_be_replaced_or_turned_off() {
+    let config = AppConfig::load_from_str("[alerts]\npids_saturation_percent = 75.0\n").unwrap();
+    assert_eq!(config.alerts.effective_rules()[0].threshold, 75.0);
+
+    let mut alerts = AlertsConfig {
+        pids_saturation_percent: 0.0,
+        ..Default::default()
+    };
+    assert!(alerts.effective_rules().is_empty());
+    alerts.pids_saturation_percent = 90.0;
+    alerts.rules = vec![AlertRule {
+        name: "pids".into(),
+        metric: "container_pids_usage_percent".into(),
+        op: ">".into(),
+        threshold: 50.0,
+        duration_secs: 0,
+        cooldown_secs: 0,
+        severity: Severity::Warning,
+        hysteresis: 0.0,
+    }];
+    let rules = alerts.effective_rules();
+    assert_eq!(
+        rules.len(),
+        1,
+        "a configured rule on the metric replaces the built-in one"
+    );
+    assert_eq!(rules[0].name, "pids");
+
+    let err = AppConfig::load_from_str("[alerts]\npids_saturation_percent = 101.0\n").unwrap_err();
+    assert!(
+        format!("{err:#}").contains("pids_saturation_percent"),
+        "{err:#}"
+    );
+}
@@ -1,14 +1,15 @@
-// Unit tests for the alerting engine: pure metric extraction + the fire/resolve/cooldown
-// state machine driven by an injected clock (no async, no real time).
+// Unit tests for the alerting engine: pure metric extraction + the fire/resolve/cooldown/
+// hysteresis state machine driven by an injected clock (no async, no real time).
 
-use homeserver::alerting::{AlertEngine, AlertState, compare, extract_metric};
-use homeserver::config::AlertRule;
+mod common;
+
+use homeserver::alerting::{AlertEngine, AlertState, compare, extract_metric, still_breached};
+use homeserver::config::{AlertRule, Severity};
 use homeserver::models::*;
 use std::time::{Duration, Instant};
 
 fn snapshot(cpu_usage: f64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp: 0,
         cpu: CpuStats {
             usage_percent: cpu_usage,
             temperature: 80.0,
@@ -20,7 +21,6 @@ fn snapshot(cpu_usage: f64) -> FullSystemSnapshot {
             swap_used: 25,
             ..Default::default()
         },
-        containers: vec![],
         storage: StorageStats {
             partitions: vec![PartitionStat {
                 mount: "/".into(),
@@ -33,7 +33,6 @@ fn snapshot(cpu_usage: f64) -> FullSystemSnapshot {
             }],
             disks: vec![],
         },
-        network: NetworkStats::default(),
         system: SystemStatsDynamic {
             load_avg_1: 3.0,
             ..Default::default()
@@ -43,7 +42,7 @@ fn snapshot(cpu_usage: f64) -> FullSystemSnapshot {
             utilization_percent: 60.0,
             ..Default::default()
         }],
-        smart: vec![],
+        ..common::snapshot(0)
     }
 }
 
@@ -62,6 +61,8 @@ fn rule(
         threshold,
         duration_secs: duration,
         cooldown_secs: cooldown,
+        severity: Severity::Warning,
+        hysteresis: 0.0,
     }
 }
 
@@ -152,3 +153,108 @@ fn empty_engine_emits_nothing() {
     assert!(engine.is_empty());
     assert!(engine.evaluate(&snapshot(99.0), Instant::now()).is_empty());
 }
+
+#[test]
+fn still_breached_moves_the_threshold_by_the_hysteresis() {
+    assert!(still_breached(86.0, ">", 90.0, 5.0));
+    assert!(!still_breached(85.0, ">", 90.0, 5.0));
+    assert!(still_breached(85.0, ">=", 90.0, 5.0));
+    assert!(still_breached(14.0, "<", 10.0, 5.0));
+    assert!(!still_breached(15.0, "<", 10.0, 5.0));
+    assert!(!still_breached(90.0, ">", 90.0, 0.0));
+}
+
+/// Feed one CPU reading per second starting at `t0`; collect (second, state) of each event.
+fn run_sequence(
+    engine: &mut AlertEngine,
+    t0: Instant,
+    readings: &[f64],
+) -> Vec<(usize, AlertState)> {
+    let mut out = Vec::new();
+    for (i, cpu) in readings.iter().enumerate() {
+        let mut s = snapshot(*cpu);
+        s.timestamp = 1_000_000 + i as u64 * 1000;
+        for ev in engine.evaluate(&s, t0 + Duration::from_secs(i as u64)) {
+            out.push((i, ev.state));
+        }
+    }
+    out
+}
+
+#[test]
+fn cpu_above_90_for_five_minutes_fires_once_and_resolves_past_the_hysteresis() {
+    let mut hot = rule("cpu busy", "cpu_usage", ">", 90.0, 300, 0);
+    hot.hysteresis = 5.0;
+    let mut engine = AlertEngine::new(vec![hot]);
+    // 2 min busy, a dip resets the timer, then 6 min busy hovering around 90, then recovery.
+    let mut readings = vec![95.0; 120];
+    readings.push(80.0);
+    readings.extend((0..360).map(|i| if i < 301 { 93.0 } else { 88.0 + (i % 3) as f64 }));
+    readings.extend([86.0, 84.0, 95.0]);
+    let events = run_sequence(&mut engine, Instant::now(), &readings);
+    // Fires 300 s after the dip (second 121), stays firing while 88-90 is inside the 5-point
+    // band, resolves at the first reading below 85 and re-arms (95 is breached again but must
+    // be sustained for another 5 minutes).
+    assert_eq!(
+        events,
+        [(421, AlertState::Firing), (482, AlertState::Resolved)]
+    );
+}
+
+#[test]
+fn without_hysteresis_a_value_at_the_threshold_resolves() {
+    let mut engine = AlertEngine::new(vec![rule("hot", "cpu_usage", ">", 90.0, 0, 0)]);
+    let events = run_sequence(&mut engine, Instant::now(), &[91.0, 90.0, 91.0]);
+    assert_eq!(
+        events,
+        [
+            (0, AlertState::Firing),
+            (1, AlertState::Resolved),
+            (2, AlertState::Firing)
+        ]
+    );
+}
+
+#[test]
+fn disk_and_ram_rules_fire_independently() {
+    let mut disk = rule("disk full", "disk_usage_percent", ">=", 90.0, 0, 300);
+    disk.severity = Severity::Critical;
+    let ram = rule("ram", "mem_usage_percent", ">", 80.0, 0, 300);
+    let mut engine = AlertEngine::new(vec![disk, ram]);
+    let mut s = snapshot(10.0);
+    s.timestamp = 42;
+    let events = engine.evaluate(&s, Instant::now());
+    assert_eq!(events.len(), 1, "disk is 95% full, RAM 50%");
+    assert_eq!(events[0].rule_name, "disk full");
+    assert_eq!(events[0].severity, Severity::Critical);
+    assert_eq!(events[0].timestamp, 42);
+    assert_eq!(events[0].value, 95.0);
+}
+
+#[test]
+fn active_lists_firing_rules_with_the_latest_value() {
+    let mut engine = AlertEngine::new(vec![
+        rule("hot", "cpu_usage", ">", 80.0, 0, 0),
+        rule("load", "load_avg_1", ">", 10.0, 0, 0),
+    ]);
+    let t0 = Instant::now();
+    assert!(engine.active().is_empty());
+    let mut s = snapshot(90.0);
+    s.timestamp = 1000;
+    engine.evaluate(&s, t0);
+    s.cpu.usage_percent = 97.0;
+    s.timestamp = 2000;
+    engine.evaluate(&s, t0 + Duration::from_secs(1));
+    let active = engine.active();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].rule, "hot");
+    assert_eq!(active[0].value, 97.0);
+    assert_eq!(
+        active[0].since, 1000,
+        "when it fired, not the latest snapshot"
+    );
+    assert_eq!(active[0].severity, Severity::Warning);
+
+    engine.evaluate(&snapshot(10.0), t0 + Duration::from_secs(2));
+    assert!(engine.active().is_empty());
+}
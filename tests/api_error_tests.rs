@@ -0,0 +1,243 @@
+// Error envelope: every error class answers `{"error", "code", "details"?}` with its status, and
+// unknown paths (404) and methods (405) get the same shape from the router fallbacks.
+
+use std::sync::Arc;
+
+use axum_test::{TestResponse, TestServer};
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn app_server(config: AppConfig, history: impl Into<HistoryHandle>) -> TestServer {
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        history,
+        Default::default(),
+    ))
+}
+
+/// A server on a fresh database in `dir`.
+async fn local_server(dir: &TempDir, configure: impl FnOnce(&mut AppConfig)) -> TestServer {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    configure(&mut config);
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    app_server(config, Arc::new(repo))
+}
+
+/// The envelope of `response`, checking the JSON shape shared by every error.
+fn envelope(response: &TestResponse, code: &str) -> serde_json::Value {
+    let content_type = response.header("content-type");
+    assert_eq!(content_type.to_str().unwrap(), "application/json");
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], code, "{body}");
+    assert!(body["error"].is_string(), "{body}");
+    let keys: Vec<_> = body.as_object().unwrap().keys().cloned().collect();
+    assert!(
+        keys.iter()
+            .all(|k| ["error", "code", "details"].contains(&k.as_str())),
+        "{body}"
+    );
+    body
+}
+
+#[tokio::test]
+async fn bad_parameters_are_bad_request() {
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |_| {}).await;
+
+    let response = server
+        .get("/api/history?envelope=bogus")
+        .expect_failure()
+        .await;
+    response.assert_status_bad_request();
+    let body = envelope(&response, "bad_request");
+    assert_eq!(body["error"], "envelope must be one of: minmax, p95");
+    assert!(body.get("details").is_none());
+
+    // History range validation shares the envelope.
+    let response = server
+        .get("/api/history?from=2000&to=1000")
+        .expect_failure()
+        .await;
+    response.assert_status_bad_request();
+    assert_eq!(
+        envelope(&response, "bad_request")["error"],
+        "from must be less than to"
+    );
+
+    // A query string that does not deserialize is rejected by the extractor, not as plain text.
+    let response = server
+        .get("/api/history?from=yesterday")
+        .expect_failure()
+        .await;
+    response.assert_status_bad_request();
+    let body = envelope(&response, "bad_request");
+    assert!(body["error"].as_str().unwrap().contains("from"), "{body}");
+}
+
+#[tokio::test]
+async fn missing_resources_are_not_found() {
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |config| {
+        config.server.admin_token = Some(Secret::new("s3cret"));
+    })
+    .await;
+    let response = server
+        .get("/api/db/backup/download")
+        .authorization_bearer("s3cret")
+        .expect_failure()
+        .await;
+    response.assert_status_not_found();
+    assert_eq!(
+        envelope(&response, "not_found")["error"],
+        "no backup available"
+    );
+
+    let mut config = AppConfig::default();
+    config.database.enabled = false;
+    let agent = app_server(config, HistoryHandle::disabled());
+    let response = agent.get("/api/history").expect_failure().await;
+    response.assert_status_not_found();
+    envelope(&response, "not_found");
+}
+
+#[tokio::test]
+async fn unknown_paths_and_methods_use_the_fallbacks() {
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |_| {}).await;
+
+    let response = server.get("/api/does-not-exist").expect_failure().await;
+    response.assert_status_not_found();
+    assert_eq!(
+        envelope(&response, "not_found")["error"],
+        "no route for /api/does-not-exist"
+    );
+
+    let response = server.post("/api/cpu").expect_failure().await;
+    response.assert_status(axum::http::StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(
+        envelope(&response, "method_not_allowed")["error"],
+        "POST is not allowed on /api/cpu"
+    );
+    let allow = response.header("allow");
+    assert!(allow.to_str().unwrap().contains("GET"), "{allow:?}");
+
+    let response = server.get("/api/worker/pause").expect_failure().await;
+    response.assert_status(axum::http::StatusCode::METHOD_NOT_ALLOWED);
+    envelope(&response, "method_not_allowed");
+}
+
+#[tokio::test]
+async fn a_database_still_opening_is_unavailable_with_details() {
+    let server = app_server(AppConfig::default(), HistoryHandle::pending());
+    let response = server.get("/api/history").expect_failure().await;
+    response.assert_status_service_unavailable();
+    assert_eq!(response.header("retry-after"), "5");
+    let body = envelope(&response, "unavailable");
+    assert_eq!(body["error"], "the history database is not ready yet");
+    assert!(body["details"]["reason"].is_null(), "{body}");
+}
+
+#[tokio::test]
+async fn server_side_failures_are_internal() {
+    let dir = TempDir::new().unwrap();
+    // A regular file where the backup directory should be: listing it fails.
+    let not_a_dir = dir.path().join("backups");
+    std::fs::write(&not_a_dir, b"").unwrap();
+    let server = local_server(&dir, |config| {
+        config.database.backup_dir = not_a_dir.to_str().unwrap().into();
+        config.server.admin_token = Some(Secret::new("s3cret"));
+    })
+    .await;
+    let response = server
+        .get("/api/db/backup/download")
+        .authorization_bearer("s3cret")
+        .expect_failure()
+        .await;
+    response.assert_status_internal_server_error();
+    assert_eq!(
+        envelope(&response, "internal")["error"],
+        "failed to list backups"
+    );
+}
+
+#[tokio::test]
+async fn admin_rejections_are_forbidden_or_unauthorized() {
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |_| {}).await;
+    let response = server.post("/api/config/reload").expect_failure().await;
+    response.assert_status_forbidden();
+    envelope(&response, "forbidden");
+
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |config| {
+        config.server.admin_token = Some(Secret::new("s3cret"));
+    })
+    .await;
+    let response = server
+        .post("/api/config/reload")
+        .authorization_bearer("wrong")
+        .expect_failure()
+        .await;
+    response.assert_status_unauthorized();
+    assert_eq!(response.header("www-authenticate"), "Bearer");
+    envelope(&response, "unauthorized");
+}
+
+#[test]
+fn every_status_has_its_own_code() {
+    use axum::http::StatusCode;
+    use homeserver::routes::error_code;
+    for (status, code) in [
+        (StatusCode::BAD_REQUEST, "bad_request"),
+        (StatusCode::UNAUTHORIZED, "unauthorized"),
+        (StatusCode::FORBIDDEN, "forbidden"),
+        (StatusCode::NOT_FOUND, "not_found"),
+        (StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed"),
+        (StatusCode::NOT_ACCEPTABLE, "not_acceptable"),
+        (StatusCode::REQUEST_TIMEOUT, "request_timeout"),
+        (StatusCode::CONFLICT, "conflict"),
+        (StatusCode::GONE, "gone"),
+        (StatusCode::LENGTH_REQUIRED, "length_required"),
+        (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large"),
+        (StatusCode::URI_TOO_LONG, "uri_too_long"),
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type"),
+        (StatusCode::UNPROCESSABLE_ENTITY, "unprocessable"),
+        (StatusCode::TOO_MANY_REQUESTS, "too_many_requests"),
+        (StatusCode::NOT_IMPLEMENTED, "not_implemented"),
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        // Not answered by any route today: the generic class of their range.
+        (StatusCode::IM_A_TEAPOT, "client_error"),
+        (StatusCode::BAD_GATEWAY, "internal"),
+    ] {
+        assert_eq!(error_code(status), code, "{status}");
+    }
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_payload_too_large() {
+    let dir = TempDir::new().unwrap();
+    let server = local_server(&dir, |config| {
+        config.remote_write.ingest_api_key = Some(Secret::new("k"));
+    })
+    .await;
+    let response = server
+        .post("/api/ingest")
+        .authorization_bearer("k")
+        .content_type("application/json")
+        .bytes(vec![b' '; 64 * 1024 * 1024].into())
+        .expect_failure()
+        .await;
+    response.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    envelope(&response, "payload_too_large");
+}
@@ -0,0 +1,152 @@
+// GET /api/bootstrap: one envelope of info, version, latest snapshot, recent history and
+// capabilities; sections can be left out, and missing data is null rather than an error.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn snapshot(ts: u64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+fn server(
+    config: AppConfig,
+    history: impl Into<HistoryHandle>,
+    metrics: ServiceMetrics,
+) -> TestServer {
+    let info = SystemInfo {
+        system_model: "nas".into(),
+        ..Default::default()
+    };
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(info),
+        Default::default(),
+        config,
+        history,
+        metrics,
+    ))
+}
+
+async fn repo() -> (AppConfig, Arc<HistoryRepo>, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    (config, repo, dir)
+}
+
+#[tokio::test]
+async fn envelope_combines_every_section() {
+    let (mut config, repo, _dir) = repo().await;
+    config.server.node_name = "nas-1".into();
+    // Ten samples in the last few minutes, one per 30 s bucket.
+    let start = (common::now_ms() as u64 - 600_000) / 30_000 * 30_000;
+    let snaps: Vec<_> = (0..10)
+        .map(|i| snapshot(start + i * 30_000, 10.0))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let metrics = ServiceMetrics::default();
+    metrics
+        .broadcast
+        .record_latest(&snapshot(common::now_ms() as u64, 42.0));
+    let server = server(config, repo, metrics);
+
+    let response = server.get("/api/bootstrap").await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let keys: Vec<&str> = body
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(keys.len(), 6, "{keys:?}");
+    assert_eq!(body["nodeName"], "nas-1");
+    assert_eq!(body["info"]["systemModel"], "nas");
+    assert_eq!(body["version"]["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["version"].get("gitCommit").is_some());
+    assert_eq!(body["latest"]["cpu"]["usagePercent"], 42.0);
+    let history = body["history"].as_array().unwrap();
+    assert_eq!(history.len(), 10);
+    assert_eq!(history[0]["timestamp"], start);
+    assert_eq!(history[0]["cpu"]["usagePercent"], 10.0);
+    assert_eq!(body["capabilities"]["features"]["history"], true);
+    assert_eq!(body["capabilities"]["historyEarliestTs"], start);
+
+    // A shorter window at a coarser resolution.
+    let body: serde_json::Value = server
+        .get("/api/bootstrap?history_secs=120&resolution=1m&info=false")
+        .await
+        .json();
+    assert!(body.get("info").is_none());
+    assert!(body["history"].as_array().unwrap().len() <= 3);
+}
+
+#[tokio::test]
+async fn sections_can_be_left_out() {
+    let config = AppConfig::default();
+    let node_name = config.server.node_name.clone();
+    let server = server(config, None, ServiceMetrics::default());
+    let body: serde_json::Value = server
+        .get("/api/bootstrap?info=false&version=false&history=false&capabilities=false")
+        .await
+        .json();
+    assert_eq!(
+        body,
+        serde_json::json!({"nodeName": node_name, "latest": null})
+    );
+}
+
+#[tokio::test]
+async fn missing_latest_and_history_are_null() {
+    let mut config = AppConfig::default();
+    config.database.enabled = false;
+    let server = server(config, None, ServiceMetrics::default());
+    let response = server.get("/api/bootstrap").await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["latest"].is_null(), "{body}");
+    assert!(body["history"].is_null(), "{body}");
+    assert_eq!(body["capabilities"]["features"]["history"], false);
+    assert_eq!(body["info"]["systemModel"], "nas");
+}
+
+#[tokio::test]
+async fn bad_history_window_is_a_bad_request() {
+    let server = server(AppConfig::default(), None, ServiceMetrics::default());
+    for query in [
+        "history_secs=0",
+        "history_secs=-5",
+        "history_secs=99999999999999999",
+        "history_secs=3000000",
+    ] {
+        let response = server.get(&format!("/api/bootstrap?{query}")).await;
+        response.assert_status_bad_request();
+        let body: serde_json::Value = response.json();
+        assert!(body["error"].is_string(), "{query}: {body}");
+    }
+    // The window is not checked when history is left out.
+    server
+        .get("/api/bootstrap?history=false&history_secs=0")
+        .await
+        .assert_status_ok();
+}
@@ -0,0 +1,228 @@
+// Snapshot broadcast accounting: the worker measures every snapshot's serialized size and counts
+// those over publishing.max_snapshot_bytes, records queue use and receivers per send, and
+// /ws/system clients falling behind are counted with a warning past lag_warn_per_minute.
+
+use axum_test::TestServer;
+use futures_util::future::BoxFuture;
+use homeserver::config::AppConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{
+    BroadcastMetrics, StatsCollector, WorkerConfig, WorkerDeps, snapshot_json_len, spawn,
+};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Reports `containers` running containers every tick.
+struct ManyContainers {
+    containers: usize,
+}
+
+fn container(i: usize) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("{i:064x}"),
+        "name": format!("service-{i}"),
+        "cpuPercent": 1.5,
+        "memoryUsageBytes": 1 << 20,
+        "memoryLimitBytes": 1 << 30,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+impl StatsCollector for ManyContainers {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(async { Ok(CpuStats::default()) })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async { Ok((0..self.containers).map(container).collect()) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
+    }
+}
+
+/// Run an agent-mode worker reporting `containers` containers for a few ticks, with one
+/// subscriber that never reads.
+async fn run_worker(
+    containers: usize,
+    max_snapshot_bytes: u64,
+) -> (Arc<BroadcastMetrics>, Vec<FullSystemSnapshot>) {
+    let (tx, mut rx) = broadcast::channel(64);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(BroadcastMetrics::default());
+    let mut config = AppConfig::default();
+    config.monitoring.sample_interval_ms = 20;
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(ManyContainers { containers }),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: None.into(),
+            tx,
+            write_tx: None,
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: metrics.clone(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        WorkerConfig {
+            collect_gpu: false,
+            storage_interval_ms: 20,
+            docker_interval_ms: 20,
+            system_interval_ms: 20,
+            max_snapshot_bytes,
+            ..WorkerConfig::from_app(&config)
+        },
+    );
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+    let mut snapshots = Vec::new();
+    while let Ok(s) = rx.try_recv() {
+        snapshots.push(s);
+    }
+    (metrics, snapshots)
+}
+
+#[tokio::test]
+async fn oversized_snapshots_are_counted_every_tick() {
+    let (metrics, snapshots) = run_worker(200, 16 * 1024).await;
+    let stats = metrics.snapshot();
+    assert!(!snapshots.is_empty());
+    assert_eq!(stats.oversized_snapshots_total, snapshots.len() as u64);
+    let last = snapshots.last().unwrap();
+    let json_len = serde_json::to_string(last).unwrap().len() as u64;
+    assert_eq!(snapshot_json_len(last), json_len);
+    assert_eq!(stats.last_snapshot_bytes, json_len);
+    assert_eq!(metrics.latest_snapshot().unwrap().timestamp, last.timestamp);
+    assert!(stats.last_snapshot_bytes > 16 * 1024, "{stats:?}");
+    // Self-stats figures make sizes differ slightly between ticks.
+    assert!(stats.max_snapshot_bytes >= stats.last_snapshot_bytes);
+    // The subscriber never read, so every snapshot sent is still queued.
+    assert_eq!(stats.sent_total, snapshots.len() as u64);
+    assert_eq!(stats.queued, stats.sent_total);
+    assert_eq!(stats.max_queued, stats.sent_total);
+    assert_eq!(stats.receivers, 1);
+}
+
+#[tokio::test]
+async fn small_snapshots_stay_under_the_default_limit() {
+    let default_limit = AppConfig::default().publishing.max_snapshot_bytes;
+    let (metrics, snapshots) = run_worker(3, default_limit).await;
+    let stats = metrics.snapshot();
+    assert!(!snapshots.is_empty());
+    assert_eq!(stats.oversized_snapshots_total, 0);
+    assert!(stats.last_snapshot_bytes > 0);
+}
+
+#[test]
+fn lag_warning_fires_once_per_minute_past_the_threshold() {
+    let metrics = BroadcastMetrics::default();
+    let warnings: Vec<_> = (0..6).map(|_| metrics.record_lag(4, 3)).collect();
+    assert_eq!(warnings, [None, None, None, Some(4), None, None]);
+    let stats = metrics.snapshot();
+    assert_eq!(stats.lag_events_total, 6);
+    assert_eq!(stats.lagged_messages_total, 24);
+    assert_eq!(stats.lag_warnings_total, 1);
+
+    let eager = BroadcastMetrics::default();
+    assert_eq!(eager.record_lag(1, 0), Some(1));
+    assert_eq!(eager.record_lag(1, 0), None);
+}
+
+#[test]
+fn publishing_limits_parse_and_are_validated() {
+    let defaults = AppConfig::default().publishing;
+    assert_eq!(defaults.max_snapshot_bytes, 1024 * 1024);
+    assert_eq!(defaults.lag_warn_per_minute, 10);
+    let config = AppConfig::load_from_str(
+        "[publishing]\nmax_snapshot_bytes = 65536\nlag_warn_per_minute = 2\n",
+    )
+    .unwrap();
+    assert_eq!(config.publishing.max_snapshot_bytes, 65536);
+    assert_eq!(WorkerConfig::from_app(&config).max_snapshot_bytes, 65536);
+    let err = AppConfig::load_from_str("[publishing]\nmax_snapshot_bytes = 100\n").unwrap_err();
+    assert!(err.to_string().contains("max_snapshot_bytes"), "{err}");
+}
+
+#[tokio::test]
+async fn lagging_websocket_client_is_counted_and_exposed() {
+    let (tx, _) = broadcast::channel(2);
+    let metrics = ServiceMetrics::default();
+    let mut config = AppConfig::default();
+    config.publishing.lag_warn_per_minute = 0;
+    config.server.node_name = "nas".into();
+    let server = TestServer::builder().http_transport().build(routes::app(
+        tx.clone(),
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        None,
+        metrics.clone(),
+    ));
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let _welcome = ws.receive_text().await;
+    // Sent without yielding, so the client task cannot keep up with a capacity of 2.
+    let snapshot: FullSystemSnapshot = serde_json::from_value(serde_json::json!({
+        "timestamp": 1, "cpu": CpuStats::default(), "ram": RamStats::default(),
+        "containers": [], "storage": StorageStats::default(), "network": NetworkStats::default(),
+        "system": SystemStatsDynamic::default(),
+    }))
+    .unwrap();
+    for _ in 0..10 {
+        tx.send(snapshot.clone()).unwrap();
+    }
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while metrics.broadcast.snapshot().lag_events_total == 0 {
+        assert!(tokio::time::Instant::now() < deadline, "no lag recorded");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let stats = metrics.broadcast.snapshot();
+    assert_eq!(stats.lag_events_total, 1);
+    assert_eq!(stats.lagged_messages_total, 8);
+    assert_eq!(stats.lag_warnings_total, 1);
+    let body: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(body["broadcast"]["lagEventsTotal"], 1);
+    assert_eq!(body["broadcast"]["laggedMessagesTotal"], 8);
+    let text = server.get("/metrics").await.text();
+    for line in [
+        "homeserver_broadcast_lag_events_total{node=\"nas\"} 1\n",
+        "homeserver_broadcast_lag_warnings_total{node=\"nas\"} 1\n",
+        "homeserver_snapshot_oversized_total{node=\"nas\"} 0\n",
+        "# TYPE homeserver_broadcast_queued_snapshots gauge\n",
+    ] {
+        assert!(text.contains(line), "missing {line:?} in {text}");
+    }
+}
@@ -0,0 +1,193 @@
+// GET /api/capabilities: response shape, history range from raw and aggregated rows (cached),
+// retention per tier, and feature flags following the config.
+
+mod common;
+
+use axum_test::TestServer;
+use common::snapshot;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const HOUR_MS: i64 = 3_600_000;
+const DAY_MS: i64 = 86_400_000;
+
+fn config(extra: &str, dir: &TempDir) -> AppConfig {
+    let db_path = dir.path().join("test.db");
+    AppConfig::load_from_str(&format!(
+        "[database]\npath = \"{}\"\nmax_pool_size = 2\n\n[monitoring]\nsample_interval_ms = 2000\n{extra}",
+        db_path.to_str().unwrap()
+    ))
+    .unwrap()
+}
+
+async fn server(config: AppConfig) -> (TestServer, Option<Arc<HistoryRepo>>) {
+    let repo = if config.database.enabled {
+        let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+        repo.init().await.unwrap();
+        Some(repo)
+    } else {
+        None
+    };
+    let (tx, _) = broadcast::channel(10);
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo.clone(),
+        Default::default(),
+    );
+    (TestServer::new(app), repo)
+}
+
+#[tokio::test]
+async fn capabilities_shape_with_default_config() {
+    let dir = TempDir::new().unwrap();
+    let (server, _) = server(config("", &dir)).await;
+    let body: serde_json::Value = server.get("/api/capabilities").await.json();
+    for key in [
+        "name",
+        "version",
+        "historyEarliestTs",
+        "historyLatestTs",
+        "sampleIntervalMs",
+        "retention",
+        "features",
+    ] {
+        assert!(body.get(key).is_some(), "missing {key}: {body}");
+    }
+    assert!(body["historyEarliestTs"].is_null());
+
+    let caps: Capabilities = serde_json::from_value(body).unwrap();
+    assert_eq!(caps.version, homeserver::version::VERSION);
+    assert_eq!(caps.sample_interval_ms, 2000);
+    let tiers: Vec<i32> = caps
+        .retention
+        .iter()
+        .map(|t| t.resolution_seconds)
+        .collect();
+    assert_eq!(tiers, [0, 60, 300, 3600, 86400]);
+    let db = AppConfig::default().database;
+    assert_eq!(
+        caps.retention[0].keep_ms,
+        i64::from(db.raw_retention_hours) * HOUR_MS
+    );
+    assert_eq!(
+        caps.retention[4].keep_ms,
+        i64::from(db.aggregated_retention_days) * DAY_MS
+    );
+    assert_eq!(
+        caps.features,
+        Features {
+            history: true,
+            aggregation: true,
+            gpu: true,
+            smart: false,
+            sensors: true,
+            alerts: false,
+            mqtt: false,
+            remote_write: false,
+            wol: false,
+            probes: false,
+            http_checks: false,
+        }
+    );
+}
+
+#[tokio::test]
+async fn feature_flags_follow_config() {
+    let dir = TempDir::new().unwrap();
+    let extra = r#"collect_gpu = false
+collect_smart = true
+collect_sensors = false
+
+[[alerts.rules]]
+name = "hot"
+metric = "cpu_usage"
+op = ">"
+threshold = 90.0
+
+[mqtt]
+broker_url = "mqtt://broker.lan"
+"#;
+    let (server, _) = server(config(extra, &dir)).await;
+    let caps: Capabilities = server.get("/api/capabilities").await.json();
+    assert!(!caps.features.gpu);
+    assert!(caps.features.smart);
+    assert!(!caps.features.sensors);
+    assert!(caps.features.alerts);
+    assert!(caps.features.mqtt);
+    assert!(!caps.features.remote_write);
+}
+
+#[tokio::test]
+async fn without_aggregation_raw_rows_keep_retention_days() {
+    let dir = TempDir::new().unwrap();
+    let mut config = config("", &dir);
+    config.database.enable_aggregation = false;
+    config.database.retention_days = 14;
+    let (server, _) = server(config).await;
+    let caps: Capabilities = server.get("/api/capabilities").await.json();
+    assert!(!caps.features.aggregation);
+    assert_eq!(
+        caps.retention,
+        [TierRetention {
+            resolution_seconds: 0,
+            keep_ms: 14 * DAY_MS,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn agent_mode_reports_no_history() {
+    let dir = TempDir::new().unwrap();
+    let mut config = config("", &dir);
+    config.database.enabled = false;
+    let (server, _) = server(config).await;
+    let caps: Capabilities = server.get("/api/capabilities").await.json();
+    assert!(!caps.features.history);
+    assert!(!caps.features.aggregation);
+    assert!(caps.retention.is_empty());
+    assert_eq!(caps.history_earliest_ts, None);
+    assert_eq!(caps.history_latest_ts, None);
+}
+
+#[tokio::test]
+async fn history_range_spans_raw_and_aggregated_rows_and_is_cached() {
+    let dir = TempDir::new().unwrap();
+    let (server, repo) = server(config("", &dir)).await;
+    let repo = repo.unwrap();
+    let base: u64 = 1_750_000_000_000;
+    repo.save_snapshots(
+        &[snapshot(base), snapshot(base + 5000)],
+        &SystemInfo::default(),
+    )
+    .await
+    .unwrap();
+    let hour_start = base as i64 - DAY_MS;
+    let older = aggregate_snapshots(&[snapshot(hour_start as u64)], hour_start, 3600).unwrap();
+    repo.save_aggregated_snapshot(&older).await.unwrap();
+
+    let caps: Capabilities = server.get("/api/capabilities").await.json();
+    assert_eq!(caps.history_earliest_ts, Some(base as i64 - DAY_MS));
+    assert_eq!(caps.history_latest_ts, Some(base as i64 + 5000));
+
+    // Within the TTL the cached range is served.
+    repo.save_snapshots(&[snapshot(base + 10_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    let cached: Capabilities = server.get("/api/capabilities").await.json();
+    assert_eq!(cached.history_latest_ts, Some(base as i64 + 5000));
+    assert_eq!(
+        repo.get_history_bounds().await.unwrap(),
+        (Some(base as i64 - DAY_MS), Some(base as i64 + 10_000))
+    );
+}
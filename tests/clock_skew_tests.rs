@@ -0,0 +1,230 @@
+// Clock skew protection: the history writer drops implausible timestamps, aggregation cutoffs
+// never move backwards across clock jumps, and pruning skips passes that would empty a table.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick_at};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use homeserver::worker::{HistoryWriterConfig, spawn_history_writer, timestamp_plausible};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    common::snapshot(ts as u64)
+}
+
+fn writer_config() -> HistoryWriterConfig {
+    HistoryWriterConfig {
+        flush_rate: 100,
+        flush_interval_secs: 3600,
+        flush_warn_ms: 1000,
+        persist_gpu: true,
+        persist_smart: true,
+        min_snapshot_timestamp_ms: DatabaseConfig::default().min_snapshot_timestamp_ms,
+        max_future_skew_minutes: 5,
+        min_free_bytes: 0,
+    }
+}
+
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        retention_days: 3,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(dir: &TempDir, max_prune_fraction: f64) -> Arc<HistoryRepo> {
+    Arc::new(
+        common::connect_with(DatabaseConfig {
+            max_prune_fraction,
+            ..common::database(&dir.path().join("h.db"))
+        })
+        .await,
+    )
+}
+
+async fn raw_count(repo: &HistoryRepo) -> usize {
+    repo.get_raw_snapshots_by_time_range(i64::MIN, i64::MAX)
+        .await
+        .unwrap()
+        .len()
+}
+
+#[test]
+fn plausible_timestamps_sit_between_floor_and_future_skew() {
+    let config = writer_config();
+    let floor = config.min_snapshot_timestamp_ms;
+    let now = floor + 365 * MS_PER_DAY as u64;
+    assert!(timestamp_plausible(now, now, &config));
+    assert!(timestamp_plausible(floor, now, &config));
+    assert!(!timestamp_plausible(floor - 1, now, &config));
+    assert!(!timestamp_plausible(5_000, now, &config), "1970 boot clock");
+    assert!(timestamp_plausible(now + 5 * 60_000, now, &config));
+    assert!(!timestamp_plausible(now + 5 * 60_000 + 1, now, &config));
+}
+
+#[tokio::test]
+async fn writer_drops_unsynced_and_future_snapshots() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 0.5).await;
+    let (tx, rx) = homeserver::worker::write_queue(
+        8,
+        homeserver::worker::OverflowPolicy::DropNew,
+        Default::default(),
+    );
+    let handle = spawn_history_writer(
+        rx,
+        repo.clone(),
+        Arc::new(SystemInfo::default()),
+        writer_config(),
+        Arc::new(AtomicU64::new(0)),
+        Default::default(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    let now = common::now_ms();
+    // Boot at 1970, then NTP sync, then a sample from an hour in the future.
+    for ts in [1_000, 2_000, now, now + MS_PER_HOUR, now + 1_000] {
+        tx.send(snapshot(ts));
+    }
+    drop(tx);
+    handle.await.unwrap();
+
+    let (_, saved) = repo.get_recent_snapshots(10).await.unwrap();
+    let saved: Vec<i64> = saved.iter().map(|s| s.timestamp as i64).collect();
+    assert_eq!(saved, [now, now + 1_000]);
+}
+
+#[tokio::test]
+async fn aggregation_cutoffs_never_move_backwards() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 0.5).await;
+    let shutdown = CancellationToken::new();
+    let t = ((common::now_ms() - MS_PER_DAY) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    // Raw samples every 10 s over the 3 hours before `t`, plus one NTP-synced hour after it.
+    let snaps: Vec<_> = (0..4 * 360)
+        .map(|i| snapshot(t - 3 * MS_PER_HOUR + i * 10_000))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    // Pass at t: everything older than t - 1 h rolls into 1-min buckets.
+    run_one_tick_at(repo.as_ref(), &worker_config(), t, &shutdown)
+        .await
+        .unwrap();
+    let watermark = repo.get_aggregation_watermark(60).await.unwrap();
+    assert_eq!(watermark, Some(t - MS_PER_HOUR));
+    assert_eq!(repo.get_aggregation_watermark(0).await.unwrap(), Some(t));
+
+    // The clock falls back to 1970 (RTC-less reboot), then to two hours before t: the cutoffs
+    // stay at t's, nothing is re-aggregated or lost.
+    for skewed in [5_000, t - 2 * MS_PER_HOUR] {
+        let raw_before = raw_count(&repo).await;
+        run_one_tick_at(repo.as_ref(), &worker_config(), skewed, &shutdown)
+            .await
+            .unwrap();
+        assert_eq!(repo.get_aggregation_watermark(0).await.unwrap(), Some(t));
+        assert_eq!(repo.get_aggregation_watermark(60).await.unwrap(), watermark);
+        assert_eq!(raw_count(&repo).await, raw_before);
+    }
+
+    // Time moves on past t: the cutoffs advance again.
+    run_one_tick_at(
+        repo.as_ref(),
+        &worker_config(),
+        t + 30 * MS_PER_MINUTE,
+        &shutdown,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        repo.get_aggregation_watermark(60).await.unwrap(),
+        Some(t - 30 * MS_PER_MINUTE)
+    );
+    // The pass clock is bookkeeping, not a tier watermark.
+    let tiers: Vec<i32> = repo
+        .get_aggregation_watermarks()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|w| w.resolution_seconds)
+        .collect();
+    assert_eq!(tiers, [60]);
+}
+
+#[tokio::test]
+async fn raw_prune_skips_a_pass_that_would_empty_the_table() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 0.5).await;
+    let now = common::now_ms();
+    // 8 rows past the 3-day retention (as after the clock jumped forward), 2 recent.
+    let mut snaps: Vec<_> = (0..8)
+        .map(|i| snapshot(now - 10 * MS_PER_DAY + i))
+        .collect();
+    snaps.extend([snapshot(now - 1_000), snapshot(now)]);
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(repo.prune_old_data().await.unwrap(), 0);
+    assert_eq!(raw_count(&repo).await, 10);
+
+    // Below the fraction the prune goes ahead.
+    repo.save_snapshots(
+        &(1..=10)
+            .map(|i| snapshot(now - i * 1_000 - 1))
+            .collect::<Vec<_>>(),
+        &SystemInfo::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(repo.prune_old_data().await.unwrap(), 8);
+    assert_eq!(raw_count(&repo).await, 12);
+}
+
+#[tokio::test]
+async fn guard_disabled_at_fraction_one() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 1.0).await;
+    let now = common::now_ms();
+    let snaps: Vec<_> = (0..5)
+        .map(|i| snapshot(now - 10 * MS_PER_DAY + i))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(repo.prune_old_data().await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn aggregated_prune_skips_after_a_forward_clock_jump() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 0.5).await;
+    let now = common::now_ms();
+    for d in 1..=5 {
+        let ts = ((now - d * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+        let agg = aggregate_snapshots(&[snapshot(ts)], ts, 86400).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+    // Clock jumps ten years ahead: every daily row is past aggregated_retention_days.
+    let jumped = now + 3650 * MS_PER_DAY;
+    let cutoffs = repo.aggregated_prune_cutoffs(jumped).await.unwrap();
+    assert_eq!(repo.prune_aggregated_old_data(&cutoffs).await.unwrap(), 0);
+    let left = repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 86400)
+        .await
+        .unwrap();
+    assert_eq!(left.len(), 5);
+
+    // The regular pass prunes nothing either: all five rows are within retention.
+    let cutoffs = repo.aggregated_prune_cutoffs(now).await.unwrap();
+    assert_eq!(repo.prune_aggregated_old_data(&cutoffs).await.unwrap(), 0);
+}
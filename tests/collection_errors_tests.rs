@@ -0,0 +1,144 @@
+// Collector failure diagnostics: collection_errors insert/read, retention pruning, rate limit.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::{CollectionError, ErrorSourceCount};
+use homeserver::worker::ErrorRateLimiter;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::time::Instant;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+async fn connect(dir: &TempDir, error_retention_days: u32) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        error_retention_days,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+fn entry(ts: i64, source: &str, suppressed: u64) -> CollectionError {
+    CollectionError {
+        ts,
+        source: source.into(),
+        message: format!("{source} failed at {ts}"),
+        suppressed,
+    }
+}
+
+#[tokio::test]
+async fn record_and_read_back() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 7).await;
+    assert!(repo.get_recent_errors(10).await.unwrap().is_empty());
+
+    let entries = [
+        entry(1_000, "docker", 0),
+        entry(2_000, "network", 0),
+        entry(3_000, "docker", 4),
+    ];
+    for e in &entries {
+        repo.record_error(e).await.unwrap();
+    }
+
+    let recent = repo.get_recent_errors(10).await.unwrap();
+    assert_eq!(
+        recent,
+        [entries[2].clone(), entries[1].clone(), entries[0].clone()]
+    );
+    assert_eq!(
+        repo.get_recent_errors(1).await.unwrap(),
+        [entries[2].clone()]
+    );
+
+    // Suppressed failures count towards their source.
+    assert_eq!(
+        repo.error_counts_since(0).await.unwrap(),
+        [
+            ErrorSourceCount {
+                source: "docker".into(),
+                count: 6
+            },
+            ErrorSourceCount {
+                source: "network".into(),
+                count: 1
+            },
+        ]
+    );
+    assert_eq!(
+        repo.error_counts_since(2_500).await.unwrap(),
+        [ErrorSourceCount {
+            source: "docker".into(),
+            count: 5
+        }]
+    );
+}
+
+#[tokio::test]
+async fn prune_drops_entries_past_error_retention() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 1).await;
+    let now = common::now_ms();
+    repo.record_error(&entry(now - 2 * MS_PER_DAY, "docker", 0))
+        .await
+        .unwrap();
+    repo.record_error(&entry(now - MS_PER_DAY / 2, "network", 0))
+        .await
+        .unwrap();
+
+    assert_eq!(repo.prune_collection_errors(now).await.unwrap(), 1);
+    let remaining = repo.get_recent_errors(10).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].source, "network");
+
+    // prune_old_data (the worker's prune tick) applies the same retention.
+    repo.record_error(&entry(now - 3 * MS_PER_DAY, "cpu", 0))
+        .await
+        .unwrap();
+    repo.prune_old_data().await.unwrap();
+    assert_eq!(repo.get_recent_errors(10).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn retention_change_applies_to_the_next_prune() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 7).await;
+    let now = common::now_ms();
+    repo.record_error(&entry(now - 2 * MS_PER_DAY, "docker", 0))
+        .await
+        .unwrap();
+    assert_eq!(repo.prune_collection_errors(now).await.unwrap(), 0);
+
+    // A config reload lowering database.error_retention_days.
+    repo.set_retention_days(3, 1);
+    assert_eq!(repo.prune_collection_errors(now).await.unwrap(), 1);
+}
+
+#[test]
+fn rate_limit_is_per_source_and_counts_suppressed() {
+    let mut limiter = ErrorRateLimiter::new(Duration::from_secs(60));
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.admit("docker", t0), Some(0));
+    assert_eq!(limiter.admit("docker", t0 + Duration::from_secs(1)), None);
+    assert_eq!(limiter.admit("docker", t0 + Duration::from_secs(59)), None);
+    // Another source has its own budget.
+    assert_eq!(
+        limiter.admit("network", t0 + Duration::from_secs(1)),
+        Some(0)
+    );
+
+    // After the interval the next failure is recorded with the two dropped ones.
+    assert_eq!(
+        limiter.admit("docker", t0 + Duration::from_secs(60)),
+        Some(2)
+    );
+    assert_eq!(limiter.admit("docker", t0 + Duration::from_secs(61)), None);
+    assert_eq!(
+        limiter.admit("docker", t0 + Duration::from_secs(200)),
+        Some(1)
+    );
+}
@@ -0,0 +1,221 @@
+// Per-source collection timings: each collector is timed on the ticks it runs, rolling mean / p95
+// / max land in CollectionMetrics (and /api/stats), and slow ticks are pinned on the slowest
+// source.
+
+use axum_test::TestServer;
+use futures_util::future::BoxFuture;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{
+    CollectionMetrics, CollectionTimings, StatsCollector, TOTAL_TIMING, WorkerConfig, WorkerDeps,
+    slowest_source, spawn,
+};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// The container listing takes `docker`, every other collector `others`.
+struct SlowDocker {
+    docker: Duration,
+    others: Duration,
+}
+
+impl SlowDocker {
+    fn after<T: Send + 'static>(&self, delay: Duration, value: T) -> BoxFuture<'_, T> {
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            value
+        })
+    }
+}
+
+impl StatsCollector for SlowDocker {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        self.after(self.others, Ok(CpuStats::default()))
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        self.after(self.others, Ok(RamStats::default()))
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        self.after(self.docker, Ok(vec![]))
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        self.after(self.others, Ok(StorageStats::default()))
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        self.after(self.others, Ok(NetworkStats::default()))
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        self.after(self.others, Ok(SystemStatsDynamic::default()))
+    }
+}
+
+/// Run the worker with `collector` every `sample_interval_ms` (Docker listed every
+/// `docker_interval_ms`) for `run_for`.
+async fn run_worker(
+    collector: SlowDocker,
+    sample_interval_ms: u64,
+    docker_interval_ms: u64,
+    run_for: Duration,
+) -> Arc<CollectionMetrics> {
+    let (tx, _rx) = broadcast::channel(64);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(CollectionMetrics::default());
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(collector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: None.into(),
+            tx,
+            write_tx: None,
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        WorkerConfig {
+            sample_interval_ms,
+            collect_gpu: false,
+            storage_interval_ms: sample_interval_ms,
+            docker_interval_ms,
+            system_interval_ms: sample_interval_ms,
+            ..WorkerConfig::from_app(&Default::default())
+        },
+    );
+    tokio::time::sleep(run_for).await;
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+    metrics
+}
+
+#[tokio::test]
+async fn slow_docker_listing_is_timed_and_blamed_for_slow_ticks() {
+    let metrics = run_worker(
+        SlowDocker {
+            docker: Duration::from_millis(120),
+            others: Duration::from_millis(2),
+        },
+        50,
+        50,
+        Duration::from_millis(600),
+    )
+    .await;
+
+    let stats = metrics.snapshot();
+    assert!(stats.ticks_total >= 2, "{stats:?}");
+    let docker = &stats.timings["docker"];
+    let cpu = &stats.timings["cpu"];
+    let total = &stats.timings[TOTAL_TIMING];
+    assert_eq!(docker.count, stats.ticks_total);
+    assert_eq!(cpu.count, stats.ticks_total);
+    assert_eq!(total.count, stats.ticks_total);
+    assert!(docker.p95_ms >= 120.0, "{docker:?}");
+    assert!(docker.mean_ms >= 120.0, "{docker:?}");
+    assert!(cpu.max_ms < 60.0, "{cpu:?}");
+    assert!(total.max_ms >= docker.max_ms);
+    // Every tick overran the 50 ms interval, each one because of the Docker listing.
+    assert_eq!(stats.slow_ticks_total, stats.ticks_total);
+    assert_eq!(stats.slow_ticks_by_source.len(), 1);
+    assert_eq!(stats.slow_ticks_by_source["docker"], stats.slow_ticks_total);
+    assert!(metrics.timings_summary().contains("docker="));
+}
+
+#[tokio::test]
+async fn sources_not_due_are_not_timed() {
+    let metrics = run_worker(
+        SlowDocker {
+            docker: Duration::from_millis(1),
+            others: Duration::from_millis(1),
+        },
+        20,
+        1_000_000,
+        Duration::from_millis(150),
+    )
+    .await;
+
+    let stats = metrics.snapshot();
+    assert!(stats.ticks_total >= 3, "{stats:?}");
+    // Docker is only due on the first tick.
+    assert_eq!(stats.timings["docker"].count, 1);
+    assert_eq!(stats.timings["network"].count, stats.ticks_total);
+    assert_eq!(stats.slow_ticks_total, 0);
+    assert!(stats.slow_ticks_by_source.is_empty());
+}
+
+#[test]
+fn rolling_window_stats() {
+    let mut timings = CollectionTimings::default();
+    for ms in 1..=100 {
+        timings.record("docker", Duration::from_millis(ms));
+    }
+    let docker = &timings.stats()["docker"];
+    assert_eq!(docker.count, 100);
+    assert_eq!(docker.mean_ms, 50.5);
+    assert_eq!(docker.p95_ms, 95.0);
+    assert_eq!(docker.max_ms, 100.0);
+
+    // Old samples fall out of the 600-sample window; the count keeps growing.
+    for _ in 0..600 {
+        timings.record("docker", Duration::from_millis(2));
+    }
+    let docker = &timings.stats()["docker"];
+    assert_eq!(docker.count, 700);
+    assert_eq!(docker.max_ms, 2.0);
+    assert_eq!(docker.p95_ms, 2.0);
+
+    assert_eq!(
+        slowest_source(&[
+            ("cpu", Duration::from_millis(3)),
+            ("docker", Duration::from_millis(40)),
+            ("storage", Duration::from_millis(7)),
+        ]),
+        Some(("docker", Duration::from_millis(40)))
+    );
+    assert_eq!(slowest_source(&[]), None);
+}
+
+#[tokio::test]
+async fn timings_are_served_on_api_stats() {
+    let metrics = ServiceMetrics::default();
+    metrics.collection.record_timings(
+        &[
+            ("cpu", Duration::from_millis(4)),
+            ("docker", Duration::from_millis(1500)),
+        ],
+        Duration::from_millis(1502),
+    );
+    metrics.collection.record_slow_source("docker");
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        Default::default(),
+        None,
+        metrics,
+    ));
+
+    let body: serde_json::Value = server.get("/api/stats").await.json();
+    let collection = &body["collection"];
+    assert_eq!(collection["timings"]["docker"]["count"], 1);
+    assert_eq!(collection["timings"]["docker"]["p95Ms"], 1500.0);
+    assert_eq!(collection["timings"]["total"]["maxMs"], 1502.0);
+    assert_eq!(collection["timings"]["cpu"]["meanMs"], 4.0);
+    assert_eq!(collection["slowTicksBySource"]["docker"], 1);
+}
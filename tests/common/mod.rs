@@ -1,42 +1,139 @@
-// Shared test helpers
+// Shared test helpers: an empty snapshot, the worker and aggregation configs the tests start
+// from, a collector whose readings all succeed at once, and a migrated SQLite history
+// database (a Postgres one is in `postgres`). Tests override what they exercise with struct
+// update syntax (`..common::snapshot(ts)`) or a local wrapper.
 
+// Each `tests/*.rs` file is its own crate and uses only some of these helpers, so the rest
+// would be reported as dead code there.
+#![allow(dead_code)]
+
+pub mod postgres;
+
+use futures_util::future::BoxFuture;
+use homeserver::aggregation_worker::AggregationWorkerConfig;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::AGGREGATED_RESOLUTIONS;
 use homeserver::models::*;
+use homeserver::worker::{StatsCollector, WorkerConfig};
+use std::path::Path;
+
+/// Milliseconds since the Unix epoch.
+pub fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// The default database settings with the SQLite file at `path`.
+pub fn database(path: &Path) -> DatabaseConfig {
+    DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    }
+}
 
-pub fn minimal_snapshot(timestamp: u64) -> FullSystemSnapshot {
+/// Open and migrate the SQLite database at `path` with the default settings.
+pub async fn connect(path: &Path) -> HistoryRepo {
+    connect_with(database(path)).await
+}
+
+/// Open and migrate the database `config` points at.
+pub async fn connect_with(config: DatabaseConfig) -> HistoryRepo {
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    repo
+}
+
+/// A snapshot at `timestamp` with every reading empty.
+pub fn snapshot(timestamp: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
         timestamp,
-        cpu: CpuStats {
-            model: String::new(),
-            physical_cores: 0,
-            logical_cores: 0,
-            usage_percent: 0.0,
-            temperature: 0.0,
-            core_usages: vec![],
-        },
-        ram: RamStats {
-            total: 0,
-            used: 0,
-            available: 0,
-            usage_percent: 0.0,
-            swap_total: 0,
-            swap_used: 0,
-            swap_free: 0,
-        },
+        cpu: CpuStats::default(),
+        ram: RamStats::default(),
         containers: vec![],
-        storage: StorageStats {
-            partitions: vec![],
-            disks: vec![],
-        },
-        network: NetworkStats {
-            interfaces: vec![],
-        },
-        system: SystemStatsDynamic {
-            uptime_secs: 0,
-            process_count: 0,
-            thread_count: 0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
-        },
+        storage: StorageStats::default(),
+        network: NetworkStats::default(),
+        system: SystemStatsDynamic::default(),
+        gpus: vec![],
+        smart: vec![],
+        probes: vec![],
+        sensors: vec![],
+        checks: vec![],
+        degraded: vec![],
+        self_stats: None,
+        historical: false,
+    }
+}
+
+/// Every subsystem sampled each `sample_interval_ms`; no GPU, SMART or sensors, no idle
+/// slowdown, and stats logging / pruning too rare to happen during a test.
+pub fn worker_config(sample_interval_ms: u64) -> WorkerConfig {
+    WorkerConfig {
+        sample_interval_ms,
+        stats_log_interval_secs: 3600,
+        prune_interval_secs: 3600,
+        collect_gpu: false,
+        collect_smart: false,
+        collect_sensors: false,
+        smart_poll_interval_secs: 900,
+        error_record_interval_secs: 60,
+        storage_interval_ms: sample_interval_ms,
+        docker_interval_ms: sample_interval_ms,
+        system_interval_ms: sample_interval_ms,
+        idle_sample_interval_ms: None,
+        idle_grace_secs: 30,
+        max_snapshot_bytes: 1024 * 1024,
+    }
+}
+
+/// All tiers, raw rows kept for an hour, no scheduled vacuum.
+pub fn aggregation_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        aggregation_interval_secs: 3600,
+        chunk_buckets: 50,
+        aggregation_tiers: AGGREGATED_RESOLUTIONS.to_vec(),
+        bucket_timezone: Default::default(),
+        container_limit: 100,
+        raw_retention_hours: 1,
+        minute_retention_hours: 24,
+        five_minute_retention_days: 7,
+        hourly_retention_days: 90,
+        retention_days: 30,
+        vacuum_schedule: None,
+        vacuum_interval_secs: 86400,
+        vacuum_incremental: false,
+        vacuum_min_free_percent: 20,
+        vacuum_incremental_pages: 0,
+        wal_checkpoint_interval_secs: 300,
+        wal_warn_bytes: 64 * 1024 * 1024,
+    }
+}
+
+/// Every reading succeeds immediately with defaults.
+pub struct InstantCollector;
+
+impl StatsCollector for InstantCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(async { Ok(CpuStats::default()) })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
     }
 }
@@ -0,0 +1,57 @@
+// A Postgres history store for the engine-parity tests: a fresh schema on the server at
+// HOMESERVER_TEST_POSTGRES_URL, or nothing when it is unset.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use homeserver::config::{DatabaseConfig, Secret};
+use homeserver::history_repo::PgHistoryRepo;
+use sqlx::AssertSqlSafe;
+
+pub const URL_ENV: &str = "HOMESERVER_TEST_POSTGRES_URL";
+
+/// Schemas created by this test binary, so parallel tests never share one.
+static SCHEMAS: AtomicU32 = AtomicU32::new(0);
+
+/// A schema of its own on the server at [`URL_ENV`], dropped by [`Self::drop`].
+pub struct PgSchema {
+    pool: sqlx::PgPool,
+    name: String,
+}
+
+impl PgSchema {
+    pub async fn drop(self) {
+        sqlx::query(AssertSqlSafe(format!("DROP SCHEMA {} CASCADE", self.name)))
+            .execute(&self.pool)
+            .await
+            .unwrap();
+        self.pool.close().await;
+    }
+}
+
+/// A Postgres store in a fresh schema; `None` (the test passes without checking anything) when
+/// [`URL_ENV`] is unset.
+pub async fn connect() -> Option<(PgHistoryRepo, PgSchema)> {
+    let url = std::env::var(URL_ENV).ok()?;
+    let name = format!(
+        "homeserver_test_{}_{}_{}",
+        std::process::id(),
+        super::now_ms(),
+        SCHEMAS.fetch_add(1, Ordering::Relaxed)
+    );
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::query(AssertSqlSafe(format!("CREATE SCHEMA {name}")))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let sep = if url.contains('?') { '&' } else { '?' };
+    let repo = PgHistoryRepo::connect(&DatabaseConfig {
+        engine: "postgres".into(),
+        url: Some(Secret::new(format!(
+            "{url}{sep}options=-c%20search_path%3D{name}"
+        ))),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    Some((repo, PgSchema { pool, name }))
+}
@@ -0,0 +1,161 @@
+// Command-line flags: parsing, precedence over file and environment, and the --print-config /
+// --check-config modes.
+
+use clap::Parser;
+use homeserver::config::{AppConfig, Cli, CliOverrides};
+
+const FILE_CONFIG: &str = r#"
+[server]
+port = 8081
+
+[database]
+path = "data/from-file.db"
+
+[alerts]
+webhook_url = "https://hooks.example.com/s3cr3t"
+"#;
+
+fn cli(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("homeserver").chain(args.iter().copied())).expect("parse")
+}
+
+fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn flags_parse_into_overrides() {
+    let cli = cli(&[
+        "--port",
+        "9191",
+        "--db-path",
+        "/tmp/x.db",
+        "--config",
+        "/etc/homeserver.toml",
+        "--log-level",
+        "debug",
+    ]);
+    assert_eq!(cli.overrides.port, Some(9191));
+    assert_eq!(cli.overrides.db_path.as_deref(), Some("/tmp/x.db"));
+    assert_eq!(
+        cli.overrides.config.as_deref(),
+        Some("/etc/homeserver.toml")
+    );
+    assert_eq!(cli.overrides.log_level.as_deref(), Some("debug"));
+    assert!(!cli.print_config && !cli.check_config);
+    assert!(cli.report().is_none(), "no mode flag: start the server");
+
+    assert!(Cli::try_parse_from(["homeserver", "--port", "http"]).is_err());
+    assert!(Cli::try_parse_from(["homeserver", "--print-config", "--check-config"]).is_err());
+}
+
+#[test]
+fn command_line_beats_environment_beats_file() {
+    let vars = env(&[
+        ("HOMESERVER_SERVER__PORT", "9090"),
+        ("HOMESERVER_DATABASE__PATH", "data/from-env.db"),
+    ]);
+    let overrides = CliOverrides {
+        port: Some(9191),
+        ..Default::default()
+    };
+    let config = AppConfig::load_layered(FILE_CONFIG, vars.clone(), &overrides).expect("load");
+    assert_eq!(config.server.port, 9191, "flag wins over env and file");
+    assert_eq!(
+        config.database.path, "data/from-env.db",
+        "env wins over file"
+    );
+
+    let config = AppConfig::load_layered(FILE_CONFIG, vec![], &overrides).expect("load");
+    assert_eq!(config.server.port, 9191);
+    assert_eq!(config.database.path, "data/from-file.db");
+
+    let overrides = CliOverrides {
+        db_path: Some("data/from-cli.db".into()),
+        ..Default::default()
+    };
+    let config = AppConfig::load_layered("", vars, &overrides).expect("load");
+    assert_eq!(config.server.port, 9090);
+    assert_eq!(config.database.path, "data/from-cli.db");
+
+    let overrides = CliOverrides {
+        log_level: Some("debug".into()),
+        ..Default::default()
+    };
+    let vars = env(&[("HOMESERVER_LOGGING__FILTER", "warn")]);
+    let config =
+        AppConfig::load_layered("[logging]\nfilter = \"info\"\n", vars, &overrides).expect("load");
+    assert_eq!(config.logging.filter.as_deref(), Some("debug"));
+    let err = AppConfig::load_layered(
+        "",
+        vec![],
+        &CliOverrides {
+            log_level: Some("[not a filter".into()),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("logging.filter"), "{err}");
+}
+
+#[test]
+fn invalid_flag_value_is_not_blamed_on_the_environment() {
+    let overrides = CliOverrides {
+        db_path: Some(String::new()),
+        ..Default::default()
+    };
+    let vars = env(&[("HOMESERVER_DATABASE__PATH", "data/env.db")]);
+    let err = AppConfig::load_layered(FILE_CONFIG, vars, &overrides).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("database.path"), "{message}");
+    assert!(!message.contains("HOMESERVER_DATABASE__PATH"), "{message}");
+}
+
+#[test]
+fn print_config_dumps_the_effective_config_redacted() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, FILE_CONFIG).unwrap();
+    let report = cli(&[
+        "--config",
+        path.to_str().unwrap(),
+        "--port",
+        "9292",
+        "--print-config",
+    ])
+    .report()
+    .expect("print mode");
+    assert_eq!(report.exit_code, 0);
+    assert!(report.output.contains("port: 9292"), "{}", report.output);
+    assert!(report.output.contains("data/from-file.db"));
+    assert!(!report.output.contains("s3cr3t"), "{}", report.output);
+}
+
+#[test]
+fn check_config_reports_validity_through_the_exit_code() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, FILE_CONFIG).unwrap();
+    let path = path.to_str().unwrap();
+
+    let report = cli(&["--config", path, "--check-config"])
+        .report()
+        .expect("check mode");
+    assert_eq!(report.exit_code, 0, "{}", report.output);
+
+    let report = cli(&["--config", path, "--db-path", "", "--check-config"])
+        .report()
+        .expect("check mode");
+    assert_eq!(report.exit_code, 1);
+    assert!(report.output.contains("database.path"), "{}", report.output);
+
+    let missing = dir.path().join("missing.toml");
+    let report = cli(&["--config", missing.to_str().unwrap(), "--check-config"])
+        .report()
+        .expect("check mode");
+    assert_eq!(report.exit_code, 1);
+    assert!(report.output.contains("missing.toml"), "{}", report.output);
+}
@@ -0,0 +1,295 @@
+// [database] pool/pragma tuning: defaults and validation
+
+use homeserver::config::{AppConfig, DatabaseConfig};
+
+const VALID_CONFIG: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "data/server.db"
+max_pool_size = 10
+flush_rate = 10
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 60
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+fn with_database_line(line: &str) -> String {
+    VALID_CONFIG.replace("flush_rate = 10", &format!("flush_rate = 10\n{}", line))
+}
+
+#[test]
+fn test_config_pragma_defaults_when_omitted() {
+    let config = AppConfig::load_from_str(VALID_CONFIG).expect("load_from_str");
+    assert_eq!(config.database.cache_size_kib, 8192);
+    assert_eq!(config.database.mmap_size_bytes, 0);
+    assert_eq!(config.database.temp_store, "memory");
+}
+
+#[test]
+fn test_config_pragma_values_loaded() {
+    let s = with_database_line(
+        "cache_size_kib = 2048\nmmap_size_bytes = 268435456\ntemp_store = \"file\"",
+    );
+    let config = AppConfig::load_from_str(&s).expect("load_from_str");
+    assert_eq!(config.database.cache_size_kib, 2048);
+    assert_eq!(config.database.mmap_size_bytes, 268_435_456);
+    assert_eq!(config.database.temp_store, "file");
+}
+
+#[test]
+fn test_config_validation_rejects_cache_size_zero() {
+    let err = AppConfig::load_from_str(&with_database_line("cache_size_kib = 0")).unwrap_err();
+    assert!(err.to_string().contains("database.cache_size_kib"));
+}
+
+#[test]
+fn test_config_validation_rejects_oversized_mmap() {
+    let bad = with_database_line("mmap_size_bytes = 2147483648");
+    let err = AppConfig::load_from_str(&bad).unwrap_err();
+    assert!(err.to_string().contains("database.mmap_size_bytes"));
+}
+
+#[test]
+fn test_config_validation_rejects_unknown_temp_store() {
+    let err = AppConfig::load_from_str(&with_database_line("temp_store = \"ram\"")).unwrap_err();
+    assert!(err.to_string().contains("database.temp_store"));
+}
+
+#[test]
+fn test_database_config_default_matches_shipped_values() {
+    let d = DatabaseConfig::default();
+    assert_eq!(d.max_pool_size, 10);
+    assert_eq!(d.retention_days, 3);
+    assert_eq!(d.cache_size_kib, 8192);
+    assert_eq!(d.temp_store, "memory");
+}
+
+#[test]
+fn test_config_aggregation_chunk_buckets() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.aggregation_chunk_buckets, 50);
+    let err =
+        AppConfig::load_from_str(&with_database_line("aggregation_chunk_buckets = 0")).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("database.aggregation_chunk_buckets")
+    );
+}
+
+#[test]
+fn test_config_vacuum_mode_and_threshold() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.vacuum_mode, "full");
+    assert_eq!(config.database.vacuum_min_free_percent, 20);
+    assert_eq!(config.database.vacuum_incremental_pages, 0);
+
+    let config = AppConfig::load_from_str(&with_database_line(
+        "vacuum_mode = \"incremental\"\nvacuum_incremental_pages = 512",
+    ))
+    .expect("incremental accepted");
+    assert_eq!(config.database.vacuum_mode, "incremental");
+    assert_eq!(config.database.vacuum_incremental_pages, 512);
+
+    let err =
+        AppConfig::load_from_str(&with_database_line("vacuum_mode = \"partial\"")).unwrap_err();
+    assert!(err.to_string().contains("database.vacuum_mode"));
+    let err =
+        AppConfig::load_from_str(&with_database_line("vacuum_min_free_percent = 101")).unwrap_err();
+    assert!(err.to_string().contains("database.vacuum_min_free_percent"));
+}
+
+#[test]
+fn test_config_overflow_policy() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.overflow_policy, "drop_new");
+    let config = AppConfig::load_from_str(&with_database_line("overflow_policy = \"drop_oldest\""))
+        .expect("drop_oldest accepted");
+    assert_eq!(config.database.overflow_policy, "drop_oldest");
+    let err =
+        AppConfig::load_from_str(&with_database_line("overflow_policy = \"block\"")).unwrap_err();
+    assert!(err.to_string().contains("database.overflow_policy"));
+}
+
+#[test]
+fn test_config_wal_checkpoint_interval() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.wal_checkpoint_interval_secs, 300);
+    assert_eq!(config.database.wal_warn_bytes, 64 * 1024 * 1024);
+
+    let err = AppConfig::load_from_str(&with_database_line("wal_checkpoint_interval_secs = 0"))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("database.wal_checkpoint_interval_secs")
+    );
+}
+
+#[test]
+fn test_config_backup_dir_and_retention() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.backup_dir, "data/backups");
+    assert_eq!(config.database.backup_retention_count, 7);
+    assert_eq!(config.database.disk_budget_bytes, 0);
+    assert_eq!(config.database.max_history_points, 50_000);
+
+    let err =
+        AppConfig::load_from_str(&with_database_line("backup_retention_count = 0")).unwrap_err();
+    assert!(err.to_string().contains("database.backup_retention_count"));
+    let err = AppConfig::load_from_str(&with_database_line("backup_dir = \"\"")).unwrap_err();
+    assert!(err.to_string().contains("database.backup_dir"));
+}
+
+#[test]
+fn test_config_integrity_check_and_recovery_defaults() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert!(config.database.integrity_check_on_start);
+    assert!(!config.database.recover_on_corruption);
+
+    let config = AppConfig::load_from_str(&with_database_line("recover_on_corruption = true"))
+        .expect("load_from_str");
+    assert!(config.database.recover_on_corruption);
+}
+
+#[test]
+fn test_config_error_retention_days() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.error_retention_days, 7);
+    assert_eq!(config.monitoring.error_record_interval_secs, 60);
+
+    let err =
+        AppConfig::load_from_str(&with_database_line("error_retention_days = 0")).unwrap_err();
+    assert!(err.to_string().contains("database.error_retention_days"));
+}
+
+#[test]
+fn test_config_tier_retention_order() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.aggregated_retention_days, 365);
+
+    // Raw for 6 hours, 5-minute data for 2 years.
+    let config = AppConfig::load_from_str(&with_database_line(
+        "raw_retention_hours = 6\nfive_minute_retention_days = 730\nhourly_retention_days = 730\naggregated_retention_days = 730",
+    ))
+    .expect("longer coarse tiers accepted");
+    assert_eq!(config.database.five_minute_retention_days, 730);
+
+    // (settings, expected error): each tier shorter than the one before it.
+    let cases = [
+        (
+            "raw_retention_hours = 48",
+            "database.minute_retention_hours (24 h) must be >= database.raw_retention_hours (48 h)",
+        ),
+        (
+            "minute_retention_hours = 200",
+            "database.five_minute_retention_days (168 h) must be >= database.minute_retention_hours (200 h)",
+        ),
+        (
+            "five_minute_retention_days = 100",
+            "database.hourly_retention_days (2160 h) must be >= database.five_minute_retention_days (2400 h)",
+        ),
+        (
+            "aggregated_retention_days = 30",
+            "database.aggregated_retention_days (720 h) must be >= database.hourly_retention_days (2160 h)",
+        ),
+        (
+            "aggregated_retention_days = 0",
+            "database.aggregated_retention_days must be > 0",
+        ),
+        (
+            "raw_retention_hours = 96\nminute_retention_hours = 96",
+            "database.retention_days (72 h) must be >= database.raw_retention_hours (96 h)",
+        ),
+    ];
+    for (lines, expected) in cases {
+        let err = AppConfig::load_from_str(&with_database_line(lines)).unwrap_err();
+        assert!(err.to_string().contains(expected), "{lines}: {err}");
+    }
+
+    // Ordering only matters while tiers roll up into each other.
+    AppConfig::load_from_str(&with_database_line(
+        "enable_aggregation = false\nminute_retention_hours = 1\naggregated_retention_days = 1",
+    ))
+    .expect("ordering not enforced without aggregation");
+}
+
+#[test]
+fn test_config_aggregation_tiers() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.aggregation_tiers, [60, 300, 3600, 86400]);
+    assert_eq!(
+        DatabaseConfig::default().aggregation_tiers,
+        config.database.aggregation_tiers
+    );
+
+    let config = AppConfig::load_from_str(&with_database_line("aggregation_tiers = [30, 120]"))
+        .expect("custom tiers accepted");
+    assert_eq!(config.database.aggregation_tiers, [30, 120]);
+
+    let cases = [
+        (
+            "aggregation_tiers = []",
+            "database.aggregation_tiers must not be empty",
+        ),
+        (
+            "aggregation_tiers = [0, 60]",
+            "database.aggregation_tiers entries must be > 0",
+        ),
+        (
+            "aggregation_tiers = [300, 60]",
+            "database.aggregation_tiers must be strictly ascending",
+        ),
+        (
+            "aggregation_tiers = [60, 60]",
+            "database.aggregation_tiers must be strictly ascending",
+        ),
+        (
+            "aggregation_tiers = [60, 90]",
+            "database.aggregation_tiers: 90 is not a multiple of 60",
+        ),
+    ];
+    for (line, expected) in cases {
+        let err = AppConfig::load_from_str(&with_database_line(line)).unwrap_err();
+        assert!(err.to_string().contains(expected), "{line}: {err}");
+    }
+}
+
+#[test]
+fn test_config_clock_skew_guards() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.min_snapshot_timestamp_ms, 1_735_689_600_000);
+    assert_eq!(config.database.max_future_skew_minutes, 5);
+    assert_eq!(config.database.max_prune_fraction, 0.5);
+
+    let config = AppConfig::load_from_str(&with_database_line(
+        "min_snapshot_timestamp_ms = 0\nmax_future_skew_minutes = 60\nmax_prune_fraction = 1.0",
+    ))
+    .expect("guards configurable");
+    assert_eq!(config.database.min_snapshot_timestamp_ms, 0);
+    assert_eq!(config.database.max_prune_fraction, 1.0);
+
+    for line in ["max_prune_fraction = 0.0", "max_prune_fraction = 1.5"] {
+        let err = AppConfig::load_from_str(&with_database_line(line)).unwrap_err();
+        assert!(
+            err.to_string().contains("database.max_prune_fraction"),
+            "{line}: {err}"
+        );
+    }
+}
+
+#[test]
+fn test_config_aggregation_container_limit() {
+    let config = AppConfig::load_from_str(&with_database_line("")).expect("load_from_str");
+    assert_eq!(config.database.aggregation_container_limit, 100);
+    let config =
+        AppConfig::load_from_str(&with_database_line("aggregation_container_limit = 0")).unwrap();
+    assert_eq!(config.database.aggregation_container_limit, 0);
+}
@@ -0,0 +1,72 @@
+// Built-in defaults: no config file, partial files, validation of explicit values, and secret
+// redaction in the Debug output logged at startup.
+
+use homeserver::config::{AppConfig, Secret};
+
+#[test]
+fn empty_config_uses_builtin_defaults() {
+    let config = AppConfig::load_with_env("", std::iter::empty()).expect("defaults are valid");
+    assert_eq!(config.server.port, 8081);
+    assert_eq!(config.server.host, "0.0.0.0");
+    assert_eq!(config.database.path, "data/server.db");
+    assert_eq!(config.database.max_pool_size, 10);
+    assert_eq!(config.database.flush_rate, 10);
+    assert_eq!(config.publishing.cpu_stats_frequency_ms, 1000);
+    assert_eq!(config.publishing.ram_stats_frequency_ms, 1000);
+    assert_eq!(config.publishing.broadcast_capacity, 60);
+    assert_eq!(config.monitoring.sample_interval_ms, 1000);
+    assert_eq!(config.monitoring.stats_log_interval_secs, 60);
+    assert!(config.monitoring.collect_gpu);
+    assert!(config.alerts.rules.is_empty());
+    assert!(config.alerts.webhook_url.is_none());
+
+    let from_str = AppConfig::load_from_str("").expect("empty string");
+    assert_eq!(from_str.server.port, config.server.port);
+    assert_eq!(
+        AppConfig::default().monitoring.idle_grace_secs,
+        config.monitoring.idle_grace_secs
+    );
+}
+
+#[test]
+fn partial_file_fills_in_the_rest() {
+    let config = AppConfig::load_from_str("[server]\nport = 9000\n").expect("partial");
+    assert_eq!(config.server.port, 9000);
+    assert_eq!(config.server.host, "0.0.0.0");
+    assert_eq!(config.database.path, "data/server.db");
+    assert_eq!(config.database.retention_days, 3);
+    assert_eq!(config.monitoring.sample_interval_ms, 1000);
+    assert_eq!(config.publishing.broadcast_capacity, 60);
+}
+
+#[test]
+fn explicit_bad_values_still_fail() {
+    let err = AppConfig::load_from_str("[monitoring]\nsample_interval_ms = 0\n").unwrap_err();
+    assert!(err.to_string().contains("sample_interval_ms"), "{err}");
+    assert!(AppConfig::load_from_str("[server]\nport = \"http\"\n").is_err());
+}
+
+#[test]
+fn debug_output_redacts_secrets() {
+    let config = AppConfig::load_from_str(
+        "[alerts]\nwebhook_url = \"https://hooks.example.com/services/T000/B000/s3cr3t\"\n",
+    )
+    .expect("valid");
+    let webhook = config.alerts.webhook_url.as_ref().expect("set");
+    assert_eq!(
+        webhook.expose(),
+        "https://hooks.example.com/services/T000/B000/s3cr3t"
+    );
+
+    let debug = format!("{config:?}");
+    assert!(!debug.contains("s3cr3t"), "{debug}");
+    assert!(
+        debug.contains("webhook_url: Some(\"<redacted>\")"),
+        "{debug}"
+    );
+    assert!(
+        debug.contains("port: 8081"),
+        "non-secret values are shown: {debug}"
+    );
+    assert_eq!(format!("{:?}", Secret::new("api-key")), "\"<redacted>\"");
+}
@@ -0,0 +1,160 @@
+// HOMESERVER_* environment overrides layered over the config file. Tests touching the process
+// environment hold ENV_LOCK so they run one at a time.
+
+use homeserver::config::AppConfig;
+use std::sync::Mutex;
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+const VALID_CONFIG: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "data/server.db"
+max_pool_size = 10
+flush_rate = 10
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 60
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+/// `AppConfig::load` from a file holding `VALID_CONFIG` with `vars` set for the duration.
+fn load_with(vars: &[(&str, &str)]) -> anyhow::Result<AppConfig> {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, VALID_CONFIG).unwrap();
+    unsafe { std::env::set_var("CONFIG_FILE", path.to_str().unwrap()) };
+    for (name, value) in vars {
+        unsafe { std::env::set_var(name, value) };
+    }
+    let result = AppConfig::load();
+    for (name, _) in vars {
+        unsafe { std::env::remove_var(name) };
+    }
+    unsafe { std::env::remove_var("CONFIG_FILE") };
+    result
+}
+
+#[test]
+fn env_overrides_file_values_of_every_type() {
+    let config = load_with(&[
+        ("HOMESERVER_SERVER__PORT", "9090"),
+        ("HOMESERVER_SERVER__HOST", "127.0.0.1"),
+        ("HOMESERVER_DATABASE__PATH", "/var/lib/homeserver/server.db"),
+        ("HOMESERVER_DATABASE__PERSIST_GPU", "false"),
+        ("HOMESERVER_DATABASE__AGGREGATION_TIERS", "[60, 3600]"),
+        ("HOMESERVER_MONITORING__COLLECT_SMART", "true"),
+        ("HOMESERVER_MONITORING__IDLE_SAMPLE_INTERVAL_MS", "10000"),
+    ])
+    .expect("overrides load");
+    assert_eq!(config.server.port, 9090);
+    assert_eq!(config.server.host, "127.0.0.1");
+    assert_eq!(config.database.path, "/var/lib/homeserver/server.db");
+    assert!(!config.database.persist_gpu);
+    assert_eq!(config.database.aggregation_tiers, vec![60, 3600]);
+    assert!(config.monitoring.collect_smart);
+    assert_eq!(config.monitoring.idle_sample_interval_ms, Some(10_000));
+    // Untouched values still come from the file.
+    assert_eq!(config.database.flush_rate, 10);
+}
+
+#[test]
+fn string_keys_keep_numeric_looking_values_as_strings() {
+    let config = load_with(&[("HOMESERVER_DATABASE__PATH", "2024")]).expect("load");
+    assert_eq!(config.database.path, "2024");
+    let config = load_with(&[("HOMESERVER_DATABASE__VACUUM_SCHEDULE", "0 3 * * *")]).expect("load");
+    assert_eq!(
+        config.database.vacuum_schedule.as_deref(),
+        Some("0 3 * * *")
+    );
+}
+
+#[test]
+fn string_keys_absent_from_the_file_keep_numeric_looking_values() {
+    // `node_name` is typed by the defaults; `admin_token` is unset there and retried as a string.
+    let config = load_with(&[
+        ("HOMESERVER_SERVER__NODE_NAME", "2024"),
+        ("HOMESERVER_SERVER__ADMIN_TOKEN", "1234567890123456"),
+        ("HOMESERVER_REMOTE_WRITE__INGEST_API_KEY", "true"),
+    ])
+    .expect("load");
+    assert_eq!(config.server.node_name, "2024");
+    assert_eq!(
+        config.server.admin_token.as_ref().map(|t| t.expose()),
+        Some("1234567890123456")
+    );
+    assert_eq!(
+        config
+            .remote_write
+            .ingest_api_key
+            .as_ref()
+            .map(|k| k.expose()),
+        Some("true")
+    );
+}
+
+#[test]
+fn type_errors_name_the_variable() {
+    let err = load_with(&[("HOMESERVER_SERVER__PORT", "http")]).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("HOMESERVER_SERVER__PORT"), "{message}");
+    assert!(message.contains("server.port"), "{message}");
+
+    let err = load_with(&[("HOMESERVER_DATABASE__AGGREGATION_TIERS", "60")]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("HOMESERVER_DATABASE__AGGREGATION_TIERS")
+    );
+}
+
+#[test]
+fn validation_runs_on_the_merged_config() {
+    let err = load_with(&[("HOMESERVER_DATABASE__FLUSH_RATE", "0")]).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("database.flush_rate"), "{message}");
+    assert!(
+        message.contains("HOMESERVER_DATABASE__FLUSH_RATE"),
+        "{message}"
+    );
+}
+
+#[test]
+fn load_with_env_ignores_other_variables() {
+    let vars = [
+        ("PATH".to_string(), "/usr/bin".to_string()),
+        (
+            "HOMESERVER_PUBLISHING__BROADCAST_CAPACITY".into(),
+            "5".into(),
+        ),
+    ];
+    let config = AppConfig::load_with_env(VALID_CONFIG, vars).expect("load");
+    assert_eq!(config.publishing.broadcast_capacity, 5);
+
+    let err = AppConfig::load_with_env(
+        VALID_CONFIG,
+        [("HOMESERVER_SERVER____PORT".to_string(), "1".to_string())],
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("empty key"));
+}
+
+#[test]
+fn environment_alone_can_supply_a_missing_section() {
+    let without_server = VALID_CONFIG.replace("[server]\nport = 8081\nhost = \"0.0.0.0\"\n", "");
+    let vars = [
+        ("HOMESERVER_SERVER__PORT".to_string(), "8082".to_string()),
+        ("HOMESERVER_SERVER__HOST".to_string(), "::".to_string()),
+    ];
+    let config = AppConfig::load_with_env(&without_server, vars).expect("load");
+    assert_eq!(config.server.port, 8082);
+    assert_eq!(config.server.host, "::");
+}
@@ -0,0 +1,86 @@
+// [monitoring] subsystem intervals (default to sample_interval_ms, must be positive multiples
+// of it) and idle sampling (off by default, never faster than sample_interval_ms).
+
+use homeserver::config::AppConfig;
+
+const BASE_CONFIG: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "data/server.db"
+max_pool_size = 10
+flush_rate = 10
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 60
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+fn with_monitoring_line(line: &str) -> String {
+    format!("{BASE_CONFIG}{line}\n")
+}
+
+#[test]
+fn test_subsystem_intervals_default_to_sample_interval() {
+    let config = AppConfig::load_from_str(BASE_CONFIG).unwrap();
+    assert_eq!(config.monitoring.storage_interval_ms, None);
+    assert_eq!(
+        config.monitoring.subsystem_intervals_ms(),
+        [
+            ("storage_interval_ms", 1000),
+            ("docker_interval_ms", 1000),
+            ("system_interval_ms", 1000),
+        ]
+    );
+}
+
+#[test]
+fn test_subsystem_intervals_accept_multiples() {
+    let toml = format!(
+        "{BASE_CONFIG}storage_interval_ms = 30000\ndocker_interval_ms = 5000\nsystem_interval_ms = 1000\n"
+    );
+    let config = AppConfig::load_from_str(&toml).unwrap();
+    let intervals = config.monitoring.subsystem_intervals_ms().map(|(_, ms)| ms);
+    assert_eq!(intervals, [30_000, 5_000, 1_000]);
+}
+
+#[test]
+fn test_subsystem_intervals_reject_non_multiples_and_zero() {
+    for (line, field) in [
+        ("storage_interval_ms = 1500", "storage_interval_ms"),
+        ("docker_interval_ms = 500", "docker_interval_ms"),
+        ("system_interval_ms = 0", "system_interval_ms"),
+    ] {
+        let err = AppConfig::load_from_str(&with_monitoring_line(line)).unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("monitoring.{field}")),
+            "{line}: {err}"
+        );
+    }
+}
+
+#[test]
+fn test_idle_sampling_defaults_to_off() {
+    let config = AppConfig::load_from_str(BASE_CONFIG).unwrap();
+    assert_eq!(config.monitoring.idle_sample_interval_ms, None);
+    assert_eq!(config.monitoring.idle_grace_secs, 30);
+
+    let toml = format!("{BASE_CONFIG}idle_sample_interval_ms = 10000\nidle_grace_secs = 5\n");
+    let config = AppConfig::load_from_str(&toml).unwrap();
+    assert_eq!(config.monitoring.idle_sample_interval_ms, Some(10_000));
+    assert_eq!(config.monitoring.idle_grace_secs, 5);
+}
+
+#[test]
+fn test_idle_sample_interval_must_not_be_faster() {
+    let err = AppConfig::load_from_str(&with_monitoring_line("idle_sample_interval_ms = 500"))
+        .unwrap_err();
+    assert!(err.to_string().contains("idle_sample_interval_ms"), "{err}");
+}
@@ -0,0 +1,100 @@
+// POST /api/config/reload: admin token checks, the reload report, and 422 for an invalid config.
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, CliOverrides};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::reload::ConfigReloader;
+use homeserver::routes;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn config_text(db: &Path, extra: &str) -> String {
+    format!(
+        "[server]\nadmin_token = \"hunter2\"\n\n[database]\npath = {:?}\n{extra}",
+        db.to_str().unwrap()
+    )
+}
+
+#[tokio::test]
+async fn reload_endpoint_requires_the_admin_token() {
+    let dir = TempDir::new().unwrap();
+    let db = dir.path().join("h.db");
+    let file = dir.path().join("config.toml");
+    std::fs::write(&file, config_text(&db, "")).unwrap();
+    let source = CliOverrides {
+        config: Some(file.to_str().unwrap().into()),
+        ..Default::default()
+    };
+    let repo = Arc::new(
+        HistoryRepo::connect(&AppConfig::load_with_overrides(&source).unwrap().database)
+            .await
+            .unwrap(),
+    );
+    repo.init().await.unwrap();
+    let config = AppConfig::load_from_str(&config_text(&db, "")).unwrap();
+    let app = |config: AppConfig| {
+        routes::app(
+            broadcast::channel(4).0,
+            Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+            Arc::new(SystemInfo::default()),
+            Default::default(),
+            config,
+            repo.clone(),
+            ServiceMetrics::default(),
+        )
+    };
+    let (reloader, worker_rx, _writer_rx) =
+        ConfigReloader::new(config.clone(), source, repo.clone());
+    let server = TestServer::new(app(config.clone()).layer(axum::Extension(Arc::new(reloader))));
+
+    server
+        .post("/api/config/reload")
+        .await
+        .assert_status_unauthorized();
+    server
+        .post("/api/config/reload")
+        .authorization_bearer("wrong")
+        .await
+        .assert_status_unauthorized();
+
+    std::fs::write(
+        &file,
+        config_text(&db, "\n[monitoring]\nsample_interval_ms = 500\n"),
+    )
+    .unwrap();
+    let response = server
+        .post("/api/config/reload")
+        .authorization_bearer("hunter2")
+        .await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["applied"][0], "monitoring.sample_interval_ms");
+    assert!(json["requiresRestart"].as_array().unwrap().is_empty());
+    assert_eq!(worker_rx.borrow().sample_interval_ms, 500);
+
+    std::fs::write(&file, config_text(&db, "flush_rate = 0\n")).unwrap();
+    let response = server
+        .post("/api/config/reload")
+        .authorization_bearer("hunter2")
+        .await;
+    assert_eq!(response.status_code(), 422);
+    assert!(response.text().contains("database.flush_rate"));
+    assert_eq!(worker_rx.borrow().sample_interval_ms, 500, "kept");
+
+    // Without server.admin_token the endpoint is off; without a reloader it is unavailable.
+    let mut open = config.clone();
+    open.server.admin_token = None;
+    TestServer::new(app(open))
+        .post("/api/config/reload")
+        .await
+        .assert_status_forbidden();
+    TestServer::new(app(config))
+        .post("/api/config/reload")
+        .authorization_bearer("hunter2")
+        .await
+        .assert_status_service_unavailable();
+}
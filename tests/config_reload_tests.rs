@@ -0,0 +1,244 @@
+// Config hot reload: changed-key classification, values pushed through the watch channels, a
+// running worker picking up a new sample interval, and atomic rejection of a bad config.
+
+mod common;
+
+use common::InstantCollector;
+use homeserver::config::{AppConfig, CliOverrides};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::reload::{ConfigReloader, changed_keys};
+use homeserver::worker::{OverflowPolicy, WorkerDeps, spawn_reloadable, write_queue};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+
+fn config_text(db: &Path, extra: &str) -> String {
+    format!(
+        "[server]\nadmin_token = \"hunter2\"\n\n[database]\npath = {:?}\n{extra}",
+        db.to_str().unwrap()
+    )
+}
+
+/// A config file in `dir` plus a reloader reading it back.
+async fn reloader(
+    dir: &TempDir,
+    extra: &str,
+) -> (
+    ConfigReloader,
+    tokio::sync::watch::Receiver<homeserver::worker::WorkerConfig>,
+    tokio::sync::watch::Receiver<homeserver::worker::HistoryWriterConfig>,
+    PathBuf,
+    Arc<HistoryRepo>,
+) {
+    let db = dir.path().join("h.db");
+    let file = dir.path().join("config.toml");
+    std::fs::write(&file, config_text(&db, extra)).unwrap();
+    let source = CliOverrides {
+        config: Some(file.to_str().unwrap().into()),
+        ..Default::default()
+    };
+    let config = AppConfig::load_with_overrides(&source).unwrap();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let (reloader, worker_rx, writer_rx) = ConfigReloader::new(config, source, repo.clone());
+    (reloader, worker_rx, writer_rx, file, repo)
+}
+
+#[test]
+fn changed_keys_lists_dotted_paths() {
+    let old = AppConfig::default();
+    let mut new = AppConfig::default();
+    new.server.port = 9000;
+    new.monitoring.idle_sample_interval_ms = Some(5000);
+    new.database.aggregation_tiers = vec![60, 3600];
+    assert_eq!(
+        changed_keys(&old, &new).unwrap(),
+        [
+            "database.aggregation_tiers",
+            "monitoring.idle_sample_interval_ms",
+            "server.port"
+        ]
+    );
+    assert!(changed_keys(&old, &old).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reload_pushes_tunables_and_reports_restart_only_changes() {
+    let dir = TempDir::new().unwrap();
+    let (reloader, mut worker_rx, mut writer_rx, file, _repo) = reloader(&dir, "").await;
+    let db = dir.path().join("h.db");
+
+    let report = reloader.reload().unwrap();
+    assert!(report.applied.is_empty() && report.requires_restart.is_empty());
+    assert!(
+        !worker_rx.has_changed().unwrap(),
+        "nothing changed, nothing sent"
+    );
+
+    std::fs::write(
+        &file,
+        config_text(
+            &db,
+            "flush_rate = 3\nretention_days = 5\n\n[server]\nport = 9000\n",
+        )
+        .replace("[server]\nadmin_token = \"hunter2\"\n\n", "")
+            + "\n[monitoring]\nsample_interval_ms = 500\n",
+    )
+    .unwrap();
+    let report = reloader.reload().unwrap();
+    assert_eq!(
+        report.applied,
+        [
+            "database.flush_rate",
+            "database.retention_days",
+            "monitoring.sample_interval_ms"
+        ]
+    );
+    assert_eq!(
+        report.requires_restart,
+        ["server.admin_token", "server.port"]
+    );
+    assert!(worker_rx.has_changed().unwrap());
+    assert_eq!(worker_rx.borrow_and_update().sample_interval_ms, 500);
+    assert!(writer_rx.has_changed().unwrap());
+    assert_eq!(writer_rx.borrow_and_update().flush_rate, 3);
+
+    // Restart-only keys stay pending against the startup config; applied ones are not repeated.
+    let report = reloader.reload().unwrap();
+    assert!(report.applied.is_empty());
+    assert_eq!(
+        report.requires_restart,
+        ["server.admin_token", "server.port"]
+    );
+}
+
+#[tokio::test]
+async fn invalid_config_is_rejected_and_the_old_one_kept() {
+    let dir = TempDir::new().unwrap();
+    let (reloader, mut worker_rx, writer_rx, file, _repo) = reloader(&dir, "").await;
+    let db = dir.path().join("h.db");
+
+    // Valid sample interval, invalid flush rate: neither may be applied.
+    std::fs::write(
+        &file,
+        config_text(
+            &db,
+            "flush_rate = 0\n\n[monitoring]\nsample_interval_ms = 500\n",
+        ),
+    )
+    .unwrap();
+    let err = reloader.reload().unwrap_err();
+    assert!(err.to_string().contains("database.flush_rate"), "{err}");
+    assert!(!worker_rx.has_changed().unwrap());
+    assert!(!writer_rx.has_changed().unwrap());
+
+    std::fs::write(&file, "not toml [[[").unwrap();
+    assert!(reloader.reload().is_err());
+    std::fs::remove_file(&file).unwrap();
+    assert!(reloader.reload().is_err(), "the --config file must exist");
+    assert!(!worker_rx.has_changed().unwrap());
+
+    std::fs::write(
+        &file,
+        config_text(&db, "\n[monitoring]\nsample_interval_ms = 500\n"),
+    )
+    .unwrap();
+    assert_eq!(
+        reloader.reload().unwrap().applied,
+        ["monitoring.sample_interval_ms"]
+    );
+    assert_eq!(worker_rx.borrow_and_update().sample_interval_ms, 500);
+}
+
+#[tokio::test]
+async fn log_filter_failure_rejects_the_whole_reload() {
+    let dir = TempDir::new().unwrap();
+    let (reloader, worker_rx, _writer_rx, file, _repo) = reloader(&dir, "").await;
+    let db = dir.path().join("h.db");
+    let seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let record = seen.clone();
+    let fail = Arc::new(Mutex::new(true));
+    let failing = fail.clone();
+    let reloader = reloader.with_log_filter(Box::new(move |filter: &str| {
+        anyhow::ensure!(!*failing.lock().unwrap(), "subscriber gone");
+        record.lock().unwrap().push(filter.to_string());
+        Ok(())
+    }));
+
+    let text = config_text(
+        &db,
+        "\n[monitoring]\nsample_interval_ms = 500\n\n[logging]\nfilter = \"debug\"\n",
+    );
+    std::fs::write(&file, &text).unwrap();
+    assert!(reloader.reload().is_err());
+    assert!(!worker_rx.has_changed().unwrap(), "nothing applied");
+
+    *fail.lock().unwrap() = false;
+    let report = reloader.reload().unwrap();
+    assert_eq!(
+        report.applied,
+        ["logging.filter", "monitoring.sample_interval_ms"]
+    );
+    assert_eq!(*seen.lock().unwrap(), ["debug"]);
+}
+
+fn drain(rx: &mut broadcast::Receiver<FullSystemSnapshot>) -> usize {
+    let mut n = 0;
+    while rx.try_recv().is_ok() {
+        n += 1;
+    }
+    n
+}
+
+#[tokio::test]
+async fn running_worker_follows_a_reloaded_sample_interval() {
+    let dir = TempDir::new().unwrap();
+    let (reloader, worker_rx, _writer_rx, file, repo) =
+        reloader(&dir, "\n[monitoring]\nsample_interval_ms = 10000\n").await;
+    let db = dir.path().join("h.db");
+    let (tx, mut rx) = broadcast::channel(256);
+    let (write_tx, _write_rx) = write_queue(256, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let handle = spawn_reloadable(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(homeserver::gpu_repo::GpuRepo::new()),
+            smart_repo: Arc::new(homeserver::smart_repo::SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: repo.into(),
+            tx,
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        worker_rx,
+    );
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(drain(&mut rx), 1, "only the first tick at 10 s");
+
+    std::fs::write(
+        &file,
+        config_text(&db, "\n[monitoring]\nsample_interval_ms = 20\n"),
+    )
+    .unwrap();
+    reloader.reload().unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(drain(&mut rx) >= 5, "ticks every 20 ms after the reload");
+
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+}
@@ -34,10 +34,12 @@ fn test_config_loads_from_str() {
 }
 
 #[test]
-fn test_config_validation_rejects_invalid_port() {
-    let bad = VALID_CONFIG.replace("port = 8081", "port = 0");
-    let err = AppConfig::load_from_str(&bad).unwrap_err();
-    assert!(err.to_string().contains("server.port"));
+fn test_config_accepts_port_zero() {
+    // 0: the OS picks a free port when binding.
+    let any = VALID_CONFIG.replace("port = 8081", "port = 0");
+    assert_eq!(AppConfig::load_from_str(&any).unwrap().server.port, 0);
+    let bad = VALID_CONFIG.replace("port = 8081", "port = 70000");
+    assert!(AppConfig::load_from_str(&bad).is_err());
 }
 
 #[test]
@@ -141,6 +143,8 @@ fn test_config_aggregation_defaults_when_omitted() {
     assert_eq!(config.database.aggregation_interval_secs, 3600);
     assert_eq!(config.database.raw_retention_hours, 1);
     assert_eq!(config.database.minute_retention_hours, 24);
+    assert_eq!(config.database.five_minute_retention_days, 7);
+    assert_eq!(config.database.hourly_retention_days, 90);
 }
 
 const VALID_CONFIG_WITH_AGGREGATION: &str = r#"
@@ -203,6 +207,18 @@ fn test_config_validation_rejects_minute_retention_hours_zero_when_enabled() {
     assert!(err.to_string().contains("minute_retention_hours"));
 }
 
+#[test]
+fn test_config_validation_rejects_tier_retention_zero_when_enabled() {
+    for key in ["five_minute_retention_days", "hourly_retention_days"] {
+        let bad = VALID_CONFIG_WITH_AGGREGATION.replace(
+            "minute_retention_hours = 24",
+            &format!("minute_retention_hours = 24\n{} = 0", key),
+        );
+        let err = AppConfig::load_from_str(&bad).unwrap_err();
+        assert!(err.to_string().contains(key));
+    }
+}
+
 #[test]
 fn test_config_validation_rejects_invalid_vacuum_schedule() {
     let with_cron = VALID_CONFIG_WITH_AGGREGATION.replace(
@@ -218,3 +234,33 @@ fn test_config_prune_interval_default() {
     let config = AppConfig::load_from_str(VALID_CONFIG).expect("valid");
     assert_eq!(config.database.prune_interval_secs, 3600);
 }
+
+#[test]
+fn test_config_container_alert_rules() {
+    let config = AppConfig::load_from_str(
+        r#"
+[[alerts.container_rules]]
+name = "db crash loop"
+event = "restarts"
+container = "postgres*"
+labels = { "com.docker.compose.project" = "db" }
+restart_count = 5
+restart_window_secs = 120
+severity = "warning"
+"#,
+    )
+    .unwrap();
+    let rule = &config.alerts.container_rules[0];
+    assert_eq!(rule.event, homeserver::config::ContainerEventKind::Restarts);
+    assert_eq!((rule.restart_count, rule.restart_window_secs), (5, 120));
+    assert_eq!(rule.cooldown_secs, 300);
+    assert_eq!(rule.labels["com.docker.compose.project"], "db");
+    assert!(
+        AppConfig::load_from_str("[[alerts.container_rules]]\nname = \"x\"\nevent = \"exit\"\n")
+            .is_err()
+    );
+    assert!(
+        AppConfig::load_from_str("[[alerts.container_rules]]\nname = \"\"\nevent = \"oom\"\n")
+            .is_err()
+    );
+}
@@ -0,0 +1,275 @@
+// Container alert rules: Docker event parsing, name/label matching, die/oom/unhealthy/restart
+// rules, per-container cooldown during crash loops, and the event-stream task.
+
+use bollard::models::{EventActor, EventMessage, EventMessageTypeEnum};
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use homeserver::alerting::{
+    AlertStatus, ContainerAlertEngine, ContainerEventSource, Notifier, chat_text, name_matches,
+    spawn_container_alerts,
+};
+use homeserver::config::{ContainerEventKind, ContainerRule, Severity};
+use homeserver::docker_repo::parse_event;
+use homeserver::models::{ContainerAction, ContainerEvent};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+fn message(action: &str, attributes: &[(&str, &str)]) -> EventMessage {
+    EventMessage {
+        typ: Some(EventMessageTypeEnum::CONTAINER),
+        action: Some(action.into()),
+        actor: Some(EventActor {
+            id: Some("abc123".into()),
+            attributes: Some(
+                attributes
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+        }),
+        time: Some(1_700_000_000),
+        time_nano: Some(1_700_000_000_123_456_789),
+        ..Default::default()
+    }
+}
+
+fn event(action: ContainerAction, name: &str, secs: u64, exit_code: Option<i64>) -> ContainerEvent {
+    ContainerEvent {
+        time_ms: secs * 1000,
+        action,
+        id: format!("{name}-id"),
+        name: name.into(),
+        image: "postgres:16".into(),
+        exit_code,
+        attributes: HashMap::from([("com.docker.compose.project".into(), "db".into())]),
+    }
+}
+
+fn rule(name: &str, event: ContainerEventKind) -> ContainerRule {
+    ContainerRule {
+        name: name.into(),
+        event,
+        container: None,
+        labels: Default::default(),
+        restart_count: 3,
+        restart_window_secs: 600,
+        cooldown_secs: 300,
+        severity: Severity::Critical,
+    }
+}
+
+#[test]
+fn docker_messages_become_container_events() {
+    let ev = parse_event(&message(
+        "die",
+        &[
+            ("name", "postgres"),
+            ("image", "postgres:16"),
+            ("exitCode", "137"),
+        ],
+    ))
+    .unwrap();
+    assert_eq!(ev.action, ContainerAction::Die);
+    assert_eq!(
+        (ev.name.as_str(), ev.image.as_str()),
+        ("postgres", "postgres:16")
+    );
+    assert_eq!((ev.id.as_str(), ev.exit_code), ("abc123", Some(137)));
+    assert_eq!(ev.time_ms, 1_700_000_000_123);
+
+    let unhealthy = parse_event(&message("health_status: unhealthy", &[])).unwrap();
+    assert_eq!(unhealthy.action, ContainerAction::Unhealthy);
+    assert_eq!(unhealthy.name, "abc123", "falls back to the id");
+    assert!(parse_event(&message("exec_start: sh", &[])).is_none());
+    let image = EventMessage {
+        typ: Some(EventMessageTypeEnum::IMAGE),
+        ..message("die", &[])
+    };
+    assert!(parse_event(&image).is_none());
+}
+
+#[test]
+fn container_names_match_glob_patterns() {
+    assert!(name_matches("postgres", "postgres"));
+    assert!(!name_matches("postgres", "postgres-1"));
+    assert!(name_matches("postgres*", "postgres-1"));
+    assert!(name_matches("*-db", "app-db"));
+    assert!(name_matches("app-*-worker*", "app-mail-worker-2"));
+    assert!(!name_matches("app-*-worker", "app-worker"));
+    assert!(name_matches("*", "anything"));
+}
+
+#[test]
+fn die_fires_only_for_nonzero_exits_and_oom_and_unhealthy_fire_once() {
+    let mut engine = ContainerAlertEngine::new(vec![
+        rule("crash", ContainerEventKind::Die),
+        rule("oom", ContainerEventKind::Oom),
+        rule("sick", ContainerEventKind::Unhealthy),
+    ]);
+    assert!(
+        engine
+            .observe(&event(ContainerAction::Die, "web", 0, Some(0)))
+            .is_empty()
+    );
+    let fired = engine.observe(&event(ContainerAction::Die, "db", 1, Some(137)));
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].metric, "container_die");
+    let container = fired[0].container.as_ref().unwrap();
+    assert_eq!(
+        (container.name.as_str(), container.exit_code),
+        ("db", Some(137))
+    );
+    assert_eq!(
+        chat_text(&fired[0]),
+        "[FIRING] crash (critical): container db died with exit code 137"
+    );
+
+    let oom = engine.observe(&event(ContainerAction::Oom, "db", 2, None));
+    assert_eq!(
+        chat_text(&oom[0]),
+        "[FIRING] oom (critical): container db was OOM-killed"
+    );
+    let sick = engine.observe(&event(ContainerAction::Unhealthy, "db", 3, None));
+    assert_eq!(sick[0].rule_name, "sick");
+    assert!(
+        engine
+            .observe(&event(ContainerAction::Healthy, "db", 4, None))
+            .is_empty()
+    );
+}
+
+#[test]
+fn rules_match_on_name_and_labels() {
+    let mut by_name = rule("db", ContainerEventKind::Oom);
+    by_name.container = Some("post*".into());
+    let mut by_label = rule("project", ContainerEventKind::Oom);
+    by_label.labels = [("com.docker.compose.project".into(), "web".into())].into();
+    let mut engine = ContainerAlertEngine::new(vec![by_name, by_label]);
+
+    let fired = engine.observe(&event(ContainerAction::Oom, "postgres", 0, None));
+    assert_eq!(fired.len(), 1, "label project=db does not match web");
+    assert_eq!(fired[0].rule_name, "db");
+    assert!(
+        engine
+            .observe(&event(ContainerAction::Oom, "redis", 0, None))
+            .is_empty()
+    );
+}
+
+#[test]
+fn crash_loop_alerts_once_per_cooldown() {
+    let mut engine = ContainerAlertEngine::new(vec![
+        rule("crash", ContainerEventKind::Die),
+        rule("loop", ContainerEventKind::Restarts),
+    ]);
+    // Crashes every 10 s for 10 minutes: die (exit 1) then start.
+    let mut fired = Vec::new();
+    for i in 0..60 {
+        fired.extend(engine.observe(&event(ContainerAction::Die, "db", i * 10, Some(1))));
+        fired.extend(engine.observe(&event(ContainerAction::Start, "db", i * 10 + 1, None)));
+    }
+    let at = |rule: &str| -> Vec<u64> {
+        fired
+            .iter()
+            .filter(|e| e.rule_name == rule)
+            .map(|e| e.timestamp / 1000)
+            .collect()
+    };
+    assert_eq!(
+        at("crash"),
+        [0, 300],
+        "first crash, then once the cooldown passed"
+    );
+    // The 4th restart (> 3 in the window) fires; the next one after the cooldown fires again.
+    assert_eq!(at("loop"), [31, 331]);
+    let looping = fired.iter().find(|e| e.rule_name == "loop").unwrap();
+    assert_eq!((looping.value, looping.threshold), (4.0, 3.0));
+    assert_eq!(
+        chat_text(looping),
+        "[FIRING] loop (critical): container db restarted 4 times (> 3)"
+    );
+
+    // Another container is deduplicated separately.
+    let other = engine.observe(&event(ContainerAction::Die, "web", 600, Some(2)));
+    assert_eq!(other.len(), 1);
+}
+
+#[test]
+fn restarts_outside_the_window_do_not_count() {
+    let mut slow = rule("loop", ContainerEventKind::Restarts);
+    slow.restart_count = 1;
+    slow.restart_window_secs = 60;
+    slow.cooldown_secs = 0;
+    let mut engine = ContainerAlertEngine::new(vec![slow]);
+    for secs in [0, 100, 200] {
+        assert!(
+            engine
+                .observe(&event(ContainerAction::Restart, "db", secs, None))
+                .is_empty()
+        );
+    }
+    let fired = engine.observe(&event(ContainerAction::Restart, "db", 230, None));
+    assert_eq!(fired[0].value, 2.0);
+}
+
+/// Hands out the receiver once; later subscriptions see an empty stream.
+struct ChannelSource(Mutex<Option<mpsc::UnboundedReceiver<anyhow::Result<ContainerEvent>>>>);
+
+impl ContainerEventSource for ChannelSource {
+    fn events(&self) -> BoxStream<'static, anyhow::Result<ContainerEvent>> {
+        match self.0.lock().unwrap().take() {
+            Some(rx) => futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|ev| (ev, rx))
+            })
+            .boxed(),
+            None => futures_util::stream::pending().boxed(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn task_records_container_alerts_in_the_history() {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let status = Arc::new(AlertStatus::default());
+    let shutdown = CancellationToken::new();
+    let handle = spawn_container_alerts(
+        ContainerAlertEngine::new(vec![rule("crash", ContainerEventKind::Die)]),
+        Notifier::new(vec![], 0, Duration::ZERO),
+        Arc::new(ChannelSource(Mutex::new(Some(rx)))),
+        status.clone(),
+        shutdown.clone(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    tx.send(Ok(event(ContainerAction::Die, "db", 1, Some(137))))
+        .unwrap();
+    tx.send(Ok(event(ContainerAction::Die, "db", 2, Some(137))))
+        .unwrap();
+    tx.send(Ok(event(ContainerAction::Die, "web", 3, Some(1))))
+        .unwrap();
+    for _ in 0..200 {
+        if status.recent().len() >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let names: Vec<_> = status
+        .recent()
+        .iter()
+        .map(|e| e.container.as_ref().unwrap().name.clone())
+        .collect();
+    assert_eq!(
+        names,
+        ["web", "db"],
+        "newest first; the repeat is in cooldown"
+    );
+    let json = serde_json::to_value(&status.recent()[1]).unwrap();
+    assert_eq!(json["container"]["exitCode"], 137);
+    assert_eq!(json["container"]["image"], "postgres:16");
+
+    shutdown.cancel();
+    handle.await.unwrap();
+}
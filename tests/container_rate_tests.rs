@@ -0,0 +1,120 @@
+// Per-container byte rates: derived from two consecutive samples (counter resets clamp to 0),
+// kept in container_data blobs (version 5 / 6) and averaged by aggregation.
+
+use homeserver::docker_repo::apply_rates;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::blob::{
+    BLOB_VERSION, BLOB_VERSION_CONTAINERS, BLOB_VERSION_CONTAINERS_COMPRESSED, decode_containers,
+    encode_blob, encode_containers,
+};
+use homeserver::models::*;
+
+fn sample(observed_at: u64, rx: u64, tx: u64, read: u64, write: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": 1.0,
+        "memoryUsageBytes": 100,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+        "networkRxBytes": rx,
+        "networkTxBytes": tx,
+        "blockReadBytes": read,
+        "blockWriteBytes": write,
+        "observedAt": observed_at,
+    }))
+    .unwrap()
+}
+
+fn rates(c: &ContainerStats) -> [f64; 4] {
+    [
+        c.network_rx_bytes_per_sec,
+        c.network_tx_bytes_per_sec,
+        c.block_read_bytes_per_sec,
+        c.block_write_bytes_per_sec,
+    ]
+}
+
+#[test]
+fn rates_come_from_the_previous_sample() {
+    let mut first = sample(10_000, 1000, 500, 0, 4096);
+    apply_rates(&mut first, None);
+    assert_eq!(rates(&first), [0.0; 4]);
+
+    // Two seconds later.
+    let mut second = sample(12_000, 5000, 1500, 2048, 4096);
+    apply_rates(&mut second, Some(&first));
+    assert_eq!(rates(&second), [2000.0, 500.0, 1024.0, 0.0]);
+
+    // Same observed_at: the previous rates carry over.
+    let mut again = sample(12_000, 9000, 1500, 2048, 4096);
+    apply_rates(&mut again, Some(&second));
+    assert_eq!(rates(&again), rates(&second));
+}
+
+#[test]
+fn counter_resets_clamp_to_zero() {
+    let before = sample(10_000, 1_000_000, 800_000, 50_000, 60_000);
+    // The container restarted: rx and block read start over, tx and write keep growing.
+    let mut after = sample(11_000, 100, 801_000, 10, 61_000);
+    apply_rates(&mut after, Some(&before));
+    assert_eq!(rates(&after), [0.0, 1000.0, 0.0, 1000.0]);
+}
+
+#[test]
+fn rates_survive_container_blobs_and_legacy_blobs_read_as_zero() {
+    let mut containers = vec![sample(1, 0, 0, 0, 0), sample(2, 0, 0, 0, 0)];
+    containers[0].network_rx_bytes_per_sec = 1234.5;
+    containers[1].block_write_bytes_per_sec = 42.0;
+    for (compress, version) in [
+        (false, BLOB_VERSION_CONTAINERS),
+        (true, BLOB_VERSION_CONTAINERS_COMPRESSED),
+    ] {
+        let bytes = encode_containers(&containers, compress).unwrap();
+        assert_eq!(bytes[0], version);
+        let decoded = decode_containers(&bytes, "container_data").unwrap();
+        assert_eq!(rates(&decoded[0]), [1234.5, 0.0, 0.0, 0.0]);
+        assert_eq!(rates(&decoded[1]), [0.0, 0.0, 0.0, 42.0]);
+        assert_eq!(decoded[1].name, "web");
+    }
+
+    let old = encode_blob(&containers, BLOB_VERSION, false).unwrap();
+    let decoded = decode_containers(&old, "container_data").unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(rates(&decoded[0]), [0.0; 4]);
+
+    // An unprefixed list of five containers starts with the version-5 byte.
+    let legacy = wincode::serialize(&vec![sample(1, 7, 0, 0, 0); 5]).unwrap();
+    assert_eq!(legacy[0], BLOB_VERSION_CONTAINERS);
+    let decoded = decode_containers(&legacy, "container_data").unwrap();
+    assert_eq!(decoded.len(), 5);
+    assert_eq!(decoded[4].network_rx_bytes, 7);
+}
+
+#[test]
+fn aggregation_averages_rates() {
+    let snapshots: Vec<FullSystemSnapshot> = [100.0, 300.0, 200.0, 0.0]
+        .into_iter()
+        .enumerate()
+        .map(|(i, rate)| {
+            let mut c = sample(60_000 + i as u64 * 1000, 0, 0, 0, 0);
+            c.network_rx_bytes_per_sec = rate;
+            c.block_read_bytes_per_sec = rate * 2.0;
+            serde_json::from_value(serde_json::json!({
+                "timestamp": 60_000 + i as u64 * 1000,
+                "cpu": CpuStats::default(),
+                "ram": RamStats::default(),
+                "containers": [c],
+                "storage": StorageStats::default(),
+                "network": NetworkStats::default(),
+                "system": SystemStatsDynamic::default(),
+            }))
+            .unwrap()
+        })
+        .collect();
+    let out = aggregate_snapshots(&snapshots, 60_000, 60).unwrap();
+    let c = &out.containers[0];
+    assert_eq!(c.network_rx_bytes_per_sec, 150.0);
+    assert_eq!(c.block_read_bytes_per_sec, 300.0);
+    assert_eq!(c.network_tx_bytes_per_sec, 0.0);
+}
@@ -0,0 +1,115 @@
+// Container CPU is collected and stored per core; `docker.cpu_percent_mode = "host_total"` only
+// rescales it where it leaves the process (history routes, MQTT). `cpu_percent_of_host` is
+// derived again when history is decoded. Collection-side scaling is in `docker_stats_tests.rs`.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, CpuPercentMode, MqttConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::blob::{decode_containers, encode_containers};
+use homeserver::models::*;
+use homeserver::mqtt::Publisher;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+/// Two of four cores busy, a quarter of it in the kernel.
+fn container() -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": 200.0,
+        "cpuKernelPercent": 50.0,
+        "cpuUserPercent": 150.0,
+        "cpuPercentOfHost": 50.0,
+        "onlineCpus": 4,
+        "memoryUsageBytes": 100,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+fn snapshot(timestamp: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers: vec![container()],
+        ..common::snapshot(timestamp)
+    }
+}
+
+#[test]
+fn history_decoding_derives_the_host_share() {
+    let bytes = encode_containers(&[container()], true).unwrap();
+    let decoded = decode_containers(&bytes, "container_data").unwrap();
+    assert_eq!(decoded[0].cpu_percent, 200.0, "stored per core");
+    assert_eq!(decoded[0].cpu_percent_of_host, 50.0);
+
+    let out = aggregate_snapshots(&[snapshot(60_000), snapshot(61_000)], 60_000, 60).unwrap();
+    assert_eq!(out.containers[0].cpu_percent, 200.0);
+    assert_eq!(out.containers[0].cpu_percent_of_host, 50.0);
+}
+
+async fn server(mode: CpuPercentMode) -> (TestServer, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("history.db").display().to_string();
+    config.docker.cpu_percent_mode = mode;
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    repo.save_snapshots(&[snapshot(1_000), snapshot(2_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    let app = routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        Arc::new(repo),
+        Default::default(),
+    );
+    (TestServer::new(app), dir)
+}
+
+#[tokio::test]
+async fn history_routes_convert_on_the_way_out() {
+    for (mode, cpu, kernel) in [
+        (CpuPercentMode::PerCore, 200.0, 50.0),
+        (CpuPercentMode::HostTotal, 50.0, 12.5),
+    ] {
+        let (server, _dir) = server(mode).await;
+        let since: serde_json::Value = server.get("/api/history/since?ts=0").await.json();
+        let sync: serde_json::Value = server.get("/api/history/sync").await.json();
+        let history: serde_json::Value = server
+            .get("/api/history?from=0&to=3000&resolution=raw")
+            .await
+            .json();
+        for c in [
+            &since[1]["containers"][0],
+            &sync["snapshots"][0]["containers"][0],
+            &history[0]["containers"][0],
+        ] {
+            assert_eq!(c["cpuPercent"], cpu, "{mode:?}");
+            assert_eq!(c["cpuKernelPercent"], kernel, "{mode:?}");
+            assert_eq!(c["cpuPercentOfHost"], 50.0, "{mode:?}");
+        }
+    }
+}
+
+#[test]
+fn mqtt_publishes_container_cpu_in_the_mode() {
+    let cpu = |mode| {
+        let mut publisher = Publisher::new(&MqttConfig::default()).with_cpu_percent_mode(mode);
+        publisher
+            .messages(&snapshot(1_000), 1)
+            .into_iter()
+            .find(|m| m.topic == "homeserver/container/web/cpu")
+            .unwrap()
+            .payload
+    };
+    assert_eq!(cpu(CpuPercentMode::PerCore), "200.0");
+    assert_eq!(cpu(CpuPercentMode::HostTotal), "50.0");
+}
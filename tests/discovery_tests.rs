@@ -0,0 +1,81 @@
+// mDNS advertisement: TXT records, the advertised instance / host names, and a start/stop smoke
+// test that must not panic whether or not the sandbox has a multicast-capable network.
+
+use std::time::Duration;
+
+use homeserver::config::{AppConfig, Secret};
+use homeserver::discovery::{MdnsService, spawn, spawn_configured, txt_records};
+use tokio_util::sync::CancellationToken;
+
+fn txt(s: &MdnsService, key: &str) -> Option<String> {
+    s.txt
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v.clone())
+}
+
+#[test]
+fn txt_records_carry_version_tls_and_auth() {
+    assert_eq!(
+        txt_records("1.2.3", true, false),
+        [
+            ("version", "1.2.3".to_string()),
+            ("tls", "true".to_string()),
+            ("auth", "false".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn service_from_config_uses_hostname_and_bound_port() {
+    let mut config = AppConfig::default();
+    config.server.ws_token = Some(Secret::new("t"));
+    let s = MdnsService::new(&config, "media-box", 40123);
+    assert_eq!(
+        (s.instance.as_str(), s.host.as_str()),
+        ("media-box", "media-box")
+    );
+    assert_eq!(s.port, 40123);
+    assert_eq!(txt(&s, "auth").as_deref(), Some("true"));
+    assert_eq!(txt(&s, "tls").as_deref(), Some("false"));
+
+    let s = MdnsService::new(&config, &"é".repeat(40), 1);
+    assert!(s.instance.len() <= 63);
+    assert_eq!(MdnsService::new(&config, "", 1).instance, "homeserver");
+}
+
+#[test]
+fn fqdn_hostnames_advertise_only_their_first_label() {
+    let config = AppConfig::default();
+    // `nas.lan.local.` would be a name no resolver asks this host for.
+    let s = MdnsService::new(&config, "nas.lan", 8081);
+    assert_eq!((s.instance.as_str(), s.host.as_str()), ("nas", "nas"));
+    let s = MdnsService::new(&config, " rack_2.home.arpa ", 8081);
+    assert_eq!((s.instance.as_str(), s.host.as_str()), ("rack_2", "rack-2"));
+    assert_eq!(MdnsService::new(&config, ".lan", 1).instance, "homeserver");
+}
+
+#[tokio::test]
+async fn advertising_starts_and_stops_without_panicking() {
+    let shutdown = CancellationToken::new();
+    let service = MdnsService::new(&AppConfig::default(), "nas", 8081);
+    let handle = spawn(service, shutdown.clone());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("advertisement stops on shutdown")
+        .expect("advertisement does not panic");
+}
+
+#[test]
+fn discovery_is_off_by_default_and_needs_tcp() {
+    let shutdown = CancellationToken::new();
+    let config = AppConfig::default();
+    assert!(!config.discovery.mdns);
+    assert!(spawn_configured(&config, Some(8081), shutdown.clone()).is_none());
+
+    let config = AppConfig::load_from_str("[discovery]\nmdns = true\n").unwrap();
+    assert!(config.discovery.mdns);
+    assert!(spawn_configured(&config, None, shutdown).is_none());
+}
@@ -0,0 +1,298 @@
+// Disk-full guard: with a fake free-space provider the history writer drops batches below
+// `database.min_free_bytes` (counted, with an emergency prune of old raw rows), writes again once
+// space recovers, and /health and /api/stats report the condition.
+
+mod common;
+
+use common::snapshot;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, DatabaseConfig};
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::{FreeSpace, HistoryRepo, Statvfs};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::worker::{
+    FlushMetrics, HistoryWriterConfig, OverflowPolicy, WriteSender, spawn_history_writer,
+    write_queue,
+};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const MIN_FREE: u64 = 1_000_000;
+const DAY_MS: u64 = 86_400_000;
+
+/// Reports whatever the test stores.
+#[derive(Default)]
+struct FakeFreeSpace(AtomicU64);
+
+impl FreeSpace for FakeFreeSpace {
+    fn available_bytes(&self, _path: &str) -> std::io::Result<u64> {
+        Ok(self.0.load(Ordering::Relaxed))
+    }
+}
+
+async fn raw_timestamps(repo: &HistoryRepo) -> Vec<u64> {
+    let mut timestamps: Vec<u64> = repo
+        .get_raw_snapshots_by_time_range(i64::MIN, i64::MAX)
+        .await
+        .unwrap()
+        .iter()
+        .map(|s| s.timestamp)
+        .collect();
+    timestamps.sort_unstable();
+    timestamps
+}
+
+/// Wait until the writer has handled `batches` batches of one snapshot (written or dropped).
+async fn wait_for_batches(metrics: &FlushMetrics, batches: u64) {
+    let handled = || {
+        let s = metrics.snapshot();
+        s.flushes_total + s.snapshots_dropped_disk_full
+    };
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while handled() < batches {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("writer handled the batch");
+}
+
+/// A history writer flushing every snapshot, with `min_free_bytes = MIN_FREE`.
+fn spawn_writer(
+    repo: Arc<HistoryRepo>,
+) -> (WriteSender, tokio::task::JoinHandle<()>, Arc<FlushMetrics>) {
+    let metrics = Arc::new(FlushMetrics::default());
+    let (tx, rx) = write_queue(8, OverflowPolicy::DropNew, Default::default());
+    let writer = spawn_history_writer(
+        rx,
+        repo,
+        Arc::new(SystemInfo::default()),
+        HistoryWriterConfig {
+            flush_rate: 1,
+            flush_interval_secs: 3600,
+            flush_warn_ms: 1000,
+            persist_gpu: true,
+            persist_smart: true,
+            min_snapshot_timestamp_ms: 0,
+            max_future_skew_minutes: 5,
+            min_free_bytes: MIN_FREE,
+        },
+        Arc::new(AtomicU64::new(0)),
+        metrics.clone(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    (tx, writer, metrics)
+}
+
+#[tokio::test]
+async fn writer_drops_batches_below_min_free_and_recovers() {
+    let dir = TempDir::new().unwrap();
+    let free = Arc::new(FakeFreeSpace(AtomicU64::new(10 * MIN_FREE)));
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        retention_days: 4,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+    .unwrap()
+    .with_free_space(free.clone());
+    repo.init().await.unwrap();
+    let repo = Arc::new(repo);
+    let now = common::now_ms() as u64;
+    // Three days old: inside the 4-day retention, outside half of it. The local row has not been
+    // rolled up yet; the one pushed by another node never will be.
+    let old = now - 3 * DAY_MS;
+    repo.save_snapshots(&[snapshot(old)], &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.save_node_snapshots("pi", &[snapshot(old)])
+        .await
+        .unwrap();
+
+    let (tx, writer, metrics) = spawn_writer(repo.clone());
+    tx.send(snapshot(now));
+    wait_for_batches(&metrics, 1).await;
+    let s = metrics.snapshot();
+    assert!(!s.disk_full);
+    assert_eq!(s.free_bytes, Some(10 * MIN_FREE));
+    assert_eq!(raw_timestamps(&repo).await, [old, now]);
+
+    // Well below the minimum: both batches are dropped; the first also prunes raw rows older
+    // than half the retention that are safe to lose.
+    free.0.store(MIN_FREE / 2, Ordering::Relaxed);
+    tx.send(snapshot(now + 1));
+    tx.send(snapshot(now + 2));
+    wait_for_batches(&metrics, 3).await;
+    let s = metrics.snapshot();
+    assert!(s.disk_full);
+    assert_eq!(s.free_bytes, Some(MIN_FREE / 2));
+    assert_eq!(s.snapshots_dropped_disk_full, 2);
+    assert_eq!(
+        s.emergency_prunes_total, 1,
+        "at most one prune per interval"
+    );
+    assert_eq!(s.flushes_total, 1);
+    assert_eq!(raw_timestamps(&repo).await, [old, now], "not rolled up yet");
+    assert_eq!(
+        repo.db_stats().await.unwrap().raw_rows,
+        2,
+        "node row pruned"
+    );
+
+    // Space is back: writing resumes.
+    free.0.store(MIN_FREE, Ordering::Relaxed);
+    tx.send(snapshot(now + 3));
+    wait_for_batches(&metrics, 4).await;
+    let s = metrics.snapshot();
+    assert!(!s.disk_full);
+    assert_eq!(s.flushes_total, 2);
+    assert_eq!(raw_timestamps(&repo).await, [old, now, now + 3]);
+
+    drop(tx);
+    writer.await.unwrap();
+}
+
+/// A disk of `.0` bytes holding only the database: whatever the prune releases is free again.
+struct FakeDisk(AtomicU64);
+
+impl FreeSpace for FakeDisk {
+    fn available_bytes(&self, path: &str) -> std::io::Result<u64> {
+        let size = |path: String| std::fs::metadata(path).map_or(0, |m| m.len());
+        let used = size(path.to_owned()) + size(format!("{path}-wal"));
+        Ok(self.0.load(Ordering::Relaxed).saturating_sub(used))
+    }
+}
+
+/// Fill a database with 50 daily rows, leave the disk just short of the minimum and check that
+/// one emergency prune makes room for the next batch: by shrinking the file (`incremental`) or
+/// by leaving free pages inside it (`full`).
+async fn prune_reclaims_space_and_writes_resume(vacuum_mode: &str) {
+    let dir = TempDir::new().unwrap();
+    let disk = Arc::new(FakeDisk(AtomicU64::new(u64::MAX)));
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        aggregated_retention_days: 60,
+        vacuum_mode: vacuum_mode.into(),
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+    .unwrap()
+    .with_free_space(disk.clone());
+    repo.init().await.unwrap();
+    let repo = Arc::new(repo);
+    // A row in the middle of each of the last 50 days, each with its own containers.
+    let now = common::now_ms() as u64;
+    for day in 1..=50 {
+        let ts = now - day * DAY_MS + DAY_MS / 2;
+        let containers = (0..50)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "id": format!("{day:032x}{i:032x}"), "name": format!("service-{day}-{i}"),
+                    "cpuPercent": 1.5, "memoryUsageBytes": 1, "memoryLimitBytes": 2,
+                    "state": "running",
+                }))
+                .unwrap()
+            })
+            .collect();
+        let snap = FullSystemSnapshot {
+            containers,
+            ..snapshot(ts)
+        };
+        let agg = aggregate_snapshots(&[snap], ts as i64, 86400).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+    repo.wal_checkpoint().await.unwrap();
+    let filled = repo.file_size().unwrap();
+    disk.0.store(filled + MIN_FREE - 1, Ordering::Relaxed);
+
+    let (tx, writer, metrics) = spawn_writer(repo.clone());
+    tx.send(snapshot(now));
+    wait_for_batches(&metrics, 1).await;
+    let s = metrics.snapshot();
+    assert!(s.disk_full);
+    assert_eq!(s.emergency_prunes_total, 1);
+    // Half the 60-day cap is kept.
+    assert_eq!(repo.db_stats().await.unwrap().aggregated_rows, 30);
+    if vacuum_mode == "incremental" {
+        assert!(repo.file_size().unwrap() < filled, "file shrank");
+    } else {
+        assert!(repo.reusable_bytes().await.unwrap() > 0, "free pages");
+    }
+
+    tx.send(snapshot(now + 1));
+    wait_for_batches(&metrics, 2).await;
+    let s = metrics.snapshot();
+    assert!(!s.disk_full, "the reclaimed space admits writes again");
+    assert_eq!(s.flushes_total, 1);
+    assert_eq!(raw_timestamps(&repo).await, [now + 1]);
+    drop(tx);
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn emergency_prune_shrinks_an_incremental_file_and_writes_resume() {
+    prune_reclaims_space_and_writes_resume("incremental").await;
+}
+
+#[tokio::test]
+async fn emergency_prune_frees_pages_for_writes_in_full_mode() {
+    prune_reclaims_space_and_writes_resume("full").await;
+}
+
+#[tokio::test]
+async fn disk_full_shows_on_health_and_stats() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    config.server.node_name = "nas".into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let metrics = ServiceMetrics::default();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        metrics.clone(),
+    ));
+    server.get("/health").await.assert_text("ok");
+
+    metrics.history_flush.record_dropped_disk_full(3);
+    metrics.history_flush.record_free_space(Some(1024), true);
+    let health = server.get("/health").expect_failure().await;
+    health.assert_status_service_unavailable();
+    health.assert_text("disk full");
+    let stats: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(stats["historyFlush"]["diskFull"], true);
+    assert_eq!(stats["historyFlush"]["freeBytes"], 1024);
+    assert_eq!(stats["historyFlush"]["snapshotsDroppedDiskFull"], 3);
+    let text = server.get("/metrics").await.text();
+    assert!(text.contains("homeserver_history_disk_full{node=\"nas\"} 1"));
+    assert!(text.contains("homeserver_snapshots_dropped_disk_full_total{node=\"nas\"} 3"));
+
+    metrics
+        .history_flush
+        .record_free_space(Some(1 << 30), false);
+    server.get("/health").await.assert_text("ok");
+}
+
+#[test]
+fn min_free_bytes_defaults_to_200_mib_and_statvfs_measures() {
+    assert_eq!(
+        AppConfig::default().database.min_free_bytes,
+        200 * 1024 * 1024
+    );
+    let config = AppConfig::load_from_str("[database]\nmin_free_bytes = 0\n").unwrap();
+    assert_eq!(config.database.min_free_bytes, 0);
+    let dir = TempDir::new().unwrap();
+    let free = Statvfs.available_bytes(dir.path().to_str().unwrap());
+    if cfg!(unix) {
+        assert!(free.unwrap() > 0);
+    }
+}
@@ -1,6 +1,11 @@
-// Optional DockerRepo tests when Docker daemon is available
+// DockerRepo: listing when a Docker daemon is available, and the cached stats' `observed_at`
+// stamp and `container_stale_ms` filtering (no daemon needed).
 
-use homeserver::docker_repo::DockerRepo;
+mod common;
+
+use homeserver::config::AppConfig;
+use homeserver::docker_repo::{DockerRepo, partition_stale};
+use homeserver::models::ContainerStats;
 
 #[tokio::test]
 async fn docker_repo_connect_and_list_running() {
@@ -12,3 +17,94 @@ async fn docker_repo_connect_and_list_running() {
     // No panic; may be empty if no containers running
     let _ = stats;
 }
+
+fn container(id: &str, observed_at: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": id,
+        "cpuPercent": 1.0,
+        "memoryUsageBytes": 1,
+        "memoryLimitBytes": 2,
+        "state": "running",
+        "observedAt": observed_at,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn partition_stale_splits_by_age() {
+    let now = 1_700_000_100_000;
+    let cached = vec![
+        container("fresh", now - 1_000),
+        container("edge", now - 5_000),
+        container("old", now - 60_000),
+        container("ahead", now + 2_000),
+    ];
+
+    let (fresh, stale) = partition_stale(cached.clone(), now, Some(5_000));
+    let ids: Vec<_> = fresh.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, ["fresh", "edge", "ahead"]);
+    assert_eq!(stale, ["old"]);
+    assert_eq!(fresh[0].observed_at, now - 1_000);
+
+    let (fresh, stale) = partition_stale(cached, now, None);
+    assert_eq!(fresh.len(), 4);
+    assert!(stale.is_empty());
+}
+
+#[tokio::test]
+async fn cached_stats_are_stamped_and_stale_ones_dropped() {
+    let Ok(repo) = DockerRepo::connect() else {
+        return;
+    };
+    let repo = repo.with_stale_after_ms(Some(100));
+    let before = common::now_ms() as u64;
+    repo.cache_stats(container("old", 0)).await;
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    repo.cache_stats(container("new", 0)).await;
+
+    let cached = repo.get_cached_stats().await;
+    assert_eq!(cached.len(), 1, "{cached:?}");
+    assert_eq!(cached[0].id, "new");
+    assert!(cached[0].observed_at >= before + 250);
+    assert!(cached[0].observed_at <= common::now_ms() as u64);
+    let json = serde_json::to_value(&cached[0]).unwrap();
+    assert_eq!(json["observedAt"], cached[0].observed_at);
+}
+
+#[tokio::test]
+async fn without_a_limit_cached_stats_are_served_however_old() {
+    let Ok(repo) = DockerRepo::connect() else {
+        return;
+    };
+    repo.cache_stats(container("a", 0)).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let cached = repo.get_cached_stats().await;
+    assert_eq!(cached.len(), 1);
+    assert!(cached[0].observed_at > 0);
+}
+
+#[test]
+fn observed_at_defaults_and_is_not_stored_in_history_blobs() {
+    let json = serde_json::json!({
+        "id": "c", "name": "c", "cpuPercent": 0.0, "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0, "state": "running",
+    });
+    let c: ContainerStats = serde_json::from_value(json).unwrap();
+    assert_eq!(c.observed_at, 0);
+
+    let stamped = container("c", 1_700_000_000_000);
+    let bytes = wincode::serialize(&stamped).unwrap();
+    let back: ContainerStats = wincode::deserialize(&bytes).unwrap();
+    assert_eq!(back.observed_at, 0);
+    assert_eq!(bytes, wincode::serialize(&container("c", 0)).unwrap());
+}
+
+#[test]
+fn container_stale_ms_parses_and_rejects_zero() {
+    let config = AppConfig::load_from_str("[monitoring]\ncontainer_stale_ms = 30000\n").unwrap();
+    assert_eq!(config.monitoring.container_stale_ms, Some(30_000));
+    assert_eq!(AppConfig::default().monitoring.container_stale_ms, None);
+    let err = AppConfig::load_from_str("[monitoring]\ncontainer_stale_ms = 0\n").unwrap_err();
+    assert!(err.to_string().contains("container_stale_ms"), "{err}");
+}
@@ -6,7 +6,9 @@ use bollard::models::{
     ContainerMemoryStats, ContainerNetworkStats, ContainerPidsStats, ContainerStatsResponse,
     ContainerThrottlingData,
 };
+use homeserver::config::{AppConfig, CpuPercentMode};
 use homeserver::docker_repo::process_statistics;
+use homeserver::models::cpu_percent_of_host;
 use std::collections::HashMap;
 
 fn minimal_cpu_stats(total_usage: u64, system_cpu_usage: u64) -> ContainerCpuStats {
@@ -89,6 +91,7 @@ fn process_statistics_computes_cpu_and_memory() {
     assert_eq!(out.id, "abc123");
     assert_eq!(out.name, "mycontainer");
     assert!((out.cpu_percent - 20.0).abs() < 0.01);
+    assert!((out.cpu_percent_of_host - 10.0).abs() < 0.01);
     assert_eq!(out.memory_usage_bytes, 256 * 1024 * 1024);
     assert_eq!(out.memory_limit_bytes, 512 * 1024 * 1024);
     assert_eq!(out.memory_max_usage_bytes, 300 * 1024 * 1024);
@@ -131,4 +134,104 @@ fn process_statistics_zero_system_delta_returns_zero_cpu_percent() {
     };
     let out = process_statistics(&s, "id", "n").unwrap();
     assert_eq!(out.cpu_percent, 0.0);
+    assert_eq!(out.cpu_percent_of_host, 0.0);
+}
+
+/// One core saturated on a 4-core host, 3/4 of it in user mode.
+fn one_busy_core_of_four() -> ContainerStatsResponse {
+    let cpu = |total: u64, kernel: u64, user: u64, system: u64| ContainerCpuStats {
+        cpu_usage: Some(ContainerCpuUsage {
+            total_usage: Some(total),
+            usage_in_kernelmode: Some(kernel),
+            usage_in_usermode: Some(user),
+            ..Default::default()
+        }),
+        system_cpu_usage: Some(system),
+        online_cpus: Some(4),
+        throttling_data: None,
+    };
+    ContainerStatsResponse {
+        cpu_stats: Some(cpu(1_000, 250, 750, 4_000)),
+        precpu_stats: Some(cpu(0, 0, 0, 0)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn process_statistics_reports_per_core_and_the_host_share() {
+    let out = process_statistics(&one_busy_core_of_four(), "id", "n").unwrap();
+    assert_eq!(out.cpu_percent, 100.0);
+    assert_eq!(out.cpu_kernel_percent, 25.0);
+    assert_eq!(out.cpu_user_percent, 75.0);
+    assert_eq!(out.cpu_percent_of_host, 25.0);
+    assert_eq!(out.online_cpus, 4);
+}
+
+#[test]
+fn host_total_mode_puts_cpu_on_the_host_scale() {
+    let mut out = process_statistics(&one_busy_core_of_four(), "id", "n").unwrap();
+    out.apply_cpu_percent_mode(CpuPercentMode::PerCore);
+    assert_eq!(
+        (
+            out.cpu_percent,
+            out.cpu_kernel_percent,
+            out.cpu_user_percent
+        ),
+        (100.0, 25.0, 75.0)
+    );
+    out.apply_cpu_percent_mode(CpuPercentMode::HostTotal);
+    assert_eq!(out.cpu_percent, 25.0);
+    assert_eq!(out.cpu_kernel_percent, 6.25);
+    assert_eq!(out.cpu_user_percent, 18.75);
+    assert_eq!(out.cpu_percent_of_host, out.cpu_percent);
+    assert_eq!(
+        cpu_percent_of_host(100.0, 0),
+        100.0,
+        "no online_cpus counts as one core"
+    );
+}
+
+#[test]
+fn cpu_percent_mode_defaults_to_per_core_and_parses() {
+    assert_eq!(
+        AppConfig::default().docker.cpu_percent_mode,
+        CpuPercentMode::PerCore
+    );
+    let config = AppConfig::load_from_str("[docker]\ncpu_percent_mode = \"host_total\"\n").unwrap();
+    assert_eq!(config.docker.cpu_percent_mode, CpuPercentMode::HostTotal);
+    assert!(AppConfig::load_from_str("[docker]\ncpu_percent_mode = \"percore\"\n").is_err());
+}
+
+fn with_pids(current: u64, limit: Option<u64>) -> ContainerStatsResponse {
+    ContainerStatsResponse {
+        cpu_stats: Some(minimal_cpu_stats(100, 1000)),
+        precpu_stats: Some(minimal_cpu_stats(50, 500)),
+        pids_stats: Some(ContainerPidsStats {
+            current: Some(current),
+            limit,
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn process_statistics_pids_usage_against_the_limit() {
+    let out = process_statistics(&with_pids(45, Some(50)), "id", "n").unwrap();
+    assert_eq!((out.pids, out.pids_limit), (45, 50));
+    assert_eq!(out.pids_usage_percent, 90.0);
+}
+
+#[test]
+fn process_statistics_pids_usage_is_zero_when_unlimited() {
+    for limit in [None, Some(0)] {
+        let out = process_statistics(&with_pids(500, limit), "id", "n").unwrap();
+        assert_eq!(out.pids, 500);
+        assert_eq!(out.pids_usage_percent, 0.0, "limit {limit:?}");
+    }
+}
+
+#[test]
+fn process_statistics_pids_usage_is_zero_without_pids() {
+    let out = process_statistics(&with_pids(0, Some(100)), "id", "n").unwrap();
+    assert_eq!(out.pids_usage_percent, 0.0);
 }
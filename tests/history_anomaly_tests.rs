@@ -0,0 +1,219 @@
+// Rolling z-score anomalies: detect_anomalies on synthetic signals with injected spikes and level
+// shifts, and /api/history?annotate=anomalies with its validation.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{
+    AnomalyParams, HistoryRepo, detect_anomalies, detect_history_anomalies,
+};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+/// A wobbling baseline around `level` (deterministic, amplitude 2).
+fn signal(len: usize, level: f64) -> Vec<f64> {
+    (0..len).map(|i| level + 2.0 * (i as f64).sin()).collect()
+}
+
+fn timestamps(len: usize) -> Vec<u64> {
+    (0..len as u64).map(|i| 1_000 + i * 60_000).collect()
+}
+
+fn cpu(values: &[f64], params: AnomalyParams) -> Vec<Anomaly> {
+    detect_anomalies(
+        &timestamps(values.len()),
+        values,
+        AnomalyMetric::Cpu,
+        params,
+    )
+}
+
+#[test]
+fn steady_signal_has_no_anomalies() {
+    assert!(cpu(&signal(200, 20.0), AnomalyParams::default()).is_empty());
+    assert!(cpu(&[], AnomalyParams::default()).is_empty());
+}
+
+#[test]
+fn spikes_are_flagged_and_consecutive_points_merge() {
+    let ts = timestamps(120);
+    let mut values = signal(120, 20.0);
+    values[50] = 90.0;
+    // Past the first spike's window: it would still inflate the baseline's deviation.
+    values[90] = 45.0;
+    values[91] = 50.0;
+    let anomalies = cpu(&values, AnomalyParams::default());
+    assert_eq!(anomalies.len(), 2, "{anomalies:?}");
+    assert_eq!(anomalies[0].from, ts[50]);
+    assert_eq!(anomalies[0].to, ts[50]);
+    assert_eq!(anomalies[0].metric, AnomalyMetric::Cpu);
+    assert_eq!(anomalies[0].severity, AnomalySeverity::Critical);
+    assert_eq!((anomalies[1].from, anomalies[1].to), (ts[90], ts[91]));
+}
+
+#[test]
+fn spikes_inside_the_first_window_only_form_the_baseline() {
+    let mut values = signal(40, 20.0);
+    values[10] = 90.0;
+    let params = AnomalyParams {
+        window: 20,
+        ..Default::default()
+    };
+    assert!(cpu(&values, params).is_empty());
+}
+
+#[test]
+fn level_shift_is_one_run_until_the_window_catches_up() {
+    let ts = timestamps(150);
+    let mut values = signal(60, 20.0);
+    values.extend(signal(90, 40.0));
+    let anomalies = cpu(&values, AnomalyParams::default());
+    assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+    assert_eq!(anomalies[0].from, ts[60]);
+    assert!(anomalies[0].to < ts[90], "{anomalies:?}");
+    assert_eq!(anomalies[0].severity, AnomalySeverity::Critical);
+}
+
+#[test]
+fn threshold_decides_flagging_and_severity() {
+    let ts = timestamps(60);
+    let mut values = signal(60, 20.0);
+    // About 4.5 standard deviations above the baseline.
+    values[45] = 26.5;
+    let at = |threshold| {
+        cpu(
+            &values,
+            AnomalyParams {
+                threshold,
+                ..Default::default()
+            },
+        )
+    };
+    assert!(at(6.0).is_empty());
+    let warning = at(3.0);
+    assert_eq!((warning.len(), warning[0].from), (1, ts[45]));
+    assert_eq!(warning[0].severity, AnomalySeverity::Warning);
+    assert_eq!(at(2.0)[0].severity, AnomalySeverity::Critical);
+
+    // A flat baseline: min_stddev keeps small steps quiet.
+    let mut flat = vec![5.0; 40];
+    flat[35] = 5.4;
+    assert!(cpu(&flat, AnomalyParams::default()).is_empty());
+    flat[35] = 9.0;
+    assert_eq!(cpu(&flat, AnomalyParams::default()).len(), 1);
+}
+
+fn snapshot(ts: u64, cpu: f64, ram_percent: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            usage_percent: ram_percent,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+/// 60 one-second samples: a CPU spike at 40 and a RAM spike at 45.
+fn seeded(start: u64) -> Vec<FullSystemSnapshot> {
+    let cpu = signal(60, 20.0);
+    let ram = signal(60, 50.0);
+    (0..60)
+        .map(|i| {
+            let c = if i == 40 { 95.0 } else { cpu[i] };
+            let r = if i == 45 { 90.0 } else { ram[i] };
+            snapshot(start + i as u64 * 1000, c, r)
+        })
+        .collect()
+}
+
+#[test]
+fn history_anomalies_cover_cpu_and_ram_in_time_order() {
+    let points: Vec<HistoryPoint> = seeded(0)
+        .into_iter()
+        .map(|snapshot| HistoryPoint {
+            snapshot,
+            envelope: None,
+        })
+        .collect();
+    let anomalies = detect_history_anomalies(&points, AnomalyParams::default());
+    let found: Vec<_> = anomalies.iter().map(|a| (a.from, a.metric)).collect();
+    assert_eq!(
+        found,
+        [(40_000, AnomalyMetric::Cpu), (45_000, AnomalyMetric::Ram)]
+    );
+}
+
+#[tokio::test]
+async fn api_history_annotates_anomalies() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let start = (now - 600_000) / 1000 * 1000;
+    repo.save_snapshots(&seeded(start), &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+    let range = format!("from={start}&to={}&resolution=1s", start + 60_000);
+
+    let body: serde_json::Value = server
+        .get(&format!("/api/history?{range}&annotate=anomalies"))
+        .await
+        .json();
+    assert_eq!(body["points"].as_array().unwrap().len(), 60);
+    assert_eq!(
+        body["anomalies"],
+        serde_json::json!([
+            {"from": start + 40_000, "to": start + 40_000, "metric": "cpu", "severity": "critical"},
+            {"from": start + 45_000, "to": start + 45_000, "metric": "ram", "severity": "critical"},
+        ])
+    );
+
+    // With an envelope the points carry it; a window longer than the series finds nothing.
+    let body: serde_json::Value = server
+        .get(&format!(
+            "/api/history?{range}&envelope=minmax&annotate=anomalies&anomaly_window=100"
+        ))
+        .await
+        .json();
+    assert!(body["points"][0]["envelope"].is_object());
+    assert_eq!(body["anomalies"], serde_json::json!([]));
+
+    // Without annotate the response stays a plain array.
+    let body: serde_json::Value = server.get(&format!("/api/history?{range}")).await.json();
+    assert_eq!(body.as_array().unwrap().len(), 60);
+
+    for query in [
+        "annotate=spikes",
+        "anomaly_threshold=2",
+        "annotate=anomalies&anomaly_window=1",
+        "annotate=anomalies&anomaly_threshold=0",
+        "annotate=anomalies&anomaly_threshold=-3",
+    ] {
+        let response = server.get(&format!("/api/history?{range}&{query}")).await;
+        response.assert_status_bad_request();
+        let body: serde_json::Value = response.json();
+        assert!(body["error"].is_string(), "{query}: {body}");
+    }
+}
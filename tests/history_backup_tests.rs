@@ -0,0 +1,159 @@
+// Online backups: VACUUM INTO snapshots, timestamped files and retention; POST /api/db/backup and
+// GET /api/db/backup/download behind the admin token.
+
+mod common;
+
+use axum_test::TestServer;
+use common::snapshot;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::{HistoryRepo, latest_backup, list_backups, prune_backups};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const TOKEN: &str = "hunter2";
+
+#[tokio::test]
+async fn backup_matches_live_row_counts() {
+    let dir = TempDir::new().unwrap();
+    let repo = common::connect(&dir.path().join("h.db")).await;
+    let snaps: Vec<_> = (0..120).map(|i| snapshot(i * 1000)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let backup_path = dir.path().join("copy.db");
+    let size = repo.backup_to(&backup_path).await.unwrap();
+    assert!(size > 0);
+    assert_eq!(std::fs::metadata(&backup_path).unwrap().len(), size);
+
+    // The live repo stays open (WAL content not checkpointed) while the copy is read.
+    let copy = common::connect(&backup_path).await;
+    let live_stats = repo.db_stats().await.unwrap();
+    let copy_stats = copy.db_stats().await.unwrap();
+    assert_eq!(copy_stats.raw_rows, 120);
+    assert_eq!(copy_stats.raw_rows, live_stats.raw_rows);
+    assert_eq!(copy_stats.aggregated_rows, live_stats.aggregated_rows);
+    assert_eq!(copy_stats.blob_store_entries, live_stats.blob_store_entries);
+    assert_eq!(copy_stats.schema_version, live_stats.schema_version);
+
+    // VACUUM INTO refuses to overwrite an existing file.
+    assert!(repo.backup_to(&backup_path).await.is_err());
+}
+
+#[tokio::test]
+async fn create_backup_keeps_newest_n() {
+    let dir = TempDir::new().unwrap();
+    let repo = common::connect(&dir.path().join("h.db")).await;
+    let backups = dir.path().join("backups");
+    assert!(latest_backup(&backups).unwrap().is_none());
+
+    let mut written = Vec::new();
+    for _ in 0..4 {
+        written.push(repo.create_backup(&backups, 2).await.unwrap());
+        // Timestamps have millisecond precision; keep file names distinct.
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let remaining = list_backups(&backups).unwrap();
+    assert_eq!(remaining.len(), 2);
+    assert_eq!(remaining[0].display().to_string(), written[2].path);
+    assert_eq!(remaining[1].display().to_string(), written[3].path);
+    assert_eq!(
+        latest_backup(&backups)
+            .unwrap()
+            .unwrap()
+            .display()
+            .to_string(),
+        written[3].path
+    );
+
+    // Unrelated files in the directory are never listed or pruned.
+    std::fs::write(backups.join("notes.txt"), "keep").unwrap();
+    assert_eq!(prune_backups(&backups, 1).unwrap(), 1);
+    assert_eq!(list_backups(&backups).unwrap().len(), 1);
+    assert!(backups.join("notes.txt").exists());
+}
+
+/// The router on a fresh database in `dir`, backups under `dir/backups`.
+async fn backup_server(dir: &TempDir, admin_token: Option<&str>) -> TestServer {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").display().to_string();
+    config.database.backup_dir = dir.path().join("backups").display().to_string();
+    config.server.admin_token = admin_token.map(Secret::new);
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        Arc::new(repo),
+        Default::default(),
+    ))
+}
+
+#[tokio::test]
+async fn backup_endpoints_create_and_serve_the_newest_backup() {
+    let dir = TempDir::new().unwrap();
+    let server = backup_server(&dir, Some(TOKEN)).await;
+    server
+        .get("/api/db/backup/download")
+        .authorization_bearer(TOKEN)
+        .await
+        .assert_status_not_found();
+
+    let response = server
+        .post("/api/db/backup")
+        .authorization_bearer(TOKEN)
+        .await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    let path = json["path"].as_str().unwrap();
+    assert!(path.starts_with(dir.path().join("backups").to_str().unwrap()));
+    let size = json["sizeBytes"].as_u64().unwrap();
+    assert_eq!(std::fs::metadata(path).unwrap().len(), size);
+
+    let response = server
+        .get("/api/db/backup/download")
+        .authorization_bearer(TOKEN)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.as_bytes().len() as u64, size);
+    assert!(response.as_bytes().starts_with(b"SQLite format 3\0"));
+}
+
+#[tokio::test]
+async fn backup_endpoints_need_the_admin_token() {
+    let dir = TempDir::new().unwrap();
+    let disabled = backup_server(&dir, None).await;
+    let response = disabled.post("/api/db/backup").expect_failure().await;
+    response.assert_status_forbidden();
+    disabled
+        .get("/api/db/backup/download")
+        .expect_failure()
+        .await
+        .assert_status_forbidden();
+
+    let dir = TempDir::new().unwrap();
+    let gated = backup_server(&dir, Some(TOKEN)).await;
+    for token in [None, Some("wrong")] {
+        let post = gated.post("/api/db/backup");
+        let get = gated.get("/api/db/backup/download");
+        let (post, get) = match token {
+            Some(token) => (
+                post.authorization_bearer(token),
+                get.authorization_bearer(token),
+            ),
+            None => (post, get),
+        };
+        post.expect_failure().await.assert_status_unauthorized();
+        get.expect_failure().await.assert_status_unauthorized();
+    }
+    assert!(
+        !dir.path().join("backups").exists(),
+        "a rejected call writes no backup"
+    );
+}
@@ -0,0 +1,145 @@
+// save_snapshots batching: several hundred rows (more than one multi-row INSERT chunk) round
+// trip intact, shared storage/network blobs are stored once, and the flush stays fast.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const ROWS: u64 = 500;
+const MOUNTS: u64 = 5;
+
+fn container(i: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("{i:064x}"),
+        "name": format!("container-{i}"),
+        "cpuPercent": i as f64,
+        "memoryUsageBytes": i * 10,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+fn snapshot(i: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: i as f64 / 10.0,
+            temperature: 40.0 + (i % 7) as f64,
+            ..Default::default()
+        },
+        ram: RamStats {
+            total: 16_000,
+            used: 1000 + i,
+            ..Default::default()
+        },
+        containers: vec![container(i)],
+        storage: StorageStats {
+            partitions: vec![PartitionStat {
+                mount: format!("/mnt/{}", i % MOUNTS),
+                name: "sda1".into(),
+                type_: "ext4".into(),
+                total_space: 1000,
+                used_space: 400,
+                available_space: 600,
+                usage_percent: 40.0,
+            }],
+            disks: vec![],
+        },
+        ..common::snapshot(1_700_000_000_000 + i * 1000)
+    }
+}
+
+#[tokio::test]
+async fn hundreds_of_snapshots_round_trip_in_one_flush() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let snaps: Vec<_> = (0..ROWS).map(snapshot).collect();
+
+    let started = Instant::now();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    // Rough guard against regressing to per-row round trips; well above the expected time.
+    assert!(
+        started.elapsed() < Duration::from_secs(5),
+        "{:?}",
+        started.elapsed()
+    );
+
+    let (info, read) = repo.get_recent_snapshots(ROWS as u32 + 10).await.unwrap();
+    assert!(info.is_some());
+    assert_eq!(read.len(), ROWS as usize);
+    for (i, s) in read.iter().enumerate() {
+        let want = &snaps[i];
+        assert_eq!(s.timestamp, want.timestamp);
+        assert_eq!(s.cpu.usage_percent, want.cpu.usage_percent);
+        assert_eq!(s.cpu.temperature, want.cpu.temperature);
+        assert_eq!(s.ram.used, want.ram.used);
+        assert_eq!(s.containers[0].name, want.containers[0].name);
+        assert_eq!(
+            s.containers[0].memory_usage_bytes,
+            want.containers[0].memory_usage_bytes
+        );
+        assert_eq!(
+            s.storage.partitions[0].mount,
+            want.storage.partitions[0].mount
+        );
+    }
+
+    // One blob per distinct storage section plus the single (default) network section.
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    let (blobs,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM blob_store")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(blobs, MOUNTS as i64 + 1);
+}
+
+#[tokio::test]
+async fn a_second_flush_reuses_stored_blobs() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let snaps: Vec<_> = (0..ROWS).map(snapshot).collect();
+    let (first, second) = snaps.split_at(200);
+    repo.save_snapshots(first, &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.save_snapshots(second, &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.save_snapshots(&[], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let (_, read) = repo.get_recent_snapshots(ROWS as u32).await.unwrap();
+    assert_eq!(read.len(), ROWS as usize);
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    let (blobs,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM blob_store")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(blobs, MOUNTS as i64 + 1);
+}
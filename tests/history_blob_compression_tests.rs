@@ -0,0 +1,207 @@
+// zstd-compressed history blobs: encode/decode round-trips and compatibility with uncompressed rows
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::blob::{
+    BLOB_VERSION, BLOB_VERSION_COMPRESSED, BLOB_VERSION_SYSTEM_DYNAMIC,
+    BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED, decode_blob, encode_blob,
+};
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn interface(i: usize) -> InterfaceStat {
+    InterfaceStat {
+        name: format!("veth{:04x}", i),
+        display_name: format!("veth{:04x}", i),
+        mac_address: format!("02:42:ac:11:00:{:02x}", i),
+        ipv4: vec![format!("172.17.0.{}", i + 2)],
+        ipv6: vec![],
+        bytes_sent: 1_000_000 + i as u64,
+        bytes_recv: 2_000_000 + i as u64,
+        packets_sent: 10_000,
+        packets_recv: 20_000,
+        speed: 10_000_000_000,
+        received_bytes_per_sec: 1024.0,
+        transmitted_bytes_per_sec: 512.0,
+        is_up: true,
+        packets_recv_per_sec: 0.0,
+        packets_sent_per_sec: 0.0,
+    }
+}
+
+/// Thirty interfaces, like a Docker host with a bridge per stack.
+fn network() -> NetworkStats {
+    NetworkStats::new((0..30).map(interface).collect())
+}
+
+fn snapshot(ts: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.5,
+            ..Default::default()
+        },
+        ram: RamStats {
+            used: 4096,
+            ..Default::default()
+        },
+        network: network(),
+        system: SystemStatsDynamic {
+            uptime_secs: 3600,
+            process_count: 321,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+async fn connect(path: &Path, compress_blobs: bool) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        compress_blobs,
+        ..common::database(path)
+    })
+    .await
+}
+
+async fn blob_prefixes(path: &Path, column: &str) -> Vec<u8> {
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    let sql = match column {
+        // Network blobs live in blob_store (schema v8+), referenced by hash.
+        "network_data" => {
+            "SELECT COALESCE(b.data, h.network_data) FROM system_history h
+             LEFT JOIN blob_store b ON b.hash = h.network_hash ORDER BY h.id"
+        }
+        _ => "SELECT system_data FROM system_history ORDER BY id",
+    };
+    let rows: Vec<Vec<u8>> = sqlx::query_scalar(sql).fetch_all(&pool).await.unwrap();
+    pool.close().await;
+    rows.iter().map(|b| b[0]).collect()
+}
+
+#[test]
+fn compressed_round_trip_is_smaller() {
+    let plain = encode_blob(&network(), BLOB_VERSION, false).unwrap();
+    let packed = encode_blob(&network(), BLOB_VERSION, true).unwrap();
+    assert_eq!(plain[0], BLOB_VERSION);
+    assert_eq!(packed[0], BLOB_VERSION_COMPRESSED);
+    assert!(
+        packed.len() * 2 < plain.len(),
+        "{} vs {}",
+        packed.len(),
+        plain.len()
+    );
+
+    let decoded: NetworkStats = decode_blob(&packed, BLOB_VERSION, "network_data").unwrap();
+    assert_eq!(decoded.interfaces.len(), 30);
+    assert_eq!(decoded.interfaces[29].name, "veth001d");
+    assert_eq!(decoded.interfaces[29].bytes_recv, 2_000_029);
+}
+
+#[test]
+fn system_dynamic_uses_its_own_compressed_version() {
+    let dynamic = SystemStatsDynamic {
+        uptime_secs: 42,
+        ..Default::default()
+    };
+    let packed = encode_blob(&dynamic, BLOB_VERSION_SYSTEM_DYNAMIC, true).unwrap();
+    assert_eq!(packed[0], BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED);
+    let decoded: SystemStatsDynamic =
+        decode_blob(&packed, BLOB_VERSION_SYSTEM_DYNAMIC, "system_data").unwrap();
+    assert_eq!(decoded.uptime_secs, 42);
+}
+
+#[test]
+fn decode_accepts_uncompressed_and_legacy_unprefixed() {
+    let plain = encode_blob(&network(), BLOB_VERSION, false).unwrap();
+    let decoded: NetworkStats = decode_blob(&plain, BLOB_VERSION, "network_data").unwrap();
+    assert_eq!(decoded.interfaces.len(), 30);
+
+    let legacy = wincode::serialize(&network()).unwrap();
+    let decoded: NetworkStats = decode_blob(&legacy, BLOB_VERSION, "network_data").unwrap();
+    assert_eq!(decoded.interfaces.len(), 30);
+}
+
+#[tokio::test]
+async fn uncompressed_rows_still_load_after_enabling_compression() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+
+    let old = connect(&path, false).await;
+    old.save_snapshots(&[snapshot(1_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    drop(old);
+
+    let repo = connect(&path, true).await;
+    repo.save_snapshots(&[snapshot(2_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        blob_prefixes(&path, "network_data").await,
+        vec![BLOB_VERSION, BLOB_VERSION_COMPRESSED]
+    );
+    assert_eq!(
+        blob_prefixes(&path, "system_data").await,
+        vec![
+            BLOB_VERSION_SYSTEM_DYNAMIC,
+            BLOB_VERSION_SYSTEM_DYNAMIC_COMPRESSED
+        ]
+    );
+
+    let (_info, snaps) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(snaps.len(), 2);
+    for s in &snaps {
+        assert_eq!(s.network.interfaces.len(), 30);
+        assert_eq!(s.system.process_count, 321);
+        assert_eq!(s.cpu.usage_percent, 12.5);
+    }
+}
+
+#[tokio::test]
+async fn compressed_aggregated_rows_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir.path().join("h.db"), true).await;
+    let agg = AggregatedSnapshot {
+        created_at: 60_000,
+        resolution_seconds: 60,
+        sample_count: 1,
+        cpu_load_avg: 10.0,
+        cpu_load_min: 5.0,
+        cpu_load_max: 15.0,
+        memory_used_avg: 512,
+        memory_used_min: 256,
+        memory_used_max: 768,
+        cpu_load_p95: Some(24.0),
+        memory_used_p95: Some(700),
+        memory_total_avg: 8192,
+        memory_total_min: 8192,
+        memory_total_max: 8192,
+        cpu_temperature_avg: 55.0,
+        cpu_temperature_min: 50.0,
+        cpu_temperature_max: 60.0,
+        cpu: CpuStats::default(),
+        ram: RamStats::default(),
+        containers: vec![],
+        storage: StorageStats::default(),
+        network: network(),
+        system: SystemStatsDynamic::default(),
+        gpus: vec![],
+        smart: vec![],
+        probes: vec![],
+        sensors: vec![],
+    };
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, 120_000, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].network.interfaces.len(), 30);
+}
@@ -0,0 +1,155 @@
+// blob_store dedup: storage/network sections stored once by hash, read back through the join,
+// garbage-collected on prune, and legacy inline rows still readable alongside.
+
+mod common;
+
+use homeserver::history_repo::blob;
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tempfile::TempDir;
+
+async fn raw_pool(path: &Path) -> SqlitePool {
+    SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap()
+}
+
+fn storage(mount: &str) -> StorageStats {
+    StorageStats {
+        partitions: vec![PartitionStat {
+            mount: mount.into(),
+            name: "sda1".into(),
+            type_: "ext4".into(),
+            total_space: 1000,
+            used_space: 400,
+            available_space: 600,
+            usage_percent: 40.0,
+        }],
+        disks: vec![],
+    }
+}
+
+fn snapshot(ts: u64, mount: &str) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        storage: storage(mount),
+        ..common::snapshot(ts)
+    }
+}
+
+#[tokio::test]
+async fn identical_sections_are_stored_once() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+
+    let snaps: Vec<_> = (0..10).map(|i| snapshot(1_000 + i, "/")).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    // One storage blob + one network blob shared by all ten rows.
+    assert_eq!(repo.blob_store_count().await.unwrap(), 2);
+
+    repo.save_snapshots(&[snapshot(2_000, "/data")], &SystemInfo::default())
+        .await
+        .unwrap();
+    assert_eq!(repo.blob_store_count().await.unwrap(), 3);
+
+    let loaded = repo
+        .get_raw_snapshots_by_time_range(0, 10_000)
+        .await
+        .unwrap();
+    assert_eq!(loaded.len(), 11);
+    assert_eq!(loaded[0].storage.partitions[0].mount, "/");
+    assert_eq!(loaded[10].storage.partitions[0].mount, "/data");
+
+    let (_, recent) = repo.get_recent_snapshots(1).await.unwrap();
+    assert_eq!(recent[0].storage.partitions[0].mount, "/data");
+
+    // Inline columns stay empty for deduplicated rows.
+    let pool = raw_pool(&path).await;
+    let inline: i64 = sqlx::query_scalar("SELECT MAX(length(storage_data)) FROM system_history")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    assert_eq!(inline, 0);
+}
+
+#[tokio::test]
+async fn deleting_rows_collects_unreferenced_blobs() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+
+    repo.save_snapshots(
+        &[snapshot(1_000, "/old"), snapshot(2_000, "/kept")],
+        &SystemInfo::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(repo.blob_store_count().await.unwrap(), 3);
+
+    // "/old" storage goes; the shared network blob is still referenced by the kept row.
+    repo.delete_raw_range(0, 1_500).await.unwrap();
+    assert_eq!(repo.blob_store_count().await.unwrap(), 2);
+
+    repo.delete_raw_range(0, 3_000).await.unwrap();
+    assert_eq!(repo.blob_store_count().await.unwrap(), 0);
+
+    // prune_old_data also collects. A recent row keeps the pass under max_prune_fraction.
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    repo.save_snapshots(
+        &[snapshot(3_000, "/"), snapshot(now, "/recent")],
+        &SystemInfo::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(repo.blob_store_count().await.unwrap(), 3);
+    assert_eq!(repo.prune_old_data().await.unwrap(), 1);
+    assert_eq!(repo.blob_store_count().await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn legacy_inline_rows_read_alongside_deduplicated_rows() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+
+    // Pre-v8 row: inline storage/network blobs, no hashes.
+    let pool = raw_pool(&path).await;
+    let storage_blob = blob::encode_blob(&storage("/legacy"), blob::BLOB_VERSION, false).unwrap();
+    let network_blob =
+        blob::encode_blob(&NetworkStats::default(), blob::BLOB_VERSION, false).unwrap();
+    let empty: &[u8] = &[];
+    sqlx::query(
+        "INSERT INTO system_history (created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data)
+         VALUES (500, 1.0, 1, $1, $2, $3, $1)",
+    )
+    .bind(empty)
+    .bind(&storage_blob)
+    .bind(&network_blob)
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+
+    repo.save_snapshots(&[snapshot(1_000, "/new")], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let loaded = repo
+        .get_raw_snapshots_by_time_range(0, 10_000)
+        .await
+        .unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].storage.partitions[0].mount, "/legacy");
+    assert_eq!(loaded[1].storage.partitions[0].mount, "/new");
+
+    // GC ignores legacy rows (they own their inline blobs) and keeps referenced entries.
+    assert_eq!(repo.gc_blob_store().await.unwrap(), 0);
+    assert_eq!(repo.blob_store_count().await.unwrap(), 2);
+}
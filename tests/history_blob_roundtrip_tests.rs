@@ -1,6 +1,9 @@
 // Verifies GPU + SMART values survive a full save -> read round-trip through HistoryRepo
 // (both the raw and aggregated tables), not just the in-memory wincode round-trip.
 
+mod common;
+
+use homeserver::config::DatabaseConfig;
 use homeserver::history_repo::HistoryRepo;
 use homeserver::models::*;
 use tempfile::TempDir;
@@ -33,24 +36,21 @@ fn smart() -> SmartHealth {
 
 fn snapshot(ts: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp: ts,
-        cpu: CpuStats::default(),
-        ram: RamStats::default(),
-        containers: vec![],
-        storage: StorageStats::default(),
-        network: NetworkStats::default(),
-        system: SystemStatsDynamic::default(),
         gpus: vec![gpu()],
         smart: vec![smart()],
+        ..common::snapshot(ts)
     }
 }
 
 #[tokio::test]
 async fn raw_snapshot_round_trip_preserves_gpu_and_smart() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     repo.save_snapshots(&[snapshot(1_700_000_000_000)], &SystemInfo::default())
@@ -66,20 +66,32 @@ async fn raw_snapshot_round_trip_preserves_gpu_and_smart() {
 #[tokio::test]
 async fn aggregated_snapshot_round_trip_preserves_gpu_and_smart() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let agg = AggregatedSnapshot {
         created_at: 60_000,
         resolution_seconds: 60,
+        sample_count: 1,
         cpu_load_avg: 10.0,
         cpu_load_min: 5.0,
         cpu_load_max: 15.0,
         memory_used_avg: 512,
         memory_used_min: 256,
         memory_used_max: 768,
+        cpu_load_p95: Some(24.0),
+        memory_used_p95: Some(700),
+        memory_total_avg: 8192,
+        memory_total_min: 8192,
+        memory_total_max: 8192,
+        cpu_temperature_avg: 55.0,
+        cpu_temperature_min: 50.0,
+        cpu_temperature_max: 60.0,
         cpu: CpuStats::default(),
         ram: RamStats::default(),
         containers: vec![],
@@ -88,6 +100,8 @@ async fn aggregated_snapshot_round_trip_preserves_gpu_and_smart() {
         system: SystemStatsDynamic::default(),
         gpus: vec![gpu()],
         smart: vec![smart()],
+        probes: vec![],
+        sensors: vec![],
     };
     repo.save_aggregated_snapshot(&agg).await.unwrap();
 
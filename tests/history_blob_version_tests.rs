@@ -0,0 +1,208 @@
+// Blob version handling: current, legacy (unprefixed), unknown-future and corrupted blobs for
+// every column type; history readers skip rows with an unknown version instead of emitting
+// empty snapshots.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::blob::{
+    self, BLOB_VERSION, BLOB_VERSION_SYSTEM_DYNAMIC, decode_blob, encode_blob,
+};
+use homeserver::history_repo::{HistoryError, HistoryRepo};
+use homeserver::models::*;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use tempfile::TempDir;
+use wincode::config::DefaultConfig;
+
+/// A version byte no build has written yet.
+const FUTURE_VERSION: u8 = 9;
+
+fn json<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+/// Round-trip `value` through each kind of blob of `column`.
+fn check_column<T>(value: &T, version: u8, column: &'static str)
+where
+    T: Serialize
+        + wincode::SchemaWrite<DefaultConfig, Src = T>
+        + for<'de> wincode::SchemaRead<'de, DefaultConfig, Dst = T>,
+{
+    for compress in [false, true] {
+        let current = encode_blob(value, version, compress).unwrap();
+        let decoded = decode_blob::<T>(&current, version, column).unwrap();
+        assert_eq!(json(&decoded), json(value), "{column} current");
+    }
+
+    let legacy = wincode::serialize(value).unwrap();
+    let decoded = decode_blob::<T>(&legacy, version, column).unwrap();
+    assert_eq!(json(&decoded), json(value), "{column} legacy");
+
+    let mut future = encode_blob(value, version, false).unwrap();
+    future[0] = FUTURE_VERSION;
+    match decode_blob::<T>(&future, version, column).err() {
+        Some(HistoryError::UnknownBlobVersion {
+            column: c,
+            version: FUTURE_VERSION,
+        }) => assert_eq!(c, column),
+        other => panic!("{column} future: {other:?}"),
+    }
+
+    match decode_blob::<T>(&[version, 0xFF, 0xFF, 0xFF], version, column).err() {
+        Some(HistoryError::BlobDecode {
+            column: c,
+            version: v,
+            ..
+        }) => assert_eq!((c, v), (column, version)),
+        other => panic!("{column} corrupt: {other:?}"),
+    }
+}
+
+fn container(i: usize) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("c{i}"),
+        "name": format!("app-{i}"),
+        "cpuPercent": 1.5,
+        "memoryUsageBytes": 1024,
+        "memoryLimitBytes": 4096,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn every_column_decodes_current_and_legacy_and_rejects_unknown_versions() {
+    // Seven entries: the legacy payload starts with byte 7, above every known version.
+    let containers: Vec<ContainerStats> = (0..7).map(container).collect();
+    check_column(&containers, BLOB_VERSION, "container_data");
+    check_column(&StorageStats::default(), BLOB_VERSION, "storage_data");
+    check_column(&NetworkStats::default(), BLOB_VERSION, "network_data");
+    let dynamic = SystemStatsDynamic {
+        uptime_secs: 3600,
+        process_count: 200,
+        ..Default::default()
+    };
+    check_column(&dynamic, BLOB_VERSION_SYSTEM_DYNAMIC, "system_data");
+    let full = merge_system_info(None, &dynamic);
+    check_column(&full, BLOB_VERSION, "system_data");
+    check_column(
+        &CpuStats {
+            usage_percent: 42.0,
+            ..Default::default()
+        },
+        BLOB_VERSION,
+        "cpu_data",
+    );
+    check_column(
+        &RamStats {
+            total: 8192,
+            used: 2048,
+            ..Default::default()
+        },
+        BLOB_VERSION,
+        "ram_data",
+    );
+    let gpus: Vec<GpuStats> = (0..7).map(|_| GpuStats::default()).collect();
+    check_column(&gpus, BLOB_VERSION, "gpu_data");
+    let smart: Vec<SmartHealth> = (0..7).map(|_| SmartHealth::default()).collect();
+    check_column(&smart, BLOB_VERSION, "smart_data");
+}
+
+fn snapshot(ts: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 30.0,
+            ..Default::default()
+        },
+        containers: vec![container(0)],
+        ..common::snapshot(ts)
+    }
+}
+
+/// Raw rows at 1000..=4000 (ids 1..=4) and one 60 s aggregated row at 0, then `updates` applied
+/// on a separate connection.
+async fn repo_with(dir: &TempDir, updates: &[&'static str]) -> HistoryRepo {
+    let config = DatabaseConfig {
+        path: dir.path().join("v.db").display().to_string(),
+        ..Default::default()
+    };
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    let snaps: Vec<_> = (1..=4).map(|i| snapshot(i * 1_000)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let agg = homeserver::history_repo::aggregation::aggregate_snapshots(&snaps, 0, 60).unwrap();
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+    let pool = SqlitePool::connect(&format!("sqlite:{}", config.path))
+        .await
+        .unwrap();
+    for &sql in updates {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+    repo
+}
+
+#[tokio::test]
+async fn readers_skip_rows_with_unknown_versions() {
+    let dir = TempDir::new().unwrap();
+    let repo = repo_with(
+        &dir,
+        &[
+            "UPDATE system_history SET cpu_data = X'09000000000000F03F' WHERE id = 2",
+            "UPDATE system_history SET system_data = X'FF' WHERE id = 3",
+            "UPDATE system_history_aggregated SET container_data = X'0A00'",
+        ],
+    )
+    .await;
+    let before = blob::unknown_version_total();
+
+    let stamps =
+        |snaps: &[FullSystemSnapshot]| snaps.iter().map(|s| s.timestamp).collect::<Vec<_>>();
+    let history = repo.get_history(0, 10_000, 1, 0).await.unwrap();
+    assert_eq!(stamps(&history), [1_000, 4_000]);
+    assert!(history.iter().all(|s| s.cpu.usage_percent == 30.0));
+    let (_, recent) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(stamps(&recent), [1_000, 4_000]);
+    let since = repo.get_snapshots_since(0, 10).await.unwrap();
+    assert_eq!(stamps(&since), [1_000, 4_000]);
+    let range = repo
+        .get_raw_snapshots_by_time_range(0, 10_000)
+        .await
+        .unwrap();
+    assert_eq!(stamps(&range), [1_000, 4_000]);
+
+    // The aggregated row is left out of aggregated reads, and of history older than raw.
+    let aggregated = repo
+        .get_aggregated_snapshots_by_time_range(0, 10_000, 60)
+        .await
+        .unwrap();
+    assert!(aggregated.is_empty());
+    let older = repo.get_history(0, 10_000, 60, 10_000).await.unwrap();
+    assert!(older.is_empty(), "{older:?}");
+
+    assert!(blob::unknown_version_total() >= before + 4);
+}
+
+#[tokio::test]
+async fn corrupt_and_legacy_blobs_keep_their_fallbacks() {
+    let dir = TempDir::new().unwrap();
+    // A known version that does not decode, and an empty (pre-v3) cpu column.
+    let repo = repo_with(
+        &dir,
+        &[
+            "UPDATE system_history SET cpu_data = X'01FF', cpu_load = 55.0 WHERE id = 1",
+            "UPDATE system_history SET cpu_data = NULL, cpu_load = 66.0 WHERE id = 2",
+            "UPDATE system_history SET container_data = X'01FFFF' WHERE id = 3",
+        ],
+    )
+    .await;
+    let history = repo.get_history(0, 10_000, 1, 0).await.unwrap();
+    assert_eq!(history.len(), 4, "no row skipped");
+    assert_eq!(history[0].cpu.usage_percent, 55.0, "scalar fallback");
+    assert_eq!(history[1].cpu.usage_percent, 66.0, "legacy fallback");
+    assert!(history[2].containers.is_empty());
+    assert_eq!(history[3].containers.len(), 1);
+}
@@ -0,0 +1,179 @@
+// Concurrent load on one HistoryRepo: saves, node pushes, aggregation passes, WAL checkpoints and
+// history reads together must not surface SQLITE_BUSY / SQLITE_LOCKED (writes share the single
+// writer connection, reads retry).
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{DownsampleMode, HistoryError, HistoryRepo};
+use homeserver::models::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tempfile::TempDir;
+use tokio::task::JoinSet;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        max_pool_size: 4,
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.5,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        chunk_buckets: 30,
+        raw_retention_hours: 1,
+        ..AggregationWorkerConfig::from_database(&DatabaseConfig::default())
+    }
+}
+
+/// Every error a task saw, so one failure does not hide the others.
+type Failures = Vec<String>;
+
+fn record<T>(failures: &mut Failures, what: &str, result: Result<T, HistoryError>) {
+    if let Err(e) = result {
+        failures.push(format!("{what}: {e} (busy: {})", e.is_busy()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_saves_aggregation_and_reads_surface_no_busy_errors() {
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(connect(&dir).await);
+    // Six hours of backlog ending 2 h ago, for aggregation to work through.
+    let start = ((common::now_ms() - 8 * MS_PER_HOUR) / MS_PER_MINUTE) * MS_PER_MINUTE;
+    let backlog: Vec<_> = (0..6 * 60 * 6)
+        .map(|i| snapshot(start + i * 10_000))
+        .collect();
+    repo.save_snapshots(&backlog, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut tasks = JoinSet::new();
+    for writer in 0..3 {
+        let (repo, stop) = (repo.clone(), stop.clone());
+        tasks.spawn(async move {
+            let mut failures = Failures::new();
+            let mut ts = common::now_ms() + writer;
+            while !stop.load(Ordering::Relaxed) {
+                let batch: Vec<_> = (0..20).map(|i| snapshot(ts + i * 3)).collect();
+                ts += 100;
+                record(
+                    &mut failures,
+                    "save",
+                    repo.save_snapshots(&batch, &SystemInfo::default()).await,
+                );
+                record(
+                    &mut failures,
+                    "push",
+                    repo.save_node_snapshots(&format!("edge-{writer}"), &batch)
+                        .await,
+                );
+            }
+            failures
+        });
+    }
+    for _ in 0..4 {
+        let (repo, stop) = (repo.clone(), stop.clone());
+        tasks.spawn(async move {
+            let mut failures = Failures::new();
+            while !stop.load(Ordering::Relaxed) {
+                let to = common::now_ms() + MS_PER_HOUR;
+                record(
+                    &mut failures,
+                    "history",
+                    repo.get_history_points_bounded(
+                        start,
+                        to,
+                        60,
+                        to - 3 * MS_PER_HOUR,
+                        DownsampleMode::Average,
+                        1000,
+                    )
+                    .await,
+                );
+                record(
+                    &mut failures,
+                    "since",
+                    repo.get_snapshots_since(common::now_ms() - MS_PER_MINUTE, 100)
+                        .await,
+                );
+                record(&mut failures, "db_stats", repo.db_stats().await);
+            }
+            failures
+        });
+    }
+    {
+        let repo = repo.clone();
+        tasks.spawn(async move {
+            let mut failures = Failures::new();
+            for _ in 0..20 {
+                record(&mut failures, "checkpoint", repo.wal_checkpoint().await);
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            failures
+        });
+    }
+
+    let config = worker_config();
+    let mut passes = 0;
+    loop {
+        let report = run_one_tick(repo.as_ref(), &config)
+            .await
+            .expect("aggregation pass");
+        passes += 1;
+        if !report.more_work_remaining {
+            break;
+        }
+    }
+    // Keep the load going a little past the backlog.
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    stop.store(true, Ordering::Relaxed);
+
+    let failures: Failures = tasks.join_all().await.into_iter().flatten().collect();
+    assert!(failures.is_empty(), "{failures:#?}");
+    assert!(passes >= 1);
+    let aggregated = repo
+        .get_aggregated_snapshots_by_time_range(start, start + 6 * MS_PER_HOUR, 60)
+        .await
+        .unwrap();
+    assert_eq!(aggregated.len(), 6 * 60);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn many_concurrent_writers_all_commit() {
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(connect(&dir).await);
+    let base = common::now_ms();
+    let mut tasks = JoinSet::new();
+    for writer in 0..16 {
+        let repo = repo.clone();
+        tasks.spawn(async move {
+            let batch: Vec<_> = (0..50)
+                .map(|i| snapshot(base + writer * 1_000 + i))
+                .collect();
+            repo.save_snapshots(&batch, &SystemInfo::default()).await
+        });
+    }
+    for result in tasks.join_all().await {
+        result.unwrap();
+    }
+    let (_, rows) = repo.get_recent_snapshots(10_000).await.unwrap();
+    assert_eq!(rows.len(), 16 * 50);
+}
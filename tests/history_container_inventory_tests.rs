@@ -0,0 +1,243 @@
+// container_inventory: first / last sighting per container id, upserted by each flush, renames
+// kept in previousNames, the gone filter of /api/containers/inventory and the retention prune.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryRepo, TopContainerMetric};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const BASE_TS: u64 = 1_700_000_000_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn container(id: &str, name: &str, image: &str, state: ContainerState) -> ContainerStats {
+    let mut c: ContainerStats = serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": name,
+        "image": image,
+        "cpuPercent": 1.0,
+        "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0,
+        "state": "running",
+    }))
+    .unwrap();
+    c.state = state;
+    c
+}
+
+fn running(id: &str, name: &str) -> ContainerStats {
+    container(id, name, "nginx:1.27", ContainerState::Running)
+}
+
+fn snapshot(ts: u64, containers: Vec<ContainerStats>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers,
+        ..common::snapshot(ts)
+    }
+}
+
+async fn repo(retention_days: u32) -> (AppConfig, Arc<HistoryRepo>, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    config.database.container_inventory_retention_days = retention_days;
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    (config, repo, dir)
+}
+
+async fn flush(repo: &HistoryRepo, snapshots: &[FullSystemSnapshot]) {
+    repo.save_snapshots(snapshots, &SystemInfo::default())
+        .await
+        .unwrap();
+}
+
+async fn entry(repo: &HistoryRepo, id: &str) -> ContainerInventoryEntry {
+    let all = repo.get_container_inventory(true).await.unwrap();
+    all.into_iter().find(|e| e.container_id == id).unwrap()
+}
+
+#[tokio::test]
+async fn flushes_widen_the_lifetime_and_keep_the_latest_state() {
+    let (_, repo, _dir) = repo(365).await;
+    flush(
+        &repo,
+        &[
+            snapshot(BASE_TS, vec![running("a", "web")]),
+            snapshot(BASE_TS + 1000, vec![running("a", "web")]),
+        ],
+    )
+    .await;
+    let mut restarting = container("a", "web", "nginx:1.28", ContainerState::Restarting);
+    flush(&repo, &[snapshot(BASE_TS + 5000, vec![restarting.clone()])]).await;
+
+    let a = entry(&repo, "a").await;
+    assert_eq!((a.first_seen, a.last_seen), (BASE_TS, BASE_TS + 5000));
+    assert_eq!(a.image, "nginx:1.28");
+    assert_eq!(a.last_state, ContainerState::Restarting);
+    assert!(a.previous_names.is_empty());
+    assert!(!a.gone);
+
+    // A late batch of older rows only widens the lifetime; the newest sighting still wins.
+    restarting.state = ContainerState::Exited;
+    restarting.image.clear();
+    flush(&repo, &[snapshot(BASE_TS - 1000, vec![restarting])]).await;
+    let a = entry(&repo, "a").await;
+    assert_eq!(
+        (a.first_seen, a.last_seen),
+        (BASE_TS - 1000, BASE_TS + 5000)
+    );
+    assert_eq!(a.image, "nginx:1.28");
+    assert_eq!(a.last_state, ContainerState::Restarting);
+}
+
+#[tokio::test]
+async fn renames_keep_the_last_name_and_record_earlier_ones() {
+    let (_, repo, _dir) = repo(365).await;
+    flush(&repo, &[snapshot(BASE_TS, vec![running("a", "web")])]).await;
+    // Renamed between flushes, then twice inside one batch (back to an earlier name).
+    flush(
+        &repo,
+        &[snapshot(BASE_TS + 1000, vec![running("a", "web-old")])],
+    )
+    .await;
+    flush(
+        &repo,
+        &[
+            snapshot(BASE_TS + 2000, vec![running("a", "frontend")]),
+            snapshot(BASE_TS + 3000, vec![running("a", "web")]),
+        ],
+    )
+    .await;
+
+    let a = entry(&repo, "a").await;
+    assert_eq!(a.name, "web");
+    assert_eq!(a.previous_names, ["web", "web-old", "frontend"]);
+    assert_eq!(a.first_seen, BASE_TS);
+    assert_eq!(a.last_seen, BASE_TS + 3000);
+}
+
+#[tokio::test]
+async fn gone_containers_are_listed_only_when_asked_for() {
+    let (config, repo, _dir) = repo(365).await;
+    flush(
+        &repo,
+        &[
+            snapshot(
+                BASE_TS,
+                vec![running("old", "batch-job"), running("b", "db")],
+            ),
+            snapshot(
+                BASE_TS + 1000,
+                vec![running("b", "db"), running("a", "web")],
+            ),
+        ],
+    )
+    .await;
+
+    let present = repo.get_container_inventory(false).await.unwrap();
+    let names: Vec<_> = present.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["db", "web"]);
+
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo.clone(),
+        Default::default(),
+    ));
+    let body: serde_json::Value = server
+        .get("/api/containers/inventory?include_gone=true")
+        .await
+        .json();
+    let names: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["db", "web", "batch-job"]);
+    assert_eq!(body[2]["gone"], true);
+    assert_eq!(body[2]["lastSeen"], BASE_TS);
+    assert_eq!(body[2]["lastState"], "running");
+    assert_eq!(body[2]["image"], "nginx:1.27");
+
+    let body: Vec<ContainerInventoryEntry> = server.get("/api/containers/inventory").await.json();
+    assert_eq!(body, present);
+    server
+        .get("/api/containers/inventory?include_gone=maybe")
+        .await
+        .assert_status_bad_request();
+
+    // Once every container has stopped, the empty latest snapshot makes them all gone.
+    flush(&repo, &[snapshot(BASE_TS + 2000, vec![])]).await;
+    assert!(
+        repo.get_container_inventory(false)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn top_containers_use_the_inventory_name() {
+    let (_, repo, _dir) = repo(365).await;
+    flush(&repo, &[snapshot(BASE_TS, vec![running("a", "web")])]).await;
+    flush(
+        &repo,
+        &[snapshot(BASE_TS + 1000, vec![running("a", "frontend")])],
+    )
+    .await;
+    let top = repo
+        .get_top_containers(0, BASE_TS as i64 + 1, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    // Only the first row is in range, but the container is listed under its current name.
+    assert_eq!(top[0].name, "frontend");
+}
+
+#[tokio::test]
+async fn rows_unseen_past_their_retention_are_pruned() {
+    let (_, repo, _dir) = repo(30).await;
+    let recent_ts = BASE_TS + 40 * MS_PER_DAY as u64;
+    flush(
+        &repo,
+        &[
+            snapshot(BASE_TS, vec![running("old", "batch-job")]),
+            snapshot(recent_ts, vec![running("a", "web")]),
+        ],
+    )
+    .await;
+    let removed = repo
+        .prune_container_inventory(recent_ts as i64 + 30 * MS_PER_DAY - 1)
+        .await
+        .unwrap();
+    assert_eq!(removed, 1);
+    let all = repo.get_container_inventory(true).await.unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].container_id, "a");
+}
+
+#[test]
+fn inventory_retention_defaults_to_a_year_and_is_validated() {
+    assert_eq!(
+        AppConfig::default()
+            .database
+            .container_inventory_retention_days,
+        365
+    );
+    let err = AppConfig::load_from_str("[database]\ncontainer_inventory_retention_days = 0\n")
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("container_inventory_retention_days"),
+        "{err}"
+    );
+}
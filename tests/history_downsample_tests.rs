@@ -0,0 +1,125 @@
+// Raw downsampling for /api/history: bucket averages vs last sample on spiky synthetic data.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{DownsampleMode, HistoryRepo};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+fn container(cpu_percent: f64, network_rx_bytes: u64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": cpu_percent,
+        "memoryUsageBytes": 100,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+        "networkRxBytes": network_rx_bytes,
+    }))
+    .unwrap()
+}
+
+/// One sample per second: idle at 0 % CPU / 1000 B used, with a 100 % / 4000 B spike at `spike_s`.
+fn spiky(seconds: u64, spike_s: u64) -> Vec<FullSystemSnapshot> {
+    (0..seconds)
+        .map(|i| {
+            let spike = i == spike_s;
+            FullSystemSnapshot {
+                cpu: CpuStats {
+                    usage_percent: if spike { 100.0 } else { 0.0 },
+                    core_usages: vec![if spike { 100.0 } else { 0.0 }, 0.0],
+                    ..Default::default()
+                },
+                ram: RamStats {
+                    total: 8000,
+                    used: if spike { 4000 } else { 1000 },
+                    ..Default::default()
+                },
+                containers: vec![container(if spike { 90.0 } else { 0.0 }, 1000 * (i + 1))],
+                ..common::snapshot(i * 1000)
+            }
+        })
+        .collect()
+}
+
+async fn repo_with(snaps: &[FullSystemSnapshot]) -> (HistoryRepo, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    repo.save_snapshots(snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    (repo, dir)
+}
+
+async fn points(repo: &HistoryRepo, mode: DownsampleMode) -> Vec<HistoryPoint> {
+    repo.get_history_points(0, 60_000, 30, 0, mode)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn average_keeps_spike_that_last_drops() {
+    // Spike mid-bucket: `Last` shows an idle machine, `Average` shows the spike's share.
+    let (repo, _dir) = repo_with(&spiky(60, 10)).await;
+
+    let last = points(&repo, DownsampleMode::Last).await;
+    assert_eq!(last.len(), 2);
+    assert_eq!(last[0].snapshot.cpu.usage_percent, 0.0);
+    assert_eq!(last[0].snapshot.ram.used, 1000);
+
+    let avg = points(&repo, DownsampleMode::Average).await;
+    assert_eq!(avg.len(), 2);
+    let s = &avg[0].snapshot;
+    assert!((s.cpu.usage_percent - 100.0 / 30.0).abs() < 1e-9);
+    assert_eq!(s.ram.used, (29 * 1000 + 4000) / 30);
+    assert_eq!(s.ram.available, 8000 - s.ram.used);
+    assert!((s.cpu.core_usages[0] - 100.0 / 30.0).abs() < 1e-9);
+    assert_eq!(avg[1].snapshot.cpu.usage_percent, 0.0);
+
+    // Envelope still exposes the spike either way.
+    let env = avg[0].envelope.as_ref().unwrap();
+    assert_eq!((env.cpu_load_min, env.cpu_load_max), (0.0, 100.0));
+    assert_eq!(avg[0].envelope, last[0].envelope);
+}
+
+#[tokio::test]
+async fn average_does_not_exaggerate_spike_on_last_sample() {
+    // Spike on the bucket's final second: `Last` reports 100 % for the whole 30 s.
+    let (repo, _dir) = repo_with(&spiky(30, 29)).await;
+
+    let last = points(&repo, DownsampleMode::Last).await;
+    assert_eq!(last[0].snapshot.cpu.usage_percent, 100.0);
+
+    let avg = points(&repo, DownsampleMode::Average).await;
+    assert!((avg[0].snapshot.cpu.usage_percent - 100.0 / 30.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn average_means_container_gauges_and_keeps_last_counters() {
+    let (repo, _dir) = repo_with(&spiky(30, 29)).await;
+    let avg = points(&repo, DownsampleMode::Average).await;
+
+    let c = &avg[0].snapshot.containers[0];
+    assert!((c.cpu_percent - 3.0).abs() < 1e-9);
+    // Docker counters are cumulative: the bucket reports the final reading, not a sum.
+    assert_eq!(c.network_rx_bytes, 30_000);
+}
+
+#[tokio::test]
+async fn averaged_points_are_stamped_with_bucket_start() {
+    let (repo, _dir) = repo_with(&spiky(60, 0)).await;
+    let avg = points(&repo, DownsampleMode::Average).await;
+    let stamps: Vec<u64> = avg.iter().map(|p| p.snapshot.timestamp).collect();
+    assert_eq!(stamps, vec![0, 30_000]);
+
+    let last = points(&repo, DownsampleMode::Last).await;
+    let stamps: Vec<u64> = last.iter().map(|p| p.snapshot.timestamp).collect();
+    assert_eq!(stamps, vec![29_000, 59_000]);
+}
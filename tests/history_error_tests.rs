@@ -0,0 +1,145 @@
+// HistoryError: forced failures produce the specific variant callers match on.
+
+mod common;
+
+use homeserver::history_repo::blob::{BLOB_VERSION, decode_blob};
+use homeserver::history_repo::{CURRENT_SCHEMA_VERSION, HistoryError, HistoryRepo};
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+async fn connect(path: &Path) -> HistoryRepo {
+    HistoryRepo::connect(&common::database(path)).await.unwrap()
+}
+
+async fn raw_exec(path: &Path, sql: &'static str) {
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    sqlx::query(sql).execute(&pool).await.unwrap();
+    pool.close().await;
+}
+
+async fn initialized(dir: &TempDir) -> (HistoryRepo, PathBuf) {
+    let path = dir.path().join("h.db");
+    let repo = connect(&path).await;
+    repo.init().await.unwrap();
+    (repo, path)
+}
+
+#[tokio::test]
+async fn corrupt_system_info_is_blob_decode() {
+    let dir = TempDir::new().unwrap();
+    let (repo, path) = initialized(&dir).await;
+    raw_exec(
+        &path,
+        "INSERT OR REPLACE INTO system_info (id, data) VALUES (1, X'FFFFFFFFFFFF')",
+    )
+    .await;
+
+    let err = repo.get_stored_system_info().await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            HistoryError::BlobDecode {
+                column: "system_info.data",
+                version: 0,
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+    // Callers that need the stored info see the same variant.
+    assert!(matches!(
+        repo.get_recent_snapshots(10).await.unwrap_err(),
+        HistoryError::BlobDecode { .. }
+    ));
+}
+
+#[test]
+fn decode_blob_reports_column_and_version() {
+    let err = decode_blob::<NetworkStats>(&[BLOB_VERSION, 0xFF], BLOB_VERSION, "network_data")
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            HistoryError::BlobDecode {
+                column: "network_data",
+                version: BLOB_VERSION,
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn closed_pool_is_unavailable() {
+    let dir = TempDir::new().unwrap();
+    let (repo, _path) = initialized(&dir).await;
+    repo.close().await;
+
+    let err = repo.ping().await.unwrap_err();
+    assert!(
+        matches!(err, HistoryError::Sqlx(sqlx::Error::PoolClosed)),
+        "{err:?}"
+    );
+    assert!(err.is_unavailable());
+    assert!(repo.db_stats().await.unwrap_err().is_unavailable());
+}
+
+#[tokio::test]
+async fn newer_schema_is_refused_not_purged() {
+    let dir = TempDir::new().unwrap();
+    let (repo, path) = initialized(&dir).await;
+    let snapshot = common::snapshot(1_000);
+    repo.save_snapshots(&[snapshot], &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.close().await;
+    raw_exec(
+        &path,
+        "UPDATE schema_version SET value = value + 1 WHERE key = 'schema'",
+    )
+    .await;
+
+    let err = connect(&path).await.init().await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            HistoryError::SchemaTooNew { found, supported }
+                if found == i64::from(CURRENT_SCHEMA_VERSION) + 1
+                    && supported == CURRENT_SCHEMA_VERSION
+        ),
+        "{err:?}"
+    );
+    assert!(!err.is_unavailable());
+    // The data was left alone for the newer build.
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_history")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+}
+
+#[tokio::test]
+async fn invalid_arguments_and_exports() {
+    let dir = TempDir::new().unwrap();
+    let (repo, _path) = initialized(&dir).await;
+    assert!(matches!(
+        repo.pragma_i64("journal_mode").await.unwrap_err(),
+        HistoryError::InvalidArgument(_)
+    ));
+    assert!(matches!(
+        repo.import(&b"not an export"[..]).await.unwrap_err(),
+        HistoryError::InvalidExport(_)
+    ));
+    assert!(matches!(
+        repo.import(&b"HS"[..]).await.unwrap_err(),
+        HistoryError::InvalidExport(_)
+    ));
+}
@@ -0,0 +1,245 @@
+// History export/import: portable round-trip (probes and sensors included), timestamp dedup,
+// range selection, format checks, version 1 files.
+
+mod common;
+
+use homeserver::history_repo::{EXPORT_FORMAT_VERSION, HistoryRepo, ImportReport};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const HOUR_MS: u64 = 3_600_000;
+
+async fn connect(dir: &TempDir, name: &str) -> HistoryRepo {
+    common::connect(&dir.path().join(name)).await
+}
+
+fn info() -> SystemInfo {
+    SystemInfo {
+        os_family: "Linux".into(),
+        system_model: "raspberry-pi".into(),
+        processor_name: "Cortex-A76".into(),
+        ..Default::default()
+    }
+}
+
+/// One sample every 10 s over `hours`, with non-trivial floats and a container per row.
+fn snapshots(hours: u64) -> Vec<FullSystemSnapshot> {
+    (0..hours * 360)
+        .map(|i| FullSystemSnapshot {
+            cpu: CpuStats {
+                usage_percent: (i as f64 / 3.0) % 100.0,
+                temperature: 40.0 + (i as f64).sqrt(),
+                core_usages: vec![i as f64 * 0.1, 1.0 / (i as f64 + 1.0)],
+                ..Default::default()
+            },
+            ram: RamStats {
+                total: 8 << 30,
+                used: (1 << 30) + i * 4096,
+                ..Default::default()
+            },
+            containers: vec![
+                serde_json::from_value(serde_json::json!({
+                    "id": "c1",
+                    "name": "web",
+                    "cpuPercent": i as f64 * 0.7,
+                    "memoryUsageBytes": 100 + i,
+                    "memoryLimitBytes": 1000,
+                    "state": "running",
+                    "networkRxBytes": i * 1500,
+                }))
+                .unwrap(),
+            ],
+            system: SystemStatsDynamic {
+                uptime_secs: i * 10,
+                load_avg_1: i as f64 / 7.0,
+                ..Default::default()
+            },
+            ..common::snapshot(1_700_000_000_000 + i * 10_000)
+        })
+        .collect()
+}
+
+async fn all_raw_bytes(repo: &HistoryRepo) -> Vec<Vec<u8>> {
+    repo.get_raw_snapshots_by_time_range(0, i64::MAX)
+        .await
+        .unwrap()
+        .iter()
+        .map(|s| wincode::serialize(s).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn export_import_round_trip_is_byte_equivalent() {
+    let dir = TempDir::new().unwrap();
+    let source = connect(&dir, "pi.db").await;
+    // 3 hours spans several export windows and more than one import batch.
+    source.save_snapshots(&snapshots(3), &info()).await.unwrap();
+
+    let mut file = Vec::new();
+    let written = source.export_range(0, i64::MAX, &mut file).await.unwrap();
+    assert_eq!(written, 3 * 360);
+
+    let target = connect(&dir, "nuc.db").await;
+    let report = target.import(file.as_slice()).await.unwrap();
+    assert_eq!(
+        report,
+        ImportReport {
+            imported: 3 * 360,
+            skipped_duplicates: 0
+        }
+    );
+    assert_eq!(all_raw_bytes(&target).await, all_raw_bytes(&source).await);
+    let stored = target.get_stored_system_info().await.unwrap().unwrap();
+    assert_eq!(stored.system_model, "raspberry-pi");
+
+    // Exporting from a read-only open (as the CLI does) gives the same file.
+    let path = dir.path().join("pi.db");
+    let read_only = HistoryRepo::connect_read_only(path.to_str().unwrap())
+        .await
+        .unwrap();
+    let mut again = Vec::new();
+    read_only
+        .export_range(0, i64::MAX, &mut again)
+        .await
+        .unwrap();
+    assert_eq!(again, file);
+
+    // Importing the same file again adds nothing.
+    let report = target.import(file.as_slice()).await.unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.skipped_duplicates, 3 * 360);
+    assert_eq!(target.db_stats().await.unwrap().raw_rows, 3 * 360);
+}
+
+#[tokio::test]
+async fn import_keeps_existing_rows_and_system_info() {
+    let dir = TempDir::new().unwrap();
+    let source = connect(&dir, "pi.db").await;
+    let snaps = snapshots(1);
+    source.save_snapshots(&snaps, &info()).await.unwrap();
+
+    // Export only the second half-hour.
+    let half = (snaps[0].timestamp + HOUR_MS / 2) as i64;
+    let mut file = Vec::new();
+    let written = source
+        .export_range(half, i64::MAX, &mut file)
+        .await
+        .unwrap();
+    assert_eq!(written, 180);
+
+    // The target already has the last 60 of those rows, recorded by another host.
+    let target = connect(&dir, "nuc.db").await;
+    let nuc = SystemInfo {
+        system_model: "nuc".into(),
+        ..info()
+    };
+    target.save_snapshots(&snaps[300..], &nuc).await.unwrap();
+    let report = target.import(file.as_slice()).await.unwrap();
+    assert_eq!(report.imported, 120);
+    assert_eq!(report.skipped_duplicates, 60);
+    assert_eq!(target.db_stats().await.unwrap().raw_rows, 180);
+    let stored = target.get_stored_system_info().await.unwrap().unwrap();
+    assert_eq!(stored.system_model, "nuc");
+}
+
+#[tokio::test]
+async fn import_rejects_invalid_files() {
+    let dir = TempDir::new().unwrap();
+    let source = connect(&dir, "pi.db").await;
+    source
+        .save_snapshots(&snapshots(1)[..10], &info())
+        .await
+        .unwrap();
+    let mut file = Vec::new();
+    source.export_range(0, i64::MAX, &mut file).await.unwrap();
+    let target = connect(&dir, "nuc.db").await;
+
+    let err = target.import(&b"SQLite format 3"[..]).await.unwrap_err();
+    assert!(err.to_string().contains("not a history export"), "{err}");
+
+    let mut wrong_version = file.clone();
+    wrong_version[4..8].copy_from_slice(&(EXPORT_FORMAT_VERSION + 1).to_le_bytes());
+    let err = target.import(wrong_version.as_slice()).await.unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported export format"),
+        "{err}"
+    );
+
+    let err = target.import(&file[..file.len() - 3]).await.unwrap_err();
+    assert!(err.to_string().contains("truncated"), "{err}");
+    assert_eq!(target.db_stats().await.unwrap().raw_rows, 0);
+
+    // A file with no snapshots (empty range) imports cleanly.
+    let mut empty = Vec::new();
+    assert_eq!(source.export_range(0, 1, &mut empty).await.unwrap(), 0);
+    assert_eq!(target.import(empty.as_slice()).await.unwrap().imported, 0);
+}
+
+#[tokio::test]
+async fn probe_and_sensor_data_survive_export() {
+    let dir = TempDir::new().unwrap();
+    let source = connect(&dir, "pi.db").await;
+    let snaps: Vec<_> = snapshots(1)[..20]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| FullSystemSnapshot {
+            probes: vec![ProbeStat {
+                name: "router".into(),
+                target: "192.168.1.1".into(),
+                method: "icmp".into(),
+                rtt_ms: Some(1.5 + i as f64),
+                loss_percent: 5.0,
+                samples: 20,
+                ..Default::default()
+            }],
+            sensors: vec![SensorStat {
+                id: "k10temp-pci-00c3/Tctl".into(),
+                name: "k10temp".into(),
+                label: "Tctl".into(),
+                kind: SensorKind::Temp,
+                value: 50.0 + i as f64 / 3.0,
+                unit: "°C".into(),
+                max: None,
+            }],
+            ..s.clone()
+        })
+        .collect();
+    source.save_snapshots(&snaps, &info()).await.unwrap();
+    let mut file = Vec::new();
+    source.export_range(0, i64::MAX, &mut file).await.unwrap();
+
+    let target = connect(&dir, "nuc.db").await;
+    assert_eq!(target.import(file.as_slice()).await.unwrap().imported, 20);
+    let imported = target
+        .get_raw_snapshots_by_time_range(0, i64::MAX)
+        .await
+        .unwrap();
+    assert_eq!(imported.len(), 20);
+    for (got, want) in imported.iter().zip(&snaps) {
+        assert_eq!(got.probes, want.probes);
+        assert_eq!(got.sensors, want.sensors);
+    }
+}
+
+#[tokio::test]
+async fn version_1_files_still_import() {
+    let snaps = &snapshots(1)[..10];
+    let mut file = b"HSHX".to_vec();
+    file.extend_from_slice(&1u32.to_le_bytes());
+    file.push(0);
+    for snapshot in snaps {
+        let bytes = wincode::serialize(snapshot).unwrap();
+        file.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        file.extend_from_slice(&bytes);
+    }
+
+    let dir = TempDir::new().unwrap();
+    let target = connect(&dir, "nuc.db").await;
+    let report = target.import(file.as_slice()).await.unwrap();
+    assert_eq!(report.imported, 10);
+    let want: Vec<_> = snaps
+        .iter()
+        .map(|s| wincode::serialize(s).unwrap())
+        .collect();
+    assert_eq!(all_raw_bytes(&target).await, want);
+}
@@ -0,0 +1,165 @@
+// History writer flush metrics: batch sizes, bytes and time since the last success recorded by
+// the writer, failed flushes counted, and the values served on /api/stats and /metrics.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::worker::{
+    FlushMetrics, HistoryWriterConfig, OverflowPolicy, spawn_history_writer, write_queue,
+};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn snapshot(ts: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 7.0,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+async fn repo(dir: &TempDir) -> Arc<HistoryRepo> {
+    Arc::new(common::connect(&dir.path().join("h.db")).await)
+}
+
+/// Feed `count` snapshots through a writer flushing every `flush_rate`, then close the queue and
+/// wait for the final flush.
+async fn drive_writer(repo: Arc<HistoryRepo>, flush_rate: u64, count: u64) -> Arc<FlushMetrics> {
+    let (tx, rx) = write_queue(64, OverflowPolicy::DropNew, Default::default());
+    let flush = Arc::new(FlushMetrics::default());
+    let handle = spawn_history_writer(
+        rx,
+        repo,
+        Arc::new(SystemInfo::default()),
+        HistoryWriterConfig {
+            flush_rate,
+            flush_interval_secs: 3600,
+            flush_warn_ms: 0,
+            persist_gpu: true,
+            persist_smart: true,
+            min_snapshot_timestamp_ms: 0,
+            max_future_skew_minutes: 5,
+            min_free_bytes: 0,
+        },
+        Arc::new(AtomicU64::new(0)),
+        flush.clone(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    for i in 0..count {
+        tx.send(snapshot(1_700_000_000_000 + i * 1000));
+    }
+    drop(tx);
+    handle.await.unwrap();
+    flush
+}
+
+#[tokio::test]
+async fn writer_records_each_flush() {
+    let dir = TempDir::new().unwrap();
+    let repo = repo(&dir).await;
+    let flush = drive_writer(repo.clone(), 2, 5).await;
+
+    let stats = flush.snapshot();
+    // Batches of at most 2 at the flush rate (the interval's first, immediate tick may flush a
+    // single one), then the final flush of what is left.
+    assert!(stats.flushes_total >= 3, "{stats:?}");
+    assert_eq!(stats.failures_total, 0);
+    assert_eq!(stats.slow_flushes_total, 0, "flush_warn_ms = 0 never warns");
+    assert_eq!(stats.max_batch, 2);
+    assert!((stats.mean_batch * stats.flushes_total as f64 - 5.0).abs() < 1e-9);
+    assert!(stats.last_bytes > 0);
+    assert!(stats.bytes_total > stats.last_bytes);
+    assert!(stats.max_ms >= stats.last_ms);
+    assert!(stats.since_last_success_ms.unwrap() < 10_000);
+
+    let (_, saved) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(saved.len(), 5);
+}
+
+#[tokio::test]
+async fn failed_flush_is_counted_and_success_time_stays_unset() {
+    let dir = TempDir::new().unwrap();
+    let repo = repo(&dir).await;
+    repo.close().await;
+    let flush = drive_writer(repo, 1, 2).await;
+
+    let stats = flush.snapshot();
+    // The failed batch stays buffered, so each later flush (and the final one) fails again.
+    assert!(stats.failures_total >= 3, "{stats:?}");
+    assert_eq!(stats.flushes_total, 0);
+    assert_eq!(stats.bytes_total, 0);
+    assert!(stats.since_last_success_ms.is_none());
+}
+
+#[test]
+fn slow_flushes_and_maxima() {
+    let flush = FlushMetrics::default();
+    flush.record(Duration::from_millis(1500), 10, 4000, true);
+    flush.record(Duration::from_millis(20), 4, 1000, false);
+    let stats = flush.snapshot();
+    assert_eq!(stats.slow_flushes_total, 1);
+    assert_eq!((stats.last_ms, stats.max_ms), (20, 1500));
+    assert_eq!(stats.mean_ms, 760.0);
+    assert_eq!((stats.last_batch, stats.max_batch), (4, 10));
+    assert_eq!((stats.last_bytes, stats.bytes_total), (1000, 5000));
+}
+
+#[tokio::test]
+async fn stats_and_prometheus_expose_flush_metrics() {
+    let dir = TempDir::new().unwrap();
+    let metrics = ServiceMetrics::default();
+    let mut config = AppConfig::default();
+    config.server.node_name = "nas".into();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo(&dir).await,
+        metrics.clone(),
+    ));
+
+    let text = server.get("/metrics").await.text();
+    assert!(
+        text.contains("homeserver_history_flushes_total{node=\"nas\"} 0\n"),
+        "{text}"
+    );
+    assert!(!text.contains("homeserver_history_flush_since_success_seconds"));
+
+    metrics
+        .history_flush
+        .record(Duration::from_millis(250), 12, 3072, false);
+    metrics.history_flush.record_failure();
+
+    let body: serde_json::Value = server.get("/api/stats").await.json();
+    let flush = &body["historyFlush"];
+    assert_eq!(flush["flushesTotal"], 1);
+    assert_eq!(flush["failuresTotal"], 1);
+    assert_eq!(flush["lastMs"], 250);
+    assert_eq!(flush["lastBatch"], 12);
+    assert_eq!(flush["bytesTotal"], 3072);
+    assert!(flush["sinceLastSuccessMs"].is_u64());
+
+    let text = server.get("/metrics").await.text();
+    for line in [
+        "homeserver_history_flushes_total{node=\"nas\"} 1\n",
+        "homeserver_history_flush_failures_total{node=\"nas\"} 1\n",
+        "homeserver_history_flush_bytes_total{node=\"nas\"} 3072\n",
+        "homeserver_history_flush_last_seconds{node=\"nas\"} 0.25\n",
+        "homeserver_history_flush_last_batch_snapshots{node=\"nas\"} 12\n",
+        "# TYPE homeserver_history_flush_since_success_seconds gauge\n",
+    ] {
+        assert!(text.contains(line), "missing {line:?} in {text}");
+    }
+}
@@ -0,0 +1,121 @@
+// Corruption handling: quick_check, the startup integrity check and recover_on_corruption.
+
+mod common;
+
+use common::snapshot;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{HistoryError, HistoryRepo};
+use homeserver::models::*;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn config(path: &Path) -> DatabaseConfig {
+    DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    }
+}
+
+/// Write some rows, close cleanly (WAL folded into the file), then cut the file in half.
+async fn corrupt_database(path: &Path) {
+    let repo = HistoryRepo::connect(&config(path)).await.unwrap();
+    repo.init().await.unwrap();
+    let snaps: Vec<_> = (0..500).map(|i| snapshot(i * 1000)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.close().await;
+
+    let len = std::fs::metadata(path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_len(len / 2)
+        .unwrap();
+}
+
+/// Database files in `dir`, ignoring `-wal` / `-shm` companions.
+fn db_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| !name.ends_with("-wal") && !name.ends_with("-shm"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn healthy_database_has_no_problems() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&config(&dir.path().join("h.db")))
+        .await
+        .unwrap();
+    repo.init().await.unwrap();
+    assert!(repo.integrity_check().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn corrupt_database_refuses_to_start() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    corrupt_database(&path).await;
+
+    let err = match HistoryRepo::connect(&config(&path)).await {
+        Ok(_) => panic!("corrupt database opened"),
+        Err(e) => e,
+    };
+    assert!(matches!(err, HistoryError::Corrupt(_)), "{err:?}");
+    assert!(err.is_corruption());
+    assert!(err.to_string().contains("recover_on_corruption"));
+    // Nothing was moved: the operator decides what to do with the file.
+    assert_eq!(db_files(dir.path()), ["h.db"]);
+}
+
+#[tokio::test]
+async fn recover_on_corruption_moves_file_aside_and_starts_fresh() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    corrupt_database(&path).await;
+    let corrupt_len = std::fs::metadata(&path).unwrap().len();
+
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        recover_on_corruption: true,
+        ..config(&path)
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    assert!(repo.integrity_check().await.unwrap().is_empty());
+    assert_eq!(repo.db_stats().await.unwrap().raw_rows, 0);
+
+    let files = db_files(dir.path());
+    assert_eq!(files.len(), 2, "{files:?}");
+    assert_eq!(files[0], "h.db");
+    assert!(files[1].starts_with("h.db.corrupt-"), "{files:?}");
+    assert_eq!(
+        std::fs::metadata(dir.path().join(&files[1])).unwrap().len(),
+        corrupt_len
+    );
+}
+
+#[tokio::test]
+async fn startup_check_can_be_disabled() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    corrupt_database(&path).await;
+
+    // Without the check the open itself still trips over the damaged file, but as a plain
+    // database error and without touching it.
+    let result = HistoryRepo::connect(&DatabaseConfig {
+        integrity_check_on_start: false,
+        recover_on_corruption: true,
+        ..config(&path)
+    })
+    .await;
+    if let Err(e) = &result {
+        assert!(!matches!(e, HistoryError::Corrupt(_)), "{e:?}");
+    }
+    assert_eq!(db_files(dir.path()), ["h.db"]);
+}
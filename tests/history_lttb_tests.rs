@@ -0,0 +1,221 @@
+// Largest-Triangle-Three-Buckets: lttb_indices against reference outputs, lttb_points keeping
+// CPU and RAM aligned, NaN samples, and /api/history?downsample=lttb&points=.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryRepo, lttb_indices, lttb_points};
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+/// Spiky series; the expected indices below come from the original reference implementation
+/// (Steinarsson, 2013).
+const SPIKY: [f64; 20] = [
+    0.0, 1.0, 0.0, 5.0, 0.0, 2.0, 8.0, 1.0, 0.0, 3.0, 0.0, 0.0, 9.0, 1.0, 0.0, 2.0, 4.0, 0.0, 7.0,
+    1.0,
+];
+
+fn xs(n: usize, step: f64) -> Vec<f64> {
+    (0..n).map(|i| i as f64 * step).collect()
+}
+
+#[test]
+fn lttb_indices_match_reference_outputs() {
+    let x = xs(SPIKY.len(), 1000.0);
+    assert_eq!(lttb_indices(&x, &SPIKY, 5), [0, 6, 8, 18, 19]);
+    assert_eq!(lttb_indices(&x, &SPIKY, 8), [0, 3, 6, 7, 12, 13, 18, 19]);
+    assert_eq!(
+        lttb_indices(&x, &SPIKY, 10),
+        [0, 2, 3, 6, 7, 11, 12, 14, 18, 19]
+    );
+
+    let sine: Vec<f64> = (0..100)
+        .map(|i| (i as f64 / 5.0).sin() * 50.0 + 50.0)
+        .collect();
+    assert_eq!(
+        lttb_indices(&xs(100, 60_000.0), &sine, 10),
+        [0, 8, 22, 36, 41, 55, 70, 85, 90, 99]
+    );
+}
+
+#[test]
+fn lttb_indices_keep_everything_below_threshold() {
+    let x = xs(SPIKY.len(), 1.0);
+    assert_eq!(lttb_indices(&x, &SPIKY, 20), (0..20).collect::<Vec<_>>());
+    assert_eq!(lttb_indices(&x, &SPIKY, 50).len(), 20);
+    assert_eq!(lttb_indices(&x, &SPIKY, 2).len(), 20);
+    assert!(lttb_indices(&[], &[], 10).is_empty());
+}
+
+fn snapshot(ts: u64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            // RAM encodes the sample's own timestamp, to check it travels with the CPU value.
+            used: ts,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+#[test]
+fn lttb_points_keeps_ram_aligned_with_selected_cpu_points() {
+    let points: Vec<HistoryPoint> = SPIKY
+        .iter()
+        .enumerate()
+        .map(|(i, &cpu)| HistoryPoint {
+            snapshot: snapshot(i as u64 * 1000, cpu),
+            envelope: None,
+        })
+        .collect();
+    let out = lttb_points(points.clone(), 8);
+    let stamps: Vec<u64> = out.iter().map(|p| p.snapshot.timestamp).collect();
+    assert_eq!(
+        stamps,
+        [0, 3000, 6000, 7000, 12_000, 13_000, 18_000, 19_000]
+    );
+    for p in &out {
+        assert_eq!(p.snapshot.ram.used, p.snapshot.timestamp);
+    }
+    // The spikes survive.
+    assert!(out.iter().any(|p| p.snapshot.cpu.usage_percent == 9.0));
+
+    assert_eq!(lttb_points(points, 100).len(), 20);
+}
+
+#[test]
+fn lttb_keeps_threshold_points_across_nan_samples() {
+    let mut ys = SPIKY;
+    ys[4] = f64::NAN;
+    ys[5] = f64::NAN;
+    ys[12] = f64::NAN;
+    let x = xs(ys.len(), 1000.0);
+    for threshold in [5, 8, 10] {
+        let kept = lttb_indices(&x, &ys, threshold);
+        assert_eq!(kept.len(), threshold, "{kept:?}");
+        assert!(kept.windows(2).all(|w| w[0] < w[1]), "{kept:?}");
+    }
+
+    // Every area of a bucket NaN: the points after it are still kept.
+    let points: Vec<HistoryPoint> = ys
+        .iter()
+        .enumerate()
+        .map(|(i, &cpu)| HistoryPoint {
+            snapshot: snapshot(i as u64 * 1000, if i >= 6 { f64::NAN } else { cpu }),
+            envelope: None,
+        })
+        .collect();
+    let out = lttb_points(points, 8);
+    assert_eq!(out.len(), 8);
+    assert!(
+        out.windows(2)
+            .all(|w| w[0].snapshot.timestamp < w[1].snapshot.timestamp)
+    );
+    assert_eq!(out.last().unwrap().snapshot.timestamp, 19_000);
+}
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+async fn test_app_with_repo() -> (axum::Router, TempDir, Arc<HistoryRepo>) {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path.to_str().unwrap());
+    let config = AppConfig::load_from_str(&config_str).unwrap();
+    let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        history_repo.clone(),
+        Default::default(),
+    );
+    (app, dir, history_repo)
+}
+
+#[tokio::test]
+async fn api_history_lttb_thins_to_points() {
+    let (app, _dir, repo) = test_app_with_repo().await;
+    let from: u64 = 1_700_000_000_000;
+    let snaps: Vec<_> = (0..200u64)
+        .map(|i| {
+            snapshot(
+                from + i * 1000,
+                if i.is_multiple_of(37) { 90.0 } else { 5.0 },
+            )
+        })
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(app);
+    let url = |query: &str| {
+        format!(
+            "/api/history?from={from}&to={}&resolution=1s{query}",
+            from + 200_000
+        )
+    };
+
+    let all: Vec<FullSystemSnapshot> = server.get(&url("")).await.json();
+    assert_eq!(all.len(), 200);
+    let thinned: Vec<FullSystemSnapshot> =
+        server.get(&url("&downsample=lttb&points=20")).await.json();
+    assert_eq!(thinned.len(), 20);
+    assert_eq!(thinned[0].timestamp, from);
+    assert_eq!(thinned[19].timestamp, from + 199_000);
+    assert!(thinned.iter().any(|s| s.cpu.usage_percent == 90.0));
+}
+
+#[tokio::test]
+async fn api_history_lttb_validates_points() {
+    let (app, _dir, _) = test_app_with_repo().await;
+    let server = TestServer::new(app);
+    for query in [
+        "downsample=lttb",
+        "downsample=lttb&points=9",
+        "downsample=lttb&points=5001",
+        "points=100",
+        "downsample=last&points=100",
+    ] {
+        server
+            .get(&format!("/api/history?{query}"))
+            .await
+            .assert_status_bad_request();
+    }
+    for query in ["downsample=lttb&points=10", "downsample=lttb&points=5000"] {
+        server
+            .get(&format!("/api/history?{query}"))
+            .await
+            .assert_status_ok();
+    }
+}
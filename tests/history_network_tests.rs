@@ -0,0 +1,199 @@
+// Per-interface network history: rate averaging in the aggregation path and /api/history/network.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::{aggregate_aggregated_snapshots, aggregate_snapshots};
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+// A multiple of 30 s, so the 30 s buckets start on sample boundaries.
+const BASE_TS: u64 = 1_700_000_010_000;
+
+fn iface(name: &str, rx_rate: f64, tx_rate: f64, bytes_recv: u64) -> InterfaceStat {
+    InterfaceStat {
+        name: name.to_string(),
+        display_name: name.to_string(),
+        mac_address: String::new(),
+        ipv4: vec![],
+        ipv6: vec![],
+        bytes_sent: bytes_recv / 2,
+        bytes_recv,
+        packets_sent: 0,
+        packets_recv: 0,
+        speed: 0,
+        received_bytes_per_sec: rx_rate,
+        transmitted_bytes_per_sec: tx_rate,
+        is_up: true,
+        packets_recv_per_sec: 0.0,
+        packets_sent_per_sec: 0.0,
+    }
+}
+
+fn snapshot(ts: u64, interfaces: Vec<InterfaceStat>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        network: NetworkStats::new(interfaces),
+        ..common::snapshot(ts)
+    }
+}
+
+#[test]
+fn aggregate_snapshots_averages_rates_per_interface() {
+    let snapshots = vec![
+        snapshot(
+            60_000,
+            vec![
+                iface("eth0", 100.0, 10.0, 1000),
+                iface("wlan0", 0.0, 0.0, 5),
+            ],
+        ),
+        snapshot(61_000, vec![iface("eth0", 300.0, 30.0, 2000)]),
+    ];
+    let out = aggregate_snapshots(&snapshots, 60_000, 60).unwrap();
+    let ifaces = &out.network.interfaces;
+    // Last sample's interfaces first, then ones that disappeared mid-bucket.
+    let names: Vec<&str> = ifaces.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["eth0", "wlan0"]);
+    assert_eq!(ifaces[0].received_bytes_per_sec, 200.0);
+    assert_eq!(ifaces[0].transmitted_bytes_per_sec, 20.0);
+    // Cumulative counters keep the last reading.
+    assert_eq!(ifaces[0].bytes_recv, 2000);
+    assert_eq!(ifaces[1].bytes_recv, 5);
+}
+
+#[test]
+fn aggregate_aggregated_snapshots_weights_rates_by_sample_count() {
+    let fine = |ts: i64, rate: f64, count: i64| {
+        let s = snapshot(ts as u64, vec![iface("eth0", rate, rate, 0)]);
+        let mut agg = aggregate_snapshots(&[s], ts, 60).unwrap();
+        agg.sample_count = count;
+        agg
+    };
+    let aggs = vec![fine(0, 100.0, 3), fine(60_000, 500.0, 1)];
+    let out = aggregate_aggregated_snapshots(&aggs, 0, 300).unwrap();
+    let eth0 = &out.network.interfaces[0];
+    assert_eq!(eth0.received_bytes_per_sec, 200.0);
+    assert_eq!(eth0.transmitted_bytes_per_sec, 200.0);
+}
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+async fn test_app() -> (axum::Router, Arc<HistoryRepo>, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path.to_str().unwrap());
+    let config = AppConfig::load_from_str(&config_str).unwrap();
+    let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        history_repo.clone(),
+        Default::default(),
+    );
+    (app, history_repo, dir)
+}
+
+#[tokio::test]
+async fn api_history_network_returns_interface_series() {
+    let (app, repo, _dir) = test_app().await;
+    // Two 30 s buckets; eth0 runs at 100 then 300 B/s, wlan0 only appears in the second.
+    let snaps: Vec<_> = (0..60u64)
+        .map(|i| {
+            let rate = if i < 30 { 100.0 } else { 300.0 };
+            let mut ifaces = vec![iface("eth0", rate, rate / 10.0, 1000 * (i + 1))];
+            if i >= 30 {
+                ifaces.push(iface("wlan0", 1.0, 1.0, 1));
+            }
+            snapshot(BASE_TS + i * 1000, ifaces)
+        })
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(app);
+
+    let url = |name: &str| {
+        format!(
+            "/api/history/network?iface={name}&from={}&to={}&resolution=30s",
+            BASE_TS,
+            BASE_TS + 60_000
+        )
+    };
+    let response = server.get(&url("eth0")).await;
+    response.assert_status_ok();
+    let points: Vec<InterfaceHistoryPoint> = response.json();
+    assert_eq!(
+        points,
+        vec![
+            InterfaceHistoryPoint {
+                timestamp: BASE_TS,
+                rx_bytes_per_sec: 100.0,
+                tx_bytes_per_sec: 10.0,
+                rx_bytes: 30_000,
+                tx_bytes: 15_000,
+            },
+            InterfaceHistoryPoint {
+                timestamp: BASE_TS + 30_000,
+                rx_bytes_per_sec: 300.0,
+                tx_bytes_per_sec: 30.0,
+                rx_bytes: 60_000,
+                tx_bytes: 30_000,
+            },
+        ]
+    );
+    let json: serde_json::Value = response.json();
+    assert!(json[0].get("rxBytesPerSec").is_some());
+
+    // Buckets without the interface are skipped; unknown interfaces give an empty series.
+    let points: Vec<InterfaceHistoryPoint> = server.get(&url("wlan0")).await.json();
+    assert_eq!(points.len(), 1);
+    let points: Vec<InterfaceHistoryPoint> = server.get(&url("eth9")).await.json();
+    assert!(points.is_empty());
+}
+
+#[tokio::test]
+async fn api_history_network_validates_query() {
+    let (app, _, _dir) = test_app().await;
+    let server = TestServer::new(app);
+    server
+        .get("/api/history/network")
+        .await
+        .assert_status_bad_request();
+    server
+        .get("/api/history/network?iface=eth0&from=100&to=50")
+        .await
+        .assert_status_bad_request();
+    server
+        .get("/api/history/network?iface=eth0")
+        .await
+        .assert_status_ok();
+}
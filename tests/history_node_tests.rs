@@ -0,0 +1,62 @@
+// The node name in the stored `system_info` row: written with it, and rows (and JSON) from before
+// `server.node_name` existed still load with an empty one. Config and routes: node_name_tests.rs.
+
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::SystemInfo;
+use sqlx::sqlite::SqlitePool;
+use tempfile::TempDir;
+
+/// `SystemInfo` as wincode-encoded before `node_name` was added.
+#[derive(wincode::SchemaWrite)]
+struct SystemInfoV1 {
+    os_family: String,
+    os_manufacturer: String,
+    os_version: String,
+    system_manufacturer: String,
+    system_model: String,
+    processor_name: String,
+}
+
+#[tokio::test]
+async fn node_name_is_stored_and_old_rows_still_load() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default().database;
+    config.path = dir.path().join("node.db").display().to_string();
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    let info = SystemInfo {
+        os_family: "Linux".into(),
+        node_name: "nas-1".into(),
+        ..Default::default()
+    };
+    repo.save_system_info(&info).await.unwrap();
+    assert_eq!(repo.get_stored_system_info().await.unwrap(), Some(info));
+
+    let legacy = wincode::serialize(&SystemInfoV1 {
+        os_family: "Linux".into(),
+        os_manufacturer: String::new(),
+        os_version: "12".into(),
+        system_manufacturer: String::new(),
+        system_model: "old-host".into(),
+        processor_name: "cpu".into(),
+    })
+    .unwrap();
+    let db_path = dir.path().join("node.db");
+    let pool = SqlitePool::connect(&format!("sqlite:{}", db_path.display()))
+        .await
+        .unwrap();
+    sqlx::query("UPDATE system_info SET data = $1 WHERE id = 1")
+        .bind(legacy)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let stored = repo.get_stored_system_info().await.unwrap().unwrap();
+    assert_eq!(stored.system_model, "old-host");
+    assert_eq!(stored.os_version, "12");
+    assert_eq!(stored.node_name, "");
+
+    // JSON without the field (older clients, serde round trips) defaults it too.
+    let parsed: SystemInfo = serde_json::from_str(r#"{"osFamily":"Linux","osManufacturer":"","osVersion":"","systemManufacturer":"","systemModel":"","processorName":""}"#).unwrap();
+    assert_eq!(parsed.node_name, "");
+}
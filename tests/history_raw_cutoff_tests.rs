@@ -0,0 +1,141 @@
+// Raw/aggregated boundary for /api/history: anchored on the newest raw row (not on `to`), so
+// past windows come from the aggregated tiers and rows written before downtime stay visible.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+
+fn snapshot(ts: i64, usage_percent: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+async fn setup(dir: &TempDir) -> (AppConfig, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("cutoff.db").display().to_string();
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    (config, Arc::new(repo))
+}
+
+fn server(config: AppConfig, repo: Arc<HistoryRepo>) -> TestServer {
+    let (tx, _) = broadcast::channel(4);
+    TestServer::new(routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo,
+        Default::default(),
+    ))
+}
+
+async fn history(server: &TestServer, from: i64, to: i64) -> Vec<FullSystemSnapshot> {
+    let response = server
+        .get(&format!("/api/history?from={from}&to={to}&resolution=1m"))
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn cutoff_follows_the_newest_local_raw_row() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = setup(&dir).await;
+    let now = common::now_ms();
+    assert_eq!(repo.get_max_raw_created_at().await.unwrap(), None);
+    assert_eq!(
+        repo.history_raw_cutoff(now, 1).await.unwrap(),
+        now - MS_PER_HOUR
+    );
+
+    let info = SystemInfo::default();
+    let newest = now - 3 * MS_PER_HOUR;
+    let raw = [snapshot(newest - MS_PER_MINUTE, 1.0), snapshot(newest, 1.0)];
+    repo.save_snapshots(&raw, &info).await.unwrap();
+    // Pushed rows from another instance do not move the boundary.
+    repo.save_node_snapshots("edge", &[snapshot(now, 1.0)])
+        .await
+        .unwrap();
+    assert_eq!(repo.get_max_raw_created_at().await.unwrap(), Some(newest));
+    assert_eq!(
+        repo.history_raw_cutoff(now, 2).await.unwrap(),
+        newest - 2 * MS_PER_HOUR
+    );
+
+    // A row from the future (clock skew) is capped at now.
+    repo.save_snapshots(&[snapshot(now + MS_PER_HOUR, 1.0)], &info)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.history_raw_cutoff(now, 1).await.unwrap(),
+        now - MS_PER_HOUR
+    );
+}
+
+#[tokio::test]
+async fn past_window_is_served_entirely_from_aggregated_rows() {
+    let dir = TempDir::new().unwrap();
+    let (config, repo) = setup(&dir).await;
+    let now = common::now_ms();
+    // Yesterday's hour, long since rolled into the 60 s tier; current raw rows only.
+    let from = (now - 26 * MS_PER_HOUR) / MS_PER_MINUTE * MS_PER_MINUTE;
+    let to = from + MS_PER_HOUR;
+    for i in 0..60 {
+        let ts = from + i * MS_PER_MINUTE;
+        let agg = aggregate_snapshots(&[snapshot(ts, 20.0)], ts, 60).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+    repo.save_snapshots(
+        &[snapshot(now - MS_PER_MINUTE, 90.0)],
+        &SystemInfo::default(),
+    )
+    .await
+    .unwrap();
+
+    let points = history(&server(config, repo), from, to).await;
+    assert_eq!(points.len(), 60, "the whole window, last hour included");
+    assert_eq!(points.first().unwrap().timestamp, from as u64);
+    assert_eq!(
+        points.last().unwrap().timestamp,
+        (to - MS_PER_MINUTE) as u64
+    );
+    assert!(points.iter().all(|p| p.cpu.usage_percent == 20.0));
+}
+
+#[tokio::test]
+async fn raw_rows_from_before_downtime_stay_visible() {
+    let dir = TempDir::new().unwrap();
+    let (config, repo) = setup(&dir).await;
+    let now = common::now_ms();
+    // The server stopped three hours ago; no aggregation pass rolled these rows up.
+    let stopped = now - 3 * MS_PER_HOUR;
+    let raw: Vec<_> = (0..10)
+        .map(|i| snapshot(stopped - i * MS_PER_MINUTE, 40.0))
+        .collect();
+    repo.save_snapshots(&raw, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let points = history(&server(config, repo), now - 4 * MS_PER_HOUR, now).await;
+    assert_eq!(points.len(), 10);
+    assert!(points.iter().all(|p| p.cpu.usage_percent == 40.0));
+}
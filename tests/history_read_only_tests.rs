@@ -0,0 +1,93 @@
+// HistoryRepo::connect_read_only: reads work, writes fail cleanly, missing files and directories
+// are rejected without creating anything.
+
+mod common;
+
+use common::snapshot;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{HistoryError, HistoryRepo};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+async fn writer(path: &str) -> HistoryRepo {
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    repo.save_snapshots(&[snapshot(1000), snapshot(2000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    repo
+}
+
+#[tokio::test]
+async fn reads_succeed_beside_a_live_writer_and_after_it_closes() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let path = path.to_str().unwrap();
+    let live = writer(path).await;
+
+    let ro = HistoryRepo::connect_read_only(path).await.unwrap();
+    let (_, snaps) = ro.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(snaps.len(), 2);
+    ro.close().await;
+
+    live.close().await;
+    let ro = HistoryRepo::connect_read_only(path).await.unwrap();
+    assert_eq!(ro.get_recent_snapshots(10).await.unwrap().1.len(), 2);
+}
+
+#[tokio::test]
+async fn writes_fail_and_leave_the_data_untouched() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let path = path.to_str().unwrap();
+    writer(path).await.close().await;
+
+    let ro = HistoryRepo::connect_read_only(path).await.unwrap();
+    let err = ro
+        .save_snapshots(&[snapshot(3000)], &SystemInfo::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, HistoryError::Sqlx(_)), "{err}");
+    assert!(err.to_string().contains("readonly"), "{err}");
+    assert_eq!(ro.get_recent_snapshots(10).await.unwrap().1.len(), 2);
+}
+
+#[tokio::test]
+async fn missing_file_is_reported_and_not_created() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("typo.db");
+    let err = HistoryRepo::connect_read_only(path.to_str().unwrap())
+        .await
+        .err()
+        .unwrap();
+    match &err {
+        HistoryError::Io(e) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        other => panic!("expected Io(NotFound), got {other}"),
+    }
+    assert!(err.to_string().contains("typo.db"));
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn directories_are_rejected_by_both_open_modes() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().to_str().unwrap();
+
+    let err = HistoryRepo::connect_read_only(path).await.err().unwrap();
+    assert!(matches!(err, HistoryError::InvalidArgument(_)), "{err}");
+
+    let err = HistoryRepo::connect(&DatabaseConfig {
+        path: path.into(),
+        ..Default::default()
+    })
+    .await
+    .err()
+    .unwrap();
+    assert!(matches!(err, HistoryError::InvalidArgument(_)), "{err}");
+    assert!(err.to_string().contains("is a directory"));
+}
@@ -1,6 +1,9 @@
 // HistoryRepo aggregated-snapshot tests: table creation, range queries, save, delete.
 // Split from history_repo_tests.rs to keep files under 300 lines.
 
+mod common;
+
+use homeserver::config::DatabaseConfig;
 use homeserver::history_repo::HistoryRepo;
 use homeserver::models::*;
 use tempfile::TempDir;
@@ -13,45 +16,27 @@ fn minimal_system_info() -> SystemInfo {
         system_manufacturer: String::new(),
         system_model: String::new(),
         processor_name: String::new(),
+        ..Default::default()
     }
 }
 
 fn minimal_snapshot(timestamp: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp,
         cpu: CpuStats {
             model: "test".into(),
             physical_cores: 1,
             logical_cores: 2,
             usage_percent: 10.0,
-            temperature: 0.0,
-            core_usages: vec![],
+            ..Default::default()
         },
         ram: RamStats {
             total: 1024,
             used: 512,
             available: 512,
             usage_percent: 50.0,
-            swap_total: 0,
-            swap_used: 0,
-            swap_free: 0,
-        },
-        containers: vec![],
-        storage: StorageStats {
-            partitions: vec![],
-            disks: vec![],
-        },
-        network: NetworkStats { interfaces: vec![] },
-        system: SystemStatsDynamic {
-            uptime_secs: 0,
-            process_count: 0,
-            thread_count: 0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
+            ..Default::default()
         },
-        gpus: vec![],
-        smart: vec![],
+        ..common::snapshot(timestamp)
     }
 }
 
@@ -59,12 +44,21 @@ fn minimal_aggregated_snapshot(created_at: i64) -> AggregatedSnapshot {
     AggregatedSnapshot {
         created_at,
         resolution_seconds: 60,
+        sample_count: 1,
         cpu_load_avg: 10.0,
         cpu_load_min: 5.0,
         cpu_load_max: 15.0,
         memory_used_avg: 512,
         memory_used_min: 256,
         memory_used_max: 768,
+        cpu_load_p95: None,
+        memory_used_p95: None,
+        memory_total_avg: 8192,
+        memory_total_min: 8192,
+        memory_total_max: 8192,
+        cpu_temperature_avg: 55.0,
+        cpu_temperature_min: 50.0,
+        cpu_temperature_max: 60.0,
         cpu: CpuStats {
             model: "agg-cpu".into(),
             physical_cores: 4,
@@ -87,7 +81,7 @@ fn minimal_aggregated_snapshot(created_at: i64) -> AggregatedSnapshot {
             partitions: vec![],
             disks: vec![],
         },
-        network: NetworkStats { interfaces: vec![] },
+        network: NetworkStats::default(),
         system: SystemStatsDynamic {
             uptime_secs: 0,
             process_count: 0,
@@ -98,15 +92,20 @@ fn minimal_aggregated_snapshot(created_at: i64) -> AggregatedSnapshot {
         },
         gpus: vec![],
         smart: vec![],
+        probes: vec![],
+        sensors: vec![],
     }
 }
 
 #[tokio::test]
 async fn history_repo_init_creates_aggregated_table() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
     repo.save_aggregated_snapshot(&minimal_aggregated_snapshot(60_000))
         .await
@@ -116,9 +115,12 @@ async fn history_repo_init_creates_aggregated_table() {
 #[tokio::test]
 async fn history_repo_get_raw_snapshots_by_time_range() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     repo.save_snapshots(
@@ -151,9 +153,12 @@ async fn history_repo_get_raw_snapshots_by_time_range() {
 #[tokio::test]
 async fn history_repo_get_min_raw_created_at_before() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     assert!(
@@ -183,9 +188,12 @@ async fn history_repo_get_min_raw_created_at_before() {
 #[tokio::test]
 async fn history_repo_delete_raw_range() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     repo.save_snapshots(
@@ -210,9 +218,12 @@ async fn history_repo_delete_raw_range() {
 #[tokio::test]
 async fn history_repo_get_aggregated_snapshots_by_time_range() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     for ts in [60_000, 120_000, 180_000] {
@@ -239,9 +250,12 @@ async fn history_repo_get_aggregated_snapshots_by_time_range() {
 #[tokio::test]
 async fn history_repo_delete_aggregated_range() {
     let dir = TempDir::new().unwrap();
-    let repo = HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     for ts in [60_000i64, 120_000, 180_000] {
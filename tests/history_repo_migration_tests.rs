@@ -1,7 +1,10 @@
-// Schema migration tests: a pre-v3 database must be upgraded in place (additive ALTER),
+// Schema migration tests: older databases must be upgraded in place (additive ALTER),
 // preserving existing rows, and new writes must carry full CPU/RAM detail.
 
-use homeserver::history_repo::HistoryRepo;
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{CURRENT_SCHEMA_VERSION, HistoryRepo};
 use homeserver::models::*;
 use sqlx::Row;
 use sqlx::sqlite::SqliteConnectOptions;
@@ -90,7 +93,12 @@ async fn migrates_v2_to_v3_preserving_rows() {
     make_v2_db(path_str).await;
 
     // Connecting + init must migrate (not purge) and bump the schema version to current.
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     // Verify the schema version was advanced and the new columns exist.
@@ -100,8 +108,12 @@ async fn migrates_v2_to_v3_preserving_rows() {
         .fetch_one(&pool)
         .await
         .unwrap();
-    assert_eq!(version, 5, "schema version migrated to current");
-    // v3 cpu_data/ram_data, v4 gpu_data, v5 smart_data columns now present on both tables.
+    assert_eq!(
+        version,
+        i64::from(CURRENT_SCHEMA_VERSION),
+        "schema version migrated to current"
+    );
+    // v3 cpu_data/ram_data, v4 gpu_data, v5 smart_data, v6 scalar columns now present on both tables.
     sqlx::query("SELECT cpu_data, ram_data, gpu_data, smart_data FROM system_history LIMIT 1")
         .fetch_optional(&pool)
         .await
@@ -112,6 +124,38 @@ async fn migrates_v2_to_v3_preserving_rows() {
     .fetch_optional(&pool)
     .await
     .expect("v3/v4/v5 columns exist on aggregated table");
+    sqlx::query("SELECT cpu_load_p95, memory_used_p95 FROM system_history_aggregated LIMIT 1")
+        .fetch_optional(&pool)
+        .await
+        .expect("v7 percentile columns exist on aggregated table");
+    sqlx::query("SELECT storage_hash, network_hash FROM system_history LIMIT 1")
+        .fetch_optional(&pool)
+        .await
+        .expect("v8 blob_store hash columns exist on system_history");
+    sqlx::query("SELECT sample_count FROM system_history_aggregated LIMIT 1")
+        .fetch_optional(&pool)
+        .await
+        .expect("v10 sample_count column exists on aggregated table");
+    let local: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_history WHERE node IS NULL")
+        .fetch_one(&pool)
+        .await
+        .expect("v11 node column exists on system_history");
+    assert_eq!(local, 1, "existing rows stay local (node NULL)");
+    for table in ["container_history", "container_inventory"] {
+        let sql = format!("SELECT COUNT(*) FROM {table}");
+        let rows: i64 = sqlx::query_scalar(sqlx::AssertSqlSafe(sql))
+            .fetch_one(&pool)
+            .await
+            .expect("v12 / v13 container tables exist");
+        assert_eq!(rows, 0, "{table} starts empty");
+    }
+    for table in ["system_history", "system_history_aggregated"] {
+        let sql = format!("SELECT probe_data FROM {table} LIMIT 1");
+        sqlx::query(sqlx::AssertSqlSafe(sql))
+            .fetch_optional(&pool)
+            .await
+            .expect("v14 probe_data column exists");
+    }
 
     // The legacy row survived the migration (no purge).
     let count: i64 = sqlx::query("SELECT COUNT(*) AS c FROM system_history")
@@ -127,15 +171,22 @@ async fn migrates_v2_to_v3_preserving_rows() {
     assert_eq!(snaps.len(), 1);
     assert!((snaps[0].cpu.usage_percent - 42.5).abs() < 0.001);
     assert_eq!(snaps[0].ram.used, 123456);
+    // No memory_total / cpu_temperature recorded for legacy rows: they default to 0.
+    assert_eq!(snaps[0].ram.total, 0);
+    assert_eq!(snaps[0].ram.usage_percent, 0.0);
+    assert_eq!(snaps[0].cpu.temperature, 0.0);
 }
 
 #[tokio::test]
 async fn new_writes_persist_full_cpu_ram_detail() {
     let dir = TempDir::new().unwrap();
     let path = dir.path().join("fresh.db");
-    let repo = HistoryRepo::connect(path.to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let info = SystemInfo {
@@ -145,9 +196,9 @@ async fn new_writes_persist_full_cpu_ram_detail() {
         system_manufacturer: String::new(),
         system_model: String::new(),
         processor_name: String::new(),
+        ..Default::default()
     };
     let snap = FullSystemSnapshot {
-        timestamp: 1700000001000,
         cpu: CpuStats {
             model: "Test CPU".into(),
             physical_cores: 4,
@@ -165,12 +216,7 @@ async fn new_writes_persist_full_cpu_ram_detail() {
             swap_used: 100,
             swap_free: 1_900,
         },
-        containers: vec![],
-        storage: StorageStats::default(),
-        network: NetworkStats::default(),
-        system: SystemStatsDynamic::default(),
-        gpus: vec![],
-        smart: vec![],
+        ..common::snapshot(1700000001000)
     };
     repo.save_snapshots(std::slice::from_ref(&snap), &info)
         .await
@@ -188,3 +234,44 @@ async fn new_writes_persist_full_cpu_ram_detail() {
     assert_eq!(ram.swap_total, 2_000);
     assert_eq!(ram.available, 12_000);
 }
+
+#[tokio::test]
+async fn v14_service_events_gain_name_and_detail() {
+    let dir = TempDir::new().unwrap();
+    let config = DatabaseConfig {
+        path: dir.path().join("v14.db").to_str().unwrap().into(),
+        ..Default::default()
+    };
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    repo.record_service_start(1).await.unwrap();
+    repo.close().await;
+    // Back to the v14 shape: no name / detail columns, no sensor_data.
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", config.path))
+        .await
+        .unwrap();
+    for stmt in [
+        "ALTER TABLE service_events DROP COLUMN name",
+        "ALTER TABLE service_events DROP COLUMN detail",
+        "ALTER TABLE system_history DROP COLUMN sensor_data",
+        "ALTER TABLE system_history_aggregated DROP COLUMN sensor_data",
+        "UPDATE schema_version SET value = 14 WHERE key = 'schema'",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    repo.record_check_event(2, ServiceEventKind::CheckDown, "a", Some("timed out"))
+        .await
+        .unwrap();
+    let events = repo.get_service_events(10).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].name.as_deref(), Some("a"));
+    assert_eq!(events[0].detail.as_deref(), Some("timed out"));
+    assert_eq!(
+        (events[1].event, events[1].name.as_ref()),
+        (ServiceEventKind::Started, None)
+    );
+}
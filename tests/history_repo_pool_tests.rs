@@ -0,0 +1,53 @@
+// HistoryRepo::connect: max_pool_size is honoured and pragmas are applied per connection
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+async fn connect(dir: &TempDir, max_pool_size: u32) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        max_pool_size,
+        cache_size_kib: 1024,
+        mmap_size_bytes: 1 << 20,
+        temp_store: "memory".into(),
+        ..common::database(&dir.path().join("h.db"))
+    })
+    .await
+}
+
+#[tokio::test]
+async fn single_connection_pool_serves_concurrent_callers() {
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(connect(&dir, 1).await);
+
+    let mut handles = Vec::new();
+    for _ in 0..8 {
+        let repo = Arc::clone(&repo);
+        handles.push(tokio::spawn(async move {
+            repo.get_recent_snapshots(5).await.map(|(_, s)| s.len())
+        }));
+    }
+    for h in handles {
+        assert_eq!(h.await.unwrap().unwrap(), 0);
+    }
+}
+
+#[tokio::test]
+async fn pool_size_follows_config() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 3).await;
+    assert_eq!(repo.pool_max_connections(), 3);
+}
+
+#[tokio::test]
+async fn pragmas_applied_on_connect() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir, 2).await;
+    assert_eq!(repo.pragma_i64("cache_size").await.unwrap(), -1024);
+    assert_eq!(repo.pragma_i64("mmap_size").await.unwrap(), 1 << 20);
+    // temp_store: 0 = default, 1 = file, 2 = memory
+    assert_eq!(repo.pragma_i64("temp_store").await.unwrap(), 2);
+}
@@ -0,0 +1,98 @@
+// memory_total / cpu_temperature columns: written on save, used to rebuild RAM/CPU when blobs are absent
+
+mod common;
+
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tempfile::TempDir;
+
+async fn raw_pool(path: &Path) -> SqlitePool {
+    SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap()
+}
+
+fn snapshot(ts: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 20.0,
+            temperature: 58.5,
+            ..Default::default()
+        },
+        ram: RamStats {
+            total: 16_000,
+            used: 4_000,
+            available: 12_000,
+            usage_percent: 25.0,
+            ..Default::default()
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+#[tokio::test]
+async fn save_snapshots_writes_scalar_columns() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+    repo.save_snapshots(&[snapshot(1_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let pool = raw_pool(&path).await;
+    let (total, temp): (i64, f64) =
+        sqlx::query_as("SELECT memory_total, cpu_temperature FROM system_history")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    pool.close().await;
+    assert_eq!(total, 16_000);
+    assert_eq!(temp, 58.5);
+}
+
+#[tokio::test]
+async fn rows_without_blobs_use_scalar_columns() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+
+    let pool = raw_pool(&path).await;
+    let stub: &[u8] = &[1u8];
+    sqlx::query(
+        "INSERT INTO system_history (created_at, cpu_load, memory_used, container_data, storage_data, network_data, system_data, memory_total, cpu_temperature)
+         VALUES (1000, 12.0, 2000, $1, $1, $1, $1, 8000, 47.5)",
+    )
+    .bind(stub)
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+
+    let (_info, snaps) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(snaps.len(), 1);
+    assert_eq!(snaps[0].ram.used, 2000);
+    assert_eq!(snaps[0].ram.total, 8000);
+    assert_eq!(snaps[0].ram.usage_percent, 25.0);
+    assert_eq!(snaps[0].cpu.usage_percent, 12.0);
+    assert_eq!(snaps[0].cpu.temperature, 47.5);
+}
+
+#[tokio::test]
+async fn aggregated_scalar_columns_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let repo = common::connect(&dir.path().join("h.db")).await;
+    let s = snapshot(60_000);
+    let agg = homeserver::history_repo::aggregation::aggregate_snapshots(&[s], 60_000, 60).unwrap();
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, 120_000, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].memory_total_avg, 16_000);
+    assert_eq!(rows[0].memory_total_max, 16_000);
+    assert_eq!(rows[0].cpu_temperature_avg, 58.5);
+    assert_eq!(rows[0].cpu_temperature_min, 58.5);
+}
@@ -1,5 +1,8 @@
 // HistoryRepo tests: connect, init, save, get_recent, prune, aggregation (range, save_aggregated, delete)
 
+mod common;
+
+use homeserver::config::DatabaseConfig;
 use homeserver::history_repo::{CURRENT_SCHEMA_VERSION, HistoryRepo};
 use homeserver::models::*;
 use sqlx::sqlite::SqlitePool;
@@ -20,50 +23,27 @@ async fn schema_version_value(db_path: &Path) -> Option<i64> {
 fn minimal_system_info() -> SystemInfo {
     SystemInfo {
         os_family: "Linux".into(),
-        os_manufacturer: String::new(),
-        os_version: String::new(),
-        system_manufacturer: String::new(),
-        system_model: String::new(),
-        processor_name: String::new(),
+        ..Default::default()
     }
 }
 
 fn minimal_snapshot(timestamp: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp,
         cpu: CpuStats {
             model: "test".into(),
             physical_cores: 1,
             logical_cores: 2,
             usage_percent: 10.0,
-            temperature: 0.0,
-            core_usages: vec![],
+            ..Default::default()
         },
         ram: RamStats {
             total: 1024,
             used: 512,
             available: 512,
             usage_percent: 50.0,
-            swap_total: 0,
-            swap_used: 0,
-            swap_free: 0,
-        },
-        containers: vec![],
-        storage: StorageStats {
-            partitions: vec![],
-            disks: vec![],
-        },
-        network: NetworkStats { interfaces: vec![] },
-        system: SystemStatsDynamic {
-            uptime_secs: 0,
-            process_count: 0,
-            thread_count: 0,
-            load_avg_1: 0.0,
-            load_avg_5: 0.0,
-            load_avg_15: 0.0,
+            ..Default::default()
         },
-        gpus: vec![],
-        smart: vec![],
+        ..common::snapshot(timestamp)
     }
 }
 
@@ -73,7 +53,12 @@ async fn history_repo_connect_and_init() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
     // Second init is no-op (IF NOT EXISTS)
     repo.init().await.unwrap();
@@ -85,7 +70,12 @@ async fn history_repo_save_and_get_recent() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let snapshots = vec![
@@ -117,7 +107,12 @@ async fn history_repo_save_empty_no_op() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
     repo.save_snapshots(&[], &minimal_system_info())
         .await
@@ -133,7 +128,12 @@ async fn history_repo_prune_old_data() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let now_ms = std::time::SystemTime::now()
@@ -162,9 +162,12 @@ async fn schema_version_written_on_fresh_db() {
     let dir = TempDir::new().unwrap();
     let path = dir.path().join("history.db");
 
-    let repo = HistoryRepo::connect(path.to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let v = schema_version_value(&path)
@@ -178,9 +181,12 @@ async fn schema_version_unchanged_on_reinit() {
     let dir = TempDir::new().unwrap();
     let path = dir.path().join("history.db");
 
-    let repo = HistoryRepo::connect(path.to_str().unwrap(), 3)
-        .await
-        .unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
     repo.init().await.unwrap();
 
@@ -196,7 +202,12 @@ async fn schema_version_mismatch_purges_tables() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
 
     let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
@@ -231,7 +242,12 @@ async fn schema_version_missing_row_with_existing_history_purges() {
     let path = dir.path().join("history.db");
     let path_str = path.to_str().unwrap();
 
-    let repo = HistoryRepo::connect(path_str, 3).await.unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: path_str.into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
     repo.init().await.unwrap();
     repo.save_snapshots(&[minimal_snapshot(111)], &minimal_system_info())
         .await
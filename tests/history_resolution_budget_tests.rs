@@ -0,0 +1,133 @@
+// /api/history point budget: the span / resolution estimate, the suggested coarser resolution,
+// the 422 for a too-dense request, `auto=1` coarsening and `X-Effective-Resolution`.
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::SystemInfo;
+use homeserver::routes::{self, RESOLUTION_STEPS, estimate_points, suggest_resolution};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const HOUR_MS: i64 = 3600 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+const FROM: i64 = 1_700_000_000_000;
+
+/// A server on a fresh database whose `/api/history` returns at most `max_points` points.
+async fn app_server(dir: &TempDir, max_points: u32) -> TestServer {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    config.database.max_history_points = max_points;
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        Arc::new(repo),
+        Default::default(),
+    ))
+}
+
+#[test]
+fn estimate_is_one_point_per_full_bucket() {
+    assert_eq!(estimate_points(3 * DAY_MS, 1), 259_200);
+    assert_eq!(estimate_points(HOUR_MS, 60), 60);
+    assert_eq!(estimate_points(HOUR_MS + 59_999, 60), 60);
+    assert_eq!(estimate_points(500, 1), 0);
+}
+
+#[test]
+fn suggestion_is_the_finest_step_within_the_budget() {
+    // 3 days: 259 200 points at 1 s, 8 640 at 30 s.
+    assert_eq!(suggest_resolution(3 * DAY_MS, 50_000), Some(30));
+    assert_eq!(suggest_resolution(3 * DAY_MS, 8_639), Some(60));
+    assert_eq!(suggest_resolution(3 * DAY_MS, 100), Some(3600));
+    // Already within the budget at the finest step.
+    assert_eq!(suggest_resolution(HOUR_MS, 3600), Some(1));
+    // 31 days is 31 points even at a day each.
+    assert_eq!(suggest_resolution(31 * DAY_MS, 31), Some(86400));
+    assert_eq!(suggest_resolution(31 * DAY_MS, 30), None);
+    let step = suggest_resolution(3 * DAY_MS, 500).unwrap();
+    assert!(RESOLUTION_STEPS.contains(&step));
+    assert!(estimate_points(3 * DAY_MS, step) <= 500);
+}
+
+#[tokio::test]
+async fn too_dense_requests_are_rejected_with_a_suggestion() {
+    let dir = TempDir::new().unwrap();
+    let server = app_server(&dir, 50_000).await;
+    let url = format!(
+        "/api/history?from={FROM}&to={}&resolution=1",
+        FROM + 3 * DAY_MS
+    );
+    let response = server.get(&url).expect_failure().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "unprocessable");
+    assert_eq!(body["details"]["estimatedPoints"], 259_200);
+    assert_eq!(body["details"]["maxPoints"], 50_000);
+    assert_eq!(body["details"]["suggestedResolution"], 30);
+    assert!(
+        body["error"].as_str().unwrap().contains("resolution=30"),
+        "{body}"
+    );
+
+    // No step fits: only narrowing the range helps.
+    let dir = TempDir::new().unwrap();
+    let server = app_server(&dir, 10).await;
+    let url = format!("/api/history?from={FROM}&to={}&auto=1", FROM + 31 * DAY_MS);
+    let response = server.get(&url).expect_failure().await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert!(body["details"]["suggestedResolution"].is_null(), "{body}");
+}
+
+#[tokio::test]
+async fn auto_coarsens_to_the_suggestion() {
+    let dir = TempDir::new().unwrap();
+    let server = app_server(&dir, 50_000).await;
+    let url = format!(
+        "/api/history?from={FROM}&to={}&resolution=1s&auto=1",
+        FROM + 3 * DAY_MS
+    );
+    let response = server
+        .get(&url)
+        .add_header("origin", "http://dashboard.local")
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("x-effective-resolution"), "30");
+    // Readable by a cross-origin dashboard.
+    let exposed = response.header("access-control-expose-headers");
+    assert!(exposed.to_str().unwrap().contains("x-effective-resolution"));
+    assert!(response.json::<serde_json::Value>().is_array());
+
+    server
+        .get("/api/history?auto=maybe")
+        .expect_failure()
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn effective_resolution_is_reported_on_every_answer() {
+    let dir = TempDir::new().unwrap();
+    let server = app_server(&dir, 50_000).await;
+    // Within the budget: the requested resolution, with or without auto.
+    let url = format!(
+        "/api/history?from={FROM}&to={}&resolution=5m",
+        FROM + DAY_MS
+    );
+    let response = server.get(&url).await;
+    assert_eq!(response.header("x-effective-resolution"), "300");
+    let response = server.get(&format!("{url}&auto=1")).await;
+    assert_eq!(response.header("x-effective-resolution"), "300");
+    // The default resolution.
+    let response = server.get("/api/history").await;
+    assert_eq!(response.header("x-effective-resolution"), "60");
+}
@@ -0,0 +1,117 @@
+// Per-tier retention: RetentionPolicy from config, aggregated_prune_cutoffs and pruning each tier
+// at its own cutoff (held back behind roll-up watermarks), raw pruned at retention_days.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::{HistoryRepo, RetentionPolicy};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Raw for 6 hours (pruned after 2 days), 1-min for 24 hours, 5-min for 2 years.
+fn config(dir: &TempDir) -> DatabaseConfig {
+    DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        retention_days: 2,
+        raw_retention_hours: 6,
+        minute_retention_hours: 24,
+        five_minute_retention_days: 730,
+        hourly_retention_days: 730,
+        aggregated_retention_days: 1000,
+        ..Default::default()
+    }
+}
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    common::snapshot(ts as u64)
+}
+
+async fn save_agg(repo: &HistoryRepo, resolution: i32, created_at: i64) {
+    let agg = aggregate_snapshots(&[snapshot(created_at)], created_at, resolution).unwrap();
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+}
+
+async fn tier_rows(repo: &HistoryRepo, resolution: i32) -> Vec<i64> {
+    repo.get_aggregated_snapshots_by_time_range(0, i64::MAX, resolution)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|a| a.created_at)
+        .collect()
+}
+
+#[test]
+fn policy_follows_the_tier_settings() {
+    let dir = TempDir::new().unwrap();
+    let policy = RetentionPolicy::from_config(&config(&dir));
+    assert_eq!(policy.raw_ms, 2 * MS_PER_DAY);
+    assert_eq!(policy.tier_ms(60), 24 * MS_PER_HOUR);
+    assert_eq!(policy.tier_ms(300), 730 * MS_PER_DAY);
+    assert_eq!(policy.tier_ms(3600), 730 * MS_PER_DAY);
+    assert_eq!(policy.tier_ms(86400), 1000 * MS_PER_DAY);
+}
+
+#[tokio::test]
+async fn each_tier_is_pruned_at_its_own_cutoff() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&config(&dir)).await.unwrap();
+    repo.init().await.unwrap();
+    let now = common::now_ms();
+    let minute = |age_h: i64| ((now - age_h * MS_PER_HOUR) / 60_000) * 60_000;
+    let day = |age_d: i64| ((now - age_d * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+
+    // 1-min rows: 2 h old, and 30 h / 40 h old (past the 24 h retention).
+    for ts in [minute(2), minute(30), minute(40)] {
+        save_agg(&repo, 60, ts).await;
+    }
+    // 5-min rows a year old stay: their tier keeps 2 years.
+    let year_old = ((now - 365 * MS_PER_DAY) / 300_000) * 300_000;
+    save_agg(&repo, 300, year_old).await;
+    // 1-day rows inside and past aggregated_retention_days.
+    for ts in [day(900), day(1100)] {
+        save_agg(&repo, 86400, ts).await;
+    }
+
+    // No 5-min roll-up yet: 1-min rows past retention may still be waiting for it.
+    let cutoffs = repo.aggregated_prune_cutoffs(now).await.unwrap();
+    assert_eq!(cutoffs[0], (60, now - 1000 * MS_PER_DAY));
+    repo.prune_aggregated_old_data(&cutoffs).await.unwrap();
+    assert_eq!(tier_rows(&repo, 60).await.len(), 3);
+    assert_eq!(tier_rows(&repo, 86400).await, [day(900)]);
+
+    // 5-min roll-up has finished up to 35 h ago (empty range: only the watermark moves), so the
+    // 40 h row is a late leftover; the 30 h row is still pending.
+    let watermark = minute(35);
+    repo.roll_up_aggregated_range(&[], watermark, watermark, 60, 300)
+        .await
+        .unwrap();
+    let cutoffs = repo.aggregated_prune_cutoffs(now).await.unwrap();
+    assert_eq!(cutoffs[0], (60, watermark));
+    let removed = repo.prune_aggregated_old_data(&cutoffs).await.unwrap();
+    assert_eq!(removed, 1);
+    assert_eq!(tier_rows(&repo, 60).await, [minute(30), minute(2)]);
+    assert_eq!(tier_rows(&repo, 300).await, [year_old]);
+}
+
+#[tokio::test]
+async fn raw_rows_are_pruned_at_retention_days_not_the_aggregated_cap() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&config(&dir)).await.unwrap();
+    repo.init().await.unwrap();
+    let now = common::now_ms();
+    let snaps = [
+        snapshot(now - 3 * MS_PER_DAY),
+        snapshot(now - MS_PER_DAY),
+        snapshot(now),
+    ];
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    repo.prune_old_data().await.unwrap();
+    let (_, left) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(left.len(), 2);
+}
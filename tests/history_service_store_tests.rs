@@ -0,0 +1,191 @@
+// `ServiceHistory` behaviour shared by both engines: collector failures, service events and the
+// per-container tables, against a temporary SQLite file and, when HOMESERVER_TEST_POSTGRES_URL is
+// set (`cargo test -- --ignored`), against a fresh schema on that Postgres server.
+
+mod common;
+
+use homeserver::history_repo::{HistoryStore, TopContainerMetric};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn error(ts: i64, source: &str, suppressed: u64) -> CollectionError {
+    CollectionError {
+        ts,
+        source: source.into(),
+        message: format!("{source} failed"),
+        suppressed,
+    }
+}
+
+fn container(id: &str, name: &str, cpu: f64) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": name,
+        "image": "nginx:1.27",
+        "cpuPercent": cpu,
+        "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+fn snapshot(ts: i64, containers: Vec<ContainerStats>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers,
+        ..common::snapshot(ts as u64)
+    }
+}
+
+async fn records_and_counts_collection_errors(repo: &dyn HistoryStore) {
+    let now = common::now_ms();
+    let old = error(now - 30 * MS_PER_DAY, "docker", 0);
+    for e in [
+        &old,
+        &error(now - 2000, "docker", 3),
+        &error(now - 1000, "network", 0),
+    ] {
+        repo.record_error(e).await.unwrap();
+    }
+
+    let recent = repo.get_recent_errors(2).await.unwrap();
+    let sources: Vec<&str> = recent.iter().map(|e| e.source.as_str()).collect();
+    assert_eq!(sources, ["network", "docker"]);
+    assert_eq!(recent[1].suppressed, 3);
+
+    let counts = repo.error_counts_since(now - MS_PER_DAY).await.unwrap();
+    let counts: Vec<(&str, u64)> = counts
+        .iter()
+        .map(|c| (c.source.as_str(), c.count))
+        .collect();
+    assert_eq!(counts, [("docker", 4), ("network", 1)]);
+
+    // Past `error_retention_days` (7 by default).
+    repo.prune_old_data().await.unwrap();
+    let left = repo.get_recent_errors(10).await.unwrap();
+    assert_eq!(left.len(), 2);
+    assert!(left.iter().all(|e| e.ts != old.ts));
+}
+
+async fn records_service_events(repo: &dyn HistoryStore) {
+    let base = common::now_ms();
+    assert_eq!(
+        repo.record_service_start(base).await.unwrap(),
+        ServiceEventKind::Started
+    );
+    repo.record_check_event(
+        base + 1,
+        ServiceEventKind::CheckDown,
+        "web",
+        Some("timeout"),
+    )
+    .await
+    .unwrap();
+    // Check events do not count as a clean shutdown.
+    assert_eq!(
+        repo.record_service_start(base + 2).await.unwrap(),
+        ServiceEventKind::RecoveredAfterCrash
+    );
+    repo.record_clean_shutdown(base + 3).await.unwrap();
+    assert_eq!(
+        repo.record_service_start(base + 4).await.unwrap(),
+        ServiceEventKind::Started
+    );
+
+    let events = repo.get_service_events(10).await.unwrap();
+    let kinds: Vec<ServiceEventKind> = events.iter().map(|e| e.event).collect();
+    assert_eq!(
+        kinds,
+        [
+            ServiceEventKind::Started,
+            ServiceEventKind::CleanShutdown,
+            ServiceEventKind::RecoveredAfterCrash,
+            ServiceEventKind::CheckDown,
+            ServiceEventKind::Started,
+        ]
+    );
+    assert_eq!(events[3].name.as_deref(), Some("web"));
+    assert_eq!(events[3].detail.as_deref(), Some("timeout"));
+    assert_eq!(events[0].name, None);
+}
+
+async fn tracks_containers(repo: &dyn HistoryStore) {
+    let base = common::now_ms() - 10 * MS_PER_MINUTE;
+    // `a` is renamed inside the batch; `b` is missing from the latest snapshot.
+    let snaps = [
+        snapshot(
+            base,
+            vec![container("a", "web", 50.0), container("b", "db", 10.0)],
+        ),
+        snapshot(
+            base + 1000,
+            vec![container("a", "web", 70.0), container("b", "db", 30.0)],
+        ),
+        snapshot(base + 2000, vec![container("a", "web-2", 60.0)]),
+    ];
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let top = repo
+        .get_top_containers(base, base + 3000, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    let ranked: Vec<(&str, &str, u64)> = top
+        .iter()
+        .map(|t| (t.container_id.as_str(), t.name.as_str(), t.samples))
+        .collect();
+    assert_eq!(ranked, [("a", "web-2", 3), ("b", "db", 2)]);
+    assert_eq!((top[0].average, top[0].max), (60.0, 70.0));
+    assert_eq!((top[1].average, top[1].max), (20.0, 30.0));
+
+    let present = repo.get_container_inventory(false).await.unwrap();
+    assert_eq!(present.len(), 1);
+    let a = &present[0];
+    assert_eq!((a.container_id.as_str(), a.name.as_str()), ("a", "web-2"));
+    assert_eq!(a.previous_names, ["web"]);
+    assert_eq!(
+        (a.first_seen, a.last_seen),
+        (base as u64, base as u64 + 2000)
+    );
+    assert!(!a.gone);
+
+    let all = repo.get_container_inventory(true).await.unwrap();
+    let b = all.iter().find(|e| e.container_id == "b").unwrap();
+    assert!(b.gone);
+    assert_eq!(b.last_seen, base as u64 + 1000);
+    assert!(b.previous_names.is_empty());
+}
+
+/// One test per behaviour and engine: `$check` against SQLite, and against Postgres when
+/// [`common::postgres::URL_ENV`] is set.
+macro_rules! on_both_engines {
+    ($($check:ident => $sqlite:ident, $postgres:ident;)*) => {$(
+        #[tokio::test]
+        async fn $sqlite() {
+            let dir = TempDir::new().unwrap();
+            let repo = common::connect(&dir.path().join("h.db")).await;
+            $check(&repo).await;
+        }
+
+        #[tokio::test]
+        #[ignore = "needs a Postgres server (HOMESERVER_TEST_POSTGRES_URL)"]
+        async fn $postgres() {
+            let Some((repo, schema)) = common::postgres::connect().await else {
+                return;
+            };
+            $check(&repo).await;
+            repo.close().await;
+            schema.drop().await;
+        }
+    )*};
+}
+
+on_both_engines! {
+    records_and_counts_collection_errors => sqlite_records_collection_errors, postgres_records_collection_errors;
+    records_service_events => sqlite_records_service_events, postgres_records_service_events;
+    tracks_containers => sqlite_tracks_containers, postgres_tracks_containers;
+}
@@ -0,0 +1,76 @@
+// Timestamp-ordered raw reads: get_recent_snapshots and get_snapshots_since (with its cap).
+
+mod common;
+
+use common::snapshot;
+use homeserver::history_repo::{HistoryRepo, MAX_SNAPSHOTS_SINCE};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect(&dir.path().join("h.db")).await
+}
+
+async fn save(repo: &HistoryRepo, timestamps: &[u64]) {
+    let snaps: Vec<_> = timestamps.iter().map(|&ts| snapshot(ts)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+}
+
+fn timestamps(snaps: &[FullSystemSnapshot]) -> Vec<u64> {
+    snaps.iter().map(|s| s.timestamp).collect()
+}
+
+/// Insert order (and so ids) deliberately disagrees with timestamp order, as after an import.
+async fn interleaved_repo(dir: &TempDir) -> HistoryRepo {
+    let repo = connect(dir).await;
+    save(&repo, &[5000, 1000]).await;
+    save(&repo, &[3000]).await;
+    save(&repo, &[2000, 4000]).await;
+    repo
+}
+
+#[tokio::test]
+async fn recent_snapshots_follow_timestamps_not_ids() {
+    let dir = TempDir::new().unwrap();
+    let repo = interleaved_repo(&dir).await;
+    let (_, recent) = repo.get_recent_snapshots(3).await.unwrap();
+    assert_eq!(timestamps(&recent), [3000, 4000, 5000]);
+    let (_, all) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(timestamps(&all), [1000, 2000, 3000, 4000, 5000]);
+}
+
+#[tokio::test]
+async fn snapshots_since_are_exclusive_ordered_and_pageable() {
+    let dir = TempDir::new().unwrap();
+    let repo = interleaved_repo(&dir).await;
+    let since = |ts, limit| {
+        let repo = &repo;
+        async move { timestamps(&repo.get_snapshots_since(ts, limit).await.unwrap()) }
+    };
+    assert_eq!(since(0, 10).await, [1000, 2000, 3000, 4000, 5000]);
+    assert_eq!(since(1000, 10).await, [2000, 3000, 4000, 5000]);
+    assert_eq!(since(1500, 2).await, [2000, 3000]);
+    // Continue from the last returned timestamp.
+    assert_eq!(since(3000, 2).await, [4000, 5000]);
+    assert!(since(5000, 10).await.is_empty());
+    assert!(since(0, 0).await.is_empty());
+}
+
+#[tokio::test]
+async fn snapshots_since_limit_is_capped() {
+    let dir = TempDir::new().unwrap();
+    let repo = connect(&dir).await;
+    let all: Vec<u64> = (1..=u64::from(MAX_SNAPSHOTS_SINCE) + 50)
+        .map(|i| i * 1000)
+        .collect();
+    save(&repo, &all).await;
+
+    let page = repo.get_snapshots_since(0, u32::MAX).await.unwrap();
+    assert_eq!(page.len(), MAX_SNAPSHOTS_SINCE as usize);
+    assert_eq!(page[0].timestamp, 1000);
+    let next = page.last().unwrap().timestamp as i64;
+    let rest = repo.get_snapshots_since(next, u32::MAX).await.unwrap();
+    assert_eq!(rest.len(), 50);
+}
@@ -0,0 +1,176 @@
+// The history database opened in the background: 503 with Retry-After while it is pending, the
+// retry loop picking up a directory that appears later, and cancellation while retrying.
+
+use axum_test::TestServer;
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use homeserver::startup::open_history_store_with_retry;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+fn app(handle: HistoryHandle) -> TestServer {
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        Default::default(),
+        handle,
+        Default::default(),
+    ))
+}
+
+fn database(path: &std::path::Path) -> DatabaseConfig {
+    DatabaseConfig {
+        path: path.to_str().unwrap().into(),
+        max_pool_size: 2,
+        enable_aggregation: false,
+        ..Default::default()
+    }
+}
+
+async fn assert_starting(server: &TestServer, path: &str) -> axum_test::TestResponse {
+    let response = server
+        .get(path)
+        .add_header("origin", "http://dashboard.local")
+        .await;
+    response.assert_status_service_unavailable();
+    assert_eq!(response.header("retry-after"), "5");
+    let exposed = response.header("access-control-expose-headers");
+    assert!(
+        exposed.to_str().unwrap().contains("retry-after"),
+        "{exposed:?}"
+    );
+    response
+}
+
+#[tokio::test]
+async fn pending_repo_answers_503_then_200_once_ready() {
+    let dir = TempDir::new().unwrap();
+    let handle = HistoryHandle::pending();
+    let server = app(handle.clone());
+
+    let body: serde_json::Value = assert_starting(&server, "/api/history").await.json();
+    assert_eq!(body["error"], "the history database is not ready yet");
+    assert_eq!(body["code"], "unavailable");
+    assert!(body["details"]["reason"].is_null());
+    for path in [
+        "/api/history/since?ts=0",
+        "/api/errors",
+        "/api/db",
+        "/health",
+    ] {
+        server.get(path).await.assert_status_service_unavailable();
+    }
+    // Endpoints that do not need the database keep answering.
+    server.get("/api/info").await.assert_status_ok();
+    let caps: serde_json::Value = server.get("/api/capabilities").await.json();
+    assert!(caps["historyEarliestTs"].is_null());
+    assert_eq!(caps["features"]["history"], true);
+
+    handle.set_error("unable to open database file");
+    let body: serde_json::Value = assert_starting(&server, "/api/db").await.json();
+    assert_eq!(body["details"]["reason"], "unable to open database file");
+
+    let repo = Arc::new(
+        HistoryRepo::connect(&database(&dir.path().join("h.db")))
+            .await
+            .unwrap(),
+    );
+    repo.init().await.unwrap();
+    handle.set_ready(repo);
+    assert!(handle.last_error().is_none());
+    server.get("/api/history").await.assert_status_ok();
+    server.get("/api/db").await.assert_status_ok();
+    server.get("/health").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn retry_opens_the_database_once_its_directory_appears() {
+    let dir = TempDir::new().unwrap();
+    // A file where the directory should be stands in for a mount that is not there yet.
+    let mount = dir.path().join("nas");
+    std::fs::write(&mount, b"not mounted").unwrap();
+    let config = database(&mount.join("h.db"));
+    let handle = HistoryHandle::pending();
+    let server = app(handle.clone());
+
+    let task = tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            open_history_store_with_retry(
+                &config,
+                &ServiceMetrics::default(),
+                CancellationToken::new(),
+                &handle,
+            )
+            .await
+        }
+    });
+    while handle.last_error().is_none() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let body: serde_json::Value = assert_starting(&server, "/api/history").await.json();
+    assert!(body["details"]["reason"].is_string(), "{body}");
+    assert_starting(&server, "/health")
+        .await
+        .assert_text("database starting");
+
+    std::fs::remove_file(&mount).unwrap();
+    std::fs::create_dir(&mount).unwrap();
+    let store = tokio::time::timeout(Duration::from_secs(10), task)
+        .await
+        .expect("opened after the directory appeared")
+        .unwrap()
+        .expect("not cancelled");
+    assert!(Arc::ptr_eq(&store.repo, handle.get().unwrap()));
+    server.get("/api/history").await.assert_status_ok();
+    server.get("/health").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn shutdown_stops_the_retry_loop() {
+    let dir = TempDir::new().unwrap();
+    let blocked = dir.path().join("blocked");
+    std::fs::write(&blocked, b"").unwrap();
+    let handle = HistoryHandle::pending();
+    let shutdown = CancellationToken::new();
+    let task = tokio::spawn({
+        let (handle, shutdown) = (handle.clone(), shutdown.clone());
+        async move {
+            open_history_store_with_retry(
+                &database(&blocked.join("h.db")),
+                &ServiceMetrics::default(),
+                shutdown,
+                &handle,
+            )
+            .await
+        }
+    });
+    while handle.last_error().is_none() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    shutdown.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(result.is_none());
+    assert!(handle.get().is_none());
+}
+
+#[test]
+fn disabled_handle_is_not_enabled_and_never_ready() {
+    let handle = HistoryHandle::disabled();
+    assert!(!handle.is_enabled());
+    handle.set_error("ignored");
+    assert!(handle.last_error().is_none());
+    assert!(HistoryHandle::pending().is_enabled());
+    assert!(!HistoryHandle::from(None).is_enabled());
+}
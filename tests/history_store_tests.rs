@@ -0,0 +1,246 @@
+// `HistoryStore` behaviour shared by both engines: each check runs against a temporary SQLite
+// file and, when HOMESERVER_TEST_POSTGRES_URL is set (`cargo test -- --ignored`), against a fresh
+// schema on that Postgres server.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick_at};
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::{DownsampleMode, HistoryRepo, HistoryStore};
+use homeserver::models::*;
+use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn snapshot(ts: i64, cpu: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            total: 1000,
+            used: 400,
+            ..Default::default()
+        },
+        network: NetworkStats::new(vec![InterfaceStat {
+            name: "eth0".into(),
+            display_name: "eth0".into(),
+            mac_address: String::new(),
+            ipv4: vec![],
+            ipv6: vec![],
+            bytes_sent: 0,
+            bytes_recv: 0,
+            packets_sent: 0,
+            packets_recv: 0,
+            speed: 1000,
+            received_bytes_per_sec: 0.0,
+            transmitted_bytes_per_sec: 0.0,
+            is_up: true,
+            packets_recv_per_sec: 0.0,
+            packets_sent_per_sec: 0.0,
+        }]),
+        ..common::snapshot(ts as u64)
+    }
+}
+
+fn system_info() -> SystemInfo {
+    SystemInfo {
+        os_family: "Linux".into(),
+        processor_name: "test cpu".into(),
+        ..Default::default()
+    }
+}
+
+fn worker_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        retention_days: 3,
+        ..common::aggregation_config()
+    }
+}
+
+async fn sqlite(dir: &TempDir) -> HistoryRepo {
+    common::connect(&dir.path().join("h.db")).await
+}
+
+async fn saves_and_reads_raw_snapshots(repo: &dyn HistoryStore) {
+    let base = common::now_ms() - 10 * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..5)
+        .map(|i| snapshot(base + i * 1000, i as f64 * 10.0))
+        .collect();
+    assert!(repo.save_snapshots(&snaps, &system_info()).await.unwrap() > 0);
+
+    let (info, recent) = repo.get_recent_snapshots(2).await.unwrap();
+    assert_eq!(info, Some(system_info()));
+    let ts: Vec<u64> = recent.iter().map(|s| s.timestamp).collect();
+    assert_eq!(ts, [snaps[3].timestamp, snaps[4].timestamp]);
+    assert_eq!(recent[1].cpu.usage_percent, 40.0);
+    assert_eq!(recent[1].network.interfaces[0].name, "eth0");
+
+    let since = repo.get_snapshots_since(base + 2000, 100).await.unwrap();
+    assert_eq!(since.len(), 2);
+    let page = repo.get_sync_batch(SyncCursor::default(), 3).await.unwrap();
+    assert!(page.has_more && !page.reset_required);
+    let rest = repo.get_sync_batch(page.next_cursor, 3).await.unwrap();
+    assert_eq!(rest.snapshots[0].timestamp, snaps[3].timestamp);
+    assert!(!rest.has_more);
+    let range = repo
+        .get_raw_snapshots_by_time_range(base + 1000, base + 3000)
+        .await
+        .unwrap();
+    assert_eq!(range.len(), 2);
+    assert_eq!(
+        repo.get_history_bounds().await.unwrap(),
+        (Some(base), Some(base + 4000))
+    );
+    assert_eq!(
+        repo.get_min_raw_created_at_in(base + 500, base + 5000)
+            .await
+            .unwrap(),
+        Some(base + 1000)
+    );
+}
+
+async fn rolls_up_raw_rows_into_the_first_tier(repo: &dyn HistoryStore) {
+    let now = common::now_ms();
+    let old = (now - 3 * MS_PER_HOUR) / MS_PER_MINUTE * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..120)
+        .map(|i| snapshot(old + i * 1000, if i < 60 { 10.0 } else { 30.0 }))
+        .chain([snapshot(now - MS_PER_MINUTE, 50.0)])
+        .collect();
+    repo.save_snapshots(&snaps, &system_info()).await.unwrap();
+
+    let report = run_one_tick_at(repo, &worker_config(), now, &CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!(report.raw_buckets, 2);
+    assert_eq!(report.raw_rows_deleted, 120);
+    assert!(repo.get_aggregation_watermark(60).await.unwrap().unwrap() >= old + 2 * MS_PER_MINUTE);
+
+    let aggs = repo
+        .get_aggregated_snapshots_by_time_range(old, old + 2 * MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    let avgs: Vec<f64> = aggs.iter().map(|a| a.cpu_load_avg).collect();
+    assert_eq!(avgs, [10.0, 30.0]);
+
+    let raw_cutoff = repo.history_raw_cutoff(now, 1).await.unwrap();
+    let points = repo
+        .get_history_points(old, now, 60, raw_cutoff, DownsampleMode::Average)
+        .await
+        .unwrap();
+    let cpu: Vec<f64> = points
+        .iter()
+        .map(|p| p.snapshot.cpu.usage_percent)
+        .collect();
+    assert_eq!(cpu, [10.0, 30.0, 50.0]);
+    assert!(
+        points[0].envelope.is_some(),
+        "aggregated points carry an envelope"
+    );
+}
+
+async fn replaces_an_aggregated_bucket(repo: &dyn HistoryStore) {
+    let bucket = 1_700_000_040_000;
+    for cpu in [10.0, 20.0] {
+        let agg = aggregate_snapshots(&[snapshot(bucket + 1000, cpu)], bucket, 60).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+    let aggs = repo
+        .get_aggregated_snapshots_by_time_range(bucket, bucket + MS_PER_MINUTE, 60)
+        .await
+        .unwrap();
+    assert_eq!(aggs.len(), 1);
+    assert_eq!(aggs[0].cpu_load_avg, 20.0);
+}
+
+async fn keeps_the_aggregation_clock_monotonic(repo: &dyn HistoryStore) {
+    let now = common::now_ms();
+    assert_eq!(repo.clamp_aggregation_clock(now).await.unwrap(), now);
+    assert_eq!(repo.clamp_aggregation_clock(now - 5000).await.unwrap(), now);
+    assert_eq!(
+        repo.clamp_aggregation_clock(now + 5000).await.unwrap(),
+        now + 5000
+    );
+}
+
+async fn prunes_raw_rows_past_retention(repo: &dyn HistoryStore) {
+    let now = common::now_ms();
+    let snaps = [
+        snapshot(now - 3 * MS_PER_DAY, 1.0),
+        snapshot(now - 2000, 2.0),
+        snapshot(now - 1000, 3.0),
+    ];
+    repo.save_snapshots(&snaps, &system_info()).await.unwrap();
+    repo.set_retention_days(1, 1);
+    assert_eq!(repo.prune_old_data().await.unwrap(), 1);
+    let (_, left) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(left.len(), 2);
+}
+
+async fn stores_and_reads_node_rows(repo: &dyn HistoryStore) {
+    let base = common::now_ms() - 10 * MS_PER_MINUTE;
+    let snaps: Vec<_> = (0..3).map(|i| snapshot(base + i * 1000, 5.0)).collect();
+    assert_eq!(repo.save_node_snapshots("pi", &snaps).await.unwrap(), 3);
+    // A batch re-sent after a lost response stores nothing twice.
+    assert_eq!(
+        repo.save_node_snapshots("pi", &snaps[1..]).await.unwrap(),
+        0
+    );
+
+    let (points, truncated) = repo
+        .get_node_history_points_bounded(
+            "pi",
+            base,
+            base + MS_PER_MINUTE,
+            1,
+            DownsampleMode::Average,
+            100,
+        )
+        .await
+        .unwrap();
+    assert!(!truncated);
+    let ts: Vec<u64> = points.iter().map(|p| p.snapshot.timestamp).collect();
+    assert_eq!(ts, snaps.iter().map(|s| s.timestamp).collect::<Vec<_>>());
+    assert_eq!(points[0].snapshot.cpu.usage_percent, 5.0);
+    // Local history leaves them out.
+    assert!(repo.get_recent_snapshots(10).await.unwrap().1.is_empty());
+}
+
+/// One test per behaviour and engine: `$check` against SQLite, and against Postgres when
+/// [`common::postgres::URL_ENV`] is set.
+macro_rules! on_both_engines {
+    ($($check:ident => $sqlite:ident, $postgres:ident;)*) => {$(
+        #[tokio::test]
+        async fn $sqlite() {
+            let dir = TempDir::new().unwrap();
+            let repo = sqlite(&dir).await;
+            assert_eq!(repo.engine(), "sqlite");
+            $check(&repo).await;
+        }
+
+        #[tokio::test]
+        #[ignore = "needs a Postgres server (HOMESERVER_TEST_POSTGRES_URL)"]
+        async fn $postgres() {
+            let Some((repo, schema)) = common::postgres::connect().await else {
+                return;
+            };
+            assert_eq!(repo.engine(), "postgres");
+            $check(&repo).await;
+            repo.close().await;
+            schema.drop().await;
+        }
+    )*};
+}
+
+on_both_engines! {
+    saves_and_reads_raw_snapshots => sqlite_saves_and_reads_raw_snapshots, postgres_saves_and_reads_raw_snapshots;
+    rolls_up_raw_rows_into_the_first_tier => sqlite_rolls_up_raw_rows, postgres_rolls_up_raw_rows;
+    replaces_an_aggregated_bucket => sqlite_replaces_an_aggregated_bucket, postgres_replaces_an_aggregated_bucket;
+    keeps_the_aggregation_clock_monotonic => sqlite_keeps_the_clock_monotonic, postgres_keeps_the_clock_monotonic;
+    prunes_raw_rows_past_retention => sqlite_prunes_raw_rows, postgres_prunes_raw_rows;
+    stores_and_reads_node_rows => sqlite_stores_and_reads_node_rows, postgres_stores_and_reads_node_rows;
+}
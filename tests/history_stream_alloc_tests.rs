@@ -0,0 +1,98 @@
+// Peak heap use of streamed get_history: rows are decoded in fixed-size batches, so a coarse query
+// over many large raw rows must not hold every decoded row at once. Own test binary: the counting
+// global allocator sees every allocation in the process.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{DownsampleMode, HistoryRepo};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = unsafe { System.alloc(layout) };
+        if !p.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// Heap growth above the starting level while `f` runs.
+async fn peak_during<F: std::future::Future>(f: F) -> (F::Output, usize) {
+    let start = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(start, Ordering::Relaxed);
+    let out = f.await;
+    (out, PEAK.load(Ordering::Relaxed).saturating_sub(start))
+}
+
+fn container(i: usize) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("{i:064x}"),
+        "name": format!("container-{i}"),
+        "cpuPercent": i as f64,
+        "memoryUsageBytes": 100,
+        "memoryLimitBytes": 1000,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+const ROWS: u64 = 4096;
+
+#[tokio::test]
+async fn coarse_history_over_many_rows_keeps_peak_allocation_bounded() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let containers: Vec<_> = (0..40).map(container).collect();
+    let snaps: Vec<_> = (0..ROWS)
+        .map(|i| FullSystemSnapshot {
+            containers: containers.clone(),
+            ..common::snapshot(i * 1000)
+        })
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    drop(snaps);
+
+    let to = (ROWS * 1000) as i64;
+    // Reference: materializing every decoded row, as the collect-then-sort path did.
+    let (all, full_peak) = peak_during(repo.get_raw_snapshots_by_time_range(0, to)).await;
+    assert_eq!(all.unwrap().len(), ROWS as usize);
+
+    let (result, stream_peak) =
+        peak_during(repo.get_history_points_bounded(0, to, 60, 0, DownsampleMode::Average, 50_000))
+            .await;
+    let (points, truncated) = result.unwrap();
+    assert_eq!(points.len(), 69); // 4096 s in 1-min buckets
+    assert!(!truncated);
+    assert!(
+        stream_peak * 4 < full_peak,
+        "streamed peak {stream_peak} B not bounded (full decode peak {full_peak} B)"
+    );
+}
@@ -0,0 +1,129 @@
+// Streamed get_history: k-way merge order across tiers and raw, decode batch boundaries, point cap.
+
+mod common;
+
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::{DownsampleMode, HistoryRepo};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+/// Hour-aligned base so 60 s / 300 s buckets line up with it.
+const BASE: i64 = 1_699_999_200_000;
+const MS_PER_MIN: i64 = 60_000;
+
+async fn connect(dir: &TempDir) -> HistoryRepo {
+    common::connect(&dir.path().join("h.db")).await
+}
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    common::snapshot(ts as u64)
+}
+
+async fn save_tier(repo: &HistoryRepo, resolution_seconds: i32, timestamps: &[i64]) {
+    for &ts in timestamps {
+        let agg = aggregate_snapshots(&[snapshot(ts)], ts, resolution_seconds).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+}
+
+async fn save_raw(repo: &HistoryRepo, from: i64, count: i64) {
+    let snaps: Vec<_> = (0..count).map(|i| snapshot(from + i * 1000)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+}
+
+fn timestamps(points: &[HistoryPoint]) -> Vec<i64> {
+    points.iter().map(|p| p.snapshot.timestamp as i64).collect()
+}
+
+/// 5-min rows over [BASE − 30 min, BASE), 1-min rows over [BASE, BASE + 10 min) and 20 minutes of
+/// 1 s raw rows from BASE + 10 min (the raw cutoff): 1200 raw rows span several decode batches.
+async fn seeded(dir: &TempDir) -> HistoryRepo {
+    let repo = connect(dir).await;
+    let five_min: Vec<i64> = (0..6)
+        .map(|i| BASE - 30 * MS_PER_MIN + i * 5 * MS_PER_MIN)
+        .collect();
+    let one_min: Vec<i64> = (0..10).map(|i| BASE + i * MS_PER_MIN).collect();
+    // Written newest tier first so insert order disagrees with the merged order.
+    save_raw(&repo, BASE + 10 * MS_PER_MIN, 1200).await;
+    save_tier(&repo, 60, &one_min).await;
+    save_tier(&repo, 300, &five_min).await;
+    repo
+}
+
+async fn history(
+    repo: &HistoryRepo,
+    resolution_secs: u32,
+    max: usize,
+) -> (Vec<HistoryPoint>, bool) {
+    repo.get_history_points_bounded(
+        BASE - 30 * MS_PER_MIN,
+        BASE + 30 * MS_PER_MIN,
+        resolution_secs,
+        BASE + 10 * MS_PER_MIN,
+        DownsampleMode::Average,
+        max,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn tiers_and_raw_merge_in_timestamp_order() {
+    let dir = TempDir::new().unwrap();
+    let repo = seeded(&dir).await;
+    let (points, truncated) = history(&repo, 60, usize::MAX).await;
+    assert!(!truncated);
+    // Every 5 minutes, then every minute (1-min tier, then raw averaged into 1-min buckets).
+    let mut expected: Vec<i64> = (0..6)
+        .map(|i| BASE - 30 * MS_PER_MIN + i * 5 * MS_PER_MIN)
+        .collect();
+    expected.extend((0..30).map(|i| BASE + i * MS_PER_MIN));
+    assert_eq!(timestamps(&points), expected);
+}
+
+#[tokio::test]
+async fn raw_rows_across_decode_batches_are_all_returned_in_order() {
+    let dir = TempDir::new().unwrap();
+    let repo = seeded(&dir).await;
+    let (points, truncated) = history(&repo, 1, usize::MAX).await;
+    assert!(!truncated);
+    let ts = timestamps(&points);
+    assert_eq!(ts.len(), 6 + 10 + 1200);
+    assert!(ts.windows(2).all(|w| w[0] < w[1]), "not strictly ascending");
+    assert_eq!(*ts.last().unwrap(), BASE + 10 * MS_PER_MIN + 1199 * 1000);
+}
+
+#[tokio::test]
+async fn cap_keeps_earliest_points_and_flags_truncation() {
+    let dir = TempDir::new().unwrap();
+    let repo = seeded(&dir).await;
+    let (all, _) = history(&repo, 1, usize::MAX).await;
+
+    let (capped, truncated) = history(&repo, 1, 100).await;
+    assert!(truncated);
+    assert_eq!(timestamps(&capped), timestamps(&all[..100]));
+
+    let (exact, truncated) = history(&repo, 1, all.len()).await;
+    assert!(!truncated);
+    assert_eq!(exact.len(), all.len());
+}
+
+#[tokio::test]
+async fn uncapped_get_history_points_matches_bounded() {
+    let dir = TempDir::new().unwrap();
+    let repo = seeded(&dir).await;
+    let (bounded, _) = history(&repo, 60, usize::MAX).await;
+    let points = repo
+        .get_history_points(
+            BASE - 30 * MS_PER_MIN,
+            BASE + 30 * MS_PER_MIN,
+            60,
+            BASE + 10 * MS_PER_MIN,
+            DownsampleMode::Average,
+        )
+        .await
+        .unwrap();
+    assert_eq!(timestamps(&points), timestamps(&bounded));
+}
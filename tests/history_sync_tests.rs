@@ -0,0 +1,200 @@
+// get_sync_batch and /api/history/sync: seq and timestamp cursors, strict paging, empty polls and
+// the reset when a cursor predates retention.
+
+mod common;
+
+use axum_test::TestServer;
+use common::snapshot;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryRepo, MAX_SNAPSHOTS_SINCE};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+async fn repo(dir: &TempDir) -> (AppConfig, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    (config, repo)
+}
+
+async fn save(repo: &HistoryRepo, timestamps: &[u64]) {
+    let snaps: Vec<_> = timestamps.iter().map(|&ts| snapshot(ts)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+}
+
+fn timestamps(batch: &SyncBatch) -> Vec<u64> {
+    batch.snapshots.iter().map(|s| s.timestamp).collect()
+}
+
+fn seq(since_seq: u64) -> SyncCursor {
+    SyncCursor {
+        since_seq: Some(since_seq),
+        since_ts: None,
+    }
+}
+
+fn ts(since_ts: u64) -> SyncCursor {
+    SyncCursor {
+        since_seq: None,
+        since_ts: Some(since_ts),
+    }
+}
+
+#[tokio::test]
+async fn pages_follow_the_seq_cursor_with_a_strict_limit() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo(&dir).await;
+    save(&repo, &[1000, 2000, 3000, 4000, 5000]).await;
+
+    let first = repo.get_sync_batch(SyncCursor::default(), 2).await.unwrap();
+    assert_eq!(timestamps(&first), [1000, 2000]);
+    assert!(first.has_more && !first.reset_required);
+    assert_eq!(first.next_cursor.since_ts, Some(2000));
+
+    let second = repo.get_sync_batch(first.next_cursor, 2).await.unwrap();
+    assert_eq!(timestamps(&second), [3000, 4000]);
+    let last = repo.get_sync_batch(second.next_cursor, 2).await.unwrap();
+    assert_eq!(timestamps(&last), [5000]);
+    assert!(!last.has_more);
+
+    // Rows imported later get new seqs, so they follow the cursor even though they are older.
+    save(&repo, &[500]).await;
+    let imported = repo.get_sync_batch(last.next_cursor, 2).await.unwrap();
+    assert_eq!(timestamps(&imported), [500]);
+    assert!(!imported.reset_required);
+
+    let capped = repo.get_sync_batch(SyncCursor::default(), 0).await.unwrap();
+    assert_eq!(capped.snapshots.len(), 1);
+    let all = repo.get_sync_batch(seq(0), u32::MAX).await.unwrap();
+    assert_eq!(all.snapshots.len(), 6);
+    assert!(all.snapshots.len() < MAX_SNAPSHOTS_SINCE as usize && !all.has_more);
+}
+
+#[tokio::test]
+async fn empty_polls_keep_the_cursor() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo(&dir).await;
+    let initial = repo
+        .get_sync_batch(SyncCursor::default(), 10)
+        .await
+        .unwrap();
+    assert!(initial.snapshots.is_empty() && !initial.reset_required && !initial.has_more);
+    assert_eq!(initial.next_cursor, SyncCursor::default());
+
+    save(&repo, &[1000, 2000]).await;
+    let page = repo.get_sync_batch(initial.next_cursor, 10).await.unwrap();
+    for _ in 0..2 {
+        let poll = repo.get_sync_batch(page.next_cursor, 10).await.unwrap();
+        assert!(poll.snapshots.is_empty() && !poll.reset_required && !poll.has_more);
+        assert_eq!(poll.next_cursor, page.next_cursor);
+    }
+    let poll = repo.get_sync_batch(ts(2000), 10).await.unwrap();
+    assert!(poll.snapshots.is_empty() && !poll.reset_required);
+    assert_eq!(poll.next_cursor.since_ts, Some(2000));
+    assert_eq!(poll.next_cursor.since_seq, page.next_cursor.since_seq);
+}
+
+#[tokio::test]
+async fn timestamp_cursors_serve_clients_without_a_seq() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo(&dir).await;
+    save(&repo, &[3000, 2000, 1000]).await;
+
+    let page = repo.get_sync_batch(ts(1000), 10).await.unwrap();
+    assert_eq!(timestamps(&page), [2000, 3000]);
+    assert!(!page.reset_required);
+    // Caught up: the next cursor carries the newest seq, so the client switches to it. Before
+    // that a seq would skip rows: after [2000], the unsent 3000 has the lower seq.
+    let next = page.next_cursor;
+    assert_eq!(next.since_seq, Some(3));
+    assert_eq!(next.since_ts, Some(3000));
+    let partial = repo.get_sync_batch(ts(1000), 1).await.unwrap();
+    assert_eq!(timestamps(&partial), [2000]);
+    assert_eq!(partial.next_cursor, ts(2000));
+
+    // since_seq wins when both are sent.
+    let both = SyncCursor {
+        since_ts: Some(1000),
+        ..seq(next.since_seq.unwrap())
+    };
+    assert!(
+        repo.get_sync_batch(both, 10)
+            .await
+            .unwrap()
+            .snapshots
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn cursors_past_retention_require_a_reset() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo(&dir).await;
+    save(&repo, &[1000, 2000, 3000, 4000]).await;
+    let held = repo.get_sync_batch(SyncCursor::default(), 1).await.unwrap();
+    assert_eq!(held.next_cursor.since_ts, Some(1000));
+
+    repo.prune_before(2500).await.unwrap();
+    let reset = repo.get_sync_batch(held.next_cursor, 10).await.unwrap();
+    assert!(reset.reset_required);
+    assert_eq!(timestamps(&reset), [3000, 4000]);
+    let resumed = repo.get_sync_batch(reset.next_cursor, 10).await.unwrap();
+    assert!(resumed.snapshots.is_empty() && !resumed.reset_required);
+
+    let stale_ts = repo.get_sync_batch(ts(2000), 10).await.unwrap();
+    assert!(stale_ts.reset_required);
+    assert_eq!(timestamps(&stale_ts), [3000, 4000]);
+    // A seq this database never issued (e.g. after it was replaced) resets too.
+    assert!(
+        repo.get_sync_batch(seq(999), 10)
+            .await
+            .unwrap()
+            .reset_required
+    );
+
+    // Nothing retained at all: reset with an empty page and a fresh cursor.
+    repo.prune_before(i64::MAX).await.unwrap();
+    let empty = repo.get_sync_batch(reset.next_cursor, 10).await.unwrap();
+    assert!(empty.reset_required && empty.snapshots.is_empty());
+    assert_eq!(empty.next_cursor, SyncCursor::default());
+}
+
+#[tokio::test]
+async fn sync_route_returns_the_batch() {
+    let dir = TempDir::new().unwrap();
+    let (config, repo) = repo(&dir).await;
+    save(&repo, &[1000, 2000, 3000]).await;
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    let body: serde_json::Value = server.get("/api/history/sync?limit=2").await.json();
+    assert_eq!(body["snapshots"].as_array().unwrap().len(), 2);
+    assert_eq!(body["hasMore"], true);
+    assert_eq!(body["resetRequired"], false);
+    assert_eq!(body["nextCursor"]["sinceTs"], 2000);
+    let seq = body["nextCursor"]["sinceSeq"].as_u64().unwrap();
+
+    let next: SyncBatch = server
+        .get(&format!("/api/history/sync?since_seq={seq}"))
+        .await
+        .json();
+    assert_eq!(timestamps(&next), [3000]);
+    assert!(!next.has_more);
+    server
+        .get("/api/history/sync?since_seq=-1")
+        .await
+        .assert_status_bad_request();
+}
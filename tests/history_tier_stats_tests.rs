@@ -0,0 +1,132 @@
+// Per-tier row statistics and the steady-state storage projection (/api/db/projection).
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{HistoryRepo, project_storage};
+use homeserver::models::TierStats;
+use tempfile::TempDir;
+
+/// 61 raw rows of 1000 blob bytes, one per second.
+const SEED_RAW: &str =
+    "WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 60)
+    INSERT INTO system_history (created_at, cpu_load, memory_used, container_data, storage_data,
+        network_data, system_data)
+    SELECT 1000000 + i * 1000, 0, 0, zeroblob(1000), x'', x'', x'' FROM n";
+/// 25 one-minute rows of 500 blob bytes, one per minute, and a single 5-minute row.
+const SEED_AGGREGATED: &str =
+    "WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 24)
+    INSERT INTO system_history_aggregated (created_at, resolution_seconds, cpu_load_avg,
+        memory_used_avg, container_data, storage_data, network_data, system_data)
+    SELECT i * 60000, 60, 0, 0, zeroblob(200), zeroblob(300), x'', x'' FROM n
+    UNION ALL SELECT 0, 300, 0, 0, zeroblob(10), x'', x'', x''";
+
+async fn seeded_repo(dir: &TempDir) -> HistoryRepo {
+    let path = dir.path().join("h.db");
+    let repo = common::connect(&path).await;
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    for sql in [SEED_RAW, SEED_AGGREGATED] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+    repo
+}
+
+fn config(enable_aggregation: bool, disk_budget_bytes: u64) -> DatabaseConfig {
+    DatabaseConfig {
+        retention_days: 3,
+        raw_retention_hours: 1,
+        minute_retention_hours: 24,
+        five_minute_retention_days: 7,
+        hourly_retention_days: 90,
+        aggregated_retention_days: 3,
+        enable_aggregation,
+        disk_budget_bytes,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn tier_stats_from_seeded_rows() {
+    let dir = TempDir::new().unwrap();
+    let tiers = seeded_repo(&dir).await.get_tier_stats().await.unwrap();
+    let resolutions: Vec<i32> = tiers.iter().map(|t| t.resolution_seconds).collect();
+    assert_eq!(resolutions, [0, 60, 300, 3600, 86400]);
+
+    assert_eq!(
+        tiers[0],
+        TierStats {
+            resolution_seconds: 0,
+            rows: 61,
+            oldest: Some(1_000_000),
+            newest: Some(1_060_000),
+            total_bytes: 61_000,
+            avg_row_bytes: 1000,
+            // 60 intervals per minute of history = 86 400 rows per day.
+            bytes_per_day: Some(86_400_000),
+        }
+    );
+    assert_eq!(tiers[1].rows, 25);
+    assert_eq!(tiers[1].total_bytes, 12_500);
+    assert_eq!(tiers[1].avg_row_bytes, 500);
+    assert_eq!(tiers[1].bytes_per_day, Some(1440 * 500));
+    // A single row has no spacing to derive a rate from.
+    assert_eq!(tiers[2].rows, 1);
+    assert_eq!(tiers[2].bytes_per_day, None);
+    assert_eq!(tiers[3].rows, 0);
+    assert_eq!(tiers[3].oldest, None);
+    assert_eq!(tiers[3].avg_row_bytes, 0);
+}
+
+#[tokio::test]
+async fn projection_uses_tier_windows_and_budget() {
+    let dir = TempDir::new().unwrap();
+    let tiers = seeded_repo(&dir).await.get_tier_stats().await.unwrap();
+
+    let projection = project_storage(&tiers, &config(true, 4_000_000));
+    let windows: Vec<f64> = projection.tiers.iter().map(|t| t.window_days).collect();
+    // raw 0–1 h, 1-min 1–24 h, 5-min 1–3 days (capped by aggregated_retention_days), nothing older.
+    assert_eq!(windows, [1.0 / 24.0, 23.0 / 24.0, 2.0, 0.0, 0.0]);
+    let projected: Vec<Option<u64>> = projection.tiers.iter().map(|t| t.projected_bytes).collect();
+    assert_eq!(
+        projected,
+        [Some(3_600_000), Some(690_000), None, None, None]
+    );
+    assert_eq!(projection.projected_bytes, 4_290_000);
+    assert!(projection.exceeds_budget);
+
+    assert!(!project_storage(&tiers, &config(true, 5_000_000)).exceeds_budget);
+    assert!(!project_storage(&tiers, &config(true, 0)).exceeds_budget);
+}
+
+#[tokio::test]
+async fn projection_without_aggregation_keeps_everything_raw() {
+    let dir = TempDir::new().unwrap();
+    let tiers = seeded_repo(&dir).await.get_tier_stats().await.unwrap();
+
+    let projection = project_storage(&tiers, &config(false, 0));
+    assert_eq!(projection.tiers[0].window_days, 3.0);
+    assert_eq!(projection.tiers[0].projected_bytes, Some(259_200_000));
+    assert_eq!(projection.tiers[1].projected_bytes, Some(0));
+    assert_eq!(projection.projected_bytes, 259_200_000);
+}
+
+#[tokio::test]
+async fn projection_caps_aggregated_tiers_at_their_own_retention() {
+    let dir = TempDir::new().unwrap();
+    let tiers = seeded_repo(&dir).await.get_tier_stats().await.unwrap();
+
+    // Raw still capped at retention_days = 3; aggregated tiers run to 365 days.
+    let config = DatabaseConfig {
+        aggregated_retention_days: 365,
+        ..config(true, 0)
+    };
+    let windows: Vec<f64> = project_storage(&tiers, &config)
+        .tiers
+        .iter()
+        .map(|t| t.window_days)
+        .collect();
+    assert_eq!(windows, [1.0 / 24.0, 23.0 / 24.0, 6.0, 83.0, 275.0]);
+}
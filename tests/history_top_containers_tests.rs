@@ -0,0 +1,225 @@
+// container_history: the busiest containers of each flushed snapshot, ranked by
+// HistoryRepo::get_top_containers and /api/history/top-containers, pruned after
+// container_history_retention_days.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryRepo, TopContainerMetric};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const BASE_TS: u64 = 1_700_000_000_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn container(id: &str, cpu_percent: f64, memory: u64, rx_rate: f64) -> ContainerStats {
+    let mut c: ContainerStats = serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": format!("{id}-app"),
+        "cpuPercent": cpu_percent,
+        "memoryUsageBytes": memory,
+        "memoryLimitBytes": 1 << 30,
+        "state": "running",
+    }))
+    .unwrap();
+    c.network_rx_bytes_per_sec = rx_rate;
+    c.network_tx_bytes_per_sec = rx_rate / 2.0;
+    c
+}
+
+fn snapshot(ts: u64, containers: Vec<ContainerStats>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers,
+        ..common::snapshot(ts)
+    }
+}
+
+/// Ten snapshots of four containers: `web` is busiest on CPU, `db` holds the most memory, `proxy`
+/// moves the most traffic and `idle` is never in the top three.
+fn seed() -> Vec<FullSystemSnapshot> {
+    (0..10u64)
+        .map(|i| {
+            let burst = if i % 2 == 0 { 10.0 } else { 30.0 };
+            snapshot(
+                BASE_TS + i * 1000,
+                vec![
+                    container("web", 40.0 + burst, 100, 1000.0),
+                    container("db", 20.0, 4000, 50.0),
+                    container("proxy", 5.0 + burst / 10.0, 200, 9000.0 + burst),
+                    container("idle", 0.0, 8000, 0.0),
+                ],
+            )
+        })
+        .collect()
+}
+
+async fn repo(top_n: usize, retention_days: u32) -> (AppConfig, Arc<HistoryRepo>, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    config.database.container_history_top_n = top_n;
+    config.database.container_history_retention_days = retention_days;
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    (config, repo, dir)
+}
+
+fn ids(top: &[TopContainer]) -> Vec<&str> {
+    top.iter().map(|c| c.container_id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn rankings_follow_the_requested_metric() {
+    let (_, repo, _dir) = repo(3, 30).await;
+    repo.save_snapshots(&seed(), &SystemInfo::default())
+        .await
+        .unwrap();
+    let (from, to) = (BASE_TS as i64, BASE_TS as i64 + 10_000);
+
+    let cpu = repo
+        .get_top_containers(from, to, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    // `idle` was never among the three busiest, so it has no rows at all.
+    assert_eq!(ids(&cpu), ["web", "db", "proxy"]);
+    assert_eq!(cpu[0].name, "web-app");
+    assert_eq!(cpu[0].average, 60.0);
+    assert_eq!(cpu[0].max, 70.0);
+    assert_eq!(cpu[0].samples, 10);
+
+    let memory = repo
+        .get_top_containers(from, to, TopContainerMetric::Memory, 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(&memory), ["db", "proxy", "web"]);
+    assert_eq!(memory[0].average, 4000.0);
+
+    let rx = repo
+        .get_top_containers(from, to, TopContainerMetric::RxRate, 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(&rx), ["proxy"]);
+    assert_eq!(rx[0].average, 9020.0);
+    assert_eq!(rx[0].max, 9030.0);
+    let tx = repo
+        .get_top_containers(from, to, TopContainerMetric::TxRate, 2)
+        .await
+        .unwrap();
+    assert_eq!(ids(&tx), ["proxy", "web"]);
+
+    // Only rows inside [from, to) count.
+    let tail = repo
+        .get_top_containers(from + 8000, to, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    assert_eq!(tail[0].samples, 2);
+    assert_eq!(tail[0].average, 60.0);
+}
+
+#[tokio::test]
+async fn top_n_zero_writes_no_rows() {
+    let (_, repo, _dir) = repo(0, 30).await;
+    repo.save_snapshots(&seed(), &SystemInfo::default())
+        .await
+        .unwrap();
+    let top = repo
+        .get_top_containers(0, i64::MAX, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    assert!(top.is_empty());
+}
+
+#[tokio::test]
+async fn old_rows_are_pruned_after_their_retention() {
+    let (_, repo, _dir) = repo(3, 2).await;
+    let old = snapshot(BASE_TS, vec![container("old", 90.0, 1, 0.0)]);
+    let recent_ts = BASE_TS + 3 * MS_PER_DAY as u64;
+    let recent = snapshot(recent_ts, vec![container("new", 10.0, 1, 0.0)]);
+    repo.save_snapshots(&[old, recent], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    // Two days after the recent row: only the three-day-old one is past retention.
+    let removed = repo
+        .prune_container_history(recent_ts as i64 + 2 * MS_PER_DAY - 1)
+        .await
+        .unwrap();
+    assert_eq!(removed, 1);
+    let top = repo
+        .get_top_containers(0, i64::MAX, TopContainerMetric::Cpu, 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(&top), ["new"]);
+}
+
+#[tokio::test]
+async fn api_top_containers_ranks_and_validates() {
+    let (config, repo, _dir) = repo(3, 30).await;
+    repo.save_snapshots(&seed(), &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+    let range = format!("from={}&to={}", BASE_TS, BASE_TS + 10_000);
+
+    let response = server
+        .get(&format!(
+            "/api/history/top-containers?{range}&metric=memory&limit=2"
+        ))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(body[0]["containerId"], "db");
+    assert_eq!(body[0]["average"], 4000.0);
+    assert_eq!(body[1]["samples"], 10);
+
+    let top: Vec<TopContainer> = server
+        .get(&format!("/api/history/top-containers?{range}"))
+        .await
+        .json();
+    assert_eq!(ids(&top), ["web", "db", "proxy"]);
+
+    let response = server
+        .get(&format!("/api/history/top-containers?{range}&metric=disk"))
+        .await;
+    response.assert_status_bad_request();
+    response.assert_json(&serde_json::json!({
+        "error": "metric must be one of cpu, memory, rx, tx",
+        "code": "bad_request",
+    }));
+    server
+        .get("/api/history/top-containers?from=100&to=50")
+        .await
+        .assert_status_bad_request();
+}
+
+#[test]
+fn container_history_settings_parse_and_are_validated() {
+    let defaults = AppConfig::default().database;
+    assert_eq!(defaults.container_history_top_n, 10);
+    assert_eq!(defaults.container_history_retention_days, 30);
+    let config = AppConfig::load_from_str(
+        "[database]\ncontainer_history_top_n = 0\ncontainer_history_retention_days = 5\n",
+    )
+    .unwrap();
+    assert_eq!(config.database.container_history_top_n, 0);
+    assert_eq!(config.database.container_history_retention_days, 5);
+    let err =
+        AppConfig::load_from_str("[database]\ncontainer_history_retention_days = 0\n").unwrap_err();
+    assert!(
+        err.to_string().contains("container_history_retention_days"),
+        "{err}"
+    );
+}
@@ -0,0 +1,139 @@
+// VACUUM guard: free-page threshold decision, full vs incremental mode, auto_vacuum on new files.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_vacuum};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::{Fragmentation, HistoryRepo};
+use sqlx::SqlitePool;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn worker_config(incremental: bool, min_free_percent: u32) -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        vacuum_incremental: incremental,
+        vacuum_min_free_percent: min_free_percent,
+        ..common::aggregation_config()
+    }
+}
+
+async fn connect(path: &Path, vacuum_mode: &str) -> HistoryRepo {
+    common::connect_with(DatabaseConfig {
+        vacuum_mode: vacuum_mode.into(),
+        ..common::database(path)
+    })
+    .await
+}
+
+/// Grow the file by ~4 MiB and then free it, leaving the pages on the freelist.
+async fn fragment(path: &Path) {
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path.display()))
+        .await
+        .unwrap();
+    for stmt in [
+        "CREATE TABLE filler (data BLOB)",
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 64)
+         INSERT INTO filler SELECT randomblob(65536) FROM n",
+        "DROP TABLE filler",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+}
+
+fn frag(page_count: i64, freelist_count: i64) -> Fragmentation {
+    Fragmentation {
+        page_count,
+        freelist_count,
+        page_size: 4096,
+    }
+}
+
+#[test]
+fn threshold_decision() {
+    assert!(!frag(0, 0).needs_vacuum(20), "empty database");
+    assert!(!frag(1000, 0).needs_vacuum(0), "nothing to reclaim");
+    assert!(!frag(1000, 100).needs_vacuum(20), "10% free");
+    assert!(!frag(1000, 200).needs_vacuum(20), "exactly at threshold");
+    assert!(frag(1000, 201).needs_vacuum(20), "just above threshold");
+    assert!(
+        frag(1000, 1).needs_vacuum(0),
+        "0% threshold vacuums any free page"
+    );
+    assert!(
+        !frag(1000, 1000).needs_vacuum(100),
+        "100% threshold never vacuums"
+    );
+    assert_eq!(frag(1000, 250).free_percent(), 25.0);
+    assert_eq!(frag(1000, 250).size_bytes(), 4_096_000);
+}
+
+#[tokio::test]
+async fn full_vacuum_skipped_below_threshold_and_run_above() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path, "full").await;
+    assert_eq!(repo.auto_vacuum().await.unwrap(), 0);
+
+    assert!(!run_vacuum(&repo, &worker_config(false, 20)).await.unwrap());
+
+    fragment(&path).await;
+    let before = repo.fragmentation().await.unwrap();
+    assert!(before.free_percent() > 50.0, "{before:?}");
+    assert!(!run_vacuum(&repo, &worker_config(false, 100)).await.unwrap());
+
+    assert!(run_vacuum(&repo, &worker_config(false, 20)).await.unwrap());
+    let after = repo.fragmentation().await.unwrap();
+    assert_eq!(after.freelist_count, 0);
+    assert!(after.size_bytes() < before.size_bytes());
+}
+
+#[tokio::test]
+async fn incremental_mode_sets_auto_vacuum_on_new_database() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path, "incremental").await;
+    assert_eq!(repo.auto_vacuum().await.unwrap(), 2, "INCREMENTAL");
+
+    fragment(&path).await;
+    let before = repo.fragmentation().await.unwrap();
+    assert!(before.freelist_count > 0);
+
+    assert!(run_vacuum(&repo, &worker_config(true, 20)).await.unwrap());
+    let after = repo.fragmentation().await.unwrap();
+    assert_eq!(after.freelist_count, 0);
+    assert!(after.page_count < before.page_count);
+}
+
+#[tokio::test]
+async fn incremental_vacuum_releases_at_most_n_pages() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    let repo = connect(&path, "incremental").await;
+    fragment(&path).await;
+
+    let before = repo.fragmentation().await.unwrap();
+    repo.incremental_vacuum(10).await.unwrap();
+    let after = repo.fragmentation().await.unwrap();
+    assert_eq!(after.freelist_count, before.freelist_count - 10);
+    assert_eq!(after.page_count, before.page_count - 10);
+}
+
+#[tokio::test]
+async fn incremental_mode_converts_existing_full_database() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db");
+    drop(connect(&path, "full").await);
+
+    // Existing file created without auto_vacuum: the setting only takes effect after a VACUUM.
+    let repo = connect(&path, "incremental").await;
+    fragment(&path).await;
+
+    assert!(run_vacuum(&repo, &worker_config(true, 20)).await.unwrap());
+    assert_eq!(repo.fragmentation().await.unwrap().freelist_count, 0);
+    drop(repo);
+
+    // The on-disk mode changed: a connection that does not request it still sees INCREMENTAL.
+    let repo = connect(&path, "full").await;
+    assert_eq!(repo.auto_vacuum().await.unwrap(), 2);
+}
@@ -0,0 +1,187 @@
+// Blob verification: HistoryRepo::verify_blobs range and row cap, delete_rows, and
+// GET /api/db/verify.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::{HistoryRepo, HistoryTable};
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use sqlx::sqlite::SqlitePool;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const ADMIN_TOKEN: &str = "hunter2";
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 7.0,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+/// A repo with raw rows at 1000, 2000, ..., 5000 (ids 1..=5); `corrupt` ids get a
+/// version-1 cpu blob that does not decode.
+async fn repo_with_rows(dir: &TempDir, corrupt: &[i64]) -> (AppConfig, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.server.admin_token = Some(Secret::new(ADMIN_TOKEN));
+    config.database.path = dir.path().join("verify.db").display().to_string();
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    let snaps: Vec<_> = (1..=5).map(|i| snapshot(i * 1_000)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let pool = SqlitePool::connect(&format!("sqlite:{}", config.database.path))
+        .await
+        .unwrap();
+    for id in corrupt {
+        sqlx::query("UPDATE system_history SET cpu_data = X'01FFFFFF' WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    pool.close().await;
+    (config, Arc::new(repo))
+}
+
+fn server(config: AppConfig, repo: Arc<HistoryRepo>) -> TestServer {
+    let (tx, _) = broadcast::channel(4);
+    TestServer::new(routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo,
+        Default::default(),
+    ))
+}
+
+#[tokio::test]
+async fn verify_blobs_honours_the_range_and_row_cap() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo_with_rows(&dir, &[2, 4]).await;
+
+    let all = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+    assert_eq!(all.raw_rows, 5);
+    assert!(!all.truncated);
+    assert_eq!(all.corrupt_ids(HistoryTable::Raw), [2, 4]);
+    assert!(all.corrupt_ids(HistoryTable::Aggregated).is_empty());
+    let c = &all.corrupt[0];
+    assert_eq!((c.table, c.id, c.created_at), (HistoryTable::Raw, 2, 2_000));
+    assert_eq!((c.column, c.version), ("cpu_data", 1));
+    assert!(!c.reason.is_empty());
+
+    // [from, to): 3000 and 4000 only.
+    let ranged = repo.verify_blobs(3_000, 5_000, u64::MAX).await.unwrap();
+    assert_eq!(ranged.raw_rows, 2);
+    assert_eq!(ranged.corrupt_ids(HistoryTable::Raw), [4]);
+
+    let capped = repo.verify_blobs(i64::MIN, i64::MAX, 3).await.unwrap();
+    assert_eq!(capped.raw_rows, 3);
+    assert!(capped.truncated);
+    assert_eq!(capped.corrupt_ids(HistoryTable::Raw), [2]);
+
+    // Exactly at the cap with nothing left over is not truncated.
+    let exact = repo.verify_blobs(i64::MIN, i64::MAX, 5).await.unwrap();
+    assert!(!exact.truncated);
+}
+
+#[tokio::test]
+async fn delete_rows_removes_only_the_given_ids() {
+    let dir = TempDir::new().unwrap();
+    let (_, repo) = repo_with_rows(&dir, &[2, 4]).await;
+    let report = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+
+    let removed = repo
+        .delete_rows(HistoryTable::Raw, &report.corrupt_ids(HistoryTable::Raw))
+        .await
+        .unwrap();
+    assert_eq!(removed, 2);
+    let after = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+    assert_eq!(after.raw_rows, 3);
+    assert!(after.corrupt.is_empty(), "{:?}", after.corrupt);
+
+    // The surviving rows still read back, and their shared blobs were kept.
+    let (_, recent) = repo.get_recent_snapshots(10).await.unwrap();
+    let stamps: Vec<u64> = recent.iter().map(|s| s.timestamp).collect();
+    assert_eq!(stamps, [1_000, 3_000, 5_000]);
+    assert!(repo.db_stats().await.unwrap().blob_store_entries > 0);
+
+    // Unknown ids and empty lists are no-ops.
+    assert_eq!(repo.delete_rows(HistoryTable::Raw, &[99]).await.unwrap(), 0);
+    assert_eq!(
+        repo.delete_rows(HistoryTable::Aggregated, &[])
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn api_db_verify_reports_corrupt_rows() {
+    let dir = TempDir::new().unwrap();
+    let (config, repo) = repo_with_rows(&dir, &[3]).await;
+    let server = server(config, repo);
+
+    server
+        .get("/api/db/verify")
+        .await
+        .assert_status_unauthorized();
+    let response = server
+        .get("/api/db/verify")
+        .authorization_bearer(ADMIN_TOKEN)
+        .await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["rawRows"], 5);
+    assert_eq!(json["truncated"], false);
+    let corrupt = json["corrupt"].as_array().unwrap();
+    assert_eq!(corrupt.len(), 1);
+    assert_eq!(corrupt[0]["table"], "system_history");
+    assert_eq!(corrupt[0]["id"], 3);
+    assert_eq!(corrupt[0]["createdAt"], 3_000);
+    assert_eq!(corrupt[0]["column"], "cpu_data");
+    assert_eq!(corrupt[0]["version"], 1);
+
+    let json: serde_json::Value = server
+        .get("/api/db/verify?from=4000&to=6000")
+        .authorization_bearer(ADMIN_TOKEN)
+        .await
+        .json();
+    assert_eq!(json["rawRows"], 2);
+    assert!(json["corrupt"].as_array().unwrap().is_empty());
+
+    let json: serde_json::Value = server
+        .get("/api/db/verify?limit=2")
+        .authorization_bearer(ADMIN_TOKEN)
+        .await
+        .json();
+    assert_eq!(
+        (json["rawRows"].as_u64(), json["truncated"].as_bool()),
+        (Some(2), Some(true))
+    );
+
+    server
+        .get("/api/db/verify?from=5000&to=5000")
+        .authorization_bearer(ADMIN_TOKEN)
+        .await
+        .assert_status_bad_request();
+}
@@ -1,6 +1,9 @@
 // Verifies the history writer's persist_gpu / persist_smart gating: when disabled, GPU/SMART
 // are stripped before persisting (live WS is unaffected); when enabled, they are written.
 
+mod common;
+
+use homeserver::config::DatabaseConfig;
 use homeserver::history_repo::HistoryRepo;
 use homeserver::models::*;
 use homeserver::worker::{HistoryWriterConfig, spawn_history_writer};
@@ -10,13 +13,6 @@ use tempfile::TempDir;
 
 fn snapshot_with_gpu_smart(ts: u64) -> FullSystemSnapshot {
     FullSystemSnapshot {
-        timestamp: ts,
-        cpu: CpuStats::default(),
-        ram: RamStats::default(),
-        containers: vec![],
-        storage: StorageStats::default(),
-        network: NetworkStats::default(),
-        system: SystemStatsDynamic::default(),
         gpus: vec![GpuStats {
             vendor: "amd".into(),
             ..Default::default()
@@ -25,6 +21,7 @@ fn snapshot_with_gpu_smart(ts: u64) -> FullSystemSnapshot {
             device: "/dev/sda".into(),
             ..Default::default()
         }],
+        ..common::snapshot(ts)
     }
 }
 
@@ -32,13 +29,20 @@ fn snapshot_with_gpu_smart(ts: u64) -> FullSystemSnapshot {
 async fn persist_and_read(persist_gpu: bool, persist_smart: bool) -> FullSystemSnapshot {
     let dir = TempDir::new().unwrap();
     let repo = Arc::new(
-        HistoryRepo::connect(dir.path().join("h.db").to_str().unwrap(), 3)
-            .await
-            .unwrap(),
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
     );
     repo.init().await.unwrap();
 
-    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    let (tx, rx) = homeserver::worker::write_queue(
+        8,
+        homeserver::worker::OverflowPolicy::DropNew,
+        Default::default(),
+    );
     let handle = spawn_history_writer(
         rx,
         repo.clone(),
@@ -46,15 +50,19 @@ async fn persist_and_read(persist_gpu: bool, persist_smart: bool) -> FullSystemS
         HistoryWriterConfig {
             flush_rate: 1,
             flush_interval_secs: 3600,
+            flush_warn_ms: 1000,
             persist_gpu,
             persist_smart,
+            min_snapshot_timestamp_ms: 0,
+            max_future_skew_minutes: 5,
+            min_free_bytes: 0,
         },
         Arc::new(AtomicU64::new(0)),
+        Default::default(),
+        Arc::new(AtomicU64::new(0)),
     );
 
-    tx.send(snapshot_with_gpu_smart(1_700_000_000_000))
-        .await
-        .unwrap();
+    tx.send(snapshot_with_gpu_smart(1_700_000_000_000));
     drop(tx); // closing the channel triggers a final flush, then the task exits
     handle.await.unwrap();
 
@@ -0,0 +1,300 @@
+// HTTP checks against a local axum server answering scripted statuses: up / down transitions,
+// expected status, timeouts, refused connections, `[[http_checks]]` validation, reporting to the
+// alert history and `service_events`, and GET /api/checks.
+
+use axum::{Router, http::StatusCode, routing::get};
+use axum_test::TestServer;
+use homeserver::alerting::{AlertState, AlertStatus, Notifier, chat_text};
+use homeserver::config::{AppConfig, DatabaseConfig, HttpCheck};
+use homeserver::history_repo::{HistoryHandle, HistoryRepo, HistoryStore};
+use homeserver::http_checks::{CheckReporter, CheckSet};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+/// Serves `/health` with the scripted statuses in order (200 once they run out) and `/slow`
+/// after a second. Returns the base URL.
+async fn server(statuses: &[u16]) -> String {
+    let script = Arc::new(Mutex::new(
+        statuses.iter().copied().collect::<VecDeque<_>>(),
+    ));
+    let app = Router::new()
+        .route(
+            "/health",
+            get(move || {
+                let status = script.lock().unwrap().pop_front().unwrap_or(200);
+                async move { StatusCode::from_u16(status).unwrap() }
+            }),
+        )
+        .route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                "late"
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+fn check(name: &str, url: String) -> HttpCheck {
+    HttpCheck {
+        name: name.into(),
+        url,
+        interval_secs: 1,
+        timeout_ms: 2000,
+        expected_status: None,
+        container: Some("jellyfin".into()),
+    }
+}
+
+/// Run every check once per call, one interval apart.
+async fn tick(set: &CheckSet, client: &reqwest::Client, t0: Instant, n: u64) -> Vec<CheckState> {
+    set.run_due(client, t0 + Duration::from_secs(n))
+        .await
+        .iter()
+        .map(|t| t.state)
+        .collect()
+}
+
+#[tokio::test]
+async fn checks_go_down_and_recover_with_their_state() {
+    let base = server(&[200, 503, 503]).await;
+    let set = CheckSet::new(&[check("jellyfin", format!("{base}/health"))]);
+    assert_eq!(set.current()[0].state, CheckState::Pending);
+    let client = reqwest::Client::new();
+    let t0 = Instant::now();
+
+    assert!(tick(&set, &client, t0, 0).await.is_empty(), "first success");
+    let up = &set.current()[0];
+    assert_eq!((up.state, up.http_status), (CheckState::Up, Some(200)));
+    assert!(up.latency_ms.is_some());
+    assert_eq!(up.container.as_deref(), Some("jellyfin"));
+
+    assert_eq!(tick(&set, &client, t0, 1).await, [CheckState::Down]);
+    assert!(tick(&set, &client, t0, 2).await.is_empty(), "still down");
+    let down = &set.current()[0];
+    assert_eq!(down.consecutive_failures, 2);
+    assert_eq!(down.last_error.as_deref(), Some("HTTP 503"));
+    let since = down.since_ms;
+
+    let recovered = set.run_due(&client, t0 + Duration::from_secs(3)).await;
+    assert_eq!(recovered[0].state, CheckState::Up);
+    assert_eq!(recovered[0].http_status, Some(200));
+    let up = &set.current()[0];
+    assert_eq!((up.consecutive_failures, up.last_error.as_ref()), (0, None));
+    assert!(up.since_ms >= since);
+    let summary = CheckSummary {
+        name: "jellyfin".into(),
+        state: CheckState::Up,
+        latency_ms: up.latency_ms,
+    };
+    assert_eq!(set.summary(), [summary]);
+}
+
+#[tokio::test]
+async fn checks_run_on_their_interval() {
+    let base = server(&[]).await;
+    let set = CheckSet::new(&[check("a", format!("{base}/health"))]);
+    let client = reqwest::Client::new();
+    let t0 = Instant::now();
+    set.run_due(&client, t0).await;
+    let first = set.current()[0].last_checked_ms;
+    assert_eq!(set.next_due(), Some(t0 + Duration::from_secs(1)));
+    set.run_due(&client, t0 + Duration::from_millis(500)).await;
+    assert_eq!(set.current()[0].last_checked_ms, first, "not due yet");
+    assert_eq!(CheckSet::default().next_due(), None);
+}
+
+#[tokio::test]
+async fn expected_status_timeouts_and_refusals() {
+    let base = server(&[404, 200]).await;
+    // Bound and dropped right away: nothing listens there.
+    let refused = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let checks = [
+        HttpCheck {
+            expected_status: Some(404),
+            ..check("gone", format!("{base}/health"))
+        },
+        HttpCheck {
+            timeout_ms: 100,
+            ..check("slow", format!("{base}/slow"))
+        },
+        check("refused", format!("http://{refused}/")),
+    ];
+    let set = CheckSet::new(&checks);
+    let client = reqwest::Client::new();
+    let transitions = set.run_due(&client, Instant::now()).await;
+    let names: Vec<_> = transitions.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["slow", "refused"], "pending → down is a transition");
+
+    let current = set.current();
+    assert_eq!(current[0].state, CheckState::Up, "404 was expected");
+    assert_eq!(current[1].last_error.as_deref(), Some("timed out"));
+    assert_eq!(current[1].http_status, None);
+    assert_eq!(current[2].state, CheckState::Down);
+    assert!(current[2].last_error.is_some());
+
+    // The expected status is exact: a 200 now fails it.
+    set.run_due(&client, Instant::now() + Duration::from_secs(5))
+        .await;
+    assert_eq!(
+        set.current()[0].last_error.as_deref(),
+        Some("HTTP 200, expected 404")
+    );
+}
+
+#[test]
+fn http_check_config_is_validated() {
+    let entry = |extra: &str| format!("[[http_checks]]\nname = \"a\"\n{extra}");
+    let load = |toml: &str| AppConfig::load_from_str(toml).map_err(|e| format!("{e:#}"));
+    let config = load(&entry("url = \"https://jellyfin.lan/health\"\n")).unwrap();
+    let parsed = &config.http_checks[0];
+    assert_eq!((parsed.interval_secs, parsed.timeout_ms), (30, 5000));
+    assert_eq!(
+        (parsed.expected_status, parsed.container.as_ref()),
+        (None, None)
+    );
+
+    let url = "url = \"http://a/\"\n";
+    for (bad, message) in [
+        (entry("url = \"ftp://a/\"\n"), "http:// or https://"),
+        (entry("url = \"not a url\"\n"), "http_checks[0].url"),
+        (
+            entry(&format!("{url}interval_secs = 0\n")),
+            "at least 1 second",
+        ),
+        (entry(&format!("{url}timeout_ms = 0\n")), "timeout_ms"),
+        (entry(&format!("{url}expected_status = 42\n")), "100-599"),
+        (entry(url).repeat(2), "duplicate name 'a'"),
+        (
+            format!("[[http_checks]]\nname = \" \"\n{url}"),
+            "must be non-empty",
+        ),
+    ] {
+        let err = load(&bad).unwrap_err();
+        assert!(err.contains(message), "{bad}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn transitions_are_alerted_and_stored_as_service_events() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("h.db").to_str().unwrap().into();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let repo = Arc::new(repo);
+    assert_eq!(
+        repo.record_service_start(1).await.unwrap(),
+        ServiceEventKind::Started
+    );
+    let base = server(&[500]).await;
+    let set = CheckSet::new(&[check("jellyfin", format!("{base}/health"))]);
+    let alerts = Arc::new(AlertStatus::default());
+    let store: Arc<dyn HistoryStore> = repo.clone();
+    let reporter = CheckReporter::new(
+        HistoryHandle::ready(store),
+        Notifier::new(vec![], 0, Duration::ZERO),
+        alerts.clone(),
+    );
+    let client = reqwest::Client::new();
+    let t0 = Instant::now();
+    for n in 0..2 {
+        let transitions = set.run_due(&client, t0 + Duration::from_secs(n)).await;
+        reporter.report(transitions).await;
+    }
+
+    let recent = alerts.recent();
+    let states: Vec<_> = recent.iter().map(|e| e.state).collect();
+    assert_eq!(states, [AlertState::Resolved, AlertState::Firing]);
+    let firing = &recent[1];
+    assert_eq!((firing.rule_name.as_str(), firing.value), ("jellyfin", 1.0));
+    let firing_check = firing.check.as_ref().unwrap();
+    assert_eq!(firing_check.error.as_deref(), Some("HTTP 500"));
+    assert_eq!(
+        chat_text(firing),
+        format!("[FIRING] jellyfin (warning): {base}/health is down: HTTP 500")
+    );
+    assert!(chat_text(&recent[0]).ends_with("/health is up"));
+
+    let events = repo.get_service_events(10).await.unwrap();
+    let rows: Vec<_> = events
+        .iter()
+        .map(|e| (e.event, e.name.as_deref(), e.detail.as_deref()))
+        .collect();
+    let down = (
+        ServiceEventKind::CheckDown,
+        Some("jellyfin"),
+        Some("HTTP 500"),
+    );
+    assert_eq!(
+        rows[0],
+        (
+            ServiceEventKind::CheckUp,
+            Some("jellyfin"),
+            Some("HTTP 200")
+        )
+    );
+    assert_eq!(rows[1..], [down, (ServiceEventKind::Started, None, None)]);
+    // Check rows do not count as the last run's end: still no clean shutdown before this start.
+    assert_eq!(
+        repo.record_service_start(2).await.unwrap(),
+        ServiceEventKind::RecoveredAfterCrash
+    );
+    repo.record_clean_shutdown(3).await.unwrap();
+    repo.record_check_event(4, ServiceEventKind::CheckUp, "jellyfin", None)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.record_service_start(5).await.unwrap(),
+        ServiceEventKind::Started
+    );
+}
+
+#[tokio::test]
+async fn api_checks_serves_every_check() {
+    let base = server(&[]).await;
+    let set = CheckSet::new(&[
+        check("up", format!("{base}/health")),
+        HttpCheck {
+            interval_secs: 3600,
+            ..check("later", format!("{base}/health"))
+        },
+    ]);
+    set.run_due(&reqwest::Client::new(), Instant::now()).await;
+    let app = routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        AppConfig::default(),
+        None,
+        ServiceMetrics {
+            checks: Arc::new(set),
+            ..Default::default()
+        },
+    );
+    let body: serde_json::Value = TestServer::new(app).get("/api/checks").await.json();
+    assert_eq!(body[0]["name"], "up");
+    assert_eq!(body[0]["state"], "up");
+    assert_eq!(body[0]["httpStatus"], 200);
+    assert_eq!(body[0]["consecutiveFailures"], 0);
+    assert_eq!(body[0]["container"], "jellyfin");
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
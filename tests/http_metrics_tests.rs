@@ -0,0 +1,146 @@
+// Per-route HTTP metrics: requests counted by matched route and status on /api/stats and
+// /metrics; WebSocket upgrades counted but not timed.
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes::{self, HttpMetrics, UNMATCHED_ROUTE};
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+async fn server(dir: &TempDir, metrics: ServiceMetrics, http_transport: bool) -> TestServer {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("http.db").display().to_string();
+    config.server.node_name = "nas".into();
+    let repo = HistoryRepo::connect(&config.database).await.unwrap();
+    repo.init().await.unwrap();
+    let (tx, _) = broadcast::channel(4);
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        Arc::new(repo),
+        metrics,
+    );
+    if http_transport {
+        TestServer::builder().http_transport().build(app)
+    } else {
+        TestServer::new(app)
+    }
+}
+
+fn route<'a>(stats: &'a serde_json::Value, route: &str) -> &'a serde_json::Value {
+    stats["http"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["route"] == route)
+        .unwrap_or_else(|| panic!("no {route} in {stats}"))
+}
+
+#[tokio::test]
+async fn requests_are_counted_per_route_and_status() {
+    let dir = TempDir::new().unwrap();
+    let server = server(&dir, ServiceMetrics::default(), false).await;
+    for _ in 0..3 {
+        server.get("/version").await.assert_status_ok();
+    }
+    server
+        .get("/api/history?from=10&to=5")
+        .await
+        .assert_status_bad_request();
+    server
+        .get("/api/history?from=0&to=1")
+        .await
+        .assert_status_ok();
+    server.get("/no/such/path").await.assert_status_not_found();
+    server
+        .get("/another/missing")
+        .await
+        .assert_status_not_found();
+
+    let stats: serde_json::Value = server.get("/api/stats").await.json();
+    let version = route(&stats, "/version");
+    assert_eq!(version["requestsTotal"], 3);
+    assert_eq!(version["statusTotal"]["200"], 3);
+    assert_eq!(version["timedTotal"], 3);
+    let max = version["maxMs"].as_f64().unwrap();
+    assert!(version["p50Ms"].as_f64().unwrap() <= max);
+    assert!(version["p99Ms"].as_f64().unwrap() <= max);
+
+    let history = route(&stats, "/api/history");
+    assert_eq!(history["requestsTotal"], 2);
+    assert_eq!(history["statusTotal"]["200"], 1);
+    assert_eq!(history["statusTotal"]["400"], 1);
+    let unmatched = route(&stats, UNMATCHED_ROUTE);
+    assert_eq!(unmatched["statusTotal"]["404"], 2);
+    assert!(stats["http"].as_array().unwrap().len() >= 3);
+
+    let text = server.get("/metrics").await.text();
+    for line in [
+        "# TYPE homeserver_http_requests_total counter",
+        "homeserver_http_requests_total{node=\"nas\",route=\"/version\",status=\"200\"} 3",
+        "homeserver_http_requests_total{node=\"nas\",route=\"/api/history\",status=\"400\"} 1",
+        "homeserver_http_requests_total{node=\"nas\",route=\"unmatched\",status=\"404\"} 2",
+        "/api/stats\",status=\"200\"} 1",
+        "# TYPE homeserver_http_request_duration_seconds summary",
+        "homeserver_http_request_duration_seconds_count{node=\"nas\",route=\"/version\"} 3",
+    ] {
+        assert!(text.contains(line), "missing {line:?} in:\n{text}");
+    }
+    assert!(text.contains(
+        "homeserver_http_request_duration_seconds{node=\"nas\",route=\"/version\",quantile=\"0.99\"} "
+    ));
+}
+
+#[tokio::test]
+async fn websocket_upgrades_are_counted_but_not_timed() {
+    let dir = TempDir::new().unwrap();
+    let metrics = ServiceMetrics::default();
+    let server = server(&dir, metrics.clone(), true).await;
+    let ws = server.get_websocket("/ws/cpu").await.into_websocket().await;
+
+    let stats = metrics.http.snapshot();
+    let cpu = stats.iter().find(|r| r.route == "/ws/cpu").unwrap();
+    assert_eq!(cpu.requests_total, 1);
+    assert_eq!(cpu.status_total.get(&101), Some(&1));
+    assert_eq!(cpu.timed_total, 0);
+    assert_eq!((cpu.p50_ms, cpu.max_ms), (0.0, 0.0));
+    drop(ws);
+
+    let text = server.get("/metrics").await.text();
+    assert!(text.contains(
+        "homeserver_http_requests_total{node=\"nas\",route=\"/ws/cpu\",status=\"101\"} 1"
+    ));
+    assert!(!text.contains(
+        "homeserver_http_request_duration_seconds_count{node=\"nas\",route=\"/ws/cpu\"}"
+    ));
+}
+
+#[test]
+fn quantiles_come_from_the_histogram_buckets() {
+    let http = HttpMetrics::default();
+    for ms in [1, 1, 1, 1, 1, 1, 1, 1, 40, 3_000] {
+        http.record("/r", 200, Some(Duration::from_millis(ms)));
+    }
+    http.record("/r", 500, None);
+    let stats = &http.snapshot()[0];
+    assert_eq!(stats.requests_total, 11);
+    assert_eq!(stats.timed_total, 10);
+    assert_eq!(stats.p50_ms, 1.0);
+    assert_eq!(stats.p95_ms, 3_000.0, "capped at the slowest request");
+    assert_eq!(stats.max_ms, 3_000.0);
+    assert!((stats.mean_ms - 304.8).abs() < 1e-9);
+
+    // A single request's quantiles are capped at its own latency, not the bucket bound.
+    let http = HttpMetrics::default();
+    http.record("/r", 200, Some(Duration::from_millis(30)));
+    assert_eq!(http.snapshot()[0].p99_ms, 30.0);
+}
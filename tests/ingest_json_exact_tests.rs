@@ -0,0 +1,123 @@
+// JSON remote write batches carry floats as measured: `IngestBatch::to_json` skips the display
+// rounding and NaN scrubbing of `json_float`, so the rows a central instance stores through
+// POST /api/ingest match the sender's. Display JSON stays rounded (json_float_tests.rs).
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::{DownsampleMode, HistoryRepo};
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const TS: u64 = 1_700_000_000_000;
+
+fn snapshot() -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.345678,
+            temperature: f64::NAN,
+            core_usages: vec![f64::NAN, 37.123456],
+            ..Default::default()
+        },
+        network: NetworkStats::new(vec![InterfaceStat {
+            name: "eth0".into(),
+            display_name: "eth0".into(),
+            mac_address: String::new(),
+            ipv4: vec![],
+            ipv6: vec![],
+            bytes_sent: 0,
+            bytes_recv: 0,
+            packets_sent: 0,
+            packets_recv: 0,
+            speed: 0,
+            received_bytes_per_sec: 1234.5678,
+            transmitted_bytes_per_sec: 0.0,
+            is_up: true,
+            packets_recv_per_sec: 0.0,
+            packets_sent_per_sec: 0.0,
+        }]),
+        system: SystemStatsDynamic {
+            load_avg_1: 0.123456,
+            ..Default::default()
+        },
+        ..common::snapshot(TS)
+    }
+}
+
+#[test]
+fn exact_json_keeps_values_that_display_json_rounds() {
+    let batch = IngestBatch {
+        node: "edge".into(),
+        snapshots: vec![snapshot()],
+    };
+    let back: IngestBatch = serde_json::from_slice(&batch.to_json().unwrap()).unwrap();
+    let cpu = &back.snapshots[0].cpu;
+    assert_eq!(cpu.usage_percent, 12.345678);
+    assert!(cpu.temperature.is_nan());
+    assert!(cpu.core_usages[0].is_nan());
+    assert_eq!(cpu.core_usages[1], 37.123456);
+
+    // Outside `to_json` the same snapshot is still written for display.
+    let display: serde_json::Value = serde_json::to_value(&batch.snapshots[0]).unwrap();
+    assert_eq!(display["cpu"]["usagePercent"], 12.35);
+    assert_eq!(display["cpu"]["temperature"], 0.0);
+}
+
+#[tokio::test]
+async fn json_ingest_round_trip_stores_exact_values() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("central.db").display().to_string();
+    config.server.node_name = "central".into();
+    config.remote_write.ingest_api_key = Some(Secret::new("s3cret"));
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo.clone(),
+        Default::default(),
+    ));
+
+    let batch = IngestBatch {
+        node: "edge".into(),
+        snapshots: vec![snapshot()],
+    };
+    server
+        .post("/api/ingest")
+        .authorization_bearer("s3cret")
+        .content_type("application/json")
+        .bytes(batch.to_json().unwrap().into())
+        .await
+        .assert_status(StatusCode::OK);
+
+    let (points, _) = repo
+        .get_node_history_points_bounded(
+            "edge",
+            TS as i64,
+            TS as i64 + 60_000,
+            1,
+            DownsampleMode::Average,
+            10,
+        )
+        .await
+        .unwrap();
+    let stored = &points[0].snapshot;
+    assert_eq!(stored.cpu.usage_percent, 12.345678);
+    assert!(stored.cpu.temperature.is_nan());
+    assert_eq!(stored.cpu.core_usages[1], 37.123456);
+    assert_eq!(
+        stored.network.interfaces[0].received_bytes_per_sec,
+        1234.5678
+    );
+    assert_eq!(stored.system.load_avg_1, 0.123456);
+}
@@ -0,0 +1,95 @@
+// Integration tests: /api/history point cap (database.max_history_points).
+// Separate from integration_history_tests.rs to keep files under 300 lines.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+max_history_points = 100
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+async fn test_app_with_repo() -> (axum::Router, TempDir, Arc<HistoryRepo>) {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path.to_str().unwrap());
+    let config = AppConfig::load_from_str(&config_str).unwrap();
+    let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        history_repo.clone(),
+        Default::default(),
+    );
+    (app, dir, history_repo)
+}
+
+#[tokio::test]
+async fn test_api_history_point_cap() {
+    let (app, _dir, repo) = test_app_with_repo().await;
+    let server = TestServer::new(app);
+    let from: u64 = 1_700_000_000_000;
+
+    // Estimated above the configured cap (2 minutes at 1 s = 120 > 100): rejected up front.
+    server
+        .get(&format!(
+            "/api/history?from={from}&to={}&resolution=1s",
+            from + 120_000
+        ))
+        .await
+        .assert_status_unprocessable_entity();
+
+    // Estimated within the cap (20 points) but 10 samples per second are stored: clamped.
+    let snaps: Vec<_> = (0..200).map(|i| common::snapshot(from + i * 100)).collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let url = format!(
+        "/api/history?from={from}&to={}&resolution=1s",
+        from + 20_000
+    );
+    let response = server.get(&url).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("x-history-truncated"), "true");
+    let json: serde_json::Value = response.json();
+    let arr = json.as_array().unwrap();
+    assert_eq!(arr.len(), 100);
+    assert_eq!(arr[0]["timestamp"], from);
+    assert_eq!(arr[99]["timestamp"], from + 99 * 100);
+
+    // Under the cap: no header.
+    let url = format!("/api/history?from={from}&to={}&resolution=1s", from + 5_000);
+    let response = server.get(&url).await;
+    response.assert_status_ok();
+    assert!(response.headers().get("x-history-truncated").is_none());
+}
@@ -0,0 +1,266 @@
+// Integration tests: /api/history and /api/db (the backup routes are in history_backup_tests.rs).
+// Split from integration_tests.rs to keep files under 300 lines.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+fn test_app_config(db_path: &str) -> AppConfig {
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path);
+    AppConfig::load_from_str(&config_str).unwrap()
+}
+
+fn test_system_info() -> Arc<SystemInfo> {
+    Arc::new(SystemInfo {
+        os_family: "Linux".to_string(),
+        os_manufacturer: String::new(),
+        os_version: String::new(),
+        system_manufacturer: String::new(),
+        system_model: "test-host".to_string(),
+        processor_name: "TestCPU".to_string(),
+        node_name: "test-node".to_string(),
+        ..Default::default()
+    })
+}
+
+async fn test_app() -> (
+    axum::Router,
+    broadcast::Sender<homeserver::models::FullSystemSnapshot>,
+    TempDir,
+) {
+    let (app, tx, dir, _) = test_app_with_repo().await;
+    (app, tx, dir)
+}
+
+async fn test_app_with_repo() -> (
+    axum::Router,
+    broadcast::Sender<homeserver::models::FullSystemSnapshot>,
+    TempDir,
+    Arc<HistoryRepo>,
+) {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let mut config = test_app_config(db_path.to_str().unwrap());
+    config.database.backup_dir = dir.path().join("backups").display().to_string();
+    let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    let app = routes::app(
+        tx.clone(),
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        test_system_info(),
+        Arc::new(WsConnections::default()),
+        config,
+        history_repo.clone(),
+        Default::default(),
+    );
+    (app, tx, dir, history_repo)
+}
+
+#[tokio::test]
+async fn test_api_history_endpoint() {
+    let (app, _, _dir) = test_app().await;
+    let server = TestServer::new(app);
+    let response = server.get("/api/history").await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert!(json.is_array(), "history returns array");
+    let arr = json.as_array().unwrap();
+    assert!(
+        arr.is_empty() || arr[0].get("timestamp").is_some(),
+        "elements have timestamp"
+    );
+
+    // A realistic 1-hour window at 1-minute resolution is accepted.
+    let response = server
+        .get("/api/history?from=1700000000000&to=1700003600000&resolution=1m")
+        .await;
+    response.assert_status_ok();
+
+    // from >= to is rejected.
+    let response = server.get("/api/history?from=100&to=50").await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    // Span larger than the 31-day cap is rejected.
+    let response = server
+        .get("/api/history?from=0&to=9999999999999&resolution=1m")
+        .await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    // A valid span but too many points for the requested resolution is unprocessable
+    // (10 days at 1-second resolution = ~864k points > 50k cap).
+    let response = server
+        .get("/api/history?from=1700000000000&to=1700864000000&resolution=1s")
+        .await;
+    response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Envelope / downsample modes: known values accepted, anything else rejected.
+    server
+        .get("/api/history?envelope=p95")
+        .await
+        .assert_status_ok();
+    server
+        .get("/api/history?envelope=minmax")
+        .await
+        .assert_status_ok();
+    server
+        .get("/api/history?envelope=p99")
+        .await
+        .assert_status_bad_request();
+    server
+        .get("/api/history?downsample=last")
+        .await
+        .assert_status_ok();
+    server
+        .get("/api/history?downsample=max")
+        .await
+        .assert_status_bad_request();
+
+    // Extreme bounds whose difference overflows i64 are rejected (not panicked on).
+    let response = server
+        .get("/api/history?from=-9223372036854775808&to=9223372036854775807")
+        .await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_api_db_endpoint() {
+    let (app, _, _dir) = test_app().await;
+    let server = TestServer::new(app);
+    let response = server.get("/api/db").await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(
+        json.get("schemaVersion").and_then(|v| v.as_i64()),
+        Some(homeserver::history_repo::CURRENT_SCHEMA_VERSION as i64)
+    );
+    assert_eq!(json.get("rawRows").and_then(|v| v.as_i64()), Some(0));
+    assert!(json.get("aggregationWatermarks").unwrap().is_array());
+    assert!(json.get("walSizeBytes").and_then(|v| v.as_u64()).is_some());
+    assert!(json.get("integrityProblems").is_none());
+
+    let json: serde_json::Value = server.get("/api/db?integrity=true").await.json();
+    assert_eq!(json.get("integrityProblems"), Some(&serde_json::json!([])));
+}
+
+#[tokio::test]
+async fn test_api_history_since_endpoint() {
+    use homeserver::models::*;
+    let (app, _, _dir, repo) = test_app_with_repo().await;
+    let server = TestServer::new(app);
+    server
+        .get("/api/history/since")
+        .await
+        .assert_status_bad_request();
+    let response = server.get("/api/history/since?ts=0").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("x-next-since"), "0");
+
+    let snaps: Vec<_> = [3000, 1000, 2000]
+        .into_iter()
+        .map(common::snapshot)
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let response = server.get("/api/history/since?ts=1000&limit=1").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("x-next-since"), "2000");
+    let json: serde_json::Value = response.json();
+    assert_eq!(json.as_array().unwrap().len(), 1);
+    assert_eq!(json[0]["timestamp"], 2000);
+    // A cross-origin dashboard may read the cursor.
+    let response = server
+        .get("/api/history/since?ts=2000")
+        .add_header("origin", "http://dashboard.local")
+        .await;
+    assert_eq!(response.header("x-next-since"), "3000");
+    let exposed = response.header("access-control-expose-headers");
+    assert!(exposed.to_str().unwrap().contains("x-next-since"));
+}
+
+#[tokio::test]
+async fn test_api_db_projection_endpoint() {
+    let (app, _, _dir) = test_app().await;
+    let server = TestServer::new(app);
+    let response = server.get("/api/db/projection").await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["tiers"].as_array().unwrap().len(), 5);
+    assert_eq!(json["tiers"][0]["resolutionSeconds"], 0);
+    assert_eq!(json["tiers"][0]["rows"], 0);
+    assert_eq!(json["projectedBytes"], 0);
+    assert_eq!(json["exceedsBudget"], false);
+}
+
+#[tokio::test]
+async fn test_api_errors_endpoint() {
+    let (app, _, _dir, repo) = test_app_with_repo().await;
+    let server = TestServer::new(app);
+    let now = chrono::Utc::now().timestamp_millis();
+    for (ts, suppressed) in [(now - 2 * 3_600_000, 0), (now - 1000, 3)] {
+        repo.record_error(&homeserver::models::CollectionError {
+            ts,
+            source: "docker".into(),
+            message: "connection refused".into(),
+            suppressed,
+        })
+        .await
+        .unwrap();
+    }
+
+    let json: serde_json::Value = server.get("/api/errors?hours=1").await.json();
+    assert_eq!(json["errors"].as_array().unwrap().len(), 2);
+    assert_eq!(json["errors"][0]["suppressed"], 3);
+    assert_eq!(
+        json["counts"],
+        serde_json::json!([{"source": "docker", "count": 4}])
+    );
+    let json: serde_json::Value = server.get("/api/errors?limit=1").await.json();
+    assert_eq!(json["errors"].as_array().unwrap().len(), 1);
+    assert_eq!(json["counts"][0]["count"], 5);
+}
+
+#[tokio::test]
+async fn test_api_db_unavailable_when_pool_closed() {
+    let (app, _, _dir, repo) = test_app_with_repo().await;
+    let server = TestServer::new(app);
+    repo.close().await;
+    server
+        .get("/api/db")
+        .await
+        .assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    server
+        .get("/api/history")
+        .await
+        .assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,250 @@
+// Integration tests: /api/stats and /metrics serve the shared service counters.
+
+mod common;
+
+use axum_test::TestServer;
+use homeserver::aggregation_worker::AggregationReport;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::worker::{OverflowPolicy, write_queue};
+use homeserver::ws_connections::{WsChannel, WsConnections};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+node_name = "nas"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 1000
+stats_log_interval_secs = 60
+"#;
+
+async fn test_server(metrics: ServiceMetrics) -> (TestServer, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path.to_str().unwrap());
+    let config = AppConfig::load_from_str(&config_str).unwrap();
+    let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    // Two /ws/system clients and one /ws/cpu client that stay connected.
+    let connections = Arc::new(WsConnections::default());
+    for channel in [WsChannel::System, WsChannel::System, WsChannel::Cpu] {
+        std::mem::forget(connections.connect(channel).0);
+    }
+    let app = routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        connections,
+        config,
+        history_repo,
+        metrics,
+    );
+    (TestServer::new(app), dir)
+}
+
+fn recorded_metrics() -> ServiceMetrics {
+    let metrics = ServiceMetrics::default();
+    metrics.snapshots_saved_total.store(42, Ordering::Relaxed);
+    metrics.worker_restarts_total.store(3, Ordering::Relaxed);
+    // A stalled writer: two queued, one dropped.
+    let (write_tx, write_rx) = write_queue(2, OverflowPolicy::DropNew, metrics.write_queue.clone());
+    for _ in 0..3 {
+        write_tx.send(common::snapshot(0));
+    }
+    std::mem::forget(write_rx);
+    for raw_buckets in [3, 4] {
+        metrics.aggregation.record(&AggregationReport {
+            raw_buckets,
+            rolled_up_buckets: 1,
+            raw_rows_deleted: raw_buckets * 60,
+            minute_rows_deleted: 5,
+            pruned_aggregated: 2,
+            duration: Duration::from_millis(250),
+            ..Default::default()
+        });
+    }
+    metrics.aggregation.record_raw_prune(9);
+    metrics.collection.record(Duration::from_millis(300), false);
+    metrics.collection.record(Duration::from_millis(1500), true);
+    metrics.collection.record_failures(["docker", "network"]);
+    metrics.collection.record_failures(["docker"]);
+    metrics.collection.record_self(SelfStats {
+        cpu_percent: 1.5,
+        rss_bytes: 12_000_000,
+        open_fds: Some(17),
+        tokio_tasks: Some(9),
+        db_file_bytes: None,
+    });
+    metrics
+}
+
+#[tokio::test]
+async fn api_stats_reports_counters() {
+    let (server, _dir) = test_server(recorded_metrics()).await;
+    let response = server.get("/api/stats").await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["snapshotsSavedTotal"], 42);
+    assert_eq!(json["workerRestartsTotal"], 3);
+    assert_eq!(json["snapshotsDroppedTotal"], 1);
+    assert_eq!(json["writerQueueDepth"], 2);
+    assert_eq!(json["wsSystemConnections"], 2);
+    assert_eq!(json["wsCpuConnections"], 1);
+    assert_eq!(json["wsRamConnections"], 0);
+    let agg = &json["aggregation"];
+    assert_eq!(agg["passesTotal"], 2);
+    assert_eq!(agg["rawBucketsTotal"], 7);
+    assert_eq!(agg["rolledUpBucketsTotal"], 2);
+    assert_eq!(agg["rawRowsDeletedTotal"], 420);
+    assert_eq!(agg["minuteRowsDeletedTotal"], 10);
+    assert_eq!(agg["prunedRawTotal"], 9);
+    assert_eq!(agg["prunedAggregatedTotal"], 4);
+    assert_eq!(agg["lastPassMs"], 250);
+    let collection = &json["collection"];
+    assert_eq!(collection["ticksTotal"], 2);
+    assert_eq!(collection["lastMs"], 1500);
+    assert_eq!(collection["maxMs"], 1500);
+    assert_eq!(collection["meanMs"], 900.0);
+    assert_eq!(collection["slowTicksTotal"], 1);
+    assert_eq!(collection["degradedTicksTotal"], 2);
+    assert_eq!(collection["failuresTotal"]["docker"], 2);
+    assert_eq!(collection["failuresTotal"]["network"], 1);
+    assert_eq!(collection["failuresTotal"]["cpu"], 0);
+    let own = &json["selfStats"];
+    assert_eq!(own["cpuPercent"], 1.5);
+    assert_eq!(own["rssBytes"], 12_000_000);
+    assert_eq!(own["openFds"], 17);
+    assert_eq!(own["tokioTasks"], 9);
+    assert!(own["dbFileBytes"].is_null());
+}
+
+#[tokio::test]
+async fn metrics_endpoint_serves_prometheus_text() {
+    let (server, _dir) = test_server(recorded_metrics()).await;
+    let response = server.get("/metrics").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header("content-type")
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain; version=0.0.4")
+    );
+    let body = response.text();
+    for line in [
+        "# TYPE homeserver_snapshots_saved_total counter",
+        "homeserver_snapshots_saved_total{node=\"nas\"} 42",
+        "homeserver_worker_restarts_total{node=\"nas\"} 3",
+        "homeserver_snapshots_dropped_total{node=\"nas\"} 1",
+        "homeserver_writer_queue_depth{node=\"nas\"} 2",
+        "homeserver_aggregation_passes_total{node=\"nas\"} 2",
+        "homeserver_aggregation_raw_buckets_total{node=\"nas\"} 7",
+        "homeserver_pruned_raw_rows_total{node=\"nas\"} 9",
+        "homeserver_pruned_aggregated_rows_total{node=\"nas\"} 4",
+        "# TYPE homeserver_history_blob_unknown_version_total counter",
+        "# TYPE homeserver_aggregation_last_pass_seconds gauge",
+        "homeserver_aggregation_last_pass_seconds{node=\"nas\"} 0.25",
+        "homeserver_ws_system_connections{node=\"nas\"} 2",
+        "homeserver_ws_cpu_connections{node=\"nas\"} 1",
+        "homeserver_collection_ticks_total{node=\"nas\"} 2",
+        "homeserver_collection_slow_ticks_total{node=\"nas\"} 1",
+        "homeserver_collection_last_seconds{node=\"nas\"} 1.5",
+        "homeserver_collection_degraded_ticks_total{node=\"nas\"} 2",
+        "homeserver_collection_failures_total{node=\"nas\",source=\"docker\"} 2",
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "missing {line:?} in\n{body}"
+        );
+    }
+    // `server.node_name` labels every sample.
+    for line in body
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        assert!(line.contains("{node=\"nas\""), "unlabelled sample {line:?}");
+    }
+}
+
+#[tokio::test]
+async fn metrics_endpoint_serves_container_pids_gauges() {
+    let metrics = ServiceMetrics::default();
+    let mut container: ContainerStats = serde_json::from_value(serde_json::json!({
+        "id": "c1",
+        "name": "web",
+        "cpuPercent": 0.0,
+        "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0,
+        "state": "running",
+        "networkRxBytes": 0,
+        "networkTxBytes": 0,
+        "blockReadBytes": 0,
+        "blockWriteBytes": 0,
+        "pids": 45,
+        "pidsLimit": 50,
+        "pidsUsagePercent": 90.0,
+    }))
+    .unwrap();
+    let mut unlimited = container.clone();
+    unlimited.name = "db".into();
+    (
+        unlimited.pids,
+        unlimited.pids_limit,
+        unlimited.pids_usage_percent,
+    ) = (7, 0, 0.0);
+    container.id = "c2".into();
+    metrics.broadcast.record_latest(&FullSystemSnapshot {
+        containers: vec![container, unlimited],
+        ..common::snapshot(0)
+    });
+    let (server, _dir) = test_server(metrics).await;
+    let body = server.get("/metrics").await.text();
+    for line in [
+        "# TYPE homeserver_container_pids gauge",
+        "homeserver_container_pids{node=\"nas\",container=\"web\"} 45",
+        "homeserver_container_pids_limit{node=\"nas\",container=\"web\"} 50",
+        "homeserver_container_pids_usage_percent{node=\"nas\",container=\"web\"} 90",
+        "homeserver_container_pids{node=\"nas\",container=\"db\"} 7",
+        "homeserver_container_pids_limit{node=\"nas\",container=\"db\"} 0",
+        "homeserver_container_pids_usage_percent{node=\"nas\",container=\"db\"} 0",
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "missing {line:?} in\n{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn fresh_metrics_are_zero() {
+    let (server, _dir) = test_server(ServiceMetrics::default()).await;
+    let json: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(json["snapshotsSavedTotal"], 0);
+    assert_eq!(json["workerRestartsTotal"], 0);
+    // Process-wide: only its presence is stable across tests.
+    assert!(json["historyBlobUnknownVersionTotal"].is_u64());
+    assert_eq!(json["aggregation"]["passesTotal"], 0);
+    assert_eq!(json["collection"]["ticksTotal"], 0);
+    assert!(json["selfStats"].is_null(), "no tick yet");
+}
@@ -1,11 +1,13 @@
 // Integration tests: HTTP and WebSocket endpoints
 
+mod common;
+
 use axum_test::TestServer;
 use homeserver::config::AppConfig;
 use homeserver::models::{CpuStats, FullSystemSnapshot, RamStats, SystemInfo};
 use homeserver::routes;
+use homeserver::ws_connections::WsConnections;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
 use tempfile::TempDir;
 use tokio::sync::broadcast;
 
@@ -42,6 +44,9 @@ fn test_system_info() -> Arc<SystemInfo> {
         system_manufacturer: String::new(),
         system_model: "test-host".to_string(),
         processor_name: "TestCPU".to_string(),
+        node_name: "test-node".to_string(),
+        primary_ipv4: Some("192.168.1.10".to_string()),
+        primary_ipv6: None,
     })
 }
 
@@ -55,21 +60,19 @@ async fn test_app() -> (
     let config = test_app_config(db_path.to_str().unwrap());
     let (tx, _) = broadcast::channel(config.publishing.broadcast_capacity);
     let history_repo = Arc::new(
-        homeserver::history_repo::HistoryRepo::connect(
-            &config.database.path,
-            config.database.retention_days,
-        )
-        .await
-        .unwrap(),
+        homeserver::history_repo::HistoryRepo::connect(&config.database)
+            .await
+            .unwrap(),
     );
     history_repo.init().await.unwrap();
     let app = routes::app(
         tx.clone(),
         Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
         test_system_info(),
-        Arc::new(AtomicUsize::new(0)),
+        Arc::new(WsConnections::default()),
         config,
         history_repo,
+        Default::default(),
     );
     (app, tx, dir)
 }
@@ -90,7 +93,7 @@ async fn test_root_endpoint() {
     let server = TestServer::new(app);
     let response = server.get("/").await;
     response.assert_status_ok();
-    response.assert_text("Ktor: Hello from Rust homeserver!");
+    assert!(response.text().contains("<!doctype html>"));
 }
 
 #[tokio::test]
@@ -114,6 +117,17 @@ async fn test_version_endpoint() {
         Some("homeserver")
     );
     assert!(json.get("version").and_then(|v| v.as_str()).is_some());
+    // Build metadata is always set; "unknown" when git or rustc was unavailable.
+    for field in ["gitCommit", "buildTime", "rustcVersion", "target"] {
+        let value = json.get(field).and_then(|v| v.as_str()).unwrap_or_default();
+        assert!(!value.is_empty(), "{field} missing in {json}");
+    }
+    assert_eq!(
+        json["target"].as_str(),
+        Some(homeserver::version::TARGET),
+        "{json}"
+    );
+    assert!(json["rustcVersion"].as_str().unwrap().starts_with("rustc "));
 }
 
 #[tokio::test]
@@ -132,50 +146,8 @@ async fn test_api_info_endpoint() {
         json.get("processorName").and_then(|v| v.as_str()),
         Some("TestCPU")
     );
-}
-
-#[tokio::test]
-async fn test_api_history_endpoint() {
-    let (app, _, _dir) = test_app().await;
-    let server = TestServer::new(app);
-    let response = server.get("/api/history").await;
-    response.assert_status_ok();
-    let json: serde_json::Value = response.json();
-    assert!(json.is_array(), "history returns array");
-    let arr = json.as_array().unwrap();
-    assert!(
-        arr.is_empty() || arr[0].get("timestamp").is_some(),
-        "elements have timestamp"
-    );
-
-    // A realistic 1-hour window at 1-minute resolution is accepted.
-    let response = server
-        .get("/api/history?from=1700000000000&to=1700003600000&resolution=1m")
-        .await;
-    response.assert_status_ok();
-
-    // from >= to is rejected.
-    let response = server.get("/api/history?from=100&to=50").await;
-    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
-
-    // Span larger than the 31-day cap is rejected.
-    let response = server
-        .get("/api/history?from=0&to=9999999999999&resolution=1m")
-        .await;
-    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
-
-    // A valid span but too many points for the requested resolution is rejected
-    // (10 days at 1-second resolution = ~864k points > 50k cap).
-    let response = server
-        .get("/api/history?from=1700000000000&to=1700864000000&resolution=1s")
-        .await;
-    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
-
-    // Extreme bounds whose difference overflows i64 are rejected (not panicked on).
-    let response = server
-        .get("/api/history?from=-9223372036854775808&to=9223372036854775807")
-        .await;
-    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    assert_eq!(json["primaryIpv4"], "192.168.1.10");
+    assert!(json["primaryIpv6"].is_null());
 }
 
 // --- WebSocket message tests (require http_transport + ws feature) ---
@@ -215,7 +187,6 @@ async fn test_ws_ram_receives_json() {
 async fn test_ws_system_receives_broadcast_snapshot() {
     let (server, tx) = test_server_with_http().await;
     let snapshot = FullSystemSnapshot {
-        timestamp: 42,
         cpu: CpuStats {
             model: "test".into(),
             physical_cores: 1,
@@ -233,12 +204,11 @@ async fn test_ws_system_receives_broadcast_snapshot() {
             swap_used: 0,
             swap_free: 0,
         },
-        containers: vec![],
         storage: homeserver::models::StorageStats {
             partitions: vec![],
             disks: vec![],
         },
-        network: homeserver::models::NetworkStats { interfaces: vec![] },
+        network: homeserver::models::NetworkStats::default(),
         system: homeserver::models::SystemStatsDynamic {
             uptime_secs: 0,
             process_count: 0,
@@ -247,8 +217,7 @@ async fn test_ws_system_receives_broadcast_snapshot() {
             load_avg_5: 0.0,
             load_avg_15: 0.0,
         },
-        gpus: vec![],
-        smart: vec![],
+        ..common::snapshot(42)
     };
     let mut ws = server
         .get_websocket("/ws/system")
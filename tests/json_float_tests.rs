@@ -0,0 +1,230 @@
+// JSON float scrubbing: NaN/±Infinity → 0, percentages to 2 decimals, byte rates to integers,
+// through the models sent over WS / HTTP and the aggregation outputs.
+
+mod common;
+
+use homeserver::history_repo::aggregation::{aggregate_aggregated_snapshots, aggregate_snapshots};
+use homeserver::models::json_float::{finite, round_percent, round_rate};
+use homeserver::models::*;
+use serde_json::Value;
+
+fn snapshot(ts: u64, cpu_percent: f64, temperature: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            model: String::new(),
+            physical_cores: 0,
+            logical_cores: 0,
+            usage_percent: cpu_percent,
+            temperature,
+            core_usages: vec![f64::NAN, 37.1 + 0.2, f64::INFINITY],
+        },
+        ram: RamStats {
+            total: 0,
+            used: 0,
+            available: 0,
+            usage_percent: f64::NAN,
+            swap_total: 0,
+            swap_used: 0,
+            swap_free: 0,
+        },
+        network: NetworkStats::new(vec![InterfaceStat {
+            name: "eth0".into(),
+            display_name: "eth0".into(),
+            mac_address: String::new(),
+            ipv4: vec![],
+            ipv6: vec![],
+            bytes_sent: 0,
+            bytes_recv: 0,
+            packets_sent: 0,
+            packets_recv: 0,
+            speed: 0,
+            received_bytes_per_sec: 1234.5678,
+            transmitted_bytes_per_sec: f64::NEG_INFINITY,
+            is_up: true,
+            packets_recv_per_sec: 0.0,
+            packets_sent_per_sec: 0.0,
+        }]),
+        system: SystemStatsDynamic {
+            uptime_secs: 0,
+            process_count: 0,
+            thread_count: 0,
+            load_avg_1: f64::NAN,
+            load_avg_5: 0.5,
+            load_avg_15: f64::INFINITY,
+        },
+        ..common::snapshot(ts)
+    }
+}
+
+/// Every number in `v` is finite.
+fn assert_clean(v: &Value) {
+    match v {
+        Value::Number(n) => assert!(n.as_f64().is_some_and(f64::is_finite), "{n}"),
+        Value::Array(items) => items.iter().for_each(assert_clean),
+        Value::Object(map) => map.values().for_each(assert_clean),
+        _ => {}
+    }
+}
+
+#[test]
+fn helpers_scrub_and_round() {
+    assert_eq!(finite(f64::NAN), 0.0);
+    assert_eq!(finite(f64::INFINITY), 0.0);
+    assert_eq!(finite(f64::NEG_INFINITY), 0.0);
+    assert_eq!(finite(1.5), 1.5);
+
+    assert_eq!(round_percent(37.1 + 0.2), 37.3);
+    assert_eq!(round_percent(12.345), 12.35);
+    assert_eq!(round_percent(f64::NAN), 0.0);
+    assert_eq!(round_percent(f64::MAX), 0.0);
+    assert!(round_percent(-0.001).is_sign_positive());
+
+    assert_eq!(round_rate(1234.5678), 1235);
+    assert_eq!(round_rate(f64::INFINITY), 0);
+    assert_eq!(round_rate(f64::NAN), 0);
+}
+
+#[test]
+fn live_snapshot_json_has_no_nan_or_infinity() {
+    let snap = snapshot(1_000, f64::NAN, f64::INFINITY);
+    let json = serde_json::to_string(&snap).unwrap();
+    assert!(
+        !json.contains("Infinity") && !json.contains("NaN"),
+        "{json}"
+    );
+
+    let v: Value = serde_json::from_str(&json).unwrap();
+    assert_clean(&v);
+    assert_eq!(v["cpu"]["usagePercent"], 0.0);
+    assert_eq!(v["cpu"]["temperature"], 0.0);
+    assert_eq!(v["cpu"]["coreUsages"], serde_json::json!([0.0, 37.3, 0.0]));
+    assert_eq!(v["ram"]["usagePercent"], 0.0);
+    assert_eq!(v["system"]["loadAvg1"], 0.0);
+    assert_eq!(v["system"]["loadAvg15"], 0.0);
+    let eth0 = &v["network"]["interfaces"][0];
+    assert_eq!(eth0["receivedBytesPerSec"], 1235);
+    assert_eq!(eth0["transmittedBytesPerSec"], 0);
+
+    // Still readable by clients and agents posting to the ingest endpoint.
+    let back: FullSystemSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.network.interfaces[0].received_bytes_per_sec, 1235.0);
+}
+
+#[test]
+fn container_percent_and_rate_fields_are_rounded() {
+    let c = ContainerStats {
+        id: "abc".into(),
+        name: "web".into(),
+        image: String::new(),
+        cpu_percent: 37.1 + 0.2,
+        cpu_kernel_percent: f64::NAN,
+        cpu_user_percent: f64::INFINITY,
+        online_cpus: 1,
+        cpu_percent_of_host: 0.0,
+        memory_usage_bytes: 0,
+        memory_limit_bytes: 0,
+        state: ContainerState::Running,
+        network_rx_bytes: 0,
+        network_tx_bytes: 0,
+        network_rx_packets: 0,
+        network_tx_packets: 0,
+        network_rx_errors: 0,
+        network_tx_errors: 0,
+        network_rx_dropped: 0,
+        network_tx_dropped: 0,
+        block_read_bytes: 0,
+        block_write_bytes: 0,
+        block_read_ops: 0,
+        block_write_ops: 0,
+        pids: 0,
+        pids_limit: 0,
+        pids_usage_percent: 0.0,
+        cpu_throttled: false,
+        cpu_throttled_periods: 0,
+        cpu_throttled_time_ns: 0,
+        memory_max_usage_bytes: 0,
+        observed_at: 0,
+        network_rx_bytes_per_sec: 99.6,
+        network_tx_bytes_per_sec: 0.0,
+        block_read_bytes_per_sec: 0.0,
+        block_write_bytes_per_sec: f64::NAN,
+    };
+    let v: Value = serde_json::to_value(&c).unwrap();
+    assert_clean(&v);
+    assert_eq!(v["cpuPercent"], 37.3);
+    assert_eq!(v["cpuKernelPercent"], 0.0);
+    assert_eq!(v["cpuUserPercent"], 0.0);
+    assert_eq!(v["networkRxBytesPerSec"], 100);
+    assert_eq!(v["blockWriteBytesPerSec"], 0);
+}
+
+#[test]
+fn aggregate_snapshots_all_nan_yields_zeros_not_infinities() {
+    let snaps = vec![
+        snapshot(60_000, f64::NAN, f64::NAN),
+        snapshot(61_000, f64::NAN, f64::NAN),
+    ];
+    let agg = aggregate_snapshots(&snaps, 60_000, 60).unwrap();
+    assert_eq!(agg.cpu_load_avg, 0.0);
+    assert_eq!(agg.cpu_load_min, 0.0);
+    assert_eq!(agg.cpu_load_max, 0.0);
+    assert_eq!(agg.cpu_load_p95, None);
+    assert_eq!(agg.cpu_temperature_min, 0.0);
+    assert_eq!(agg.cpu_temperature_max, 0.0);
+
+    let json = serde_json::to_string(&agg).unwrap();
+    assert!(
+        !json.contains("Infinity") && !json.contains("NaN"),
+        "{json}"
+    );
+    assert_clean(&serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn aggregate_snapshots_skips_bad_readings_among_good_ones() {
+    let snaps = vec![
+        snapshot(60_000, 10.0, 40.0),
+        snapshot(61_000, f64::NAN, f64::INFINITY),
+        snapshot(62_000, 30.0, 50.0),
+    ];
+    let agg = aggregate_snapshots(&snaps, 60_000, 60).unwrap();
+    assert_eq!(agg.cpu_load_avg, 20.0);
+    assert_eq!(agg.cpu_load_min, 10.0);
+    assert_eq!(agg.cpu_load_max, 30.0);
+    assert_eq!(agg.cpu_temperature_min, 40.0);
+    assert_eq!(agg.cpu_temperature_max, 50.0);
+}
+
+#[test]
+fn rollup_of_pathological_children_is_clean() {
+    let mut bad = aggregate_snapshots(&[snapshot(60_000, 5.0, 40.0)], 60_000, 60).unwrap();
+    bad.cpu_load_min = f64::INFINITY;
+    bad.cpu_load_max = f64::NEG_INFINITY;
+    bad.cpu_load_avg = f64::NAN;
+    bad.cpu_load_p95 = Some(f64::NAN);
+    bad.cpu_temperature_min = f64::INFINITY;
+
+    let rolled = aggregate_aggregated_snapshots(&[bad.clone(), bad], 0, 3600).unwrap();
+    assert_eq!(rolled.cpu_load_min, 0.0);
+    assert_eq!(rolled.cpu_load_max, 0.0);
+    assert_eq!(rolled.cpu_load_avg, 0.0);
+    assert_eq!(rolled.cpu_load_p95, None);
+    assert_clean(&serde_json::to_value(&rolled).unwrap());
+}
+
+#[test]
+fn history_envelope_json_is_scrubbed() {
+    let env = HistoryEnvelope {
+        cpu_load_min: f64::INFINITY,
+        cpu_load_max: 87.666666,
+        memory_used_min: 0,
+        memory_used_max: 0,
+        cpu_load_p95: Some(f64::NAN),
+        memory_used_p95: None,
+    };
+    let v: Value = serde_json::to_value(&env).unwrap();
+    assert_clean(&v);
+    assert_eq!(v["cpuLoadMin"], 0.0);
+    assert_eq!(v["cpuLoadMax"], 87.67);
+    assert_eq!(v["cpuLoadP95"], 0.0);
+}
@@ -0,0 +1,285 @@
+// homeserver-cli library functions: WAL guard for write commands, dump (recent and range),
+// stats, prune --older-than, offline aggregation, vacuum, blob verification and deletion.
+
+mod common;
+
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::history_repo::{HistoryRepo, HistoryTable};
+use homeserver::maintenance::{self, DumpRange};
+use homeserver::models::*;
+use sqlx::sqlite::SqlitePool;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const MS_PER_HOUR: i64 = 3_600_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn snapshot(ts: i64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.0,
+            ..Default::default()
+        },
+        ..common::snapshot(ts as u64)
+    }
+}
+
+fn config(dir: &TempDir) -> DatabaseConfig {
+    DatabaseConfig {
+        path: dir.path().join("h.db").display().to_string(),
+        ..Default::default()
+    }
+}
+
+/// A closed database (no `-wal` left) with raw rows at `raw_ts` and daily rows at `daily_ts`.
+async fn seeded(dir: &TempDir, raw_ts: &[i64], daily_ts: &[i64]) -> DatabaseConfig {
+    let config = config(dir);
+    let repo = HistoryRepo::connect(&config).await.unwrap();
+    repo.init().await.unwrap();
+    let info = SystemInfo {
+        os_family: "box".into(),
+        ..Default::default()
+    };
+    let snaps: Vec<_> = raw_ts.iter().map(|&ts| snapshot(ts)).collect();
+    repo.save_snapshots(&snaps, &info).await.unwrap();
+    for &ts in daily_ts {
+        let agg = aggregate_snapshots(&[snapshot(ts)], ts, 86400).unwrap();
+        repo.save_aggregated_snapshot(&agg).await.unwrap();
+    }
+    repo.close().await;
+    assert!(!maintenance::server_holds_wal(&config.path));
+    config
+}
+
+#[test]
+fn parse_duration_accepts_units_and_plain_seconds() {
+    let secs = |s| maintenance::parse_duration(s).unwrap().as_secs();
+    assert_eq!(secs("90s"), 90);
+    assert_eq!(secs("15m"), 900);
+    assert_eq!(secs("12h"), 43_200);
+    assert_eq!(secs("30d"), 2_592_000);
+    assert_eq!(secs(" 3600 "), 3600);
+    for bad in ["", "0", "0d", "d", "1w", "-5m", "99999999999999999999d"] {
+        assert!(maintenance::parse_duration(bad).is_err(), "{bad}");
+    }
+}
+
+#[tokio::test]
+async fn write_commands_refuse_while_the_wal_is_held() {
+    let dir = TempDir::new().unwrap();
+    let config = seeded(&dir, &[1_000], &[]).await;
+
+    // A "server" with the database open.
+    let server = HistoryRepo::connect(&config).await.unwrap();
+    server.init().await.unwrap();
+    assert!(maintenance::server_holds_wal(&config.path));
+    let err = maintenance::open_for_write(&config, false)
+        .await
+        .err()
+        .expect("refused");
+    assert!(err.to_string().contains("--force"), "{err}");
+
+    let forced = maintenance::open_for_write(&config, true).await.unwrap();
+    forced.close().await;
+    server.close().await;
+
+    let repo = maintenance::open_for_write(&config, false).await.unwrap();
+    repo.close().await;
+    assert!(
+        !maintenance::server_holds_wal(&config.path),
+        "closed cleanly"
+    );
+
+    let missing = DatabaseConfig {
+        path: dir.path().join("typo.db").display().to_string(),
+        ..Default::default()
+    };
+    assert!(maintenance::open_for_write(&missing, true).await.is_err());
+    assert!(!std::path::Path::new(&missing.path).exists(), "not created");
+
+    // `import` may fill a new database, but honours the same guard.
+    let imported = maintenance::open_for_import(&missing, false).await.unwrap();
+    imported.close().await;
+    assert!(std::path::Path::new(&missing.path).exists(), "created");
+    let _server = HistoryRepo::connect(&missing).await.unwrap();
+    assert!(maintenance::open_for_import(&missing, false).await.is_err());
+}
+
+#[tokio::test]
+async fn dump_reads_recent_rows_or_a_range_read_only() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms();
+    let raw: Vec<i64> = (0..10).map(|i| now - 10 * 60_000 + i * 60_000).collect();
+    let day = ((now - 20 * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+    let config = seeded(&dir, &raw, &[day]).await;
+    let repo = HistoryRepo::connect_read_only(&config.path).await.unwrap();
+
+    let recent = maintenance::dump(&repo, 3, None, 24).await.unwrap();
+    let stamps: Vec<u64> = recent.iter().map(|s| s.timestamp).collect();
+    assert_eq!(
+        stamps,
+        raw[7..].iter().map(|&t| t as u64).collect::<Vec<_>>()
+    );
+    assert_eq!(recent[0].system.os_family, "box", "static info merged in");
+
+    // Range across the aggregated tiers and raw: the daily row plus raw rows in 5-minute buckets.
+    let range = DumpRange {
+        from_ms: day - MS_PER_HOUR,
+        to_ms: now + 1,
+        resolution_secs: 300,
+    };
+    let ranged = maintenance::dump(&repo, 3, Some(range), 24).await.unwrap();
+    assert_eq!(ranged.first().unwrap().timestamp, day as u64);
+    assert!(
+        ranged.len() > 1 && ranged.len() < raw.len() + 1,
+        "{}",
+        ranged.len()
+    );
+    assert!(ranged.iter().all(|s| s.cpu.usage_percent == 12.0));
+
+    // Read-only: the reader neither writes nor holds the database for writing.
+    assert!(repo.prune_before(i64::MAX).await.is_err());
+}
+
+#[tokio::test]
+async fn stats_reports_rows_and_tiers() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms();
+    let day = ((now - 2 * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+    let config = seeded(&dir, &[now - 2_000, now - 1_000], &[day]).await;
+    let repo = HistoryRepo::connect_read_only(&config.path).await.unwrap();
+    let report = maintenance::stats(&repo).await.unwrap();
+    assert_eq!((report.db.raw_rows, report.db.aggregated_rows), (2, 1));
+    assert_eq!(report.tiers[0].resolution_seconds, 0);
+    assert_eq!(report.tiers[0].rows, 2);
+    let daily = report
+        .tiers
+        .iter()
+        .find(|t| t.resolution_seconds == 86400)
+        .unwrap();
+    assert_eq!(daily.rows, 1);
+    assert!(report.file_bytes > 0 && report.page_count > 0);
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json["db"]["rawRows"].is_number() && json["freePercent"].is_number());
+}
+
+#[tokio::test]
+async fn prune_deletes_raw_and_aggregated_rows_past_the_age() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms();
+    let old_day = ((now - 40 * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+    let new_day = ((now - 5 * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+    let config = seeded(
+        &dir,
+        &[now - 35 * MS_PER_DAY, now - 31 * MS_PER_DAY, now - 1_000],
+        &[old_day, new_day],
+    )
+    .await;
+    let repo = maintenance::open_for_write(&config, false).await.unwrap();
+    // Far beyond `max_prune_fraction`: an explicit prune is not guarded.
+    let report = maintenance::prune(&repo, Duration::from_secs(30 * 86_400), now)
+        .await
+        .unwrap();
+    assert_eq!(report.cutoff_ms, now - 30 * MS_PER_DAY);
+    assert_eq!((report.raw_rows, report.aggregated_rows), (2, 1));
+    let stats = repo.db_stats().await.unwrap();
+    assert_eq!((stats.raw_rows, stats.aggregated_rows), (1, 1));
+    assert_eq!(
+        stats.blob_store_entries, 2,
+        "storage + network of the kept row"
+    );
+    repo.close().await;
+}
+
+#[tokio::test]
+async fn aggregate_and_vacuum_run_offline() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms();
+    // Raw rows past raw_retention_hours roll into the first tier.
+    let old: Vec<i64> = (0..120)
+        .map(|i| now - 30 * MS_PER_HOUR + i * 1_000)
+        .collect();
+    let config = seeded(&dir, &old, &[]).await;
+    let repo = maintenance::open_for_write(&config, false).await.unwrap();
+    let agg_config = AggregationWorkerConfig::from_database(&config);
+    assert_eq!(agg_config.aggregation_tiers, config.aggregation_tiers);
+    assert_eq!(
+        agg_config.vacuum_incremental,
+        config.vacuum_mode == "incremental"
+    );
+    let report = run_one_tick(&repo, &agg_config).await.unwrap();
+    assert!(report.raw_buckets > 0, "{report:?}");
+    assert_eq!(repo.db_stats().await.unwrap().raw_rows, 0);
+
+    let (before, after) = maintenance::vacuum(&repo).await.unwrap();
+    assert!(before.freelist_count > 0, "{before:?}");
+    assert_eq!(after.freelist_count, 0);
+    repo.close().await;
+}
+
+#[tokio::test]
+async fn verify_reports_corrupt_blobs() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms();
+    let day = ((now - 2 * MS_PER_DAY) / MS_PER_DAY) * MS_PER_DAY;
+    let config = seeded(&dir, &[now - 2_000, now - 1_000], &[day]).await;
+
+    let repo = maintenance::open_read_only(&config.path).await.unwrap();
+    let clean = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+    assert_eq!((clean.raw_rows, clean.aggregated_rows), (2, 1));
+    assert!(clean.corrupt.is_empty(), "{:?}", clean.corrupt);
+    repo.close().await;
+
+    let pool = SqlitePool::connect(&format!("sqlite:{}", config.path))
+        .await
+        .unwrap();
+    for sql in [
+        "UPDATE system_history SET cpu_data = X'01FFFFFF' WHERE id = 1",
+        "UPDATE system_history SET storage_hash = X'00' WHERE id = 2",
+        "UPDATE system_history_aggregated SET container_data = X'01FFFFFFFFFFFFFFFF'",
+    ] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+
+    let repo = maintenance::open_read_only(&config.path).await.unwrap();
+    let report = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+    let found: Vec<_> = report
+        .corrupt
+        .iter()
+        .map(|c| (c.table, c.id, c.column))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (HistoryTable::Raw, 1, "cpu_data"),
+            (HistoryTable::Raw, 2, "storage_data"),
+            (HistoryTable::Aggregated, 1, "container_data"),
+        ]
+    );
+    let versions: Vec<u8> = report.corrupt.iter().map(|c| c.version).collect();
+    assert_eq!(versions, [1, 0, 1]);
+    assert!(report.corrupt[1].reason.contains("blob_store"));
+    repo.close().await;
+    // The read-only pass tidied up after itself, so the write guard lets --delete through.
+    assert!(!maintenance::server_holds_wal(&config.path));
+
+    let repo = maintenance::open_for_write(&config, false).await.unwrap();
+    let deleted = maintenance::delete_corrupt(&repo, &report).await.unwrap();
+    assert_eq!(deleted, (2, 1));
+    let after = repo
+        .verify_blobs(i64::MIN, i64::MAX, u64::MAX)
+        .await
+        .unwrap();
+    assert_eq!((after.raw_rows, after.aggregated_rows), (0, 0));
+    assert_eq!(repo.db_stats().await.unwrap().blob_store_entries, 0);
+    repo.close().await;
+}
@@ -40,10 +40,12 @@ fn test_container_stats_serialization() {
     let c = ContainerStats {
         id: "abc123".into(),
         name: "foo".into(),
+        image: String::new(),
         cpu_percent: 1.5,
         cpu_kernel_percent: 0.0,
         cpu_user_percent: 0.0,
         online_cpus: 1,
+        cpu_percent_of_host: 0.0,
         memory_usage_bytes: 1000,
         memory_limit_bytes: 256 * 1024 * 1024,
         state: ContainerState::Running,
@@ -61,12 +63,19 @@ fn test_container_stats_serialization() {
         block_write_ops: 0,
         pids: 10,
         pids_limit: 0,
+        pids_usage_percent: 0.0,
         cpu_throttled: false,
         cpu_throttled_periods: 0,
         cpu_throttled_time_ns: 0,
         memory_max_usage_bytes: 0,
+        observed_at: 0,
+        network_rx_bytes_per_sec: 512.0,
+        network_tx_bytes_per_sec: 0.0,
+        block_read_bytes_per_sec: 0.0,
+        block_write_bytes_per_sec: 0.0,
     };
     let json = serde_json::to_string(&c).unwrap();
+    assert!(json.contains("\"networkRxBytesPerSec\":512,"));
     assert!(json.contains("\"memoryUsageBytes\""));
     assert!(json.contains("\"cpuPercent\""));
     let back: ContainerStats = serde_json::from_str(&json).unwrap();
@@ -99,7 +108,7 @@ fn test_full_system_snapshot_serialization() {
             partitions: vec![],
             disks: vec![],
         },
-        network: NetworkStats { interfaces: vec![] },
+        network: NetworkStats::default(),
         system: SystemStatsDynamic {
             uptime_secs: 0,
             process_count: 0,
@@ -110,12 +119,31 @@ fn test_full_system_snapshot_serialization() {
         },
         gpus: vec![],
         smart: vec![],
+        probes: vec![],
+        sensors: vec![],
+        checks: vec![],
+        degraded: vec!["network".into()],
+        self_stats: None,
+        historical: false,
     };
     let json = serde_json::to_string(&snapshot).unwrap();
     assert!(json.contains("\"timestamp\""));
     assert!(json.contains("\"usagePercent\""));
+    assert!(json.contains("\"degraded\":[\"network\"]"));
     let back: FullSystemSnapshot = serde_json::from_str(&json).unwrap();
     assert_eq!(back.timestamp, snapshot.timestamp);
+    assert_eq!(back.degraded, ["network"]);
+
+    // Snapshots serialized before `degraded` existed still parse.
+    let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+    legacy.as_object_mut().unwrap().remove("degraded");
+    let back: FullSystemSnapshot = serde_json::from_value(legacy).unwrap();
+    assert!(back.degraded.is_empty());
+
+    // The wincode encoding (history exports) leaves it out.
+    let bytes = wincode::serialize(&snapshot).unwrap();
+    let back: FullSystemSnapshot = wincode::deserialize(&bytes).unwrap();
+    assert!(back.degraded.is_empty());
 }
 
 #[test]
@@ -169,6 +197,8 @@ fn test_interface_stat_json_roundtrip() {
         received_bytes_per_sec: 0.0,
         transmitted_bytes_per_sec: 0.0,
         is_up: true,
+        packets_recv_per_sec: 0.0,
+        packets_sent_per_sec: 0.0,
     };
     let json = serde_json::to_string(&i).unwrap();
     let back: InterfaceStat = serde_json::from_str(&json).unwrap();
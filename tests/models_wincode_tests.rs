@@ -28,7 +28,7 @@ fn test_full_system_snapshot_wincode_roundtrip() {
             partitions: vec![],
             disks: vec![],
         },
-        network: NetworkStats { interfaces: vec![] },
+        network: NetworkStats::default(),
         system: SystemStatsDynamic {
             uptime_secs: 0,
             process_count: 0,
@@ -57,6 +57,12 @@ fn test_full_system_snapshot_wincode_roundtrip() {
             reallocated_sectors: Some(0),
             wear_level_percent: Some(5),
         }],
+        probes: vec![],
+        sensors: vec![],
+        checks: vec![],
+        degraded: vec![],
+        self_stats: None,
+        historical: false,
     };
     let bytes = wincode::serialize(&snapshot).unwrap();
     let back: FullSystemSnapshot = wincode::deserialize(&bytes).unwrap();
@@ -104,23 +110,23 @@ fn test_storage_stats_json_and_wincode_roundtrip() {
 
 #[test]
 fn test_network_stats_json_and_wincode_roundtrip() {
-    let n = NetworkStats {
-        interfaces: vec![InterfaceStat {
-            name: "lo".into(),
-            display_name: "loopback".into(),
-            mac_address: "".into(),
-            ipv4: vec![],
-            ipv6: vec!["::1".into()],
-            bytes_sent: 0,
-            bytes_recv: 0,
-            packets_sent: 0,
-            packets_recv: 0,
-            speed: 0,
-            received_bytes_per_sec: 0.0,
-            transmitted_bytes_per_sec: 0.0,
-            is_up: true,
-        }],
-    };
+    let n = NetworkStats::new(vec![InterfaceStat {
+        name: "lo".into(),
+        display_name: "loopback".into(),
+        mac_address: "".into(),
+        ipv4: vec![],
+        ipv6: vec!["::1".into()],
+        bytes_sent: 0,
+        bytes_recv: 0,
+        packets_sent: 0,
+        packets_recv: 0,
+        speed: 0,
+        received_bytes_per_sec: 0.0,
+        transmitted_bytes_per_sec: 0.0,
+        is_up: true,
+        packets_recv_per_sec: 0.0,
+        packets_sent_per_sec: 0.0,
+    }]);
     let json = serde_json::to_string(&n).unwrap();
     let _: NetworkStats = serde_json::from_str(&json).unwrap();
     let bytes = wincode::serialize(&n).unwrap();
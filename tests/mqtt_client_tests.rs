@@ -0,0 +1,204 @@
+// MQTT client: the rate-limited publish loop against a recording sink, and the full client
+// against a minimal in-process broker.
+
+mod common;
+
+use homeserver::config::{CpuPercentMode, MqttConfig};
+use homeserver::models::*;
+use homeserver::mqtt::{self, MqttMessage, MqttSink, Publisher, publish_loop};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+
+fn snapshot(cpu: f64, containers: &[&str]) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            used: 4_000_000_000,
+            usage_percent: 25.04,
+            ..Default::default()
+        },
+        containers: containers
+            .iter()
+            .map(|name| {
+                serde_json::from_value(serde_json::json!({
+                    "id": name,
+                    "name": name,
+                    "cpuPercent": 1.25,
+                    "memoryUsageBytes": 1024,
+                    "memoryLimitBytes": 4096,
+                    "state": "running",
+                }))
+                .unwrap()
+            })
+            .collect(),
+        ..common::snapshot(1000)
+    }
+}
+
+#[derive(Default)]
+struct Recorder(Mutex<Vec<MqttMessage>>);
+
+impl MqttSink for Recorder {
+    fn publish(&self, message: MqttMessage) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(message);
+        Ok(())
+    }
+}
+
+impl Recorder {
+    fn states(&self, topic: &str) -> Vec<String> {
+        let messages = self.0.lock().unwrap();
+        messages
+            .iter()
+            .filter(|m| m.topic == topic)
+            .map(|m| m.payload.clone())
+            .collect()
+    }
+}
+
+async fn eventually(what: &str, check: impl Fn() -> bool) {
+    for _ in 0..300 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("timed out waiting for {what}");
+}
+
+#[tokio::test]
+async fn publish_loop_waits_for_a_connection_and_rate_limits() {
+    let sink = Arc::new(Recorder::default());
+    let (tx, _) = broadcast::channel(16);
+    let (connection, connection_rx) = watch::channel(None);
+    let shutdown = CancellationToken::new();
+    let task = tokio::spawn(publish_loop(
+        Publisher::new(&MqttConfig::default()),
+        sink.clone(),
+        tx.subscribe(),
+        connection_rx,
+        Duration::from_millis(100),
+        shutdown.clone(),
+    ));
+
+    tx.send(snapshot(5.0, &[])).unwrap();
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert!(sink.0.lock().unwrap().is_empty(), "not connected yet");
+
+    connection.send_replace(Some(1));
+    for cpu in [10.0, 20.0, 30.0] {
+        tx.send(snapshot(cpu, &[])).unwrap();
+    }
+    eventually("a publish", || {
+        !sink.states("homeserver/cpu/usage").is_empty()
+    })
+    .await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(
+        sink.states("homeserver/cpu/usage"),
+        ["30.0"],
+        "one publish of the latest snapshot, none without a new one"
+    );
+
+    shutdown.cancel();
+    task.await.unwrap();
+}
+
+/// One MQTT packet: the fixed-header byte and the body.
+async fn read_packet(stream: &mut tokio::net::TcpStream) -> Option<(u8, Vec<u8>)> {
+    let header = stream.read_u8().await.ok()?;
+    let (mut len, mut shift) = (0usize, 0);
+    loop {
+        let byte = stream.read_u8().await.ok()?;
+        len |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body).await.ok()?;
+    Some((header, body))
+}
+
+/// A broker for one QoS 0 client: accepts the CONNECT and records (topic, payload, retain) of
+/// every PUBLISH until DISCONNECT, recorded as `("DISCONNECT", "", false)`.
+async fn broker() -> (u16, Arc<Mutex<Vec<(String, String, bool)>>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let seen = received.clone();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        while let Some((header, body)) = read_packet(&mut stream).await {
+            match header >> 4 {
+                1 => stream.write_all(&[0x20, 2, 0, 0]).await.unwrap(),
+                3 => {
+                    let len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                    let topic = String::from_utf8_lossy(&body[2..2 + len]).into_owned();
+                    let payload = String::from_utf8_lossy(&body[2 + len..]).into_owned();
+                    seen.lock().unwrap().push((topic, payload, header & 1 == 1));
+                }
+                12 => stream.write_all(&[0xd0, 0]).await.unwrap(),
+                14 => {
+                    seen.lock()
+                        .unwrap()
+                        .push(("DISCONNECT".into(), "".into(), false));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+    (port, received)
+}
+
+#[tokio::test]
+async fn client_publishes_to_a_broker_and_says_goodbye() {
+    let (port, received) = broker().await;
+    let config = MqttConfig {
+        broker_url: Some(format!("mqtt://127.0.0.1:{port}")),
+        publish_interval_secs: 1,
+        ..Default::default()
+    };
+    let (tx, _) = broadcast::channel(16);
+    let shutdown = CancellationToken::new();
+    let handle = mqtt::spawn(
+        config,
+        CpuPercentMode::PerCore,
+        tx.clone(),
+        shutdown.clone(),
+        Arc::new(AtomicU64::new(0)),
+    )
+    .unwrap();
+    let has = |topic: &str| received.lock().unwrap().iter().any(|(t, ..)| t == topic);
+    for _ in 0..100 {
+        if has("homeserver/cpu/usage") {
+            break;
+        }
+        let _ = tx.send(snapshot(42.0, &["db"]));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(has("homeserver/container/db/memory"));
+    assert!(received.lock().unwrap().iter().any(|(topic, _, retain)| {
+        topic == "homeassistant/sensor/homeserver/cpu_usage/config" && *retain
+    }));
+
+    shutdown.cancel();
+    handle.await.unwrap();
+    eventually("DISCONNECT", || has("DISCONNECT")).await;
+    let received = received.lock().unwrap();
+    let status: Vec<_> = received
+        .iter()
+        .filter(|(topic, ..)| topic == "homeserver/status")
+        .map(|(_, payload, _)| payload.as_str())
+        .collect();
+    assert_eq!(status, ["online", "offline"]);
+}
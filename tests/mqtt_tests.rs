@@ -0,0 +1,160 @@
+// MQTT publishing: [mqtt] config, the snapshot → topic/payload mapping and Home Assistant
+// discovery (pure).
+
+mod common;
+
+use homeserver::config::{AppConfig, MqttConfig};
+use homeserver::models::*;
+use homeserver::mqtt::{self, MqttMessage, Publisher};
+
+fn snapshot(cpu: f64, containers: &[&str]) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ram: RamStats {
+            used: 4_000_000_000,
+            usage_percent: 25.04,
+            ..Default::default()
+        },
+        containers: containers
+            .iter()
+            .map(|name| {
+                serde_json::from_value(serde_json::json!({
+                    "id": name,
+                    "name": name,
+                    "cpuPercent": 1.25,
+                    "memoryUsageBytes": 1024,
+                    "memoryLimitBytes": 4096,
+                    "state": "running",
+                }))
+                .unwrap()
+            })
+            .collect(),
+        ..common::snapshot(1000)
+    }
+}
+
+fn payload_of<'a>(messages: &'a [MqttMessage], topic: &str) -> Option<&'a str> {
+    messages
+        .iter()
+        .find(|m| m.topic == topic)
+        .map(|m| m.payload.as_str())
+}
+
+#[test]
+fn mqtt_config_defaults_and_validation() {
+    let config = AppConfig::default().mqtt;
+    assert_eq!(config.broker().unwrap(), None);
+    assert_eq!(
+        (config.base_topic.as_str(), config.discovery_prefix.as_str()),
+        ("homeserver", "homeassistant")
+    );
+    assert_eq!((config.qos, config.publish_interval_secs), (0, 10));
+
+    let config = AppConfig::load_from_str(
+        "[mqtt]\nbroker_url = \"mqtt://broker.lan\"\nusername = \"ha\"\npassword = \"hunter2\"\nqos = 1\n",
+    )
+    .unwrap()
+    .mqtt;
+    assert_eq!(config.broker().unwrap(), Some(("broker.lan".into(), 1883)));
+    assert!(!format!("{config:?}").contains("hunter2"));
+    let with_port = MqttConfig {
+        broker_url: Some("tcp://10.0.0.2:11883".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        with_port.broker().unwrap(),
+        Some(("10.0.0.2".into(), 11883))
+    );
+
+    for bad in [
+        "broker_url = \"http://broker\"",
+        "broker_url = \"mqtt://broker:x\"",
+        "qos = 3",
+        "publish_interval_secs = 0",
+        "base_topic = \"home/#\"",
+    ] {
+        let err = AppConfig::load_from_str(&format!("[mqtt]\n{bad}\n")).unwrap_err();
+        assert!(err.to_string().contains("mqtt."), "{bad}: {err}");
+    }
+}
+
+#[test]
+fn snapshot_maps_to_metric_topics() {
+    let sensors = mqtt::sensors(&snapshot(12.345, &["db", "media/plex"]));
+    let states: Vec<_> = sensors
+        .iter()
+        .map(|s| mqtt::state_message("homeserver", s))
+        .collect();
+    assert_eq!(payload_of(&states, "homeserver/cpu/usage"), Some("12.3"));
+    assert_eq!(
+        payload_of(&states, "homeserver/ram/used"),
+        Some("4000000000")
+    );
+    assert_eq!(payload_of(&states, "homeserver/ram/usage"), Some("25.0"));
+    assert_eq!(
+        payload_of(&states, "homeserver/container/db/cpu"),
+        Some("1.2")
+    );
+    assert_eq!(
+        payload_of(&states, "homeserver/container/media_plex/memory"),
+        Some("1024")
+    );
+    assert!(states.iter().all(|m| !m.retain));
+    assert!(
+        payload_of(&states, "homeserver/disk/usage").is_none(),
+        "no partitions, no disk sensor"
+    );
+}
+
+#[test]
+fn discovery_config_describes_the_sensor() {
+    let sensors = mqtt::sensors(&snapshot(1.0, &["media/plex"]));
+    let ram = sensors.iter().find(|s| s.id == "ram_used").unwrap();
+    let message = mqtt::discovery_message("homeassistant", "home/server", ram);
+    assert_eq!(
+        message.topic,
+        "homeassistant/sensor/home_server/ram_used/config"
+    );
+    assert!(message.retain);
+    let config: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+    assert_eq!(config["state_topic"], "home/server/ram/used");
+    assert_eq!(config["unique_id"], "home_server_ram_used");
+    assert_eq!(config["unit_of_measurement"], "B");
+    assert_eq!(config["device_class"], "data_size");
+    assert_eq!(config["availability_topic"], "home/server/status");
+    assert_eq!(config["device"]["identifiers"][0], "home_server");
+
+    let plex = sensors.iter().find(|s| s.path.ends_with("/cpu")).unwrap();
+    assert_eq!(plex.id, "container_media_plex_cpu");
+    let load = sensors.iter().find(|s| s.id == "load_1").unwrap();
+    let config = mqtt::discovery_message("homeassistant", "homeserver", load).payload;
+    assert!(!config.contains("unit_of_measurement"), "{config}");
+}
+
+#[test]
+fn publisher_announces_once_per_connection_and_for_new_containers() {
+    let mut publisher = Publisher::new(&MqttConfig::default());
+    let is_discovery = |m: &MqttMessage| m.topic.starts_with("homeassistant/");
+    let first = publisher.messages(&snapshot(1.0, &["db"]), 1);
+    assert_eq!(payload_of(&first, "homeserver/status"), Some("online"));
+    let sensors = mqtt::sensors(&snapshot(1.0, &["db"])).len();
+    assert_eq!(first.iter().filter(|m| is_discovery(m)).count(), sensors);
+
+    let second = publisher.messages(&snapshot(2.0, &["db"]), 1);
+    assert!(!second.iter().any(is_discovery));
+    assert_eq!(second.len(), sensors, "states only");
+
+    let grown = publisher.messages(&snapshot(2.0, &["db", "web"]), 1);
+    let announced: Vec<_> = grown.iter().filter(|m| is_discovery(m)).collect();
+    assert_eq!(announced.len(), 2, "web CPU and memory");
+
+    let reconnected = publisher.messages(&snapshot(2.0, &["db", "web"]), 2);
+    assert!(payload_of(&reconnected, "homeserver/status").is_some());
+    assert_eq!(
+        reconnected.iter().filter(|m| is_discovery(m)).count(),
+        sensors + 2
+    );
+}
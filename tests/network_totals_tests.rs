@@ -0,0 +1,208 @@
+// NetworkStats.totals and the per-interface packet rates: the physical-interface heuristic, the
+// rate math against synthetic previous readings, aggregation and history round-trips.
+
+mod common;
+
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::models::*;
+use homeserver::sysinfo_repo::network_rates::apply_network_rates;
+use tempfile::TempDir;
+
+fn iface(
+    name: &str,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+) -> InterfaceStat {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "displayName": name,
+        "macAddress": "",
+        "ipv4": [],
+        "ipv6": [],
+        "bytesSent": tx_bytes,
+        "bytesRecv": rx_bytes,
+        "packetsSent": tx_packets,
+        "packetsRecv": rx_packets,
+        "speed": 1000,
+        "isUp": true,
+    }))
+    .unwrap()
+}
+
+/// `name` with the given byte rates and packet rates already filled in.
+fn with_rates(name: &str, rx: f64, tx: f64, rx_packets: f64, tx_packets: f64) -> InterfaceStat {
+    InterfaceStat {
+        received_bytes_per_sec: rx,
+        transmitted_bytes_per_sec: tx,
+        packets_recv_per_sec: rx_packets,
+        packets_sent_per_sec: tx_packets,
+        ..iface(name, 0, 0, 0, 0)
+    }
+}
+
+fn snapshot(ts: u64, network: NetworkStats) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        network,
+        ..common::snapshot(ts)
+    }
+}
+
+#[test]
+fn only_physical_interfaces_count_towards_the_totals() {
+    for name in ["eth0", "enp3s0", "eno1", "wlan0", "wlp2s0", "bond0"] {
+        assert!(is_physical_interface(name), "{name}");
+    }
+    for name in [
+        "lo",
+        "veth1a2b3c",
+        "docker0",
+        "br-4f1e2d",
+        "br0",
+        "vmbr0",
+        "virbr0",
+        "cni0",
+        "flannel.1",
+        "cali12ab",
+        "vxlan.calico",
+        "tun0",
+        "tap0",
+        "wg0",
+        "tailscale0",
+        "zt5u4y",
+    ] {
+        assert!(!is_physical_interface(name), "{name}");
+    }
+
+    // One container download: it crosses eth0, docker0 and the container's veth.
+    let network = NetworkStats::new(vec![
+        with_rates("eth0", 1000.0, 100.0, 10.0, 2.0),
+        with_rates("docker0", 1000.0, 100.0, 10.0, 2.0),
+        with_rates("veth9f8e", 1000.0, 100.0, 10.0, 2.0),
+        with_rates("lo", 50.0, 50.0, 1.0, 1.0),
+        with_rates("wlan0", 24.0, 4.0, 0.5, 0.5),
+    ]);
+    assert_eq!(
+        network.totals,
+        NetworkTotals {
+            rx_bytes_per_sec: 1024.0,
+            tx_bytes_per_sec: 104.0,
+            rx_packets_per_sec: 10.5,
+            tx_packets_per_sec: 2.5,
+        }
+    );
+}
+
+#[test]
+fn rates_come_from_the_previous_reading() {
+    let prev = vec![iface("eth0", 1_000, 500, 10, 5), iface("wlan0", 0, 0, 0, 0)];
+    let mut now = vec![
+        iface("eth0", 3_000, 1_500, 30, 9),
+        iface("wlan0", 400, 0, 4, 0),
+        iface("eth1", 9_000, 9_000, 90, 90),
+    ];
+    apply_network_rates(&mut now, &prev, 2.0);
+
+    assert_eq!(now[0].received_bytes_per_sec, 1000.0);
+    assert_eq!(now[0].transmitted_bytes_per_sec, 500.0);
+    assert_eq!(now[0].packets_recv_per_sec, 10.0);
+    assert_eq!(now[0].packets_sent_per_sec, 2.0);
+    assert_eq!(now[1].packets_recv_per_sec, 2.0);
+    // No previous reading for eth1 yet.
+    assert_eq!(now[2].received_bytes_per_sec, 0.0);
+    assert_eq!(now[2].packets_recv_per_sec, 0.0);
+
+    let totals = NetworkStats::new(now).totals;
+    assert_eq!(totals.rx_bytes_per_sec, 1200.0);
+    assert_eq!(totals.rx_packets_per_sec, 12.0);
+}
+
+#[test]
+fn a_counter_reset_skips_only_that_rate() {
+    let prev = vec![iface("eth0", 5_000, 500, 50, 5)];
+    let mut now = vec![iface("eth0", 100, 1_500, 1, 15)];
+    apply_network_rates(&mut now, &prev, 1.0);
+    assert_eq!(now[0].received_bytes_per_sec, 0.0);
+    assert_eq!(now[0].packets_recv_per_sec, 0.0);
+    assert_eq!(now[0].transmitted_bytes_per_sec, 1000.0);
+    assert_eq!(now[0].packets_sent_per_sec, 10.0);
+
+    // A zero interval leaves every rate alone.
+    let mut same = vec![iface("eth0", 9_000, 9_000, 90, 90)];
+    apply_network_rates(&mut same, &prev, 0.0);
+    assert_eq!(same[0].received_bytes_per_sec, 0.0);
+}
+
+#[test]
+fn aggregation_averages_the_totals() {
+    let snaps = [
+        snapshot(
+            0,
+            NetworkStats::new(vec![with_rates("eth0", 100.0, 10.0, 4.0, 2.0)]),
+        ),
+        snapshot(
+            1000,
+            NetworkStats::new(vec![
+                with_rates("eth0", 300.0, 30.0, 8.0, 6.0),
+                with_rates("veth1", 999.0, 999.0, 99.0, 99.0),
+            ]),
+        ),
+    ];
+    let agg = aggregate_snapshots(&snaps, 0, 60).unwrap();
+    assert_eq!(
+        agg.network.totals,
+        NetworkTotals {
+            rx_bytes_per_sec: 200.0,
+            tx_bytes_per_sec: 20.0,
+            rx_packets_per_sec: 6.0,
+            tx_packets_per_sec: 4.0,
+        }
+    );
+    assert_eq!(agg.network.interfaces[0].packets_recv_per_sec, 6.0);
+}
+
+#[tokio::test]
+async fn history_rows_derive_the_byte_totals() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&homeserver::config::DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let network = NetworkStats::new(vec![
+        with_rates("eth0", 100.0, 10.0, 4.0, 2.0),
+        with_rates("docker0", 100.0, 10.0, 4.0, 2.0),
+    ]);
+    repo.save_snapshots(&[snapshot(1000, network)], &SystemInfo::default())
+        .await
+        .unwrap();
+
+    let (_, rows) = repo.get_recent_snapshots(1).await.unwrap();
+    let totals = rows[0].network.totals;
+    assert_eq!(
+        (totals.rx_bytes_per_sec, totals.tx_bytes_per_sec),
+        (100.0, 10.0)
+    );
+    // Packet rates are live only.
+    assert_eq!(totals.rx_packets_per_sec, 0.0);
+    assert_eq!(rows[0].network.interfaces[0].packets_recv_per_sec, 0.0);
+}
+
+#[test]
+fn json_without_the_new_fields_still_parses() {
+    let n: NetworkStats =
+        serde_json::from_value(serde_json::json!({ "interfaces": [iface("eth0", 1, 2, 3, 4)] }))
+            .unwrap();
+    assert_eq!(n.totals, NetworkTotals::default());
+    let json = serde_json::to_value(NetworkStats::new(vec![with_rates(
+        "eth0", 1500.0, 0.0, 2.0, 0.0,
+    )]))
+    .unwrap();
+    assert_eq!(json["totals"]["rxBytesPerSec"], 1500.0);
+    assert_eq!(json["totals"]["rxPacketsPerSec"], 2.0);
+    assert_eq!(json["interfaces"][0]["packetsRecvPerSec"], 2.0);
+}
@@ -0,0 +1,128 @@
+// `server.node_name`: validated, stamped on the detected SystemInfo and served on /api/info, the
+// /ws/system welcome and /api/stats; also the local name for /api/history?node= and ingest. Stored system_info rows: history_node_tests.rs; /api/bootstrap
+// and the /metrics `node` label: bootstrap_tests.rs and integration_stats_tests.rs.
+
+mod common;
+
+use axum::http::{HeaderValue, StatusCode, header};
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::{FullSystemSnapshot, IngestBatch, SystemInfo};
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+async fn repo(dir: &TempDir) -> (AppConfig, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("node.db").display().to_string();
+    config.server.node_name = "nas-1".into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    (config, repo)
+}
+
+#[test]
+fn node_name_defaults_to_a_valid_name_and_is_validated() {
+    let default = AppConfig::default().server.node_name;
+    assert!(homeserver::config::valid_node(&default), "{default}");
+
+    let config = AppConfig::load_from_str("[server]\nnode_name = \"rack-2.lan\"\n").unwrap();
+    assert_eq!(config.server.node_name, "rack-2.lan");
+    let err = AppConfig::load_from_str("[server]\nnode_name = \"has space\"\n").unwrap_err();
+    assert!(format!("{err:#}").contains("server.node_name"), "{err:#}");
+}
+
+#[tokio::test]
+async fn node_name_reaches_info_ws_welcome_and_stats() {
+    let dir = TempDir::new().unwrap();
+    let (config, repo) = repo(&dir).await;
+    let sysinfo_repo = SysinfoRepo::new().with_node_name("nas-1");
+    let info = sysinfo_repo.get_system_info().await.unwrap();
+    assert_eq!(info.node_name, "nas-1");
+    let server = TestServer::builder().http_transport().build(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(sysinfo_repo),
+        Arc::new(info),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    let body: serde_json::Value = server.get("/api/info").await.json();
+    assert_eq!(body["nodeName"], "nas-1");
+    let body: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(body["nodeName"], "nas-1");
+    assert!(
+        body["snapshotsSavedTotal"].is_u64(),
+        "counters stay top-level"
+    );
+
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let welcome: serde_json::Value =
+        tokio::time::timeout(Duration::from_secs(3), ws.receive_json())
+            .await
+            .expect("no welcome");
+    assert_eq!(welcome["type"], "info");
+    assert_eq!(welcome["systemInfo"]["nodeName"], "nas-1");
+}
+
+#[tokio::test]
+async fn node_name_keys_history_and_ingest_instead_of_the_hostname() {
+    const TS: u64 = 1_700_000_000_000;
+    let dir = TempDir::new().unwrap();
+    let (mut config, repo) = repo(&dir).await;
+    config.remote_write.ingest_api_key = Some(Secret::new("k"));
+    let hostname = AppConfig::default().server.node_name;
+    assert_ne!(hostname, "nas-1");
+    repo.save_snapshots(&[common::snapshot(TS + 1000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+    let ingest = |node: &str| {
+        server
+            .post("/api/ingest")
+            .add_header(header::AUTHORIZATION, HeaderValue::from_static("Bearer k"))
+            .json(&IngestBatch {
+                node: node.into(),
+                snapshots: vec![common::snapshot(TS + 2000)],
+            })
+    };
+
+    // The configured name is this instance; the hostname is just another node.
+    ingest("nas-1").await.assert_status(StatusCode::BAD_REQUEST);
+    ingest(&hostname).await.assert_status_ok();
+
+    let history = |node: &str| {
+        server.get(&format!(
+            "/api/history?from={TS}&to={}&resolution=1s&node={node}",
+            TS + 60_000
+        ))
+    };
+    let local: Vec<FullSystemSnapshot> = history("nas-1").await.json();
+    assert_eq!(
+        local.iter().map(|s| s.timestamp).collect::<Vec<_>>(),
+        [TS + 1000]
+    );
+    let pushed: Vec<FullSystemSnapshot> = history(&hostname).await.json();
+    assert_eq!(
+        pushed.iter().map(|s| s.timestamp).collect::<Vec<_>>(),
+        [TS + 2000]
+    );
+}
@@ -0,0 +1,80 @@
+// GET /api/cpu and /api/ram: one reading as camelCase JSON, reused for READING_CACHE_TTL; the
+// first CPU reading on an unsampled repo is the baseline, a later one reports real usage.
+
+use axum_test::TestServer;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::routes::{self, READING_CACHE_TTL};
+use homeserver::sysinfo_repo::SysinfoRepo;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+fn server() -> TestServer {
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        Default::default(),
+        None,
+        ServiceMetrics::default(),
+    ))
+}
+
+/// Keep one core busy for `d`, so the next CPU refresh sees usage.
+fn spin(d: Duration) {
+    let until = Instant::now() + d;
+    let mut x = 0u64;
+    while Instant::now() < until {
+        x = std::hint::black_box(x.wrapping_add(1));
+    }
+}
+
+#[tokio::test]
+async fn ram_reading_is_camel_case_and_cached() {
+    let server = server();
+    let first = server.get("/api/ram").await;
+    first.assert_status_ok();
+    let body: serde_json::Value = first.json();
+    for field in ["total", "used", "available", "usagePercent", "swapTotal"] {
+        assert!(body.get(field).is_some(), "missing {field} in {body}");
+    }
+    assert!(body["total"].as_u64().unwrap() > 0);
+    // Within the TTL the same reading is served again.
+    let again: serde_json::Value = server.get("/api/ram").await.json();
+    assert_eq!(again, body);
+}
+
+#[tokio::test]
+async fn cpu_reading_reports_usage_after_the_baseline() {
+    assert!(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL < READING_CACHE_TTL / 2);
+    let server = server();
+    let baseline: serde_json::Value = server.get("/api/cpu").await.json();
+    for field in [
+        "model",
+        "physicalCores",
+        "logicalCores",
+        "usagePercent",
+        "temperature",
+        "coreUsages",
+    ] {
+        assert!(
+            baseline.get(field).is_some(),
+            "missing {field} in {baseline}"
+        );
+    }
+    assert_eq!(baseline["usagePercent"], 0.0);
+
+    // Past MINIMUM_CPU_UPDATE_INTERVAL but within the TTL: still the cached baseline.
+    let started = Instant::now();
+    spin(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL + Duration::from_millis(20));
+    let cached: serde_json::Value = server.get("/api/cpu").await.json();
+    assert!(started.elapsed() < READING_CACHE_TTL);
+    assert_eq!(cached, baseline);
+
+    spin(READING_CACHE_TTL);
+    let fresh: serde_json::Value = server.get("/api/cpu").await.json();
+    let usage = fresh["usagePercent"].as_f64().unwrap();
+    assert!(usage > 0.0 && usage <= 100.0, "{fresh}");
+}
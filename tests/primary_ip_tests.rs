@@ -0,0 +1,191 @@
+// Primary IPv4 / IPv6 detection: the /proc/net/route parser over fixtures, the interface
+// preference, the fields on /api/info and the /ws/system welcome, and system_info rows stored
+// before the fields existed.
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::sysinfo_repo::primary_ip::{parse_default_route, primary_addresses};
+use sqlx::sqlite::SqlitePool;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const ROUTE_HEADER: &str =
+    "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+
+/// `SystemInfo` as wincode-encoded before the primary addresses were added.
+#[derive(wincode::SchemaWrite)]
+struct SystemInfoV2 {
+    os_family: String,
+    os_manufacturer: String,
+    os_version: String,
+    system_manufacturer: String,
+    system_model: String,
+    processor_name: String,
+    node_name: String,
+}
+
+fn iface(name: &str, addrs: &[&str]) -> (String, Vec<IpAddr>) {
+    let addrs = addrs.iter().map(|a| a.parse().unwrap()).collect();
+    (name.to_string(), addrs)
+}
+
+#[test]
+fn parse_default_route_picks_the_lowest_metric_default() {
+    let table = format!(
+        "{ROUTE_HEADER}\
+         eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+         wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n\
+         eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+         docker0\t000011AC\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0\n"
+    );
+    assert_eq!(parse_default_route(&table).as_deref(), Some("eth0"));
+}
+
+#[test]
+fn parse_default_route_ignores_down_and_malformed_routes() {
+    // Flags 0002 (gateway, not up), then a truncated line and a non-hex flag column.
+    let table = format!(
+        "{ROUTE_HEADER}\
+         eth0\t00000000\t0101A8C0\t0002\t0\t0\t0\t00000000\t0\t0\t0\n\
+         eth1\t00000000\t0101A8C0\n\
+         eth2\t00000000\t0101A8C0\tzz\t0\t0\t0\t00000000\t0\t0\t0\n\
+         eth3\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0\n"
+    );
+    assert_eq!(parse_default_route(&table), None);
+    assert_eq!(parse_default_route(ROUTE_HEADER), None);
+    assert_eq!(parse_default_route(""), None);
+}
+
+#[test]
+fn the_default_route_interface_wins() {
+    let interfaces = [
+        iface("docker0", &["172.17.0.1"]),
+        iface("eth0", &["192.168.1.10", "fe80::1", "2001:db8::10"]),
+        iface("lo", &["127.0.0.1", "::1"]),
+        iface("wg0", &["10.8.0.1", "fd00::1"]),
+    ];
+    let (v4, v6) = primary_addresses(Some("wg0"), &interfaces);
+    assert_eq!(v4.unwrap().to_string(), "10.8.0.1");
+    assert_eq!(v6.unwrap().to_string(), "fd00::1");
+
+    // No default route: physical interfaces before bridges, link-local skipped.
+    let (v4, v6) = primary_addresses(None, &interfaces);
+    assert_eq!(v4.unwrap().to_string(), "192.168.1.10");
+    assert_eq!(v6.unwrap().to_string(), "2001:db8::10");
+}
+
+#[test]
+fn a_default_interface_without_addresses_falls_back() {
+    let interfaces = [
+        iface("ppp0", &[]),
+        iface("eth1", &["fe80::2"]),
+        iface("eth0", &["10.0.0.5"]),
+        iface("br0", &["2001:db8::1"]),
+    ];
+    let (v4, v6) = primary_addresses(Some("ppp0"), &interfaces);
+    assert_eq!(v4.unwrap().to_string(), "10.0.0.5");
+    assert_eq!(v6.unwrap().to_string(), "2001:db8::1");
+
+    let loopback_only = [iface("lo", &["127.0.0.1", "::1"])];
+    assert_eq!(primary_addresses(Some("lo"), &loopback_only), (None, None));
+    assert_eq!(primary_addresses(None, &[]), (None, None));
+}
+
+#[tokio::test]
+async fn primary_addresses_reach_info_and_the_ws_welcome() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("ip.db").display().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let sysinfo_repo = SysinfoRepo::new();
+    let info = sysinfo_repo.get_system_info().await.unwrap();
+    let server = TestServer::builder().http_transport().build(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(sysinfo_repo),
+        Arc::new(info.clone()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    // Whatever the sandbox has (possibly nothing): the keys are always present.
+    let expected = serde_json::to_value(&info).unwrap();
+    let body: serde_json::Value = server.get("/api/info").await.json();
+    for key in ["primaryIpv4", "primaryIpv6"] {
+        assert!(body.get(key).is_some(), "{key} missing from /api/info");
+        assert_eq!(body[key], expected[key]);
+    }
+
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let welcome: serde_json::Value =
+        tokio::time::timeout(Duration::from_secs(3), ws.receive_json())
+            .await
+            .expect("no welcome");
+    assert_eq!(welcome["type"], "info");
+    for key in ["primaryIpv4", "primaryIpv6"] {
+        assert!(
+            welcome["systemInfo"].get(key).is_some(),
+            "{key} missing from the welcome"
+        );
+    }
+}
+
+#[tokio::test]
+async fn primary_addresses_are_stored_and_old_rows_still_load() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("ip.db");
+    let repo = HistoryRepo::connect(&homeserver::config::DatabaseConfig {
+        path: db_path.display().to_string(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let info = SystemInfo {
+        node_name: "nas-1".into(),
+        primary_ipv4: Some("192.168.1.10".into()),
+        primary_ipv6: Some("2001:db8::10".into()),
+        ..Default::default()
+    };
+    repo.save_system_info(&info).await.unwrap();
+    assert_eq!(repo.get_stored_system_info().await.unwrap(), Some(info));
+
+    let legacy = wincode::serialize(&SystemInfoV2 {
+        os_family: "Linux".into(),
+        os_manufacturer: String::new(),
+        os_version: "12".into(),
+        system_manufacturer: String::new(),
+        system_model: "old-host".into(),
+        processor_name: "cpu".into(),
+        node_name: "nas-1".into(),
+    })
+    .unwrap();
+    let pool = SqlitePool::connect(&format!("sqlite:{}", db_path.display()))
+        .await
+        .unwrap();
+    sqlx::query("UPDATE system_info SET data = $1 WHERE id = 1")
+        .bind(legacy)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let stored = repo.get_stored_system_info().await.unwrap().unwrap();
+    assert_eq!(stored.node_name, "nas-1");
+    assert_eq!(stored.system_model, "old-host");
+    assert_eq!((stored.primary_ipv4, stored.primary_ipv6), (None, None));
+
+    let parsed: SystemInfo = serde_json::from_str(r#"{"osFamily":"Linux","osManufacturer":"","osVersion":"","systemManufacturer":"","systemModel":"","processorName":""}"#).unwrap();
+    assert_eq!(parsed.primary_ipv4, None);
+}
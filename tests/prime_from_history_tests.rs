@@ -0,0 +1,230 @@
+// Startup priming: with a pre-populated database, worker::prime_from_history serves the newest
+// stored snapshot as the latest one (tagged `historical`) on /api/bootstrap and the broadcast,
+// and a worker started afterwards falls back to its readings when a collector fails.
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+use axum_test::TestServer;
+use futures_util::future::BoxFuture;
+use homeserver::config::{AppConfig, DatabaseConfig};
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{self, BroadcastMetrics, StatsCollector, WorkerDeps};
+use homeserver::{metrics::ServiceMetrics, routes};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn snapshot(timestamp: u64, cpu_percent: f64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: cpu_percent,
+            ..Default::default()
+        },
+        system: SystemStatsDynamic {
+            process_count: 321,
+            ..Default::default()
+        },
+        ..common::snapshot(timestamp)
+    }
+}
+
+/// A database holding `snapshots`.
+async fn repo(dir: &TempDir, snapshots: &[FullSystemSnapshot]) -> Arc<HistoryRepo> {
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    repo.save_snapshots(snapshots, &SystemInfo::default())
+        .await
+        .unwrap();
+    Arc::new(repo)
+}
+
+/// CPU always fails; everything else succeeds.
+struct FailingCpu;
+
+impl StatsCollector for FailingCpu {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(async { Err(anyhow::anyhow!("cpu unavailable")) })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
+    }
+}
+
+#[tokio::test]
+async fn primes_the_latest_snapshot_and_publishes_it_once_tagged() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms() as u64;
+    let repo = repo(
+        &dir,
+        &[snapshot(now - 2000, 10.0), snapshot(now - 1000, 42.0)],
+    )
+    .await;
+    let metrics = ServiceMetrics::default();
+    let (tx, mut rx) = broadcast::channel(4);
+
+    let primed =
+        worker::prime_from_history(repo.as_ref(), 5 * MINUTE, &metrics.broadcast, Some(&tx))
+            .await
+            .unwrap()
+            .expect("recent snapshot primes");
+    assert_eq!(primed.timestamp, now - 1000);
+    assert!(primed.historical);
+    let latest = metrics.broadcast.latest_snapshot().unwrap();
+    assert_eq!(latest.timestamp, now - 1000);
+    assert!(latest.historical);
+    let published = rx.try_recv().unwrap();
+    assert_eq!(published.cpu.usage_percent, 42.0);
+    assert!(published.historical);
+
+    // Only once: the latest snapshot is already set.
+    let again =
+        worker::prime_from_history(repo.as_ref(), 5 * MINUTE, &metrics.broadcast, Some(&tx))
+            .await
+            .unwrap();
+    assert!(again.is_none());
+    assert!(rx.try_recv().is_err());
+
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let server = TestServer::new(routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        metrics,
+    ));
+    let body: serde_json::Value = server
+        .get("/api/bootstrap?history=false&capabilities=false")
+        .await
+        .json();
+    assert_eq!(body["latest"]["timestamp"], now - 1000);
+    assert_eq!(body["latest"]["historical"], true);
+}
+
+#[tokio::test]
+async fn stale_or_missing_history_and_earlier_ticks_do_not_prime() {
+    let dir = TempDir::new().unwrap();
+    let empty = repo(&dir, &[]).await;
+    let metrics = BroadcastMetrics::default();
+    assert!(
+        worker::prime_from_history(empty.as_ref(), 5 * MINUTE, &metrics, None)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms() as u64;
+    let old = repo(&dir, &[snapshot(now - 10 * 60_000, 1.0)]).await;
+    assert!(
+        worker::prime_from_history(old.as_ref(), 5 * MINUTE, &metrics, None)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(metrics.latest_snapshot().is_none());
+
+    // A tick recorded first: the live snapshot stays.
+    metrics.record_latest(&snapshot(now, 99.0));
+    assert!(
+        worker::prime_from_history(old.as_ref(), 60 * MINUTE, &metrics, None)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let latest = metrics.latest_snapshot().unwrap();
+    assert_eq!(latest.timestamp, now);
+    assert!(!latest.historical);
+}
+
+#[tokio::test]
+async fn worker_falls_back_to_the_primed_readings() {
+    let dir = TempDir::new().unwrap();
+    let now = common::now_ms() as u64;
+    let repo = repo(&dir, &[snapshot(now - 1000, 42.0)]).await;
+    let broadcast_metrics = Arc::new(BroadcastMetrics::default());
+    worker::prime_from_history(repo.as_ref(), 5 * MINUTE, &broadcast_metrics, None)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (tx, mut rx) = broadcast::channel(16);
+    let (stop, shutdown_rx) = tokio::sync::oneshot::channel();
+    let handle = worker::spawn(
+        WorkerDeps {
+            collector: Arc::new(FailingCpu),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: repo.into(),
+            tx,
+            write_tx: None,
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: broadcast_metrics.clone(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(50),
+    );
+
+    let live = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!live.historical);
+    assert_eq!(live.degraded, ["cpu"]);
+    assert_eq!(live.cpu.usage_percent, 42.0, "last known-good from history");
+    assert_eq!(live.system.process_count, 0, "successful readings win");
+    assert!(!broadcast_metrics.latest_snapshot().unwrap().historical);
+
+    stop.send(()).unwrap();
+    handle.await.unwrap();
+}
+
+#[test]
+fn prime_window_defaults_to_five_minutes() {
+    assert_eq!(
+        AppConfig::default().monitoring.prime_from_history_minutes,
+        5
+    );
+    let config =
+        AppConfig::load_from_str("[monitoring]\nprime_from_history_minutes = 0\n").unwrap();
+    assert_eq!(config.monitoring.prime_from_history_minutes, 0);
+}
@@ -0,0 +1,288 @@
+// Latency probes: scheduling per `interval_secs`, window statistics, `[probes]` validation,
+// ICMP echo encoding, the TCP fallback, GET /api/probes, and persistence + aggregation.
+
+mod common;
+
+use axum_test::TestServer;
+use futures_util::future::BoxFuture;
+use homeserver::config::{AppConfig, DatabaseConfig, ProbeTarget, ProbesConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::aggregate_snapshots;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::probes::{
+    ProbeMethod, ProbeOutcome, ProbeSet, ProbeWindow, Prober, SystemProber, icmp,
+};
+use homeserver::routes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+
+/// Answers each target with its scripted round trips (ms) in order; `None` = lost.
+#[derive(Default)]
+struct Scripted {
+    rtts: Mutex<HashMap<String, VecDeque<Option<u64>>>>,
+}
+
+impl Scripted {
+    fn with(target: &str, rtts: &[Option<u64>]) -> Self {
+        let scripted = Self::default();
+        scripted
+            .rtts
+            .lock()
+            .unwrap()
+            .insert(target.into(), rtts.iter().copied().collect());
+        scripted
+    }
+}
+
+impl Prober for Scripted {
+    fn probe<'a>(&'a self, target: &'a ProbeTarget) -> BoxFuture<'a, ProbeOutcome> {
+        let rtt = self
+            .rtts
+            .lock()
+            .unwrap()
+            .get_mut(&target.target)
+            .and_then(VecDeque::pop_front)
+            .flatten();
+        Box::pin(async move {
+            ProbeOutcome {
+                method: ProbeMethod::Icmp,
+                rtt: rtt.map(Duration::from_millis),
+            }
+        })
+    }
+}
+
+fn target(name: &str, interval_secs: u64) -> ProbeTarget {
+    ProbeTarget {
+        name: name.into(),
+        target: name.into(),
+        interval_secs,
+        port: None,
+    }
+}
+
+fn config(targets: Vec<ProbeTarget>, window: usize) -> ProbesConfig {
+    ProbesConfig {
+        targets,
+        window,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn targets_are_probed_on_their_own_interval() {
+    let set = ProbeSet::new(&config(vec![target("a", 1), target("b", 3)], 10));
+    let prober = Scripted::default();
+    let t0 = Instant::now();
+    assert_eq!(set.run_due(&prober, t0).await, 2, "never probed: due");
+    assert_eq!(set.next_due(), Some(t0 + Duration::from_secs(1)));
+    assert_eq!(
+        set.run_due(&prober, t0 + Duration::from_millis(500)).await,
+        0
+    );
+    assert_eq!(set.run_due(&prober, t0 + Duration::from_secs(1)).await, 1);
+    assert_eq!(set.run_due(&prober, t0 + Duration::from_secs(2)).await, 1);
+    assert_eq!(set.run_due(&prober, t0 + Duration::from_secs(3)).await, 2);
+    assert_eq!(ProbeSet::default().next_due(), None);
+}
+
+#[tokio::test]
+async fn current_reports_probed_targets_in_config_order() {
+    let set = ProbeSet::new(&config(vec![target("lan", 1), target("wan", 1)], 10));
+    assert!(set.current().is_empty(), "nothing before the first probe");
+    let prober = Scripted::with("wan", &[Some(12)]);
+    set.run_due(&prober, Instant::now()).await;
+    let current = set.current();
+    let names: Vec<_> = current.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["lan", "wan"]);
+    assert_eq!(current[0].loss_percent, 100.0, "no answer scripted");
+    assert_eq!(current[0].rtt_ms, None);
+    assert_eq!(current[1].rtt_ms, Some(12.0));
+    assert_eq!(current[1].method, "icmp");
+}
+
+#[test]
+fn window_statistics_cover_only_the_last_results() {
+    let mut window = ProbeWindow::new(3);
+    let t = target("gw", 30);
+    assert_eq!(window.stat(&t), None);
+    for rtt in [Some(10), None, Some(30), Some(20)] {
+        window.record(ProbeOutcome {
+            method: ProbeMethod::Tcp,
+            rtt: rtt.map(Duration::from_millis),
+        });
+    }
+    // The 10 ms result was evicted: [lost, 30, 20].
+    let stat = window.stat(&t).unwrap();
+    assert_eq!(stat.samples, 3);
+    assert_eq!(stat.method, "tcp");
+    assert_eq!(stat.rtt_ms, Some(20.0));
+    assert_eq!(stat.rtt_avg_ms, Some(25.0));
+    assert_eq!(stat.rtt_min_ms, Some(20.0));
+    assert_eq!(stat.rtt_max_ms, Some(30.0));
+    assert!((stat.loss_percent - 100.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn probe_config_is_validated() {
+    let load = |targets: &str| AppConfig::load_from_str(&format!("[probes]\n{targets}"));
+    let config = load("[[probes.targets]]\nname = \"gw\"\ntarget = \"192.168.1.1\"\n").unwrap();
+    assert_eq!(config.probes.targets, vec![target_at("gw", "192.168.1.1")]);
+    assert_eq!(config.probes.window, 10);
+
+    let err = load("[[probes.targets]]\nname = \"gw\"\ntarget = \"gw\"\ninterval_secs = 0\n")
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("at least 1 second"), "{err:#}");
+    let dup = "[[probes.targets]]\nname = \"gw\"\ntarget = \"a\"\n";
+    let err = load(&format!("{dup}{dup}")).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("duplicate name 'gw'"),
+        "{err:#}"
+    );
+    assert!(load("[[probes.targets]]\nname = \"\"\ntarget = \"a\"\n").is_err());
+    assert!(load("[[probes.targets]]\nname = \"a\"\ntarget = \"a\"\nport = 0\n").is_err());
+    assert!(load("timeout_ms = 0\n").is_err());
+    assert!(load("window = 0\n").is_err());
+}
+
+fn target_at(name: &str, host: &str) -> ProbeTarget {
+    ProbeTarget {
+        target: host.into(),
+        ..target(name, 30)
+    }
+}
+
+#[test]
+fn icmp_echo_requests_carry_a_valid_checksum_and_replies_parse() {
+    let request = icmp::echo_request(false, 0x1234, 7);
+    assert_eq!(request[0], icmp::ECHO_REQUEST_V4);
+    assert_eq!(icmp::checksum(&request), 0, "checksum verifies");
+    assert_eq!(request[4..8], [0x12, 0x34, 0, 7]);
+
+    // An IPv4 reply as a raw socket reads it: 20-byte IP header, then the ICMP message.
+    let mut reply = vec![0x45; 1];
+    reply.extend_from_slice(&[0; 19]);
+    reply.extend_from_slice(&request);
+    reply[20] = icmp::ECHO_REPLY_V4;
+    assert_eq!(icmp::parse_echo_reply(&reply, false), Some((0x1234, 7)));
+    assert_eq!(icmp::parse_echo_reply(&request, false), None, "not a reply");
+    assert_eq!(
+        icmp::parse_echo_reply(&reply[..24], false),
+        None,
+        "truncated"
+    );
+
+    let mut v6 = icmp::echo_request(true, 1, 2);
+    assert_eq!(v6[0], icmp::ECHO_REQUEST_V6);
+    v6[0] = icmp::ECHO_REPLY_V6;
+    assert_eq!(icmp::parse_echo_reply(&v6, true), Some((1, 2)));
+}
+
+#[tokio::test]
+async fn tcp_probes_time_connects_and_count_refusals_as_answers() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let open = listener.local_addr().unwrap().port();
+    let closed = {
+        let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        l.local_addr().unwrap().port()
+    };
+    let prober = SystemProber::new(&ProbesConfig::default()).tcp_only();
+    for port in [open, closed] {
+        let probe = ProbeTarget {
+            port: Some(port),
+            ..target_at("local", "127.0.0.1")
+        };
+        let outcome = prober.probe(&probe).await;
+        assert_eq!(outcome.method, ProbeMethod::Tcp);
+        assert!(outcome.rtt.is_some(), "port {port} answered");
+    }
+}
+
+#[tokio::test]
+async fn api_probes_serves_the_current_results() {
+    let set = ProbeSet::new(&config(vec![target("gw", 30)], 10));
+    set.run_due(&Scripted::with("gw", &[Some(3)]), Instant::now())
+        .await;
+    let metrics = ServiceMetrics {
+        probes: Arc::new(set),
+        ..Default::default()
+    };
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        AppConfig::default(),
+        None,
+        metrics,
+    ));
+    let body: serde_json::Value = server.get("/api/probes").await.json();
+    assert_eq!(body[0]["name"], "gw");
+    assert_eq!(body[0]["rttMs"], 3.0);
+    assert_eq!(body[0]["lossPercent"], 0.0);
+    assert_eq!(body[0]["samples"], 1);
+}
+
+fn probe(rtt: f64, loss_percent: f64) -> ProbeStat {
+    ProbeStat {
+        name: "gw".into(),
+        target: "192.168.1.1".into(),
+        method: "icmp".into(),
+        rtt_ms: Some(rtt),
+        rtt_avg_ms: Some(rtt),
+        rtt_min_ms: Some(rtt - 1.0),
+        rtt_max_ms: Some(rtt + 1.0),
+        loss_percent,
+        samples: 10,
+    }
+}
+
+fn snapshot(ts: u64, probes: Vec<ProbeStat>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        probes,
+        ..common::snapshot(ts)
+    }
+}
+
+#[tokio::test]
+async fn probes_are_stored_and_aggregated() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let snap = snapshot(1_700_000_000_000, vec![probe(4.0, 10.0)]);
+    repo.save_snapshots(std::slice::from_ref(&snap), &SystemInfo::default())
+        .await
+        .unwrap();
+    let (_info, snaps) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(snaps[0].probes, snap.probes);
+
+    let agg = aggregate_snapshots(
+        &[snap, snapshot(1_700_000_002_000, vec![probe(8.0, 30.0)])],
+        1_700_000_000_000,
+        60,
+    )
+    .unwrap();
+    assert_eq!(agg.probes.len(), 1);
+    let gw = &agg.probes[0];
+    assert_eq!(gw.rtt_avg_ms, Some(6.0));
+    assert_eq!(gw.rtt_min_ms, Some(3.0));
+    assert_eq!(gw.rtt_max_ms, Some(9.0));
+    assert_eq!(gw.loss_percent, 20.0);
+    assert_eq!(gw.rtt_ms, Some(8.0), "latest sample");
+
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].probes, agg.probes);
+}
@@ -0,0 +1,236 @@
+// Remote write end to end: an edge instance's remote_write task pushes snapshots to a central
+// instance served on a local port; /api/history?node= on the central side reads them back.
+
+mod common;
+
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::IntoResponse;
+use homeserver::config::{AppConfig, RemoteWriteConfig, RemoteWriteFormat, Secret};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::remote_write;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+const BASE_TS: u64 = 1_700_000_000_000;
+
+fn snapshot(timestamp: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 42.0,
+            ..Default::default()
+        },
+        probes: vec![ProbeStat {
+            name: "router".into(),
+            target: "192.168.1.1".into(),
+            method: "icmp".into(),
+            rtt_ms: Some(1.25),
+            samples: 10,
+            ..Default::default()
+        }],
+        sensors: vec![SensorStat {
+            id: "nvme-0000:01:00.0/Composite".into(),
+            name: "nvme".into(),
+            label: "Composite".into(),
+            value: 38.5,
+            unit: "°C".into(),
+            ..Default::default()
+        }],
+        ..common::snapshot(timestamp)
+    }
+}
+
+/// The central instance on 127.0.0.1, answering 503 to everything while `down` is set.
+async fn serve_central(dir: &TempDir, down: Arc<AtomicBool>) -> (String, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("central.db").display().to_string();
+    config.server.node_name = "central".into();
+    config.remote_write.ingest_api_key = Some(Secret::new("s3cret"));
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let (tx, _) = broadcast::channel(4);
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo.clone(),
+        ServiceMetrics::default(),
+    )
+    .layer(middleware::from_fn(move |request: Request, next: Next| {
+        let down = down.clone();
+        async move {
+            if down.load(Ordering::SeqCst) {
+                StatusCode::SERVICE_UNAVAILABLE.into_response()
+            } else {
+                next.run(request).await
+            }
+        }
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, repo)
+}
+
+fn edge_config(url: &str, dir: &TempDir, format: RemoteWriteFormat) -> RemoteWriteConfig {
+    RemoteWriteConfig {
+        url: Some(url.into()),
+        api_key: Some(Secret::new("s3cret")),
+        format,
+        batch_size: 2,
+        flush_interval_secs: 1,
+        spill_dir: dir.path().join("spill").display().to_string(),
+        ..Default::default()
+    }
+}
+
+async fn history(url: &str, query: &str) -> Vec<FullSystemSnapshot> {
+    let response = reqwest::get(format!(
+        "{url}/api/history?from={}&to={}&resolution=1s{query}",
+        BASE_TS,
+        BASE_TS + 60_000
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+/// Poll `/api/history?node=edge` until it holds `count` snapshots.
+async fn wait_for_edge_rows(url: &str, count: usize) -> Vec<FullSystemSnapshot> {
+    for _ in 0..200 {
+        let rows = history(url, "&node=edge").await;
+        if rows.len() >= count {
+            return rows;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("edge rows never reached {count}");
+}
+
+fn spilled(dir: &TempDir) -> usize {
+    std::fs::read_dir(dir.path().join("spill"))
+        .map(|entries| entries.count())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn pushed_snapshots_are_stored_under_the_node_and_filtered() {
+    for format in [RemoteWriteFormat::Json, RemoteWriteFormat::Wincode] {
+        let (central_dir, edge_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let (url, repo) = serve_central(&central_dir, Arc::default()).await;
+        let local = vec![snapshot(BASE_TS + 500)];
+        repo.save_snapshots(&local, &SystemInfo::default())
+            .await
+            .unwrap();
+
+        let (tx, _) = broadcast::channel(16);
+        let shutdown = CancellationToken::new();
+        let task = remote_write::spawn(
+            edge_config(&url, &edge_dir, format),
+            "edge".into(),
+            tx.clone(),
+            shutdown.clone(),
+            Arc::new(AtomicU64::new(0)),
+        )
+        .unwrap();
+        // Let the task subscribe before sending.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for i in 1..=5 {
+            tx.send(snapshot(BASE_TS + i * 1000)).unwrap();
+        }
+
+        // Two full batches right away, the fifth snapshot with the next flush.
+        let rows = wait_for_edge_rows(&url, 5).await;
+        let timestamps: Vec<_> = rows.iter().map(|s| s.timestamp - BASE_TS).collect();
+        assert_eq!(timestamps, [1000, 2000, 3000, 4000, 5000], "{format:?}");
+        assert_eq!(rows[0].cpu.usage_percent, 42.0);
+        let sent = snapshot(BASE_TS + 1000);
+        assert_eq!(rows[0].probes, sent.probes, "{format:?}");
+        assert_eq!(rows[0].sensors, sent.sensors, "{format:?}");
+
+        // Without a node (or with the central's own name) only local rows come back.
+        for query in ["", "&node=central"] {
+            let local_rows = history(&url, query).await;
+            let timestamps: Vec<_> = local_rows.iter().map(|s| s.timestamp - BASE_TS).collect();
+            assert_eq!(timestamps, [500], "{query}");
+        }
+        assert!(history(&url, "&node=other").await.is_empty());
+
+        shutdown.cancel();
+        task.await.unwrap();
+        assert_eq!(spilled(&edge_dir), 0, "nothing left to spill");
+    }
+}
+
+#[tokio::test]
+async fn undelivered_batches_spill_to_disk_and_drain_when_central_returns() {
+    let (central_dir, edge_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let down = Arc::new(AtomicBool::new(true));
+    let (url, _repo) = serve_central(&central_dir, down.clone()).await;
+
+    let (tx, _) = broadcast::channel(64);
+    let shutdown = CancellationToken::new();
+    let config = RemoteWriteConfig {
+        batch_size: 1,
+        ..edge_config(&url, &edge_dir, RemoteWriteFormat::Json)
+    };
+    let task = remote_write::spawn(
+        config.clone(),
+        "edge".into(),
+        tx.clone(),
+        shutdown.clone(),
+        Arc::new(AtomicU64::new(0)),
+    )
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    for i in 1..=8 {
+        tx.send(snapshot(BASE_TS + i * 1000)).unwrap();
+    }
+    // Four batches stay in memory; the older ones go to disk.
+    for _ in 0..100 {
+        if spilled(&edge_dir) >= 4 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(spilled(&edge_dir), 4);
+
+    // Shutting down while the central instance is down keeps the rest on disk too.
+    shutdown.cancel();
+    task.await.unwrap();
+    assert_eq!(spilled(&edge_dir), 8);
+
+    // A new run delivers the backlog, oldest first, once the central instance is back.
+    down.store(false, Ordering::SeqCst);
+    let shutdown = CancellationToken::new();
+    let task = remote_write::spawn(
+        config,
+        "edge".into(),
+        tx,
+        shutdown.clone(),
+        Arc::new(AtomicU64::new(0)),
+    )
+    .unwrap();
+    let rows = wait_for_edge_rows(&url, 8).await;
+    let timestamps: Vec<_> = rows
+        .iter()
+        .map(|s| (s.timestamp - BASE_TS) / 1000)
+        .collect();
+    assert_eq!(timestamps, [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(spilled(&edge_dir), 0);
+    shutdown.cancel();
+    task.await.unwrap();
+}
@@ -0,0 +1,270 @@
+// Remote write: [remote_write] config, the on-disk spill, and POST /api/ingest auth,
+// validation and wincode decoding. End-to-end delivery between two instances:
+// remote_write_delivery_tests.rs.
+
+mod common;
+
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, RemoteWriteConfig, RemoteWriteFormat, Secret};
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::remote_write::Spill;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn snapshot(timestamp: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        cpu: CpuStats {
+            usage_percent: 12.5,
+            ..Default::default()
+        },
+        probes: vec![ProbeStat {
+            name: "router".into(),
+            rtt_ms: Some(0.75),
+            ..Default::default()
+        }],
+        sensors: vec![SensorStat {
+            id: "k10temp-pci-00c3/Tctl".into(),
+            value: 51.0,
+            ..Default::default()
+        }],
+        ..common::snapshot(timestamp)
+    }
+}
+
+fn batch(node: &str, timestamps: &[u64]) -> IngestBatch {
+    IngestBatch {
+        node: node.into(),
+        snapshots: timestamps.iter().copied().map(snapshot).collect(),
+    }
+}
+
+async fn central(dir: &TempDir) -> (TestServer, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("central.db").display().to_string();
+    config.server.node_name = "central".into();
+    config.remote_write.ingest_api_key = Some(Secret::new("s3cret"));
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let (tx, _) = broadcast::channel(4);
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo.clone(),
+        ServiceMetrics::default(),
+    );
+    (TestServer::new(app), repo)
+}
+
+fn bearer(key: &str) -> (HeaderName, HeaderValue) {
+    (
+        axum::http::header::AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {key}")).unwrap(),
+    )
+}
+
+#[test]
+fn remote_write_config_defaults_and_validation() {
+    let config = RemoteWriteConfig::default();
+    assert!(config.url.is_none() && config.ingest_api_key.is_none());
+    assert_eq!(config.format, RemoteWriteFormat::Json);
+    assert_eq!((config.batch_size, config.flush_interval_secs), (60, 10));
+
+    let config = AppConfig::load_from_str(
+        "[remote_write]\nurl = \"http://central:8081\"\napi_key = \"k3y\"\nformat = \"wincode\"\n",
+    )
+    .unwrap()
+    .remote_write;
+    assert_eq!(config.format, RemoteWriteFormat::Wincode);
+    assert!(!format!("{config:?}").contains("k3y"));
+
+    for bad in [
+        "url = \"ftp://central\"",
+        "api_key = \"\"",
+        "batch_size = 0",
+        "batch_size = 5000",
+        "flush_interval_secs = 0",
+        "spill_dir = \"\"",
+    ] {
+        let err = AppConfig::load_from_str(&format!("[remote_write]\n{bad}\n")).unwrap_err();
+        assert!(err.to_string().contains("remote_write."), "{bad}: {err}");
+    }
+}
+
+#[test]
+fn spill_keeps_batches_in_order_under_the_byte_cap() {
+    let dir = TempDir::new().unwrap();
+    let size = batch("edge", &[1]).to_wincode().unwrap().len() as u64;
+    let mut spill = Spill::open(dir.path(), size * 3).unwrap();
+    for ts in 1..=5 {
+        spill.push(&batch("edge", &[ts])).unwrap();
+    }
+    assert_eq!(spill.len(), 3, "the two oldest were dropped");
+    assert_eq!(spill.bytes(), size * 3);
+
+    // A restart picks the files up again, oldest first.
+    let mut reopened = Spill::open(dir.path(), size * 3).unwrap();
+    let mut seen = Vec::new();
+    while let Some(batch) = reopened.front() {
+        seen.push(batch.snapshots[0].timestamp);
+        assert_eq!(batch.snapshots[0].probes, snapshot(1).probes);
+        assert_eq!(batch.snapshots[0].sensors, snapshot(1).sensors);
+        reopened.pop_front();
+    }
+    assert_eq!(seen, [3, 4, 5]);
+    assert!(reopened.is_empty());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn ingest_requires_the_key_and_validates_batches() {
+    let dir = TempDir::new().unwrap();
+    let (server, repo) = central(&dir).await;
+
+    let response = server
+        .post("/api/ingest")
+        .json(&batch("edge", &[1000]))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    let (name, value) = bearer("wrong");
+    let response = server
+        .post("/api/ingest")
+        .add_header(name, value)
+        .json(&batch("edge", &[1000]))
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    for (invalid, why) in [
+        (batch("bad node", &[1000]), "node name"),
+        (batch("central", &[1000]), "own name"),
+        (batch("edge", &[0]), "timestamp"),
+    ] {
+        let (name, value) = bearer("s3cret");
+        let response = server
+            .post("/api/ingest")
+            .add_header(name, value)
+            .json(&invalid)
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert!(!response.text().is_empty(), "{why}");
+    }
+    let (name, value) = bearer("s3cret");
+    server
+        .post("/api/ingest")
+        .add_header(name, value)
+        .text("not json")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Accepted; a re-sent batch stores nothing twice.
+    for expected in [2, 0] {
+        let (name, value) = bearer("s3cret");
+        let response = server
+            .post("/api/ingest")
+            .add_header(name, value)
+            .json(&batch("edge", &[1000, 2000]))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["stored"], expected);
+    }
+    // Pushed rows are not local rows.
+    let (_, local) = repo.get_recent_snapshots(10).await.unwrap();
+    assert!(local.is_empty());
+    assert_eq!(
+        repo.get_min_raw_created_at_before(i64::MAX).await.unwrap(),
+        None,
+        "and never rolled up"
+    );
+}
+
+#[tokio::test]
+async fn ingest_is_off_without_a_key() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("plain.db").display().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let (tx, _) = broadcast::channel(4);
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo,
+        ServiceMetrics::default(),
+    );
+    TestServer::new(app)
+        .post("/api/ingest")
+        .json(&batch("edge", &[1000]))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn ingest_without_a_key_is_401_whatever_the_database_state() {
+    let starting = HistoryHandle::pending();
+    starting.set_error("unable to open /srv/secret/history.db");
+    for handle in [HistoryHandle::disabled(), starting] {
+        let mut config = AppConfig::default();
+        config.remote_write.ingest_api_key = Some(Secret::new("s3cret"));
+        let app = routes::app(
+            broadcast::channel(4).0,
+            Arc::new(SysinfoRepo::new()),
+            Arc::new(SystemInfo::default()),
+            Arc::new(WsConnections::default()),
+            config,
+            handle,
+            ServiceMetrics::default(),
+        );
+        let response = TestServer::new(app)
+            .post("/api/ingest")
+            .json(&batch("edge", &[1000]))
+            .await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        assert!(!response.text().contains("secret"), "{}", response.text());
+    }
+}
+
+#[tokio::test]
+async fn ingest_reads_wincode_batches_old_and_new() {
+    let dir = TempDir::new().unwrap();
+    let (server, _repo) = central(&dir).await;
+    let wincode_type = (
+        axum::http::header::CONTENT_TYPE,
+        HeaderValue::from_static(INGEST_WINCODE_CONTENT_TYPE),
+    );
+
+    // Senders from before probes and sensors were carried: bare snapshots.
+    let legacy = wincode::serialize(&("edge".to_string(), vec![snapshot(1000)])).unwrap();
+    let current = batch("edge", &[2000]).to_wincode().unwrap();
+    for body in [legacy, current] {
+        let (name, value) = bearer("s3cret");
+        let response = server
+            .post("/api/ingest")
+            .add_header(name, value)
+            .add_header(wincode_type.0.clone(), wincode_type.1.clone())
+            .bytes(body.into())
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<serde_json::Value>()["stored"], 1);
+    }
+    let (name, value) = bearer("s3cret");
+    server
+        .post("/api/ingest")
+        .add_header(name, value)
+        .add_header(wincode_type.0, wincode_type.1)
+        .bytes(vec![1, 2, 3].into())
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
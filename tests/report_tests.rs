@@ -0,0 +1,236 @@
+// Usage reports: build_report over synthetic multi-day history, the markdown rendering and webhook
+// payloads, GET /api/report and alerts.report_schedule validation.
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, WebhookFormat};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::reports::{
+    ReportPeriod, build_report, format_bytes, render_markdown, report_payload,
+    report_resolution_secs,
+};
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const BASE_TS: i64 = 1_700_000_000_000;
+const MS_PER_HOUR: i64 = 3_600_000;
+const GIB: u64 = 1 << 30;
+
+fn snapshot(ts: i64, cpu: f64, ram_used: u64, disk_used: u64, rx: u64) -> FullSystemSnapshot {
+    serde_json::from_value(serde_json::json!({
+        "timestamp": ts,
+        "cpu": CpuStats { usage_percent: cpu, ..Default::default() },
+        "ram": RamStats { total: 16 * GIB, used: ram_used, ..Default::default() },
+        "containers": [],
+        "storage": {
+            "partitions": [{
+                "mount": "/data", "name": "sda1", "type": "ext4",
+                "totalSpace": 100 * GIB, "usedSpace": disk_used,
+                "availableSpace": 100 * GIB - disk_used, "usagePercent": 0.0,
+            }],
+            "disks": [],
+        },
+        "network": { "interfaces": [{
+            "name": "eth0", "displayName": "eth0", "macAddress": "", "ipv4": [], "ipv6": [],
+            "bytesSent": rx / 4, "bytesRecv": rx, "packetsSent": 0, "packetsRecv": 0,
+            "speed": 0, "isUp": true,
+        }]},
+        "system": SystemStatsDynamic::default(),
+    }))
+    .unwrap()
+}
+
+/// Hourly aggregated points over three days: CPU alternates 10 / 30 % (peaks of 90 % inside the
+/// buckets), RAM 4 GiB (peak 6 GiB), the disk grows 1 GiB a day and eth0 receives 1 MiB an hour
+/// with a reboot (counter reset) halfway.
+fn three_days() -> Vec<HistoryPoint> {
+    (0..72i64)
+        .map(|hour| {
+            let cpu = if hour % 2 == 0 { 10.0 } else { 30.0 };
+            let disk = 10 * GIB + hour as u64 * GIB / 24;
+            let rx = if hour < 36 {
+                hour as u64
+            } else {
+                hour as u64 - 36
+            } * (1 << 20);
+            HistoryPoint {
+                snapshot: snapshot(BASE_TS + hour * MS_PER_HOUR, cpu, 4 * GIB, disk, rx),
+                envelope: Some(HistoryEnvelope {
+                    cpu_load_min: 0.0,
+                    cpu_load_max: if hour == 50 { 90.0 } else { cpu + 5.0 },
+                    memory_used_min: 0,
+                    memory_used_max: if hour == 10 {
+                        6 * GIB as i64
+                    } else {
+                        4 * GIB as i64
+                    },
+                    cpu_load_p95: None,
+                    memory_used_p95: None,
+                }),
+            }
+        })
+        .collect()
+}
+
+fn top(name: &str, average: f64) -> TopContainer {
+    TopContainer {
+        container_id: name.into(),
+        name: name.into(),
+        average,
+        max: average * 2.0,
+        samples: 10,
+    }
+}
+
+#[test]
+fn report_over_three_days() {
+    let to = BASE_TS + 72 * MS_PER_HOUR;
+    let report = build_report(BASE_TS, to, &three_days(), vec![top("web", 12.5)]);
+    assert_eq!((report.from, report.to, report.samples), (BASE_TS, to, 72));
+    assert_eq!(report.cpu_average_percent, 20.0);
+    assert_eq!(report.cpu_peak_percent, 90.0);
+    assert_eq!(report.ram_average_bytes, 4 * GIB);
+    assert_eq!(report.ram_peak_bytes, 6 * GIB);
+    assert_eq!(report.ram_total_bytes, 16 * GIB);
+
+    assert_eq!(report.partitions.len(), 1);
+    let data = &report.partitions[0];
+    assert_eq!(data.mount, "/data");
+    assert_eq!(data.start_used_bytes, 10 * GIB);
+    assert_eq!(data.end_used_bytes, 10 * GIB + 71 * GIB / 24);
+    assert_eq!(data.growth_bytes, (71 * GIB / 24) as i64);
+
+    // 35 hours before the reset, the 0 MiB reading right after it, then 35 more.
+    assert_eq!(report.network_rx_bytes, 70 * (1 << 20));
+    assert_eq!(report.network_tx_bytes, 70 * (1 << 20) / 4);
+    assert_eq!(report.top_containers[0].name, "web");
+}
+
+#[test]
+fn shrinking_disks_and_empty_windows() {
+    let points: Vec<HistoryPoint> = [(50 * GIB, 0.0), (45 * GIB, 100.0)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (disk, cpu))| HistoryPoint {
+            snapshot: snapshot(BASE_TS + i as i64, cpu, GIB, disk, 0),
+            envelope: None,
+        })
+        .collect();
+    let report = build_report(BASE_TS, BASE_TS + 10, &points, vec![]);
+    assert_eq!(report.partitions[0].growth_bytes, -5 * GIB as i64);
+    assert_eq!(report.cpu_peak_percent, 100.0);
+    assert_eq!(report.cpu_average_percent, 50.0);
+
+    let empty = build_report(BASE_TS, BASE_TS + 10, &[], vec![]);
+    assert_eq!(empty.samples, 0);
+    assert_eq!(empty.cpu_average_percent, 0.0);
+    assert!(empty.partitions.is_empty());
+    assert!(render_markdown(&empty).contains("No history in this window."));
+}
+
+#[test]
+fn markdown_and_webhook_payloads() {
+    let to = BASE_TS + 72 * MS_PER_HOUR;
+    let report = build_report(BASE_TS, to, &three_days(), vec![top("web", 12.5)]);
+    let text = render_markdown(&report);
+    assert!(
+        text.starts_with("**Usage report** 2023-11-14 22:13 UTC to 2023-11-17 22:13 UTC"),
+        "{text}"
+    );
+    assert!(text.contains("- CPU: 20.0% average, 90.0% peak"), "{text}");
+    assert!(
+        text.contains("- RAM: 4.0 GiB average, 6.0 GiB peak of 16.0 GiB"),
+        "{text}"
+    );
+    assert!(
+        text.contains("- /data: +3.0 GiB (13.0 GiB of 100.0 GiB used)"),
+        "{text}"
+    );
+    assert!(text.contains("1. web: 12.5% average, 25.0% peak"), "{text}");
+
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+
+    let generic = report_payload(WebhookFormat::Generic, &report);
+    assert_eq!(generic["type"], "report");
+    assert_eq!(generic["report"]["samples"], 72);
+    assert_eq!(generic["text"], text);
+    assert_eq!(
+        report_payload(WebhookFormat::Discord, &report)["content"],
+        text
+    );
+    assert_eq!(report_payload(WebhookFormat::Slack, &report)["text"], text);
+}
+
+#[test]
+fn periods_and_resolutions() {
+    assert_eq!(ReportPeriod::parse("week"), Some(ReportPeriod::Week));
+    assert_eq!(ReportPeriod::parse("year"), None);
+    assert_eq!(ReportPeriod::Month.span_ms(), 30 * 24 * MS_PER_HOUR);
+    assert_eq!(report_resolution_secs(ReportPeriod::Day.span_ms()), 300);
+    assert_eq!(report_resolution_secs(ReportPeriod::Week.span_ms()), 3600);
+}
+
+#[tokio::test]
+async fn api_report_serves_json_and_markdown() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let now = chrono::Utc::now().timestamp_millis();
+    let snaps: Vec<_> = (0..6)
+        .map(|i| snapshot(now - 3 * MS_PER_HOUR + i * 60_000, 40.0, GIB, 10 * GIB, 0))
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    let report: UsageReport = server.get("/api/report").await.json();
+    assert!(report.samples > 0);
+    assert_eq!(report.cpu_average_percent, 40.0);
+    assert_eq!(report.to - report.from, ReportPeriod::Day.span_ms());
+
+    let response = server.get("/api/report?period=week&format=markdown").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header("content-type")
+            .to_str()
+            .unwrap()
+            .starts_with("text/markdown")
+    );
+    assert!(response.text().contains("- CPU: 40.0% average"));
+
+    for query in ["period=year", "format=html"] {
+        let response = server.get(&format!("/api/report?{query}")).await;
+        response.assert_status_bad_request();
+        let body: serde_json::Value = response.json();
+        assert!(body["error"].is_string(), "{query}: {body}");
+    }
+}
+
+#[test]
+fn report_schedule_is_validated() {
+    let config = AppConfig::load_from_str(
+        "[alerts]\nreport_schedule = \"0 8 * * 1\"\nreport_period = \"week\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.alerts.report_period, ReportPeriod::Week);
+    assert_eq!(AppConfig::default().alerts.report_period, ReportPeriod::Day);
+    let err =
+        AppConfig::load_from_str("[alerts]\nreport_schedule = \"every monday\"\n").unwrap_err();
+    assert!(err.to_string().contains("report_schedule"), "{err}");
+    assert!(AppConfig::load_from_str("[alerts]\nreport_period = \"year\"\n").is_err());
+}
@@ -0,0 +1,129 @@
+// Self-monitoring: worker snapshots carry the server's own CPU / RSS / descriptors / tasks and
+// database size, the latest sample lands in CollectionMetrics, and snapshots without the field
+// (history rows, older JSON, wincode batches) still load.
+
+mod common;
+
+use common::InstantCollector;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{CollectionMetrics, OverflowPolicy, WorkerDeps, spawn, write_queue};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn snapshot(timestamp: u64) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        self_stats: Some(SelfStats {
+            rss_bytes: 1,
+            ..Default::default()
+        }),
+        ..common::snapshot(timestamp)
+    }
+}
+
+async fn repo(dir: &TempDir) -> Arc<HistoryRepo> {
+    Arc::new(common::connect(&dir.path().join("h.db")).await)
+}
+
+/// Run the worker for a few 20 ms ticks; returns the broadcast snapshots and the metrics.
+async fn run_worker(
+    history_repo: Option<Arc<HistoryRepo>>,
+) -> (Vec<FullSystemSnapshot>, Arc<CollectionMetrics>) {
+    let (tx, mut rx) = broadcast::channel(64);
+    let (write_tx, _write_rx) = write_queue(64, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(CollectionMetrics::default());
+    let config = common::worker_config(20);
+    let write_tx = history_repo.as_ref().map(|_| write_tx);
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.into(),
+            tx,
+            write_tx,
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        config,
+    );
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+    let mut snapshots = Vec::new();
+    while let Ok(s) = rx.try_recv() {
+        snapshots.push(s);
+    }
+    (snapshots, metrics)
+}
+
+#[tokio::test]
+async fn worker_snapshots_carry_the_process_usage() {
+    let dir = TempDir::new().unwrap();
+    let (snapshots, metrics) = run_worker(Some(repo(&dir).await)).await;
+    assert!(snapshots.len() >= 2, "{}", snapshots.len());
+    for s in &snapshots {
+        let own = s.self_stats.as_ref().expect("self_stats on every tick");
+        assert!(own.rss_bytes > 0, "{own:?}");
+        assert!(own.cpu_percent >= 0.0);
+        assert!(own.tokio_tasks.is_some_and(|n| n > 0), "{own:?}");
+        assert!(own.db_file_bytes.is_some_and(|n| n > 0), "{own:?}");
+        if cfg!(target_os = "linux") {
+            assert!(own.open_fds.is_some_and(|n| n > 0), "{own:?}");
+        }
+    }
+    assert_eq!(
+        metrics.latest_self(),
+        snapshots.last().unwrap().self_stats,
+        "latest sample kept for /api/stats"
+    );
+
+    // Agent mode: no database to measure.
+    let (snapshots, _) = run_worker(None).await;
+    let own = snapshots[0].self_stats.as_ref().unwrap();
+    assert!(own.rss_bytes > 0);
+    assert_eq!(own.db_file_bytes, None);
+}
+
+#[tokio::test]
+async fn snapshots_without_self_stats_still_load() {
+    // History rows never stored it.
+    let dir = TempDir::new().unwrap();
+    let repo = repo(&dir).await;
+    repo.save_snapshots(&[snapshot(1_000)], &SystemInfo::default())
+        .await
+        .unwrap();
+    let (_, rows) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].self_stats, None);
+
+    // JSON from before the field existed.
+    let mut json = serde_json::to_value(snapshot(2_000)).unwrap();
+    json.as_object_mut().unwrap().remove("selfStats");
+    let parsed: FullSystemSnapshot = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.self_stats, None);
+
+    // wincode (exports, remote write batches) skips it, so the encoding is unchanged.
+    let bytes = wincode::serialize(&snapshot(3_000)).unwrap();
+    let mut without = snapshot(3_000);
+    without.self_stats = None;
+    assert_eq!(bytes, wincode::serialize(&without).unwrap());
+    let decoded: FullSystemSnapshot = wincode::deserialize(&bytes).unwrap();
+    assert_eq!((decoded.timestamp, decoded.self_stats), (3_000, None));
+}
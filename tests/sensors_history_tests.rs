@@ -0,0 +1,88 @@
+// Sensor readings in history: stored with raw snapshots (`sensor_data`) and rolled up per sensor
+// id, the average weighted by sample count and the highest reading kept as `max`.
+
+mod common;
+
+use homeserver::config::DatabaseConfig;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::history_repo::aggregation::{aggregate_aggregated_snapshots, aggregate_snapshots};
+use homeserver::models::*;
+use tempfile::TempDir;
+
+fn temp(id: &str, value: f64) -> SensorStat {
+    SensorStat {
+        id: id.into(),
+        name: "nvme".into(),
+        label: "Composite".into(),
+        kind: SensorKind::Temp,
+        value,
+        unit: "°C".into(),
+        max: None,
+    }
+}
+
+fn snapshot(ts: u64, sensors: Vec<SensorStat>) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        sensors,
+        ..common::snapshot(ts)
+    }
+}
+
+#[tokio::test]
+async fn sensors_are_stored_and_aggregated() {
+    let dir = TempDir::new().unwrap();
+    let repo = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    repo.init().await.unwrap();
+    let t0 = 1_700_000_000_000;
+    let snap = snapshot(t0, vec![temp("a", 40.0), temp("b", 30.0)]);
+    repo.save_snapshots(std::slice::from_ref(&snap), &SystemInfo::default())
+        .await
+        .unwrap();
+    let (_info, snaps) = repo.get_recent_snapshots(10).await.unwrap();
+    assert_eq!(snaps[0].sensors, snap.sensors);
+
+    let later = [temp("a", 50.0), temp("b", 31.0)];
+    let agg = aggregate_snapshots(
+        &[
+            snap,
+            snapshot(t0 + 2_000, later.to_vec()),
+            snapshot(t0 + 4_000, vec![temp("a", 45.0)]),
+        ],
+        t0 as i64,
+        60,
+    )
+    .unwrap();
+    let rolled: Vec<_> = agg
+        .sensors
+        .iter()
+        .map(|s| (s.id.as_str(), s.value, s.max))
+        .collect();
+    assert_eq!(
+        rolled,
+        [("a", 45.0, Some(50.0)), ("b", 30.5, Some(31.0))],
+        "keyed by id, avg / max"
+    );
+
+    repo.save_aggregated_snapshot(&agg).await.unwrap();
+    let rows = repo
+        .get_aggregated_snapshots_by_time_range(0, i64::MAX, 60)
+        .await
+        .unwrap();
+    assert_eq!(rows[0].sensors, agg.sensors);
+
+    // Rolling up rolled-up buckets keeps their maxima and weights their averages.
+    let mut other = agg.clone();
+    other.created_at += 60_000;
+    other.sample_count = 1;
+    other.sensors = vec![temp("a", 60.0)];
+    let hour = aggregate_aggregated_snapshots(&[agg, other], t0 as i64, 3600).unwrap();
+    assert_eq!(
+        (hour.sensors[0].value, hour.sensors[0].max),
+        (48.75, Some(60.0))
+    );
+}
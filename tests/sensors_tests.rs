@@ -0,0 +1,279 @@
+// hwmon sensors against fixture sysfs trees: discovery (hwmon and NVMe controllers), ids that
+// survive renumbered `hwmon*` / `nvme*`, parsing, rescans, GET /api/sensors and `[monitoring]`
+// validation. Persistence and aggregation are in `sensors_history_tests.rs`.
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::sensors_repo::{
+    SensorsRepo, chip_id, discover, parse_input_file_name, parse_sensor_value,
+};
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// An hwmon chip at `dir` (relative to `root`) with `files`, linked from `class/hwmon/<class>`.
+fn chip(root: &Path, dir: &str, class: &str, files: &[(&str, &str)]) {
+    for (name, content) in files {
+        write(&root.join(dir).join(name), content);
+    }
+    fs::create_dir_all(root.join("class/hwmon")).unwrap();
+    symlink(root.join(dir), root.join("class/hwmon").join(class)).unwrap();
+}
+
+/// A host with a CPU (k10temp), one NVMe drive, a Super I/O chip (fan + voltage) and an ACPI
+/// zone without a device. `n` shifts every `hwmon*` and `nvme*` number, as a reboot may.
+fn host(n: usize) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    let cpu = "devices/pci0000:00/0000:00:18.3";
+    // PCI devices have a `device` file (the PCI id), not a link.
+    write(&root.join(cpu).join("device"), "0x1463\n");
+    let hwmon = format!("{cpu}/hwmon/hwmon{n}");
+    chip(
+        root,
+        &hwmon,
+        &format!("hwmon{n}"),
+        &[
+            ("name", "k10temp\n"),
+            ("temp1_input", "45500\n"),
+            ("temp1_label", "Tctl\n"),
+        ],
+    );
+    symlink(root.join(cpu), root.join(&hwmon).join("device")).unwrap();
+
+    let pci = "devices/pci0000:00/0000:01:00.0";
+    write(&root.join(pci).join("device"), "0x5017\n");
+    let ctrl = format!("{pci}/nvme/nvme{n}");
+    fs::create_dir_all(root.join(&ctrl)).unwrap();
+    symlink(root.join(pci), root.join(&ctrl).join("device")).unwrap();
+    let hwmon = format!("{ctrl}/hwmon{}", n + 1);
+    chip(
+        root,
+        &hwmon,
+        &format!("hwmon{}", n + 1),
+        &[
+            ("name", "nvme\n"),
+            ("temp1_input", "38850\n"),
+            ("temp1_label", "Composite\n"),
+            ("temp2_input", "41850\n"),
+            ("temp2_label", "Sensor 1\n"),
+        ],
+    );
+    symlink(root.join(&ctrl), root.join(&hwmon).join("device")).unwrap();
+    fs::create_dir_all(root.join("class/nvme")).unwrap();
+    symlink(root.join(&ctrl), root.join(format!("class/nvme/nvme{n}"))).unwrap();
+
+    let sio = "devices/platform/nct6775.656";
+    let hwmon = format!("{sio}/hwmon/hwmon{}", n + 2);
+    chip(
+        root,
+        &hwmon,
+        &format!("hwmon{}", n + 2),
+        &[
+            ("name", "nct6798\n"),
+            ("fan2_input", "1200\n"),
+            ("in0_input", "1032\n"),
+            ("intrusion0_input", "0\n"),
+        ],
+    );
+    symlink(root.join(sio), root.join(&hwmon).join("device")).unwrap();
+
+    let zone = format!("devices/virtual/thermal/thermal_zone0/hwmon{}", n + 3);
+    chip(
+        root,
+        &zone,
+        &format!("hwmon{}", n + 3),
+        &[("name", "acpitz\n"), ("temp1_input", "27800\n")],
+    );
+    dir
+}
+
+fn ids(root: &Path) -> Vec<String> {
+    discover(root).into_iter().map(|s| s.id).collect()
+}
+
+#[test]
+fn discovers_every_chip_once() {
+    let dir = host(0);
+    let ids = ids(dir.path());
+    assert_eq!(
+        ids,
+        [
+            "acpitz/temp1",
+            "k10temp-0000:00:18.3/Tctl",
+            "nct6798-nct6775.656/fan2",
+            "nct6798-nct6775.656/in0",
+            "nvme-0000:01:00.0/Composite",
+            "nvme-0000:01:00.0/Sensor 1",
+        ],
+        "the NVMe chip is listed under class/hwmon and class/nvme but read once"
+    );
+    let repo = SensorsRepo::with_root(dir.path(), Duration::from_secs(3600));
+    let read = repo.collect();
+    let find = |id: &str| read.iter().find(|s| s.id == id).unwrap();
+    let composite = find("nvme-0000:01:00.0/Composite");
+    assert_eq!(
+        (composite.name.as_str(), composite.label.as_str()),
+        ("nvme", "Composite")
+    );
+    assert_eq!(
+        (composite.kind, composite.value, composite.unit.as_str()),
+        (SensorKind::Temp, 38.85, "°C")
+    );
+    assert_eq!(composite.max, None);
+    let fan = find("nct6798-nct6775.656/fan2");
+    assert_eq!(
+        (fan.kind, fan.value, fan.unit.as_str()),
+        (SensorKind::Fan, 1200.0, "RPM")
+    );
+    let vcore = find("nct6798-nct6775.656/in0");
+    assert_eq!(
+        (vcore.kind, vcore.value, vcore.unit.as_str()),
+        (SensorKind::Voltage, 1.032, "V")
+    );
+    assert_eq!(repo.current(), read);
+}
+
+#[test]
+fn ids_survive_renumbering() {
+    let before = host(0);
+    let after = host(4);
+    assert_eq!(ids(before.path()), ids(after.path()));
+}
+
+#[test]
+fn sensor_files_are_parsed() {
+    assert_eq!(
+        parse_input_file_name("temp10_input"),
+        Some((SensorKind::Temp, "temp10"))
+    );
+    assert_eq!(
+        parse_input_file_name("fan1_input"),
+        Some((SensorKind::Fan, "fan1"))
+    );
+    assert_eq!(
+        parse_input_file_name("in0_input"),
+        Some((SensorKind::Voltage, "in0"))
+    );
+    for other in [
+        "temp1_label",
+        "temp1_max",
+        "power1_input",
+        "intrusion0_input",
+        "temp_input",
+    ] {
+        assert_eq!(parse_input_file_name(other), None, "{other}");
+    }
+    assert_eq!(parse_sensor_value(SensorKind::Temp, "-5500\n"), Some(-5.5));
+    assert_eq!(parse_sensor_value(SensorKind::Fan, "0"), Some(0.0));
+    assert_eq!(parse_sensor_value(SensorKind::Voltage, "N/A"), None);
+    assert_eq!(chip_id("acpitz", None), "acpitz");
+    assert_eq!(chip_id("nvme", Some("0000:01:00.0")), "nvme-0000:01:00.0");
+}
+
+#[test]
+fn repeated_labels_fall_back_to_the_file_prefix() {
+    let dir = TempDir::new().unwrap();
+    chip(
+        dir.path(),
+        "devices/virtual/hwmon/hwmon0",
+        "hwmon0",
+        &[
+            ("name", "drivetemp\n"),
+            ("temp1_input", "30000"),
+            ("temp1_label", "Drive"),
+            ("temp2_input", "31000"),
+            ("temp2_label", "Drive"),
+        ],
+    );
+    assert_eq!(ids(dir.path()), ["drivetemp/Drive", "drivetemp/temp2"]);
+}
+
+#[test]
+fn rescans_pick_up_new_sensors_and_unreadable_ones_are_skipped() {
+    let dir = host(0);
+    let cached = SensorsRepo::with_root(dir.path(), Duration::from_secs(3600));
+    let rescanned = SensorsRepo::with_root(dir.path(), Duration::ZERO);
+    assert_eq!(cached.collect().len(), 6);
+    assert_eq!(rescanned.collect().len(), 6);
+
+    let drive = "devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0";
+    chip(
+        dir.path(),
+        &format!("{drive}/hwmon/hwmon9"),
+        "hwmon9",
+        &[("name", "drivetemp\n"), ("temp1_input", "33000\n")],
+    );
+    symlink(
+        dir.path().join(drive),
+        dir.path().join(drive).join("hwmon/hwmon9/device"),
+    )
+    .unwrap();
+    assert_eq!(
+        cached.collect().len(),
+        6,
+        "the list is kept until the rescan interval"
+    );
+    let read = rescanned.collect();
+    assert!(read.iter().any(|s| s.id == "drivetemp-0:0:0:0/temp1"));
+
+    // A sleeping device's input that does not read as a number is left out.
+    fs::write(
+        dir.path()
+            .join("devices/pci0000:00/0000:01:00.0/nvme/nvme0/hwmon1/temp2_input"),
+        "",
+    )
+    .unwrap();
+    assert!(!rescanned.collect().iter().any(|s| s.label == "Sensor 1"));
+    assert_eq!(
+        SensorsRepo::with_root(dir.path().join("missing"), Duration::ZERO).collect(),
+        []
+    );
+}
+
+#[tokio::test]
+async fn api_sensors_serves_the_last_reading() {
+    let dir = host(0);
+    let repo = SensorsRepo::with_root(dir.path(), Duration::from_secs(3600));
+    repo.collect();
+    let app = routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        AppConfig::default(),
+        None,
+        ServiceMetrics {
+            sensors: Arc::new(repo),
+            ..Default::default()
+        },
+    );
+    let body: serde_json::Value = TestServer::new(app).get("/api/sensors").await.json();
+    assert_eq!(body.as_array().unwrap().len(), 6);
+    assert_eq!(body[1]["id"], "k10temp-0000:00:18.3/Tctl");
+    assert_eq!(body[1]["kind"], "temp");
+    assert_eq!(body[1]["value"], 45.5);
+    assert_eq!(body[1]["unit"], "°C");
+    assert!(body[1].get("max").is_none(), "max only on aggregated rows");
+}
+
+#[test]
+fn sensor_rescan_interval_is_validated() {
+    let config = AppConfig::load_from_str("").unwrap();
+    assert!(config.monitoring.collect_sensors);
+    assert_eq!(config.monitoring.sensor_rescan_secs, 300);
+    let err = AppConfig::load_from_str("[monitoring]\nsensor_rescan_secs = 0\n").unwrap_err();
+    assert!(format!("{err:#}").contains("monitoring.sensor_rescan_secs must be > 0"));
+}
@@ -0,0 +1,119 @@
+// serve::run end to end: boot on port 0, reach the router at the returned address, see it as
+// `boundAddress` on /version, /api/info and /api/bootstrap, then stop through the handle.
+
+use std::sync::Arc;
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, ServerConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::{routes, serve};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+async fn app(dir: &TempDir) -> axum::Router {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo {
+            system_model: "test-host".into(),
+            ..Default::default()
+        }),
+        Default::default(),
+        config,
+        repo,
+        ServiceMetrics::default(),
+    )
+}
+
+fn any_port() -> ServerConfig {
+    ServerConfig {
+        host: "127.0.0.1".into(),
+        port: 0,
+        ..Default::default()
+    }
+}
+
+async fn get_json(url: String) -> serde_json::Value {
+    let response = reqwest::get(url).await.unwrap();
+    assert!(response.status().is_success());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn port_zero_serves_on_the_returned_address() {
+    let dir = TempDir::new().unwrap();
+    let server = serve::run(app(&dir).await, &any_port(), std::future::pending())
+        .await
+        .unwrap();
+    let addr = server.bound_address().expect("TCP listener");
+    assert!(addr.ip().is_loopback());
+    assert_ne!(addr.port(), 0);
+    let base = format!("http://{addr}");
+
+    let health = reqwest::get(format!("{base}/health")).await.unwrap();
+    assert_eq!(health.text().await.unwrap(), "ok");
+    let version = get_json(format!("{base}/version")).await;
+    assert_eq!(version["name"], "homeserver");
+    assert_eq!(version["boundAddress"], addr.to_string());
+    let info = get_json(format!("{base}/api/info")).await;
+    assert_eq!(info["systemModel"], "test-host");
+    assert_eq!(info["boundAddress"], addr.to_string());
+    let bootstrap = get_json(format!("{base}/api/bootstrap?history=false")).await;
+    assert_eq!(bootstrap["info"]["boundAddress"], addr.to_string());
+    assert_eq!(bootstrap["version"]["boundAddress"], addr.to_string());
+
+    server.shutdown();
+    tokio::time::timeout(std::time::Duration::from_secs(10), server.wait())
+        .await
+        .expect("server stops after shutdown")
+        .unwrap();
+    assert!(reqwest::get(format!("{base}/health")).await.is_err());
+}
+
+#[tokio::test]
+async fn two_servers_on_port_zero_get_different_ports() {
+    let dir = TempDir::new().unwrap();
+    let first = serve::run(app(&dir).await, &any_port(), std::future::pending())
+        .await
+        .unwrap();
+    let second = serve::run(app(&dir).await, &any_port(), async {})
+        .await
+        .unwrap();
+    assert_ne!(first.bound_address(), second.bound_address());
+    // The shutdown future already resolved: the second one stops on its own.
+    second.wait().await.unwrap();
+    first.shutdown();
+    first.wait().await.unwrap();
+}
+
+#[tokio::test]
+async fn without_a_listener_bound_address_is_null() {
+    let dir = TempDir::new().unwrap();
+    let server = TestServer::new(app(&dir).await);
+    let version: serde_json::Value = server.get("/version").await.json();
+    assert_eq!(version["boundAddress"], serde_json::Value::Null);
+    let info: serde_json::Value = server.get("/api/info").await.json();
+    assert_eq!(info["boundAddress"], serde_json::Value::Null);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_only_has_no_bound_address() {
+    let dir = TempDir::new().unwrap();
+    let config = ServerConfig {
+        tcp_enabled: false,
+        unix_socket_path: Some(dir.path().join("s.sock").to_str().unwrap().into()),
+        ..Default::default()
+    };
+    let server = serve::run(app(&dir).await, &config, async {})
+        .await
+        .unwrap();
+    assert_eq!(server.bound_address(), None);
+    server.wait().await.unwrap();
+}
@@ -0,0 +1,163 @@
+// TLS listener: HTTPS to /version with a client trusting a self-signed certificate generated at
+// test time, certificate reload on SIGHUP, and validation of unreadable or mismatched files.
+#![cfg(unix)]
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use homeserver::config::{AppConfig, ServerConfig, TlsConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::{routes, serve};
+use tempfile::TempDir;
+use tokio::sync::{broadcast, oneshot};
+
+/// Writes a self-signed certificate for `name` and its key; returns the config and the cert PEM.
+fn self_signed(dir: &Path, name: &str) -> (TlsConfig, String) {
+    let rcgen::CertifiedKey { cert, signing_key } =
+        rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+    let tls = TlsConfig {
+        cert_path: dir.join(format!("{name}.crt")).to_str().unwrap().into(),
+        key_path: dir.join(format!("{name}.key")).to_str().unwrap().into(),
+    };
+    std::fs::write(&tls.cert_path, cert.pem()).unwrap();
+    std::fs::write(&tls.key_path, signing_key.serialize_pem()).unwrap();
+    (tls, cert.pem())
+}
+
+async fn app(dir: &TempDir) -> axum::Router {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        ServiceMetrics::default(),
+    )
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// GET https://{name}:{port}/version (resolved to 127.0.0.1), trusting only `cert_pem`.
+async fn get_version(name: &str, port: u16, cert_pem: &str) -> reqwest::Result<String> {
+    let client = reqwest::Client::builder()
+        .tls_certs_only([reqwest::Certificate::from_pem(cert_pem.as_bytes())?])
+        .resolve(name, SocketAddr::from(([127, 0, 0, 1], port)))
+        .build()?;
+    client
+        .get(format!("https://{name}:{port}/version"))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+}
+
+#[tokio::test]
+async fn serves_https_and_reloads_the_certificate_on_sighup() {
+    let dir = TempDir::new().unwrap();
+    let (tls, first_pem) = self_signed(dir.path(), "localhost");
+    let port = free_port();
+    let server = ServerConfig {
+        host: "127.0.0.1".into(),
+        port,
+        tls: Some(tls.clone()),
+        ..Default::default()
+    };
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let app = app(&dir).await;
+    let handle = tokio::spawn(async move {
+        serve::serve(app, &server, async {
+            let _ = stop_rx.await;
+        })
+        .await
+    });
+
+    let mut body = get_version("localhost", port, &first_pem).await;
+    for _ in 0..100 {
+        if body.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        body = get_version("localhost", port, &first_pem).await;
+    }
+    assert!(body.unwrap().contains(env!("CARGO_PKG_VERSION")));
+
+    // Renewal: a new certificate at the same paths is served after SIGHUP.
+    let (renewed, renewed_pem) = self_signed(dir.path(), "homeserver.test");
+    std::fs::rename(&renewed.cert_path, &tls.cert_path).unwrap();
+    std::fs::rename(&renewed.key_path, &tls.key_path).unwrap();
+    assert!(
+        get_version("homeserver.test", port, &renewed_pem)
+            .await
+            .is_err()
+    );
+    let status = std::process::Command::new("kill")
+        .args(["-HUP", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut reloaded = false;
+    for _ in 0..100 {
+        if get_version("homeserver.test", port, &renewed_pem)
+            .await
+            .is_ok()
+        {
+            reloaded = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(reloaded, "renewed certificate served after SIGHUP");
+
+    stop_tx.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[test]
+fn unreadable_or_mismatched_files_fail_validation() {
+    let dir = TempDir::new().unwrap();
+    let (good, _) = self_signed(dir.path(), "localhost");
+    let (other, _) = self_signed(dir.path(), "other");
+    let config = |cert: &str, key: &str| {
+        AppConfig::load_from_str(&format!(
+            "[server.tls]\ncert_path = {cert:?}\nkey_path = {key:?}\n"
+        ))
+    };
+
+    let loaded = config(&good.cert_path, &good.key_path).unwrap();
+    assert_eq!(loaded.server.tls, Some(good.clone()));
+
+    let missing = dir.path().join("missing.pem");
+    let err = config(missing.to_str().unwrap(), &good.key_path).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("server.tls.cert_path"),
+        "{err:#}"
+    );
+
+    let err = config(&good.cert_path, &good.cert_path).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("server.tls.key_path"),
+        "{err:#}"
+    );
+
+    let err = config(&good.key_path, &good.key_path).unwrap_err();
+    assert!(format!("{err:#}").contains("no PEM certificate"), "{err:#}");
+
+    let err = config(&good.cert_path, &other.key_path).unwrap_err();
+    assert!(format!("{err:#}").contains("does not match"), "{err:#}");
+}
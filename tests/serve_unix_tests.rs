@@ -0,0 +1,119 @@
+// Unix domain socket listener: the router answers over the socket, stale sockets are replaced,
+// other files are left alone, permissions are applied, and the socket is removed on shutdown.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+
+use homeserver::config::{AppConfig, ServerConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::{routes, serve};
+use hyper_util::rt::TokioIo;
+use tempfile::TempDir;
+use tokio::sync::{broadcast, oneshot};
+
+async fn app(dir: &TempDir) -> axum::Router {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        ServiceMetrics::default(),
+    )
+}
+
+fn socket_only(path: &Path) -> ServerConfig {
+    ServerConfig {
+        tcp_enabled: false,
+        unix_socket_path: Some(path.to_str().unwrap().into()),
+        ..Default::default()
+    }
+}
+
+async fn get(socket: &Path, uri: &str) -> (u16, String) {
+    let stream = tokio::net::UnixStream::connect(socket).await.unwrap();
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+    let request = hyper::Request::get(uri)
+        .header(hyper::header::HOST, "localhost")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(axum::body::Body::new(response.into_body()), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn serves_the_router_on_a_unix_socket() {
+    let dir = TempDir::new().unwrap();
+    let socket = dir.path().join("homeserver.sock");
+    // A socket left behind by a crashed run is replaced.
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    let server = ServerConfig {
+        unix_socket_mode: 0o600,
+        ..socket_only(&socket)
+    };
+
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let app = app(&dir).await;
+    let handle = tokio::spawn(async move {
+        serve::serve(app, &server, async {
+            let _ = stop_rx.await;
+        })
+        .await
+    });
+    // The stale socket refuses connections until serve has replaced it.
+    while tokio::net::UnixStream::connect(&socket).await.is_err() {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    let (status, body) = get(&socket, "/version").await;
+    assert_eq!(status, 200);
+    assert!(body.contains(env!("CARGO_PKG_VERSION")), "{body}");
+    let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    stop_tx.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+    assert!(!socket.exists(), "socket file removed on shutdown");
+}
+
+#[tokio::test]
+async fn refuses_to_replace_a_regular_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("not-a-socket");
+    std::fs::write(&path, "keep me").unwrap();
+    let err = serve::serve(app(&dir).await, &socket_only(&path), async {})
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a socket"), "{err:#}");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+}
+
+#[test]
+fn listener_settings_are_validated() {
+    let config = AppConfig::load_from_str("[server]\ntcp_enabled = false\n");
+    let err = config.unwrap_err().to_string();
+    assert!(err.contains("server.unix_socket_path"), "{err}");
+
+    let config = AppConfig::load_from_str(
+        "[server]\ntcp_enabled = false\nunix_socket_path = \"/run/homeserver.sock\"\nunix_socket_mode = 0o666\n",
+    )
+    .unwrap();
+    assert_eq!(config.server.unix_socket_mode, 0o666);
+    assert!(AppConfig::load_from_str("[server]\nunix_socket_mode = 0o1777\n").is_err());
+}
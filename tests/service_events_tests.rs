@@ -0,0 +1,160 @@
+// Service events: starts and clean shutdowns across simulated restarts of the repo (a start after
+// anything but a clean shutdown is a crash recovery), the drain recording the shutdown, and the
+// service uptime on /api/events, /api/stats and /metrics.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::models::{ServiceEventKind, SystemInfo};
+use homeserver::ws_connections::WsConnections;
+use homeserver::{routes, shutdown, version};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// Open (creating on first use) the database in `dir`, as a server start does.
+async fn open(dir: &TempDir) -> Arc<HistoryRepo> {
+    Arc::new(common::connect(&dir.path().join("h.db")).await)
+}
+
+fn kinds(events: &[homeserver::models::ServiceEvent]) -> Vec<ServiceEventKind> {
+    events.iter().map(|e| e.event).collect()
+}
+
+#[tokio::test]
+async fn a_start_after_a_crash_is_recorded_as_a_recovery() {
+    let dir = TempDir::new().unwrap();
+
+    // First start ever, then a clean shutdown.
+    let repo = open(&dir).await;
+    assert_eq!(
+        repo.record_service_start(1_000).await.unwrap(),
+        ServiceEventKind::Started
+    );
+    repo.record_clean_shutdown(2_000).await.unwrap();
+    repo.close().await;
+
+    // Started again, then killed without a shutdown.
+    let repo = open(&dir).await;
+    assert_eq!(
+        repo.record_service_start(3_000).await.unwrap(),
+        ServiceEventKind::Started
+    );
+    repo.close().await;
+
+    let repo = open(&dir).await;
+    assert_eq!(
+        repo.record_service_start(4_000).await.unwrap(),
+        ServiceEventKind::RecoveredAfterCrash
+    );
+    // Crashed again: still a recovery.
+    repo.close().await;
+    let repo = open(&dir).await;
+    assert_eq!(
+        repo.record_service_start(5_000).await.unwrap(),
+        ServiceEventKind::RecoveredAfterCrash
+    );
+
+    let events = repo.get_service_events(10).await.unwrap();
+    assert_eq!(
+        events.iter().map(|e| e.ts).collect::<Vec<_>>(),
+        [5_000, 4_000, 3_000, 2_000, 1_000]
+    );
+    assert_eq!(
+        kinds(&events),
+        [
+            ServiceEventKind::RecoveredAfterCrash,
+            ServiceEventKind::RecoveredAfterCrash,
+            ServiceEventKind::Started,
+            ServiceEventKind::CleanShutdown,
+            ServiceEventKind::Started,
+        ]
+    );
+    assert_eq!(repo.get_service_events(2).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn drain_records_the_clean_shutdown() {
+    let dir = TempDir::new().unwrap();
+    let repo = open(&dir).await;
+    repo.record_service_start(1_000).await.unwrap();
+
+    let (worker_stop, _worker_rx) = tokio::sync::oneshot::channel();
+    shutdown::Drain {
+        worker_stop,
+        worker: tokio::spawn(async {}),
+        history_startup: None,
+        history_shutdown: CancellationToken::new(),
+        tasks_shutdown: CancellationToken::new(),
+        tasks: vec![],
+        ws_connections: Arc::new(WsConnections::default()),
+        history_repo: HistoryHandle::from(repo),
+    }
+    .run()
+    .await;
+
+    let repo = open(&dir).await;
+    let events = repo.get_service_events(10).await.unwrap();
+    assert_eq!(
+        kinds(&events),
+        [ServiceEventKind::CleanShutdown, ServiceEventKind::Started]
+    );
+    assert!(events[0].ts > 1_000);
+    assert_eq!(
+        repo.record_service_start(events[0].ts + 1).await.unwrap(),
+        ServiceEventKind::Started
+    );
+}
+
+#[tokio::test]
+async fn events_and_service_uptime_are_served() {
+    let dir = TempDir::new().unwrap();
+    let repo = open(&dir).await;
+    let started = version::service_start_epoch_ms();
+    repo.record_service_start(started as i64).await.unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    let body: serde_json::Value = server.get("/api/events?limit=5").await.json();
+    assert_eq!(body["serviceStartEpochMs"], started);
+    assert!(body["serviceUptimeSecs"].is_u64());
+    assert_eq!(body["events"][0]["event"], "started");
+    assert_eq!(body["events"][0]["ts"], started);
+
+    let stats: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(stats["serviceStartEpochMs"], started);
+    assert!(stats["serviceUptimeSecs"].is_u64());
+    let text = server.get("/metrics").await.text();
+    assert!(text.contains("# TYPE homeserver_service_uptime_seconds gauge"));
+}
+
+#[tokio::test]
+async fn events_answer_503_while_the_database_opens() {
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        AppConfig::default(),
+        HistoryHandle::pending(),
+        Default::default(),
+    ));
+    server
+        .get("/api/events")
+        .expect_failure()
+        .await
+        .assert_status_service_unavailable();
+}
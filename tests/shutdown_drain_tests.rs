@@ -0,0 +1,257 @@
+// Shutdown drain: stopping a server started with serve::run mid-stream still stores every
+// snapshot the worker broadcast, closes the pool afterwards, and WebSocket clients get a Close
+// frame (1001, going away) instead of a dead socket.
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
+
+use axum_test::{TestServer, WsMessage};
+use futures_util::future::BoxFuture;
+use homeserver::config::{AppConfig, DatabaseConfig, ServerConfig};
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::{HistoryHandle, HistoryRepo};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::worker::{
+    HistoryWriterConfig, OverflowPolicy, StatsCollector, WorkerDeps, spawn_history_writer,
+    write_queue,
+};
+use homeserver::ws_connections::WsConnections;
+use homeserver::{routes, serve, shutdown, startup, worker};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// Instant, constant readings.
+struct FixedCollector;
+
+impl StatsCollector for FixedCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        Box::pin(async {
+            Ok(CpuStats {
+                usage_percent: 12.5,
+                ..Default::default()
+            })
+        })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
+    }
+}
+
+/// Never flushes on its own: whatever is stored got there through the final flush.
+fn writer_config() -> HistoryWriterConfig {
+    HistoryWriterConfig {
+        flush_rate: 10_000,
+        flush_interval_secs: 3600,
+        flush_warn_ms: 1000,
+        persist_gpu: true,
+        persist_smart: true,
+        min_snapshot_timestamp_ms: 0,
+        max_future_skew_minutes: 5,
+        min_free_bytes: 0,
+    }
+}
+
+#[tokio::test]
+async fn shutdown_mid_stream_stores_the_last_snapshots() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let metrics = ServiceMetrics::default();
+    let history_shutdown = CancellationToken::new();
+    let store = startup::open_history_store(&config.database, &metrics, history_shutdown.clone())
+        .await
+        .unwrap();
+    assert!(store.aggregation.is_some());
+    let repo = store.repo.clone();
+    let history_repo = HistoryHandle::from(repo.clone());
+
+    let (write_tx, write_rx) = write_queue(256, OverflowPolicy::DropNew, Default::default());
+    let writer = spawn_history_writer(
+        write_rx,
+        repo.clone(),
+        Arc::new(SystemInfo::default()),
+        writer_config(),
+        Arc::new(AtomicU64::new(0)),
+        Default::default(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    let aggregation = store.aggregation;
+    let history_startup = tokio::spawn(async move { Some((writer, aggregation)) });
+
+    let (tx, mut rx) = broadcast::channel(256);
+    let ws_connections = Arc::new(WsConnections::default());
+    let (worker_stop, shutdown_rx) = tokio::sync::oneshot::channel();
+    let worker = worker::spawn(
+        WorkerDeps {
+            collector: Arc::new(FixedCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.clone(),
+            tx: tx.clone(),
+            write_tx: Some(write_tx),
+            ws_connections: ws_connections.clone(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(20),
+    );
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        ws_connections.clone(),
+        config,
+        history_repo.clone(),
+        metrics,
+    );
+    let listen = ServerConfig {
+        host: "127.0.0.1".into(),
+        port: 0,
+        ..Default::default()
+    };
+    let server = serve::run(app, &listen, std::future::pending())
+        .await
+        .unwrap();
+    let addr = server.bound_address().unwrap();
+
+    // Mid-stream: snapshots are flowing and the server answers.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let health = reqwest::get(format!("http://{addr}/health")).await.unwrap();
+    assert!(health.status().is_success());
+    server.shutdown();
+    server.wait().await.unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        shutdown::Drain {
+            worker_stop,
+            worker,
+            history_startup: Some(history_startup),
+            history_shutdown: history_shutdown.clone(),
+            tasks_shutdown: CancellationToken::new(),
+            tasks: vec![],
+            ws_connections,
+            history_repo,
+        }
+        .run(),
+    )
+    .await
+    .expect("drain finishes");
+    assert!(history_shutdown.is_cancelled());
+
+    let mut broadcast = Vec::new();
+    while let Ok(snapshot) = rx.try_recv() {
+        broadcast.push(snapshot.timestamp as i64);
+    }
+    assert!(broadcast.len() >= 3, "worker ran: {broadcast:?}");
+    assert!(
+        repo.get_raw_snapshots_by_time_range(i64::MIN, i64::MAX)
+            .await
+            .is_err(),
+        "pool closed at the end"
+    );
+
+    let reopened = HistoryRepo::connect(&DatabaseConfig {
+        path: dir.path().join("h.db").to_str().unwrap().into(),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let mut stored: Vec<i64> = reopened
+        .get_raw_snapshots_by_time_range(i64::MIN, i64::MAX)
+        .await
+        .unwrap()
+        .iter()
+        .map(|s| s.timestamp as i64)
+        .collect();
+    stored.sort_unstable();
+    assert_eq!(stored, broadcast, "every broadcast snapshot is stored");
+}
+
+#[tokio::test]
+async fn websocket_clients_get_a_going_away_close() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("h.db").to_str().unwrap().into();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let ws_connections = Arc::new(WsConnections::default());
+    let app = routes::app(
+        broadcast::channel(4).0,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        ws_connections.clone(),
+        config,
+        repo,
+        Default::default(),
+    );
+    let server = TestServer::builder().http_transport().build(app);
+    let mut system = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let mut cpu = server
+        .get_websocket("/ws/cpu?interval_ms=100")
+        .await
+        .into_websocket()
+        .await;
+    let _welcome = system.receive_text().await;
+    let _reading = cpu.receive_text().await;
+    assert_eq!(ws_connections.total(), 2);
+
+    let still_open = ws_connections.close_all(Duration::from_secs(5));
+    let receive = async {
+        for ws in [&mut system, &mut cpu] {
+            loop {
+                match ws.receive_message().await {
+                    WsMessage::Close(Some(frame)) => {
+                        assert_eq!(u16::from(frame.code), 1001);
+                        assert_eq!(frame.reason.as_str(), "server shutting down");
+                        break;
+                    }
+                    WsMessage::Close(None) => panic!("close frame without a code"),
+                    _ => {}
+                }
+            }
+        }
+    };
+    let (still_open, ()) = tokio::time::timeout(
+        Duration::from_secs(10),
+        futures_util::future::join(still_open, receive),
+    )
+    .await
+    .expect("connections close");
+    assert_eq!(still_open, 0);
+    assert_eq!(ws_connections.total(), 0);
+}
@@ -0,0 +1,100 @@
+// Status page at /: HTML with the host identity, version and container count substituted (and
+// escaped), and the plain text fallback with `server.status_page = false`.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::HistoryHandle;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::version::{NAME, VERSION};
+use tokio::sync::broadcast;
+
+fn server(config: AppConfig, info: SystemInfo, metrics: ServiceMetrics) -> TestServer {
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(info),
+        Default::default(),
+        config,
+        HistoryHandle::disabled(),
+        metrics,
+    ))
+}
+
+fn info(hostname: &str) -> SystemInfo {
+    SystemInfo {
+        os_family: "Ubuntu".into(),
+        os_version: "24.04".into(),
+        system_model: hostname.into(),
+        processor_name: "AMD Ryzen 7".into(),
+        ..Default::default()
+    }
+}
+
+fn container(id: &str) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "name": id,
+        "cpuPercent": 0.0,
+        "memoryUsageBytes": 0,
+        "memoryLimitBytes": 0,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+fn snapshot(containers: &[&str]) -> FullSystemSnapshot {
+    FullSystemSnapshot {
+        containers: containers.iter().map(|id| container(id)).collect(),
+        ..common::snapshot(1)
+    }
+}
+
+#[tokio::test]
+async fn root_serves_the_status_page() {
+    let metrics = ServiceMetrics::default();
+    metrics.broadcast.record_latest(&snapshot(&["a", "b", "c"]));
+    let server = server(AppConfig::default(), info("nas-01"), metrics);
+
+    let response = server.get("/").await;
+    response.assert_status_ok();
+    let content_type = response.header("content-type");
+    assert!(content_type.to_str().unwrap().starts_with("text/html"));
+    let page = response.text();
+    assert!(page.contains("<h1>nas-01</h1>"));
+    assert!(page.contains(&format!("homeserver {VERSION}")));
+    assert!(page.contains("Ubuntu 24.04"));
+    assert!(page.contains("<td>3</td>"), "container count");
+    assert!(page.contains("/api/cpu") && page.contains("/api/ram"));
+    assert!(page.contains(r#"href="/api/history""#) && page.contains(r#"href="/metrics""#));
+    assert!(!page.contains("{{"), "every placeholder substituted");
+}
+
+#[tokio::test]
+async fn host_identity_is_escaped() {
+    let server = server(
+        AppConfig::default(),
+        info("<script>x</script>"),
+        ServiceMetrics::default(),
+    );
+    let page = server.get("/").await.text();
+    assert!(page.contains("&lt;script&gt;x&lt;/script&gt;"));
+    assert!(!page.contains("<script>x"));
+}
+
+#[tokio::test]
+async fn opting_out_restores_plain_text() {
+    let config = AppConfig::load_from_str("[server]\nstatus_page = false\n").unwrap();
+    assert!(AppConfig::default().server.status_page);
+    let server = server(config, info("nas-01"), ServiceMetrics::default());
+    let response = server.get("/").await;
+    response.assert_status_ok();
+    let content_type = response.header("content-type");
+    assert!(content_type.to_str().unwrap().starts_with("text/plain"));
+    response.assert_text(format!("{NAME} {VERSION}"));
+}
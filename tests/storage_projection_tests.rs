@@ -0,0 +1,184 @@
+// Days-until-full: linear_slope / project_partition on increasing, flat, noisy and shrinking
+// series, and GET /api/storage/projection over seeded history.
+
+use axum_test::TestServer;
+use homeserver::config::AppConfig;
+use homeserver::history_repo::{HistoryRepo, PartitionSample, linear_slope, project_partition};
+use homeserver::models::*;
+use homeserver::routes;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+const GIB: u64 = 1 << 30;
+const MS_PER_DAY: u64 = 86_400_000;
+
+/// One sample a day, `used[i]` on day `i`, of a 100 GiB partition.
+fn daily(used: &[u64]) -> Vec<PartitionSample> {
+    used.iter()
+        .enumerate()
+        .map(|(day, &used_space)| PartitionSample {
+            timestamp: 1_700_000_000_000 + day as u64 * MS_PER_DAY,
+            used_space,
+            total_space: 100 * GIB,
+        })
+        .collect()
+}
+
+#[test]
+fn slope_of_a_line_and_degenerate_inputs() {
+    assert_eq!(
+        linear_slope(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]),
+        Some(2.0)
+    );
+    assert_eq!(linear_slope(&[(1.0, 5.0)]), None);
+    assert_eq!(linear_slope(&[]), None);
+    assert_eq!(linear_slope(&[(1.0, 5.0), (1.0, 9.0)]), None);
+}
+
+#[test]
+fn increasing_series_projects_days_until_full() {
+    let samples = daily(&[10 * GIB, 20 * GIB, 30 * GIB, 40 * GIB]);
+    let p = project_partition("/srv/media".into(), &samples).unwrap();
+    assert_eq!(p.mount, "/srv/media");
+    assert_eq!((p.used_space, p.total_space), (40 * GIB, 100 * GIB));
+    assert_eq!(p.bytes_per_day, Some(10.0 * GIB as f64));
+    assert_eq!(p.days_until_full, Some(6.0));
+
+    // Already full: nothing left to fill.
+    let full = project_partition("/full".into(), &daily(&[90 * GIB, 100 * GIB])).unwrap();
+    assert_eq!(full.days_until_full, Some(0.0));
+}
+
+#[test]
+fn flat_and_shrinking_series_have_no_projection() {
+    let flat = project_partition("/".into(), &daily(&[50 * GIB; 5])).unwrap();
+    assert_eq!(flat.bytes_per_day, Some(0.0));
+    assert_eq!(flat.days_until_full, None);
+
+    // A few KiB a day is noise, not growth.
+    let creeping = daily(&[50 * GIB, 50 * GIB + 4096, 50 * GIB + 8192]);
+    let creeping = project_partition("/".into(), &creeping).unwrap();
+    assert_eq!(creeping.bytes_per_day, Some(4096.0));
+    assert_eq!(creeping.days_until_full, None);
+
+    let shrinking = daily(&[60 * GIB, 55 * GIB, 50 * GIB]);
+    let shrinking = project_partition("/tmp".into(), &shrinking).unwrap();
+    assert_eq!(shrinking.bytes_per_day, Some(-5.0 * GIB as f64));
+    assert_eq!(shrinking.days_until_full, None);
+
+    // One sample: no trend yet; none at all: no entry.
+    let single = project_partition("/new".into(), &daily(&[GIB])).unwrap();
+    assert_eq!((single.bytes_per_day, single.days_until_full), (None, None));
+    assert!(project_partition("/gone".into(), &[]).is_none());
+}
+
+#[test]
+fn noisy_series_follows_the_trend() {
+    // 2 GiB a day with up to ±1.5 GiB of churn (downloads deleted, logs rotated).
+    let used: Vec<u64> = (0..30u64)
+        .map(|day| {
+            let noise = [0i64, 1536, -1024, 512, -1536, 1024][day as usize % 6] * (1 << 20);
+            (20 * GIB as i64 + day as i64 * 2 * GIB as i64 + noise) as u64
+        })
+        .collect();
+    let p = project_partition("/srv/media".into(), &daily(&used)).unwrap();
+    let rate = p.bytes_per_day.unwrap() / GIB as f64;
+    assert!((rate - 2.0).abs() < 0.05, "{rate}");
+    let left = (100 * GIB - p.used_space) as f64 / GIB as f64;
+    let days = p.days_until_full.unwrap();
+    assert!((days - left / 2.0).abs() < 1.0, "{days} vs {}", left / 2.0);
+}
+
+fn snapshot(ts: u64, partitions: &[(&str, u64)]) -> FullSystemSnapshot {
+    let partitions: Vec<_> = partitions
+        .iter()
+        .map(|(mount, used)| {
+            serde_json::json!({
+                "mount": mount, "name": mount, "type": "ext4",
+                "totalSpace": 500 * GIB, "usedSpace": used,
+                "availableSpace": 500 * GIB - used, "usagePercent": 0.0,
+            })
+        })
+        .collect();
+    serde_json::from_value(serde_json::json!({
+        "timestamp": ts,
+        "cpu": CpuStats::default(),
+        "ram": RamStats::default(),
+        "containers": [],
+        "storage": { "partitions": partitions, "disks": [] },
+        "network": NetworkStats::default(),
+        "system": SystemStatsDynamic::default(),
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn api_storage_projection_over_seeded_history() {
+    let dir = TempDir::new().unwrap();
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db").to_str().unwrap().to_string();
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    // Six hourly samples ending about an hour ago: /srv/media grows 1 GiB an hour, / is flat.
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let hour = 3_600_000;
+    let start = now / hour * hour - 6 * hour;
+    let snaps: Vec<_> = (0..6)
+        .map(|i| {
+            snapshot(
+                start + i * hour,
+                &[("/srv/media", 100 * GIB + i * GIB), ("/", 20 * GIB)],
+            )
+        })
+        .collect();
+    repo.save_snapshots(&snaps, &SystemInfo::default())
+        .await
+        .unwrap();
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        repo,
+        Default::default(),
+    ));
+
+    let response = server.get("/api/storage/projection").await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body,
+        serde_json::json!([
+            {
+                "mount": "/", "usedSpace": 20 * GIB, "totalSpace": 500 * GIB,
+                "bytesPerDay": 0.0, "daysUntilFull": null,
+            },
+            {
+                "mount": "/srv/media", "usedSpace": 105 * GIB, "totalSpace": 500 * GIB,
+                "bytesPerDay": 24.0 * GIB as f64,
+                // 395 GiB left at 24 GiB a day.
+                "daysUntilFull": 395.0 / 24.0,
+            },
+        ])
+    );
+
+    for days in [0, 32] {
+        let response = server
+            .get(&format!("/api/storage/projection?days={days}"))
+            .await;
+        response.assert_status_bad_request();
+        response.assert_json(&serde_json::json!({
+            "error": "days must be between 1 and 31",
+            "code": "bad_request",
+        }));
+    }
+    server
+        .get("/api/storage/projection?days=31")
+        .await
+        .assert_status_ok();
+}
@@ -0,0 +1,166 @@
+// Restart-on-panic supervision: `supervise` respawns with backoff and counts restarts; the
+// stats worker keeps ticking after a collector panics.
+
+mod common;
+
+use futures_util::future::BoxFuture;
+use homeserver::config::DatabaseConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::supervisor::{Backoff, supervise};
+use homeserver::worker::{
+    CollectionMetrics, OverflowPolicy, StatsCollector, WorkerDeps, spawn, write_queue,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+const FAST: Backoff = Backoff {
+    initial: Duration::from_millis(10),
+    max: Duration::from_millis(40),
+};
+
+#[tokio::test]
+async fn restarts_a_panicking_task_until_it_returns() {
+    let restarts = Arc::new(AtomicU64::new(0));
+    let runs = Arc::new(AtomicU64::new(0));
+    let counter = runs.clone();
+    let started = Instant::now();
+    supervise(
+        "test",
+        restarts.clone(),
+        FAST,
+        CancellationToken::new(),
+        move || {
+            let run = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run < 3 {
+                    panic!("run {run} fails");
+                }
+            }
+        },
+    )
+    .await;
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+    assert_eq!(restarts.load(Ordering::SeqCst), 3);
+    // Backoff 10 + 20 + 40 ms.
+    assert!(started.elapsed() >= Duration::from_millis(70));
+}
+
+#[tokio::test]
+async fn shutdown_during_backoff_stops_restarting() {
+    let restarts = Arc::new(AtomicU64::new(0));
+    let shutdown = CancellationToken::new();
+    let backoff = Backoff {
+        initial: Duration::from_secs(60),
+        max: Duration::from_secs(60),
+    };
+    let handle = tokio::spawn(supervise(
+        "test",
+        restarts.clone(),
+        backoff,
+        shutdown.clone(),
+        || async { panic!("always fails") },
+    ));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(1), handle)
+        .await
+        .expect("supervisor exits during backoff")
+        .unwrap();
+    assert_eq!(restarts.load(Ordering::SeqCst), 1);
+}
+
+/// Panics on the first CPU reading, then behaves.
+#[derive(Default)]
+struct PanicOnceCollector {
+    cpu_calls: AtomicU64,
+}
+
+impl StatsCollector for PanicOnceCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        let call = self.cpu_calls.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move {
+            assert!(call > 0, "injected collector panic");
+            Ok(CpuStats::default())
+        })
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        Box::pin(async { Ok(RamStats::default()) })
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        Box::pin(async { Ok(StorageStats::default()) })
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        Box::pin(async { Ok(NetworkStats::default()) })
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        Box::pin(async { Ok(SystemStatsDynamic::default()) })
+    }
+}
+
+#[tokio::test]
+async fn worker_restarts_after_a_collector_panic() {
+    let dir = TempDir::new().unwrap();
+    let history_repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    history_repo.init().await.unwrap();
+    let (tx, _rx) = broadcast::channel(64);
+    let (write_tx, _write_rx) = write_queue(64, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(CollectionMetrics::default());
+    let restarts = Arc::new(AtomicU64::new(0));
+
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(PanicOnceCollector::default()),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.into(),
+            tx,
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: restarts.clone(),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(20),
+    );
+
+    // The first tick panics; the restart comes after the default 1 s backoff.
+    tokio::time::sleep(Duration::from_millis(1_300)).await;
+    assert_eq!(restarts.load(Ordering::SeqCst), 1);
+    let ticks = metrics.snapshot().ticks_total;
+    assert!(ticks >= 5, "ticking again after the restart: {ticks} ticks");
+
+    let _ = shutdown_tx.send(());
+    tokio::time::timeout(Duration::from_secs(2), handle)
+        .await
+        .expect("worker stops on shutdown")
+        .unwrap();
+}
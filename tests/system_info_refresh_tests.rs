@@ -0,0 +1,185 @@
+// System info refresh: SharedSystemInfo swaps, POST /api/info/refresh (admin token) updating
+// /api/info, the stored row and /ws/system clients, the periodic task, and its config key.
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::system_info_refresh::{self, SharedSystemInfo};
+use homeserver::ws_connections::WsConnections;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+const TOKEN: &str = "hunter2";
+
+/// What a boot with a slow udev could have captured.
+fn stale() -> SystemInfo {
+    SystemInfo {
+        system_model: "old-hostname".into(),
+        system_manufacturer: String::new(),
+        ..Default::default()
+    }
+}
+
+async fn detected() -> SystemInfo {
+    SysinfoRepo::new().get_system_info().await.unwrap()
+}
+
+async fn setup(dir: &TempDir, http_transport: bool) -> (TestServer, Arc<HistoryRepo>) {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("info.db").display().to_string();
+    config.server.admin_token = Some(Secret::new(TOKEN));
+    let repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    repo.init().await.unwrap();
+    let (tx, _) = broadcast::channel(4);
+    let app = routes::app(
+        tx,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(stale()),
+        Arc::new(WsConnections::default()),
+        config,
+        repo.clone(),
+        Default::default(),
+    );
+    let server = if http_transport {
+        TestServer::builder().http_transport().build(app)
+    } else {
+        TestServer::new(app)
+    };
+    (server, repo)
+}
+
+/// The next `info` message, skipping frames that are not JSON (pings).
+async fn next_info(ws: &mut axum_test::TestWebSocket) -> serde_json::Value {
+    let receive = async {
+        loop {
+            let text = ws.receive_text().await;
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text)
+                && json["type"] == "info"
+            {
+                return json;
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(3), receive)
+        .await
+        .expect("no info message")
+}
+
+#[tokio::test]
+async fn replace_only_notifies_on_a_change() {
+    let shared = SharedSystemInfo::new(stale());
+    let mut rx = shared.subscribe();
+    assert!(!shared.replace(stale()));
+    assert!(!rx.has_changed().unwrap());
+
+    let fresh = SystemInfo {
+        system_model: "new-hostname".into(),
+        ..stale()
+    };
+    assert!(shared.replace(fresh.clone()));
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(*rx.borrow_and_update().clone(), fresh);
+    assert_eq!(*shared.clone().get(), fresh, "clones share the value");
+}
+
+#[tokio::test]
+async fn refresh_endpoint_swaps_serves_and_stores_the_new_info() {
+    let dir = TempDir::new().unwrap();
+    let (server, repo) = setup(&dir, false).await;
+    let before: SystemInfo = server.get("/api/info").await.json();
+    assert_eq!(before, stale());
+
+    server
+        .post("/api/info/refresh")
+        .await
+        .assert_status_unauthorized();
+    let response = server
+        .post("/api/info/refresh")
+        .authorization_bearer(TOKEN)
+        .await;
+    response.assert_status_ok();
+    let report: serde_json::Value = response.json();
+    let fresh = detected().await;
+    assert_eq!(report["changed"], true);
+    assert_eq!(report["systemInfo"], serde_json::to_value(&fresh).unwrap());
+
+    let after: SystemInfo = server.get("/api/info").await.json();
+    assert_eq!(after, fresh);
+    assert_eq!(repo.get_stored_system_info().await.unwrap(), Some(fresh));
+
+    let again: serde_json::Value = server
+        .post("/api/info/refresh")
+        .authorization_bearer(TOKEN)
+        .await
+        .json();
+    assert_eq!(again["changed"], false);
+}
+
+#[tokio::test]
+async fn ws_system_clients_receive_the_refreshed_info() {
+    let dir = TempDir::new().unwrap();
+    let (server, _) = setup(&dir, true).await;
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let welcome = next_info(&mut ws).await;
+    assert_eq!(welcome["systemInfo"]["systemModel"], "old-hostname");
+
+    server
+        .post("/api/info/refresh")
+        .authorization_bearer(TOKEN)
+        .await
+        .assert_status_ok();
+    let update = next_info(&mut ws).await;
+    assert_eq!(
+        update["systemInfo"],
+        serde_json::to_value(detected().await).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn periodic_refresh_picks_up_the_detected_info() {
+    let shared = SharedSystemInfo::new(stale());
+    let mut rx = shared.subscribe();
+    let shutdown = CancellationToken::new();
+    let handle = system_info_refresh::spawn_periodic(
+        shared.clone(),
+        Arc::new(SysinfoRepo::new()),
+        None,
+        Duration::from_millis(20),
+        shutdown.clone(),
+    );
+    tokio::time::timeout(Duration::from_secs(3), rx.changed())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(*shared.get(), detected().await);
+    shutdown.cancel();
+    handle.await.unwrap();
+}
+
+#[test]
+fn refresh_interval_is_optional_and_positive() {
+    let config = AppConfig::load_from_str("[monitoring]\nsystem_info_refresh_secs = 3600\n");
+    assert_eq!(
+        config.unwrap().monitoring.system_info_refresh_secs,
+        Some(3600)
+    );
+    assert_eq!(
+        AppConfig::default().monitoring.system_info_refresh_secs,
+        None
+    );
+    let err = AppConfig::load_from_str("[monitoring]\nsystem_info_refresh_secs = 0\n").unwrap_err();
+    assert!(
+        err.to_string().contains("system_info_refresh_secs"),
+        "{err}"
+    );
+}
@@ -0,0 +1,134 @@
+// systemd watchdog gating: pings only while the worker's heartbeat is fresh, stop when it stalls
+// and resume when it ticks again; the tick-age limit follows the (idle) sample interval.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use homeserver::config::AppConfig;
+use homeserver::systemd::{self, Notifier, SdNotifier, ServiceState};
+use homeserver::worker::{CollectionMetrics, WorkerConfig};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Default)]
+struct RecordingNotifier(Mutex<Vec<ServiceState>>);
+
+impl Notifier for RecordingNotifier {
+    fn notify(&self, state: ServiceState) {
+        self.0.lock().unwrap().push(state);
+    }
+}
+
+impl RecordingNotifier {
+    fn pings(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+fn worker_config(sample_interval_ms: u64) -> WorkerConfig {
+    let mut config = AppConfig::default();
+    config.monitoring.sample_interval_ms = sample_interval_ms;
+    WorkerConfig::from_app(&config)
+}
+
+#[test]
+fn tick_age_limit_covers_slow_and_idle_sampling() {
+    let watchdog = Duration::from_secs(30);
+    let mut config = worker_config(1000);
+    assert_eq!(systemd::max_tick_age(watchdog, &config), watchdog);
+    config.sample_interval_ms = 20_000;
+    assert_eq!(
+        systemd::max_tick_age(watchdog, &config),
+        Duration::from_secs(60)
+    );
+    config.idle_sample_interval_ms = Some(60_000);
+    assert_eq!(
+        systemd::max_tick_age(watchdog, &config),
+        Duration::from_secs(180)
+    );
+}
+
+#[tokio::test]
+async fn pings_only_while_the_worker_ticks() {
+    let notifier = Arc::new(RecordingNotifier::default());
+    let metrics = Arc::new(CollectionMetrics::default());
+    let (_config_tx, config_rx) = watch::channel(worker_config(10));
+    let stop = CancellationToken::new();
+    let watchdog = tokio::spawn(systemd::run_watchdog(
+        notifier.clone(),
+        Duration::from_millis(200),
+        metrics.clone(),
+        config_rx,
+        stop.clone(),
+    ));
+
+    // Ticking: a ping every 100 ms.
+    for _ in 0..25 {
+        metrics.beat();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let pinged = notifier.pings();
+    assert!(pinged >= 3, "{pinged} pings while ticking");
+    assert!(
+        notifier
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|s| *s == ServiceState::Watchdog)
+    );
+
+    // Stalled: once the last tick is older than 200 ms, pings stop.
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let stalled = notifier.pings();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(notifier.pings(), stalled, "no pings while stalled");
+
+    // Ticking again: pings resume.
+    for _ in 0..15 {
+        metrics.beat();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(notifier.pings() > stalled, "pings resume");
+
+    stop.cancel();
+    watchdog.await.unwrap();
+}
+
+#[tokio::test]
+async fn no_pings_when_the_worker_never_ticks() {
+    let notifier = Arc::new(RecordingNotifier::default());
+    let (_config_tx, config_rx) = watch::channel(worker_config(10));
+    let stop = CancellationToken::new();
+    let watchdog = tokio::spawn(systemd::run_watchdog(
+        notifier.clone(),
+        Duration::from_millis(100),
+        Arc::new(CollectionMetrics::default()),
+        config_rx,
+        stop.clone(),
+    ));
+    // Startup grace of one limit (100 ms) measured from the watchdog start, then nothing.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let pinged = notifier.pings();
+    assert!(pinged <= 3, "{pinged}");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(notifier.pings(), pinged);
+    stop.cancel();
+    watchdog.await.unwrap();
+}
+
+#[test]
+fn heartbeat_reports_the_time_since_the_last_tick() {
+    let metrics = CollectionMetrics::default();
+    assert_eq!(metrics.since_last_tick(), None);
+    metrics.beat();
+    assert!(metrics.since_last_tick().unwrap() < Duration::from_secs(1));
+}
+
+#[test]
+fn sd_notifier_is_a_no_op_outside_systemd() {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        SdNotifier.notify(ServiceState::Ready);
+        SdNotifier.notify(ServiceState::Stopping);
+    }
+}
@@ -0,0 +1,235 @@
+// OpenTelemetry export: [telemetry] parsing and validation, the resource attributes, and a smoke
+// test collecting the worker tick, history flush, aggregation pass and HTTP request spans.
+
+mod common;
+
+use axum_test::TestServer;
+use common::InstantCollector;
+use homeserver::aggregation_worker::{AggregationWorkerConfig, run_one_tick};
+use homeserver::config::{AppConfig, DatabaseConfig, TelemetryConfig};
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::telemetry::OtelLayer;
+use homeserver::worker::{
+    HistoryWriterConfig, OverflowPolicy, WorkerDeps, spawn, spawn_history_writer, write_queue,
+};
+use homeserver::{routes, telemetry, version};
+use opentelemetry::Key;
+use opentelemetry_sdk::trace::InMemorySpanExporter;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, OnceLock};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tracing_subscriber::Registry;
+use tracing_subscriber::prelude::*;
+
+fn host(name: &str) -> SystemInfo {
+    SystemInfo {
+        system_model: name.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn telemetry_is_off_by_default_and_parses_when_set() {
+    let config = AppConfig::load_from_str("").unwrap();
+    assert!(config.telemetry.otlp_endpoint.is_none());
+    assert_eq!(config.telemetry.sampling_ratio, 1.0);
+
+    let config = AppConfig::load_from_str(
+        "[telemetry]\notlp_endpoint = \"http://collector:4318/v1/traces\"\nsampling_ratio = 0.25\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.telemetry.otlp_endpoint.as_deref(),
+        Some("http://collector:4318/v1/traces")
+    );
+    assert_eq!(config.telemetry.sampling_ratio, 0.25);
+}
+
+#[test]
+fn invalid_telemetry_values_are_rejected() {
+    let err = AppConfig::load_from_str("[telemetry]\nsampling_ratio = 1.5\n").unwrap_err();
+    assert!(
+        err.to_string().contains("telemetry.sampling_ratio"),
+        "{err}"
+    );
+    let err = AppConfig::load_from_str("[telemetry]\nsampling_ratio = -0.1\n").unwrap_err();
+    assert!(
+        err.to_string().contains("telemetry.sampling_ratio"),
+        "{err}"
+    );
+    let err =
+        AppConfig::load_from_str("[telemetry]\notlp_endpoint = \"collector:4318\"\n").unwrap_err();
+    assert!(err.to_string().contains("telemetry.otlp_endpoint"), "{err}");
+}
+
+#[test]
+fn resource_names_the_service_and_host() {
+    let resource = telemetry::resource(&host("nas"));
+    let get = |key: &'static str| resource.get(&Key::new(key)).map(|v| v.to_string());
+    assert_eq!(get("service.name").as_deref(), Some(version::NAME));
+    assert_eq!(get("service.version").as_deref(), Some(version::VERSION));
+    assert_eq!(get("host.name").as_deref(), Some("nas"));
+}
+
+#[test]
+fn provider_only_with_an_endpoint() {
+    let provider = telemetry::otlp_tracer_provider(&TelemetryConfig::default(), &host("nas"));
+    assert!(provider.unwrap().is_none());
+
+    let config = TelemetryConfig {
+        otlp_endpoint: Some("http://127.0.0.1:4318/v1/traces".into()),
+        sampling_ratio: 1.0,
+    };
+    let provider = telemetry::otlp_tracer_provider(&config, &host("nas")).unwrap();
+    provider.expect("endpoint configured").shutdown().unwrap();
+}
+
+fn aggregation_config() -> AggregationWorkerConfig {
+    AggregationWorkerConfig {
+        aggregation_tiers: vec![60, 300, 3600],
+        raw_retention_hours: 24,
+        minute_retention_hours: 168,
+        five_minute_retention_days: 30,
+        hourly_retention_days: 365,
+        ..common::aggregation_config()
+    }
+}
+
+/// The OpenTelemetry slot of a process-wide subscriber, as in `main`. Spans entered on SQLite's
+/// worker threads are not reliably closed under a thread-scoped (`set_default`) subscriber, so the
+/// export test installs a global one; its scenarios then run one after another in a single test.
+fn otel_slot() -> &'static tracing_subscriber::reload::Handle<Option<OtelLayer>, Registry> {
+    static SLOT: OnceLock<tracing_subscriber::reload::Handle<Option<OtelLayer>, Registry>> =
+        OnceLock::new();
+    SLOT.get_or_init(|| {
+        let (layer, handle) = tracing_subscriber::reload::Layer::new(None);
+        tracing_subscriber::registry().with(layer).init();
+        handle
+    })
+}
+
+/// Run a worker (feeding a history writer), an aggregation pass and one HTTP request with spans
+/// exported to memory; return the names of the exported spans.
+async fn exported_span_names(sampling_ratio: f64) -> Vec<String> {
+    let exporter = InMemorySpanExporter::default();
+    let telemetry_config = TelemetryConfig {
+        otlp_endpoint: Some("http://127.0.0.1:4318/v1/traces".into()),
+        sampling_ratio,
+    };
+    let provider = telemetry::tracer_provider(exporter.clone(), &telemetry_config, &host("nas"));
+    otel_slot()
+        .reload(Some(telemetry::layer(&provider)))
+        .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    repo.init().await.unwrap();
+
+    let (write_tx, write_rx) = write_queue(16, OverflowPolicy::DropNew, Default::default());
+    let writer = spawn_history_writer(
+        write_rx,
+        repo.clone(),
+        Arc::new(host("nas")),
+        HistoryWriterConfig {
+            flush_rate: 1,
+            flush_interval_secs: 3600,
+            flush_warn_ms: 1000,
+            persist_gpu: false,
+            persist_smart: false,
+            min_snapshot_timestamp_ms: 0,
+            max_future_skew_minutes: 5,
+            min_free_bytes: 0,
+        },
+        Arc::new(AtomicU64::new(0)),
+        Default::default(),
+        Arc::new(AtomicU64::new(0)),
+    );
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let worker = spawn(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(host("nas")),
+            gpu_repo: Arc::new(homeserver::gpu_repo::GpuRepo::new()),
+            smart_repo: Arc::new(homeserver::smart_repo::SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: repo.clone().into(),
+            tx: broadcast::channel(16).0,
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(20),
+    );
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let _ = shutdown_tx.send(());
+    worker.await.unwrap();
+    writer.await.unwrap();
+
+    run_one_tick(repo.as_ref(), &aggregation_config())
+        .await
+        .unwrap();
+
+    let config = AppConfig {
+        telemetry: telemetry_config,
+        ..Default::default()
+    };
+    let server = TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(host("nas")),
+        Default::default(),
+        config,
+        repo.clone(),
+        ServiceMetrics::default(),
+    ));
+    server.get("/health").await.assert_status_ok();
+    // A SQLite worker thread may still be dropping the span of its last command; closing the pool
+    // waits for them.
+    repo.close().await;
+
+    provider.force_flush().unwrap();
+    otel_slot().reload(None).unwrap();
+    exporter
+        .get_finished_spans()
+        .unwrap()
+        .into_iter()
+        .map(|span| span.name.into_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn key_operations_are_exported_as_spans_at_the_sampling_ratio() {
+    let names = exported_span_names(1.0).await;
+    for expected in [
+        "worker_tick",
+        "history_flush",
+        "aggregation_pass",
+        "request",
+    ] {
+        assert!(
+            names.iter().any(|name| name == expected),
+            "no {expected} span in {names:?}"
+        );
+    }
+
+    let names = exported_span_names(0.0).await;
+    assert!(names.is_empty(), "{names:?}");
+}
@@ -0,0 +1,219 @@
+// Wake-on-LAN: magic packet bytes, MAC parsing, directed broadcast addresses, `[server]
+// enable_wol` / `wol_targets` config, and POST /api/wol + GET /api/wol/targets gating.
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret, WolTarget};
+use homeserver::models::SystemInfo;
+use homeserver::net_tools::{MAGIC_PACKET_LEN, MacAddr, directed_broadcast, magic_packet};
+use homeserver::routes;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const TOKEN: &str = "s3cret";
+
+fn server(configure: impl FnOnce(&mut AppConfig)) -> TestServer {
+    let mut config = AppConfig::default();
+    configure(&mut config);
+    TestServer::new(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        None,
+        Default::default(),
+    ))
+}
+
+fn enabled(config: &mut AppConfig) {
+    config.server.enable_wol = true;
+    config.server.admin_token = Some(Secret::new(TOKEN));
+    config.server.wol_targets = vec![WolTarget {
+        name: "desktop".into(),
+        mac: "aa-bb-cc-dd-ee-ff".into(),
+        broadcast: Some(Ipv4Addr::LOCALHOST),
+    }];
+}
+
+#[test]
+fn magic_packet_is_six_ff_then_the_mac_sixteen_times() {
+    let mac = MacAddr([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+    let packet = magic_packet(mac);
+    assert_eq!(packet.len(), MAGIC_PACKET_LEN);
+    assert_eq!(MAGIC_PACKET_LEN, 102);
+    assert_eq!(packet[..6], [0xFF; 6]);
+    for repeat in packet[6..].chunks(6) {
+        assert_eq!(repeat, mac.0);
+    }
+}
+
+#[test]
+fn mac_addresses_are_validated() {
+    let mac: MacAddr = "aa:BB:cc:01:02:3f".parse().unwrap();
+    assert_eq!(mac.0, [0xAA, 0xBB, 0xCC, 0x01, 0x02, 0x3F]);
+    assert_eq!(mac.to_string(), "AA:BB:CC:01:02:3F");
+    assert_eq!("AA-BB-CC-01-02-3F".parse::<MacAddr>().unwrap(), mac);
+    for bad in [
+        "",
+        "AA:BB:CC:DD:EE",
+        "AA:BB:CC:DD:EE:FF:00",
+        "AA:BB:CC:DD:EE:GG",
+        "AA:BB:CC:DD:EE:F",
+        "AABBCCDDEEFF",
+        "AA:BB-CC:DD:EE:FF",
+        "+A:BB:CC:DD:EE:FF",
+    ] {
+        let err = bad.parse::<MacAddr>().unwrap_err();
+        assert!(err.to_string().contains("invalid MAC address"), "{bad}");
+    }
+}
+
+#[test]
+fn directed_broadcast_follows_the_prefix() {
+    let addr = Ipv4Addr::new(192, 168, 1, 20);
+    assert_eq!(
+        directed_broadcast(addr, 24),
+        Ipv4Addr::new(192, 168, 1, 255)
+    );
+    assert_eq!(
+        directed_broadcast(addr, 16),
+        Ipv4Addr::new(192, 168, 255, 255)
+    );
+    assert_eq!(directed_broadcast(addr, 0), Ipv4Addr::BROADCAST);
+    assert_eq!(directed_broadcast(addr, 31), Ipv4Addr::BROADCAST);
+    assert_eq!(directed_broadcast(addr, 32), Ipv4Addr::BROADCAST);
+}
+
+#[test]
+fn wol_is_off_by_default_and_targets_are_validated() {
+    let config = AppConfig::default();
+    assert!(!config.server.enable_wol);
+    assert!(config.server.wol_targets.is_empty());
+
+    let config = AppConfig::load_from_str(
+        "[server]\nenable_wol = true\n\n[[server.wol_targets]]\nname = \"desktop\"\nmac = \"AA:BB:CC:DD:EE:FF\"\nbroadcast = \"192.168.1.255\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.server.wol_targets[0].broadcast,
+        Some(Ipv4Addr::new(192, 168, 1, 255))
+    );
+
+    for (toml, expected) in [
+        ("name = \"pc\"\nmac = \"nope\"", "wol_targets[0].mac"),
+        (
+            "name = \" \"\nmac = \"AA:BB:CC:DD:EE:FF\"",
+            "name must be non-empty",
+        ),
+    ] {
+        let err =
+            AppConfig::load_from_str(&format!("[[server.wol_targets]]\n{toml}\n")).unwrap_err();
+        assert!(format!("{err:#}").contains(expected), "{err:#}");
+    }
+    let twice = "[[server.wol_targets]]\nname = \"pc\"\nmac = \"AA:BB:CC:DD:EE:FF\"\n";
+    let err = AppConfig::load_from_str(&twice.repeat(2)).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("duplicate name 'pc'"),
+        "{err:#}"
+    );
+}
+
+#[tokio::test]
+async fn the_endpoints_answer_404_until_enabled() {
+    let server = server(|config| config.server.admin_token = Some(Secret::new(TOKEN)));
+    let body = serde_json::json!({"mac": "AA:BB:CC:DD:EE:FF"});
+    let response = server
+        .post("/api/wol")
+        .authorization_bearer(TOKEN)
+        .json(&body)
+        .expect_failure()
+        .await;
+    response.assert_status_not_found();
+    assert!(response.text().contains("server.enable_wol"));
+    server
+        .get("/api/wol/targets")
+        .authorization_bearer(TOKEN)
+        .expect_failure()
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn sending_needs_the_admin_token() {
+    let body = serde_json::json!({"mac": "AA:BB:CC:DD:EE:FF"});
+    let no_token = server(|config| config.server.enable_wol = true);
+    no_token
+        .post("/api/wol")
+        .json(&body)
+        .expect_failure()
+        .await
+        .assert_status_forbidden();
+    let server = server(enabled);
+    server
+        .post("/api/wol")
+        .authorization_bearer("wrong")
+        .json(&body)
+        .expect_failure()
+        .await
+        .assert_status_unauthorized();
+    server
+        .get("/api/wol/targets")
+        .expect_failure()
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn packets_go_to_a_mac_or_a_named_target() {
+    let server = server(enabled);
+    let targets: Vec<WolTarget> = server
+        .get("/api/wol/targets")
+        .authorization_bearer(TOKEN)
+        .await
+        .json();
+    assert_eq!(targets[0].name, "desktop");
+
+    let sent: serde_json::Value = server
+        .post("/api/wol")
+        .authorization_bearer(TOKEN)
+        .json(&serde_json::json!({"mac": "00:11:22:33:44:55", "broadcast": "127.0.0.1"}))
+        .await
+        .json();
+    assert_eq!(
+        sent,
+        serde_json::json!({"mac": "00:11:22:33:44:55", "broadcast": "127.0.0.1", "port": 9})
+    );
+    let sent: serde_json::Value = server
+        .post("/api/wol")
+        .authorization_bearer(TOKEN)
+        .json(&serde_json::json!({"target": "desktop"}))
+        .await
+        .json();
+    assert_eq!(sent["mac"], "AA:BB:CC:DD:EE:FF");
+    assert_eq!(sent["broadcast"], "127.0.0.1");
+
+    for (body, status) in [
+        (serde_json::json!({"mac": "AA:BB"}), 400),
+        (serde_json::json!({}), 400),
+        (
+            serde_json::json!({"mac": "AA:BB:CC:DD:EE:FF", "target": "desktop"}),
+            400,
+        ),
+        (
+            serde_json::json!({"mac": "AA:BB:CC:DD:EE:FF", "broadcast": "x"}),
+            422,
+        ),
+        (serde_json::json!({"target": "laptop"}), 404),
+    ] {
+        let response = server
+            .post("/api/wol")
+            .authorization_bearer(TOKEN)
+            .json(&body)
+            .expect_failure()
+            .await;
+        assert_eq!(response.status_code().as_u16(), status, "{body}");
+        let envelope: serde_json::Value = response.json();
+        assert!(envelope["error"].is_string(), "{body}");
+    }
+}
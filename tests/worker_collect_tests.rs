@@ -0,0 +1,291 @@
+// Worker collection with injected StatsCollector mocks: collectors run concurrently (a tick takes
+// about as long as the slowest one, not their sum), timings land in CollectionMetrics, slow ticks
+// are counted, and a failing collector leaves its section at the last known-good value with a
+// `degraded` marker while snapshots keep flowing. Storage, Docker and system stats are only
+// re-collected on their own intervals.
+
+mod common;
+
+use futures_util::future::BoxFuture;
+use homeserver::config::DatabaseConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{
+    CollectionMetrics, OverflowPolicy, StatsCollector, WorkerConfig, WorkerDeps, spawn, write_queue,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+/// Every collector sleeps `delay`; the container listing fails when `docker_down`, and with
+/// `flaky_cpu` only the first CPU reading succeeds. System readings report their call number as
+/// `uptime_secs`.
+#[derive(Default)]
+struct SlowCollector {
+    delay: Duration,
+    docker_down: bool,
+    flaky_cpu: bool,
+    cpu_calls: AtomicUsize,
+    containers_calls: AtomicUsize,
+    storage_calls: AtomicUsize,
+    system_calls: AtomicUsize,
+}
+
+impl SlowCollector {
+    fn after<T: Send + 'static>(&self, value: T) -> BoxFuture<'_, T> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            value
+        })
+    }
+}
+
+fn cached_container() -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": "cached",
+        "name": "cached",
+        "cpuPercent": 1.0,
+        "memoryUsageBytes": 1,
+        "memoryLimitBytes": 2,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+impl StatsCollector for SlowCollector {
+    fn cpu_stats(&self) -> BoxFuture<'_, anyhow::Result<CpuStats>> {
+        let call = self.cpu_calls.fetch_add(1, Ordering::Relaxed);
+        let result = if self.flaky_cpu && call > 0 {
+            Err(anyhow::anyhow!("cpu sensor read failed"))
+        } else {
+            Ok(CpuStats {
+                usage_percent: 42.0 + call as f64,
+                ..Default::default()
+            })
+        };
+        self.after(result)
+    }
+    fn ram_stats(&self) -> BoxFuture<'_, anyhow::Result<RamStats>> {
+        self.after(Ok(RamStats::default()))
+    }
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        self.containers_calls.fetch_add(1, Ordering::Relaxed);
+        let result = if self.docker_down {
+            Err(anyhow::anyhow!("docker socket unavailable"))
+        } else {
+            Ok(vec![])
+        };
+        self.after(result)
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { vec![cached_container()] })
+    }
+    fn storage_stats(&self) -> BoxFuture<'_, anyhow::Result<StorageStats>> {
+        self.storage_calls.fetch_add(1, Ordering::Relaxed);
+        self.after(Ok(StorageStats::default()))
+    }
+    fn network_stats(&self) -> BoxFuture<'_, anyhow::Result<NetworkStats>> {
+        self.after(Ok(NetworkStats::default()))
+    }
+    fn system_stats(&self) -> BoxFuture<'_, anyhow::Result<SystemStatsDynamic>> {
+        let call = self.system_calls.fetch_add(1, Ordering::Relaxed);
+        self.after(Ok(SystemStatsDynamic {
+            uptime_secs: call as u64,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Every subsystem on every tick of `sample_interval_ms`.
+/// Run the worker with `collector` for `run_for`, returning its metrics and broadcast snapshots.
+async fn run_worker(
+    collector: Arc<SlowCollector>,
+    config: WorkerConfig,
+    run_for: Duration,
+) -> (Arc<CollectionMetrics>, Vec<FullSystemSnapshot>) {
+    let dir = TempDir::new().unwrap();
+    let history_repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    history_repo.init().await.unwrap();
+    let (tx, mut rx) = broadcast::channel(64);
+    let (write_tx, _write_rx) = write_queue(64, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(CollectionMetrics::default());
+
+    let handle = spawn(
+        WorkerDeps {
+            collector,
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.into(),
+            tx,
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        config,
+    );
+    tokio::time::sleep(run_for).await;
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+
+    let mut snapshots = Vec::new();
+    while let Ok(s) = rx.try_recv() {
+        snapshots.push(s);
+    }
+    (metrics, snapshots)
+}
+
+#[tokio::test]
+async fn collectors_overlap_so_a_tick_takes_the_max_not_the_sum() {
+    let delay = Duration::from_millis(150);
+    let collector = SlowCollector {
+        delay,
+        ..Default::default()
+    };
+    let (metrics, snapshots) = run_worker(
+        Arc::new(collector),
+        common::worker_config(5_000),
+        Duration::from_millis(600),
+    )
+    .await;
+
+    let stats = metrics.snapshot();
+    assert_eq!(stats.ticks_total, 1);
+    // Six collectors of 150 ms each: sequential would be 900 ms.
+    assert!(stats.last_ms >= 150, "{stats:?}");
+    assert!(stats.last_ms < 450, "{stats:?}");
+    assert_eq!(stats.max_ms, stats.last_ms);
+    assert_eq!(stats.slow_ticks_total, 0);
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].cpu.usage_percent, 42.0);
+}
+
+#[tokio::test]
+async fn ticks_slower_than_the_sample_interval_are_counted() {
+    let collector = SlowCollector {
+        delay: Duration::from_millis(80),
+        ..Default::default()
+    };
+    let (metrics, _) = run_worker(
+        Arc::new(collector),
+        common::worker_config(20),
+        Duration::from_millis(400),
+    )
+    .await;
+
+    let stats = metrics.snapshot();
+    assert!(stats.ticks_total >= 2, "{stats:?}");
+    assert_eq!(stats.slow_ticks_total, stats.ticks_total);
+    assert!(stats.mean_ms >= 80.0, "{stats:?}");
+}
+
+#[tokio::test]
+async fn failed_container_listing_uses_the_cached_containers() {
+    let collector = SlowCollector {
+        delay: Duration::from_millis(1),
+        docker_down: true,
+        ..Default::default()
+    };
+    let (_, snapshots) = run_worker(
+        Arc::new(collector),
+        common::worker_config(5_000),
+        Duration::from_millis(200),
+    )
+    .await;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].containers.len(), 1);
+    assert_eq!(snapshots[0].containers[0].id, "cached");
+    assert_eq!(snapshots[0].degraded, ["containers"]);
+}
+
+#[tokio::test]
+async fn failing_collector_keeps_the_last_good_section_and_marks_it_degraded() {
+    let collector = SlowCollector {
+        delay: Duration::from_millis(1),
+        flaky_cpu: true,
+        ..Default::default()
+    };
+    let (metrics, snapshots) = run_worker(
+        Arc::new(collector),
+        common::worker_config(30),
+        Duration::from_millis(250),
+    )
+    .await;
+
+    assert!(
+        snapshots.len() >= 3,
+        "ticks kept flowing: {}",
+        snapshots.len()
+    );
+    assert!(snapshots[0].degraded.is_empty());
+    for s in &snapshots[1..] {
+        assert_eq!(s.degraded, ["cpu"]);
+        assert_eq!(s.cpu.usage_percent, 42.0, "last known-good CPU reading");
+    }
+
+    let stats = metrics.snapshot();
+    let failed = snapshots.len() as u64 - 1;
+    assert_eq!(stats.failures_total["cpu"], failed);
+    assert_eq!(stats.failures_total["network"], 0);
+    assert_eq!(stats.degraded_ticks_total, failed);
+}
+
+#[tokio::test]
+async fn slow_subsystems_are_collected_on_their_own_interval() {
+    let collector = Arc::new(SlowCollector::default());
+    let mut config = common::worker_config(20);
+    config.docker_interval_ms = 40;
+    config.storage_interval_ms = 60;
+    config.system_interval_ms = 60;
+    let (metrics, snapshots) =
+        run_worker(collector.clone(), config, Duration::from_millis(250)).await;
+
+    let ticks = metrics.snapshot().ticks_total as usize;
+    assert!(ticks >= 6, "ticks: {ticks}");
+    assert_eq!(collector.cpu_calls.load(Ordering::Relaxed), ticks);
+    assert_eq!(
+        collector.containers_calls.load(Ordering::Relaxed),
+        ticks.div_ceil(2)
+    );
+    assert_eq!(
+        collector.storage_calls.load(Ordering::Relaxed),
+        ticks.div_ceil(3)
+    );
+    assert_eq!(
+        collector.system_calls.load(Ordering::Relaxed),
+        ticks.div_ceil(3)
+    );
+
+    // A snapshot goes out every tick; in-between ticks repeat the last system reading.
+    assert_eq!(snapshots.len(), ticks);
+    for (i, s) in snapshots.iter().enumerate() {
+        assert_eq!(s.system.uptime_secs, (i / 3) as u64, "tick {i}");
+        assert!(s.degraded.is_empty());
+    }
+    assert!(
+        snapshots
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp)
+    );
+}
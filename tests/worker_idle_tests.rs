@@ -0,0 +1,157 @@
+// Adaptive sampling: IdleSampler switches to the idle interval after the grace period without
+// WebSocket clients and back on connect; WsConnections counts per channel and wakes the worker.
+
+mod common;
+
+use common::InstantCollector;
+use homeserver::config::DatabaseConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{
+    CollectionMetrics, IdleSampler, OverflowPolicy, WorkerConfig, WorkerDeps, spawn, write_queue,
+};
+use homeserver::ws_connections::{WsChannel, WsConnections};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+
+const FAST: Duration = Duration::from_secs(1);
+const IDLE: Duration = Duration::from_secs(10);
+const GRACE: Duration = Duration::from_secs(30);
+
+fn sampler() -> IdleSampler {
+    IdleSampler::new(FAST, Some(IDLE), GRACE)
+}
+
+#[test]
+fn switches_to_idle_only_after_the_grace_period() {
+    let mut s = sampler();
+    let t0 = Instant::now();
+    assert_eq!(s.interval(), FAST);
+    assert_eq!(s.observe(0, t0), None);
+    assert_eq!(s.observe(0, t0 + GRACE - Duration::from_millis(1)), None);
+    assert_eq!(s.observe(0, t0 + GRACE), Some(IDLE));
+    assert!(s.is_idle());
+    assert_eq!(s.interval(), IDLE);
+    assert_eq!(s.observe(0, t0 + GRACE * 2), None, "no repeated switch");
+}
+
+#[test]
+fn a_client_restarts_the_grace_period() {
+    let mut s = sampler();
+    let t0 = Instant::now();
+    s.observe(0, t0);
+    assert_eq!(s.observe(1, t0 + GRACE / 2), None);
+    // Zero again from t0 + GRACE: idle a full grace period later.
+    assert_eq!(s.observe(0, t0 + GRACE), None);
+    assert_eq!(s.observe(0, t0 + GRACE + GRACE / 2), None);
+    assert_eq!(s.observe(0, t0 + GRACE * 2), Some(IDLE));
+}
+
+#[test]
+fn connecting_snaps_back_to_fast() {
+    let mut s = sampler();
+    let t0 = Instant::now();
+    s.observe(0, t0);
+    s.observe(0, t0 + GRACE);
+    assert_eq!(s.on_connect(), Some(FAST));
+    assert!(!s.is_idle());
+    assert_eq!(s.on_connect(), None, "already fast");
+
+    // A client seen on a tick (wake-up missed) switches back too.
+    s.observe(0, t0 + GRACE * 2);
+    s.observe(0, t0 + GRACE * 3);
+    assert!(s.is_idle());
+    assert_eq!(s.observe(2, t0 + GRACE * 4), Some(FAST));
+}
+
+#[test]
+fn disabled_sampler_never_goes_idle() {
+    let mut s = IdleSampler::new(FAST, None, GRACE);
+    let t0 = Instant::now();
+    assert_eq!(s.observe(0, t0), None);
+    assert_eq!(s.observe(0, t0 + GRACE * 10), None);
+    assert_eq!(s.interval(), FAST);
+}
+
+#[tokio::test]
+async fn connections_count_per_channel_and_wake_the_waiter() {
+    let connections = Arc::new(WsConnections::default());
+    let (system, current) = connections.connect(WsChannel::System);
+    assert_eq!(current, 1);
+    let (_ram, _) = connections.connect(WsChannel::Ram);
+    assert_eq!(connections.get(WsChannel::System), 1);
+    assert_eq!(connections.get(WsChannel::Cpu), 0);
+    assert_eq!(connections.total(), 2);
+    drop(system);
+    assert_eq!(connections.get(WsChannel::System), 0);
+    assert_eq!(connections.total(), 1);
+
+    // The connect above left a wake-up for the next waiter.
+    tokio::time::timeout(Duration::from_millis(100), connections.connected())
+        .await
+        .expect("connect wakes the waiter");
+}
+
+#[tokio::test]
+async fn worker_slows_down_without_clients_and_resumes_on_connect() {
+    let dir = TempDir::new().unwrap();
+    let history_repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    history_repo.init().await.unwrap();
+    let (tx, _rx) = broadcast::channel(64);
+    let (write_tx, _write_rx) = write_queue(64, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = Arc::new(CollectionMetrics::default());
+    let connections = Arc::new(WsConnections::default());
+
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.into(),
+            tx,
+            write_tx: Some(write_tx),
+            ws_connections: connections.clone(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        WorkerConfig {
+            idle_sample_interval_ms: Some(60_000),
+            idle_grace_secs: 0,
+            ..common::worker_config(20)
+        },
+    );
+
+    // No client and no grace: the first tick switches to the idle interval.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(metrics.snapshot().ticks_total, 1);
+
+    let (_client, _) = connections.connect(WsChannel::System);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let ticks = metrics.snapshot().ticks_total;
+    assert!(ticks >= 4, "fast sampling resumed: {ticks} ticks");
+
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+}
@@ -0,0 +1,252 @@
+// Pausing collection: CollectionPause deadlines, POST /api/worker/pause|resume (admin token)
+// stopping and restarting the snapshot flow of a running worker, and the /ws/system heartbeat.
+
+mod common;
+
+use axum_test::TestServer;
+use common::InstantCollector;
+use homeserver::collection_pause::CollectionPause;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::*;
+use homeserver::routes;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{OverflowPolicy, WorkerDeps, spawn, write_queue};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+
+const TOKEN: &str = "hunter2";
+
+const TEST_CONFIG_TEMPLATE: &str = r#"
+[server]
+port = 8081
+host = "0.0.0.0"
+
+[database]
+path = "DB_PATH_PLACEHOLDER"
+max_pool_size = 2
+flush_rate = 5
+
+[publishing]
+cpu_stats_frequency_ms = 1000
+ram_stats_frequency_ms = 1000
+broadcast_capacity = 10
+
+[monitoring]
+sample_interval_ms = 20
+stats_log_interval_secs = 60
+"#;
+
+#[tokio::test]
+async fn timed_pause_resumes_by_itself() {
+    let pause = CollectionPause::default();
+    assert!(!pause.is_paused());
+    pause.pause(Some(Duration::from_millis(50)));
+    let status = pause.status();
+    assert!(status.paused);
+    assert_eq!(status.resumes_in_secs, Some(1));
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(!pause.is_paused());
+
+    pause.pause(None);
+    assert_eq!(pause.status().resumes_in_secs, None);
+    assert!(pause.is_paused());
+    pause.resume();
+    assert!(!pause.is_paused());
+}
+
+fn admin_post(server: &TestServer, path: &str) -> axum_test::TestRequest {
+    server.post(path).authorization_bearer(TOKEN)
+}
+
+fn drain(rx: &mut broadcast::Receiver<FullSystemSnapshot>) -> usize {
+    let mut n = 0;
+    while rx.try_recv().is_ok() {
+        n += 1;
+    }
+    n
+}
+
+#[tokio::test]
+async fn pause_and_resume_endpoints_stop_and_restart_snapshots() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("test.db");
+    let config_str = TEST_CONFIG_TEMPLATE.replace("DB_PATH_PLACEHOLDER", db_path.to_str().unwrap());
+    let mut config = AppConfig::load_from_str(&config_str).unwrap();
+    config.server.admin_token = Some(Secret::new(TOKEN));
+    let history_repo = Arc::new(HistoryRepo::connect(&config.database).await.unwrap());
+    history_repo.init().await.unwrap();
+    let (tx, mut rx) = broadcast::channel(256);
+    let (write_tx, _write_rx) = write_queue(256, OverflowPolicy::DropNew, Default::default());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let metrics = ServiceMetrics::default();
+
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.clone().into(),
+            tx: tx.clone(),
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: metrics.collection.clone(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: metrics.pause.clone(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(20),
+    );
+    let server = TestServer::new(routes::app(
+        tx,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        history_repo,
+        metrics,
+    ));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(drain(&mut rx) > 0, "snapshots flow before pausing");
+
+    let response = admin_post(&server, "/api/worker/pause").await;
+    response.assert_status_ok();
+    let json: serde_json::Value = response.json();
+    assert_eq!(json["paused"], true);
+    assert!(json["resumesInSecs"].is_null());
+    let stats: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(stats["paused"], true);
+
+    // A tick already collecting when the pause landed may still arrive.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drain(&mut rx);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(drain(&mut rx), 0, "no snapshots while paused");
+
+    let response = admin_post(&server, "/api/worker/resume").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["paused"], false);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(drain(&mut rx) > 0, "snapshots flow again after resuming");
+    let stats: serde_json::Value = server.get("/api/stats").await.json();
+    assert_eq!(stats["paused"], false);
+
+    let response = admin_post(&server, "/api/worker/pause?duration_secs=600").await;
+    assert_eq!(response.json::<serde_json::Value>()["resumesInSecs"], 600);
+    admin_post(&server, "/api/worker/pause?duration_secs=0")
+        .await
+        .assert_status_bad_request();
+
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+}
+
+/// The router without a history database or worker.
+fn app(config: AppConfig, metrics: ServiceMetrics) -> axum::Router {
+    routes::app(
+        broadcast::channel(4).0,
+        Arc::new(homeserver::sysinfo_repo::SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        Default::default(),
+        config,
+        None,
+        metrics,
+    )
+}
+
+#[tokio::test]
+async fn pause_endpoints_need_the_admin_token() {
+    let mut config = AppConfig::default();
+    let metrics = ServiceMetrics::default();
+    let disabled = TestServer::new(app(config.clone(), metrics.clone()));
+    for path in ["/api/worker/pause", "/api/worker/resume"] {
+        disabled
+            .post(path)
+            .authorization_bearer(TOKEN)
+            .expect_failure()
+            .await
+            .assert_status_forbidden();
+    }
+
+    config.server.admin_token = Some(Secret::new(TOKEN));
+    let gated = TestServer::new(app(config, metrics.clone()));
+    for path in ["/api/worker/pause", "/api/worker/resume"] {
+        gated
+            .post(path)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+        gated
+            .post(path)
+            .authorization_bearer("wrong")
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+    }
+    assert!(!metrics.pause.is_paused(), "rejected calls change nothing");
+}
+
+/// The next `heartbeat` message, skipping snapshots and pings.
+async fn next_heartbeat(ws: &mut axum_test::TestWebSocket) -> serde_json::Value {
+    let receive = async {
+        loop {
+            let text = ws.receive_text().await;
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text)
+                && json["type"] == "heartbeat"
+            {
+                return json;
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(3), receive)
+        .await
+        .expect("no heartbeat message")
+}
+
+#[tokio::test]
+async fn ws_system_heartbeat_reports_the_pause() {
+    let mut config = AppConfig::default();
+    config.server.admin_token = Some(Secret::new(TOKEN));
+    let server = TestServer::builder()
+        .http_transport()
+        .build(app(config, ServiceMetrics::default()));
+    let mut ws = server
+        .get_websocket("/ws/system")
+        .await
+        .into_websocket()
+        .await;
+    let first = next_heartbeat(&mut ws).await;
+    assert_eq!(
+        first["paused"], false,
+        "one heartbeat right after the welcome"
+    );
+    let uptime = first["serviceUptimeSecs"]
+        .as_u64()
+        .expect("uptime in the heartbeat");
+    assert!(uptime <= homeserver::version::service_uptime_secs());
+
+    admin_post(&server, "/api/worker/pause?duration_secs=600")
+        .await
+        .assert_status_ok();
+    let paused = next_heartbeat(&mut ws).await;
+    assert_eq!(paused["paused"], true);
+    assert_eq!(paused["resumesInSecs"], 600);
+
+    admin_post(&server, "/api/worker/resume")
+        .await
+        .assert_status_ok();
+    assert_eq!(next_heartbeat(&mut ws).await["paused"], false);
+}
@@ -1,23 +1,67 @@
-// Worker integration test: spawn collector + writer, tick, shutdown, assert history flushed
+// Worker integration tests: the host collector (real sysinfo, mocked container source) feeding
+// the writer, tick, shutdown, assert history flushed; a failing container source degrades
+// containers to the cached list.
 
-use homeserver::docker_repo::DockerRepo;
+mod common;
+
+use futures_util::future::BoxFuture;
+use homeserver::config::DatabaseConfig;
 use homeserver::gpu_repo::GpuRepo;
 use homeserver::history_repo::HistoryRepo;
+use homeserver::models::{ContainerStats, FullSystemSnapshot};
 use homeserver::sysinfo_repo::SysinfoRepo;
 use homeserver::worker::{
-    HistoryWriterConfig, WorkerConfig, WorkerDeps, spawn, spawn_history_writer,
+    ContainerCollector, HistoryWriterConfig, HostCollector, WorkerConfig, WorkerDeps, spawn,
+    spawn_history_writer,
 };
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, AtomicUsize};
+use std::sync::atomic::AtomicU64;
 use tokio::sync::broadcast;
 
+fn container(name: &str) -> ContainerStats {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("{name}-id"),
+        "name": name,
+        "cpuPercent": 0.5,
+        "memoryUsageBytes": 1 << 20,
+        "memoryLimitBytes": 1 << 30,
+        "state": "running",
+    }))
+    .unwrap()
+}
+
+/// Lists `running`, or fails while keeping `cached` as the last known containers.
+struct FakeDocker {
+    running: Option<Vec<ContainerStats>>,
+    cached: Vec<ContainerStats>,
+}
+
+impl ContainerCollector for FakeDocker {
+    fn containers(&self) -> BoxFuture<'_, anyhow::Result<Vec<ContainerStats>>> {
+        Box::pin(async {
+            self.running
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("docker socket unavailable"))
+        })
+    }
+    fn cached_containers(&self) -> BoxFuture<'_, Vec<ContainerStats>> {
+        Box::pin(async { self.cached.clone() })
+    }
+}
+
+fn config(interval_ms: u64) -> WorkerConfig {
+    WorkerConfig {
+        collect_gpu: true,
+        ..common::worker_config(interval_ms)
+    }
+}
+
 #[tokio::test]
 async fn worker_spawn_ticks_and_shutdown_flushes_history() {
-    let docker_repo = match DockerRepo::connect() {
-        Ok(r) => Arc::new(r),
-        Err(_) => return, // Skip when Docker is not available
-    };
-
+    let docker = Arc::new(FakeDocker {
+        running: Some(vec![container("web")]),
+        cached: vec![],
+    });
     let sysinfo_repo = Arc::new(SysinfoRepo::new());
     let gpu_repo = Arc::new(GpuRepo::new());
     let smart_repo = Arc::new(homeserver::smart_repo::SmartRepo::new());
@@ -31,16 +75,26 @@ async fn worker_spawn_ticks_and_shutdown_flushes_history() {
     let dir = tempfile::TempDir::new().unwrap();
     let db_path = dir.path().join("history.db");
     let path_str = db_path.to_str().unwrap();
-    let history_repo = Arc::new(HistoryRepo::connect(path_str, 3).await.unwrap());
+    let history_repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: path_str.into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
     history_repo.init().await.unwrap();
 
     let (tx, _rx) = broadcast::channel(10);
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
-    let ws_system_connections = Arc::new(AtomicUsize::new(0));
     let snapshots_saved_total = Arc::new(AtomicU64::new(0));
 
     let writer_capacity = homeserver::worker::writer_channel_capacity(2);
-    let (write_tx, write_rx) = tokio::sync::mpsc::channel(writer_capacity);
+    let (write_tx, write_rx) = homeserver::worker::write_queue(
+        writer_capacity,
+        homeserver::worker::OverflowPolicy::DropNew,
+        Default::default(),
+    );
     let writer_handle = spawn_history_writer(
         write_rx,
         history_repo.clone(),
@@ -48,37 +102,43 @@ async fn worker_spawn_ticks_and_shutdown_flushes_history() {
         HistoryWriterConfig {
             flush_rate: 2,
             flush_interval_secs: 60,
+            flush_warn_ms: 1000,
             persist_gpu: true,
             persist_smart: true,
+            min_snapshot_timestamp_ms: 0,
+            max_future_skew_minutes: 5,
+            min_free_bytes: 0,
         },
         snapshots_saved_total.clone(),
+        Default::default(),
+        Arc::new(AtomicU64::new(0)),
     );
 
     let deps = WorkerDeps {
-        sysinfo_repo,
+        collector: Arc::new(HostCollector {
+            sysinfo_repo,
+            docker,
+        }),
         system_info,
-        docker_repo,
         gpu_repo,
         smart_repo,
-        history_repo: history_repo.clone(),
+        sensors_repo: Default::default(),
+        history_repo: history_repo.clone().into(),
         tx,
-        write_tx,
-        ws_system_connections,
+        write_tx: Some(write_tx),
+        ws_connections: Default::default(),
         snapshots_saved_total,
-        alert_engine: homeserver::alerting::AlertEngine::new(vec![]),
-        notifier: homeserver::alerting::Notifier::new(None),
+        aggregation_metrics: Default::default(),
+        collection_metrics: Default::default(),
+        broadcast_metrics: Default::default(),
+        worker_restarts_total: Arc::new(AtomicU64::new(0)),
+        pause: Default::default(),
+        probes: Default::default(),
+        checks: Default::default(),
         shutdown_rx,
     };
-    let config = WorkerConfig {
-        sample_interval_ms: 25,
-        stats_log_interval_secs: 3600,
-        prune_interval_secs: 3600,
-        collect_gpu: true,
-        collect_smart: false,
-        smart_poll_interval_secs: 900,
-    };
 
-    let worker_handle = spawn(deps, config);
+    let worker_handle = spawn(deps, config(25));
     tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
     let _ = shutdown_tx.send(());
     worker_handle.await.unwrap();
@@ -89,4 +149,56 @@ async fn worker_spawn_ticks_and_shutdown_flushes_history() {
         !recent.is_empty(),
         "worker should have flushed at least one snapshot (via writer on shutdown)"
     );
+    assert!(recent.iter().all(|s| s.containers.len() == 1));
+}
+
+#[tokio::test]
+async fn failing_container_source_falls_back_to_cached_containers() {
+    let (tx, mut rx) = broadcast::channel::<FullSystemSnapshot>(64);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let collection_metrics = Arc::new(homeserver::worker::CollectionMetrics::default());
+    let deps = WorkerDeps {
+        collector: Arc::new(HostCollector {
+            sysinfo_repo: Arc::new(SysinfoRepo::new()),
+            docker: Arc::new(FakeDocker {
+                running: None,
+                cached: vec![container("db")],
+            }),
+        }),
+        system_info: Default::default(),
+        gpu_repo: Arc::new(GpuRepo::new()),
+        smart_repo: Arc::new(homeserver::smart_repo::SmartRepo::new()),
+        sensors_repo: Default::default(),
+        history_repo: None.into(),
+        tx,
+        write_tx: None,
+        ws_connections: Default::default(),
+        snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+        aggregation_metrics: Default::default(),
+        collection_metrics: collection_metrics.clone(),
+        broadcast_metrics: Default::default(),
+        worker_restarts_total: Arc::new(AtomicU64::new(0)),
+        pause: Default::default(),
+        probes: Default::default(),
+        checks: Default::default(),
+        shutdown_rx,
+    };
+    let worker_handle = spawn(
+        deps,
+        WorkerConfig {
+            collect_gpu: false,
+            ..config(25)
+        },
+    );
+    let snapshot = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let _ = shutdown_tx.send(());
+    worker_handle.await.unwrap();
+
+    assert_eq!(snapshot.degraded, ["containers"]);
+    assert_eq!(snapshot.containers.len(), 1);
+    assert_eq!(snapshot.containers[0].name, "db");
+    assert!(collection_metrics.snapshot().failures_total["docker"] >= 1);
 }
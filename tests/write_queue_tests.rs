@@ -0,0 +1,122 @@
+// History writer queue: overflow policies, depth / drop counters, close semantics, and a worker
+// that keeps broadcasting while the writer is stalled.
+
+mod common;
+
+use common::InstantCollector;
+use common::snapshot;
+use homeserver::config::DatabaseConfig;
+use homeserver::gpu_repo::GpuRepo;
+use homeserver::history_repo::HistoryRepo;
+use homeserver::models::*;
+use homeserver::smart_repo::SmartRepo;
+use homeserver::worker::{
+    OverflowPolicy, SendOutcome, WorkerDeps, WriteQueueMetrics, spawn, write_queue,
+};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+
+/// Push timestamps 1..=3 into a queue of capacity 2 nobody reads; return what the writer gets.
+async fn overflow(policy: OverflowPolicy) -> (Vec<u64>, Vec<SendOutcome>, Arc<WriteQueueMetrics>) {
+    let metrics = Arc::new(WriteQueueMetrics::default());
+    let (tx, mut rx) = write_queue(2, policy, metrics.clone());
+    let outcomes = (1..=3).map(|ts| tx.send(snapshot(ts))).collect();
+    assert_eq!(metrics.depth(), 2);
+    drop(tx);
+    let mut received = Vec::new();
+    while let Some(s) = rx.recv().await {
+        received.push(s.timestamp);
+    }
+    assert_eq!(metrics.depth(), 0, "drained");
+    (received, outcomes, metrics)
+}
+
+#[tokio::test]
+async fn drop_new_keeps_the_queued_snapshots() {
+    let (received, outcomes, metrics) = overflow(OverflowPolicy::DropNew).await;
+    assert_eq!(received, vec![1, 2]);
+    assert_eq!(
+        outcomes,
+        vec![
+            SendOutcome::Queued,
+            SendOutcome::Queued,
+            SendOutcome::Dropped
+        ]
+    );
+    assert_eq!(metrics.dropped_total(), 1);
+}
+
+#[tokio::test]
+async fn drop_oldest_makes_room_for_the_new_snapshot() {
+    let (received, outcomes, metrics) = overflow(OverflowPolicy::DropOldest).await;
+    assert_eq!(received, vec![2, 3]);
+    assert_eq!(outcomes[2], SendOutcome::Dropped);
+    assert_eq!(metrics.dropped_total(), 1);
+}
+
+#[tokio::test]
+async fn receiver_wakes_on_send_and_sender_sees_a_closed_writer() {
+    let (tx, mut rx) = write_queue(4, OverflowPolicy::DropNew, Default::default());
+    let reader = tokio::spawn(async move { rx.recv().await.map(|s| s.timestamp) });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(tx.send(snapshot(7)), SendOutcome::Queued);
+    assert_eq!(reader.await.unwrap(), Some(7));
+    assert_eq!(tx.send(snapshot(8)), SendOutcome::Closed);
+}
+
+#[tokio::test]
+async fn stalled_writer_does_not_hold_up_the_broadcast() {
+    let dir = TempDir::new().unwrap();
+    let history_repo = Arc::new(
+        HistoryRepo::connect(&DatabaseConfig {
+            path: dir.path().join("h.db").to_str().unwrap().into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap(),
+    );
+    history_repo.init().await.unwrap();
+    let (tx, mut rx) = broadcast::channel(256);
+    let queue_metrics = Arc::new(WriteQueueMetrics::default());
+    // Never read: the writer is stuck on a stalled disk.
+    let (write_tx, _write_rx) = write_queue(2, OverflowPolicy::DropOldest, queue_metrics.clone());
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+
+    let handle = spawn(
+        WorkerDeps {
+            collector: Arc::new(InstantCollector),
+            system_info: Arc::new(SystemInfo::default()),
+            gpu_repo: Arc::new(GpuRepo::new()),
+            smart_repo: Arc::new(SmartRepo::new()),
+            sensors_repo: Default::default(),
+            history_repo: history_repo.into(),
+            tx,
+            write_tx: Some(write_tx),
+            ws_connections: Default::default(),
+            snapshots_saved_total: Arc::new(AtomicU64::new(0)),
+            aggregation_metrics: Default::default(),
+            collection_metrics: Default::default(),
+            broadcast_metrics: Default::default(),
+            worker_restarts_total: Arc::new(AtomicU64::new(0)),
+            pause: Default::default(),
+            probes: Default::default(),
+            checks: Default::default(),
+            shutdown_rx,
+        },
+        common::worker_config(20),
+    );
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let _ = shutdown_tx.send(());
+    handle.await.unwrap();
+
+    let mut broadcast = 0;
+    while rx.try_recv().is_ok() {
+        broadcast += 1;
+    }
+    assert!(broadcast >= 5, "broadcast kept going: {broadcast}");
+    assert_eq!(queue_metrics.depth(), 2);
+    assert_eq!(queue_metrics.dropped_total(), broadcast - 2);
+}
@@ -0,0 +1,141 @@
+// WebSocket upgrades refused before the handshake answer a status and a JSON error body: 401
+// without server.ws_token, 400 for a bad interval_ms, 503 with Retry-After at
+// publishing.max_ws_connections.
+
+use axum_test::TestServer;
+use homeserver::config::{AppConfig, Secret};
+use homeserver::metrics::ServiceMetrics;
+use homeserver::models::SystemInfo;
+use homeserver::routes;
+use homeserver::sysinfo_repo::SysinfoRepo;
+use homeserver::ws_connections::{WsChannel, WsConnections};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+fn server(config: AppConfig, connections: Arc<WsConnections>) -> TestServer {
+    TestServer::builder().http_transport().build(routes::app(
+        broadcast::channel(4).0,
+        Arc::new(SysinfoRepo::new()),
+        Arc::new(SystemInfo::default()),
+        connections,
+        config,
+        None,
+        ServiceMetrics::default(),
+    ))
+}
+
+/// First JSON text message; the server may send a ping first.
+async fn first_json(ws: &mut axum_test::TestWebSocket) -> serde_json::Value {
+    loop {
+        if let Ok(value) = serde_json::from_str(&ws.receive_text().await) {
+            return value;
+        }
+    }
+}
+
+fn with_token() -> AppConfig {
+    let mut config = AppConfig::default();
+    config.server.ws_token = Some(Secret::new("s3cret"));
+    config
+}
+
+#[tokio::test]
+async fn missing_or_wrong_token_is_unauthorized_on_every_channel() {
+    let server = server(with_token(), Default::default());
+    for path in ["/ws/cpu", "/ws/ram", "/ws/system", "/ws/cpu?token=nope"] {
+        let response = server.get_websocket(path).await;
+        response.assert_status_unauthorized();
+        response.assert_json(&serde_json::json!({"error": "unauthorized", "code": "unauthorized"}));
+        assert_eq!(response.header("www-authenticate"), "Bearer");
+    }
+    let response = server
+        .get_websocket("/ws/ram")
+        .authorization_bearer("wrong")
+        .await;
+    response.assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn token_in_the_query_or_header_is_accepted() {
+    let server = server(with_token(), Default::default());
+    let mut ws = server
+        .get_websocket("/ws/system?token=s3cret")
+        .await
+        .into_websocket()
+        .await;
+    let welcome = first_json(&mut ws).await;
+    assert_eq!(welcome["type"], "info");
+
+    let mut ws = server
+        .get_websocket("/ws/cpu?interval_ms=100")
+        .authorization_bearer("s3cret")
+        .await
+        .into_websocket()
+        .await;
+    let cpu = first_json(&mut ws).await;
+    assert!(cpu.get("usagePercent").is_some(), "{cpu}");
+}
+
+#[tokio::test]
+async fn bad_interval_is_a_bad_request() {
+    let server = server(AppConfig::default(), Default::default());
+    for path in [
+        "/ws/cpu?interval_ms=fast",
+        "/ws/cpu?interval_ms=5",
+        "/ws/ram?interval_ms=600000",
+        "/ws/ram?interval_ms=-1",
+    ] {
+        let response = server.get_websocket(path).await;
+        response.assert_status_bad_request();
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["error"], "interval_ms must be an integer between 100 and 60000",
+            "{path}"
+        );
+    }
+    let response = server.get_websocket("/ws/system?interval_ms=500").await;
+    response.assert_status_bad_request();
+    let body: serde_json::Value = response.json();
+    assert!(
+        body["error"].as_str().unwrap().contains("/ws/system"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn connection_limit_answers_503_with_retry_after() {
+    let mut config = AppConfig::default();
+    config.publishing.max_ws_connections = Some(2);
+    let connections = Arc::new(WsConnections::default());
+    let server = server(config, connections.clone());
+
+    let (_cpu, _) = connections.connect(WsChannel::Cpu);
+    // One slot left.
+    let _ws = server.get_websocket("/ws/ram").await.into_websocket().await;
+    let (_system, _) = connections.connect(WsChannel::System);
+
+    for path in ["/ws/system", "/ws/cpu"] {
+        let response = server.get_websocket(path).await;
+        response.assert_status_service_unavailable();
+        response.assert_json(&serde_json::json!({
+            "error": "too many connections",
+            "code": "unavailable",
+            "details": {"retryAfterSecs": 5},
+        }));
+        assert_eq!(response.header("retry-after"), "5");
+    }
+}
+
+#[test]
+fn ws_limits_are_validated() {
+    let err = AppConfig::load_from_str("[publishing]\nmax_ws_connections = 0\n").unwrap_err();
+    assert!(err.to_string().contains("max_ws_connections"), "{err}");
+    let err = AppConfig::load_from_str("[server]\nws_token = \"\"\n").unwrap_err();
+    assert!(err.to_string().contains("ws_token"), "{err}");
+    let config = AppConfig::load_from_str(
+        "[server]\nws_token = \"abc\"\n[publishing]\nmax_ws_connections = 8\n",
+    )
+    .unwrap();
+    assert_eq!(config.server.ws_token.unwrap().expose(), "abc");
+    assert_eq!(config.publishing.max_ws_connections, Some(8));
+}